@@ -44,8 +44,9 @@ pub fn map_to_block_issuer(
     }
 }
 
-pub fn map_to_block_era(block: &MultiEraBlock) -> Result<Era> {
-    Ok(match block.era() {
+/// Map a Pallas era, if it is one we know
+pub fn map_era(era: PallasEra) -> Option<Era> {
+    Some(match era {
         PallasEra::Byron => Era::Byron,
         PallasEra::Shelley => Era::Shelley,
         PallasEra::Allegra => Era::Allegra,
@@ -53,10 +54,18 @@ pub fn map_to_block_era(block: &MultiEraBlock) -> Result<Era> {
         PallasEra::Alonzo => Era::Alonzo,
         PallasEra::Babbage => Era::Babbage,
         PallasEra::Conway => Era::Conway,
-        x => bail!(
-            "Block slot {}, number {} has impossible era: {x:?}",
-            block.slot(),
-            block.number()
-        ),
+        _ => return None,
     })
 }
+
+pub fn map_to_block_era(block: &MultiEraBlock) -> Result<Era> {
+    let Some(era) = map_era(block.era()) else {
+        bail!(
+            "Block slot {}, number {} has impossible era: {:?}",
+            block.slot(),
+            block.number(),
+            block.era()
+        );
+    };
+    Ok(era)
+}
@@ -0,0 +1,81 @@
+//! Compare the default (buffered serde) and CBOR encodings of a bulk message
+//!
+//! cargo bench -p acropolis_common --bench message_codec
+
+use acropolis_common::{
+    message_schema::{decode_cbor, encode_cbor},
+    messages::{CardanoMessage, Message, UTXODeltasMessage},
+    Address, BlockHash, BlockInfo, BlockIntent, BlockStatus, Era, TxHash, TxIdentifier, TxOutput,
+    TxUTxODeltas, UTxOIdentifier, Value,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A busy block's worth of UTXO deltas
+fn utxo_deltas(txs: u16) -> Message {
+    let deltas = (0..txs)
+        .map(|index| {
+            let tx_hash = TxHash::new([index as u8; 32]);
+            TxUTxODeltas {
+                tx_identifier: TxIdentifier::new(1, index),
+                consumes: (0..2).map(|i| UTxOIdentifier::new(TxHash::default(), i)).collect(),
+                produces: (0..3)
+                    .map(|i| TxOutput {
+                        utxo_identifier: UTxOIdentifier::new(tx_hash, i),
+                        address: Address::None,
+                        value: Value::new(1_000_000, Vec::new()),
+                        datum: None,
+                        script_ref: None,
+                    })
+                    .collect(),
+                fee: 170_000,
+                ..TxUTxODeltas::default()
+            }
+        })
+        .collect();
+
+    let block = BlockInfo {
+        status: BlockStatus::Immutable,
+        intent: BlockIntent::Apply,
+        slot: 1,
+        number: 1,
+        hash: BlockHash::default(),
+        epoch: 0,
+        epoch_slot: 1,
+        new_epoch: false,
+        is_new_era: false,
+        timestamp: 0,
+        era: Era::Conway,
+        tip_slot: None,
+    };
+    Message::Cardano((
+        block,
+        CardanoMessage::UTXODeltas(UTXODeltasMessage { deltas }),
+    ))
+}
+
+fn message_codec(c: &mut Criterion) {
+    let message = utxo_deltas(300);
+    let json = serde_json::to_vec(&message).unwrap();
+    let cbor = encode_cbor(&message).unwrap();
+    println!(
+        "UTXO deltas: {} bytes as JSON, {} as CBOR",
+        json.len(),
+        cbor.len()
+    );
+
+    c.bench_function("encode json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&message)).unwrap())
+    });
+    c.bench_function("encode cbor", |b| {
+        b.iter(|| encode_cbor(black_box(&message)).unwrap())
+    });
+    c.bench_function("decode json", |b| {
+        b.iter(|| serde_json::from_slice::<Message>(black_box(&json)).unwrap())
+    });
+    c.bench_function("decode cbor", |b| {
+        b.iter(|| decode_cbor(black_box(&cbor)).unwrap())
+    });
+}
+
+criterion_group!(benches, message_codec);
+criterion_main!(benches);
@@ -13,6 +13,29 @@ use serde::{Deserialize, Deserializer};
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 
+/// Byron address kinds, from the last element of the payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByronAddressType {
+    /// ATVerKey - paid to a verification key
+    PubKey,
+    /// ATScript - never used on chain
+    Script,
+    /// ATRedeem - an AVVM redeem address
+    Redeem,
+}
+
+/// The parts of a Byron address payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByronAddressDetails {
+    /// Hash of the address type, spending data and attributes
+    pub root: Hash<28>,
+    pub address_type: ByronAddressType,
+    /// Protocol magic - only given off mainnet
+    pub network_magic: Option<u32>,
+    /// Whether it has the encrypted HD derivation path of a Daedalus wallet
+    pub has_derivation_path: bool,
+}
+
 /// a Byron-era address
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct ByronAddress {
@@ -65,6 +88,7 @@ impl ByronAddress {
         Ok(address)
     }
 
+    /// Read from the base58 string form ("Ae2...", "DdzFF...")
     pub fn from_string(s: &str) -> Result<Self> {
         let bytes = bs58::decode(s).into_vec()?;
         let mut dec = minicbor::Decoder::new(&bytes);
@@ -101,6 +125,56 @@ impl ByronAddress {
     pub fn is_redeem_address(&self) -> bool {
         self.payload.last() == Some(&2)
     }
+
+    /// Decode the payload: [root_hash, attributes, addr_type]
+    ///
+    /// Attributes are a map of CBOR-in-bytes values: 1 holds the encrypted
+    /// derivation path, and 2 the protocol magic, which is left out on mainnet.
+    pub fn details(&self) -> Result<ByronAddressDetails> {
+        let mut dec = minicbor::Decoder::new(&self.payload);
+        if dec.array()? != Some(3) {
+            bail!("Invalid Byron address payload array length");
+        }
+
+        let root = Hash::<28>::try_from(dec.bytes()?)?;
+
+        let mut network_magic = None;
+        let mut has_derivation_path = false;
+        let attributes =
+            dec.map()?.ok_or_else(|| anyhow!("Indefinite Byron address attributes"))?;
+        for _ in 0..attributes {
+            match dec.u64()? {
+                1 => {
+                    dec.bytes()?;
+                    has_derivation_path = true;
+                }
+                2 => network_magic = Some(minicbor::decode::<u32>(dec.bytes()?)?),
+                _ => dec.skip()?,
+            }
+        }
+
+        let address_type = match dec.u64()? {
+            0 => ByronAddressType::PubKey,
+            1 => ByronAddressType::Script,
+            2 => ByronAddressType::Redeem,
+            other => bail!("Unknown Byron address type {other}"),
+        };
+
+        Ok(ByronAddressDetails {
+            root,
+            address_type,
+            network_magic,
+            has_derivation_path,
+        })
+    }
+
+    /// Network, from the protocol magic attribute
+    pub fn network(&self) -> Result<NetworkId> {
+        Ok(match self.details()?.network_magic {
+            None => NetworkId::Mainnet,
+            Some(_) => NetworkId::Testnet,
+        })
+    }
 }
 
 /// A Shelley-era address - payment part
@@ -384,6 +458,10 @@ impl ShelleyAddress {
             return Err(anyhow!("invalid header: high bit set"));
         }
 
+        if data.len() < 29 {
+            return Err(anyhow!("address too short: {} bytes", data.len()));
+        }
+
         let network = match header & 0x0F {
             0 => NetworkId::Testnet,
             1 => NetworkId::Mainnet,
@@ -407,6 +485,10 @@ impl ShelleyAddress {
             _ => return Err(anyhow!("invalid payment bits")),
         };
 
+        if delegation_bits < 2 && data.len() < 57 {
+            return Err(anyhow!("base address too short: {} bytes", data.len()));
+        }
+
         let delegation = match delegation_bits {
             0 => {
                 let mut arr = [0u8; 28];
@@ -675,17 +757,16 @@ impl Address {
         None
     }
 
-    /// Read from string format ("addr1...")
+    /// Read from string format ("addr1...", "stake1..." or a base58 Byron address)
     pub fn from_string(text: &str) -> Result<Self> {
         if text.starts_with("addr1") || text.starts_with("addr_test1") {
             Ok(Self::Shelley(ShelleyAddress::from_string(text)?))
         } else if text.starts_with("stake1") || text.starts_with("stake_test1") {
             Ok(Self::Stake(StakeAddress::from_string(text)?))
         } else {
-            match ByronAddress::from_string(text) {
-                Ok(byron) => Ok(Self::Byron(byron)),
-                Err(_) => Ok(Self::None),
-            }
+            let byron = ByronAddress::from_string(text)
+                .map_err(|e| anyhow!("Not a Cardano address: {e}"))?;
+            Ok(Self::Byron(byron))
         }
     }
 
@@ -711,6 +792,22 @@ impl Address {
         }
     }
 
+    /// Read the binary form given by `to_bytes_key`
+    pub fn from_bytes_key(data: &[u8]) -> Result<Self> {
+        match data.first() {
+            None => Ok(Address::None),
+            // Byron addresses are CBOR, an array of 2
+            Some(0x82) => {
+                let mut dec = minicbor::Decoder::new(data);
+                Ok(Address::Byron(ByronAddress::from_cbor(&mut dec)?))
+            }
+            Some(header) if header >> 4 >= 0b1110 => {
+                Ok(Address::Stake(StakeAddress::from_binary(data)?))
+            }
+            Some(_) => Ok(Address::Shelley(ShelleyAddress::from_bytes_key(data)?)),
+        }
+    }
+
     pub fn to_stake_address(&self) -> Option<StakeAddress> {
         match self {
             Address::Shelley(shelley) => match &shelley.delegation {
@@ -772,6 +869,10 @@ impl<'de> Deserialize<'de> for Address {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
+        // As serialized for Address::None
+        if s == "None" {
+            return Ok(Address::None);
+        }
         Address::from_string(&s).map_err(|e| SerdeError::custom(format!("Invalid address: {}", e)))
     }
 }
@@ -810,6 +911,60 @@ mod tests {
         assert_eq!(address, unpacked);
     }
 
+    #[test]
+    fn byron_daedalus_address_details() {
+        let text = "DdzFFzCqrht7fNAHwdou7iXPJ5NZrssAH53yoRMUtF9t6momHH52EAxM5KmqDwhrjT7QsHjbMPJUBywmzAgmF4hj2h9eKj4U6Ahandyy";
+        let Address::Byron(byron) = Address::from_string(text).unwrap() else {
+            panic!("not a Byron address");
+        };
+        assert_eq!(byron.to_string().unwrap(), text);
+
+        let details = byron.details().unwrap();
+        assert_eq!(details.address_type, ByronAddressType::PubKey);
+        assert_eq!(details.network_magic, None);
+        assert!(details.has_derivation_path);
+        assert_eq!(byron.network().unwrap(), NetworkId::Mainnet);
+    }
+
+    #[test]
+    fn byron_testnet_address_details() {
+        // [root, {2: bytes(cbor(magic))}, 2]
+        let mut magic = Vec::new();
+        minicbor::Encoder::new(&mut magic).u32(1097911063).unwrap();
+        let mut payload = Vec::new();
+        minicbor::Encoder::new(&mut payload)
+            .array(3)
+            .unwrap()
+            .bytes(&[7; 28])
+            .unwrap()
+            .map(1)
+            .unwrap()
+            .u8(2)
+            .unwrap()
+            .bytes(&magic)
+            .unwrap()
+            .u8(2)
+            .unwrap();
+        let byron = ByronAddress { payload };
+
+        let details = byron.details().unwrap();
+        assert_eq!(details.root, Hash::new([7; 28]));
+        assert_eq!(details.address_type, ByronAddressType::Redeem);
+        assert_eq!(details.network_magic, Some(1097911063));
+        assert!(!details.has_derivation_path);
+        assert!(byron.is_redeem_address());
+        assert_eq!(byron.network().unwrap(), NetworkId::Testnet);
+
+        let text = byron.to_string().unwrap();
+        assert_eq!(Address::from_string(&text).unwrap(), Address::Byron(byron));
+    }
+
+    #[test]
+    fn unrecognised_address_is_an_error() {
+        assert!(Address::from_string("not an address").is_err());
+        assert!(Address::from_string("8MMy4x9jE734Ga").is_err());
+    }
+
     // Standard keys from CIP-19
     fn test_payment_key_hash() -> KeyHash {
         let payment_key = "addr_vk1w0l2sr2zgfm26ztc6nl9xy8ghsk5sh6ldwemlpmp9xylzy4dtf7st80zhd";
@@ -0,0 +1,522 @@
+//! Subscriptions which read ahead into a bounded buffer
+//!
+//! A module which falls behind its topics otherwise leaves the backlog wherever
+//! the bus keeps it, out of sight. A [`BufferedSubscription`] reads its topic ahead
+//! into a buffer of at most `subscription.buffer` messages, and what happens once
+//! that is full is set by `subscription.overflow`:
+//!
+//! - `block` stops reading until the module catches up, holding the publisher back
+//! - `drop-oldest` discards the oldest unread message, for topics where only the
+//!   latest matters
+//! - `spill` writes further messages to a file under `subscription.spill-directory`,
+//!   and reads them back in order as the buffer drains
+//!
+//! Each subscription reports its unread, in-flight, dropped and spilled counts to
+//! the metrics, labelled by subscriber and topic, so a module which can't keep up
+//! shows as the one whose unread count climbs.
+//!
+//! Buffering is off unless `subscription.buffer` is set, under `[global.subscription]`
+//! or in a module's own section.
+//!
+//! Subscriptions made here are also where messages are sampled for
+//! [`crate::message_trace`], as the module reads them.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use caryatid_sdk::{async_trait, Context, Subscription};
+use config::Config;
+use serde::Deserialize;
+use tokio::{sync::Notify, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    configuration::{conf_enum, get_string_flag, get_u64_flag},
+    message_trace::{self, TracedSubscription},
+    messages::Message,
+    metrics::{self, Counter, Gauge},
+};
+
+/// Messages read ahead of the module - unbuffered if 0
+pub const DEFAULT_SUBSCRIPTION_BUFFER: (&str, u64) = ("subscription.buffer", 0);
+
+/// What to do when the buffer is full
+pub const DEFAULT_SUBSCRIPTION_OVERFLOW: (&str, OverflowPolicy) =
+    ("subscription.overflow", OverflowPolicy::Block);
+
+/// Directory spilled messages are kept in
+pub const DEFAULT_SUBSCRIPTION_SPILL_DIRECTORY: (&str, &str) =
+    ("subscription.spill-directory", "spill");
+
+/// What a full buffer does with the next message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Wait for the module to read one
+    Block,
+
+    /// Discard the oldest unread message
+    DropOldest,
+
+    /// Write it to disk until the buffer drains
+    Spill,
+}
+
+/// How a subscription is buffered
+#[derive(Debug, Clone)]
+pub struct BufferSettings {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    pub spill_directory: PathBuf,
+}
+
+impl BufferSettings {
+    /// Settings from config, or None if buffering is off
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let capacity = get_u64_flag(config, DEFAULT_SUBSCRIPTION_BUFFER) as usize;
+        if capacity == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            capacity,
+            overflow: conf_enum(config, DEFAULT_SUBSCRIPTION_OVERFLOW)?,
+            spill_directory: get_string_flag(config, DEFAULT_SUBSCRIPTION_SPILL_DIRECTORY).into(),
+        }))
+    }
+}
+
+/// Subscribe to `topic` for `subscriber`, buffered if the config asks for it
+pub async fn subscribe(
+    context: &Context<Message>,
+    config: &Config,
+    subscriber: &str,
+    topic: &str,
+) -> Result<Box<dyn Subscription<Message>>> {
+    let inner = context.subscribe(topic).await?;
+    let subscription: Box<dyn Subscription<Message>> = match BufferSettings::from_config(config)? {
+        Some(settings) => {
+            info!(
+                "Buffering {} messages on '{topic}' for {subscriber}, overflow {:?}",
+                settings.capacity, settings.overflow
+            );
+            Box::new(BufferedSubscription::new(
+                inner, subscriber, topic, &settings,
+            ))
+        }
+        None => inner,
+    };
+
+    Ok(if message_trace::enabled() {
+        Box::new(TracedSubscription::new(subscription, subscriber))
+    } else {
+        subscription
+    })
+}
+
+/// Messages which didn't fit in the buffer, as JSON lines, oldest first
+struct Spill {
+    path: PathBuf,
+    writer: File,
+    reader: BufReader<File>,
+    count: usize,
+}
+
+impl Spill {
+    fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = OpenOptions::new().create(true).append(true).open(path)?;
+        writer.set_len(0)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            reader: BufReader::new(File::open(path)?),
+            count: 0,
+        })
+    }
+
+    fn push(&mut self, topic: &str, message: &Message) -> Result<()> {
+        writeln!(self.writer, "{}", serde_json::to_string(&(topic, message))?)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Option<(String, Arc<Message>)>> {
+        if self.count == 0 {
+            return Ok(None);
+        }
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        let (topic, message): (String, Message) = serde_json::from_str(&line)?;
+        self.count -= 1;
+
+        // Start the file afresh each time it empties, so it doesn't grow for ever
+        if self.count == 0 {
+            self.writer.set_len(0)?;
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+        Ok(Some((topic, Arc::new(message))))
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct Buffer {
+    queue: VecDeque<(String, Arc<Message>)>,
+    spill: Option<Spill>,
+
+    /// Why the topic stopped, once it has
+    closed: Option<String>,
+}
+
+impl Buffer {
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.count)
+    }
+}
+
+struct Shared {
+    buffer: Mutex<Buffer>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    spill_path: PathBuf,
+
+    /// Signalled when a message is buffered or the topic closes
+    readable: Notify,
+
+    /// Signalled when the module takes a message
+    writable: Notify,
+
+    unread: Gauge,
+    in_flight: Gauge,
+    dropped: Counter,
+    spilled: Counter,
+}
+
+impl Shared {
+    /// Buffer a message read from the topic, applying the overflow policy
+    async fn offer(&self, topic: String, message: Arc<Message>) -> Result<()> {
+        loop {
+            {
+                let mut buffer = self.buffer.lock().unwrap();
+                if buffer.queue.len() < self.capacity && buffer.spilled() == 0 {
+                    buffer.queue.push_back((topic, message));
+                    break;
+                }
+                match self.overflow {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropOldest => {
+                        buffer.queue.pop_front();
+                        buffer.queue.push_back((topic, message));
+                        self.dropped.inc();
+                        break;
+                    }
+                    OverflowPolicy::Spill => {
+                        if buffer.spill.is_none() {
+                            buffer.spill = Some(Spill::create(&self.spill_path)?);
+                        }
+                        if let Some(spill) = &mut buffer.spill {
+                            spill.push(&topic, &message)?;
+                        }
+                        self.spilled.inc();
+                        break;
+                    }
+                }
+            }
+            self.writable.notified().await;
+        }
+
+        self.count_unread();
+        self.readable.notify_one();
+        Ok(())
+    }
+
+    /// Take the next message, if one is buffered
+    fn take(&self) -> Result<Option<(String, Arc<Message>)>> {
+        let next = {
+            let mut guard = self.buffer.lock().unwrap();
+            let buffer = &mut *guard;
+            let Some(next) = buffer.queue.pop_front() else {
+                if let Some(reason) = &buffer.closed {
+                    bail!("Subscription closed: {reason}");
+                }
+                return Ok(None);
+            };
+
+            // Spilled messages come back in as room is made
+            if let Some(spill) = &mut buffer.spill {
+                if let Some(spilled) = spill.pop()? {
+                    buffer.queue.push_back(spilled);
+                }
+            }
+            next
+        };
+
+        self.count_unread();
+        self.writable.notify_one();
+        Ok(Some(next))
+    }
+
+    fn close(&self, reason: String) {
+        self.buffer.lock().unwrap().closed = Some(reason);
+        self.readable.notify_one();
+    }
+
+    fn count_unread(&self) {
+        let buffer = self.buffer.lock().unwrap();
+        self.unread.set((buffer.queue.len() + buffer.spilled()) as f64);
+    }
+}
+
+/// Subscription read ahead into a bounded buffer
+pub struct BufferedSubscription {
+    shared: Arc<Shared>,
+    reader: JoinHandle<()>,
+}
+
+impl BufferedSubscription {
+    /// Buffer `inner`, which `subscriber` subscribed to `topic`
+    pub fn new(
+        inner: Box<dyn Subscription<Message>>,
+        subscriber: &str,
+        topic: &str,
+        settings: &BufferSettings,
+    ) -> Self {
+        let labels = [("subscriber", subscriber), ("topic", topic)];
+        let file_name: String = format!("{subscriber}.{topic}")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(Buffer {
+                queue: VecDeque::with_capacity(settings.capacity),
+                spill: None,
+                closed: None,
+            }),
+            capacity: settings.capacity,
+            overflow: settings.overflow,
+            spill_path: settings.spill_directory.join(format!("{file_name}.jsonl")),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            unread: metrics::gauge(
+                "acropolis_subscription_unread",
+                "Messages buffered or spilled which the subscriber has yet to read",
+                &labels,
+            ),
+            in_flight: metrics::gauge(
+                "acropolis_subscription_in_flight",
+                "Messages the subscriber has read and not yet finished with",
+                &labels,
+            ),
+            dropped: metrics::counter(
+                "acropolis_subscription_dropped_total",
+                "Messages dropped from a full subscription buffer",
+                &labels,
+            ),
+            spilled: metrics::counter(
+                "acropolis_subscription_spilled_total",
+                "Messages spilled to disk from a full subscription buffer",
+                &labels,
+            ),
+        });
+
+        let reader = tokio::spawn(Self::read_ahead(inner, shared.clone(), topic.to_string()));
+        Self { shared, reader }
+    }
+
+    async fn read_ahead(
+        mut inner: Box<dyn Subscription<Message>>,
+        shared: Arc<Shared>,
+        topic: String,
+    ) {
+        loop {
+            let result = match inner.read().await {
+                Ok((read_topic, message)) => shared.offer(read_topic, message).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!("Stopped reading ahead on '{topic}': {e:#}");
+                shared.close(format!("{e:#}"));
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for BufferedSubscription {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[async_trait]
+impl Subscription<Message> for BufferedSubscription {
+    async fn read(&mut self) -> Result<(String, Arc<Message>)> {
+        // Reading again means the module is done with the last one
+        self.shared.in_flight.set(0.0);
+        loop {
+            if let Some(next) = self.shared.take()? {
+                self.shared.in_flight.set(1.0);
+                return Ok(next);
+            }
+            self.shared.readable.notified().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Subscription fed from a channel, which closes when the sender is dropped
+    struct ChannelSubscription(mpsc::UnboundedReceiver<Message>);
+
+    #[async_trait]
+    impl Subscription<Message> for ChannelSubscription {
+        async fn read(&mut self) -> Result<(String, Arc<Message>)> {
+            match self.0.recv().await {
+                Some(message) => Ok(("test.topic".to_string(), Arc::new(message))),
+                None => bail!("sender gone"),
+            }
+        }
+    }
+
+    /// Each test has its own subscriber name, so its metrics are its own
+    fn buffered(
+        subscriber: &str,
+        capacity: usize,
+        overflow: OverflowPolicy,
+        spill_directory: &Path,
+        messages: &[&str],
+    ) -> BufferedSubscription {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        for text in messages {
+            sender.send(Message::String(text.to_string())).unwrap();
+        }
+        let settings = BufferSettings {
+            capacity,
+            overflow,
+            spill_directory: spill_directory.to_path_buf(),
+        };
+        BufferedSubscription::new(
+            Box::new(ChannelSubscription(receiver)),
+            subscriber,
+            "test.topic",
+            &settings,
+        )
+    }
+
+    async fn until(condition: impl Fn() -> bool) {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    async fn read_text(subscription: &mut BufferedSubscription) -> Result<String> {
+        match subscription.read().await?.1.as_ref() {
+            Message::String(text) => Ok(text.clone()),
+            other => bail!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn block_holds_back_the_topic() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut subscription = buffered(
+            "block-test",
+            2,
+            OverflowPolicy::Block,
+            dir.path(),
+            &["a", "b", "c", "d"],
+        );
+        let shared = subscription.shared.clone();
+        until(|| shared.unread.get() == 2.0).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(shared.unread.get(), 2.0);
+
+        for expected in ["a", "b", "c", "d"] {
+            assert_eq!(read_text(&mut subscription).await?, expected);
+            assert_eq!(shared.in_flight.get(), 1.0);
+        }
+        assert_eq!(shared.dropped.get(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_latest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut subscription = buffered(
+            "drop-oldest-test",
+            2,
+            OverflowPolicy::DropOldest,
+            dir.path(),
+            &["a", "b", "c", "d", "e"],
+        );
+        let shared = subscription.shared.clone();
+        until(|| shared.buffer.lock().unwrap().closed.is_some()).await;
+
+        assert_eq!(shared.dropped.get(), 3);
+        assert_eq!(read_text(&mut subscription).await?, "d");
+        assert_eq!(read_text(&mut subscription).await?, "e");
+        assert!(subscription.read().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn spill_keeps_every_message_in_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let texts = ["a", "b", "c", "d", "e", "f"];
+        let mut subscription = buffered("spill-test", 2, OverflowPolicy::Spill, dir.path(), &texts);
+        let shared = subscription.shared.clone();
+        until(|| shared.buffer.lock().unwrap().closed.is_some()).await;
+
+        assert_eq!(shared.spilled.get(), 4);
+        assert_eq!(shared.unread.get(), 6.0);
+        assert!(shared.spill_path.exists());
+
+        for expected in texts {
+            assert_eq!(read_text(&mut subscription).await?, expected);
+        }
+        assert_eq!(shared.unread.get(), 0.0);
+        assert!(subscription.read().await.is_err());
+
+        drop(subscription);
+        drop(shared);
+        assert!(!dir.path().join("spill-test.test.topic.jsonl").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn buffering_is_off_by_default() -> Result<()> {
+        assert!(BufferSettings::from_config(&Config::default())?.is_none());
+
+        let config = Config::builder()
+            .set_override("subscription.buffer", 100)?
+            .set_override("subscription.overflow", "drop-oldest")?
+            .build()?;
+        let settings = BufferSettings::from_config(&config)?.expect("buffered");
+        assert_eq!(settings.capacity, 100);
+        assert_eq!(settings.overflow, OverflowPolicy::DropOldest);
+        Ok(())
+    }
+}
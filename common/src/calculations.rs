@@ -42,6 +42,21 @@ pub fn slot_to_timestamp_with_params(slot: u64, byron_timestamp: u64, shelley_ep
     }
 }
 
+/// Derive the slot in progress at a timestamp - the inverse of [`slot_to_timestamp_with_params`]
+pub fn timestamp_to_slot_with_params(
+    timestamp: u64,
+    byron_timestamp: u64,
+    shelley_epoch: u64,
+) -> u64 {
+    let shelley_start_slot = shelley_epoch * BYRON_SLOTS_PER_EPOCH;
+    let shelley_timestamp = byron_timestamp + shelley_start_slot * 20;
+    if timestamp < shelley_timestamp {
+        timestamp.saturating_sub(byron_timestamp) / 20
+    } else {
+        shelley_start_slot + (timestamp - shelley_timestamp)
+    }
+}
+
 // -- Tests --
 #[cfg(test)]
 mod tests {
@@ -62,6 +77,10 @@ mod tests {
         slot_to_timestamp_with_params(slot, BYRON_START_TIMESTAMP, SHELLEY_START_EPOCH)
     }
 
+    fn timestamp_to_slot(timestamp: u64) -> u64 {
+        timestamp_to_slot_with_params(timestamp, BYRON_START_TIMESTAMP, SHELLEY_START_EPOCH)
+    }
+
     #[test]
     fn byron_epoch_0() {
         assert_eq!(slot_to_epoch(0), (0, 0));
@@ -107,4 +126,14 @@ mod tests {
         assert_eq!(slot_to_epoch(epoch_to_first_slot(209)), (209, 0));
         assert_eq!(slot_to_epoch(epoch_to_first_slot(150)), (150, 0));
     }
+
+    #[test]
+    fn timestamp_to_slot_test() {
+        assert_eq!(timestamp_to_slot(1506203091), 0);
+        assert_eq!(timestamp_to_slot(1506203091 + 19), 0);
+        assert_eq!(timestamp_to_slot(1506635091), 21_600);
+        assert_eq!(timestamp_to_slot(1596059091), 4_492_800);
+        assert_eq!(timestamp_to_slot(1689838294), 98_272_003);
+        assert_eq!(timestamp_to_slot(0), 0);
+    }
 }
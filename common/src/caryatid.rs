@@ -151,17 +151,51 @@ impl PrimaryRead<Message> {
     }
 }
 
+/// Puts a message published in chunks back in order for a reader - either whole, or
+/// handing each chunk on to a sink which takes the message in as it arrives
+pub trait ChunkAssembler: Default {
+    type Chunk;
+    type Message;
+
+    /// What takes in the message a chunk at a time
+    type Sink: ?Sized;
+
+    /// Add a chunk, returning the whole message once its last chunk arrives
+    fn push(&mut self, chunk: &Self::Chunk) -> Result<Option<Self::Message>>;
+
+    /// Check a chunk is next in order and hand it to `sink` without keeping it,
+    /// returning whether the message is now complete
+    fn stream(&mut self, chunk: &Self::Chunk, sink: &mut Self::Sink) -> Result<bool>;
+
+    /// Hand a message which was not chunked to `sink`
+    fn stream_whole(message: &Self::Message, sink: &mut Self::Sink);
+
+    /// Drop any partly received message, on rollback
+    fn reset(&mut self);
+}
+
 /// Declares locally tailored cardano reader struct, providing a lightweight wrapper around
 /// Subscribers from topics. The Main intention is to get rid of boilerplate code, and simplify:
 /// (a) topic configuration, config parameters reading and reader initialization;
 /// (b) data reading from the topic subscriber. The data are taken from the enum constructor
 /// by the functions, provided in the struct being declared, so that the user does not need to
 /// manually specify pattern matching code.
+///
+/// If the message may also be published in chunks, name the chunk constructor and the
+/// assembler for it after the message type (`..., ChunkConstructor => Assembler`), and the
+/// reader returns the message put back together once its last chunk arrives. Or read it
+/// with `stream_with_rollbacks`, which hands each chunk to the assembler's sink as it
+/// arrives, so the whole message is never held.
+///
+/// Readers read ahead into a bounded buffer if the module's config sets one up - see
+/// [`crate::buffered_subscription`].
 #[macro_export]
 macro_rules! declare_cardano_reader {
-    ($reader_name:ident, $param:expr, $def_topic:expr, $msg_constructor:ident, $msg_type:ty) => {
+    ($reader_name:ident, $param:expr, $def_topic:expr, $msg_constructor:ident, $msg_type:ty
+     $(, $chunk_constructor:ident => $assembler:ty)?) => {
         pub struct $reader_name {
             sub: Box<dyn Subscription<Message>>,
+            $(assembler: $assembler,)?
         }
 
         impl $reader_name {
@@ -174,7 +208,14 @@ macro_rules! declare_cardano_reader {
 
                 info!("Creating subscriber on '{topic_name}' for '{}'", $param);
                 Ok(Self {
-                    sub: ctx.subscribe(&topic_name).await?,
+                    sub: $crate::buffered_subscription::subscribe(
+                        ctx,
+                        cfg,
+                        module_path!(),
+                        &topic_name,
+                    )
+                    .await?,
+                    $(assembler: <$assembler>::default(),)?
                 })
             }
 
@@ -211,7 +252,14 @@ macro_rules! declare_cardano_reader {
                         }
                         info!("Creating subscriber on '{topic_name}' for '{}'", $param);
                         Ok(Some(Self {
-                            sub: ctx.subscribe(&topic_name).await?,
+                            sub: $crate::buffered_subscription::subscribe(
+                                ctx,
+                                cfg,
+                                module_path!(),
+                                &topic_name,
+                            )
+                            .await?,
+                            $(assembler: <$assembler>::default(),)?
                         }))
                     }
                     Err(e) => {
@@ -227,19 +275,90 @@ macro_rules! declare_cardano_reader {
             /// Reads message, returning rollback messages as well.
             /// Unexpected message (not applicable to the topic and not a rollback)
             /// results in error.
+            #[allow(clippy::never_loop)] // Only loops over chunks
             pub async fn read_with_rollbacks(&mut self) -> Result<RollbackWrapper<$msg_type>> {
-                let res = self.sub.read().await?.1;
-                match res.as_ref() {
-                    Message::Cardano((blk, CardanoMessage::$msg_constructor(body))) => Ok(
-                        RollbackWrapper::Normal((Arc::new(blk.clone()), Arc::new(body.clone()))),
-                    ),
-                    Message::Cardano((
-                        blk,
-                        CardanoMessage::StateTransition(StateTransitionMessage::Rollback(_)),
-                    )) => Ok(RollbackWrapper::Rollback((Arc::new(blk.clone()),res.clone()))),
-                    msg => bail!("Unexpected message {msg:?} for {}", $param),
+                loop {
+                    let res = self.sub.read().await?.1;
+                    return match res.as_ref() {
+                        Message::Cardano((blk, CardanoMessage::$msg_constructor(body))) => Ok(
+                            RollbackWrapper::Normal((Arc::new(blk.clone()), Arc::new(body.clone()))),
+                        ),
+                        $(Message::Cardano((blk, CardanoMessage::$chunk_constructor(chunk))) => {
+                            match <$assembler as $crate::caryatid::ChunkAssembler>::push(
+                                &mut self.assembler,
+                                chunk,
+                            )? {
+                                Some(body) => Ok(RollbackWrapper::Normal((
+                                    Arc::new(blk.clone()),
+                                    Arc::new(body),
+                                ))),
+                                None => continue,
+                            }
+                        })?
+                        Message::Cardano((
+                            blk,
+                            CardanoMessage::StateTransition(StateTransitionMessage::Rollback(_)),
+                        )) => {
+                            $(<$assembler as $crate::caryatid::ChunkAssembler>::reset(
+                                &mut self.assembler,
+                            );)?
+                            Ok(RollbackWrapper::Rollback((Arc::new(blk.clone()), res.clone())))
+                        }
+                        msg => bail!("Unexpected message {msg:?} for {}", $param),
+                    };
+                }
+            }
+
+            $(
+            /// Reads a message a chunk at a time, handing each chunk to `sink` as it arrives
+            /// rather than putting the whole message together. A message which was not
+            /// chunked goes to `sink` in one. Returns once the message is complete, or on
+            /// a rollback - in which case drop whatever `sink` took in.
+            pub async fn stream_with_rollbacks(
+                &mut self,
+                sink: &mut <$assembler as $crate::caryatid::ChunkAssembler>::Sink,
+            ) -> Result<RollbackWrapper<()>> {
+                loop {
+                    let res = self.sub.read().await?.1;
+                    match res.as_ref() {
+                        Message::Cardano((blk, CardanoMessage::$msg_constructor(body))) => {
+                            <$assembler as $crate::caryatid::ChunkAssembler>::stream_whole(
+                                body, sink,
+                            );
+                            return Ok(RollbackWrapper::Normal((
+                                Arc::new(blk.clone()),
+                                Arc::new(()),
+                            )));
+                        }
+                        Message::Cardano((blk, CardanoMessage::$chunk_constructor(chunk))) => {
+                            if <$assembler as $crate::caryatid::ChunkAssembler>::stream(
+                                &mut self.assembler,
+                                chunk,
+                                sink,
+                            )? {
+                                return Ok(RollbackWrapper::Normal((
+                                    Arc::new(blk.clone()),
+                                    Arc::new(()),
+                                )));
+                            }
+                        }
+                        Message::Cardano((
+                            blk,
+                            CardanoMessage::StateTransition(StateTransitionMessage::Rollback(_)),
+                        )) => {
+                            <$assembler as $crate::caryatid::ChunkAssembler>::reset(
+                                &mut self.assembler,
+                            );
+                            return Ok(RollbackWrapper::Rollback((
+                                Arc::new(blk.clone()),
+                                res.clone(),
+                            )));
+                        }
+                        msg => bail!("Unexpected message {msg:?} for {}", $param),
+                    }
                 }
             }
+            )?
         }
     };
 }
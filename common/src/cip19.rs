@@ -1,5 +1,11 @@
-//! Variable length integer encoding/decoding according to CIP19
-use anyhow::{anyhow, Result};
+//! CIP-19 addresses: variable length integer encoding/decoding, building Shelley
+//! addresses from credentials, and breaking any address down into its parts
+use crate::address::{
+    Address, ShelleyAddress, ShelleyAddressDelegationPart, ShelleyAddressPaymentPart,
+    ShelleyAddressPointer, StakeAddress,
+};
+use crate::{Credential, KeyHash, NetworkId, StakeCredential};
+use anyhow::{anyhow, bail, Result};
 
 // ANBF:
 // VARIABLE-LENGTH-UINT = (%b1 | UINT7 | VARIABLE-LENGTH-UINT)
@@ -85,6 +91,199 @@ impl<'a> VarIntDecoder<'a> {
     }
 }
 
+fn payment_part(credential: &Credential) -> ShelleyAddressPaymentPart {
+    match credential {
+        Credential::AddrKeyHash(hash) => ShelleyAddressPaymentPart::PaymentKeyHash(*hash),
+        Credential::ScriptHash(hash) => ShelleyAddressPaymentPart::ScriptHash(*hash),
+    }
+}
+
+/// Base address - payment credential, delegating to a stake credential
+pub fn base_address(
+    network: NetworkId,
+    payment: &Credential,
+    stake: &StakeCredential,
+) -> ShelleyAddress {
+    ShelleyAddress {
+        network,
+        payment: payment_part(payment),
+        delegation: match stake {
+            Credential::AddrKeyHash(hash) => ShelleyAddressDelegationPart::StakeKeyHash(*hash),
+            Credential::ScriptHash(hash) => ShelleyAddressDelegationPart::ScriptHash(*hash),
+        },
+    }
+}
+
+/// Pointer address - payment credential, delegating to the stake registration
+/// certificate at `pointer`
+pub fn pointer_address(
+    network: NetworkId,
+    payment: &Credential,
+    pointer: ShelleyAddressPointer,
+) -> ShelleyAddress {
+    ShelleyAddress {
+        network,
+        payment: payment_part(payment),
+        delegation: ShelleyAddressDelegationPart::Pointer(pointer),
+    }
+}
+
+/// Enterprise address - payment credential with no delegation
+pub fn enterprise_address(network: NetworkId, payment: &Credential) -> ShelleyAddress {
+    ShelleyAddress {
+        network,
+        payment: payment_part(payment),
+        delegation: ShelleyAddressDelegationPart::None,
+    }
+}
+
+/// Reward (stake) address for a stake credential
+pub fn reward_address(network: NetworkId, stake: &StakeCredential) -> StakeAddress {
+    StakeAddress::new(stake.clone(), network)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressEra {
+    Byron,
+    Shelley,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressType {
+    Base,
+    Pointer,
+    Enterprise,
+    Byron,
+    Reward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    KeyHash,
+    ScriptHash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CredentialInspection {
+    pub kind: CredentialKind,
+    pub hash: KeyHash,
+}
+
+impl CredentialInspection {
+    fn key(hash: &KeyHash) -> Self {
+        Self {
+            kind: CredentialKind::KeyHash,
+            hash: *hash,
+        }
+    }
+
+    fn script(hash: &KeyHash) -> Self {
+        Self {
+            kind: CredentialKind::ScriptHash,
+            hash: *hash,
+        }
+    }
+}
+
+impl From<&Credential> for CredentialInspection {
+    fn from(credential: &Credential) -> Self {
+        match credential {
+            Credential::AddrKeyHash(hash) => Self::key(hash),
+            Credential::ScriptHash(hash) => Self::script(hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeReference {
+    Credential(CredentialInspection),
+    Pointer(ShelleyAddressPointer),
+}
+
+/// Structured breakdown of an address
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AddressInspection {
+    pub era: AddressEra,
+    pub address_type: AddressType,
+    /// Header type, the top four bits of the first byte
+    pub header_type: u8,
+    /// For Byron addresses, from the protocol magic attribute
+    pub network: Option<NetworkId>,
+    pub payment_credential: Option<CredentialInspection>,
+    pub stake_reference: Option<StakeReference>,
+}
+
+/// Break an address in any of its text forms down into its parts
+pub fn inspect(text: &str) -> Result<AddressInspection> {
+    let inspection = match Address::from_string(text)? {
+        Address::None => bail!("Not a Cardano address"),
+        Address::Byron(byron) => AddressInspection {
+            era: AddressEra::Byron,
+            address_type: AddressType::Byron,
+            header_type: 0b1000,
+            network: byron.network().ok(),
+            payment_credential: None,
+            stake_reference: None,
+        },
+        Address::Shelley(shelley) => {
+            let (payment_credential, payment_bit) = match &shelley.payment {
+                ShelleyAddressPaymentPart::PaymentKeyHash(hash) => {
+                    (CredentialInspection::key(hash), 0)
+                }
+                ShelleyAddressPaymentPart::ScriptHash(hash) => {
+                    (CredentialInspection::script(hash), 1)
+                }
+            };
+            let (address_type, stake_reference, header_type) = match &shelley.delegation {
+                ShelleyAddressDelegationPart::StakeKeyHash(hash) => (
+                    AddressType::Base,
+                    Some(StakeReference::Credential(CredentialInspection::key(hash))),
+                    0b0000,
+                ),
+                ShelleyAddressDelegationPart::ScriptHash(hash) => (
+                    AddressType::Base,
+                    Some(StakeReference::Credential(CredentialInspection::script(
+                        hash,
+                    ))),
+                    0b0010,
+                ),
+                ShelleyAddressDelegationPart::Pointer(pointer) => (
+                    AddressType::Pointer,
+                    Some(StakeReference::Pointer(pointer.clone())),
+                    0b0100,
+                ),
+                ShelleyAddressDelegationPart::None => (AddressType::Enterprise, None, 0b0110),
+            };
+            AddressInspection {
+                era: AddressEra::Shelley,
+                address_type,
+                header_type: header_type | payment_bit,
+                network: Some(shelley.network),
+                payment_credential: Some(payment_credential),
+                stake_reference,
+            }
+        }
+        Address::Stake(stake) => AddressInspection {
+            era: AddressEra::Shelley,
+            address_type: AddressType::Reward,
+            header_type: match stake.credential {
+                Credential::AddrKeyHash(_) => 0b1110,
+                Credential::ScriptHash(_) => 0b1111,
+            },
+            network: Some(stake.network),
+            payment_credential: None,
+            stake_reference: Some(StakeReference::Credential(CredentialInspection::from(
+                &stake.credential,
+            ))),
+        },
+    };
+    Ok(inspection)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,6 +315,84 @@ mod tests {
         }
     }
 
+    // Credentials from the CIP-19 test vectors
+    fn payment_key() -> Credential {
+        Credential::AddrKeyHash(
+            "9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e".parse().unwrap(),
+        )
+    }
+
+    fn stake_key() -> Credential {
+        Credential::AddrKeyHash(
+            "337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251".parse().unwrap(),
+        )
+    }
+
+    fn script() -> Credential {
+        Credential::ScriptHash(
+            "c37b1b5dc0669f1d3c61a6fddb2e8fde96be87b881c60bce8e8d542f".parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn builds_cip19_test_vectors() {
+        let pointer = ShelleyAddressPointer {
+            slot: 2498243,
+            tx_index: 27,
+            cert_index: 3,
+        };
+        let mainnet = NetworkId::Mainnet;
+
+        assert_eq!(
+            base_address(mainnet.clone(), &payment_key(), &stake_key()).to_string().unwrap(),
+            "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x"
+        );
+        assert_eq!(
+            base_address(mainnet.clone(), &payment_key(), &script()).to_string().unwrap(),
+            "addr1yx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerkr0vd4msrxnuwnccdxlhdjar77j6lg0wypcc9uar5d2shs2z78ve"
+        );
+        assert_eq!(
+            pointer_address(mainnet.clone(), &script(), pointer).to_string().unwrap(),
+            "addr128phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtupnz75xxcrtw79hu"
+        );
+        assert_eq!(
+            enterprise_address(mainnet.clone(), &payment_key()).to_string().unwrap(),
+            "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8"
+        );
+        assert_eq!(
+            reward_address(mainnet, &stake_key()).to_string().unwrap(),
+            "stake1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw"
+        );
+    }
+
+    #[test]
+    fn inspects_shelley_and_reward_addresses() {
+        let text = base_address(NetworkId::Testnet, &script(), &stake_key()).to_string().unwrap();
+        let inspection = inspect(&text).unwrap();
+        assert_eq!(inspection.era, AddressEra::Shelley);
+        assert_eq!(inspection.address_type, AddressType::Base);
+        assert_eq!(inspection.header_type, 1);
+        assert_eq!(inspection.network, Some(NetworkId::Testnet));
+        assert_eq!(
+            inspection.payment_credential,
+            Some(CredentialInspection::from(&script()))
+        );
+        assert_eq!(
+            inspection.stake_reference,
+            Some(StakeReference::Credential(CredentialInspection::from(
+                &stake_key()
+            )))
+        );
+
+        let inspection =
+            inspect("stake178phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtcccycj5").unwrap();
+        assert_eq!(inspection.address_type, AddressType::Reward);
+        assert_eq!(inspection.header_type, 15);
+        assert_eq!(inspection.payment_credential, None);
+
+        assert!(inspect("not an address").is_err());
+    }
+
     #[test]
     fn uint_deserialization_test() {
         let data: Vec<u8> = vec![0, 1, 0x7f, 0x81, 0, 0x81, 0x80, 0, 0x88, 0];
@@ -1,2 +1,3 @@
 pub mod chain_sync;
+pub mod peer_network;
 pub mod transactions;
@@ -0,0 +1,17 @@
+/// Upstream access rules for the peer network interface. Each rule is a `host:port`
+/// address, a bare host or IP address, or a CIDR subnet such as `10.0.0.0/8`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PeerAccessRules {
+    /// If not empty, only peers matching one of these rules are connected to
+    pub allow: Vec<String>,
+    /// Peers matching any of these rules are never connected to, even if allowed
+    pub deny: Vec<String>,
+    /// Most new peer connections opened per minute, 0 = unlimited
+    pub max_connections_per_minute: u32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PeerNetworkCommand {
+    /// Replace the access rules, dropping connections to peers they no longer permit
+    SetAccessRules(PeerAccessRules),
+}
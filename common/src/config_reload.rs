@@ -0,0 +1,300 @@
+//! Reloading the configuration while the process runs
+//!
+//! Modules call [`on_reload`] during init with the config keys they can change
+//! on the fly, and a function which applies them. When the process is asked to
+//! reload it re-reads its config, sends each module its new section if any of
+//! those keys changed, and reports which changes were applied and which only
+//! take effect on a restart.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use caryatid_sdk::Context;
+use config::{Config, File, FileFormat};
+use tracing::{error, info, warn};
+
+use crate::{
+    configuration::get_string_flag,
+    messages::{
+        Command, CommandResponse, ConfigApplyAck, ConfigReloadReport, ConfigUpdate, Message,
+    },
+};
+
+/// Topic a reload is requested on - set under `[global.reload]`
+pub const DEFAULT_RELOAD_TOPIC: (&str, &str) = ("reload.topic", "cardano.config.reload");
+
+/// Topic modules are sent their changed config on
+pub const DEFAULT_RELOAD_APPLY_TOPIC: (&str, &str) = ("reload.apply-topic", "cardano.config.apply");
+
+/// Topic modules ack their changed config on
+pub const DEFAULT_RELOAD_ACK_TOPIC: (&str, &str) = ("reload.ack-topic", "cardano.config.apply.ack");
+
+/// Topic the report of each reload is published on
+pub const DEFAULT_RELOAD_REPORT_TOPIC: (&str, &str) =
+    ("reload.report-topic", "cardano.config.reload.report");
+
+/// How long to wait for the modules to apply their changes
+pub const DEFAULT_RELOAD_TIMEOUT: (&str, &str) = ("reload.timeout", "10s");
+
+/// Keys each module in this process can change without a restart
+static HOT_KEYS: LazyLock<Mutex<BTreeMap<String, BTreeSet<String>>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Keys each module in this process can change without a restart
+pub fn hot_keys() -> BTreeMap<String, BTreeSet<String>> {
+    HOT_KEYS.lock().unwrap().clone()
+}
+
+/// Run `apply` with the new `[module.*]` section whenever one of `keys` in it
+/// changes, then ack on behalf of `module`
+pub async fn on_reload<F, Fut>(
+    context: Arc<Context<Message>>,
+    config: &Config,
+    module: &'static str,
+    keys: &[&str],
+    apply: F,
+) -> Result<()>
+where
+    F: Fn(Config) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let topic = get_string_flag(config, DEFAULT_RELOAD_APPLY_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_RELOAD_ACK_TOPIC);
+    let mut subscription = context.subscribe(&topic).await?;
+    HOT_KEYS.lock().unwrap().insert(
+        module.to_string(),
+        keys.iter().map(|key| key.to_string()).collect(),
+    );
+
+    let run_context = context.clone();
+    context.run(async move {
+        loop {
+            let Ok((_, message)) = subscription.read().await else {
+                return;
+            };
+            let Message::Command(Command::ApplyConfig(update)) = message.as_ref() else {
+                continue;
+            };
+            if update.module != module {
+                continue;
+            }
+
+            info!("Applying changed {} to {module}", update.changed.join(", "));
+            let result = match section_config(&update.section) {
+                Ok(section) => apply(section).await,
+                Err(e) => Err(e),
+            };
+            let error = result.err().map(|e| format!("{e:#}"));
+            if let Some(e) = &error {
+                error!("{module} failed to apply its changed config: {e}");
+            }
+
+            let ack = Arc::new(Message::CommandResponse(CommandResponse::ConfigApplied(
+                ConfigApplyAck {
+                    module: module.to_string(),
+                    error,
+                },
+            )));
+            if let Err(e) = run_context.publish(&ack_topic, ack).await {
+                warn!("Could not publish {module} config ack: {e:#}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn section_config(section: &serde_json::Value) -> Result<Config> {
+    Ok(Config::builder()
+        .add_source(File::from_str(&section.to_string(), FileFormat::Json))
+        .build()?)
+}
+
+/// What a reload changes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReloadPlan {
+    /// Changed keys each module can apply now
+    pub updates: BTreeMap<String, Vec<String>>,
+
+    /// Changed keys which need a restart, in full
+    pub restart_required: Vec<String>,
+}
+
+/// Work out which changes between the `old` and `new` configs can be applied
+/// by the modules now, given the keys each can change
+pub fn plan(
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    hot_keys: &BTreeMap<String, BTreeSet<String>>,
+) -> ReloadPlan {
+    let mut old_values = BTreeMap::new();
+    flatten("", old, &mut old_values);
+    let mut new_values = BTreeMap::new();
+    flatten("", new, &mut new_values);
+
+    let changed: BTreeSet<&String> = old_values
+        .keys()
+        .chain(new_values.keys())
+        .filter(|path| old_values.get(*path) != new_values.get(*path))
+        .collect();
+
+    let mut plan = ReloadPlan::default();
+    for path in changed {
+        let parts: Vec<&str> = path.splitn(4, '.').collect();
+        if let ["module", module, key, ..] = parts.as_slice() {
+            if hot_keys.get(*module).is_some_and(|keys| keys.contains(*key)) {
+                let keys = plan.updates.entry(module.to_string()).or_default();
+                if !keys.iter().any(|k| k == *key) {
+                    keys.push(key.to_string());
+                }
+                continue;
+            }
+        }
+        plan.restart_required.push(path.clone());
+    }
+    plan
+}
+
+/// Dotted paths of every value which isn't a table
+fn flatten(prefix: &str, value: &serde_json::Value, out: &mut BTreeMap<String, serde_json::Value>) {
+    match value {
+        serde_json::Value::Object(table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&path, value, out);
+            }
+        }
+        value => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Send each module its changed config, and wait for them to apply it
+pub async fn reload(
+    context: &Arc<Context<Message>>,
+    config: &Config,
+    old: &Config,
+    new: &Config,
+) -> Result<ConfigReloadReport> {
+    let apply_topic = get_string_flag(config, DEFAULT_RELOAD_APPLY_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_RELOAD_ACK_TOPIC);
+    let timeout = get_string_flag(config, DEFAULT_RELOAD_TIMEOUT);
+    let timeout: Duration = humantime::parse_duration(&timeout)
+        .map_err(|e| anyhow!("bad {} '{timeout}': {e}", DEFAULT_RELOAD_TIMEOUT.0))?;
+
+    let new_values: serde_json::Value = new.clone().try_deserialize()?;
+    let plan = plan(&old.clone().try_deserialize()?, &new_values, &hot_keys());
+    let mut report = ConfigReloadReport {
+        restart_required: plan.restart_required,
+        ..ConfigReloadReport::default()
+    };
+    if plan.updates.is_empty() {
+        return Ok(report);
+    }
+
+    // Subscribe before sending, so no ack is missed
+    let mut subscription = context.subscribe(&ack_topic).await?;
+    for (module, changed) in &plan.updates {
+        let update = ConfigUpdate {
+            module: module.clone(),
+            changed: changed.clone(),
+            section: new_values["module"][module].clone(),
+        };
+        context
+            .publish(
+                &apply_topic,
+                Arc::new(Message::Command(Command::ApplyConfig(update))),
+            )
+            .await?;
+    }
+
+    let mut acks = BTreeMap::new();
+    let wait = async {
+        while acks.len() < plan.updates.len() {
+            let Ok((_, message)) = subscription.read().await else {
+                break;
+            };
+            if let Message::CommandResponse(CommandResponse::ConfigApplied(ack)) = message.as_ref()
+            {
+                if plan.updates.contains_key(&ack.module) {
+                    acks.insert(ack.module.clone(), ack.error.clone());
+                }
+            }
+        }
+    };
+    let _ = tokio::time::timeout(timeout, wait).await;
+
+    for (module, changed) in plan.updates {
+        match acks.remove(&module) {
+            Some(None) => {
+                report.applied.extend(changed.iter().map(|key| format!("module.{module}.{key}")))
+            }
+            Some(Some(error)) => report.failed.push((module, error)),
+            None => report.failed.push((module, format!("no ack after {timeout:?}"))),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn plan_splits_hot_and_restart_changes() {
+        let old = json!({
+            "global": { "startup": { "network": "mainnet" } },
+            "module": {
+                "peer-network-interface": {
+                    "allow-peers": [],
+                    "max-connections-per-minute": 0,
+                    "cache-dir": "upstream-cache",
+                },
+                "chain-store": { "store-txs": true },
+            },
+        });
+        let new = json!({
+            "global": { "startup": { "network": "preview" } },
+            "module": {
+                "peer-network-interface": {
+                    "allow-peers": ["10.0.0.0/8"],
+                    "max-connections-per-minute": 0,
+                    "cache-dir": "cache",
+                },
+                "chain-store": { "store-txs": true },
+                "spy": { "topic": "cardano.#" },
+            },
+        });
+        let hot_keys = BTreeMap::from([(
+            "peer-network-interface".to_string(),
+            BTreeSet::from(["allow-peers".to_string(), "deny-peers".to_string()]),
+        )]);
+
+        let plan = plan(&old, &new, &hot_keys);
+        assert_eq!(
+            plan.updates,
+            BTreeMap::from([(
+                "peer-network-interface".to_string(),
+                vec!["allow-peers".to_string()]
+            )])
+        );
+        assert_eq!(
+            plan.restart_required,
+            vec![
+                "global.startup.network",
+                "module.peer-network-interface.cache-dir",
+                "module.spy.topic",
+            ]
+        );
+    }
+}
@@ -1,6 +1,8 @@
+use anyhow::{anyhow, bail};
 use config::Config;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
 use std::fmt::{Display, Formatter, Result};
+use std::time::Duration;
 
 pub const CONFIG_KEY_STARTUP_MODE: &str = "startup.startup-mode";
 pub const CONFIG_KEY_SYNC_MODE: &str = "startup.sync-mode";
@@ -28,6 +30,77 @@ pub fn conf_enum<'a, T: Deserialize<'a>>(config: &Config, keydef: (&str, T)) ->
     }
 }
 
+/// Deserialise a [`Duration`] from a human readable string such as `"30s"` or `"5m"`
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    humantime::parse_duration(&value)
+        .map_err(|e| serde::de::Error::custom(format!("bad duration '{value}': {e}")))
+}
+
+/// Typed configuration of a module, read from its `[module.<name>]` section. Every
+/// field should have a default, so that an empty section is valid.
+pub trait ModuleConfig: DeserializeOwned {
+    /// Module name, as used in its config section
+    const MODULE: &'static str;
+
+    /// Report conflicting or out of range options
+    fn validate(&self, _errors: &mut Vec<String>) {}
+
+    /// Read and validate the config passed to the module. This has the global
+    /// sections merged in, so unknown keys are only reported by [`check_config`].
+    fn load(config: &Config) -> anyhow::Result<Self> {
+        let module_config: Self = config
+            .clone()
+            .try_deserialize()
+            .map_err(|e| anyhow!("invalid [module.{}] config: {e}", Self::MODULE))?;
+
+        let mut errors = Vec::new();
+        module_config.validate(&mut errors);
+        if !errors.is_empty() {
+            bail!(
+                "invalid [module.{}] config: {}",
+                Self::MODULE,
+                errors.join("; ")
+            );
+        }
+        Ok(module_config)
+    }
+}
+
+/// Checks one module's config section, adding any problems found
+pub type ConfigCheck = (&'static str, fn(&config::Value, &mut Vec<String>));
+
+pub fn config_check<T: ModuleConfig>() -> ConfigCheck {
+    (T::MODULE, check_section::<T>)
+}
+
+fn check_section<T: ModuleConfig>(section: &config::Value, errors: &mut Vec<String>) {
+    let mut unknown = Vec::new();
+    match serde_ignored::deserialize(section.clone(), |path| unknown.push(path.to_string())) {
+        Ok(module_config) => T::validate(&module_config, errors),
+        Err(e) => errors.push(e.to_string()),
+    }
+    errors.extend(unknown.into_iter().map(|key| format!("unknown key '{key}'")));
+}
+
+/// Check every `[module.*]` section which has a typed config, returning all the
+/// problems found rather than stopping at the first
+pub fn check_config(config: &Config, checks: &[ConfigCheck]) -> Vec<String> {
+    let modules = config.get_table("module").unwrap_or_default();
+    let mut errors = Vec::new();
+    for (module, check) in checks {
+        let Some(section) = modules.get(*module) else {
+            continue;
+        };
+        let mut module_errors = Vec::new();
+        check(section, &mut module_errors);
+        errors.extend(module_errors.into_iter().map(|e| format!("[module.{module}] {e}")));
+    }
+    errors
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SyncMode {
@@ -115,3 +188,73 @@ impl Display for BlockFlowMode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{File, FileFormat};
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case", default)]
+    struct TestConfig {
+        topic: String,
+        #[serde(deserialize_with = "deserialize_duration")]
+        interval: Duration,
+        enabled: bool,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                topic: "test.topic".to_string(),
+                interval: Duration::from_secs(60),
+                enabled: false,
+            }
+        }
+    }
+
+    impl ModuleConfig for TestConfig {
+        const MODULE: &'static str = "test";
+
+        fn validate(&self, errors: &mut Vec<String>) {
+            if self.enabled && self.topic.is_empty() {
+                errors.push("enabled needs a topic".to_string());
+            }
+        }
+    }
+
+    fn config(toml: &str) -> Config {
+        Config::builder().add_source(File::from_str(toml, FileFormat::Toml)).build().unwrap()
+    }
+
+    #[test]
+    fn empty_sections_use_defaults() {
+        let config = config("[module.test]\n[module.other]\nanything = 1\n");
+        assert!(check_config(&config, &[config_check::<TestConfig>()]).is_empty());
+
+        let loaded = TestConfig::load(&Config::default()).unwrap();
+        assert_eq!(loaded.interval, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn unknown_keys_and_conflicts_are_all_reported() {
+        let config = config(
+            "[module.test]\ntopic = \"\"\nenabled = true\ninterval = \"5m\"\ntopik = \"x\"\n",
+        );
+        assert_eq!(
+            check_config(&config, &[config_check::<TestConfig>()]),
+            vec![
+                "[module.test] enabled needs a topic".to_string(),
+                "[module.test] unknown key 'topik'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_durations_are_reported() {
+        let config = config("[module.test]\ninterval = \"soon\"\n");
+        let errors = check_config(&config, &[config_check::<TestConfig>()]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("bad duration 'soon'"), "{errors:?}");
+    }
+}
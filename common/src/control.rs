@@ -0,0 +1,250 @@
+//! Operator control of running modules
+//!
+//! Modules which opt in call [`on_control`] during init, and can then be asked
+//! over the control topic to pause and resume their consumption, dump their state
+//! or take a snapshot. Each command names the module it is for and the operator
+//! who sent it, and the module acks it with the outcome. Every command a module
+//! receives is logged, and appended to the audit file if one is configured.
+//!
+//! Control is off unless enabled under `[global.control]` - until then modules
+//! don't subscribe at all, and never pause.
+
+use std::{
+    collections::BTreeSet,
+    fs::OpenOptions,
+    future::Future,
+    io::Write,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::Context;
+use config::Config;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::{
+    configuration::{get_bool_flag, get_string_flag},
+    messages::{Command, CommandResponse, ControlAck, ControlAction, ControlCommand, Message},
+};
+
+/// Whether modules take control commands - set under `[global.control]`
+pub const DEFAULT_CONTROL_ENABLED: (&str, bool) = ("control.enabled", false);
+
+/// Topic control commands are published on
+pub const DEFAULT_CONTROL_TOPIC: (&str, &str) = ("control.topic", "cardano.control");
+
+/// Topic modules ack control commands on
+pub const DEFAULT_CONTROL_ACK_TOPIC: (&str, &str) = ("control.ack-topic", "cardano.control.ack");
+
+/// File each control command is appended to as a JSON line - none if empty
+pub const DEFAULT_CONTROL_AUDIT_FILE: (&str, &str) = ("control.audit-file", "");
+
+/// How long [`control`] waits for the module to ack
+pub const DEFAULT_CONTROL_TIMEOUT: (&str, &str) = ("control.timeout", "30s");
+
+/// Modules in this process which take control commands
+static PARTICIPANTS: LazyLock<Mutex<BTreeSet<String>>> =
+    LazyLock::new(|| Mutex::new(BTreeSet::new()));
+
+/// Modules in this process which take control commands
+pub fn participants() -> BTreeSet<String> {
+    PARTICIPANTS.lock().unwrap().clone()
+}
+
+/// Holds a module's consumption while it is paused
+#[derive(Clone)]
+pub struct PauseGate(watch::Receiver<bool>);
+
+impl PauseGate {
+    /// Gate which never closes, for modules without control
+    pub fn open() -> Self {
+        Self(watch::channel(false).1)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until the module isn't paused - call before reading each message
+    pub async fn wait(&mut self) {
+        // Only fails once the control task has gone, and then nothing can pause
+        let _ = self.0.wait_for(|paused| !paused).await;
+    }
+}
+
+/// Take control commands for `module`, if control is enabled. Pause and resume
+/// are handled here, through the returned gate; other actions are passed to
+/// `handle`, which returns a description of what it did.
+pub async fn on_control<F, Fut>(
+    context: Arc<Context<Message>>,
+    config: &Config,
+    module: &'static str,
+    handle: F,
+) -> Result<PauseGate>
+where
+    F: Fn(ControlAction) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<String>> + Send + 'static,
+{
+    if !get_bool_flag(config, DEFAULT_CONTROL_ENABLED) {
+        return Ok(PauseGate::open());
+    }
+
+    let topic = get_string_flag(config, DEFAULT_CONTROL_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_CONTROL_ACK_TOPIC);
+    let audit_file = get_string_flag(config, DEFAULT_CONTROL_AUDIT_FILE);
+    let mut subscription = context.subscribe(&topic).await?;
+    PARTICIPANTS.lock().unwrap().insert(module.to_string());
+
+    let (paused, gate) = watch::channel(false);
+    let run_context = context.clone();
+    context.run(async move {
+        loop {
+            let Ok((_, message)) = subscription.read().await else {
+                return;
+            };
+            let Message::Command(Command::Control(command)) = message.as_ref() else {
+                continue;
+            };
+            if command.module != module {
+                continue;
+            }
+
+            info!(
+                "{} asked {module} to {:?}",
+                command.operator, command.action
+            );
+            let result = match command.action {
+                ControlAction::Pause => {
+                    paused.send_replace(true);
+                    Ok("paused".to_string())
+                }
+                ControlAction::Resume => {
+                    paused.send_replace(false);
+                    Ok("resumed".to_string())
+                }
+                action => handle(action).await,
+            };
+
+            let ack = match result {
+                Ok(detail) => ControlAck {
+                    module: module.to_string(),
+                    action: command.action,
+                    detail: Some(detail),
+                    error: None,
+                },
+                Err(e) => {
+                    error!("{module} failed to {:?}: {e:#}", command.action);
+                    ControlAck {
+                        module: module.to_string(),
+                        action: command.action,
+                        detail: None,
+                        error: Some(format!("{e:#}")),
+                    }
+                }
+            };
+
+            if !audit_file.is_empty() {
+                if let Err(e) = audit(&audit_file, command, &ack) {
+                    warn!("Could not write control audit to {audit_file}: {e:#}");
+                }
+            }
+
+            let ack = Arc::new(Message::CommandResponse(CommandResponse::Control(ack)));
+            if let Err(e) = run_context.publish(&ack_topic, ack).await {
+                warn!("Could not publish {module} control ack: {e:#}");
+            }
+        }
+    });
+    Ok(PauseGate(gate))
+}
+
+/// Append a command and its outcome to the audit file
+fn audit(path: &str, command: &ControlCommand, ack: &ControlAck) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", audit_line(command, ack))?;
+    Ok(())
+}
+
+fn audit_line(command: &ControlCommand, ack: &ControlAck) -> serde_json::Value {
+    serde_json::json!({
+        "time": chrono::Utc::now().to_rfc3339(),
+        "operator": command.operator,
+        "module": command.module,
+        "action": command.action,
+        "detail": ack.detail,
+        "error": ack.error,
+    })
+}
+
+/// Send a control command and wait for the module to ack it
+pub async fn control(
+    context: &Arc<Context<Message>>,
+    config: &Config,
+    command: ControlCommand,
+) -> Result<ControlAck> {
+    let topic = get_string_flag(config, DEFAULT_CONTROL_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_CONTROL_ACK_TOPIC);
+    let timeout = get_string_flag(config, DEFAULT_CONTROL_TIMEOUT);
+    let timeout: Duration = humantime::parse_duration(&timeout)
+        .map_err(|e| anyhow!("bad {} '{timeout}': {e}", DEFAULT_CONTROL_TIMEOUT.0))?;
+
+    // Subscribe before asking, so the ack isn't missed
+    let mut subscription = context.subscribe(&ack_topic).await?;
+    let (module, action) = (command.module.clone(), command.action);
+    context
+        .publish(
+            &topic,
+            Arc::new(Message::Command(Command::Control(command))),
+        )
+        .await?;
+
+    let wait = async {
+        while let Ok((_, message)) = subscription.read().await {
+            if let Message::CommandResponse(CommandResponse::Control(ack)) = message.as_ref() {
+                if ack.module == module && ack.action == action {
+                    return Ok(ack.clone());
+                }
+            }
+        }
+        bail!("Lost the control acks waiting for {module}")
+    };
+    tokio::time::timeout(timeout, wait)
+        .await
+        .map_err(|_| anyhow!("Timed out after {timeout:?} waiting for {module} to {action:?}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_line_records_operator_and_outcome() {
+        let command = ControlCommand {
+            module: "spdd-state".to_string(),
+            action: ControlAction::RequestSnapshot,
+            operator: "alice".to_string(),
+        };
+        let ack = ControlAck {
+            module: "spdd-state".to_string(),
+            action: ControlAction::RequestSnapshot,
+            detail: None,
+            error: Some("no snapshots".to_string()),
+        };
+
+        let line = audit_line(&command, &ack);
+        assert_eq!(line["operator"], "alice");
+        assert_eq!(line["module"], "spdd-state");
+        assert_eq!(line["action"], "RequestSnapshot");
+        assert!(line["detail"].is_null());
+        assert_eq!(line["error"], "no snapshots");
+    }
+
+    #[tokio::test]
+    async fn open_gate_never_waits() {
+        let mut gate = PauseGate::open();
+        assert!(!gate.is_paused());
+        gate.wait().await;
+    }
+}
@@ -0,0 +1,275 @@
+//! Dead letters - messages a module failed to apply
+//!
+//! Rather than log and drop a message it can't apply, a module hands it to its
+//! [`DeadLetterQueue`] with the error. The queue appends it to
+//! `<directory>/<module>.jsonl`, one JSON line per message in the versioned message
+//! encoding, and counts it in the metrics. Once a fix ships, an operator sends the
+//! module a `ReplayDeadLetters` control command, and the module passes its letters
+//! back through [`DeadLetterQueue::replay`] - those which fail again stay queued.
+//!
+//! The directory is set under `[global.dead-letter]`. If it is empty, dead letters
+//! are still logged and counted, but not kept.
+
+use std::{
+    fs::{self, OpenOptions},
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use config::Config;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::{
+    configuration::get_string_flag,
+    messages::Message,
+    metrics::{self, Counter, Gauge},
+};
+
+/// Directory dead letters are kept in - not kept if empty
+pub const DEFAULT_DEAD_LETTER_DIRECTORY: (&str, &str) = ("dead-letter.directory", "dead-letters");
+
+/// A message a module failed to apply, and why
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    /// When it failed, RFC 3339
+    pub time: String,
+
+    pub module: String,
+
+    /// What the module was doing with it, e.g. the handler or reader name
+    pub source: String,
+
+    /// The error, with its context
+    pub error: String,
+
+    pub message: Message,
+}
+
+/// A module's dead letters
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    module: String,
+    path: Option<PathBuf>,
+
+    /// Held while the file is written, so replays and new letters don't interleave
+    lock: Arc<Mutex<()>>,
+    failed: Counter,
+    pending: Gauge,
+}
+
+impl DeadLetterQueue {
+    pub fn from_config(config: &Config, module: &str) -> Self {
+        let directory = get_string_flag(config, DEFAULT_DEAD_LETTER_DIRECTORY);
+        let path =
+            (!directory.is_empty()).then(|| Path::new(&directory).join(format!("{module}.jsonl")));
+        if let Some(path) = &path {
+            info!("Keeping {module} dead letters in {}", path.display());
+        }
+        Self::new(module, path)
+    }
+
+    fn new(module: &str, path: Option<PathBuf>) -> Self {
+        let failed = metrics::counter(
+            "acropolis_dead_letters_total",
+            "Messages a module failed to apply",
+            &[("module", module)],
+        );
+        let pending = metrics::gauge(
+            "acropolis_dead_letters_pending",
+            "Dead letters waiting to be replayed",
+            &[("module", module)],
+        );
+        let queue = Self {
+            module: module.to_string(),
+            path,
+            lock: Arc::new(Mutex::new(())),
+            failed,
+            pending,
+        };
+
+        // Letters left from an earlier run are still waiting
+        match queue.read() {
+            Ok(letters) => queue.pending.set(letters.len() as f64),
+            Err(e) => warn!("Could not read {module} dead letters: {e:#}"),
+        }
+        queue
+    }
+
+    /// Record a message the module failed to apply
+    pub async fn push(&self, source: &str, message: &Message, error: &anyhow::Error) {
+        error!(
+            "{} failed to apply message in {source}: {error:#}",
+            self.module
+        );
+        self.failed.inc();
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        let letter = DeadLetter {
+            time: chrono::Utc::now().to_rfc3339(),
+            module: self.module.clone(),
+            source: source.to_string(),
+            error: format!("{error:#}"),
+            message: message.clone(),
+        };
+
+        let _lock = self.lock.lock().await;
+        match append(path, &[letter]) {
+            Ok(()) => self.pending.set(self.pending.get() + 1.0),
+            Err(e) => error!("Could not keep {} dead letter: {e:#}", self.module),
+        }
+    }
+
+    /// Letters waiting to be replayed, oldest first
+    pub fn read(&self) -> Result<Vec<DeadLetter>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str::<DeadLetter>(line)?))
+            .collect()
+    }
+
+    /// Pass each letter back to `apply`, oldest first, keeping those which fail
+    /// again with their new error. Returns a summary for the operator.
+    pub async fn replay<F, Fut>(&self, mut apply: F) -> Result<String>
+    where
+        F: FnMut(Message) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let Some(path) = &self.path else {
+            return Ok(format!("{} keeps no dead letters", self.module));
+        };
+
+        let _lock = self.lock.lock().await;
+        let letters = self.read()?;
+        let total = letters.len();
+        let mut failed = Vec::new();
+        for mut letter in letters {
+            if let Err(e) = apply(letter.message.clone()).await {
+                letter.time = chrono::Utc::now().to_rfc3339();
+                letter.error = format!("{e:#}");
+                failed.push(letter);
+            }
+        }
+
+        // Written aside first, so a crash can't lose the letters
+        let replacement = path.with_extension("jsonl.new");
+        let _ = fs::remove_file(&replacement);
+        append(&replacement, &failed)?;
+        fs::rename(&replacement, path)?;
+        self.pending.set(failed.len() as f64);
+
+        let summary = format!(
+            "replayed {} of {total} dead letters, {} still failing",
+            total - failed.len(),
+            failed.len()
+        );
+        info!("{} {summary}", self.module);
+        Ok(summary)
+    }
+}
+
+fn append(path: &Path, letters: &[DeadLetter]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for letter in letters {
+        writeln!(file, "{}", serde_json::to_string(letter)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, bail};
+
+    /// Each test has its own module name, so its metrics are its own
+    fn queue(dir: &tempfile::TempDir, module: &str) -> DeadLetterQueue {
+        DeadLetterQueue::new(module, Some(dir.path().join(format!("{module}.jsonl"))))
+    }
+
+    #[tokio::test]
+    async fn letters_persist_across_queues() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let first = queue(&dir, "persist-state");
+        first
+            .push(
+                "handler",
+                &Message::String("one".to_string()),
+                &anyhow!("bad"),
+            )
+            .await;
+        first
+            .push(
+                "handler",
+                &Message::String("two".to_string()),
+                &anyhow!("worse"),
+            )
+            .await;
+        assert_eq!(first.pending.get(), 2.0);
+
+        let second = queue(&dir, "persist-state");
+        let letters = second.read()?;
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].source, "handler");
+        assert_eq!(letters[1].error, "worse");
+        assert!(matches!(&letters[0].message, Message::String(s) if s == "one"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replay_keeps_letters_which_fail_again() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let queue = queue(&dir, "replay-state");
+        for text in ["fixed", "still broken"] {
+            queue
+                .push(
+                    "handler",
+                    &Message::String(text.to_string()),
+                    &anyhow!("bad"),
+                )
+                .await;
+        }
+
+        let summary = queue
+            .replay(|message| async move {
+                match message {
+                    Message::String(s) if s == "fixed" => Ok(()),
+                    _ => bail!("still bad"),
+                }
+            })
+            .await?;
+        assert_eq!(summary, "replayed 1 of 2 dead letters, 1 still failing");
+
+        let letters = queue.read()?;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].error, "still bad");
+        assert_eq!(queue.pending.get(), 1.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn letters_are_not_kept_without_a_directory() -> Result<()> {
+        let queue = DeadLetterQueue::new("unkept-state", None);
+        queue.push("handler", &Message::None, &anyhow!("bad")).await;
+        assert!(queue.read()?.is_empty());
+        assert_eq!(
+            queue.replay(|_| async { Ok(()) }).await?,
+            "unkept-state keeps no dead letters"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Barrier at each epoch boundary, so that the work done at the end of an epoch
+//! (rewards, stake distribution snapshots, parameter changes) is known to be
+//! finished everywhere before the next epoch's block deltas are applied.
+//!
+//! Each state module taking part acks the epoch once it has processed the first
+//! block of the next one. epochs_state collects the acks and announces when the
+//! epoch is complete, and tx_unpacker holds back the deltas of the following
+//! blocks until then. Without the barrier, the modules rely on the order in
+//! which messages happen to be delivered.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use caryatid_sdk::Context;
+use config::Config;
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    configuration::{get_bool_flag, get_string_flag},
+    messages::{CardanoMessage, EpochBarrierAckMessage, Message},
+    BlockInfo,
+};
+
+/// Whether the barrier is used - set under `[global.epoch-barrier]`
+const DEFAULT_EPOCH_BARRIER_ENABLED: (&str, bool) = ("epoch-barrier.enabled", false);
+
+/// Topic modules ack an epoch on
+pub const DEFAULT_EPOCH_BARRIER_ACK_TOPIC: (&str, &str) =
+    ("epoch-barrier.ack-topic", "cardano.epoch.barrier.ack");
+
+/// Topic the completion of an epoch is announced on
+pub const DEFAULT_EPOCH_BARRIER_COMPLETE_TOPIC: (&str, &str) = (
+    "epoch-barrier.complete-topic",
+    "cardano.epoch.barrier.complete",
+);
+
+/// How long to hold back block deltas before giving up on the barrier
+const DEFAULT_EPOCH_BARRIER_TIMEOUT: (&str, &str) = ("epoch-barrier.timeout", "5m");
+
+/// Config key for the modules which must ack each epoch
+const EPOCH_BARRIER_PARTICIPANTS_KEY: &str = "epoch-barrier.participants";
+
+/// Modules which must ack each epoch, unless configured otherwise
+const DEFAULT_EPOCH_BARRIER_PARTICIPANTS: [&str; 6] = [
+    "utxo-state",
+    "accounts-state",
+    "spo-state",
+    "drep-state",
+    "parameters-state",
+    "epochs-state",
+];
+
+/// Whether the barrier is enabled
+pub fn epoch_barrier_enabled(config: &Config) -> bool {
+    get_bool_flag(config, DEFAULT_EPOCH_BARRIER_ENABLED)
+}
+
+/// Modules which must ack each epoch
+pub fn epoch_barrier_participants(config: &Config) -> Vec<String> {
+    config.get::<Vec<String>>(EPOCH_BARRIER_PARTICIPANTS_KEY).unwrap_or_else(|_| {
+        DEFAULT_EPOCH_BARRIER_PARTICIPANTS.iter().map(|m| m.to_string()).collect()
+    })
+}
+
+/// Acks the end of each epoch on behalf of a module
+pub struct EpochBarrierAcker {
+    context: Arc<Context<Message>>,
+    topic: String,
+    module: &'static str,
+}
+
+impl EpochBarrierAcker {
+    /// Create an acker if the barrier is enabled
+    pub fn from_config(
+        context: Arc<Context<Message>>,
+        config: &Config,
+        module: &'static str,
+    ) -> Option<Self> {
+        if !epoch_barrier_enabled(config) {
+            return None;
+        }
+
+        let topic = get_string_flag(config, DEFAULT_EPOCH_BARRIER_ACK_TOPIC);
+        info!("Publishing {module} epoch barrier acks on '{topic}'");
+        Some(Self {
+            context,
+            topic,
+            module,
+        })
+    }
+
+    /// Ack the epoch before `block`, which must be the first block of a new epoch
+    /// and already fully processed
+    pub async fn ack(&self, block: &BlockInfo) {
+        let epoch = block.epoch.saturating_sub(1);
+        debug!(module = self.module, epoch, "Acking epoch barrier");
+        let message = Arc::new(Message::Cardano((
+            block.clone(),
+            CardanoMessage::EpochBarrierAck(EpochBarrierAckMessage {
+                module: self.module.to_string(),
+                epoch,
+            }),
+        )));
+        if let Err(e) = self.context.publish(&self.topic, message).await {
+            warn!("Could not publish {} epoch barrier ack: {e:#}", self.module);
+        }
+    }
+}
+
+/// Collects acks and works out when each epoch is complete
+#[derive(Debug, Clone)]
+pub struct EpochBarrierTracker {
+    participants: BTreeSet<String>,
+
+    /// Modules which have acked each epoch not yet complete
+    acks: BTreeMap<u64, BTreeSet<String>>,
+
+    /// Last epoch to complete
+    completed: Option<u64>,
+}
+
+impl EpochBarrierTracker {
+    pub fn new(participants: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            participants: participants.into_iter().collect(),
+            acks: BTreeMap::new(),
+            completed: None,
+        }
+    }
+
+    /// Record an ack, returning the epoch if it is now complete
+    pub fn ack(&mut self, module: &str, epoch: u64) -> Option<u64> {
+        if !self.participants.contains(module) {
+            debug!(
+                module,
+                epoch, "Ignoring epoch barrier ack from non-participant"
+            );
+            return None;
+        }
+
+        // Replayed after a rollback, or after we gave up waiting
+        if self.completed.is_some_and(|completed| epoch <= completed) {
+            return None;
+        }
+
+        let acked = self.acks.entry(epoch).or_default();
+        acked.insert(module.to_string());
+        if *acked != self.participants {
+            return None;
+        }
+
+        self.acks = self.acks.split_off(&(epoch + 1));
+        self.completed = Some(epoch);
+        Some(epoch)
+    }
+
+    /// Participants which haven't acked an epoch yet
+    pub fn missing(&self, epoch: u64) -> Vec<&str> {
+        let acked = self.acks.get(&epoch);
+        self.participants
+            .iter()
+            .filter(|module| !acked.is_some_and(|acked| acked.contains(*module)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Holds back block processing until the previous epoch is complete
+pub struct EpochBarrierGate {
+    /// Last epoch announced as complete
+    completed: watch::Receiver<Option<u64>>,
+
+    /// Epoch which must complete before the next block is let through
+    pending: Option<u64>,
+
+    timeout: Duration,
+}
+
+impl EpochBarrierGate {
+    /// Create a gate if the barrier is enabled
+    pub async fn from_config(
+        context: Arc<Context<Message>>,
+        config: &Config,
+    ) -> Result<Option<Self>> {
+        if !epoch_barrier_enabled(config) {
+            return Ok(None);
+        }
+
+        let timeout = get_string_flag(config, DEFAULT_EPOCH_BARRIER_TIMEOUT);
+        let timeout = humantime::parse_duration(&timeout)
+            .map_err(|e| anyhow!("bad {} '{timeout}': {e}", DEFAULT_EPOCH_BARRIER_TIMEOUT.0))?;
+
+        let topic = get_string_flag(config, DEFAULT_EPOCH_BARRIER_COMPLETE_TOPIC);
+        info!("Creating epoch barrier subscriber on '{topic}'");
+        let mut subscription = context.subscribe(&topic).await?;
+
+        let (sender, completed) = watch::channel(None);
+        context.run(async move {
+            loop {
+                let Ok((_, message)) = subscription.read().await else {
+                    error!("Failed to read epoch barrier completion");
+                    continue;
+                };
+                if let Message::Cardano((_, CardanoMessage::EpochBarrierComplete(complete))) =
+                    message.as_ref()
+                {
+                    sender.send_if_modified(|completed| {
+                        if completed.is_some_and(|completed| completed >= complete.epoch) {
+                            return false;
+                        }
+                        *completed = Some(complete.epoch);
+                        true
+                    });
+                }
+            }
+        });
+
+        Ok(Some(Self {
+            completed,
+            pending: None,
+            timeout,
+        }))
+    }
+
+    /// Wait until `block` can be processed. The first block of a new epoch goes
+    /// straight through, since the modules need it to finish the old epoch; the
+    /// one after it waits for the old epoch to complete.
+    pub async fn admit(&mut self, block: &BlockInfo) {
+        if let Some(epoch) = self.pending.take() {
+            let wait = self.completed.wait_for(|completed| completed.is_some_and(|c| c >= epoch));
+            match tokio::time::timeout(self.timeout, wait).await {
+                Ok(Ok(_)) => debug!(epoch, "Epoch barrier complete"),
+                Ok(Err(_)) => warn!(epoch, "Epoch barrier closed, carrying on"),
+                Err(_) => warn!(
+                    epoch,
+                    "Timed out after {:?} waiting for epoch barrier, carrying on", self.timeout
+                ),
+            }
+        }
+
+        if block.new_epoch && block.epoch > 0 {
+            self.pending = Some(block.epoch - 1);
+        }
+    }
+
+    /// Forget any pending epoch - after a rollback the boundary may be replayed,
+    /// or may not be reached again
+    pub fn rollback(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> EpochBarrierTracker {
+        EpochBarrierTracker::new(["a".to_string(), "b".to_string()])
+    }
+
+    #[test]
+    fn epoch_completes_when_all_participants_ack() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.ack("a", 5), None);
+        assert_eq!(tracker.missing(5), vec!["b"]);
+        assert_eq!(tracker.ack("a", 5), None);
+        assert_eq!(tracker.ack("b", 5), Some(5));
+        assert_eq!(tracker.missing(6), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn acks_from_others_and_for_completed_epochs_are_ignored() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.ack("c", 5), None);
+        assert_eq!(tracker.ack("a", 5), None);
+        assert_eq!(tracker.ack("b", 5), Some(5));
+
+        // Replayed after a rollback
+        assert_eq!(tracker.ack("a", 5), None);
+        assert_eq!(tracker.ack("b", 5), None);
+    }
+
+    #[test]
+    fn later_epoch_acks_are_kept_until_complete() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.ack("a", 5), None);
+        assert_eq!(tracker.ack("a", 6), None);
+        assert_eq!(tracker.ack("b", 5), Some(5));
+        assert_eq!(tracker.ack("b", 6), Some(6));
+    }
+}
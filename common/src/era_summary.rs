@@ -28,6 +28,8 @@ pub struct EraParams {
     #[serde_as(as = "serde_with::DurationSeconds<u64>")]
     #[serde(rename = "slot_length_secs")]
     pub slot_length: Duration,
+    /// Slots before the end of the known chain within which the era cannot end.
+    pub safe_zone: u64,
 }
 
 /// Metadata for a single era, including its boundaries and parameters.
@@ -1,11 +1,139 @@
 use crate::{
     calculations::{
         epoch_to_first_slot_with_shelley_params, slot_to_epoch_with_shelley_params,
-        slot_to_timestamp_with_params,
+        slot_to_timestamp_with_params, timestamp_to_slot_with_params,
     },
     hash::Hash,
+    rational_number::RationalNumber,
     GenesisDelegates, MagicNumber, NetworkId, Pots,
 };
+use anyhow::{bail, Result};
+use config::Config;
+use std::fmt::{Display, Formatter};
+
+pub const CONFIG_KEY_NETWORK: &str = "startup.network";
+pub const CONFIG_KEY_NETWORK_NAME: &str = "startup.network-name";
+
+/// Mithril genesis verification key shared by the release-preprod and
+/// pre-release-preview aggregators
+const MITHRIL_TESTNET_GENESIS_KEY: &str = "5b3132372c37332c3132342c3136312c362c3133372c3133312c3231332c3230372c3131372c3139382c38352c3137362c3139392c3136322c3234312c36382c3132332c3131392c3134352c31332c3233322c3234332c34392c3232392c322c3234392c3230352c3230352c33392c3233352c34345d";
+
+/// Well-known network profile, selected by `startup.network` (or the older
+/// `startup.network-name`).  Any other network must be named `custom`
+/// explicitly and supply its own genesis files, relays and aggregator - an
+/// unrecognised name is an error rather than a custom network, so a typo can't
+/// start a node with the wrong genesis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Preprod,
+    Preview,
+    SanchoNet,
+    Custom,
+}
+
+impl Network {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config
+            .get_string(CONFIG_KEY_NETWORK)
+            .or_else(|_| config.get_string(CONFIG_KEY_NETWORK_NAME))
+        {
+            Ok(name) => Self::from_name(&name),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "mainnet" => Network::Mainnet,
+            "preprod" => Network::Preprod,
+            "preview" => Network::Preview,
+            "sanchonet" | "sancho" => Network::SanchoNet,
+            "custom" => Network::Custom,
+            _ => bail!(
+                "Unknown network '{name}' - expected mainnet, preprod, preview, sanchonet or custom"
+            ),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Preprod => "preprod",
+            Network::Preview => "preview",
+            Network::SanchoNet => "sanchonet",
+            Network::Custom => "custom",
+        }
+    }
+
+    /// Network magic, if this is a well-known network
+    pub fn magic_number(&self) -> Option<MagicNumber> {
+        match self {
+            Network::Mainnet => Some(MagicNumber(764824073)),
+            Network::Preprod => Some(MagicNumber(1)),
+            Network::Preview => Some(MagicNumber(2)),
+            Network::SanchoNet => Some(MagicNumber(4)),
+            Network::Custom => None,
+        }
+    }
+
+    /// Network ID used for address encoding - only mainnet is mainnet
+    pub fn network_id(&self) -> NetworkId {
+        match self {
+            Network::Mainnet => NetworkId::Mainnet,
+            _ => NetworkId::Testnet,
+        }
+    }
+
+    /// Default upstream relays to connect to
+    pub fn default_relays(&self) -> &'static [&'static str] {
+        match self {
+            Network::Mainnet => &[
+                "backbone.cardano.iog.io:3001",
+                "backbone.mainnet.cardanofoundation.org:3001",
+                "backbone.mainnet.emurgornd.com:3001",
+            ],
+            Network::Preprod => &["preprod-node.play.dev.cardano.org:3001"],
+            Network::Preview => &["preview-node.play.dev.cardano.org:3001"],
+            Network::SanchoNet => &["sanchonet-node.play.dev.cardano.org:3001"],
+            Network::Custom => &[],
+        }
+    }
+
+    /// Default Mithril aggregator URL
+    pub fn mithril_aggregator_url(&self) -> Option<&'static str> {
+        match self {
+            Network::Mainnet => {
+                Some("https://aggregator.release-mainnet.api.mithril.network/aggregator")
+            }
+            Network::Preprod => {
+                Some("https://aggregator.release-preprod.api.mithril.network/aggregator")
+            }
+            Network::Preview => {
+                Some("https://aggregator.pre-release-preview.api.mithril.network/aggregator")
+            }
+            Network::SanchoNet | Network::Custom => None,
+        }
+    }
+
+    /// Default Mithril genesis verification key, matching the aggregator
+    pub fn mithril_genesis_key(&self) -> Option<&'static str> {
+        match self {
+            Network::Mainnet => Some("5b3139312c36362c3134302c3138352c3133382c31312c3233372c3230372c3235302c3134342c32372c322c3138382c33302c31322c38312c3135352c3230342c31302c3137392c37352c32332c3133382c3139362c3231372c352c31342c32302c35372c37392c33392c3137365d"),
+            Network::Preprod | Network::Preview => Some(MITHRIL_TESTNET_GENESIS_KEY),
+            Network::SanchoNet | Network::Custom => None,
+        }
+    }
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GenesisValues {
@@ -17,6 +145,30 @@ pub struct GenesisValues {
     pub magic_number: MagicNumber,
     pub security_param: u64,
     pub initial_pots: Pots,
+
+    /// Slots in each KES period. Fixed by the Shelley genesis, not a protocol parameter.
+    #[serde(default = "default_slots_per_kes_period")]
+    pub slots_per_kes_period: u64,
+
+    /// KES periods an operational certificate is valid for, from the Shelley genesis
+    #[serde(default = "default_max_kes_evolutions")]
+    pub max_kes_evolutions: u64,
+
+    /// Fraction of slots expected to hold a block (`f`), from the Shelley genesis
+    #[serde(default = "default_active_slots_coeff")]
+    pub active_slots_coeff: RationalNumber,
+}
+
+fn default_slots_per_kes_period() -> u64 {
+    129600
+}
+
+fn default_max_kes_evolutions() -> u64 {
+    62
+}
+
+fn default_active_slots_coeff() -> RationalNumber {
+    RationalNumber::new(1, 20)
 }
 
 impl GenesisValues {
@@ -33,8 +185,95 @@ impl GenesisValues {
     pub fn slot_to_timestamp(&self, slot: u64) -> u64 {
         slot_to_timestamp_with_params(slot, self.byron_timestamp, self.shelley_epoch)
     }
+    pub fn timestamp_to_slot(&self, timestamp: u64) -> u64 {
+        timestamp_to_slot_with_params(timestamp, self.byron_timestamp, self.shelley_epoch)
+    }
 
     pub fn epoch_to_first_slot(&self, epoch: u64) -> u64 {
         epoch_to_first_slot_with_shelley_params(epoch, self.shelley_epoch, self.shelley_epoch_len)
     }
+
+    /// Stability window (3k/f slots) from the genesis values, for use before the
+    /// Shelley protocol parameters are known
+    pub fn stability_window(&self) -> u64 {
+        self.security_param * self.active_slots_coeff.denom() / self.active_slots_coeff.numer() * 3
+    }
+
+    /// Randomness stabilisation window (4k/f slots) from the genesis values
+    pub fn randomness_stabilization_window(&self) -> u64 {
+        self.security_param * self.active_slots_coeff.denom() / self.active_slots_coeff.numer() * 4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_from_name() {
+        assert_eq!(Network::from_name("mainnet").unwrap(), Network::Mainnet);
+        assert_eq!(Network::from_name("Preprod").unwrap(), Network::Preprod);
+        assert_eq!(Network::from_name("preview").unwrap(), Network::Preview);
+        assert_eq!(Network::from_name("sancho").unwrap(), Network::SanchoNet);
+        assert_eq!(Network::from_name("custom").unwrap(), Network::Custom);
+    }
+
+    #[test]
+    fn unknown_network_name_is_an_error() {
+        assert!(Network::from_name("mainet").is_err());
+        assert!(Network::from_name("my-devnet").is_err());
+
+        let config =
+            Config::builder().set_override(CONFIG_KEY_NETWORK, "mainet").unwrap().build().unwrap();
+        assert!(Network::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn network_from_config_prefers_network_key() {
+        let config = Config::builder()
+            .set_override(CONFIG_KEY_NETWORK, "preprod")
+            .unwrap()
+            .set_override(CONFIG_KEY_NETWORK_NAME, "preview")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(Network::from_config(&config).unwrap(), Network::Preprod);
+    }
+
+    #[test]
+    fn network_from_config_falls_back_to_network_name() {
+        let config = Config::builder()
+            .set_override(CONFIG_KEY_NETWORK_NAME, "preview")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(Network::from_config(&config).unwrap(), Network::Preview);
+    }
+
+    #[test]
+    fn network_defaults_to_mainnet() {
+        let config = Config::builder().build().unwrap();
+        assert_eq!(Network::from_config(&config).unwrap(), Network::Mainnet);
+    }
+
+    #[test]
+    fn network_magic_matches_network_name() {
+        for network in [
+            Network::Mainnet,
+            Network::Preprod,
+            Network::Preview,
+            Network::SanchoNet,
+        ] {
+            let magic = network.magic_number().unwrap();
+            assert_eq!(magic.to_network_name(), network.name());
+        }
+        assert_eq!(Network::Custom.magic_number(), None);
+    }
+
+    #[test]
+    fn only_mainnet_uses_mainnet_addresses() {
+        assert_eq!(Network::Mainnet.network_id(), NetworkId::Mainnet);
+        assert_eq!(Network::Preprod.network_id(), NetworkId::Testnet);
+        assert_eq!(Network::Custom.network_id(), NetworkId::Testnet);
+    }
 }
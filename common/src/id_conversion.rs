@@ -0,0 +1,176 @@
+//! Conversions between the text forms of pool, DRep and stake identifiers
+//!
+//! Each conversion takes an identifier in any of its usual forms and gives back all
+//! of them, so API consumers can check which one an endpoint expects:
+//!
+//! - pool IDs: `pool1...` Bech32 or the hex key hash
+//! - DRep IDs: CIP-105 `drep1...` / `drep_script1...`, CIP-129 `drep1...` with its
+//!   header byte, or a `keyHash-...` / `scriptHash-...` credential
+//! - stake addresses: `stake1...` / `stake_test1...`, or a `keyHash-...` /
+//!   `scriptHash-...` stake credential
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    address::StakeAddress,
+    cip19::CredentialInspection,
+    serialization::{Bech32Conversion, Bech32WithHrp},
+    Credential, KeyHash, NetworkId, PoolId,
+};
+
+/// CIP-129 header bytes for DRep key and script credentials
+const CIP129_DREP_KEY_HEADER: u8 = 0x22;
+const CIP129_DREP_SCRIPT_HEADER: u8 = 0x23;
+
+/// A pool ID in both its forms
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PoolIdConversion {
+    pub pool_id: String,
+    pub hex: String,
+}
+
+/// A DRep ID in each of its forms
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DRepIdConversion {
+    /// CIP-105 form, as our endpoints give it
+    pub drep_id: String,
+    /// CIP-129 form, with the credential kind in a header byte
+    pub cip129_drep_id: String,
+    pub credential: CredentialInspection,
+}
+
+/// A stake address and the credential it is for
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StakeAddressConversion {
+    pub stake_address: String,
+    pub network: NetworkId,
+    pub credential: CredentialInspection,
+}
+
+/// Is this a `keyHash-`/`scriptHash-` credential rather than Bech32?
+fn is_credential(id: &str) -> bool {
+    id.starts_with("keyHash-") || id.starts_with("scriptHash-")
+}
+
+/// Convert a pool ID given as Bech32 or hex
+pub fn convert_pool_id(id: &str) -> Result<PoolIdConversion> {
+    let pool_id = if id.starts_with("pool1") {
+        PoolId::from_bech32(id)?
+    } else {
+        id.parse::<PoolId>().map_err(|e| anyhow!("Not a pool1 or hex pool ID: {e}"))?
+    };
+
+    Ok(PoolIdConversion {
+        pool_id: pool_id.to_bech32()?,
+        hex: pool_id.to_string(),
+    })
+}
+
+/// Convert a DRep ID given in CIP-105 or CIP-129 Bech32, or as a credential
+pub fn convert_drep_id(id: &str) -> Result<DRepIdConversion> {
+    let credential = if is_credential(id) {
+        Credential::from_json_string(id)?
+    } else {
+        let (hrp, data) = bech32::decode(id).map_err(|e| anyhow!("Invalid Bech32 DRep ID: {e}"))?;
+        if hrp.as_str() == "drep" && data.len() == 29 {
+            let hash = KeyHash::try_from(&data[1..])?;
+            match data[0] {
+                CIP129_DREP_KEY_HEADER => Credential::AddrKeyHash(hash),
+                CIP129_DREP_SCRIPT_HEADER => Credential::ScriptHash(hash),
+                header => bail!("Invalid CIP-129 DRep header byte {header:#04x}"),
+            }
+        } else {
+            Credential::from_drep_bech32(id)?
+        }
+    };
+
+    let header = match credential {
+        Credential::AddrKeyHash(_) => CIP129_DREP_KEY_HEADER,
+        Credential::ScriptHash(_) => CIP129_DREP_SCRIPT_HEADER,
+    };
+    let mut cip129 = vec![header];
+    cip129.extend_from_slice(credential.get_hash().as_ref());
+
+    Ok(DRepIdConversion {
+        drep_id: credential.to_drep_bech32()?,
+        cip129_drep_id: cip129.to_bech32_with_hrp("drep")?,
+        credential: CredentialInspection::from(&credential),
+    })
+}
+
+/// Convert a stake address, or a stake credential on `network` - which is ignored
+/// for addresses, since they carry their own
+pub fn convert_stake_address(id: &str, network: NetworkId) -> Result<StakeAddressConversion> {
+    let address = if is_credential(id) {
+        StakeAddress::new(Credential::from_json_string(id)?, network)
+    } else {
+        StakeAddress::from_string(id)?
+    };
+
+    Ok(StakeAddressConversion {
+        stake_address: address.to_string()?,
+        network: address.network.clone(),
+        credential: CredentialInspection::from(&address.credential),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cip19::CredentialKind;
+
+    const DREP_KEY_HASH: &str = "00000000000000000000000000000000000000000000000000000000";
+    const CIP105_DREP: &str = "drep1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqua9udh";
+    const CIP129_DREP: &str = "drep1ygqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq7vlc9n";
+
+    #[test]
+    fn pool_id_round_trips() {
+        let hex = "0f292fcaa02b8b2f9b3c8f9fd8e0bb21abedb692a6d5058df3ef2735";
+        let from_hex = convert_pool_id(hex).unwrap();
+        assert_eq!(
+            from_hex.pool_id,
+            "pool1pu5jlj4q9w9jlxeu370a3c9myx47md5j5m2str0naunn2q3lkdy"
+        );
+        assert_eq!(from_hex.hex, hex);
+        assert_eq!(convert_pool_id(&from_hex.pool_id).unwrap(), from_hex);
+
+        assert!(convert_pool_id("pool1notbech32").is_err());
+        assert!(convert_pool_id("0f29").is_err());
+    }
+
+    #[test]
+    fn drep_id_forms_agree() {
+        let credential = format!("keyHash-{DREP_KEY_HASH}");
+        let conversion = convert_drep_id(&credential).unwrap();
+        assert_eq!(conversion.drep_id, CIP105_DREP);
+        assert_eq!(conversion.cip129_drep_id, CIP129_DREP);
+        assert_eq!(conversion.credential.kind, CredentialKind::KeyHash);
+
+        assert_eq!(convert_drep_id(CIP105_DREP).unwrap(), conversion);
+        assert_eq!(convert_drep_id(CIP129_DREP).unwrap(), conversion);
+
+        let script = convert_drep_id(&format!("scriptHash-{DREP_KEY_HASH}")).unwrap();
+        assert!(script.drep_id.starts_with("drep_script1"));
+        assert_eq!(convert_drep_id(&script.cip129_drep_id).unwrap(), script);
+    }
+
+    #[test]
+    fn stake_address_from_credential_uses_network() {
+        let hash = "337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251";
+        let conversion =
+            convert_stake_address(&format!("keyHash-{hash}"), NetworkId::Mainnet).unwrap();
+        assert_eq!(
+            conversion.stake_address,
+            "stake1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw"
+        );
+        assert_eq!(conversion.credential.hash.to_string(), hash);
+
+        // The address's own network wins
+        let back = convert_stake_address(&conversion.stake_address, NetworkId::Testnet).unwrap();
+        assert_eq!(back, conversion);
+
+        let testnet =
+            convert_stake_address(&format!("keyHash-{hash}"), NetworkId::Testnet).unwrap();
+        assert!(testnet.stake_address.starts_with("stake_test1"));
+    }
+}
@@ -0,0 +1,641 @@
+//! Conversions between our ledger state types and the canonical ledger state
+//! types generated from `ledger-state/cddl`, so state can be imported from and
+//! exported to the canonical CBOR form.
+//!
+//! The canonical types can't yet hold reference scripts, and converting an
+//! output with one fails rather than dropping it.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use anyhow::{anyhow, bail, Context, Error, Result};
+use canonical_ledger_state::{common as c_common, spos as c_spos, utxos as c_utxos};
+
+use super::{Deposit, SPOState, UTxOState};
+use crate::{
+    hash::Hash, Address, AssetName, Credential, Datum, GovActionId, MultiHostName, NativeAsset,
+    PoolId, PoolMetadata, PoolRegistration, Ratio, Relay, SingleHostAddr, SingleHostName,
+    StakeAddress, UTXOValue, UTxOIdentifier, Value,
+};
+
+fn from_hash28(hash: c_common::Hash28) -> Result<Hash<28>> {
+    Vec::from(hash)
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow!("bad 28-byte hash {}", hex::encode(bytes)))
+}
+
+fn from_hash32(hash: c_common::Hash32) -> Result<Hash<32>> {
+    Vec::from(hash)
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow!("bad 32-byte hash {}", hex::encode(bytes)))
+}
+
+fn to_hash28(hash: &Hash<28>) -> Result<c_common::Hash28> {
+    Ok(c_common::Hash28::new(hash.to_vec())?)
+}
+
+fn to_hash32(hash: &Hash<32>) -> Result<c_common::Hash32> {
+    Ok(c_common::Hash32::new(hash.to_vec())?)
+}
+
+// -- Common --
+
+impl TryFrom<c_common::Credential> for Credential {
+    type Error = Error;
+
+    fn try_from(credential: c_common::Credential) -> Result<Self> {
+        Ok(match credential {
+            c_common::Credential::AddrKeyhashCredential(c) => {
+                Credential::AddrKeyHash(from_hash28(c.addr_keyhash)?)
+            }
+            c_common::Credential::ScriptHashCredential(c) => {
+                Credential::ScriptHash(from_hash28(c.script_hash)?)
+            }
+        })
+    }
+}
+
+impl TryFrom<Credential> for c_common::Credential {
+    type Error = Error;
+
+    fn try_from(credential: Credential) -> Result<Self> {
+        Ok(match credential {
+            Credential::AddrKeyHash(hash) => {
+                c_common::Credential::new_addr_keyhash_credential(to_hash28(&hash)?)
+            }
+            Credential::ScriptHash(hash) => {
+                c_common::Credential::new_script_hash_credential(to_hash28(&hash)?)
+            }
+        })
+    }
+}
+
+impl TryFrom<c_common::GovActionId> for GovActionId {
+    type Error = Error;
+
+    fn try_from(id: c_common::GovActionId) -> Result<Self> {
+        Ok(GovActionId {
+            transaction_id: from_hash32(id.hash_32)?,
+            action_index: id.uint.try_into().context("governance action index")?,
+        })
+    }
+}
+
+impl TryFrom<GovActionId> for c_common::GovActionId {
+    type Error = Error;
+
+    fn try_from(id: GovActionId) -> Result<Self> {
+        Ok(c_common::GovActionId::new(
+            c_common::Hash32::new(id.transaction_id.to_vec())?,
+            id.action_index.into(),
+        ))
+    }
+}
+
+// -- UTxO state --
+
+impl TryFrom<c_utxos::TxIn> for UTxOIdentifier {
+    type Error = Error;
+
+    fn try_from(tx_in: c_utxos::TxIn) -> Result<Self> {
+        Ok(UTxOIdentifier::new(from_hash32(tx_in.hash_32)?, tx_in.uint))
+    }
+}
+
+impl TryFrom<UTxOIdentifier> for c_utxos::TxIn {
+    type Error = Error;
+
+    fn try_from(utxo: UTxOIdentifier) -> Result<Self> {
+        Ok(c_utxos::TxIn::new(
+            c_common::Hash32::new(utxo.tx_hash.to_vec())?,
+            utxo.output_index,
+        ))
+    }
+}
+
+impl TryFrom<c_utxos::Value> for Value {
+    type Error = Error;
+
+    fn try_from(value: c_utxos::Value) -> Result<Self> {
+        Ok(match value {
+            c_utxos::Value::Coin(lovelace) => Value::new(lovelace, Vec::new()),
+            c_utxos::Value::AssetValue(value) => Value::new(
+                value.lovelace,
+                value
+                    .multi_asset
+                    .into_iter()
+                    .map(|(policy, bundle)| {
+                        let assets = bundle
+                            .into_iter()
+                            .map(|(name, amount)| {
+                                let name = Vec::from(name);
+                                Ok(NativeAsset {
+                                    name: AssetName::new(&name).ok_or_else(|| {
+                                        anyhow!("asset name {} too long", hex::encode(&name))
+                                    })?,
+                                    amount,
+                                })
+                            })
+                            .collect::<Result<_>>()?;
+                        Ok((from_hash28(policy)?, assets))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl TryFrom<Value> for c_utxos::Value {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        if value.assets.is_empty() {
+            return Ok(c_utxos::Value::new_coin(value.lovelace));
+        }
+
+        let mut multi_asset = c_utxos::Multiasset::new();
+        for (policy, assets) in &value.assets {
+            let bundle = multi_asset.entry(to_hash28(policy)?).or_default();
+            for asset in assets {
+                let name = c_utxos::AssetName::new(asset.name.as_slice().to_vec())?;
+                *bundle.entry(name).or_default() += asset.amount;
+            }
+        }
+        Ok(c_utxos::Value::new_asset_value(c_utxos::AssetValue::new(
+            value.lovelace,
+            multi_asset,
+        )))
+    }
+}
+
+impl TryFrom<c_utxos::DatumOption> for Datum {
+    type Error = Error;
+
+    fn try_from(datum: c_utxos::DatumOption) -> Result<Self> {
+        Ok(match datum {
+            c_utxos::DatumOption::DatumHashOption(d) => Datum::Hash(from_hash32(d.hash_32)?),
+            c_utxos::DatumOption::InlineDatumOption(d) => Datum::Inline(d.data_bytes),
+        })
+    }
+}
+
+impl TryFrom<Datum> for c_utxos::DatumOption {
+    type Error = Error;
+
+    fn try_from(datum: Datum) -> Result<Self> {
+        Ok(match datum {
+            Datum::Hash(hash) => c_utxos::DatumOption::new_datum_hash_option(to_hash32(&hash)?),
+            Datum::Inline(bytes) => c_utxos::DatumOption::new_inline_datum_option(bytes),
+        })
+    }
+}
+
+impl TryFrom<c_utxos::TxOut> for UTXOValue {
+    type Error = Error;
+
+    fn try_from(tx_out: c_utxos::TxOut) -> Result<Self> {
+        Ok(match tx_out {
+            c_utxos::TxOut::ShelleyTxOut(out) => UTXOValue {
+                address: Address::from_bytes_key(&out.address)?,
+                value: out.value.try_into()?,
+                datum: out.hash_32.map(from_hash32).transpose()?.map(Datum::Hash),
+                script_ref: None,
+            },
+            c_utxos::TxOut::BabbageTxOut(out) => {
+                if out.key_3.is_some() {
+                    bail!("canonical reference scripts don't carry the script");
+                }
+                UTXOValue {
+                    address: Address::from_bytes_key(&out.key_0)?,
+                    value: out.key_1.try_into()?,
+                    datum: out.key_2.map(Datum::try_from).transpose()?,
+                    script_ref: None,
+                }
+            }
+        })
+    }
+}
+
+/// Outputs take the Babbage form only if they need it, for an inline datum
+impl TryFrom<UTXOValue> for c_utxos::TxOut {
+    type Error = Error;
+
+    fn try_from(utxo: UTXOValue) -> Result<Self> {
+        if utxo.script_ref.is_some() {
+            bail!("canonical reference scripts don't carry the script");
+        }
+        let address = utxo.address.to_bytes_key();
+        let value = utxo.value.try_into()?;
+        Ok(match utxo.datum {
+            Some(Datum::Inline(bytes)) => {
+                let mut out = c_utxos::BabbageTxOut::new(address, value);
+                out.key_2 = Some(c_utxos::DatumOption::new_inline_datum_option(bytes));
+                c_utxos::TxOut::new_babbage_tx_out(out)
+            }
+            datum => {
+                let mut out = c_utxos::ShelleyTxOut::new(address, value);
+                out.hash_32 = match datum {
+                    Some(Datum::Hash(hash)) => Some(to_hash32(&hash)?),
+                    _ => None,
+                };
+                c_utxos::TxOut::new_shelley_tx_out(out)
+            }
+        })
+    }
+}
+
+impl TryFrom<c_utxos::Deposit> for Deposit {
+    type Error = Error;
+
+    fn try_from(deposit: c_utxos::Deposit) -> Result<Self> {
+        Ok(match deposit {
+            c_utxos::Deposit::CredentialDeposit(d) => {
+                Deposit::StakeCredential(d.credential.try_into()?)
+            }
+            c_utxos::Deposit::PoolDeposit(d) => Deposit::Pool(from_hash28(d.keyhash)?.into()),
+            c_utxos::Deposit::DrepDeposit(d) => Deposit::DRep(d.credential.try_into()?),
+            c_utxos::Deposit::GovActionDeposit(d) => {
+                Deposit::GovAction(d.gov_action_id.try_into()?)
+            }
+        })
+    }
+}
+
+impl TryFrom<Deposit> for c_utxos::Deposit {
+    type Error = Error;
+
+    fn try_from(deposit: Deposit) -> Result<Self> {
+        Ok(match deposit {
+            Deposit::StakeCredential(credential) => {
+                c_utxos::Deposit::new_credential_deposit(credential.try_into()?)
+            }
+            Deposit::Pool(pool) => c_utxos::Deposit::new_pool_deposit(to_hash28(&pool)?),
+            Deposit::DRep(credential) => c_utxos::Deposit::new_drep_deposit(credential.try_into()?),
+            Deposit::GovAction(id) => c_utxos::Deposit::new_gov_action_deposit(id.try_into()?),
+        })
+    }
+}
+
+impl TryFrom<c_utxos::UtxoState> for UTxOState {
+    type Error = Error;
+
+    fn try_from(state: c_utxos::UtxoState) -> Result<Self> {
+        Ok(UTxOState {
+            utxos: state
+                .utxos
+                .into_iter()
+                .map(|(tx_in, tx_out)| {
+                    let utxo = UTxOIdentifier::try_from(tx_in)?;
+                    let value =
+                        UTXOValue::try_from(tx_out).with_context(|| format!("UTxO {utxo}"))?;
+                    Ok((utxo, value))
+                })
+                .collect::<Result<_>>()?,
+            fees: state.fees,
+            deposits: state
+                .deposits
+                .into_iter()
+                .map(|(deposit, amount)| Ok((deposit.try_into()?, amount)))
+                .collect::<Result<_>>()?,
+            donations: state.donations,
+        })
+    }
+}
+
+impl TryFrom<UTxOState> for c_utxos::UtxoState {
+    type Error = Error;
+
+    fn try_from(state: UTxOState) -> Result<Self> {
+        Ok(c_utxos::UtxoState::new(
+            state
+                .utxos
+                .into_iter()
+                .map(|(utxo, value)| {
+                    let tx_out = value.try_into().with_context(|| format!("UTxO {utxo}"))?;
+                    Ok((utxo.try_into()?, tx_out))
+                })
+                .collect::<Result<_>>()?,
+            state.fees,
+            state
+                .deposits
+                .into_iter()
+                .map(|(deposit, amount)| Ok((deposit.try_into()?, amount)))
+                .collect::<Result<_>>()?,
+            state.donations,
+        ))
+    }
+}
+
+// -- SPO state --
+
+impl TryFrom<c_spos::Relay> for Relay {
+    type Error = Error;
+
+    fn try_from(relay: c_spos::Relay) -> Result<Self> {
+        Ok(match relay {
+            c_spos::Relay::SingleHostAddr(r) => Relay::SingleHostAddr(SingleHostAddr {
+                port: r.port,
+                ipv4: r
+                    .ipv4
+                    .map(|ip| <[u8; 4]>::try_from(Vec::from(ip)).map(Ipv4Addr::from))
+                    .transpose()
+                    .map_err(|_| anyhow!("bad IPv4 address"))?,
+                ipv6: r
+                    .ipv6
+                    .map(|ip| <[u8; 16]>::try_from(Vec::from(ip)).map(Ipv6Addr::from))
+                    .transpose()
+                    .map_err(|_| anyhow!("bad IPv6 address"))?,
+            }),
+            c_spos::Relay::SingleHostName(r) => Relay::SingleHostName(SingleHostName {
+                port: r.port,
+                dns_name: r.dns_name.into(),
+            }),
+            c_spos::Relay::MultiHostName(r) => Relay::MultiHostName(MultiHostName {
+                dns_name: r.dns_name.into(),
+            }),
+        })
+    }
+}
+
+impl TryFrom<Relay> for c_spos::Relay {
+    type Error = Error;
+
+    fn try_from(relay: Relay) -> Result<Self> {
+        Ok(match relay {
+            Relay::SingleHostAddr(r) => c_spos::Relay::new_single_host_addr(
+                r.port,
+                r.ipv4.map(|ip| c_spos::Ipv4::new(ip.octets().to_vec())).transpose()?,
+                r.ipv6.map(|ip| c_spos::Ipv6::new(ip.octets().to_vec())).transpose()?,
+            ),
+            Relay::SingleHostName(r) => {
+                c_spos::Relay::new_single_host_name(r.port, c_spos::DnsName::new(r.dns_name)?)
+            }
+            Relay::MultiHostName(r) => {
+                c_spos::Relay::new_multi_host_name(c_spos::DnsName::new(r.dns_name)?)
+            }
+        })
+    }
+}
+
+/// Owners are key hashes in the canonical form, so they take the network of
+/// the reward account
+impl TryFrom<c_spos::PoolParameters> for PoolRegistration {
+    type Error = Error;
+
+    fn try_from(params: c_spos::PoolParameters) -> Result<Self> {
+        let reward_account = StakeAddress::from_binary(&params.reward_account)?;
+        Ok(PoolRegistration {
+            operator: from_hash28(params.operator)?.into(),
+            vrf_key_hash: from_hash32(params.vrf_keyhash)?.into(),
+            pledge: params.pledge,
+            cost: params.cost,
+            margin: Ratio {
+                numerator: params.margin.index_0,
+                denominator: params.margin.index_1,
+            },
+            pool_owners: params
+                .pool_owners
+                .into_iter()
+                .map(|owner| {
+                    Ok(StakeAddress::new(
+                        Credential::AddrKeyHash(from_hash28(owner)?),
+                        reward_account.network.clone(),
+                    ))
+                })
+                .collect::<Result<_>>()?,
+            reward_account,
+            relays: params.relays.into_iter().map(Relay::try_from).collect::<Result<_>>()?,
+            pool_metadata: params
+                .pool_metadata
+                .map(|metadata| -> Result<_> {
+                    Ok(PoolMetadata {
+                        url: metadata.url.into(),
+                        hash: metadata.index_1.try_into().map_err(|bytes: Vec<u8>| {
+                            anyhow!("bad pool metadata hash {}", hex::encode(bytes))
+                        })?,
+                    })
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<PoolRegistration> for c_spos::PoolParameters {
+    type Error = Error;
+
+    fn try_from(pool: PoolRegistration) -> Result<Self> {
+        Ok(c_spos::PoolParameters::new(
+            to_hash28(&pool.operator)?,
+            c_common::Hash32::new(pool.vrf_key_hash.to_vec())?,
+            pool.pledge,
+            pool.cost,
+            c_common::UnitInterval::new(pool.margin.numerator, pool.margin.denominator),
+            pool.reward_account.to_binary(),
+            pool.pool_owners
+                .iter()
+                .map(|owner| match &owner.credential {
+                    Credential::AddrKeyHash(hash) => to_hash28(hash),
+                    Credential::ScriptHash(_) => bail!("pool owner {owner} is a script"),
+                })
+                .collect::<Result<_>>()?,
+            pool.relays.into_iter().map(c_spos::Relay::try_from).collect::<Result<_>>()?,
+            pool.pool_metadata
+                .map(|metadata| -> Result<_> {
+                    Ok(c_spos::PoolMetadata::new(
+                        c_common::Url::new(metadata.url)?,
+                        metadata.hash.to_vec(),
+                    ))
+                })
+                .transpose()?,
+        ))
+    }
+}
+
+/// Pending updates aren't part of the canonical state, so they start empty
+impl TryFrom<c_spos::SpoState> for SPOState {
+    type Error = Error;
+
+    fn try_from(state: c_spos::SpoState) -> Result<Self> {
+        Ok(SPOState {
+            pools: state
+                .pools
+                .into_iter()
+                .map(|(id, params)| {
+                    let id = PoolId::from(from_hash28(id)?);
+                    let pool =
+                        PoolRegistration::try_from(params).with_context(|| format!("pool {id}"))?;
+                    Ok((id, pool))
+                })
+                .collect::<Result<_>>()?,
+            updates: Default::default(),
+            retiring: state
+                .retiring
+                .into_iter()
+                .map(|(id, epoch)| Ok((from_hash28(id)?.into(), epoch)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Pending updates aren't part of the canonical state and are left out
+impl TryFrom<SPOState> for c_spos::SpoState {
+    type Error = Error;
+
+    fn try_from(state: SPOState) -> Result<Self> {
+        Ok(c_spos::SpoState::new(
+            state
+                .pools
+                .into_iter()
+                .map(|(id, pool)| {
+                    let params = pool.try_into().with_context(|| format!("pool {id}"))?;
+                    Ok((to_hash28(&id)?, params))
+                })
+                .collect::<Result<_>>()?,
+            state
+                .retiring
+                .iter()
+                .map(|(id, epoch)| Ok((to_hash28(id)?, *epoch)))
+                .collect::<Result<_>>()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        NetworkId, ShelleyAddress, ShelleyAddressDelegationPart, ShelleyAddressPaymentPart,
+    };
+    use canonical_ledger_state::serialization::{Deserialize, ToCBORBytes};
+
+    fn stake_address(byte: u8) -> StakeAddress {
+        StakeAddress::new(
+            Credential::AddrKeyHash(Hash::new([byte; 28])),
+            NetworkId::Testnet,
+        )
+    }
+
+    fn utxo_value(datum: Option<Datum>) -> UTXOValue {
+        UTXOValue {
+            address: Address::Shelley(ShelleyAddress {
+                network: NetworkId::Testnet,
+                payment: ShelleyAddressPaymentPart::PaymentKeyHash(Hash::new([1; 28])),
+                delegation: ShelleyAddressDelegationPart::StakeKeyHash(Hash::new([2; 28])),
+            }),
+            value: Value::new(
+                2_000_000,
+                vec![(
+                    Hash::new([3; 28]),
+                    vec![NativeAsset {
+                        name: AssetName::new(b"token").unwrap(),
+                        amount: 42,
+                    }],
+                )],
+            ),
+            datum,
+            script_ref: None,
+        }
+    }
+
+    #[test]
+    fn utxo_state_round_trips_through_cbor() {
+        let mut state = UTxOState {
+            fees: 1_000,
+            donations: 7,
+            ..Default::default()
+        };
+        state.utxos.insert(
+            UTxOIdentifier::new(Hash::new([4; 32]), 0),
+            utxo_value(Some(Datum::Hash(Hash::new([5; 32])))),
+        );
+        state.utxos.insert(
+            UTxOIdentifier::new(Hash::new([4; 32]), 1),
+            utxo_value(Some(Datum::Inline(vec![0xd8, 0x79, 0x80]))),
+        );
+        state.deposits.insert(Deposit::Pool(PoolId::from([6; 28])), 500_000_000);
+        state.deposits.insert(
+            Deposit::StakeCredential(Credential::ScriptHash(Hash::new([7; 28]))),
+            2_000_000,
+        );
+
+        let canonical = c_utxos::UtxoState::try_from(state.clone()).unwrap();
+        let bytes = canonical.to_cbor_bytes();
+        let decoded =
+            UTxOState::try_from(c_utxos::UtxoState::from_cbor_bytes(&bytes).unwrap()).unwrap();
+
+        assert_eq!(decoded.fees, state.fees);
+        assert_eq!(decoded.donations, state.donations);
+        assert_eq!(decoded.deposits, state.deposits);
+        assert_eq!(decoded.utxos.len(), 2);
+        for (utxo, value) in &state.utxos {
+            let got = &decoded.utxos[utxo];
+            assert_eq!(got.address, value.address);
+            assert_eq!(got.value, value.value);
+            assert_eq!(got.datum, value.datum);
+        }
+    }
+
+    #[test]
+    fn values_with_several_policies_and_assets_round_trip() {
+        let mut utxo = utxo_value(None);
+        // Canonical assets are ordered by policy and name
+        utxo.value.assets[0].1.insert(
+            0,
+            NativeAsset {
+                name: AssetName::new(b"other").unwrap(),
+                amount: 1,
+            },
+        );
+        utxo.value.assets.push((
+            Hash::new([9; 28]),
+            vec![NativeAsset {
+                name: AssetName::new(b"").unwrap(),
+                amount: 1_000_000,
+            }],
+        ));
+
+        let bytes = c_utxos::TxOut::try_from(utxo.clone()).unwrap().to_cbor_bytes();
+        let decoded =
+            UTXOValue::try_from(c_utxos::TxOut::from_cbor_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(decoded.value, utxo.value);
+    }
+
+    #[test]
+    fn spo_state_round_trips_through_cbor() {
+        let pool = PoolRegistration {
+            operator: PoolId::from([1; 28]),
+            vrf_key_hash: [2; 32].into(),
+            pledge: 1_000,
+            cost: 340_000_000,
+            margin: Ratio {
+                numerator: 1,
+                denominator: 50,
+            },
+            reward_account: stake_address(3),
+            pool_owners: vec![stake_address(3), stake_address(4)],
+            relays: vec![
+                Relay::SingleHostAddr(SingleHostAddr {
+                    port: Some(3001),
+                    ipv4: Some(Ipv4Addr::new(10, 0, 0, 1)),
+                    ipv6: None,
+                }),
+                Relay::MultiHostName(MultiHostName {
+                    dns_name: "relays.example.com".to_string(),
+                }),
+            ],
+            pool_metadata: Some(PoolMetadata {
+                url: "https://example.com/pool.json".to_string(),
+                hash: Hash::new([5; 32]),
+            }),
+        };
+        let mut state = SPOState::new();
+        state.pools.insert(pool.operator, pool.clone());
+        state.retiring.insert(pool.operator, 300);
+
+        let canonical = c_spos::SpoState::try_from(state.clone()).unwrap();
+        let bytes = canonical.to_cbor_bytes();
+        let decoded =
+            SPOState::try_from(c_spos::SpoState::from_cbor_bytes(&bytes).unwrap()).unwrap();
+
+        assert_eq!(decoded, state);
+    }
+}
@@ -1,16 +1,34 @@
 use crate::{
-    MultiHostName, PoolId, PoolRegistration, Ratio, Relay, SingleHostAddr, SingleHostName,
+    DRepCredential, GovActionId, Lovelace, MultiHostName, PoolId, PoolRegistration, Ratio, Relay,
+    SingleHostAddr, SingleHostName, StakeCredential, UTXOValue, UTxOIdentifier,
 };
 use anyhow::{bail, Context, Result};
 use minicbor::data::Tag;
 use std::{collections::BTreeMap, fs, path::Path};
 
+pub mod canonical;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
 pub struct LedgerState {
     pub spo_state: SPOState,
 }
 
-pub struct UTxOState {}
+#[derive(Debug, Clone, Default)]
+pub struct UTxOState {
+    pub utxos: BTreeMap<UTxOIdentifier, UTXOValue>,
+    pub fees: Lovelace,
+    pub deposits: BTreeMap<Deposit, Lovelace>,
+    pub donations: Lovelace,
+}
+
+/// What a deposit held in the UTxO state was paid for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Deposit {
+    StakeCredential(StakeCredential),
+    Pool(PoolId),
+    DRep(DRepCredential),
+    GovAction(GovActionId),
+}
 
 pub struct StakeDistributionState {}
 
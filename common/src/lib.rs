@@ -1,40 +1,57 @@
 // Acropolis common library - main library exports
 
 pub mod address;
+pub mod buffered_subscription;
 pub mod calculations;
 pub mod caryatid;
 pub mod cbor;
 pub mod certificate;
 pub mod cip19;
 pub mod commands;
+pub mod config_reload;
 pub mod configuration;
+pub mod control;
 pub mod crypto;
+pub mod dead_letter;
 pub mod drep;
+pub mod epoch_barrier;
 pub mod epoch_snapshot;
 pub mod era_summary;
 pub mod genesis_values;
 pub mod hash;
+pub mod id_conversion;
 pub mod ledger_state;
 pub mod math;
+pub mod message_schema;
+pub mod message_trace;
 pub mod messages;
 pub mod metadata;
+pub mod metrics;
 pub mod params;
 pub mod protocol_params;
 pub mod queries;
 pub mod rational_number;
+pub mod readiness;
+pub mod relay_topology;
 pub mod resolver;
 pub mod rest_error;
 pub mod rest_helper;
 pub mod script;
 pub mod serialization;
+pub mod shutdown;
+pub mod sidechain;
 pub mod snapshot;
 pub mod soft_fork;
+pub mod spdd_chunks;
 pub mod stake_addresses;
+pub mod state_digest;
 pub mod state_history;
+pub mod sync_stop;
 pub mod tx;
 pub mod types;
 pub mod upstream_cache;
 pub mod utxo;
+pub mod utxo_batches;
 pub mod validation;
 
 // Flattened re-exports
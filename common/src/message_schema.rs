@@ -0,0 +1,381 @@
+//! Versioned encoding of [`Message`] for external buses
+//!
+//! Messages are encoded as a `(version, body)` pair. Receivers upgrade bodies from
+//! older versions one step at a time, and a process can be pinned to send an older
+//! version (downgrading on the way out) while the rest of a deployment catches up.
+//! A body which still can't be decoded arrives as [`Message::Incompatible`] instead
+//! of being dropped by the bus.
+//!
+//! Bodies from before the envelope was introduced are treated as version 0. The
+//! body is buffered as a self-describing value, so the wire format must be one
+//! (CBOR, JSON).
+//!
+//! Bulk messages (UTXO deltas, stake distributions...) can instead be sent as a
+//! `(version, "cbor", bytes)` triple, with the body encoded by minicbor. That skips
+//! the buffering on receipt and is much more compact on the wire. This is selected
+//! per message type rather than per topic, since the encoding happens beneath the
+//! bus where the topic isn't visible - but each of these types has a topic of its
+//! own. Receivers from before the CBOR codec see these as incompatible.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_value::{DeserializerError, Value, ValueDeserializer};
+
+use crate::messages::{CardanoMessage, Message};
+
+/// Schema version of the messages in this crate. Bump it, and add a migration,
+/// whenever a change to the messages alters their encoding.
+pub const MESSAGE_SCHEMA_VERSION: u32 = 2;
+
+/// Conversion between `from` and `from + 1`
+struct Migration {
+    from: u32,
+    upgrade: fn(Value) -> Result<Value>,
+    downgrade: fn(Value) -> Result<Value>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    // Version 1 only introduced the envelope
+    Migration {
+        from: 0,
+        upgrade: Ok,
+        downgrade: Ok,
+    },
+    // Version 2 shares raw transactions, which encode as byte strings rather than
+    // sequences of integers. Either decodes now, but older receivers need the latter
+    Migration {
+        from: 1,
+        upgrade: Ok,
+        downgrade: raw_txs_as_seqs,
+    },
+];
+
+/// Turn the byte strings in any `txs` field back into sequences of integers
+fn raw_txs_as_seqs(body: Value) -> Result<Value> {
+    Ok(match body {
+        Value::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = match (&key, value) {
+                        (Value::String(name), Value::Seq(txs)) if name == "txs" => Value::Seq(
+                            txs.into_iter()
+                                .map(|tx| match tx {
+                                    Value::Bytes(bytes) => {
+                                        Value::Seq(bytes.into_iter().map(Value::U8).collect())
+                                    }
+                                    other => other,
+                                })
+                                .collect(),
+                        ),
+                        (_, value) => raw_txs_as_seqs(value)?,
+                    };
+                    Ok((key, value))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        Value::Seq(items) => {
+            Value::Seq(items.into_iter().map(raw_txs_as_seqs).collect::<Result<_>>()?)
+        }
+        Value::Newtype(inner) => Value::Newtype(Box::new(raw_txs_as_seqs(*inner)?)),
+        Value::Option(Some(inner)) => Value::Option(Some(Box::new(raw_txs_as_seqs(*inner)?))),
+        other => other,
+    })
+}
+
+static SEND_VERSION: AtomicU32 = AtomicU32::new(MESSAGE_SCHEMA_VERSION);
+
+/// Message types sent with the CBOR codec
+static CBOR_MESSAGES: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+const CBOR_CODEC: &str = "cbor";
+
+/// Message types which may be sent with the CBOR codec
+pub const CBOR_MESSAGE_TYPES: &[&str] = &[
+    "GenesisUTxOs",
+    "UTXODeltas",
+    "UTXODeltasBatch",
+    "AssetDeltas",
+    "TxCertificates",
+    "AddressDeltas",
+    "StakeAddressDeltas",
+    "StakeRewardDeltas",
+    "DRepState",
+    "SPOState",
+    "DRepStakeDistribution",
+    "SPOStakeDistribution",
+    "SPOStakeDistributionChunk",
+    "SPORewards",
+];
+
+fn cbor_message_type(message: &Message) -> Option<&'static str> {
+    let Message::Cardano((_, message)) = message else {
+        return None;
+    };
+    Some(match message {
+        CardanoMessage::GenesisUTxOs(_) => "GenesisUTxOs",
+        CardanoMessage::UTXODeltas(_) => "UTXODeltas",
+        CardanoMessage::UTXODeltasBatch(_) => "UTXODeltasBatch",
+        CardanoMessage::AssetDeltas(_) => "AssetDeltas",
+        CardanoMessage::TxCertificates(_) => "TxCertificates",
+        CardanoMessage::AddressDeltas(_) => "AddressDeltas",
+        CardanoMessage::StakeAddressDeltas(_) => "StakeAddressDeltas",
+        CardanoMessage::StakeRewardDeltas(_) => "StakeRewardDeltas",
+        CardanoMessage::DRepState(_) => "DRepState",
+        CardanoMessage::SPOState(_) => "SPOState",
+        CardanoMessage::DRepStakeDistribution(_) => "DRepStakeDistribution",
+        CardanoMessage::SPOStakeDistribution(_) => "SPOStakeDistribution",
+        CardanoMessage::SPOStakeDistributionChunk(_) => "SPOStakeDistributionChunk",
+        CardanoMessage::SPORewards(_) => "SPORewards",
+        _ => return None,
+    })
+}
+
+/// Send the given message types (from [`CBOR_MESSAGE_TYPES`]) with the CBOR codec.
+/// Can only be set once, at startup.
+pub fn set_cbor_messages(types: &[String]) -> Result<()> {
+    let mut selected = HashSet::new();
+    for name in types {
+        let Some(message_type) = CBOR_MESSAGE_TYPES.iter().find(|t| *t == name) else {
+            bail!("Message type {name} can't be sent as CBOR");
+        };
+        selected.insert(*message_type);
+    }
+    CBOR_MESSAGES.set(selected).map_err(|_| anyhow!("CBOR message types already set"))
+}
+
+/// Encode a message body with minicbor
+pub fn encode_cbor(message: &Message) -> Result<Vec<u8>> {
+    Ok(minicbor_serde::to_vec(&Body(message))?)
+}
+
+/// Decode a message body encoded by [`encode_cbor`]
+pub fn decode_cbor(bytes: &[u8]) -> Result<Message> {
+    let mut deserializer = minicbor_serde::Deserializer::new(bytes);
+    Ok(Message::deserialize(&mut deserializer)?)
+}
+
+/// A message received from the bus which could not be decoded
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IncompatibleMessage {
+    /// Schema version the sender used
+    pub version: u32,
+
+    /// Why it could not be decoded
+    pub error: String,
+}
+
+/// Send messages in an older schema version, for peers built from older crates
+pub fn set_send_version(version: u32) -> Result<()> {
+    if version > MESSAGE_SCHEMA_VERSION {
+        bail!("Can't send message schema version {version}, newest is {MESSAGE_SCHEMA_VERSION}");
+    }
+    for from in version..MESSAGE_SCHEMA_VERSION {
+        migration(from)?;
+    }
+    SEND_VERSION.store(version, Ordering::Relaxed);
+    Ok(())
+}
+
+fn migration(from: u32) -> Result<&'static Migration> {
+    MIGRATIONS
+        .iter()
+        .find(|m| m.from == from)
+        .ok_or_else(|| anyhow!("No migration from message schema version {from}"))
+}
+
+/// Bring a body in schema `version` up to the current one
+pub fn upgrade(version: u32, mut body: Value) -> Result<Value> {
+    if version > MESSAGE_SCHEMA_VERSION {
+        bail!("Message schema version {version} is newer than {MESSAGE_SCHEMA_VERSION}");
+    }
+    for from in version..MESSAGE_SCHEMA_VERSION {
+        body = (migration(from)?.upgrade)(body)?;
+    }
+    Ok(body)
+}
+
+/// Take a body in the current schema down to `version`
+pub fn downgrade(mut body: Value, version: u32) -> Result<Value> {
+    for from in (version..MESSAGE_SCHEMA_VERSION).rev() {
+        body = (migration(from)?.downgrade)(body)?;
+    }
+    Ok(body)
+}
+
+/// Message body in the current schema, with the derived encoding
+struct Body<'a>(&'a Message);
+
+impl Serialize for Body<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Message::serialize(self.0, serializer)
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let version = SEND_VERSION.load(Ordering::Relaxed);
+        if version == MESSAGE_SCHEMA_VERSION {
+            let use_cbor = cbor_message_type(self).is_some_and(|message_type| {
+                CBOR_MESSAGES.get().is_some_and(|selected| selected.contains(message_type))
+            });
+            if use_cbor {
+                let bytes = encode_cbor(self).map_err(S::Error::custom)?;
+                return (version, CBOR_CODEC, serde_bytes::Bytes::new(&bytes))
+                    .serialize(serializer);
+            }
+            return (version, Body(self)).serialize(serializer);
+        }
+
+        let body = serde_value::to_value(Body(self)).map_err(S::Error::custom)?;
+        let body = downgrade(body, version).map_err(S::Error::custom)?;
+        match version {
+            0 => body.serialize(serializer),
+            _ => (version, body).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        Ok(decode(value))
+    }
+}
+
+fn decode(value: Value) -> Message {
+    // Versioned messages are a pair, or a triple with a codec, unversioned ones an
+    // enum variant
+    let (version, body) = match value {
+        Value::Seq(triple) if triple.len() == 3 => {
+            return decode_with_codec(triple.try_into().unwrap())
+        }
+        Value::Seq(mut pair) if pair.len() == 2 => {
+            let body = pair.pop().unwrap();
+            match pair.pop().unwrap().deserialize_into::<u32>() {
+                Ok(version) => (version, body),
+                Err(e) => return incompatible(0, anyhow!("Bad schema version: {e}")),
+            }
+        }
+        body => (0, body),
+    };
+
+    let message = upgrade(version, body).and_then(|body| {
+        Ok(Message::deserialize(
+            ValueDeserializer::<DeserializerError>::new(body),
+        )?)
+    });
+    message.unwrap_or_else(|e| incompatible(version, e))
+}
+
+fn decode_with_codec([version, codec, body]: [Value; 3]) -> Message {
+    let Ok(version) = version.deserialize_into::<u32>() else {
+        return incompatible(0, anyhow!("Bad schema version"));
+    };
+
+    let message = (|| {
+        if version != MESSAGE_SCHEMA_VERSION {
+            bail!("CBOR codec needs schema version {MESSAGE_SCHEMA_VERSION}, not {version}");
+        }
+        let codec = codec.deserialize_into::<String>()?;
+        if codec != CBOR_CODEC {
+            bail!("Unknown message codec {codec}");
+        }
+        let bytes = body.deserialize_into::<serde_bytes::ByteBuf>()?;
+        decode_cbor(&bytes)
+    })();
+    message.unwrap_or_else(|e| incompatible(version, e))
+}
+
+fn incompatible(version: u32, error: anyhow::Error) -> Message {
+    Message::Incompatible(IncompatibleMessage {
+        version,
+        error: format!("{error:#}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode_json(value: serde_json::Value) -> Message {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn messages_round_trip_with_their_version() {
+        let encoded = serde_json::to_value(Message::String("hello".to_string())).unwrap();
+        assert_eq!(
+            encoded,
+            json!([MESSAGE_SCHEMA_VERSION, {"String": "hello"}])
+        );
+        assert!(matches!(decode_json(encoded), Message::String(s) if s == "hello"));
+    }
+
+    #[test]
+    fn unversioned_messages_are_upgraded() {
+        assert!(matches!(
+            decode_json(json!({"String": "hello"})),
+            Message::String(s) if s == "hello"
+        ));
+        assert!(matches!(decode_json(json!("None")), Message::None));
+    }
+
+    #[test]
+    fn cbor_bodies_round_trip() {
+        let message = Message::String("hello".to_string());
+        let bytes = encode_cbor(&message).unwrap();
+        assert!(matches!(decode_cbor(&bytes).unwrap(), Message::String(s) if s == "hello"));
+
+        let envelope = json!([MESSAGE_SCHEMA_VERSION, CBOR_CODEC, bytes]);
+        assert!(matches!(decode_json(envelope), Message::String(s) if s == "hello"));
+
+        let unknown = json!([MESSAGE_SCHEMA_VERSION, "protobuf", [0]]);
+        assert!(matches!(decode_json(unknown), Message::Incompatible(_)));
+    }
+
+    #[test]
+    fn raw_txs_downgrade_to_integer_sequences() {
+        let txs = |tx: Value| {
+            let key = |name: &str| Value::String(name.to_string());
+            Value::Map(
+                [(
+                    key("ReceivedTxs"),
+                    Value::Map([(key("txs"), Value::Seq(vec![tx]))].into()),
+                )]
+                .into(),
+            )
+        };
+        let downgraded = downgrade(txs(Value::Bytes(vec![1, 2])), 1).unwrap();
+        assert_eq!(
+            downgraded,
+            txs(Value::Seq(vec![Value::U8(1), Value::U8(2)]))
+        );
+
+        // and the current schema still reads them
+        let tx = Value::Seq(vec![Value::U8(1), Value::U8(2)]);
+        assert_eq!(tx.deserialize_into::<bytes::Bytes>().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn only_bulk_messages_can_be_cbor() {
+        assert!(set_cbor_messages(&["ProtocolParams".to_string()]).is_err());
+    }
+
+    #[test]
+    fn undecodable_messages_are_incompatible() {
+        let newer = decode_json(json!([MESSAGE_SCHEMA_VERSION + 1, {"String": "hello"}]));
+        assert!(matches!(
+            newer,
+            Message::Incompatible(IncompatibleMessage { version, .. })
+                if version == MESSAGE_SCHEMA_VERSION + 1
+        ));
+
+        let unknown = decode_json(json!([MESSAGE_SCHEMA_VERSION, {"NoSuchMessage": 1}]));
+        assert!(matches!(unknown, Message::Incompatible(_)));
+    }
+}
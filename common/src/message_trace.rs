@@ -0,0 +1,404 @@
+//! Sampled tracing of the messages modules read
+//!
+//! Tracing an inter-module problem otherwise means attaching the Spy module and
+//! grepping its logs. With a `[global.trace]` section, each message a module reads
+//! on a matching topic is sampled into a process-wide ring buffer, with when it was
+//! read, by which module, and its payload as JSON up to a size limit:
+//!
+//! ```toml
+//! [global.trace]
+//! topics = ["cardano.certificates", "cardano.enact.*"]
+//! sample-rate = 0.1
+//! max-payload-bytes = 4096
+//! capacity = 1000
+//! ```
+//!
+//! Sampling is by count rather than at random - a rate of 0.1 keeps every tenth
+//! message on a topic - so every subscriber to a topic keeps the same messages, and
+//! one message can be followed from module to module. The stats module serves the
+//! buffer and publishes new traces for monitoring.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::{async_trait, Subscription};
+
+use crate::messages::Message;
+
+/// What to trace, from `[global.trace]`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct TraceSettings {
+    /// Topics to trace, where `*` matches any run of characters - none if empty
+    pub topics: Vec<String>,
+
+    /// Fraction of the messages on each topic to keep, from 0 to 1
+    pub sample_rate: f64,
+
+    /// Payload kept for each message, as JSON - cut short beyond this
+    pub max_payload_bytes: usize,
+
+    /// Traces kept, the oldest going first
+    pub capacity: usize,
+}
+
+impl Default for TraceSettings {
+    fn default() -> Self {
+        Self {
+            topics: Vec::new(),
+            sample_rate: 1.0,
+            max_payload_bytes: 4096,
+            capacity: 1000,
+        }
+    }
+}
+
+impl TraceSettings {
+    fn traces(&self, topic: &str) -> bool {
+        self.topics.iter().any(|pattern| topic_matches(pattern, topic))
+    }
+}
+
+/// A message as one module read it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MessageTrace {
+    /// Order the trace was recorded in, across all topics
+    pub sequence: u64,
+
+    /// When it was read, RFC 3339
+    pub time: String,
+
+    pub topic: String,
+
+    /// The module which read it
+    pub subscriber: String,
+
+    /// Message variant, e.g. `TxCertificates`
+    pub kind: String,
+
+    /// Block the message is about, if it is a Cardano message
+    pub block_number: Option<u64>,
+
+    /// Size of the whole payload as JSON
+    pub payload_bytes: usize,
+
+    /// The payload as JSON, up to `max-payload-bytes`
+    pub payload: String,
+    pub truncated: bool,
+}
+
+static SETTINGS: OnceLock<TraceSettings> = OnceLock::new();
+
+/// Set what to trace, before any modules are started
+pub fn set_settings(settings: TraceSettings) -> Result<()> {
+    if !(0.0..=1.0).contains(&settings.sample_rate) {
+        bail!(
+            "Trace sample-rate must be from 0 to 1, not {}",
+            settings.sample_rate
+        );
+    }
+    SETTINGS.set(settings).map_err(|_| anyhow!("Trace settings already set"))
+}
+
+fn settings() -> &'static TraceSettings {
+    SETTINGS.get_or_init(TraceSettings::default)
+}
+
+/// Is anything traced at all?
+pub fn enabled() -> bool {
+    !settings().topics.is_empty()
+}
+
+#[derive(Default)]
+struct TraceBuffer {
+    traces: VecDeque<MessageTrace>,
+
+    /// Messages seen, by subscriber and topic, for sampling
+    seen: HashMap<(String, String), u64>,
+    next_sequence: u64,
+}
+
+impl TraceBuffer {
+    /// Count a message, returning whether the sample keeps it
+    fn sample(&mut self, subscriber: &str, topic: &str, rate: f64) -> bool {
+        let seen = self.seen.entry((subscriber.to_string(), topic.to_string())).or_default();
+        *seen += 1;
+        (*seen as f64 * rate).floor() > ((*seen - 1) as f64 * rate).floor()
+    }
+
+    fn push(&mut self, mut trace: MessageTrace, capacity: usize) {
+        trace.sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.traces.push_back(trace);
+        while self.traces.len() > capacity {
+            self.traces.pop_front();
+        }
+    }
+}
+
+static TRACES: LazyLock<Mutex<TraceBuffer>> = LazyLock::new(Mutex::default);
+
+/// Record a message `subscriber` read on `topic`, if it is traced and sampled
+pub fn record(subscriber: &str, topic: &str, message: &Message) {
+    record_with(settings(), &TRACES, subscriber, topic, message);
+}
+
+fn record_with(
+    settings: &TraceSettings,
+    traces: &Mutex<TraceBuffer>,
+    subscriber: &str,
+    topic: &str,
+    message: &Message,
+) {
+    if !settings.traces(topic)
+        || !traces.lock().unwrap().sample(subscriber, topic, settings.sample_rate)
+    {
+        return;
+    }
+
+    // Encoded outside the lock, since payloads can be large
+    let mut payload =
+        payload_json(message).unwrap_or_else(|e| format!("\"payload not encodable: {e}\""));
+    let payload_bytes = payload.len();
+    let truncated = payload_bytes > settings.max_payload_bytes;
+    if truncated {
+        let mut end = settings.max_payload_bytes;
+        while !payload.is_char_boundary(end) {
+            end -= 1;
+        }
+        payload.truncate(end);
+    }
+
+    let trace = MessageTrace {
+        sequence: 0,
+        time: chrono::Utc::now().to_rfc3339(),
+        topic: topic.to_string(),
+        subscriber: subscriber.to_string(),
+        kind: message_kind(message),
+        block_number: match message {
+            Message::Cardano((block, _)) => Some(block.number),
+            _ => None,
+        },
+        payload_bytes,
+        payload,
+        truncated,
+    };
+    traces.lock().unwrap().push(trace, settings.capacity);
+}
+
+/// Subscription which records the messages read from it
+pub struct TracedSubscription {
+    inner: Box<dyn Subscription<Message>>,
+    subscriber: String,
+}
+
+impl TracedSubscription {
+    pub fn new(inner: Box<dyn Subscription<Message>>, subscriber: &str) -> Self {
+        Self {
+            inner,
+            subscriber: subscriber.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Subscription<Message> for TracedSubscription {
+    async fn read(&mut self) -> Result<(String, Arc<Message>)> {
+        let (topic, message) = self.inner.read().await?;
+        record(&self.subscriber, &topic, &message);
+        Ok((topic, message))
+    }
+}
+
+/// The message as JSON, without the versioned envelope it has on a bus
+fn payload_json(message: &Message) -> Result<String> {
+    let mut json = Vec::new();
+    Message::serialize(message, &mut serde_json::Serializer::new(&mut json))?;
+    Ok(String::from_utf8(json)?)
+}
+
+/// Traces after `after` on topics matching `topic`, oldest first, keeping the
+/// latest `limit`
+pub fn traces(topic: Option<&str>, after: Option<u64>, limit: usize) -> Vec<MessageTrace> {
+    select(&TRACES.lock().unwrap(), topic, after, limit)
+}
+
+fn select(
+    buffer: &TraceBuffer,
+    topic: Option<&str>,
+    after: Option<u64>,
+    limit: usize,
+) -> Vec<MessageTrace> {
+    let mut selected: Vec<MessageTrace> = buffer
+        .traces
+        .iter()
+        .rev()
+        .filter(|trace| after.is_none_or(|after| trace.sequence > after))
+        .filter(|trace| topic.is_none_or(|pattern| topic_matches(pattern, &trace.topic)))
+        .take(limit)
+        .cloned()
+        .collect();
+    selected.reverse();
+    selected
+}
+
+/// Does `topic` match `pattern`, where `*` matches any run of characters?
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = topic.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`, so the whole topic must match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Collects a variant name from a `Debug` rendering, stopping at its contents
+#[derive(Default)]
+struct VariantNameWriter {
+    name: String,
+}
+
+impl fmt::Write for VariantNameWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if matches!(c, '(' | '{' | ' ') {
+                return Err(fmt::Error);
+            }
+            self.name.push(c);
+        }
+        Ok(())
+    }
+}
+
+fn variant_name(value: &dyn fmt::Debug) -> String {
+    let mut writer = VariantNameWriter::default();
+    // Stopping early is reported as an error
+    let _ = fmt::write(&mut writer, format_args!("{value:?}"));
+    writer.name
+}
+
+/// Variant of the message, or of the Cardano message within it
+fn message_kind(message: &Message) -> String {
+    match message {
+        Message::Cardano((_, cardano)) => variant_name(cardano),
+        other => variant_name(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(topics: &[&str], sample_rate: f64, max_payload_bytes: usize) -> TraceSettings {
+        TraceSettings {
+            topics: topics.iter().map(|t| t.to_string()).collect(),
+            sample_rate,
+            max_payload_bytes,
+            capacity: 3,
+        }
+    }
+
+    #[test]
+    fn topics_match_patterns() {
+        assert!(topic_matches(
+            "cardano.certificates",
+            "cardano.certificates"
+        ));
+        assert!(!topic_matches(
+            "cardano.certificates",
+            "cardano.certificates.more"
+        ));
+        assert!(topic_matches("cardano.*", "cardano.enact.state"));
+        assert!(topic_matches("*.state", "cardano.enact.state"));
+        assert!(topic_matches("cardano.*.state", "cardano.enact.state"));
+        assert!(!topic_matches("cardano.*.state", "cardano.enact.states"));
+        assert!(topic_matches("*", "anything"));
+        assert!(!topic_matches("rest.*", "cardano.enact.state"));
+    }
+
+    #[test]
+    fn messages_are_sampled_by_count() {
+        let settings = settings(&["cardano.*"], 0.25, 4096);
+        let buffer = Mutex::new(TraceBuffer::default());
+        for n in 0..8 {
+            let message = Message::String(format!("message {n}"));
+            record_with(&settings, &buffer, "a-state", "cardano.test", &message);
+            record_with(&settings, &buffer, "b-state", "cardano.test", &message);
+            record_with(&settings, &buffer, "a-state", "other.test", &message);
+        }
+
+        // Every fourth message, the same ones for each subscriber, within capacity
+        let buffer = buffer.lock().unwrap();
+        let traced: Vec<_> = buffer
+            .traces
+            .iter()
+            .map(|t| (t.sequence, t.subscriber.as_str(), t.payload.as_str()))
+            .collect();
+        assert_eq!(
+            traced,
+            vec![
+                (1, "b-state", r#"{"String":"message 3"}"#),
+                (2, "a-state", r#"{"String":"message 7"}"#),
+                (3, "b-state", r#"{"String":"message 7"}"#),
+            ]
+        );
+        assert_eq!(buffer.traces[0].kind, "String");
+        assert_eq!(buffer.traces[0].block_number, None);
+    }
+
+    #[test]
+    fn payloads_are_cut_short() {
+        let settings = settings(&["*"], 1.0, 10);
+        let buffer = Mutex::new(TraceBuffer::default());
+        let message = Message::String("a long message".to_string());
+        record_with(&settings, &buffer, "a-state", "cardano.test", &message);
+
+        let buffer = buffer.lock().unwrap();
+        let trace = &buffer.traces[0];
+        assert!(trace.truncated);
+        assert_eq!(trace.payload.len(), 10);
+        assert_eq!(trace.payload, r#"{"String""#);
+        assert_eq!(trace.payload_bytes, r#"{"String":"a long message"}"#.len());
+    }
+
+    #[test]
+    fn traces_are_selected_latest_first() {
+        let settings = settings(&["*"], 1.0, 4096);
+        let buffer = Mutex::new(TraceBuffer::default());
+        for topic in ["cardano.a", "cardano.b", "cardano.a"] {
+            record_with(&settings, &buffer, "a-state", topic, &Message::None);
+        }
+        let buffer = buffer.lock().unwrap();
+
+        let sequences =
+            |traces: Vec<MessageTrace>| traces.iter().map(|t| t.sequence).collect::<Vec<_>>();
+        assert_eq!(sequences(select(&buffer, None, None, 2)), vec![1, 2]);
+        assert_eq!(
+            sequences(select(&buffer, Some("*.a"), None, 10)),
+            vec![0, 2]
+        );
+        assert_eq!(sequences(select(&buffer, None, Some(0), 10)), vec![1, 2]);
+        assert_eq!(buffer.traces[0].kind, "None");
+    }
+
+    #[test]
+    fn sample_rate_is_checked() {
+        assert!(set_settings(settings(&["*"], 1.5, 10)).is_err());
+    }
+}
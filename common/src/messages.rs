@@ -1,11 +1,16 @@
 //! Definition of Acropolis messages
 
-use crate::address::StakeAddress;
+use crate::address::{ShelleyAddressPointer, StakeAddress};
 use crate::commands::chain_sync::ChainSyncCommand;
+use crate::commands::peer_network::PeerNetworkCommand;
 use crate::commands::transactions::{TransactionsCommand, TransactionsCommandResponse};
+use crate::epoch_snapshot::EpochSnapshot;
 use crate::genesis_values::GenesisValues;
+use crate::hash::Hash;
 use crate::ledger_state::SPOState;
+use crate::message_trace::MessageTrace;
 use crate::protocol_params::{Nonce, Nonces, ProtocolParams};
+use crate::queries::drdd::{DRDDStateQuery, DRDDStateQueryResponse};
 use crate::queries::parameters::{ParametersStateQuery, ParametersStateQueryResponse};
 use crate::queries::spdd::{SPDDStateQuery, SPDDStateQueryResponse};
 use crate::queries::stake_deltas::{StakeDeltaQuery, StakeDeltaQueryResponse};
@@ -15,6 +20,7 @@ use crate::queries::{
     addresses::{AddressStateQuery, AddressStateQueryResponse},
     assets::{AssetsStateQuery, AssetsStateQueryResponse},
     blocks::{BlocksStateQuery, BlocksStateQueryResponse},
+    consensus::{ConsensusStateQuery, ConsensusStateQueryResponse},
     epochs::{EpochsStateQuery, EpochsStateQueryResponse},
     governance::{GovernanceStateQuery, GovernanceStateQueryResponse},
     ledger::{LedgerStateQuery, LedgerStateQueryResponse},
@@ -26,9 +32,11 @@ use crate::queries::{
     transactions::{TransactionsStateQuery, TransactionsStateQueryResponse},
 };
 use crate::snapshot::AccountState;
-use crate::{Pots, ReferenceScript, TxUTxODeltas, UTXOValue, UTxOIdentifier};
+use crate::{PlutusVersion, Pots, ReferenceScript, TxUTxODeltas, UTXOValue, UTxOIdentifier};
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 use crate::cbor::u128_cbor_codec;
 use crate::validation::ValidationStatus;
@@ -37,6 +45,8 @@ use std::borrow::Cow;
 
 // Caryatid core messages which we re-export
 use crate::epoch_snapshot::SnapshotsContainer;
+use crate::message_schema::IncompatibleMessage;
+use crate::state_history::StateHistoryMetrics;
 pub use caryatid_module_clock::messages::ClockTickMessage;
 pub use caryatid_module_rest_server::messages::{GetRESTResponse, RESTRequest, RESTResponse};
 
@@ -60,8 +70,23 @@ pub enum StateTransitionMessage {
 /// Transactions message
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RawTxsMessage {
-    /// Raw Data for each transaction
-    pub txs: Vec<Vec<u8>>,
+    /// Raw Data for each transaction - shared, so consumers can hold on to a
+    /// transaction without copying it
+    pub txs: Vec<Bytes>,
+}
+
+/// Block header without the decoded body - the transactions can be fetched from the
+/// chain store when they are needed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BlockHeaderMessage {
+    /// Header raw data
+    pub header: Vec<u8>,
+
+    /// Number of transactions in the body
+    pub tx_count: usize,
+
+    /// Size of the raw block in bytes
+    pub block_size: usize,
 }
 
 /// Genesis completion message
@@ -76,10 +101,25 @@ pub struct GenesisUTxOsMessage {
     pub utxos: Vec<(UTxOIdentifier, TxIdentifier)>,
 }
 
-/// Message encapsulating multiple UTXO deltas, in order
+/// Message encapsulating multiple UTXO deltas, in order - one is published per block,
+/// carrying the deltas of all its transactions, rather than one per delta
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UTXODeltasMessage {
-    /// Ordered set of deltas
+    /// Ordered set of deltas, one entry per transaction
+    pub deltas: Vec<TxUTxODeltas>,
+}
+
+/// Part of a block's UTXO deltas, published in batches when the block has more
+/// transactions than the publisher puts in one message - all against the same block
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UTXODeltasBatchMessage {
+    /// Position of this batch, from 0
+    pub sequence: u64,
+
+    /// Number of batches for the block
+    pub batches: u64,
+
+    /// Ordered set of deltas, one entry per transaction
     pub deltas: Vec<TxUTxODeltas>,
 }
 
@@ -89,8 +129,8 @@ pub struct AssetDeltasMessage {
     /// Mint and burn deltas per tx
     pub deltas: Vec<(TxIdentifier, NativeAssetsDelta)>,
 
-    /// CIP 25 metadata blobs (Using 721 label)
-    pub cip25_metadata_updates: Vec<Vec<u8>>,
+    /// CIP 25 metadata blobs (Using 721 label), with the tx carrying each
+    pub cip25_metadata_updates: Vec<(TxIdentifier, Vec<u8>)>,
 }
 
 /// Message encapsulating multiple transaction certificates, in order
@@ -263,6 +303,11 @@ pub struct EpochActivityMessage {
     /// Nonce
     #[n(12)]
     pub nonce: Option<Nonce>,
+
+    /// Total active stake for this epoch, from the SPDD taken two epochs before.
+    /// None if the SPDD is not tracked
+    #[n(13)]
+    pub active_stake: Option<Lovelace>,
 }
 
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
@@ -322,6 +367,50 @@ pub struct SPOStakeDistributionMessage {
     pub spos: Vec<(PoolId, DelegatedStake)>,
 }
 
+/// Stake snapshot taken as an epoch ended, with each pool's delegators and their
+/// active stake - which is the distribution active two epochs later
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StakeSnapshotMessage {
+    /// Shared with accounts-state, which uses it for rewards
+    pub snapshot: Arc<EpochSnapshot>,
+}
+
+/// Part of an SPDD published in chunks, so that no one message has to carry the
+/// whole distribution - a header, the pools over one or more chunks, then a trailer,
+/// all against the same block
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SPOStakeDistributionChunkMessage {
+    /// Opens the distribution
+    Header {
+        /// Epoch which has ended
+        epoch: u64,
+
+        /// Number of pools in the distribution
+        pools: u64,
+
+        /// Active stake of all the pools, for readers taking the chunks as they arrive
+        total_active: u64,
+
+        /// Number of chunks of pools to follow
+        chunks: u64,
+    },
+
+    /// Next chunk of pools, in operator ID order
+    Pools {
+        /// Position of this chunk, from 0
+        sequence: u64,
+
+        /// SPO stake distribution by operator ID
+        spos: Vec<(PoolId, DelegatedStake)>,
+    },
+
+    /// Closes the distribution
+    Trailer {
+        /// Number of chunks sent
+        chunks: u64,
+    },
+}
+
 /// Default vote for each SPO, published at epoch boundary.
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SPODefaultVoteMessage {
@@ -346,6 +435,37 @@ pub struct ProtocolParamsMessage {
     pub params: ProtocolParams,
 }
 
+/// Published at an epoch boundary where the major protocol version changes, whether it
+/// moves to a new era or is a hard fork within one
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EraTransitionMessage {
+    pub old_era: Era,
+    pub new_era: Era,
+
+    /// First epoch and slot under the new protocol version
+    pub epoch: u64,
+    pub slot: u64,
+
+    pub old_protocol_major: Option<u64>,
+    pub new_protocol_major: Option<u64>,
+}
+
+impl EraTransitionMessage {
+    pub fn is_new_era(&self) -> bool {
+        self.old_era != self.new_era
+    }
+}
+
+/// Published at an epoch boundary where the cost model of any Plutus language changes
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CostModelsMessage {
+    /// Cost models in force from this epoch
+    pub cost_models: CostModels,
+
+    /// Languages whose cost model was added or replaced
+    pub changed: Vec<PlutusVersion>,
+}
+
 /// Generated after all governance actions for the current epoch are processed.
 /// Includes info about all actions that are accepted or expired at the epoch edge.
 /// `VotingOutcome` informs about action_id, voting outcome and votes cast for the
@@ -372,6 +492,47 @@ pub struct SPOStateMessage {
     pub retired_spos: Vec<(PoolId, StakeAddress)>,
 }
 
+/// Digest of a module's state at the end of an epoch, see state_digest
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StateDigestMessage {
+    /// Module the state belongs to
+    pub module: String,
+
+    /// Epoch which has ended
+    pub epoch: u64,
+
+    pub digest: Hash<32>,
+
+    /// Digest of each section of the state, by name, if the module splits it up
+    #[serde(default)]
+    pub sections: BTreeMap<String, Hash<32>>,
+}
+
+/// A module has finished its processing for the end of an epoch, see epoch_barrier
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EpochBarrierAckMessage {
+    /// Module which has finished
+    pub module: String,
+
+    /// Epoch which has ended
+    pub epoch: u64,
+}
+
+/// Every module taking part in the epoch barrier has finished with the end of an epoch
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EpochBarrierCompleteMessage {
+    /// Epoch which has ended
+    pub epoch: u64,
+}
+
+/// Block ingestion has stopped at the configured stop point - sent with the
+/// last block let through
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SyncCompleteMessage {
+    pub stop_at_epoch: Option<u64>,
+    pub stop_at_slot: Option<u64>,
+}
+
 /// Cardano message enum
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[allow(clippy::large_enum_variant)]
@@ -380,9 +541,11 @@ pub enum CardanoMessage {
     StateTransition(StateTransitionMessage), // Our position on the chain has changed
     BlockValidation(ValidationStatus),       // Result of a block validation
     ReceivedTxs(RawTxsMessage),              // Transaction available
+    BlockHeader(BlockHeaderMessage),         // Header only, body on demand
     GenesisComplete(GenesisCompleteMessage), // Genesis UTXOs done + genesis params
     GenesisUTxOs(GenesisUTxOsMessage),       // Genesis UTxOs with their UTxOIdentifiers
     UTXODeltas(UTXODeltasMessage),           // UTXO deltas received
+    UTXODeltasBatch(UTXODeltasBatchMessage), // Part of a block's UTXO deltas
     AssetDeltas(AssetDeltasMessage),         // Asset mint and burn deltas
     TxCertificates(TxCertificatesMessage),   // Transaction certificates received
     AddressDeltas(AddressDeltasMessage),     // Address deltas received
@@ -397,11 +560,15 @@ pub enum CardanoMessage {
     // Protocol Parameters
     ProtocolParams(ProtocolParamsMessage), // Generated by Parameter State module
     GovernanceOutcomes(GovernanceOutcomesMessage), // Enacted updates from Governance
+    CostModels(CostModelsMessage),         // Changed Plutus cost models
+    EraTransition(EraTransitionMessage),   // Protocol major version changed
 
     // Stake distribution info
     DRepStakeDistribution(DRepStakeDistributionMessage), // Info about drep stake
     SPOStakeDistribution(SPOStakeDistributionMessage),   // SPO delegation distribution (SPDD)
+    SPOStakeDistributionChunk(SPOStakeDistributionChunkMessage), // Part of a chunked SPDD
     SPORewards(SPORewardsMessage),                       // SPO rewards distribution (SPRD)
+    StakeSnapshot(StakeSnapshotMessage),                 // Per-account stake snapshot
     SPODefaultVote(SPODefaultVoteMessage),               // SPO default vote
     StakeAddressDeltas(StakeAddressDeltasMessage),       // Stake part of address deltas
     StakeRewardDeltas(StakeRewardDeltasMessage),         // Stake Reward Deltas
@@ -412,6 +579,16 @@ pub enum CardanoMessage {
 
     // Pots
     Pots(Pots), // Current Pots
+
+    // State digests for comparing nodes
+    StateDigest(StateDigestMessage),
+
+    // Epoch boundary barrier
+    EpochBarrierAck(EpochBarrierAckMessage), // A module has finished with an epoch
+    EpochBarrierComplete(EpochBarrierCompleteMessage), // All modules have finished with an epoch
+
+    // Partial sync
+    SyncComplete(SyncCompleteMessage), // Sync stopped at the configured epoch or slot
 }
 
 /// A new block has been announced by some peer
@@ -421,6 +598,14 @@ pub struct BlockOfferedMessage {
     pub slot: u64,
     pub number: u64,
     pub parent_hash: BlockHash,
+
+    /// Leader VRF output from the header, to break ties between equally long chains
+    #[serde(default)]
+    pub tiebreak_vrf: Option<Vec<u8>>,
+
+    /// Address of the first peer to announce the block, if known
+    #[serde(default)]
+    pub origin_peer: Option<String>,
 }
 
 /// A block has been rescinded by all peers (they rolled back to before it)
@@ -444,12 +629,32 @@ pub struct BlockRejectedMessage {
     pub slot: u64,
 }
 
+/// A block has been offered which branches off a block that already has a child,
+/// so the node is straddling a fork
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ForkDetectedMessage {
+    /// First block of the new branch
+    pub hash: BlockHash,
+    pub slot: u64,
+    pub number: u64,
+
+    /// Block both branches extend
+    pub fork_point: BlockHash,
+
+    /// Whether consensus now favours the new branch
+    pub favoured: bool,
+
+    /// Address of the peer which offered the new branch, if known
+    pub origin_peer: Option<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum ConsensusMessage {
     BlockOffered(BlockOfferedMessage), // A new block has been announced (by at least one peer)
     BlockRescinded(BlockRescindedMessage), // All peers have un-announced (rolled back to before) a block
     BlockWanted(BlockWantedMessage),       // A particular block has been requested
     BlockRejected(BlockRejectedMessage), // A particular block has failed validation, and all peers who offered it should be penalized
+    ForkDetected(ForkDetectedMessage),   // A second chain has branched off a block consensus knows
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -577,6 +782,10 @@ pub struct AccountsBootstrapMessage {
 
     /// Total proposal deposits by stake address
     pub proposal_deposits: HashMap<StakeAddress, Lovelace>,
+
+    /// Registration pointers of pre-Conway accounts (the ledger's pointer map), so that
+    /// pointer addresses can be resolved without replaying the chain
+    pub pointers: Vec<(ShelleyAddressPointer, StakeAddress)>,
 }
 
 /// Deltas to apply to pots at epoch boundary during snapshot bootstrap
@@ -655,8 +864,180 @@ pub enum SnapshotStateMessage {
     BlockKesValidatorState(BlockKesValidatorBootstrapMessage),
 }
 
+/// Connection-level metrics for one upstream peer
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PeerConnectionMetrics {
+    pub address: String,
+    /// Hot (syncing) rather than warm (connected but idle)
+    pub hot: bool,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub headers_received: u64,
+    pub blocks_received: u64,
+    /// Smoothed request/response round trip over the multiplexer
+    pub mux_latency_ms: Option<f64>,
+}
+
+/// Health of the upstream peer network, published periodically.
+/// Byte counts are mini-protocol payloads, excluding mux framing.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkMetricsMessage {
+    pub hot_peers: usize,
+    pub warm_peers: usize,
+    pub cold_peers: usize,
+    /// Totals since startup, across all connections
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub headers_received: u64,
+    pub blocks_received: u64,
+    /// Rates over the last reporting interval
+    pub bytes_in_per_sec: f64,
+    pub bytes_out_per_sec: f64,
+    pub headers_per_sec: f64,
+    pub blocks_per_sec: f64,
+    /// Mean mux latency across hot peers
+    pub mux_latency_ms: Option<f64>,
+    pub peers: Vec<PeerConnectionMetrics>,
+}
+
+/// Response cache statistics, published periodically. Counts are totals since startup.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CacheMetricsMessage {
+    /// Name of the cache, e.g. the module holding it
+    pub cache: String,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped because their TTL had passed
+    pub expired: u64,
+    /// Entries dropped to stay within the size limit
+    pub evicted: u64,
+    /// Times the whole cache was cleared, on a new epoch or a rollback
+    pub invalidations: u64,
+}
+
+/// Load on one REST worker pool. Counts are totals since startup.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerPoolMetrics {
+    pub pool: String,
+    pub max_concurrent: usize,
+    pub max_queued: usize,
+    /// Requests being handled now
+    pub in_flight: u64,
+    /// Requests waiting for a worker now
+    pub queued: u64,
+    /// Most requests ever waiting at once
+    pub peak_queued: u64,
+    pub completed: u64,
+    /// Requests refused because the queue was full
+    pub rejected: u64,
+    /// Total time requests spent waiting for a worker
+    pub wait_seconds: f64,
+}
+
+/// Load on each of a REST module's worker pools, published periodically
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerMetricsMessage {
+    /// Module the pools belong to
+    pub module: String,
+    pub pools: Vec<WorkerPoolMetrics>,
+}
+
+/// Size of every state history in the process
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StateHistoryMetricsMessage {
+    pub histories: Vec<StateHistoryMetrics>,
+}
+
+/// Messages sampled for tracing since the last of these, oldest first
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MessageTracesMessage {
+    pub traces: Vec<MessageTrace>,
+}
+
+/// Time spent answering one type of state query, totals since startup
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QueryTypeMetrics {
+    pub module: String,
+    /// Query variant, e.g. `GetAccountInfo`
+    pub query: String,
+    pub count: u64,
+    pub total_seconds: f64,
+}
+
+impl QueryTypeMetrics {
+    pub fn mean_seconds(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_seconds / self.count as f64
+        }
+    }
+}
+
+/// State query service times of every module in the process, slowest first
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QueryMetricsMessage {
+    pub queries: Vec<QueryTypeMetrics>,
+
+    /// Queries received and not yet answered, by module
+    pub depths: BTreeMap<String, u64>,
+}
+
+/// Published data checked against reference data from another node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CorrectnessCheck {
+    /// SPO stake distribution, per pool
+    SPDD,
+
+    /// SPO rewards, total and operator's per pool
+    SPORewards,
+}
+
+/// One value which differs from the reference. An absent value means the
+/// pool (or other key) is missing from that side.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Discrepancy {
+    /// What the value is for, e.g. a pool ID, or "total"
+    pub key: String,
+    pub reference: Option<Lovelace>,
+    pub published: Option<Lovelace>,
+}
+
+/// Result of checking one epoch's published data against the reference
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DiscrepancyReportMessage {
+    pub check: CorrectnessCheck,
+
+    /// Epoch the reference data is for
+    pub epoch: u64,
+
+    /// Reference file compared against
+    pub reference: String,
+
+    /// Number of values compared
+    pub compared: usize,
+
+    /// Empty if everything matched
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Metrics published for monitoring rather than consumed by the ledger
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum MonitoringMessage {
+    Network(NetworkMetricsMessage),
+    Cache(CacheMetricsMessage),
+    StateHistory(StateHistoryMetricsMessage),
+    Discrepancy(DiscrepancyReportMessage),
+    Queries(QueryMetricsMessage),
+    MessageTraces(MessageTracesMessage),
+    Workers(WorkerMetricsMessage),
+}
+
 // === Global message enum ===
+// Encoded with a schema version, see message_schema
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(remote = "Self")]
 pub enum Message {
     #[default]
     None, // Just so we have a simple default
@@ -686,6 +1067,12 @@ pub enum Message {
     // Commands
     Command(Command),
     CommandResponse(CommandResponse),
+
+    // Monitoring
+    Monitoring(MonitoringMessage),
+
+    // Received from an external bus, but not decodable by this version
+    Incompatible(IncompatibleMessage),
 }
 
 // Casts from specific Caryatid messages
@@ -724,6 +1111,7 @@ pub enum StateQuery {
     Addresses(AddressStateQuery),
     Assets(AssetsStateQuery),
     Blocks(BlocksStateQuery),
+    Consensus(ConsensusStateQuery),
     Epochs(EpochsStateQuery),
     Governance(GovernanceStateQuery),
     Ledger(LedgerStateQuery),
@@ -737,6 +1125,7 @@ pub enum StateQuery {
     Transactions(TransactionsStateQuery),
     UTxOs(UTxOStateQuery),
     SPDD(SPDDStateQuery),
+    DRDD(DRDDStateQuery),
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -746,6 +1135,7 @@ pub enum StateQueryResponse {
     Addresses(AddressStateQueryResponse),
     Assets(AssetsStateQueryResponse),
     Blocks(BlocksStateQueryResponse),
+    Consensus(ConsensusStateQueryResponse),
     Epochs(EpochsStateQueryResponse),
     Governance(GovernanceStateQueryResponse),
     Ledger(LedgerStateQueryResponse),
@@ -759,17 +1149,120 @@ pub enum StateQueryResponse {
     Transactions(TransactionsStateQueryResponse),
     UTxOs(UTxOStateQueryResponse),
     SPDD(SPDDStateQueryResponse),
+    DRDD(DRDDStateQueryResponse),
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Command {
     Transactions(TransactionsCommand),
     ChainSync(ChainSyncCommand),
+    PeerNetwork(PeerNetworkCommand),
+
+    /// Flush on-disk stores, as the process is about to exit
+    Shutdown,
+
+    /// Re-read the configuration, applying what can be applied without a restart
+    ReloadConfig,
+
+    /// A module's config has changed in keys it can apply without a restart
+    ApplyConfig(ConfigUpdate),
+
+    /// Operator action on a module, see control
+    Control(ControlCommand),
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum CommandResponse {
     Transactions(TransactionsCommandResponse),
+    Shutdown(ShutdownAck),
+    ConfigApplied(ConfigApplyAck),
+    ConfigReload(ConfigReloadReport),
+    Control(ControlAck),
+}
+
+/// A module has finished flushing for shutdown
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ShutdownAck {
+    pub module: String,
+
+    /// Why the flush failed, if it did
+    pub error: Option<String>,
+}
+
+/// New config for a module
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConfigUpdate {
+    pub module: String,
+
+    /// Keys which changed
+    pub changed: Vec<String>,
+
+    /// The module's whole new `[module.*]` section
+    pub section: serde_json::Value,
+}
+
+/// A module has applied its new config
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConfigApplyAck {
+    pub module: String,
+
+    /// Why it couldn't be applied, if it couldn't
+    pub error: Option<String>,
+}
+
+/// What a configuration reload changed
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConfigReloadReport {
+    /// Changes now in effect, as `module.<name>.<key>`
+    pub applied: Vec<String>,
+
+    /// Changes which only take effect on a restart
+    pub restart_required: Vec<String>,
+
+    /// Modules which couldn't apply their changes, and why
+    pub failed: Vec<(String, String)>,
+}
+
+/// What an operator can ask a module to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ControlAction {
+    /// Stop consuming messages, leaving them queued on the bus
+    Pause,
+
+    /// Carry on consuming after a pause
+    Resume,
+
+    /// Write out a summary of the module's state
+    DumpState,
+
+    /// Take a snapshot of the module's state now
+    RequestSnapshot,
+
+    /// Apply the module's dead letters again, once a fix has shipped
+    ReplayDeadLetters,
+}
+
+/// Operator action on a module
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ControlCommand {
+    pub module: String,
+    pub action: ControlAction,
+
+    /// Who asked, for the audit log
+    pub operator: String,
+}
+
+/// A module has carried out a control action
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ControlAck {
+    pub module: String,
+    pub action: ControlAction,
+
+    /// What was done, e.g. the state summary
+    pub detail: Option<String>,
+
+    /// Why it couldn't be done, if it couldn't
+    pub error: Option<String>,
 }
 
 #[cfg(test)]
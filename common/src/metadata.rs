@@ -23,7 +23,7 @@ impl Deref for Metadata {
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Metadatum {
     Int(i128),
     Bytes(Vec<u8>),
@@ -0,0 +1,586 @@
+//! Process-wide metrics, exposed in the Prometheus text format
+//!
+//! Handles are cheap to clone and update, so look them up once and keep them:
+//!
+//! ```ignore
+//! let applied = metrics::counter("acropolis_blocks_applied_total", "Blocks applied",
+//!                                &[("module", "utxo-state")]);
+//! applied.inc();
+//! ```
+//!
+//! Asking for the same name and labels again returns the same series.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::messages::{Message, QueryMetricsMessage, QueryTypeMetrics};
+use crate::state_history;
+
+/// Latency buckets, in seconds, for block application and queries
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Buckets, in seconds, for work done once per epoch, which can run far longer than a block
+pub const EPOCH_BOUNDARY_BUCKETS: &[f64] = &[
+    0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Monotonic count
+#[derive(Clone, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Value which can go up and down
+#[derive(Clone, Default)]
+pub struct Gauge(Arc<AtomicU64>);
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Default)]
+struct HistogramData {
+    /// Count per bucket, not cumulative
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+/// Distribution of observations, in fixed buckets
+#[derive(Clone)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    data: Arc<Mutex<HistogramData>>,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            data: Arc::new(Mutex::new(HistogramData {
+                buckets: vec![0; bounds.len()],
+                ..HistogramData::default()
+            })),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let mut data = self.data.lock().unwrap();
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            data.buckets[bucket] += 1;
+        }
+        data.count += 1;
+        data.sum += value;
+    }
+
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    /// Number and sum of the observations so far
+    pub fn totals(&self) -> (u64, f64) {
+        let data = self.data.lock().unwrap();
+        (data.count, data.sum)
+    }
+
+    /// Observe the time until the timer is dropped
+    pub fn start_timer(&self) -> Timer {
+        Timer {
+            histogram: self.clone(),
+            start: Instant::now(),
+        }
+    }
+}
+
+pub struct Timer {
+    histogram: Histogram,
+    start: Instant,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.histogram.observe_duration(self.start.elapsed());
+    }
+}
+
+#[derive(Clone)]
+enum Metric {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl Metric {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Metric::Counter(_) => "counter",
+            Metric::Gauge(_) => "gauge",
+            Metric::Histogram(_) => "histogram",
+        }
+    }
+}
+
+type Labels = Vec<(String, String)>;
+
+struct Family {
+    help: String,
+    series: BTreeMap<Labels, Metric>,
+}
+
+static REGISTRY: LazyLock<Mutex<BTreeMap<String, Family>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn register(
+    name: &str,
+    help: &str,
+    labels: &[(&str, &str)],
+    create: impl FnOnce() -> Metric,
+) -> Metric {
+    let mut labels: Labels = labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    labels.sort();
+
+    let mut registry = REGISTRY.lock().unwrap();
+    let family = registry.entry(name.to_string()).or_insert_with(|| Family {
+        help: help.to_string(),
+        series: BTreeMap::new(),
+    });
+    family.series.entry(labels).or_insert_with(create).clone()
+}
+
+/// Get or create a counter
+pub fn counter(name: &str, help: &str, labels: &[(&str, &str)]) -> Counter {
+    match register(name, help, labels, || Metric::Counter(Counter::default())) {
+        Metric::Counter(counter) => counter,
+        // Name already used for another type - record into a detached handle
+        _ => Counter::default(),
+    }
+}
+
+/// Get or create a gauge
+pub fn gauge(name: &str, help: &str, labels: &[(&str, &str)]) -> Gauge {
+    match register(name, help, labels, || Metric::Gauge(Gauge::default())) {
+        Metric::Gauge(gauge) => gauge,
+        _ => Gauge::default(),
+    }
+}
+
+/// Get or create a histogram with the given bucket upper bounds
+pub fn histogram(
+    name: &str,
+    help: &str,
+    labels: &[(&str, &str)],
+    buckets: &'static [f64],
+) -> Histogram {
+    match register(name, help, labels, || {
+        Metric::Histogram(Histogram::new(buckets))
+    }) {
+        Metric::Histogram(histogram) => histogram,
+        _ => Histogram::new(buckets),
+    }
+}
+
+/// Every series of a gauge, with its labels
+pub fn gauge_series(name: &str) -> Vec<(Vec<(String, String)>, Gauge)> {
+    let registry = REGISTRY.lock().unwrap();
+    let Some(family) = registry.get(name) else {
+        return Vec::new();
+    };
+    family
+        .series
+        .iter()
+        .filter_map(|(labels, metric)| match metric {
+            Metric::Gauge(gauge) => Some((labels.clone(), gauge.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every series of a histogram, with its labels
+pub fn histogram_series(name: &str) -> Vec<(Vec<(String, String)>, Histogram)> {
+    let registry = REGISTRY.lock().unwrap();
+    let Some(family) = registry.get(name) else {
+        return Vec::new();
+    };
+    family
+        .series
+        .iter()
+        .filter_map(|(labels, metric)| match metric {
+            Metric::Histogram(histogram) => Some((labels.clone(), histogram.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Name of the block application latency histogram
+pub const BLOCK_APPLY_SECONDS: &str = "acropolis_block_apply_seconds";
+
+/// Time taken to apply a block, by module
+pub fn block_apply_latency(module: &str) -> Histogram {
+    histogram(
+        BLOCK_APPLY_SECONDS,
+        "Time taken to apply a block to a module's state",
+        &[("module", module)],
+        LATENCY_BUCKETS,
+    )
+}
+
+/// Name of the epoch boundary latency histogram
+pub const EPOCH_BOUNDARY_SECONDS: &str = "acropolis_epoch_boundary_seconds";
+
+/// Time taken by a stage of a module's epoch boundary work
+pub fn epoch_boundary_latency(module: &str, stage: &str) -> Histogram {
+    histogram(
+        EPOCH_BOUNDARY_SECONDS,
+        "Time taken by a stage of a module's epoch boundary work",
+        &[("module", module), ("stage", stage)],
+        EPOCH_BOUNDARY_BUCKETS,
+    )
+}
+
+/// Name of the state query latency histogram
+pub const QUERY_SECONDS: &str = "acropolis_query_seconds";
+
+/// Name of the state query depth gauge
+pub const QUERY_DEPTH: &str = "acropolis_query_depth";
+
+/// Time taken to answer a type of state query, by module
+pub fn query_latency(module: &str, query: &str) -> Histogram {
+    histogram(
+        QUERY_SECONDS,
+        "Time taken to answer a state query",
+        &[("module", module), ("query", query)],
+        LATENCY_BUCKETS,
+    )
+}
+
+/// Number of state queries a module has received but not yet answered
+pub fn query_depth(module: &str) -> Gauge {
+    gauge(
+        QUERY_DEPTH,
+        "State queries received and not yet answered",
+        &[("module", module)],
+    )
+}
+
+/// Collects a variant name from a `Debug` rendering such as
+/// `Accounts(GetAccountInfo { .. })`, failing the write once it has it so
+/// the query's fields are never formatted
+#[derive(Default)]
+struct QueryNameWriter {
+    name: String,
+    in_query: bool,
+}
+
+impl fmt::Write for QueryNameWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match (self.in_query, c) {
+                (false, '(') => self.in_query = true,
+                (false, _) => {}
+                (true, '(' | ')' | '{' | ' ') => return Err(fmt::Error),
+                (true, c) => self.name.push(c),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Name of the query in a state query message, e.g. `GetAccountInfo`
+pub fn query_name(message: &Message) -> String {
+    let Message::StateQuery(query) = message else {
+        return "unknown".to_string();
+    };
+    let mut writer = QueryNameWriter::default();
+    // Stopping early is reported as an error
+    let _ = fmt::write(&mut writer, format_args!("{query:?}"));
+    writer.name
+}
+
+/// Service time by query type, and queue depth, of a module's state queries
+#[derive(Clone)]
+pub struct QueryMetrics {
+    module: Arc<str>,
+    pending: Arc<AtomicU64>,
+    depth: Gauge,
+}
+
+impl QueryMetrics {
+    pub fn new(module: &str) -> Self {
+        Self {
+            module: module.into(),
+            pending: Arc::new(AtomicU64::new(0)),
+            depth: query_depth(module),
+        }
+    }
+
+    /// Start timing a query as it arrives - it is counted as pending, and its
+    /// service time observed, until the returned timer is dropped
+    pub fn start(&self, message: &Message) -> QueryTimer {
+        let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        self.depth.set(pending as f64);
+        QueryTimer {
+            _timer: query_latency(&self.module, &query_name(message)).start_timer(),
+            pending: self.pending.clone(),
+            depth: self.depth.clone(),
+        }
+    }
+}
+
+pub struct QueryTimer {
+    _timer: Timer,
+    pending: Arc<AtomicU64>,
+    depth: Gauge,
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        let pending = self.pending.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.depth.set(pending as f64);
+    }
+}
+
+fn label(labels: &[(String, String)], key: &str) -> String {
+    labels.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone()).unwrap_or_default()
+}
+
+/// Service times of every module's query types, slowest on average first, and
+/// their queue depths
+pub fn query_metrics() -> QueryMetricsMessage {
+    let mut queries: Vec<QueryTypeMetrics> = histogram_series(QUERY_SECONDS)
+        .into_iter()
+        .map(|(labels, histogram)| {
+            let (count, total_seconds) = histogram.totals();
+            QueryTypeMetrics {
+                module: label(&labels, "module"),
+                query: label(&labels, "query"),
+                count,
+                total_seconds,
+            }
+        })
+        .collect();
+    queries.sort_by(|a, b| b.mean_seconds().total_cmp(&a.mean_seconds()));
+
+    let depths = gauge_series(QUERY_DEPTH)
+        .into_iter()
+        .map(|(labels, gauge)| (label(&labels, "module"), gauge.get() as u64))
+        .collect();
+
+    QueryMetricsMessage { queries, depths }
+}
+
+/// Size of some part of a module's state, e.g. the number of UTxOs
+pub fn state_size(module: &str, state: &str) -> Gauge {
+    gauge(
+        "acropolis_state_size",
+        "Number of entries in part of a module's state",
+        &[("module", module), ("state", state)],
+    )
+}
+
+fn format_labels(labels: &[(String, String)], extra: Option<(&str, String)>) -> String {
+    let mut parts: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    if let Some((k, v)) = extra {
+        parts.push(format!("{k}=\"{v}\""));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+/// Render every metric in the Prometheus text exposition format
+pub fn render() -> String {
+    // State history sizes are tracked by the histories themselves
+    for history in state_history::metrics() {
+        state_size(&history.name, "history_entries").set(history.entries as f64);
+        if let Some(bytes) = history.bytes {
+            state_size(&history.name, "history_bytes").set(bytes as f64);
+        }
+    }
+
+    let registry = REGISTRY.lock().unwrap();
+    let mut out = String::new();
+    for (name, family) in registry.iter() {
+        let Some(first) = family.series.values().next() else {
+            continue;
+        };
+        out.push_str(&format!("# HELP {name} {}\n", family.help));
+        out.push_str(&format!("# TYPE {name} {}\n", first.type_name()));
+
+        for (labels, metric) in &family.series {
+            match metric {
+                Metric::Counter(counter) => {
+                    out.push_str(&format!(
+                        "{name}{} {}\n",
+                        format_labels(labels, None),
+                        counter.get()
+                    ));
+                }
+                Metric::Gauge(gauge) => {
+                    out.push_str(&format!(
+                        "{name}{} {}\n",
+                        format_labels(labels, None),
+                        gauge.get()
+                    ));
+                }
+                Metric::Histogram(histogram) => {
+                    let data = histogram.data.lock().unwrap();
+                    let mut cumulative = 0;
+                    for (bound, count) in histogram.bounds.iter().zip(&data.buckets) {
+                        cumulative += count;
+                        let labels = format_labels(labels, Some(("le", bound.to_string())));
+                        out.push_str(&format!("{name}_bucket{labels} {cumulative}\n"));
+                    }
+                    let labels_inf = format_labels(labels, Some(("le", "+Inf".to_string())));
+                    out.push_str(&format!("{name}_bucket{labels_inf} {}\n", data.count));
+                    let labels = format_labels(labels, None);
+                    out.push_str(&format!("{name}_sum{labels} {}\n", data.sum));
+                    out.push_str(&format!("{name}_count{labels} {}\n", data.count));
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_name_and_labels_share_a_series() {
+        let a = counter(
+            "test_shared_total",
+            "Shared",
+            &[("module", "a"), ("kind", "x")],
+        );
+        let b = counter(
+            "test_shared_total",
+            "Shared",
+            &[("kind", "x"), ("module", "a")],
+        );
+        a.inc();
+        b.inc_by(2);
+        assert_eq!(a.get(), 3);
+
+        let text = render();
+        assert!(text.contains("# TYPE test_shared_total counter\n"));
+        assert!(text.contains("test_shared_total{kind=\"x\",module=\"a\"} 3\n"));
+    }
+
+    #[test]
+    fn histograms_render_cumulative_buckets() {
+        let h = histogram("test_latency_seconds", "Latency", &[], &[0.1, 1.0]);
+        h.observe(0.05);
+        h.observe(0.5);
+        h.observe(5.0);
+
+        let text = render();
+        assert!(text.contains("test_latency_seconds_bucket{le=\"0.1\"} 1\n"));
+        assert!(text.contains("test_latency_seconds_bucket{le=\"1\"} 2\n"));
+        assert!(text.contains("test_latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("test_latency_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn histogram_series_share_the_recorded_totals() {
+        let h = histogram("test_series_seconds", "Series", &[("module", "a")], &[1.0]);
+        h.observe(0.5);
+        h.observe(2.0);
+
+        let series = histogram_series("test_series_seconds");
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].0, vec![("module".to_string(), "a".to_string())]);
+        assert_eq!(series[0].1.totals(), (2, 2.5));
+        assert!(histogram_series("test_missing_seconds").is_empty());
+    }
+
+    #[test]
+    fn epoch_boundary_latency_is_labelled_by_stage() {
+        epoch_boundary_latency("test-epoch-module", "rewards").observe(45.0);
+        drop(epoch_boundary_latency("test-epoch-module", "enter-epoch").start_timer());
+
+        let text = render();
+        assert!(text.contains(
+            "acropolis_epoch_boundary_seconds_bucket{module=\"test-epoch-module\",stage=\"rewards\",le=\"60\"} 1\n"
+        ));
+        assert!(text.contains(
+            "acropolis_epoch_boundary_seconds_count{module=\"test-epoch-module\",stage=\"enter-epoch\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn query_name_is_the_inner_variant() {
+        use crate::queries::accounts::AccountsStateQuery;
+        use crate::queries::epochs::EpochsStateQuery;
+        use crate::{messages::StateQuery, PoolId};
+
+        let unit = Message::StateQuery(StateQuery::Accounts(
+            AccountsStateQuery::GetOptimalPoolSizing,
+        ));
+        assert_eq!(query_name(&unit), "GetOptimalPoolSizing");
+
+        let with_fields = Message::StateQuery(StateQuery::Accounts(
+            AccountsStateQuery::GetPoolDelegators {
+                pool_operator: PoolId::default(),
+            },
+        ));
+        assert_eq!(query_name(&with_fields), "GetPoolDelegators");
+
+        let other = Message::StateQuery(StateQuery::Epochs(EpochsStateQuery::GetLatestEpoch));
+        assert_eq!(query_name(&other), "GetLatestEpoch");
+        assert_eq!(query_name(&Message::None), "unknown");
+    }
+
+    #[test]
+    fn query_metrics_track_depth_and_latency_by_query() {
+        use crate::messages::StateQuery;
+        use crate::queries::epochs::EpochsStateQuery;
+
+        let metrics = QueryMetrics::new("test-query-module");
+        let message = Message::StateQuery(StateQuery::Epochs(EpochsStateQuery::GetLatestEpoch));
+
+        let first = metrics.start(&message);
+        let second = metrics.start(&message);
+        assert_eq!(query_depth("test-query-module").get(), 2.0);
+        drop(first);
+        drop(second);
+        assert_eq!(query_depth("test-query-module").get(), 0.0);
+
+        let (count, _) = query_latency("test-query-module", "GetLatestEpoch").totals();
+        assert_eq!(count, 2);
+
+        let published = query_metrics();
+        assert!(published.queries.iter().any(|q| {
+            q.module == "test-query-module" && q.query == "GetLatestEpoch" && q.count == 2
+        }));
+        assert_eq!(published.depths.get("test-query-module"), Some(&0));
+    }
+}
@@ -36,6 +36,11 @@ impl ProtocolParams {
         self.shelley.as_ref().map(|s| s.protocol_params.protocol_version.major)
     }
 
+    /// Era implied by the protocol version, Byron until Shelley parameters exist
+    pub fn era(&self) -> Era {
+        self.major_protocol_version().map(Era::from_protocol_major).unwrap_or(Era::Byron)
+    }
+
     pub fn protocol_version(&self) -> Option<ProtocolVersion> {
         self.shelley.as_ref().map(|s| s.protocol_params.protocol_version.clone())
     }
@@ -547,11 +552,15 @@ impl Nonces {
         }
     }
 
+    /// Returns true if a block at `slot` still contributes to the candidate nonce, i.e. it
+    /// is before the stability window at the end of its epoch. Without `params` (e.g. after
+    /// a snapshot bootstrap, until the next parameters arrive) the windows are computed
+    /// from the genesis values.
     pub fn randomness_stability_window(
         era: Era,
         slot: u64,
         genesis: &GenesisValues,
-        params: &PraosParams,
+        params: Option<&PraosParams>,
     ) -> bool {
         let (epoch, _) = genesis.slot_to_epoch(slot);
         let next_epoch_first_slot = genesis.epoch_to_first_slot(epoch + 1);
@@ -559,9 +568,11 @@ impl Nonces {
         // For Praos in Babbage (just as in all TPraos eras) we use the
         // smaller (3k/f vs 4k/f slots) stability window here for
         // backwards-compatibility.
-        let window = match era {
-            Era::Conway => params.randomness_stabilization_window,
-            _ => params.stability_window,
+        let window = match (era, params) {
+            (Era::Conway, Some(params)) => params.randomness_stabilization_window,
+            (Era::Conway, None) => genesis.randomness_stabilization_window(),
+            (_, Some(params)) => params.stability_window,
+            (_, None) => genesis.stability_window(),
         };
 
         slot + window < next_epoch_first_slot
@@ -39,6 +39,7 @@ pub enum AccountsStateQuery {
     GetPoolsLiveStakes { pools_operators: Vec<PoolId> },
     GetPoolDelegators { pool_operator: PoolId },
     GetPoolLiveStake { pool_operator: PoolId },
+    GetNetworkStats,
 
     // Dreps related queries
     GetDrepDelegators { drep: DRepChoice },
@@ -71,6 +72,7 @@ pub enum AccountsStateQueryResponse {
     PoolsLiveStakes(Vec<u64>),
     PoolDelegators(PoolDelegators),
     PoolLiveStake(PoolLiveStakeInfo),
+    NetworkStats(Option<NetworkStats>),
 
     // DReps-related responses
     DrepDelegators(DrepDelegators),
@@ -140,6 +142,8 @@ pub struct AccountWithdrawal {
     pub tx_identifier: TxIdentifier,
     #[n(1)]
     pub amount: u64,
+    #[n(2)]
+    pub epoch: u32,
 }
 
 #[derive(
@@ -162,6 +166,38 @@ pub struct OptimalPoolSizing {
     pub nopt: u64,
 }
 
+/// Network-wide supply and stake totals
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkStats {
+    /// Maximum supply of lovelace, from the Shelley genesis
+    pub max_supply: u64,
+
+    /// Lovelace released from the reserves (max supply - reserves)
+    pub total_supply: u64,
+
+    /// Lovelace held in UTxOs (total supply - treasury - deposits - rewards)
+    pub circulating_supply: u64,
+
+    pub reserves: u64,
+    pub treasury: u64,
+    pub deposits: u64,
+
+    /// Unwithdrawn rewards across all stake addresses
+    pub rewards: u64,
+
+    /// Current stake (UTxO value and rewards) of all delegated stake addresses
+    pub live_stake: u64,
+
+    /// Stake in the latest snapshot
+    pub active_stake: u64,
+
+    /// Number of registered pools
+    pub pools: u64,
+
+    /// Number of stake addresses delegated to a pool
+    pub delegators: u64,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PoolDelegators {
     pub delegators: Vec<(StakeAddress, u64)>,
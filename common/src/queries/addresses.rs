@@ -13,6 +13,9 @@ pub enum AddressStateQuery {
     // Accounts related queries
     GetAddressesTotals { addresses: Vec<ShelleyAddress> },
     GetAddressesUTxOs { addresses: Vec<ShelleyAddress> },
+
+    // Store related queries
+    GetStoreStats,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -24,5 +27,30 @@ pub enum AddressStateQueryResponse {
     // Accounts related queries
     AddressesTotals(AddressTotals),
     AddressesUTxOs(Vec<UTxOIdentifier>),
+
+    // Store related queries
+    StoreStats(AddressStoreStats),
     Error(QueryError),
 }
+
+/// Size of the persisted address index, and what is still held in memory
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AddressStoreStats {
+    /// Approximate number of addresses with persisted UTxOs
+    pub utxo_addresses: u64,
+
+    /// Approximate number of addresses with persisted transactions
+    pub transaction_addresses: u64,
+
+    /// Approximate number of addresses with persisted totals
+    pub totals_addresses: u64,
+
+    /// Immutable blocks cached until the end of their epoch is persisted
+    pub pending_blocks: u64,
+
+    /// Blocks still within the rollback window
+    pub volatile_blocks: u64,
+
+    /// Last epoch persisted across all the indexes
+    pub last_epoch_stored: Option<u64>,
+}
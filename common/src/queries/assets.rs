@@ -22,12 +22,34 @@ pub type PolicyAssets = Vec<PolicyAsset>;
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum AssetsStateQuery {
     GetAssetsList,
-    GetAssetInfo { policy: PolicyId, name: AssetName },
-    GetAssetHistory { policy: PolicyId, name: AssetName },
-    GetPolicyIdAssets { policy: PolicyId },
-    GetAssetAddresses { policy: PolicyId, name: AssetName },
-    GetAssetTransactions { policy: PolicyId, name: AssetName },
-    GetAssetsMetadata { assets: NativeAssets },
+    GetAssetInfo {
+        policy: PolicyId,
+        name: AssetName,
+    },
+    GetAssetHistory {
+        policy: PolicyId,
+        name: AssetName,
+    },
+    /// Supply at the end of an epoch, from the mint and burn history
+    GetAssetSupplyAtEpoch {
+        policy: PolicyId,
+        name: AssetName,
+        epoch: u64,
+    },
+    GetPolicyIdAssets {
+        policy: PolicyId,
+    },
+    GetAssetAddresses {
+        policy: PolicyId,
+        name: AssetName,
+    },
+    GetAssetTransactions {
+        policy: PolicyId,
+        name: AssetName,
+    },
+    GetAssetsMetadata {
+        assets: NativeAssets,
+    },
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -35,6 +57,7 @@ pub enum AssetsStateQueryResponse {
     AssetsList(AssetList),
     AssetInfo(AssetInfo),
     AssetHistory(AssetHistory),
+    AssetSupplyAtEpoch(u64),
     AssetAddresses(AssetAddresses),
     AssetTransactions(AssetTransactions),
     PolicyIdAssets(PolicyAssets),
@@ -1,3 +1,4 @@
+use crate::messages::RawTxsMessage;
 use crate::queries::errors::QueryError;
 use crate::{
     queries::misc::Order,
@@ -59,6 +60,13 @@ pub enum BlocksStateQuery {
         epoch: u64,
         slot: u64,
     },
+    GetBlockCBOR {
+        block_key: BlockKey,
+    },
+    GetBlocksCBORByNumberRange {
+        min_number: u64,
+        max_number: u64,
+    },
     GetBlockTransactions {
         block_key: BlockKey,
         limit: u64,
@@ -71,6 +79,10 @@ pub enum BlocksStateQuery {
         skip: u64,
         order: Order,
     },
+    /// All transactions of a block, as the block unpacker would have published them
+    GetBlockBody {
+        block_key: BlockKey,
+    },
     GetBlockInvolvedAddresses {
         block_key: BlockKey,
         limit: u64,
@@ -111,8 +123,11 @@ pub enum BlocksStateQueryResponse {
     BlockBySlot(BlockInfo),
     BlockByHash(BlockInfo),
     BlockByEpochSlot(BlockInfo),
+    BlockCBOR(BlockCBOR),
+    BlocksCBORByNumberRange(Vec<BlockCBOR>),
     BlockTransactions(BlockTransactions),
     BlockTransactionsCBOR(BlockTransactionsCBOR),
+    BlockBody(RawTxsMessage),
     BlockInvolvedAddresses(BlockInvolvedAddresses),
     BlockHashes(BlockHashes),
     BlockHashesByNumberRange(Vec<BlockHash>),
@@ -224,6 +239,17 @@ pub struct BlockTransaction {
     pub cbor: Vec<u8>,
 }
 
+/// Raw block as stored, with the position needed to reference it
+#[serde_as]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BlockCBOR {
+    pub hash: BlockHash,
+    pub number: u64,
+    pub slot: u64,
+    #[serde_as(as = "Hex")]
+    pub cbor: Vec<u8>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BlockInvolvedAddresses {
     pub addresses: Vec<BlockInvolvedAddress>,
@@ -0,0 +1,366 @@
+//! Timeouts, retries and circuit breaking for state queries.
+//!
+//! Every query sent through [`request`] is given up on after a timeout, and
+//! retried a few times. When a topic keeps failing its circuit opens, and
+//! queries on it fail straight away for a while instead of each waiting for the
+//! timeout - so one stuck module only degrades the endpoints which need it.
+//! After that a single trial query is let through, closing the circuit again
+//! if it succeeds.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use caryatid_sdk::Context;
+use tracing::{info, warn};
+
+use crate::{
+    configuration::deserialize_duration,
+    messages::Message,
+    metrics::{self, Counter, Gauge},
+    queries::errors::QueryError,
+};
+
+/// How queries on a topic are timed out, retried and cut off
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct QueryPolicy {
+    /// How long to wait for each attempt
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+
+    /// Further attempts after a timeout or bus error
+    pub retries: u32,
+
+    /// Failed queries in a row which open the circuit
+    pub failure_threshold: u32,
+
+    /// How long the circuit stays open before a trial query is let through
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub open_duration: Duration,
+}
+
+impl Default for QueryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 1,
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A policy for one topic, overriding the default
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TopicQueryPolicy {
+    pub topic: String,
+
+    /// Anything not given takes the built in default, not the `[global.query]` one
+    #[serde(flatten)]
+    pub policy: QueryPolicy,
+}
+
+/// Query policies for the process, from `[global.query]`
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct QueryPolicies {
+    #[serde(flatten)]
+    pub default: QueryPolicy,
+
+    /// Overrides for particular topics, as `[[global.query.topic]]`
+    #[serde(default, rename = "topic")]
+    pub topics: Vec<TopicQueryPolicy>,
+}
+
+impl QueryPolicies {
+    fn for_topic(&self, topic: &str) -> &QueryPolicy {
+        self.topics
+            .iter()
+            .find(|policy| policy.topic == topic)
+            .map_or(&self.default, |policy| &policy.policy)
+    }
+}
+
+static POLICIES: OnceLock<QueryPolicies> = OnceLock::new();
+
+/// Set the query policies, before any modules are started
+pub fn set_policies(policies: QueryPolicies) -> Result<()> {
+    POLICIES.set(policies).map_err(|_| anyhow!("Query policies already set"))
+}
+
+fn policies() -> &'static QueryPolicies {
+    POLICIES.get_or_init(QueryPolicies::default)
+}
+
+/// State of a topic's circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    /// Queries go through, counting failures in a row
+    Closed { failures: u32 },
+
+    /// Queries fail straight away until the given time
+    Open { until: Instant },
+
+    /// A trial query is in flight - others fail straight away until it is
+    /// answered, or until the given time if it never is
+    HalfOpen { until: Instant },
+}
+
+impl CircuitState {
+    /// Value of the state gauge - 0 closed, 1 half open, 2 open
+    fn gauge_value(&self) -> f64 {
+        match self {
+            CircuitState::Closed { .. } => 0.0,
+            CircuitState::HalfOpen { .. } => 1.0,
+            CircuitState::Open { .. } => 2.0,
+        }
+    }
+}
+
+/// Circuit breaker for one topic
+#[derive(Debug)]
+struct Circuit {
+    policy: QueryPolicy,
+    state: CircuitState,
+}
+
+impl Circuit {
+    fn new(policy: QueryPolicy) -> Self {
+        Self {
+            policy,
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    /// Whether a query can be sent now
+    fn admit(&mut self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => true,
+            CircuitState::Open { until } | CircuitState::HalfOpen { until } if now < until => false,
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => {
+                let attempts = self.policy.retries + 1;
+                self.state = CircuitState::HalfOpen {
+                    until: now + self.policy.timeout * attempts,
+                };
+                true
+            }
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.state = CircuitState::Closed { failures: 0 };
+    }
+
+    fn failed(&mut self, now: Instant) {
+        self.state = match self.state {
+            CircuitState::Closed { failures } if failures + 1 < self.policy.failure_threshold => {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            CircuitState::Open { until } => CircuitState::Open { until },
+            _ => CircuitState::Open {
+                until: now + self.policy.open_duration,
+            },
+        };
+    }
+}
+
+/// A topic's circuit and its metrics
+struct TopicCircuit {
+    topic: String,
+    circuit: Mutex<Circuit>,
+    state: Gauge,
+    timeouts: Counter,
+    retries: Counter,
+    rejected: Counter,
+}
+
+impl TopicCircuit {
+    fn new(topic: &str) -> Self {
+        let labels = [("topic", topic)];
+        let circuit = Self {
+            topic: topic.to_string(),
+            circuit: Mutex::new(Circuit::new(policies().for_topic(topic).clone())),
+            state: metrics::gauge(
+                "acropolis_query_circuit_state",
+                "State of a query topic's circuit breaker - 0 closed, 1 half open, 2 open",
+                &labels,
+            ),
+            timeouts: metrics::counter(
+                "acropolis_query_timeouts_total",
+                "Query attempts which timed out",
+                &labels,
+            ),
+            retries: metrics::counter(
+                "acropolis_query_retries_total",
+                "Query attempts retried after a timeout or bus error",
+                &labels,
+            ),
+            rejected: metrics::counter(
+                "acropolis_query_rejected_total",
+                "Queries failed straight away because the circuit was open",
+                &labels,
+            ),
+        };
+        circuit.state.set(0.0);
+        circuit
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut Circuit) -> T) -> T {
+        let mut circuit = self.circuit.lock().unwrap();
+        let was_open = !matches!(circuit.state, CircuitState::Closed { .. });
+        let result = f(&mut circuit);
+        let is_open = !matches!(circuit.state, CircuitState::Closed { .. });
+        self.state.set(circuit.state.gauge_value());
+        if is_open != was_open {
+            if is_open {
+                warn!("Query circuit for {} opened", self.topic);
+            } else {
+                info!("Query circuit for {} closed", self.topic);
+            }
+        }
+        result
+    }
+}
+
+static CIRCUITS: LazyLock<Mutex<HashMap<String, Arc<TopicCircuit>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn topic_circuit(topic: &str) -> Arc<TopicCircuit> {
+    CIRCUITS
+        .lock()
+        .unwrap()
+        .entry(topic.to_string())
+        .or_insert_with(|| Arc::new(TopicCircuit::new(topic)))
+        .clone()
+}
+
+/// Send a query on `topic` under its policy, returning the raw response
+pub async fn request(
+    context: &Arc<Context<Message>>,
+    topic: &str,
+    request_msg: Arc<Message>,
+) -> Result<Arc<Message>, QueryError> {
+    let circuit = topic_circuit(topic);
+    if !circuit.update(|c| c.admit(Instant::now())) {
+        circuit.rejected.inc();
+        return Err(QueryError::unavailable(format!(
+            "{topic} is not responding"
+        )));
+    }
+
+    let policy = circuit.circuit.lock().unwrap().policy.clone();
+    let mut attempt = 0;
+    loop {
+        let error = match tokio::time::timeout(
+            policy.timeout,
+            context.message_bus.request(topic, request_msg.clone()),
+        )
+        .await
+        {
+            Ok(Ok(response)) => {
+                circuit.update(Circuit::succeeded);
+                return Ok(response);
+            }
+            Ok(Err(e)) => format!("{e:#}"),
+            Err(_) => {
+                circuit.timeouts.inc();
+                format!("no response within {:?}", policy.timeout)
+            }
+        };
+
+        if attempt >= policy.retries {
+            circuit.update(|c| c.failed(Instant::now()));
+            return Err(QueryError::unavailable(format!("{topic}: {error}")));
+        }
+        attempt += 1;
+        circuit.retries.inc();
+        warn!("Query on {topic} failed ({error}), retrying");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit() -> Circuit {
+        Circuit::new(QueryPolicy {
+            timeout: Duration::from_secs(1),
+            retries: 1,
+            failure_threshold: 2,
+            open_duration: Duration::from_secs(10),
+        })
+    }
+
+    #[test]
+    fn circuit_opens_after_failures_in_a_row() {
+        let mut circuit = circuit();
+        let now = Instant::now();
+        circuit.failed(now);
+        circuit.succeeded();
+        circuit.failed(now);
+        assert!(circuit.admit(now));
+
+        circuit.failed(now);
+        assert!(!circuit.admit(now));
+        assert!(!circuit.admit(now + Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn circuit_lets_one_trial_through_once_open_duration_passes() {
+        let mut circuit = circuit();
+        let now = Instant::now();
+        circuit.failed(now);
+        circuit.failed(now);
+
+        let later = now + Duration::from_secs(10);
+        assert!(circuit.admit(later));
+        assert!(!circuit.admit(later));
+
+        // A failed trial opens it again
+        circuit.failed(later);
+        assert!(!circuit.admit(later + Duration::from_secs(9)));
+
+        // A successful one closes it
+        let much_later = later + Duration::from_secs(10);
+        assert!(circuit.admit(much_later));
+        circuit.succeeded();
+        assert!(circuit.admit(much_later));
+        assert!(circuit.admit(much_later));
+    }
+
+    #[test]
+    fn abandoned_trial_is_replaced() {
+        let mut circuit = circuit();
+        let now = Instant::now();
+        circuit.failed(now);
+        circuit.failed(now);
+
+        let trial = now + Duration::from_secs(10);
+        assert!(circuit.admit(trial));
+        assert!(!circuit.admit(trial + Duration::from_secs(1)));
+        assert!(circuit.admit(trial + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn topics_can_override_the_default_policy() {
+        let policies: QueryPolicies = serde_json::from_value(serde_json::json!({
+            "timeout": "5s",
+            "topic": [{ "topic": "cardano.query.accounts", "timeout": "2m", "retries": 0 }],
+        }))
+        .unwrap();
+
+        assert_eq!(policies.default.timeout, Duration::from_secs(5));
+        assert_eq!(policies.default.retries, 1);
+        let accounts = policies.for_topic("cardano.query.accounts");
+        assert_eq!(accounts.timeout, Duration::from_secs(120));
+        assert_eq!(accounts.retries, 0);
+        assert_eq!(policies.for_topic("cardano.query.pools"), &policies.default);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::queries::errors::QueryError;
+use crate::BlockHash;
+
+pub const DEFAULT_CONSENSUS_QUERY_TOPIC: (&str, &str) =
+    ("consensus-query-topic", "cardano.query.consensus");
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ConsensusStateQuery {
+    GetChainTips,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ConsensusStateQueryResponse {
+    ChainTips(Vec<ChainTip>),
+    Error(QueryError),
+}
+
+/// A candidate chain tip in the consensus volatile window
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChainTip {
+    pub hash: BlockHash,
+
+    /// Block number, which is also the length of the chain
+    pub number: u64,
+
+    pub slot: u64,
+
+    /// Whether this is the tip of the favoured chain
+    pub favoured: bool,
+
+    /// Number of the block where this chain leaves the favoured chain - its own
+    /// number if it is the favoured chain
+    pub fork_point: u64,
+
+    /// Number of blocks on this chain since the fork point
+    pub branch_length: u64,
+
+    /// Blocks per slot on this chain across the volatile window
+    pub density: f64,
+
+    /// Address of the peer which first offered the tip, if known
+    pub origin_peer: Option<String>,
+}
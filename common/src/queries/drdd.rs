@@ -0,0 +1,25 @@
+use crate::{queries::errors::QueryError, DRepChoice, Lovelace};
+
+pub const DEFAULT_DRDD_QUERY_TOPIC: (&str, &str) = ("drdd-state-query-topic", "cardano.query.drdd");
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum DRDDStateQuery {
+    GetDRepVotingPower { drep: DRepChoice },
+    GetVotingPowerBreakdown,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum DRDDStateQueryResponse {
+    DRepVotingPower(Lovelace),
+    VotingPowerBreakdown(DRepVotingPowerBreakdown),
+    Error(QueryError),
+}
+
+/// Total voting power of all DReps, by the kind of DRep it is delegated to
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DRepVotingPowerBreakdown {
+    pub key: Lovelace,
+    pub script: Lovelace,
+    pub abstain: Lovelace,
+    pub no_confidence: Lovelace,
+}
@@ -1,5 +1,8 @@
 use crate::queries::errors::QueryError;
-use crate::{messages::EpochActivityMessage, protocol_params::ProtocolParams, PoolId};
+use crate::{
+    era_summary::EraSummary, messages::EpochActivityMessage, protocol_params::ProtocolParams,
+    PoolId,
+};
 
 pub const DEFAULT_EPOCHS_QUERY_TOPIC: (&str, &str) =
     ("epochs-state-query-topic", "cardano.query.epochs");
@@ -14,13 +17,33 @@ pub enum EpochsStateQuery {
     GetLatestEpoch,
 
     // Served from historical epochs state
-    GetEpochInfo { epoch_number: u64 },
-    GetNextEpochs { epoch_number: u64 },
-    GetPreviousEpochs { epoch_number: u64 },
+    GetEpochInfo {
+        epoch_number: u64,
+    },
+    GetNextEpochs {
+        epoch_number: u64,
+        limit: u64,
+        skip: u64,
+    },
+    GetPreviousEpochs {
+        epoch_number: u64,
+        limit: u64,
+        skip: u64,
+    },
+    GetEpochParameters {
+        epoch_number: u64,
+    },
 
-    GetEpochStakeDistribution { epoch_number: u64 },
-    GetEpochStakeDistributionByPool { epoch_number: u64 },
-    GetLatestEpochBlocksMintedByPool { spo_id: PoolId },
+    GetEpochStakeDistribution {
+        epoch_number: u64,
+    },
+    GetEpochStakeDistributionByPool {
+        epoch_number: u64,
+    },
+    GetLatestEpochBlocksMintedByPool {
+        spo_id: PoolId,
+    },
+    GetEraSummaries,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -29,9 +52,11 @@ pub enum EpochsStateQueryResponse {
     EpochInfo(EpochInfo),
     NextEpochs(NextEpochs),
     PreviousEpochs(PreviousEpochs),
+    EpochParameters(EpochParameters),
     EpochStakeDistribution(EpochStakeDistribution),
     EpochStakeDistributionByPool(EpochStakeDistributionByPool),
     LatestEpochBlocksMintedByPool(u64),
+    EraSummaries(Vec<EraSummary>),
     Error(QueryError),
 }
 
@@ -45,6 +70,11 @@ pub struct LatestEpochParameters {
     pub parameters: ProtocolParams,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EpochParameters {
+    pub parameters: ProtocolParams,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct EpochInfo {
     pub epoch: EpochActivityMessage,
@@ -23,6 +23,18 @@ pub enum QueryError {
     /// Query variant is not implemented yet
     #[error("Query not implemented: {query}")]
     NotImplemented { query: String },
+
+    /// State is not available yet, because the node has not synced far enough
+    #[error("Not yet synced: {message}")]
+    NotSynced { message: String },
+
+    /// The requested resource was removed by a chain rollback
+    #[error("Rolled back: {resource}")]
+    RolledBack { resource: String },
+
+    /// The module answering the query is not responding
+    #[error("Unavailable: {message}")]
+    Unavailable { message: String },
 }
 
 impl QueryError {
@@ -55,6 +67,24 @@ impl QueryError {
             query: query.into(),
         }
     }
+
+    pub fn not_synced(message: impl Into<String>) -> Self {
+        Self::NotSynced {
+            message: message.into(),
+        }
+    }
+
+    pub fn rolled_back(resource: impl Into<String>) -> Self {
+        Self::RolledBack {
+            resource: resource.into(),
+        }
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::Unavailable {
+            message: message.into(),
+        }
+    }
 }
 
 impl From<anyhow::Error> for QueryError {
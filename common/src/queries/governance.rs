@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::queries::errors::QueryError;
+use crate::queries::{drdd::DRepVotingPowerBreakdown, errors::QueryError};
 use crate::{
     Anchor, DRepCredential, GovActionId, Lovelace, ProposalProcedure, StakeAddress, TxHash,
     TxIdentifier, Vote, Voter, VotingProcedure,
@@ -16,6 +16,7 @@ pub enum GovernanceStateQuery {
     GetDRepsList,
     GetDRepInfoWithDelegators { drep_credential: DRepCredential },
     GetDRepDelegators { drep_credential: DRepCredential },
+    GetDRepDelegatorStakes { drep_credential: DRepCredential },
     GetDRepMetadata { drep_credential: DRepCredential },
     GetDRepUpdates { drep_credential: DRepCredential },
     GetDRepVotes { drep_credential: DRepCredential },
@@ -33,6 +34,7 @@ pub enum GovernanceStateQueryResponse {
     DRepsList(DRepsList),
     DRepInfoWithDelegators(DRepInfoWithDelegators),
     DRepDelegators(DRepDelegatorAddresses),
+    DRepDelegatorStakes(DRepDelegatorStakes),
     DRepMetadata(Option<Option<Anchor>>),
     DRepUpdates(DRepUpdates),
     DRepVotes(DRepVotes),
@@ -70,6 +72,15 @@ pub struct DRepDelegatorAddresses {
     pub addresses: Vec<StakeAddress>,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DRepDelegatorStakes {
+    /// Delegators in the order they delegated, with their balances (UTxOs and rewards)
+    pub delegators: Vec<(StakeAddress, Lovelace)>,
+
+    /// Voting power of all DReps as of the latest epoch boundary, if drdd-state stores it
+    pub voting_power: Option<DRepVotingPowerBreakdown>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DRepUpdates {
     pub updates: Vec<DRepUpdateEvent>,
@@ -98,17 +109,25 @@ pub struct DRepVotes {
 pub struct VoteRecord {
     pub tx_hash: TxHash,
     pub vote_index: u32,
+    pub proposal: GovActionId,
     pub vote: Vote,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProposalsList {
-    pub proposals: Vec<GovActionId>,
+    /// Proposals in the order they were submitted
+    pub proposals: Vec<ProposalProcedure>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProposalInfo {
     pub procedure: ProposalProcedure,
+    pub submitted_epoch: u64,
+    /// Last epoch the proposal can be voted on before it expires
+    pub expiration: u64,
+    pub ratified_epoch: Option<u64>,
+    pub enacted_epoch: Option<u64>,
+    pub expired_epoch: Option<u64>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -6,6 +6,9 @@ pub mod accounts;
 pub mod addresses;
 pub mod assets;
 pub mod blocks;
+pub mod circuit;
+pub mod consensus;
+pub mod drdd;
 pub mod epochs;
 pub mod errors;
 pub mod governance;
@@ -1,15 +1,20 @@
 use crate::queries::errors::QueryError;
 
+pub const DEFAULT_NETWORK_QUERY_TOPIC: (&str, &str) =
+    ("network-query-topic", "cardano.query.network");
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum NetworkStateQuery {
     GetNetworkInformation,
     GetEraSummary,
+    GetPeerScores,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum NetworkStateQueryResponse {
     NetworkInformation(NetworkInformation),
     EraSummary(EraSummary),
+    PeerScores(Vec<PeerScore>),
     Error(QueryError),
 }
 
@@ -18,3 +23,31 @@ pub struct NetworkInformation {}
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct EraSummary {}
+
+/// Observed performance of an upstream peer
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PeerScore {
+    /// Peer address (host:port)
+    pub address: String,
+
+    /// Whether the peer is currently connected (hot)
+    pub connected: bool,
+
+    /// Overall score, 0 (worst) to 100 (best)
+    pub score: f64,
+
+    /// Smoothed block fetch latency in milliseconds, if any blocks were fetched
+    pub latency_ms: Option<u64>,
+
+    /// Number of blocks fetched from this peer
+    pub blocks_fetched: u64,
+
+    /// Number of headers received from this peer
+    pub headers_received: u64,
+
+    /// Number of headers which were already known from another peer
+    pub useless_headers: u64,
+
+    /// Number of times the peer has disconnected
+    pub disconnects: u64,
+}
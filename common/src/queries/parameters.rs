@@ -1,5 +1,6 @@
 use crate::protocol_params::ProtocolParams;
 use crate::queries::errors::QueryError;
+use crate::{CostModels, Era, GenesisKeyhash, GovActionId, ProtocolParamUpdate};
 
 pub const DEFAULT_PARAMETERS_QUERY_TOPIC: (&str, &str) =
     ("parameters-state-query-topic", "cardano.query.parameters");
@@ -9,6 +10,10 @@ pub enum ParametersStateQuery {
     GetLatestEpochParameters,
     GetEpochParameters { epoch_number: u64 },
     GetNetworkName,
+    GetCostModels,
+    GetPendingParameterUpdates,
+    GetFutureParameters,
+    GetParameterChangeLog,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -16,6 +21,10 @@ pub enum ParametersStateQueryResponse {
     LatestEpochParameters(ProtocolParams),
     EpochParameters(ProtocolParams),
     NetworkName(String),
+    CostModels(CostModels),
+    PendingParameterUpdates(PendingParameterUpdates),
+    FutureParameters(FutureParameters),
+    ParameterChangeLog(Vec<ParameterChangeRecord>),
     Error(QueryError),
 }
 
@@ -23,3 +32,75 @@ pub enum ParametersStateQueryResponse {
 pub struct LatestParameters {
     pub parameters: ProtocolParams,
 }
+
+/// Parameter updates submitted on chain but not yet enacted
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PendingParameterUpdates {
+    /// Pre-Conway update proposals from the genesis delegates
+    pub update_proposals: Vec<PendingUpdateProposal>,
+
+    /// Conway parameter change governance actions which have not been ratified yet
+    pub governance_actions: Vec<PendingGovernanceAction>,
+}
+
+/// A pre-Conway update, with the genesis keys currently proposing it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PendingUpdateProposal {
+    /// Epoch at whose start the update is applied if it reaches quorum
+    pub enactment_epoch: u64,
+    pub proposers: Vec<GenesisKeyhash>,
+    pub update_quorum: u32,
+    pub parameter_update: Box<ProtocolParamUpdate>,
+}
+
+impl PendingUpdateProposal {
+    pub fn reaches_quorum(&self) -> bool {
+        self.proposers.len() as u64 >= self.update_quorum as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PendingGovernanceAction {
+    pub action_id: GovActionId,
+    /// Epoch the action was submitted in
+    pub proposed_epoch: u64,
+    pub parameter_update: Box<ProtocolParamUpdate>,
+}
+
+/// Parameters for the next epoch if nothing else is proposed or ratified. Only
+/// update proposals which already reach quorum are applied, since Conway actions
+/// depend on the votes counted at the boundary.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FutureParameters {
+    pub epoch: u64,
+    pub parameters: ProtocolParams,
+}
+
+/// What caused a set of parameter changes at an epoch boundary
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ParameterChangeCause {
+    /// Genesis parameters of a new era
+    Genesis(Era),
+    /// Pre-Conway update proposal which reached quorum
+    UpdateProposal { proposers: Vec<GenesisKeyhash> },
+    /// Enacted Conway governance action
+    GovernanceAction(GovActionId),
+    /// Pre-Conway genesis key delegation certificates which fell due
+    GenesisKeyDelegation { genesis_keys: Vec<GenesisKeyhash> },
+}
+
+/// A single changed parameter, addressed by its path in [`ProtocolParams`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ParameterFieldChange {
+    pub path: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+/// Audit log entry for the parameter changes applied at the start of `epoch`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ParameterChangeRecord {
+    pub epoch: u64,
+    pub causes: Vec<ParameterChangeCause>,
+    pub changes: Vec<ParameterFieldChange>,
+}
@@ -1,4 +1,5 @@
 use crate::queries::errors::QueryError;
+use crate::queries::misc::Order;
 use crate::{
     queries::governance::VoteRecord, rational_number::RationalNumber, PoolEpochState, PoolId,
     PoolMetadata, PoolRegistration, PoolRetirement, PoolUpdateEvent, Relay, StakeAddress,
@@ -36,9 +37,16 @@ pub enum PoolsStateQuery {
     GetPoolRelays {
         pool_id: PoolId,
     },
+    GetPoolsRelays,
     GetPoolDelegators {
         pool_id: PoolId,
     },
+    GetPoolDelegatorsPage {
+        pool_id: PoolId,
+        limit: u64,
+        skip: u64,
+        order: Order,
+    },
     GetPoolTotalBlocksMinted {
         pool_id: PoolId,
     },
@@ -70,6 +78,7 @@ pub enum PoolsStateQueryResponse {
     PoolHistory(Vec<PoolEpochState>),
     PoolMetadata(PoolMetadata),
     PoolRelays(Vec<Relay>),
+    PoolsRelays(Vec<PoolRelays>),
     PoolDelegators(PoolDelegators),
     PoolTotalBlocksMinted(u64),
     // Vector of Block Heights
@@ -86,6 +95,13 @@ pub struct PoolsListWithInfo {
     pub pools: Vec<(PoolId, PoolRegistration)>,
 }
 
+/// Relays a registered pool advertises
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PoolRelays {
+    pub pool_id: PoolId,
+    pub relays: Vec<Relay>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PoolActiveStakeInfo {
     pub active_stake: u64,
@@ -1,14 +1,24 @@
 use crate::queries::errors::QueryError;
+use crate::queries::transactions::TransactionRedeemers;
+use crate::{
+    DataHash, DatumHash, ExUnits, NativeScript, RedeemerTag, ScriptHash, ScriptLang, TxIdentifier,
+};
+
+pub const DEFAULT_SCRIPTS_QUERY_TOPIC: (&str, &str) =
+    ("scripts-state-query-topic", "cardano.query.scripts");
+
+pub type ScriptsList = Vec<ScriptHash>;
+pub type ScriptRedeemers = Vec<ScriptRedeemer>;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum ScriptsStateQuery {
     GetScriptsList,
-    GetScriptInfo,
-    GetScriptJSON,
-    GetScriptCBOR,
-    GetScriptRedeemers,
-    GetScriptDatumJSON,
-    GetScriptDatumCBOR,
+    GetScriptInfo { script_hash: ScriptHash },
+    GetScriptJSON { script_hash: ScriptHash },
+    GetScriptCBOR { script_hash: ScriptHash },
+    GetScriptRedeemers { script_hash: ScriptHash },
+    GetTransactionRedeemers { tx_identifier: TxIdentifier },
+    GetScriptDatum { datum_hash: DatumHash },
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -18,28 +28,76 @@ pub enum ScriptsStateQueryResponse {
     ScriptJSON(ScriptJSON),
     ScriptCBOR(ScriptCBOR),
     ScriptRedeemers(ScriptRedeemers),
-    ScriptDatumJSON(ScriptDatumJSON),
-    ScriptDatumCBOR(ScriptDatumCBOR),
+    TransactionRedeemers(TransactionRedeemers),
+    ScriptDatum(ScriptDatum),
     Error(QueryError),
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptsList {}
+pub struct ScriptInfo {
+    pub script_hash: ScriptHash,
+    pub script_lang: ScriptLang,
+    /// Size of the script bytes, for Plutus scripts only
+    pub serialised_size: Option<u64>,
+    /// Transaction the script was first seen in, as a witness or reference script
+    pub first_seen: TxIdentifier,
+    pub redeemer_counts: ScriptRedeemerCounts,
+}
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptInfo {}
+/// How many times a script has been run, by purpose
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScriptRedeemerCounts {
+    pub spend: u64,
+    pub mint: u64,
+    pub cert: u64,
+    pub reward: u64,
+    pub vote: u64,
+    pub propose: u64,
+}
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptJSON {}
+impl ScriptRedeemerCounts {
+    pub fn add(&mut self, purpose: &RedeemerTag) {
+        match purpose {
+            RedeemerTag::Spend => self.spend += 1,
+            RedeemerTag::Mint => self.mint += 1,
+            RedeemerTag::Cert => self.cert += 1,
+            RedeemerTag::Reward => self.reward += 1,
+            RedeemerTag::Vote => self.vote += 1,
+            RedeemerTag::Propose => self.propose += 1,
+        }
+    }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptCBOR {}
+    pub fn total(&self) -> u64 {
+        self.spend + self.mint + self.cert + self.reward + self.vote + self.propose
+    }
+}
 
+/// The native script, if the script is one
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptRedeemers {}
+pub struct ScriptJSON {
+    pub native: Option<NativeScript>,
+}
 
+/// The Plutus script bytes, if the script is one
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptDatumJSON {}
+pub struct ScriptCBOR {
+    pub cbor: Option<Vec<u8>>,
+}
 
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScriptRedeemer {
+    pub tx_identifier: TxIdentifier,
+    /// Epoch of the transaction, for the execution prices
+    pub epoch: u64,
+    /// Index of the redeemed item, among those with the same purpose
+    pub tx_index: u32,
+    pub purpose: RedeemerTag,
+    pub redeemer_data_hash: DataHash,
+    pub ex_units: ExUnits,
+}
+
+/// CBOR of a datum, from a witness set or an inline datum
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ScriptDatumCBOR {}
+pub struct ScriptDatum {
+    pub cbor: Vec<u8>,
+}
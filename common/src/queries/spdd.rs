@@ -1,16 +1,45 @@
-use crate::{queries::errors::QueryError, Lovelace, PoolId};
+use crate::{queries::errors::QueryError, Lovelace, PoolId, StakeAddress};
 
 pub const DEFAULT_SPDD_QUERY_TOPIC: (&str, &str) = ("spdd-state-query-topic", "cardano.query.spdd");
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum SPDDStateQuery {
-    GetEpochTotalActiveStakes { epoch: u64 },
-    GetEpochSPDD { epoch: u64 },
+    GetEpochTotalActiveStakes {
+        epoch: u64,
+    },
+    GetEpochSPDD {
+        epoch: u64,
+    },
+
+    /// Active stake of each delegator in an epoch, pools in ID order
+    GetEpochStakes {
+        epoch: u64,
+        limit: u64,
+        skip: u64,
+    },
+
+    /// Active stake of each of a pool's delegators in an epoch
+    GetEpochPoolStakes {
+        epoch: u64,
+        pool_id: PoolId,
+        limit: u64,
+        skip: u64,
+    },
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum SPDDStateQueryResponse {
     EpochTotalActiveStakes(u64),
     EpochSPDD(Vec<(PoolId, Lovelace)>),
+    EpochStakes(Vec<AccountStake>),
+    EpochPoolStakes(Vec<AccountStake>),
     Error(QueryError),
 }
+
+/// A delegator's active stake in an epoch
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AccountStake {
+    pub stake_address: StakeAddress,
+    pub pool_id: PoolId,
+    pub amount: Lovelace,
+}
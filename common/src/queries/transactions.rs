@@ -1,6 +1,6 @@
 use crate::{
-    BlockHash, InstantaneousRewardSource, Lovelace, Metadatum, NativeAsset, PoolId,
-    PoolRegistration, StakeAddress, TxHash,
+    BlockHash, DataHash, ExUnits, InstantaneousRewardSource, Lovelace, Metadatum, NativeAsset,
+    PoolId, PoolRegistration, RedeemerTag, ScriptHash, StakeAddress, TxHash, TxOutput,
 };
 
 pub const DEFAULT_TRANSACTIONS_QUERY_TOPIC: (&str, &str) = (
@@ -12,7 +12,7 @@ use crate::queries::errors::QueryError;
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum TransactionsStateQuery {
     GetTransactionInfo { tx_hash: TxHash },
-    GetTransactionUTxOs,
+    GetTransactionUTxOs { tx_hash: TxHash },
     GetTransactionStakeCertificates { tx_hash: TxHash },
     GetTransactionDelegationCertificates { tx_hash: TxHash },
     GetTransactionWithdrawals { tx_hash: TxHash },
@@ -21,7 +21,7 @@ pub enum TransactionsStateQuery {
     GetTransactionPoolRetirementCertificates { tx_hash: TxHash },
     GetTransactionMetadata { tx_hash: TxHash },
     GetTransactionMetadataCBOR,
-    GetTransactionRedeemers,
+    GetTransactionRedeemers { tx_hash: TxHash },
     GetTransactionRequiredSigners,
     GetTransactionCBOR,
 }
@@ -78,7 +78,25 @@ pub struct TransactionInfo {
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct TransactionUTxOs {}
+pub struct TransactionUTxOInput {
+    /// The output being spent, resolved from the transaction which created it
+    pub output: TxOutput,
+    pub collateral: bool,
+    pub reference: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TransactionUTxOOutput {
+    pub output: TxOutput,
+    pub collateral: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TransactionUTxOs {
+    pub hash: TxHash,
+    pub inputs: Vec<TransactionUTxOInput>,
+    pub outputs: Vec<TransactionUTxOOutput>,
+}
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TransactionStakeCertificate {
@@ -179,7 +197,22 @@ pub struct TransactionMetadataCBOR {
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct TransactionRedeemers {}
+pub struct TransactionRedeemer {
+    /// Index of the redeemed item, among those with the same purpose
+    pub tx_index: u32,
+    pub purpose: RedeemerTag,
+    /// Hash of the validator script, if it could be resolved
+    pub script_hash: Option<ScriptHash>,
+    pub redeemer_data_hash: DataHash,
+    pub ex_units: ExUnits,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TransactionRedeemers {
+    /// Epoch of the transaction, for the execution prices
+    pub epoch: u64,
+    pub redeemers: Vec<TransactionRedeemer>,
+}
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct TransactionRequiredSigners {}
@@ -3,7 +3,7 @@ use serde::Serialize;
 use std::{future::Future, sync::Arc};
 
 use crate::messages::{Message, RESTResponse};
-use crate::queries::errors::QueryError;
+use crate::queries::{circuit, errors::QueryError};
 use crate::rest_error::RESTError;
 
 pub async fn query_state<T, F>(
@@ -15,7 +15,7 @@ pub async fn query_state<T, F>(
 where
     F: FnOnce(Message) -> Result<T, QueryError>,
 {
-    let raw_msg = context.message_bus.request(topic, request_msg).await?;
+    let raw_msg = circuit::request(context, topic, request_msg).await?;
     let message = Arc::try_unwrap(raw_msg).unwrap_or_else(|arc| (*arc).clone());
 
     extractor(message)
@@ -32,7 +32,7 @@ where
     Fut: Future<Output = Result<T, QueryError>>,
 {
     // build message to query
-    let raw_msg = context.message_bus.request(topic, request_msg).await?;
+    let raw_msg = circuit::request(context, topic, request_msg).await?;
 
     let message = Arc::try_unwrap(raw_msg).unwrap_or_else(|arc| (*arc).clone());
 
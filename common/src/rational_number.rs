@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Result};
 use bigdecimal::BigDecimal;
 use minicbor::Decode;
-use num_rational::Ratio;
+use num_bigint::BigInt;
+use num_rational::{BigRational, Ratio};
 use num_traits::ToPrimitive;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -33,6 +34,21 @@ impl RationalNumber {
     }
     pub const ZERO: RationalNumber = Self::new(0, 1);
     pub const ONE: RationalNumber = Self::new(1, 1);
+
+    pub fn to_big_rational(&self) -> BigRational {
+        BigRational::new(BigInt::from(*self.numer()), BigInt::from(*self.denom()))
+    }
+}
+
+/// Exact rational for a whole amount. Calculations which must match the Haskell ledger
+/// use [`BigRational`] throughout, as its `Rational` does, and only round the result.
+pub fn big_rational(amount: impl Into<BigInt>) -> BigRational {
+    BigRational::from_integer(amount.into())
+}
+
+/// Round down to whole Lovelace, as `rationalToCoinViaFloor` - None if out of range
+pub fn floor_to_lovelace(value: &BigRational) -> Option<u64> {
+    value.floor().to_integer().to_u64()
 }
 
 // Implement Deref to automatically access Ratio's methods
@@ -212,6 +228,14 @@ mod tests {
     //use crate::rational_number::RationalNumber;
     //use crate::rational_number::rational_number_from_f32;
 
+    #[test]
+    fn big_rationals_floor_to_lovelace() {
+        let third = RationalNumber::new(1, 3).to_big_rational();
+        assert_eq!(floor_to_lovelace(&(big_rational(3) * &third)), Some(1));
+        assert_eq!(floor_to_lovelace(&(big_rational(7) * &third)), Some(2));
+        assert_eq!(floor_to_lovelace(&(big_rational(-1) * &third)), None);
+    }
+
     #[test]
     fn test_fractions() -> Result<(), anyhow::Error> {
         assert_eq!(
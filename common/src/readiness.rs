@@ -0,0 +1,233 @@
+//! Readiness of the modules in the process
+//!
+//! Modules call [`declare`] during init with the messages they must see before
+//! their state means anything - usually [`Awaited::startup`], the genesis values
+//! and, when starting from a snapshot, the end of the snapshot. Until then they
+//! are not ready, so APIs over their state can refuse requests rather than
+//! answer from empty state.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use caryatid_sdk::Context;
+use config::Config;
+use tracing::info;
+
+use crate::{
+    configuration::{get_string_flag, StartupMode},
+    messages::{CardanoMessage, Message, SnapshotMessage},
+};
+
+/// Topic the genesis values are published on - set under `[global.readiness]`
+pub const DEFAULT_GENESIS_TOPIC: (&str, &str) =
+    ("readiness.genesis-topic", "cardano.sequence.bootstrapped");
+
+/// Topic the snapshot is published on, in snapshot startup mode
+pub const DEFAULT_SNAPSHOT_TOPIC: (&str, &str) = ("readiness.snapshot-topic", "cardano.snapshot");
+
+/// A message a module must see before it is ready
+#[derive(Debug, Clone)]
+pub struct Awaited {
+    pub topic: String,
+
+    /// Whether a message on the topic is the one awaited
+    pub matches: fn(&Message) -> bool,
+}
+
+impl Awaited {
+    pub fn new(topic: &str, matches: fn(&Message) -> bool) -> Self {
+        Self {
+            topic: topic.to_string(),
+            matches,
+        }
+    }
+
+    /// What state modules must see before they are ready, in this startup mode
+    pub fn startup(config: &Config) -> Vec<Self> {
+        let mut awaited = vec![Self::new(
+            &get_string_flag(config, DEFAULT_GENESIS_TOPIC),
+            |message| {
+                matches!(
+                    message,
+                    Message::Cardano((_, CardanoMessage::GenesisComplete(_)))
+                )
+            },
+        )];
+        if StartupMode::from_config(config).is_snapshot() {
+            awaited.push(Self::new(
+                &get_string_flag(config, DEFAULT_SNAPSHOT_TOPIC),
+                |message| matches!(message, Message::Snapshot(SnapshotMessage::Complete)),
+            ));
+        }
+        awaited
+    }
+}
+
+/// Which of its awaited topics each module has seen
+#[derive(Debug, Clone, Default)]
+pub struct ReadinessMatrix {
+    modules: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+impl ReadinessMatrix {
+    pub fn declare(&mut self, module: &str, topics: impl IntoIterator<Item = String>) {
+        let awaited = self.modules.entry(module.to_string()).or_default();
+        for topic in topics {
+            awaited.entry(topic).or_insert(false);
+        }
+    }
+
+    /// Record a module has seen an awaited topic, returning whether that made
+    /// it ready
+    pub fn seen(&mut self, module: &str, topic: &str) -> bool {
+        let Some(awaited) = self.modules.get_mut(module) else {
+            return false;
+        };
+        match awaited.get_mut(topic) {
+            Some(seen) if !*seen => {
+                *seen = true;
+                awaited.values().all(|seen| *seen)
+            }
+            _ => false,
+        }
+    }
+
+    /// Modules which haven't declared anything are taken to be ready
+    pub fn is_ready(&self, module: &str) -> bool {
+        self.waiting_for(module).is_empty()
+    }
+
+    /// Topics a module is still waiting for
+    pub fn waiting_for(&self, module: &str) -> Vec<&str> {
+        self.modules.get(module).map_or_else(Vec::new, |awaited| {
+            awaited.iter().filter(|(_, seen)| !**seen).map(|(topic, _)| topic.as_str()).collect()
+        })
+    }
+
+    /// Declared modules which aren't ready
+    pub fn not_ready(&self) -> Vec<&str> {
+        self.modules.keys().filter(|module| !self.is_ready(module)).map(String::as_str).collect()
+    }
+
+    /// One line for each declared module
+    pub fn lines(&self) -> Vec<String> {
+        self.modules
+            .keys()
+            .map(|module| match self.waiting_for(module).as_slice() {
+                [] => format!("{module}: ready"),
+                waiting => format!("{module}: waiting for {}", waiting.join(", ")),
+            })
+            .collect()
+    }
+}
+
+static MATRIX: LazyLock<Mutex<ReadinessMatrix>> =
+    LazyLock::new(|| Mutex::new(ReadinessMatrix::default()));
+
+/// Readiness of the modules in this process
+pub fn matrix() -> ReadinessMatrix {
+    MATRIX.lock().unwrap().clone()
+}
+
+/// Which of the given modules aren't ready - all declared modules if none are given
+pub fn not_ready(modules: &[String]) -> Vec<String> {
+    let matrix = MATRIX.lock().unwrap();
+    if modules.is_empty() {
+        matrix.not_ready().into_iter().map(str::to_string).collect()
+    } else {
+        modules.iter().filter(|module| !matrix.is_ready(module)).cloned().collect()
+    }
+}
+
+/// Hold `module` not ready until it has seen each of the `awaited` messages
+pub async fn declare(
+    context: Arc<Context<Message>>,
+    module: &'static str,
+    awaited: Vec<Awaited>,
+) -> Result<()> {
+    MATRIX.lock().unwrap().declare(module, awaited.iter().map(|a| a.topic.clone()));
+
+    for Awaited { topic, matches } in awaited {
+        let mut subscription = context.subscribe(&topic).await?;
+        context.run(async move {
+            loop {
+                let Ok((_, message)) = subscription.read().await else {
+                    return;
+                };
+                if matches(message.as_ref()) {
+                    break;
+                }
+            }
+            if MATRIX.lock().unwrap().seen(module, &topic) {
+                info!("{module} is ready");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Hold a state module not ready until it has seen its startup messages
+pub async fn declare_startup(
+    context: Arc<Context<Message>>,
+    config: &Config,
+    module: &'static str,
+) -> Result<()> {
+    declare(context, module, Awaited::startup(config)).await
+}
+
+/// Log the readiness matrix every `interval`, until every module is ready
+pub async fn log_until_ready(interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let matrix = matrix();
+        if matrix.not_ready().is_empty() {
+            info!("All modules ready");
+            return;
+        }
+        info!("Readiness:\n  {}", matrix.lines().join("\n  "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_ready_once_every_topic_seen() {
+        let mut matrix = ReadinessMatrix::default();
+        matrix.declare(
+            "accounts-state",
+            [
+                "cardano.sequence.bootstrapped".into(),
+                "cardano.snapshot".into(),
+            ],
+        );
+        matrix.declare("spdd-state", ["cardano.sequence.bootstrapped".into()]);
+        assert_eq!(matrix.not_ready(), vec!["accounts-state", "spdd-state"]);
+
+        assert!(!matrix.seen("accounts-state", "cardano.snapshot"));
+        assert_eq!(
+            matrix.waiting_for("accounts-state"),
+            vec!["cardano.sequence.bootstrapped"]
+        );
+
+        // Undeclared modules and topics change nothing
+        assert!(!matrix.seen("drep-state", "cardano.snapshot"));
+        assert!(!matrix.seen("spdd-state", "cardano.snapshot"));
+        assert!(matrix.is_ready("drep-state"));
+
+        assert!(matrix.seen("accounts-state", "cardano.sequence.bootstrapped"));
+        assert!(!matrix.seen("accounts-state", "cardano.sequence.bootstrapped"));
+        assert_eq!(
+            matrix.lines(),
+            vec![
+                "accounts-state: ready",
+                "spdd-state: waiting for cardano.sequence.bootstrapped"
+            ]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Topology files from the relays registered pools advertise
+//!
+//! [`topology`] turns the relays of each registered pool into a `publicRoots` group
+//! of a cardano-node P2P topology file, so operators can peer with the network's
+//! advertised relays or map them. Relays registered without a port are given the
+//! usual 3001, and SRV relays are left out, since an access point needs a port.
+
+use crate::{queries::pools::PoolRelays, Relay};
+
+/// Port assumed for relays registered without one
+pub const DEFAULT_RELAY_PORT: u16 = 3001;
+
+/// A relay address and port, as a topology file gives it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AccessPoint {
+    /// IP address or DNS name
+    pub address: String,
+    pub port: u16,
+}
+
+/// A group of roots in a topology file
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootGroup {
+    pub access_points: Vec<AccessPoint>,
+    pub advertise: bool,
+}
+
+/// A cardano-node P2P topology file, with a public root group for each pool
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Topology {
+    pub local_roots: Vec<RootGroup>,
+    pub public_roots: Vec<RootGroup>,
+}
+
+/// Access points for a pool's relays, without duplicates
+pub fn access_points(relays: &[Relay]) -> Vec<AccessPoint> {
+    let mut points: Vec<AccessPoint> = Vec::new();
+    let mut add = |address: String, port: Option<u16>| {
+        let point = AccessPoint {
+            address,
+            port: port.unwrap_or(DEFAULT_RELAY_PORT),
+        };
+        if !points.contains(&point) {
+            points.push(point);
+        }
+    };
+
+    for relay in relays {
+        match relay {
+            Relay::SingleHostAddr(host) => {
+                if let Some(ipv4) = host.ipv4 {
+                    add(ipv4.to_string(), host.port);
+                }
+                if let Some(ipv6) = host.ipv6 {
+                    add(ipv6.to_string(), host.port);
+                }
+            }
+            Relay::SingleHostName(host) => add(host.dns_name.clone(), host.port),
+            Relay::MultiHostName(_) => {}
+        }
+    }
+    points
+}
+
+/// Topology of the given pools' relays, leaving out pools with no usable relay
+pub fn topology(pools: &[PoolRelays]) -> Topology {
+    Topology {
+        local_roots: Vec::new(),
+        public_roots: pools
+            .iter()
+            .map(|pool| access_points(&pool.relays))
+            .filter(|points| !points.is_empty())
+            .map(|access_points| RootGroup {
+                access_points,
+                advertise: false,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash::Hash, MultiHostName, PoolId, SingleHostAddr, SingleHostName};
+
+    fn pool(n: u8, relays: Vec<Relay>) -> PoolRelays {
+        PoolRelays {
+            pool_id: PoolId::new(Hash::new([n; 28])),
+            relays,
+        }
+    }
+
+    #[test]
+    fn relays_become_access_points() {
+        let relays = vec![
+            Relay::SingleHostAddr(SingleHostAddr {
+                port: Some(6000),
+                ipv4: Some("10.0.0.1".parse().unwrap()),
+                ipv6: Some("2001:db8::1".parse().unwrap()),
+            }),
+            Relay::SingleHostName(SingleHostName {
+                port: None,
+                dns_name: "relay.example.com".to_string(),
+            }),
+            Relay::SingleHostName(SingleHostName {
+                port: Some(3001),
+                dns_name: "relay.example.com".to_string(),
+            }),
+            Relay::MultiHostName(MultiHostName {
+                dns_name: "_cardano._tcp.example.com".to_string(),
+            }),
+        ];
+
+        let points = access_points(&relays);
+        assert_eq!(
+            points,
+            vec![
+                AccessPoint {
+                    address: "10.0.0.1".to_string(),
+                    port: 6000
+                },
+                AccessPoint {
+                    address: "2001:db8::1".to_string(),
+                    port: 6000
+                },
+                AccessPoint {
+                    address: "relay.example.com".to_string(),
+                    port: DEFAULT_RELAY_PORT
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn topology_has_a_group_per_pool_with_relays() {
+        let srv_only = vec![Relay::MultiHostName(MultiHostName {
+            dns_name: "_cardano._tcp.example.com".to_string(),
+        })];
+        let named = vec![Relay::SingleHostName(SingleHostName {
+            port: Some(3002),
+            dns_name: "relay.example.com".to_string(),
+        })];
+        let topology = topology(&[pool(1, srv_only), pool(2, named), pool(3, Vec::new())]);
+
+        assert!(topology.local_roots.is_empty());
+        assert_eq!(topology.public_roots.len(), 1);
+
+        let json = serde_json::to_value(&topology).unwrap();
+        assert_eq!(
+            json["publicRoots"][0],
+            serde_json::json!({
+                "accessPoints": [{"address": "relay.example.com", "port": 3002}],
+                "advertise": false,
+            })
+        );
+        assert_eq!(json["localRoots"], serde_json::json!([]));
+    }
+}
@@ -1,8 +1,15 @@
 use crate::queries::errors::QueryError;
 use anyhow::Error as AnyhowError;
 use caryatid_module_rest_server::messages::RESTResponse;
+use serde::Serialize;
 use thiserror::Error;
 
+/// Content type of error responses (RFC 9457 problem details)
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Seconds a client is asked to wait before retrying while the node catches up
+pub const NOT_SYNCED_RETRY_AFTER_SECS: u64 = 30;
+
 /// Standard REST error types
 #[derive(Debug, Error)]
 pub enum RESTError {
@@ -12,11 +19,48 @@ pub enum RESTError {
     #[error("{0}")]
     NotFound(String),
 
+    /// The resource existed but was removed by a chain rollback
+    #[error("{0}")]
+    RolledBack(String),
+
+    /// The node has not synced far enough to answer yet
+    #[error("{message}")]
+    NotSynced {
+        message: String,
+        retry_after: Option<u64>,
+    },
+
     #[error("{0}")]
     InternalServerError(String),
 
     #[error("{0}")]
     NotImplemented(String),
+
+    #[error("{0}")]
+    StorageDisabled(String),
+
+    /// A module needed to answer is not responding
+    #[error("{0}")]
+    ServiceUnavailable(String),
+
+    /// The client is over a configured rate limit or quota
+    #[error("{message}")]
+    TooManyRequests { message: String, retry_after: u64 },
+}
+
+/// Problem details body, as defined by RFC 9457
+#[derive(Debug, Serialize)]
+struct ProblemDetails<'a> {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'a str,
+    /// Machine-readable error code
+    code: &'static str,
+    /// Seconds to wait before retrying, if the request may succeed later
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
 }
 
 impl RESTError {
@@ -25,8 +69,28 @@ impl RESTError {
         match self {
             RESTError::BadRequest(_) => 400,
             RESTError::NotFound(_) => 404,
+            RESTError::RolledBack(_) => 404,
+            RESTError::NotSynced { .. } => 503,
             RESTError::InternalServerError(_) => 500,
             RESTError::NotImplemented(_) => 501,
+            RESTError::StorageDisabled(_) => 501,
+            RESTError::ServiceUnavailable(_) => 503,
+            RESTError::TooManyRequests { .. } => 429,
+        }
+    }
+
+    /// Get the machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            RESTError::BadRequest(_) => "bad_request",
+            RESTError::NotFound(_) => "not_found",
+            RESTError::RolledBack(_) => "rolled_back",
+            RESTError::NotSynced { .. } => "not_synced",
+            RESTError::InternalServerError(_) => "internal_error",
+            RESTError::NotImplemented(_) => "not_implemented",
+            RESTError::StorageDisabled(_) => "storage_disabled",
+            RESTError::ServiceUnavailable(_) => "unavailable",
+            RESTError::TooManyRequests { .. } => "rate_limited",
         }
     }
 
@@ -35,11 +99,40 @@ impl RESTError {
         match self {
             RESTError::BadRequest(msg) => msg,
             RESTError::NotFound(msg) => msg,
+            RESTError::RolledBack(msg) => msg,
+            RESTError::NotSynced { message, .. } => message,
             RESTError::InternalServerError(msg) => msg,
             RESTError::NotImplemented(msg) => msg,
+            RESTError::StorageDisabled(msg) => msg,
+            RESTError::ServiceUnavailable(msg) => msg,
+            RESTError::TooManyRequests { message, .. } => message,
         }
     }
 
+    /// Seconds the client should wait before retrying, if retrying may help
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            RESTError::NotSynced { retry_after, .. } => *retry_after,
+            RESTError::TooManyRequests { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    /// Render as an `application/problem+json` body
+    pub fn to_problem_json(&self) -> String {
+        let status = self.status_code();
+        let problem = ProblemDetails {
+            problem_type: "about:blank",
+            title: status_title(status),
+            status,
+            detail: self.message(),
+            code: self.code(),
+            retry_after: self.retry_after(),
+        };
+        // Only strings and integers, so serialization cannot fail
+        serde_json::to_string(&problem).unwrap_or_default()
+    }
+
     /// Parameter missing error
     pub fn param_missing(param_name: &str) -> Self {
         RESTError::BadRequest(format!("Missing {} parameter", param_name))
@@ -60,6 +153,19 @@ impl RESTError {
         RESTError::NotFound(message.to_string())
     }
 
+    /// Resource rolled back error
+    pub fn rolled_back(message: &str) -> Self {
+        RESTError::RolledBack(message.to_string())
+    }
+
+    /// Node not yet synced error, with the default retry hint
+    pub fn not_synced(message: &str) -> Self {
+        RESTError::NotSynced {
+            message: message.to_string(),
+            retry_after: Some(NOT_SYNCED_RETRY_AFTER_SECS),
+        }
+    }
+
     /// Feature not implemented error
     pub fn not_implemented(message: &str) -> Self {
         RESTError::NotImplemented(message.to_string())
@@ -67,7 +173,7 @@ impl RESTError {
 
     /// Storage disabled error
     pub fn storage_disabled(storage_type: &str) -> Self {
-        RESTError::NotImplemented(format!("{} storage is disabled in config", storage_type))
+        RESTError::StorageDisabled(format!("{} storage is disabled in config", storage_type))
     }
 
     /// Unexpected response error
@@ -86,10 +192,24 @@ impl RESTError {
     }
 }
 
+/// Reason phrase for the status codes RESTError produces
+fn status_title(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
 /// Convert RESTError to RESTResponse
 impl From<RESTError> for RESTResponse {
     fn from(error: RESTError) -> Self {
-        RESTResponse::with_text(error.status_code(), error.message())
+        let mut response = RESTResponse::with_json(error.status_code(), &error.to_problem_json());
+        response.content_type = PROBLEM_JSON_CONTENT_TYPE.to_string();
+        response
     }
 }
 
@@ -139,6 +259,12 @@ impl From<QueryError> for RESTError {
             }
             QueryError::InvalidRequest { message } => RESTError::BadRequest(message),
             QueryError::NotImplemented { query } => RESTError::NotImplemented(query),
+            QueryError::NotSynced { message } => RESTError::NotSynced {
+                message,
+                retry_after: Some(NOT_SYNCED_RETRY_AFTER_SECS),
+            },
+            QueryError::RolledBack { resource } => RESTError::RolledBack(resource),
+            QueryError::Unavailable { message } => RESTError::ServiceUnavailable(message),
         }
     }
 }
@@ -187,6 +313,63 @@ mod tests {
         let error = RESTError::BadRequest("Invalid stake address".to_string());
         let response: RESTResponse = error.into();
         assert_eq!(response.code, 400);
-        assert_eq!(response.body, "Invalid stake address");
+        assert_eq!(response.content_type, PROBLEM_JSON_CONTENT_TYPE);
+
+        let body: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Bad Request",
+                "status": 400,
+                "detail": "Invalid stake address",
+                "code": "bad_request",
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_query_error() {
+        let error = RESTError::from(QueryError::not_synced("No current DRep state"));
+        assert_eq!(error.status_code(), 503);
+        assert_eq!(error.code(), "not_synced");
+        assert_eq!(error.retry_after(), Some(NOT_SYNCED_RETRY_AFTER_SECS));
+
+        let error = RESTError::from(QueryError::rolled_back("Block abc"));
+        assert_eq!(error.status_code(), 404);
+        assert_eq!(error.code(), "rolled_back");
+        assert_eq!(error.retry_after(), None);
+
+        let error = RESTError::from(QueryError::storage_disabled("Block"));
+        assert_eq!(error.status_code(), 501);
+        assert_eq!(error.code(), "storage_disabled");
+
+        let error = RESTError::from(QueryError::unavailable("cardano.query.accounts"));
+        assert_eq!(error.status_code(), 503);
+        assert_eq!(error.code(), "unavailable");
+    }
+
+    #[test]
+    fn test_problem_json_retry_after() {
+        let body: serde_json::Value =
+            serde_json::from_str(&RESTError::not_synced("Syncing").to_problem_json()).unwrap();
+        assert_eq!(body["status"], 503);
+        assert_eq!(body["title"], "Service Unavailable");
+        assert_eq!(body["retry_after"], NOT_SYNCED_RETRY_AFTER_SECS);
+    }
+
+    #[test]
+    fn test_too_many_requests() {
+        let error = RESTError::TooManyRequests {
+            message: "Over the rate limit".to_string(),
+            retry_after: 2,
+        };
+        assert_eq!(error.status_code(), 429);
+        assert_eq!(error.code(), "rate_limited");
+
+        let response: RESTResponse = error.into();
+        let body: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["title"], "Too Many Requests");
+        assert_eq!(body["retry_after"], 2);
     }
 }
@@ -1,6 +1,7 @@
 //! Helper functions for REST handlers
 
 use crate::messages::{Message, RESTResponse};
+use crate::queries::misc::Order;
 use crate::rest_error::RESTError;
 use anyhow::{anyhow, Result};
 use caryatid_sdk::Context;
@@ -30,7 +31,7 @@ where
                 }
                 _ => {
                     error!("Unexpected message type {:?}", message);
-                    RESTResponse::with_text(500, "Unexpected message in REST request")
+                    RESTError::unexpected_response("Unexpected message in REST request").into()
                 }
             };
 
@@ -65,7 +66,7 @@ where
                 }
                 _ => {
                     error!("Unexpected message type {:?}", message);
-                    RESTResponse::with_text(500, "Unexpected message in REST request")
+                    RESTError::unexpected_response("Unexpected message in REST request").into()
                 }
             };
 
@@ -92,7 +93,36 @@ where
                     let params = request.query_parameters.clone();
                     handler(params).await.unwrap_or_else(|error| error.into())
                 }
-                _ => RESTResponse::with_text(500, "Unexpected message in REST request"),
+                _ => RESTError::unexpected_response("Unexpected message in REST request").into(),
+            };
+
+            Arc::new(Message::RESTResponse(response))
+        }
+    })
+}
+
+/// Handle a REST request carrying a body, such as a POST
+pub fn handle_rest_with_body<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: &str,
+    handler: F,
+) -> JoinHandle<()>
+where
+    F: Fn(String) -> Fut + Send + Sync + Clone + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    context.handle(topic, move |message: Arc<Message>| {
+        let handler = handler.clone();
+        async move {
+            let response = match message.as_ref() {
+                Message::RESTRequest(request) => {
+                    info!("REST received {} {}", request.method, request.path);
+                    handler(request.body.clone()).await.unwrap_or_else(|error| error.into())
+                }
+                _ => {
+                    error!("Unexpected message type {:?}", message);
+                    RESTError::unexpected_response("Unexpected message in REST request").into()
+                }
             };
 
             Arc::new(Message::RESTResponse(response))
@@ -123,7 +153,7 @@ where
                     let query_params = request.query_parameters.clone();
                     handler(&params_slice, query_params).await.unwrap_or_else(|error| error.into())
                 }
-                _ => RESTResponse::with_text(500, "Unexpected message in REST request"),
+                _ => RESTError::unexpected_response("Unexpected message in REST request").into(),
             };
 
             Arc::new(Message::RESTResponse(response))
@@ -152,6 +182,109 @@ fn extract_params_from_topic_and_path(topic: &str, path_elements: &[String]) ->
         .collect()
 }
 
+/// Blockfrost `count`, `page` and `order` query parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pagination {
+    pub count: u64,
+    pub page: u64,
+    pub order: Order,
+}
+
+impl Pagination {
+    pub const MAX_COUNT: u64 = 100;
+    pub const MAX_PAGE: u64 = 21_474_836;
+
+    /// Apply Blockfrost's defaults and limits to the query parameters
+    pub fn new(
+        count: Option<u64>,
+        page: Option<u64>,
+        order: Option<Order>,
+    ) -> Result<Self, RESTError> {
+        let count = count.unwrap_or(Self::MAX_COUNT);
+        if !(1..=Self::MAX_COUNT).contains(&count) {
+            return Err(RESTError::invalid_param(
+                "count",
+                &format!("must be between 1 and {}", Self::MAX_COUNT),
+            ));
+        }
+        let page = page.unwrap_or(1);
+        if !(1..=Self::MAX_PAGE).contains(&page) {
+            return Err(RESTError::invalid_param(
+                "page",
+                &format!("must be between 1 and {}", Self::MAX_PAGE),
+            ));
+        }
+        Ok(Self {
+            count,
+            page,
+            order: order.unwrap_or(Order::Asc),
+        })
+    }
+
+    /// Parse the query parameters of a list endpoint which takes no others
+    pub fn from_query_params(params: &HashMap<String, String>) -> Result<Self, RESTError> {
+        let (mut count, mut page, mut order) = (None, None, None);
+        for (key, value) in params {
+            match key.as_str() {
+                "count" => {
+                    count = Some(value.parse().map_err(|_| {
+                        RESTError::invalid_param("count", "must be a positive integer")
+                    })?)
+                }
+                "page" => {
+                    page = Some(value.parse().map_err(|_| {
+                        RESTError::invalid_param("page", "must be a positive integer")
+                    })?)
+                }
+                "order" => {
+                    order = Some(value.parse().map_err(|_| {
+                        RESTError::invalid_param("order", "must be 'asc' or 'desc'")
+                    })?)
+                }
+                _ => {
+                    return Err(RESTError::BadRequest(format!(
+                        "Unexpected query parameter {key}: only count, page and order are allowed"
+                    )))
+                }
+            }
+        }
+        Self::new(count, page, order)
+    }
+
+    /// Number of items before the requested page, for queries which page at source
+    pub fn offset(&self) -> u64 {
+        (self.page - 1) * self.count
+    }
+
+    /// Bounds of the requested page within an inclusive range of numbered items,
+    /// for queries over a range, or None if the page is past the end. The items
+    /// are still returned oldest first, so a descending page must be reversed.
+    pub fn range_bounds(&self, first: u64, last: u64) -> Option<(u64, u64)> {
+        let offset = self.offset();
+        if last < first || offset > last - first {
+            return None;
+        }
+        Some(match self.order {
+            Order::Asc => {
+                let start = first + offset;
+                (start, start.saturating_add(self.count - 1).min(last))
+            }
+            Order::Desc => {
+                let end = last - offset;
+                (end.saturating_sub(self.count - 1).max(first), end)
+            }
+        })
+    }
+
+    /// Select the requested page from items held oldest first
+    pub fn apply<T>(&self, mut items: Vec<T>) -> Vec<T> {
+        if self.order == Order::Desc {
+            items.reverse();
+        }
+        items.into_iter().skip(self.offset() as usize).take(self.count as usize).collect()
+    }
+}
+
 pub trait ToCheckedF64 {
     fn to_checked_f64(&self, name: &str) -> Result<f64>;
 }
@@ -177,21 +310,91 @@ macro_rules! extract_strict_query_params {
                         $var = match v.parse::<$type>() {
                             Ok(val) => Some(val),
                             Err(_) => {
-                                return Ok($crate::messages::RESTResponse::with_text(
-                                    400,
-                                    concat!("Invalid ", $key, " query parameter: must be a valid type"),
-                                ));
+                                return Ok($crate::rest_error::RESTError::BadRequest(
+                                    concat!("Invalid ", $key, " query parameter: must be a valid type").to_string(),
+                                ).into());
                             }
                         };
                     }
                 )*
                 _ => {
-                    return Ok($crate::messages::RESTResponse::with_text(
-                        400,
-                        concat!("Unexpected query parameter: only allowed keys are: ", $( $key, " ", )*)
-                    ));
+                    return Ok($crate::rest_error::RESTError::BadRequest(
+                        concat!("Unexpected query parameter: only allowed keys are: ", $( $key, " ", )*).to_string()
+                    ).into());
                 }
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pagination_defaults() {
+        let pagination = Pagination::new(None, None, None).unwrap();
+        assert_eq!(pagination.count, 100);
+        assert_eq!(pagination.page, 1);
+        assert_eq!(pagination.order, Order::Asc);
+        assert_eq!(
+            pagination.apply((0..150).collect()),
+            (0..100).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn pagination_pages_and_order() {
+        let items: Vec<u32> = (0..10).collect();
+        let page_2 = Pagination::new(Some(3), Some(2), None).unwrap();
+        assert_eq!(page_2.offset(), 3);
+        assert_eq!(page_2.apply(items.clone()), vec![3, 4, 5]);
+
+        let desc = Pagination::new(Some(3), Some(1), Some(Order::Desc)).unwrap();
+        assert_eq!(desc.apply(items.clone()), vec![9, 8, 7]);
+
+        let last = Pagination::new(Some(3), Some(4), None).unwrap();
+        assert_eq!(last.apply(items.clone()), vec![9]);
+
+        let beyond = Pagination::new(Some(3), Some(5), None).unwrap();
+        assert!(beyond.apply(items).is_empty());
+    }
+
+    #[test]
+    fn pagination_range_bounds() {
+        let page_2 = Pagination::new(Some(3), Some(2), None).unwrap();
+        assert_eq!(page_2.range_bounds(10, 19), Some((13, 15)));
+        assert_eq!(page_2.range_bounds(10, 14), Some((13, 14)));
+        assert_eq!(page_2.range_bounds(10, 12), None);
+
+        let desc = Pagination::new(Some(3), Some(2), Some(Order::Desc)).unwrap();
+        assert_eq!(desc.range_bounds(10, 19), Some((14, 16)));
+        assert_eq!(desc.range_bounds(10, 14), Some((10, 11)));
+        assert_eq!(desc.range_bounds(10, 12), None);
+    }
+
+    #[test]
+    fn pagination_limits() {
+        assert!(Pagination::new(Some(0), None, None).is_err());
+        assert!(Pagination::new(Some(101), None, None).is_err());
+        assert!(Pagination::new(None, Some(0), None).is_err());
+    }
+
+    #[test]
+    fn pagination_from_query_params() {
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+
+        let pagination =
+            Pagination::from_query_params(&params(&[("count", "10"), ("order", "desc")])).unwrap();
+        assert_eq!(
+            pagination,
+            Pagination::new(Some(10), None, Some(Order::Desc)).unwrap()
+        );
+
+        assert!(Pagination::from_query_params(&params(&[("page", "-1")])).is_err());
+        assert!(Pagination::from_query_params(&params(&[("order", "newest")])).is_err());
+        assert!(Pagination::from_query_params(&params(&[("from", "1")])).is_err());
+    }
+}
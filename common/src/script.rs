@@ -12,20 +12,46 @@ pub type ScriptIntegrityHash = Hash<32>;
 pub type DatumHash = Hash<32>;
 
 #[derive(
-    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+    Debug,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    minicbor::Encode,
+    minicbor::Decode,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
 )]
 pub enum PlutusVersion {
+    #[n(0)]
     V1,
+    #[n(1)]
     V2,
+    #[n(2)]
     V3,
 }
 
 #[derive(
-    Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+    Debug,
+    Clone,
+    serde::Serialize,
+    serde::Deserialize,
+    minicbor::Encode,
+    minicbor::Decode,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
 )]
 pub enum ScriptLang {
+    #[n(0)]
     Native,
-    Plutus(PlutusVersion),
+    #[n(1)]
+    Plutus(#[n(0)] PlutusVersion),
 }
 
 impl ScriptLang {
@@ -50,9 +76,13 @@ impl ScriptLang {
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, serde::Serialize, serde::Deserialize, minicbor::Encode, minicbor::Decode,
+)]
 pub struct ScriptRef {
+    #[n(0)]
     pub script_hash: ScriptHash,
+    #[n(1)]
     pub script_lang: ScriptLang,
 }
 
@@ -252,10 +282,24 @@ impl NativeScript {
 }
 
 /// Datum (inline or hash)
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    minicbor::Encode,
+    minicbor::Decode,
+)]
 pub enum Datum {
-    Hash(DatumHash),
-    Inline(Vec<u8>),
+    #[n(0)]
+    Hash(#[n(0)] DatumHash),
+    #[n(1)]
+    Inline(
+        #[n(0)]
+        #[cbor(with = "minicbor::bytes")]
+        Vec<u8>,
+    ),
 }
 
 impl Datum {
@@ -0,0 +1,209 @@
+//! Coordinated shutdown, so that an interrupted process leaves its on-disk stores
+//! consistent.
+//!
+//! Modules with stores call [`on_shutdown`] during init with a function which
+//! flushes them. When the process is told to stop it publishes a Shutdown command,
+//! each module flushes and acks, and the process waits (for a bounded time) until
+//! every module in it has acked before exiting.
+
+use std::{
+    collections::BTreeSet,
+    future::Future,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use caryatid_sdk::Context;
+use config::Config;
+use tracing::{error, info, warn};
+
+use crate::{
+    configuration::get_string_flag,
+    messages::{Command, CommandResponse, Message, ShutdownAck},
+};
+
+/// Topic the Shutdown command is published on - set under `[global.shutdown]`
+pub const DEFAULT_SHUTDOWN_TOPIC: (&str, &str) = ("shutdown.topic", "cardano.shutdown");
+
+/// Topic modules ack the Shutdown command on
+pub const DEFAULT_SHUTDOWN_ACK_TOPIC: (&str, &str) = ("shutdown.ack-topic", "cardano.shutdown.ack");
+
+/// How long to wait for the modules to ack before exiting anyway
+pub const DEFAULT_SHUTDOWN_TIMEOUT: (&str, &str) = ("shutdown.timeout", "30s");
+
+/// Modules in this process which flush on shutdown
+static PARTICIPANTS: LazyLock<Mutex<BTreeSet<String>>> =
+    LazyLock::new(|| Mutex::new(BTreeSet::new()));
+
+/// Modules in this process which must ack a shutdown
+pub fn participants() -> BTreeSet<String> {
+    PARTICIPANTS.lock().unwrap().clone()
+}
+
+/// Run `flush` when the process shuts down, then ack on behalf of `module`
+pub async fn on_shutdown<F, Fut>(
+    context: Arc<Context<Message>>,
+    config: &Config,
+    module: &'static str,
+    flush: F,
+) -> Result<()>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let topic = get_string_flag(config, DEFAULT_SHUTDOWN_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_SHUTDOWN_ACK_TOPIC);
+    let mut subscription = context.subscribe(&topic).await?;
+    PARTICIPANTS.lock().unwrap().insert(module.to_string());
+
+    let run_context = context.clone();
+    context.run(async move {
+        loop {
+            let Ok((_, message)) = subscription.read().await else {
+                return;
+            };
+            if matches!(message.as_ref(), Message::Command(Command::Shutdown)) {
+                break;
+            }
+        }
+
+        info!("Flushing {module} for shutdown");
+        let error = flush().await.err().map(|e| format!("{e:#}"));
+        if let Some(e) = &error {
+            error!("{module} failed to flush for shutdown: {e}");
+        }
+
+        let ack = Arc::new(Message::CommandResponse(CommandResponse::Shutdown(
+            ShutdownAck {
+                module: module.to_string(),
+                error,
+            },
+        )));
+        if let Err(e) = run_context.publish(&ack_topic, ack).await {
+            warn!("Could not publish {module} shutdown ack: {e:#}");
+        }
+    });
+    Ok(())
+}
+
+/// Collects shutdown acks and works out when every module has flushed
+#[derive(Debug, Clone)]
+pub struct ShutdownTracker {
+    /// Modules yet to ack
+    pending: BTreeSet<String>,
+
+    /// Modules which acked with an error
+    failed: BTreeSet<String>,
+}
+
+impl ShutdownTracker {
+    pub fn new(participants: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            pending: participants.into_iter().collect(),
+            failed: BTreeSet::new(),
+        }
+    }
+
+    /// Record an ack, returning whether every module has now acked
+    pub fn ack(&mut self, ack: &ShutdownAck) -> bool {
+        if self.pending.remove(&ack.module) && ack.error.is_some() {
+            self.failed.insert(ack.module.clone());
+        }
+        self.is_complete()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Modules which haven't acked
+    pub fn pending(&self) -> Vec<&str> {
+        self.pending.iter().map(String::as_str).collect()
+    }
+
+    /// Modules which acked, but couldn't flush
+    pub fn failed(&self) -> Vec<&str> {
+        self.failed.iter().map(String::as_str).collect()
+    }
+}
+
+/// Tell every module to flush, and wait for them to ack. Returns whether they
+/// all flushed in time.
+pub async fn shutdown(context: &Arc<Context<Message>>, config: &Config) -> Result<bool> {
+    let topic = get_string_flag(config, DEFAULT_SHUTDOWN_TOPIC);
+    let ack_topic = get_string_flag(config, DEFAULT_SHUTDOWN_ACK_TOPIC);
+    let timeout = get_string_flag(config, DEFAULT_SHUTDOWN_TIMEOUT);
+    let timeout: Duration = humantime::parse_duration(&timeout)
+        .map_err(|e| anyhow!("bad {} '{timeout}': {e}", DEFAULT_SHUTDOWN_TIMEOUT.0))?;
+
+    let mut tracker = ShutdownTracker::new(participants());
+    if tracker.is_complete() {
+        return Ok(true);
+    }
+
+    // Subscribe before asking, so no ack is missed
+    let mut subscription = context.subscribe(&ack_topic).await?;
+    info!(
+        "Shutting down, waiting up to {timeout:?} for {} to flush",
+        tracker.pending().join(", ")
+    );
+    context.publish(&topic, Arc::new(Message::Command(Command::Shutdown))).await?;
+
+    let wait = async {
+        while !tracker.is_complete() {
+            let Ok((_, message)) = subscription.read().await else {
+                break;
+            };
+            if let Message::CommandResponse(CommandResponse::Shutdown(ack)) = message.as_ref() {
+                tracker.ack(ack);
+            }
+        }
+    };
+    if tokio::time::timeout(timeout, wait).await.is_err() {
+        warn!(
+            "Timed out after {timeout:?} waiting for {} to flush",
+            tracker.pending().join(", ")
+        );
+        return Ok(false);
+    }
+
+    if !tracker.is_complete() {
+        warn!(
+            "Lost the shutdown acks, still waiting for {}",
+            tracker.pending().join(", ")
+        );
+        return Ok(false);
+    }
+    if !tracker.failed().is_empty() {
+        warn!("{} failed to flush", tracker.failed().join(", "));
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ack(module: &str, error: Option<&str>) -> ShutdownAck {
+        ShutdownAck {
+            module: module.to_string(),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn tracker_completes_once_every_module_acks() {
+        let mut tracker = ShutdownTracker::new(["chain-store".into(), "address-state".into()]);
+        assert!(!tracker.ack(&ack("chain-store", None)));
+        assert_eq!(tracker.pending(), vec!["address-state"]);
+
+        // Unknown and repeated acks change nothing
+        assert!(!tracker.ack(&ack("spo-state", None)));
+        assert!(!tracker.ack(&ack("chain-store", Some("late"))));
+
+        assert!(tracker.ack(&ack("address-state", Some("disk full"))));
+        assert_eq!(tracker.failed(), vec!["address-state"]);
+    }
+}
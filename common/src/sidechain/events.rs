@@ -0,0 +1,81 @@
+//! The events observers produce, the same for every partner chain
+
+use crate::{
+    metadata::{Metadata, Metadatum, MetadatumLabel},
+    Address, BlockHash, BlockInfo, BlockNumber, Datum, Epoch, Slot, TxHash, UTxOIdentifier,
+    ValueMap,
+};
+
+/// Something a rule saw, and where on the chain it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidechainEvent {
+    /// Name of the rule which matched
+    pub rule: String,
+
+    pub block_number: BlockNumber,
+    pub block_hash: BlockHash,
+    pub slot: Slot,
+    pub epoch: Epoch,
+    pub block_timestamp: u64,
+
+    /// Transaction the event is in
+    pub tx_index: u16,
+    pub tx_hash: TxHash,
+
+    pub kind: SidechainEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SidechainEventKind {
+    /// A matching output was created
+    Created {
+        utxo: UTxOIdentifier,
+        address: Address,
+        value: ValueMap,
+        datum: Option<Datum>,
+    },
+
+    /// An output created by a matching event was spent
+    Spent { utxo: UTxOIdentifier },
+
+    /// A transaction carried metadata under a watched label
+    Metadata {
+        label: MetadatumLabel,
+        metadatum: Metadatum,
+    },
+}
+
+impl SidechainEvent {
+    pub(super) fn new(
+        rule: &str,
+        block: &BlockInfo,
+        tx_index: u16,
+        tx_hash: TxHash,
+        kind: SidechainEventKind,
+    ) -> Self {
+        Self {
+            rule: rule.to_string(),
+            block_number: block.number,
+            block_hash: block.hash,
+            slot: block.slot,
+            epoch: block.epoch,
+            block_timestamp: block.timestamp,
+            tx_index,
+            tx_hash,
+            kind,
+        }
+    }
+
+    /// Position on the chain, for ordering and paging
+    pub fn position(&self) -> (BlockNumber, u16) {
+        (self.block_number, self.tx_index)
+    }
+}
+
+/// Metadata of one transaction in a block, for rules with metadata labels
+#[derive(Debug, Clone)]
+pub struct TxMetadata {
+    pub tx_index: u16,
+    pub tx_hash: TxHash,
+    pub metadata: Metadata,
+}
@@ -0,0 +1,22 @@
+//! Observing partner chain activity on Cardano.
+//!
+//! A partner chain follows what happens to a handful of addresses, tokens and
+//! metadata labels on the main chain. This provides the parts every such
+//! observer needs, so that adding one doesn't mean copying the whole of an
+//! existing module:
+//! - Declarative rules for what to watch (`rules.rs`)
+//! - A standard event schema (`events.rs`)
+//! - A rollback-safe event store, kept in a `StateHistory` (`observer.rs`)
+//! - A REST handler over the stored events (`rest.rs`)
+//!
+//! The `sidechain-observer` module puts these together with a gRPC service,
+//! and is the template for chains needing more than the events.
+
+mod events;
+mod observer;
+pub mod rest;
+mod rules;
+
+pub use events::{SidechainEvent, SidechainEventKind, TxMetadata};
+pub use observer::SidechainObserver;
+pub use rules::{validate_rules, ObservationRule};
@@ -0,0 +1,287 @@
+//! Matching blocks against the rules, and keeping the events
+
+use std::sync::Arc;
+
+use imbl::{HashMap, OrdMap};
+
+use crate::{BlockInfo, BlockNumber, ExtendedAddressDelta, UTxOIdentifier};
+
+use super::{ObservationRule, SidechainEvent, SidechainEventKind, TxMetadata};
+
+/// Events seen so far, and the outputs whose spending is still to be seen.
+/// Cheap to clone, so it can be kept in a `StateHistory` to handle rollbacks.
+#[derive(Debug, Clone, Default)]
+pub struct SidechainObserver {
+    rules: Arc<Vec<ObservationRule>>,
+
+    /// Unspent matching outputs, with the indexes of the rules they matched
+    watched: HashMap<UTxOIdentifier, Vec<usize>>,
+
+    /// Events by block, in transaction order
+    events: OrdMap<BlockNumber, Vec<SidechainEvent>>,
+
+    last_block: Option<BlockNumber>,
+}
+
+impl AsRef<SidechainObserver> for SidechainObserver {
+    fn as_ref(&self) -> &SidechainObserver {
+        self
+    }
+}
+
+impl SidechainObserver {
+    pub fn new(rules: Vec<ObservationRule>) -> Self {
+        Self {
+            rules: Arc::new(rules),
+            ..Self::default()
+        }
+    }
+
+    pub fn rules(&self) -> &[ObservationRule] {
+        &self.rules
+    }
+
+    /// Whether any rule needs transaction metadata, which doesn't come with
+    /// the address deltas
+    pub fn watches_metadata(&self) -> bool {
+        self.rules.iter().any(|rule| !rule.metadata_labels.is_empty())
+    }
+
+    /// Last block handled
+    pub fn last_block(&self) -> Option<BlockNumber> {
+        self.last_block
+    }
+
+    /// Match a block's extended address deltas and transaction metadata, and
+    /// return how many events it produced
+    pub fn handle_block(
+        &mut self,
+        block: &BlockInfo,
+        deltas: &[ExtendedAddressDelta],
+        metadata: &[TxMetadata],
+    ) -> usize {
+        let mut events = Vec::new();
+
+        // Creations first, since deltas are by address rather than in transaction
+        // order, and an output may be spent later in the block it was created in
+        for delta in deltas {
+            let tx_index = delta.tx_identifier.tx_index();
+            for created in &delta.created_utxos {
+                let matched: Vec<usize> = self
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rule)| {
+                        rule.matches_output(&delta.address, &created.value, created.datum.as_ref())
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+                if matched.is_empty() {
+                    continue;
+                }
+
+                for index in &matched {
+                    events.push(SidechainEvent::new(
+                        &self.rules[*index].name,
+                        block,
+                        tx_index,
+                        created.utxo.tx_hash,
+                        SidechainEventKind::Created {
+                            utxo: created.utxo,
+                            address: delta.address.clone(),
+                            value: created.value.clone(),
+                            datum: created.datum.clone(),
+                        },
+                    ));
+                }
+                self.watched.insert(created.utxo, matched);
+            }
+        }
+
+        for delta in deltas {
+            let tx_index = delta.tx_identifier.tx_index();
+            for spent in &delta.spent_utxos {
+                let Some(matched) = self.watched.remove(&spent.utxo) else {
+                    continue;
+                };
+                for index in matched {
+                    events.push(SidechainEvent::new(
+                        &self.rules[index].name,
+                        block,
+                        tx_index,
+                        spent.spent_by,
+                        SidechainEventKind::Spent { utxo: spent.utxo },
+                    ));
+                }
+            }
+        }
+
+        for tx in metadata {
+            for (label, metadatum) in tx.metadata.iter() {
+                for rule in self.rules.iter().filter(|rule| rule.matches_label(*label)) {
+                    events.push(SidechainEvent::new(
+                        &rule.name,
+                        block,
+                        tx.tx_index,
+                        tx.tx_hash,
+                        SidechainEventKind::Metadata {
+                            label: *label,
+                            metadatum: metadatum.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        self.last_block = Some(block.number);
+        let count = events.len();
+        if count > 0 {
+            // Stable, so a transaction's events stay in the order they were found
+            events.sort_by_key(|event| event.tx_index);
+            self.events.insert(block.number, events);
+        }
+        count
+    }
+
+    /// Events from a position on, optionally for one rule only. At most
+    /// `capacity` are returned, except that a transaction's events are never
+    /// split, so the next page can start at the transaction after the last.
+    pub fn events_from(
+        &self,
+        start_block: BlockNumber,
+        start_tx_index: u16,
+        capacity: usize,
+        rule: Option<&str>,
+    ) -> Vec<&SidechainEvent> {
+        let mut result: Vec<&SidechainEvent> = Vec::new();
+        let events = self
+            .events
+            .range(start_block..)
+            .flat_map(|(_, events)| events.iter())
+            .filter(|event| event.position() >= (start_block, start_tx_index))
+            .filter(|event| rule.is_none_or(|rule| event.rule == rule));
+        for event in events {
+            if result.len() >= capacity
+                && result.last().is_some_and(|last| last.position() != event.position())
+            {
+                break;
+            }
+            result.push(event);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metadata::{Metadata, Metadatum},
+        Address, BlockHash, BlockIntent, BlockStatus, CreatedUTxOExtended, Era, NativeAssetsMap,
+        PolicyId, SpentUTxOExtended, TxHash, TxIdentifier, ValueMap,
+    };
+
+    fn block(number: u64) -> BlockInfo {
+        BlockInfo {
+            status: BlockStatus::Volatile,
+            intent: BlockIntent::Apply,
+            slot: number * 20,
+            number,
+            hash: BlockHash::default(),
+            epoch: 1,
+            epoch_slot: 0,
+            new_epoch: false,
+            is_new_era: false,
+            tip_slot: None,
+            timestamp: 0,
+            era: Era::Conway,
+        }
+    }
+
+    fn delta(
+        block: u64,
+        tx_index: u16,
+        value: ValueMap,
+        created: &[UTxOIdentifier],
+        spent: &[UTxOIdentifier],
+    ) -> ExtendedAddressDelta {
+        ExtendedAddressDelta {
+            address: Address::None,
+            tx_identifier: TxIdentifier::new(block as u32, tx_index),
+            spent_utxos: spent
+                .iter()
+                .map(|utxo| SpentUTxOExtended {
+                    utxo: *utxo,
+                    spent_by: TxHash::from([tx_index as u8; 32]),
+                })
+                .collect(),
+            created_utxos: created
+                .iter()
+                .map(|utxo| CreatedUTxOExtended {
+                    utxo: *utxo,
+                    value: value.clone(),
+                    datum: None,
+                })
+                .collect(),
+            sent: ValueMap::default(),
+            received: value,
+        }
+    }
+
+    fn utxo(byte: u8) -> UTxOIdentifier {
+        UTxOIdentifier::new(TxHash::from([byte; 32]), 0)
+    }
+
+    #[test]
+    fn follows_matching_outputs_to_their_spending() {
+        let policy = PolicyId::from([7; 28]);
+        let mut observer = SidechainObserver::new(vec![
+            ObservationRule {
+                name: "token".to_string(),
+                policies: vec![policy],
+                ..ObservationRule::default()
+            },
+            ObservationRule {
+                name: "label".to_string(),
+                metadata_labels: vec![674],
+                ..ObservationRule::default()
+            },
+        ]);
+        let token = ValueMap {
+            lovelace: 2_000_000,
+            assets: NativeAssetsMap::from([(
+                policy,
+                [(crate::AssetName::new(b"T").unwrap(), 1)].into_iter().collect(),
+            )]),
+        };
+
+        // Created then spent in the same block, the spend listed first
+        let deltas = [
+            delta(1, 3, ValueMap::default(), &[], &[utxo(1)]),
+            delta(1, 1, token.clone(), &[utxo(1)], &[]),
+            delta(1, 2, ValueMap::default(), &[utxo(2)], &[]),
+        ];
+        let metadata = [TxMetadata {
+            tx_index: 2,
+            tx_hash: TxHash::from([2; 32]),
+            metadata: Metadata(vec![
+                (674, Metadatum::Text("hello".to_string())),
+                (1, Metadatum::Int(1)),
+            ]),
+        }];
+        assert_eq!(observer.handle_block(&block(1), &deltas, &metadata), 3);
+        assert_eq!(observer.last_block(), Some(1));
+
+        let events = observer.events_from(0, 0, 10, None);
+        let kinds: Vec<(&str, u16)> =
+            events.iter().map(|event| (event.rule.as_str(), event.tx_index)).collect();
+        assert_eq!(kinds, vec![("token", 1), ("label", 2), ("token", 3)]);
+        assert_eq!(events[2].kind, SidechainEventKind::Spent { utxo: utxo(1) });
+
+        // Paging and filtering by rule
+        assert_eq!(observer.events_from(1, 2, 10, None).len(), 2);
+        assert_eq!(observer.events_from(0, 0, 1, None).len(), 1);
+        assert_eq!(observer.events_from(0, 0, 10, Some("label")).len(), 1);
+        assert!(observer.events_from(2, 0, 10, None).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+//! REST handler over an observer's events, for any state holding an observer
+
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::sync::Mutex;
+
+use crate::{
+    extract_strict_query_params, messages::RESTResponse, rest_error::RESTError,
+    state_history::StateHistory, Datum,
+};
+
+use super::{SidechainEvent, SidechainEventKind, SidechainObserver};
+
+/// Events returned when no count is given, and the most which can be asked for
+const DEFAULT_COUNT: usize = 100;
+const MAX_COUNT: usize = 1000;
+
+#[derive(serde::Serialize)]
+struct RESTSidechainEvent {
+    rule: String,
+    block_number: u64,
+    block_hash: String,
+    slot: u64,
+    epoch: u64,
+    block_timestamp: u64,
+    tx_index: u16,
+    tx_hash: String,
+    #[serde(flatten)]
+    kind: RESTSidechainEventKind,
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum RESTSidechainEventKind {
+    Created {
+        utxo: String,
+        address: String,
+        lovelace: u64,
+        assets: Vec<(String, String, u64)>,
+        inline_datum: Option<String>,
+        datum_hash: Option<String>,
+    },
+    Spent {
+        utxo: String,
+    },
+    Metadata {
+        label: u64,
+        metadatum: serde_json::Value,
+    },
+}
+
+impl TryFrom<&SidechainEvent> for RESTSidechainEvent {
+    type Error = RESTError;
+
+    fn try_from(event: &SidechainEvent) -> Result<Self, Self::Error> {
+        let kind = match &event.kind {
+            SidechainEventKind::Created {
+                utxo,
+                address,
+                value,
+                datum,
+            } => RESTSidechainEventKind::Created {
+                utxo: utxo.to_string(),
+                address: address
+                    .to_string()
+                    .map_err(|e| RESTError::encoding_failed(&format!("address: {e}")))?,
+                lovelace: value.lovelace,
+                assets: value
+                    .assets
+                    .iter()
+                    .flat_map(|(policy, assets)| {
+                        assets.iter().map(move |(name, amount)| {
+                            (policy.to_string(), hex::encode(name.as_slice()), *amount)
+                        })
+                    })
+                    .collect(),
+                inline_datum: match datum {
+                    Some(Datum::Inline(bytes)) => Some(hex::encode(bytes)),
+                    _ => None,
+                },
+                datum_hash: match datum {
+                    Some(Datum::Hash(hash)) => Some(hash.to_string()),
+                    _ => None,
+                },
+            },
+            SidechainEventKind::Spent { utxo } => RESTSidechainEventKind::Spent {
+                utxo: utxo.to_string(),
+            },
+            SidechainEventKind::Metadata { label, metadatum } => RESTSidechainEventKind::Metadata {
+                label: *label,
+                metadatum: serde_json::to_value(metadatum)?,
+            },
+        };
+
+        Ok(Self {
+            rule: event.rule.clone(),
+            block_number: event.block_number,
+            block_hash: event.block_hash.to_string(),
+            slot: event.slot,
+            epoch: event.epoch,
+            block_timestamp: event.block_timestamp,
+            tx_index: event.tx_index,
+            tx_hash: event.tx_hash.to_string(),
+            kind,
+        })
+    }
+}
+
+/// Events from `from_block` (and `from_tx_index`) on, `count` at most, and only
+/// for `rule` if given. Register it with `handle_rest_with_query_parameters`.
+pub async fn handle_events<S: AsRef<SidechainObserver> + Clone + Default>(
+    history: Arc<Mutex<StateHistory<S>>>,
+    params: HashMap<String, String>,
+) -> Result<RESTResponse, RESTError> {
+    extract_strict_query_params!(params, {
+        "from_block" => from_block: Option<u64>,
+        "from_tx_index" => from_tx_index: Option<u16>,
+        "count" => count: Option<usize>,
+        "rule" => rule: Option<String>,
+    });
+    let count = count.unwrap_or(DEFAULT_COUNT);
+    if count == 0 || count > MAX_COUNT {
+        return Err(RESTError::invalid_param(
+            "count",
+            &format!("must be between 1 and {MAX_COUNT}"),
+        ));
+    }
+
+    let history = history.lock().await;
+    let Some(state) = history.current() else {
+        return Err(RESTError::not_synced("no blocks observed yet"));
+    };
+    let events = state
+        .as_ref()
+        .events_from(
+            from_block.unwrap_or(0),
+            from_tx_index.unwrap_or(0),
+            count,
+            rule.as_deref(),
+        )
+        .into_iter()
+        .map(RESTSidechainEvent::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let json = serde_json::to_string(&events)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
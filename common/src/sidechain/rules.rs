@@ -0,0 +1,101 @@
+//! What an observer watches, as `[[rule]]` entries in its config
+
+use std::collections::HashSet;
+
+use crate::{metadata::MetadatumLabel, Address, AssetName, Datum, PolicyId, ValueMap};
+
+/// One thing to watch. An output matches if it satisfies every condition the
+/// rule sets - at an address in `addresses`, holding a token of a policy in
+/// `policies` - and metadata matches by label alone.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ObservationRule {
+    /// Name given to the events the rule produces
+    pub name: String,
+
+    /// Outputs to any of these addresses
+    pub addresses: Vec<Address>,
+
+    /// Outputs holding tokens of any of these policies
+    pub policies: Vec<PolicyId>,
+
+    /// Narrows `policies` to tokens with these names
+    pub asset_names: Vec<AssetName>,
+
+    /// Only outputs carrying a datum
+    pub require_datum: bool,
+
+    /// Transaction metadata under any of these labels
+    pub metadata_labels: Vec<MetadatumLabel>,
+}
+
+impl ObservationRule {
+    /// Whether the rule looks at outputs at all
+    pub fn watches_outputs(&self) -> bool {
+        !self.addresses.is_empty() || !self.policies.is_empty()
+    }
+
+    pub fn matches_output(
+        &self,
+        address: &Address,
+        value: &ValueMap,
+        datum: Option<&Datum>,
+    ) -> bool {
+        if !self.watches_outputs() {
+            return false;
+        }
+        if !self.addresses.is_empty() && !self.addresses.contains(address) {
+            return false;
+        }
+        if self.require_datum && datum.is_none() {
+            return false;
+        }
+        self.policies.is_empty()
+            || self.policies.iter().any(|policy| {
+                value.assets.get(policy).is_some_and(|assets| {
+                    assets.iter().any(|(name, amount)| {
+                        *amount > 0
+                            && (self.asset_names.is_empty() || self.asset_names.contains(name))
+                    })
+                })
+            })
+    }
+
+    pub fn matches_label(&self, label: MetadatumLabel) -> bool {
+        self.metadata_labels.contains(&label)
+    }
+}
+
+/// Report rules which can never match, or whose events couldn't be told apart
+pub fn validate_rules(rules: &[ObservationRule], errors: &mut Vec<String>) {
+    if rules.is_empty() {
+        errors.push("needs at least one [[rule]] to observe".to_string());
+    }
+
+    let mut names = HashSet::new();
+    for (index, rule) in rules.iter().enumerate() {
+        if rule.name.is_empty() {
+            errors.push(format!("rule {index} needs a name"));
+        } else if !names.insert(rule.name.as_str()) {
+            errors.push(format!("rule name '{}' is used twice", rule.name));
+        }
+        if !rule.watches_outputs() && rule.metadata_labels.is_empty() {
+            errors.push(format!(
+                "rule '{}' needs addresses, policies or metadata-labels",
+                rule.name
+            ));
+        }
+        if !rule.asset_names.is_empty() && rule.policies.is_empty() {
+            errors.push(format!(
+                "rule '{}' has asset-names but no policies",
+                rule.name
+            ));
+        }
+        if rule.require_datum && !rule.watches_outputs() {
+            errors.push(format!(
+                "rule '{}' has require-datum but watches no outputs",
+                rule.name
+            ));
+        }
+    }
+}
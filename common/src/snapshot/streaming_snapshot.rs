@@ -42,7 +42,10 @@ pub use crate::{
     MultiHostName, NetworkId, PoolId, PoolMetadata, PoolRegistration, Ratio, Relay, SingleHostAddr,
     SingleHostName, StakeAddress, StakeCredential,
 };
-use crate::{DataHash, Epoch, PoolBlockProduction, Pots, ProtocolParamUpdate, RewardParams};
+use crate::{
+    DataHash, Epoch, PoolBlockProduction, Pots, ProtocolParamUpdate, RewardParams,
+    ShelleyAddressPointer,
+};
 // Import snapshot parsing support
 use super::mark_set_go::{RawSnapshotsContainer, SnapshotsCallback};
 use super::reward_snapshot::PulsingRewardUpdate;
@@ -366,6 +369,7 @@ impl<C> minicbor::Encode<C> for DRep {
 /// This is converted to AccountState for the external API.
 #[derive(Debug)]
 struct SnapshotAccountValue {
+    pub pointer: Option<ShelleyAddressPointer>,
     pub balance: Lovelace,
     pub pool: StrictMaybe<PoolId>,
     pub drep: StrictMaybe<DRep>,
@@ -416,12 +420,30 @@ impl SnapshotAccountValue {
         let drep = d.decode_with(ctx)?;
 
         Ok(Self {
+            pointer: None,
             balance,
             pool,
             drep,
         })
     }
 
+    /// Registration pointer of a pre-Conway account, if it is a plain [slot, tx, cert]
+    fn decode_pointer(
+        d: &mut Decoder<'_>,
+    ) -> Result<Option<ShelleyAddressPointer>, minicbor::decode::Error> {
+        let mut pointer_decoder = d.clone();
+        match pointer_decoder.decode::<ShelleyAddressPointer>() {
+            Ok(pointer) => {
+                d.set_position(pointer_decoder.position());
+                Ok(Some(pointer))
+            }
+            Err(_) => {
+                d.skip()?;
+                Ok(None)
+            }
+        }
+    }
+
     fn decode_shelley_layout<'b, C>(
         d: &mut Decoder<'b>,
         ctx: &mut C,
@@ -433,12 +455,13 @@ impl SnapshotAccountValue {
             ));
         }
 
-        d.skip()?; // ptr
+        let pointer = Self::decode_pointer(d)?;
         let balance = d.decode_with(ctx)?;
         d.skip()?; // deposit
         let pool = d.decode_with(ctx)?;
 
         Ok(Self {
+            pointer,
             balance,
             pool,
             drep: StrictMaybe::Nothing,
@@ -459,7 +482,7 @@ impl SnapshotAccountValue {
             }
         }
 
-        d.skip()?; // ptr
+        let pointer = Self::decode_pointer(d)?;
         let balance = d.decode_with(ctx)?;
         d.skip()?; // deposit
         let pool = d.decode_with(ctx)?;
@@ -467,6 +490,7 @@ impl SnapshotAccountValue {
         skip_remaining_array_items(d, len, 5)?;
 
         Ok(Self {
+            pointer,
             balance,
             pool,
             drep,
@@ -475,6 +499,7 @@ impl SnapshotAccountValue {
 
     fn to_normalized(&self) -> NormalizedAccount {
         NormalizedAccount {
+            pointer: self.pointer.clone(),
             rewards: self.balance,
             delegated_spo: match &self.pool {
                 StrictMaybe::Nothing => None,
@@ -490,6 +515,7 @@ impl SnapshotAccountValue {
 
 #[derive(Debug, Clone)]
 struct NormalizedAccount {
+    pointer: Option<ShelleyAddressPointer>,
     rewards: Lovelace,
     delegated_spo: Option<PoolId>,
     delegated_drep: Option<DRepChoice>,
@@ -1069,6 +1095,8 @@ pub struct AccountsBootstrapData {
     /// Fully processed bootstrap snapshots (mark/set/go) for rewards calculation.
     /// Empty (default) for pre-Shelley eras.
     pub snapshots: SnapshotsContainer,
+    /// Registration pointers of pre-Conway accounts, for resolving pointer addresses
+    pub pointers: Vec<(ShelleyAddressPointer, StakeAddress)>,
 }
 
 /// Callback invoked with accounts bootstrap data
@@ -1316,6 +1344,7 @@ impl StreamingSnapshotParser {
             dreps,
             pools,
             accounts,
+            pointers,
             utxo_file_position,
             instant_rewards_result,
         ) = {
@@ -1467,11 +1496,15 @@ impl StreamingSnapshotParser {
             );
 
             // Convert to AccountState for API, combining regular rewards with instant rewards
+            let mut pointers = Vec::new();
             let accounts: Vec<AccountState> = accounts_map
                 .into_iter()
                 .map(|(credential, account)| {
                     // Convert StakeCredential to stake address representation
                     let stake_address = StakeAddress::new(credential.clone(), network.clone());
+                    if let Some(pointer) = account.pointer {
+                        pointers.push((pointer, stake_address.clone()));
+                    }
 
                     // Add instant rewards (MIRs) if any
                     let mir_rewards =
@@ -1516,6 +1549,7 @@ impl StreamingSnapshotParser {
                 dreps,
                 pools,
                 accounts,
+                pointers,
                 utxo_file_position,
                 instant_rewards_result,
             )
@@ -1932,6 +1966,7 @@ impl StreamingSnapshotParser {
             },
             pot_deltas,
             snapshots: bootstrap_snapshots,
+            pointers,
         };
 
         // Emit bulk callbacks
@@ -2541,8 +2576,16 @@ impl StreamingSnapshotParser {
             ));
         }
 
-        let states = decoder.decode_with(ctx)?;
-        decoder.skip()?; // saPtrs
+        let mut states: BTreeMap<StakeCredential, SnapshotAccountValue> =
+            decoder.decode_with(ctx)?;
+
+        // saPtrs is the ledger's own pointer map, so it wins over the account entries
+        for entry in decoder.map_iter_with::<_, ShelleyAddressPointer, StakeCredential>(ctx)? {
+            let (pointer, credential) = entry?;
+            if let Some(account) = states.get_mut(&credential) {
+                account.pointer = Some(pointer);
+            }
+        }
         Ok(states)
     }
 
@@ -3124,11 +3167,24 @@ mod tests {
         enc.u64(500).unwrap();
         enc.u64(60).unwrap();
         enc.bytes(&[0x44; 28]).unwrap();
-        enc.map(0).unwrap(); // saPtrs
+        enc.map(1).unwrap(); // saPtrs
+        enc.array(3).unwrap();
+        enc.u64(4).unwrap();
+        enc.u64(5).unwrap();
+        enc.u64(6).unwrap();
+        encode_addr_key_hash_credential(&mut enc, 0x33);
 
         let parsed = decode_accounts_map(&buf);
         let account = parsed.get(&StakeCredential::AddrKeyHash(Hash::new([0x33; 28]))).unwrap();
 
+        assert_eq!(
+            account.pointer,
+            Some(ShelleyAddressPointer {
+                slot: 4,
+                tx_index: 5,
+                cert_index: 6,
+            })
+        );
         assert_eq!(account.rewards, 500);
         assert_eq!(
             account.delegated_spo,
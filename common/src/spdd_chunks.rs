@@ -0,0 +1,335 @@
+//! Chunked publication of the stake pool delegation distribution (SPDD)
+//!
+//! On mainnet the SPDD is too big to want in one message, so the publisher can
+//! split it into a header, chunks of a bounded number of pools, and a trailer,
+//! building each chunk from its own map only as it is sent. Readers take it in a
+//! chunk at a time through an [`SPDDSink`] - the reader's `stream_with_rollbacks`
+//! hands each chunk on as it arrives and drops it, checking they all arrive in
+//! order, so no reader holds the whole distribution as a message. The header
+//! carries the total active stake, which most readers need before any pool.
+//!
+//! Readers which do want the whole [`SPOStakeDistributionMessage`] - the correctness
+//! verifier and the replayer, which compare or record it - put it back together
+//! with [`SPDDAssembler::push`].
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+
+use crate::caryatid::ChunkAssembler;
+use crate::messages::{SPOStakeDistributionChunkMessage, SPOStakeDistributionMessage};
+use crate::{DelegatedStake, PoolId};
+
+/// Split an SPDD into a header, chunks of at most `chunk_size` pools, and a trailer
+pub fn spdd_chunks(
+    epoch: u64,
+    spos: BTreeMap<PoolId, DelegatedStake>,
+    chunk_size: usize,
+) -> impl Iterator<Item = SPOStakeDistributionChunkMessage> {
+    let chunk_size = chunk_size.max(1);
+    let pools = spos.len() as u64;
+    let total_active = spos.values().map(|stake| stake.active).sum();
+    let chunks = spos.len().div_ceil(chunk_size) as u64;
+
+    let mut spos = spos.into_iter();
+    let body = (0..chunks).map(move |sequence| SPOStakeDistributionChunkMessage::Pools {
+        sequence,
+        spos: spos.by_ref().take(chunk_size).collect(),
+    });
+
+    std::iter::once(SPOStakeDistributionChunkMessage::Header {
+        epoch,
+        pools,
+        total_active,
+        chunks,
+    })
+    .chain(body)
+    .chain(std::iter::once(SPOStakeDistributionChunkMessage::Trailer {
+        chunks,
+    }))
+}
+
+/// Takes in an SPDD as it arrives, a chunk at a time
+pub trait SPDDSink: Send {
+    /// The distribution for `epoch` is starting, with `pools` pools holding
+    /// `total_active` active stake between them
+    fn start(&mut self, epoch: u64, pools: u64, total_active: u64);
+
+    /// Next pools of the distribution, in operator ID order
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]);
+}
+
+/// Just the totals of an SPDD, for readers which need no more
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SPDDTotals {
+    pub epoch: u64,
+    pub pools: u64,
+    pub total_active: u64,
+}
+
+impl SPDDSink for SPDDTotals {
+    fn start(&mut self, epoch: u64, pools: u64, total_active: u64) {
+        *self = Self {
+            epoch,
+            pools,
+            total_active,
+        };
+    }
+
+    fn pools(&mut self, _spos: &[(PoolId, DelegatedStake)]) {}
+}
+
+/// Whole distribution, for [`SPDDAssembler::push`]
+#[derive(Default)]
+struct Collected {
+    epoch: u64,
+    spos: Vec<(PoolId, DelegatedStake)>,
+}
+
+impl SPDDSink for Collected {
+    fn start(&mut self, epoch: u64, pools: u64, _total_active: u64) {
+        self.epoch = epoch;
+        self.spos = Vec::with_capacity(pools as usize);
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        self.spos.extend_from_slice(spos);
+    }
+}
+
+/// Distribution being received
+struct Pending {
+    epoch: u64,
+    pools: u64,
+    chunks: u64,
+    received: u64,
+    received_pools: u64,
+}
+
+/// Checks the chunks of an SPDD arrive in order, handing them on or putting them
+/// back together
+#[derive(Default)]
+pub struct SPDDAssembler {
+    pending: Option<Pending>,
+    collected: Collected,
+}
+
+impl ChunkAssembler for SPDDAssembler {
+    type Chunk = SPOStakeDistributionChunkMessage;
+    type Message = SPOStakeDistributionMessage;
+    type Sink = dyn SPDDSink;
+
+    fn push(
+        &mut self,
+        chunk: &SPOStakeDistributionChunkMessage,
+    ) -> Result<Option<SPOStakeDistributionMessage>> {
+        let mut collected = std::mem::take(&mut self.collected);
+        if !self.stream(chunk, &mut collected)? {
+            self.collected = collected;
+            return Ok(None);
+        }
+        Ok(Some(SPOStakeDistributionMessage {
+            epoch: collected.epoch,
+            spos: collected.spos,
+        }))
+    }
+
+    fn stream(
+        &mut self,
+        chunk: &SPOStakeDistributionChunkMessage,
+        sink: &mut Self::Sink,
+    ) -> Result<bool> {
+        match chunk {
+            SPOStakeDistributionChunkMessage::Header {
+                epoch,
+                pools,
+                total_active,
+                chunks,
+            } => {
+                if let Some(pending) = self.pending.take() {
+                    bail!(
+                        "SPDD for epoch {} incomplete when epoch {epoch} started",
+                        pending.epoch
+                    );
+                }
+                self.pending = Some(Pending {
+                    epoch: *epoch,
+                    pools: *pools,
+                    chunks: *chunks,
+                    received: 0,
+                    received_pools: 0,
+                });
+                sink.start(*epoch, *pools, *total_active);
+                Ok(false)
+            }
+
+            SPOStakeDistributionChunkMessage::Pools { sequence, spos } => {
+                let Some(pending) = &mut self.pending else {
+                    bail!("SPDD chunk {sequence} without a header");
+                };
+                if *sequence != pending.received || *sequence >= pending.chunks {
+                    let (epoch, expected) = (pending.epoch, pending.received);
+                    self.pending = None;
+                    bail!(
+                        "SPDD chunk {sequence} for epoch {epoch} out of order, expected {expected}"
+                    );
+                }
+                pending.received += 1;
+                pending.received_pools += spos.len() as u64;
+                sink.pools(spos);
+                Ok(false)
+            }
+
+            SPOStakeDistributionChunkMessage::Trailer { chunks } => {
+                let Some(pending) = self.pending.take() else {
+                    bail!("SPDD trailer without a header");
+                };
+                if *chunks != pending.received || pending.received_pools != pending.pools {
+                    bail!(
+                        "SPDD for epoch {} incomplete: {} of {} chunks, {} of {} pools",
+                        pending.epoch,
+                        pending.received,
+                        pending.chunks,
+                        pending.received_pools,
+                        pending.pools
+                    );
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn stream_whole(message: &SPOStakeDistributionMessage, sink: &mut Self::Sink) {
+        let total_active = message.spos.iter().map(|(_, stake)| stake.active).sum();
+        sink.start(message.epoch, message.spos.len() as u64, total_active);
+        sink.pools(&message.spos);
+    }
+
+    fn reset(&mut self) {
+        self.pending = None;
+        self.collected = Collected::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Hash;
+
+    fn spdd(pools: u8) -> BTreeMap<PoolId, DelegatedStake> {
+        (0..pools)
+            .map(|i| {
+                (
+                    PoolId::new(Hash::new([i; 28])),
+                    DelegatedStake {
+                        active: i as u64 * 1000,
+                        active_delegators_count: i as u64,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Records what it is handed, chunk by chunk
+    #[derive(Default)]
+    struct Recorder {
+        totals: SPDDTotals,
+        chunk_sizes: Vec<usize>,
+    }
+
+    impl SPDDSink for Recorder {
+        fn start(&mut self, epoch: u64, pools: u64, total_active: u64) {
+            self.totals.start(epoch, pools, total_active);
+            self.chunk_sizes.clear();
+        }
+
+        fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+            self.chunk_sizes.push(spos.len());
+        }
+    }
+
+    #[test]
+    fn chunks_reassemble_to_the_distribution() -> Result<()> {
+        let spos = spdd(7);
+        let chunks: Vec<_> = spdd_chunks(42, spos.clone(), 3).collect();
+        assert_eq!(chunks.len(), 5);
+
+        let mut assembler = SPDDAssembler::default();
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            assert!(assembler.push(chunk)?.is_none());
+        }
+        let spdd = assembler.push(last)?.expect("complete SPDD");
+        assert_eq!(spdd.epoch, 42);
+        assert_eq!(spdd.spos, spos.into_iter().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn chunks_stream_to_the_sink_as_they_arrive() -> Result<()> {
+        let chunks: Vec<_> = spdd_chunks(42, spdd(7), 3).collect();
+
+        let mut assembler = SPDDAssembler::default();
+        let mut recorder = Recorder::default();
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            assert!(!assembler.stream(chunk, &mut recorder)?);
+        }
+        assert_eq!(recorder.chunk_sizes, vec![3, 3, 1]);
+        assert!(assembler.stream(last, &mut recorder)?);
+        assert_eq!(
+            recorder.totals,
+            SPDDTotals {
+                epoch: 42,
+                pools: 7,
+                total_active: 21_000,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn whole_distribution_streams_in_one() {
+        let message = SPOStakeDistributionMessage {
+            epoch: 42,
+            spos: spdd(7).into_iter().collect(),
+        };
+        let mut recorder = Recorder::default();
+        SPDDAssembler::stream_whole(&message, &mut recorder);
+        assert_eq!(recorder.totals.total_active, 21_000);
+        assert_eq!(recorder.chunk_sizes, vec![7]);
+    }
+
+    #[test]
+    fn empty_distribution_has_no_pool_chunks() -> Result<()> {
+        let chunks: Vec<_> = spdd_chunks(3, BTreeMap::new(), 100).collect();
+        assert_eq!(chunks.len(), 2);
+
+        let mut assembler = SPDDAssembler::default();
+        assert!(assembler.push(&chunks[0])?.is_none());
+        assert!(assembler.push(&chunks[1])?.expect("complete SPDD").spos.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_chunk_is_an_error() {
+        let chunks: Vec<_> = spdd_chunks(42, spdd(7), 3).collect();
+        let mut assembler = SPDDAssembler::default();
+        assembler.push(&chunks[0]).unwrap();
+        assembler.push(&chunks[1]).unwrap();
+        assert!(assembler.push(&chunks[3]).is_err());
+
+        // Starts afresh with the next header
+        assert!(assembler.push(&chunks[0]).unwrap().is_none());
+    }
+
+    #[test]
+    fn reset_drops_a_partial_distribution() {
+        let chunks: Vec<_> = spdd_chunks(42, spdd(7), 3).collect();
+        let mut assembler = SPDDAssembler::default();
+        assembler.push(&chunks[0]).unwrap();
+        assembler.push(&chunks[1]).unwrap();
+        assembler.reset();
+        assert!(assembler.push(&chunks[4]).is_err());
+    }
+}
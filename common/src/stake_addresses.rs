@@ -10,10 +10,7 @@ use rayon::prelude::*;
 use serde_with::{hex::Hex, serde_as};
 use std::collections::HashSet;
 use std::{
-    collections::{
-        hash_map::{Entry, Iter, Values},
-        BTreeMap, HashMap,
-    },
+    collections::{hash_map::Entry, BTreeMap, HashMap},
     sync::atomic::AtomicU64,
 };
 use tracing::error;
@@ -46,23 +43,64 @@ pub struct AccountState {
     pub address_state: StakeAddressState,
 }
 
-#[derive(Default, Debug)]
+/// Number of shards the stake addresses are split over, by the first byte of their credential
+const SHARD_COUNT: usize = 16;
+
+/// Outcome of applying one stake delta, with the address state either side of it
+#[derive(Debug)]
+pub struct StakeDeltaOutcome {
+    pub previous: Option<StakeAddressState>,
+    pub current: Option<StakeAddressState>,
+    pub result: Result<()>,
+}
+
+#[derive(Debug)]
 pub struct StakeAddressMap {
-    inner: HashMap<StakeAddress, StakeAddressState>,
+    /// Stake address states, sharded by credential prefix so deltas can be applied in parallel
+    shards: Vec<HashMap<StakeAddress, StakeAddressState>>,
 
     /// Reverse indexing for tracking which stake addresses delegate to a given DRep credential.
     drep_delegates: HashMap<DRepCredential, HashSet<StakeAddress>>,
 }
 
+impl Default for StakeAddressMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StakeAddressMap {
     #[inline]
     pub fn new() -> Self {
         Self {
-            inner: HashMap::new(),
+            shards: (0..SHARD_COUNT).map(|_| HashMap::new()).collect(),
             drep_delegates: HashMap::new(),
         }
     }
 
+    #[inline]
+    fn shard_index(stake_address: &StakeAddress) -> usize {
+        stake_address.get_hash().as_ref()[0] as usize % SHARD_COUNT
+    }
+
+    #[inline]
+    fn shard(&self, stake_address: &StakeAddress) -> &HashMap<StakeAddress, StakeAddressState> {
+        &self.shards[Self::shard_index(stake_address)]
+    }
+
+    #[inline]
+    fn shard_mut(
+        &mut self,
+        stake_address: &StakeAddress,
+    ) -> &mut HashMap<StakeAddress, StakeAddressState> {
+        &mut self.shards[Self::shard_index(stake_address)]
+    }
+
+    /// All stake addresses in parallel, across the shards
+    fn par_iter(&self) -> impl ParallelIterator<Item = (&StakeAddress, &StakeAddressState)> {
+        self.shards.par_iter().flat_map(|shard| shard.par_iter())
+    }
+
     #[inline]
     fn remove_drep_delegate(&mut self, stake_address: &StakeAddress, drep: Option<&DRepChoice>) {
         let Some(drep_cred) = drep.and_then(DRepChoice::to_credential) else {
@@ -88,12 +126,12 @@ impl StakeAddressMap {
 
     #[inline]
     pub fn get(&self, stake_address: &StakeAddress) -> Option<StakeAddressState> {
-        self.inner.get(stake_address).cloned()
+        self.shard(stake_address).get(stake_address).cloned()
     }
 
     #[inline]
     pub fn get_mut(&mut self, stake_address: &StakeAddress) -> Option<&mut StakeAddressState> {
-        self.inner.get_mut(stake_address)
+        self.shard_mut(stake_address).get_mut(stake_address)
     }
 
     #[inline]
@@ -104,7 +142,8 @@ impl StakeAddressMap {
     ) -> Option<StakeAddressState> {
         let current_drep = stake_address_state.delegated_drep.clone();
 
-        let old_stake_address = self.inner.insert(stake_address.clone(), stake_address_state);
+        let old_stake_address =
+            self.shard_mut(&stake_address).insert(stake_address.clone(), stake_address_state);
 
         let old_drep = old_stake_address.as_ref().and_then(|s| s.delegated_drep.as_ref());
         if old_drep != current_drep.as_ref() {
@@ -116,7 +155,7 @@ impl StakeAddressMap {
 
     #[inline]
     pub fn remove(&mut self, stake_address: &StakeAddress) -> Option<StakeAddressState> {
-        let old_stake_address = self.inner.remove(stake_address);
+        let old_stake_address = self.shard_mut(stake_address).remove(stake_address);
         let old_drep = old_stake_address.as_ref().and_then(|s| s.delegated_drep.as_ref());
         self.remove_drep_delegate(stake_address, old_drep);
         old_stake_address
@@ -127,27 +166,27 @@ impl StakeAddressMap {
         &mut self,
         stake_address: StakeAddress,
     ) -> Entry<'_, StakeAddress, StakeAddressState> {
-        self.inner.entry(stake_address)
+        self.shard_mut(&stake_address).entry(stake_address)
     }
 
     #[inline]
-    pub fn values(&self) -> Values<'_, StakeAddress, StakeAddressState> {
-        self.inner.values()
+    pub fn values(&self) -> impl Iterator<Item = &StakeAddressState> {
+        self.shards.iter().flat_map(|shard| shard.values())
     }
 
     #[inline]
-    pub fn iter(&self) -> Iter<'_, StakeAddress, StakeAddressState> {
-        self.inner.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&StakeAddress, &StakeAddressState)> {
+        self.shards.iter().flat_map(|shard| shard.iter())
     }
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.inner.len()
+        self.shards.iter().map(HashMap::len).sum()
     }
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+        self.shards.iter().all(HashMap::is_empty)
     }
 
     #[inline]
@@ -162,7 +201,7 @@ impl StakeAddressMap {
         let live_delegators = AtomicU64::new(0);
 
         // Par Iter stake addresses values
-        self.inner.par_iter().for_each(|(_, sas)| {
+        self.par_iter().for_each(|(_, sas)| {
             total_live_stakes.fetch_add(sas.utxo_value, std::sync::atomic::Ordering::Relaxed);
             if sas.delegated_spo.as_ref().map(|d_spo| d_spo.eq(spo)).unwrap_or(false) {
                 live_stake.fetch_add(
@@ -183,13 +222,28 @@ impl StakeAddressMap {
         }
     }
 
+    /// Get the live stake and count of all delegators to pools, and the rewards
+    /// of all stake addresses, as (live_stake, delegators, rewards)
+    pub fn get_delegation_totals(&self) -> (u64, u64, u64) {
+        self.values().fold((0, 0, 0), |(live_stake, delegators, rewards), sas| {
+            if sas.delegated_spo.is_some() {
+                (
+                    live_stake + sas.utxo_value + sas.rewards,
+                    delegators + 1,
+                    rewards + sas.rewards,
+                )
+            } else {
+                (live_stake, delegators, rewards + sas.rewards)
+            }
+        })
+    }
+
     /// Get Pool's Live Stake (same order as spos)
     pub fn get_pools_live_stakes(&self, spos: &[PoolId]) -> Vec<u64> {
         let mut live_stakes_map = HashMap::<PoolId, u64>::new();
 
         // Collect the SPO keys and UTXO
         let sas_data: Vec<(PoolId, u64)> = self
-            .inner
             .values()
             .filter_map(|sas| sas.delegated_spo.as_ref().map(|spo| (*spo, sas.utxo_value)))
             .collect();
@@ -207,7 +261,6 @@ impl StakeAddressMap {
     pub fn get_pool_delegators(&self, pool_operator: &PoolId) -> Vec<(StakeAddress, u64)> {
         // Find stake addresses delegated to pool_operator
         let delegators: Vec<(StakeAddress, u64)> = self
-            .inner
             .iter()
             .filter_map(|(stake_address, sas)| match sas.delegated_spo.as_ref() {
                 Some(delegated_spo) => {
@@ -226,8 +279,7 @@ impl StakeAddressMap {
 
     /// Get the stake addresses delegated to a pool.
     pub fn get_pool_delegator_addresses(&self, pool_operator: &PoolId) -> Vec<StakeAddress> {
-        self.inner
-            .iter()
+        self.iter()
             .filter_map(|(stake_address, sas)| {
                 (sas.delegated_spo.as_ref() == Some(pool_operator)).then_some(stake_address.clone())
             })
@@ -238,7 +290,6 @@ impl StakeAddressMap {
     pub fn get_drep_delegators(&self, drep: &DRepChoice) -> Vec<(StakeAddress, u64)> {
         // Find stake addresses delegated to drep
         let delegators: Vec<(StakeAddress, u64)> = self
-            .inner
             .iter()
             .filter_map(|(stake_address, sas)| match sas.delegated_drep.as_ref() {
                 Some(delegated_drep) => {
@@ -260,8 +311,7 @@ impl StakeAddressMap {
         &self,
         drep_credential: &DRepCredential,
     ) -> Vec<StakeAddress> {
-        self.inner
-            .iter()
+        self.iter()
             .filter_map(|(stake_address, sas)| {
                 (sas.delegated_drep.as_ref().and_then(DRepChoice::to_credential).as_ref()
                     == Some(drep_credential))
@@ -355,7 +405,6 @@ impl StakeAddressMap {
         // because imbl::OrdMap doesn't work in Rayon
         // Collect the SPO keys and UTXO, reward values
         let sas_data: Vec<(PoolId, (u64, u64))> = self
-            .inner
             .values()
             .filter_map(|sas| {
                 sas.delegated_spo.as_ref().map(|spo| (*spo, (sas.utxo_value, sas.rewards)))
@@ -397,7 +446,7 @@ impl StakeAddressMap {
             .iter()
             .filter_map(|(pool_id, reg)| {
                 use DelegatedStakeDefaultVote::*;
-                let sas = self.inner.get(&reg.reward_account)?;
+                let sas = self.shard(&reg.reward_account).get(&reg.reward_account)?;
                 match sas.delegated_drep {
                     Some(DRepChoice::Abstain) => Some((*pool_id, AlwaysAbstain)),
                     Some(DRepChoice::NoConfidence) => Some((*pool_id, AlwaysNoConfidence)),
@@ -411,7 +460,6 @@ impl StakeAddressMap {
     /// <PoolId -> (Stake Key, Active Stakes Amount)>
     pub fn dump_spdd_state(&self) -> HashMap<PoolId, Vec<(StakeAddress, u64)>> {
         let entries: Vec<_> = self
-            .inner
             .par_iter()
             .filter_map(|(key, sas)| {
                 sas.delegated_spo.as_ref().map(|spo| (*spo, (key.clone(), sas.utxo_value)))
@@ -438,7 +486,7 @@ impl StakeAddressMap {
             .iter()
             .map(|(cred, _)| (cred.clone(), AtomicU64::new(0)))
             .collect::<BTreeMap<_, _>>();
-        self.inner.iter().collect::<Vec<_>>().par_iter().for_each(|(stake_address, state)| {
+        self.par_iter().for_each(|(stake_address, state)| {
             let Some(drep) = state.delegated_drep.clone() else {
                 return;
             };
@@ -452,7 +500,7 @@ impl StakeAddressMap {
             let Some(total) = total else {
                 return;
             };
-            let proposal_deposit = proposal_deposits.get(*stake_address).copied().unwrap_or(0);
+            let proposal_deposit = proposal_deposits.get(stake_address).copied().unwrap_or(0);
             let stake = state.utxo_value + state.rewards + proposal_deposit;
             total.fetch_add(stake, std::sync::atomic::Ordering::Relaxed);
         });
@@ -536,7 +584,7 @@ impl StakeAddressMap {
 
     /// Remove all delegations to a given SPO
     pub fn remove_all_delegations_to(&mut self, spo: &PoolId) {
-        for sas in self.inner.values_mut() {
+        for sas in self.shards.iter_mut().flat_map(|shard| shard.values_mut()) {
             if sas.delegated_spo.as_ref() == Some(spo) {
                 sas.delegated_spo = None;
             }
@@ -547,12 +595,12 @@ impl StakeAddressMap {
     /// Uses 'reverse index' for O(k) clearing, where k = number of delegators.
     pub fn deregister_drep(&mut self, drep_credential: &DRepCredential) {
         let Some(delegators) = self.drep_delegates.remove(drep_credential) else {
-            // If there are no delegators, there is nothing to remove from StakeAddressMap::inner
+            // If there are no delegators, there is nothing to remove from StakeAddressMap::shards
             return;
         };
 
         for stake_address in delegators {
-            if let Some(sas) = self.inner.get_mut(&stake_address) {
+            if let Some(sas) = self.get_mut(&stake_address) {
                 if sas.delegated_drep.as_ref().and_then(DRepChoice::to_credential).as_ref()
                     == Some(drep_credential)
                 {
@@ -570,7 +618,7 @@ impl StakeAddressMap {
     ) -> Result<Option<DRepChoice>> {
         let prev_drep = {
             let sas = self
-                .inner
+                .shard(stake_address)
                 .get(stake_address)
                 .ok_or_else(|| anyhow!("Invalid stake address: {stake_address}"))?;
 
@@ -587,7 +635,7 @@ impl StakeAddressMap {
             self.add_drep_delegate(stake_address, Some(drep));
         }
 
-        let sas = self.inner.get_mut(stake_address).unwrap();
+        let sas = self.get_mut(stake_address).unwrap();
         sas.delegated_drep = Some(drep.clone());
 
         Ok(prev_drep)
@@ -624,12 +672,19 @@ impl StakeAddressMap {
 
     /// Stake Delta
     pub fn process_stake_delta(&mut self, stake_delta: &StakeAddressDelta) -> Result<()> {
+        Self::apply_stake_delta(self.shard_mut(&stake_delta.stake_address), stake_delta)
+    }
+
+    fn apply_stake_delta(
+        shard: &mut HashMap<StakeAddress, StakeAddressState>,
+        stake_delta: &StakeAddressDelta,
+    ) -> Result<()> {
         // Use the full stake address directly - no need to extract hash!
         let stake_address = &stake_delta.stake_address;
 
         // Stake addresses don't need to be registered if they aren't used for
         // stake or drep delegation, but we need to track them in case they are later
-        let sas = self.entry(stake_address.clone()).or_default();
+        let sas = shard.entry(stake_address.clone()).or_default();
         if let Err(e) = update_value_with_delta(&mut sas.utxo_value, stake_delta.delta) {
             bail!("Applying delta to stake address {}: {e}", stake_address);
         }
@@ -637,6 +692,47 @@ impl StakeAddressMap {
         Ok(())
     }
 
+    /// Apply a block's stake deltas, each shard in parallel. Deltas to the same address
+    /// are applied in their given order, and outcomes are returned in delta order, so the
+    /// result is the same as applying them one by one.
+    pub fn process_stake_deltas(
+        &mut self,
+        stake_deltas: &[StakeAddressDelta],
+    ) -> Vec<StakeDeltaOutcome> {
+        let mut by_shard: Vec<Vec<usize>> = vec![Vec::new(); SHARD_COUNT];
+        for (index, stake_delta) in stake_deltas.iter().enumerate() {
+            by_shard[Self::shard_index(&stake_delta.stake_address)].push(index);
+        }
+
+        let mut outcomes: Vec<(usize, StakeDeltaOutcome)> = self
+            .shards
+            .par_iter_mut()
+            .zip(by_shard.par_iter())
+            .flat_map_iter(|(shard, indices)| {
+                indices
+                    .iter()
+                    .map(|&index| {
+                        let stake_delta = &stake_deltas[index];
+                        let previous = shard.get(&stake_delta.stake_address).cloned();
+                        let result = Self::apply_stake_delta(shard, stake_delta);
+                        let current = shard.get(&stake_delta.stake_address).cloned();
+                        (
+                            index,
+                            StakeDeltaOutcome {
+                                previous,
+                                current,
+                                result,
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        outcomes.sort_unstable_by_key(|(index, _)| *index);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
     /// Withdraw
     pub fn process_withdrawal(&mut self, withdrawal: &Withdrawal) -> Result<()> {
         let stake_address = &withdrawal.address;
@@ -1015,6 +1111,45 @@ mod tests {
             // Value should remain unchanged after error
             assert_eq!(stake_addresses.get(&stake_address).unwrap().utxo_value, 50);
         }
+
+        #[test]
+        fn test_batch_deltas_match_sequential_application() {
+            let stake_delta = |hash: KeyHash, delta: i64| StakeAddressDelta {
+                stake_address: create_stake_address(hash),
+                addresses: Vec::new(),
+                tx_count: 1,
+                delta,
+            };
+            let deltas = vec![
+                stake_delta(STAKE_KEY_HASH, 100),
+                stake_delta(STAKE_KEY_HASH_2, 20),
+                stake_delta(STAKE_KEY_HASH, -30),
+                stake_delta(STAKE_KEY_HASH_3, -5),
+                stake_delta(STAKE_KEY_HASH_2, 7),
+                stake_delta(STAKE_KEY_HASH, -80),
+            ];
+
+            let mut sequential = StakeAddressMap::new();
+            let sequential_results: Vec<bool> =
+                deltas.iter().map(|delta| sequential.process_stake_delta(delta).is_ok()).collect();
+
+            let mut batched = StakeAddressMap::new();
+            let outcomes = batched.process_stake_deltas(&deltas);
+
+            assert_eq!(
+                outcomes.iter().map(|outcome| outcome.result.is_ok()).collect::<Vec<_>>(),
+                sequential_results
+            );
+            assert_eq!(outcomes[0].previous, None);
+            assert_eq!(outcomes[2].previous.as_ref().unwrap().utxo_value, 100);
+            assert_eq!(outcomes[2].current.as_ref().unwrap().utxo_value, 70);
+            assert_eq!(outcomes[5].current.as_ref().unwrap().utxo_value, 70);
+            for hash in [STAKE_KEY_HASH, STAKE_KEY_HASH_2, STAKE_KEY_HASH_3] {
+                let stake_address = create_stake_address(hash);
+                assert_eq!(batched.get(&stake_address), sequential.get(&stake_address));
+            }
+            assert_eq!(batched.len(), sequential.len());
+        }
     }
 
     mod reward_tests {
@@ -1468,6 +1603,37 @@ mod tests {
             assert_eq!(stakes, vec![1000, 2000]);
         }
 
+        #[test]
+        fn test_get_delegation_totals() {
+            let mut stake_addresses = StakeAddressMap::new();
+
+            let addr1 = create_stake_address(STAKE_KEY_HASH);
+            let addr2 = create_stake_address(STAKE_KEY_HASH_2);
+            let addr3 = create_stake_address(STAKE_KEY_HASH_3);
+
+            stake_addresses.register_stake_address(&addr1);
+            stake_addresses.register_stake_address(&addr2);
+            stake_addresses.register_stake_address(&addr3);
+            stake_addresses.record_stake_delegation(&addr1, &SPO_HASH);
+            stake_addresses.record_stake_delegation(&addr2, &SPO_HASH_2);
+
+            for (addr, delta) in [(&addr1, 1000), (&addr2, 2000), (&addr3, 4000)] {
+                stake_addresses
+                    .process_stake_delta(&StakeAddressDelta {
+                        stake_address: addr.clone(),
+                        addresses: Vec::new(),
+                        tx_count: 1,
+                        delta,
+                    })
+                    .unwrap();
+            }
+            stake_addresses.add_to_reward(&addr1, 100);
+            stake_addresses.add_to_reward(&addr3, 400);
+
+            // The undelegated address counts towards rewards only
+            assert_eq!(stake_addresses.get_delegation_totals(), (3100, 2, 500));
+        }
+
         #[test]
         fn test_get_pool_delegators() {
             let mut stake_addresses = StakeAddressMap::new();
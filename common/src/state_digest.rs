@@ -0,0 +1,340 @@
+//! Canonical digests of module state, so two nodes (or a node and a reference
+//! dump) can be compared cheaply at each epoch boundary.
+//!
+//! A digest is a multiset hash: each entry of the state is encoded as CBOR and
+//! hashed, and the hashes are summed modulo 2^256. The result doesn't depend on
+//! the order the entries are visited in, so a state held in a `HashMap` has the
+//! same digest on every node, and entries can be added and removed (or partial
+//! digests merged) without revisiting the rest of the state.
+//!
+//! Entries should be tagged with what they are (e.g. `("pool", id, registration)`)
+//! so that entries of different kinds can't collide, and must not contain maps
+//! with an unstable iteration order - add those entry by entry instead.
+//!
+//! A state can also be split into named sections (pools, pending retirements, ...)
+//! with a digest each, published alongside the whole so that a divergence can be
+//! narrowed down to the section. The sections merge to the digest of the whole.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use caryatid_sdk::Context;
+use config::Config;
+use cryptoxide::hashing::blake2b::Blake2b;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    configuration::{get_bool_flag, get_string_flag},
+    crypto::keyhash_256,
+    hash::Hash,
+    messages::{CardanoMessage, Message, StateDigestMessage},
+    BlockInfo,
+};
+
+/// Whether modules publish digests of their state - set under `[global.state-digest]`
+const DEFAULT_STATE_DIGEST_ENABLED: (&str, bool) = ("state-digest.enabled", false);
+
+/// Topic digests are published on, shared by all modules
+pub const DEFAULT_STATE_DIGEST_TOPIC: (&str, &str) = ("state-digest.topic", "cardano.state.digest");
+
+/// Order-independent hash of a set of entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetDigest {
+    /// Sum of the entry hashes, as a little-endian 256-bit integer
+    sum: [u64; 4],
+
+    /// Number of entries
+    count: u64,
+}
+
+impl SetDigest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry, encoded as CBOR
+    pub fn insert<T: Serialize + ?Sized>(&mut self, entry: &T) -> Result<()> {
+        self.insert_bytes(&minicbor_serde::to_vec(entry)?);
+        Ok(())
+    }
+
+    /// Remove an entry previously added with [`Self::insert`]
+    pub fn remove<T: Serialize + ?Sized>(&mut self, entry: &T) -> Result<()> {
+        self.remove_bytes(&minicbor_serde::to_vec(entry)?);
+        Ok(())
+    }
+
+    /// Add an entry which is already encoded
+    pub fn insert_bytes(&mut self, bytes: &[u8]) {
+        self.add(Self::entry_limbs(bytes));
+        self.count = self.count.wrapping_add(1);
+    }
+
+    /// Remove an entry which is already encoded
+    pub fn remove_bytes(&mut self, bytes: &[u8]) {
+        let limbs = Self::entry_limbs(bytes);
+        let mut borrow = false;
+        for (sum, limb) in self.sum.iter_mut().zip(limbs) {
+            let (value, b1) = sum.overflowing_sub(limb);
+            let (value, b2) = value.overflowing_sub(borrow as u64);
+            *sum = value;
+            borrow = b1 || b2;
+        }
+        self.count = self.count.wrapping_sub(1);
+    }
+
+    /// Add all the entries of another digest
+    pub fn merge(&mut self, other: &SetDigest) {
+        self.add(other.sum);
+        self.count = self.count.wrapping_add(other.count);
+    }
+
+    /// Number of entries in the digest
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Final hash of the set
+    pub fn finish(&self) -> Hash<32> {
+        let mut context = Blake2b::<256>::new();
+        for limb in self.sum {
+            context.update_mut(&limb.to_le_bytes());
+        }
+        context.update_mut(&self.count.to_le_bytes());
+        Hash::new(context.finalize())
+    }
+
+    fn add(&mut self, limbs: [u64; 4]) {
+        let mut carry = false;
+        for (sum, limb) in self.sum.iter_mut().zip(limbs) {
+            let (value, c1) = sum.overflowing_add(limb);
+            let (value, c2) = value.overflowing_add(carry as u64);
+            *sum = value;
+            carry = c1 || c2;
+        }
+    }
+
+    fn entry_limbs(bytes: &[u8]) -> [u64; 4] {
+        let hash = keyhash_256(bytes);
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(hash.as_inner().chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes"));
+        }
+        limbs
+    }
+}
+
+/// Hashes of a whole state and of each of its sections, by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionDigests {
+    pub digest: Hash<32>,
+    pub sections: BTreeMap<String, Hash<32>>,
+}
+
+/// State with a canonical digest.
+///
+/// States held partly in an asynchronous store (the UTxO set) build their
+/// digest directly with a [`SetDigest`] instead.
+pub trait StateDigest {
+    /// Add every entry of the state to the digest
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()>;
+
+    /// Digest of each section of the state, by name - every entry in exactly one.
+    /// States which aren't split up have none
+    fn section_digests(&self) -> Result<BTreeMap<&'static str, SetDigest>> {
+        Ok(BTreeMap::new())
+    }
+
+    /// Hash of the whole state, independent of how it is held in memory
+    fn state_digest(&self) -> Result<Hash<32>> {
+        let mut digest = SetDigest::new();
+        self.add_to_digest(&mut digest)?;
+        Ok(digest.finish())
+    }
+
+    /// Hash of the whole state, and of each of its sections
+    fn state_section_digests(&self) -> Result<SectionDigests> {
+        let sections = self.section_digests()?;
+        let mut digest = SetDigest::new();
+        if sections.is_empty() {
+            self.add_to_digest(&mut digest)?;
+        }
+        for section in sections.values() {
+            digest.merge(section);
+        }
+        Ok(SectionDigests {
+            digest: digest.finish(),
+            sections: sections
+                .into_iter()
+                .map(|(name, section)| (name.to_string(), section.finish()))
+                .collect(),
+        })
+    }
+}
+
+/// Add every entry of a state split into sections, for [`StateDigest::add_to_digest`]
+pub fn add_sections_to_digest(state: &impl StateDigest, digest: &mut SetDigest) -> Result<()> {
+    for section in state.section_digests()?.values() {
+        digest.merge(section);
+    }
+    Ok(())
+}
+
+/// Publishes a module's state digest at each epoch boundary
+pub struct StateDigestPublisher {
+    context: Arc<Context<Message>>,
+    topic: String,
+    module: &'static str,
+}
+
+impl StateDigestPublisher {
+    /// Create a publisher if state digests are enabled
+    pub fn from_config(
+        context: Arc<Context<Message>>,
+        config: &Config,
+        module: &'static str,
+    ) -> Option<Self> {
+        if !get_bool_flag(config, DEFAULT_STATE_DIGEST_ENABLED) {
+            return None;
+        }
+
+        let topic = get_string_flag(config, DEFAULT_STATE_DIGEST_TOPIC);
+        info!("Publishing {module} state digests on '{topic}'");
+        Some(Self {
+            context,
+            topic,
+            module,
+        })
+    }
+
+    /// Publish the digest of the state at the end of the epoch before `block`,
+    /// which must be the first block of a new epoch
+    pub async fn publish(&self, block: &BlockInfo, digest: Result<Hash<32>>) {
+        let digest = digest.map(|digest| SectionDigests {
+            digest,
+            sections: BTreeMap::new(),
+        });
+        self.publish_sections(block, digest).await;
+    }
+
+    /// Publish the digests of a state and its sections, as [`Self::publish`]
+    pub async fn publish_state(&self, block: &BlockInfo, state: &impl StateDigest) {
+        self.publish_sections(block, state.state_section_digests()).await;
+    }
+
+    async fn publish_sections(&self, block: &BlockInfo, digests: Result<SectionDigests>) {
+        let epoch = block.epoch.saturating_sub(1);
+        let SectionDigests { digest, sections } = match digests {
+            Ok(digests) => digests,
+            Err(e) => {
+                warn!(
+                    module = self.module,
+                    epoch, "Can't compute state digest: {e:#}"
+                );
+                return;
+            }
+        };
+
+        info!(module = self.module, epoch, %digest, "State digest");
+        let message = Arc::new(Message::Cardano((
+            block.clone(),
+            CardanoMessage::StateDigest(StateDigestMessage {
+                module: self.module.to_string(),
+                epoch,
+                digest,
+                sections,
+            }),
+        )));
+        if let Err(e) = self.context.publish(&self.topic, message).await {
+            warn!("Could not publish {} state digest: {e:#}", self.module);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_of(entries: &[(&str, u64)]) -> SetDigest {
+        let mut digest = SetDigest::new();
+        for entry in entries {
+            digest.insert(entry).unwrap();
+        }
+        digest
+    }
+
+    #[test]
+    fn digest_is_independent_of_order() {
+        let forwards = digest_of(&[("a", 1), ("b", 2), ("c", 3)]);
+        let backwards = digest_of(&[("c", 3), ("b", 2), ("a", 1)]);
+        assert_eq!(forwards, backwards);
+        assert_eq!(forwards.finish(), backwards.finish());
+        assert_ne!(
+            forwards.finish(),
+            digest_of(&[("a", 1), ("b", 2), ("c", 4)]).finish()
+        );
+    }
+
+    #[test]
+    fn removing_and_merging_match_building_from_scratch() {
+        let mut digest = digest_of(&[("a", 1), ("b", 2), ("c", 3)]);
+        digest.remove(&("b", 2)).unwrap();
+        assert_eq!(digest, digest_of(&[("a", 1), ("c", 3)]));
+
+        let mut merged = digest_of(&[("a", 1)]);
+        merged.merge(&digest_of(&[("c", 3)]));
+        assert_eq!(merged, digest);
+        assert_eq!(merged.len(), 2);
+    }
+
+    /// Pools and retirements, in sections
+    struct Sectioned;
+
+    impl StateDigest for Sectioned {
+        fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+            add_sections_to_digest(self, digest)
+        }
+
+        fn section_digests(&self) -> Result<BTreeMap<&'static str, SetDigest>> {
+            Ok(BTreeMap::from([
+                ("pools", digest_of(&[("a", 1), ("b", 2)])),
+                ("retirements", digest_of(&[("c", 3)])),
+            ]))
+        }
+    }
+
+    #[test]
+    fn sections_merge_to_the_whole() {
+        let digests = Sectioned.state_section_digests().unwrap();
+        assert_eq!(digests.digest, Sectioned.state_digest().unwrap());
+        assert_eq!(
+            digests.digest,
+            digest_of(&[("a", 1), ("b", 2), ("c", 3)]).finish()
+        );
+        assert_eq!(
+            digests.sections.keys().collect::<Vec<_>>(),
+            vec!["pools", "retirements"]
+        );
+        assert_eq!(
+            digests.sections["retirements"],
+            digest_of(&[("c", 3)]).finish()
+        );
+    }
+
+    #[test]
+    fn empty_digest_differs_from_cancelled_entries() {
+        let empty = SetDigest::new();
+        assert!(empty.is_empty());
+
+        // A duplicate entry is counted twice rather than cancelling out
+        let twice = digest_of(&[("a", 1), ("a", 1)]);
+        assert_ne!(twice.finish(), empty.finish());
+        assert_ne!(twice.finish(), digest_of(&[("a", 1)]).finish());
+    }
+}
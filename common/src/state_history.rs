@@ -1,12 +1,65 @@
 //! Generic state history
 //! Keeps per-block state for rollbacks or per-epoch state for historical lookups
 //! Use imbl collections in the state to avoid memory explosion!
+//!
+//! Retention for all block histories in a process can be set once at startup - a
+//! number of blocks to keep instead of k, and a memory budget for each history which
+//! can estimate its size. Entries past either limit are compacted away: every entry
+//! is a complete state, so the oldest one kept becomes the immutable base. A history
+//! compacted to fewer blocks than k can't roll back as far as the chain may, which
+//! is warned of.
 
-use std::collections::VecDeque;
-use tracing::info;
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
 
 use crate::params::SECURITY_PARAMETER_K;
 
+/// Retention applied to every block history in the process
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HistoryRetention {
+    /// Blocks of history to keep, instead of k
+    pub blocks: Option<u64>,
+
+    /// Memory budget for each history which estimates its size
+    pub max_bytes: Option<usize>,
+}
+
+static RETENTION: OnceLock<HistoryRetention> = OnceLock::new();
+
+/// Set the retention, before any modules are started
+pub fn set_retention(retention: HistoryRetention) -> Result<()> {
+    if let Some(blocks) = retention.blocks.filter(|b| *b < SECURITY_PARAMETER_K) {
+        warn!("Keeping {blocks} blocks of state history, rollbacks deeper than that will fail");
+    }
+    RETENTION.set(retention).map_err(|_| anyhow!("State history retention already set"))
+}
+
+fn retention() -> &'static HistoryRetention {
+    RETENTION.get_or_init(HistoryRetention::default)
+}
+
+/// Size of a state history, for monitoring
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StateHistoryMetrics {
+    /// Name of the history - a module may keep several
+    pub name: String,
+    pub entries: usize,
+    /// Estimated size, if the history can estimate it
+    pub bytes: Option<usize>,
+    /// Entries dropped to stay within the memory budget
+    pub compacted: u64,
+}
+
+static METRICS: Mutex<BTreeMap<String, StateHistoryMetrics>> = Mutex::new(BTreeMap::new());
+
+/// Latest metrics of every state history in the process
+pub fn metrics() -> Vec<StateHistoryMetrics> {
+    METRICS.lock().unwrap().values().cloned().collect()
+}
+
 pub enum StateHistoryStore {
     Bounded(u64), // Used for rollbacks, bounded at k
     Unbounded,    // Used for historical lookups, unbounded
@@ -14,7 +67,7 @@ pub enum StateHistoryStore {
 
 impl StateHistoryStore {
     pub fn default_block_store() -> Self {
-        Self::Bounded(SECURITY_PARAMETER_K)
+        Self::Bounded(retention().blocks.unwrap_or(SECURITY_PARAMETER_K))
     }
     pub fn default_epoch_store() -> Self {
         Self::Bounded(2)
@@ -24,6 +77,8 @@ impl StateHistoryStore {
 struct HistoryEntry<S> {
     index: u64,
     state: S,
+    /// Estimated size, if the history can estimate it
+    size: usize,
 }
 
 /// Generic state history - S is the state to be stored
@@ -31,22 +86,56 @@ pub struct StateHistory<S> {
     /// History, one per block or epoch
     history: VecDeque<HistoryEntry<S>>,
 
-    /// Module name
-    module: String,
+    /// Name of the history, for logs and metrics
+    name: String,
 
     store: StateHistoryStore,
+
+    /// Estimates the memory used by an entry, for the memory budget
+    size_estimate: Option<fn(&S) -> usize>,
+
+    /// Estimated size of all entries
+    bytes: usize,
+
+    /// Memory budget, from the process retention unless set for this history
+    max_bytes: Option<usize>,
+
+    /// Entries dropped to stay within the memory budget
+    compacted: u64,
+
+    /// Whether the memory budget has left less than k blocks of history
+    short_of_k: bool,
 }
 
 impl<S: Clone + Default> StateHistory<S> {
     /// Construct
-    pub fn new(module: &str, store: StateHistoryStore) -> Self {
+    pub fn new(name: &str, store: StateHistoryStore) -> Self {
         Self {
             history: VecDeque::new(),
-            module: module.to_string(),
+            name: name.to_string(),
             store,
+            size_estimate: None,
+            bytes: 0,
+            max_bytes: retention().max_bytes,
+            compacted: 0,
+            short_of_k: false,
         }
     }
 
+    /// Estimate the size of each entry, so the history can keep to the memory budget.
+    /// imbl collections share most of their nodes between entries, so counting them
+    /// in full gives an upper bound, which compacts early rather than late.
+    pub fn with_size_estimate(mut self, size_estimate: fn(&S) -> usize) -> Self {
+        self.size_estimate = Some(size_estimate);
+        self
+    }
+
+    /// Set the memory budget for this history, instead of the process retention
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
     /// Get the current state (if any), direct ref
     pub fn current(&self) -> Option<&S> {
         self.history.back().map(|entry| &entry.state)
@@ -70,13 +159,16 @@ impl<S: Clone + Default> StateHistory<S> {
             if entry.index >= index {
                 info!(
                     "{} rolling back state to {} removing block {}",
-                    self.module, index, entry.index
+                    self.name, index, entry.index
                 );
-                self.history.pop_back();
+                if let Some(entry) = self.history.pop_back() {
+                    self.bytes -= entry.size;
+                }
             } else {
                 break;
             }
         }
+        self.record_metrics();
         self.get_current_state()
     }
 
@@ -108,11 +200,11 @@ impl<S: Clone + Default> StateHistory<S> {
     /// Commit new state without checking the block number
     /// TODO: enhance block number logic to commit state without check (for bootstrapping)
     pub fn commit_forced(&mut self, state: S) {
-        self.history.push_back(HistoryEntry { index: 0, state });
+        self.push(0, state);
     }
 
     pub fn bootstrap_init_with(&mut self, state: S, index: u64) {
-        self.history.push_back(HistoryEntry { index, state });
+        self.push(index, state);
     }
 
     /// Commit the new state
@@ -121,17 +213,64 @@ impl<S: Clone + Default> StateHistory<S> {
             StateHistoryStore::Bounded(k) => {
                 while let Some(entry) = self.history.front() {
                     if (index - entry.index) > k {
-                        self.history.pop_front();
+                        self.pop_front();
                     } else {
                         break;
                     }
                 }
-                self.history.push_back(HistoryEntry { index, state });
+                self.push(index, state);
+
+                if let Some(max_bytes) = self.max_bytes {
+                    while self.bytes > max_bytes && self.history.len() > 1 {
+                        self.pop_front();
+                        self.compacted += 1;
+                    }
+                    self.check_depth(index, k, max_bytes);
+                }
             }
             StateHistoryStore::Unbounded => {
-                self.history.push_back(HistoryEntry { index, state });
+                self.push(index, state);
             }
         }
+        self.record_metrics();
+    }
+
+    /// Warn once the memory budget leaves less history than rollbacks of up to k blocks
+    /// need, and again if it recovers and falls short later
+    fn check_depth(&mut self, index: u64, bound: u64, max_bytes: usize) {
+        let needed = bound.min(SECURITY_PARAMETER_K);
+        let depth = self.history.front().map(|entry| index - entry.index).unwrap_or_default();
+        let short_of_k = depth < needed && self.compacted > 0;
+        if short_of_k && !self.short_of_k {
+            warn!(
+                "{} keeps {depth} of the {needed} entries of history rollbacks may need, to \
+                 stay within {max_bytes} bytes - deeper rollbacks will fail",
+                self.name
+            );
+        }
+        self.short_of_k = short_of_k;
+    }
+
+    fn push(&mut self, index: u64, state: S) {
+        let size = self.size_estimate.map(|estimate| estimate(&state)).unwrap_or_default();
+        self.bytes += size;
+        self.history.push_back(HistoryEntry { index, state, size });
+    }
+
+    fn pop_front(&mut self) {
+        if let Some(entry) = self.history.pop_front() {
+            self.bytes -= entry.size;
+        }
+    }
+
+    fn record_metrics(&self) {
+        let metrics = StateHistoryMetrics {
+            name: self.name.clone(),
+            entries: self.history.len(),
+            bytes: self.size_estimate.map(|_| self.bytes),
+            compacted: self.compacted,
+        };
+        METRICS.lock().unwrap().insert(self.name.clone(), metrics);
     }
 }
 
@@ -151,5 +290,149 @@ impl<S: Clone> StateHistory<S> {
     /// Clear the history
     pub fn clear(&mut self) {
         self.history.clear();
+        self.bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use imbl::Vector;
+
+    /// Blocks applied so far, as a stand-in for a module's ledger state
+    type AppliedBlocks = Vector<u64>;
+
+    /// Apply blocks as a state module does: clone the current state, update it, commit
+    fn apply(history: &mut StateHistory<AppliedBlocks>, blocks: impl Iterator<Item = (u64, u64)>) {
+        for (number, id) in blocks {
+            let mut state = history.get_current_state();
+            state.push_back(id);
+            history.commit(number, state);
+        }
+    }
+
+    #[test]
+    fn rollback_removes_states_from_index() {
+        let mut history = StateHistory::new("test", StateHistoryStore::default_block_store());
+        apply(&mut history, (1..=5).map(|n| (n, n)));
+
+        let state = history.get_rolled_back_state(4);
+
+        assert_eq!(state, Vector::from(vec![1, 2, 3]));
+        assert_eq!(history.len(), 3);
+        assert!(history.get_by_index(4).is_none());
+    }
+
+    #[test]
+    fn rollback_and_fork_converges_with_fork_applied_directly() {
+        let main_chain = (1..=10).map(|n| (n, n));
+        let fork = (7..=12).map(|n| (n, 100 + n));
+
+        let mut switched = StateHistory::new("switched", StateHistoryStore::default_block_store());
+        apply(&mut switched, main_chain.clone());
+        // The rollback carries the first block after the common ancestor
+        switched.get_rolled_back_state(7);
+        apply(&mut switched, fork.clone());
+
+        let mut direct = StateHistory::new("direct", StateHistoryStore::default_block_store());
+        apply(&mut direct, main_chain.take(6).chain(fork));
+
+        assert_eq!(switched.len(), direct.len());
+        for number in 1..=12 {
+            assert_eq!(switched.get_by_index(number), direct.get_by_index(number));
+        }
+        assert_eq!(switched.current(), direct.current());
+    }
+
+    #[test]
+    fn size_estimates_are_tracked_and_reported() {
+        let mut history = StateHistory::new("size-test", StateHistoryStore::Bounded(3))
+            .with_size_estimate(|state: &AppliedBlocks| state.len() * 8);
+        apply(&mut history, (1..=6).map(|n| (n, n)));
+
+        // Blocks 3 to 6 kept, holding 3 to 6 applied blocks each
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.bytes, (3 + 4 + 5 + 6) * 8);
+
+        history.get_rolled_back_state(6);
+        let reported = metrics().into_iter().find(|m| m.name == "size-test").unwrap();
+        assert_eq!(reported.entries, 3);
+        assert_eq!(reported.bytes, Some((3 + 4 + 5) * 8));
+        assert_eq!(reported.compacted, 0);
+    }
+
+    #[test]
+    fn oldest_entries_are_compacted_to_the_memory_budget() {
+        let mut history = StateHistory::new("budget-test", StateHistoryStore::Bounded(100))
+            .with_size_estimate(|state: &AppliedBlocks| state.len() * 8)
+            .with_max_bytes(Some(100));
+        apply(&mut history, (1..=4).map(|n| (n, n)));
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.bytes, (1 + 2 + 3 + 4) * 8);
+
+        // Each block past the budget drops the oldest entries until it fits again
+        apply(&mut history, (5..=6).map(|n| (n, n)));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.bytes, (5 + 6) * 8);
+        assert!(history.get_by_index(4).is_none());
+        assert_eq!(
+            history.get_by_index(5),
+            Some(&Vector::from(vec![1, 2, 3, 4, 5]))
+        );
+
+        let reported = metrics().into_iter().find(|m| m.name == "budget-test").unwrap();
+        assert_eq!(reported.entries, 2);
+        assert_eq!(reported.compacted, 4);
+        assert!(history.short_of_k);
+
+        // The latest entry is kept even if it is over the budget on its own
+        let mut history = StateHistory::new("over-budget-test", StateHistoryStore::Bounded(100))
+            .with_size_estimate(|state: &AppliedBlocks| state.len() * 8)
+            .with_max_bytes(Some(4));
+        apply(&mut history, (1..=3).map(|n| (n, n)));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.current(), Some(&Vector::from(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn budget_that_keeps_the_rollback_window_is_not_short_of_k() {
+        let mut history = StateHistory::new("window-test", StateHistoryStore::Bounded(2))
+            .with_size_estimate(|_: &AppliedBlocks| 8)
+            .with_max_bytes(Some(24));
+        apply(&mut history, (1..=6).map(|n| (n, n)));
+
+        // Blocks 4 to 6 kept by the bound, within the budget
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.compacted, 0);
+        assert!(!history.short_of_k);
+    }
+
+    #[test]
+    fn malformed_retention_is_an_error() {
+        let retention = |toml: &str| {
+            config::Config::builder()
+                .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap()
+                .get::<HistoryRetention>("global.state-history")
+        };
+
+        let parsed = retention("[global.state-history]\nblocks = 100").unwrap();
+        assert_eq!(parsed.blocks, Some(100));
+        assert!(retention("[global.state-history]\nblocks = \"many\"").is_err());
+        assert!(retention("[global.state-history]\nmax-byte = 1024").is_err());
+    }
+
+    #[test]
+    fn metrics_are_kept_per_history() {
+        let mut blocks = StateHistory::new("module.blocks", StateHistoryStore::Bounded(10));
+        let mut epochs = StateHistory::new("module.epochs", StateHistoryStore::Bounded(10));
+        apply(&mut blocks, (1..=3).map(|n| (n, n)));
+        apply(&mut epochs, (1..=2).map(|n| (n, n)));
+
+        let reported = metrics();
+        let entries = |name: &str| reported.iter().find(|m| m.name == name).unwrap().entries;
+        assert_eq!(entries("module.blocks"), 3);
+        assert_eq!(entries("module.epochs"), 2);
     }
 }
@@ -0,0 +1,203 @@
+//! Stopping a sync at a set epoch or slot, for partial syncs in tests and tools.
+//!
+//! With `stop-at-epoch` and/or `stop-at-slot` set under `[global.sync]`, the peer
+//! network interface and consensus let no block past that point through. The
+//! first of them to know the stop point has been reached publishes a SyncComplete
+//! message - once per process - so that anything waiting for a partial sync to
+//! finish can subscribe to it rather than guess with a timeout.
+//!
+//! The end of an epoch is only known when the first block of the next one
+//! arrives, so a stop at an epoch completes then. A stop at a slot completes on a
+//! block in that slot, or else the first one after it. Once stopped, ingestion
+//! stays stopped.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use caryatid_sdk::Context;
+use config::Config;
+use tracing::{info, warn};
+
+use crate::{
+    configuration::get_string_flag,
+    messages::{CardanoMessage, Message, SyncCompleteMessage},
+    BlockInfo,
+};
+
+/// Config key for the last epoch to sync
+const STOP_AT_EPOCH_KEY: &str = "sync.stop-at-epoch";
+
+/// Config key for the last slot to sync
+const STOP_AT_SLOT_KEY: &str = "sync.stop-at-slot";
+
+/// Topic the completion of the sync is announced on
+pub const DEFAULT_SYNC_COMPLETE_TOPIC: (&str, &str) =
+    ("sync.complete-topic", "cardano.sync.complete");
+
+/// Whether the completion has been announced in this process
+static COMPLETED: AtomicBool = AtomicBool::new(false);
+
+/// Last epoch and/or slot to sync
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StopPoint {
+    pub epoch: Option<u64>,
+    pub slot: Option<u64>,
+}
+
+impl StopPoint {
+    /// The configured stop point, if there is one
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let get = |key| config.get_int(key).ok().and_then(|v| u64::try_from(v).ok());
+        let point = Self {
+            epoch: get(STOP_AT_EPOCH_KEY),
+            slot: get(STOP_AT_SLOT_KEY),
+        };
+        (point.epoch.is_some() || point.slot.is_some()).then_some(point)
+    }
+
+    /// Whether a block is beyond the stop point
+    pub fn is_past(&self, block: &BlockInfo) -> bool {
+        self.epoch.is_some_and(|epoch| block.epoch > epoch)
+            || self.slot.is_some_and(|slot| block.slot > slot)
+    }
+
+    /// Whether a block is known to be the last one before the stop point
+    pub fn is_at(&self, block: &BlockInfo) -> bool {
+        self.slot == Some(block.slot)
+    }
+}
+
+/// Holds back blocks past the stop point on behalf of a module
+pub struct SyncStop {
+    context: Arc<Context<Message>>,
+    topic: String,
+    point: StopPoint,
+    module: &'static str,
+
+    /// Last block let through
+    last: Option<BlockInfo>,
+    stopped: bool,
+}
+
+impl SyncStop {
+    /// Create a stop if a stop point is configured
+    pub fn from_config(
+        context: Arc<Context<Message>>,
+        config: &Config,
+        module: &'static str,
+    ) -> Option<Self> {
+        let point = StopPoint::from_config(config)?;
+        let topic = get_string_flag(config, DEFAULT_SYNC_COMPLETE_TOPIC);
+        info!(
+            "{module} stopping the sync at epoch {:?}, slot {:?}, announced on '{topic}'",
+            point.epoch, point.slot
+        );
+        Some(Self {
+            context,
+            topic,
+            point,
+            module,
+            last: None,
+            stopped: false,
+        })
+    }
+
+    /// Whether to let a block through. The first block past the stop point
+    /// completes the sync, and none are let through after it.
+    pub async fn admit(&mut self, block: &BlockInfo) -> bool {
+        if self.stopped {
+            return false;
+        }
+        if self.point.is_past(block) {
+            self.complete(block).await;
+            return false;
+        }
+
+        self.last = Some(block.clone());
+        if self.point.is_at(block) {
+            self.complete(block).await;
+        }
+        true
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Announce the end of the sync, with the last block let through - or, if
+    /// the sync started past the stop point, the block which showed it
+    async fn complete(&mut self, block: &BlockInfo) {
+        self.stopped = true;
+        let last = self.last.clone().unwrap_or_else(|| block.clone());
+        info!(
+            "{} stopped the sync after block {} in slot {}, epoch {}",
+            self.module, last.number, last.slot, last.epoch
+        );
+        if COMPLETED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let message = Arc::new(Message::Cardano((
+            last,
+            CardanoMessage::SyncComplete(SyncCompleteMessage {
+                stop_at_epoch: self.point.epoch,
+                stop_at_slot: self.point.slot,
+            }),
+        )));
+        if let Err(e) = self.context.publish(&self.topic, message).await {
+            warn!("Could not publish sync completion: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockHash, BlockIntent, BlockStatus, Era};
+
+    fn block(epoch: u64, slot: u64) -> BlockInfo {
+        BlockInfo {
+            status: BlockStatus::Volatile,
+            intent: BlockIntent::Apply,
+            slot,
+            number: slot,
+            hash: BlockHash::default(),
+            epoch,
+            epoch_slot: 0,
+            new_epoch: false,
+            is_new_era: false,
+            tip_slot: None,
+            timestamp: 0,
+            era: Era::Conway,
+        }
+    }
+
+    #[test]
+    fn stop_point_bounds_epoch_and_slot() {
+        let epoch = StopPoint {
+            epoch: Some(10),
+            slot: None,
+        };
+        assert!(!epoch.is_past(&block(10, 1000)));
+        assert!(epoch.is_past(&block(11, 1001)));
+        assert!(!epoch.is_at(&block(10, 1000)));
+
+        let slot = StopPoint {
+            epoch: None,
+            slot: Some(1000),
+        };
+        assert!(slot.is_at(&block(10, 1000)));
+        assert!(!slot.is_past(&block(10, 1000)));
+        assert!(slot.is_past(&block(10, 1001)));
+
+        // Whichever comes first
+        let both = StopPoint {
+            epoch: Some(10),
+            slot: Some(5000),
+        };
+        assert!(both.is_past(&block(11, 1001)));
+        assert!(both.is_past(&block(9, 5001)));
+    }
+}
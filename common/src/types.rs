@@ -5,7 +5,7 @@
 use crate::certificate::TxCertificateIdentifier;
 use crate::crypto::keyhash_224;
 use crate::drep::{Anchor, DRepVotingThresholds};
-use crate::script::Datum;
+use crate::script::{Datum, PlutusVersion};
 use crate::UTxOIdentifier;
 // Re-export certificate types for backward compatibility
 pub use crate::certificate::{
@@ -146,6 +146,22 @@ impl TryFrom<u8> for Era {
     }
 }
 
+impl Era {
+    /// Era of a ledger running the given major protocol version. Hard forks within an
+    /// era (Alonzo 5 to 6, Babbage 7 to 8, Conway 9 onwards) do not change it.
+    pub fn from_protocol_major(major: u64) -> Era {
+        match major {
+            0..=1 => Era::Byron,
+            2 => Era::Shelley,
+            3 => Era::Allegra,
+            4 => Era::Mary,
+            5..=6 => Era::Alonzo,
+            7..=8 => Era::Babbage,
+            _ => Era::Conway,
+        }
+    }
+}
+
 impl Display for Era {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
@@ -1311,14 +1327,21 @@ pub struct PoolUpdateEvent {
     pub tx_identifier: TxIdentifier,
     pub cert_index: u64,
     pub action: PoolUpdateAction,
+    /// Parameters set by a registration or update certificate, None for retirements
+    pub parameters: Option<PoolRegistration>,
 }
 
 impl PoolUpdateEvent {
-    pub fn register_event(tx_identifier: TxIdentifier, cert_index: u64) -> Self {
+    pub fn register_event(
+        tx_identifier: TxIdentifier,
+        cert_index: u64,
+        parameters: PoolRegistration,
+    ) -> Self {
         Self {
             tx_identifier,
             cert_index,
             action: PoolUpdateAction::Registered,
+            parameters: Some(parameters),
         }
     }
 
@@ -1327,6 +1350,7 @@ impl PoolUpdateEvent {
             tx_identifier,
             cert_index,
             action: PoolUpdateAction::Deregistered,
+            parameters: None,
         }
     }
 }
@@ -1516,15 +1540,65 @@ impl CostModel {
     pub fn as_vec(&self) -> &Vec<i64> {
         &self.0
     }
+
+    /// Number of parameters the language was introduced with
+    pub fn min_params(version: PlutusVersion) -> usize {
+        match version {
+            PlutusVersion::V1 => 166,
+            PlutusVersion::V2 => 175,
+            PlutusVersion::V3 => 251,
+        }
+    }
+
+    /// Parameters as the evaluator should see them. A model with fewer parameters than
+    /// the language was introduced with has the missing ones set to the maximum, so that
+    /// the builtins they price exhaust any budget rather than running for free.
+    pub fn for_evaluation(&self, version: PlutusVersion) -> CostModel {
+        let mut params = self.0.clone();
+        let min_params = Self::min_params(version);
+        if params.len() < min_params {
+            params.resize(min_params, i64::MAX);
+        }
+        CostModel(params)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
 pub struct CostModels {
     pub plutus_v1: Option<CostModel>,
     pub plutus_v2: Option<CostModel>,
     pub plutus_v3: Option<CostModel>,
 }
 
+impl CostModels {
+    /// Cost model for a language, or None if it is not yet available, in which case
+    /// scripts of that language cannot be run
+    pub fn get(&self, version: PlutusVersion) -> Option<&CostModel> {
+        match version {
+            PlutusVersion::V1 => self.plutus_v1.as_ref(),
+            PlutusVersion::V2 => self.plutus_v2.as_ref(),
+            PlutusVersion::V3 => self.plutus_v3.as_ref(),
+        }
+    }
+
+    /// Languages whose cost model differs from `other`
+    pub fn changed_languages(&self, other: &CostModels) -> Vec<PlutusVersion> {
+        [PlutusVersion::V1, PlutusVersion::V2, PlutusVersion::V3]
+            .into_iter()
+            .filter(|version| self.get(*version) != other.get(*version))
+            .collect()
+    }
+
+    /// All models with the evaluation defaults applied
+    pub fn for_evaluation(&self) -> CostModels {
+        CostModels {
+            plutus_v1: self.plutus_v1.as_ref().map(|m| m.for_evaluation(PlutusVersion::V1)),
+            plutus_v2: self.plutus_v2.as_ref().map(|m| m.for_evaluation(PlutusVersion::V2)),
+            plutus_v3: self.plutus_v3.as_ref().map(|m| m.for_evaluation(PlutusVersion::V3)),
+        }
+    }
+}
+
 #[derive(
     Default, serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, minicbor::Decode,
 )]
@@ -2350,13 +2424,61 @@ pub struct AssetMetadata {
     pub cip25_version: Option<AssetMetadataStandard>,
     pub cip68_metadata: Option<Vec<u8>>,
     pub cip68_version: Option<AssetMetadataStandard>,
+    /// Display fields decoded from the raw metadata, CIP-68 taking precedence over CIP-25
+    pub normalized: Option<NormalizedAssetMetadata>,
+    /// Times the on-chain metadata has been set or replaced, e.g. by a re-mint
+    pub updates: u64,
+    /// Transaction which last changed the on-chain metadata
+    pub updated_tx: Option<TxIdentifier>,
+}
+
+/// Where normalized asset metadata was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AssetMetadataSource {
+    /// 721-label transaction metadata of the minting transaction
+    CIP25,
+    /// Inline datum of the CIP-68 reference token
+    CIP68,
+}
+
+/// Display fields of a token's metadata, common to the CIP-25 and CIP-68 standards
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NormalizedAssetMetadata {
+    pub source: AssetMetadataSource,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub media_type: Option<String>,
+    pub ticker: Option<String>,
+    pub url: Option<String>,
+    pub logo: Option<String>,
+    pub decimals: Option<u64>,
+}
+
+impl NormalizedAssetMetadata {
+    pub fn new(source: AssetMetadataSource) -> Self {
+        Self {
+            source,
+            name: None,
+            description: None,
+            image: None,
+            media_type: None,
+            ticker: None,
+            url: None,
+            logo: None,
+            decimals: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct AssetMintRecord {
     pub tx: TxIdentifier,
+    pub epoch: u64,
     pub amount: u64,
     pub burn: bool,
+    /// Total supply after this mint or burn
+    pub supply: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -2427,6 +2549,47 @@ impl AddressTotals {
         self.tx_count += 1;
     }
 
+    /// What is held now: everything received less everything sent, leaving out
+    /// assets which have all been sent on. Fails if more was sent than received,
+    /// which means the totals are inconsistent.
+    pub fn balance(&self) -> Result<ValueMap> {
+        let Some(lovelace) = self.received.lovelace.checked_sub(self.sent.lovelace) else {
+            bail!(
+                "Address totals sent {} lovelace but only received {}",
+                self.sent.lovelace,
+                self.received.lovelace
+            );
+        };
+        let mut balance = ValueMap {
+            lovelace,
+            assets: HashMap::new(),
+        };
+
+        for (policy, assets) in &self.received.assets {
+            for (name, received) in assets {
+                let sent = self
+                    .sent
+                    .assets
+                    .get(policy)
+                    .and_then(|assets| assets.get(name))
+                    .copied()
+                    .unwrap_or(0);
+                let Some(held) = received.checked_sub(sent) else {
+                    bail!(
+                        "Address totals sent {sent} of asset {}.{} but only received {received}",
+                        hex::encode(policy),
+                        hex::encode(name.as_slice())
+                    );
+                };
+                if held > 0 {
+                    balance.assets.entry(*policy).or_default().insert(*name, held);
+                }
+            }
+        }
+
+        Ok(balance)
+    }
+
     fn apply_asset(
         target: &mut HashMap<PolicyId, HashMap<AssetName, u64>>,
         policy: PolicyId,
@@ -2613,6 +2776,37 @@ mod tests {
         assert_eq!(NetworkId::from("sanchonet".to_string()), NetworkId::Testnet);
     }
 
+    #[test]
+    fn era_from_protocol_major() {
+        assert_eq!(Era::from_protocol_major(1), Era::Byron);
+        assert_eq!(Era::from_protocol_major(2), Era::Shelley);
+        assert_eq!(Era::from_protocol_major(6), Era::Alonzo);
+        assert_eq!(Era::from_protocol_major(8), Era::Babbage);
+        assert_eq!(Era::from_protocol_major(10), Era::Conway);
+    }
+
+    #[test]
+    fn short_cost_models_are_padded_for_evaluation() {
+        let models = CostModels {
+            plutus_v1: Some(CostModel::new(vec![1; 170])),
+            plutus_v2: None,
+            plutus_v3: Some(CostModel::new(vec![1; 10])),
+        };
+        let evaluated = models.for_evaluation();
+
+        assert_eq!(evaluated.plutus_v1, models.plutus_v1);
+        assert!(evaluated.get(PlutusVersion::V2).is_none());
+        let v3 = evaluated.get(PlutusVersion::V3).unwrap().as_vec();
+        assert_eq!(v3.len(), 251);
+        assert_eq!(v3[9], 1);
+        assert_eq!(v3[10], i64::MAX);
+
+        assert_eq!(
+            models.changed_languages(&evaluated),
+            vec![PlutusVersion::V3]
+        );
+    }
+
     #[test]
     fn network_id_from_mainnet_is_mainnet() {
         assert_eq!(NetworkId::from("mainnet".to_string()), NetworkId::Mainnet);
@@ -2641,4 +2835,60 @@ mod tests {
         };
         assert_eq!(error.to_string(), message);
     }
+
+    #[test]
+    fn address_totals_balance_nets_sent_from_received() {
+        let policy = PolicyId::from([1u8; 28]);
+        let kept = AssetName::new(b"kept").unwrap();
+        let spent = AssetName::new(b"spent").unwrap();
+        let totals = AddressTotals {
+            received: ValueMap {
+                lovelace: 10_000_000,
+                assets: HashMap::from([(policy, HashMap::from([(kept, 5), (spent, 2)]))]),
+            },
+            sent: ValueMap {
+                lovelace: 4_000_000,
+                assets: HashMap::from([(policy, HashMap::from([(kept, 1), (spent, 2)]))]),
+            },
+            tx_count: 3,
+        };
+
+        let balance = totals.balance().unwrap();
+        assert_eq!(balance.lovelace, 6_000_000);
+        assert_eq!(
+            balance.assets,
+            HashMap::from([(policy, HashMap::from([(kept, 4)]))])
+        );
+    }
+
+    #[test]
+    fn address_totals_balance_fails_when_more_is_sent_than_received() {
+        let policy = PolicyId::from([1u8; 28]);
+        let name = AssetName::new(b"token").unwrap();
+        let overspent_lovelace = AddressTotals {
+            received: ValueMap {
+                lovelace: 1_000_000,
+                assets: HashMap::new(),
+            },
+            sent: ValueMap {
+                lovelace: 2_000_000,
+                assets: HashMap::new(),
+            },
+            tx_count: 2,
+        };
+        assert!(overspent_lovelace.balance().is_err());
+
+        let overspent_asset = AddressTotals {
+            received: ValueMap {
+                lovelace: 1_000_000,
+                assets: HashMap::from([(policy, HashMap::from([(name, 1)]))]),
+            },
+            sent: ValueMap {
+                lovelace: 0,
+                assets: HashMap::from([(policy, HashMap::from([(name, 3)]))]),
+            },
+            tx_count: 2,
+        };
+        assert!(overspent_asset.balance().is_err());
+    }
 }
@@ -1,21 +1,60 @@
-use crate::{messages::RawBlockMessage, BlockInfo};
+//! Local cache of the blocks fetched from upstream, so a node can be restarted
+//! without fetching the chain again.
+//!
+//! Blocks are kept in numbered chunks of JSON, each with a checksum alongside it
+//! so that a damaged chunk is found before it is replayed. Chunks written before
+//! checksums were added have none, and are trusted as they are.
+
+use crate::{crypto::keyhash_256, messages::RawBlockMessage, BlockInfo};
 use anyhow::{anyhow, bail, Context, Result};
 use std::{
-    fs::File,
-    io::{BufReader, Write},
+    collections::VecDeque,
+    fs,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+/// Chunks of records kept back while compacting, so that a rollback up to this
+/// deep can still drop the records it replaced
+const COMPACTION_WINDOW_CHUNKS: usize = 3;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UpstreamCacheRecord {
     pub id: BlockInfo,
     pub message: Arc<RawBlockMessage>,
 }
 
+/// Summary of the cache contents, from [`UpstreamCacheImpl::verify`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpstreamCacheReport {
+    /// Good chunks, up to the first bad one
+    pub chunks: usize,
+    pub records: usize,
+    pub bytes: u64,
+
+    /// Number of the first and last cached blocks
+    pub first_block: Option<u64>,
+    pub last_block: Option<u64>,
+
+    /// Rollbacks in the records - compaction drops the records they replaced
+    pub rollbacks: usize,
+
+    /// First bad chunk and what is wrong with it - it and every later chunk
+    /// are dropped by a repair
+    pub bad_chunk: Option<(usize, String)>,
+}
+
 pub trait Storage {
     fn read_chunk(&mut self, chunk_no: usize) -> Result<Vec<UpstreamCacheRecord>>;
     fn write_chunk(&mut self, chunk_no: usize, chunk: &[UpstreamCacheRecord]) -> Result<()>;
+
+    /// Numbers of the chunks present, in order
+    fn chunk_numbers(&mut self) -> Result<Vec<usize>>;
+
+    /// Size of a chunk in bytes
+    fn chunk_size(&mut self, chunk_no: usize) -> Result<u64>;
+
+    fn remove_chunk(&mut self, chunk_no: usize) -> Result<()>;
 }
 
 pub struct FileStorage {
@@ -32,6 +71,19 @@ impl FileStorage {
     fn get_file_name(&self, chunk_no: usize) -> PathBuf {
         self.path.join(format!("chunk-{chunk_no}.json"))
     }
+
+    fn get_checksum_file_name(&self, chunk_no: usize) -> PathBuf {
+        self.path.join(format!("chunk-{chunk_no}.sum"))
+    }
+
+    /// Replace a file in one step, so it is never left half written
+    fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        fs::write(&temp, data).with_context(|| format!("could not write {}", temp.display()))?;
+        fs::rename(&temp, path).with_context(|| format!("could not replace {}", path.display()))
+    }
 }
 
 pub type UpstreamCache = UpstreamCacheImpl<FileStorage>;
@@ -71,13 +123,154 @@ impl<S: Storage> UpstreamCacheImpl<S> {
         }
     }
 
+    /// Move to the first record of the cache, which is the start of the chain
+    /// unless older chunks have been expired
     pub fn start_reading(&mut self) -> Result<()> {
-        self.current_chunk = 0;
+        self.current_chunk = self.storage.chunk_numbers()?.first().copied().unwrap_or(0);
         self.current_record = 0;
-        self.chunk_cached = self.storage.read_chunk(0)?;
+        self.chunk_cached = self.storage.read_chunk(self.current_chunk)?;
         Ok(())
     }
 
+    /// Check every chunk against its checksum, and that the chunks follow on
+    /// from each other
+    pub fn verify(&mut self) -> Result<UpstreamCacheReport> {
+        let mut report = UpstreamCacheReport::default();
+        let chunks = self.storage.chunk_numbers()?;
+        let mut previous: Option<usize> = None;
+        for (index, &chunk_no) in chunks.iter().enumerate() {
+            if previous.is_some_and(|previous| chunk_no != previous + 1) {
+                report.bad_chunk = Some((chunk_no, "follows a missing chunk".to_string()));
+                break;
+            }
+            previous = Some(chunk_no);
+
+            let records = match self.storage.read_chunk(chunk_no) {
+                Ok(records) => records,
+                Err(e) => {
+                    report.bad_chunk = Some((chunk_no, format!("{e:#}")));
+                    break;
+                }
+            };
+            let is_last = index + 1 == chunks.len();
+            if records.len() > self.density || (!is_last && records.len() != self.density) {
+                report.bad_chunk = Some((
+                    chunk_no,
+                    format!("has {} records, expected {}", records.len(), self.density),
+                ));
+                break;
+            }
+
+            for record in &records {
+                if report.last_block.is_some_and(|last| record.id.number <= last) {
+                    report.rollbacks += 1;
+                }
+                report.first_block.get_or_insert(record.id.number);
+                report.last_block = Some(record.id.number);
+            }
+            report.chunks += 1;
+            report.records += records.len();
+            report.bytes += self.storage.chunk_size(chunk_no)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Drop the first bad chunk found by [`Self::verify`] and every chunk after
+    /// it, so the cache again holds an unbroken run of blocks. Returns the
+    /// number of chunks dropped.
+    pub fn repair(&mut self) -> Result<usize> {
+        let removed = match self.verify()?.bad_chunk {
+            Some((bad_chunk, _)) => self.remove_chunks_from(bad_chunk)?,
+            None => 0,
+        };
+        self.start_reading()?;
+        Ok(removed)
+    }
+
+    /// Rewrite the cache without the records replaced by rollbacks, which would
+    /// otherwise be replayed along with the blocks which replaced them. Returns
+    /// the number of records dropped.
+    pub fn compact(&mut self) -> Result<usize> {
+        let chunks = self.storage.chunk_numbers()?;
+        let Some(&first_chunk) = chunks.first() else {
+            return Ok(0);
+        };
+
+        // Chunks are only ever rewritten after they have been read
+        let window = COMPACTION_WINDOW_CHUNKS * self.density;
+        let mut pending = VecDeque::new();
+        let mut last_written: Option<u64> = None;
+        let mut out_chunk = first_chunk;
+        let mut dropped = 0;
+        for chunk_no in chunks {
+            for record in self.storage.read_chunk(chunk_no)? {
+                while pending
+                    .back()
+                    .is_some_and(|last: &UpstreamCacheRecord| last.id.number >= record.id.number)
+                {
+                    pending.pop_back();
+                    dropped += 1;
+                }
+                if pending.is_empty() && last_written.is_some_and(|last| last >= record.id.number) {
+                    bail!(
+                        "Rollback to block {} is too deep to compact",
+                        record.id.number
+                    );
+                }
+
+                pending.push_back(record);
+                if pending.len() >= window + self.density {
+                    let chunk: Vec<_> = pending.drain(..self.density).collect();
+                    last_written = chunk.last().map(|record| record.id.number);
+                    self.storage.write_chunk(out_chunk, &chunk)?;
+                    out_chunk += 1;
+                }
+            }
+        }
+
+        for chunk in pending.make_contiguous().chunks(self.density) {
+            self.storage.write_chunk(out_chunk, chunk)?;
+            out_chunk += 1;
+        }
+        self.remove_chunks_from(out_chunk)?;
+        self.start_reading()?;
+        Ok(dropped)
+    }
+
+    /// Drop the oldest chunks until the cache fits in `max_bytes`, always keeping
+    /// the latest one. Returns the number of chunks dropped.
+    pub fn expire(&mut self, max_bytes: u64) -> Result<usize> {
+        let chunks = self.storage.chunk_numbers()?;
+        let mut total = 0;
+        for &chunk_no in &chunks {
+            total += self.storage.chunk_size(chunk_no)?;
+        }
+
+        let mut removed = 0;
+        for &chunk_no in chunks.iter().take(chunks.len().saturating_sub(1)) {
+            if total <= max_bytes {
+                break;
+            }
+            total -= self.storage.chunk_size(chunk_no)?;
+            self.storage.remove_chunk(chunk_no)?;
+            removed += 1;
+        }
+        self.start_reading()?;
+        Ok(removed)
+    }
+
+    fn remove_chunks_from(&mut self, first_chunk: usize) -> Result<usize> {
+        let mut removed = 0;
+        for chunk_no in self.storage.chunk_numbers()? {
+            if chunk_no >= first_chunk {
+                self.storage.remove_chunk(chunk_no)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
     /// Returns true if we're in the middle of cache, returns false if pointer points
     /// to first record after the end of cache.
     fn has_record(&self) -> bool {
@@ -146,9 +339,20 @@ impl Storage for FileStorage {
             return Ok(vec![]);
         }
 
-        let file = File::open(&path)?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader).with_context(|| {
+        let data = fs::read(&path)?;
+        let checksum_path = self.get_checksum_file_name(chunk_no);
+        if checksum_path.try_exists()? {
+            let expected = fs::read_to_string(&checksum_path)?;
+            let actual = keyhash_256(&data).to_string();
+            if expected.trim() != actual {
+                bail!(
+                    "Checksum mismatch in upstream cache chunk {}",
+                    path.display()
+                );
+            }
+        }
+
+        serde_json::from_slice(&data).with_context(|| {
             format!(
                 "Error reading upstream cache chunk JSON from {}",
                 path.display()
@@ -157,19 +361,54 @@ impl Storage for FileStorage {
     }
 
     fn write_chunk(&mut self, chunk_no: usize, data: &[UpstreamCacheRecord]) -> Result<()> {
-        let mut file =
-            File::create(self.get_file_name(chunk_no)).context("could not write chunk")?;
-        file.write_all(&serde_json::to_vec(data)?)?;
-        Ok(())
+        let data = serde_json::to_vec(data)?;
+        Self::write_file(&self.get_file_name(chunk_no), &data).context("could not write chunk")?;
+        Self::write_file(
+            &self.get_checksum_file_name(chunk_no),
+            keyhash_256(&data).to_string().as_bytes(),
+        )
+        .context("could not write chunk checksum")
+    }
+
+    fn chunk_numbers(&mut self) -> Result<Vec<usize>> {
+        let mut chunks = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let name = entry?.file_name();
+            let chunk_no = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("chunk-"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|number| number.parse().ok());
+            chunks.extend(chunk_no);
+        }
+        chunks.sort_unstable();
+        Ok(chunks)
+    }
+
+    fn chunk_size(&mut self, chunk_no: usize) -> Result<u64> {
+        Ok(fs::metadata(self.get_file_name(chunk_no))?.len())
+    }
+
+    fn remove_chunk(&mut self, chunk_no: usize) -> Result<()> {
+        fs::remove_file(self.get_file_name(chunk_no))?;
+        match fs::remove_file(self.get_checksum_file_name(chunk_no)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::upstream_cache::{Storage, UpstreamCacheImpl, UpstreamCacheRecord};
+    use crate::upstream_cache::{
+        FileStorage, Storage, UpstreamCache, UpstreamCacheImpl, UpstreamCacheRecord,
+    };
     use crate::{messages::RawBlockMessage, BlockHash, BlockInfo, BlockIntent, BlockStatus, Era};
-    use anyhow::Result;
-    use std::{collections::HashMap, sync::Arc};
+    use anyhow::{bail, Result};
+    use std::{
+        collections::{BTreeMap, HashSet},
+        sync::Arc,
+    };
 
     fn blk(n: u64) -> BlockInfo {
         BlockInfo {
@@ -200,18 +439,56 @@ mod test {
 
     #[derive(Default)]
     struct TestStorage {
-        rec: HashMap<usize, Vec<UpstreamCacheRecord>>,
+        rec: BTreeMap<usize, Vec<UpstreamCacheRecord>>,
+        corrupt: HashSet<usize>,
     }
 
     impl Storage for TestStorage {
         fn read_chunk(&mut self, chunk_no: usize) -> Result<Vec<UpstreamCacheRecord>> {
+            if self.corrupt.contains(&chunk_no) {
+                bail!("Checksum mismatch in chunk {chunk_no}");
+            }
             Ok(self.rec.get(&chunk_no).unwrap_or(&vec![]).clone())
         }
 
         fn write_chunk(&mut self, chunk_no: usize, chunk: &[UpstreamCacheRecord]) -> Result<()> {
             self.rec.insert(chunk_no, chunk.to_vec());
+            self.corrupt.remove(&chunk_no);
             Ok(())
         }
+
+        fn chunk_numbers(&mut self) -> Result<Vec<usize>> {
+            Ok(self.rec.keys().copied().collect())
+        }
+
+        fn chunk_size(&mut self, chunk_no: usize) -> Result<u64> {
+            Ok(self.rec.get(&chunk_no).map_or(0, |chunk| chunk.len() as u64))
+        }
+
+        fn remove_chunk(&mut self, chunk_no: usize) -> Result<()> {
+            self.rec.remove(&chunk_no);
+            self.corrupt.remove(&chunk_no);
+            Ok(())
+        }
+    }
+
+    fn cache_of(numbers: impl IntoIterator<Item = u64>) -> Result<UpstreamCacheImpl<TestStorage>> {
+        let mut cache = UpstreamCacheImpl::<TestStorage>::new_impl(TestStorage::default());
+        cache.density = 3;
+        for n in numbers {
+            cache.write_record(&ucr(n, n as usize, n as usize))?;
+        }
+        Ok(cache)
+    }
+
+    fn read_all<S: Storage>(cache: &mut UpstreamCacheImpl<S>) -> Result<Vec<u64>> {
+        cache.start_reading()?;
+        let mut numbers = Vec::new();
+        while let Some(record) = cache.read_record()? {
+            numbers.push(record.id.number);
+            cache.next_record()?;
+        }
+        Ok(numbers)
     }
 
     #[test]
@@ -301,4 +578,91 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_verify_and_repair() -> Result<()> {
+        let mut cache = cache_of(0..11)?;
+        let report = cache.verify()?;
+        assert_eq!(report.chunks, 4);
+        assert_eq!(report.records, 11);
+        assert_eq!(report.first_block, Some(0));
+        assert_eq!(report.last_block, Some(10));
+        assert_eq!(report.bad_chunk, None);
+        assert_eq!(cache.repair()?, 0);
+
+        cache.storage.corrupt.insert(2);
+        let report = cache.verify()?;
+        assert_eq!(report.chunks, 2);
+        assert_eq!(report.bad_chunk.map(|(chunk, _)| chunk), Some(2));
+
+        assert_eq!(cache.repair()?, 2);
+        assert_eq!(read_all(&mut cache)?, (0..6).collect::<Vec<_>>());
+        assert_eq!(cache.verify()?.bad_chunk, None);
+
+        // Carries on writing after the last good record
+        cache.write_record(&ucr(6, 6, 6))?;
+        assert_eq!(read_all(&mut cache)?, (0..7).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_finds_missing_chunk() -> Result<()> {
+        let mut cache = cache_of(0..11)?;
+        cache.storage.rec.remove(&1);
+        assert_eq!(cache.verify()?.bad_chunk.map(|(chunk, _)| chunk), Some(2));
+        assert_eq!(cache.repair()?, 2);
+        assert_eq!(read_all(&mut cache)?, vec![0, 1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_drops_rolled_back_records() -> Result<()> {
+        // Rolled back from 5 to 3, and from 8 to 7
+        let mut cache = cache_of([0, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 8, 9])?;
+        assert_eq!(cache.verify()?.rollbacks, 2);
+
+        assert_eq!(cache.compact()?, 3);
+        assert_eq!(read_all(&mut cache)?, (0..10).collect::<Vec<_>>());
+        assert_eq!(cache.storage.rec.len(), 4);
+
+        let report = cache.verify()?;
+        assert_eq!(report.rollbacks, 0);
+        assert_eq!(report.bad_chunk, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_keeps_latest_chunk() -> Result<()> {
+        let mut cache = cache_of(0..11)?;
+
+        // Sizes are record counts in the test storage
+        assert_eq!(cache.expire(6)?, 2);
+        assert_eq!(read_all(&mut cache)?, (6..11).collect::<Vec<_>>());
+        assert_eq!(cache.verify()?.bad_chunk, None);
+
+        assert_eq!(cache.expire(0)?, 1);
+        assert_eq!(read_all(&mut cache)?, vec![9, 10]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_storage_checksum() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut cache = UpstreamCache::new(dir.path())?;
+        for n in 0..3 {
+            cache.write_record(&ucr(n, n as usize, n as usize))?;
+        }
+        assert_eq!(read_all(&mut cache)?, vec![0, 1, 2]);
+        assert_eq!(FileStorage::new(dir.path())?.chunk_numbers()?, vec![0]);
+
+        let chunk = dir.path().join("chunk-0.json");
+        let data = std::fs::read_to_string(&chunk)?.replace("\"slot\":1", "\"slot\":7");
+        std::fs::write(&chunk, data)?;
+
+        let mut cache = UpstreamCache::new(dir.path())?;
+        assert!(cache.verify()?.bad_chunk.is_some());
+        assert_eq!(cache.repair()?, 1);
+        assert_eq!(read_all(&mut cache)?, Vec::<u64>::new());
+        Ok(())
+    }
 }
@@ -0,0 +1,167 @@
+//! Batched publication of a block's UTXO deltas
+//!
+//! The deltas of every transaction in a block go out together, as one
+//! [`UTXODeltasMessage`]. A block with many transactions (or the genesis UTXOs) can
+//! make that message very large, so the publisher can cap the transactions per
+//! message, splitting the block's deltas into numbered batches against the same
+//! block. Readers put the batches back together with [`UTXODeltasAssembler`] -
+//! a block is applied, validated and rolled back as a whole.
+
+use anyhow::{bail, Result};
+
+use crate::caryatid::ChunkAssembler;
+use crate::messages::{CardanoMessage, UTXODeltasBatchMessage, UTXODeltasMessage};
+use crate::TxUTxODeltas;
+
+/// The messages carrying a block's deltas - one UTXODeltas if `max_batch` is 0 or
+/// they fit within it, else UTXODeltasBatch messages of at most `max_batch`
+/// transactions each
+pub fn utxo_delta_messages(deltas: Vec<TxUTxODeltas>, max_batch: usize) -> Vec<CardanoMessage> {
+    if max_batch == 0 || deltas.len() <= max_batch {
+        return vec![CardanoMessage::UTXODeltas(UTXODeltasMessage { deltas })];
+    }
+
+    let batches = deltas.len().div_ceil(max_batch) as u64;
+    let mut deltas = deltas.into_iter();
+    (0..batches)
+        .map(|sequence| {
+            CardanoMessage::UTXODeltasBatch(UTXODeltasBatchMessage {
+                sequence,
+                batches,
+                deltas: deltas.by_ref().take(max_batch).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Reassembles a block's batched deltas
+#[derive(Default)]
+pub struct UTXODeltasAssembler {
+    received: u64,
+    deltas: Vec<TxUTxODeltas>,
+}
+
+impl UTXODeltasAssembler {
+    /// Check a batch is next in order, returning whether it is the block's last
+    fn next(&mut self, batch: &UTXODeltasBatchMessage) -> Result<bool> {
+        if batch.sequence != self.received || batch.sequence >= batch.batches {
+            let expected = self.received;
+            self.reset();
+            bail!(
+                "UTXO deltas batch {} of {} out of order, expected {expected}",
+                batch.sequence,
+                batch.batches
+            );
+        }
+
+        self.received += 1;
+        if self.received < batch.batches {
+            return Ok(false);
+        }
+        self.received = 0;
+        Ok(true)
+    }
+}
+
+impl ChunkAssembler for UTXODeltasAssembler {
+    type Chunk = UTXODeltasBatchMessage;
+    type Message = UTXODeltasMessage;
+    type Sink = dyn FnMut(&[TxUTxODeltas]) + Send;
+
+    fn push(&mut self, batch: &UTXODeltasBatchMessage) -> Result<Option<UTXODeltasMessage>> {
+        let last = self.next(batch)?;
+        self.deltas.extend_from_slice(&batch.deltas);
+        if !last {
+            return Ok(None);
+        }
+        Ok(Some(UTXODeltasMessage {
+            deltas: std::mem::take(&mut self.deltas),
+        }))
+    }
+
+    fn stream(&mut self, batch: &UTXODeltasBatchMessage, sink: &mut Self::Sink) -> Result<bool> {
+        let last = self.next(batch)?;
+        sink(&batch.deltas);
+        Ok(last)
+    }
+
+    fn stream_whole(message: &UTXODeltasMessage, sink: &mut Self::Sink) {
+        sink(&message.deltas);
+    }
+
+    fn reset(&mut self) {
+        self.received = 0;
+        self.deltas.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TxIdentifier;
+
+    fn deltas(count: u16) -> Vec<TxUTxODeltas> {
+        (0..count)
+            .map(|index| TxUTxODeltas {
+                tx_identifier: TxIdentifier::new(7, index),
+                ..TxUTxODeltas::default()
+            })
+            .collect()
+    }
+
+    fn tx_indexes(message: &UTXODeltasMessage) -> Vec<u16> {
+        message.deltas.iter().map(|d| d.tx_identifier.tx_index()).collect()
+    }
+
+    #[test]
+    fn small_blocks_are_one_message() {
+        for max_batch in [0, 5, 10] {
+            let messages = utxo_delta_messages(deltas(5), max_batch);
+            assert_eq!(messages.len(), 1);
+            assert!(matches!(messages[0], CardanoMessage::UTXODeltas(_)));
+        }
+    }
+
+    #[test]
+    fn batches_reassemble_to_the_block() -> Result<()> {
+        let messages = utxo_delta_messages(deltas(7), 3);
+        assert_eq!(messages.len(), 3);
+
+        let mut assembler = UTXODeltasAssembler::default();
+        let mut assembled = Vec::new();
+        for message in &messages {
+            let CardanoMessage::UTXODeltasBatch(batch) = message else {
+                panic!("expected a batch");
+            };
+            assert!(batch.deltas.len() <= 3);
+            assembled.extend(assembler.push(batch)?);
+        }
+        assert_eq!(assembled.len(), 1);
+        assert_eq!(tx_indexes(&assembled[0]), (0..7).collect::<Vec<_>>());
+
+        // Ready for the next block
+        let CardanoMessage::UTXODeltasBatch(first) = &messages[0] else {
+            panic!("expected a batch");
+        };
+        assert!(assembler.push(first)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_batch_is_an_error() {
+        let messages = utxo_delta_messages(deltas(7), 3);
+        let batch = |i: usize| match &messages[i] {
+            CardanoMessage::UTXODeltasBatch(batch) => batch.clone(),
+            _ => panic!("expected a batch"),
+        };
+
+        let mut assembler = UTXODeltasAssembler::default();
+        assembler.push(&batch(0)).unwrap();
+        assert!(assembler.push(&batch(2)).is_err());
+
+        // Starts afresh with the next block
+        assert!(assembler.push(&batch(0)).unwrap().is_none());
+        assembler.reset();
+        assert!(assembler.push(&batch(1)).is_err());
+    }
+}
@@ -326,6 +326,22 @@ pub enum KesValidationError {
     /// **Cause:** No OCert counter found for this issuer (not a stake pool or genesis delegate)
     #[error("No OCert Counter For Issuer: Pool ID={}", hex::encode(pool_id))]
     NoOCertCounter { pool_id: PoolId },
+    /// **Cause:** The block header has no issuer verification key
+    #[error("Missing Issuer VKey")]
+    MissingIssuerVkey,
+    /// **Cause:** The issuer verification key is not a 32 byte Ed25519 key
+    #[error("Invalid Issuer VKey: Length={}", length)]
+    InvalidIssuerVkey { length: usize },
+    /// **Cause:** The block header has no operational certificate, KES signature or
+    /// header body (e.g. a Byron header)
+    #[error("Missing Header Field: {}", field)]
+    MissingHeaderField { field: String },
+    /// **Cause:** The operational certificate's hot key is not a valid KES public key
+    #[error("Invalid OCert Hot VKey: Length={}", length)]
+    InvalidOcertHotVkey { length: usize },
+    /// **Cause:** The body signature is not a valid KES signature
+    #[error("Invalid KES Signature Bytes: Length={}", length)]
+    InvalidKesSignatureBytes { length: usize },
     /// **Cause:** Some data has incorrect bytes
     #[error("TryFromSlice: {0}")]
     TryFromSlice(String),
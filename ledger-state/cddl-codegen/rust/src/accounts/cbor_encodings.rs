@@ -0,0 +1,32 @@
+// This file was code-generated using an experimental CDDL to rust tool:
+// https://github.com/dcSpark/cddl-codegen
+
+use crate::serialization::{LenEncoding, StringEncoding};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default)]
+pub struct AccountEncoding {
+    pub len_encoding: LenEncoding,
+    pub rewards_encoding: Option<cbor_event::Sz>,
+    pub deposit_encoding: Option<cbor_event::Sz>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccountsStateEncoding {
+    pub len_encoding: LenEncoding,
+    pub orig_deser_order: Vec<usize>,
+    pub accounts_encoding: LenEncoding,
+    pub accounts_key_encoding: StringEncoding,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DrepKeyhashEncoding {
+    pub len_encoding: LenEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DrepScriptHashEncoding {
+    pub len_encoding: LenEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+}
@@ -0,0 +1,85 @@
+// This file was code-generated using an experimental CDDL to rust tool:
+// https://github.com/dcSpark/cddl-codegen
+
+pub mod serialization;
+
+use crate::common::{AddrKeyhash, Coin, Credential, Keyhash, ScriptHash};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub rewards: Coin,
+    pub deposit: Coin,
+    pub pool: Option<Keyhash>,
+    pub drep: Option<Drep>,
+}
+
+impl Account {
+    pub fn new(rewards: Coin, deposit: Coin, pool: Option<Keyhash>, drep: Option<Drep>) -> Self {
+        Self {
+            rewards,
+            deposit,
+            pool,
+            drep,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountsState {
+    pub accounts: BTreeMap<Credential, Account>,
+}
+
+impl AccountsState {
+    pub fn new(accounts: BTreeMap<Credential, Account>) -> Self {
+        Self { accounts }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Drep {
+    DrepKeyhash(DrepKeyhash),
+    DrepScriptHash(DrepScriptHash),
+    AlwaysAbstain,
+    AlwaysNoConfidence,
+}
+
+impl Drep {
+    pub fn new_drep_keyhash(addr_keyhash: AddrKeyhash) -> Self {
+        Self::DrepKeyhash(DrepKeyhash::new(addr_keyhash))
+    }
+
+    pub fn new_drep_script_hash(script_hash: ScriptHash) -> Self {
+        Self::DrepScriptHash(DrepScriptHash::new(script_hash))
+    }
+
+    pub fn new_always_abstain() -> Self {
+        Self::AlwaysAbstain
+    }
+
+    pub fn new_always_no_confidence() -> Self {
+        Self::AlwaysNoConfidence
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DrepKeyhash {
+    pub addr_keyhash: AddrKeyhash,
+}
+
+impl DrepKeyhash {
+    pub fn new(addr_keyhash: AddrKeyhash) -> Self {
+        Self { addr_keyhash }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DrepScriptHash {
+    pub script_hash: ScriptHash,
+}
+
+impl DrepScriptHash {
+    pub fn new(script_hash: ScriptHash) -> Self {
+        Self { script_hash }
+    }
+}
@@ -0,0 +1,489 @@
+// This file was code-generated using an experimental CDDL to rust tool:
+// https://github.com/dcSpark/cddl-codegen
+
+use super::*;
+use crate::error::*;
+use crate::serialization::*;
+use cbor_event::de::Deserializer;
+use cbor_event::se::Serializer;
+use std::io::{BufRead, Seek, SeekFrom, Write};
+
+impl Serialize for Account {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(4))?;
+        serializer.write_unsigned_integer(self.rewards)?;
+        serializer.write_unsigned_integer(self.deposit)?;
+        match &self.pool {
+            Some(x) => x.serialize(serializer, force_canonical),
+            None => serializer.write_special(cbor_event::Special::Null),
+        }?;
+        match &self.drep {
+            Some(x) => x.serialize(serializer, force_canonical),
+            None => serializer.write_special(cbor_event::Special::Null),
+        }?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for Account {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(4)?;
+        read_len.finish()?;
+        (|| -> Result<_, DeserializeError> {
+            let rewards = Ok(raw.unsigned_integer()? as u64)
+                .map_err(|e: DeserializeError| e.annotate("rewards"))?;
+            let deposit = Ok(raw.unsigned_integer()? as u64)
+                .map_err(|e: DeserializeError| e.annotate("deposit"))?;
+            let pool = (|| -> Result<_, DeserializeError> {
+                Ok(match raw.cbor_type()? != cbor_event::Type::Special {
+                    true => Some(Keyhash::deserialize(raw)?),
+                    false => {
+                        if raw.special()? != cbor_event::Special::Null {
+                            return Err(DeserializeFailure::ExpectedNull.into());
+                        }
+                        None
+                    }
+                })
+            })()
+            .map_err(|e| e.annotate("pool"))?;
+            let drep = (|| -> Result<_, DeserializeError> {
+                Ok(match raw.cbor_type()? != cbor_event::Type::Special {
+                    true => Some(Drep::deserialize(raw)?),
+                    false => {
+                        if raw.special()? != cbor_event::Special::Null {
+                            return Err(DeserializeFailure::ExpectedNull.into());
+                        }
+                        None
+                    }
+                })
+            })()
+            .map_err(|e| e.annotate("drep"))?;
+            match len {
+                cbor_event::Len::Len(_) => (),
+                cbor_event::Len::Indefinite => match raw.special()? {
+                    cbor_event::Special::Break => (),
+                    _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                },
+            }
+            Ok(Account {
+                rewards,
+                deposit,
+                pool,
+                drep,
+            })
+        })()
+        .map_err(|e| e.annotate("Account"))
+    }
+}
+
+impl Serialize for AccountsState {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_map(cbor_event::Len::Len(1))?;
+        serializer.write_text("accounts")?;
+        serializer.write_map(cbor_event::Len::Len(self.accounts.len() as u64))?;
+        let mut key_order = self
+            .accounts
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
+            value.serialize(serializer, force_canonical)?;
+        }
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for AccountsState {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.map()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(1)?;
+        read_len.finish()?;
+        (|| -> Result<_, DeserializeError> {
+            let mut accounts = None;
+            let mut read = 0;
+            while match len {
+                cbor_event::Len::Len(n) => read < n,
+                cbor_event::Len::Indefinite => true,
+            } {
+                match raw.cbor_type()? {
+                    cbor_event::Type::UnsignedInteger => {
+                        return Err(DeserializeFailure::UnknownKey(Key::Uint(
+                            raw.unsigned_integer()?,
+                        ))
+                        .into())
+                    }
+                    cbor_event::Type::Text => match raw.text()?.as_str() {
+                        "accounts" => {
+                            if accounts.is_some() {
+                                return Err(DeserializeFailure::DuplicateKey(Key::Str(
+                                    "accounts".into(),
+                                ))
+                                .into());
+                            }
+                            accounts = Some(
+                                (|| -> Result<_, DeserializeError> {
+                                    let mut accounts_table = BTreeMap::new();
+                                    let accounts_len = raw.map()?;
+                                    while match accounts_len {
+                                        cbor_event::Len::Len(n) => {
+                                            (accounts_table.len() as u64) < n
+                                        }
+                                        cbor_event::Len::Indefinite => true,
+                                    } {
+                                        if raw.cbor_type()? == cbor_event::Type::Special {
+                                            assert_eq!(raw.special()?, cbor_event::Special::Break);
+                                            break;
+                                        }
+                                        let accounts_key = Credential::deserialize(raw)?;
+                                        let accounts_value = Account::deserialize(raw)?;
+                                        if accounts_table
+                                            .insert(accounts_key.clone(), accounts_value)
+                                            .is_some()
+                                        {
+                                            return Err(DeserializeFailure::DuplicateKey(
+                                                Key::Str(String::from(
+                                                    "some complicated/unsupported type",
+                                                )),
+                                            )
+                                            .into());
+                                        }
+                                    }
+                                    Ok(accounts_table)
+                                })()
+                                .map_err(|e| e.annotate("accounts"))?,
+                            );
+                        }
+                        unknown_key => {
+                            return Err(DeserializeFailure::UnknownKey(Key::Str(
+                                unknown_key.to_owned(),
+                            ))
+                            .into())
+                        }
+                    },
+                    cbor_event::Type::Special => match len {
+                        cbor_event::Len::Len(_) => {
+                            return Err(DeserializeFailure::BreakInDefiniteLen.into())
+                        }
+                        cbor_event::Len::Indefinite => match raw.special()? {
+                            cbor_event::Special::Break => break,
+                            _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                        },
+                    },
+                    other_type => {
+                        return Err(DeserializeFailure::UnexpectedKeyType(other_type).into())
+                    }
+                }
+                read += 1;
+            }
+            let accounts =
+                match accounts {
+                    Some(x) => x,
+                    None => {
+                        return Err(DeserializeFailure::MandatoryFieldMissing(Key::Str(
+                            String::from("accounts"),
+                        ))
+                        .into())
+                    }
+                };
+            ();
+            Ok(Self { accounts })
+        })()
+        .map_err(|e| e.annotate("AccountsState"))
+    }
+}
+
+impl Serialize for Drep {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        match self {
+            Drep::DrepKeyhash(drep_keyhash) => drep_keyhash.serialize(serializer, force_canonical),
+            Drep::DrepScriptHash(drep_script_hash) => {
+                drep_script_hash.serialize(serializer, force_canonical)
+            }
+            Drep::AlwaysAbstain => {
+                serializer.write_array(cbor_event::Len::Len(1))?;
+                serializer.write_unsigned_integer(2u64)
+            }
+            Drep::AlwaysNoConfidence => {
+                serializer.write_array(cbor_event::Len::Len(1))?;
+                serializer.write_unsigned_integer(3u64)
+            }
+        }
+    }
+}
+
+impl Deserialize for Drep {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            let len = raw.array()?;
+            let initial_position = raw.as_mut_ref().stream_position().unwrap();
+            let mut errs = Vec::new();
+            let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
+                let mut read_len = CBORReadLen::new(len);
+                read_len.read_elems(2)?;
+                read_len.finish()?;
+                let ret = DrepKeyhash::deserialize_as_embedded_group(raw, &mut read_len, len);
+                match len {
+                    cbor_event::Len::Len(_) => (),
+                    cbor_event::Len::Indefinite => match raw.special()? {
+                        cbor_event::Special::Break => (),
+                        _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                    },
+                }
+                ret
+            })(raw);
+            match deser_variant {
+                Ok(drep_keyhash) => return Ok(Self::DrepKeyhash(drep_keyhash)),
+                Err(e) => {
+                    errs.push(e.annotate("DrepKeyhash"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
+                }
+            };
+            let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
+                let mut read_len = CBORReadLen::new(len);
+                read_len.read_elems(2)?;
+                read_len.finish()?;
+                let ret = DrepScriptHash::deserialize_as_embedded_group(raw, &mut read_len, len);
+                match len {
+                    cbor_event::Len::Len(_) => (),
+                    cbor_event::Len::Indefinite => match raw.special()? {
+                        cbor_event::Special::Break => (),
+                        _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                    },
+                }
+                ret
+            })(raw);
+            match deser_variant {
+                Ok(drep_script_hash) => return Ok(Self::DrepScriptHash(drep_script_hash)),
+                Err(e) => {
+                    errs.push(e.annotate("DrepScriptHash"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
+                }
+            };
+            let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
+                let mut read_len = CBORReadLen::new(len);
+                read_len.read_elems(1)?;
+                read_len.finish()?;
+                let always_abstain_value = raw.unsigned_integer()?;
+                if always_abstain_value != 2 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(always_abstain_value),
+                        expected: Key::Uint(2),
+                    }
+                    .into());
+                }
+                match len {
+                    cbor_event::Len::Len(_) => (),
+                    cbor_event::Len::Indefinite => match raw.special()? {
+                        cbor_event::Special::Break => (),
+                        _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                    },
+                }
+                Ok(())
+            })(raw);
+            match deser_variant {
+                Ok(()) => return Ok(Drep::AlwaysAbstain),
+                Err(e) => {
+                    errs.push(e.annotate("AlwaysAbstain"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
+                }
+            };
+            let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
+                let mut read_len = CBORReadLen::new(len);
+                read_len.read_elems(1)?;
+                read_len.finish()?;
+                let always_no_confidence_value = raw.unsigned_integer()?;
+                if always_no_confidence_value != 3 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(always_no_confidence_value),
+                        expected: Key::Uint(3),
+                    }
+                    .into());
+                }
+                match len {
+                    cbor_event::Len::Len(_) => (),
+                    cbor_event::Len::Indefinite => match raw.special()? {
+                        cbor_event::Special::Break => (),
+                        _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+                    },
+                }
+                Ok(())
+            })(raw);
+            match deser_variant {
+                Ok(()) => return Ok(Drep::AlwaysNoConfidence),
+                Err(e) => {
+                    errs.push(e.annotate("AlwaysNoConfidence"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
+                }
+            };
+            Err(DeserializeError::new(
+                "Drep",
+                DeserializeFailure::NoVariantMatchedWithCauses(errs),
+            ))
+        })()
+        .map_err(|e| e.annotate("Drep"))
+    }
+}
+
+impl Serialize for DrepKeyhash {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for DrepKeyhash {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_unsigned_integer(0u64)?;
+        self.addr_keyhash.serialize(serializer, force_canonical)?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for DrepKeyhash {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(2)?;
+        read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for DrepKeyhash {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 0 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(0),
+                    }
+                    .into());
+                }
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let addr_keyhash = AddrKeyhash::deserialize(raw)
+                .map_err(|e: DeserializeError| e.annotate("addr_keyhash"))?;
+            Ok(DrepKeyhash { addr_keyhash })
+        })()
+        .map_err(|e| e.annotate("DrepKeyhash"))
+    }
+}
+
+impl Serialize for DrepScriptHash {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for DrepScriptHash {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_unsigned_integer(1u64)?;
+        self.script_hash.serialize(serializer, force_canonical)?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for DrepScriptHash {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(2)?;
+        read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for DrepScriptHash {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 1 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(1),
+                    }
+                    .into());
+                }
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let script_hash = ScriptHash::deserialize(raw)
+                .map_err(|e: DeserializeError| e.annotate("script_hash"))?;
+            Ok(DrepScriptHash { script_hash })
+        })()
+        .map_err(|e| e.annotate("DrepScriptHash"))
+    }
+}
@@ -4,6 +4,12 @@
 use crate::serialization::{LenEncoding, StringEncoding};
 use std::collections::BTreeMap;
 
+#[derive(Clone, Debug, Default)]
+pub struct AddrKeyhashCredentialEncoding {
+    pub len_encoding: LenEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DenominatorEncoding {
     pub inner_encoding: Option<cbor_event::Sz>,
@@ -25,6 +31,12 @@ pub struct Hash32Encoding {
     pub inner_encoding: StringEncoding,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct ScriptHashCredentialEncoding {
+    pub len_encoding: LenEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct UnitIntervalEncoding {
     pub len_encoding: LenEncoding,
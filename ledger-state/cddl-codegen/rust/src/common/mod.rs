@@ -7,23 +7,36 @@ use crate::error::*;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
+pub type AddrKeyhash = Hash28;
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AddrKeyhashCredential {
+    pub addr_keyhash: AddrKeyhash,
+}
+
+impl AddrKeyhashCredential {
+    pub fn new(addr_keyhash: AddrKeyhash) -> Self {
+        Self { addr_keyhash }
+    }
+}
+
 pub type Address = Vec<u8>;
 
 pub type Coin = u64;
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Credential {
-    I0,
-    I1,
+    AddrKeyhashCredential(AddrKeyhashCredential),
+    ScriptHashCredential(ScriptHashCredential),
 }
 
 impl Credential {
-    pub fn new_i0() -> Self {
-        Self::I0
+    pub fn new_addr_keyhash_credential(addr_keyhash: AddrKeyhash) -> Self {
+        Self::AddrKeyhashCredential(AddrKeyhashCredential::new(addr_keyhash))
     }
 
-    pub fn new_i1() -> Self {
-        Self::I1
+    pub fn new_script_hash_credential(script_hash: ScriptHash) -> Self {
+        Self::ScriptHashCredential(ScriptHashCredential::new(script_hash))
     }
 }
 
@@ -140,6 +153,19 @@ pub type PositiveCoin = u64;
 
 pub type RewardAccount = Vec<u8>;
 
+pub type ScriptHash = Hash28;
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ScriptHashCredential {
+    pub script_hash: ScriptHash,
+}
+
+impl ScriptHashCredential {
+    pub fn new(script_hash: ScriptHash) -> Self {
+        Self { script_hash }
+    }
+}
+
 impl From<Url> for String {
     fn from(wrapper: Url) -> Self {
         wrapper.0
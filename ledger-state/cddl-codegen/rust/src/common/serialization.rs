@@ -5,22 +5,89 @@ use super::*;
 use crate::error::*;
 use crate::serialization::*;
 use cbor_event::de::Deserializer;
-use cbor_event::se::{Serialize, Serializer};
+use cbor_event::se::Serializer;
 use std::io::{BufRead, Seek, SeekFrom, Write};
 
-impl cbor_event::se::Serialize for Credential {
+impl Serialize for AddrKeyhashCredential {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for AddrKeyhashCredential {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_unsigned_integer(0u64)?;
+        self.addr_keyhash.serialize(serializer, force_canonical)?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for AddrKeyhashCredential {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(2)?;
+        read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for AddrKeyhashCredential {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 0 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(0),
+                    }
+                    .into());
+                }
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let addr_keyhash = AddrKeyhash::deserialize(raw)
+                .map_err(|e: DeserializeError| e.annotate("addr_keyhash"))?;
+            Ok(AddrKeyhashCredential { addr_keyhash })
+        })()
+        .map_err(|e| e.annotate("AddrKeyhashCredential"))
+    }
+}
+
+impl Serialize for Credential {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            Credential::I0 => {
-                serializer.write_array(cbor_event::Len::Len(1))?;
-                serializer.write_unsigned_integer(0u64)
+            Credential::AddrKeyhashCredential(addr_keyhash_credential) => {
+                addr_keyhash_credential.serialize(serializer, force_canonical)
             }
-            Credential::I1 => {
-                serializer.write_array(cbor_event::Len::Len(1))?;
-                serializer.write_unsigned_integer(1u64)
+            Credential::ScriptHashCredential(script_hash_credential) => {
+                script_hash_credential.serialize(serializer, force_canonical)
             }
         }
     }
@@ -34,16 +101,10 @@ impl Deserialize for Credential {
             let mut errs = Vec::new();
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
                 let mut read_len = CBORReadLen::new(len);
-                read_len.read_elems(1)?;
+                read_len.read_elems(2)?;
                 read_len.finish()?;
-                let i0_value = raw.unsigned_integer()?;
-                if i0_value != 0 {
-                    return Err(DeserializeFailure::FixedValueMismatch {
-                        found: Key::Uint(i0_value),
-                        expected: Key::Uint(0),
-                    }
-                    .into());
-                }
+                let ret =
+                    AddrKeyhashCredential::deserialize_as_embedded_group(raw, &mut read_len, len);
                 match len {
                     cbor_event::Len::Len(_) => (),
                     cbor_event::Len::Indefinite => match raw.special()? {
@@ -51,29 +112,23 @@ impl Deserialize for Credential {
                         _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
                     },
                 }
-                Ok(())
+                ret
             })(raw);
             match deser_variant {
-                Ok(()) => return Ok(Credential::I0),
+                Ok(addr_keyhash_credential) => {
+                    return Ok(Self::AddrKeyhashCredential(addr_keyhash_credential))
+                }
                 Err(e) => {
-                    errs.push(e.annotate("I0"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    errs.push(e.annotate("AddrKeyhashCredential"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
                 let mut read_len = CBORReadLen::new(len);
-                read_len.read_elems(1)?;
+                read_len.read_elems(2)?;
                 read_len.finish()?;
-                let i1_value = raw.unsigned_integer()?;
-                if i1_value != 1 {
-                    return Err(DeserializeFailure::FixedValueMismatch {
-                        found: Key::Uint(i1_value),
-                        expected: Key::Uint(1),
-                    }
-                    .into());
-                }
+                let ret =
+                    ScriptHashCredential::deserialize_as_embedded_group(raw, &mut read_len, len);
                 match len {
                     cbor_event::Len::Len(_) => (),
                     cbor_event::Len::Indefinite => match raw.special()? {
@@ -81,15 +136,15 @@ impl Deserialize for Credential {
                         _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
                     },
                 }
-                Ok(())
+                ret
             })(raw);
             match deser_variant {
-                Ok(()) => return Ok(Credential::I1),
+                Ok(script_hash_credential) => {
+                    return Ok(Self::ScriptHashCredential(script_hash_credential))
+                }
                 Err(e) => {
-                    errs.push(e.annotate("I1"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    errs.push(e.annotate("ScriptHashCredential"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -101,10 +156,11 @@ impl Deserialize for Credential {
     }
 }
 
-impl cbor_event::se::Serialize for Denominator {
+impl Serialize for Denominator {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(self.0)
     }
@@ -127,13 +183,14 @@ impl Deserialize for Denominator {
     }
 }
 
-impl cbor_event::se::Serialize for GovActionId {
+impl Serialize for GovActionId {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.hash_32.serialize(serializer)?;
+        self.hash_32.serialize(serializer, force_canonical)?;
         serializer.write_unsigned_integer(self.uint as u64)?;
         Ok(serializer)
     }
@@ -163,10 +220,11 @@ impl Deserialize for GovActionId {
     }
 }
 
-impl cbor_event::se::Serialize for Hash28 {
+impl Serialize for Hash28 {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_bytes(&self.0)
     }
@@ -189,10 +247,11 @@ impl Deserialize for Hash28 {
     }
 }
 
-impl cbor_event::se::Serialize for Hash32 {
+impl Serialize for Hash32 {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_bytes(&self.0)
     }
@@ -215,10 +274,79 @@ impl Deserialize for Hash32 {
     }
 }
 
-impl cbor_event::se::Serialize for UnitInterval {
+impl Serialize for ScriptHashCredential {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for ScriptHashCredential {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_unsigned_integer(1u64)?;
+        self.script_hash.serialize(serializer, force_canonical)?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for ScriptHashCredential {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(2)?;
+        read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for ScriptHashCredential {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 1 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(1),
+                    }
+                    .into());
+                }
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let script_hash = ScriptHash::deserialize(raw)
+                .map_err(|e: DeserializeError| e.annotate("script_hash"))?;
+            Ok(ScriptHashCredential { script_hash })
+        })()
+        .map_err(|e| e.annotate("ScriptHashCredential"))
+    }
+}
+
+impl Serialize for UnitInterval {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_tag(30u64)?;
         serializer.write_array(cbor_event::Len::Len(2))?;
@@ -262,10 +390,11 @@ impl Deserialize for UnitInterval {
     }
 }
 
-impl cbor_event::se::Serialize for Url {
+impl Serialize for Url {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_text(&self.0)
     }
@@ -1,5 +1,6 @@
 #![allow(clippy::too_many_arguments)]
 
+pub mod accounts;
 pub mod common;
 pub mod error;
 pub mod spos;
@@ -9,31 +10,9 @@ pub mod utxos;
 
 pub mod serialization;
 
-use crate::error::*;
 use common::Keyhash;
-use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
-#[derive(Clone, Debug)]
-pub struct AssetQuantityU64 {
-    pub asset_id: Vec<u8>,
-    pub quantity: u64,
-}
-
-impl AssetQuantityU64 {
-    pub fn new(asset_id: Vec<u8>, quantity: u64) -> Result<Self, DeserializeError> {
-        if asset_id.len() > 32 {
-            return Err(DeserializeFailure::RangeCheck {
-                found: asset_id.len() as isize,
-                min: None,
-                max: Some(32),
-            }
-            .into());
-        }
-        Ok(Self { asset_id, quantity })
-    }
-}
-
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Int {
     Uint(u64),
@@ -74,21 +74,54 @@ pub trait DeserializeEmbeddedGroup {
     where
         Self: Sized;
 }
+// same as cbor_event::se::Serialize but with a canonical encoding flag
+//
+// Lengths are always definite and integers always take their smallest width, so
+// `force_canonical` only changes map key order: with it set, keys are sorted by
+// their encoded bytes, shortest first (RFC 7049 canonical CBOR), instead of
+// being written in the order of the maps holding them.
+pub trait Serialize {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'a mut Serializer<W>>;
+}
+
+impl<T: cbor_event::se::Serialize> Serialize for T {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+        _force_canonical: bool,
+    ) -> cbor_event::Result<&'a mut Serializer<W>> {
+        <T as cbor_event::se::Serialize>::serialize(self, serializer)
+    }
+}
+
 pub trait SerializeEmbeddedGroup {
     fn serialize_as_embedded_group<'a, W: Write + Sized>(
         &self,
         serializer: &'a mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'a mut Serializer<W>>;
 }
 
 pub trait ToCBORBytes {
     fn to_cbor_bytes(&self) -> Vec<u8>;
+
+    fn to_canonical_cbor_bytes(&self) -> Vec<u8>;
 }
 
-impl<T: cbor_event::se::Serialize> ToCBORBytes for T {
+impl<T: Serialize> ToCBORBytes for T {
     fn to_cbor_bytes(&self) -> Vec<u8> {
         let mut buf = Serializer::new_vec();
-        self.serialize(&mut buf).unwrap();
+        self.serialize(&mut buf, false).unwrap();
+        buf.finalize()
+    }
+
+    fn to_canonical_cbor_bytes(&self) -> Vec<u8> {
+        let mut buf = Serializer::new_vec();
+        self.serialize(&mut buf, true).unwrap();
         buf.finalize()
     }
 }
@@ -99,136 +132,14 @@ impl<T: cbor_event::se::Serialize> ToCBORBytes for T {
 use super::*;
 use crate::error::*;
 use cbor_event::de::Deserializer;
-use cbor_event::se::{Serialize, Serializer};
-use std::io::{BufRead, Seek, SeekFrom, Write};
-
-impl cbor_event::se::Serialize for AssetQuantityU64 {
-    fn serialize<'se, W: Write>(
-        &self,
-        serializer: &'se mut Serializer<W>,
-    ) -> cbor_event::Result<&'se mut Serializer<W>> {
-        serializer.write_map(cbor_event::Len::Len(2))?;
-        serializer.write_text("asset_id")?;
-        serializer.write_bytes(&self.asset_id)?;
-        serializer.write_text("quantity")?;
-        serializer.write_unsigned_integer(self.quantity)?;
-        Ok(serializer)
-    }
-}
-
-impl Deserialize for AssetQuantityU64 {
-    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
-        let len = raw.map()?;
-        let mut read_len = CBORReadLen::new(len);
-        read_len.read_elems(2)?;
-        read_len.finish()?;
-        (|| -> Result<_, DeserializeError> {
-            let mut asset_id = None;
-            let mut quantity = None;
-            let mut read = 0;
-            while match len {
-                cbor_event::Len::Len(n) => read < n,
-                cbor_event::Len::Indefinite => true,
-            } {
-                match raw.cbor_type()? {
-                    cbor_event::Type::UnsignedInteger => {
-                        return Err(DeserializeFailure::UnknownKey(Key::Uint(
-                            raw.unsigned_integer()?,
-                        ))
-                        .into())
-                    }
-                    cbor_event::Type::Text => match raw.text()?.as_str() {
-                        "asset_id" => {
-                            if asset_id.is_some() {
-                                return Err(DeserializeFailure::DuplicateKey(Key::Str(
-                                    "asset_id".into(),
-                                ))
-                                .into());
-                            }
-                            asset_id = Some(
-                                Ok(raw
-                                    .bytes()
-                                    .map_err(Into::<DeserializeError>::into)
-                                    .and_then(|bytes| {
-                                        if bytes.len() > 32 {
-                                            Err(DeserializeFailure::RangeCheck {
-                                                found: bytes.len() as isize,
-                                                min: None,
-                                                max: Some(32),
-                                            }
-                                            .into())
-                                        } else {
-                                            Ok(bytes)
-                                        }
-                                    })? as Vec<u8>)
-                                .map_err(|e: DeserializeError| e.annotate("asset_id"))?,
-                            );
-                        }
-                        "quantity" => {
-                            if quantity.is_some() {
-                                return Err(DeserializeFailure::DuplicateKey(Key::Str(
-                                    "quantity".into(),
-                                ))
-                                .into());
-                            }
-                            quantity = Some(
-                                Ok(raw.unsigned_integer()? as u64)
-                                    .map_err(|e: DeserializeError| e.annotate("quantity"))?,
-                            );
-                        }
-                        unknown_key => {
-                            return Err(DeserializeFailure::UnknownKey(Key::Str(
-                                unknown_key.to_owned(),
-                            ))
-                            .into())
-                        }
-                    },
-                    cbor_event::Type::Special => match len {
-                        cbor_event::Len::Len(_) => {
-                            return Err(DeserializeFailure::BreakInDefiniteLen.into())
-                        }
-                        cbor_event::Len::Indefinite => match raw.special()? {
-                            cbor_event::Special::Break => break,
-                            _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
-                        },
-                    },
-                    other_type => {
-                        return Err(DeserializeFailure::UnexpectedKeyType(other_type).into())
-                    }
-                }
-                read += 1;
-            }
-            let asset_id =
-                match asset_id {
-                    Some(x) => x,
-                    None => {
-                        return Err(DeserializeFailure::MandatoryFieldMissing(Key::Str(
-                            String::from("asset_id"),
-                        ))
-                        .into())
-                    }
-                };
-            let quantity =
-                match quantity {
-                    Some(x) => x,
-                    None => {
-                        return Err(DeserializeFailure::MandatoryFieldMissing(Key::Str(
-                            String::from("quantity"),
-                        ))
-                        .into())
-                    }
-                };
-            ();
-            Ok(Self { asset_id, quantity })
-        })()
-        .map_err(|e| e.annotate("AssetQuantityU64"))
-    }
-}
+use cbor_event::se::Serializer;
+use std::io::{BufRead, Seek, Write};
 
-impl cbor_event::se::Serialize for Int {
+impl Serialize for Int {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
             Self::Uint(x) => serializer.write_unsigned_integer(*x),
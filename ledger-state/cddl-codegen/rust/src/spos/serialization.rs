@@ -5,13 +5,14 @@ use super::*;
 use crate::error::*;
 use crate::serialization::*;
 use cbor_event::de::Deserializer;
-use cbor_event::se::{Serialize, Serializer};
+use cbor_event::se::Serializer;
 use std::io::{BufRead, Seek, SeekFrom, Write};
 
-impl cbor_event::se::Serialize for DnsName {
+impl Serialize for DnsName {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_text(&self.0)
     }
@@ -34,10 +35,11 @@ impl Deserialize for DnsName {
     }
 }
 
-impl cbor_event::se::Serialize for Ipv4 {
+impl Serialize for Ipv4 {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_bytes(&self.0)
     }
@@ -60,10 +62,11 @@ impl Deserialize for Ipv4 {
     }
 }
 
-impl cbor_event::se::Serialize for Ipv6 {
+impl Serialize for Ipv6 {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_bytes(&self.0)
     }
@@ -86,13 +89,14 @@ impl Deserialize for Ipv6 {
     }
 }
 
-impl cbor_event::se::Serialize for MultiHostName {
+impl Serialize for MultiHostName {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -100,9 +104,10 @@ impl SerializeEmbeddedGroup for MultiHostName {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(2u64)?;
-        self.dns_name.serialize(serializer)?;
+        self.dns_name.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -152,13 +157,14 @@ impl DeserializeEmbeddedGroup for MultiHostName {
     }
 }
 
-impl cbor_event::se::Serialize for PoolMetadata {
+impl Serialize for PoolMetadata {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.url.serialize(serializer)?;
+        self.url.serialize(serializer, force_canonical)?;
         serializer.write_bytes(&self.index_1)?;
         Ok(serializer)
     }
@@ -187,25 +193,26 @@ impl Deserialize for PoolMetadata {
     }
 }
 
-impl cbor_event::se::Serialize for PoolParameters {
+impl Serialize for PoolParameters {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(9))?;
-        self.operator.serialize(serializer)?;
-        self.vrf_keyhash.serialize(serializer)?;
+        self.operator.serialize(serializer, force_canonical)?;
+        self.vrf_keyhash.serialize(serializer, force_canonical)?;
         serializer.write_unsigned_integer(self.pledge)?;
         serializer.write_unsigned_integer(self.cost)?;
-        self.margin.serialize(serializer)?;
+        self.margin.serialize(serializer, force_canonical)?;
         serializer.write_bytes(&self.reward_account)?;
-        self.pool_owners.serialize(serializer)?;
+        self.pool_owners.serialize(serializer, force_canonical)?;
         serializer.write_array(cbor_event::Len::Len(self.relays.len() as u64))?;
         for element in self.relays.iter() {
-            element.serialize(serializer)?;
+            element.serialize(serializer, force_canonical)?;
         }
         match &self.pool_metadata {
-            Some(x) => x.serialize(serializer),
+            Some(x) => x.serialize(serializer, force_canonical),
             None => serializer.write_special(cbor_event::Special::Null),
         }?;
         Ok(serializer)
@@ -284,15 +291,22 @@ impl Deserialize for PoolParameters {
     }
 }
 
-impl cbor_event::se::Serialize for Relay {
+impl Serialize for Relay {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            Relay::SingleHostAddr(single_host_addr) => single_host_addr.serialize(serializer),
-            Relay::SingleHostName(single_host_name) => single_host_name.serialize(serializer),
-            Relay::MultiHostName(multi_host_name) => multi_host_name.serialize(serializer),
+            Relay::SingleHostAddr(single_host_addr) => {
+                single_host_addr.serialize(serializer, force_canonical)
+            }
+            Relay::SingleHostName(single_host_name) => {
+                single_host_name.serialize(serializer, force_canonical)
+            }
+            Relay::MultiHostName(multi_host_name) => {
+                multi_host_name.serialize(serializer, force_canonical)
+            }
         }
     }
 }
@@ -321,9 +335,7 @@ impl Deserialize for Relay {
                 Ok(single_host_addr) => return Ok(Self::SingleHostAddr(single_host_addr)),
                 Err(e) => {
                     errs.push(e.annotate("SingleHostAddr"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -344,9 +356,7 @@ impl Deserialize for Relay {
                 Ok(single_host_name) => return Ok(Self::SingleHostName(single_host_name)),
                 Err(e) => {
                     errs.push(e.annotate("SingleHostName"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -367,9 +377,7 @@ impl Deserialize for Relay {
                 Ok(multi_host_name) => return Ok(Self::MultiHostName(multi_host_name)),
                 Err(e) => {
                     errs.push(e.annotate("MultiHostName"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -381,13 +389,14 @@ impl Deserialize for Relay {
     }
 }
 
-impl cbor_event::se::Serialize for SingleHostAddr {
+impl Serialize for SingleHostAddr {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(4))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -395,6 +404,7 @@ impl SerializeEmbeddedGroup for SingleHostAddr {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(0u64)?;
         match &self.port {
@@ -402,11 +412,11 @@ impl SerializeEmbeddedGroup for SingleHostAddr {
             None => serializer.write_special(cbor_event::Special::Null),
         }?;
         match &self.ipv4 {
-            Some(x) => x.serialize(serializer),
+            Some(x) => x.serialize(serializer, force_canonical),
             None => serializer.write_special(cbor_event::Special::Null),
         }?;
         match &self.ipv6 {
-            Some(x) => x.serialize(serializer),
+            Some(x) => x.serialize(serializer, force_canonical),
             None => serializer.write_special(cbor_event::Special::Null),
         }?;
         Ok(serializer)
@@ -492,13 +502,14 @@ impl DeserializeEmbeddedGroup for SingleHostAddr {
     }
 }
 
-impl cbor_event::se::Serialize for SingleHostName {
+impl Serialize for SingleHostName {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(3))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -506,13 +517,14 @@ impl SerializeEmbeddedGroup for SingleHostName {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(1u64)?;
         match &self.port {
             Some(x) => serializer.write_unsigned_integer(*x as u64),
             None => serializer.write_special(cbor_event::Special::Null),
         }?;
-        self.dns_name.serialize(serializer)?;
+        self.dns_name.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -574,22 +586,57 @@ impl DeserializeEmbeddedGroup for SingleHostName {
     }
 }
 
-impl cbor_event::se::Serialize for SpoState {
+impl Serialize for SpoState {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_map(cbor_event::Len::Len(2))?;
         serializer.write_text("pools")?;
         serializer.write_map(cbor_event::Len::Len(self.pools.len() as u64))?;
-        for (key, value) in self.pools.iter() {
-            key.serialize(serializer)?;
-            value.serialize(serializer)?;
+        let mut key_order = self
+            .pools
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
+            value.serialize(serializer, force_canonical)?;
         }
         serializer.write_text("retiring")?;
         serializer.write_map(cbor_event::Len::Len(self.retiring.len() as u64))?;
-        for (key, value) in self.retiring.iter() {
-            key.serialize(serializer)?;
+        let mut key_order = self
+            .retiring
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
             serializer.write_unsigned_integer(*value)?;
         }
         Ok(serializer)
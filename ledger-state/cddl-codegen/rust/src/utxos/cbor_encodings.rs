@@ -4,10 +4,18 @@
 use crate::serialization::{LenEncoding, StringEncoding};
 use std::collections::BTreeMap;
 
+#[derive(Clone, Debug, Default)]
+pub struct AssetNameEncoding {
+    pub inner_encoding: StringEncoding,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct AssetValueEncoding {
     pub len_encoding: LenEncoding,
     pub lovelace_encoding: Option<cbor_event::Sz>,
+    pub multi_asset_encoding: LenEncoding,
+    pub multi_asset_value_encodings:
+        BTreeMap<Hash28, (LenEncoding, BTreeMap<AssetName, Option<cbor_event::Sz>>)>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -34,6 +42,12 @@ pub struct CredentialDepositEncoding {
     pub index_0_encoding: Option<cbor_event::Sz>,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct DatumHashOptionEncoding {
+    pub len_encoding: LenEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DrepDepositEncoding {
     pub len_encoding: LenEncoding,
@@ -47,11 +61,11 @@ pub struct GovActionDepositEncoding {
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct MultiassetEncoding {
+pub struct InlineDatumOptionEncoding {
     pub len_encoding: LenEncoding,
-    pub orig_deser_order: Vec<usize>,
-    pub policy_id_key_encoding: StringEncoding,
-    pub asset_bundle_key_encoding: StringEncoding,
+    pub index_0_encoding: Option<cbor_event::Sz>,
+    pub data_tag_encoding: Option<cbor_event::Sz>,
+    pub data_bytes_encoding: StringEncoding,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -5,10 +5,46 @@ pub mod serialization;
 
 use crate::common::{Address, Coin, Credential, GovActionId, Hash28, Hash32, Keyhash};
 use crate::error::*;
-use crate::{AssetQuantityU64, Int};
+use crate::serialization::Deserialize;
+use crate::Int;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
+pub type AssetBundle = BTreeMap<AssetName, u64>;
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AssetName(Vec<u8>);
+
+impl AssetName {
+    pub fn new(inner: Vec<u8>) -> Result<Self, DeserializeError> {
+        if inner.len() > 32 {
+            return Err(DeserializeError::new(
+                "AssetName",
+                DeserializeFailure::RangeCheck {
+                    found: inner.len() as isize,
+                    min: Some(0),
+                    max: Some(32),
+                },
+            ));
+        }
+        Ok(Self(inner))
+    }
+}
+
+impl TryFrom<Vec<u8>> for AssetName {
+    type Error = DeserializeError;
+
+    fn try_from(inner: Vec<u8>) -> Result<Self, Self::Error> {
+        AssetName::new(inner)
+    }
+}
+
+impl From<AssetName> for Vec<u8> {
+    fn from(wrapper: AssetName) -> Self {
+        wrapper.0
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AssetValue {
     pub lovelace: Coin,
@@ -155,19 +191,30 @@ impl CredentialDeposit {
 
 pub type Data = PlutusData;
 
+#[derive(Clone, Debug)]
+pub struct DatumHashOption {
+    pub hash_32: Hash32,
+}
+
+impl DatumHashOption {
+    pub fn new(hash_32: Hash32) -> Self {
+        Self { hash_32 }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum DatumOption {
-    I0,
-    I1,
+    DatumHashOption(DatumHashOption),
+    InlineDatumOption(InlineDatumOption),
 }
 
 impl DatumOption {
-    pub fn new_i0() -> Self {
-        Self::I0
+    pub fn new_datum_hash_option(hash_32: Hash32) -> Self {
+        Self::DatumHashOption(DatumHashOption::new(hash_32))
     }
 
-    pub fn new_i1() -> Self {
-        Self::I1
+    pub fn new_inline_datum_option(data_bytes: Vec<u8>) -> Self {
+        Self::InlineDatumOption(InlineDatumOption::new(data_bytes))
     }
 }
 
@@ -219,6 +266,24 @@ impl GovActionDeposit {
     }
 }
 
+/// Hand-written rather than generated: the datum is kept as the bytes it was
+/// encoded in, since its hash is over those and `PlutusData` doesn't keep the
+/// details of its encoding, so a decoded and re-encoded datum may differ.
+#[derive(Clone, Debug)]
+pub struct InlineDatumOption {
+    pub data_bytes: Vec<u8>,
+}
+
+impl InlineDatumOption {
+    pub fn new(data_bytes: Vec<u8>) -> Self {
+        Self { data_bytes }
+    }
+
+    pub fn data(&self) -> Result<Data, DeserializeError> {
+        Data::from_cbor_bytes(&self.data_bytes)
+    }
+}
+
 pub type Int64 = i64;
 
 #[derive(Clone, Debug)]
@@ -243,20 +308,7 @@ impl InvalidHereafter {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Multiasset {
-    pub policy_id: Hash28,
-    pub asset_bundle: AssetQuantityU64,
-}
-
-impl Multiasset {
-    pub fn new(policy_id: Hash28, asset_bundle: AssetQuantityU64) -> Self {
-        Self {
-            policy_id,
-            asset_bundle,
-        }
-    }
-}
+pub type Multiasset = BTreeMap<PolicyId, AssetBundle>;
 
 #[derive(Clone, Debug)]
 pub enum NativeScript {
@@ -333,6 +385,8 @@ pub type PlutusV2Script = Vec<u8>;
 
 pub type PlutusV3Script = Vec<u8>;
 
+pub type PolicyId = Hash28;
+
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct PoolDeposit {
     pub keyhash: Keyhash,
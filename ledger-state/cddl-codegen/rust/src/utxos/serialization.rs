@@ -5,17 +5,86 @@ use super::*;
 use crate::error::*;
 use crate::serialization::*;
 use cbor_event::de::Deserializer;
-use cbor_event::se::{Serialize, Serializer};
+use cbor_event::se::Serializer;
 use std::io::{BufRead, Seek, SeekFrom, Write};
 
-impl cbor_event::se::Serialize for AssetValue {
+impl Serialize for AssetName {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_bytes(&self.0)
+    }
+}
+
+impl Deserialize for AssetName {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let inner = raw.bytes()? as Vec<u8>;
+        if inner.len() > 32 {
+            return Err(DeserializeError::new(
+                "AssetName",
+                DeserializeFailure::RangeCheck {
+                    found: inner.len() as isize,
+                    min: Some(0),
+                    max: Some(32),
+                },
+            ));
+        }
+        Ok(Self(inner))
+    }
+}
+
+impl Serialize for AssetValue {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
         serializer.write_unsigned_integer(self.lovelace)?;
-        self.multi_asset.serialize(serializer)?;
+        serializer.write_map(cbor_event::Len::Len(self.multi_asset.len() as u64))?;
+        let mut key_order = self
+            .multi_asset
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
+            serializer.write_map(cbor_event::Len::Len(value.len() as u64))?;
+            let mut key_order = value
+                .iter()
+                .map(|(k, v)| {
+                    let mut buf = Serializer::new_vec();
+                    k.serialize(&mut buf, force_canonical)?;
+                    Ok((buf.finalize(), k, v))
+                })
+                .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+            if force_canonical {
+                key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                    match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                        std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                        diff_ord => diff_ord,
+                    }
+                });
+            }
+            for (key_bytes, _key, value) in key_order {
+                serializer.write_raw_bytes(&key_bytes)?;
+                serializer.write_unsigned_integer(*value)?;
+            }
+        }
         Ok(serializer)
     }
 }
@@ -29,8 +98,53 @@ impl Deserialize for AssetValue {
         (|| -> Result<_, DeserializeError> {
             let lovelace = Ok(raw.unsigned_integer()? as u64)
                 .map_err(|e: DeserializeError| e.annotate("lovelace"))?;
-            let multi_asset = Multiasset::deserialize(raw)
-                .map_err(|e: DeserializeError| e.annotate("multi_asset"))?;
+            let multi_asset = (|| -> Result<_, DeserializeError> {
+                let mut multi_asset_table = BTreeMap::new();
+                let multi_asset_len = raw.map()?;
+                while match multi_asset_len {
+                    cbor_event::Len::Len(n) => (multi_asset_table.len() as u64) < n,
+                    cbor_event::Len::Indefinite => true,
+                } {
+                    if raw.cbor_type()? == cbor_event::Type::Special {
+                        assert_eq!(raw.special()?, cbor_event::Special::Break);
+                        break;
+                    }
+                    let multi_asset_key = Hash28::deserialize(raw)?;
+                    let mut multi_asset_value_table = BTreeMap::new();
+                    let multi_asset_value_len = raw.map()?;
+                    while match multi_asset_value_len {
+                        cbor_event::Len::Len(n) => (multi_asset_value_table.len() as u64) < n,
+                        cbor_event::Len::Indefinite => true,
+                    } {
+                        if raw.cbor_type()? == cbor_event::Type::Special {
+                            assert_eq!(raw.special()?, cbor_event::Special::Break);
+                            break;
+                        }
+                        let multi_asset_value_key = AssetName::deserialize(raw)?;
+                        let multi_asset_value_value = raw.unsigned_integer()? as u64;
+                        if multi_asset_value_table
+                            .insert(multi_asset_value_key.clone(), multi_asset_value_value)
+                            .is_some()
+                        {
+                            return Err(DeserializeFailure::DuplicateKey(Key::Str(String::from(
+                                "some complicated/unsupported type",
+                            )))
+                            .into());
+                        }
+                    }
+                    if multi_asset_table
+                        .insert(multi_asset_key.clone(), multi_asset_value_table)
+                        .is_some()
+                    {
+                        return Err(DeserializeFailure::DuplicateKey(Key::Str(String::from(
+                            "some complicated/unsupported type",
+                        )))
+                        .into());
+                    }
+                }
+                Ok(multi_asset_table)
+            })()
+            .map_err(|e| e.annotate("multi_asset"))?;
             match len {
                 cbor_event::Len::Len(_) => (),
                 cbor_event::Len::Indefinite => match raw.special()? {
@@ -47,10 +161,11 @@ impl Deserialize for AssetValue {
     }
 }
 
-impl cbor_event::se::Serialize for BabbageTxOut {
+impl Serialize for BabbageTxOut {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_map(cbor_event::Len::Len(
             2 + match &self.key_2 {
@@ -64,16 +179,16 @@ impl cbor_event::se::Serialize for BabbageTxOut {
         serializer.write_unsigned_integer(0u64)?;
         serializer.write_bytes(&self.key_0)?;
         serializer.write_unsigned_integer(1u64)?;
-        self.key_1.serialize(serializer)?;
+        self.key_1.serialize(serializer, force_canonical)?;
         if let Some(field) = &self.key_2 {
             serializer.write_unsigned_integer(2u64)?;
-            field.serialize(serializer)?;
+            field.serialize(serializer, force_canonical)?;
         }
         if let Some(field) = &self.key_3 {
             serializer.write_unsigned_integer(3u64)?;
             serializer.write_tag(24u64)?;
             let mut key_3_inner_se = Serializer::new_vec();
-            field.serialize(&mut key_3_inner_se)?;
+            field.serialize(&mut key_3_inner_se, force_canonical)?;
             let key_3_bytes = key_3_inner_se.finalize();
             serializer.write_bytes(&key_3_bytes)?;
         }
@@ -197,20 +312,21 @@ impl Deserialize for BabbageTxOut {
     }
 }
 
-impl cbor_event::se::Serialize for BigInt {
+impl Serialize for BigInt {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            BigInt::Int(int) => int.serialize(serializer),
+            BigInt::Int(int) => int.serialize(serializer, force_canonical),
             BigInt::BigUint(big_uint) => {
                 serializer.write_tag(2u64)?;
-                big_uint.serialize(serializer)
+                big_uint.serialize(serializer, force_canonical)
             }
             BigInt::BigNint(big_nint) => {
                 serializer.write_tag(3u64)?;
-                big_nint.serialize(serializer)
+                big_nint.serialize(serializer, force_canonical)
             }
         }
     }
@@ -226,9 +342,7 @@ impl Deserialize for BigInt {
                 Ok(int) => return Ok(Self::Int(int)),
                 Err(e) => {
                     errs.push(e.annotate("Int"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -245,9 +359,7 @@ impl Deserialize for BigInt {
                 Ok(big_uint) => return Ok(Self::BigUint(big_uint)),
                 Err(e) => {
                     errs.push(e.annotate("BigUint"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -264,9 +376,7 @@ impl Deserialize for BigInt {
                 Ok(big_nint) => return Ok(Self::BigNint(big_nint)),
                 Err(e) => {
                     errs.push(e.annotate("BigNint"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -278,10 +388,11 @@ impl Deserialize for BigInt {
     }
 }
 
-impl cbor_event::se::Serialize for BoundedBytes {
+impl Serialize for BoundedBytes {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_bytes(&self.0)
     }
@@ -304,17 +415,18 @@ impl Deserialize for BoundedBytes {
     }
 }
 
-impl cbor_event::se::Serialize for Constr {
+impl Serialize for Constr {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
             Constr::ArrPlutusData(arr_plutus_data) => {
                 serializer.write_tag(121u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data.len() as u64))?;
                 for element in arr_plutus_data.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -322,7 +434,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(122u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data2.len() as u64))?;
                 for element in arr_plutus_data2.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -330,7 +442,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(123u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data3.len() as u64))?;
                 for element in arr_plutus_data3.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -338,7 +450,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(124u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data4.len() as u64))?;
                 for element in arr_plutus_data4.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -346,7 +458,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(125u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data5.len() as u64))?;
                 for element in arr_plutus_data5.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -354,7 +466,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(126u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data6.len() as u64))?;
                 for element in arr_plutus_data6.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -362,7 +474,7 @@ impl cbor_event::se::Serialize for Constr {
                 serializer.write_tag(127u64)?;
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data7.len() as u64))?;
                 for element in arr_plutus_data7.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
@@ -403,9 +515,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data) => return Ok(Self::ArrPlutusData(arr_plutus_data)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -436,9 +546,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data2) => return Ok(Self::ArrPlutusData2(arr_plutus_data2)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData2"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -469,9 +577,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data3) => return Ok(Self::ArrPlutusData3(arr_plutus_data3)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData3"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -502,9 +608,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data4) => return Ok(Self::ArrPlutusData4(arr_plutus_data4)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData4"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -535,9 +639,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data5) => return Ok(Self::ArrPlutusData5(arr_plutus_data5)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData5"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -568,9 +670,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data6) => return Ok(Self::ArrPlutusData6(arr_plutus_data6)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData6"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -601,9 +701,7 @@ impl Deserialize for Constr {
                 Ok(arr_plutus_data7) => return Ok(Self::ArrPlutusData7(arr_plutus_data7)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData7"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -615,13 +713,14 @@ impl Deserialize for Constr {
     }
 }
 
-impl cbor_event::se::Serialize for CredentialDeposit {
+impl Serialize for CredentialDeposit {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -629,9 +728,10 @@ impl SerializeEmbeddedGroup for CredentialDeposit {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(0u64)?;
-        self.credential.serialize(serializer)?;
+        self.credential.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -681,19 +781,86 @@ impl DeserializeEmbeddedGroup for CredentialDeposit {
     }
 }
 
-impl cbor_event::se::Serialize for DatumOption {
+impl Serialize for DatumHashOption {
+    fn serialize<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for DatumHashOption {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_unsigned_integer(0u64)?;
+        self.hash_32.serialize(serializer, force_canonical)?;
+        Ok(serializer)
+    }
+}
+
+impl Deserialize for DatumHashOption {
+    fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
+        let len = raw.array()?;
+        let mut read_len = CBORReadLen::new(len);
+        read_len.read_elems(2)?;
+        read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for DatumHashOption {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
+        (|| -> Result<_, DeserializeError> {
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 0 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(0),
+                    }
+                    .into());
+                }
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let hash_32 =
+                Hash32::deserialize(raw).map_err(|e: DeserializeError| e.annotate("hash_32"))?;
+            Ok(DatumHashOption { hash_32 })
+        })()
+        .map_err(|e| e.annotate("DatumHashOption"))
+    }
+}
+
+impl Serialize for DatumOption {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            DatumOption::I0 => {
-                serializer.write_array(cbor_event::Len::Len(1))?;
-                serializer.write_unsigned_integer(0u64)
+            DatumOption::DatumHashOption(datum_hash_option) => {
+                datum_hash_option.serialize(serializer, force_canonical)
             }
-            DatumOption::I1 => {
-                serializer.write_array(cbor_event::Len::Len(1))?;
-                serializer.write_unsigned_integer(1u64)
+            DatumOption::InlineDatumOption(inline_datum_option) => {
+                inline_datum_option.serialize(serializer, force_canonical)
             }
         }
     }
@@ -707,16 +874,9 @@ impl Deserialize for DatumOption {
             let mut errs = Vec::new();
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
                 let mut read_len = CBORReadLen::new(len);
-                read_len.read_elems(1)?;
+                read_len.read_elems(2)?;
                 read_len.finish()?;
-                let i0_value = raw.unsigned_integer()?;
-                if i0_value != 0 {
-                    return Err(DeserializeFailure::FixedValueMismatch {
-                        found: Key::Uint(i0_value),
-                        expected: Key::Uint(0),
-                    }
-                    .into());
-                }
+                let ret = DatumHashOption::deserialize_as_embedded_group(raw, &mut read_len, len);
                 match len {
                     cbor_event::Len::Len(_) => (),
                     cbor_event::Len::Indefinite => match raw.special()? {
@@ -724,29 +884,20 @@ impl Deserialize for DatumOption {
                         _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
                     },
                 }
-                Ok(())
+                ret
             })(raw);
             match deser_variant {
-                Ok(()) => return Ok(DatumOption::I0),
+                Ok(datum_hash_option) => return Ok(Self::DatumHashOption(datum_hash_option)),
                 Err(e) => {
-                    errs.push(e.annotate("I0"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    errs.push(e.annotate("DatumHashOption"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
                 let mut read_len = CBORReadLen::new(len);
-                read_len.read_elems(1)?;
+                read_len.read_elems(2)?;
                 read_len.finish()?;
-                let i1_value = raw.unsigned_integer()?;
-                if i1_value != 1 {
-                    return Err(DeserializeFailure::FixedValueMismatch {
-                        found: Key::Uint(i1_value),
-                        expected: Key::Uint(1),
-                    }
-                    .into());
-                }
+                let ret = InlineDatumOption::deserialize_as_embedded_group(raw, &mut read_len, len);
                 match len {
                     cbor_event::Len::Len(_) => (),
                     cbor_event::Len::Indefinite => match raw.special()? {
@@ -754,15 +905,13 @@ impl Deserialize for DatumOption {
                         _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
                     },
                 }
-                Ok(())
+                ret
             })(raw);
             match deser_variant {
-                Ok(()) => return Ok(DatumOption::I1),
+                Ok(inline_datum_option) => return Ok(Self::InlineDatumOption(inline_datum_option)),
                 Err(e) => {
-                    errs.push(e.annotate("I1"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    errs.push(e.annotate("InlineDatumOption"));
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -774,19 +923,24 @@ impl Deserialize for DatumOption {
     }
 }
 
-impl cbor_event::se::Serialize for Deposit {
+impl Serialize for Deposit {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
             Deposit::CredentialDeposit(credential_deposit) => {
-                credential_deposit.serialize(serializer)
+                credential_deposit.serialize(serializer, force_canonical)
+            }
+            Deposit::PoolDeposit(pool_deposit) => {
+                pool_deposit.serialize(serializer, force_canonical)
+            }
+            Deposit::DrepDeposit(drep_deposit) => {
+                drep_deposit.serialize(serializer, force_canonical)
             }
-            Deposit::PoolDeposit(pool_deposit) => pool_deposit.serialize(serializer),
-            Deposit::DrepDeposit(drep_deposit) => drep_deposit.serialize(serializer),
             Deposit::GovActionDeposit(gov_action_deposit) => {
-                gov_action_deposit.serialize(serializer)
+                gov_action_deposit.serialize(serializer, force_canonical)
             }
         }
     }
@@ -816,9 +970,7 @@ impl Deserialize for Deposit {
                 Ok(credential_deposit) => return Ok(Self::CredentialDeposit(credential_deposit)),
                 Err(e) => {
                     errs.push(e.annotate("CredentialDeposit"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -839,9 +991,7 @@ impl Deserialize for Deposit {
                 Ok(pool_deposit) => return Ok(Self::PoolDeposit(pool_deposit)),
                 Err(e) => {
                     errs.push(e.annotate("PoolDeposit"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -862,9 +1012,7 @@ impl Deserialize for Deposit {
                 Ok(drep_deposit) => return Ok(Self::DrepDeposit(drep_deposit)),
                 Err(e) => {
                     errs.push(e.annotate("DrepDeposit"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -885,9 +1033,7 @@ impl Deserialize for Deposit {
                 Ok(gov_action_deposit) => return Ok(Self::GovActionDeposit(gov_action_deposit)),
                 Err(e) => {
                     errs.push(e.annotate("GovActionDeposit"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -899,13 +1045,14 @@ impl Deserialize for Deposit {
     }
 }
 
-impl cbor_event::se::Serialize for DrepDeposit {
+impl Serialize for DrepDeposit {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -913,9 +1060,10 @@ impl SerializeEmbeddedGroup for DrepDeposit {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(2u64)?;
-        self.credential.serialize(serializer)?;
+        self.credential.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -965,13 +1113,14 @@ impl DeserializeEmbeddedGroup for DrepDeposit {
     }
 }
 
-impl cbor_event::se::Serialize for GovActionDeposit {
+impl Serialize for GovActionDeposit {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -979,9 +1128,10 @@ impl SerializeEmbeddedGroup for GovActionDeposit {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(3u64)?;
-        self.gov_action_id.serialize(serializer)?;
+        self.gov_action_id.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -1031,28 +1181,31 @@ impl DeserializeEmbeddedGroup for GovActionDeposit {
     }
 }
 
-impl cbor_event::se::Serialize for InvalidBefore {
+impl Serialize for InlineDatumOption {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
-impl SerializeEmbeddedGroup for InvalidBefore {
+impl SerializeEmbeddedGroup for InlineDatumOption {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
-        serializer.write_unsigned_integer(4u64)?;
-        serializer.write_unsigned_integer(self.slot_no)?;
+        serializer.write_unsigned_integer(1u64)?;
+        serializer.write_tag(24u64)?;
+        serializer.write_bytes(&self.data_bytes)?;
         Ok(serializer)
     }
 }
 
-impl Deserialize for InvalidBefore {
+impl Deserialize for InlineDatumOption {
     fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
         let len = raw.array()?;
         let mut read_len = CBORReadLen::new(len);
@@ -1070,7 +1223,7 @@ impl Deserialize for InvalidBefore {
     }
 }
 
-impl DeserializeEmbeddedGroup for InvalidBefore {
+impl DeserializeEmbeddedGroup for InlineDatumOption {
     fn deserialize_as_embedded_group<R: BufRead + Seek>(
         raw: &mut Deserializer<R>,
         _read_len: &mut CBORReadLen,
@@ -1079,46 +1232,57 @@ impl DeserializeEmbeddedGroup for InvalidBefore {
         (|| -> Result<_, DeserializeError> {
             (|| -> Result<_, DeserializeError> {
                 let index_0_value = raw.unsigned_integer()?;
-                if index_0_value != 4 {
+                if index_0_value != 1 {
                     return Err(DeserializeFailure::FixedValueMismatch {
                         found: Key::Uint(index_0_value),
-                        expected: Key::Uint(4),
+                        expected: Key::Uint(1),
                     }
                     .into());
                 }
                 Ok(())
             })()
             .map_err(|e| e.annotate("index_0"))?;
-            let slot_no = Ok(raw.unsigned_integer()? as u64)
-                .map_err(|e: DeserializeError| e.annotate("slot_no"))?;
-            Ok(InvalidBefore { slot_no })
+            let data_bytes = (|| -> Result<_, DeserializeError> {
+                match raw.tag()? {
+                    24 => Ok(raw.bytes()?),
+                    tag => Err(DeserializeFailure::TagMismatch {
+                        found: tag,
+                        expected: 24,
+                    }
+                    .into()),
+                }
+            })()
+            .map_err(|e| e.annotate("data"))?;
+            Ok(InlineDatumOption { data_bytes })
         })()
-        .map_err(|e| e.annotate("InvalidBefore"))
+        .map_err(|e| e.annotate("InlineDatumOption"))
     }
 }
 
-impl cbor_event::se::Serialize for InvalidHereafter {
+impl Serialize for InvalidBefore {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
-impl SerializeEmbeddedGroup for InvalidHereafter {
+impl SerializeEmbeddedGroup for InvalidBefore {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
-        serializer.write_unsigned_integer(5u64)?;
+        serializer.write_unsigned_integer(4u64)?;
         serializer.write_unsigned_integer(self.slot_no)?;
         Ok(serializer)
     }
 }
 
-impl Deserialize for InvalidHereafter {
+impl Deserialize for InvalidBefore {
     fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
         let len = raw.array()?;
         let mut read_len = CBORReadLen::new(len);
@@ -1136,7 +1300,7 @@ impl Deserialize for InvalidHereafter {
     }
 }
 
-impl DeserializeEmbeddedGroup for InvalidHereafter {
+impl DeserializeEmbeddedGroup for InvalidBefore {
     fn deserialize_as_embedded_group<R: BufRead + Seek>(
         raw: &mut Deserializer<R>,
         _read_len: &mut CBORReadLen,
@@ -1145,10 +1309,10 @@ impl DeserializeEmbeddedGroup for InvalidHereafter {
         (|| -> Result<_, DeserializeError> {
             (|| -> Result<_, DeserializeError> {
                 let index_0_value = raw.unsigned_integer()?;
-                if index_0_value != 5 {
+                if index_0_value != 4 {
                     return Err(DeserializeFailure::FixedValueMismatch {
                         found: Key::Uint(index_0_value),
-                        expected: Key::Uint(5),
+                        expected: Key::Uint(4),
                     }
                     .into());
                 }
@@ -1157,137 +1321,104 @@ impl DeserializeEmbeddedGroup for InvalidHereafter {
             .map_err(|e| e.annotate("index_0"))?;
             let slot_no = Ok(raw.unsigned_integer()? as u64)
                 .map_err(|e: DeserializeError| e.annotate("slot_no"))?;
-            Ok(InvalidHereafter { slot_no })
+            Ok(InvalidBefore { slot_no })
         })()
-        .map_err(|e| e.annotate("InvalidHereafter"))
+        .map_err(|e| e.annotate("InvalidBefore"))
     }
 }
 
-impl cbor_event::se::Serialize for Multiasset {
+impl Serialize for InvalidHereafter {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
+    ) -> cbor_event::Result<&'se mut Serializer<W>> {
+        serializer.write_array(cbor_event::Len::Len(2))?;
+        self.serialize_as_embedded_group(serializer, force_canonical)
+    }
+}
+
+impl SerializeEmbeddedGroup for InvalidHereafter {
+    fn serialize_as_embedded_group<'se, W: Write>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
-        serializer.write_map(cbor_event::Len::Len(2))?;
-        serializer.write_text("policy_id")?;
-        self.policy_id.serialize(serializer)?;
-        serializer.write_text("asset_bundle")?;
-        self.asset_bundle.serialize(serializer)?;
+        serializer.write_unsigned_integer(5u64)?;
+        serializer.write_unsigned_integer(self.slot_no)?;
         Ok(serializer)
     }
 }
 
-impl Deserialize for Multiasset {
+impl Deserialize for InvalidHereafter {
     fn deserialize<R: BufRead + Seek>(raw: &mut Deserializer<R>) -> Result<Self, DeserializeError> {
-        let len = raw.map()?;
+        let len = raw.array()?;
         let mut read_len = CBORReadLen::new(len);
         read_len.read_elems(2)?;
         read_len.finish()?;
+        let ret = Self::deserialize_as_embedded_group(raw, &mut read_len, len);
+        match len {
+            cbor_event::Len::Len(_) => (),
+            cbor_event::Len::Indefinite => match raw.special()? {
+                cbor_event::Special::Break => (),
+                _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
+            },
+        }
+        ret
+    }
+}
+
+impl DeserializeEmbeddedGroup for InvalidHereafter {
+    fn deserialize_as_embedded_group<R: BufRead + Seek>(
+        raw: &mut Deserializer<R>,
+        _read_len: &mut CBORReadLen,
+        len: cbor_event::Len,
+    ) -> Result<Self, DeserializeError> {
         (|| -> Result<_, DeserializeError> {
-            let mut policy_id = None;
-            let mut asset_bundle = None;
-            let mut read = 0;
-            while match len {
-                cbor_event::Len::Len(n) => read < n,
-                cbor_event::Len::Indefinite => true,
-            } {
-                match raw.cbor_type()? {
-                    cbor_event::Type::UnsignedInteger => {
-                        return Err(DeserializeFailure::UnknownKey(Key::Uint(
-                            raw.unsigned_integer()?,
-                        ))
-                        .into())
-                    }
-                    cbor_event::Type::Text => match raw.text()?.as_str() {
-                        "policy_id" => {
-                            if policy_id.is_some() {
-                                return Err(DeserializeFailure::DuplicateKey(Key::Str(
-                                    "policy_id".into(),
-                                ))
-                                .into());
-                            }
-                            policy_id = Some(
-                                Hash28::deserialize(raw)
-                                    .map_err(|e: DeserializeError| e.annotate("policy_id"))?,
-                            );
-                        }
-                        "asset_bundle" => {
-                            if asset_bundle.is_some() {
-                                return Err(DeserializeFailure::DuplicateKey(Key::Str(
-                                    "asset_bundle".into(),
-                                ))
-                                .into());
-                            }
-                            asset_bundle = Some(
-                                AssetQuantityU64::deserialize(raw)
-                                    .map_err(|e: DeserializeError| e.annotate("asset_bundle"))?,
-                            );
-                        }
-                        unknown_key => {
-                            return Err(DeserializeFailure::UnknownKey(Key::Str(
-                                unknown_key.to_owned(),
-                            ))
-                            .into())
-                        }
-                    },
-                    cbor_event::Type::Special => match len {
-                        cbor_event::Len::Len(_) => {
-                            return Err(DeserializeFailure::BreakInDefiniteLen.into())
-                        }
-                        cbor_event::Len::Indefinite => match raw.special()? {
-                            cbor_event::Special::Break => break,
-                            _ => return Err(DeserializeFailure::EndingBreakMissing.into()),
-                        },
-                    },
-                    other_type => {
-                        return Err(DeserializeFailure::UnexpectedKeyType(other_type).into())
+            (|| -> Result<_, DeserializeError> {
+                let index_0_value = raw.unsigned_integer()?;
+                if index_0_value != 5 {
+                    return Err(DeserializeFailure::FixedValueMismatch {
+                        found: Key::Uint(index_0_value),
+                        expected: Key::Uint(5),
                     }
+                    .into());
                 }
-                read += 1;
-            }
-            let policy_id =
-                match policy_id {
-                    Some(x) => x,
-                    None => {
-                        return Err(DeserializeFailure::MandatoryFieldMissing(Key::Str(
-                            String::from("policy_id"),
-                        ))
-                        .into())
-                    }
-                };
-            let asset_bundle =
-                match asset_bundle {
-                    Some(x) => x,
-                    None => {
-                        return Err(DeserializeFailure::MandatoryFieldMissing(Key::Str(
-                            String::from("asset_bundle"),
-                        ))
-                        .into())
-                    }
-                };
-            ();
-            Ok(Self {
-                policy_id,
-                asset_bundle,
-            })
+                Ok(())
+            })()
+            .map_err(|e| e.annotate("index_0"))?;
+            let slot_no = Ok(raw.unsigned_integer()? as u64)
+                .map_err(|e: DeserializeError| e.annotate("slot_no"))?;
+            Ok(InvalidHereafter { slot_no })
         })()
-        .map_err(|e| e.annotate("Multiasset"))
+        .map_err(|e| e.annotate("InvalidHereafter"))
     }
 }
 
-impl cbor_event::se::Serialize for NativeScript {
+impl Serialize for NativeScript {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            NativeScript::ScriptPubkey(script_pubkey) => script_pubkey.serialize(serializer),
-            NativeScript::ScriptAll(script_all) => script_all.serialize(serializer),
-            NativeScript::ScriptAny(script_any) => script_any.serialize(serializer),
-            NativeScript::ScriptNOfK(script_n_of_k) => script_n_of_k.serialize(serializer),
-            NativeScript::InvalidBefore(invalid_before) => invalid_before.serialize(serializer),
+            NativeScript::ScriptPubkey(script_pubkey) => {
+                script_pubkey.serialize(serializer, force_canonical)
+            }
+            NativeScript::ScriptAll(script_all) => {
+                script_all.serialize(serializer, force_canonical)
+            }
+            NativeScript::ScriptAny(script_any) => {
+                script_any.serialize(serializer, force_canonical)
+            }
+            NativeScript::ScriptNOfK(script_n_of_k) => {
+                script_n_of_k.serialize(serializer, force_canonical)
+            }
+            NativeScript::InvalidBefore(invalid_before) => {
+                invalid_before.serialize(serializer, force_canonical)
+            }
             NativeScript::InvalidHereafter(invalid_hereafter) => {
-                invalid_hereafter.serialize(serializer)
+                invalid_hereafter.serialize(serializer, force_canonical)
             }
         }
     }
@@ -1317,9 +1448,7 @@ impl Deserialize for NativeScript {
                 Ok(script_pubkey) => return Ok(Self::ScriptPubkey(script_pubkey)),
                 Err(e) => {
                     errs.push(e.annotate("ScriptPubkey"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1340,9 +1469,7 @@ impl Deserialize for NativeScript {
                 Ok(script_all) => return Ok(Self::ScriptAll(script_all)),
                 Err(e) => {
                     errs.push(e.annotate("ScriptAll"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1363,9 +1490,7 @@ impl Deserialize for NativeScript {
                 Ok(script_any) => return Ok(Self::ScriptAny(script_any)),
                 Err(e) => {
                     errs.push(e.annotate("ScriptAny"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1386,9 +1511,7 @@ impl Deserialize for NativeScript {
                 Ok(script_n_of_k) => return Ok(Self::ScriptNOfK(script_n_of_k)),
                 Err(e) => {
                     errs.push(e.annotate("ScriptNOfK"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1409,9 +1532,7 @@ impl Deserialize for NativeScript {
                 Ok(invalid_before) => return Ok(Self::InvalidBefore(invalid_before)),
                 Err(e) => {
                     errs.push(e.annotate("InvalidBefore"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1432,9 +1553,7 @@ impl Deserialize for NativeScript {
                 Ok(invalid_hereafter) => return Ok(Self::InvalidHereafter(invalid_hereafter)),
                 Err(e) => {
                     errs.push(e.annotate("InvalidHereafter"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -1446,32 +1565,51 @@ impl Deserialize for NativeScript {
     }
 }
 
-impl cbor_event::se::Serialize for PlutusData {
+impl Serialize for PlutusData {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            PlutusData::Constr(constr) => constr.serialize(serializer),
+            PlutusData::Constr(constr) => constr.serialize(serializer, force_canonical),
             PlutusData::MapPlutusDataToPlutusData(map_plutus_data_to_plutus_data) => {
                 serializer.write_map(cbor_event::Len::Len(
                     map_plutus_data_to_plutus_data.len() as u64
                 ))?;
-                for (key, value) in map_plutus_data_to_plutus_data.iter() {
-                    key.serialize(serializer)?;
-                    value.serialize(serializer)?;
+                let mut key_order = map_plutus_data_to_plutus_data
+                    .iter()
+                    .map(|(k, v)| {
+                        let mut buf = Serializer::new_vec();
+                        k.serialize(&mut buf, force_canonical)?;
+                        Ok((buf.finalize(), k, v))
+                    })
+                    .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+                if force_canonical {
+                    key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                        match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                            std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                            diff_ord => diff_ord,
+                        }
+                    });
+                }
+                for (key_bytes, _key, value) in key_order {
+                    serializer.write_raw_bytes(&key_bytes)?;
+                    value.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
             PlutusData::ArrPlutusData(arr_plutus_data) => {
                 serializer.write_array(cbor_event::Len::Len(arr_plutus_data.len() as u64))?;
                 for element in arr_plutus_data.iter() {
-                    element.serialize(serializer)?;
+                    element.serialize(serializer, force_canonical)?;
                 }
                 Ok(serializer)
             }
-            PlutusData::BigInt(big_int) => big_int.serialize(serializer),
-            PlutusData::BoundedBytes(bounded_bytes) => bounded_bytes.serialize(serializer),
+            PlutusData::BigInt(big_int) => big_int.serialize(serializer, force_canonical),
+            PlutusData::BoundedBytes(bounded_bytes) => {
+                bounded_bytes.serialize(serializer, force_canonical)
+            }
         }
     }
 }
@@ -1486,9 +1624,7 @@ impl Deserialize for PlutusData {
                 Ok(constr) => return Ok(Self::Constr(constr)),
                 Err(e) => {
                     errs.push(e.annotate("Constr"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1529,9 +1665,7 @@ impl Deserialize for PlutusData {
                 }
                 Err(e) => {
                     errs.push(e.annotate("MapPlutusDataToPlutusData"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1553,9 +1687,7 @@ impl Deserialize for PlutusData {
                 Ok(arr_plutus_data) => return Ok(Self::ArrPlutusData(arr_plutus_data)),
                 Err(e) => {
                     errs.push(e.annotate("ArrPlutusData"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant: Result<_, DeserializeError> = BigInt::deserialize(raw);
@@ -1563,9 +1695,7 @@ impl Deserialize for PlutusData {
                 Ok(big_int) => return Ok(Self::BigInt(big_int)),
                 Err(e) => {
                     errs.push(e.annotate("BigInt"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant: Result<_, DeserializeError> = BoundedBytes::deserialize(raw);
@@ -1573,9 +1703,7 @@ impl Deserialize for PlutusData {
                 Ok(bounded_bytes) => return Ok(Self::BoundedBytes(bounded_bytes)),
                 Err(e) => {
                     errs.push(e.annotate("BoundedBytes"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -1587,13 +1715,14 @@ impl Deserialize for PlutusData {
     }
 }
 
-impl cbor_event::se::Serialize for PoolDeposit {
+impl Serialize for PoolDeposit {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -1601,9 +1730,10 @@ impl SerializeEmbeddedGroup for PoolDeposit {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(1u64)?;
-        self.keyhash.serialize(serializer)?;
+        self.keyhash.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -1653,10 +1783,11 @@ impl DeserializeEmbeddedGroup for PoolDeposit {
     }
 }
 
-impl cbor_event::se::Serialize for Script {
+impl Serialize for Script {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        _force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
             Script::Naitve => {
@@ -1710,9 +1841,7 @@ impl Deserialize for Script {
                 Ok(()) => return Ok(Script::Naitve),
                 Err(e) => {
                     errs.push(e.annotate("Naitve"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1740,9 +1869,7 @@ impl Deserialize for Script {
                 Ok(()) => return Ok(Script::PlutusV1),
                 Err(e) => {
                     errs.push(e.annotate("PlutusV1"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1770,9 +1897,7 @@ impl Deserialize for Script {
                 Ok(()) => return Ok(Script::PlutusV2),
                 Err(e) => {
                     errs.push(e.annotate("PlutusV2"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             let deser_variant = (|raw: &mut Deserializer<_>| -> Result<_, DeserializeError> {
@@ -1800,9 +1925,7 @@ impl Deserialize for Script {
                 Ok(()) => return Ok(Script::PlutusV3),
                 Err(e) => {
                     errs.push(e.annotate("PlutusV3"));
-                    raw.as_mut_ref()
-                        .seek(SeekFrom::Start(initial_position))
-                        .unwrap();
+                    raw.as_mut_ref().seek(SeekFrom::Start(initial_position)).unwrap();
                 }
             };
             Err(DeserializeError::new(
@@ -1814,13 +1937,14 @@ impl Deserialize for Script {
     }
 }
 
-impl cbor_event::se::Serialize for ScriptAll {
+impl Serialize for ScriptAll {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -1828,11 +1952,12 @@ impl SerializeEmbeddedGroup for ScriptAll {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(1u64)?;
         serializer.write_array(cbor_event::Len::Len(self.native_scripts.len() as u64))?;
         for element in self.native_scripts.iter() {
-            element.serialize(serializer)?;
+            element.serialize(serializer, force_canonical)?;
         }
         Ok(serializer)
     }
@@ -1897,13 +2022,14 @@ impl DeserializeEmbeddedGroup for ScriptAll {
     }
 }
 
-impl cbor_event::se::Serialize for ScriptAny {
+impl Serialize for ScriptAny {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -1911,11 +2037,12 @@ impl SerializeEmbeddedGroup for ScriptAny {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(2u64)?;
         serializer.write_array(cbor_event::Len::Len(self.native_scripts.len() as u64))?;
         for element in self.native_scripts.iter() {
-            element.serialize(serializer)?;
+            element.serialize(serializer, force_canonical)?;
         }
         Ok(serializer)
     }
@@ -1980,13 +2107,14 @@ impl DeserializeEmbeddedGroup for ScriptAny {
     }
 }
 
-impl cbor_event::se::Serialize for ScriptNOfK {
+impl Serialize for ScriptNOfK {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(3))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -1994,6 +2122,7 @@ impl SerializeEmbeddedGroup for ScriptNOfK {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(3u64)?;
         if self.n >= 0 {
@@ -2006,7 +2135,7 @@ impl SerializeEmbeddedGroup for ScriptNOfK {
         }
         serializer.write_array(cbor_event::Len::Len(self.native_scripts.len() as u64))?;
         for element in self.native_scripts.iter() {
-            element.serialize(serializer)?;
+            element.serialize(serializer, force_canonical)?;
         }
         Ok(serializer)
     }
@@ -2078,13 +2207,14 @@ impl DeserializeEmbeddedGroup for ScriptNOfK {
     }
 }
 
-impl cbor_event::se::Serialize for ScriptPubkey {
+impl Serialize for ScriptPubkey {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.serialize_as_embedded_group(serializer)
+        self.serialize_as_embedded_group(serializer, force_canonical)
     }
 }
 
@@ -2092,9 +2222,10 @@ impl SerializeEmbeddedGroup for ScriptPubkey {
     fn serialize_as_embedded_group<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_unsigned_integer(0u64)?;
-        self.hash_28.serialize(serializer)?;
+        self.hash_28.serialize(serializer, force_canonical)?;
         Ok(serializer)
     }
 }
@@ -2144,10 +2275,11 @@ impl DeserializeEmbeddedGroup for ScriptPubkey {
     }
 }
 
-impl cbor_event::se::Serialize for ShelleyTxOut {
+impl Serialize for ShelleyTxOut {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(
             2 + match &self.hash_32 {
@@ -2156,9 +2288,9 @@ impl cbor_event::se::Serialize for ShelleyTxOut {
             },
         ))?;
         serializer.write_bytes(&self.address)?;
-        self.value.serialize(serializer)?;
+        self.value.serialize(serializer, force_canonical)?;
         if let Some(field) = &self.hash_32 {
-            field.serialize(serializer)?;
+            field.serialize(serializer, force_canonical)?;
         }
         Ok(serializer)
     }
@@ -2174,20 +2306,17 @@ impl Deserialize for ShelleyTxOut {
                 Ok(raw.bytes()? as Vec<u8>).map_err(|e: DeserializeError| e.annotate("address"))?;
             let value =
                 Value::deserialize(raw).map_err(|e: DeserializeError| e.annotate("value"))?;
-            let hash_32 = if raw
-                .cbor_type()
-                .map(|ty| ty == cbor_event::Type::Bytes)
-                .unwrap_or(false)
-            {
-                (|| -> Result<_, DeserializeError> {
-                    read_len.read_elems(1)?;
-                    Hash32::deserialize(raw)
-                })()
-                .map_err(|e| e.annotate("hash_32"))
-                .map(Some)
-            } else {
-                Ok(None)
-            }?;
+            let hash_32 =
+                if raw.cbor_type().map(|ty| ty == cbor_event::Type::Bytes).unwrap_or(false) {
+                    (|| -> Result<_, DeserializeError> {
+                        read_len.read_elems(1)?;
+                        Hash32::deserialize(raw)
+                    })()
+                    .map_err(|e| e.annotate("hash_32"))
+                    .map(Some)
+                } else {
+                    Ok(None)
+                }?;
             match len {
                 cbor_event::Len::Len(_) => read_len.finish()?,
                 cbor_event::Len::Indefinite => match raw.special()? {
@@ -2205,13 +2334,14 @@ impl Deserialize for ShelleyTxOut {
     }
 }
 
-impl cbor_event::se::Serialize for TxIn {
+impl Serialize for TxIn {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_array(cbor_event::Len::Len(2))?;
-        self.hash_32.serialize(serializer)?;
+        self.hash_32.serialize(serializer, force_canonical)?;
         serializer.write_unsigned_integer(self.uint as u64)?;
         Ok(serializer)
     }
@@ -2241,14 +2371,19 @@ impl Deserialize for TxIn {
     }
 }
 
-impl cbor_event::se::Serialize for TxOut {
+impl Serialize for TxOut {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
-            TxOut::ShelleyTxOut(shelley_tx_out) => shelley_tx_out.serialize(serializer),
-            TxOut::BabbageTxOut(babbage_tx_out) => babbage_tx_out.serialize(serializer),
+            TxOut::ShelleyTxOut(shelley_tx_out) => {
+                shelley_tx_out.serialize(serializer, force_canonical)
+            }
+            TxOut::BabbageTxOut(babbage_tx_out) => {
+                babbage_tx_out.serialize(serializer, force_canonical)
+            }
         }
     }
 }
@@ -2269,24 +2404,59 @@ impl Deserialize for TxOut {
     }
 }
 
-impl cbor_event::se::Serialize for UtxoState {
+impl Serialize for UtxoState {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         serializer.write_map(cbor_event::Len::Len(4))?;
         serializer.write_text("fees")?;
         serializer.write_unsigned_integer(self.fees)?;
         serializer.write_text("utxos")?;
         serializer.write_map(cbor_event::Len::Len(self.utxos.len() as u64))?;
-        for (key, value) in self.utxos.iter() {
-            key.serialize(serializer)?;
-            value.serialize(serializer)?;
+        let mut key_order = self
+            .utxos
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
+            value.serialize(serializer, force_canonical)?;
         }
         serializer.write_text("deposits")?;
         serializer.write_map(cbor_event::Len::Len(self.deposits.len() as u64))?;
-        for (key, value) in self.deposits.iter() {
-            key.serialize(serializer)?;
+        let mut key_order = self
+            .deposits
+            .iter()
+            .map(|(k, v)| {
+                let mut buf = Serializer::new_vec();
+                k.serialize(&mut buf, force_canonical)?;
+                Ok((buf.finalize(), k, v))
+            })
+            .collect::<Result<Vec<(Vec<u8>, &_, &_)>, cbor_event::Error>>()?;
+        if force_canonical {
+            key_order.sort_by(|(lhs_bytes, _, _), (rhs_bytes, _, _)| {
+                match lhs_bytes.len().cmp(&rhs_bytes.len()) {
+                    std::cmp::Ordering::Equal => lhs_bytes.cmp(rhs_bytes),
+                    diff_ord => diff_ord,
+                }
+            });
+        }
+        for (key_bytes, _key, value) in key_order {
+            serializer.write_raw_bytes(&key_bytes)?;
             serializer.write_unsigned_integer(*value)?;
         }
         serializer.write_text("donations")?;
@@ -2493,14 +2663,15 @@ impl Deserialize for UtxoState {
     }
 }
 
-impl cbor_event::se::Serialize for Value {
+impl Serialize for Value {
     fn serialize<'se, W: Write>(
         &self,
         serializer: &'se mut Serializer<W>,
+        force_canonical: bool,
     ) -> cbor_event::Result<&'se mut Serializer<W>> {
         match self {
             Value::Coin(coin) => serializer.write_unsigned_integer(*coin),
-            Value::AssetValue(asset_value) => asset_value.serialize(serializer),
+            Value::AssetValue(asset_value) => asset_value.serialize(serializer, force_canonical),
         }
     }
 }
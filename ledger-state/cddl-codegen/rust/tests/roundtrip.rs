@@ -0,0 +1,359 @@
+//! Property tests: every generated value must decode from its own CBOR and
+//! encode back to the same bytes. Choices are decoded by trying each variant in
+//! turn and seeking back, so an encoding which an earlier variant also accepts
+//! would quietly come back as the wrong thing - these are meant to catch that.
+
+use std::fmt::Debug;
+
+use canonical_ledger_state::{
+    accounts::{Account, AccountsState, Drep},
+    common::{Credential, GovActionId, Hash28, Hash32, UnitInterval, Url},
+    serialization::{Deserialize, ToCBORBytes},
+    spos::{DnsName, Ipv4, Ipv6, PoolMetadata, PoolParameters, Relay, SpoState},
+    utxos::{
+        AssetName, AssetValue, BabbageTxOut, BigInt, BoundedBytes, Constr, DatumOption, Deposit,
+        Multiasset, NativeScript, PlutusData, Script, ShelleyTxOut, TxIn, TxOut, UtxoState, Value,
+    },
+    Int,
+};
+use proptest::{
+    collection::{btree_map, vec},
+    option,
+    prelude::*,
+};
+
+/// Decodes `value`'s CBOR, checks it encodes to the same bytes and hands the
+/// decoded value back for any further checks
+fn round_trip<T: Deserialize + ToCBORBytes + Debug>(value: &T) -> Result<T, TestCaseError> {
+    let bytes = value.to_cbor_bytes();
+    let decoded = T::from_cbor_bytes(&bytes)
+        .map_err(|e| TestCaseError::fail(format!("{:?} didn't decode: {:?}", value, e)))?;
+    prop_assert_eq!(hex::encode(decoded.to_cbor_bytes()), hex::encode(&bytes));
+    Ok(decoded)
+}
+
+fn hash28() -> impl Strategy<Value = Hash28> {
+    vec(any::<u8>(), 28).prop_map(|bytes| Hash28::new(bytes).unwrap())
+}
+
+fn hash32() -> impl Strategy<Value = Hash32> {
+    vec(any::<u8>(), 32).prop_map(|bytes| Hash32::new(bytes).unwrap())
+}
+
+fn credential() -> impl Strategy<Value = Credential> {
+    prop_oneof![
+        hash28().prop_map(Credential::new_addr_keyhash_credential),
+        hash28().prop_map(Credential::new_script_hash_credential),
+    ]
+}
+
+fn int() -> impl Strategy<Value = Int> {
+    prop_oneof![
+        any::<u64>().prop_map(Int::new_uint),
+        any::<u64>().prop_map(Int::new_nint),
+    ]
+}
+
+fn bounded_bytes() -> impl Strategy<Value = BoundedBytes> {
+    vec(any::<u8>(), 0..=64).prop_map(|bytes| BoundedBytes::new(bytes).unwrap())
+}
+
+fn constr(alternative: usize, fields: Vec<PlutusData>) -> Constr {
+    match alternative {
+        0 => Constr::new_arr_plutus_data(fields),
+        1 => Constr::new_arr_plutus_data2(fields),
+        2 => Constr::new_arr_plutus_data3(fields),
+        3 => Constr::new_arr_plutus_data4(fields),
+        4 => Constr::new_arr_plutus_data5(fields),
+        5 => Constr::new_arr_plutus_data6(fields),
+        _ => Constr::new_arr_plutus_data7(fields),
+    }
+}
+
+/// Plutus data nested at most four deep
+fn plutus_data() -> impl Strategy<Value = PlutusData> {
+    let leaf = prop_oneof![
+        int().prop_map(|int| PlutusData::new_big_int(BigInt::new_int(int))),
+        bounded_bytes().prop_map(|bytes| PlutusData::new_big_int(BigInt::new_big_uint(bytes))),
+        bounded_bytes().prop_map(|bytes| PlutusData::new_big_int(BigInt::new_big_nint(bytes))),
+        bounded_bytes().prop_map(PlutusData::new_bounded_bytes),
+    ];
+    leaf.prop_recursive(4, 64, 4, |inner| {
+        prop_oneof![
+            (0..7usize, vec(inner.clone(), 0..4)).prop_map(|(alternative, fields)| {
+                PlutusData::new_constr(constr(alternative, fields))
+            }),
+            btree_map(inner.clone(), inner.clone(), 0..4)
+                .prop_map(PlutusData::new_map_plutus_data_to_plutus_data),
+            vec(inner, 0..4).prop_map(PlutusData::new_arr_plutus_data),
+        ]
+    })
+}
+
+/// Native scripts nested at most four deep
+fn native_script() -> impl Strategy<Value = NativeScript> {
+    let leaf = prop_oneof![
+        hash28().prop_map(NativeScript::new_script_pubkey),
+        any::<u64>().prop_map(NativeScript::new_invalid_before),
+        any::<u64>().prop_map(NativeScript::new_invalid_hereafter),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(NativeScript::new_script_all),
+            vec(inner.clone(), 0..4).prop_map(NativeScript::new_script_any),
+            (any::<i64>(), vec(inner, 0..4))
+                .prop_map(|(n, scripts)| NativeScript::new_script_n_of_k(n, scripts)),
+        ]
+    })
+}
+
+fn deposit() -> impl Strategy<Value = Deposit> {
+    prop_oneof![
+        credential().prop_map(Deposit::new_credential_deposit),
+        hash28().prop_map(Deposit::new_pool_deposit),
+        credential().prop_map(Deposit::new_drep_deposit),
+        (hash32(), any::<u16>()).prop_map(|(tx_hash, index)| {
+            Deposit::new_gov_action_deposit(GovActionId::new(tx_hash, index))
+        }),
+    ]
+}
+
+fn multiasset() -> impl Strategy<Value = Multiasset> {
+    let asset_name = vec(any::<u8>(), 0..=32).prop_map(|bytes| AssetName::new(bytes).unwrap());
+    btree_map(hash28(), btree_map(asset_name, any::<u64>(), 1..4), 0..4)
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        any::<u64>().prop_map(Value::new_coin),
+        (any::<u64>(), multiasset())
+            .prop_map(|(coin, assets)| Value::new_asset_value(AssetValue::new(coin, assets))),
+    ]
+}
+
+fn datum_option() -> impl Strategy<Value = DatumOption> {
+    prop_oneof![
+        hash32().prop_map(DatumOption::new_datum_hash_option),
+        plutus_data().prop_map(|data| DatumOption::new_inline_datum_option(data.to_cbor_bytes())),
+    ]
+}
+
+fn script() -> impl Strategy<Value = Script> {
+    prop_oneof![
+        Just(Script::new_naitve()),
+        Just(Script::new_plutus_v1()),
+        Just(Script::new_plutus_v2()),
+        Just(Script::new_plutus_v3()),
+    ]
+}
+
+fn address() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![vec(any::<u8>(), 29), vec(any::<u8>(), 57)]
+}
+
+fn tx_out() -> impl Strategy<Value = TxOut> {
+    prop_oneof![
+        (address(), value(), option::of(hash32())).prop_map(|(address, value, datum_hash)| {
+            let mut out = ShelleyTxOut::new(address, value);
+            out.hash_32 = datum_hash;
+            TxOut::new_shelley_tx_out(out)
+        }),
+        (
+            address(),
+            value(),
+            option::of(datum_option()),
+            option::of(script())
+        )
+            .prop_map(|(address, value, datum, script_ref)| {
+                let mut out = BabbageTxOut::new(address, value);
+                out.key_2 = datum;
+                out.key_3 = script_ref;
+                TxOut::new_babbage_tx_out(out)
+            }),
+    ]
+}
+
+fn utxo_state() -> impl Strategy<Value = UtxoState> {
+    let tx_in = (hash32(), any::<u16>()).prop_map(|(tx_hash, index)| TxIn::new(tx_hash, index));
+    (
+        btree_map(tx_in, tx_out(), 0..4),
+        any::<u64>(),
+        btree_map(deposit(), any::<u64>(), 0..4),
+        any::<u64>(),
+    )
+        .prop_map(|(utxos, fees, deposits, donations)| {
+            UtxoState::new(utxos, fees, deposits, donations)
+        })
+}
+
+fn relay() -> impl Strategy<Value = Relay> {
+    let dns_name = "[a-z0-9.-]{1,64}".prop_map(|name| DnsName::new(name).unwrap());
+    let ipv4 = vec(any::<u8>(), 4).prop_map(|bytes| Ipv4::new(bytes).unwrap());
+    let ipv6 = vec(any::<u8>(), 16).prop_map(|bytes| Ipv6::new(bytes).unwrap());
+    prop_oneof![
+        (option::of(any::<u16>()), option::of(ipv4), option::of(ipv6))
+            .prop_map(|(port, ipv4, ipv6)| Relay::new_single_host_addr(port, ipv4, ipv6)),
+        (option::of(any::<u16>()), dns_name.clone())
+            .prop_map(|(port, dns_name)| Relay::new_single_host_name(port, dns_name)),
+        dns_name.prop_map(Relay::new_multi_host_name),
+    ]
+}
+
+fn pool_parameters() -> impl Strategy<Value = PoolParameters> {
+    let margin = (any::<u64>(), 1..=u64::MAX).prop_map(|(n, d)| UnitInterval::new(n, d));
+    let metadata = (
+        "https://[a-z]{1,20}\\.example/[a-z]{0,20}",
+        vec(any::<u8>(), 32),
+    )
+        .prop_map(|(url, hash)| PoolMetadata::new(Url::new(url).unwrap(), hash));
+    (
+        hash28(),
+        hash32(),
+        any::<u64>(),
+        any::<u64>(),
+        margin,
+        vec(any::<u8>(), 29),
+        vec(hash28(), 1..4),
+        vec(relay(), 0..3),
+        option::of(metadata),
+    )
+        .prop_map(
+            |(operator, vrf, pledge, cost, margin, reward_account, owners, relays, metadata)| {
+                PoolParameters::new(
+                    operator,
+                    vrf,
+                    pledge,
+                    cost,
+                    margin,
+                    reward_account,
+                    owners,
+                    relays,
+                    metadata,
+                )
+            },
+        )
+}
+
+fn drep() -> impl Strategy<Value = Drep> {
+    prop_oneof![
+        hash28().prop_map(Drep::new_drep_keyhash),
+        hash28().prop_map(Drep::new_drep_script_hash),
+        Just(Drep::new_always_abstain()),
+        Just(Drep::new_always_no_confidence()),
+    ]
+}
+
+fn accounts_state() -> impl Strategy<Value = AccountsState> {
+    let account = (
+        any::<u64>(),
+        any::<u64>(),
+        option::of(hash28()),
+        option::of(drep()),
+    )
+        .prop_map(|(rewards, deposit, pool, drep)| Account::new(rewards, deposit, pool, drep));
+    btree_map(credential(), account, 0..4).prop_map(AccountsState::new)
+}
+
+proptest! {
+    #[test]
+    fn plutus_data_round_trips(data in plutus_data()) {
+        prop_assert_eq!(round_trip(&data)?, data);
+    }
+
+    #[test]
+    fn native_scripts_round_trip(script in native_script()) {
+        round_trip(&script)?;
+    }
+
+    #[test]
+    fn deposits_round_trip(deposit in deposit()) {
+        prop_assert_eq!(round_trip(&deposit)?, deposit);
+    }
+
+    #[test]
+    fn tx_outs_round_trip(out in tx_out()) {
+        round_trip(&out)?;
+    }
+
+    #[test]
+    fn utxo_states_round_trip(state in utxo_state()) {
+        let decoded = round_trip(&state)?;
+        prop_assert_eq!(
+            decoded.deposits.keys().collect::<Vec<_>>(),
+            state.deposits.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn spo_states_round_trip(
+        pools in btree_map(hash28(), pool_parameters(), 0..3),
+        retiring in btree_map(hash28(), any::<u64>(), 0..3),
+    ) {
+        round_trip(&SpoState::new(pools, retiring))?;
+    }
+
+    #[test]
+    fn accounts_states_round_trip(state in accounts_state()) {
+        let decoded = round_trip(&state)?;
+        let dreps = |state: &AccountsState| {
+            state.accounts.values().map(|account| account.drep.clone()).collect::<Vec<_>>()
+        };
+        prop_assert_eq!(dreps(&decoded), dreps(&state));
+    }
+
+    #[test]
+    fn canonical_encodings_are_stable(state in utxo_state()) {
+        let canonical = state.to_canonical_cbor_bytes();
+        let decoded = UtxoState::from_cbor_bytes(&canonical).map_err(|e| {
+            TestCaseError::fail(format!("canonical encoding didn't decode: {:?}", e))
+        })?;
+        prop_assert_eq!(hex::encode(decoded.to_canonical_cbor_bytes()), hex::encode(&canonical));
+        prop_assert_eq!(hex::encode(decoded.to_cbor_bytes()), hex::encode(state.to_cbor_bytes()));
+    }
+
+    #[test]
+    fn canonical_plutus_data_decodes_to_the_same_data(data in plutus_data()) {
+        let decoded = PlutusData::from_cbor_bytes(&data.to_canonical_cbor_bytes()).map_err(|e| {
+            TestCaseError::fail(format!("canonical encoding didn't decode: {:?}", e))
+        })?;
+        prop_assert_eq!(decoded, data);
+    }
+}
+
+#[test]
+fn inline_datums_keep_their_bytes() {
+    let data = PlutusData::new_constr(Constr::new_arr_plutus_data(vec![PlutusData::new_big_int(
+        BigInt::new_int(Int::new_nint(41)),
+    )]));
+    let out = DatumOption::new_inline_datum_option(data.to_cbor_bytes());
+    let decoded = round_trip(&out).unwrap();
+    match decoded {
+        DatumOption::InlineDatumOption(inline) => {
+            let inner = PlutusData::from_cbor_bytes(&inline.data_bytes).unwrap();
+            assert_eq!(inner, data);
+        }
+        other => panic!("expected an inline datum, got {:?}", other),
+    }
+}
+
+#[test]
+fn canonical_encoding_puts_shorter_keys_first() {
+    let policy = Hash28::new(vec![7; 28]).unwrap();
+    let assets = vec![(vec![0, 0], 1), (vec![0xff], 2)]
+        .into_iter()
+        .map(|(name, quantity)| (AssetName::new(name).unwrap(), quantity))
+        .collect();
+    let value = Value::new_asset_value(AssetValue::new(
+        0,
+        vec![(policy, assets)].into_iter().collect(),
+    ));
+
+    let position = |bytes: &[u8], key: &[u8]| {
+        bytes.windows(key.len()).position(|window| window == key).unwrap()
+    };
+    // The maps are ordered by name, which puts 0000 first, but its encoding is longer
+    let plain = value.to_cbor_bytes();
+    assert!(position(&plain, &[0x42, 0, 0]) < position(&plain, &[0x41, 0xff]));
+    let canonical = value.to_canonical_cbor_bytes();
+    assert!(position(&canonical, &[0x41, 0xff]) < position(&canonical, &[0x42, 0, 0]));
+}
@@ -4,13 +4,17 @@
 use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper, ValidationContext},
     declare_cardano_reader,
+    epoch_barrier::EpochBarrierAcker,
     messages::{
         CardanoMessage, EpochActivityMessage, GenesisCompleteMessage, GovernanceOutcomesMessage,
         GovernanceProceduresMessage, Message, ProtocolParamsMessage, SPOStateMessage,
         SnapshotMessage, SnapshotStateMessage, StakeAddressDeltasMessage, StateQueryResponse,
         StateTransitionMessage, TxCertificatesMessage, WithdrawalsMessage,
     },
+    metrics,
     queries::{accounts::AccountsStateQueryResponse, errors::QueryError},
+    readiness,
+    state_digest::{StateDigest, StateDigestPublisher},
     state_history::{StateHistory, StateHistoryStore},
     Era,
 };
@@ -18,6 +22,7 @@ use anyhow::{bail, Result};
 use caryatid_sdk::{message_bus::Subscription, module, Context};
 use config::Config;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 use tracing::{error, info, info_span, Instrument};
 
@@ -41,6 +46,7 @@ mod pots_publisher;
 mod queries;
 mod rewards;
 mod runtime;
+mod stake_snapshot_publisher;
 mod verifier;
 
 use runtime::{AccountsRuntime, BlockStakeAddressUndoRecorder};
@@ -142,6 +148,9 @@ struct AccountsPublishers {
     pub stake_reward_deltas: StakeRewardDeltasPublisher,
     pub registration_updates: StakeRegistrationUpdatesPublisher,
     pub pots: PotsPublisher,
+    pub stake_snapshot: Option<StakeSnapshotPublisher>,
+    pub state_digest: Option<StateDigestPublisher>,
+    pub epoch_barrier: Option<EpochBarrierAcker>,
 }
 
 /// Accounts State module
@@ -225,6 +234,8 @@ impl AccountsState {
 
         let mut skip_first_epoch_rewards = snapshot_mode;
 
+        let block_apply_latency = metrics::block_apply_latency("accounts-state");
+
         // Main loop of synchronised messages
         loop {
             let mut ctx =
@@ -245,6 +256,7 @@ impl AccountsState {
                 "readers.certs",
                 readers.certs.read_with_rollbacks().await,
             )?;
+            let started = Instant::now();
 
             if let Some(rollback_message) = primary.rollback_message() {
                 state.rollback_stake_addresses(
@@ -261,6 +273,14 @@ impl AccountsState {
                 publishers.stake_reward_deltas.publish_message(rollback_message.clone()).await?;
                 publishers.registration_updates.publish_message(rollback_message.clone()).await?;
                 publishers.pots.publish_message(rollback_message.clone()).await?;
+                if let Some(stake_snapshot) = publishers.stake_snapshot.as_mut() {
+                    stake_snapshot.publish_message(rollback_message.clone()).await?;
+                }
+            }
+
+            // Digest of the state as the previous epoch ended
+            if let (Some(state_digest), Some(_)) = (&publishers.state_digest, primary.epoch()) {
+                state_digest.publish_state(primary.block_info(), &state).await;
             }
 
             // Init drains the epoch-0 bootstrap messages, so the main loop only
@@ -332,6 +352,18 @@ impl AccountsState {
                         publishers.spo_distribution.publish_spdd(block_info, spdd).await,
                     );
 
+                    // Publish the per-account snapshot behind it, if wanted - there is
+                    // none before Shelley
+                    if let Some(stake_snapshot) = publishers.stake_snapshot.as_mut() {
+                        let snapshot = state.get_latest_snapshot();
+                        if snapshot.epoch + 1 == block_info.epoch {
+                            ctx.handle(
+                                "publish_stake_snapshot",
+                                stake_snapshot.publish_snapshot(block_info, snapshot).await,
+                            );
+                        }
+                    }
+
                     let default_vote = state.generate_default_vote();
                     ctx.handle(
                         "publish_spo_default_vote",
@@ -534,10 +566,15 @@ impl AccountsState {
 
                 runtime.stake_address_undo_history.commit(block_info.number, stake_address_undo);
                 history.lock().await.commit(block_info.number, state);
+                block_apply_latency.observe_duration(started.elapsed());
 
                 if primary.do_validation() {
                     ctx.publish().await;
                 }
+
+                if let (Some(acker), Some(_)) = (&publishers.epoch_barrier, primary.epoch()) {
+                    acker.ack(block_info).await;
+                }
             } else {
                 ctx.get_validation().print_errors("accounts_state", None);
             }
@@ -546,20 +583,25 @@ impl AccountsState {
 
     /// Async initialisation
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "accounts-state").await?;
+
         // Get configuration
         let accounts_cfg = AccountsConfig::load(context.clone(), &config).await?;
 
         // History
-        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
-            "accounts_state",
-            StateHistoryStore::default_block_store(),
-        )));
+        let history = Arc::new(Mutex::new(
+            StateHistory::<State>::new("accounts_state", StateHistoryStore::default_block_store())
+                .with_size_estimate(State::estimated_size),
+        ));
         let history_query = history.clone();
         let history_tick = history.clone();
 
+        let query_metrics = metrics::QueryMetrics::new("accounts-state");
         context.handle(&accounts_cfg.accounts_query_topic, move |message| {
             let history = history_query.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let guard = history.lock().await;
 
                 let state = match guard.current() {
@@ -567,7 +609,7 @@ impl AccountsState {
                     None => {
                         return Arc::new(Message::StateQueryResponse(
                             StateQueryResponse::Accounts(AccountsStateQueryResponse::Error(
-                                QueryError::not_found("Current state"),
+                                QueryError::not_synced("No current accounts state"),
                             )),
                         ));
                     }
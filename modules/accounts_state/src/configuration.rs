@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
 use acropolis_common::{
-    configuration::{get_string_flag, StartupMode},
+    configuration::{get_string_flag, get_u64_flag, StartupMode},
+    epoch_barrier::EpochBarrierAcker,
     messages::Message,
     queries::accounts::DEFAULT_ACCOUNTS_QUERY_TOPIC,
+    state_digest::StateDigestPublisher,
 };
 use caryatid_sdk::Context;
 use config::Config;
@@ -14,10 +16,10 @@ use crate::{
     registration_updates_publisher::StakeRegistrationUpdatesPublisher,
     spo_distribution_publisher::SPODistributionPublisher,
     spo_rewards_publisher::SPORewardsPublisher,
-    stake_reward_deltas_publisher::StakeRewardDeltasPublisher, verifier::Verifier,
-    AccountsPublishers, AccountsReaders, CertsReader, EpochActivityReader, GenesisReader,
-    GovOutcomesReader, GovProceduresReader, ParamsReader, SPOReader, StakeDeltasReader,
-    WithdrawalsReader,
+    stake_reward_deltas_publisher::StakeRewardDeltasPublisher,
+    stake_snapshot_publisher::StakeSnapshotPublisher, verifier::Verifier, AccountsPublishers,
+    AccountsReaders, CertsReader, EpochActivityReader, GenesisReader, GovOutcomesReader,
+    GovProceduresReader, ParamsReader, SPOReader, StakeDeltasReader, WithdrawalsReader,
 };
 
 // Publishers
@@ -27,6 +29,8 @@ const DEFAULT_DREP_DISTRIBUTION_TOPIC: (&str, &str) = (
 );
 const DEFAULT_SPO_DISTRIBUTION_TOPIC: (&str, &str) =
     ("publish-spo-distribution-topic", "cardano.spo.distribution");
+/// Maximum pools per SPDD message - 0 publishes the whole SPDD in one message
+const DEFAULT_SPDD_CHUNK_SIZE: (&str, u64) = ("spdd-chunk-size", 0);
 const DEFAULT_SPO_DEFAULT_VOTE_TOPIC: (&str, &str) =
     ("publish-spo-default-vote-topic", "cardano.spo.default-vote");
 const DEFAULT_SPO_REWARDS_TOPIC: (&str, &str) =
@@ -40,6 +44,8 @@ const DEFAULT_STAKE_REGISTRATION_UPDATES_TOPIC: (&str, &str) = (
     "cardano.stake.registration.updates",
 );
 const DEFAULT_POTS_TOPIC: (&str, &str) = ("publish-pots-topic", "cardano.pots");
+/// Per-account stake snapshots are big, so only published if a topic is set
+const DEFAULT_STAKE_SNAPSHOT_TOPIC: (&str, &str) = ("publish-stake-snapshot-topic", "");
 const DEFAULT_VALIDATION_OUTCOMES_TOPIC: (&str, &str) =
     ("validation-outcomes-topic", "cardano.validation.accounts");
 
@@ -102,6 +108,7 @@ impl AccountsConfig {
                 spo_distribution: SPODistributionPublisher::new(
                     context.clone(),
                     get_string_flag(config, DEFAULT_SPO_DISTRIBUTION_TOPIC),
+                    get_u64_flag(config, DEFAULT_SPDD_CHUNK_SIZE) as usize,
                 ),
                 spo_default_vote: SPODefaultVotePublisher::new(
                     context.clone(),
@@ -123,6 +130,19 @@ impl AccountsConfig {
                     context.clone(),
                     get_string_flag(config, DEFAULT_POTS_TOPIC),
                 ),
+                stake_snapshot: Some(get_string_flag(config, DEFAULT_STAKE_SNAPSHOT_TOPIC))
+                    .filter(|topic| !topic.is_empty())
+                    .map(|topic| StakeSnapshotPublisher::new(context.clone(), topic)),
+                state_digest: StateDigestPublisher::from_config(
+                    context.clone(),
+                    config,
+                    "accounts-state",
+                ),
+                epoch_barrier: EpochBarrierAcker::from_config(
+                    context.clone(),
+                    config,
+                    "accounts-state",
+                ),
             },
             validation_outcomes_topic: get_string_flag(config, DEFAULT_VALIDATION_OUTCOMES_TOPIC),
             verifier,
@@ -1,10 +1,13 @@
 //! Acropolis AccountsState: monetary (reserves, treasury) calculations
 
 use acropolis_common::{
-    protocol_params::ShelleyParams, rational_number::RationalNumber, Lovelace, Pots,
+    protocol_params::ShelleyParams,
+    rational_number::{big_rational, floor_to_lovelace, RationalNumber},
+    Lovelace, Pots,
 };
-use anyhow::{anyhow, Result};
-use bigdecimal::{BigDecimal, One, ToPrimitive};
+use anyhow::{anyhow, bail, Result};
+use num_rational::BigRational;
+use num_traits::{One, Zero};
 use tracing::debug;
 
 /// Result of monetary calculation
@@ -37,23 +40,20 @@ pub fn calculate_monetary_change(
 
     // Total rewards available is monetary expansion plus fees from last epoch
     // TODO not sure why this is one epoch behind
-    let total_reward_pot = &monetary_expansion + BigDecimal::from(total_fees_last_epoch);
+    let total_reward_pot = &monetary_expansion + big_rational(total_fees_last_epoch);
 
     // Top-slice some for treasury
     let treasury_cut = &params.protocol_params.treasury_cut; // Tau
-    let treasury_increase = (&total_reward_pot * BigDecimal::from(treasury_cut.numer())
-        / BigDecimal::from(treasury_cut.denom()))
-    .with_scale(0);
+    let treasury_increase = (&total_reward_pot * treasury_cut.to_big_rational()).floor();
 
-    let treasury_increase_u64 =
-        treasury_increase.to_u64().ok_or(anyhow!("Can't calculate integral treasury cut"))?;
+    let treasury_increase_u64 = floor_to_lovelace(&treasury_increase)
+        .ok_or(anyhow!("Can't calculate integral treasury cut"))?;
 
     new_pots.treasury += treasury_increase_u64;
     new_pots.reserves -= treasury_increase_u64;
 
     // Remainder goes to stakeholders
-    let stake_rewards = (&total_reward_pot - &treasury_increase)
-        .to_u64()
+    let stake_rewards = floor_to_lovelace(&(&total_reward_pot - &treasury_increase))
         .ok_or(anyhow!("Can't calculate integral stake rewards"))?;
 
     debug!(total_rewards=%total_reward_pot, cut=%treasury_cut, increase=treasury_increase_u64,
@@ -66,22 +66,24 @@ pub fn calculate_monetary_change(
 }
 
 // Calculate 'eta' - ratio of blocks produced during the epoch vs expected
-fn calculate_eta(params: &ShelleyParams, total_non_obft_blocks: usize) -> Result<BigDecimal> {
+fn calculate_eta(params: &ShelleyParams, total_non_obft_blocks: usize) -> Result<BigRational> {
     let decentralisation = &params.protocol_params.decentralisation_param;
-    let active_slots_coeff = BigDecimal::from(params.active_slots_coeff.numer())
-        / BigDecimal::from(params.active_slots_coeff.denom());
-    let epoch_length = BigDecimal::from(params.epoch_length);
+    let active_slots_coeff = params.active_slots_coeff.to_big_rational();
+    let epoch_length = big_rational(params.epoch_length);
 
     let eta = if decentralisation >= &RationalNumber::new(8, 10) {
-        BigDecimal::one()
+        BigRational::one()
     } else {
-        let expected_blocks = epoch_length
+        // Whole blocks, as the Haskell ledger
+        let expected_blocks = (epoch_length
             * active_slots_coeff
-            * (BigDecimal::one()
-                - BigDecimal::from(decentralisation.numer())
-                    / BigDecimal::from(decentralisation.denom()));
+            * (BigRational::one() - decentralisation.to_big_rational()))
+        .floor();
+        if expected_blocks.is_zero() {
+            bail!("No blocks expected in an epoch");
+        }
 
-        (BigDecimal::from(total_non_obft_blocks as u64) / expected_blocks).min(BigDecimal::one())
+        (big_rational(total_non_obft_blocks as u64) / expected_blocks).min(BigRational::one())
     };
 
     Ok(eta)
@@ -91,13 +93,11 @@ fn calculate_eta(params: &ShelleyParams, total_non_obft_blocks: usize) -> Result
 fn calculate_monetary_expansion(
     params: &ShelleyParams,
     reserves: Lovelace,
-    eta: &BigDecimal,
-) -> BigDecimal {
+    eta: &BigRational,
+) -> BigRational {
     let monetary_expansion_factor = params.protocol_params.monetary_expansion.clone();
     let monetary_expansion =
-        (BigDecimal::from(reserves) * eta * BigDecimal::from(monetary_expansion_factor.numer())
-            / BigDecimal::from(monetary_expansion_factor.denom()))
-        .with_scale(0);
+        (big_rational(reserves) * eta * monetary_expansion_factor.to_big_rational()).floor();
 
     debug!(eta=%eta, rho=%monetary_expansion_factor, %monetary_expansion, "Monetary:");
 
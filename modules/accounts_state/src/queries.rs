@@ -71,6 +71,10 @@ pub fn handle_accounts_query(state: &State, message: &Message) -> Arc<Message> {
             AccountsStateQueryResponse::OptimalPoolSizing(state.get_optimal_pool_sizing())
         }
 
+        AccountsStateQuery::GetNetworkStats => {
+            AccountsStateQueryResponse::NetworkStats(state.get_network_stats())
+        }
+
         AccountsStateQuery::GetAccountsUtxoValuesMap { stake_addresses } => {
             match state.get_accounts_utxo_values_map(stake_addresses) {
                 Some(map) => AccountsStateQueryResponse::AccountsUtxoValuesMap(map),
@@ -2,12 +2,16 @@
 
 use acropolis_common::epoch_snapshot::{EpochSnapshot, SnapshotSPO};
 use acropolis_common::{
-    protocol_params::ShelleyParams, rational_number::RationalNumber, Era, Lovelace, PoolId,
-    RewardType, SPORewards, StakeAddress,
+    metrics,
+    protocol_params::ShelleyParams,
+    rational_number::{big_rational, floor_to_lovelace, RationalNumber},
+    Era, Lovelace, PoolId, RewardType, SPORewards, StakeAddress,
 };
 use acropolis_common::{RegistrationChange, RegistrationChangeKind};
 use anyhow::{bail, Result};
-use bigdecimal::{BigDecimal, One, ToPrimitive, Zero};
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+use rayon::prelude::*;
 use std::cmp::min;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
@@ -68,6 +72,8 @@ pub fn calculate_rewards(
     registrations: &HashSet<StakeAddress>,
     deregistrations: &HashSet<StakeAddress>,
 ) -> Result<RewardsResult> {
+    let _timer = metrics::epoch_boundary_latency("accounts-state", "rewards").start_timer();
+
     // Determine which reward rules apply based on the era of the rewarded epoch
     let is_shelley = era == Era::Shelley;
     let is_pre_babbage = era < Era::Babbage;
@@ -83,31 +89,30 @@ pub fn calculate_rewards(
     }
 
     // Take stake rewards from epoch we just left
-    let stake_rewards = BigDecimal::from(stake_rewards);
+    let stake_rewards = big_rational(stake_rewards);
 
     // Calculate total supply (total in circulation + treasury) or
     // equivalently (max-supply-reserves) - this is the denominator
     // for sigma, z0, s
-    let total_supply = BigDecimal::from(params.max_lovelace_supply - performance.pots.reserves);
+    let total_supply = big_rational(params.max_lovelace_supply - performance.pots.reserves);
 
     // Get total active stake across all SPOs
     let total_active_stake =
-        BigDecimal::from(staking.spos.values().map(|s| s.total_stake).sum::<Lovelace>());
+        big_rational(staking.spos.values().map(|s| s.total_stake).sum::<Lovelace>());
 
     debug!(epoch, go=staking.epoch, mark=performance.epoch,
           %total_supply, %total_active_stake, %stake_rewards, total_blocks,
           "Calculating rewards:");
 
     // Relative pool saturation size (z0)
-    let k = BigDecimal::from(&params.protocol_params.stake_pool_target_num);
-    if k.is_zero() {
+    let k = params.protocol_params.stake_pool_target_num;
+    if k == 0 {
         bail!("k is zero!");
     }
-    let relative_pool_saturation_size = k.inverse();
+    let relative_pool_saturation_size = big_rational(k).recip();
 
     // Pledge influence factor (a0)
-    let a0 = &params.protocol_params.pool_pledge_influence;
-    let pledge_influence_factor = BigDecimal::from(a0.numer()) / BigDecimal::from(a0.denom());
+    let pledge_influence_factor = params.protocol_params.pool_pledge_influence.to_big_rational();
 
     // Calculate for every registered SPO (even those who didn't participate in this epoch)
     // from epoch (i-2) "Go"
@@ -115,107 +120,119 @@ pub fn calculate_rewards(
     let mut total_paid_to_delegators: Lovelace = 0;
     let mut num_pools_paid: usize = 0;
     let mut num_delegators_paid: usize = 0;
-    for (operator_id, staking_spo) in staking.spos.iter() {
-        // Actual blocks produced for epoch i, no rewards if none
-        let performance_spo = performance.spos.get(operator_id);
-        let blocks_produced = performance_spo.map(|s| s.blocks_produced).unwrap_or(0);
-        if blocks_produced == 0 {
-            continue;
-        }
-        let performance_spo = performance_spo.unwrap();
 
-        // SPO's reward account from staking time must be registered now
-        // We get the registration status *as it is in performance* for the reward account
-        // *as it was during staking*
-        let mut pay_to_pool_reward_account =
-            performance_spo.two_previous_reward_account_is_registered;
+    // Pools are independent, so compute each one's rewards in parallel - in operator
+    // order, so the aggregation below and the results are the same on every run
+    let mut pools: Vec<_> = staking.spos.iter().collect();
+    pools.sort_unstable_by_key(|(operator_id, _)| **operator_id);
+    let pool_rewards: Vec<(PoolId, Vec<RewardDetail>)> = pools
+        .into_par_iter()
+        .filter_map(|(operator_id, staking_spo)| {
+            // Actual blocks produced for epoch i, no rewards if none
+            let performance_spo = performance.spos.get(operator_id);
+            let blocks_produced = performance_spo.map(|s| s.blocks_produced).unwrap_or(0);
+            if blocks_produced == 0 {
+                return None;
+            }
+            let performance_spo = performance_spo.unwrap();
 
-        debug!(
-            "SPO {} reward account registered two epochs ago: {}",
-            operator_id, pay_to_pool_reward_account
-        );
+            // SPO's reward account from staking time must be registered now
+            // We get the registration status *as it is in performance* for the reward account
+            // *as it was during staking*
+            let mut pay_to_pool_reward_account =
+                performance_spo.two_previous_reward_account_is_registered;
 
-        // Also, check if the reward account was registered during the current epoch
-        // (before the stability window). In Cardano, addrsRew is captured at the stability
-        // window, so registrations that happen before that point should allow leader rewards.
-        // This applies to all pre-Babbage eras.
-        if is_pre_babbage && !pay_to_pool_reward_account {
             debug!(
-                "Checking old reward account {} for late registration",
-                staking_spo.reward_account
+                "SPO {} reward account registered two epochs ago: {}",
+                operator_id, pay_to_pool_reward_account
             );
 
-            // Note we use the staking reward account - it could have changed
-            pay_to_pool_reward_account = registrations.contains(&staking_spo.reward_account);
+            // Also, check if the reward account was registered during the current epoch
+            // (before the stability window). In Cardano, addrsRew is captured at the stability
+            // window, so registrations that happen before that point should allow leader rewards.
+            // This applies to all pre-Babbage eras.
+            if is_pre_babbage && !pay_to_pool_reward_account {
+                debug!(
+                    "Checking old reward account {} for late registration",
+                    staking_spo.reward_account
+                );
+
+                // Note we use the staking reward account - it could have changed
+                pay_to_pool_reward_account = registrations.contains(&staking_spo.reward_account);
 
-            if pay_to_pool_reward_account {
+                if pay_to_pool_reward_account {
+                    debug!(
+                        "SPO {}'s reward account {} registered before stability window - will pay leader reward",
+                        operator_id, staking_spo.reward_account
+                    );
+                }
+            }
+
+            // Check if the reward account deregistered between the snapshot and stability window.
+            // In Cardano, addrsRew is captured at the stability window, so accounts that deregister
+            // after the snapshot but before the stability window should NOT receive leader rewards.
+            if pay_to_pool_reward_account && deregistrations.contains(&staking_spo.reward_account) {
                 debug!(
-                    "SPO {}'s reward account {} registered before stability window - will pay leader reward",
+                    "SPO {}'s reward account {} deregistered before stability window - not paying",
                     operator_id, staking_spo.reward_account
                 );
+                pay_to_pool_reward_account = false;
             }
-        }
-
-        // Check if the reward account deregistered between the snapshot and stability window.
-        // In Cardano, addrsRew is captured at the stability window, so accounts that deregister
-        // after the snapshot but before the stability window should NOT receive leader rewards.
-        if pay_to_pool_reward_account && deregistrations.contains(&staking_spo.reward_account) {
-            debug!(
-                "SPO {}'s reward account {} deregistered before stability window - not paying",
-                operator_id, staking_spo.reward_account
-            );
-            pay_to_pool_reward_account = false;
-        }
 
-        // Shelley-only bug: if multiple SPOs shared a reward account, only one got paid.
-        // Fixed in Allegra (hardforkAllegraAggregatedRewards allows aggregation of all rewards).
-        // When colliding, the SPO with the lowest operator hash wins.
-        if is_shelley {
-            if pay_to_pool_reward_account {
-                // Check all SPOs to see if they match this reward account
-                for (other_id, other_spo) in staking.spos.iter() {
-                    if other_spo.reward_account == staking_spo.reward_account
-                        && other_id.cmp(operator_id) == Ordering::Less
-                    // Lower ID (hash) wins
-                    {
-                        // It must have been paid a reward - we assume that checking it produced
-                        // any blocks is enough here - if not we'll have to do this as a post-process
-                        if performance.spos.get(other_id).map(|s| s.blocks_produced).unwrap_or(0)
-                            > 0
+            // Shelley-only bug: if multiple SPOs shared a reward account, only one got paid.
+            // Fixed in Allegra (hardforkAllegraAggregatedRewards allows aggregation of all rewards).
+            // When colliding, the SPO with the lowest operator hash wins.
+            if is_shelley {
+                if pay_to_pool_reward_account {
+                    // Check all SPOs to see if they match this reward account
+                    for (other_id, other_spo) in staking.spos.iter() {
+                        if other_spo.reward_account == staking_spo.reward_account
+                            && other_id.cmp(operator_id) == Ordering::Less
+                        // Lower ID (hash) wins
                         {
-                            pay_to_pool_reward_account = false;
-                            debug!("Shelley shared reward account bug: Dropping reward to {} in favour of {} on shared account {}",
-                                  operator_id,
-                                  other_id,
-                                  staking_spo.reward_account);
-                            break;
+                            // It must have been paid a reward - we assume that checking it produced
+                            // any blocks is enough here - if not we'll have to do this as a post-process
+                            if performance.spos.get(other_id).map(|s| s.blocks_produced).unwrap_or(0)
+                                > 0
+                            {
+                                pay_to_pool_reward_account = false;
+                                debug!("Shelley shared reward account bug: Dropping reward to {} in favour of {} on shared account {}",
+                                      operator_id,
+                                      other_id,
+                                      staking_spo.reward_account);
+                                break;
+                            }
                         }
                     }
+                } else {
+                    debug!("Reward account for SPO {} isn't registered", operator_id);
                 }
-            } else {
-                debug!("Reward account for SPO {} isn't registered", operator_id);
             }
-        }
 
-        // Calculate rewards for this SPO
-        let rewards = calculate_spo_rewards(
-            operator_id,
-            staking_spo,
-            blocks_produced as u64,
-            total_blocks,
-            &stake_rewards,
-            &total_supply,
-            &total_active_stake,
-            &relative_pool_saturation_size,
-            &pledge_influence_factor,
-            params,
-            staking.clone(),
-            pay_to_pool_reward_account,
-            deregistrations,
-            is_pre_babbage,
-            is_shelley,
-        );
+            // Calculate rewards for this SPO
+            let rewards = calculate_spo_rewards(
+                operator_id,
+                staking_spo,
+                blocks_produced as u64,
+                total_blocks,
+                &stake_rewards,
+                &total_supply,
+                &total_active_stake,
+                &relative_pool_saturation_size,
+                &pledge_influence_factor,
+                params,
+                staking.clone(),
+                pay_to_pool_reward_account,
+                deregistrations,
+                is_pre_babbage,
+                is_shelley,
+            );
+
+            Some((*operator_id, rewards))
+        })
+        .collect();
 
+    for (operator_id, rewards) in pool_rewards {
         if !rewards.is_empty() {
             let mut spo_rewards = SPORewards {
                 total_rewards: 0,
@@ -245,8 +262,8 @@ pub fn calculate_rewards(
                 }
             }
 
-            result.rewards.insert(*operator_id, rewards);
-            result.spo_rewards.push((*operator_id, spo_rewards));
+            result.rewards.insert(operator_id, rewards);
+            result.spo_rewards.push((operator_id, spo_rewards));
         }
     }
 
@@ -270,11 +287,11 @@ fn calculate_spo_rewards(
     spo: &SnapshotSPO,
     blocks_produced: u64,
     total_blocks: usize,
-    stake_rewards: &BigDecimal,
-    total_supply: &BigDecimal,
-    total_active_stake: &BigDecimal,
-    relative_pool_saturation_size: &BigDecimal,
-    pledge_influence_factor: &BigDecimal,
+    stake_rewards: &BigRational,
+    total_supply: &BigRational,
+    total_active_stake: &BigRational,
+    relative_pool_saturation_size: &BigRational,
+    pledge_influence_factor: &BigRational,
     params: &ShelleyParams,
     staking: Arc<EpochSnapshot>,
     pay_to_pool_reward_account: bool,
@@ -283,7 +300,7 @@ fn calculate_spo_rewards(
     is_shelley: bool,
 ) -> Vec<RewardDetail> {
     // Active stake (sigma)
-    let pool_stake = BigDecimal::from(spo.total_stake);
+    let pool_stake = big_rational(spo.total_stake);
     if pool_stake.is_zero() {
         warn!("SPO {} has no stake - skipping", operator_id);
 
@@ -304,37 +321,30 @@ fn calculate_spo_rewards(
         return vec![];
     }
 
-    let pool_pledge = BigDecimal::from(&spo.pledge);
+    let pool_pledge = big_rational(spo.pledge);
 
-    // Relative stake as fraction of total supply (sigma), and capped with 1/k (sigma')
+    // Relative stake as fraction of total supply (sigma), and stake pledged by operator (s)
     let relative_pool_stake = &pool_stake / total_supply;
-    let capped_relative_pool_stake = min(&relative_pool_stake, relative_pool_saturation_size);
-
-    // Stake pledged by operator (s) and capped with 1/k (s')
     let relative_pool_pledge = &pool_pledge / total_supply;
-    let capped_relative_pool_pledge = min(&relative_pool_pledge, relative_pool_saturation_size);
 
     // Get the optimum reward for this pool
-    let optimum_rewards = ((stake_rewards / (BigDecimal::one() + pledge_influence_factor))
-        * (capped_relative_pool_stake
-            + (capped_relative_pool_pledge
-                * pledge_influence_factor
-                * (capped_relative_pool_stake
-                    - (capped_relative_pool_pledge
-                        * ((relative_pool_saturation_size - capped_relative_pool_stake)
-                            / relative_pool_saturation_size))))
-                / relative_pool_saturation_size))
-        .with_scale(0);
+    let optimum_rewards = max_pool(
+        stake_rewards,
+        pledge_influence_factor,
+        relative_pool_saturation_size,
+        &relative_pool_stake,
+        &relative_pool_pledge,
+    );
 
     // If decentralisation_param >= 0.8 => performance = 1
     // Shelley Delegation Spec 3.8.3
     let decentralisation = &params.protocol_params.decentralisation_param;
     let pool_performance = if decentralisation >= &RationalNumber::new(8, 10) {
-        BigDecimal::one()
+        BigRational::one()
     } else {
         let relative_active_stake = &pool_stake / total_active_stake;
-        let relative_blocks = BigDecimal::from(blocks_produced)  // Beta
-            / BigDecimal::from(total_blocks as u64);
+        let relative_blocks = big_rational(blocks_produced)  // Beta
+            / big_rational(total_blocks as u64);
 
         debug!(blocks_produced, %relative_blocks, %pool_stake, %relative_active_stake,
                "Pool performance calc:");
@@ -342,58 +352,59 @@ fn calculate_spo_rewards(
     };
 
     // Get actual pool rewards
-    let pool_rewards = (&optimum_rewards * &pool_performance).with_scale(0);
+    let pool_rewards = (&optimum_rewards * &pool_performance).floor();
 
     debug!(%pool_stake, %relative_pool_stake, %pool_performance,
            %optimum_rewards, %pool_rewards, pool_owner_stake, %pool_pledge,
            "Pool {}", operator_id);
 
     // Subtract fixed costs
-    let fixed_cost = BigDecimal::from(spo.fixed_cost);
+    let fixed_cost = big_rational(spo.fixed_cost);
     let mut rewards = Vec::<RewardDetail>::new();
     let spo_benefit = if pool_rewards <= fixed_cost {
         debug!("Rewards < cost - all paid to SPO");
 
         // No margin or pledge reward if under cost - all goes to SPO
-        pool_rewards.to_u64().unwrap_or(0)
+        floor_to_lovelace(&pool_rewards).unwrap_or(0)
     } else {
         // Enough left over for some margin split
-        let margin =
-            BigDecimal::from(spo.margin.numerator) / BigDecimal::from(spo.margin.denominator);
-
-        let relative_owner_stake = pool_owner_stake / total_supply;
-        let margin_cost = ((&pool_rewards - &fixed_cost)
-            * (&margin
-                + (BigDecimal::one() - &margin) * (relative_owner_stake / relative_pool_stake)))
-            .with_scale(0);
-        let costs = &fixed_cost + &margin_cost;
+        let margin = BigRational::new(spo.margin.numerator.into(), spo.margin.denominator.into());
+
+        let relative_owner_stake = big_rational(pool_owner_stake) / total_supply;
+        let costs = leader_reward(
+            &pool_rewards,
+            &fixed_cost,
+            &margin,
+            &relative_owner_stake,
+            &relative_pool_stake,
+        );
 
         // Pay the delegators - split the remainder proportional to the delegated stake,
         // * as it was 2 epochs ago *
-
-        // You'd think this was just (pool_rewards - costs) here, but the Haskell code recalculates
-        // the margin without the relative_owner_stake term !?
-        // Note keeping the fractional part, which is non-obvious
-        let to_delegators = (&pool_rewards - &fixed_cost) * (BigDecimal::one() - &margin);
         let mut total_paid: u64 = 0;
         let mut delegators_paid: usize = 0;
-        let owner_rewards: u64 = costs.to_u64().unwrap_or(0);
-        if !to_delegators.is_zero() {
-            let total_stake = BigDecimal::from(spo.total_stake);
+        let owner_rewards: u64 = floor_to_lovelace(&costs).unwrap_or(0);
+        if margin < BigRational::one() {
             for (delegator_stake_address, stake) in &spo.delegators {
-                let proportion = BigDecimal::from(stake) / &total_stake;
-
-                // and hence how much of the total reward they get
-                let reward = &to_delegators * &proportion;
-                let to_pay = reward.with_scale(0).to_u64().unwrap_or(0);
+                let relative_stake = big_rational(*stake) / total_supply;
+                let reward = member_reward(
+                    &pool_rewards,
+                    &fixed_cost,
+                    &margin,
+                    &relative_stake,
+                    &relative_pool_stake,
+                );
+                let to_pay = floor_to_lovelace(&reward).unwrap_or(0);
 
                 // Skip if it's rounded to zero
                 if to_pay == 0 {
                     continue;
                 }
 
-                debug!("Reward stake {stake} -> proportion {proportion} of SPO rewards {to_delegators} -> {to_pay} to hash {}",
-                       delegator_stake_address);
+                debug!(
+                    "Reward stake {stake} of pool stake {pool_stake} -> {to_pay} to hash {}",
+                    delegator_stake_address
+                );
 
                 let is_pool_owner = spo.pool_owners.contains(delegator_stake_address);
                 let is_reward_account = &spo.reward_account == delegator_stake_address;
@@ -449,8 +460,8 @@ fn calculate_spo_rewards(
             }
         }
 
-        debug!(%fixed_cost, %margin_cost, leader_reward=%owner_rewards, %to_delegators, total_paid,
-               delegators_paid, "Reward split:");
+        debug!(%fixed_cost, leader_reward=%owner_rewards, total_paid, delegators_paid,
+               "Reward split:");
 
         owner_rewards
     };
@@ -487,6 +498,55 @@ fn calculate_spo_rewards(
     rewards
 }
 
+/// Optimal rewards for a pool with relative stake `sigma` and pledge `pledge`, as
+/// `maxPool'` in the Haskell ledger
+fn max_pool(
+    stake_rewards: &BigRational,
+    a0: &BigRational,
+    z0: &BigRational,
+    sigma: &BigRational,
+    pledge: &BigRational,
+) -> BigRational {
+    let sigma = min(sigma, z0);
+    let pledge = min(pledge, z0);
+    let factor1 = stake_rewards / (BigRational::one() + a0);
+    let factor4 = (z0 - sigma) / z0;
+    let factor3 = (sigma - pledge * factor4) / z0;
+    let factor2 = sigma + pledge * a0 * factor3;
+    (factor1 * factor2).floor()
+}
+
+/// Pool operator's rewards - the cost plus the margin, and the owners' share of what is
+/// left, as `leaderRew`. `owner_share` and `pool_share` are relative to total supply.
+fn leader_reward(
+    pool_rewards: &BigRational,
+    cost: &BigRational,
+    margin: &BigRational,
+    owner_share: &BigRational,
+    pool_share: &BigRational,
+) -> BigRational {
+    if pool_rewards <= cost {
+        return pool_rewards.clone();
+    }
+    cost + ((pool_rewards - cost)
+        * (margin + (BigRational::one() - margin) * owner_share / pool_share))
+        .floor()
+}
+
+/// Delegator's share of what is left after the cost and margin, as `memberRew`
+fn member_reward(
+    pool_rewards: &BigRational,
+    cost: &BigRational,
+    margin: &BigRational,
+    member_share: &BigRational,
+    pool_share: &BigRational,
+) -> BigRational {
+    if pool_rewards <= cost {
+        return BigRational::zero();
+    }
+    ((pool_rewards - cost) * (BigRational::one() - margin) * member_share / pool_share).floor()
+}
+
 pub fn wait_for_rewards_start_signal(
     start_rewards_rx: std::sync::mpsc::Receiver<Vec<RegistrationChange>>,
 ) -> Result<Vec<RegistrationChange>> {
@@ -537,3 +597,90 @@ pub fn apply_registration_changes_filtered(
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Expected values are the Haskell ledger's formulas evaluated with exact rationals
+
+    fn shelley_total_supply() -> BigRational {
+        big_rational(45_000_000_000_000_000u64 - 13_262_280_841_681_299)
+    }
+
+    fn share(lovelace: u64) -> BigRational {
+        big_rational(lovelace) / shelley_total_supply()
+    }
+
+    #[test]
+    fn max_pool_matches_haskell() {
+        let stake_rewards = big_rational(31_854_784_667_376u64);
+        let a0 = RationalNumber::new(3, 10).to_big_rational();
+        let z0 = big_rational(150).recip();
+
+        let pool = max_pool(
+            &stake_rewards,
+            &a0,
+            &z0,
+            &share(64_000_000_000_000),
+            &share(1_000_000_000_000),
+        );
+        assert_eq!(floor_to_lovelace(&pool), Some(49_481_655_160));
+
+        // Stake and pledge both capped at z0
+        let saturated = max_pool(
+            &stake_rewards,
+            &a0,
+            &z0,
+            &share(300_000_000_000_000),
+            &share(400_000_000_000_000),
+        );
+        assert_eq!(floor_to_lovelace(&saturated), Some(212_365_231_115));
+    }
+
+    #[test]
+    fn leader_and_member_rewards_match_haskell() {
+        let pool_rewards = big_rational(49_481_655_160u64);
+        let cost = big_rational(340_000_000);
+        let margin = RationalNumber::new(1, 40).to_big_rational();
+        let pool_share = share(64_000_000_000_000);
+
+        let leader = leader_reward(
+            &pool_rewards,
+            &cost,
+            &margin,
+            &share(1_000_000_000_000),
+            &pool_share,
+        );
+        assert_eq!(floor_to_lovelace(&leader), Some(2_317_183_781));
+
+        let member = member_reward(
+            &pool_rewards,
+            &cost,
+            &margin,
+            &share(123_456_789_012),
+            &pool_share,
+        );
+        assert_eq!(floor_to_lovelace(&member), Some(92_424_987));
+    }
+
+    #[test]
+    fn member_rewards_are_exact_before_flooring() {
+        // 3 lovelace split by a third of the stake is exactly 1 - rounding the third to
+        // any fixed precision first would floor to 0
+        let pool_rewards = big_rational(340_000_003);
+        let cost = big_rational(340_000_000);
+        let member = member_reward(
+            &pool_rewards,
+            &cost,
+            &BigRational::zero(),
+            &share(1),
+            &share(3),
+        );
+        assert_eq!(floor_to_lovelace(&member), Some(1));
+
+        // Nothing for members if the rewards don't cover the cost
+        let member = member_reward(&cost, &cost, &BigRational::zero(), &share(1), &share(3));
+        assert!(member.is_zero());
+    }
+}
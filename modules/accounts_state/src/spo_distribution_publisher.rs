@@ -1,17 +1,26 @@
 use acropolis_common::caryatid::RollbackAwarePublisher;
 use acropolis_common::messages::{CardanoMessage, Message, SPOStakeDistributionMessage};
+use acropolis_common::spdd_chunks::spdd_chunks;
 use acropolis_common::{BlockInfo, DelegatedStake, PoolId};
 use caryatid_sdk::Context;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
 /// Message publisher for Stake Pool Delegation Distribution (SPDD)
-pub struct SPODistributionPublisher(RollbackAwarePublisher<Message>);
+pub struct SPODistributionPublisher {
+    publisher: RollbackAwarePublisher<Message>,
+
+    /// Maximum pools per message, or 0 to publish the SPDD as a single message
+    chunk_size: usize,
+}
 
 impl SPODistributionPublisher {
-    /// Construct with context and topic to publish on
-    pub fn new(context: Arc<Context<Message>>, topic: String) -> Self {
-        Self(RollbackAwarePublisher::new(context, topic))
+    /// Construct with context, topic to publish on and chunk size
+    pub fn new(context: Arc<Context<Message>>, topic: String, chunk_size: usize) -> Self {
+        Self {
+            publisher: RollbackAwarePublisher::new(context, topic),
+            chunk_size,
+        }
     }
 
     /// Publish the SPDD
@@ -20,19 +29,35 @@ impl SPODistributionPublisher {
         block: &BlockInfo,
         spos: BTreeMap<PoolId, DelegatedStake>,
     ) -> anyhow::Result<()> {
-        self.0
-            .publish(Arc::new(Message::Cardano((
-                block.clone(),
-                CardanoMessage::SPOStakeDistribution(SPOStakeDistributionMessage {
-                    epoch: block.epoch - 1, // End of the previous epoch
-                    spos: spos.into_iter().collect(),
-                }),
-            ))))
-            .await
+        let epoch = block.epoch - 1; // End of the previous epoch
+
+        if self.chunk_size == 0 {
+            return self
+                .publisher
+                .publish(Arc::new(Message::Cardano((
+                    block.clone(),
+                    CardanoMessage::SPOStakeDistribution(SPOStakeDistributionMessage {
+                        epoch,
+                        spos: spos.into_iter().collect(),
+                    }),
+                ))))
+                .await;
+        }
+
+        // Each chunk is only built as it is sent
+        for chunk in spdd_chunks(epoch, spos, self.chunk_size) {
+            self.publisher
+                .publish(Arc::new(Message::Cardano((
+                    block.clone(),
+                    CardanoMessage::SPOStakeDistributionChunk(chunk),
+                ))))
+                .await?;
+        }
+        Ok(())
     }
 
     /// Publish a pre-constructed message on the SPDD topic.
     pub async fn publish_message(&mut self, message: Arc<Message>) -> anyhow::Result<()> {
-        self.0.publish(message).await
+        self.publisher.publish(message).await
     }
 }
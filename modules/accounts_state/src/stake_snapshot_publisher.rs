@@ -0,0 +1,35 @@
+use acropolis_common::caryatid::RollbackAwarePublisher;
+use acropolis_common::epoch_snapshot::EpochSnapshot;
+use acropolis_common::messages::{CardanoMessage, Message, StakeSnapshotMessage};
+use acropolis_common::BlockInfo;
+use caryatid_sdk::Context;
+use std::sync::Arc;
+
+/// Message publisher for per-account stake snapshots
+pub struct StakeSnapshotPublisher(RollbackAwarePublisher<Message>);
+
+impl StakeSnapshotPublisher {
+    /// Construct with context and topic to publish on
+    pub fn new(context: Arc<Context<Message>>, topic: String) -> Self {
+        Self(RollbackAwarePublisher::new(context, topic))
+    }
+
+    /// Publish the snapshot taken as the previous epoch ended
+    pub async fn publish_snapshot(
+        &mut self,
+        block: &BlockInfo,
+        snapshot: Arc<EpochSnapshot>,
+    ) -> anyhow::Result<()> {
+        self.0
+            .publish(Arc::new(Message::Cardano((
+                block.clone(),
+                CardanoMessage::StakeSnapshot(StakeSnapshotMessage { snapshot }),
+            ))))
+            .await
+    }
+
+    /// Publish a pre-constructed message on the stake snapshot topic.
+    pub async fn publish_message(&mut self, message: Arc<Message>) -> anyhow::Result<()> {
+        self.0.publish(message).await
+    }
+}
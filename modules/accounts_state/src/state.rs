@@ -17,9 +17,10 @@ use acropolis_common::{
         SPOStateMessage, StakeAddressDeltasMessage, StateQuery, StateQueryResponse,
         TxCertificatesMessage, WithdrawalsMessage,
     },
+    metrics,
     protocol_params::{ProtocolParams, ShelleyParams},
     queries::{
-        accounts::OptimalPoolSizing,
+        accounts::{NetworkStats, OptimalPoolSizing},
         get_query_topic,
         stake_deltas::{
             StakeDeltaQuery, StakeDeltaQueryResponse, DEFAULT_STAKE_DELTAS_QUERY_TOPIC,
@@ -27,6 +28,7 @@ use acropolis_common::{
         utxos::{UTxOStateQuery, UTxOStateQueryResponse, DEFAULT_UTXOS_QUERY_TOPIC},
     },
     stake_addresses::{StakeAddressMap, StakeAddressState},
+    state_digest::{add_sections_to_digest, SetDigest, StateDigest},
     BlockInfo, DRepChoice, DRepCredential, DelegatedStake, DelegatedStakeDefaultVote, Era,
     GovernanceOutcomeVariant, InstantaneousRewardSource, InstantaneousRewardTarget, Lovelace,
     MoveInstantaneousReward, PoolId, PoolLiveStakeInfo, PoolRegistration, RegistrationChange,
@@ -150,6 +152,22 @@ pub struct State {
 }
 
 impl State {
+    /// Estimated memory used by the state, for the state history memory budget. The
+    /// stake addresses and snapshots are shared between entries, so are not counted.
+    pub fn estimated_size(&self) -> usize {
+        self.spos.len() * size_of::<(PoolId, PoolRegistration)>()
+            + self.retiring_spos.len() * size_of::<PoolId>()
+            + self.dreps.len() * size_of::<(DRepCredential, Lovelace)>()
+            + self.pool_refunds.len() * size_of::<(PoolId, StakeAddress)>()
+            + self.proposal_deposits.len() * size_of::<(StakeAddress, Lovelace)>()
+            + self.proposal_refunds.len() * size_of::<(StakeAddress, Lovelace)>()
+            + self.current_epoch_registration_changes.len() * size_of::<RegistrationChange>()
+            + self.drep_delegators.values().map(OrdSet::len).sum::<usize>()
+                * size_of::<StakeAddress>()
+            + (self.pending_mir_reserves.len() + self.pending_mir_treasury.len())
+                * size_of::<(StakeAddress, i64)>()
+    }
+
     /// Bootstrap state from snapshot data (consumes the message to avoid cloning)
     pub fn bootstrap(&mut self, bootstrap_msg: AccountsBootstrapMessage) -> Result<()> {
         let num_accounts = bootstrap_msg.accounts.len();
@@ -355,6 +373,34 @@ impl State {
         Some(OptimalPoolSizing { total_supply, nopt })
     }
 
+    /// Get the network-wide supply and stake totals
+    /// Return None if it is before Shelley Era
+    pub fn get_network_stats(&self) -> Option<NetworkStats> {
+        let max_supply = self.protocol_parameters.get_shelley()?.max_lovelace_supply;
+        let (live_stake, delegators, rewards) =
+            self.stake_addresses.lock().unwrap().get_delegation_totals();
+
+        let total_supply = max_supply.saturating_sub(self.pots.reserves);
+        let circulating_supply = total_supply
+            .saturating_sub(self.pots.treasury)
+            .saturating_sub(self.pots.deposits)
+            .saturating_sub(rewards);
+
+        Some(NetworkStats {
+            max_supply,
+            total_supply,
+            circulating_supply,
+            reserves: self.pots.reserves,
+            treasury: self.pots.treasury,
+            deposits: self.pots.deposits,
+            rewards,
+            live_stake,
+            active_stake: self.get_latest_snapshot_account_balances(),
+            pools: self.spos.len() as u64,
+            delegators,
+        })
+    }
+
     /// Get Pool Live Stake Info
     pub fn get_pool_live_stake_info(&self, pool_operator: &PoolId) -> PoolLiveStakeInfo {
         self.stake_addresses.lock().unwrap().get_pool_live_stake_info(pool_operator)
@@ -399,6 +445,11 @@ impl State {
         stake_addresses.get_accounts_balances_map(stake_keys)
     }
 
+    /// Snapshot taken as the last epoch ended
+    pub fn get_latest_snapshot(&self) -> Arc<EpochSnapshot> {
+        self.epoch_snapshots.mark.clone()
+    }
+
     /// Sum total_active_stake for delegators of all spos in the latest snapshot
     pub fn get_latest_snapshot_account_balances(&self) -> u64 {
         let mut total_active_stake: u64 = 0;
@@ -1284,6 +1335,7 @@ impl State {
         rewards_runtime: &mut RewardRuntime,
         undo: &mut BlockStakeAddressUndoRecorder,
     ) -> Result<Vec<StakeRewardDelta>> {
+        let _timer = metrics::epoch_boundary_latency("accounts-state", "enter-epoch").start_timer();
         let mut reward_deltas = Vec::<StakeRewardDelta>::new();
 
         // Map block counts, filtering out SPOs we don't know (OBFT in early Shelley)
@@ -1790,14 +1842,17 @@ impl State {
         ctx: &mut ValidationContext,
         undo: &mut BlockStakeAddressUndoRecorder,
     ) {
-        // Handle deltas
-        for delta in deltas_msg.deltas.iter() {
-            ctx.handle(
-                "process_stake_delta",
-                self.mutate_stake_address(undo, &delta.stake_address, |stake_addresses| {
-                    stake_addresses.process_stake_delta(delta)
-                }),
+        // Apply the deltas across the stake address shards in parallel, then record the
+        // outcomes in delta order so undo and validation stay deterministic
+        let outcomes =
+            self.stake_addresses.lock().unwrap().process_stake_deltas(&deltas_msg.deltas);
+        for (delta, outcome) in deltas_msg.deltas.iter().zip(outcomes) {
+            undo.record_change(
+                &delta.stake_address,
+                outcome.previous.as_ref(),
+                outcome.current.as_ref(),
             );
+            ctx.handle("process_stake_delta", outcome.result);
         }
     }
 
@@ -1959,6 +2014,64 @@ impl State {
     }
 }
 
+impl StateDigest for State {
+    /// Accounts, pools, DReps, pots and pending transfers - snapshots are
+    /// published separately as the SPDD
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        add_sections_to_digest(self, digest)
+    }
+
+    /// Accounts hold each stake address's rewards as well as its stake and delegations
+    fn section_digests(&self) -> Result<BTreeMap<&'static str, SetDigest>> {
+        let mut accounts = SetDigest::new();
+        for (address, account) in self.stake_addresses.lock().unwrap().iter() {
+            accounts.insert(&("account", address, account))?;
+        }
+        let mut pools = SetDigest::new();
+        for (id, registration) in &self.spos {
+            pools.insert(&("pool", id, registration))?;
+        }
+        let mut retirements = SetDigest::new();
+        for id in &self.retiring_spos {
+            retirements.insert(&("retiring-pool", id))?;
+        }
+        let mut dreps = SetDigest::new();
+        for (credential, deposit) in &self.dreps {
+            dreps.insert(&("drep", credential, deposit))?;
+        }
+        let mut refunds = SetDigest::new();
+        for (id, reward_account) in &self.pool_refunds {
+            refunds.insert(&("pool-refund", id, reward_account))?;
+        }
+        for (address, amount) in &self.proposal_refunds {
+            refunds.insert(&("proposal-refund", address, amount))?;
+        }
+        let mut deposits = SetDigest::new();
+        for (address, amount) in &self.proposal_deposits {
+            deposits.insert(&("proposal-deposit", address, amount))?;
+        }
+        let mut mirs = SetDigest::new();
+        for (address, amount) in &self.pending_mir_reserves {
+            mirs.insert(&("mir-reserves", address, amount))?;
+        }
+        for (address, amount) in &self.pending_mir_treasury {
+            mirs.insert(&("mir-treasury", address, amount))?;
+        }
+        let mut pots = SetDigest::new();
+        pots.insert(&("pots", &self.pots))?;
+        Ok(BTreeMap::from([
+            ("accounts", accounts),
+            ("pools", pools),
+            ("retirements", retirements),
+            ("dreps", dreps),
+            ("refunds", refunds),
+            ("proposal-deposits", deposits),
+            ("mirs", mirs),
+            ("pots", pots),
+        ]))
+    }
+}
+
 /// This structure concentrates knowledge about parameter handling.
 impl ProtocolParametersState {
     pub fn is_chang(&self) -> Result<bool> {
@@ -13,7 +13,9 @@ use acropolis_common::{
     configuration::{get_bool_flag, get_string_flag, StartupMode},
     declare_cardano_reader,
     messages::{AddressDeltasMessage, ProtocolParamsMessage, StateTransitionMessage},
+    metrics,
     queries::errors::QueryError,
+    readiness, shutdown,
 };
 use acropolis_common::{
     messages::{CardanoMessage, Message, StateQuery, StateQueryResponse},
@@ -162,6 +164,8 @@ impl AddressState {
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "address-state").await?;
+
         // Get configuration flags and query topic
         let storage_config = AddressStorageConfig {
             db_path: get_string_flag(&config, DEFAULT_ADDRESS_DB_PATH),
@@ -180,9 +184,22 @@ impl AddressState {
         let state_mutex = Arc::new(Mutex::new(state));
         let state_run = state_mutex.clone();
 
+        // Flush the store before the process exits
+        let store = state_mutex.lock().await.immutable.clone();
+        shutdown::on_shutdown(
+            context.clone(),
+            &config,
+            "address-state",
+            move || async move { store.flush().await },
+        )
+        .await?;
+
+        let query_metrics = metrics::QueryMetrics::new("address-state");
         context.handle(&address_query_topic, move |message| {
             let state_mutex = state_mutex.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Addresses(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Addresses(
                         AddressStateQueryResponse::Error(QueryError::internal_error(
@@ -192,6 +209,12 @@ impl AddressState {
                 };
 
                 let state = state_mutex.lock().await;
+                if let Some(storage) = disabled_storage(&state.config, query) {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::Addresses(
+                        AddressStateQueryResponse::Error(QueryError::storage_disabled(storage)),
+                    )));
+                }
+
                 let response = match query {
                     AddressStateQuery::GetAddressUTxOs { address } => {
                         match state.get_address_utxos(address).await {
@@ -235,6 +258,19 @@ impl AddressState {
                     }
                     AddressStateQuery::GetAddressTotals { address } => {
                         match state.get_address_totals(address).await {
+                            // An address with no transactions has never been seen on chain
+                            Ok(totals) if totals.tx_count == 0 => match address.to_string() {
+                                Ok(addr_str) => {
+                                    AddressStateQueryResponse::Error(QueryError::not_found(
+                                        format!("Address {} not found", addr_str),
+                                    ))
+                                }
+                                Err(e) => {
+                                    AddressStateQueryResponse::Error(QueryError::internal_error(
+                                        format!("Could not convert address to string: {}", e),
+                                    ))
+                                }
+                            },
                             Ok(totals) => AddressStateQueryResponse::AddressTotals(totals),
                             Err(e) => AddressStateQueryResponse::Error(QueryError::internal_error(
                                 e.to_string(),
@@ -257,6 +293,12 @@ impl AddressState {
                             )),
                         }
                     }
+                    AddressStateQuery::GetStoreStats => match state.get_store_stats().await {
+                        Ok(stats) => AddressStateQueryResponse::StoreStats(stats),
+                        Err(e) => AddressStateQueryResponse::Error(QueryError::internal_error(
+                            e.to_string(),
+                        )),
+                    },
                 };
                 Arc::new(Message::StateQueryResponse(StateQueryResponse::Addresses(
                     response,
@@ -287,3 +329,27 @@ impl AddressState {
         Ok(())
     }
 }
+
+/// Storage a query needs which is disabled in configuration, if any
+fn disabled_storage(
+    config: &AddressStorageConfig,
+    query: &AddressStateQuery,
+) -> Option<&'static str> {
+    match query {
+        AddressStateQuery::GetAddressUTxOs { .. } | AddressStateQuery::GetAddressesUTxOs { .. }
+            if !config.store_info =>
+        {
+            Some("Address info")
+        }
+        AddressStateQuery::GetAddressTransactions { .. } if !config.store_transactions => {
+            Some("Address transactions")
+        }
+        AddressStateQuery::GetAddressTotals { .. }
+        | AddressStateQuery::GetAddressesTotals { .. }
+            if !config.store_totals =>
+        {
+            Some("Address totals")
+        }
+        _ => None,
+    }
+}
@@ -1,9 +1,11 @@
 use std::{collections::HashMap, path::Path};
 
 use crate::state::{AddressEntry, AddressStorageConfig, UtxoDelta};
-use acropolis_common::{Address, AddressTotals, TxIdentifier, UTxOIdentifier};
+use acropolis_common::{
+    queries::addresses::AddressStoreStats, Address, AddressTotals, TxIdentifier, UTxOIdentifier,
+};
 use anyhow::Result;
-use fjall::{Database, Keyspace, KeyspaceCreateOptions};
+use fjall::{Database, Keyspace, KeyspaceCreateOptions, PersistMode};
 use minicbor::{decode, to_vec};
 use tokio::{sync::Mutex, task};
 use tracing::{debug, error, info};
@@ -27,6 +29,9 @@ pub struct ImmutableAddressStore {
     totals: Keyspace,
     database: Database,
     pub pending: Mutex<Vec<HashMap<Address, AddressEntry>>>,
+
+    /// Held while an epoch is being persisted, so a flush waits for it
+    persisting: Mutex<()>,
 }
 
 impl ImmutableAddressStore {
@@ -48,6 +53,7 @@ impl ImmutableAddressStore {
             totals,
             database,
             pending: Mutex::new(Vec::new()),
+            persisting: Mutex::new(()),
         })
     }
 
@@ -55,6 +61,8 @@ impl ImmutableAddressStore {
     /// for an entire epoch. Skips any partitions that have already stored the given epoch.
     /// All writes are batched and committed atomically, preventing on-disk corruption in case of failure.
     pub async fn persist_epoch(&self, epoch: u64, config: &AddressStorageConfig) -> Result<()> {
+        let _persisting = self.persisting.lock().await;
+
         // Skip if all options disabled
         if !(config.store_info || config.store_transactions || config.store_totals) {
             debug!("no persistence needed for epoch {epoch} (all stores disabled)");
@@ -166,6 +174,13 @@ impl ImmutableAddressStore {
         }
     }
 
+    /// Wait for any epoch being persisted, then make everything written durable
+    pub async fn flush(&self) -> Result<()> {
+        let _persisting = self.persisting.lock().await;
+        self.database.persist(PersistMode::SyncAll)?;
+        Ok(())
+    }
+
     pub async fn update_immutable(&self, drained: Vec<HashMap<Address, AddressEntry>>) {
         let mut pending = self.pending.lock().await;
         pending.extend(drained);
@@ -253,7 +268,41 @@ impl ImmutableAddressStore {
         }
     }
 
+    /// Persisted index sizes and cached blocks - the volatile window is the caller's
+    pub async fn stats(&self) -> Result<AddressStoreStats> {
+        Ok(AddressStoreStats {
+            utxo_addresses: Self::address_count(&self.utxos, ADDRESS_UTXOS_EPOCH_COUNTER)?,
+            transaction_addresses: Self::address_count(&self.txs, ADDRESS_TXS_EPOCH_COUNTER)?,
+            totals_addresses: Self::address_count(&self.totals, ADDRESS_TOTALS_EPOCH_COUNTER)?,
+            pending_blocks: self.pending.lock().await.len() as u64,
+            volatile_blocks: 0,
+            last_epoch_stored: self.read_last_epoch_stored().await?,
+        })
+    }
+
+    /// Approximate number of addresses in a partition, leaving out its epoch marker
+    fn address_count(keyspace: &Keyspace, marker: &[u8]) -> Result<u64> {
+        let entries = keyspace.approximate_len() as u64;
+        Ok(if keyspace.get(marker)?.is_some() {
+            entries.saturating_sub(1)
+        } else {
+            entries
+        })
+    }
+
     pub async fn get_last_epoch_stored(&self) -> Result<Option<u64>> {
+        let min_epoch = self.read_last_epoch_stored().await?;
+
+        if let Some(epoch) = min_epoch {
+            info!("last epoch already stored across partitions: {epoch}");
+        } else {
+            info!("no epoch markers found across partitions");
+        }
+
+        Ok(min_epoch)
+    }
+
+    async fn read_last_epoch_stored(&self) -> Result<Option<u64>> {
         let read_marker = |keyspace: Keyspace, key: &'static [u8]| async move {
             task::spawn_blocking(move || {
                 Ok::<_, anyhow::Error>(match keyspace.get(key)? {
@@ -277,15 +326,7 @@ impl ImmutableAddressStore {
         let t = read_marker(self.txs.clone(), ADDRESS_TXS_EPOCH_COUNTER).await?;
         let tot = read_marker(self.totals.clone(), ADDRESS_TOTALS_EPOCH_COUNTER).await?;
 
-        let min_epoch = [u, t, tot].into_iter().flatten().min();
-
-        if let Some(epoch) = min_epoch {
-            info!("last epoch already stored across partitions: {epoch}");
-        } else {
-            info!("no epoch markers found across partitions");
-        }
-
-        Ok(min_epoch)
+        Ok([u, t, tot].into_iter().flatten().min())
     }
 
     async fn epoch_exists(
@@ -1,8 +1,8 @@
 use std::{collections::HashSet, path::Path, sync::Arc};
 
 use acropolis_common::{
-    Address, AddressDelta, AddressTotals, BlockInfo, ShelleyAddress, TxIdentifier, TxTotals,
-    UTxOIdentifier,
+    queries::addresses::AddressStoreStats, Address, AddressDelta, AddressTotals, BlockInfo,
+    ShelleyAddress, TxIdentifier, TxTotals, UTxOIdentifier,
 };
 use anyhow::Result;
 
@@ -228,6 +228,12 @@ impl State {
         }
         Ok(utxos)
     }
+
+    pub async fn get_store_stats(&self) -> Result<AddressStoreStats> {
+        let mut stats = self.immutable.stats().await?;
+        stats.volatile_blocks = self.volatile.window.len() as u64;
+        Ok(stats)
+    }
 }
 
 #[cfg(test)]
@@ -328,6 +334,40 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_store_stats_follow_persistence() -> Result<()> {
+        let mut state = setup_state_and_store().await?;
+
+        let addr = dummy_address();
+        let utxo = UTxOIdentifier::new(TxHash::default(), 0);
+        state.apply_address_deltas(&[delta(
+            &addr,
+            TxIdentifier::new(0, 0),
+            vec![],
+            vec![utxo],
+            0,
+            1,
+        )]);
+
+        let stats = state.get_store_stats().await?;
+        assert_eq!(stats.volatile_blocks, 1);
+        assert_eq!(stats.pending_blocks, 0);
+        assert_eq!(stats.utxo_addresses, 0);
+
+        state.prune_volatile().await;
+        assert_eq!(state.get_store_stats().await?.pending_blocks, 1);
+
+        state.immutable.persist_epoch(0, &state.config).await?;
+        let stats = state.get_store_stats().await?;
+        assert_eq!(stats.pending_blocks, 0);
+        assert_eq!(stats.utxo_addresses, 1);
+        assert_eq!(stats.transaction_addresses, 1);
+        assert_eq!(stats.totals_addresses, 1);
+        assert_eq!(stats.last_epoch_stored, Some(0));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_utxo_removed_when_spent() -> Result<()> {
         let _ = tracing_subscriber::fmt::try_init();
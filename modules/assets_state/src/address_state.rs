@@ -36,13 +36,16 @@ impl AddressState {
         asset_id: &AssetId,
     ) -> Result<Option<Vec<AssetAddressEntry>>> {
         Ok(self.addresses.get(asset_id).map(|inner_map| {
-            inner_map
+            let mut entries: Vec<AssetAddressEntry> = inner_map
                 .iter()
                 .map(|(addr, qty)| AssetAddressEntry {
                     address: addr.clone(),
                     quantity: *qty,
                 })
-                .collect()
+                .collect();
+            // Sorted so that pages of the list are stable
+            entries.sort_by_cached_key(|entry| entry.address.to_bytes_key());
+            entries
         }))
     }
 
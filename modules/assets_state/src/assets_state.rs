@@ -15,11 +15,14 @@ use acropolis_common::{
         AddressDeltasMessage, AssetDeltasMessage, CardanoMessage, Message, StateQuery,
         StateQueryResponse, StateTransitionMessage, UTXODeltasMessage,
     },
+    metrics,
     queries::{
         assets::{AssetsStateQuery, AssetsStateQueryResponse, DEFAULT_ASSETS_QUERY_TOPIC},
         errors::QueryError,
     },
+    readiness,
     state_history::{StateHistory, StateHistoryStore},
+    utxo_batches::UTXODeltasAssembler,
 };
 use anyhow::{bail, Result};
 use caryatid_sdk::{module, Context, Subscription};
@@ -29,6 +32,7 @@ use tokio::sync::Mutex;
 use tracing::{debug, error, info, info_span, Instrument};
 mod address_state;
 pub mod asset_registry;
+mod metadata;
 mod state;
 
 // Subscription topics
@@ -44,7 +48,8 @@ declare_cardano_reader!(
     "utxo-deltas-subscribe-topic",
     "cardano.utxo.deltas",
     UTXODeltas,
-    UTXODeltasMessage
+    UTXODeltasMessage,
+    UTXODeltasBatch => UTXODeltasAssembler
 );
 declare_cardano_reader!(
     AddressDeltasReader,
@@ -117,7 +122,11 @@ impl AssetsState {
                 // Always handle the mint deltas (This is how assets get initialized)
                 {
                     let mut reg = registry.lock().await;
-                    state = match state.handle_mint_deltas(&deltas_msg.deltas, &mut reg) {
+                    state = match state.handle_mint_deltas(
+                        &deltas_msg.deltas,
+                        primary.block_info().epoch,
+                        &mut reg,
+                    ) {
                         Ok((new_state, updated_asset_ids)) => {
                             if let Some(ref address_state) = address_state {
                                 let mut address_state = address_state.lock().await;
@@ -210,6 +219,8 @@ impl AssetsState {
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "assets-state").await?;
+
         fn get_transactions_flag(config: &Config, key: (&str, &str)) -> StoreTransactions {
             let val = get_string_flag(config, key);
             match val.as_str() {
@@ -264,11 +275,14 @@ impl AssetsState {
         let query_registry = registry.clone();
 
         // Query handler
+        let query_metrics = metrics::QueryMetrics::new("assets-state");
         context.handle(&assets_query_topic, move |message| {
             let history = query_history.clone();
             let address_state = query_address_state.clone();
             let registry = query_registry.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Assets(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Assets(
                         AssetsStateQueryResponse::Error(QueryError::internal_error(
@@ -356,6 +370,37 @@ impl AssetsState {
                             }
                         }
                     }
+                    AssetsStateQuery::GetAssetSupplyAtEpoch {
+                        policy,
+                        name,
+                        epoch,
+                    } => {
+                        let reg = registry.lock().await;
+                        if !state.config.store_history {
+                            AssetsStateQueryResponse::Error(QueryError::storage_disabled(
+                                "asset history",
+                            ))
+                        } else {
+                            let supply = reg
+                                .lookup_id(policy, name)
+                                .map(|id| state.get_asset_supply_at_epoch(&id, *epoch));
+                            match supply {
+                                Some(Ok(Some(supply))) => {
+                                    AssetsStateQueryResponse::AssetSupplyAtEpoch(supply)
+                                }
+                                Some(Err(e)) => AssetsStateQueryResponse::Error(
+                                    QueryError::internal_error(e.to_string()),
+                                ),
+                                Some(Ok(None)) | None => {
+                                    AssetsStateQueryResponse::Error(QueryError::not_found(format!(
+                                        "Asset history for {}:{}",
+                                        hex::encode(policy),
+                                        hex::encode(name.as_slice())
+                                    )))
+                                }
+                            }
+                        }
+                    }
                     AssetsStateQuery::GetAssetAddresses { policy, name } => match address_state {
                         Some(address_state) => {
                             let reg = registry.lock().await;
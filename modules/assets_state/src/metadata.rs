@@ -0,0 +1,141 @@
+//! Acropolis AssetsState: decoding of on-chain token metadata
+//!
+//! CIP-25 metadata is a map per asset under the 721 transaction metadata label.
+//! CIP-68 metadata is the inline datum of the reference (label 100) token, of the
+//! form `Constr 0 [metadata, version, extra]`. Both are reduced to the display
+//! fields of [`NormalizedAssetMetadata`].
+
+use std::collections::BTreeMap;
+
+use acropolis_common::{AssetMetadataSource, AssetMetadataStandard, NormalizedAssetMetadata};
+use serde_cbor::Value;
+
+/// CBOR tag of Plutus data constructor 0
+const CONSTR_0_TAG: u64 = 121;
+
+/// Normalize the CIP-25 metadata of a single asset
+pub fn decode_cip25(metadata: &Value) -> Option<NormalizedAssetMetadata> {
+    let Value::Map(map) = metadata else {
+        return None;
+    };
+    Some(normalize(map, AssetMetadataSource::CIP25))
+}
+
+/// Decode a CIP-68 reference datum into its normalized metadata and standard
+pub fn decode_cip68(datum: &[u8]) -> Option<(NormalizedAssetMetadata, AssetMetadataStandard)> {
+    let Value::Tag(CONSTR_0_TAG, fields) = serde_cbor::from_slice::<Value>(datum).ok()? else {
+        return None;
+    };
+    let Value::Array(fields) = *fields else {
+        return None;
+    };
+    let [Value::Map(metadata), Value::Integer(version), ..] = fields.as_slice() else {
+        return None;
+    };
+
+    let standard = match version {
+        1 => AssetMetadataStandard::CIP68v1,
+        2 => AssetMetadataStandard::CIP68v2,
+        3 => AssetMetadataStandard::CIP68v3,
+        _ => return None,
+    };
+    Some((normalize(metadata, AssetMetadataSource::CIP68), standard))
+}
+
+fn normalize(map: &BTreeMap<Value, Value>, source: AssetMetadataSource) -> NormalizedAssetMetadata {
+    let mut metadata = NormalizedAssetMetadata::new(source);
+    for (key, value) in map {
+        let Some(key) = text(key) else {
+            continue;
+        };
+        match key.as_str() {
+            "name" => metadata.name = text(value),
+            "description" => metadata.description = text(value),
+            "image" => metadata.image = text(value),
+            "mediaType" => metadata.media_type = text(value),
+            "ticker" => metadata.ticker = text(value),
+            "url" => metadata.url = text(value),
+            "logo" => metadata.logo = text(value),
+            "decimals" => {
+                metadata.decimals = match value {
+                    Value::Integer(decimals) => u64::try_from(*decimals).ok(),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// Text from a string or UTF-8 bytes (CIP-68 keys and values are bytes), or from an
+/// array of them, which is how CIP-25 splits strings over the 64 byte metadata limit
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(s) => Some(s.clone()),
+        Value::Bytes(b) => String::from_utf8(b.clone()).ok(),
+        Value::Array(parts) => {
+            parts.iter().map(text).collect::<Option<Vec<_>>>().map(|p| p.concat())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> Value {
+        Value::Bytes(s.as_bytes().to_vec())
+    }
+
+    fn cip68_datum(version: i128) -> Vec<u8> {
+        let metadata = BTreeMap::from([
+            (bytes("name"), bytes("Token")),
+            (bytes("decimals"), Value::Integer(6)),
+        ]);
+        let fields = Value::Array(vec![
+            Value::Map(metadata),
+            Value::Integer(version),
+            Value::Null,
+        ]);
+        serde_cbor::to_vec(&Value::Tag(CONSTR_0_TAG, Box::new(fields))).unwrap()
+    }
+
+    #[test]
+    fn cip25_joins_split_strings() {
+        let metadata = Value::Map(BTreeMap::from([
+            (Value::Text("name".into()), Value::Text("NFT #1".into())),
+            (
+                Value::Text("image".into()),
+                Value::Array(vec![
+                    Value::Text("ipfs://Qm".into()),
+                    Value::Text("abc".into()),
+                ]),
+            ),
+            (
+                Value::Text("mediaType".into()),
+                Value::Text("image/png".into()),
+            ),
+        ]));
+
+        let normalized = decode_cip25(&metadata).unwrap();
+        assert_eq!(normalized.source, AssetMetadataSource::CIP25);
+        assert_eq!(normalized.name.as_deref(), Some("NFT #1"));
+        assert_eq!(normalized.image.as_deref(), Some("ipfs://Qmabc"));
+        assert_eq!(normalized.media_type.as_deref(), Some("image/png"));
+        assert_eq!(decode_cip25(&Value::Text("not a map".into())), None);
+    }
+
+    #[test]
+    fn cip68_reads_version_from_datum() {
+        let (normalized, standard) = decode_cip68(&cip68_datum(2)).unwrap();
+        assert_eq!(standard, AssetMetadataStandard::CIP68v2);
+        assert_eq!(normalized.source, AssetMetadataSource::CIP68);
+        assert_eq!(normalized.name.as_deref(), Some("Token"));
+        assert_eq!(normalized.decimals, Some(6));
+
+        assert!(decode_cip68(&cip68_datum(9)).is_none());
+        assert!(decode_cip68(&[1, 2, 3, 4]).is_none());
+    }
+}
@@ -2,11 +2,15 @@
 
 use std::collections::HashSet;
 
-use crate::asset_registry::{AssetId, AssetRegistry};
+use crate::{
+    asset_registry::{AssetId, AssetRegistry},
+    metadata::{decode_cip25, decode_cip68},
+};
 use acropolis_common::{
     queries::assets::{AssetHistory, PolicyAssets},
-    AssetInfoRecord, AssetMetadata, AssetMetadataStandard, AssetMintRecord, AssetName, Datum,
-    Lovelace, NativeAssets, NativeAssetsDelta, PolicyAsset, PolicyId, TxIdentifier, TxUTxODeltas,
+    AssetInfoRecord, AssetMetadata, AssetMetadataSource, AssetMetadataStandard, AssetMintRecord,
+    AssetName, Datum, Lovelace, NativeAssets, NativeAssetsDelta, PolicyAsset, PolicyId,
+    TxIdentifier, TxUTxODeltas,
 };
 use anyhow::Result;
 use imbl::{HashMap, Vector};
@@ -133,8 +137,17 @@ impl State {
         // Overwrite asset metadata if an associated CIP68 reference token is found
         if let Some(ref_info) = self.resolve_cip68_metadata(asset_id, registry) {
             if let Some(info_mut) = info.as_mut() {
-                info_mut.metadata.cip68_metadata = ref_info.metadata.cip68_metadata;
-                info_mut.metadata.cip68_version = ref_info.metadata.cip68_version;
+                let metadata = &mut info_mut.metadata;
+                metadata.cip68_metadata = ref_info.metadata.cip68_metadata;
+                metadata.cip68_version = ref_info.metadata.cip68_version;
+
+                // Reference token metadata replaces any CIP-25 metadata of the asset itself
+                let cip25 =
+                    metadata.normalized.take().filter(|m| m.source == AssetMetadataSource::CIP25);
+                metadata.normalized = ref_info.metadata.normalized.or(cip25);
+                if ref_info.metadata.updated_tx > metadata.updated_tx {
+                    metadata.updated_tx = ref_info.metadata.updated_tx;
+                }
             }
         }
 
@@ -156,6 +169,24 @@ impl State {
             .map(|v| v.iter().cloned().collect()))
     }
 
+    /// Supply at the end of `epoch`, or None if the asset has no history
+    pub fn get_asset_supply_at_epoch(&self, asset_id: &AssetId, epoch: u64) -> Result<Option<u64>> {
+        if !self.config.store_history {
+            return Err(anyhow::anyhow!("asset history storage disabled in config"));
+        }
+
+        let Some(history) = self.history.as_ref().and_then(|hist_map| hist_map.get(asset_id))
+        else {
+            return Ok(None);
+        };
+
+        // History is in chain order, so epochs are ascending
+        let minted_by_epoch = history.iter().take_while(|record| record.epoch <= epoch);
+        Ok(Some(
+            minted_by_epoch.last().map(|record| record.supply).unwrap_or(0),
+        ))
+    }
+
     pub fn get_asset_transactions(&self, asset_id: &AssetId) -> Result<Option<Vec<TxIdentifier>>> {
         if !self.config.store_transactions.is_enabled() {
             return Err(anyhow::anyhow!(
@@ -271,6 +302,7 @@ impl State {
     pub fn handle_mint_deltas(
         &self,
         deltas: &[(TxIdentifier, NativeAssetsDelta)],
+        epoch: u64,
         registry: &mut AssetRegistry,
     ) -> Result<(Self, Vec<AssetId>)> {
         let mut new_supply = self.supply.clone();
@@ -315,23 +347,25 @@ impl State {
                             .or_insert(AssetInfoRecord {
                                 initial_mint_tx: *tx_identifier,
                                 mint_or_burn_count: 1,
-                                metadata: AssetMetadata {
-                                    cip25_metadata: None,
-                                    cip25_version: None,
-                                    cip68_metadata: None,
-                                    cip68_version: None,
-                                },
+                                metadata: AssetMetadata::default(),
                             });
                     }
 
                     if let Some(hist_map) = new_history.as_mut() {
-                        hist_map.entry(asset_id).or_insert_with(Vector::new).push_back(
-                            AssetMintRecord {
-                                tx: *tx_identifier,
-                                amount: delta.amount.unsigned_abs(),
-                                burn: delta.amount < 0,
+                        let history = hist_map.entry(asset_id).or_insert_with(Vector::new);
+                        let amount = delta.amount.unsigned_abs();
+                        let previous = history.last().map(|record| record.supply).unwrap_or(0);
+                        history.push_back(AssetMintRecord {
+                            tx: *tx_identifier,
+                            epoch,
+                            amount,
+                            burn: delta.amount < 0,
+                            supply: if delta.amount < 0 {
+                                previous.saturating_sub(amount)
+                            } else {
+                                previous + amount
                             },
-                        );
+                        });
                     }
 
                     if let Some(index) = new_index.as_mut() {
@@ -413,7 +447,7 @@ impl State {
     pub fn handle_cip25_metadata(
         &self,
         registry: &mut AssetRegistry,
-        metadata_bytes: &[Vec<u8>],
+        metadata_updates: &[(TxIdentifier, Vec<u8>)],
     ) -> Result<Self> {
         let mut new_info = self.info.clone();
         let Some(info_map) = new_info.as_mut() else {
@@ -423,7 +457,7 @@ impl State {
             });
         };
 
-        for bytes in metadata_bytes {
+        for (tx_identifier, bytes) in metadata_updates {
             let Ok(decoded) = serde_cbor::from_slice::<serde_cbor::Value>(bytes) else {
                 continue;
             };
@@ -477,8 +511,21 @@ impl State {
                         if let Some(asset_id) = registry.lookup_id(&policy_id, &asset_name) {
                             if let Ok(metadata_raw) = serde_cbor::to_vec(&metadata_val) {
                                 if let Some(record) = info_map.get_mut(&asset_id) {
-                                    record.metadata.cip25_metadata = Some(metadata_raw);
-                                    record.metadata.cip25_version = Some(standard);
+                                    let metadata = &mut record.metadata;
+                                    // A re-mint replaces the metadata of the previous mint
+                                    if metadata.cip25_metadata.as_ref() != Some(&metadata_raw) {
+                                        metadata.updates += 1;
+                                        metadata.updated_tx = Some(*tx_identifier);
+                                    }
+                                    if metadata
+                                        .normalized
+                                        .as_ref()
+                                        .is_none_or(|m| m.source == AssetMetadataSource::CIP25)
+                                    {
+                                        metadata.normalized = decode_cip25(&metadata_val);
+                                    }
+                                    metadata.cip25_metadata = Some(metadata_raw);
+                                    metadata.cip25_version = Some(standard);
                                 }
                             }
                         }
@@ -506,20 +553,11 @@ impl State {
                     continue;
                 };
 
-                let mut cip68_version = Some(AssetMetadataStandard::CIP68v1);
-
-                if let Ok(serde_cbor::Value::Map(m)) =
-                    serde_cbor::from_slice::<serde_cbor::Value>(blob)
-                {
-                    let version_key = serde_cbor::Value::Text("version".to_string());
-
-                    if let Some(serde_cbor::Value::Text(ver)) = m.get(&version_key) {
-                        cip68_version = match ver.as_str() {
-                            "2.0" => Some(AssetMetadataStandard::CIP68v2),
-                            _ => Some(AssetMetadataStandard::CIP68v1),
-                        };
-                    }
-                }
+                // The version is part of the datum; default to v1 if it does not decode
+                let (normalized, cip68_version) = match decode_cip68(blob) {
+                    Some((normalized, standard)) => (Some(normalized), standard),
+                    None => (None, AssetMetadataStandard::CIP68v1),
+                };
 
                 for (policy_id, native_assets) in &output.value.assets {
                     for asset in native_assets {
@@ -534,8 +572,15 @@ impl State {
                                 if let Some(record) =
                                     new_info.as_mut().and_then(|m| m.get_mut(&asset_id))
                                 {
-                                    record.metadata.cip68_metadata = Some(blob.clone());
-                                    record.metadata.cip68_version = cip68_version;
+                                    let metadata = &mut record.metadata;
+                                    // Moving the reference token with an unchanged datum is not an update
+                                    if metadata.cip68_metadata.as_ref() != Some(blob) {
+                                        metadata.updates += 1;
+                                        metadata.updated_tx = Some(tx.tx_identifier);
+                                    }
+                                    metadata.cip68_metadata = Some(blob.clone());
+                                    metadata.cip68_version = Some(cip68_version);
+                                    metadata.normalized = normalized.clone();
                                 }
                             }
                             None => {
@@ -576,6 +621,7 @@ impl State {
                 // Hide metadata on the reference itself (Per Blockfrost spec)
                 rec.metadata.cip68_metadata = None;
                 rec.metadata.cip68_version = None;
+                rec.metadata.normalized = None;
                 rec
             }),
 
@@ -751,7 +797,7 @@ mod tests {
             vec![(policy, vec![NativeAssetDelta { name, amount: 100 }])],
         )];
 
-        let (new_state, _) = state.handle_mint_deltas(&deltas, &mut registry).unwrap();
+        let (new_state, _) = state.handle_mint_deltas(&deltas, 0, &mut registry).unwrap();
 
         // supply updated
         let asset_id = registry.lookup_id(&policy, &name).unwrap();
@@ -794,8 +840,8 @@ mod tests {
             vec![(policy, vec![NativeAssetDelta { name, amount: 25 }])],
         )];
 
-        let (state, _) = state.handle_mint_deltas(&deltas1, &mut registry).unwrap();
-        let (state, _) = state.handle_mint_deltas(&deltas2, &mut registry).unwrap();
+        let (state, _) = state.handle_mint_deltas(&deltas1, 0, &mut registry).unwrap();
+        let (state, _) = state.handle_mint_deltas(&deltas2, 0, &mut registry).unwrap();
 
         let asset_id = registry.lookup_id(&policy, &name).unwrap();
 
@@ -832,8 +878,8 @@ mod tests {
             vec![(policy, vec![NativeAssetDelta { name, amount: -40 }])],
         )];
 
-        let (state, _) = state.handle_mint_deltas(&mint, &mut registry).unwrap();
-        let (state, _) = state.handle_mint_deltas(&burn, &mut registry).unwrap();
+        let (state, _) = state.handle_mint_deltas(&mint, 0, &mut registry).unwrap();
+        let (state, _) = state.handle_mint_deltas(&burn, 0, &mut registry).unwrap();
 
         let asset_id = registry.lookup_id(&policy, &name).unwrap();
 
@@ -852,6 +898,51 @@ mod tests {
         assert_eq!(hist[1].amount, 40);
     }
 
+    #[test]
+    fn supply_at_epoch_follows_mint_and_burn_history() {
+        let mut registry = AssetRegistry::new();
+        let state = State::new(full_config());
+
+        let policy = dummy_policy(1);
+        let name = asset_name_from_str("tokenA");
+
+        let mint = vec![(
+            dummy_tx_identifier(1),
+            vec![(policy, vec![NativeAssetDelta { name, amount: 100 }])],
+        )];
+        let burn = vec![(
+            dummy_tx_identifier(2),
+            vec![(policy, vec![NativeAssetDelta { name, amount: -30 }])],
+        )];
+
+        let (state, _) = state.handle_mint_deltas(&mint, 5, &mut registry).unwrap();
+        let (state, _) = state.handle_mint_deltas(&burn, 7, &mut registry).unwrap();
+
+        let asset_id = registry.lookup_id(&policy, &name).unwrap();
+
+        let hist = state.get_asset_history(&asset_id).unwrap().unwrap();
+        assert_eq!((hist[0].epoch, hist[0].supply), (5, 100));
+        assert_eq!((hist[1].epoch, hist[1].supply), (7, 70));
+
+        // Before the first mint, between events and after the last one
+        assert_eq!(
+            state.get_asset_supply_at_epoch(&asset_id, 4).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            state.get_asset_supply_at_epoch(&asset_id, 6).unwrap(),
+            Some(100)
+        );
+        assert_eq!(
+            state.get_asset_supply_at_epoch(&asset_id, 7).unwrap(),
+            Some(70)
+        );
+        assert_eq!(
+            state.get_asset_supply_at_epoch(&asset_id, u64::MAX).unwrap(),
+            Some(70)
+        );
+    }
+
     #[test]
     fn first_tx_as_burn_fails() {
         let mut registry = AssetRegistry::new();
@@ -866,7 +957,7 @@ mod tests {
             vec![(policy, vec![NativeAssetDelta { name, amount: -50 }])],
         )];
 
-        let result = state.handle_mint_deltas(&deltas, &mut registry);
+        let result = state.handle_mint_deltas(&deltas, 0, &mut registry);
         // Error on first tx being a burn
         assert!(result.is_err());
     }
@@ -885,7 +976,7 @@ mod tests {
             vec![(policy, vec![NativeAssetDelta { name, amount: -10 }])],
         )];
 
-        let result = state.handle_mint_deltas(&deltas, &mut registry);
+        let result = state.handle_mint_deltas(&deltas, 0, &mut registry);
 
         // Error on negative supply
         assert!(result.is_err());
@@ -922,7 +1013,9 @@ mod tests {
 
         let metadata_cbor = build_cip25_metadata(policy_id, &asset_name, "hello world", None);
 
-        let new_state = state.handle_cip25_metadata(&mut registry, &[metadata_cbor]).unwrap();
+        let new_state = state
+            .handle_cip25_metadata(&mut registry, &[(dummy_tx_identifier(1), metadata_cbor)])
+            .unwrap();
         let info = new_state.info.expect("info should be Some");
         let record = info.get(&asset_id).unwrap();
 
@@ -951,7 +1044,9 @@ mod tests {
         let metadata_cbor =
             build_cip25_metadata(policy_id, &asset_name, "metadata for v2", Some("2.0"));
 
-        let new_state = state.handle_cip25_metadata(&mut registry, &[metadata_cbor]).unwrap();
+        let new_state = state
+            .handle_cip25_metadata(&mut registry, &[(dummy_tx_identifier(1), metadata_cbor)])
+            .unwrap();
         let info = new_state.info.expect("info should be Some");
         let record = info.get(&asset_id).unwrap();
 
@@ -980,7 +1075,9 @@ mod tests {
         let metadata_cbor =
             build_cip25_metadata(policy_id, &other_asset_name, "ignored metadata", None);
 
-        let new_state = state.handle_cip25_metadata(&mut registry, &[metadata_cbor]).unwrap();
+        let new_state = state
+            .handle_cip25_metadata(&mut registry, &[(dummy_tx_identifier(1), metadata_cbor)])
+            .unwrap();
         let info = new_state.info.expect("info should be Some");
         let record = info.get(&asset_id).unwrap();
 
@@ -1005,7 +1102,9 @@ mod tests {
 
         let metadata_cbor = vec![0xff, 0x00, 0x13, 0x37];
 
-        let new_state = state.handle_cip25_metadata(&mut registry, &[metadata_cbor]).unwrap();
+        let new_state = state
+            .handle_cip25_metadata(&mut registry, &[(dummy_tx_identifier(1), metadata_cbor)])
+            .unwrap();
         let info = new_state.info.expect("info should be Some");
         let record = info.get(&asset_id).unwrap();
 
@@ -1021,6 +1120,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn handle_cip25_metadata_tracks_remints() {
+        let mut registry = AssetRegistry::new();
+        let policy_id = PolicyId::from([4u8; 28]);
+        let (state, asset_id, asset_name) = setup_state_with_asset(
+            &mut registry,
+            policy_id,
+            b"RemintedAsset",
+            true,
+            StoreTransactions::None,
+        );
+
+        let first = build_cip25_metadata(policy_id, &asset_name, "first", None);
+        let second = build_cip25_metadata(policy_id, &asset_name, "second", None);
+        let new_state = state
+            .handle_cip25_metadata(
+                &mut registry,
+                &[
+                    (dummy_tx_identifier(1), first),
+                    (dummy_tx_identifier(2), second.clone()),
+                    // Repeating the same metadata is not an update
+                    (dummy_tx_identifier(3), second.clone()),
+                ],
+            )
+            .unwrap();
+
+        let info = new_state.info.expect("info should be Some");
+        let record = info.get(&asset_id).unwrap();
+        assert_eq!(record.metadata.updates, 2);
+        assert_eq!(record.metadata.updated_tx, Some(dummy_tx_identifier(2)));
+    }
+
     // CIP-68 tests
     #[test]
     fn handle_cip68_metadata_updates_onchain_metadata() {
@@ -1225,10 +1356,9 @@ mod tests {
                 initial_mint_tx: dummy_tx_identifier(0),
                 mint_or_burn_count: 0,
                 metadata: AssetMetadata {
-                    cip25_metadata: None,
-                    cip25_version: None,
                     cip68_metadata: Some(vec![1, 2, 3]),
                     cip68_version: Some(AssetMetadataStandard::CIP68v1),
+                    ..Default::default()
                 },
             },
         );
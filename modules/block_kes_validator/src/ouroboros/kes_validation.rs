@@ -127,28 +127,21 @@ pub fn validate_block_kes<'a>(
 ) -> Result<(Vec<KesValidation<'a>>, PoolId, u64), Box<KesValidationError>> {
     let is_praos = matches!(header, MultiEraHeader::BabbageCompatible(_));
 
-    let issuer_vkey = header.issuer_vkey().ok_or(Box::new(KesValidationError::Other(
-        "Block header missing issuer verification key".to_string(),
-    )))?;
+    let issuer_vkey =
+        header.issuer_vkey().ok_or(Box::new(KesValidationError::MissingIssuerVkey))?;
     let issuer = ed25519::PublicKey::from(
         <[u8; ed25519::PublicKey::SIZE]>::try_from(issuer_vkey).map_err(|_| {
-            Box::new(KesValidationError::Other(
-                "Issuer verification key has invalid length (expected 32 bytes)".to_string(),
-            ))
+            Box::new(KesValidationError::InvalidIssuerVkey {
+                length: issuer_vkey.len(),
+            })
         })?,
     );
     let pool_id = PoolId::from(keyhash_224(issuer_vkey));
 
     let slot_kes_period = header.slot() / slots_per_kes_period;
-    let cert = operational_cert(header).ok_or(Box::new(KesValidationError::Other(
-        "Block header missing operational certificate".to_string(),
-    )))?;
-    let body_sig = body_signature(header).ok_or(Box::new(KesValidationError::Other(
-        "Block header missing KES body signature".to_string(),
-    )))?;
-    let raw_header_body = header.header_body_cbor().ok_or(Box::new(KesValidationError::Other(
-        "Block header body CBOR not available".to_string(),
-    )))?;
+    let cert = operational_cert(header).ok_or_else(|| missing_field("operational certificate"))?;
+    let body_sig = body_signature(header).ok_or_else(|| missing_field("body signature"))?;
+    let raw_header_body = header.header_body_cbor().ok_or_else(|| missing_field("header body"))?;
 
     let declared_sequence_number = cert.operational_cert_sequence_number;
     let latest_sequence_number = if is_praos {
@@ -166,12 +159,14 @@ pub fn validate_block_kes<'a>(
                     cert.operational_cert_kes_period,
                     raw_header_body,
                     &kes::PublicKey::try_from(cert.operational_cert_hot_vkey).map_err(|_| {
-                        KesValidationError::Other(
-                            "Invalid operational certificate hot vkey".to_string(),
-                        )
+                        KesValidationError::InvalidOcertHotVkey {
+                            length: cert.operational_cert_hot_vkey.len(),
+                        }
                     })?,
                     &kes::Signature::try_from(body_sig).map_err(|_| {
-                        KesValidationError::Other("Invalid body signature".to_string())
+                        KesValidationError::InvalidKesSignatureBytes {
+                            length: body_sig.len(),
+                        }
                     })?,
                     max_kes_evolutions,
                 )?;
@@ -193,6 +188,12 @@ pub fn validate_block_kes<'a>(
     ))
 }
 
+fn missing_field(field: &str) -> Box<KesValidationError> {
+    Box::new(KesValidationError::MissingHeaderField {
+        field: field.to_string(),
+    })
+}
+
 fn operational_cert<'a>(header: &'a MultiEraHeader) -> Option<OperationalCertificate<'a>> {
     match header {
         MultiEraHeader::ShelleyCompatible(x) => {
@@ -496,4 +497,46 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_7854823_praos_block_past_kes_evolutions() {
+        let slots_per_kes_period = 129600;
+        let block_header_7854823: Vec<u8> =
+            hex::decode(include_str!("./data/7854823.cbor")).unwrap();
+        let block_header =
+            MultiEraHeader::decode(Era::Babbage as u8, None, &block_header_7854823).unwrap();
+        let current_period = block_header.slot() / slots_per_kes_period;
+        let ocert_start_period =
+            operational_cert(&block_header).unwrap().operational_cert_kes_period;
+
+        // The certificate expires just before the block's KES period
+        let max_kes_evolutions = current_period - ocert_start_period;
+        let pool_id =
+            PoolId::from_bech32("pool195gdnmj6smzuakm4etxsxw3fgh8asqc4awtcskpyfnkpcvh2v8t")
+                .unwrap();
+        let ocert_counters = HashMap::from_iter([(pool_id, 11)]);
+        let active_spos = HashSet::from_iter([pool_id]);
+
+        let result = validate_block_kes(
+            &block_header,
+            &ocert_counters,
+            &active_spos,
+            &mainnet_genesis_values().genesis_delegs,
+            slots_per_kes_period,
+            max_kes_evolutions,
+        )
+        .and_then(|(kes_validations, _, _)| {
+            kes_validations.iter().try_for_each(|assert| assert().map_err(Box::new))
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            Box::new(KesValidationError::KesSignatureError(
+                KesSignatureError::KesAfterEndOcert {
+                    current_period,
+                    ocert_start_period,
+                    max_kes_evolutions,
+                }
+            ))
+        );
+    }
 }
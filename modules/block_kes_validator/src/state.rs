@@ -3,8 +3,8 @@ use std::collections::{HashMap, HashSet};
 use acropolis_common::{
     genesis_values::GenesisValues,
     messages::{ProtocolParamsMessage, SPOStateMessage},
-    validation::{KesValidationError, ValidationError},
-    BlockInfo, PoolId,
+    validation::ValidationError,
+    BlockInfo, GenesisDelegates, PoolId,
 };
 use imbl::HashMap as ImblHashMap;
 use pallas::ledger::traverse::MultiEraHeader;
@@ -21,6 +21,9 @@ pub struct State {
 
     pub max_kes_evolutions: Option<u64>,
 
+    /// Genesis delegates, as changed by certificate since genesis
+    pub genesis_delegs: Option<GenesisDelegates>,
+
     pub active_spos: HashSet<PoolId>,
 }
 
@@ -30,6 +33,7 @@ impl State {
             ocert_counters: ImblHashMap::new(),
             slots_per_kes_period: None,
             max_kes_evolutions: None,
+            genesis_delegs: None,
             active_spos: HashSet::new(),
         }
     }
@@ -42,6 +46,7 @@ impl State {
         if let Some(shelley_params) = msg.params.shelley.as_ref() {
             self.slots_per_kes_period = Some(shelley_params.slots_per_kes_period as u64);
             self.max_kes_evolutions = Some(shelley_params.max_kes_evolutions as u64);
+            self.genesis_delegs = Some(shelley_params.gen_delegs.clone());
         }
     }
 
@@ -76,22 +81,17 @@ impl State {
             }
         };
 
-        let Some(slots_per_kes_period) = self.slots_per_kes_period else {
-            return Err(Box::new(
-                KesValidationError::Other("Slots per KES period is not set".to_string()).into(),
-            ));
-        };
-        let Some(max_kes_evolutions) = self.max_kes_evolutions else {
-            return Err(Box::new(
-                KesValidationError::Other("Max KES evolutions is not set".to_string()).into(),
-            ));
-        };
+        // These are fixed by the Shelley genesis, so the genesis values stand in until
+        // protocol parameters arrive - after a snapshot bootstrap, not until the next epoch
+        let slots_per_kes_period =
+            self.slots_per_kes_period.unwrap_or(genesis.slots_per_kes_period);
+        let max_kes_evolutions = self.max_kes_evolutions.unwrap_or(genesis.max_kes_evolutions);
 
         let result = ouroboros::kes_validation::validate_block_kes(
             &header,
             &self.ocert_counters,
             &self.active_spos,
-            &genesis.genesis_delegs,
+            self.genesis_delegs.as_ref().unwrap_or(&genesis.genesis_delegs),
             slots_per_kes_period,
             max_kes_evolutions,
         )
@@ -0,0 +1,482 @@
+//! Acropolis block producer module for Caryatid
+//!
+//! Checks, slot by slot, whether the configured stake pool is elected to lead,
+//! using the pool's VRF key, the live stake distribution and the epoch nonce.
+//! Led slots are forged into blocks from the mempool, KES-signed and offered to
+//! consensus, which adopts them like blocks from any peer.
+
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use acropolis_codec::map_transaction_inputs;
+use acropolis_common::{
+    caryatid::RollbackWrapper,
+    commands::transactions::{TransactionsCommand, TransactionsCommandResponse},
+    configuration::{get_bool_flag, get_string_flag, get_u64_flag},
+    declare_cardano_reader,
+    messages::{
+        BlockOfferedMessage, CardanoMessage, Command, CommandResponse, ConsensusMessage,
+        GenesisCompleteMessage, Message, ProtocolParamsMessage, RawBlockMessage,
+        SPOStakeDistributionMessage, StateQuery, StateQueryResponse, StateTransitionMessage,
+    },
+    protocol_params::Nonce,
+    queries::utxos::{UTxOStateQuery, UTxOStateQueryResponse, DEFAULT_UTXOS_QUERY_TOPIC},
+    serialization::Bech32Conversion,
+    spdd_chunks::SPDDAssembler,
+    Era, PoolId, TxHash, UTxOIdentifier,
+};
+use anyhow::{bail, Context as _, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use pallas::ledger::traverse::MultiEraBlock;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+mod forging;
+mod leadership;
+mod mempool;
+use forging::Forger;
+use leadership::{LeaderProof, State};
+use mempool::{Mempool, PendingTx};
+
+const DEFAULT_ENABLED: (&str, bool) = ("enabled", false);
+const DEFAULT_POOL_ID: (&str, &str) = ("pool-id", "");
+const DEFAULT_VRF_SKEY_FILE: (&str, &str) = ("vrf-skey-file", "vrf.skey");
+const DEFAULT_KES_SKEY_FILE: (&str, &str) = ("kes-skey-file", "kes.skey");
+const DEFAULT_OPCERT_FILE: (&str, &str) = ("opcert-file", "node.cert");
+const DEFAULT_MAX_MEMPOOL_TXS: (&str, u64) = ("max-mempool-txs", 4096);
+
+const DEFAULT_CLOCK_TICK_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("clock-tick-subscribe-topic", "clock.tick");
+const DEFAULT_MEMPOOL_SUBMIT_TOPIC: (&str, &str) =
+    ("mempool-submit-topic", "cardano.mempool.submit");
+const DEFAULT_BLOCK_WANTED_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("block-wanted-subscribe-topic", "cardano.consensus.wants");
+const DEFAULT_CONSENSUS_PUBLISH_TOPIC: (&str, &str) =
+    ("consensus-publish-topic", "cardano.consensus.offers");
+const DEFAULT_BLOCK_PUBLISH_TOPIC: (&str, &str) =
+    ("block-publish-topic", "cardano.block.available");
+
+declare_cardano_reader!(
+    GenesisReader,
+    "genesis-subscribe-topic",
+    "cardano.sequence.bootstrapped",
+    GenesisComplete,
+    GenesisCompleteMessage
+);
+
+declare_cardano_reader!(
+    NonceReader,
+    "epoch-nonce-subscribe-topic",
+    "cardano.epoch.nonce",
+    EpochNonce,
+    Option<Nonce>
+);
+
+declare_cardano_reader!(
+    SPDDReader,
+    "spdd-subscribe-topic",
+    "cardano.spo.distribution",
+    SPOStakeDistribution,
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
+);
+
+declare_cardano_reader!(
+    ParamsReader,
+    "protocol-parameters-subscribe-topic",
+    "cardano.protocol.parameters",
+    ProtocolParams,
+    ProtocolParamsMessage
+);
+
+declare_cardano_reader!(
+    BlockReader,
+    "block-subscribe-topic",
+    "cardano.block.proposed",
+    BlockAvailable,
+    RawBlockMessage
+);
+
+/// Where forged blocks go
+struct PublishTopics {
+    consensus: String,
+    block: String,
+}
+
+/// Block producer module
+#[module(
+    message_type(Message),
+    name = "block-producer",
+    description = "Block forging for a stake pool"
+)]
+pub struct BlockProducer;
+
+impl BlockProducer {
+    async fn run_nonces(state: Arc<Mutex<State>>, mut nonces: NonceReader) -> Result<()> {
+        loop {
+            if let RollbackWrapper::Normal((block_info, nonce)) =
+                nonces.read_with_rollbacks().await?
+            {
+                state.lock().await.handle_epoch_nonce(block_info.epoch, &nonce);
+            }
+        }
+    }
+
+    async fn run_spdd(state: Arc<Mutex<State>>, mut spdd: SPDDReader) -> Result<()> {
+        loop {
+            // Only our stake is kept from each chunk
+            let mut stake = state.lock().await.our_stake();
+            if let RollbackWrapper::Normal(_) = spdd.stream_with_rollbacks(&mut stake).await? {
+                state.lock().await.handle_spdd(&stake);
+            }
+        }
+    }
+
+    async fn run_params(forger: Arc<Mutex<Forger>>, mut params: ParamsReader) -> Result<()> {
+        loop {
+            if let RollbackWrapper::Normal((_, message)) = params.read_with_rollbacks().await? {
+                forger.lock().await.handle_params(&message.params);
+            }
+        }
+    }
+
+    /// Follow the adopted chain, to know what to build on and which transactions
+    /// are no longer pending
+    async fn run_blocks(
+        forger: Arc<Mutex<Forger>>,
+        mempool: Arc<Mutex<Mempool>>,
+        mut blocks: BlockReader,
+    ) -> Result<()> {
+        loop {
+            match blocks.read_with_rollbacks().await? {
+                RollbackWrapper::Normal((block_info, block)) => {
+                    forger.lock().await.handle_block(&block_info);
+                    if block_info.era == Era::Byron {
+                        continue;
+                    }
+                    match MultiEraBlock::decode(&block.body) {
+                        Ok(block) => {
+                            let txs = block.txs();
+                            let ids: HashSet<TxHash> =
+                                txs.iter().map(|tx| TxHash::from(*tx.hash())).collect();
+                            let spent: HashSet<UTxOIdentifier> = txs
+                                .iter()
+                                .flat_map(|tx| map_transaction_inputs(&tx.consumes()))
+                                .collect();
+                            mempool.lock().await.handle_block(block_info.slot, &ids, &spent);
+                        }
+                        Err(e) => error!("Can't decode block {}: {e}", block_info.number),
+                    }
+                }
+                RollbackWrapper::Rollback((_, message)) => {
+                    if let Message::Cardano((
+                        block_info,
+                        CardanoMessage::StateTransition(StateTransitionMessage::Rollback(point)),
+                    )) = message.as_ref()
+                    {
+                        forger.lock().await.handle_rollback(block_info, point);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Forge a block for a slot we lead, and offer it to consensus
+    async fn forge(
+        context: &Context<Message>,
+        topics: &PublishTopics,
+        proof: &LeaderProof,
+        state: &State,
+        forger: &Mutex<Forger>,
+        mempool: &Mutex<Mempool>,
+    ) -> Result<()> {
+        let Some(genesis) = state.genesis() else {
+            bail!("Genesis values are not known yet");
+        };
+        let mut forger = forger.lock().await;
+        let Some(max_size) = forger.max_block_body_size() else {
+            bail!("Protocol parameters are not known yet");
+        };
+        let txs = mempool.lock().await.select(max_size);
+        let forged = forger.forge(proof, &txs, genesis)?;
+        info!(
+            slot = forged.info.slot,
+            number = forged.info.number,
+            hash = %forged.info.hash,
+            txs = forged.tx_ids.len(),
+            "Forged block"
+        );
+
+        let message = Message::Consensus(ConsensusMessage::BlockOffered(BlockOfferedMessage {
+            hash: forged.info.hash,
+            slot: forged.info.slot,
+            number: forged.info.number,
+            parent_hash: forged.parent_hash,
+            tiebreak_vrf: Some(forged.vrf_output),
+            origin_peer: None,
+        }));
+        context.publish(&topics.consensus, Arc::new(message)).await
+    }
+
+    /// Check leadership of the slots which have started since the last clock tick,
+    /// and forge blocks for the ones we lead
+    async fn run_clock(
+        context: Arc<Context<Message>>,
+        topics: Arc<PublishTopics>,
+        state: Arc<Mutex<State>>,
+        forger: Arc<Mutex<Forger>>,
+        mempool: Arc<Mutex<Mempool>>,
+        mut clock_tick_subscription: Box<dyn Subscription<Message>>,
+    ) -> Result<()> {
+        loop {
+            let (_, message) = clock_tick_subscription.read().await?;
+            if !matches!(message.as_ref(), Message::Clock(_)) {
+                continue;
+            }
+
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let mut state = state.lock().await;
+            let Some(slot) = state.genesis().map(|genesis| genesis.timestamp_to_slot(now)) else {
+                continue;
+            };
+
+            for proof in state.check_slots_to(slot) {
+                info!(
+                    slot = proof.slot,
+                    epoch = proof.epoch,
+                    vrf_output = hex::encode(proof.vrf_output),
+                    "Elected slot leader"
+                );
+                if let Err(e) =
+                    Self::forge(&context, &topics, &proof, &state, &forger, &mempool).await
+                {
+                    warn!("Slot {} will be empty: {e:#}", proof.slot);
+                }
+            }
+            debug!("Checked leadership up to slot {slot}");
+        }
+    }
+
+    /// Publish blocks we forged when consensus wants them
+    async fn run_wants(
+        context: Arc<Context<Message>>,
+        topics: Arc<PublishTopics>,
+        forger: Arc<Mutex<Forger>>,
+        mut wants_subscription: Box<dyn Subscription<Message>>,
+    ) -> Result<()> {
+        loop {
+            let (_, message) = wants_subscription.read().await?;
+            let Message::Consensus(ConsensusMessage::BlockWanted(wanted)) = message.as_ref() else {
+                continue;
+            };
+            let Some(forged) = forger.lock().await.forged_block(&wanted.hash).cloned() else {
+                continue;
+            };
+            debug!("Publishing forged block {}", forged.info.hash);
+            let message =
+                Message::Cardano((forged.info, CardanoMessage::BlockAvailable(forged.block)));
+            context.publish(&topics.block, Arc::new(message)).await?;
+        }
+    }
+
+    /// Check with utxo_state that the UTxOs a transaction spends are unspent
+    async fn check_inputs(
+        context: &Context<Message>,
+        utxos_query_topic: &str,
+        tx: &PendingTx,
+    ) -> Result<()> {
+        if tx.inputs.is_empty() {
+            bail!("Transaction {} spends nothing", tx.id);
+        }
+        let query = Arc::new(Message::StateQuery(StateQuery::UTxOs(
+            UTxOStateQuery::GetUTxOs {
+                utxo_identifiers: tx.inputs.clone(),
+            },
+        )));
+        let response = context.message_bus.request(utxos_query_topic, query).await?;
+        match response.as_ref() {
+            Message::StateQueryResponse(StateQueryResponse::UTxOs(
+                UTxOStateQueryResponse::UTxOs(_),
+            )) => Ok(()),
+            Message::StateQueryResponse(StateQueryResponse::UTxOs(
+                UTxOStateQueryResponse::Error(e),
+            )) => bail!("Transaction {} can't spend its inputs: {e}", tx.id),
+            _ => bail!("Unexpected utxo-state response"),
+        }
+    }
+
+    /// Validate a submitted transaction and add it to the mempool
+    async fn handle_submit(
+        context: &Context<Message>,
+        utxos_query_topic: &str,
+        message: &Message,
+        mempool: &Mutex<Mempool>,
+    ) -> Result<TxHash> {
+        let Message::Command(Command::Transactions(TransactionsCommand::Submit { cbor, .. })) =
+            message
+        else {
+            bail!("Unexpected mempool request");
+        };
+        let tx = PendingTx::from_cbor(cbor)?;
+        Self::check_inputs(context, utxos_query_topic, &tx).await?;
+        mempool.lock().await.add(tx)
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        if !get_bool_flag(&config, DEFAULT_ENABLED) {
+            info!("Block producer is disabled in configuration");
+            return Ok(());
+        }
+
+        let pool_id = PoolId::from_bech32(&get_string_flag(&config, DEFAULT_POOL_ID))
+            .context("Block producer needs a bech32 'pool-id'")?;
+        let vrf_skey_file = get_string_flag(&config, DEFAULT_VRF_SKEY_FILE);
+        let vrf_key = leadership::read_vrf_signing_key(
+            &std::fs::read_to_string(&vrf_skey_file)
+                .with_context(|| format!("Failed to read VRF signing key {vrf_skey_file}"))?,
+        )
+        .with_context(|| format!("Invalid VRF signing key {vrf_skey_file}"))?;
+
+        let kes_skey_file = get_string_flag(&config, DEFAULT_KES_SKEY_FILE);
+        let kes_key = forging::read_kes_signing_key(
+            &std::fs::read_to_string(&kes_skey_file)
+                .with_context(|| format!("Failed to read KES signing key {kes_skey_file}"))?,
+        )
+        .with_context(|| format!("Invalid KES signing key {kes_skey_file}"))?;
+        let opcert_file = get_string_flag(&config, DEFAULT_OPCERT_FILE);
+        let (opcert, cold_vkey) = forging::read_operational_cert(
+            &std::fs::read_to_string(&opcert_file)
+                .with_context(|| format!("Failed to read operational certificate {opcert_file}"))?,
+        )
+        .with_context(|| format!("Invalid operational certificate {opcert_file}"))?;
+        info!("Forging blocks for pool {pool_id}");
+
+        let state = State::new(pool_id, vrf_key);
+        let forger = Forger::new(kes_key, opcert, cold_vkey, state.vrf_vkey())?;
+        if forger.pool_id() != pool_id {
+            bail!(
+                "Operational certificate {opcert_file} is for pool {}, not {pool_id}",
+                forger.pool_id()
+            );
+        }
+        let state = Arc::new(Mutex::new(state));
+        let forger = Arc::new(Mutex::new(forger));
+        let mempool = Arc::new(Mutex::new(Mempool::new(get_u64_flag(
+            &config,
+            DEFAULT_MAX_MEMPOOL_TXS,
+        ) as usize)));
+
+        let topics = Arc::new(PublishTopics {
+            consensus: get_string_flag(&config, DEFAULT_CONSENSUS_PUBLISH_TOPIC),
+            block: get_string_flag(&config, DEFAULT_BLOCK_PUBLISH_TOPIC),
+        });
+
+        let clock_tick_subscribe_topic =
+            get_string_flag(&config, DEFAULT_CLOCK_TICK_SUBSCRIBE_TOPIC);
+        info!("Creating subscriber on '{clock_tick_subscribe_topic}'");
+        let clock_tick_subscription = context.subscribe(&clock_tick_subscribe_topic).await?;
+
+        let block_wanted_subscribe_topic =
+            get_string_flag(&config, DEFAULT_BLOCK_WANTED_SUBSCRIBE_TOPIC);
+        info!("Creating subscriber on '{block_wanted_subscribe_topic}'");
+        let wants_subscription = context.subscribe(&block_wanted_subscribe_topic).await?;
+
+        let mut genesis = GenesisReader::new(&context, &config).await?;
+        let nonces = NonceReader::new(&context, &config).await?;
+        let spdd = SPDDReader::new(&context, &config).await?;
+        let params = ParamsReader::new(&context, &config).await?;
+        let blocks = BlockReader::new(&context, &config).await?;
+
+        let mempool_submit_topic = get_string_flag(&config, DEFAULT_MEMPOOL_SUBMIT_TOPIC);
+        info!("Handling transaction submissions on '{mempool_submit_topic}'");
+        let utxos_query_topic = Arc::new(get_string_flag(&config, DEFAULT_UTXOS_QUERY_TOPIC));
+        let submit_context = context.clone();
+        let submit_mempool = mempool.clone();
+        context.handle(&mempool_submit_topic, move |message| {
+            let context = submit_context.clone();
+            let utxos_query_topic = utxos_query_topic.clone();
+            let mempool = submit_mempool.clone();
+            async move {
+                let response =
+                    match Self::handle_submit(&context, &utxos_query_topic, &message, &mempool)
+                        .await
+                    {
+                        Ok(id) => TransactionsCommandResponse::Submitted { id },
+                        Err(e) => TransactionsCommandResponse::Error(e.to_string()),
+                    };
+                Arc::new(Message::CommandResponse(CommandResponse::Transactions(
+                    response,
+                )))
+            }
+        });
+
+        let run_state = state.clone();
+        context.run(async move {
+            match genesis.read_with_rollbacks().await {
+                Ok(RollbackWrapper::Normal((_, message))) => {
+                    run_state.lock().await.handle_genesis(&message.values);
+                }
+                Ok(_) => error!("Unexpected rollback before genesis"),
+                Err(e) => error!("Failed to read genesis values: {e}"),
+            }
+        });
+
+        let run_state = state.clone();
+        context.run(async move {
+            Self::run_nonces(run_state, nonces)
+                .await
+                .unwrap_or_else(|e| error!("Epoch nonce tracking failed: {e}"));
+        });
+
+        let run_state = state.clone();
+        context.run(async move {
+            Self::run_spdd(run_state, spdd)
+                .await
+                .unwrap_or_else(|e| error!("Stake distribution tracking failed: {e}"));
+        });
+
+        let run_forger = forger.clone();
+        context.run(async move {
+            Self::run_params(run_forger, params)
+                .await
+                .unwrap_or_else(|e| error!("Protocol parameter tracking failed: {e}"));
+        });
+
+        let run_forger = forger.clone();
+        let run_mempool = mempool.clone();
+        context.run(async move {
+            Self::run_blocks(run_forger, run_mempool, blocks)
+                .await
+                .unwrap_or_else(|e| error!("Chain tracking failed: {e}"));
+        });
+
+        let run_context = context.clone();
+        let run_topics = topics.clone();
+        let run_forger = forger.clone();
+        context.run(async move {
+            Self::run_wants(run_context, run_topics, run_forger, wants_subscription)
+                .await
+                .unwrap_or_else(|e| error!("Serving forged blocks failed: {e}"));
+        });
+
+        let run_context = context.clone();
+        context.run(async move {
+            Self::run_clock(
+                run_context,
+                topics,
+                state,
+                forger,
+                mempool,
+                clock_tick_subscription,
+            )
+            .await
+            .unwrap_or_else(|e| error!("Leadership checks failed: {e}"));
+        });
+
+        Ok(())
+    }
+}
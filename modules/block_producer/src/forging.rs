@@ -0,0 +1,499 @@
+//! Forging blocks for the slots we lead
+
+use acropolis_common::{
+    crypto::{keyhash_224, keyhash_256},
+    genesis_values::GenesisValues,
+    messages::RawBlockMessage,
+    protocol_params::{ProtocolParams, ProtocolVersion},
+    BlockHash, BlockInfo, BlockIntent, BlockStatus, Era, Point, PoolId, Slot, TxHash,
+};
+use anyhow::{anyhow, bail, Result};
+use kes_summed_ed25519::{
+    kes::Sum6Kes,
+    traits::{KesSig, KesSk},
+};
+use minicbor::{Decoder, Encoder};
+
+use crate::{leadership::LeaderProof, mempool::PendingTx};
+
+/// Text envelope type of a cardano-cli KES signing key
+const KES_SIGNING_KEY_TYPE: &str = "KesSigningKey_ed25519_kes_2^6";
+
+/// Text envelope type of a cardano-cli operational certificate
+const OPERATIONAL_CERT_TYPE: &str = "NodeOperationalCertificate";
+
+/// Size of a Sum6 KES signing key, in bytes
+const KES_SIGNING_KEY_SIZE: usize = 608;
+
+/// Era tags of the network block wrapper
+const BABBAGE_TAG: u64 = 6;
+const CONWAY_TAG: u64 = 7;
+
+/// Operational certificate, delegating the pool's cold key to a hot KES key
+#[derive(Debug, Clone)]
+pub struct OperationalCert {
+    pub hot_vkey: Vec<u8>,
+    pub sequence_number: u64,
+    pub kes_period: u64,
+    pub sigma: Vec<u8>,
+}
+
+/// Read a KES secret key from a cardano-cli `kes.skey` file
+pub fn read_kes_signing_key(contents: &str) -> Result<Vec<u8>> {
+    let cbor = crate::leadership::read_text_envelope(contents, KES_SIGNING_KEY_TYPE)?;
+    let mut key = Decoder::new(&cbor).bytes()?.to_vec();
+    if key.len() != KES_SIGNING_KEY_SIZE {
+        bail!("KES signing key is not {KES_SIGNING_KEY_SIZE} bytes");
+    }
+
+    // The in-memory form ends with the period the key has evolved to
+    key.extend([0u8; 4]);
+    Ok(key)
+}
+
+/// Read an operational certificate and the cold verification key it is issued by
+/// from a cardano-cli `node.cert` file
+pub fn read_operational_cert(contents: &str) -> Result<(OperationalCert, Vec<u8>)> {
+    let cbor = crate::leadership::read_text_envelope(contents, OPERATIONAL_CERT_TYPE)?;
+    let mut decoder = Decoder::new(&cbor);
+    if decoder.array()? != Some(2) || decoder.array()? != Some(4) {
+        bail!(
+            "Operational certificate is not a [[hot key, counter, period, sigma], cold key] array"
+        );
+    }
+    let opcert = OperationalCert {
+        hot_vkey: decoder.bytes()?.to_vec(),
+        sequence_number: decoder.u64()?,
+        kes_period: decoder.u64()?,
+        sigma: decoder.bytes()?.to_vec(),
+    };
+    let cold_vkey = decoder.bytes()?.to_vec();
+    Ok((opcert, cold_vkey))
+}
+
+/// The block we would build on
+#[derive(Debug, Clone)]
+struct Tip {
+    hash: BlockHash,
+    number: u64,
+    slot: Slot,
+
+    /// Whether the chain is known to go no further than this block
+    synced: bool,
+}
+
+/// A block we have forged, ready to be offered to consensus
+#[derive(Debug, Clone)]
+pub struct ForgedBlock {
+    pub info: BlockInfo,
+    pub parent_hash: BlockHash,
+    pub block: RawBlockMessage,
+    pub vrf_output: Vec<u8>,
+    pub tx_ids: Vec<TxHash>,
+}
+
+/// Builds and signs blocks on top of the current tip
+pub struct Forger {
+    /// KES signing key, evolved in place. It lives for the life of the process.
+    kes_key: Sum6Kes<'static>,
+    opcert: OperationalCert,
+    cold_vkey: Vec<u8>,
+    vrf_vkey: Vec<u8>,
+
+    tip: Option<Tip>,
+    params: Option<ProtocolParams>,
+
+    /// Blocks we have forged which are not yet behind the tip, to serve when
+    /// consensus wants them
+    forged: Vec<ForgedBlock>,
+}
+
+impl Forger {
+    pub fn new(
+        kes_key: Vec<u8>,
+        opcert: OperationalCert,
+        cold_vkey: Vec<u8>,
+        vrf_vkey: Vec<u8>,
+    ) -> Result<Self> {
+        let kes_key = Sum6Kes::from_bytes(kes_key.leak())
+            .map_err(|e| anyhow!("Invalid KES signing key: {e}"))?;
+        if kes_key.to_pk().as_bytes() != opcert.hot_vkey.as_slice() {
+            bail!("KES signing key does not match the operational certificate");
+        }
+        Ok(Self {
+            kes_key,
+            opcert,
+            cold_vkey,
+            vrf_vkey,
+            tip: None,
+            params: None,
+            forged: Vec::new(),
+        })
+    }
+
+    /// Pool the operational certificate is issued by
+    pub fn pool_id(&self) -> PoolId {
+        keyhash_224(&self.cold_vkey).into()
+    }
+
+    /// Handle a block being adopted, which we build on from now on
+    pub fn handle_block(&mut self, info: &BlockInfo) {
+        self.tip = Some(Tip {
+            hash: info.hash,
+            number: info.number,
+            slot: info.slot,
+            synced: info.tip_slot.is_some_and(|tip_slot| tip_slot <= info.slot),
+        });
+        self.forged.retain(|forged| forged.info.slot > info.slot);
+    }
+
+    /// Handle a rollback to `point` - `info` is the block which is next to come
+    pub fn handle_rollback(&mut self, info: &BlockInfo, point: &Point) {
+        self.tip = match point {
+            Point::Origin => None,
+            Point::Specific { hash, slot } => Some(Tip {
+                hash: *hash,
+                number: info.number.saturating_sub(1),
+                slot: *slot,
+                synced: false,
+            }),
+        };
+    }
+
+    pub fn handle_params(&mut self, params: &ProtocolParams) {
+        self.params = Some(params.clone());
+    }
+
+    /// Largest block body the protocol parameters allow, if they are known
+    pub fn max_block_body_size(&self) -> Option<usize> {
+        self.params
+            .as_ref()
+            .and_then(|params| params.shelley.as_ref())
+            .map(|shelley| shelley.protocol_params.max_block_body_size as usize)
+    }
+
+    /// A block we forged, if it is still ahead of the tip
+    pub fn forged_block(&self, hash: &BlockHash) -> Option<&ForgedBlock> {
+        self.forged.iter().find(|forged| forged.info.hash == *hash)
+    }
+
+    /// Sign with the KES key evolved to `evolution` periods after the one in the
+    /// operational certificate
+    fn kes_sign(&mut self, evolution: u32, message: &[u8]) -> Result<[u8; 448]> {
+        if self.kes_key.get_period() > evolution {
+            bail!(
+                "KES signing key has evolved to period {}, past {evolution}",
+                self.kes_key.get_period()
+            );
+        }
+        while self.kes_key.get_period() < evolution {
+            self.kes_key.update().map_err(|e| anyhow!("Failed to evolve KES signing key: {e}"))?;
+        }
+        Ok(self.kes_key.sign(message).to_bytes())
+    }
+
+    /// Forge a block for a slot we lead, containing the given transactions
+    pub fn forge(
+        &mut self,
+        proof: &LeaderProof,
+        txs: &[PendingTx],
+        genesis: &GenesisValues,
+    ) -> Result<ForgedBlock> {
+        let Some(tip) = self.tip.clone() else {
+            bail!("No block to build on");
+        };
+        if !tip.synced {
+            bail!("Still catching up with the chain, at slot {}", tip.slot);
+        }
+        if tip.slot >= proof.slot {
+            bail!("Tip at slot {} is not before slot {}", tip.slot, proof.slot);
+        }
+        let Some(params) = self.params.as_ref() else {
+            bail!("Protocol parameters are not known yet");
+        };
+        let era = params.era();
+        let era_tag = match era {
+            Era::Babbage => BABBAGE_TAG,
+            Era::Conway => CONWAY_TAG,
+            _ => bail!("Forging is not supported in the {era} era"),
+        };
+        let ProtocolVersion { major, minor } =
+            params.protocol_version().ok_or_else(|| anyhow!("No protocol version"))?;
+
+        let kes_period = proof.slot / genesis.slots_per_kes_period;
+        let evolution = kes_period
+            .checked_sub(self.opcert.kes_period)
+            .filter(|evolution| *evolution < genesis.max_kes_evolutions)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Operational certificate from KES period {} is not valid in period {kes_period}",
+                    self.opcert.kes_period
+                )
+            })?;
+
+        // Body - transaction bodies, witness sets, auxiliary data and invalid
+        // transactions, each hashed separately
+        let mut bodies = Encoder::new(Vec::new());
+        let mut witnesses = Encoder::new(Vec::new());
+        let mut auxiliary_data = Encoder::new(Vec::new());
+        let mut invalid = Encoder::new(Vec::new());
+        bodies.array(txs.len() as u64)?;
+        witnesses.array(txs.len() as u64)?;
+        auxiliary_data.map(txs.iter().filter(|tx| tx.auxiliary_data.is_some()).count() as u64)?;
+        invalid.array(txs.iter().filter(|tx| !tx.is_valid).count() as u64)?;
+        for (index, tx) in txs.iter().enumerate() {
+            bodies.writer_mut().extend(&tx.body);
+            witnesses.writer_mut().extend(&tx.witnesses);
+            if let Some(aux) = &tx.auxiliary_data {
+                auxiliary_data.u64(index as u64)?;
+                auxiliary_data.writer_mut().extend(aux);
+            }
+            if !tx.is_valid {
+                invalid.u64(index as u64)?;
+            }
+        }
+        let parts = [bodies, witnesses, auxiliary_data, invalid].map(Encoder::into_writer);
+        let body_size = parts.iter().map(Vec::len).sum::<usize>() as u64;
+        let body_hash = keyhash_256(
+            &parts.iter().flat_map(|part| keyhash_256(part).to_vec()).collect::<Vec<_>>(),
+        );
+
+        let mut header_body = Encoder::new(Vec::new());
+        header_body
+            .array(10)?
+            .u64(tip.number + 1)?
+            .u64(proof.slot)?
+            .bytes(tip.hash.as_ref())?
+            .bytes(&self.cold_vkey)?
+            .bytes(&self.vrf_vkey)?
+            .array(2)?
+            .bytes(&proof.vrf_output)?
+            .bytes(&proof.vrf_proof)?
+            .u64(body_size)?
+            .bytes(body_hash.as_ref())?
+            .array(4)?
+            .bytes(&self.opcert.hot_vkey)?
+            .u64(self.opcert.sequence_number)?
+            .u64(self.opcert.kes_period)?
+            .bytes(&self.opcert.sigma)?
+            .array(2)?
+            .u64(major)?
+            .u64(minor)?;
+        let header_body = header_body.into_writer();
+        let signature = self.kes_sign(evolution as u32, &header_body)?;
+
+        let mut header = Encoder::new(Vec::new());
+        header.array(2)?;
+        header.writer_mut().extend(&header_body);
+        header.bytes(&signature)?;
+        let header = header.into_writer();
+
+        let mut block = Encoder::new(Vec::new());
+        block.array(2)?.u64(era_tag)?.array(5)?;
+        block.writer_mut().extend(&header);
+        for part in &parts {
+            block.writer_mut().extend(part);
+        }
+
+        let (epoch, epoch_slot) = genesis.slot_to_epoch(proof.slot);
+        let info = BlockInfo {
+            status: BlockStatus::Volatile,
+            intent: BlockIntent::ValidateAndApply,
+            slot: proof.slot,
+            number: tip.number + 1,
+            hash: keyhash_256(&header),
+            epoch,
+            epoch_slot,
+            new_epoch: genesis.slot_to_epoch(tip.slot).0 != epoch,
+            is_new_era: false,
+            tip_slot: Some(proof.slot),
+            timestamp: genesis.slot_to_timestamp(proof.slot),
+            era,
+        };
+
+        let forged = ForgedBlock {
+            info,
+            parent_hash: tip.hash,
+            block: RawBlockMessage {
+                header,
+                body: block.into_writer(),
+            },
+            vrf_output: proof.vrf_output.to_vec(),
+            tx_ids: txs.iter().map(|tx| tx.id).collect(),
+        };
+        self.forged.push(forged.clone());
+        Ok(forged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::protocol_params::ShelleyParams;
+    use acropolis_test_utils::mainnet_genesis_values;
+    use kes_summed_ed25519::{kes::Sum6KesSig, PublicKey};
+    use pallas::ledger::traverse::{MultiEraBlock, MultiEraHeader};
+
+    /// Sum6 KES signing key, and its public key
+    const KES_KEY: &str = "68b77b6e61925be0499d1445fd9210cec5bdfd5dd92662802eb2720ff70bc68fd89\
+        64580ff18bd2b232eb716dfbbeef82e2844b466ddd5dacaad9f15d3c753b3483541\
+        41e973d039b1147c48e71e5b7cadc6deb28c86e4ae4fc26e8bbe1695c3374d4eb10\
+        94a7a698722894301546466c750947778b18ac3270397efd2eced4d25ced55d2bd2\
+        c09e7c0fa7b849d41787ca11defc91609d930a9870881a56a587bff20b2c5c59f63\
+        ccb008be495917da3fcae536d05401b6771bb1f9356f031b3ddadbffbc426a9a23e\
+        34274b187f7e93892e990644f6273772a02d3e38bee7459ed6a9bb5760fe012e47a\
+        2e75880125e7fb072b2b7a626a5375e2039d8d748cb8ad4dd02697250d3155eee39\
+        308ecc2925405a8c15e1cbe556cc4315d43ee5101003639bcb33bd6e27da3885888\
+        d7cca20b05cadbaa53941ef5282cde8f377c3bd0bf732cfac6b5d4d5597a1f72d81\
+        bc0d8af634a4c760b309fe8959bbde666ff10310377b313860bd52d56fd7cb14963\
+        3beb1eb2e0076111df61e570a042f7cebae74a8de298a6f114938946230db42651e\
+        a4eddf5df2d7d2f3016464073da8a9dc715817b43586a61874e576da7b47a2bb6c2\
+        e19d4cbd5b1b39a24427e89b812cce6d30e0506e207f1eaab313c45a236068ea319\
+        958474237a5ffe02736e1c51c02a05999816c9253a557f09375c83acf5d7250f3bb\
+        c638e10c58fb274e2002eed841ecef6a9cbc57c3157a7c3cf47e66b1741e8173b66\
+        76ac973bc9715027a3225087cabad45407b891416330485891dc9a3875488a26428\
+        d20d581b629a8f4f42e3aa00cbcaae6c8e2b8f3fe033b874d1de6a3f8c321c92b77\
+        643f00d28e";
+    const KES_VKEY: &str = "2e5823037de29647e495b97d9dd7bf739f7ebc11d3701c8d0720f55618e1b292";
+
+    const OPCERT_KES_PERIOD: u64 = 100;
+    const COLD_VKEY: [u8; 32] = [3; 32];
+
+    fn envelope(kind: &str, cbor: &[u8]) -> String {
+        format!(
+            r#"{{"type": "{kind}", "description": "", "cborHex": "{}"}}"#,
+            hex::encode(cbor)
+        )
+    }
+
+    fn kes_key_envelope() -> String {
+        let mut cbor = Encoder::new(Vec::new());
+        cbor.bytes(&hex::decode(KES_KEY).unwrap()).unwrap();
+        envelope(KES_SIGNING_KEY_TYPE, &cbor.into_writer())
+    }
+
+    fn opcert_envelope() -> String {
+        let mut cbor = Encoder::new(Vec::new());
+        cbor.array(2)
+            .unwrap()
+            .array(4)
+            .unwrap()
+            .bytes(&hex::decode(KES_VKEY).unwrap())
+            .unwrap()
+            .u64(1)
+            .unwrap()
+            .u64(OPCERT_KES_PERIOD)
+            .unwrap()
+            .bytes(&[0; 64])
+            .unwrap()
+            .bytes(&COLD_VKEY)
+            .unwrap();
+        envelope(OPERATIONAL_CERT_TYPE, &cbor.into_writer())
+    }
+
+    /// A Conway forger building on block 10 at slot `tip_slot`
+    fn forger(tip_slot: Slot) -> Forger {
+        let kes_key = read_kes_signing_key(&kes_key_envelope()).unwrap();
+        let (opcert, cold_vkey) = read_operational_cert(&opcert_envelope()).unwrap();
+        let mut forger = Forger::new(kes_key, opcert, cold_vkey, vec![4; 32]).unwrap();
+
+        let mut shelley = ShelleyParams::default();
+        shelley.protocol_params.protocol_version = ProtocolVersion {
+            major: 10,
+            minor: 0,
+        };
+        shelley.protocol_params.max_block_body_size = 90112;
+        forger.handle_params(&ProtocolParams {
+            shelley: Some(shelley),
+            ..ProtocolParams::default()
+        });
+        forger.handle_block(&BlockInfo {
+            status: BlockStatus::Volatile,
+            intent: BlockIntent::ValidateAndApply,
+            slot: tip_slot,
+            number: 10,
+            hash: BlockHash::new([9; 32]),
+            epoch: 0,
+            epoch_slot: 0,
+            new_epoch: false,
+            is_new_era: false,
+            tip_slot: Some(tip_slot),
+            timestamp: 0,
+            era: Era::Conway,
+        });
+        forger
+    }
+
+    fn proof(slot: Slot) -> LeaderProof {
+        LeaderProof {
+            slot,
+            epoch: 0,
+            vrf_proof: [5; 80],
+            vrf_output: [6; 64],
+        }
+    }
+
+    #[test]
+    fn forged_blocks_decode_and_verify() {
+        let genesis = mainnet_genesis_values();
+        let evolution = 2;
+        let slot = (OPCERT_KES_PERIOD + evolution) * genesis.slots_per_kes_period + 5;
+        let mut forger = forger(slot - 20);
+        assert_eq!(forger.pool_id(), PoolId::from(keyhash_224(&COLD_VKEY)));
+
+        let tx = PendingTx::from_cbor(&[
+            0x84, 0xa3, 0x00, 0x80, 0x01, 0x80, 0x02, 0x01, 0xa0, 0xf5, 0xf6,
+        ])
+        .unwrap();
+        let forged = forger.forge(&proof(slot), &[tx.clone()], &genesis).unwrap();
+        assert_eq!(forged.info.number, 11);
+        assert_eq!(forged.parent_hash, BlockHash::new([9; 32]));
+        assert_eq!(forged.tx_ids, vec![tx.id]);
+        assert!(forger.forged_block(&forged.info.hash).is_some());
+
+        let block = MultiEraBlock::decode(&forged.block.body).unwrap();
+        assert_eq!(block.number(), 11);
+        assert_eq!(block.slot(), slot);
+        assert_eq!(*block.hash(), *forged.info.hash);
+        assert_eq!(*block.header().previous_hash().unwrap(), [9; 32]);
+        let txs = block.txs();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(*txs[0].hash(), *tx.id);
+
+        let header = MultiEraHeader::decode(Era::Conway as u8, None, &forged.block.header).unwrap();
+        assert_eq!(*header.hash(), *forged.info.hash);
+        let MultiEraHeader::BabbageCompatible(babbage) = &header else {
+            panic!("Not a Babbage header");
+        };
+        let signature = Sum6KesSig::from_bytes(&babbage.body_signature).unwrap();
+        let public_key = PublicKey::from_bytes(&hex::decode(KES_VKEY).unwrap()).unwrap();
+        signature
+            .verify(
+                evolution as u32,
+                &public_key,
+                header.header_body_cbor().unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn refuses_slots_outside_the_operational_certificate() {
+        let genesis = mainnet_genesis_values();
+        let before = OPCERT_KES_PERIOD * genesis.slots_per_kes_period - 1;
+        let after = (OPCERT_KES_PERIOD + genesis.max_kes_evolutions) * genesis.slots_per_kes_period;
+        assert!(forger(before - 20).forge(&proof(before), &[], &genesis).is_err());
+        assert!(forger(after - 20).forge(&proof(after), &[], &genesis).is_err());
+    }
+
+    #[test]
+    fn refuses_slots_not_after_a_synced_tip() {
+        let genesis = mainnet_genesis_values();
+        let slot = OPCERT_KES_PERIOD * genesis.slots_per_kes_period;
+        assert!(forger(slot).forge(&proof(slot), &[], &genesis).is_err());
+
+        // A peer's chain goes further than our tip
+        let mut forger = forger(slot - 20);
+        forger.tip.as_mut().unwrap().synced = false;
+        assert!(forger.forge(&proof(slot), &[], &genesis).is_err());
+    }
+}
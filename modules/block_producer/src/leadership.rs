@@ -0,0 +1,330 @@
+//! Slot leadership checks for the pool we produce blocks for
+
+use std::collections::BTreeMap;
+
+use acropolis_common::{
+    genesis_values::GenesisValues, protocol_params::Nonce, rational_number::RationalNumber,
+    spdd_chunks::SPDDSink, DelegatedStake, PoolId, Slot,
+};
+use acropolis_module_block_vrf_validator::ouroboros::{vrf, vrf_validation};
+use anyhow::{bail, Result};
+use pallas::ledger::primitives::babbage::{derive_tagged_vrf_output, VrfDerivation};
+use serde::Deserialize;
+
+/// Text envelope type of a cardano-cli VRF signing key
+const VRF_SIGNING_KEY_TYPE: &str = "VrfSigningKey_PraosVRF";
+
+/// Epochs of nonces and stake snapshots to keep
+const EPOCHS_TO_KEEP: usize = 3;
+
+/// Key file in the cardano-cli text envelope format
+#[derive(Deserialize)]
+struct TextEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(rename = "cborHex")]
+    cbor_hex: String,
+}
+
+/// CBOR held in a cardano-cli text envelope of the given type
+pub fn read_text_envelope(contents: &str, kind: &str) -> Result<Vec<u8>> {
+    let envelope: TextEnvelope = serde_json::from_str(contents)?;
+    if envelope.kind != kind {
+        bail!("Expected a {kind} file, found {}", envelope.kind);
+    }
+    Ok(hex::decode(&envelope.cbor_hex)?)
+}
+
+/// Read a VRF secret key from a cardano-cli `vrf.skey` file
+pub fn read_vrf_signing_key(contents: &str) -> Result<vrf::SecretKey> {
+    // CBOR byte string of 64 bytes - the secret seed followed by the public key
+    let cbor = read_text_envelope(contents, VRF_SIGNING_KEY_TYPE)?;
+    let key = match cbor.as_slice() {
+        [0x58, 0x40, key @ ..] if key.len() == 64 => key,
+        _ => bail!("VRF signing key is not a 64 byte CBOR byte string"),
+    };
+    let (seed, public_key) = key.split_at(vrf::SecretKey::SIZE);
+    let secret_key = vrf::SecretKey::try_from(seed)?;
+    if secret_key.public_key().as_ref() != public_key {
+        bail!("VRF signing key does not match its public key");
+    }
+    Ok(secret_key)
+}
+
+/// Proof that we lead a slot, to go in the header of the block we forge for it
+#[derive(Debug, Clone)]
+pub struct LeaderProof {
+    pub slot: Slot,
+    pub epoch: u64,
+    pub vrf_proof: [u8; vrf::Proof::SIZE],
+    pub vrf_output: [u8; vrf::Proof::HASH_SIZE],
+}
+
+/// Our stake and the total, taken in from the SPDD a chunk at a time
+pub struct OurStake {
+    pool_id: PoolId,
+    epoch: u64,
+    ours: u64,
+    total: u64,
+}
+
+impl SPDDSink for OurStake {
+    fn start(&mut self, epoch: u64, _pools: u64, total_active: u64) {
+        self.epoch = epoch;
+        self.ours = 0;
+        self.total = total_active;
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        if let Some((_, stake)) = spos.iter().find(|(pool_id, _)| *pool_id == self.pool_id) {
+            self.ours = stake.active;
+        }
+    }
+}
+
+/// Leadership state for a single pool
+pub struct State {
+    pool_id: PoolId,
+    vrf_key: vrf::SecretKey,
+
+    genesis: Option<GenesisValues>,
+
+    /// Active nonces, keyed by the epoch they apply to
+    nonces: BTreeMap<u64, Nonce>,
+
+    /// Our active stake and the total active stake, keyed by the epoch the
+    /// snapshot was taken at the end of
+    stakes: BTreeMap<u64, (u64, u64)>,
+
+    /// Last slot we checked, so no slot is checked twice
+    last_checked_slot: Option<Slot>,
+}
+
+impl State {
+    pub fn new(pool_id: PoolId, vrf_key: vrf::SecretKey) -> Self {
+        Self {
+            pool_id,
+            vrf_key,
+            genesis: None,
+            nonces: BTreeMap::new(),
+            stakes: BTreeMap::new(),
+            last_checked_slot: None,
+        }
+    }
+
+    /// Our VRF verification key, for the headers of blocks we forge
+    pub fn vrf_vkey(&self) -> Vec<u8> {
+        self.vrf_key.public_key().as_ref().to_vec()
+    }
+
+    pub fn genesis(&self) -> Option<&GenesisValues> {
+        self.genesis.as_ref()
+    }
+
+    pub fn handle_genesis(&mut self, genesis: &GenesisValues) {
+        self.genesis = Some(genesis.clone());
+    }
+
+    /// Handle the active nonce published at the start of an epoch
+    pub fn handle_epoch_nonce(&mut self, epoch: u64, nonce: &Option<Nonce>) {
+        if let Some(nonce) = nonce {
+            self.nonces.insert(epoch, nonce.clone());
+            while self.nonces.len() > EPOCHS_TO_KEEP {
+                self.nonces.pop_first();
+            }
+        }
+    }
+
+    /// Somewhere to take in the next stake distribution
+    pub fn our_stake(&self) -> OurStake {
+        OurStake {
+            pool_id: self.pool_id,
+            epoch: 0,
+            ours: 0,
+            total: 0,
+        }
+    }
+
+    /// Handle the stake distribution published at the end of an epoch
+    pub fn handle_spdd(&mut self, spdd: &OurStake) {
+        self.stakes.insert(spdd.epoch, (spdd.ours, spdd.total));
+        while self.stakes.len() > EPOCHS_TO_KEEP {
+            self.stakes.pop_first();
+        }
+    }
+
+    /// Our relative stake for leader election in an epoch, which comes from the
+    /// snapshot taken at the end of the epoch before last
+    fn relative_stake(&self, epoch: u64) -> Option<RationalNumber> {
+        let (ours, total) = self.stakes.get(&epoch.checked_sub(2)?)?;
+        Some(RationalNumber::new(*ours, *total))
+    }
+
+    /// Check every slot up to and including `slot` which has not been checked yet,
+    /// returning proofs for the ones we lead
+    pub fn check_slots_to(&mut self, slot: Slot) -> Vec<LeaderProof> {
+        let from = match self.last_checked_slot {
+            Some(last) if last >= slot => return Vec::new(),
+            Some(last) => last + 1,
+            None => slot,
+        };
+        self.last_checked_slot = Some(slot);
+        (from..=slot).filter_map(|slot| self.check_slot(slot)).collect()
+    }
+
+    /// Check whether we lead a slot (Praos only). Returns None if we don't, or if
+    /// the nonce or stake for its epoch are not known yet.
+    pub fn check_slot(&self, slot: Slot) -> Option<LeaderProof> {
+        let genesis = self.genesis.as_ref()?;
+        let (epoch, _) = genesis.slot_to_epoch(slot);
+        let nonce = self.nonces.get(&epoch)?;
+        let relative_stake = self.relative_stake(epoch)?;
+
+        let input = vrf::VrfInput::mk_vrf_input(slot, nonce);
+        let proof = vrf::Proof::generate(&self.vrf_key, &input);
+        let vrf_output: [u8; vrf::Proof::HASH_SIZE] = (&proof).into();
+        let leader_value = derive_tagged_vrf_output(&vrf_output, VrfDerivation::Leader);
+
+        vrf_validation::validate_vrf_leader_value(
+            &self.pool_id,
+            &leader_value,
+            &relative_stake,
+            &genesis.active_slots_coeff,
+        )
+        .ok()?;
+
+        Some(LeaderProof {
+            slot,
+            epoch,
+            vrf_proof: (&proof).into(),
+            vrf_output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{
+        caryatid::ChunkAssembler, hash::Hash, messages::SPOStakeDistributionMessage,
+        spdd_chunks::SPDDAssembler,
+    };
+    use acropolis_test_utils::mainnet_genesis_values;
+
+    const SEED: [u8; 32] = [7; 32];
+    const EPOCH: u64 = 500;
+
+    fn pool_id(byte: u8) -> PoolId {
+        PoolId::new(Hash::new([byte; 28]))
+    }
+
+    fn key_envelope(kind: &str, seed: &[u8; 32]) -> String {
+        let public_key = vrf::SecretKey::from(seed).public_key();
+        let cbor_hex = format!(
+            "5840{}{}",
+            hex::encode(seed),
+            hex::encode(public_key.as_ref())
+        );
+        format!(r#"{{"type": "{kind}", "description": "", "cborHex": "{cbor_hex}"}}"#)
+    }
+
+    fn stake(active: u64) -> DelegatedStake {
+        DelegatedStake {
+            active,
+            active_delegators_count: 1,
+        }
+    }
+
+    fn state_with_stake(ours: u64, others: u64) -> State {
+        let mut state = State::new(pool_id(1), vrf::SecretKey::from(&SEED));
+        state.handle_genesis(&mainnet_genesis_values());
+        state.handle_epoch_nonce(EPOCH, &Some(Nonce::from_number(EPOCH)));
+        let mut spdd = state.our_stake();
+        SPDDAssembler::stream_whole(
+            &SPOStakeDistributionMessage {
+                epoch: EPOCH - 2,
+                spos: vec![(pool_id(1), stake(ours)), (pool_id(2), stake(others))],
+            },
+            &mut spdd,
+        );
+        state.handle_spdd(&spdd);
+        state
+    }
+
+    fn epoch_slots(state: &State) -> std::ops::Range<Slot> {
+        let first = state.genesis().unwrap().epoch_to_first_slot(EPOCH);
+        first..first + 1000
+    }
+
+    #[test]
+    fn reads_vrf_signing_key() {
+        let key = read_vrf_signing_key(&key_envelope(VRF_SIGNING_KEY_TYPE, &SEED)).unwrap();
+        assert_eq!(key.public_key(), vrf::SecretKey::from(&SEED).public_key());
+    }
+
+    #[test]
+    fn rejects_other_key_types() {
+        assert!(
+            read_vrf_signing_key(&key_envelope("KesSigningKey_ed25519_kes_2^6", &SEED)).is_err()
+        );
+    }
+
+    #[test]
+    fn leader_proofs_pass_block_validation() {
+        let state = state_with_stake(1, 0);
+        let nonce = Nonce::from_number(EPOCH);
+        let public_key = vrf::SecretKey::from(&SEED).public_key();
+
+        // With all of the stake we lead around one slot in twenty
+        let proofs: Vec<_> =
+            epoch_slots(&state).filter_map(|slot| state.check_slot(slot)).collect();
+        assert!(!proofs.is_empty());
+        assert!(proofs.len() < 200);
+
+        for proof in proofs {
+            let leader_value = derive_tagged_vrf_output(&proof.vrf_output, VrfDerivation::Leader);
+            vrf_validation::validate_praos_vrf_proof(
+                proof.slot,
+                &nonce,
+                &leader_value,
+                &public_key,
+                &proof.vrf_output,
+                &proof.vrf_proof,
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn pool_without_stake_never_leads() {
+        let state = state_with_stake(0, 1_000_000);
+        assert!(epoch_slots(&state).all(|slot| state.check_slot(slot).is_none()));
+    }
+
+    #[test]
+    fn no_leadership_without_epoch_data() {
+        let mut state = state_with_stake(1, 0);
+        let next_epoch_slot = state.genesis().unwrap().epoch_to_first_slot(EPOCH + 1);
+        assert!(
+            (next_epoch_slot..next_epoch_slot + 1000).all(|slot| state.check_slot(slot).is_none())
+        );
+
+        // A nonce alone is not enough - the stake snapshot is needed too
+        state.handle_epoch_nonce(EPOCH + 1, &Some(Nonce::from_number(EPOCH + 1)));
+        assert!(
+            (next_epoch_slot..next_epoch_slot + 1000).all(|slot| state.check_slot(slot).is_none())
+        );
+    }
+
+    #[test]
+    fn slots_are_checked_once() {
+        let mut state = state_with_stake(1, 0);
+        let slots = epoch_slots(&state);
+        let first_check = state.check_slots_to(slots.start);
+        let all: Vec<_> = slots.clone().filter_map(|slot| state.check_slot(slot)).collect();
+
+        let rest = state.check_slots_to(slots.end - 1);
+        assert_eq!(first_check.len() + rest.len(), all.len());
+        assert!(state.check_slots_to(slots.end - 1).is_empty());
+    }
+}
@@ -0,0 +1,385 @@
+//! Transactions waiting to go in a block we forge
+
+use std::collections::{HashSet, VecDeque};
+
+use acropolis_common::{crypto::keyhash_256, Slot, TxHash, UTxOIdentifier};
+use anyhow::{anyhow, bail, Result};
+use minicbor::{data::Type, Decoder};
+
+/// Transaction body keys
+const BODY_INPUTS: u64 = 0;
+const BODY_TTL: u64 = 3;
+const BODY_COLLATERAL: u64 = 13;
+
+/// A submitted transaction, split into the parts which go in different sections
+/// of a block body
+#[derive(Debug, Clone)]
+pub struct PendingTx {
+    pub id: TxHash,
+    pub body: Vec<u8>,
+    pub witnesses: Vec<u8>,
+    pub is_valid: bool,
+    pub auxiliary_data: Option<Vec<u8>>,
+
+    /// UTxOs spent - the collateral if the transaction is marked invalid
+    pub inputs: Vec<UTxOIdentifier>,
+
+    /// Slot the transaction is valid until, exclusive
+    pub ttl: Option<Slot>,
+}
+
+impl PendingTx {
+    /// Split a transaction in its Alonzo onwards CBOR form
+    /// `[body, witness set, is valid, auxiliary data / null]`
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self> {
+        let mut decoder = Decoder::new(cbor);
+        if decoder.array()? != Some(4) {
+            bail!("Transaction is not a 4 element array");
+        }
+        let body = Self::item(&mut decoder, cbor)?;
+        let witnesses = Self::item(&mut decoder, cbor)?;
+        let is_valid = decoder.bool()?;
+        let auxiliary_data = match decoder.datatype()? {
+            Type::Null => {
+                decoder.null()?;
+                None
+            }
+            _ => Some(Self::item(&mut decoder, cbor)?),
+        };
+        if decoder.position() != cbor.len() {
+            bail!("Trailing bytes after transaction");
+        }
+
+        let (inputs, collateral, ttl) = Self::body_fields(&body)?;
+        Ok(Self {
+            id: keyhash_256(&body),
+            body,
+            witnesses,
+            is_valid,
+            auxiliary_data,
+            inputs: if is_valid { inputs } else { collateral },
+            ttl,
+        })
+    }
+
+    /// Inputs, collateral inputs and TTL from a transaction body
+    fn body_fields(
+        body: &[u8],
+    ) -> Result<(Vec<UTxOIdentifier>, Vec<UTxOIdentifier>, Option<Slot>)> {
+        let mut decoder = Decoder::new(body);
+        let entries =
+            decoder.map()?.ok_or_else(|| anyhow!("Transaction body is an indefinite map"))?;
+        let (mut inputs, mut collateral, mut ttl) = (Vec::new(), Vec::new(), None);
+        for _ in 0..entries {
+            match decoder.u64()? {
+                BODY_INPUTS => inputs = Self::inputs(&mut decoder)?,
+                BODY_COLLATERAL => collateral = Self::inputs(&mut decoder)?,
+                BODY_TTL => ttl = Some(decoder.u64()?),
+                _ => decoder.skip()?,
+            }
+        }
+        Ok((inputs, collateral, ttl))
+    }
+
+    /// A set of inputs, with or without the set tag
+    fn inputs(decoder: &mut Decoder) -> Result<Vec<UTxOIdentifier>> {
+        if decoder.datatype()? == Type::Tag {
+            decoder.tag()?;
+        }
+        let count = decoder.array()?.ok_or_else(|| anyhow!("Inputs are an indefinite array"))?;
+        (0..count)
+            .map(|_| -> Result<UTxOIdentifier> {
+                decoder.array()?;
+                let tx_hash = TxHash::try_from(decoder.bytes()?)?;
+                Ok(UTxOIdentifier::new(tx_hash, decoder.u16()?))
+            })
+            .collect()
+    }
+
+    /// Whether the transaction can't go in a block after `slot`
+    pub fn is_expired(&self, slot: Slot) -> bool {
+        self.ttl.is_some_and(|ttl| ttl <= slot + 1)
+    }
+
+    /// Raw bytes of the next item
+    fn item(decoder: &mut Decoder, cbor: &[u8]) -> Result<Vec<u8>> {
+        let start = decoder.position();
+        decoder.skip()?;
+        Ok(cbor[start..decoder.position()].to_vec())
+    }
+
+    /// Bytes the transaction adds to a block body
+    pub fn size(&self) -> usize {
+        self.body.len()
+            + self.witnesses.len()
+            + self.auxiliary_data.as_ref().map_or(0, |aux| aux.len())
+    }
+}
+
+/// Bytes taken by the head of a CBOR item with argument `value`
+fn cbor_head_size(value: u64) -> usize {
+    match value {
+        0..24 => 1,
+        24..0x100 => 2,
+        0x100..0x1_0000 => 3,
+        0x1_0000..0x1_0000_0000 => 5,
+        _ => 9,
+    }
+}
+
+/// Size of a block body as transactions are added to it - the transactions
+/// themselves, plus the array and map heads of each section and the indices
+/// of the auxiliary data and invalid transactions
+#[derive(Default)]
+struct BodySize {
+    txs: u64,
+    auxiliary_data: u64,
+    invalid: u64,
+    items: usize,
+}
+
+impl BodySize {
+    fn add(&mut self, tx: &PendingTx) {
+        self.items += tx.size();
+        if tx.auxiliary_data.is_some() {
+            self.items += cbor_head_size(self.txs);
+            self.auxiliary_data += 1;
+        }
+        if !tx.is_valid {
+            self.items += cbor_head_size(self.txs);
+            self.invalid += 1;
+        }
+        self.txs += 1;
+    }
+
+    fn total(&self) -> usize {
+        self.items
+            + 2 * cbor_head_size(self.txs)
+            + cbor_head_size(self.auxiliary_data)
+            + cbor_head_size(self.invalid)
+    }
+}
+
+/// Submitted transactions, oldest first
+pub struct Mempool {
+    txs: VecDeque<PendingTx>,
+    ids: HashSet<TxHash>,
+
+    /// Inputs spent by the transactions, so no two spend the same UTxO
+    spent: HashSet<UTxOIdentifier>,
+
+    /// Slot of the latest adopted block
+    tip_slot: Option<Slot>,
+
+    max_txs: usize,
+}
+
+impl Mempool {
+    pub fn new(max_txs: usize) -> Self {
+        Self {
+            txs: VecDeque::new(),
+            ids: HashSet::new(),
+            spent: HashSet::new(),
+            tip_slot: None,
+            max_txs,
+        }
+    }
+
+    /// Add a transaction whose inputs are known to be unspent on chain,
+    /// returning its ID
+    pub fn add(&mut self, tx: PendingTx) -> Result<TxHash> {
+        let id = tx.id;
+        if self.ids.contains(&id) {
+            return Ok(id);
+        }
+        if self.txs.len() >= self.max_txs {
+            bail!("Mempool is full ({} transactions)", self.max_txs);
+        }
+        if let Some(slot) = self.tip_slot.filter(|slot| tx.is_expired(*slot)) {
+            bail!("Transaction {id} can't go in a block after slot {slot}");
+        }
+        if let Some(input) = tx.inputs.iter().find(|input| self.spent.contains(input)) {
+            bail!("Transaction {id} spends {input}, which a pending transaction already spends");
+        }
+        self.ids.insert(id);
+        self.spent.extend(tx.inputs.iter().copied());
+        self.txs.push_back(tx);
+        Ok(id)
+    }
+
+    /// The oldest transactions which fit in a block body of `max_size` bytes
+    pub fn select(&self, max_size: usize) -> Vec<PendingTx> {
+        let mut size = BodySize::default();
+        self.txs
+            .iter()
+            .take_while(|tx| {
+                size.add(tx);
+                size.total() <= max_size
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Follow an adopted block at `slot`, containing transactions `ids` which
+    /// spent `spent` - drop those transactions, and the ones which can no longer
+    /// go in a block because their inputs are spent or their TTL has passed
+    pub fn handle_block(
+        &mut self,
+        slot: Slot,
+        ids: &HashSet<TxHash>,
+        spent: &HashSet<UTxOIdentifier>,
+    ) {
+        self.tip_slot = Some(slot);
+        self.txs.retain(|tx| {
+            !ids.contains(&tx.id)
+                && !tx.is_expired(slot)
+                && !tx.inputs.iter().any(|input| spent.contains(input))
+        });
+        self.ids = self.txs.iter().map(|tx| tx.id).collect();
+        self.spent = self.txs.iter().flat_map(|tx| tx.inputs.iter().copied()).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicbor::{data::Tag, Encoder};
+
+    /// A transaction with an empty body of the given fee and no witnesses
+    fn tx(fee: u8) -> Vec<u8> {
+        vec![
+            0x84, 0xa3, 0x00, 0x80, 0x01, 0x80, 0x02, fee, 0xa0, 0xf5, 0xf6,
+        ]
+    }
+
+    /// A transaction spending output 0 of each of `inputs`, with a TTL if given
+    fn tx_spending(inputs: &[u8], ttl: Option<Slot>) -> Result<Vec<u8>> {
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.array(4)?.map(3 + u64::from(ttl.is_some()))?;
+        encoder.u64(BODY_INPUTS)?.tag(Tag::new(258))?.array(inputs.len() as u64)?;
+        for input in inputs {
+            encoder.array(2)?.bytes(&[*input; 32])?.u16(0)?;
+        }
+        encoder.u64(1)?.array(0)?.u64(2)?.u64(0)?;
+        if let Some(ttl) = ttl {
+            encoder.u64(BODY_TTL)?.u64(ttl)?;
+        }
+        encoder.map(0)?.bool(true)?.null()?;
+        Ok(encoder.into_writer())
+    }
+
+    fn add(mempool: &mut Mempool, cbor: &[u8]) -> Result<TxHash> {
+        mempool.add(PendingTx::from_cbor(cbor)?)
+    }
+
+    fn tx_ids(txs: &[PendingTx]) -> Vec<TxHash> {
+        txs.iter().map(|tx| tx.id).collect()
+    }
+
+    fn utxo(input: u8) -> UTxOIdentifier {
+        UTxOIdentifier::new(TxHash::from([input; 32]), 0)
+    }
+
+    #[test]
+    fn splits_transactions_into_their_parts() {
+        let tx = PendingTx::from_cbor(&tx(1)).unwrap();
+        assert_eq!(tx.body, vec![0xa3, 0x00, 0x80, 0x01, 0x80, 0x02, 0x01]);
+        assert_eq!(tx.witnesses, vec![0xa0]);
+        assert!(tx.is_valid);
+        assert_eq!(tx.auxiliary_data, None);
+        assert_eq!(tx.id, keyhash_256(&tx.body));
+        assert!(tx.inputs.is_empty());
+        assert_eq!(tx.ttl, None);
+    }
+
+    #[test]
+    fn reads_inputs_and_ttl_from_the_body() {
+        let tx = PendingTx::from_cbor(&tx_spending(&[1, 2], Some(100)).unwrap()).unwrap();
+        assert_eq!(tx.inputs, vec![utxo(1), utxo(2)]);
+        assert_eq!(tx.ttl, Some(100));
+        assert!(!tx.is_expired(98));
+        assert!(tx.is_expired(99));
+    }
+
+    #[test]
+    fn rejects_malformed_transactions() {
+        assert!(PendingTx::from_cbor(&[0x83, 0xa0, 0xa0, 0xf5]).is_err());
+        assert!(PendingTx::from_cbor(&[tx(1), vec![0x00]].concat()).is_err());
+    }
+
+    #[test]
+    fn selects_oldest_transactions_which_fit() {
+        let mut mempool = Mempool::new(10);
+        let ids: Vec<_> = (1..=3).map(|fee| add(&mut mempool, &tx(fee)).unwrap()).collect();
+        assert_eq!(add(&mut mempool, &tx(1)).unwrap(), ids[0]);
+        assert_eq!(mempool.select(usize::MAX).len(), 3);
+
+        // Two 8 byte transactions, and the heads of the four body sections
+        assert_eq!(tx_ids(&mempool.select(20)), ids[..2]);
+        assert_eq!(tx_ids(&mempool.select(19)), ids[..1]);
+
+        mempool.handle_block(1, &HashSet::from([ids[0]]), &HashSet::new());
+        assert_eq!(tx_ids(&mempool.select(usize::MAX)), ids[1..]);
+    }
+
+    #[test]
+    fn counts_auxiliary_data_and_invalid_indices_in_the_body_size() {
+        let mut mempool = Mempool::new(10);
+        // Marked invalid, with empty auxiliary data
+        add(
+            &mut mempool,
+            &[
+                0x84, 0xa3, 0x00, 0x80, 0x01, 0x80, 0x02, 0x01, 0xa0, 0xf4, 0xa0,
+            ],
+        )
+        .unwrap();
+
+        // [body] [witnesses] {0: aux} [0]
+        assert_eq!(mempool.select(15).len(), 1);
+        assert!(mempool.select(14).is_empty());
+    }
+
+    #[test]
+    fn refuses_transactions_when_full() {
+        let mut mempool = Mempool::new(1);
+        add(&mut mempool, &tx(1)).unwrap();
+        assert!(add(&mut mempool, &tx(2)).is_err());
+    }
+
+    #[test]
+    fn rejects_expired_and_conflicting_transactions() {
+        let mut mempool = Mempool::new(10);
+        mempool.handle_block(50, &HashSet::new(), &HashSet::new());
+        assert!(add(&mut mempool, &tx_spending(&[1], Some(51)).unwrap()).is_err());
+        add(&mut mempool, &tx_spending(&[1], Some(52)).unwrap()).unwrap();
+        assert!(add(&mut mempool, &tx_spending(&[1, 2], None).unwrap()).is_err());
+        add(&mut mempool, &tx_spending(&[2], None).unwrap()).unwrap();
+        assert_eq!(mempool.select(usize::MAX).len(), 2);
+    }
+
+    #[test]
+    fn evicts_transactions_whose_inputs_an_adopted_block_spent() {
+        let mut mempool = Mempool::new(10);
+        add(&mut mempool, &tx_spending(&[1], None).unwrap()).unwrap();
+        let kept = add(&mut mempool, &tx_spending(&[2], None).unwrap()).unwrap();
+
+        mempool.handle_block(10, &HashSet::new(), &HashSet::from([utxo(1)]));
+        assert_eq!(tx_ids(&mempool.select(usize::MAX)), vec![kept]);
+
+        // Its input is free to spend again
+        add(&mut mempool, &tx_spending(&[1, 3], None).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn evicts_transactions_whose_ttl_has_passed() {
+        let mut mempool = Mempool::new(10);
+        add(&mut mempool, &tx_spending(&[1], Some(20)).unwrap()).unwrap();
+        let kept = add(&mut mempool, &tx_spending(&[2], Some(30)).unwrap()).unwrap();
+
+        mempool.handle_block(18, &HashSet::new(), &HashSet::new());
+        assert_eq!(mempool.select(usize::MAX).len(), 2);
+
+        mempool.handle_block(19, &HashSet::new(), &HashSet::new());
+        assert_eq!(tx_ids(&mempool.select(usize::MAX)), vec![kept]);
+    }
+}
@@ -2,18 +2,21 @@
 //! Unpacks block bodies into transactions
 
 use acropolis_common::{
-    configuration::get_string_flag,
-    messages::{CardanoMessage, Message, RawTxsMessage, StateTransitionMessage},
+    configuration::ModuleConfig,
+    messages::{
+        BlockHeaderMessage, CardanoMessage, Message, RawTxsMessage, StateTransitionMessage,
+    },
 };
 use anyhow::Result;
+use bytes::Bytes;
 use caryatid_sdk::{module, Context};
 use config::Config;
 use pallas::ledger::traverse::MultiEraBlock;
 use std::sync::Arc;
 use tracing::{debug, error, info, info_span, Instrument};
 
-const DEFAULT_SUBSCRIBE_TOPIC: (&str, &str) = ("subscribe-topic", "cardano.block.proposed");
-const DEFAULT_PUBLISH_TOPIC: (&str, &str) = ("publish-topic", "cardano.txs");
+mod configuration;
+pub use configuration::BlockUnpackerConfig;
 
 /// Block unpacker module
 /// Parameterised by the outer message enum used on the bus
@@ -29,11 +32,16 @@ impl BlockUnpacker {
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
         // Subscribe for block body messages
         // Get configuration
-        let subscribe_topic = get_string_flag(&config, DEFAULT_SUBSCRIBE_TOPIC);
+        let BlockUnpackerConfig {
+            subscribe_topic,
+            publish_topic,
+            publish_bodies,
+        } = BlockUnpackerConfig::load(&config)?;
         info!("Creating subscriber on '{subscribe_topic}'");
-
-        let publish_topic = get_string_flag(&config, DEFAULT_PUBLISH_TOPIC);
         info!("Publishing on '{publish_topic}'");
+        if !publish_bodies {
+            info!("Publishing block headers only");
+        }
 
         let mut subscription = context.subscribe(&subscribe_topic).await?;
 
@@ -58,15 +66,26 @@ impl BlockUnpacker {
                                         );
                                     }
 
-                                    // Encode the Tx into hex, and take ownership
-                                    let txs: Vec<_> =
-                                        block.txs().into_iter().map(|tx| tx.encode()).collect();
-
-                                    let tx_message = RawTxsMessage { txs };
-                                    let message_enum = Message::Cardano((
-                                        block_info.clone(),
-                                        CardanoMessage::ReceivedTxs(tx_message),
-                                    ));
+                                    let cardano_message = if publish_bodies {
+                                        // Encode each Tx once and hand the buffer over without
+                                        // copying - a Shelley+ body splits bodies, witnesses and
+                                        // metadata into separate arrays, so a whole tx can't
+                                        // be sliced out of the original block
+                                        let txs: Vec<_> = block
+                                            .txs()
+                                            .into_iter()
+                                            .map(|tx| Bytes::from(tx.encode()))
+                                            .collect();
+                                        CardanoMessage::ReceivedTxs(RawTxsMessage { txs })
+                                    } else {
+                                        CardanoMessage::BlockHeader(BlockHeaderMessage {
+                                            header: block_msg.header.clone(),
+                                            tx_count: block.tx_count(),
+                                            block_size: block_msg.body.len(),
+                                        })
+                                    };
+                                    let message_enum =
+                                        Message::Cardano((block_info.clone(), cardano_message));
                                     context
                                         .message_bus
                                         .publish(&publish_topic, Arc::new(message_enum))
@@ -0,0 +1,26 @@
+use acropolis_common::configuration::ModuleConfig;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct BlockUnpackerConfig {
+    pub subscribe_topic: String,
+    pub publish_topic: String,
+
+    /// Publish the decoded transactions of every block, or only its header - consumers
+    /// then fetch the bodies they need from the chain store with `GetBlockBody`
+    pub publish_bodies: bool,
+}
+
+impl Default for BlockUnpackerConfig {
+    fn default() -> Self {
+        Self {
+            subscribe_topic: "cardano.block.proposed".to_string(),
+            publish_topic: "cardano.txs".to_string(),
+            publish_bodies: true,
+        }
+    }
+}
+
+impl ModuleConfig for BlockUnpackerConfig {
+    const MODULE: &'static str = "block-unpacker";
+}
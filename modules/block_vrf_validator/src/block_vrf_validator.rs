@@ -11,6 +11,7 @@ use acropolis_common::{
         StateTransitionMessage,
     },
     protocol_params::Nonce,
+    spdd_chunks::SPDDAssembler,
     state_history::{StateHistory, StateHistoryStore},
 };
 use anyhow::{bail, Result};
@@ -21,9 +22,10 @@ use tokio::sync::Mutex;
 use tracing::{error, info, info_span, Instrument};
 mod state;
 use state::State;
-mod ouroboros;
+pub mod ouroboros;
 
 mod snapshot;
+use snapshot::ActiveStakes;
 
 const DEFAULT_VALIDATION_VRF_PUBLISHER_TOPIC: (&str, &str) =
     ("validation-vrf-publisher-topic", "cardano.validation.vrf");
@@ -65,7 +67,8 @@ declare_cardano_reader!(
     "spdd-subscribe-topic",
     "cardano.spo.distribution",
     SPOStakeDistribution,
-    SPOStakeDistributionMessage
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
 );
 
 declare_cardano_reader!(
@@ -226,15 +229,16 @@ impl BlockVrfValidator {
                         RollbackWrapper::Rollback(_) => None,
                     };
 
-                let spdd_msg =
-                    match ctx.consume("spdd_reader", spdd_reader.read_with_rollbacks().await)? {
-                        RollbackWrapper::Normal((_, spdd_msg)) => Some(spdd_msg),
-                        RollbackWrapper::Rollback(_) => None,
-                    };
+                // Taken in a chunk at a time
+                let mut active_stakes = ActiveStakes::default();
+                let spdd_read = ctx.consume(
+                    "spdd_reader",
+                    spdd_reader.stream_with_rollbacks(&mut active_stakes).await,
+                )?;
 
                 if let Some(spo_state_msg) = spo_state_msg {
-                    if let Some(spdd_msg) = spdd_msg {
-                        state.handle_new_snapshot(&spo_state_msg, &spdd_msg);
+                    if let RollbackWrapper::Normal(_) = spdd_read {
+                        state.handle_new_snapshot(&spo_state_msg, active_stakes);
                     }
                 }
             }
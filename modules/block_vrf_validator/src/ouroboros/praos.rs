@@ -50,7 +50,7 @@ pub fn validate_vrf_praos<'a>(
     let vrf_cert =
         vrf_result(header).ok_or(VrfValidationError::Other("VRF Cert is not set".to_string()))?;
 
-    // Regular TPraos rules apply
+    // Praos has a single VRF output, used both for the leader check and the nonce
     Ok(vec![
         Box::new(move || {
             validate_leader_vrf_key(&pool_id, registered_vrf_key_hash, vrf_vkey)?;
@@ -94,25 +94,12 @@ fn vrf_result<'a>(header: &'a MultiEraHeader) -> Option<&'a VrfCert> {
 mod tests {
     use acropolis_common::{
         crypto::keyhash_256, protocol_params::NonceHash, serialization::Bech32Conversion,
-        BlockHash, BlockIntent, BlockStatus, Era,
+        validation::VrfLeaderValueTooBigError, BlockHash, BlockIntent, BlockStatus, Era,
     };
 
     use super::*;
 
-    #[test]
-    fn test_7854823_block() {
-        let epoch_nonce = Nonce::from(
-            NonceHash::try_from(
-                hex::decode("8dad163edf4607452fec9c5955d593fb598ca728bae162138f88da6667bba79b")
-                    .unwrap()
-                    .as_slice(),
-            )
-            .unwrap(),
-        );
-        let active_slots_coeff = RationalNumber::new(1, 20);
-
-        let block_header_7854823: Vec<u8> =
-            hex::decode(include_str!("./data/7854823.cbor")).unwrap();
+    fn block_7854823() -> (BlockInfo, Vec<u8>) {
         let block_info = BlockInfo {
             status: BlockStatus::Immutable,
             intent: BlockIntent::Validate,
@@ -131,8 +118,29 @@ mod tests {
             tip_slot: None,
             era: Era::Babbage,
         };
-        let block_header =
-            MultiEraHeader::decode(block_info.era as u8, None, &block_header_7854823).unwrap();
+        (
+            block_info,
+            hex::decode(include_str!("./data/7854823.cbor")).unwrap(),
+        )
+    }
+
+    fn epoch_368_nonce() -> Nonce {
+        Nonce::from(
+            NonceHash::try_from(
+                hex::decode("8dad163edf4607452fec9c5955d593fb598ca728bae162138f88da6667bba79b")
+                    .unwrap()
+                    .as_slice(),
+            )
+            .unwrap(),
+        )
+    }
+
+    fn validate_7854823_with_stake(
+        pool_stake: Option<u64>,
+        total_active_stake: u64,
+    ) -> Result<(), Box<VrfValidationError>> {
+        let (block_info, raw_header) = block_7854823();
+        let block_header = MultiEraHeader::decode(block_info.era as u8, None, &raw_header).unwrap();
         let pool_id =
             PoolId::from_bech32("pool195gdnmj6smzuakm4etxsxw3fgh8asqc4awtcskpyfnkpcvh2v8t")
                 .unwrap();
@@ -140,19 +148,57 @@ mod tests {
             pool_id,
             VrfKeyHash::from(keyhash_256(block_header.vrf_vkey().unwrap())),
         )]);
-        let active_spdd = HashMap::from([(pool_id, 64590523391239)]);
-        let result = validate_vrf_praos(
+        let active_spdd: HashMap<PoolId, u64> =
+            pool_stake.map(|stake| (pool_id, stake)).into_iter().collect();
+        let epoch_nonce = epoch_368_nonce();
+        validate_vrf_praos(
             &block_info,
             &block_header,
             &epoch_nonce,
-            active_slots_coeff,
+            RationalNumber::new(1, 20),
             &active_spos,
             &active_spdd,
-            25069171797357766,
+            total_active_stake,
         )
         .and_then(|vrf_validations| {
             vrf_validations.iter().try_for_each(|assert| assert().map_err(Box::new))
-        });
+        })
+    }
+
+    #[test]
+    fn test_7854823_block() {
+        let result = validate_7854823_with_stake(Some(64590523391239), 25069171797357766);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_7854823_block_with_small_active_stake() {
+        // correct active stake is 64590523391239
+        let result = validate_7854823_with_stake(Some(64590523391), 25069171797357766);
+        let pool_id =
+            PoolId::from_bech32("pool195gdnmj6smzuakm4etxsxw3fgh8asqc4awtcskpyfnkpcvh2v8t")
+                .unwrap();
+        assert_eq!(
+            result.unwrap_err(),
+            Box::new(VrfValidationError::VrfLeaderValueTooBig(
+                VrfLeaderValueTooBigError::VrfLeaderValueTooBig {
+                    pool_id,
+                    active_stake: 64590523391,
+                    relative_stake: RationalNumber::new(64590523391, 25069171797357766),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_7854823_block_without_active_stake() {
+        assert!(matches!(
+            *validate_7854823_with_stake(None, 25069171797357766).unwrap_err(),
+            VrfValidationError::VrfLeaderValueTooBig(_)
+        ));
+        assert!(matches!(
+            *validate_7854823_with_stake(None, 0).unwrap_err(),
+            VrfValidationError::VrfLeaderValueTooBig(_)
+        ));
+    }
 }
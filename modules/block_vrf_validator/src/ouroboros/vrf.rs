@@ -6,9 +6,35 @@ use blake2::{digest::consts::U32, Blake2b, Digest};
 use thiserror::Error;
 use vrf_dalek::{
     errors::VrfError,
-    vrf03::{PublicKey03, VrfProof03},
+    vrf03::{PublicKey03, SecretKey03, VrfProof03},
 };
 
+/// A VRF secret key, used by block producers to prove slot leadership
+pub struct SecretKey(SecretKey03);
+impl SecretKey {
+    /// Size of a VRF secret key (the seed half of a signing key), in bytes.
+    pub const SIZE: usize = 32;
+
+    /// The public key matching this secret key
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(PublicKey03::from(&self.0))
+    }
+}
+
+impl From<&[u8; Self::SIZE]> for SecretKey {
+    fn from(slice: &[u8; Self::SIZE]) -> Self {
+        SecretKey(SecretKey03::from_bytes(slice))
+    }
+}
+
+impl TryFrom<&[u8]> for SecretKey {
+    type Error = TryFromSliceError;
+
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        Ok(Self::from(<&[u8; Self::SIZE]>::try_from(slice)?))
+    }
+}
+
 /// A VRF public key
 #[derive(Debug, PartialEq)]
 pub struct PublicKey(PublicKey03);
@@ -157,6 +183,16 @@ impl Proof {
     /// Size of a VRF proof hash digest (SHA512), in bytes.
     pub const HASH_SIZE: usize = 64;
 
+    /// Prove an input with a vrf secret key
+    pub fn generate(secret_key: &SecretKey, input: &VrfInput) -> Self {
+        let public_key = PublicKey03::from(&secret_key.0);
+        Proof(VrfProof03::generate(
+            &public_key,
+            &secret_key.0,
+            input.as_ref(),
+        ))
+    }
+
     /// Verify a proof signature with a vrf public key. This will return a hash to compare with the original
     /// signature hash, but any non-error result is considered a successful verification without needing
     /// to do the extra comparison check.
@@ -164,6 +164,18 @@ pub fn validate_vrf_leader_value(
     leader_relative_stake: &RationalNumber,
     active_slot_coeff: &RationalNumber,
 ) -> Result<(), VrfLeaderValueTooBigError> {
+    let too_big = || VrfLeaderValueTooBigError::VrfLeaderValueTooBig {
+        pool_id: *pool_id,
+        active_stake: *leader_relative_stake.numer(),
+        relative_stake: leader_relative_stake.clone(),
+    };
+
+    // A pool without stake can never be elected (the threshold is zero). This also
+    // covers an empty stake distribution, which would otherwise divide by zero.
+    if *leader_relative_stake.numer() == 0 || *leader_relative_stake.denom() == 0 {
+        return Err(too_big());
+    }
+
     let certified_leader_vrf = &FixedDecimal::from(leader_vrf_output);
     let output_size_bits = leader_vrf_output.len() * 8;
     let cert_nat_max = FixedDecimal::from(UBig::ONE << output_size_bits);
@@ -179,13 +191,7 @@ pub fn validate_vrf_leader_value(
     let ordering = x.exp_cmp(1000, 3, &recip_q);
     match ordering.estimation {
         ExpOrdering::LT => Ok(()),
-        ExpOrdering::GT | ExpOrdering::UNKNOWN => {
-            Err(VrfLeaderValueTooBigError::VrfLeaderValueTooBig {
-                pool_id: *pool_id,
-                active_stake: *leader_relative_stake.numer(),
-                relative_stake: leader_relative_stake.clone(),
-            })
-        }
+        ExpOrdering::GT | ExpOrdering::UNKNOWN => Err(too_big()),
     }
 }
 
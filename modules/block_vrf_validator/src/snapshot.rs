@@ -1,6 +1,7 @@
 use acropolis_common::{
-    messages::{AccountsBootstrapMessage, SPOStakeDistributionMessage, SPOStateMessage},
-    PoolId, VrfKeyHash,
+    messages::{AccountsBootstrapMessage, SPOStateMessage},
+    spdd_chunks::SPDDSink,
+    DelegatedStake, PoolId, VrfKeyHash,
 };
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
@@ -27,20 +28,35 @@ impl Display for Snapshot {
     }
 }
 
-impl From<(&SPOStateMessage, &SPOStakeDistributionMessage)> for Snapshot {
-    fn from((spo_state_msg, spdd_msg): (&SPOStateMessage, &SPOStakeDistributionMessage)) -> Self {
+/// Active stakes taken in from the SPDD, a chunk at a time
+#[derive(Debug, Default)]
+pub struct ActiveStakes {
+    stakes: HashMap<PoolId, u64>,
+    total: u64,
+}
+
+impl SPDDSink for ActiveStakes {
+    fn start(&mut self, _epoch: u64, pools: u64, total_active: u64) {
+        self.stakes = HashMap::with_capacity(pools as usize);
+        self.total = total_active;
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        self.stakes.extend(spos.iter().map(|(pool_id, stake)| (*pool_id, stake.active)));
+    }
+}
+
+impl From<(&SPOStateMessage, ActiveStakes)> for Snapshot {
+    fn from((spo_state_msg, active_stakes): (&SPOStateMessage, ActiveStakes)) -> Self {
         let active_spos: HashMap<PoolId, VrfKeyHash> = spo_state_msg
             .spos
             .iter()
             .map(|registration| (registration.operator, registration.vrf_key_hash))
             .collect();
-        let active_stakes: HashMap<PoolId, u64> =
-            spdd_msg.spos.iter().map(|(pool_id, stake)| (*pool_id, stake.active)).collect();
-        let total_active_stakes = active_stakes.values().sum();
         Self {
             active_spos,
-            active_stakes,
-            total_active_stakes,
+            active_stakes: active_stakes.stakes,
+            total_active_stakes: active_stakes.total,
         }
     }
 }
@@ -2,17 +2,17 @@
 
 use std::sync::Arc;
 
-use crate::{ouroboros, snapshot::Snapshot};
+use crate::{
+    ouroboros,
+    snapshot::{ActiveStakes, Snapshot},
+};
 use acropolis_common::{
     genesis_values::GenesisValues,
-    messages::{
-        AccountsBootstrapMessage, ProtocolParamsMessage, SPOStakeDistributionMessage,
-        SPOStateMessage,
-    },
+    messages::{AccountsBootstrapMessage, ProtocolParamsMessage, SPOStateMessage},
     protocol_params::Nonce,
     rational_number::RationalNumber,
     validation::{ValidationError, VrfValidationError},
-    BlockInfo, Era,
+    BlockInfo, Era, GenesisDelegates,
 };
 use anyhow::Result;
 use pallas::ledger::traverse::MultiEraHeader;
@@ -38,6 +38,9 @@ pub struct State {
 
     pub active_slots_coeff: Option<RationalNumber>,
 
+    /// Genesis delegates, as changed by certificate since genesis
+    pub genesis_delegs: Option<GenesisDelegates>,
+
     /// epoch nonce
     pub epoch_nonce: Option<Nonce>,
 
@@ -50,6 +53,7 @@ impl State {
         Self {
             active_slots_coeff: None,
             decentralisation_param: None,
+            genesis_delegs: None,
             epoch_nonce: None,
             epoch_snapshots: EpochSnapshots::default(),
         }
@@ -66,6 +70,7 @@ impl State {
             self.decentralisation_param =
                 Some(shelley_params.protocol_params.decentralisation_param.clone());
             self.active_slots_coeff = Some(shelley_params.active_slots_coeff.clone());
+            self.genesis_delegs = Some(shelley_params.gen_delegs.clone());
         }
     }
 
@@ -76,9 +81,9 @@ impl State {
     pub fn handle_new_snapshot(
         &mut self,
         spo_state_msg: &SPOStateMessage,
-        spdd_msg: &SPOStakeDistributionMessage,
+        active_stakes: ActiveStakes,
     ) {
-        let new_snapshot = Snapshot::from((spo_state_msg, spdd_msg));
+        let new_snapshot = Snapshot::from((spo_state_msg, active_stakes));
         self.epoch_snapshots.push(new_snapshot);
     }
 
@@ -105,16 +110,10 @@ impl State {
             }
         };
 
-        let Some(decentralisation_param) = self.decentralisation_param.clone() else {
-            return Err(Box::new(
-                VrfValidationError::Other("Decentralisation Param is not set".to_string()).into(),
-            ));
-        };
-        let Some(active_slots_coeff) = self.active_slots_coeff.clone() else {
-            return Err(Box::new(
-                VrfValidationError::Other("Active Slots Coeff is not set".to_string()).into(),
-            ));
-        };
+        // The active slots coefficient is fixed by the Shelley genesis, so use that
+        // until the protocol parameters arrive (e.g. after a snapshot bootstrap)
+        let active_slots_coeff =
+            self.active_slots_coeff.clone().unwrap_or_else(|| genesis.active_slots_coeff.clone());
         let Some(epoch_nonce) = self.epoch_nonce.as_ref() else {
             return Err(Box::new(
                 VrfValidationError::Other("Epoch Nonce is not set".to_string()).into(),
@@ -127,11 +126,17 @@ impl State {
         );
 
         let result = if is_tpraos {
+            let Some(decentralisation_param) = self.decentralisation_param.clone() else {
+                return Err(Box::new(
+                    VrfValidationError::Other("Decentralisation Param is not set".to_string())
+                        .into(),
+                ));
+            };
             ouroboros::tpraos::validate_vrf_tpraos(
                 block_info,
                 &header,
                 epoch_nonce,
-                &genesis.genesis_delegs,
+                self.genesis_delegs.as_ref().unwrap_or(&genesis.genesis_delegs),
                 active_slots_coeff,
                 decentralisation_param,
                 &self.epoch_snapshots.set.active_spos,
@@ -4,7 +4,7 @@ use crate::queries::{handle_blocks_query, handle_txs_query};
 use crate::state::State;
 use crate::stores::{fjall::FjallStore, Store};
 
-use acropolis_common::configuration::get_string_flag;
+use acropolis_common::configuration::ModuleConfig;
 use acropolis_common::messages::GenesisCompleteMessage;
 use acropolis_common::queries::errors::QueryError;
 use acropolis_common::NetworkId;
@@ -15,8 +15,10 @@ use acropolis_common::{
         CardanoMessage, Message, ProtocolParamsMessage, RawBlockMessage, StateQuery,
         StateQueryResponse, StateTransitionMessage,
     },
-    queries::blocks::{BlocksStateQueryResponse, DEFAULT_BLOCKS_QUERY_TOPIC},
-    queries::transactions::{TransactionsStateQueryResponse, DEFAULT_TRANSACTIONS_QUERY_TOPIC},
+    metrics,
+    queries::blocks::BlocksStateQueryResponse,
+    queries::transactions::TransactionsStateQueryResponse,
+    readiness, shutdown,
     state_history::{StateHistory, StateHistoryStore},
 };
 use anyhow::{bail, Result};
@@ -27,13 +29,12 @@ use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tracing::info;
 
+mod configuration;
 mod helpers;
 mod queries;
 mod state;
 
-const DEFAULT_STORE: (&str, &str) = ("store", "fjall");
-const DEFAULT_VALIDATION_OUTCOME_PUBLISH_TOPIC: (&str, &str) =
-    ("validation-publish-topic", "cardano.validation.chainstore");
+pub use configuration::{ChainStoreConfig, StoreType};
 
 declare_cardano_reader!(
     BlocksReader,
@@ -67,18 +68,19 @@ pub struct ChainStore;
 
 impl ChainStore {
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
-        let block_queries_topic = get_string_flag(&config, DEFAULT_BLOCKS_QUERY_TOPIC);
-        let txs_queries_topic = get_string_flag(&config, DEFAULT_TRANSACTIONS_QUERY_TOPIC);
-        let validation_topic = get_string_flag(&config, DEFAULT_VALIDATION_OUTCOME_PUBLISH_TOPIC);
+        readiness::declare_startup(context.clone(), &config, "chain-store").await?;
+
+        let store_config = ChainStoreConfig::load(&config)?;
+        let block_queries_topic = store_config.blocks_state_query_topic;
+        let txs_queries_topic = store_config.transactions_state_query_topic;
+        let validation_topic = store_config.validation_publish_topic;
         info!("Publishing validation outcomes on '{validation_topic}'");
 
         // Network ID is set from the GenesisValues (Wrapped in Arc<RwLock<>> to share with txs query handler)
         let network_id: Arc<RwLock<Option<NetworkId>>> = Arc::new(RwLock::new(None));
 
-        let store_type = get_string_flag(&config, DEFAULT_STORE);
-        let store: Arc<dyn Store> = match store_type.as_str() {
-            "fjall" => Arc::new(FjallStore::new(config.clone())?),
-            _ => bail!("Unknown store type {store_type}"),
+        let store: Arc<dyn Store> = match store_config.store {
+            StoreType::Fjall => Arc::new(FjallStore::new(config.clone())?),
         };
 
         let history = Arc::new(Mutex::new(StateHistory::<State>::new(
@@ -87,12 +89,25 @@ impl ChainStore {
         )));
         history.lock().await.commit_forced(State::new());
 
+        // Flush the store before the process exits
+        let flush_store = store.clone();
+        shutdown::on_shutdown(
+            context.clone(),
+            &config,
+            "chain-store",
+            move || async move { flush_store.flush() },
+        )
+        .await?;
+
         let query_store = store.clone();
         let query_history = history.clone();
+        let block_query_metrics = metrics::QueryMetrics::new("chain-store");
         context.handle(&block_queries_topic, move |req| {
             let query_store = query_store.clone();
             let query_history = query_history.clone();
+            let timer = block_query_metrics.start(&req);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Blocks(query)) = req.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Blocks(
                         BlocksStateQueryResponse::Error(QueryError::internal_error(
@@ -102,7 +117,7 @@ impl ChainStore {
                 };
                 let Some(state) = query_history.lock().await.current().cloned() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Blocks(
-                        BlocksStateQueryResponse::Error(QueryError::internal_error(
+                        BlocksStateQueryResponse::Error(QueryError::not_synced(
                             "uninitialized state",
                         )),
                     )));
@@ -116,10 +131,13 @@ impl ChainStore {
 
         let query_store = store.clone();
         let network_id_for_txs = network_id.clone();
+        let txs_query_metrics = metrics::QueryMetrics::new("chain-store");
         context.handle(&txs_queries_topic, move |req| {
             let query_store = query_store.clone();
             let network_id = network_id_for_txs.clone();
+            let timer = txs_query_metrics.start(&req);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Transactions(query)) = req.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(
                         StateQueryResponse::Transactions(TransactionsStateQueryResponse::Error(
@@ -135,9 +153,9 @@ impl ChainStore {
                         None => {
                             return Arc::new(Message::StateQueryResponse(
                                 StateQueryResponse::Transactions(
-                                    TransactionsStateQueryResponse::Error(
-                                        QueryError::internal_error("network not initialized"),
-                                    ),
+                                    TransactionsStateQueryResponse::Error(QueryError::not_synced(
+                                        "network not initialized",
+                                    )),
                                 ),
                             ));
                         }
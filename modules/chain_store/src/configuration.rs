@@ -0,0 +1,49 @@
+use acropolis_common::configuration::ModuleConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreType {
+    Fjall,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ChainStoreConfig {
+    pub blocks_state_query_topic: String,
+    pub transactions_state_query_topic: String,
+    pub validation_publish_topic: String,
+    pub store: StoreType,
+
+    // Read by the message readers
+    pub blocks_subscribe_topic: String,
+    pub protocol_parameters_subscribe_topic: String,
+    pub genesis_subscribe_topic: String,
+
+    // Read by the Fjall store
+    pub database_path: Option<String>,
+    pub clear_on_start: bool,
+    pub network_name: Option<String>,
+    pub network_id: Option<String>,
+}
+
+impl Default for ChainStoreConfig {
+    fn default() -> Self {
+        Self {
+            blocks_state_query_topic: "cardano.query.blocks".to_string(),
+            transactions_state_query_topic: "cardano.query.transactions".to_string(),
+            validation_publish_topic: "cardano.validation.chainstore".to_string(),
+            store: StoreType::Fjall,
+            blocks_subscribe_topic: "cardano.block.available".to_string(),
+            protocol_parameters_subscribe_topic: "cardano.protocol.parameters".to_string(),
+            genesis_subscribe_topic: "cardano.sequence.bootstrapped".to_string(),
+            database_path: None,
+            clear_on_start: true,
+            network_name: None,
+            network_id: None,
+        }
+    }
+}
+
+impl ModuleConfig for ChainStoreConfig {
+    const MODULE: &'static str = "chain-store";
+}
@@ -1,25 +1,28 @@
 use std::{collections::BTreeMap, sync::Arc};
 
 use acropolis_common::{
-    crypto::keyhash_224,
+    crypto::{keyhash_224, keyhash_256},
+    messages::RawTxsMessage,
     queries::{
         blocks::{
-            BlockInfo, BlockInvolvedAddress, BlockInvolvedAddresses, BlockKey, BlockTransaction,
-            BlockTransactions, BlockTransactionsCBOR,
+            BlockCBOR, BlockInfo, BlockInvolvedAddress, BlockInvolvedAddresses, BlockKey,
+            BlockTransaction, BlockTransactions, BlockTransactionsCBOR,
         },
         misc::Order,
         transactions::{
             TransactionDelegationCertificate, TransactionInfo, TransactionMIR,
             TransactionMetadataItem, TransactionOutputAmount, TransactionPoolRetirementCertificate,
-            TransactionPoolUpdateCertificate, TransactionStakeCertificate, TransactionWithdrawal,
+            TransactionPoolUpdateCertificate, TransactionRedeemer, TransactionRedeemers,
+            TransactionStakeCertificate, TransactionUTxOInput, TransactionUTxOOutput,
+            TransactionUTxOs, TransactionWithdrawal,
         },
     },
-    AssetName, BechOrdAddress, BlockHash, InstantaneousRewardSource, NativeAsset, NetworkId,
-    StakeAddress, TxHash,
+    Address, AssetName, BechOrdAddress, BlockHash, InstantaneousRewardSource, NativeAsset,
+    NetworkId, RedeemerTag, ScriptHash, StakeAddress, TxHash, TxOutput, UTxOIdentifier,
 };
 use anyhow::{anyhow, Result};
 use pallas::ledger::primitives::{alonzo, conway};
-use pallas_traverse::{MultiEraCert, MultiEraMeta};
+use pallas_traverse::{MultiEraCert, MultiEraInput, MultiEraMeta, MultiEraOutput};
 
 use crate::{
     state::State,
@@ -43,6 +46,16 @@ pub fn get_block_hash(block: &Block) -> Result<BlockHash> {
     ))
 }
 
+pub fn to_block_cbor(block: Block) -> Result<BlockCBOR> {
+    let decoded = pallas_traverse::MultiEraBlock::decode(&block.bytes)?;
+    Ok(BlockCBOR {
+        hash: BlockHash::from(*decoded.hash()),
+        number: decoded.number(),
+        slot: decoded.slot(),
+        cbor: block.bytes,
+    })
+}
+
 pub fn to_block_info(
     block: Block,
     store: &Arc<dyn Store>,
@@ -206,6 +219,12 @@ pub fn to_block_transactions_cbor(
     Ok(BlockTransactionsCBOR { txs })
 }
 
+pub fn to_block_body(block: Block) -> Result<RawTxsMessage> {
+    let decoded = pallas_traverse::MultiEraBlock::decode(&block.bytes)?;
+    let txs = decoded.txs().iter().map(|tx| tx.encode().into()).collect();
+    Ok(RawTxsMessage { txs })
+}
+
 pub fn to_block_involved_addresses(
     block: Block,
     limit: &u64,
@@ -648,3 +667,137 @@ pub fn to_tx_metadata(tx: &Tx) -> Result<Vec<TransactionMetadataItem>> {
     }
     Ok(items)
 }
+
+fn to_tx_output(tx_hash: TxHash, index: usize, output: &MultiEraOutput) -> Result<TxOutput> {
+    Ok(TxOutput {
+        utxo_identifier: UTxOIdentifier::new(tx_hash, index as u16),
+        address: acropolis_codec::map_address(&output.address()?)?,
+        value: acropolis_codec::map_value(&output.value()),
+        datum: acropolis_codec::map_datum(&output.datum()),
+        script_ref: acropolis_codec::map_reference_script(&output.script_ref())
+            .map(|script| script.get_script_ref()),
+    })
+}
+
+/// Find the output spent by an input, from the transaction which created it
+fn resolve_input(store: &Arc<dyn Store>, input: &MultiEraInput) -> Result<TxOutput> {
+    let output_ref = input.output_ref();
+    let Some(source) = store.get_tx_by_hash(output_ref.hash().as_ref())? else {
+        return Err(anyhow!(
+            "Transaction {} spent by input not found",
+            output_ref.hash()
+        ));
+    };
+    let block = pallas_traverse::MultiEraBlock::decode(&source.block.bytes)?;
+    let txs = block.txs();
+    let Some(source_decoded) = txs.get(source.index as usize) else {
+        return Err(anyhow!("Transaction not found in block for given index"));
+    };
+    let index = output_ref.index() as usize;
+    let Some((_, output)) = source_decoded.produces().into_iter().find(|(i, _)| *i == index) else {
+        return Err(anyhow!(
+            "Output {}#{} spent by input not found",
+            output_ref.hash(),
+            index
+        ));
+    };
+    to_tx_output(TxHash::from(*source_decoded.hash()), index, &output)
+}
+
+pub fn to_tx_utxos(store: &Arc<dyn Store>, tx: &Tx) -> Result<TransactionUTxOs> {
+    let block = pallas_traverse::MultiEraBlock::decode(&tx.block.bytes)?;
+    let txs = block.txs();
+    let Some(tx_decoded) = txs.get(tx.index as usize) else {
+        return Err(anyhow!("Transaction not found in block for given index"));
+    };
+    let hash = TxHash::from(*tx_decoded.hash());
+
+    let spent = tx_decoded.inputs_sorted_set();
+    let collateral_inputs = tx_decoded.collateral();
+    let reference_inputs = tx_decoded.reference_inputs();
+    let mut inputs = Vec::new();
+    for (input, collateral, reference) in spent
+        .iter()
+        .map(|input| (input, false, false))
+        .chain(collateral_inputs.iter().map(|input| (input, true, false)))
+        .chain(reference_inputs.iter().map(|input| (input, false, true)))
+    {
+        inputs.push(TransactionUTxOInput {
+            output: resolve_input(store, input)?,
+            collateral,
+            reference,
+        });
+    }
+
+    let mut outputs = Vec::new();
+    let tx_outputs = tx_decoded.outputs();
+    for (index, output) in tx_outputs.iter().enumerate() {
+        outputs.push(TransactionUTxOOutput {
+            output: to_tx_output(hash, index, output)?,
+            collateral: false,
+        });
+    }
+    // The collateral return output is indexed after the regular outputs
+    if let Some(output) = tx_decoded.collateral_return() {
+        outputs.push(TransactionUTxOOutput {
+            output: to_tx_output(hash, tx_outputs.len(), &output)?,
+            collateral: true,
+        });
+    }
+
+    Ok(TransactionUTxOs {
+        hash,
+        inputs,
+        outputs,
+    })
+}
+
+pub fn to_tx_redeemers(store: &Arc<dyn Store>, tx: &Tx) -> Result<TransactionRedeemers> {
+    let block = pallas_traverse::MultiEraBlock::decode(&tx.block.bytes)?;
+    let txs = block.txs();
+    let Some(tx_decoded) = txs.get(tx.index as usize) else {
+        return Err(anyhow!("Transaction not found in block for given index"));
+    };
+
+    let mut redeemers = Vec::new();
+    for redeemer in tx_decoded.redeemers() {
+        let redeemer = acropolis_codec::map_redeemer(&redeemer)?;
+        let index = redeemer.index as usize;
+
+        // Find the script the redeemer is passed to from what it points at. Scripts
+        // behind certificates and governance actions are not resolved.
+        let script_hash = match redeemer.tag {
+            RedeemerTag::Spend => tx_decoded
+                .inputs_sorted_set()
+                .get(index)
+                .and_then(|input| resolve_input(store, input).ok())
+                .and_then(|output| match output.address {
+                    Address::Shelley(address) => address.payment.to_script_hash(),
+                    _ => None,
+                }),
+            RedeemerTag::Mint => tx_decoded
+                .mints_sorted_set()
+                .get(index)
+                .map(|policy| ScriptHash::from(**policy.policy())),
+            RedeemerTag::Reward => tx_decoded
+                .withdrawals_sorted_set()
+                .get(index)
+                .and_then(|(address, _)| StakeAddress::from_binary(address).ok())
+                .and_then(|address| address.credential.get_script_hash()),
+            RedeemerTag::Cert | RedeemerTag::Vote | RedeemerTag::Propose => None,
+        };
+
+        redeemers.push(TransactionRedeemer {
+            tx_index: redeemer.index,
+            purpose: redeemer.tag,
+            script_hash,
+            redeemer_data_hash: keyhash_256(&redeemer.data),
+            ex_units: redeemer.ex_units,
+        });
+    }
+
+    Ok(TransactionRedeemers {
+        epoch: tx.block.extra.epoch,
+        redeemers,
+    })
+}
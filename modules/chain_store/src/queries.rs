@@ -21,10 +21,11 @@ use anyhow::Result;
 
 use crate::{
     helpers::{
-        get_block_by_key, get_block_hash, get_block_number, to_block_info, to_block_info_bulk,
-        to_block_involved_addresses, to_block_transaction_hashes, to_block_transactions,
-        to_block_transactions_cbor, to_tx_delegations, to_tx_info, to_tx_metadata, to_tx_mirs,
-        to_tx_pool_retirements, to_tx_pool_updates, to_tx_stakes, to_tx_withdrawals,
+        get_block_by_key, get_block_hash, get_block_number, to_block_body, to_block_cbor,
+        to_block_info, to_block_info_bulk, to_block_involved_addresses,
+        to_block_transaction_hashes, to_block_transactions, to_block_transactions_cbor,
+        to_tx_delegations, to_tx_info, to_tx_metadata, to_tx_mirs, to_tx_pool_retirements,
+        to_tx_pool_updates, to_tx_redeemers, to_tx_stakes, to_tx_utxos, to_tx_withdrawals,
     },
     state::State,
     stores::{Block, Store},
@@ -100,6 +101,30 @@ pub fn handle_blocks_query(
             let info = to_block_info(block, store, state, false)?;
             Ok(BlocksStateQueryResponse::BlockByEpochSlot(info))
         }
+        BlocksStateQuery::GetBlockCBOR { block_key } => {
+            let Some(block) = get_block_by_key(store, block_key)? else {
+                return Ok(BlocksStateQueryResponse::Error(QueryError::not_found(
+                    format!("Block {:?} not found", block_key),
+                )));
+            };
+            Ok(BlocksStateQueryResponse::BlockCBOR(to_block_cbor(block)?))
+        }
+        BlocksStateQuery::GetBlocksCBORByNumberRange {
+            min_number,
+            max_number,
+        } => {
+            if *max_number < *min_number {
+                return Ok(BlocksStateQueryResponse::Error(
+                    QueryError::invalid_request("Invalid number range"),
+                ));
+            }
+            let blocks = store
+                .get_blocks_by_number_range(*min_number, *max_number)?
+                .into_iter()
+                .map(to_block_cbor)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(BlocksStateQueryResponse::BlocksCBORByNumberRange(blocks))
+        }
         BlocksStateQuery::GetNextBlocks {
             block_key,
             limit,
@@ -186,6 +211,14 @@ pub fn handle_blocks_query(
             let txs = to_block_transactions_cbor(block, limit, skip, order)?;
             Ok(BlocksStateQueryResponse::BlockTransactionsCBOR(txs))
         }
+        BlocksStateQuery::GetBlockBody { block_key } => {
+            let Some(block) = get_block_by_key(store, block_key)? else {
+                return Ok(BlocksStateQueryResponse::Error(QueryError::not_found(
+                    format!("Block {:?} not found", block_key),
+                )));
+            };
+            Ok(BlocksStateQueryResponse::BlockBody(to_block_body(block)?))
+        }
         BlocksStateQuery::GetBlockInvolvedAddresses {
             block_key,
             limit,
@@ -629,6 +662,26 @@ pub fn handle_txs_query(
                 },
             ))
         }
+        TransactionsStateQuery::GetTransactionUTxOs { tx_hash } => {
+            let Some(tx) = store.get_tx_by_hash(tx_hash.as_ref())? else {
+                return Ok(TransactionsStateQueryResponse::Error(
+                    QueryError::not_found("Transaction not found"),
+                ));
+            };
+            Ok(TransactionsStateQueryResponse::TransactionUTxOs(
+                to_tx_utxos(store, &tx)?,
+            ))
+        }
+        TransactionsStateQuery::GetTransactionRedeemers { tx_hash } => {
+            let Some(tx) = store.get_tx_by_hash(tx_hash.as_ref())? else {
+                return Ok(TransactionsStateQueryResponse::Error(
+                    QueryError::not_found("Transaction not found"),
+                ));
+            };
+            Ok(TransactionsStateQueryResponse::TransactionRedeemers(
+                to_tx_redeemers(store, &tx)?,
+            ))
+        }
         _ => Ok(TransactionsStateQueryResponse::Error(
             QueryError::not_implemented("Unimplemented".to_string()),
         )),
@@ -641,6 +694,7 @@ mod tests {
 
     use super::*;
     use crate::stores::{fjall::FjallStore, Block, ExtraBlockData, Store, Tx, TxBlockReference};
+    use acropolis_common::queries::misc::Order;
     use anyhow::{anyhow, Result};
     use config::Config;
     use tempfile::TempDir;
@@ -742,6 +796,10 @@ mod tests {
             false
         }
 
+        fn flush(&self) -> Result<()> {
+            Ok(())
+        }
+
         fn get_earliest_block_number(&self) -> Result<Option<u64>> {
             Ok(self.blocks_by_number.first_key_value().map(|(number, _)| *number))
         }
@@ -1011,4 +1069,118 @@ mod tests {
             other => panic!("unexpected response: {other:?}"),
         }
     }
+
+    #[test]
+    fn should_return_next_and_previous_blocks_in_chain_order() {
+        let (_dir, store, infos) = init_store_with_blocks(6);
+        let state = State::new();
+
+        let next = handle_blocks_query(
+            &store,
+            &state,
+            &BlocksStateQuery::GetNextBlocks {
+                block_key: BlockKey::Hash(infos[1].hash),
+                limit: 2,
+                skip: 1,
+            },
+        )
+        .unwrap();
+
+        match next {
+            BlocksStateQueryResponse::NextBlocks(NextBlocks { blocks }) => {
+                assert_eq!(blocks.len(), 2);
+                assert_block_matches_info(&blocks[0], &infos[3]);
+                assert_block_matches_info(&blocks[1], &infos[4]);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        let previous = handle_blocks_query(
+            &store,
+            &state,
+            &BlocksStateQuery::GetPreviousBlocks {
+                block_key: BlockKey::Number(infos[2].number),
+                limit: 5,
+                skip: 0,
+            },
+        )
+        .unwrap();
+
+        match previous {
+            BlocksStateQueryResponse::PreviousBlocks(PreviousBlocks { blocks }) => {
+                assert_eq!(blocks.len(), 2);
+                assert_block_matches_info(&blocks[0], &infos[0]);
+                assert_block_matches_info(&blocks[1], &infos[1]);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn should_return_block_by_slot() {
+        let (_dir, store, infos) = init_store_with_blocks(6);
+        let state = State::new();
+
+        let response = handle_blocks_query(
+            &store,
+            &state,
+            &BlocksStateQuery::GetBlockBySlot {
+                slot: infos[3].slot,
+            },
+        )
+        .unwrap();
+
+        match response {
+            BlocksStateQueryResponse::BlockBySlot(block) => {
+                assert_block_matches_info(&block, &infos[3]);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        let missing = handle_blocks_query(
+            &store,
+            &state,
+            &BlocksStateQuery::GetBlockBySlot {
+                slot: infos[5].slot + 1,
+            },
+        )
+        .unwrap();
+
+        match missing {
+            BlocksStateQueryResponse::Error(QueryError::NotFound { .. }) => {}
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn should_page_block_transactions_in_either_order() {
+        let (_dir, store, infos) = init_store_with_blocks(6);
+        let state = State::new();
+        let block = store.get_block_by_number(infos[2].number).unwrap().unwrap();
+        let tx_hashes = crate::stores::extract_tx_hashes(&block.bytes).unwrap();
+
+        for (order, expected) in [
+            (Order::Asc, tx_hashes.clone()),
+            (Order::Desc, tx_hashes.iter().rev().cloned().collect()),
+        ] {
+            let response = handle_blocks_query(
+                &store,
+                &state,
+                &BlocksStateQuery::GetBlockTransactions {
+                    block_key: BlockKey::Hash(infos[2].hash),
+                    limit: 100,
+                    skip: 0,
+                    order,
+                },
+            )
+            .unwrap();
+
+            match response {
+                BlocksStateQueryResponse::BlockTransactions(txs) => {
+                    assert_eq!(txs.hashes, expected);
+                }
+                other => panic!("unexpected response: {other:?}"),
+            }
+        }
+    }
 }
@@ -4,10 +4,13 @@ use std::{
     sync::{atomic::AtomicU64, Arc},
 };
 
-use acropolis_common::{BlockInfo, TxHash};
+use acropolis_common::{
+    genesis_values::{CONFIG_KEY_NETWORK, CONFIG_KEY_NETWORK_NAME},
+    BlockInfo, TxHash,
+};
 use anyhow::{anyhow, Result};
 use config::Config;
-use fjall::{Database, Keyspace, OwnedWriteBatch};
+use fjall::{Database, Keyspace, OwnedWriteBatch, PersistMode};
 use pallas_traverse::MultiEraBlock;
 
 use crate::stores::{extract_tx_hashes, Block, ExtraBlockData, Tx, TxBlockReference};
@@ -71,7 +74,8 @@ impl FjallStore {
 
     fn network_scope_from_config(config: &Config) -> String {
         config
-            .get_string("startup.network-name")
+            .get_string(CONFIG_KEY_NETWORK)
+            .or_else(|_| config.get_string(CONFIG_KEY_NETWORK_NAME))
             .or_else(|_| config.get_string("network-name"))
             .or_else(|_| config.get_string("network-id"))
             .unwrap_or_else(|_| DEFAULT_NETWORK_NAME.to_string())
@@ -129,6 +133,11 @@ impl super::Store for FjallStore {
         tip == 0 || block_number > tip
     }
 
+    fn flush(&self) -> Result<()> {
+        self.database.persist(PersistMode::SyncAll)?;
+        Ok(())
+    }
+
     fn get_earliest_block_number(&self) -> Result<Option<u64>> {
         self.blocks.get_earliest_block_number()
     }
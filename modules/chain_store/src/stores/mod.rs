@@ -7,6 +7,8 @@ pub trait Store: Send + Sync {
     fn insert_block(&self, info: &BlockInfo, block: &[u8]) -> Result<()>;
     fn rollback(&self, info: &BlockInfo) -> Result<()>;
     fn should_persist(&self, block_number: u64) -> bool;
+    /// Make everything written so far durable
+    fn flush(&self) -> Result<()>;
 
     fn get_earliest_block_number(&self) -> Result<Option<u64>>;
     fn get_tip_block_number(&self) -> u64;
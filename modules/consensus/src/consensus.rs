@@ -11,21 +11,34 @@ use acropolis_common::{
     configuration::{get_bool_flag, get_string_flag, get_u64_flag, BlockFlowMode},
     genesis_values::GenesisValues,
     messages::{
-        BlockRejectedMessage, BlockWantedMessage, CardanoMessage, ConsensusMessage, Message,
-        RawBlockMessage, StateTransitionMessage,
+        BlockOfferedMessage, BlockRejectedMessage, BlockWantedMessage, CardanoMessage,
+        ConsensusMessage, ForkDetectedMessage, Message, RESTResponse, RawBlockMessage, StateQuery,
+        StateQueryResponse, StateTransitionMessage,
     },
+    queries::{
+        consensus::{
+            ChainTip, ConsensusStateQuery, ConsensusStateQueryResponse,
+            DEFAULT_CONSENSUS_QUERY_TOPIC,
+        },
+        errors::QueryError,
+    },
+    rest_helper::handle_rest,
+    sync_stop::SyncStop,
     types::{BlockInfo, Point},
     validation::ValidationStatus,
     BlockHash, BlockIntent, BlockStatus, Era,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use caryatid_sdk::{module, Context, Subscription};
 use config::Config;
 use consensus_tree::ConsensusTree;
 use futures::future::try_join_all;
 use pallas::ledger::traverse::MultiEraHeader;
 use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{sync::Mutex, time::timeout};
+use tokio::{
+    sync::{mpsc, oneshot, Mutex},
+    time::timeout,
+};
 use tracing::{debug, error, info, info_span, warn, Instrument};
 use tree_error::ConsensusTreeError;
 use tree_observer::ConsensusTreeObserver;
@@ -42,17 +55,24 @@ const DEFAULT_FORCE_VALIDATION: (&str, bool) = ("force-validation", true);
 const DEFAULT_VALIDATION_TIMEOUT: (&str, u64) = ("validation-timeout", 60); // seconds
 const DEFAULT_GENESIS_COMPLETION_TOPIC: (&str, &str) =
     ("genesis-completion-topic", "cardano.sequence.bootstrapped");
+const DEFAULT_FORKS_TOPIC: (&str, &str) = ("forks-topic", "cardano.consensus.forks");
+const DEFAULT_HANDLE_CHAIN_TIPS_TOPIC: (&str, &str) =
+    ("handle-topic-chain-tips", "rest.get.consensus.tips");
 
 /// Events emitted by the consensus tree observer, queued for async publishing.
 enum ObserverEvent {
     BlockProposed { hash: BlockHash },
     Rollback { to_block_number: u64 },
     BlockRejected { hash: BlockHash },
+    ForkDetected { hash: BlockHash },
 }
 
 /// Shared event queue between the observer and the main loop.
 type EventQueue = Arc<std::sync::Mutex<Vec<ObserverEvent>>>;
 
+/// Request to the main loop for the current chain tips.
+type ChainTipsRequest = oneshot::Sender<Vec<ChainTip>>;
+
 /// Return type of a timed-out validator read batch: outer `Err` = timeout, inner `Err` = read failure.
 type ValidationBatchResult =
     Result<anyhow::Result<Vec<(String, Arc<Message>)>>, tokio::time::error::Elapsed>;
@@ -74,6 +94,10 @@ impl ConsensusTreeObserver for QueuingConsensusTreeObserver {
     fn block_rejected(&self, hash: BlockHash) {
         self.events.lock().unwrap().push(ObserverEvent::BlockRejected { hash });
     }
+
+    fn fork_detected(&self, _fork_point: BlockHash, hash: BlockHash) {
+        self.events.lock().unwrap().push(ObserverEvent::ForkDetected { hash });
+    }
 }
 
 /// Bundled runtime state for the consensus loop.
@@ -81,6 +105,7 @@ struct ConsensusRuntime {
     context: Arc<Context<Message>>,
     blocks_proposed_topic: String,
     consensus_wants_topic: String,
+    forks_topic: String,
     event_queue: EventQueue,
     tree: ConsensusTree,
     /// Cache of full block payloads for re-publication.
@@ -95,11 +120,15 @@ struct ConsensusRuntime {
     /// This might be a subject for optimization should the size of the cache
     /// turns out to be unacceptable.
     block_data: HashMap<BlockHash, (BlockInfo, RawBlockMessage)>,
+    /// Address of the peer which first offered each block, where known.
+    block_origins: HashMap<BlockHash, String>,
     validator_topics: Vec<String>,
     validator_subscriptions: Vec<Box<dyn Subscription<Message>>>,
     validation_timeout: Duration,
     do_validation: bool,
     stats: ConsensusStats,
+    /// Blocks past the configured stop point are not proposed
+    sync_stop: Option<SyncStop>,
 }
 
 /// Periodic logging counters.
@@ -111,6 +140,7 @@ struct ConsensusStats {
     proposed: u64,
     rollbacks: u64,
     rejected: u64,
+    forks: u64,
     parent_missing: u64,
     last_logged_at: std::time::Instant,
 }
@@ -125,6 +155,7 @@ impl Default for ConsensusStats {
             proposed: 0,
             rollbacks: 0,
             rejected: 0,
+            forks: 0,
             parent_missing: 0,
             last_logged_at: std::time::Instant::now(),
         }
@@ -135,8 +166,8 @@ impl ConsensusStats {
     fn maybe_log(&mut self) {
         if self.last_logged_at.elapsed() >= Duration::from_secs(60) {
             info!(
-                "Consensus stats: offered={}, wanted={}, available={}, validated={}, proposed={}, rollbacks={}, rejected={}, parent_missing={}",
-                self.offered, self.wanted, self.available, self.validated, self.proposed, self.rollbacks, self.rejected, self.parent_missing
+                "Consensus stats: offered={}, wanted={}, available={}, validated={}, proposed={}, rollbacks={}, rejected={}, forks={}, parent_missing={}",
+                self.offered, self.wanted, self.available, self.validated, self.proposed, self.rollbacks, self.rejected, self.forks, self.parent_missing
             );
             self.last_logged_at = std::time::Instant::now();
         }
@@ -186,6 +217,25 @@ impl Consensus {
         let genesis_completion_topic = get_string_flag(&config, DEFAULT_GENESIS_COMPLETION_TOPIC);
         info!("Subscribing to genesis completion on '{genesis_completion_topic}'");
 
+        let forks_topic = get_string_flag(&config, DEFAULT_FORKS_TOPIC);
+        info!("Publishing detected forks on '{forks_topic}'");
+
+        // Chain tips are only known to the main loop, so queries ask it for them
+        let (chain_tips_requests, chain_tips_receiver) = mpsc::channel::<ChainTipsRequest>(16);
+        let consensus_query_topic = get_string_flag(&config, DEFAULT_CONSENSUS_QUERY_TOPIC);
+        Self::handle_consensus_queries(&context, &consensus_query_topic, &chain_tips_requests);
+
+        let handle_chain_tips_topic = get_string_flag(&config, DEFAULT_HANDLE_CHAIN_TIPS_TOPIC);
+        info!("Creating request handler on '{handle_chain_tips_topic}'");
+        handle_rest(context.clone(), &handle_chain_tips_topic, move || {
+            let requests = chain_tips_requests.clone();
+            async move {
+                let tips = Self::request_chain_tips(&requests).await?;
+                let json = serde_json::to_string_pretty(&tips)?;
+                Ok(RESTResponse::with_json(200, &json))
+            }
+        });
+
         // Subscribe for incoming blocks (BlockAvailable)
         let block_subscription = context.subscribe(&blocks_available_topic).await?;
 
@@ -205,6 +255,7 @@ impl Consensus {
             try_join_all(validator_topics.iter().map(|topic| context.subscribe(topic))).await?;
 
         let do_validation = !validator_subscriptions.is_empty();
+        let sync_stop = SyncStop::from_config(context.clone(), &config, "consensus");
 
         let run_context = context.clone();
         context.run(async move {
@@ -235,25 +286,35 @@ impl Consensus {
                 context: run_context,
                 blocks_proposed_topic,
                 consensus_wants_topic,
+                forks_topic,
                 event_queue,
                 tree,
                 block_data: HashMap::new(),
+                block_origins: HashMap::new(),
                 validator_topics,
                 validator_subscriptions,
                 validation_timeout,
                 do_validation,
                 stats: ConsensusStats::default(),
+                sync_stop,
             };
             // TODO: Temporary until consensus flow fully works.
             match flow_mode {
                 BlockFlowMode::Direct => {
+                    // No tree to report on, so fail chain tips queries straight away
+                    drop(chain_tips_receiver);
                     runtime.run_direct(block_subscription, force_validation).await;
                 }
                 BlockFlowMode::Consensus => {
                     let consensus_subscription = consensus_subscription
                         .expect("consensus subscription missing for consensus flow mode");
                     runtime
-                        .run_consensus(block_subscription, consensus_subscription, force_validation)
+                        .run_consensus(
+                            block_subscription,
+                            consensus_subscription,
+                            chain_tips_receiver,
+                            force_validation,
+                        )
                         .await;
                 }
             }
@@ -273,6 +334,53 @@ impl Consensus {
             msg => anyhow::bail!("Unexpected message on genesis completion topic: {msg:?}"),
         }
     }
+
+    /// Ask the main loop for the current chain tips
+    async fn request_chain_tips(
+        requests: &mpsc::Sender<ChainTipsRequest>,
+    ) -> Result<Vec<ChainTip>> {
+        let (reply, tips) = oneshot::channel();
+        requests
+            .send(reply)
+            .await
+            .map_err(|_| anyhow!("Chain tips are only tracked in consensus flow mode"))?;
+        Ok(tips.await?)
+    }
+
+    fn handle_consensus_queries(
+        context: &Arc<Context<Message>>,
+        topic: &str,
+        chain_tips_requests: &mpsc::Sender<ChainTipsRequest>,
+    ) {
+        info!("Creating query handler on '{topic}'");
+        let chain_tips_requests = chain_tips_requests.clone();
+        context.handle(topic, move |message| {
+            let chain_tips_requests = chain_tips_requests.clone();
+            async move {
+                let Message::StateQuery(StateQuery::Consensus(query)) = message.as_ref() else {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::Consensus(
+                        ConsensusStateQueryResponse::Error(QueryError::internal_error(
+                            "Invalid message for consensus",
+                        )),
+                    )));
+                };
+
+                let response = match query {
+                    ConsensusStateQuery::GetChainTips => {
+                        match Self::request_chain_tips(&chain_tips_requests).await {
+                            Ok(tips) => ConsensusStateQueryResponse::ChainTips(tips),
+                            Err(e) => ConsensusStateQueryResponse::Error(
+                                QueryError::internal_error(e.to_string()),
+                            ),
+                        }
+                    }
+                };
+                Arc::new(Message::StateQueryResponse(StateQueryResponse::Consensus(
+                    response,
+                )))
+            }
+        });
+    }
 }
 
 impl ConsensusRuntime {
@@ -281,6 +389,7 @@ impl ConsensusRuntime {
         &mut self,
         mut block_subscription: Box<dyn Subscription<Message>>,
         mut consensus_subscription: Box<dyn Subscription<Message>>,
+        mut chain_tips_requests: mpsc::Receiver<ChainTipsRequest>,
         force_validation: bool,
     ) {
         // If force_validation is disabled, treat immutable Mithril replay blocks
@@ -296,6 +405,9 @@ impl ConsensusRuntime {
 
                     match message.as_ref() {
                         Message::Cardano((raw_blk_info, CardanoMessage::BlockAvailable(raw_block))) => {
+                            if !self.admit(raw_blk_info).await {
+                                continue;
+                            }
                             let block_info = if !mithril_passthrough_active && self.do_validation {
                                 raw_blk_info.with_intent(BlockIntent::ValidateAndApply)
                             } else {
@@ -338,7 +450,7 @@ impl ConsensusRuntime {
                     match message.as_ref() {
                         Message::Consensus(ConsensusMessage::BlockOffered(offered)) => {
                             let span = info_span!("consensus-offered", hash = %offered.hash);
-                            self.handle_block_offered(offered.hash, offered.parent_hash, offered.number, offered.slot)
+                            self.handle_block_offered(offered)
                                 .instrument(span)
                                 .await;
                         }
@@ -355,6 +467,10 @@ impl ConsensusRuntime {
 
                     self.stats.maybe_log();
                 }
+
+                Some(reply) = chain_tips_requests.recv() => {
+                    let _ = reply.send(self.chain_tips());
+                }
             }
         }
     }
@@ -509,13 +625,15 @@ impl ConsensusRuntime {
     }
 
     /// Handle a BlockOffered message: check_block_wanted, publish wanted hashes.
-    async fn handle_block_offered(
-        &mut self,
-        hash: BlockHash,
-        parent_hash: BlockHash,
-        number: u64,
-        slot: u64,
-    ) {
+    async fn handle_block_offered(&mut self, offered: &BlockOfferedMessage) {
+        let BlockOfferedMessage {
+            hash,
+            slot,
+            number,
+            parent_hash,
+            ..
+        } = *offered;
+
         // Bootstrap the tree with a virtual root when the first offer arrives.
         self.set_maybe_bootstrap_tree_root(parent_hash, number);
 
@@ -530,10 +648,22 @@ impl ConsensusRuntime {
             return;
         }
 
-        let wanted = match self.tree.check_block_wanted(hash, parent_hash, number, slot) {
+        // Recorded first, so a fork detected on insertion can name its origin
+        if let Some(origin_peer) = &offered.origin_peer {
+            self.block_origins.entry(hash).or_insert_with(|| origin_peer.clone());
+        }
+
+        let wanted = match self.tree.check_block_wanted_with_vrf(
+            hash,
+            parent_hash,
+            number,
+            slot,
+            offered.tiebreak_vrf.clone(),
+        ) {
             Ok(w) => w,
             Err(e) => {
                 warn!(block = number, %hash, "Offered block rejected: {e}");
+                self.prune_block_data();
                 return;
             }
         };
@@ -583,6 +713,7 @@ impl ConsensusRuntime {
                 }
                 ObserverEvent::Rollback { .. } => self.stats.rollbacks += 1,
                 ObserverEvent::BlockRejected { .. } => self.stats.rejected += 1,
+                ObserverEvent::ForkDetected { .. } => self.stats.forks += 1,
             }
         }
         let events = self.resolve_observer_events(raw_events);
@@ -615,6 +746,49 @@ impl ConsensusRuntime {
     /// Keep only metadata for blocks still present in the tree.
     fn prune_block_data(&mut self) {
         self.block_data.retain(|hash, _| self.tree.get_block(hash).is_some());
+        self.block_origins.retain(|hash, _| self.tree.get_block(hash).is_some());
+    }
+
+    /// Describe every chain tip in the tree, longest first.
+    fn chain_tips(&self) -> Vec<ChainTip> {
+        let favoured = self.tree.favoured_tip();
+        self.tree
+            .tips()
+            .into_iter()
+            .filter_map(|hash| {
+                let tip = self.tree.get_block(&hash)?;
+                let fork_point = favoured
+                    .and_then(|f| self.tree.find_common_ancestor(hash, f).ok())
+                    .and_then(|ancestor| self.tree.get_block(&ancestor))
+                    .map_or(tip.number, |ancestor| ancestor.number);
+
+                // Density over the chain's blocks above the root
+                let mut first = tip;
+                while let Some(parent) = first.parent.and_then(|p| self.tree.get_block(&p)) {
+                    if parent.parent.is_none() {
+                        break;
+                    }
+                    first = parent;
+                }
+                let density = if tip.parent.is_some() {
+                    let slots = tip.slot.saturating_sub(first.slot) + 1;
+                    (tip.number - first.number + 1) as f64 / slots as f64
+                } else {
+                    0.0
+                };
+
+                Some(ChainTip {
+                    hash,
+                    number: tip.number,
+                    slot: tip.slot,
+                    favoured: favoured == Some(hash),
+                    fork_point,
+                    branch_length: tip.number - fork_point,
+                    density,
+                    origin_peer: self.block_origins.get(&hash).cloned(),
+                })
+            })
+            .collect()
     }
 
     /// Consume validation responses for each block proposed in this batch.
@@ -755,6 +929,35 @@ impl ConsensusRuntime {
                     )));
                     messages.push((self.consensus_wants_topic.to_string(), msg));
                 }
+                ObserverEvent::ForkDetected { hash } => {
+                    let Some(block) = self.tree.get_block(&hash) else {
+                        // Already gone again, e.g. rescinded
+                        continue;
+                    };
+                    let fork_point = block.parent.unwrap_or_default();
+                    let favoured =
+                        self.tree.favoured_tip().is_some_and(|t| self.tree.chain_contains(hash, t));
+                    let origin_peer = self.block_origins.get(&hash).cloned();
+                    info!(
+                        block = block.number,
+                        %hash,
+                        %fork_point,
+                        favoured,
+                        origin_peer = origin_peer.as_deref().unwrap_or("unknown"),
+                        "Fork detected"
+                    );
+                    let msg = Arc::new(Message::Consensus(ConsensusMessage::ForkDetected(
+                        ForkDetectedMessage {
+                            hash,
+                            slot: block.slot,
+                            number: block.number,
+                            fork_point,
+                            favoured,
+                            origin_peer,
+                        },
+                    )));
+                    messages.push((self.forks_topic.to_string(), msg));
+                }
             }
         }
 
@@ -891,6 +1094,14 @@ impl ConsensusRuntime {
         }
     }
 
+    /// Whether a block is within the configured stop point, if any
+    async fn admit(&mut self, block_info: &BlockInfo) -> bool {
+        match self.sync_stop.as_mut() {
+            Some(sync_stop) => sync_stop.admit(block_info).await,
+            None => true,
+        }
+    }
+
     /// Direct flow: pass-through BlockAvailable and Rollback (main-branch behavior).
     /// TODO: Temporary until consensus flow fully works
     async fn run_direct(
@@ -906,6 +1117,9 @@ impl ConsensusRuntime {
 
             match message.as_ref() {
                 Message::Cardano((raw_blk_info, CardanoMessage::BlockAvailable(raw_block))) => {
+                    if !self.admit(raw_blk_info).await {
+                        continue;
+                    }
                     let block_info = if force_validation && self.do_validation {
                         raw_blk_info.with_intent(BlockIntent::ValidateAndApply)
                     } else {
@@ -1052,14 +1266,17 @@ mod tests {
             context,
             blocks_proposed_topic: DEFAULT_BLOCKS_PROPOSED_TOPIC.1.to_string(),
             consensus_wants_topic: DEFAULT_CONSENSUS_WANTS_TOPIC.1.to_string(),
+            forks_topic: DEFAULT_FORKS_TOPIC.1.to_string(),
             event_queue,
             tree,
             block_data,
+            block_origins: HashMap::new(),
             validator_topics: Vec::new(),
             validator_subscriptions: Vec::new(),
             validation_timeout: Duration::from_secs(1),
             do_validation: false,
             stats: ConsensusStats::default(),
+            sync_stop: None,
         }
     }
 
@@ -1097,4 +1314,39 @@ mod tests {
             other => panic!("unexpected proposed message: {other:?}"),
         }
     }
+
+    #[test]
+    fn fork_is_published_and_listed_in_chain_tips() {
+        let mut runtime = test_runtime();
+        runtime.block_origins.insert(hash(5), "10.0.0.1:3001".to_string());
+        runtime.tree.check_block_wanted(hash(2), hash(1), 2371, 420_860).unwrap();
+        runtime.tree.check_block_wanted(hash(3), hash(2), 2372, 420_880).unwrap();
+        runtime.tree.check_block_wanted(hash(5), hash(1), 2371, 420_870).unwrap();
+
+        let events: Vec<_> = runtime.event_queue.lock().unwrap().drain(..).collect();
+        let messages = runtime.resolve_observer_events(events);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, DEFAULT_FORKS_TOPIC.1);
+        match message_at(&messages, 0) {
+            Message::Consensus(ConsensusMessage::ForkDetected(fork)) => {
+                assert_eq!(fork.hash, hash(5));
+                assert_eq!(fork.fork_point, hash(1));
+                assert!(!fork.favoured);
+                assert_eq!(fork.origin_peer.as_deref(), Some("10.0.0.1:3001"));
+            }
+            other => panic!("unexpected fork message: {other:?}"),
+        }
+
+        let tips = runtime.chain_tips();
+        assert_eq!(tips.len(), 2);
+        assert_eq!(tips[0].hash, hash(3));
+        assert!(tips[0].favoured);
+        assert_eq!(tips[0].branch_length, 0);
+        assert_eq!(tips[0].density, 2.0 / 21.0);
+        assert_eq!(tips[1].hash, hash(5));
+        assert!(!tips[1].favoured);
+        assert_eq!(tips[1].fork_point, 2370);
+        assert_eq!(tips[1].branch_length, 1);
+        assert_eq!(tips[1].origin_peer.as_deref(), Some("10.0.0.1:3001"));
+    }
 }
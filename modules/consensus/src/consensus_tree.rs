@@ -1,8 +1,16 @@
 //! Consensus tree data structure for tracking volatile chain forks.
 //!
 //! Implements the Praos `maxvalid` chain selection rule: select the
-//! longest valid chain, with ties broken in favour of the current chain.
+//! longest valid chain. Ties between equally long chains go to the tip
+//! with the lower leader VRF output, and otherwise to the current chain.
 //! The bounded variant rejects chains forking deeper than k blocks.
+//!
+//! The Ouroboros Genesis density rule (prefer the chain with more blocks in
+//! the `s` slots after the intersection) is deliberately not applied. It only
+//! decides between chains forking more than k blocks back, which Praos never
+//! switches to, and the Haskell node only uses it while syncing in Genesis
+//! mode. For forks within k blocks the Praos analysis already guarantees the
+//! longest chain is the honest one, so length alone is the rule here.
 
 use acropolis_common::BlockHash;
 use std::collections::HashMap;
@@ -115,7 +123,8 @@ impl ConsensusTree {
     /// Find the tip of the longest chain starting from the root.
     ///
     /// Implements the Praos `maxvalid` rule: returns the longest chain
-    /// tip. Ties are broken in favour of the current `favoured_tip`
+    /// tip. Ties are broken by the leader VRF output of the tips, and if
+    /// that does not decide, in favour of the current `favoured_tip`
     /// (Praos paper, line 667-668).
     pub fn get_favoured_chain(&self) -> Option<BlockHash> {
         let root = self.root?;
@@ -143,19 +152,39 @@ impl ConsensusTree {
             if child_len > max_length {
                 max_length = child_len;
                 best_tip = child_tip;
-            } else if child_len == max_length {
-                // Tie-break: favour current tip (Praos maxvalid)
-                if Some(child_tip) == self.favoured_tip
-                    || self.is_ancestor_of(child_tip, self.favoured_tip)
-                {
-                    best_tip = child_tip;
-                }
+            } else if child_len == max_length && self.wins_tie(child_tip, best_tip) {
+                best_tip = child_tip;
             }
         }
 
         (max_length + 1, best_tip)
     }
 
+    /// Returns true if `candidate` should replace `best` as the tip of two
+    /// equally long chains.
+    ///
+    /// As in the Haskell node, the current chain is only given up for a
+    /// tip with a strictly lower leader VRF output, so a tip without a
+    /// known VRF output never displaces the current chain.
+    fn wins_tie(&self, candidate: BlockHash, best: BlockHash) -> bool {
+        let vrf = |hash: BlockHash| self.blocks.get(&hash).and_then(|b| b.tiebreak_vrf.as_deref());
+        let lower_vrf = |a: BlockHash, b: BlockHash| match (vrf(a), vrf(b)) {
+            (Some(a), Some(b)) => a < b,
+            _ => false,
+        };
+        let is_current = |hash: BlockHash| {
+            Some(hash) == self.favoured_tip || self.is_ancestor_of(hash, self.favoured_tip)
+        };
+
+        if is_current(best) {
+            lower_vrf(candidate, best)
+        } else if is_current(candidate) {
+            !lower_vrf(best, candidate)
+        } else {
+            lower_vrf(candidate, best)
+        }
+    }
+
     /// Returns true if `candidate` is an ancestor of `descendant`
     /// (i.e., `descendant` is on a chain that passes through `candidate`).
     fn is_ancestor_of(&self, candidate: BlockHash, descendant: Option<BlockHash>) -> bool {
@@ -224,6 +253,16 @@ impl ConsensusTree {
         false
     }
 
+    /// Returns the tips of all chains in the tree, longest first.
+    ///
+    /// A tree without forks has a single tip, the favoured one.
+    pub fn tips(&self) -> Vec<BlockHash> {
+        let mut tips: Vec<&TreeBlock> =
+            self.blocks.values().filter(|b| b.children.is_empty()).collect();
+        tips.sort_by(|a, b| b.number.cmp(&a.number).then(a.slot.cmp(&b.slot)));
+        tips.into_iter().map(|b| b.hash).collect()
+    }
+
     /// Compute the fork depth: how many blocks back from the current
     /// favoured chain the fork point of the given block's chain is.
     ///
@@ -318,6 +357,19 @@ impl ConsensusTree {
         parent_hash: BlockHash,
         number: u64,
         slot: u64,
+    ) -> Result<Vec<BlockHash>, ConsensusTreeError> {
+        self.check_block_wanted_with_vrf(hash, parent_hash, number, slot, None)
+    }
+
+    /// As `check_block_wanted`, also recording the block's leader VRF
+    /// output to break ties between equally long chains.
+    pub fn check_block_wanted_with_vrf(
+        &mut self,
+        hash: BlockHash,
+        parent_hash: BlockHash,
+        number: u64,
+        slot: u64,
+        tiebreak_vrf: Option<Vec<u8>>,
     ) -> Result<Vec<BlockHash>, ConsensusTreeError> {
         // Idempotent for already-known block headers: avoid reinserting
         // the same hash and duplicating parent->child edges.
@@ -361,13 +413,14 @@ impl ConsensusTree {
         let old_tip = self.favoured_tip;
 
         // Tentatively insert as Offered to check fork depth
-        let block = TreeBlock::new(
+        let mut block = TreeBlock::new(
             hash,
             number,
             slot,
             Some(parent_hash),
             BlockValidationStatus::Offered,
         );
+        block.tiebreak_vrf = tiebreak_vrf;
         self.blocks.insert(hash, block);
         if let Some(parent_block) = self.blocks.get_mut(&parent_hash) {
             parent_block.children.push(hash);
@@ -384,6 +437,10 @@ impl ConsensusTree {
             });
         }
 
+        if self.blocks[&parent_hash].children.len() > 1 {
+            self.observer.fork_detected(parent_hash, hash);
+        }
+
         // Compute new favoured chain
         let new_tip = self.get_favoured_chain();
         self.favoured_tip = new_tip;
@@ -825,6 +882,7 @@ mod tests {
         proposed: Mutex<Vec<(u64, BlockHash)>>,
         rollbacks: Mutex<Vec<u64>>,
         rejected: Mutex<Vec<BlockHash>>,
+        forks: Mutex<Vec<(BlockHash, BlockHash)>>,
     }
 
     impl TestObserver {
@@ -833,6 +891,7 @@ mod tests {
                 proposed: Mutex::new(Vec::new()),
                 rollbacks: Mutex::new(Vec::new()),
                 rejected: Mutex::new(Vec::new()),
+                forks: Mutex::new(Vec::new()),
             }
         }
     }
@@ -849,6 +908,10 @@ mod tests {
         fn block_rejected(&self, hash: BlockHash) {
             self.rejected.lock().unwrap().push(hash);
         }
+
+        fn fork_detected(&self, fork_point: BlockHash, hash: BlockHash) {
+            self.forks.lock().unwrap().push((fork_point, hash));
+        }
     }
 
     /// Helper: create a BlockHash from a u8 value (for test convenience).
@@ -1018,6 +1081,32 @@ mod tests {
         assert_eq!(tree.fork_depth(hash(7)).unwrap(), 3);
     }
 
+    #[test]
+    fn test_tips_and_fork_detected() {
+        let (mut tree, obs) = make_tree(2160);
+        let obs = unsafe { &*obs };
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted(hash(2), hash(1), 1, 1).unwrap();
+        tree.check_block_wanted(hash(3), hash(2), 2, 2).unwrap();
+        assert_eq!(tree.tips(), vec![hash(3)]);
+        assert!(obs.forks.lock().unwrap().is_empty());
+
+        // Branch off block 2, and again off the root
+        tree.check_block_wanted(hash(4), hash(2), 2, 3).unwrap();
+        tree.check_block_wanted(hash(5), hash(4), 3, 4).unwrap();
+        tree.check_block_wanted(hash(6), hash(1), 1, 5).unwrap();
+        assert_eq!(
+            *obs.forks.lock().unwrap(),
+            vec![(hash(2), hash(4)), (hash(1), hash(6))]
+        );
+        assert_eq!(tree.tips(), vec![hash(5), hash(3), hash(6)]);
+        assert_eq!(tree.favoured_tip(), Some(hash(5)));
+
+        // Re-offering a known block is not a new fork
+        tree.check_block_wanted(hash(4), hash(2), 2, 3).unwrap();
+        assert_eq!(obs.forks.lock().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_fork_topologies() {
         // 1. Linear chain
@@ -1627,4 +1716,189 @@ mod tests {
         assert!(root_block.parent.is_none()); // New root has no parent
         assert!(root_block.number >= 2); // At or after prune boundary
     }
+
+    #[test]
+    fn test_equal_length_fork_with_lower_vrf_wins() {
+        let (mut tree, obs) = make_tree(2160);
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted_with_vrf(hash(2), hash(1), 1, 1, Some(vec![5; 32])).unwrap();
+
+        let wanted =
+            tree.check_block_wanted_with_vrf(hash(3), hash(1), 1, 1, Some(vec![3; 32])).unwrap();
+
+        assert_eq!(tree.favoured_tip(), Some(hash(3)));
+        assert_eq!(wanted, vec![hash(3)]);
+        assert_eq!(*unsafe { &*obs }.rollbacks.lock().unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_equal_length_fork_with_higher_vrf_is_not_selected() {
+        let (mut tree, obs) = make_tree(2160);
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted_with_vrf(hash(2), hash(1), 1, 1, Some(vec![5; 32])).unwrap();
+
+        let wanted =
+            tree.check_block_wanted_with_vrf(hash(3), hash(1), 1, 1, Some(vec![7; 32])).unwrap();
+
+        assert_eq!(tree.favoured_tip(), Some(hash(2)));
+        assert!(wanted.is_empty());
+        assert!(unsafe { &*obs }.rollbacks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_equal_length_fork_without_vrf_keeps_current_chain() {
+        let (mut tree, obs) = make_tree(2160);
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted_with_vrf(hash(2), hash(1), 1, 1, Some(vec![5; 32])).unwrap();
+
+        tree.check_block_wanted(hash(3), hash(1), 1, 1).unwrap();
+
+        assert_eq!(tree.favoured_tip(), Some(hash(2)));
+        assert!(unsafe { &*obs }.rollbacks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_longer_sparse_chain_beats_shorter_dense_chain() {
+        // Praos compares length only - a fork with its blocks packed into the
+        // first slots after the intersection doesn't win on density
+        let (mut tree, _) = make_tree(2160);
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted(hash(2), hash(1), 1, 1).unwrap();
+        tree.check_block_wanted(hash(3), hash(2), 2, 2).unwrap();
+        tree.check_block_wanted(hash(4), hash(1), 1, 50).unwrap();
+        tree.check_block_wanted(hash(5), hash(4), 2, 100).unwrap();
+        tree.check_block_wanted(hash(6), hash(5), 3, 150).unwrap();
+
+        assert_eq!(tree.favoured_tip(), Some(hash(6)));
+    }
+
+    #[test]
+    fn test_longer_chain_beats_lower_vrf() {
+        let (mut tree, _) = make_tree(2160);
+        tree.set_root(hash(1), 0, 0);
+        tree.check_block_wanted_with_vrf(hash(2), hash(1), 1, 1, Some(vec![0; 32])).unwrap();
+        tree.check_block_wanted_with_vrf(hash(3), hash(1), 1, 1, Some(vec![9; 32])).unwrap();
+        tree.check_block_wanted_with_vrf(hash(4), hash(3), 2, 2, Some(vec![9; 32])).unwrap();
+
+        assert_eq!(tree.favoured_tip(), Some(hash(4)));
+    }
+
+    /// Simulated forks: blocks are offered one at a time on top of a random
+    /// earlier block, each with a random leader VRF output.
+    mod simulated_forks {
+        use super::*;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        const SEEDS: u64 = 50;
+        const BLOCKS: u64 = 200;
+
+        fn block_hash(n: u64) -> BlockHash {
+            let mut bytes = [0u8; 32];
+            bytes[..8].copy_from_slice(&n.to_be_bytes());
+            BlockHash::from(bytes)
+        }
+
+        struct Offered {
+            hash: BlockHash,
+            number: u64,
+            vrf: [u8; 32],
+        }
+
+        #[test]
+        fn favoured_tip_is_longest_chain_with_lowest_vrf() {
+            for seed in 0..SEEDS {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let (mut tree, _) = make_tree(2160);
+                tree.set_root(block_hash(0), 0, 0);
+                let mut offered: Vec<Offered> = Vec::new();
+
+                for n in 1..=BLOCKS {
+                    let (parent, number) = match offered.len() {
+                        0 => (block_hash(0), 1),
+                        len => {
+                            let parent = &offered[rng.random_range(0..len)];
+                            (parent.hash, parent.number + 1)
+                        }
+                    };
+                    let vrf: [u8; 32] = rng.random();
+                    let hash = block_hash(n);
+                    tree.check_block_wanted_with_vrf(
+                        hash,
+                        parent,
+                        number,
+                        number,
+                        Some(vrf.to_vec()),
+                    )
+                    .unwrap();
+                    offered.push(Offered { hash, number, vrf });
+
+                    let best = offered
+                        .iter()
+                        .max_by(|a, b| a.number.cmp(&b.number).then(b.vrf.cmp(&a.vrf)))
+                        .unwrap();
+                    assert_eq!(
+                        tree.favoured_tip(),
+                        Some(best.hash),
+                        "seed {seed}, block {n}"
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn rollbacks_never_exceed_k() {
+            const K: u64 = 3;
+            for seed in 0..SEEDS {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let (mut tree, obs) = make_tree(K);
+                tree.set_root(block_hash(0), 0, 0);
+                let mut offered: Vec<Offered> = Vec::new();
+
+                for n in 1..=BLOCKS {
+                    let tip = tree.favoured_tip().unwrap();
+                    let tip_number = tree.get_block(&tip).unwrap().number;
+
+                    // Fork from anywhere near the tip, some deeper than k
+                    let candidates: Vec<&Offered> = offered
+                        .iter()
+                        .filter(|b| {
+                            b.number + 2 * K >= tip_number && tree.get_block(&b.hash).is_some()
+                        })
+                        .collect();
+                    let (parent, number) = match candidates.len() {
+                        0 => (tip, tip_number + 1),
+                        len => {
+                            let parent = candidates[rng.random_range(0..len)];
+                            (parent.hash, parent.number + 1)
+                        }
+                    };
+                    let vrf: [u8; 32] = rng.random();
+                    let hash = block_hash(n);
+
+                    match tree.check_block_wanted_with_vrf(
+                        hash,
+                        parent,
+                        number,
+                        number,
+                        Some(vrf.to_vec()),
+                    ) {
+                        Ok(_) => offered.push(Offered { hash, number, vrf }),
+                        Err(ConsensusTreeError::ForkTooDeep { fork_depth, max_k }) => {
+                            assert!(fork_depth > max_k);
+                            assert!(tree.get_block(&hash).is_none());
+                            assert_eq!(tree.favoured_tip(), Some(tip));
+                        }
+                        Err(e) => panic!("seed {seed}, block {n}: unexpected error {e}"),
+                    }
+
+                    for to in unsafe { &*obs }.rollbacks.lock().unwrap().drain(..) {
+                        assert!(
+                            tip_number - to <= K,
+                            "seed {seed}, block {n}: rollback from {tip_number} to {to}"
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -41,6 +41,9 @@ pub struct TreeBlock {
     pub status: BlockValidationStatus,
     /// True for the synthetic root inserted when starting from genesis (Origin).
     pub is_genesis_root: bool,
+    /// Leader VRF output from the header, used to break ties between
+    /// equally long chains; `None` if not known.
+    pub tiebreak_vrf: Option<Vec<u8>>,
 }
 
 impl TreeBlock {
@@ -61,6 +64,7 @@ impl TreeBlock {
             children: Vec::new(),
             status,
             is_genesis_root: false,
+            tiebreak_vrf: None,
         }
     }
 }
@@ -22,4 +22,8 @@ pub trait ConsensusTreeObserver {
     ///
     /// PNI should sanction the peers that provided this block.
     fn block_rejected(&self, hash: BlockHash);
+
+    /// A block has been inserted as a further child of `fork_point`,
+    /// starting a new branch.
+    fn fork_detected(&self, _fork_point: BlockHash, _hash: BlockHash) {}
 }
@@ -0,0 +1,52 @@
+use acropolis_common::configuration::ModuleConfig;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CorrectnessVerifierConfig {
+    pub spdd_subscribe_topic: String,
+    pub spo_rewards_subscribe_topic: String,
+
+    /// Where the discrepancy reports are published
+    pub publish_report_topic: String,
+
+    /// Reference SPDD files, with {} for the epoch or a range of epochs
+    /// (10-14), one amount column per epoch
+    pub spdd_files: Option<String>,
+
+    /// Reference rewards files, with {} for the epoch the rewards were earned in
+    pub rewards_files: Option<String>,
+
+    /// Exit the process on any discrepancy, so that a test run fails
+    pub fail_on_discrepancy: bool,
+}
+
+impl Default for CorrectnessVerifierConfig {
+    fn default() -> Self {
+        Self {
+            spdd_subscribe_topic: "cardano.spo.distribution".to_string(),
+            spo_rewards_subscribe_topic: "cardano.spo.rewards".to_string(),
+            publish_report_topic: "cardano.verification.report".to_string(),
+            spdd_files: None,
+            rewards_files: None,
+            fail_on_discrepancy: false,
+        }
+    }
+}
+
+impl ModuleConfig for CorrectnessVerifierConfig {
+    const MODULE: &'static str = "correctness-verifier";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.spdd_files.is_none() && self.rewards_files.is_none() {
+            errors.push("needs spdd-files and/or rewards-files to check against".to_string());
+        }
+        for (name, template) in [
+            ("spdd-files", &self.spdd_files),
+            ("rewards-files", &self.rewards_files),
+        ] {
+            if template.as_ref().is_some_and(|template| !template.contains("{}")) {
+                errors.push(format!("{name} must contain {{}} for the epoch"));
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Acropolis correctness verifier module for Caryatid
+//! Checks the SPDD and SPO rewards published at each epoch boundary against
+//! reference dumps from the Haskell node, and publishes a discrepancy report
+
+use acropolis_common::{
+    caryatid::ChunkAssembler,
+    configuration::ModuleConfig,
+    messages::{
+        CardanoMessage, CorrectnessCheck, DiscrepancyReportMessage, Message, MonitoringMessage,
+    },
+    metrics,
+    spdd_chunks::SPDDAssembler,
+};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+mod configuration;
+mod diff;
+mod reference;
+pub use configuration::CorrectnessVerifierConfig;
+use reference::ReferenceFiles;
+
+/// Correctness verifier module
+#[module(
+    message_type(Message),
+    name = "correctness-verifier",
+    description = "Checks published SPDD and rewards against reference data"
+)]
+pub struct CorrectnessVerifier;
+
+/// Publishes the reports, failing the process if asked to
+struct Reporter {
+    context: Arc<Context<Message>>,
+    topic: String,
+    fail_on_discrepancy: bool,
+}
+
+impl Reporter {
+    async fn report(&self, report: DiscrepancyReportMessage) {
+        let check = format!("{:?}", report.check);
+        if report.discrepancies.is_empty() {
+            info!(
+                "{check} for epoch {} matches {} ({} values)",
+                report.epoch, report.reference, report.compared
+            );
+        } else {
+            for discrepancy in &report.discrepancies {
+                error!(
+                    "{check} for epoch {}, {}: reference {:?}, published {:?}",
+                    report.epoch, discrepancy.key, discrepancy.reference, discrepancy.published
+                );
+            }
+            error!(
+                "{check} for epoch {} has {} discrepancies with {} ({} values)",
+                report.epoch,
+                report.discrepancies.len(),
+                report.reference,
+                report.compared
+            );
+            metrics::counter(
+                "acropolis_correctness_discrepancies_total",
+                "Published values which differ from the reference data",
+                &[("check", &check)],
+            )
+            .inc_by(report.discrepancies.len() as u64);
+        }
+
+        let failed = !report.discrepancies.is_empty();
+        let message = Arc::new(Message::Monitoring(MonitoringMessage::Discrepancy(report)));
+        if let Err(e) = self.context.publish(&self.topic, message).await {
+            error!("Could not publish discrepancy report: {e:#}");
+        }
+        if failed {
+            self.fail();
+        }
+    }
+
+    /// A reference which can't be read mustn't let a test run pass
+    fn reference_error(&self, check: CorrectnessCheck, epoch: u64, e: anyhow::Error) {
+        error!("Could not check {check:?} for epoch {epoch}: {e:#}");
+        self.fail();
+    }
+
+    fn fail(&self) {
+        if self.fail_on_discrepancy {
+            error!("Exiting on correctness failure (fail-on-discrepancy)");
+            std::process::exit(1);
+        }
+    }
+}
+
+impl CorrectnessVerifier {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = CorrectnessVerifierConfig::load(&config)?;
+        let reporter = Arc::new(Reporter {
+            context: context.clone(),
+            topic: cfg.publish_report_topic.clone(),
+            fail_on_discrepancy: cfg.fail_on_discrepancy,
+        });
+
+        if let Some(template) = &cfg.spdd_files {
+            let files = ReferenceFiles::scan(template)?;
+            info!(
+                "Checking SPDD against {} epochs from {template}",
+                files.epochs()
+            );
+
+            info!("Creating subscriber on '{}'", cfg.spdd_subscribe_topic);
+            let mut subscription = context.subscribe(&cfg.spdd_subscribe_topic).await?;
+            let reporter = reporter.clone();
+            context.run(async move {
+                let mut assembler = SPDDAssembler::default();
+                while let Ok((_, message)) = subscription.read().await {
+                    let assembled;
+                    let spdd = match message.as_ref() {
+                        Message::Cardano((_, CardanoMessage::SPOStakeDistribution(spdd))) => spdd,
+                        Message::Cardano((_, CardanoMessage::SPOStakeDistributionChunk(chunk))) => {
+                            match assembler.push(chunk) {
+                                Ok(Some(spdd)) => {
+                                    assembled = spdd;
+                                    &assembled
+                                }
+                                Ok(None) => continue,
+                                Err(e) => {
+                                    error!("Can't reassemble SPDD: {e:#}");
+                                    continue;
+                                }
+                            }
+                        }
+                        Message::Cardano((_, CardanoMessage::StateTransition(_))) => {
+                            assembler.reset();
+                            continue;
+                        }
+                        _ => continue,
+                    };
+
+                    let epoch = spdd.epoch;
+                    let Some(file) = files.get(epoch) else {
+                        debug!("No reference SPDD for epoch {epoch}");
+                        continue;
+                    };
+                    match reference::read_spdd(file) {
+                        Ok(reference) => {
+                            let (compared, discrepancies) = diff::diff(
+                                &diff::spdd_values(
+                                    reference.iter().map(|(pool, amount)| (pool, *amount)),
+                                ),
+                                &diff::published_spdd(&spdd.spos),
+                            );
+                            reporter
+                                .report(DiscrepancyReportMessage {
+                                    check: CorrectnessCheck::SPDD,
+                                    epoch,
+                                    reference: file.name(),
+                                    compared,
+                                    discrepancies,
+                                })
+                                .await;
+                        }
+                        Err(e) => reporter.reference_error(CorrectnessCheck::SPDD, epoch, e),
+                    }
+                }
+            });
+        }
+
+        if let Some(template) = &cfg.rewards_files {
+            let files = ReferenceFiles::scan(template)?;
+            info!(
+                "Checking SPO rewards against {} epochs from {template}",
+                files.epochs()
+            );
+
+            info!(
+                "Creating subscriber on '{}'",
+                cfg.spo_rewards_subscribe_topic
+            );
+            let mut subscription = context.subscribe(&cfg.spo_rewards_subscribe_topic).await?;
+            context.run(async move {
+                while let Ok((_, message)) = subscription.read().await {
+                    let Message::Cardano((_, CardanoMessage::SPORewards(rewards))) =
+                        message.as_ref()
+                    else {
+                        continue;
+                    };
+
+                    // Paid at the end of the epoch after the one they were earned in,
+                    // which is what the reference files are named by
+                    let Some(epoch) = rewards.epoch.checked_sub(1) else {
+                        continue;
+                    };
+                    let Some(file) = files.get(epoch) else {
+                        debug!("No reference rewards for epoch {epoch}");
+                        continue;
+                    };
+                    match reference::read_rewards(file) {
+                        Ok(reference) => {
+                            let (compared, discrepancies) = diff::diff(
+                                &diff::rewards_values(
+                                    reference.iter().map(|(pool, rewards)| (pool, *rewards)),
+                                ),
+                                &diff::published_rewards(&rewards.spos),
+                            );
+                            reporter
+                                .report(DiscrepancyReportMessage {
+                                    check: CorrectnessCheck::SPORewards,
+                                    epoch,
+                                    reference: file.name(),
+                                    compared,
+                                    discrepancies,
+                                })
+                                .await;
+                        }
+                        Err(e) => reporter.reference_error(CorrectnessCheck::SPORewards, epoch, e),
+                    }
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
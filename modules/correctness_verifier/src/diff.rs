@@ -0,0 +1,143 @@
+//! Comparing published values with the reference
+
+use acropolis_common::{messages::Discrepancy, DelegatedStake, Lovelace, PoolId, SPORewards};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::reference::PoolRewards;
+
+/// Values which differ, and how many were compared. A zero value is the same
+/// as an absent one, since neither side lists every pool with nothing in it.
+pub fn diff(
+    reference: &BTreeMap<String, Lovelace>,
+    published: &BTreeMap<String, Lovelace>,
+) -> (usize, Vec<Discrepancy>) {
+    let keys: BTreeSet<&String> = reference.keys().chain(published.keys()).collect();
+    let discrepancies = keys
+        .iter()
+        .filter_map(|key| {
+            let reference = reference.get(*key).copied().filter(|amount| *amount != 0);
+            let published = published.get(*key).copied().filter(|amount| *amount != 0);
+            (reference != published).then(|| Discrepancy {
+                key: key.to_string(),
+                reference,
+                published,
+            })
+        })
+        .collect();
+    (keys.len(), discrepancies)
+}
+
+/// Active stake by pool, and the total
+pub fn spdd_values<'a>(
+    spdd: impl IntoIterator<Item = (&'a PoolId, Lovelace)>,
+) -> BTreeMap<String, Lovelace> {
+    let mut values = BTreeMap::new();
+    let mut total = 0;
+    for (pool, amount) in spdd {
+        total += amount;
+        values.insert(pool.to_string(), amount);
+    }
+    values.insert("total".to_string(), total);
+    values
+}
+
+pub fn published_spdd(spos: &[(PoolId, DelegatedStake)]) -> BTreeMap<String, Lovelace> {
+    spdd_values(spos.iter().map(|(pool, stake)| (pool, stake.active)))
+}
+
+/// Total and operator's rewards by pool
+pub fn rewards_values<'a>(
+    rewards: impl IntoIterator<Item = (&'a PoolId, PoolRewards)>,
+) -> BTreeMap<String, Lovelace> {
+    let mut values = BTreeMap::new();
+    for (pool, pool_rewards) in rewards {
+        values.insert(format!("{pool} total"), pool_rewards.total);
+        values.insert(format!("{pool} operator"), pool_rewards.operator);
+    }
+    values
+}
+
+pub fn published_rewards(spos: &[(PoolId, SPORewards)]) -> BTreeMap<String, Lovelace> {
+    rewards_values(spos.iter().map(|(pool, rewards)| {
+        (
+            pool,
+            PoolRewards {
+                total: rewards.total_rewards,
+                operator: rewards.operator_rewards,
+            },
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_is_the_same_as_absent() {
+        // As the accounts-state verifier's cases: (published, reference)
+        let cases: [(Option<Lovelace>, Option<Lovelace>); 10] = [
+            (Some(0), None),
+            (Some(1), None),
+            (None, Some(0)),
+            (None, Some(1)),
+            (Some(0), Some(0)),
+            (Some(0), Some(10)),
+            (Some(10), Some(0)),
+            (Some(2), Some(2)),
+            (Some(2), Some(3)),
+            (Some(3), Some(2)),
+        ];
+        let pools: Vec<PoolId> = (0..cases.len()).map(|i| PoolId::from([i as u8; 28])).collect();
+
+        let published = spdd_values(
+            cases
+                .iter()
+                .zip(&pools)
+                .filter_map(|((published, _), pool)| Some((pool, (*published)?))),
+        );
+        let reference = spdd_values(
+            cases
+                .iter()
+                .zip(&pools)
+                .filter_map(|((_, reference), pool)| Some((pool, (*reference)?))),
+        );
+
+        // Both total 18, so only the pools differ
+        let (compared, discrepancies) = diff(&reference, &published);
+        assert_eq!(compared, 11);
+        let keys: BTreeSet<String> = discrepancies.into_iter().map(|d| d.key).collect();
+        let expected: BTreeSet<String> = [1, 3, 5, 6, 8, 9].map(|i| pools[i].to_string()).into();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn rewards_compare_total_and_operator() {
+        let pool = PoolId::from([1; 28]);
+        let published = published_rewards(&[(
+            pool,
+            SPORewards {
+                total_rewards: 100,
+                operator_rewards: 10,
+            },
+        )]);
+        let reference = rewards_values([(
+            &pool,
+            PoolRewards {
+                total: 100,
+                operator: 12,
+            },
+        )]);
+
+        let (compared, discrepancies) = diff(&reference, &published);
+        assert_eq!(compared, 2);
+        assert_eq!(
+            discrepancies,
+            vec![Discrepancy {
+                key: format!("{pool} operator"),
+                reference: Some(12),
+                published: Some(10),
+            }]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Reference data dumped from the Haskell node (or DBSync), as CSV or JSON
+//! files named by a template with {} for the epoch.
+//!
+//! SPDD files are CSV with a pool_id column then an amount column per epoch - a
+//! file for a range of epochs (spdd.mainnet.507-511.csv) has one for each - or
+//! JSON objects of pool ID to amount. Rewards files are CSV with
+//! spo,address,type,amount columns, or JSON arrays of objects with those fields.
+
+use acropolis_common::{Lovelace, PoolId};
+use anyhow::{anyhow, bail, Result};
+use hex::FromHex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{read_dir, File},
+    io::BufReader,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Where one epoch's data is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceFile {
+    pub path: PathBuf,
+
+    /// Amount column for the epoch, and how many columns there are, in CSV
+    column: usize,
+    columns: usize,
+}
+
+impl ReferenceFile {
+    fn is_json(&self) -> bool {
+        self.path.extension().is_some_and(|extension| extension == "json")
+    }
+
+    pub fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Pool operator's and total rewards, as in SPORewardsMessage
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolRewards {
+    pub total: Lovelace,
+    pub operator: Lovelace,
+}
+
+/// Reference files found for a template, by epoch
+#[derive(Debug, Default)]
+pub struct ReferenceFiles {
+    files: HashMap<u64, ReferenceFile>,
+}
+
+impl ReferenceFiles {
+    /// Find the files in the template's directory which match it
+    pub fn scan(template: &str) -> Result<Self> {
+        let template_path = Path::new(template);
+        let (Some(dir), Some(pattern)) = (
+            template_path.parent(),
+            template_path.file_name().and_then(|name| name.to_str()),
+        ) else {
+            bail!("Bad reference file template {template}");
+        };
+        let Some((prefix, suffix)) = pattern.split_once("{}") else {
+            bail!("Reference file template {template} needs {{}} in the file name");
+        };
+
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let entries = read_dir(dir)
+            .map_err(|e| anyhow!("Could not read {} for {template}: {e}", dir.display()))?;
+
+        let mut files = HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let Some(epochs) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(prefix)?.strip_suffix(suffix))
+                .and_then(epochs)
+            else {
+                continue;
+            };
+
+            let columns = (epochs.end() - epochs.start()) as usize + 2;
+            for (index, epoch) in epochs.enumerate() {
+                let file = ReferenceFile {
+                    path: path.clone(),
+                    column: index + 1,
+                    columns,
+                };
+                if let Some(other) = files.insert(epoch, file) {
+                    warn!(
+                        "Epoch {epoch} is in {} and {}",
+                        other.name(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        Ok(Self { files })
+    }
+
+    pub fn epochs(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn get(&self, epoch: u64) -> Option<&ReferenceFile> {
+        self.files.get(&epoch)
+    }
+}
+
+/// Epochs named by the {} part of a file name - one, or a range like 10-14
+fn epochs(part: &str) -> Option<RangeInclusive<u64>> {
+    match part.split_once('-') {
+        None => part.parse().ok().map(|epoch| epoch..=epoch),
+        Some((start, end)) => {
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            (start <= end).then_some(start..=end)
+        }
+    }
+}
+
+fn parse_pool(pool: &str) -> Result<PoolId> {
+    Vec::from_hex(pool)
+        .ok()
+        .and_then(|bytes| PoolId::try_from(bytes).ok())
+        .ok_or_else(|| anyhow!("bad pool ID {pool}"))
+}
+
+/// Stake per pool. Pools with no amount for the epoch are left out.
+pub fn read_spdd(file: &ReferenceFile) -> Result<BTreeMap<PoolId, Lovelace>> {
+    let name = file.name();
+    let mut spdd = BTreeMap::new();
+    if file.is_json() {
+        if file.columns != 2 {
+            bail!("{name}: JSON files can only hold one epoch");
+        }
+        let json: BTreeMap<String, Lovelace> =
+            serde_json::from_reader(BufReader::new(File::open(&file.path)?))
+                .map_err(|e| anyhow!("{name}: {e}"))?;
+        for (pool, amount) in json {
+            spdd.insert(
+                parse_pool(&pool).map_err(|e| anyhow!("{name}: {e}"))?,
+                amount,
+            );
+        }
+        return Ok(spdd);
+    }
+
+    let mut reader = csv::Reader::from_path(&file.path).map_err(|e| anyhow!("{name}: {e}"))?;
+    for (row, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| anyhow!("{name} row {}: {e}", row + 1))?;
+        if record.len() != file.columns {
+            bail!(
+                "{name} row {}: {} columns, expected {}",
+                row + 1,
+                record.len(),
+                file.columns
+            );
+        }
+        let pool = parse_pool(&record[0]).map_err(|e| anyhow!("{name} row {}: {e}", row + 1))?;
+        let amount = &record[file.column];
+        if amount.is_empty() {
+            continue;
+        }
+        let amount =
+            amount.parse::<Lovelace>().map_err(|e| anyhow!("{name} row {}: {e}", row + 1))?;
+        if spdd.insert(pool, amount).is_some() {
+            bail!("{name} row {}: {pool} is in the file twice", row + 1);
+        }
+    }
+    Ok(spdd)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RewardRow {
+    spo: String,
+    #[serde(rename = "type")]
+    reward_type: String,
+    amount: Lovelace,
+}
+
+/// Rewards per pool, adding up each leader and member reward. Refunds are not
+/// pool rewards, so are left out.
+pub fn read_rewards(file: &ReferenceFile) -> Result<BTreeMap<PoolId, PoolRewards>> {
+    let name = file.name();
+    if file.columns != 2 {
+        bail!("{name}: rewards files can only hold one epoch");
+    }
+
+    let rows: Vec<RewardRow> = if file.is_json() {
+        serde_json::from_reader(BufReader::new(File::open(&file.path)?))
+            .map_err(|e| anyhow!("{name}: {e}"))?
+    } else {
+        csv::Reader::from_path(&file.path)
+            .and_then(|mut reader| reader.deserialize().collect())
+            .map_err(|e| anyhow!("{name}: {e}"))?
+    };
+
+    let mut rewards: BTreeMap<PoolId, PoolRewards> = BTreeMap::new();
+    for row in rows {
+        let leader = match row.reward_type.as_str() {
+            "leader" => true,
+            "member" => false,
+            _ => continue,
+        };
+        let pool = parse_pool(&row.spo).map_err(|e| anyhow!("{name}: {e}"))?;
+        let pool_rewards = rewards.entry(pool).or_default();
+        pool_rewards.total += row.amount;
+        if leader {
+            pool_rewards.operator += row.amount;
+        }
+    }
+    Ok(rewards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epochs_from_file_names() {
+        assert_eq!(epochs("208"), Some(208..=208));
+        assert_eq!(epochs("507-511"), Some(507..=511));
+        assert_eq!(epochs("511-507"), None);
+        assert_eq!(epochs("latest"), None);
+    }
+
+    #[test]
+    fn reads_spdd_from_accounts_state_test_data() -> Result<()> {
+        let files = ReferenceFiles::scan("../accounts_state/test-data/spdd-test.{}.csv")?;
+        let file = files.get(99999).expect("test file found");
+        assert_eq!(
+            read_spdd(file)?,
+            BTreeMap::from([
+                (PoolId::from([1; 28]), 1000),
+                (PoolId::from([0xee; 28]), 1111),
+            ])
+        );
+
+        // Ranges have a column per epoch
+        let files = ReferenceFiles::scan("../accounts_state/test-data/spdd.mainnet.{}.csv")?;
+        let file = files.get(509).expect("range file found");
+        assert_eq!((file.column, file.columns), (3, 6));
+        Ok(())
+    }
+}
@@ -3,12 +3,21 @@ use anyhow::Result;
 use caryatid_sdk::async_trait;
 use pallas::ledger::traverse::MultiEraTx;
 
+use crate::filter::TxFilter;
+
 #[async_trait]
 pub trait ChainIndex: Send + Sync + 'static {
     /// A human-readable identifier for the index.
     /// Used for logging, error messages, and cursor store keys.
     fn name(&self) -> String;
 
+    /// Only the transactions matching this filter are passed to the index.
+    ///
+    /// Can be overridden from the config, under `filters.<name>`.
+    fn filter(&self) -> Option<TxFilter> {
+        None
+    }
+
     /// High-level transaction handler.
     ///
     /// Most indexes override this
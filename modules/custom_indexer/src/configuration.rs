@@ -1,13 +1,20 @@
+use std::collections::HashMap;
+
 use acropolis_common::configuration::SyncMode;
 use anyhow::Result;
 use config::Config;
 
+use crate::filter::TxFilter;
+
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct CustomIndexerConfig {
     pub sync_command_publisher_topic: String,
     pub genesis_complete_topic: String,
     pub txs_subscribe_topic: String,
+    /// Transaction filters by index name
+    #[serde(default)]
+    pub filters: HashMap<String, TxFilter>,
     #[serde(flatten)]
     global: GlobalConfig,
 }
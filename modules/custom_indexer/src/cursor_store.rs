@@ -8,7 +8,10 @@ use anyhow::Result;
 use caryatid_sdk::async_trait;
 use fjall::{Database, Keyspace, KeyspaceCreateOptions};
 use tokio::sync::Mutex;
-use tracing::warn;
+use tokio_postgres::{Client, NoTls};
+use tracing::{error, warn};
+
+use crate::postgres_sink::PostgresSink;
 
 #[derive(
     Debug,
@@ -163,3 +166,89 @@ impl CursorStore for FjallCursorStore {
         }
     }
 }
+
+// Postgres backed cursor store (Commits queued index writes with the cursors)
+const CURSOR_TABLE: &str = "indexer_cursors";
+
+pub struct PostgresCursorStore {
+    client: Mutex<Client>,
+    sink: PostgresSink,
+}
+
+impl PostgresCursorStore {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("CursorStore: postgres connection failed: {:#}", e);
+            }
+        });
+
+        client
+            .batch_execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {CURSOR_TABLE} (name TEXT PRIMARY KEY, entry BYTEA NOT NULL)"
+            ))
+            .await?;
+
+        Ok(Self {
+            client: Mutex::new(client),
+            sink: PostgresSink::new(),
+        })
+    }
+
+    /// Sink for indexes whose tables live in the same database. Everything queued on
+    /// it is committed atomically with the next cursor save.
+    pub fn sink(&self) -> PostgresSink {
+        self.sink.clone()
+    }
+}
+
+#[async_trait]
+impl CursorStore for PostgresCursorStore {
+    async fn load(&self) -> Result<HashMap<String, CursorEntry>> {
+        let client = self.client.lock().await;
+        let rows = client.query(&format!("SELECT name, entry FROM {CURSOR_TABLE}"), &[]).await?;
+
+        let mut out = HashMap::new();
+        for row in rows {
+            let name: String = row.get(0);
+            let entry: Vec<u8> = row.get(1);
+            match bincode::deserialize::<CursorEntry>(&entry) {
+                Ok(entry) => {
+                    out.insert(name, entry);
+                }
+                Err(e) => warn!(
+                    "CursorStore: failed to deserialize cursor for '{}': {:#}",
+                    name, e
+                ),
+            }
+        }
+
+        Ok(out)
+    }
+
+    async fn save(&self, entries: &HashMap<String, CursorEntry>) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let transaction = client.transaction().await?;
+
+        // If anything fails the whole block is dropped, and replayed from the
+        // previously saved cursors on restart
+        for statement in self.sink.take_pending() {
+            transaction.execute(&statement.sql, &statement.param_refs()).await?;
+        }
+
+        let upsert = transaction
+            .prepare(&format!(
+                "INSERT INTO {CURSOR_TABLE} (name, entry) VALUES ($1, $2) \
+                 ON CONFLICT (name) DO UPDATE SET entry = EXCLUDED.entry"
+            ))
+            .await?;
+        for (name, entry) in entries {
+            let entry = bincode::serialize(entry)?;
+            transaction.execute(&upsert, &[name, &entry]).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+}
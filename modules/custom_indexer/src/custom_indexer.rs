@@ -7,7 +7,10 @@
 pub mod chain_index;
 mod configuration;
 pub mod cursor_store;
+pub mod export_sink;
+pub mod filter;
 mod index_actor;
+pub mod postgres_sink;
 mod utils;
 
 use std::{
@@ -29,7 +32,10 @@ use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 
 use crate::{
-    chain_index::ChainIndex, configuration::CustomIndexerConfig, cursor_store::CursorStore,
+    chain_index::ChainIndex,
+    configuration::CustomIndexerConfig,
+    cursor_store::CursorStore,
+    filter::{TxFeatures, TxFilter},
     index_actor::IndexActor,
 };
 
@@ -84,7 +90,7 @@ impl<CS: CursorStore> CustomIndexer<CS> {
     async fn run(
         &self,
         context: Arc<Context<Message>>,
-        cfg: CustomIndexerConfig,
+        mut cfg: CustomIndexerConfig,
         mut txs_subscription: Box<dyn Subscription<Message>>,
     ) -> Result<()> {
         let indexes: HashMap<String, IndexConfig> = {
@@ -124,18 +130,29 @@ impl<CS: CursorStore> CustomIndexer<CS> {
             if sync_points.back().is_none_or(|p| p.slot() > tip.slot()) {
                 sync_points = my_sync_points;
             }
-            actors.push(IndexActor::new(
-                name,
-                index.index,
-                cursor,
-                SECURITY_PARAMETER_K,
-            ));
+            // Filters from the config take precedence over the index's own
+            let filter = cfg
+                .filters
+                .remove(&name)
+                .or_else(|| index.index.filter())
+                .filter(|f: &TxFilter| !f.is_empty());
+            if filter.is_some() {
+                info!("index {name} is filtered");
+            }
+            actors.push(
+                IndexActor::new(name, index.index, cursor, SECURITY_PARAMETER_K)
+                    .with_filter(filter),
+            );
         }
 
         if sync_points.is_empty() {
             warn!("no indexes configured, nothing to do");
             return Ok(());
         }
+        for name in cfg.filters.keys() {
+            warn!("filter configured for unknown index {name}");
+        }
+        let any_filtered = actors.iter().any(|a| a.is_filtered());
 
         if !cfg.sync_mode().is_mithril() {
             // TODO: pass multiple points
@@ -159,9 +176,15 @@ impl<CS: CursorStore> CustomIndexer<CS> {
             match message.as_ref() {
                 Message::Cardano((block, CardanoMessage::ReceivedTxs(txs_msg))) => {
                     let block = Arc::new(block.clone());
-                    let txs: Vec<Arc<[u8]>> =
-                        txs_msg.txs.iter().map(|tx| Arc::<[u8]>::from(tx.as_slice())).collect();
-                    join_all(actors.iter_mut().map(|a| a.apply_txs(block.clone(), &txs))).await;
+                    let txs = &txs_msg.txs;
+                    // Extract what the filters match on once, for all the indexes
+                    let features: Vec<_> = if any_filtered {
+                        txs.iter().map(|tx| TxFeatures::decode(tx).ok()).collect()
+                    } else {
+                        Vec::new()
+                    };
+                    join_all(actors.iter_mut().map(|a| a.apply_txs(block.clone(), txs, &features)))
+                        .await;
                     // update cursors
                     for actor in actors.iter_mut() {
                         let cursor = cursors.get_mut(&actor.name).unwrap();
@@ -0,0 +1,228 @@
+//! File export sink for index outputs
+//!
+//! Rows are buffered per range of blocks and written out as one file per range, in
+//! hive-style partitions (`<dir>/<name>/block_range=<start>/<start>-<end>.parquet`)
+//! which DuckDB and Spark can load directly. The file schema is derived from the
+//! serde row type; CSV needs flat rows, Parquet also handles nested ones.
+//!
+//! A range is only written once a block after it arrives, so rollbacks within the
+//! open range just drop the buffered rows. Ranges already written are not
+//! rewritten - use a range well above the security parameter if that matters.
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use acropolis_common::{BlockInfo, Point};
+use anyhow::{bail, Result};
+use arrow::datatypes::FieldRef;
+use parquet::arrow::ArrowWriter;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_arrow::schema::{SchemaLike, TracingOptions};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+pub struct ExportSink<T> {
+    dir: PathBuf,
+    format: ExportFormat,
+    blocks_per_file: u64,
+    /// Parquet schema, traced from `T`
+    fields: Vec<FieldRef>,
+    /// First block number of the open range
+    range_start: Option<u64>,
+    /// Buffered rows of the open range, with the slot they came from
+    rows: Vec<(u64, T)>,
+}
+
+impl<T: Serialize + DeserializeOwned> ExportSink<T> {
+    pub fn new(
+        dir: impl AsRef<Path>,
+        name: &str,
+        format: ExportFormat,
+        blocks_per_file: u64,
+    ) -> Result<Self> {
+        if blocks_per_file == 0 {
+            bail!("export sink {name} needs at least one block per file");
+        }
+        let dir = dir.as_ref().join(name);
+        fs::create_dir_all(&dir)?;
+        let fields = match format {
+            ExportFormat::Parquet => {
+                Vec::<FieldRef>::from_type::<T>(TracingOptions::default().allow_null_fields(true))?
+            }
+            ExportFormat::Csv => Vec::new(),
+        };
+
+        Ok(Self {
+            dir,
+            format,
+            blocks_per_file,
+            fields,
+            range_start: None,
+            rows: Vec::new(),
+        })
+    }
+
+    /// Add a row derived from `block`, writing out the previous range if the block
+    /// is beyond it
+    pub fn push(&mut self, block: &BlockInfo, row: T) -> Result<()> {
+        let range_start = block.number - block.number % self.blocks_per_file;
+        if self.range_start.is_some_and(|start| start < range_start) {
+            self.flush()?;
+        }
+        self.range_start = Some(range_start);
+        self.rows.push((block.slot, row));
+        Ok(())
+    }
+
+    /// Drop the buffered rows after `point`
+    pub fn rollback(&mut self, point: &Point) {
+        self.rows.retain(|(slot, _)| *slot <= point.slot());
+    }
+
+    /// Write out the open range, even if it is not complete yet
+    pub fn flush(&mut self) -> Result<()> {
+        let Some(start) = self.range_start.take() else {
+            return Ok(());
+        };
+        let rows: Vec<T> = std::mem::take(&mut self.rows).into_iter().map(|(_, row)| row).collect();
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let end = start + self.blocks_per_file - 1;
+        let partition = self.dir.join(format!("block_range={start}"));
+        fs::create_dir_all(&partition)?;
+        let path = partition.join(format!("{start}-{end}.{}", self.format.extension()));
+
+        // Write to a temporary file first, so readers never see a partial file
+        let tmp_path = path.with_extension("tmp");
+        match self.format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_path(&tmp_path)?;
+                for row in &rows {
+                    writer.serialize(row)?;
+                }
+                writer.flush()?;
+            }
+            ExportFormat::Parquet => {
+                let batch = serde_arrow::to_record_batch(&self.fields, &rows)?;
+                let mut writer =
+                    ArrowWriter::try_new(File::create(&tmp_path)?, batch.schema(), None)?;
+                writer.write(&batch)?;
+                writer.close()?;
+            }
+        }
+        fs::rename(&tmp_path, &path)?;
+
+        info!("Exported {} rows to {}", rows.len(), path.display());
+        Ok(())
+    }
+}
+
+impl<T> Drop for ExportSink<T> {
+    fn drop(&mut self) {
+        if !self.rows.is_empty() {
+            tracing::warn!(
+                "Export sink for {} dropped with {} unwritten rows",
+                self.dir.display(),
+                self.rows.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{BlockHash, BlockIntent, BlockStatus, Era};
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Row {
+        pool: String,
+        cost: u64,
+    }
+
+    fn block(number: u64) -> BlockInfo {
+        BlockInfo {
+            status: BlockStatus::Immutable,
+            intent: BlockIntent::Apply,
+            slot: number * 20,
+            number,
+            hash: BlockHash::default(),
+            epoch: 0,
+            epoch_slot: number * 20,
+            new_epoch: false,
+            is_new_era: false,
+            timestamp: 0,
+            era: Era::Conway,
+            tip_slot: None,
+        }
+    }
+
+    fn row(cost: u64) -> Row {
+        Row {
+            pool: "pool1".to_string(),
+            cost,
+        }
+    }
+
+    fn csv_rows(path: &Path) -> Vec<Row> {
+        csv::Reader::from_path(path).unwrap().deserialize().map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn ranges_are_written_once_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = ExportSink::new(dir.path(), "costs", ExportFormat::Csv, 10).unwrap();
+        sink.push(&block(3), row(1)).unwrap();
+        sink.push(&block(9), row(2)).unwrap();
+
+        let first = dir.path().join("costs/block_range=0/0-9.csv");
+        assert!(!first.exists());
+
+        sink.push(&block(12), row(3)).unwrap();
+        assert_eq!(csv_rows(&first), vec![row(1), row(2)]);
+
+        sink.flush().unwrap();
+        assert_eq!(
+            csv_rows(&dir.path().join("costs/block_range=10/10-19.csv")),
+            vec![row(3)]
+        );
+    }
+
+    #[test]
+    fn rollback_drops_buffered_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = ExportSink::new(dir.path(), "costs", ExportFormat::Csv, 10).unwrap();
+        sink.push(&block(3), row(1)).unwrap();
+        sink.push(&block(5), row(2)).unwrap();
+        sink.rollback(&Point::Specific {
+            hash: BlockHash::default(),
+            slot: block(4).slot,
+        });
+        sink.flush().unwrap();
+
+        assert_eq!(
+            csv_rows(&dir.path().join("costs/block_range=0/0-9.csv")),
+            vec![row(1)]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Declarative transaction filters for custom indexes
+//!
+//! An index with a filter is only handed the transactions which match it. The
+//! features the filters look at are extracted once per transaction and shared by
+//! every index, so simple indexes don't each have to decode and scan every tx.
+
+use std::collections::HashSet;
+
+use acropolis_common::{Address, PolicyId, ScriptHash, ShelleyAddressDelegationPart};
+use anyhow::Result;
+use pallas::ledger::{
+    primitives::{alonzo, conway},
+    traverse::{MultiEraCert, MultiEraMeta, MultiEraTx},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertificateKind {
+    StakeRegistration,
+    StakeDeregistration,
+    StakeDelegation,
+    VoteDelegation,
+    PoolRegistration,
+    PoolRetirement,
+    GenesisKeyDelegation,
+    MoveInstantaneousRewards,
+    DRepRegistration,
+    DRepDeregistration,
+    DRepUpdate,
+    CommitteeHotAuthorization,
+    CommitteeResignation,
+}
+
+/// Matches a transaction with any of the listed features. An empty filter matches
+/// every transaction.
+///
+/// In TOML, under `[module.custom-indexer.filters.<index name>]`:
+/// ```toml
+/// addresses = ["addr1...", "stake1..."]
+/// policy-ids = ["<hex>"]
+/// metadata-labels = [674]
+/// certificates = ["pool-registration", "pool-retirement"]
+/// script-hashes = ["<hex>"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct TxFilter {
+    /// Output addresses. A stake address matches any output delegating to it.
+    pub addresses: HashSet<Address>,
+    /// Minted, burned or output assets
+    pub policy_ids: HashSet<PolicyId>,
+    pub metadata_labels: HashSet<u64>,
+    pub certificates: HashSet<CertificateKind>,
+    /// Scripts locking outputs, in their payment or delegation part, or minting
+    pub script_hashes: HashSet<ScriptHash>,
+}
+
+impl TxFilter {
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+            && self.policy_ids.is_empty()
+            && self.metadata_labels.is_empty()
+            && self.certificates.is_empty()
+            && self.script_hashes.is_empty()
+    }
+
+    pub fn matches(&self, features: &TxFeatures) -> bool {
+        self.is_empty()
+            || features.addresses.iter().any(|a| self.addresses.contains(a))
+            || features.policy_ids.iter().any(|p| self.policy_ids.contains(p))
+            || features.metadata_labels.iter().any(|l| self.metadata_labels.contains(l))
+            || features.certificates.iter().any(|c| self.certificates.contains(c))
+            || features.script_hashes.iter().any(|s| self.script_hashes.contains(s))
+    }
+}
+
+/// Everything about a transaction a [`TxFilter`] can match on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TxFeatures {
+    /// Output addresses, and the stake addresses they delegate to
+    pub addresses: Vec<Address>,
+    pub policy_ids: Vec<PolicyId>,
+    pub metadata_labels: Vec<u64>,
+    pub certificates: Vec<CertificateKind>,
+    pub script_hashes: Vec<ScriptHash>,
+}
+
+impl TxFeatures {
+    pub fn decode(raw_tx: &[u8]) -> Result<Self> {
+        Ok(Self::from_tx(&MultiEraTx::decode(raw_tx)?))
+    }
+
+    pub fn from_tx(tx: &MultiEraTx) -> Self {
+        let mut features = Self::default();
+
+        for output in tx.outputs() {
+            if let Some(address) =
+                output.address().ok().and_then(|a| acropolis_codec::map_address(&a).ok())
+            {
+                if let Address::Shelley(shelley) = &address {
+                    features.script_hashes.extend(shelley.payment.to_script_hash());
+                    if let ShelleyAddressDelegationPart::ScriptHash(hash) = &shelley.delegation {
+                        features.script_hashes.push(*hash);
+                    }
+                }
+                if let Some(stake_address) = address.to_stake_address() {
+                    features.addresses.push(Address::Stake(stake_address));
+                }
+                features.addresses.push(address);
+            }
+            for policy in output.value().assets() {
+                features.policy_ids.push(PolicyId::from(**policy.policy()));
+            }
+        }
+
+        for policy in tx.mints() {
+            let policy_id = PolicyId::from(**policy.policy());
+            features.policy_ids.push(policy_id);
+            features.script_hashes.push(policy_id);
+        }
+
+        if let MultiEraMeta::AlonzoCompatible(metadata) = tx.metadata() {
+            features.metadata_labels.extend(metadata.iter().map(|(label, _)| *label));
+        }
+
+        for cert in tx.certs() {
+            features.certificates.extend(certificate_kinds(&cert));
+        }
+
+        features
+    }
+}
+
+fn certificate_kinds(cert: &MultiEraCert) -> Vec<CertificateKind> {
+    use CertificateKind::*;
+    match cert {
+        MultiEraCert::AlonzoCompatible(cert) => match cert.as_ref().as_ref() {
+            alonzo::Certificate::StakeRegistration { .. } => vec![StakeRegistration],
+            alonzo::Certificate::StakeDeregistration { .. } => vec![StakeDeregistration],
+            alonzo::Certificate::StakeDelegation { .. } => vec![StakeDelegation],
+            alonzo::Certificate::PoolRegistration { .. } => vec![PoolRegistration],
+            alonzo::Certificate::PoolRetirement { .. } => vec![PoolRetirement],
+            alonzo::Certificate::GenesisKeyDelegation { .. } => vec![GenesisKeyDelegation],
+            alonzo::Certificate::MoveInstantaneousRewardsCert { .. } => {
+                vec![MoveInstantaneousRewards]
+            }
+        },
+        MultiEraCert::Conway(cert) => match cert.as_ref().as_ref() {
+            conway::Certificate::StakeRegistration { .. } | conway::Certificate::Reg { .. } => {
+                vec![StakeRegistration]
+            }
+            conway::Certificate::StakeDeregistration { .. } | conway::Certificate::UnReg { .. } => {
+                vec![StakeDeregistration]
+            }
+            conway::Certificate::StakeDelegation { .. } => vec![StakeDelegation],
+            conway::Certificate::VoteDeleg { .. } => vec![VoteDelegation],
+            conway::Certificate::StakeVoteDeleg { .. } => vec![StakeDelegation, VoteDelegation],
+            conway::Certificate::StakeRegDeleg { .. } => {
+                vec![StakeRegistration, StakeDelegation]
+            }
+            conway::Certificate::VoteRegDeleg { .. } => vec![StakeRegistration, VoteDelegation],
+            conway::Certificate::StakeVoteRegDeleg { .. } => {
+                vec![StakeRegistration, StakeDelegation, VoteDelegation]
+            }
+            conway::Certificate::PoolRegistration { .. } => vec![PoolRegistration],
+            conway::Certificate::PoolRetirement { .. } => vec![PoolRetirement],
+            conway::Certificate::AuthCommitteeHot { .. } => vec![CommitteeHotAuthorization],
+            conway::Certificate::ResignCommitteeCold { .. } => vec![CommitteeResignation],
+            conway::Certificate::RegDRepCert { .. } => vec![DRepRegistration],
+            conway::Certificate::UnRegDRepCert { .. } => vec![DRepDeregistration],
+            conway::Certificate::UpdateDRepCert { .. } => vec![DRepUpdate],
+        },
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(TxFilter::default().matches(&TxFeatures::default()));
+    }
+
+    #[test]
+    fn filter_matches_any_listed_feature() {
+        let filter = TxFilter {
+            metadata_labels: HashSet::from([674]),
+            certificates: HashSet::from([CertificateKind::PoolRetirement]),
+            ..TxFilter::default()
+        };
+
+        let registration = TxFeatures {
+            certificates: vec![CertificateKind::PoolRegistration],
+            ..TxFeatures::default()
+        };
+        assert!(!filter.matches(&registration));
+
+        let retirement = TxFeatures {
+            certificates: vec![CertificateKind::PoolRetirement],
+            ..TxFeatures::default()
+        };
+        assert!(filter.matches(&retirement));
+
+        let message = TxFeatures {
+            metadata_labels: vec![721, 674],
+            ..TxFeatures::default()
+        };
+        assert!(filter.matches(&message));
+    }
+
+    #[test]
+    fn filters_load_from_toml() {
+        let filter: TxFilter = config::Config::builder()
+            .add_source(config::File::from_str(
+                "metadata-labels = [674]\ncertificates = [\"stake-delegation\"]",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(filter.metadata_labels, HashSet::from([674]));
+        assert_eq!(
+            filter.certificates,
+            HashSet::from([CertificateKind::StakeDelegation])
+        );
+    }
+}
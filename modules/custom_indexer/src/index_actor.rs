@@ -2,15 +2,20 @@ use std::{collections::VecDeque, sync::Arc};
 
 use acropolis_common::{BlockInfo, Point};
 use anyhow::{Context, Result};
+use bytes::Bytes;
 use tokio::sync::{mpsc, oneshot};
 use tracing::warn;
 
-use crate::{chain_index::ChainIndex, cursor_store::CursorEntry};
+use crate::{
+    chain_index::ChainIndex,
+    cursor_store::CursorEntry,
+    filter::{TxFeatures, TxFilter},
+};
 
 enum IndexCommand {
     ApplyTx {
         block: Arc<BlockInfo>,
-        tx: Arc<[u8]>,
+        tx: Bytes,
         response_tx: oneshot::Sender<Result<()>>,
     },
     Rollback {
@@ -26,6 +31,7 @@ pub struct IndexActor {
     next_tx: Option<u64>,
     halted: bool,
     security_param: u64,
+    filter: Option<TxFilter>,
 }
 
 impl IndexActor {
@@ -44,9 +50,19 @@ impl IndexActor {
             next_tx: cursor.next_tx,
             halted: false,
             security_param,
+            filter: None,
         }
     }
 
+    pub fn with_filter(mut self, filter: Option<TxFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
+    }
+
     pub fn update_cursor(&self, cursor: &mut CursorEntry) {
         cursor.next_tx = self.next_tx;
         let (Some(first), Some(last)) = (self.points.front(), self.points.back()) else {
@@ -74,7 +90,14 @@ impl IndexActor {
         }
     }
 
-    pub async fn apply_txs(&mut self, block: Arc<BlockInfo>, txs: &[Arc<[u8]>]) {
+    /// `features` holds the filter features of each tx, if any index is filtered.
+    /// Txs without features are always applied.
+    pub async fn apply_txs(
+        &mut self,
+        block: Arc<BlockInfo>,
+        txs: &[Bytes],
+        features: &[Option<TxFeatures>],
+    ) {
         // Origin is a virtual starting point (slot 0, no hash). Replace it with
         // the actual first block we receive so that rollback detection doesn't
         // confuse it with a different block at slot 0.
@@ -150,6 +173,11 @@ impl IndexActor {
             if self.next_tx.is_some_and(|i| i as usize > idx) {
                 continue;
             }
+            if let (Some(filter), Some(Some(features))) = (&self.filter, features.get(idx)) {
+                if !filter.matches(features) {
+                    continue;
+                }
+            }
 
             if let Err(error) = self.call_apply_tx(block.clone(), tx.clone()).await {
                 self.next_tx = Some(idx as u64);
@@ -188,7 +216,7 @@ impl IndexActor {
         }
     }
 
-    async fn call_apply_tx(&self, block: Arc<BlockInfo>, tx: Arc<[u8]>) -> Result<()> {
+    async fn call_apply_tx(&self, block: Arc<BlockInfo>, tx: Bytes) -> Result<()> {
         let (response_tx, response_rx) = oneshot::channel();
         let cmd = IndexCommand::ApplyTx {
             block,
@@ -228,7 +256,10 @@ async fn index_actor(mut index: Box<dyn ChainIndex>, mut rx: mpsc::Receiver<Inde
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::VecDeque, sync::Arc};
+    use std::{
+        collections::{HashSet, VecDeque},
+        sync::Arc,
+    };
 
     use acropolis_common::{
         params::SECURITY_PARAMETER_K, BlockInfo, BlockIntent, BlockStatus, Era, Point,
@@ -236,7 +267,12 @@ mod tests {
     use caryatid_sdk::async_trait;
     use pallas::ledger::traverse::MultiEraTx;
 
-    use crate::{chain_index::ChainIndex, cursor_store::CursorEntry, index_actor::IndexActor};
+    use crate::{
+        chain_index::ChainIndex,
+        cursor_store::CursorEntry,
+        filter::{CertificateKind, TxFeatures, TxFilter},
+        index_actor::IndexActor,
+    };
 
     #[derive(Default)]
     pub struct MockIndex {
@@ -296,11 +332,11 @@ mod tests {
         }
     }
 
-    fn valid_tx() -> Arc<[u8]> {
+    fn valid_tx() -> Bytes {
         let raw_tx = hex::decode(
             "84a600d9010281825820565573dcde964aa30e7e307531ee6c6f8e47279dcbade4b4301e9ef291b6791601018282583901b786e57fa44f9707d023719c60b712a3ebbaf89a932ee87ea4de39ce65f459f57e462edc82d90225fac6162f4757c226ad50a7adf230e4c81b0000000ac336383982583901b786e57fa44f9707d023719c60b712a3ebbaf89a932ee87ea4de39ce65f459f57e462edc82d90225fac6162f4757c226ad50a7adf230e4c81a004c4b40021a0002aac1031a0a0d7b1705a1581de165f459f57e462edc82d90225fac6162f4757c226ad50a7adf230e4c81a42fa31010801a100d9010282825820ed67aef668355b2f6220aeb7b5118adeb31b7cf0de7d9a4bb4ea0aac7bdfea5a58406718e1a35b9fae1c91d0ca08b90c0270bcd0e98b9df2b826b0ea6b9742b93631e0f2c43d098a9a8fdd58f1ba44c649d397ca32bd207a9d3fa784611694184904825820086b567b1b34bd97e1a79c46533ed4e771e170848a50983297605f1d7fe6acb8584040fe7d3108c4eaca8484ef9590a52214dae09af501aa84cba4f093c590acdd2c9c15977fc381c0224306567e775d2c7e62a65319fcf504657221e7648411bd0af5f6"
         ).unwrap();
-        Arc::from(raw_tx.as_slice())
+        Bytes::from(raw_tx)
     }
 
     fn new_cursor(slot: u64) -> CursorEntry {
@@ -325,7 +361,7 @@ mod tests {
         let mut cursor = new_cursor(0);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(block.clone(), &txs).await;
+        actor.apply_txs(block.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(actor.halted);
@@ -351,7 +387,7 @@ mod tests {
         let mut cursor = new_cursor(0);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(b1.clone(), &txs).await;
+        actor.apply_txs(b1.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(actor.halted);
@@ -365,7 +401,7 @@ mod tests {
         assert_eq!(cursor.next_tx, Some(0));
 
         let b2 = Arc::new(test_block(2));
-        actor.apply_txs(b2.clone(), &txs).await;
+        actor.apply_txs(b2.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(actor.halted);
@@ -391,7 +427,7 @@ mod tests {
         let mut cursor = new_cursor(0);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(b1.clone(), &txs).await;
+        actor.apply_txs(b1.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(!actor.halted);
@@ -405,6 +441,32 @@ mod tests {
         assert_eq!(cursor.next_tx, None);
     }
 
+    #[tokio::test]
+    async fn apply_txs_skips_txs_not_matching_filter() {
+        let mock = MockIndex {
+            on_tx: Some(Box::new(|| anyhow::bail!("filtered tx applied"))),
+            ..Default::default()
+        };
+
+        let b1 = Arc::new(test_block(1));
+        let txs = vec![valid_tx()];
+        let features = vec![Some(TxFeatures::decode(&txs[0]).unwrap())];
+        let mut cursor = new_cursor(0);
+
+        let filter = TxFilter {
+            certificates: HashSet::from([CertificateKind::PoolRetirement]),
+            ..TxFilter::default()
+        };
+        let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K)
+            .with_filter(Some(filter));
+        actor.apply_txs(b1.clone(), &txs, &features).await;
+        actor.update_cursor(&mut cursor);
+
+        assert!(!actor.halted);
+        assert_eq!(cursor.points.back().map(|p| p.slot()), Some(b1.slot));
+        assert_eq!(cursor.next_tx, None);
+    }
+
     #[tokio::test]
     async fn apply_txs_skips_txs_from_older_block() {
         let mock = MockIndex {
@@ -429,7 +491,7 @@ mod tests {
         cursor.next_tx = Some(0);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(b1.clone(), &txs).await;
+        actor.apply_txs(b1.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(!actor.halted);
@@ -467,7 +529,7 @@ mod tests {
         cursor.next_tx = Some(0);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(b2.clone(), &txs).await;
+        actor.apply_txs(b2.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(!actor.halted);
@@ -495,7 +557,7 @@ mod tests {
         let mut cursor = new_cursor(123);
 
         let mut actor = IndexActor::new(mock.name(), Box::new(mock), &cursor, SECURITY_PARAMETER_K);
-        actor.apply_txs(b1.clone(), &txs).await;
+        actor.apply_txs(b1.clone(), &txs, &[]).await;
         actor.update_cursor(&mut cursor);
 
         assert!(actor.halted);
@@ -0,0 +1,71 @@
+//! Postgres sink for index writes
+//!
+//! Indexes queue their inserts and deletes on a [`PostgresSink`] instead of executing
+//! them directly. The [`PostgresCursorStore`](crate::cursor_store::PostgresCursorStore)
+//! sharing the sink runs everything queued in the same transaction which saves the
+//! cursors, so derived tables never get ahead of (or fall behind) the cursor.
+
+use std::sync::{Arc, Mutex};
+
+use tokio_postgres::types::ToSql;
+
+pub type SqlParam = Box<dyn ToSql + Send + Sync>;
+
+pub struct PendingStatement {
+    pub sql: String,
+    pub params: Vec<SqlParam>,
+}
+
+impl PendingStatement {
+    pub fn param_refs(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.params.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync)).collect()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PostgresSink {
+    pending: Arc<Mutex<Vec<PendingStatement>>>,
+}
+
+impl PostgresSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a statement, to be run when the cursors are next saved
+    pub fn execute(&self, sql: impl Into<String>, params: Vec<SqlParam>) {
+        self.pending.lock().unwrap().push(PendingStatement {
+            sql: sql.into(),
+            params,
+        });
+    }
+
+    /// Take everything queued so far, in the order it was queued
+    pub fn take_pending(&self) -> Vec<PendingStatement> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_are_taken_in_order_once() {
+        let sink = PostgresSink::new();
+        let clone = sink.clone();
+        sink.execute("INSERT INTO pools VALUES ($1)", vec![Box::new(1i64)]);
+        clone.execute("DELETE FROM pools WHERE slot > $1", vec![Box::new(5i64)]);
+
+        let pending = sink.take_pending();
+        assert_eq!(
+            pending.iter().map(|s| s.sql.as_str()).collect::<Vec<_>>(),
+            vec![
+                "INSERT INTO pools VALUES ($1)",
+                "DELETE FROM pools WHERE slot > $1"
+            ]
+        );
+        assert_eq!(pending[0].param_refs().len(), 1);
+        assert!(sink.take_pending().is_empty());
+    }
+}
@@ -4,7 +4,16 @@ use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper},
     configuration::{get_bool_flag, get_string_flag},
     declare_cardano_reader,
-    messages::{CardanoMessage, DRepStakeDistributionMessage, Message, StateTransitionMessage},
+    messages::{
+        CardanoMessage, DRepStakeDistributionMessage, Message, StateQuery, StateQueryResponse,
+        StateTransitionMessage,
+    },
+    metrics,
+    queries::{
+        drdd::{DRDDStateQuery, DRDDStateQueryResponse, DEFAULT_DRDD_QUERY_TOPIC},
+        errors::QueryError,
+    },
+    readiness,
     rest_helper::handle_rest_with_query_parameters,
     state_history::{StateHistory, StateHistoryStore},
 };
@@ -65,10 +74,15 @@ impl DRDDState {
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "drdd-state").await?;
+
         // Get configuration
         let handle_drdd_topic = get_string_flag(&config, DEFAULT_HANDLE_DRDD_TOPIC);
         info!("Creating request handler on '{}'", handle_drdd_topic);
 
+        let drdd_query_topic = get_string_flag(&config, DEFAULT_DRDD_QUERY_TOPIC);
+        info!("Creating query handler on '{}'", drdd_query_topic);
+
         let store_drdd = get_bool_flag(&config, DEFAULT_STORE_DRDD);
 
         let history_opt = if store_drdd {
@@ -119,6 +133,55 @@ impl DRDDState {
             handle_drdd(history_rest, params)
         });
 
+        // Handle DRDD queries
+        let history_query = history_opt.clone();
+        let query_metrics = metrics::QueryMetrics::new("drdd-state");
+        context.handle(&drdd_query_topic, move |message| {
+            let history_query = history_query.clone();
+            let timer = query_metrics.start(&message);
+            async move {
+                let _timer = timer;
+                let Message::StateQuery(StateQuery::DRDD(query)) = message.as_ref() else {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::DRDD(
+                        DRDDStateQueryResponse::Error(QueryError::internal_error(
+                            "Invalid message for drdd-state",
+                        )),
+                    )));
+                };
+
+                let Some(history) = history_query else {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::DRDD(
+                        DRDDStateQueryResponse::Error(QueryError::storage_disabled("DRDD")),
+                    )));
+                };
+
+                let locked = history.lock().await;
+
+                let response = match query {
+                    DRDDStateQuery::GetDRepVotingPower { drep } => match locked.current() {
+                        Some(state) => DRDDStateQueryResponse::DRepVotingPower(
+                            state.get_latest().voting_power(drep),
+                        ),
+                        None => DRDDStateQueryResponse::Error(QueryError::not_found(
+                            "DRep delegation distribution",
+                        )),
+                    },
+                    DRDDStateQuery::GetVotingPowerBreakdown => match locked.current() {
+                        Some(state) => DRDDStateQueryResponse::VotingPowerBreakdown(
+                            state.get_latest().voting_power_breakdown(),
+                        ),
+                        None => DRDDStateQueryResponse::Error(QueryError::not_found(
+                            "DRep delegation distribution",
+                        )),
+                    },
+                };
+
+                Arc::new(Message::StateQueryResponse(StateQueryResponse::DRDD(
+                    response,
+                )))
+            }
+        });
+
         Ok(())
     }
 }
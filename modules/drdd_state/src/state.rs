@@ -1,4 +1,6 @@
-use acropolis_common::DRepCredential;
+use acropolis_common::{
+    queries::drdd::DRepVotingPowerBreakdown, DRepChoice, DRepCredential, Lovelace,
+};
 use imbl::{OrdMap, OrdSet};
 use tracing::info;
 
@@ -14,6 +16,39 @@ pub struct DRepDistribution {
     pub no_confidence: u64,
 }
 
+impl DRepDistribution {
+    /// Stake delegated to a DRep, or to one of the special abstain and no confidence
+    /// options
+    pub fn voting_power(&self, drep: &DRepChoice) -> Lovelace {
+        match drep {
+            DRepChoice::Key(hash) => {
+                self.dreps.get(&DRepCredential::AddrKeyHash(*hash)).copied().unwrap_or(0)
+            }
+            DRepChoice::Script(hash) => {
+                self.dreps.get(&DRepCredential::ScriptHash(*hash)).copied().unwrap_or(0)
+            }
+            DRepChoice::Abstain => self.abstain,
+            DRepChoice::NoConfidence => self.no_confidence,
+        }
+    }
+
+    /// Stake delegated to key DReps, script DReps and the two special options
+    pub fn voting_power_breakdown(&self) -> DRepVotingPowerBreakdown {
+        let mut breakdown = DRepVotingPowerBreakdown {
+            abstain: self.abstain,
+            no_confidence: self.no_confidence,
+            ..Default::default()
+        };
+        for (credential, stake) in self.dreps.iter() {
+            match credential {
+                DRepCredential::AddrKeyHash(_) => breakdown.key += stake,
+                DRepCredential::ScriptHash(_) => breakdown.script += stake,
+            }
+        }
+        breakdown
+    }
+}
+
 impl State {
     pub fn new() -> Self {
         Self {
@@ -59,3 +94,33 @@ impl State {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::KeyHash;
+
+    #[test]
+    fn voting_power_breakdown_splits_key_and_script_dreps() {
+        let mut state = State::new();
+        state.apply_drdd_snapshot(
+            [
+                (DRepCredential::AddrKeyHash(KeyHash::new([1; 28])), 100),
+                (DRepCredential::AddrKeyHash(KeyHash::new([2; 28])), 20),
+                (DRepCredential::ScriptHash(KeyHash::new([3; 28])), 5),
+            ],
+            7,
+            3,
+        );
+
+        assert_eq!(
+            state.get_latest().voting_power_breakdown(),
+            DRepVotingPowerBreakdown {
+                key: 120,
+                script: 5,
+                abstain: 7,
+                no_confidence: 3,
+            }
+        );
+    }
+}
@@ -5,19 +5,28 @@ use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper, ValidationContext},
     configuration::{get_bool_flag, get_string_flag, StartupMode},
     declare_cardano_reader,
+    epoch_barrier::EpochBarrierAcker,
     messages::{
         CardanoMessage, GovernanceProceduresMessage, Message, ProtocolParamsMessage,
         SnapshotMessage, SnapshotStateMessage, StateQuery, StateQueryResponse,
         StateTransitionMessage, TxCertificatesMessage,
     },
+    metrics,
     queries::{
+        accounts::{AccountsStateQuery, AccountsStateQueryResponse, DEFAULT_ACCOUNTS_QUERY_TOPIC},
+        drdd::{DRDDStateQuery, DRDDStateQueryResponse, DEFAULT_DRDD_QUERY_TOPIC},
         errors::QueryError,
+        get_query_topic,
         governance::{
-            DRepDelegatorAddresses, DRepInfo, DRepInfoWithDelegators, DRepUpdates, DRepVotes,
-            DRepsList, GovernanceStateQuery, GovernanceStateQueryResponse,
+            DRepDelegatorAddresses, DRepDelegatorStakes, DRepInfo, DRepInfoWithDelegators,
+            DRepUpdates, DRepVotes, DRepsList, GovernanceStateQuery, GovernanceStateQueryResponse,
         },
+        utils::query_state,
     },
+    readiness,
+    state_digest::{StateDigest, StateDigestPublisher},
     state_history::{StateHistory, StateHistoryStore},
+    StakeAddress,
 };
 use anyhow::{bail, Result};
 use caryatid_sdk::{module, Context, Subscription};
@@ -152,6 +161,8 @@ impl DRepState {
         history: Arc<Mutex<StateHistory<State>>>,
         mut subs: Box<DRepSubscriptions>,
         mut drep_state_publisher: DRepStatePublisher,
+        digest_publisher: Option<StateDigestPublisher>,
+        barrier_acker: Option<EpochBarrierAcker>,
         validation_topic: String,
         context: Arc<Context<Message>>,
         storage_config: DRepStorageConfig,
@@ -224,6 +235,10 @@ impl DRepState {
 
             // Read from epoch-boundary messages only when it's a new epoch
             if let Some(new_epoch) = epoch {
+                if let Some(digest_publisher) = &digest_publisher {
+                    digest_publisher.publish(primary.block_info(), state.state_digest()).await;
+                }
+
                 state.update_num_dormant_epochs(new_epoch);
 
                 // Update expirations on epoch transition using the current protocol params.
@@ -293,11 +308,83 @@ impl DRepState {
                 }
 
                 history.lock().await.commit(block_info.number, state);
+
+                if let Some(acker) = barrier_acker.as_ref().filter(|_| epoch.is_some()) {
+                    acker.ack(block_info).await;
+                }
             }
         }
     }
 
+    /// Balances of a DRep's delegators from accounts-state, and the voting power of all
+    /// DReps from drdd-state
+    async fn delegator_stakes(
+        context: &Arc<Context<Message>>,
+        delegators: Vec<StakeAddress>,
+    ) -> Result<DRepDelegatorStakes, QueryError> {
+        let accounts_query_topic = get_query_topic(context.clone(), DEFAULT_ACCOUNTS_QUERY_TOPIC);
+        let msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
+            AccountsStateQuery::GetAccountsBalancesMap {
+                stake_addresses: delegators.clone(),
+            },
+        )));
+        let balances = query_state(
+            context,
+            &accounts_query_topic,
+            msg,
+            |message| match message {
+                Message::StateQueryResponse(StateQueryResponse::Accounts(
+                    AccountsStateQueryResponse::AccountsBalancesMap(map),
+                )) => Ok(map),
+                Message::StateQueryResponse(StateQueryResponse::Accounts(
+                    AccountsStateQueryResponse::Error(e),
+                )) => Err(e),
+                _ => Err(QueryError::internal_error(
+                    "Unexpected response from accounts-state",
+                )),
+            },
+        )
+        .await?;
+
+        let drdd_query_topic = get_query_topic(context.clone(), DEFAULT_DRDD_QUERY_TOPIC);
+        let msg = Arc::new(Message::StateQuery(StateQuery::DRDD(
+            DRDDStateQuery::GetVotingPowerBreakdown,
+        )));
+        let breakdown = query_state(context, &drdd_query_topic, msg, |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::DRDD(
+                DRDDStateQueryResponse::VotingPowerBreakdown(breakdown),
+            )) => Ok(breakdown),
+            Message::StateQueryResponse(StateQueryResponse::DRDD(
+                DRDDStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected response from drdd-state",
+            )),
+        })
+        .await;
+        let voting_power = match breakdown {
+            Ok(breakdown) => Some(breakdown),
+            // No distribution is stored, or none has been seen yet
+            Err(QueryError::StorageDisabled { .. } | QueryError::NotFound { .. }) => None,
+            Err(e) => return Err(e),
+        };
+
+        let delegators = delegators
+            .into_iter()
+            .map(|stake_address| {
+                let balance = balances.get(&stake_address).copied().unwrap_or(0);
+                (stake_address, balance)
+            })
+            .collect();
+        Ok(DRepDelegatorStakes {
+            delegators,
+            voting_power,
+        })
+    }
+
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "drep-state").await?;
+
         // Get configuration flags and topis
         let storage_config = DRepStorageConfig {
             store_info: get_bool_flag(&config, DEFAULT_STORE_INFO),
@@ -334,19 +421,24 @@ impl DRepState {
         info!("Creating DRep state publisher on '{validation_topic}'");
 
         // Initalize state history
-        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
-            "DRepState",
-            StateHistoryStore::default_block_store(),
-        )));
+        let history = Arc::new(Mutex::new(
+            StateHistory::<State>::new("DRepState", StateHistoryStore::default_block_store())
+                .with_size_estimate(State::estimated_size),
+        ));
         let history_run = history.clone();
         let query_history = history.clone();
         let ticker_history = history.clone();
         let ctx_run = context.clone();
+        let query_context = context.clone();
 
         // Query handler
+        let query_metrics = metrics::QueryMetrics::new("drep-state");
         context.handle(&drep_query_topic, move |message| {
             let history = query_history.clone();
+            let context = query_context.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Governance(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Governance(
                         GovernanceStateQueryResponse::Error(QueryError::internal_error(
@@ -355,6 +447,36 @@ impl DRepState {
                     )));
                 };
 
+                // The balances and voting power come from other modules, so the state is
+                // only locked to read the delegators
+                if let GovernanceStateQuery::GetDRepDelegatorStakes { drep_credential } = query {
+                    let delegators = match history.lock().await.current() {
+                        Some(state) => match state.get_drep_delegators(drep_credential) {
+                            Ok(Some(delegators)) => Ok(delegators.clone()),
+                            Ok(None) => Err(QueryError::not_found(format!(
+                                "DRep delegators for {:?} not found",
+                                drep_credential
+                            ))),
+                            Err(msg) => Err(QueryError::internal_error(msg)),
+                        },
+                        None => Err(QueryError::not_synced("No current DRep state")),
+                    };
+                    let response = match delegators {
+                        Ok(delegators) => {
+                            match Self::delegator_stakes(&context, delegators).await {
+                                Ok(stakes) => {
+                                    GovernanceStateQueryResponse::DRepDelegatorStakes(stakes)
+                                }
+                                Err(e) => GovernanceStateQueryResponse::Error(e),
+                            }
+                        }
+                        Err(e) => GovernanceStateQueryResponse::Error(e),
+                    };
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::Governance(
+                        response,
+                    )));
+                }
+
                 let locked = history.lock().await;
 
                 let response = match query {
@@ -363,7 +485,7 @@ impl DRepState {
                             let dreps = state.list();
                             GovernanceStateQueryResponse::DRepsList(DRepsList { dreps })
                         }
-                        None => GovernanceStateQueryResponse::Error(QueryError::internal_error(
+                        None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
                             "No current DRep state",
                         )),
                     },
@@ -410,9 +532,9 @@ impl DRepState {
                                     QueryError::internal_error(msg),
                                 ),
                             },
-                            None => GovernanceStateQueryResponse::Error(
-                                QueryError::internal_error("No current state"),
-                            ),
+                            None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
+                                "No current DRep state",
+                            )),
                         }
                     }
                     GovernanceStateQuery::GetDRepDelegators { drep_credential } => {
@@ -435,9 +557,9 @@ impl DRepState {
                                     QueryError::internal_error(msg),
                                 ),
                             },
-                            None => GovernanceStateQueryResponse::Error(
-                                QueryError::internal_error("No current state"),
-                            ),
+                            None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
+                                "No current DRep state",
+                            )),
                         }
                     }
                     GovernanceStateQuery::GetDRepMetadata { drep_credential } => {
@@ -456,9 +578,9 @@ impl DRepState {
                                     QueryError::internal_error(msg),
                                 ),
                             },
-                            None => GovernanceStateQueryResponse::Error(
-                                QueryError::internal_error("No current state"),
-                            ),
+                            None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
+                                "No current DRep state",
+                            )),
                         }
                     }
 
@@ -479,9 +601,9 @@ impl DRepState {
                                     QueryError::internal_error(msg),
                                 ),
                             },
-                            None => GovernanceStateQueryResponse::Error(
-                                QueryError::internal_error("No current state"),
-                            ),
+                            None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
+                                "No current DRep state",
+                            )),
                         }
                     }
                     GovernanceStateQuery::GetDRepVotes { drep_credential } => {
@@ -501,9 +623,9 @@ impl DRepState {
                                     QueryError::internal_error(msg),
                                 ),
                             },
-                            None => GovernanceStateQueryResponse::Error(
-                                QueryError::internal_error("No current state"),
-                            ),
+                            None => GovernanceStateQueryResponse::Error(QueryError::not_synced(
+                                "No current DRep state",
+                            )),
                         }
                     }
                     _ => GovernanceStateQueryResponse::Error(QueryError::internal_error(format!(
@@ -545,6 +667,9 @@ impl DRepState {
 
         // Publisher for DRep State
         let drep_state_publisher = DRepStatePublisher::new(context.clone(), drep_state_topic);
+        let digest_publisher =
+            StateDigestPublisher::from_config(context.clone(), &config, "drep-state");
+        let barrier_acker = EpochBarrierAcker::from_config(context.clone(), &config, "drep-state");
 
         // Start run task
         context.run(async move {
@@ -552,6 +677,8 @@ impl DRepState {
                 history_run,
                 Box::new(subscriptions),
                 drep_state_publisher,
+                digest_publisher,
+                barrier_acker,
                 validation_topic,
                 ctx_run,
                 storage_config,
@@ -8,6 +8,7 @@ use acropolis_common::{
         get_query_topic,
         governance::{DRepActionUpdate, DRepUpdateEvent, VoteRecord},
     },
+    state_digest::{SetDigest, StateDigest},
     validation::ValidationOutcomes,
     Anchor, DRepChoice, DRepCredential, DRepRecord, GovActionId, Lovelace, ProposalProcedure,
     StakeAddress, TxCertificate, TxCertificateWithPos, TxHash, Voter, VotingProcedures,
@@ -106,6 +107,16 @@ pub struct State {
 }
 
 impl State {
+    /// Estimated memory used by the state, for the state history memory budget
+    pub fn estimated_size(&self) -> usize {
+        self.dreps.len() * size_of::<(DRepCredential, DRepRecord)>()
+            + self.drep_expiry.len() * size_of::<(DRepCredential, u64)>()
+            + self.proposals_expires_after.len() * size_of::<(GovActionId, u64)>()
+            + self.historical_dreps.as_ref().map_or(0, |historical| {
+                historical.len() * size_of::<(DRepCredential, HistoricalDRepState)>()
+            })
+    }
+
     pub fn new(config: DRepStorageConfig) -> Self {
         Self {
             config,
@@ -221,7 +232,10 @@ impl State {
     }
 
     pub fn list(&self) -> Vec<DRepCredential> {
-        self.dreps.keys().cloned().collect()
+        let mut dreps: Vec<_> = self.dreps.keys().cloned().collect();
+        // Sorted so pages of the list are stable
+        dreps.sort();
+        dreps
     }
 
     async fn log_stats(&self) {
@@ -391,10 +405,11 @@ impl State {
                     }
 
                     if let Some(votes) = entry.votes.as_mut() {
-                        for vp in single_votes.voting_procedures.values() {
+                        for (proposal, vp) in single_votes.voting_procedures.iter() {
                             votes.push(VoteRecord {
                                 tx_hash: *tx_hash,
                                 vote_index: vp.vote_index,
+                                proposal: proposal.clone(),
                                 vote: vp.vote.clone(),
                             });
                         }
@@ -735,6 +750,22 @@ impl State {
     }
 }
 
+impl StateDigest for State {
+    /// Ledger state only - the historical state depends on the storage config
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        for (credential, record) in &self.dreps {
+            digest.insert(&("drep", credential, record))?;
+        }
+        for (credential, epoch) in &self.drep_expiry {
+            digest.insert(&("expiry", credential, epoch))?;
+        }
+        for (action_id, epoch) in &self.proposals_expires_after {
+            digest.insert(&("proposal", action_id, epoch))?;
+        }
+        digest.insert(&("dormant-epochs", self.num_dormant_epochs))
+    }
+}
+
 fn drep_choice_to_credential(choice: &DRepChoice) -> Option<DRepCredential> {
     match choice {
         DRepChoice::Key(k) => Some(DRepCredential::AddrKeyHash(*k)),
@@ -3,19 +3,28 @@
 
 use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper, ValidationContext},
-    configuration::{get_string_flag, StartupMode},
+    configuration::{get_bool_flag, get_string_flag, StartupMode},
     declare_cardano_reader,
+    epoch_barrier::{
+        epoch_barrier_enabled, epoch_barrier_participants, EpochBarrierAcker, EpochBarrierTracker,
+        DEFAULT_EPOCH_BARRIER_ACK_TOPIC, DEFAULT_EPOCH_BARRIER_COMPLETE_TOPIC,
+    },
     messages::{
-        BlockTxsMessage, CardanoMessage, EpochBootstrapMessage, GenesisCompleteMessage, Message,
-        ProtocolParamsMessage, RawBlockMessage, SnapshotMessage, SnapshotStateMessage, StateQuery,
+        BlockTxsMessage, CardanoMessage, EpochBarrierCompleteMessage, EpochBootstrapMessage,
+        GenesisCompleteMessage, Message, ProtocolParamsMessage, RawBlockMessage,
+        SPOStakeDistributionMessage, SnapshotMessage, SnapshotStateMessage, StateQuery,
         StateQueryResponse, StateTransitionMessage,
     },
+    metrics,
     queries::{
         epochs::{
             EpochsStateQuery, EpochsStateQueryResponse, LatestEpoch, DEFAULT_EPOCHS_QUERY_TOPIC,
         },
         errors::QueryError,
     },
+    readiness,
+    spdd_chunks::{SPDDAssembler, SPDDTotals},
+    state_digest::{StateDigest, StateDigestPublisher},
     state_history::{StateHistory, StateHistoryStore},
 };
 use anyhow::{anyhow, bail, Result};
@@ -24,7 +33,7 @@ use config::Config;
 use pallas::ledger::traverse::MultiEraHeader;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::{error, info, info_span, warn};
+use tracing::{debug, error, info, info_span, warn};
 
 mod epoch_activity_publisher;
 mod epoch_nonce_publisher;
@@ -62,6 +71,17 @@ declare_cardano_reader!(
     ProtocolParams,
     ProtocolParamsMessage
 );
+declare_cardano_reader!(
+    SPDDReader,
+    "spdd-subscribe-topic",
+    "cardano.spo.distribution",
+    SPOStakeDistribution,
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
+);
+
+/// Fill in each epoch's active stake from the SPDD published by accounts_state
+const DEFAULT_TRACK_ACTIVE_STAKE: (&str, bool) = ("track-active-stake", false);
 
 const DEFAULT_SNAPSHOT_SUBSCRIBE_TOPIC: (&str, &str) =
     ("snapshot-subscribe-topic", "cardano.snapshot");
@@ -135,6 +155,57 @@ impl EpochsState {
         }
     }
 
+    /// Collect epoch barrier acks from all the participating modules, and announce
+    /// each epoch as complete once they have all acked it
+    async fn coordinate_epoch_barrier(
+        context: Arc<Context<Message>>,
+        config: &Config,
+    ) -> Result<()> {
+        let ack_topic = get_string_flag(config, DEFAULT_EPOCH_BARRIER_ACK_TOPIC);
+        let complete_topic = get_string_flag(config, DEFAULT_EPOCH_BARRIER_COMPLETE_TOPIC);
+        let participants = epoch_barrier_participants(config);
+        info!("Coordinating epoch barrier for {participants:?} on '{ack_topic}'");
+        info!("Publishing epoch barrier completions on '{complete_topic}'");
+
+        let mut tracker = EpochBarrierTracker::new(participants);
+        let mut subscription = context.subscribe(&ack_topic).await?;
+        let run_context = context.clone();
+        context.run(async move {
+            loop {
+                let Ok((_, message)) = subscription.read().await else {
+                    error!("Failed to read epoch barrier ack");
+                    continue;
+                };
+                let Message::Cardano((block, CardanoMessage::EpochBarrierAck(ack))) =
+                    message.as_ref()
+                else {
+                    continue;
+                };
+
+                let Some(epoch) = tracker.ack(&ack.module, ack.epoch) else {
+                    debug!(
+                        module = ack.module,
+                        epoch = ack.epoch,
+                        "Epoch barrier waiting for {:?}",
+                        tracker.missing(ack.epoch)
+                    );
+                    continue;
+                };
+
+                info!(epoch, "Epoch barrier complete");
+                let message = Arc::new(Message::Cardano((
+                    block.clone(),
+                    CardanoMessage::EpochBarrierComplete(EpochBarrierCompleteMessage { epoch }),
+                )));
+                if let Err(e) = run_context.publish(&complete_topic, message).await {
+                    error!("Could not publish epoch barrier completion: {e:#}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
     /// Run loop
     #[allow(clippy::too_many_arguments)]
     async fn run(
@@ -144,9 +215,12 @@ impl EpochsState {
         mut params_reader: ParamsReader,
         mut block_reader: BlockReader,
         mut txs_reader: TxsReader,
+        mut spdd_reader: Option<SPDDReader>,
         snapshot_subscription: Option<Box<dyn Subscription<Message>>>,
         mut epoch_activity_publisher: EpochActivityPublisher,
         mut epoch_nonce_publisher: EpochNoncePublisher,
+        barrier_acker: Option<EpochBarrierAcker>,
+        digest_publisher: Option<StateDigestPublisher>,
         validation_topic: String,
         is_snapshot_mode: bool,
     ) -> Result<()> {
@@ -217,6 +291,12 @@ impl EpochsState {
             }
 
             let epoch = primary.epoch();
+
+            // Digest of the state as the previous epoch ended
+            if let (Some(digest_publisher), Some(_)) = (&digest_publisher, epoch) {
+                digest_publisher.publish(primary.block_info(), state.state_digest()).await;
+            }
+
             if primary.should_read_epoch_transition_messages() {
                 match ctx.consume("params_reader", params_reader.read_with_rollbacks().await)? {
                     RollbackWrapper::Normal((_, params)) => {
@@ -234,6 +314,19 @@ impl EpochsState {
                         epoch_activity_publisher.publish(&blk_info, ea).await,
                     );
                 }
+
+                // SPDD taken at this boundary, giving the active stake two epochs on
+                if let Some(reader) = spdd_reader.as_mut() {
+                    // Only the totals are needed, so the pools are dropped as they arrive
+                    let mut totals = SPDDTotals::default();
+                    match ctx.consume(
+                        "spdd_reader",
+                        reader.stream_with_rollbacks(&mut totals).await,
+                    )? {
+                        RollbackWrapper::Normal(_) => state.handle_spdd(&totals),
+                        RollbackWrapper::Rollback(_) => {}
+                    }
+                }
             }
 
             if let Some(blk_msg) = primary.message() {
@@ -264,6 +357,8 @@ impl EpochsState {
                     );
                 }
 
+                state.handle_era(&genesis.values, &blk_info);
+
                 let span = info_span!("epochs_state.handle_mint", block = blk_info.number);
                 span.in_scope(|| {
                     if let Some(header) = header.as_ref() {
@@ -292,17 +387,29 @@ impl EpochsState {
                     ctx.publish().await;
                 }
                 history.lock().await.commit(block_info.number, state);
+
+                if let Some(acker) = barrier_acker.as_ref().filter(|_| epoch.is_some()) {
+                    acker.ack(block_info).await;
+                }
             }
         }
     }
 
     /// Main init function
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "epochs-state").await?;
+
         // Subscription topics
         let bootstrap_reader = BootstrapReader::new(&context, &config).await?;
         let params_reader = ParamsReader::new(&context, &config).await?;
         let txs_reader = TxsReader::new(&context, &config).await?;
         let block_reader = BlockReader::new(&context, &config).await?;
+        let spdd_reader = SPDDReader::new_opt(
+            get_bool_flag(&config, DEFAULT_TRACK_ACTIVE_STAKE),
+            &context,
+            &config,
+        )
+        .await?;
 
         let snapshot_subscribe_topic = get_string_flag(&config, DEFAULT_SNAPSHOT_SUBSCRIBE_TOPIC);
         info!("Creating subscriber for snapshot on '{snapshot_subscribe_topic}'");
@@ -344,11 +451,24 @@ impl EpochsState {
         let epoch_nonce_publisher =
             EpochNoncePublisher::new(context.clone(), epoch_nonce_publish_topic);
 
+        // Epoch barrier, coordinated from here
+        let barrier_acker =
+            EpochBarrierAcker::from_config(context.clone(), &config, "epochs-state");
+        if epoch_barrier_enabled(&config) {
+            Self::coordinate_epoch_barrier(context.clone(), &config).await?;
+        }
+
+        let digest_publisher =
+            StateDigestPublisher::from_config(context.clone(), &config, "epochs-state");
+
         // handle epochs query
+        let query_metrics = metrics::QueryMetrics::new("epochs-state");
         context.handle(&epochs_query_topic, move |message| {
             let history = history_query.clone();
 
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Epochs(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Epochs(
                         EpochsStateQueryResponse::Error(QueryError::internal_error(
@@ -371,6 +491,10 @@ impl EpochsState {
                         )
                     }
 
+                    EpochsStateQuery::GetEraSummaries => {
+                        EpochsStateQueryResponse::EraSummaries(state.get_era_summaries())
+                    }
+
                     _ => EpochsStateQueryResponse::Error(QueryError::not_implemented(format!(
                         "Unimplemented query variant: {query:?}"
                     ))),
@@ -392,9 +516,12 @@ impl EpochsState {
                 params_reader,
                 block_reader,
                 txs_reader,
+                spdd_reader,
                 snapshot_subscription,
                 epoch_activity_publisher,
                 epoch_nonce_publisher,
+                barrier_acker,
+                digest_publisher,
                 validation_outcome_topic,
                 is_snapshot_mode,
             )
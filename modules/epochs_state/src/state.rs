@@ -3,15 +3,20 @@
 use acropolis_common::messages::EpochBootstrapMessage;
 use acropolis_common::{
     crypto::keyhash_224,
+    era_summary::{EraBound, EraParams, EraSummary},
     genesis_values::GenesisValues,
     messages::{BlockTxsMessage, EpochActivityMessage, ProtocolParamsMessage},
     params::EPOCH_LENGTH,
     protocol_params::{Nonce, Nonces, PraosParams},
-    BlockHash, BlockInfo, Era, PoolId,
+    spdd_chunks::SPDDTotals,
+    state_digest::{SetDigest, StateDigest},
+    BlockHash, BlockInfo, Era, Lovelace, PoolId,
 };
 use anyhow::{bail, Result};
 use imbl::HashMap;
 use pallas::ledger::traverse::MultiEraHeader;
+use std::collections::BTreeMap;
+use std::time::Duration;
 use tracing::info;
 
 #[derive(Default, Debug, Clone)]
@@ -55,11 +60,17 @@ pub struct State {
     // fees seen this epoch
     epoch_fees: u64,
 
+    // total active stake by the epoch it is active in, from the SPDD
+    active_stakes: BTreeMap<u64, Lovelace>,
+
     // nonces will be set starting from Shelley Era
     nonces: Option<Nonces>,
 
     // protocol parameter for Praos and TPraos
     praos_params: Option<PraosParams>,
+
+    // eras seen since the start of the sync, the last one still open
+    eras: Vec<EraSummary>,
 }
 
 impl State {
@@ -82,8 +93,10 @@ impl State {
             epoch_txs: 0,
             epoch_outputs: 0,
             epoch_fees: 0,
+            active_stakes: BTreeMap::new(),
             nonces: None,
             praos_params: None,
+            eras: Vec::new(),
         }
     }
 
@@ -135,10 +148,6 @@ impl State {
         // - Pre-Shelley epochs: nonce evolution starts at shelley_epoch
         // - Byron-era blocks: no VRF nonces (relevant on preview where shelley_epoch=0)
         if block_info.epoch >= genesis.shelley_epoch && block_info.era != Era::Byron {
-            let Some(praos_params) = self.praos_params.as_ref() else {
-                bail!("Praos Param is not set");
-            };
-
             // Initialize nonces from genesis if not yet set. This covers:
             // - The normal case: first block of shelley_epoch with new_epoch=true
             // - Preview-like networks: shelley_epoch=0, module starts mid-epoch
@@ -157,7 +166,7 @@ impl State {
                 block_info.era,
                 header.slot(),
                 genesis,
-                praos_params,
+                self.praos_params.as_ref(),
             );
 
             // extract header's nonce vrf output
@@ -190,7 +199,13 @@ impl State {
                     Nonces::from_candidate(
                         &current_nonces.candidate,
                         &current_nonces.prev_lab,
-                        &praos_params.extra_entropy,
+                        // Extra entropy can only be set by a pre-Babbage update
+                        // proposal, so neutral is right if we have no parameters yet
+                        &self
+                            .praos_params
+                            .as_ref()
+                            .map(|params| params.extra_entropy.clone())
+                            .unwrap_or_default(),
                     )?
                 } else {
                     current_nonces.active.clone()
@@ -246,6 +261,44 @@ impl State {
         }
     }
 
+    // Handle the era of a block
+    // Eras change on an epoch boundary, so the first block of a new era closes
+    // the previous era and opens the next at the start of its epoch
+    pub fn handle_era(&mut self, genesis: &GenesisValues, block_info: &BlockInfo) {
+        if self.eras.last().is_some_and(|era| era.params.era_name == block_info.era) {
+            return;
+        }
+
+        let start_slot = genesis.epoch_to_first_slot(block_info.epoch);
+        let start = EraBound {
+            time: Duration::from_secs(
+                genesis.slot_to_timestamp(start_slot) - genesis.byron_timestamp,
+            ),
+            slot: start_slot,
+            epoch: block_info.epoch,
+        };
+        if let Some(previous) = self.eras.last_mut() {
+            previous.end = Some(start.clone());
+        }
+
+        let params = EraParams {
+            era_name: block_info.era,
+            epoch_size_slots: genesis.epoch_to_first_slot(block_info.epoch + 1) - start_slot,
+            slot_length: Duration::from_secs(
+                genesis.slot_to_timestamp(start_slot + 1) - genesis.slot_to_timestamp(start_slot),
+            ),
+            safe_zone: match block_info.era {
+                Era::Byron => 2 * genesis.security_param,
+                _ => genesis.stability_window(),
+            },
+        };
+        self.eras.push(EraSummary {
+            start,
+            end: None,
+            params,
+        });
+    }
+
     // Handle Block Txs
     pub fn handle_block_txs(&mut self, _block_info: &BlockInfo, msg: &BlockTxsMessage) {
         self.epoch_fees += msg.total_fees;
@@ -253,6 +306,15 @@ impl State {
         self.epoch_outputs += msg.total_output;
     }
 
+    // Handle SPDD
+    // The distribution taken at the end of epoch N is the active stake of epoch N + 2
+    pub fn handle_spdd(&mut self, spdd: &SPDDTotals) {
+        self.active_stakes.insert(spdd.epoch + 2, spdd.total_active);
+
+        // Keep only the current and upcoming epochs
+        self.active_stakes.retain(|epoch, _| *epoch >= spdd.epoch);
+    }
+
     // Handle end of epoch, returns message to be published
     // block is the first block of coming epoch
     pub fn end_epoch(&mut self, block_info: &BlockInfo) -> EpochActivityMessage {
@@ -309,12 +371,50 @@ impl State {
             total_fees: self.epoch_fees,
             spo_blocks: self.blocks_minted.iter().map(|(k, v)| (*k, *v)).collect(),
             nonce: self.nonces.as_ref().map(|n| n.active.clone()),
+            active_stake: self.active_stakes.get(&self.epoch).copied(),
         }
     }
 
     pub fn get_latest_epoch_blocks_minted_by_pool(&self, spo_id: &PoolId) -> u64 {
         self.blocks_minted.get(spo_id).map(|v| *v as u64).unwrap_or(0)
     }
+
+    pub fn get_era_summaries(&self) -> Vec<EraSummary> {
+        self.eras.clone()
+    }
+}
+
+impl StateDigest for State {
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        digest.insert(&("block", self.block))?;
+        digest.insert(&("epoch", self.epoch, self.epoch_start_time))?;
+        digest.insert(&(
+            "first-block",
+            self.first_block_time,
+            self.first_block_height,
+        ))?;
+        digest.insert(&("last-block", self.last_block_time, self.last_block_height))?;
+        for (pool, count) in &self.blocks_minted {
+            digest.insert(&("minted", pool, count))?;
+        }
+        // CBOR has no 128-bit integers
+        digest.insert(&(
+            "totals",
+            self.epoch_blocks,
+            self.epoch_txs,
+            self.epoch_outputs.to_be_bytes(),
+            self.epoch_fees,
+        ))?;
+        for (epoch, stake) in &self.active_stakes {
+            digest.insert(&("active-stake", epoch, stake))?;
+        }
+        digest.insert(&("nonces", &self.nonces))?;
+        digest.insert(&("praos-params", &self.praos_params))?;
+        for (index, era) in self.eras.iter().enumerate() {
+            digest.insert(&("era", index, era))?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -323,10 +423,13 @@ mod tests {
 
     use super::*;
     use acropolis_common::{
+        caryatid::ChunkAssembler,
         crypto::keyhash_224,
+        messages::SPOStakeDistributionMessage,
         protocol_params::{Nonce, NonceHash},
+        spdd_chunks::SPDDAssembler,
         state_history::{StateHistory, StateHistoryStore},
-        BlockHash, BlockInfo, BlockIntent, BlockStatus, Era,
+        BlockHash, BlockInfo, BlockIntent, BlockStatus, DelegatedStake, Era,
     };
     use acropolis_test_utils::mainnet_genesis_values;
     use tokio::sync::Mutex;
@@ -425,6 +528,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn handle_era_closes_previous_era_at_epoch_start() {
+        let genesis = mainnet_genesis_values();
+        let mut state = State::new(&genesis);
+
+        let mut block = make_block(0);
+        block.era = Era::Byron;
+        state.handle_era(&genesis, &block);
+        block.number += 1;
+        state.handle_era(&genesis, &block);
+        assert_eq!(state.get_era_summaries().len(), 1);
+
+        let mut block = make_block(208);
+        block.era = Era::Shelley;
+        state.handle_era(&genesis, &block);
+
+        let eras = state.get_era_summaries();
+        assert_eq!(eras.len(), 2);
+
+        let shelley_start = EraBound {
+            time: Duration::from_secs(4_492_800 * 20),
+            slot: 4_492_800,
+            epoch: 208,
+        };
+        assert_eq!(eras[0].start.slot, 0);
+        assert_eq!(eras[0].end, Some(shelley_start.clone()));
+        assert_eq!(eras[0].params.epoch_size_slots, 21_600);
+        assert_eq!(eras[0].params.slot_length, Duration::from_secs(20));
+        assert_eq!(eras[0].params.safe_zone, 4_320);
+
+        assert_eq!(eras[1].start, shelley_start);
+        assert_eq!(eras[1].end, None);
+        assert_eq!(eras[1].params.era_name, Era::Shelley);
+        assert_eq!(eras[1].params.epoch_size_slots, 432_000);
+        assert_eq!(eras[1].params.slot_length, Duration::from_secs(1));
+        assert_eq!(eras[1].params.safe_zone, 129_600);
+    }
+
     #[test]
     fn handle_mint_multiple_issuer_records_counts() {
         let mut state = State::new(&mainnet_genesis_values());
@@ -540,6 +681,40 @@ mod tests {
         assert_eq!(blocks_minted, 0);
     }
 
+    #[test]
+    fn end_epoch_reports_active_stake_from_spdd() {
+        let genesis = mainnet_genesis_values();
+        let mut state = State::new(&genesis);
+        let stake = |active| DelegatedStake {
+            active,
+            active_delegators_count: 1,
+        };
+
+        // Distribution at the end of epoch 0 is active in epoch 2
+        let mut totals = SPDDTotals::default();
+        SPDDAssembler::stream_whole(
+            &SPOStakeDistributionMessage {
+                epoch: 0,
+                spos: vec![
+                    (PoolId::from(keyhash_224(b"pool_1")), stake(100)),
+                    (PoolId::from(keyhash_224(b"pool_2")), stake(50)),
+                ],
+            },
+            &mut totals,
+        );
+        state.handle_spdd(&totals);
+
+        let ea = state.end_epoch(&make_new_epoch_block(1));
+        assert_eq!(ea.active_stake, None);
+        let ea = state.end_epoch(&make_new_epoch_block(2));
+        assert_eq!(ea.active_stake, None);
+        assert_eq!(state.get_epoch_info().active_stake, Some(150));
+
+        let ea = state.end_epoch(&make_new_epoch_block(3));
+        assert_eq!(ea.epoch, 2);
+        assert_eq!(ea.active_stake, Some(150));
+    }
+
     #[tokio::test]
     async fn state_is_rolled_back() {
         let history = Arc::new(Mutex::new(StateHistory::<State>::new(
@@ -661,6 +836,61 @@ mod tests {
         assert!(nonces.prev_lab.eq(&Nonce::default()));
     }
 
+    #[test]
+    fn test_nonce_evolving_without_protocol_params() {
+        // As after a snapshot bootstrap, before the next protocol parameters arrive
+        let mut state = State::new(&mainnet_genesis_values());
+        let genesis_value = mainnet_genesis_values();
+
+        let e_208_first_block_header_cbor =
+            hex::decode(include_str!("../data/4490511.cbor")).unwrap();
+        let block = make_new_epoch_block(208);
+        let block_header = MultiEraHeader::decode(1, None, &e_208_first_block_header_cbor).unwrap();
+        assert!(state.evolve_nonces(&genesis_value, &block, &block_header).is_ok());
+
+        let nonces = state.nonces.unwrap();
+        let evolved = Nonce::from(
+            NonceHash::try_from(
+                hex::decode("2af15f57076a8ff225746624882a77c8d2736fe41d3db70154a22b50af851246")
+                    .unwrap()
+                    .as_slice(),
+            )
+            .unwrap(),
+        );
+        assert!(nonces.candidate.eq(&evolved));
+        assert!(nonces.evolving.eq(&evolved));
+    }
+
+    #[test]
+    fn test_randomness_stability_window() {
+        let genesis_value = mainnet_genesis_values();
+        let params = PraosParams::mainnet();
+        let epoch_209_first_slot = genesis_value.epoch_to_first_slot(209);
+
+        // TPraos and Babbage use 3k/f, Conway 4k/f
+        for (era, window) in [
+            (Era::Shelley, 129600),
+            (Era::Babbage, 129600),
+            (Era::Conway, 172800),
+        ] {
+            let last_contributing = epoch_209_first_slot - window - 1;
+            for params in [Some(&params), None] {
+                assert!(Nonces::randomness_stability_window(
+                    era,
+                    last_contributing,
+                    &genesis_value,
+                    params
+                ));
+                assert!(!Nonces::randomness_stability_window(
+                    era,
+                    last_contributing + 1,
+                    &genesis_value,
+                    params
+                ));
+            }
+        }
+    }
+
     #[test]
     fn test_epoch_209_nonce() {
         let mut state = State::new(&mainnet_genesis_values());
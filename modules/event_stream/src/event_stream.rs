@@ -0,0 +1,145 @@
+//! Acropolis event stream module for Caryatid
+//!
+//! Bridges block and address delta messages from the bus to server-sent event
+//! subscribers, so clients can follow the chain without polling the REST API:
+//!
+//! * `GET /events/blocks` - new blocks and rollbacks
+//! * `GET /events/txs?address=<addr>[,<addr>...]` - transactions touching the given
+//!   addresses (all transactions if omitted), and rollbacks
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    caryatid::RollbackWrapper,
+    configuration::{get_bool_flag, get_string_flag, get_u64_flag},
+    declare_cardano_reader,
+    messages::{
+        AddressDeltasMessage, CardanoMessage, Message, RawBlockMessage, StateTransitionMessage,
+    },
+};
+use anyhow::{bail, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+mod events;
+mod server;
+
+use events::{BlockEvent, ChainEvent, TxEvent};
+
+declare_cardano_reader!(
+    BlockReader,
+    "blocks-subscribe-topic",
+    "cardano.block.proposed",
+    BlockAvailable,
+    RawBlockMessage
+);
+declare_cardano_reader!(
+    AddressDeltasReader,
+    "address-deltas-subscribe-topic",
+    "cardano.address.deltas",
+    AddressDeltas,
+    AddressDeltasMessage
+);
+
+const DEFAULT_ENABLED: (&str, bool) = ("enabled", false);
+const DEFAULT_ADDRESS: (&str, &str) = ("address", "127.0.0.1");
+const DEFAULT_PORT: (&str, u64) = ("port", 4342);
+/// Events held for subscribers before the slowest start to lag
+const DEFAULT_CHANNEL_CAPACITY: (&str, u64) = ("channel-capacity", 1024);
+/// Events buffered per connection awaiting delivery to the client
+const DEFAULT_CONNECTION_BUFFER: (&str, u64) = ("connection-buffer", 64);
+
+/// Event stream module
+#[module(
+    message_type(Message),
+    name = "event-stream",
+    description = "Server-sent event stream of chain events"
+)]
+pub struct EventStream;
+
+impl EventStream {
+    async fn run_blocks(
+        mut reader: BlockReader,
+        events: broadcast::Sender<ChainEvent>,
+    ) -> Result<()> {
+        loop {
+            let event = match reader.read_with_rollbacks().await? {
+                RollbackWrapper::Normal((block_info, _)) => {
+                    ChainEvent::Block(BlockEvent::from(block_info.as_ref()))
+                }
+                RollbackWrapper::Rollback((block_info, _)) => {
+                    ChainEvent::Rollback(BlockEvent::from(block_info.as_ref()))
+                }
+            };
+
+            // Sending only fails when there are no subscribers
+            let _ = events.send(event);
+        }
+    }
+
+    async fn run_txs(
+        mut reader: AddressDeltasReader,
+        events: broadcast::Sender<ChainEvent>,
+    ) -> Result<()> {
+        loop {
+            // Rollbacks are reported on the block stream
+            let RollbackWrapper::Normal((block_info, deltas)) =
+                reader.read_with_rollbacks().await?
+            else {
+                continue;
+            };
+
+            if events.receiver_count() == 0 {
+                continue;
+            }
+
+            for delta in deltas.as_compact_or_convert().iter() {
+                if let Some(event) = TxEvent::from_delta(&block_info, delta) {
+                    let _ = events.send(ChainEvent::Tx(event));
+                }
+            }
+        }
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        if !get_bool_flag(&config, DEFAULT_ENABLED) {
+            info!("Event stream is disabled in configuration");
+            return Ok(());
+        }
+
+        let address = get_string_flag(&config, DEFAULT_ADDRESS);
+        let port: u16 = get_u64_flag(&config, DEFAULT_PORT).try_into()?;
+        let capacity = get_u64_flag(&config, DEFAULT_CHANNEL_CAPACITY).max(1) as usize;
+        let buffer = get_u64_flag(&config, DEFAULT_CONNECTION_BUFFER).max(1) as usize;
+
+        let block_reader = BlockReader::new(&context, &config).await?;
+        let address_deltas_reader = AddressDeltasReader::new(&context, &config).await?;
+
+        let (events, _) = broadcast::channel(capacity);
+
+        let block_events = events.clone();
+        context.run(async move {
+            Self::run_blocks(block_reader, block_events)
+                .await
+                .unwrap_or_else(|e| error!("Block event stream failed: {e}"));
+        });
+
+        let tx_events = events.clone();
+        context.run(async move {
+            Self::run_txs(address_deltas_reader, tx_events)
+                .await
+                .unwrap_or_else(|e| error!("Transaction event stream failed: {e}"));
+        });
+
+        info!("Initializing event stream on {}:{}", address, port);
+        tokio::spawn(async move {
+            if let Err(e) = server::run(&address, port, events, buffer).await {
+                error!("Event stream server error: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+}
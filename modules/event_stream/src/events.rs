@@ -0,0 +1,205 @@
+//! Chain events pushed to stream subscribers, and the per-connection filters applied to them
+
+use std::collections::HashSet;
+
+use acropolis_common::{Address, AddressDelta, BlockInfo};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+/// Maximum number of addresses a single `/events/txs` subscription may filter on
+pub const MAX_FILTER_ADDRESSES: usize = 100;
+
+/// Event published to subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChainEvent {
+    /// A new block was applied to the chain
+    Block(BlockEvent),
+
+    /// A transaction touched an address
+    Tx(TxEvent),
+
+    /// The chain rolled back - events after this block are no longer valid
+    Rollback(BlockEvent),
+}
+
+impl ChainEvent {
+    /// SSE event name for this event
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Block(_) => "block",
+            Self::Tx(_) => "tx",
+            Self::Rollback(_) => "rollback",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockEvent {
+    pub slot: u64,
+    pub number: u64,
+    pub hash: String,
+    pub epoch: u64,
+    pub epoch_slot: u64,
+    pub timestamp: u64,
+    pub era: String,
+}
+
+impl From<&BlockInfo> for BlockEvent {
+    fn from(block: &BlockInfo) -> Self {
+        Self {
+            slot: block.slot,
+            number: block.number,
+            hash: block.hash.to_string(),
+            epoch: block.epoch,
+            epoch_slot: block.epoch_slot,
+            timestamp: block.timestamp,
+            era: block.era.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TxEvent {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub slot: u64,
+    pub tx_index: u16,
+    pub address: String,
+    pub sent_lovelace: u64,
+    pub received_lovelace: u64,
+}
+
+impl TxEvent {
+    /// Build an event from an address delta, or None if the address cannot be encoded
+    pub fn from_delta(block: &BlockInfo, delta: &AddressDelta) -> Option<Self> {
+        Some(Self {
+            block_number: block.number,
+            block_hash: block.hash.to_string(),
+            slot: block.slot,
+            tx_index: delta.tx_identifier.tx_index(),
+            address: delta.address.to_string().ok()?,
+            sent_lovelace: delta.sent.lovelace,
+            received_lovelace: delta.received.lovelace,
+        })
+    }
+}
+
+/// Per-connection selection of events
+#[derive(Debug, Clone)]
+pub enum EventFilter {
+    /// Block and rollback events
+    Blocks,
+
+    /// Transaction and rollback events, optionally restricted to a set of addresses
+    Txs(Option<HashSet<String>>),
+}
+
+impl EventFilter {
+    /// Build a transaction filter from a comma-separated `address` query parameter
+    pub fn txs(addresses: Option<&str>) -> Result<Self> {
+        let Some(addresses) = addresses else {
+            return Ok(Self::Txs(None));
+        };
+
+        let mut set = HashSet::new();
+        for text in addresses.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            // Normalise through the parser so the filter matches published encodings
+            let address = Address::from_string(text)
+                .and_then(|address| address.to_string())
+                .map_err(|e| anyhow!("Invalid address '{text}': {e}"))?;
+            set.insert(address);
+        }
+
+        if set.is_empty() {
+            return Err(anyhow!("No addresses given"));
+        }
+        if set.len() > MAX_FILTER_ADDRESSES {
+            return Err(anyhow!(
+                "Too many addresses: {} (maximum {MAX_FILTER_ADDRESSES})",
+                set.len()
+            ));
+        }
+
+        Ok(Self::Txs(Some(set)))
+    }
+
+    pub fn matches(&self, event: &ChainEvent) -> bool {
+        match (self, event) {
+            (_, ChainEvent::Rollback(_)) => true,
+            (Self::Blocks, ChainEvent::Block(_)) => true,
+            (Self::Txs(None), ChainEvent::Tx(_)) => true,
+            (Self::Txs(Some(addresses)), ChainEvent::Tx(tx)) => addresses.contains(&tx.address),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x";
+    const OTHER_ADDRESS: &str = "addr1z8phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gten0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgs9yc0hh";
+
+    fn block() -> BlockEvent {
+        BlockEvent {
+            slot: 10,
+            number: 5,
+            hash: "00".repeat(32),
+            epoch: 1,
+            epoch_slot: 10,
+            timestamp: 0,
+            era: "Conway".to_string(),
+        }
+    }
+
+    fn tx(address: &str) -> ChainEvent {
+        ChainEvent::Tx(TxEvent {
+            block_number: 5,
+            block_hash: "00".repeat(32),
+            slot: 10,
+            tx_index: 0,
+            address: address.to_string(),
+            sent_lovelace: 0,
+            received_lovelace: 1_000_000,
+        })
+    }
+
+    #[test]
+    fn blocks_filter_selects_blocks_and_rollbacks() {
+        let filter = EventFilter::Blocks;
+        assert!(filter.matches(&ChainEvent::Block(block())));
+        assert!(filter.matches(&ChainEvent::Rollback(block())));
+        assert!(!filter.matches(&tx(ADDRESS)));
+    }
+
+    #[test]
+    fn txs_filter_selects_matching_addresses() {
+        let all = EventFilter::txs(None).unwrap();
+        assert!(all.matches(&tx(ADDRESS)));
+        assert!(all.matches(&tx(OTHER_ADDRESS)));
+        assert!(!all.matches(&ChainEvent::Block(block())));
+
+        let one = EventFilter::txs(Some(ADDRESS)).unwrap();
+        assert!(one.matches(&tx(ADDRESS)));
+        assert!(!one.matches(&tx(OTHER_ADDRESS)));
+        assert!(one.matches(&ChainEvent::Rollback(block())));
+
+        let both = EventFilter::txs(Some(&format!("{ADDRESS}, {OTHER_ADDRESS}"))).unwrap();
+        assert!(both.matches(&tx(OTHER_ADDRESS)));
+    }
+
+    #[test]
+    fn txs_filter_rejects_bad_addresses() {
+        assert!(EventFilter::txs(Some("not-an-address")).is_err());
+        assert!(EventFilter::txs(Some(",")).is_err());
+    }
+
+    #[test]
+    fn events_serialize_with_type_tag() {
+        let json = serde_json::to_value(ChainEvent::Block(block())).unwrap();
+        assert_eq!(json["type"], "block");
+        assert_eq!(json["number"], 5);
+    }
+}
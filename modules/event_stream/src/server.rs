@@ -0,0 +1,123 @@
+//! HTTP server exposing chain events as server-sent event streams
+//!
+//! Each connection gets its own broadcast receiver and a bounded forwarding
+//! buffer. A client that reads too slowly fills its buffer, its receiver falls
+//! behind the broadcast channel, and it is sent a `lagged` event with the number
+//! of events it missed rather than holding up other subscribers.
+
+use std::{collections::HashMap, convert::Infallible, time::Duration};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, info};
+
+use crate::events::{ChainEvent, EventFilter};
+
+/// Interval between keep-alive comments on idle streams
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone)]
+struct ServerState {
+    events: broadcast::Sender<ChainEvent>,
+    buffer: usize,
+}
+
+pub async fn run(
+    address: &str,
+    port: u16,
+    events: broadcast::Sender<ChainEvent>,
+    buffer: usize,
+) -> Result<()> {
+    let app = Router::new()
+        .route("/events/blocks", get(handle_blocks))
+        .route("/events/txs", get(handle_txs))
+        .with_state(ServerState { events, buffer });
+
+    let listener = TcpListener::bind((address, port)).await?;
+    info!(
+        "Event stream listening on http://{}:{}/events",
+        address, port
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn handle_blocks(State(state): State<ServerState>) -> Response {
+    subscribe(&state, EventFilter::Blocks)
+}
+
+async fn handle_txs(
+    State(state): State<ServerState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    if let Some(unknown) = params.keys().find(|key| key.as_str() != "address") {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown query parameter '{unknown}'"),
+        )
+            .into_response();
+    }
+
+    match EventFilter::txs(params.get("address").map(String::as_str)) {
+        Ok(filter) => subscribe(&state, filter),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// Start forwarding matching events to a new SSE connection
+fn subscribe(state: &ServerState, filter: EventFilter) -> Response {
+    let receiver = state.events.subscribe();
+    let (sender, stream) = mpsc::channel(state.buffer);
+    tokio::spawn(forward(receiver, sender, filter));
+
+    Sse::new(ReceiverStream::new(stream))
+        .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL))
+        .into_response()
+}
+
+/// Copy matching events from the broadcast channel into a connection's buffer,
+/// until either side closes
+async fn forward(
+    mut receiver: broadcast::Receiver<ChainEvent>,
+    sender: mpsc::Sender<Result<Event, Infallible>>,
+    filter: EventFilter,
+) {
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) if filter.matches(&event) => to_sse(&event),
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Event stream subscriber lagged by {skipped} events");
+                Event::default().event("lagged").data(format!("{{\"skipped\":{skipped}}}"))
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        if sender.send(Ok(event)).await.is_err() {
+            // Client disconnected
+            break;
+        }
+    }
+}
+
+fn to_sse(event: &ChainEvent) -> Event {
+    let sse = Event::default().event(event.name());
+    match serde_json::to_string(event) {
+        Ok(json) => sse.data(json),
+        Err(e) => sse.comment(format!("failed to serialize event: {e}")),
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use acropolis_common::configuration::{deserialize_duration, ModuleConfig};
+
+/// Faults to inject into one topic, relaying it to another
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct FaultRule {
+    pub subscribe_topic: String,
+
+    /// Where the disturbed messages are published - the modules under test
+    /// subscribe to this instead
+    pub publish_topic: String,
+
+    /// Chance of dropping each message
+    pub drop: f64,
+
+    /// Chance of delaying each message, by up to max-delay
+    pub delay: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max_delay: Duration,
+
+    /// Chance of publishing each message twice
+    pub duplicate: f64,
+
+    /// Chance of holding each message back until after the next one
+    pub reorder: f64,
+}
+
+impl Default for FaultRule {
+    fn default() -> Self {
+        Self {
+            subscribe_topic: String::new(),
+            publish_topic: String::new(),
+            drop: 0.0,
+            delay: 0.0,
+            max_delay: Duration::from_millis(100),
+            duplicate: 0.0,
+            reorder: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct FaultInjectorConfig {
+    /// Seed for the fault choices, so a failing run can be repeated
+    pub seed: u64,
+
+    pub rule: Vec<FaultRule>,
+}
+
+impl ModuleConfig for FaultInjectorConfig {
+    const MODULE: &'static str = "fault-injector";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        for (index, rule) in self.rule.iter().enumerate() {
+            if rule.subscribe_topic.is_empty() || rule.publish_topic.is_empty() {
+                errors.push(format!(
+                    "rule {index} needs a subscribe-topic and a publish-topic"
+                ));
+            } else if rule.subscribe_topic == rule.publish_topic {
+                errors.push(format!(
+                    "rule {index} can't publish to the topic it subscribes to"
+                ));
+            }
+            for (name, chance) in [
+                ("drop", rule.drop),
+                ("delay", rule.delay),
+                ("duplicate", rule.duplicate),
+                ("reorder", rule.reorder),
+            ] {
+                if !(0.0..=1.0).contains(&chance) {
+                    errors.push(format!(
+                        "rule {index} {name} must be between 0 and 1, not {chance}"
+                    ));
+                }
+            }
+        }
+    }
+}
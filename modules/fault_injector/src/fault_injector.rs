@@ -0,0 +1,79 @@
+//! Acropolis fault injector module for Caryatid
+//! Relays topics with messages dropped, delayed, duplicated or reordered, to
+//! test how the modules reading them cope with a disturbed bus. For tests only.
+
+use acropolis_common::{configuration::ModuleConfig, messages::Message, metrics};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+mod configuration;
+mod faults;
+pub use configuration::{FaultInjectorConfig, FaultRule};
+use faults::Injector;
+
+/// Fault injector module
+#[module(
+    message_type(Message),
+    name = "fault-injector",
+    description = "Disturbs messages on selected topics, for testing"
+)]
+pub struct FaultInjector;
+
+impl FaultInjector {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = FaultInjectorConfig::load(&config)?;
+        if cfg.rule.is_empty() {
+            warn!("No fault rules configured");
+        }
+
+        // Each rule gets its own seed, so adding a rule doesn't change the
+        // faults in the others
+        for (index, rule) in cfg.rule.into_iter().enumerate() {
+            info!(
+                "Relaying '{}' to '{}' with drop {}, delay {} (up to {:?}), duplicate {}, \
+                 reorder {}",
+                rule.subscribe_topic,
+                rule.publish_topic,
+                rule.drop,
+                rule.delay,
+                rule.max_delay,
+                rule.duplicate,
+                rule.reorder
+            );
+            let mut subscription = context.subscribe(&rule.subscribe_topic).await?;
+            let topic = rule.subscribe_topic.clone();
+            let publish_topic = rule.publish_topic.clone();
+            let mut injector = Injector::new(rule, cfg.seed.wrapping_add(index as u64));
+
+            let run_context = context.clone();
+            context.run(async move {
+                while let Ok((_, message)) = subscription.read().await {
+                    let injected = injector.inject(message);
+                    for fault in &injected.faults {
+                        debug!("Message on '{topic}' {}", fault.name());
+                        metrics::counter(
+                            "acropolis_faults_injected_total",
+                            "Messages disturbed by the fault injector",
+                            &[("topic", &topic), ("fault", fault.name())],
+                        )
+                        .inc();
+                    }
+
+                    for (delay, message) in injected.publish {
+                        if !delay.is_zero() {
+                            tokio::time::sleep(delay).await;
+                        }
+                        if let Err(e) = run_context.publish(&publish_topic, message).await {
+                            warn!("Could not relay to '{publish_topic}': {e:#}");
+                        }
+                    }
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
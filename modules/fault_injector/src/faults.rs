@@ -0,0 +1,158 @@
+//! Choosing the faults for each message
+
+use std::{sync::Arc, time::Duration};
+
+use acropolis_common::messages::Message;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::configuration::FaultRule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Dropped,
+    Delayed,
+    Duplicated,
+    Reordered,
+}
+
+impl Fault {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fault::Dropped => "dropped",
+            Fault::Delayed => "delayed",
+            Fault::Duplicated => "duplicated",
+            Fault::Reordered => "reordered",
+        }
+    }
+}
+
+/// Disturbs one topic's messages according to its rule
+pub struct Injector {
+    rule: FaultRule,
+    rng: StdRng,
+
+    /// Message held back to be reordered
+    held: Option<Arc<Message>>,
+}
+
+/// What to do with a message - publish each of `publish` in order, after
+/// waiting its delay
+#[derive(Debug, Default)]
+pub struct Injected {
+    pub publish: Vec<(Duration, Arc<Message>)>,
+    pub faults: Vec<Fault>,
+}
+
+impl Injector {
+    pub fn new(rule: FaultRule, seed: u64) -> Self {
+        Self {
+            rule,
+            rng: StdRng::seed_from_u64(seed),
+            held: None,
+        }
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.random::<f64>() < probability
+    }
+
+    pub fn inject(&mut self, message: Arc<Message>) -> Injected {
+        let mut injected = Injected::default();
+        if self.chance(self.rule.drop) {
+            injected.faults.push(Fault::Dropped);
+            return injected;
+        }
+
+        let delay = if self.chance(self.rule.delay) {
+            injected.faults.push(Fault::Delayed);
+            self.rule.max_delay.mul_f64(self.rng.random::<f64>())
+        } else {
+            Duration::ZERO
+        };
+
+        let copies = if self.chance(self.rule.duplicate) {
+            injected.faults.push(Fault::Duplicated);
+            2
+        } else {
+            1
+        };
+
+        // Hold it back for the next message to overtake
+        if self.held.is_none() && self.chance(self.rule.reorder) {
+            injected.faults.push(Fault::Reordered);
+            self.held = Some(message);
+            return injected;
+        }
+
+        for copy in 0..copies {
+            let wait = if copy == 0 { delay } else { Duration::ZERO };
+            injected.publish.push((wait, message.clone()));
+        }
+        if let Some(held) = self.held.take() {
+            injected.publish.push((Duration::ZERO, held));
+        }
+        injected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(drop: f64, duplicate: f64, reorder: f64) -> FaultRule {
+        FaultRule {
+            subscribe_topic: "cardano.txs".to_string(),
+            publish_topic: "cardano.txs.faulty".to_string(),
+            drop,
+            duplicate,
+            reorder,
+            ..FaultRule::default()
+        }
+    }
+
+    fn message(s: &str) -> Arc<Message> {
+        Arc::new(Message::String(s.to_string()))
+    }
+
+    fn published(injected: &Injected) -> Vec<String> {
+        injected
+            .publish
+            .iter()
+            .map(|(_, message)| match message.as_ref() {
+                Message::String(s) => s.clone(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn faults_follow_the_rule() {
+        let mut clean = Injector::new(rule(0.0, 0.0, 0.0), 1);
+        assert_eq!(published(&clean.inject(message("a"))), vec!["a"]);
+
+        let mut dropping = Injector::new(rule(1.0, 0.0, 0.0), 1);
+        let injected = dropping.inject(message("a"));
+        assert!(injected.publish.is_empty());
+        assert_eq!(injected.faults, vec![Fault::Dropped]);
+
+        let mut duplicating = Injector::new(rule(0.0, 1.0, 0.0), 1);
+        assert_eq!(published(&duplicating.inject(message("a"))), vec!["a", "a"]);
+
+        // Only one message is held at a time, so the next overtakes it
+        let mut reordering = Injector::new(rule(0.0, 0.0, 1.0), 1);
+        assert!(published(&reordering.inject(message("a"))).is_empty());
+        assert_eq!(published(&reordering.inject(message("b"))), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn same_seed_gives_same_faults() {
+        let run = |seed| {
+            let mut injector = Injector::new(rule(0.3, 0.3, 0.3), seed);
+            (0..100)
+                .flat_map(|n| published(&injector.inject(message(&n.to_string()))))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+}
@@ -72,6 +72,16 @@ async fn main() -> Result<()> {
             "https://book.world.dev.cardano.org/environments/preview/shelley-genesis.json",
             "preview-shelley-genesis.json",
         ),
+        download(
+            &client,
+            "https://book.world.dev.cardano.org/environments/preprod/byron-genesis.json",
+            "preprod-byron-genesis.json",
+        ),
+        download(
+            &client,
+            "https://book.world.dev.cardano.org/environments/preprod/shelley-genesis.json",
+            "preprod-shelley-genesis.json",
+        ),
         download(
             &client,
             "https://raw.githubusercontent.com/Hornan7/SanchoNet-Tutorials/refs/heads/main/genesis/byron-genesis.json",
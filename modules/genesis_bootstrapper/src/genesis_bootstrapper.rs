@@ -3,15 +3,16 @@
 
 use acropolis_common::{
     configuration::{get_string_flag, get_u64_flag, StartupMode},
-    genesis_values::GenesisValues,
+    genesis_values::{GenesisValues, Network},
     hash::Hash,
     messages::{
         CardanoMessage, GenesisCompleteMessage, GenesisUTxOsMessage, Message, UTXODeltasMessage,
     },
+    rational_number::rational_number_from_f32,
     Address, BlockHash, BlockInfo, BlockIntent, BlockStatus, ByronAddress, Era, GenesisDelegates,
     MagicNumber, Pots, TxHash, TxIdentifier, TxOutput, TxUTxODeltas, UTxOIdentifier, Value,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use blake2::{digest::consts::U32, Blake2b, Digest};
 use caryatid_sdk::{module, Context};
 use config::Config;
@@ -30,9 +31,10 @@ const DEFAULT_PUBLISH_GENESIS_UTXO_REGISTRY_TOPIC: (&str, &str) =
     ("publish-genesis-utxos-topic", "cardano.genesis.utxos");
 const DEFAULT_COMPLETION_TOPIC: (&str, &str) =
     ("completion-topic", "cardano.sequence.bootstrapped");
-const DEFAULT_NETWORK_NAME: (&str, &str) = ("startup.network-name", "mainnet");
 const DEFAULT_SHELLEY_START_EPOCH: (&str, u64) = ("shelley-start-epoch", 0);
 const DEFAULT_FIRST_BLOCK_ERA: (&str, &str) = ("first-block-era", "byron");
+const CONFIG_BYRON_GENESIS_FILE: &str = "byron-genesis-file";
+const CONFIG_SHELLEY_GENESIS_FILE: &str = "shelley-genesis-file";
 
 // Include genesis data (downloaded by build.rs)
 const MAINNET_BYRON_GENESIS: &[u8] = include_bytes!("../downloads/mainnet-byron-genesis.json");
@@ -41,6 +43,9 @@ const MAINNET_SHELLEY_START_EPOCH: u64 = 208;
 const PREVIEW_BYRON_GENESIS: &[u8] = include_bytes!("../downloads/preview-byron-genesis.json");
 const PREVIEW_SHELLEY_GENESIS: &[u8] = include_bytes!("../downloads/preview-shelley-genesis.json");
 const PREVIEW_SHELLEY_START_EPOCH: u64 = 0;
+const PREPROD_BYRON_GENESIS: &[u8] = include_bytes!("../downloads/preprod-byron-genesis.json");
+const PREPROD_SHELLEY_GENESIS: &[u8] = include_bytes!("../downloads/preprod-shelley-genesis.json");
+const PREPROD_SHELLEY_START_EPOCH: u64 = 4;
 const SANCHONET_BYRON_GENESIS: &[u8] = include_bytes!("../downloads/sanchonet-byron-genesis.json");
 const SANCHONET_SHELLEY_GENESIS: &[u8] =
     include_bytes!("../downloads/sanchonet-shelley-genesis.json");
@@ -48,6 +53,7 @@ const SANCHONET_SHELLEY_START_EPOCH: u64 = 0;
 
 const MAINNET_FIRST_BLOCK_ERA: Era = Era::Byron;
 const PREVIEW_FIRST_BLOCK_ERA: Era = Era::Shelley;
+const PREPROD_FIRST_BLOCK_ERA: Era = Era::Byron;
 const SANCHONET_FIRST_BLOCK_ERA: Era = Era::Conway;
 
 fn hash_genesis_bytes(raw_bytes: &[u8]) -> Hash<32> {
@@ -81,6 +87,15 @@ impl GenesisBootstrapper {
 
         let snapshot_bootstrap = StartupMode::from_config(config.as_ref()).is_snapshot();
 
+        // A custom network has no built in genesis, so refuse to start without one
+        let network = Network::from_config(&config)?;
+        if network == Network::Custom
+            && (config.get_string(CONFIG_BYRON_GENESIS_FILE).is_err()
+                || config.get_string(CONFIG_SHELLEY_GENESIS_FILE).is_err())
+        {
+            bail!("Custom network requires {CONFIG_BYRON_GENESIS_FILE} and {CONFIG_SHELLEY_GENESIS_FILE} to be set");
+        }
+
         let mut subscription = context.subscribe(&startup_topic).await?;
         context.clone().run(async move {
             let Ok(_) = subscription.read().await else {
@@ -93,32 +108,37 @@ impl GenesisBootstrapper {
                 let completion_topic = get_string_flag(&config, DEFAULT_COMPLETION_TOPIC);
                 info!("Completing with '{completion_topic}'");
 
-                let network_name = get_string_flag(&config, DEFAULT_NETWORK_NAME);
 
                 let (byron_genesis_bytes, shelley_genesis_bytes, shelley_start_epoch, first_block_era):
-                    (Cow<'static, [u8]>, Cow<'static, [u8]>, u64, Era) = match network_name.as_ref()
+                    (Cow<'static, [u8]>, Cow<'static, [u8]>, u64, Era) = match network
                 {
-                    "mainnet" => (
+                    Network::Mainnet => (
                         Cow::Borrowed(MAINNET_BYRON_GENESIS),
                         Cow::Borrowed(MAINNET_SHELLEY_GENESIS),
                         MAINNET_SHELLEY_START_EPOCH,
                         MAINNET_FIRST_BLOCK_ERA,
                     ),
-                    "preview" => (
+                    Network::Preprod => (
+                        Cow::Borrowed(PREPROD_BYRON_GENESIS),
+                        Cow::Borrowed(PREPROD_SHELLEY_GENESIS),
+                        PREPROD_SHELLEY_START_EPOCH,
+                        PREPROD_FIRST_BLOCK_ERA,
+                    ),
+                    Network::Preview => (
                         Cow::Borrowed(PREVIEW_BYRON_GENESIS),
                         Cow::Borrowed(PREVIEW_SHELLEY_GENESIS),
                         PREVIEW_SHELLEY_START_EPOCH,
                         PREVIEW_FIRST_BLOCK_ERA,
                     ),
-                    "sanchonet" => (
+                    Network::SanchoNet => (
                         Cow::Borrowed(SANCHONET_BYRON_GENESIS),
                         Cow::Borrowed(SANCHONET_SHELLEY_GENESIS),
                         SANCHONET_SHELLEY_START_EPOCH,
                         SANCHONET_FIRST_BLOCK_ERA,
                     ),
-                    _ => {
-                        let byron_path = config.get_string("byron-genesis-file");
-                        let shelley_path = config.get_string("shelley-genesis-file");
+                    Network::Custom => {
+                        let byron_path = config.get_string(CONFIG_BYRON_GENESIS_FILE);
+                        let shelley_path = config.get_string(CONFIG_SHELLEY_GENESIS_FILE);
                         match (byron_path, shelley_path) {
                             (Ok(bp), Ok(sp)) => {
                                 info!("Loading custom genesis files: byron={bp}, shelley={sp}");
@@ -144,14 +164,14 @@ impl GenesisBootstrapper {
                                 (Cow::Owned(byron), Cow::Owned(shelley), shelley_start_epoch, first_block_era)
                             }
                             _ => {
-                                error!("Cannot find genesis for {network_name}; set byron-genesis-file and shelley-genesis-file for custom networks");
+                                error!("Custom network requires byron-genesis-file and shelley-genesis-file to be set");
                                 return;
                             }
                         }
                     }
                 };
 
-                info!("Reading genesis for '{network_name}'");
+                info!("Reading genesis for '{network}'");
                 let shelley_genesis_hash = hash_genesis_bytes(&shelley_genesis_bytes);
 
                 // Read genesis data
@@ -312,6 +332,12 @@ impl GenesisBootstrapper {
                         .map(|k| k as u64)
                         .unwrap_or(byron_genesis.protocol_consts.k as u64),
                     initial_pots,
+                    slots_per_kes_period: shelley_genesis.slots_per_kes_period.unwrap() as u64,
+                    max_kes_evolutions: shelley_genesis.max_kes_evolutions.unwrap() as u64,
+                    active_slots_coeff: rational_number_from_f32(
+                        shelley_genesis.active_slots_coeff.unwrap(),
+                    )
+                    .unwrap(),
                 };
 
                 // Send completion message
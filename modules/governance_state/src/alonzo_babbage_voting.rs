@@ -1,6 +1,6 @@
 use acropolis_common::{
-    AlonzoBabbageUpdateProposal, AlonzoBabbageVotingOutcome, BlockInfo, Era, GenesisKeyhash,
-    ProtocolParamUpdate,
+    state_digest::SetDigest, AlonzoBabbageUpdateProposal, AlonzoBabbageVotingOutcome, BlockInfo,
+    Era, GenesisKeyhash, ProtocolParamUpdate,
 };
 use anyhow::{bail, Result};
 use imbl::HashMap;
@@ -20,6 +20,22 @@ pub struct AlonzoBabbageVoting {
 }
 
 impl AlonzoBabbageVoting {
+    /// Estimated memory used by the votes, for the state history memory budget
+    pub fn estimated_size(&self) -> usize {
+        self.proposals.values().map(HashMap::len).sum::<usize>()
+            * (size_of::<(GenesisKeyhash, VoteData)>() + size_of::<ProtocolParamUpdate>())
+    }
+
+    /// Add the update proposal votes to a state digest
+    pub fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        for (enact_epoch, votes) in &self.proposals {
+            for (voter, vote) in votes {
+                digest.insert(&("update-vote", enact_epoch, voter, vote))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Vote is counted for the new epoch if cast in previous epoch
     /// before 4/10 of its start (not too fresh).
     /// Here is it: [!++++++++++!++++------!]
@@ -2,6 +2,8 @@ use crate::voting_state::{AggregatedVotes, AggregatedVotesOutcome, VotingRegistr
 use acropolis_common::{
     messages::GovernanceBootstrapMessage,
     protocol_params::ConwayParams,
+    queries::governance::ProposalInfo,
+    state_digest::SetDigest,
     validation::{GovernanceValidationError, ValidationError, ValidationOutcomes},
     AddrKeyhash, BlockInfo, ConstitutionalCommitteeKeyHash, ConstitutionalCommitteeScriptHash,
     DRepCredential, DRepKeyHash, DRepScriptHash, DelegatedStake, DelegatedStakeDefaultVote,
@@ -22,7 +24,7 @@ use std::{
 };
 use tracing::{debug, error, info, warn};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
 pub struct ActionStatus {
     voting_epochs: Range<u64>,
     ratification_epoch: Option<u64>,
@@ -47,6 +49,11 @@ impl ActionStatus {
     pub fn is_accepted(&self) -> bool {
         self.ratification_epoch.is_some()
     }
+
+    /// Last epoch of the action's lifetime (the voting range runs two epochs past it)
+    pub fn expires_after(&self) -> u64 {
+        self.voting_epochs.end - 2
+    }
 }
 
 #[derive(Default)]
@@ -214,6 +221,44 @@ pub struct ConwayVoting {
 }
 
 impl ConwayVoting {
+    /// Estimated memory used by the proposals and votes, for the state history
+    /// memory budget
+    pub fn estimated_size(&self) -> usize {
+        let votes = |votes: &imbl::HashMap<GovActionId, imbl::HashMap<_, _>>| {
+            votes.values().map(imbl::HashMap::len).sum::<usize>()
+                * size_of::<(Voter, (TxHash, VotingProcedure))>()
+        };
+        self.proposals.len() * size_of::<(GovActionId, (u64, ProposalProcedure))>()
+            + self.proposal_order.len() * size_of::<GovActionId>()
+            + votes(&self.pending_votes)
+            + votes(&self.votes)
+            + self.action_status.len() * size_of::<(GovActionId, ActionStatus)>()
+    }
+
+    /// Add the proposals, votes and action statuses to a state digest
+    pub fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        for (id, proposal) in &self.proposals {
+            digest.insert(&("proposal", id, proposal))?;
+        }
+        for (index, id) in self.proposal_order.iter().enumerate() {
+            digest.insert(&("proposal-order", index, id))?;
+        }
+        for (id, votes) in &self.pending_votes {
+            for (voter, vote) in votes {
+                digest.insert(&("pending-vote", id, voter, vote))?;
+            }
+        }
+        for (id, votes) in &self.votes {
+            for (voter, vote) in votes {
+                digest.insert(&("vote", id, voter, vote))?;
+            }
+        }
+        for (id, status) in &self.action_status {
+            digest.insert(&("action-status", id, status))?;
+        }
+        Ok(())
+    }
+
     pub fn new(
         verification_output_file: Option<String>,
         verify_votes_files: Option<String>,
@@ -502,6 +547,29 @@ impl ConwayVoting {
         })
     }
 
+    /// Proposals still being voted on, in the order they were submitted
+    pub fn list_proposals(&self) -> Vec<ProposalProcedure> {
+        self.proposal_order
+            .iter()
+            .filter_map(|id| self.proposals.get(id))
+            .map(|(_epoch, proposal)| proposal.clone())
+            .collect()
+    }
+
+    /// Proposal details with its submission epoch and status
+    pub fn get_proposal_info(&self, action_id: &GovActionId) -> Option<ProposalInfo> {
+        let (epoch, procedure) = self.proposals.get(action_id)?;
+        let status = self.action_status.get(action_id)?;
+        Some(ProposalInfo {
+            procedure: procedure.clone(),
+            submitted_epoch: *epoch,
+            expiration: status.expires_after(),
+            ratified_epoch: status.ratification_epoch,
+            enacted_epoch: status.enactment_epoch,
+            expired_epoch: status.expiration_epoch,
+        })
+    }
+
     /// Should be called when `action_id` is either ratified, or expired.
     fn end_voting(&mut self, action_id: &GovActionId) {
         self.pending_votes.remove(action_id);
@@ -1016,6 +1084,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_proposal_info() -> Result<()> {
+        let mut voting = ConwayVoting::new(None, None, None)?;
+        let first = create_governance_outcome(2, false).voting.procedure;
+        let second = create_governance_outcome(1, false).voting.procedure;
+        voting.insert_proposal_procedure_impl(500, &first, 6)?;
+        voting.insert_proposal_procedure_impl(501, &second, 6)?;
+
+        let listed: Vec<_> = voting.list_proposals().into_iter().map(|p| p.gov_action_id).collect();
+        assert_eq!(
+            listed,
+            vec![first.gov_action_id.clone(), second.gov_action_id.clone()]
+        );
+
+        let info = voting.get_proposal_info(&second.gov_action_id).unwrap();
+        assert_eq!(info.submitted_epoch, 501);
+        assert_eq!(info.expiration, 507);
+        assert_eq!(info.ratified_epoch, None);
+        assert_eq!(info.expired_epoch, None);
+
+        voting.end_voting(&first.gov_action_id);
+        assert!(voting.get_proposal_info(&first.gov_action_id).is_none());
+        assert_eq!(voting.list_proposals().len(), 1);
+        Ok(())
+    }
+
     /// Simple test for general mechanics of action_status processing:
     /// Outcome, published at epoch E:
     /// * either expired at epoch E-1
@@ -11,13 +11,17 @@ use acropolis_common::{
         SPOStakeDistributionMessage, SnapshotMessage, SnapshotStateMessage, StateQuery,
         StateQueryResponse, StateTransitionMessage,
     },
+    metrics,
     queries::{
         errors::QueryError,
         governance::{
-            GovernanceStateQuery, GovernanceStateQueryResponse, ProposalInfo, ProposalVotes,
-            ProposalsList, DEFAULT_GOVERNANCE_QUERY_TOPIC,
+            GovernanceStateQuery, GovernanceStateQueryResponse, ProposalVotes, ProposalsList,
+            DEFAULT_GOVERNANCE_QUERY_TOPIC,
         },
     },
+    readiness,
+    spdd_chunks::SPDDAssembler,
+    state_digest::{StateDigest, StateDigestPublisher},
     state_history::{StateHistory, StateHistoryStore},
     BlockInfo,
 };
@@ -34,7 +38,7 @@ mod conway_voting_test;
 mod state;
 mod voting_state;
 
-use state::State;
+use state::{SPOStake, State};
 use voting_state::VotingRegistrationState;
 
 declare_cardano_reader!(
@@ -65,7 +69,8 @@ declare_cardano_reader!(
     "stake-spo-distribution-topic",
     "cardano.spo.distribution",
     SPOStakeDistribution,
-    SPOStakeDistributionMessage
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
 );
 
 declare_cardano_reader!(
@@ -209,8 +214,12 @@ impl GovernanceState {
             readers.drep_reader.read_with_rollbacks().await,
         )?;
 
-        let spo_msg =
-            vld.consume_opt("spo_reader", readers.spo_reader.read_with_rollbacks().await)?;
+        // Taken in a chunk at a time
+        let mut spo_stake = SPOStake::default();
+        let spo_read = vld.consume_opt(
+            "spo_reader",
+            readers.spo_reader.stream_with_rollbacks(&mut spo_stake).await,
+        )?;
 
         let drep_state = vld.consume_opt(
             "drep_state_reader",
@@ -222,16 +231,16 @@ impl GovernanceState {
             readers.spo_default_vote_reader.read_with_rollbacks().await,
         )?;
 
-        if let Some(d_spo) = spo_msg {
+        if spo_read.is_some() {
             if let Some(drep_state) = drep_state {
                 if let Some(d_drep) = d_drep {
                     if let Some(spo_default_vote) = spo_default_vote {
-                        if block_info.epoch != d_spo.epoch + 1 {
+                        if block_info.epoch != spo_stake.epoch + 1 {
                             vld.handle_error(
                                 "spo",
                                 &anyhow!(
                                     "SPO distibution {block_info:?} != SPO epoch + 1 ({})",
-                                    d_spo.epoch
+                                    spo_stake.epoch
                                 ),
                             );
                         }
@@ -250,7 +259,12 @@ impl GovernanceState {
                         vld.handle(
                             "handle_drep_stake",
                             state
-                                .handle_drep_stake(&d_drep, &drep_state, &d_spo, &spo_default_vote)
+                                .handle_drep_stake(
+                                    &d_drep,
+                                    &drep_state,
+                                    spo_stake,
+                                    &spo_default_vote,
+                                )
                                 .await,
                         );
                     }
@@ -267,6 +281,7 @@ impl GovernanceState {
         config: Arc<GovernanceStateConfig>,
         snapshot_subscription: Option<Box<dyn Subscription<Message>>>,
         mut readers: Box<Readers>,
+        digest_publisher: Option<StateDigestPublisher>,
     ) -> Result<()> {
         // Wait for snapshot bootstrap if subscription is provided
         if let Some(subscription) = snapshot_subscription {
@@ -297,9 +312,12 @@ impl GovernanceState {
         });
 
         let query_history = history.clone();
+        let query_metrics = metrics::QueryMetrics::new("governance-state");
         context.handle(&config.governance_query_topic, move |message| {
             let state_handle = query_history.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Governance(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Governance(
                         GovernanceStateQueryResponse::Error(QueryError::internal_error(
@@ -318,11 +336,7 @@ impl GovernanceState {
 
                     GovernanceStateQuery::GetProposalInfo { proposal } => {
                         match locked.get_proposal(proposal) {
-                            Some(proc) => {
-                                GovernanceStateQueryResponse::ProposalInfo(ProposalInfo {
-                                    procedure: proc.clone(),
-                                })
-                            }
+                            Some(info) => GovernanceStateQueryResponse::ProposalInfo(info),
                             None => GovernanceStateQueryResponse::Error(QueryError::not_found(
                                 format!("Proposal {} not found", proposal),
                             )),
@@ -372,12 +386,21 @@ impl GovernanceState {
             )?;
 
             if let Some(message) = primary.rollback_message() {
+                state = history.lock().await.get_rolled_back_state(primary.block_info().number);
                 context.publish(&config.enact_publish_topic, message.clone()).await?;
             }
 
             async {
                 if let Some(gov_procs) = primary.message() {
                     let blk_g = primary.block_info();
+
+                    // Digest of the state as the previous epoch ended
+                    if primary.epoch().is_some() {
+                        if let Some(digest_publisher) = &digest_publisher {
+                            digest_publisher.publish(blk_g, state.state_digest()).await;
+                        }
+                    }
+
                     if blk_g.new_epoch {
                         // New governance from new epoch means that we must prepare all governance
                         // outcome for the previous epoch.
@@ -456,6 +479,8 @@ impl GovernanceState {
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "governance-state").await?;
+
         let cfg = GovernanceStateConfig::new(&config);
 
         // Subscribe for snapshot bootstrap if starting from snapshot
@@ -465,10 +490,13 @@ impl GovernanceState {
             None
         };
 
-        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
-            "governance_state",
-            StateHistoryStore::default_block_store(),
-        )));
+        let history = Arc::new(Mutex::new(
+            StateHistory::<State>::new(
+                "governance_state",
+                StateHistoryStore::default_block_store(),
+            )
+            .with_size_estimate(State::estimated_size),
+        ));
 
         let readers = Box::new(Readers {
             gov_reader: GovReader::new(&context, &config).await?,
@@ -479,10 +507,20 @@ impl GovernanceState {
             spo_default_vote_reader: SPODefaultVoteReader::new(&context, &config).await?,
         });
 
+        let digest_publisher =
+            StateDigestPublisher::from_config(context.clone(), &config, "governance-state");
+
         tokio::spawn(async move {
-            Self::run(history, context, cfg, snapshot_subscription, readers)
-                .await
-                .unwrap_or_else(|e| error!("Failed: {e}"));
+            Self::run(
+                history,
+                context,
+                cfg,
+                snapshot_subscription,
+                readers,
+                digest_publisher,
+            )
+            .await
+            .unwrap_or_else(|e| error!("Failed: {e}"));
         });
 
         Ok(())
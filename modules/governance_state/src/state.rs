@@ -9,9 +9,11 @@ use acropolis_common::{
     messages::{
         DRepStakeDistributionMessage, DRepStateMessage, GovernanceOutcomesMessage,
         GovernanceProceduresMessage, ProtocolParamsMessage, SPODefaultVoteMessage,
-        SPOStakeDistributionMessage,
     },
     protocol_params::ProtocolVersion,
+    queries::governance::ProposalInfo,
+    spdd_chunks::SPDDSink,
+    state_digest::{SetDigest, StateDigest},
     validation::{GovernanceValidationError, ValidationError},
     BlockInfo, DRepCredential, DelegatedStake, DelegatedStakeDefaultVote, Era, GovActionId,
     Lovelace, PoolId, ProposalProcedure, TxHash, Voter, VotingProcedure,
@@ -21,6 +23,25 @@ use hex::ToHex;
 use imbl::HashMap;
 use tracing::debug;
 
+/// SPO stake distribution, taken in a chunk at a time
+#[derive(Default)]
+pub struct SPOStake {
+    /// Epoch which ended
+    pub epoch: u64,
+    stake: HashMap<PoolId, DelegatedStake>,
+}
+
+impl SPDDSink for SPOStake {
+    fn start(&mut self, epoch: u64, _pools: u64, _total_active: u64) {
+        self.epoch = epoch;
+        self.stake = HashMap::new();
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        self.stake.extend(spos.iter().cloned());
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct State {
     pub drep_stake_messages_count: usize,
@@ -37,6 +58,15 @@ pub struct State {
 }
 
 impl State {
+    /// Estimated memory used by the state, for the state history memory budget
+    pub fn estimated_size(&self) -> usize {
+        self.drep_stake.len() * size_of::<(DRepCredential, Lovelace)>()
+            + self.spo_stake.len() * size_of::<(PoolId, DelegatedStake)>()
+            + self.spo_default_vote.len() * size_of::<(PoolId, DelegatedStakeDefaultVote)>()
+            + self.alonzo_babbage_voting.estimated_size()
+            + self.conway_voting.estimated_size()
+    }
+
     pub fn new(
         verification_output_file: Option<String>,
         verify_votes_files: Option<String>,
@@ -85,7 +115,7 @@ impl State {
         &mut self,
         drep_message: &DRepStakeDistributionMessage,
         drep_state_message: &DRepStateMessage,
-        spo_message: &SPOStakeDistributionMessage,
+        spo_stake: SPOStake,
         spo_default_vote_message: &SPODefaultVoteMessage,
     ) -> Result<()> {
         self.drep_stake_messages_count += 1;
@@ -99,7 +129,7 @@ impl State {
         self.drep_stake = HashMap::from_iter(filtered_dreps);
         self.drep_no_confidence = drep_message.drdd.no_confidence;
         self.drep_abstain = drep_message.drdd.abstain;
-        self.spo_stake = HashMap::from_iter(spo_message.spos.iter().cloned());
+        self.spo_stake = spo_stake.stake;
         self.spo_default_vote = spo_default_vote_message.default_vote.clone();
 
         Ok(())
@@ -237,13 +267,13 @@ impl State {
     }
 
     /// Get list of actual voting proposals
-    pub fn list_proposals(&self) -> Vec<GovActionId> {
-        self.conway_voting.proposals.keys().cloned().collect()
+    pub fn list_proposals(&self) -> Vec<ProposalProcedure> {
+        self.conway_voting.list_proposals()
     }
 
     /// Get details for a specific proposal
-    pub fn get_proposal(&self, id: &GovActionId) -> Option<ProposalProcedure> {
-        self.conway_voting.proposals.get(id).map(|(_epoch, prop)| prop.clone())
+    pub fn get_proposal(&self, id: &GovActionId) -> Option<ProposalInfo> {
+        self.conway_voting.get_proposal_info(id)
     }
 
     /// Get list of votes for a specific proposal
@@ -270,3 +300,23 @@ impl State {
         &mut self.conway_voting
     }
 }
+
+impl StateDigest for State {
+    /// Ledger state only - the verification files and counters are left out
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        digest.insert(&("era", self.current_era))?;
+        for (credential, stake) in &self.drep_stake {
+            digest.insert(&("drep-stake", credential, stake))?;
+        }
+        digest.insert(&("drep-no-confidence", self.drep_no_confidence))?;
+        digest.insert(&("drep-abstain", self.drep_abstain))?;
+        for (pool, stake) in &self.spo_stake {
+            digest.insert(&("spo-stake", pool, stake))?;
+        }
+        for (pool, vote) in &self.spo_default_vote {
+            digest.insert(&("spo-default-vote", pool, vote))?;
+        }
+        self.alonzo_babbage_voting.add_to_digest(digest)?;
+        self.conway_voting.add_to_digest(digest)
+    }
+}
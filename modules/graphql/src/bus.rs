@@ -0,0 +1,100 @@
+//! Access to the state query topics from GraphQL resolvers
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    messages::{Message, StateQuery, StateQueryResponse},
+    queries::{errors::QueryError, utils::query_state},
+};
+use async_graphql::{ErrorExtensions, Result};
+use caryatid_sdk::Context;
+
+/// Query topics the resolvers fan out to
+#[derive(Clone)]
+pub struct Topics {
+    pub accounts: String,
+    pub assets: String,
+    pub blocks: String,
+    pub dreps: String,
+    pub governance: String,
+    pub pools: String,
+    pub transactions: String,
+}
+
+/// Shared resolver context, stored in the schema data
+#[derive(Clone)]
+pub struct Bus {
+    context: Arc<Context<Message>>,
+    pub topics: Topics,
+}
+
+impl Bus {
+    pub fn new(context: Arc<Context<Message>>, topics: Topics) -> Self {
+        Self { context, topics }
+    }
+
+    async fn request<T>(
+        &self,
+        topic: &str,
+        query: StateQuery,
+        extractor: impl FnOnce(StateQueryResponse) -> Option<Result<T, QueryError>>,
+    ) -> Result<T, QueryError> {
+        let msg = Arc::new(Message::StateQuery(query));
+        query_state(&self.context, topic, msg, |message| match message {
+            Message::StateQueryResponse(response) => extractor(response).unwrap_or_else(|| {
+                Err(QueryError::internal_error(format!(
+                    "Unexpected response message type while calling {topic}"
+                )))
+            }),
+            _ => Err(QueryError::internal_error(format!(
+                "Unexpected message type while calling {topic}"
+            ))),
+        })
+        .await
+    }
+
+    /// Send a state query and extract the expected response
+    pub async fn query<T>(
+        &self,
+        topic: &str,
+        query: StateQuery,
+        extractor: impl FnOnce(StateQueryResponse) -> Option<Result<T, QueryError>>,
+    ) -> Result<T> {
+        self.request(topic, query, extractor).await.map_err(to_graphql_error)
+    }
+
+    /// As `query`, but a missing resource resolves to `None` (GraphQL `null`)
+    pub async fn query_optional<T>(
+        &self,
+        topic: &str,
+        query: StateQuery,
+        extractor: impl FnOnce(StateQueryResponse) -> Option<Result<T, QueryError>>,
+    ) -> Result<Option<T>> {
+        match self.request(topic, query, extractor).await {
+            Ok(value) => Ok(Some(value)),
+            Err(QueryError::NotFound { .. }) => Ok(None),
+            Err(e) => Err(to_graphql_error(e)),
+        }
+    }
+}
+
+/// Convert a query error to a GraphQL error, with a machine-readable `code` extension
+pub fn to_graphql_error(error: QueryError) -> async_graphql::Error {
+    let code = match &error {
+        QueryError::NotFound { .. } => "NOT_FOUND",
+        QueryError::Internal { .. } => "INTERNAL",
+        QueryError::StorageDisabled { .. } => "STORAGE_DISABLED",
+        QueryError::InvalidRequest { .. } => "BAD_REQUEST",
+        QueryError::NotImplemented { .. } => "NOT_IMPLEMENTED",
+        QueryError::NotSynced { .. } => "NOT_SYNCED",
+        QueryError::RolledBack { .. } => "ROLLED_BACK",
+        QueryError::Unavailable { .. } => "UNAVAILABLE",
+    };
+    async_graphql::Error::new(error.to_string()).extend_with(|_, e| e.set("code", code))
+}
+
+/// Error for a malformed argument
+pub fn invalid_argument(name: &str, message: impl std::fmt::Display) -> async_graphql::Error {
+    async_graphql::Error::new(format!("Invalid {name}: {message}"))
+        .extend_with(|_, e| e.set("code", "BAD_REQUEST"))
+}
@@ -0,0 +1,76 @@
+//! Acropolis GraphQL module for Caryatid
+//!
+//! Serves a GraphQL schema over blocks, transactions, accounts, pools, assets
+//! and governance at `/graphql`. Each field is resolved by querying the existing
+//! state query topics, so clients fetch only the fields - and pay only for the
+//! queries - they select. `GET /graphql` serves a GraphiQL explorer.
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    configuration::{get_bool_flag, get_string_flag, get_u64_flag},
+    messages::Message,
+    queries::{
+        accounts::DEFAULT_ACCOUNTS_QUERY_TOPIC, assets::DEFAULT_ASSETS_QUERY_TOPIC,
+        blocks::DEFAULT_BLOCKS_QUERY_TOPIC, governance::DEFAULT_DREPS_QUERY_TOPIC,
+        governance::DEFAULT_GOVERNANCE_QUERY_TOPIC, pools::DEFAULT_POOLS_QUERY_TOPIC,
+        transactions::DEFAULT_TRANSACTIONS_QUERY_TOPIC,
+    },
+};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use tracing::{error, info};
+
+mod bus;
+mod schema;
+mod server;
+
+use bus::{Bus, Topics};
+
+const DEFAULT_ENABLED: (&str, bool) = ("enabled", false);
+const DEFAULT_ADDRESS: (&str, &str) = ("address", "127.0.0.1");
+const DEFAULT_PORT: (&str, u64) = ("port", 4343);
+/// Deepest selection accepted, bounding the queries one request can fan out to
+const DEFAULT_MAX_DEPTH: (&str, u64) = ("max-depth", 8);
+
+/// GraphQL module
+#[module(
+    message_type(Message),
+    name = "graphql",
+    description = "GraphQL API over the query bus"
+)]
+pub struct GraphQL;
+
+impl GraphQL {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        if !get_bool_flag(&config, DEFAULT_ENABLED) {
+            info!("GraphQL server is disabled in configuration");
+            return Ok(());
+        }
+
+        let address = get_string_flag(&config, DEFAULT_ADDRESS);
+        let port: u16 = get_u64_flag(&config, DEFAULT_PORT).try_into()?;
+        let max_depth = get_u64_flag(&config, DEFAULT_MAX_DEPTH).max(1) as usize;
+
+        let topics = Topics {
+            accounts: get_string_flag(&config, DEFAULT_ACCOUNTS_QUERY_TOPIC),
+            assets: get_string_flag(&config, DEFAULT_ASSETS_QUERY_TOPIC),
+            blocks: get_string_flag(&config, DEFAULT_BLOCKS_QUERY_TOPIC),
+            dreps: get_string_flag(&config, DEFAULT_DREPS_QUERY_TOPIC),
+            governance: get_string_flag(&config, DEFAULT_GOVERNANCE_QUERY_TOPIC),
+            pools: get_string_flag(&config, DEFAULT_POOLS_QUERY_TOPIC),
+            transactions: get_string_flag(&config, DEFAULT_TRANSACTIONS_QUERY_TOPIC),
+        };
+        let schema = schema::build_schema(Bus::new(context.clone(), topics), max_depth);
+
+        info!("Initializing GraphQL server on {}:{}", address, port);
+        tokio::spawn(async move {
+            if let Err(e) = server::run(&address, port, schema).await {
+                error!("GraphQL server error: {}", e);
+            }
+        });
+
+        Ok(())
+    }
+}
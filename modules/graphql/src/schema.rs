@@ -0,0 +1,725 @@
+//! GraphQL schema over blocks, transactions, accounts, pools, assets and governance
+//!
+//! Root fields look an entity up with a single state query. Nested fields which need
+//! further queries (a block's transactions, an account's pool, a pool's delegators,
+//! ...) are resolved only when selected, so clients pay only for what they ask for.
+
+use acropolis_common::{
+    messages::{StateQuery, StateQueryResponse},
+    queries::{
+        accounts::{AccountInfo, AccountsStateQuery, AccountsStateQueryResponse},
+        assets::{AssetsStateQuery, AssetsStateQueryResponse},
+        blocks::{BlockInfo, BlockKey, BlocksStateQuery, BlocksStateQueryResponse},
+        governance::{
+            DRepInfoWithDelegators, GovernanceStateQuery, GovernanceStateQueryResponse,
+            ProposalInfo,
+        },
+        misc::Order,
+        pools::{PoolsStateQuery, PoolsStateQueryResponse},
+        transactions::{TransactionInfo, TransactionsStateQuery, TransactionsStateQueryResponse},
+    },
+    serialization::{Bech32Conversion, Bech32WithHrp},
+    AssetMetadata, AssetMetadataSource, AssetName, BlockHash, Credential, DRepChoice, GovActionId,
+    PolicyId, PoolId, PoolRegistration, StakeAddress, TxHash,
+};
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject,
+};
+
+use crate::bus::{invalid_argument, Bus};
+
+pub type AcropolisSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Maximum items in a nested list field
+const MAX_COUNT: u64 = 100;
+
+/// Build the schema, rejecting queries nested deeper than `max_depth`
+pub fn build_schema(bus: Bus, max_depth: usize) -> AcropolisSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(bus)
+        .limit_depth(max_depth)
+        .finish()
+}
+
+/// Turn `count`/`page` arguments into a (limit, skip) pair
+fn page_bounds(count: u64, page: u64) -> Result<(u64, u64)> {
+    if count == 0 || count > MAX_COUNT {
+        return Err(invalid_argument(
+            "count",
+            format!("must be between 1 and {MAX_COUNT}"),
+        ));
+    }
+    if page == 0 {
+        return Err(invalid_argument("page", "must be at least 1"));
+    }
+    Ok((count, (page - 1) * count))
+}
+
+fn parse_hex_hash<T: for<'a> TryFrom<&'a [u8]>>(name: &str, text: &str) -> Result<T> {
+    let bytes = hex::decode(text).map_err(|e| invalid_argument(name, e))?;
+    T::try_from(bytes.as_slice()).map_err(|_| invalid_argument(name, "wrong length"))
+}
+
+fn stake_address_string(address: &StakeAddress) -> Result<String> {
+    Ok(address.to_string()?)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Block by hash or number
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        hash: Option<String>,
+        number: Option<u64>,
+    ) -> Result<Option<Block>> {
+        let block_key = match (hash, number) {
+            (Some(hash), None) => BlockKey::Hash(parse_hex_hash("hash", &hash)?),
+            (None, Some(number)) => BlockKey::Number(number),
+            _ => {
+                return Err(invalid_argument(
+                    "block",
+                    "give exactly one of hash or number",
+                ))
+            }
+        };
+        query_block(ctx.data::<Bus>()?, block_key).await
+    }
+
+    /// Most recent block
+    async fn latest_block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        let bus = ctx.data::<Bus>()?;
+        bus.query_optional(
+            &bus.topics.blocks,
+            StateQuery::Blocks(BlocksStateQuery::GetLatestBlock),
+            |response| match response {
+                StateQueryResponse::Blocks(BlocksStateQueryResponse::LatestBlock(info)) => {
+                    Some(Ok(Block::from(info)))
+                }
+                StateQueryResponse::Blocks(BlocksStateQueryResponse::Error(e)) => Some(Err(e)),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Transaction by hash
+    async fn transaction(&self, ctx: &Context<'_>, hash: String) -> Result<Option<Transaction>> {
+        query_transaction(ctx.data::<Bus>()?, parse_hex_hash("hash", &hash)?).await
+    }
+
+    /// Stake account by stake address
+    async fn account(&self, ctx: &Context<'_>, stake_address: String) -> Result<Option<Account>> {
+        let account = StakeAddress::from_string(&stake_address)
+            .map_err(|e| invalid_argument("stake address", e))?;
+        let bus = ctx.data::<Bus>()?;
+        let info = bus
+            .query_optional(
+                &bus.topics.accounts,
+                StateQuery::Accounts(AccountsStateQuery::GetAccountInfo {
+                    account: account.clone(),
+                }),
+                |response| match response {
+                    StateQueryResponse::Accounts(AccountsStateQueryResponse::AccountInfo(info)) => {
+                        Some(Ok(info))
+                    }
+                    StateQueryResponse::Accounts(AccountsStateQueryResponse::Error(e)) => {
+                        Some(Err(e))
+                    }
+                    _ => None,
+                },
+            )
+            .await?;
+
+        info.map(|info| Account::new(stake_address, info)).transpose()
+    }
+
+    /// Stake pool by bech32 pool ID
+    async fn pool(&self, ctx: &Context<'_>, pool_id: String) -> Result<Option<Pool>> {
+        let pool_id = PoolId::from_bech32(&pool_id).map_err(|e| invalid_argument("pool ID", e))?;
+        query_pool(ctx.data::<Bus>()?, pool_id).await
+    }
+
+    /// Native asset by hex policy ID and asset name
+    async fn asset(&self, ctx: &Context<'_>, asset: String) -> Result<Option<Asset>> {
+        let bytes = hex::decode(&asset).map_err(|e| invalid_argument("asset", e))?;
+        if bytes.len() < 28 {
+            return Err(invalid_argument("asset", "must be at least 28 bytes"));
+        }
+        let (policy, name) = bytes.split_at(28);
+        let policy =
+            PolicyId::try_from(policy).map_err(|_| invalid_argument("asset", "bad policy"))?;
+        let name = AssetName::new(name)
+            .ok_or_else(|| invalid_argument("asset", "name longer than 32 bytes"))?;
+
+        let bus = ctx.data::<Bus>()?;
+        let info = bus
+            .query_optional(
+                &bus.topics.assets,
+                StateQuery::Assets(AssetsStateQuery::GetAssetInfo { policy, name }),
+                |response| match response {
+                    StateQueryResponse::Assets(AssetsStateQueryResponse::AssetInfo(info)) => {
+                        Some(Ok(info))
+                    }
+                    StateQueryResponse::Assets(AssetsStateQueryResponse::Error(e)) => Some(Err(e)),
+                    _ => None,
+                },
+            )
+            .await?;
+
+        Ok(info.map(|(quantity, record)| Asset {
+            asset,
+            policy_id: hex::encode(policy),
+            asset_name: hex::encode(name.as_slice()),
+            quantity: quantity.to_string(),
+            mint_or_burn_count: record.mint_or_burn_count,
+            metadata: TokenMetadata::new(&record.metadata),
+        }))
+    }
+
+    /// DRep by bech32 DRep ID
+    async fn drep(&self, ctx: &Context<'_>, drep_id: String) -> Result<Option<DRep>> {
+        let drep_credential =
+            Credential::from_drep_bech32(&drep_id).map_err(|e| invalid_argument("DRep ID", e))?;
+        let bus = ctx.data::<Bus>()?;
+        let info = bus
+            .query_optional(
+                &bus.topics.dreps,
+                StateQuery::Governance(GovernanceStateQuery::GetDRepInfoWithDelegators {
+                    drep_credential,
+                }),
+                |response| match response {
+                    StateQueryResponse::Governance(
+                        GovernanceStateQueryResponse::DRepInfoWithDelegators(info),
+                    ) => Some(Ok(info)),
+                    StateQueryResponse::Governance(GovernanceStateQueryResponse::Error(e)) => {
+                        Some(Err(e))
+                    }
+                    _ => None,
+                },
+            )
+            .await?;
+
+        info.map(|info| DRep::new(drep_id, info)).transpose()
+    }
+
+    /// Governance proposal by bech32 governance action ID
+    async fn proposal(&self, ctx: &Context<'_>, id: String) -> Result<Option<Proposal>> {
+        let proposal =
+            GovActionId::from_bech32(&id).map_err(|e| invalid_argument("proposal ID", e))?;
+        let bus = ctx.data::<Bus>()?;
+        let info = bus
+            .query_optional(
+                &bus.topics.governance,
+                StateQuery::Governance(GovernanceStateQuery::GetProposalInfo { proposal }),
+                |response| match response {
+                    StateQueryResponse::Governance(GovernanceStateQueryResponse::ProposalInfo(
+                        info,
+                    )) => Some(Ok(info)),
+                    StateQueryResponse::Governance(GovernanceStateQueryResponse::Error(e)) => {
+                        Some(Err(e))
+                    }
+                    _ => None,
+                },
+            )
+            .await?;
+
+        info.map(|info| Proposal::new(id, info)).transpose()
+    }
+}
+
+async fn query_block(bus: &Bus, block_key: BlockKey) -> Result<Option<Block>> {
+    bus.query_optional(
+        &bus.topics.blocks,
+        StateQuery::Blocks(BlocksStateQuery::GetBlockInfo { block_key }),
+        |response| match response {
+            StateQueryResponse::Blocks(BlocksStateQueryResponse::BlockInfo(info)) => {
+                Some(Ok(Block::from(info)))
+            }
+            StateQueryResponse::Blocks(BlocksStateQueryResponse::Error(e)) => Some(Err(e)),
+            _ => None,
+        },
+    )
+    .await
+}
+
+async fn query_transaction(bus: &Bus, tx_hash: TxHash) -> Result<Option<Transaction>> {
+    bus.query_optional(
+        &bus.topics.transactions,
+        StateQuery::Transactions(TransactionsStateQuery::GetTransactionInfo { tx_hash }),
+        |response| match response {
+            StateQueryResponse::Transactions(TransactionsStateQueryResponse::TransactionInfo(
+                info,
+            )) => Some(Ok(Transaction::from(info))),
+            StateQueryResponse::Transactions(TransactionsStateQueryResponse::Error(e)) => {
+                Some(Err(e))
+            }
+            _ => None,
+        },
+    )
+    .await
+}
+
+async fn query_pool(bus: &Bus, pool_id: PoolId) -> Result<Option<Pool>> {
+    let registration = bus
+        .query_optional(
+            &bus.topics.pools,
+            StateQuery::Pools(PoolsStateQuery::GetPoolInfo { pool_id }),
+            |response| match response {
+                StateQueryResponse::Pools(PoolsStateQueryResponse::PoolInfo(info)) => {
+                    Some(Ok(info))
+                }
+                StateQueryResponse::Pools(PoolsStateQueryResponse::Error(e)) => Some(Err(e)),
+                _ => None,
+            },
+        )
+        .await?;
+
+    registration.map(Pool::try_from).transpose()
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Block {
+    hash: String,
+    number: u64,
+    slot: u64,
+    epoch: u64,
+    epoch_slot: u64,
+    timestamp: u64,
+    size: u64,
+    tx_count: u64,
+    /// Total output in lovelace
+    output: Option<String>,
+    /// Total fees in lovelace
+    fees: Option<String>,
+    previous_block: Option<String>,
+    next_block: Option<String>,
+    confirmations: u64,
+    #[graphql(skip)]
+    key: BlockHash,
+}
+
+impl From<BlockInfo> for Block {
+    fn from(info: BlockInfo) -> Self {
+        Self {
+            hash: info.hash.to_string(),
+            number: info.number,
+            slot: info.slot,
+            epoch: info.epoch,
+            epoch_slot: info.epoch_slot,
+            timestamp: info.timestamp,
+            size: info.size,
+            tx_count: info.tx_count,
+            output: info.output.map(|v| v.to_string()),
+            fees: info.fees.map(|v| v.to_string()),
+            previous_block: info.previous_block.map(|h| h.to_string()),
+            next_block: info.next_block.map(|h| h.to_string()),
+            confirmations: info.confirmations,
+            key: info.hash,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Block {
+    /// Transactions in the block, in block order
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 100)] count: u64,
+        #[graphql(default = 1)] page: u64,
+    ) -> Result<Vec<Transaction>> {
+        let (limit, skip) = page_bounds(count, page)?;
+        let bus = ctx.data::<Bus>()?;
+        let hashes = bus
+            .query(
+                &bus.topics.blocks,
+                StateQuery::Blocks(BlocksStateQuery::GetBlockTransactions {
+                    block_key: BlockKey::Hash(self.key),
+                    limit,
+                    skip,
+                    order: Order::Asc,
+                }),
+                |response| match response {
+                    StateQueryResponse::Blocks(BlocksStateQueryResponse::BlockTransactions(
+                        txs,
+                    )) => Some(Ok(txs.hashes)),
+                    StateQueryResponse::Blocks(BlocksStateQueryResponse::Error(e)) => Some(Err(e)),
+                    _ => None,
+                },
+            )
+            .await?;
+
+        let mut transactions = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            if let Some(tx) = query_transaction(bus, hash).await? {
+                transactions.push(tx);
+            }
+        }
+        Ok(transactions)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Transaction {
+    hash: String,
+    block_hash: String,
+    block_number: u64,
+    block_time: u64,
+    epoch: u64,
+    slot: u64,
+    /// Index within the block
+    index: u64,
+    /// Fee in lovelace
+    fee: Option<String>,
+    size: u64,
+    invalid_before: Option<u64>,
+    invalid_hereafter: Option<u64>,
+    utxo_count: u64,
+    withdrawal_count: u64,
+    mir_cert_count: u64,
+    delegation_count: u64,
+    stake_cert_count: u64,
+    pool_update_count: u64,
+    pool_retire_count: u64,
+    asset_mint_or_burn_count: u64,
+    redeemer_count: u64,
+    valid_contract: bool,
+    #[graphql(skip)]
+    block_key: BlockHash,
+}
+
+impl From<TransactionInfo> for Transaction {
+    fn from(info: TransactionInfo) -> Self {
+        Self {
+            hash: info.hash.to_string(),
+            block_hash: info.block_hash.to_string(),
+            block_number: info.block_number,
+            block_time: info.block_time,
+            epoch: info.epoch,
+            slot: info.slot,
+            index: info.index,
+            fee: info.recorded_fee.map(|v| v.to_string()),
+            size: info.size,
+            invalid_before: info.invalid_before,
+            invalid_hereafter: info.invalid_after,
+            utxo_count: info.utxo_count,
+            withdrawal_count: info.withdrawal_count,
+            mir_cert_count: info.mir_cert_count,
+            delegation_count: info.delegation_count,
+            stake_cert_count: info.stake_cert_count,
+            pool_update_count: info.pool_update_count,
+            pool_retire_count: info.pool_retire_count,
+            asset_mint_or_burn_count: info.asset_mint_or_burn_count,
+            redeemer_count: info.redeemer_count,
+            valid_contract: info.valid_contract,
+            block_key: info.block_hash,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Transaction {
+    /// Block containing the transaction
+    async fn block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        query_block(ctx.data::<Bus>()?, BlockKey::Hash(self.block_key)).await
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Account {
+    stake_address: String,
+    /// Lovelace in UTxOs controlled by the stake key
+    utxo_value: String,
+    /// Withdrawable rewards in lovelace
+    rewards: String,
+    /// UTxO value plus rewards, in lovelace
+    controlled_amount: String,
+    /// Delegated DRep ID, or `drep_always_abstain` / `drep_always_no_confidence`
+    delegated_drep: Option<String>,
+    #[graphql(skip)]
+    delegated_pool: Option<PoolId>,
+}
+
+impl Account {
+    fn new(stake_address: String, info: AccountInfo) -> Result<Self> {
+        let delegated_drep = match info.delegated_drep {
+            Some(DRepChoice::Key(hash)) => Some(hash.to_vec().to_bech32_with_hrp("drep")?),
+            Some(DRepChoice::Script(hash)) => {
+                Some(hash.to_vec().to_bech32_with_hrp("drep_script")?)
+            }
+            Some(DRepChoice::Abstain) => Some("drep_always_abstain".to_string()),
+            Some(DRepChoice::NoConfidence) => Some("drep_always_no_confidence".to_string()),
+            None => None,
+        };
+
+        Ok(Self {
+            stake_address,
+            utxo_value: info.utxo_value.to_string(),
+            rewards: info.rewards.to_string(),
+            controlled_amount: (info.utxo_value + info.rewards).to_string(),
+            delegated_drep,
+            delegated_pool: info.delegated_spo,
+        })
+    }
+}
+
+#[ComplexObject]
+impl Account {
+    /// Pool the account delegates to
+    async fn pool(&self, ctx: &Context<'_>) -> Result<Option<Pool>> {
+        match self.delegated_pool {
+            Some(pool_id) => query_pool(ctx.data::<Bus>()?, pool_id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Pool {
+    pool_id: String,
+    vrf_key_hash: String,
+    /// Pledge in lovelace
+    pledge: String,
+    /// Fixed cost in lovelace
+    cost: String,
+    margin: f64,
+    reward_account: String,
+    owners: Vec<String>,
+    metadata_url: Option<String>,
+    metadata_hash: Option<String>,
+    #[graphql(skip)]
+    key: PoolId,
+}
+
+impl TryFrom<PoolRegistration> for Pool {
+    type Error = async_graphql::Error;
+
+    fn try_from(registration: PoolRegistration) -> Result<Self> {
+        let margin = if registration.margin.denominator == 0 {
+            0.0
+        } else {
+            registration.margin.numerator as f64 / registration.margin.denominator as f64
+        };
+
+        Ok(Self {
+            pool_id: registration.operator.to_bech32()?,
+            vrf_key_hash: hex::encode(registration.vrf_key_hash),
+            pledge: registration.pledge.to_string(),
+            cost: registration.cost.to_string(),
+            margin,
+            reward_account: stake_address_string(&registration.reward_account)?,
+            owners: registration
+                .pool_owners
+                .iter()
+                .map(stake_address_string)
+                .collect::<Result<_>>()?,
+            metadata_url: registration.pool_metadata.as_ref().map(|m| m.url.clone()),
+            metadata_hash: registration.pool_metadata.as_ref().map(|m| hex::encode(m.hash)),
+            key: registration.operator,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Delegator {
+    stake_address: String,
+    /// Live stake in lovelace
+    amount: String,
+}
+
+#[ComplexObject]
+impl Pool {
+    /// Accounts delegating to the pool, ordered by stake address
+    async fn delegators(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 100)] count: u64,
+        #[graphql(default = 1)] page: u64,
+    ) -> Result<Vec<Delegator>> {
+        let (limit, skip) = page_bounds(count, page)?;
+        let bus = ctx.data::<Bus>()?;
+        let delegators = bus
+            .query(
+                &bus.topics.pools,
+                StateQuery::Pools(PoolsStateQuery::GetPoolDelegatorsPage {
+                    pool_id: self.key,
+                    limit,
+                    skip,
+                    order: Order::Asc,
+                }),
+                |response| match response {
+                    StateQueryResponse::Pools(PoolsStateQueryResponse::PoolDelegators(
+                        delegators,
+                    )) => Some(Ok(delegators.delegators)),
+                    StateQueryResponse::Pools(PoolsStateQueryResponse::Error(e)) => Some(Err(e)),
+                    _ => None,
+                },
+            )
+            .await?;
+
+        delegators
+            .iter()
+            .map(|(address, amount)| {
+                Ok(Delegator {
+                    stake_address: stake_address_string(address)?,
+                    amount: amount.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Asset {
+    /// Hex policy ID followed by hex asset name
+    asset: String,
+    policy_id: String,
+    asset_name: String,
+    quantity: String,
+    mint_or_burn_count: u64,
+    /// On-chain CIP-25 or CIP-68 metadata, if any
+    metadata: Option<TokenMetadata>,
+}
+
+/// Token metadata normalized across CIP-25 and CIP-68
+#[derive(SimpleObject)]
+pub struct TokenMetadata {
+    /// `CIP25` or `CIP68`
+    source: String,
+    /// Version of the standard, e.g. `CIP68v2`
+    standard: Option<String>,
+    name: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    media_type: Option<String>,
+    ticker: Option<String>,
+    url: Option<String>,
+    logo: Option<String>,
+    decimals: Option<u64>,
+    /// Times the metadata has been set or replaced on chain
+    updates: u64,
+}
+
+impl TokenMetadata {
+    fn new(metadata: &AssetMetadata) -> Option<Self> {
+        let normalized = metadata.normalized.clone()?;
+        let standard = match normalized.source {
+            AssetMetadataSource::CIP25 => metadata.cip25_version,
+            AssetMetadataSource::CIP68 => metadata.cip68_version,
+        };
+        Some(Self {
+            source: format!("{:?}", normalized.source),
+            standard: standard.map(|s| format!("{s:?}")),
+            name: normalized.name,
+            description: normalized.description,
+            image: normalized.image,
+            media_type: normalized.media_type,
+            ticker: normalized.ticker,
+            url: normalized.url,
+            logo: normalized.logo,
+            decimals: normalized.decimals,
+            updates: metadata.updates,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct DRep {
+    drep_id: String,
+    /// Deposit in lovelace
+    deposit: String,
+    retired: bool,
+    expired: bool,
+    active_epoch: Option<u64>,
+    last_active_epoch: u64,
+    delegators: Vec<String>,
+}
+
+impl DRep {
+    fn new(drep_id: String, drep: DRepInfoWithDelegators) -> Result<Self> {
+        Ok(Self {
+            drep_id,
+            deposit: drep.info.deposit.to_string(),
+            retired: drep.info.retired,
+            expired: drep.info.expired,
+            active_epoch: drep.info.active_epoch,
+            last_active_epoch: drep.info.last_active_epoch,
+            delegators: drep.delegators.iter().map(stake_address_string).collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Proposal {
+    id: String,
+    tx_hash: String,
+    cert_index: u32,
+    governance_type: String,
+    /// Deposit in lovelace
+    deposit: String,
+    return_address: String,
+    submitted_epoch: u64,
+    /// Last epoch the proposal can be voted on
+    expiration: u64,
+    ratified_epoch: Option<u64>,
+    enacted_epoch: Option<u64>,
+    expired_epoch: Option<u64>,
+    anchor_url: String,
+    anchor_hash: String,
+}
+
+impl Proposal {
+    fn new(id: String, proposal: ProposalInfo) -> Result<Self> {
+        let procedure = proposal.procedure;
+        Ok(Self {
+            id,
+            tx_hash: procedure.gov_action_id.transaction_id.to_string(),
+            cert_index: procedure.gov_action_id.action_index as u32,
+            governance_type: procedure.gov_action.get_action_name().to_string(),
+            deposit: procedure.deposit.to_string(),
+            return_address: stake_address_string(&procedure.reward_account)?,
+            submitted_epoch: proposal.submitted_epoch,
+            expiration: proposal.expiration,
+            ratified_epoch: proposal.ratified_epoch,
+            enacted_epoch: proposal.enacted_epoch,
+            expired_epoch: proposal.expired_epoch,
+            anchor_url: procedure.anchor.url,
+            anchor_hash: hex::encode(procedure.anchor.data_hash),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_bounds_converts_pages_to_offsets() {
+        assert_eq!(page_bounds(100, 1).unwrap(), (100, 0));
+        assert_eq!(page_bounds(10, 3).unwrap(), (10, 20));
+        assert!(page_bounds(0, 1).is_err());
+        assert!(page_bounds(101, 1).is_err());
+        assert!(page_bounds(10, 0).is_err());
+    }
+
+    #[test]
+    fn sdl_exposes_nested_fields() {
+        let sdl = Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish().sdl();
+        for field in [
+            "latestBlock",
+            "transactions(count: Int! = 100, page: Int! = 1)",
+            "delegators(count: Int! = 100, page: Int! = 1)",
+            "drep(drepId: String!)",
+            "proposal(id: String!)",
+        ] {
+            assert!(sdl.contains(field), "schema is missing {field}");
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! HTTP server for the GraphQL endpoint and its GraphiQL explorer
+
+use anyhow::Result;
+use async_graphql::http::GraphiQLSource;
+use async_graphql_axum::GraphQL;
+use axum::{
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use tokio::net::TcpListener;
+use tracing::info;
+
+use crate::schema::AcropolisSchema;
+
+pub async fn run(address: &str, port: u16, schema: AcropolisSchema) -> Result<()> {
+    let app = Router::new().route("/graphql", get(graphiql).post_service(GraphQL::new(schema)));
+
+    let listener = TcpListener::bind((address, port)).await?;
+    info!("GraphQL listening on http://{}:{}/graphql", address, port);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
@@ -9,10 +9,12 @@ use acropolis_common::messages::{
     ProtocolParamsMessage, StakeAddressDeltasMessage, StakeRewardDeltasMessage,
     StateTransitionMessage, TxCertificatesMessage, WithdrawalsMessage,
 };
+use acropolis_common::metrics;
 use acropolis_common::queries::accounts::{
     AccountsStateQuery, AccountsStateQueryResponse, DEFAULT_HISTORICAL_ACCOUNTS_QUERY_TOPIC,
 };
 use acropolis_common::queries::errors::QueryError;
+use acropolis_common::readiness;
 use anyhow::{bail, Result};
 use caryatid_sdk::{message_bus::Subscription, module, Context};
 use config::Config;
@@ -172,9 +174,9 @@ impl HistoricalAccountsState {
 
             // Handle withdrawals
             match withdrawals_reader.read_with_rollbacks().await? {
-                RollbackWrapper::Normal((_, withdrawals_msg)) => {
+                RollbackWrapper::Normal((block_info, withdrawals_msg)) => {
                     let mut state = state_mutex.lock().await;
-                    state.handle_withdrawals(&withdrawals_msg);
+                    state.handle_withdrawals(&withdrawals_msg, block_info.epoch as u32);
                 }
                 RollbackWrapper::Rollback(_) => {}
             }
@@ -219,6 +221,8 @@ impl HistoricalAccountsState {
 
     /// Async initialisation
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "historical-accounts-state").await?;
+
         // Get configuration
         let is_snapshot_mode = StartupMode::from_config(config.as_ref()).is_snapshot();
 
@@ -248,9 +252,12 @@ impl HistoricalAccountsState {
         let state_mutex = Arc::new(Mutex::new(state));
         let state_query = state_mutex.clone();
 
+        let query_metrics = metrics::QueryMetrics::new("historical-accounts-state");
         context.handle(&historical_accounts_query_topic, move |message| {
             let state = state_query.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Accounts(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Accounts(
                         AccountsStateQueryResponse::Error(QueryError::internal_error(
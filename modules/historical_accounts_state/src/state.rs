@@ -215,7 +215,7 @@ impl State {
 
                 // MIR certs
                 TxCertificate::MoveInstantaneousReward(mir) => {
-                    self.handle_mir(&mir.target, &tx_cert.tx_identifier);
+                    self.handle_mir(&mir.target, &tx_cert.tx_identifier, epoch);
                 }
 
                 _ => (),
@@ -234,7 +234,7 @@ impl State {
         }
     }
 
-    pub fn handle_withdrawals(&mut self, withdrawals_msg: &WithdrawalsMessage) {
+    pub fn handle_withdrawals(&mut self, withdrawals_msg: &WithdrawalsMessage, epoch: u32) {
         let window = self.volatile.window.back_mut().expect("window should never be empty");
 
         for w in &withdrawals_msg.withdrawals {
@@ -246,6 +246,7 @@ impl State {
                 .push(AccountWithdrawal {
                     tx_identifier: w.tx_identifier,
                     amount: w.value,
+                    epoch,
                 })
         }
     }
@@ -420,7 +421,12 @@ impl State {
         entry.delegation_history.get_or_insert_with(Vec::new).push(update);
     }
 
-    fn handle_mir(&mut self, mir: &InstantaneousRewardTarget, tx_identifier: &TxIdentifier) {
+    fn handle_mir(
+        &mut self,
+        mir: &InstantaneousRewardTarget,
+        tx_identifier: &TxIdentifier,
+        epoch: u32,
+    ) {
         let volatile = self.volatile.window.back_mut().expect("window should never be empty");
 
         if let InstantaneousRewardTarget::StakeAddresses(payments) = mir {
@@ -437,6 +443,7 @@ impl State {
                 let update = AccountWithdrawal {
                     tx_identifier: *tx_identifier,
                     amount: *amount as u64,
+                    epoch,
                 };
 
                 entry.mir_history.get_or_insert_with(Vec::new).push(update);
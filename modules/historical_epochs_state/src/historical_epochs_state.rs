@@ -4,19 +4,23 @@
 use crate::immutable_historical_epochs_state::ImmutableHistoricalEpochsState;
 use crate::state::{HistoricalEpochsStateConfig, State};
 use acropolis_common::caryatid::{PrimaryRead, RollbackWrapper};
-use acropolis_common::configuration::{get_bool_flag, get_string_flag, StartupMode};
+use acropolis_common::configuration::{get_bool_flag, get_string_flag, get_u64_flag, StartupMode};
 use acropolis_common::declare_cardano_reader;
 use acropolis_common::messages::{
     EpochActivityMessage, ProtocolParamsMessage, RawBlockMessage, StateQuery,
     StateTransitionMessage,
 };
 use acropolis_common::queries::epochs::{
-    EpochInfo, EpochsStateQuery, NextEpochs, PreviousEpochs, DEFAULT_HISTORICAL_EPOCHS_QUERY_TOPIC,
+    EpochInfo, EpochParameters, EpochsStateQuery, NextEpochs, PreviousEpochs,
+    DEFAULT_HISTORICAL_EPOCHS_QUERY_TOPIC,
 };
+use acropolis_common::shutdown;
 use acropolis_common::{
     messages::{CardanoMessage, Message, StateQueryResponse},
+    metrics,
     queries::epochs::EpochsStateQueryResponse,
     queries::errors::QueryError,
+    readiness,
 };
 use anyhow::{bail, Result};
 use caryatid_sdk::{message_bus::Subscription, module, Context};
@@ -53,6 +57,7 @@ declare_cardano_reader!(
 
 const DEFAULT_HISTORICAL_EPOCHS_STATE_DB_PATH: (&str, &str) = ("db-path", "./fjall-epochs");
 const DEFAULT_CLEAR_ON_START: (&str, bool) = ("clear-on-start", true);
+const DEFAULT_RETENTION_EPOCHS: (&str, u64) = ("retention-epochs", 0);
 
 /// Historical Epochs State module
 #[module(
@@ -73,7 +78,14 @@ impl HistoricalEpochsState {
     ) -> Result<()> {
         if !is_snapshot_mode {
             match params_reader.read_with_rollbacks().await? {
-                RollbackWrapper::Normal(_) => {}
+                RollbackWrapper::Normal((block_info, params)) => {
+                    // Genesis parameters can't be rolled back
+                    let state = state_mutex.lock().await;
+                    state
+                        .immutable
+                        .update_immutable_params(block_info.epoch, params.params.clone())
+                        .await;
+                }
                 RollbackWrapper::Rollback(_) => {
                     bail!("Unexpected rollback while reading initial params");
                 }
@@ -107,11 +119,12 @@ impl HistoricalEpochsState {
             // synchronizes these readers on rollbacks and real transitions.
             if primary.should_read_epoch_transition_messages() {
                 match params_reader.read_with_rollbacks().await? {
-                    RollbackWrapper::Normal((_, params)) => {
+                    RollbackWrapper::Normal((block_info, params)) => {
                         let mut state = state_mutex.lock().await;
                         if let Some(shelley) = &params.params.shelley {
                             state.volatile.update_k(shelley.security_param);
                         }
+                        state.volatile.handle_new_params(&block_info, &params.params);
                     }
                     RollbackWrapper::Rollback(_) => {}
                 }
@@ -153,6 +166,8 @@ impl HistoricalEpochsState {
 
     /// Async initialisation
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "historical-epochs-state").await?;
+
         // Get configuration
         let is_snapshot_mode = StartupMode::from_config(config.as_ref()).is_snapshot();
 
@@ -165,6 +180,7 @@ impl HistoricalEpochsState {
         let cfg = HistoricalEpochsStateConfig {
             db_path: get_string_flag(&config, DEFAULT_HISTORICAL_EPOCHS_STATE_DB_PATH),
             clear_on_start: get_bool_flag(&config, DEFAULT_CLEAR_ON_START),
+            retention_epochs: get_u64_flag(&config, DEFAULT_RETENTION_EPOCHS),
         };
 
         // Initalize state
@@ -172,9 +188,22 @@ impl HistoricalEpochsState {
         let state_mutex = Arc::new(Mutex::new(state));
         let state_query = state_mutex.clone();
 
+        // Flush the store before the process exits
+        let store = state_mutex.lock().await.immutable.clone();
+        shutdown::on_shutdown(
+            context.clone(),
+            &config,
+            "historical-epochs-state",
+            move || async move { store.flush().await },
+        )
+        .await?;
+
+        let query_metrics = metrics::QueryMetrics::new("historical-epochs-state");
         context.handle(&historical_epochs_query_topic, move |message| {
             let state = state_query.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Epochs(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Epochs(
                         EpochsStateQueryResponse::Error(QueryError::internal_error(
@@ -201,29 +230,52 @@ impl HistoricalEpochsState {
                         }
                     }
 
-                    EpochsStateQuery::GetNextEpochs { epoch_number } => {
-                        match state.lock().await.get_next_epochs(*epoch_number) {
+                    EpochsStateQuery::GetNextEpochs {
+                        epoch_number,
+                        limit,
+                        skip,
+                    } => match state.lock().await.get_next_epochs(*epoch_number, *limit, *skip) {
+                        Ok(epochs) => EpochsStateQueryResponse::NextEpochs(NextEpochs { epochs }),
+                        Err(e) => {
+                            warn!("failed to get next epochs: {e}");
+                            EpochsStateQueryResponse::Error(QueryError::internal_error(
+                                "historical next epochs",
+                            ))
+                        }
+                    },
+
+                    EpochsStateQuery::GetPreviousEpochs {
+                        epoch_number,
+                        limit,
+                        skip,
+                    } => {
+                        match state.lock().await.get_previous_epochs(*epoch_number, *limit, *skip) {
                             Ok(epochs) => {
-                                EpochsStateQueryResponse::NextEpochs(NextEpochs { epochs })
+                                EpochsStateQueryResponse::PreviousEpochs(PreviousEpochs { epochs })
                             }
                             Err(e) => {
-                                warn!("failed to get next epochs: {e}");
+                                warn!("failed to get previous epochs: {e}");
                                 EpochsStateQueryResponse::Error(QueryError::internal_error(
-                                    "historical next epochs",
+                                    "historical previous epochs",
                                 ))
                             }
                         }
                     }
 
-                    EpochsStateQuery::GetPreviousEpochs { epoch_number } => {
-                        match state.lock().await.get_previous_epochs(*epoch_number) {
-                            Ok(epochs) => {
-                                EpochsStateQueryResponse::PreviousEpochs(PreviousEpochs { epochs })
+                    EpochsStateQuery::GetEpochParameters { epoch_number } => {
+                        match state.lock().await.get_epoch_params(*epoch_number) {
+                            Ok(Some(parameters)) => {
+                                EpochsStateQueryResponse::EpochParameters(EpochParameters {
+                                    parameters,
+                                })
                             }
+                            Ok(None) => EpochsStateQueryResponse::Error(QueryError::not_found(
+                                format!("Parameters for epoch {epoch_number}"),
+                            )),
                             Err(e) => {
-                                warn!("failed to get previous epochs: {e}");
+                                warn!("failed to get epoch parameters: {e}");
                                 EpochsStateQueryResponse::Error(QueryError::internal_error(
-                                    "historical previous epochs",
+                                    "historical epoch parameters",
                                 ))
                             }
                         }
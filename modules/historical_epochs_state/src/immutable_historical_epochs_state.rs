@@ -1,21 +1,33 @@
 use std::{collections::VecDeque, path::Path};
 
-use acropolis_common::messages::EpochActivityMessage;
-use anyhow::Result;
+use acropolis_common::{messages::EpochActivityMessage, protocol_params::ProtocolParams};
+use anyhow::{anyhow, Result};
 use fjall::{Database, Keyspace, KeyspaceCreateOptions, PersistMode};
 use minicbor::{decode, to_vec};
 use tokio::sync::Mutex;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 pub struct ImmutableHistoricalEpochsState {
     epochs_history: Keyspace,
+    epochs_params: Keyspace,
     database: Database,
     pub pending: Mutex<VecDeque<EpochActivityMessage>>,
+    pub pending_params: Mutex<VecDeque<(u64, ProtocolParams)>>,
     pub max_pending: usize,
+
+    /// Number of epochs to keep, or all of them if 0
+    pub retention_epochs: u64,
+
+    /// Held while an epoch is being persisted, so a flush waits for it
+    persisting: Mutex<()>,
 }
 
 impl ImmutableHistoricalEpochsState {
-    pub fn new(path: impl AsRef<Path>, clear_on_start: bool) -> Result<Self> {
+    pub fn new(
+        path: impl AsRef<Path>,
+        clear_on_start: bool,
+        retention_epochs: u64,
+    ) -> Result<Self> {
         let path = path.as_ref();
         if clear_on_start && path.exists() {
             std::fs::remove_dir_all(path)?;
@@ -28,15 +40,28 @@ impl ImmutableHistoricalEpochsState {
             .open()?;
 
         let epochs_history = database.keyspace("epochs_history", KeyspaceCreateOptions::default)?;
+        let epochs_params = database.keyspace("epochs_params", KeyspaceCreateOptions::default)?;
 
         Ok(Self {
             epochs_history,
+            epochs_params,
             database,
             pending: Mutex::new(VecDeque::new()),
+            pending_params: Mutex::new(VecDeque::new()),
             max_pending: 5,
+            retention_epochs,
+            persisting: Mutex::new(()),
         })
     }
 
+    /// The last epoch persisted by a previous run, if the store was kept
+    pub fn last_persisted_epoch(&self) -> Result<Option<u64>> {
+        let Some(entry) = self.epochs_history.iter().next_back() else {
+            return Ok(None);
+        };
+        Ok(Some(Self::parse_epoch_key(entry.key()?.as_ref())?))
+    }
+
     pub async fn update_immutable(&self, ea: EpochActivityMessage) {
         let mut pending = self.pending.lock().await;
         if pending.len() >= self.max_pending {
@@ -46,17 +71,32 @@ impl ImmutableHistoricalEpochsState {
         pending.push_back(ea);
     }
 
+    pub async fn update_immutable_params(&self, epoch: u64, params: ProtocolParams) {
+        let mut pending = self.pending_params.lock().await;
+        if pending.len() >= self.max_pending {
+            warn!("historical epochs state pending params buffer full, dropping oldest");
+            pending.pop_front();
+        }
+        pending.push_back((epoch, params));
+    }
+
     /// Persists pending EpochActivityMessages for Epoch N - 1
-    /// at the first block of Epoch N
+    /// and the protocol parameters for Epoch N at the first block of Epoch N
     /// There should be only one EpochActivityMessage for each epoch
+    /// Epochs older than the retention are removed in the same batch
     /// Returns the number of persisted EpochActivityMessages
     /// Errors if the batch commit or persist fails
     pub async fn persist_epoch(&self, epoch: u64) -> Result<u32> {
+        let _persisting = self.persisting.lock().await;
         let saving_epoch = epoch - 1;
         let drained_epochs = {
             let mut pending = self.pending.lock().await;
             std::mem::take(&mut *pending)
         };
+        let drained_params = {
+            let mut pending = self.pending_params.lock().await;
+            std::mem::take(&mut *pending)
+        };
 
         let mut batch = self.database.batch();
         let mut persisted_epochs: u32 = 0;
@@ -67,6 +107,21 @@ impl ImmutableHistoricalEpochsState {
             persisted_epochs += 1;
         }
 
+        for (params_epoch, params) in drained_params {
+            let epoch_key = Self::make_epoch_key(params_epoch);
+            batch.insert(&self.epochs_params, epoch_key, serde_cbor::to_vec(&params)?);
+        }
+
+        let first_retained = self.first_retained_epoch(saving_epoch);
+        if first_retained > 0 {
+            let end_key = Self::make_epoch_key(first_retained);
+            for keyspace in [&self.epochs_history, &self.epochs_params] {
+                for entry in keyspace.range(..end_key) {
+                    batch.remove(keyspace, entry.key()?);
+                }
+            }
+        }
+
         if let Err(e) = batch.commit() {
             error!("batch commit failed for epoch {saving_epoch}: {e}");
             return Err(e.into());
@@ -77,9 +132,29 @@ impl ImmutableHistoricalEpochsState {
             return Err(e.into());
         }
 
+        if first_retained > 0 {
+            info!("historical epochs retained from epoch {first_retained}");
+        }
+
         Ok(persisted_epochs)
     }
 
+    /// Wait for any epoch being persisted, then make everything written durable
+    pub async fn flush(&self) -> Result<()> {
+        let _persisting = self.persisting.lock().await;
+        self.database.persist(PersistMode::SyncAll)?;
+        Ok(())
+    }
+
+    /// First epoch kept once `last_epoch` has been persisted
+    pub fn first_retained_epoch(&self, last_epoch: u64) -> u64 {
+        if self.retention_epochs == 0 {
+            0
+        } else {
+            (last_epoch + 1).saturating_sub(self.retention_epochs)
+        }
+    }
+
     pub fn get_historical_epoch(&self, epoch: u64) -> Result<Option<EpochActivityMessage>> {
         let epoch_key = Self::make_epoch_key(epoch);
         let slice = self.epochs_history.get(epoch_key)?;
@@ -91,6 +166,14 @@ impl ImmutableHistoricalEpochsState {
         }
     }
 
+    pub fn get_epoch_params(&self, epoch: u64) -> Result<Option<ProtocolParams>> {
+        let epoch_key = Self::make_epoch_key(epoch);
+        match self.epochs_params.get(epoch_key)? {
+            Some(slice) => Ok(Some(serde_cbor::from_slice(&slice)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_epochs(
         &self,
         range: std::ops::RangeInclusive<u64>,
@@ -110,4 +193,9 @@ impl ImmutableHistoricalEpochsState {
     fn make_epoch_key(epoch: u64) -> [u8; 8] {
         epoch.to_be_bytes()
     }
+
+    fn parse_epoch_key(key: &[u8]) -> Result<u64> {
+        let key = <[u8; 8]>::try_from(key).map_err(|_| anyhow!("Invalid stored epoch key"))?;
+        Ok(u64::from_be_bytes(key))
+    }
 }
@@ -2,7 +2,9 @@ use crate::{
     immutable_historical_epochs_state::ImmutableHistoricalEpochsState,
     volatile_historical_epochs_state::VolatileHistoricalEpochsState,
 };
-use acropolis_common::{messages::EpochActivityMessage, BlockInfo};
+use acropolis_common::{
+    messages::EpochActivityMessage, protocol_params::ProtocolParams, BlockInfo,
+};
 use anyhow::Result;
 use std::{path::Path, sync::Arc};
 
@@ -10,6 +12,9 @@ use std::{path::Path, sync::Arc};
 pub struct HistoricalEpochsStateConfig {
     pub db_path: String,
     pub clear_on_start: bool,
+
+    /// Number of epochs to keep, or all of them if 0
+    pub retention_epochs: u64,
 }
 
 /// Overall state - stored per epoch
@@ -25,10 +30,15 @@ impl State {
         let immutable = Arc::new(ImmutableHistoricalEpochsState::new(
             db_path,
             config.clear_on_start,
+            config.retention_epochs,
         )?);
 
+        // Pick up from whatever a previous run persisted
+        let mut volatile = VolatileHistoricalEpochsState::new();
+        volatile.last_persisted_epoch = immutable.last_persisted_epoch()?;
+
         Ok(Self {
-            volatile: VolatileHistoricalEpochsState::new(),
+            volatile,
             immutable,
         })
     }
@@ -38,6 +48,16 @@ impl State {
         if let Some(ea) = drained {
             self.immutable.update_immutable(ea).await;
         }
+        if let Some((epoch, params)) = self.volatile.prune_volatile_params() {
+            self.immutable.update_immutable_params(epoch, params).await;
+        }
+    }
+
+    /// First epoch still held, once the retention has been applied
+    fn first_retained_epoch(&self) -> u64 {
+        self.volatile.last_persisted_epoch.map_or(0, |last_persisted_epoch| {
+            self.immutable.first_retained_epoch(last_persisted_epoch)
+        })
     }
 
     /// block_info is the first block of Epoch N (epoch param)
@@ -52,6 +72,9 @@ impl State {
     pub fn get_historical_epoch(&self, epoch: u64) -> Result<Option<EpochActivityMessage>> {
         if let Some(last_persisted_epoch) = self.volatile.last_persisted_epoch {
             if epoch <= last_persisted_epoch {
+                if epoch < self.first_retained_epoch() {
+                    return Ok(None);
+                }
                 return self.immutable.get_historical_epoch(epoch);
             }
         }
@@ -59,43 +82,60 @@ impl State {
         Ok(self.volatile.get_volatile_epoch(epoch))
     }
 
-    pub fn get_next_epochs(&self, epoch: u64) -> Result<Vec<EpochActivityMessage>> {
-        let mut epochs = vec![];
-        let immutable_epochs_range =
-            self.volatile.last_persisted_epoch.and_then(|last_persisted_epoch| {
-                if last_persisted_epoch > epoch {
-                    Some(epoch + 1..=last_persisted_epoch)
-                } else {
-                    None
-                }
-            });
+    pub fn get_epoch_params(&self, epoch: u64) -> Result<Option<ProtocolParams>> {
+        if let Some(params) = self.volatile.get_volatile_params(epoch) {
+            return Ok(Some(params));
+        }
+        if epoch < self.first_retained_epoch() {
+            return Ok(None);
+        }
+        self.immutable.get_epoch_params(epoch)
+    }
 
-        if let Some(immutable_epochs_range) = immutable_epochs_range {
-            epochs.extend(self.immutable.get_epochs(immutable_epochs_range)?);
+    /// Up to `limit` epochs after `epoch`, skipping the first `skip` of them
+    pub fn get_next_epochs(
+        &self,
+        epoch: u64,
+        limit: u64,
+        skip: u64,
+    ) -> Result<Vec<EpochActivityMessage>> {
+        if limit == 0 {
+            return Ok(vec![]);
         }
+        let start = epoch.saturating_add(1).saturating_add(skip);
+        self.get_epochs_range(start, start.saturating_add(limit - 1))
+    }
 
-        if let Some(volatile_ea) = self.volatile.volatile_ea.as_ref() {
-            if volatile_ea.epoch > epoch {
-                epochs.push(volatile_ea.clone());
-            }
+    /// Up to `limit` epochs before `epoch`, skipping the nearest `skip` of them,
+    /// still returned oldest first
+    pub fn get_previous_epochs(
+        &self,
+        epoch: u64,
+        limit: u64,
+        skip: u64,
+    ) -> Result<Vec<EpochActivityMessage>> {
+        if limit == 0 {
+            return Ok(vec![]);
         }
-        epochs.sort_by(|a, b| a.epoch.cmp(&b.epoch));
-        Ok(epochs)
+        let Some(end) = epoch.checked_sub(skip.saturating_add(1)) else {
+            return Ok(vec![]);
+        };
+        self.get_epochs_range(end.saturating_sub(limit - 1), end)
     }
 
-    pub fn get_previous_epochs(&self, epoch: u64) -> Result<Vec<EpochActivityMessage>> {
+    /// Epochs held in the inclusive range, from the store and the volatile epoch
+    fn get_epochs_range(&self, start: u64, end: u64) -> Result<Vec<EpochActivityMessage>> {
         let mut epochs = vec![];
-        let immutable_epochs_range = self
-            .volatile
-            .last_persisted_epoch
-            .map(|last_persisted_epoch| 0..=last_persisted_epoch.min(epoch - 1));
-
-        if let Some(immutable_epochs_range) = immutable_epochs_range {
-            epochs.extend(self.immutable.get_epochs(immutable_epochs_range)?);
+        if let Some(last_persisted_epoch) = self.volatile.last_persisted_epoch {
+            let start = start.max(self.first_retained_epoch());
+            let end = end.min(last_persisted_epoch);
+            if start <= end {
+                epochs.extend(self.immutable.get_epochs(start..=end)?);
+            }
         }
 
         if let Some(volatile_ea) = self.volatile.volatile_ea.as_ref() {
-            if volatile_ea.epoch < epoch {
+            if (start..=end).contains(&volatile_ea.epoch) {
                 epochs.push(volatile_ea.clone());
             }
         }
@@ -125,6 +165,7 @@ mod tests {
             total_fees: 10000,
             spo_blocks: vec![(PoolId::default(), 100)],
             nonce: None,
+            active_stake: None,
         }
     }
 
@@ -151,6 +192,7 @@ mod tests {
         let config = HistoricalEpochsStateConfig {
             db_path: temp_dir.path().to_string_lossy().into_owned(),
             clear_on_start: true,
+            retention_epochs: 0,
         };
         let mut state = State::new(&config).unwrap();
 
@@ -161,10 +203,10 @@ mod tests {
         let historical_epoch = state.get_historical_epoch(0).unwrap().unwrap();
         assert_eq!(historical_epoch, ea);
 
-        let next_epochs = state.get_next_epochs(0).unwrap();
+        let next_epochs = state.get_next_epochs(0, 100, 0).unwrap();
         assert_eq!(next_epochs, vec![]);
 
-        let previous_epochs = state.get_previous_epochs(1).unwrap();
+        let previous_epochs = state.get_previous_epochs(1, 100, 0).unwrap();
         assert_eq!(previous_epochs, vec![ea.clone()]);
     }
 
@@ -174,6 +216,7 @@ mod tests {
         let config = HistoricalEpochsStateConfig {
             db_path: temp_dir.path().to_string_lossy().into_owned(),
             clear_on_start: true,
+            retention_epochs: 0,
         };
         let mut state = State::new(&config).unwrap();
 
@@ -208,4 +251,57 @@ mod tests {
         let epochs = state.immutable.get_epochs(0..=1).unwrap();
         assert_eq!(epochs, vec![ea_0, ea_1]);
     }
+
+    #[tokio::test]
+    async fn test_retention_ranges_and_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = HistoricalEpochsStateConfig {
+            db_path: temp_dir.path().to_string_lossy().into_owned(),
+            clear_on_start: true,
+            retention_epochs: 3,
+        };
+        let mut state = State::new(&config).unwrap();
+
+        for epoch in 0..5 {
+            let block_info = make_block_info(epoch + 1, true);
+            state.volatile.handle_new_params(&block_info, &ProtocolParams::default());
+            state.volatile.handle_new_epoch(&block_info, &make_ea(epoch));
+            state.prune_volatile().await;
+            state.immutable.persist_epoch(epoch + 1).await.unwrap();
+        }
+
+        // Epochs 0 and 1 are past the retention
+        assert_eq!(state.get_historical_epoch(1).unwrap(), None);
+        assert_eq!(state.get_historical_epoch(2).unwrap(), Some(make_ea(2)));
+        assert_eq!(state.get_epoch_params(1).unwrap(), None);
+        assert_eq!(
+            state.get_epoch_params(2).unwrap(),
+            Some(ProtocolParams::default())
+        );
+
+        let epochs = |epochs: Vec<EpochActivityMessage>| {
+            epochs.into_iter().map(|ea| ea.epoch).collect::<Vec<_>>()
+        };
+        assert_eq!(epochs(state.get_next_epochs(0, 2, 1).unwrap()), vec![2, 3]);
+        assert_eq!(epochs(state.get_next_epochs(3, 100, 0).unwrap()), vec![4]);
+        assert_eq!(
+            epochs(state.get_previous_epochs(5, 2, 1).unwrap()),
+            vec![2, 3]
+        );
+        assert_eq!(
+            epochs(state.get_previous_epochs(0, 100, 0).unwrap()),
+            vec![]
+        );
+
+        // Reopening picks up where the store left off
+        drop(state);
+        config.clear_on_start = false;
+        let state = State::new(&config).unwrap();
+        assert_eq!(state.volatile.last_persisted_epoch, Some(4));
+        assert_eq!(state.get_historical_epoch(4).unwrap(), Some(make_ea(4)));
+        assert_eq!(
+            epochs(state.get_previous_epochs(5, 100, 0).unwrap()),
+            vec![2, 3, 4]
+        );
+    }
 }
@@ -1,4 +1,6 @@
-use acropolis_common::{messages::EpochActivityMessage, BlockInfo};
+use acropolis_common::{
+    messages::EpochActivityMessage, protocol_params::ProtocolParams, BlockInfo,
+};
 
 #[derive(Default, Debug, Clone)]
 pub struct VolatileHistoricalEpochsState {
@@ -6,6 +8,9 @@ pub struct VolatileHistoricalEpochsState {
 
     pub volatile_ea: Option<EpochActivityMessage>,
 
+    /// Protocol parameters of the epoch begun at `block_number`
+    pub volatile_params: Option<(u64, ProtocolParams)>,
+
     pub last_persisted_epoch: Option<u64>,
 
     pub security_param_k: u64,
@@ -16,6 +21,7 @@ impl VolatileHistoricalEpochsState {
         Self {
             block_number: 0,
             volatile_ea: None,
+            volatile_params: None,
             last_persisted_epoch: None,
             security_param_k: 0,
         }
@@ -23,6 +29,7 @@ impl VolatileHistoricalEpochsState {
 
     pub fn rollback_before(&mut self, rollbacked_block: u64) -> Option<EpochActivityMessage> {
         if self.block_number >= rollbacked_block {
+            self.volatile_params = None;
             std::mem::take(&mut self.volatile_ea)
         } else {
             None
@@ -34,6 +41,15 @@ impl VolatileHistoricalEpochsState {
         self.volatile_ea = Some(ea.clone());
     }
 
+    pub fn handle_new_params(&mut self, block_info: &BlockInfo, params: &ProtocolParams) {
+        self.block_number = block_info.number;
+        self.volatile_params = Some((block_info.epoch, params.clone()));
+    }
+
+    pub fn prune_volatile_params(&mut self) -> Option<(u64, ProtocolParams)> {
+        self.volatile_params.take()
+    }
+
     pub fn prune_volatile(&mut self) -> Option<EpochActivityMessage> {
         if let Some(ea) = self.volatile_ea.as_ref() {
             self.last_persisted_epoch = Some(ea.epoch);
@@ -55,6 +71,13 @@ impl VolatileHistoricalEpochsState {
         }
         None
     }
+
+    pub fn get_volatile_params(&self, epoch: u64) -> Option<ProtocolParams> {
+        match self.volatile_params.as_ref() {
+            Some((params_epoch, params)) if *params_epoch == epoch => Some(params.clone()),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +113,7 @@ mod tests {
             total_fees: 100,
             spo_blocks: vec![],
             nonce: None,
+            active_stake: None,
             first_block_time: 1,
             first_block_height: 1,
             last_block_time: 1,
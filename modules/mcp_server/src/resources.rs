@@ -63,42 +63,75 @@ async fn dispatch_handler(
     route: &RouteDefinition,
 ) -> Result<acropolis_common::messages::RESTResponse, RESTError> {
     use acropolis_module_rest_blockfrost::handlers::{
-        accounts::*, addresses::*, assets::*, blocks::*, epochs::*, governance::*, pools::*,
-        transactions::*,
+        accounts::*, addresses::*, assets::*, blocks::*, epochs::*, governance::*, health::*,
+        network::*, pools::*, scripts::*, transactions::*, utilities::*,
     };
 
     // Match on handler name and call the appropriate function
     match route.handler_name {
+        // Health
+        "handle_health_blockfrost" => {
+            handle_health_blockfrost(context, params, handlers_config).await
+        }
+        "handle_sync_blockfrost" => handle_sync_blockfrost(context, params, handlers_config).await,
+
+        // Network
+        "handle_network_blockfrost" => {
+            handle_network_blockfrost(context, params, handlers_config).await
+        }
+        "handle_network_eras_blockfrost" => {
+            handle_network_eras_blockfrost(context, params, handlers_config).await
+        }
+
+        // Utilities
+        "handle_address_inspect_blockfrost" => {
+            handle_address_inspect_blockfrost(context, params, handlers_config).await
+        }
+        "handle_pool_id_convert_blockfrost" => {
+            handle_pool_id_convert_blockfrost(context, params, handlers_config).await
+        }
+        "handle_drep_id_convert_blockfrost" => {
+            handle_drep_id_convert_blockfrost(context, params, handlers_config).await
+        }
+        "handle_stake_address_convert_blockfrost" => {
+            handle_stake_address_convert_blockfrost(context, params, query_params, handlers_config)
+                .await
+        }
+
         // Accounts
         "handle_single_account_blockfrost" => {
             handle_single_account_blockfrost(context, params, handlers_config).await
         }
         "handle_account_registrations_blockfrost" => {
-            handle_account_registrations_blockfrost(context, params, handlers_config).await
+            handle_account_registrations_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_account_delegations_blockfrost" => {
-            handle_account_delegations_blockfrost(context, params, handlers_config).await
+            handle_account_delegations_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_account_mirs_blockfrost" => {
-            handle_account_mirs_blockfrost(context, params, handlers_config).await
+            handle_account_mirs_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_account_withdrawals_blockfrost" => {
-            handle_account_withdrawals_blockfrost(context, params, handlers_config).await
+            handle_account_withdrawals_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_account_rewards_blockfrost" => {
-            handle_account_rewards_blockfrost(context, params, handlers_config).await
+            handle_account_rewards_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_account_addresses_blockfrost" => {
-            handle_account_addresses_blockfrost(context, params, handlers_config).await
+            handle_account_addresses_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_account_assets_blockfrost" => {
-            handle_account_assets_blockfrost(context, params, handlers_config).await
+            handle_account_assets_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_account_totals_blockfrost" => {
             handle_account_totals_blockfrost(context, params, handlers_config).await
         }
         "handle_account_utxos_blockfrost" => {
-            handle_account_utxos_blockfrost(context, params, handlers_config).await
+            handle_account_utxos_blockfrost(context, params, query_params, handlers_config).await
         }
 
         // Blocks
@@ -159,27 +192,27 @@ async fn dispatch_handler(
 
         // Governance - DReps
         "handle_dreps_list_blockfrost" => {
-            handle_dreps_list_blockfrost(context, params, handlers_config).await
+            handle_dreps_list_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_single_drep_blockfrost" => {
             handle_single_drep_blockfrost(context, params, handlers_config).await
         }
         "handle_drep_delegators_blockfrost" => {
-            handle_drep_delegators_blockfrost(context, params, handlers_config).await
+            handle_drep_delegators_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_drep_metadata_blockfrost" => {
             handle_drep_metadata_blockfrost(context, params, handlers_config).await
         }
         "handle_drep_updates_blockfrost" => {
-            handle_drep_updates_blockfrost(context, params, handlers_config).await
+            handle_drep_updates_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_drep_votes_blockfrost" => {
-            handle_drep_votes_blockfrost(context, params, handlers_config).await
+            handle_drep_votes_blockfrost(context, params, query_params, handlers_config).await
         }
 
         // Governance - Proposals
         "handle_proposals_list_blockfrost" => {
-            handle_proposals_list_blockfrost(context, params, handlers_config).await
+            handle_proposals_list_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_single_proposal_blockfrost" => {
             handle_single_proposal_blockfrost(context, params, handlers_config).await
@@ -191,7 +224,7 @@ async fn dispatch_handler(
             handle_proposal_withdrawals_blockfrost(context, params, handlers_config).await
         }
         "handle_proposal_votes_blockfrost" => {
-            handle_proposal_votes_blockfrost(context, params, handlers_config).await
+            handle_proposal_votes_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_proposal_metadata_blockfrost" => {
             handle_proposal_metadata_blockfrost(context, params, handlers_config).await
@@ -199,18 +232,19 @@ async fn dispatch_handler(
 
         // Pools
         "handle_pools_list_blockfrost" => {
-            handle_pools_list_blockfrost(context, params, handlers_config).await
+            handle_pools_list_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_pools_extended_retired_retiring_single_blockfrost" => {
             handle_pools_extended_retired_retiring_single_blockfrost(
                 context,
                 params,
+                query_params,
                 handlers_config,
             )
             .await
         }
         "handle_pool_history_blockfrost" => {
-            handle_pool_history_blockfrost(context, params, handlers_config).await
+            handle_pool_history_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_pool_metadata_blockfrost" => {
             handle_pool_metadata_blockfrost(context, params, handlers_config).await
@@ -219,16 +253,16 @@ async fn dispatch_handler(
             handle_pool_relays_blockfrost(context, params, handlers_config).await
         }
         "handle_pool_delegators_blockfrost" => {
-            handle_pool_delegators_blockfrost(context, params, handlers_config).await
+            handle_pool_delegators_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_pool_blocks_blockfrost" => {
-            handle_pool_blocks_blockfrost(context, params, handlers_config).await
+            handle_pool_blocks_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_pool_updates_blockfrost" => {
-            handle_pool_updates_blockfrost(context, params, handlers_config).await
+            handle_pool_updates_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_pool_votes_blockfrost" => {
-            handle_pool_votes_blockfrost(context, params, handlers_config).await
+            handle_pool_votes_blockfrost(context, params, query_params, handlers_config).await
         }
 
         // Epochs
@@ -239,10 +273,10 @@ async fn dispatch_handler(
             handle_epoch_params_blockfrost(context, params, handlers_config).await
         }
         "handle_epoch_next_blockfrost" => {
-            handle_epoch_next_blockfrost(context, params, handlers_config).await
+            handle_epoch_next_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_epoch_previous_blockfrost" => {
-            handle_epoch_previous_blockfrost(context, params, handlers_config).await
+            handle_epoch_previous_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_epoch_total_stakes_blockfrost" => {
             handle_epoch_total_stakes_blockfrost(context, params, handlers_config).await
@@ -251,30 +285,59 @@ async fn dispatch_handler(
             handle_epoch_pool_stakes_blockfrost(context, params, handlers_config).await
         }
         "handle_epoch_total_blocks_blockfrost" => {
-            handle_epoch_total_blocks_blockfrost(context, params, handlers_config).await
+            handle_epoch_total_blocks_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_epoch_pool_blocks_blockfrost" => {
-            handle_epoch_pool_blocks_blockfrost(context, params, handlers_config).await
+            handle_epoch_pool_blocks_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
 
         // Assets
         "handle_assets_list_blockfrost" => {
-            handle_assets_list_blockfrost(context, params, handlers_config).await
+            handle_assets_list_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_asset_single_blockfrost" => {
             handle_asset_single_blockfrost(context, params, handlers_config).await
         }
         "handle_asset_history_blockfrost" => {
-            handle_asset_history_blockfrost(context, params, handlers_config).await
+            handle_asset_history_blockfrost(context, params, query_params, handlers_config).await
+        }
+        "handle_asset_supply_blockfrost" => {
+            handle_asset_supply_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_asset_transactions_blockfrost" => {
-            handle_asset_transactions_blockfrost(context, params, handlers_config).await
+            handle_asset_transactions_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_asset_addresses_blockfrost" => {
-            handle_asset_addresses_blockfrost(context, params, handlers_config).await
+            handle_asset_addresses_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_policy_assets_blockfrost" => {
-            handle_policy_assets_blockfrost(context, params, handlers_config).await
+            handle_policy_assets_blockfrost(context, params, query_params, handlers_config).await
+        }
+
+        // Scripts
+        "handle_scripts_list_blockfrost" => {
+            handle_scripts_list_blockfrost(context, params, query_params, handlers_config).await
+        }
+        "handle_script_single_blockfrost" => {
+            handle_script_single_blockfrost(context, params, handlers_config).await
+        }
+        "handle_script_json_blockfrost" => {
+            handle_script_json_blockfrost(context, params, handlers_config).await
+        }
+        "handle_script_cbor_blockfrost" => {
+            handle_script_cbor_blockfrost(context, params, handlers_config).await
+        }
+        "handle_script_redeemers_blockfrost" => {
+            handle_script_redeemers_blockfrost(context, params, query_params, handlers_config).await
+        }
+        "handle_script_datum_blockfrost" => {
+            handle_script_datum_blockfrost(context, params, handlers_config).await
+        }
+        "handle_script_datum_cbor_blockfrost" => {
+            handle_script_datum_cbor_blockfrost(context, params, handlers_config).await
         }
 
         // Addresses
@@ -288,13 +351,15 @@ async fn dispatch_handler(
             handle_address_totals_blockfrost(context, params, handlers_config).await
         }
         "handle_address_utxos_blockfrost" => {
-            handle_address_utxos_blockfrost(context, params, handlers_config).await
+            handle_address_utxos_blockfrost(context, params, query_params, handlers_config).await
         }
         "handle_address_asset_utxos_blockfrost" => {
-            handle_address_asset_utxos_blockfrost(context, params, handlers_config).await
+            handle_address_asset_utxos_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
         "handle_address_transactions_blockfrost" => {
-            handle_address_transactions_blockfrost(context, params, handlers_config).await
+            handle_address_transactions_blockfrost(context, params, query_params, handlers_config)
+                .await
         }
 
         // Transactions
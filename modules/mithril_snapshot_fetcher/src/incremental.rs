@@ -0,0 +1,175 @@
+//! Incremental (Cardano database v2) snapshot support
+//! Works out which immutable chunks are already present locally so only
+//! the missing ones are fetched from Mithril
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use acropolis_common::configuration::conf_enum;
+use anyhow::Result;
+use config::Config;
+use mithril_client::cardano_database_client::ImmutableFileRange;
+use serde::Deserialize;
+
+const DEFAULT_SNAPSHOT_TYPE: (&str, SnapshotType) = ("snapshot-type", SnapshotType::Full);
+
+/// Extensions making up a complete immutable chunk
+const IMMUTABLE_FILE_EXTENSIONS: [&str; 3] = ["chunk", "primary", "secondary"];
+
+/// Which Mithril artifact to bootstrap from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotType {
+    /// Legacy full archive, downloaded in one go
+    Full,
+    /// Cardano database v2, only the missing immutable files are downloaded
+    Incremental,
+}
+
+impl SnapshotType {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        conf_enum(config, DEFAULT_SNAPSHOT_TYPE)
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        matches!(self, SnapshotType::Incremental)
+    }
+}
+
+/// Find the last immutable file number for which every file from 0 onwards is
+/// complete (chunk, primary and secondary all present).
+/// Returns None if immutable file 0 is not complete, since the run must start at
+/// the beginning of the chain for the rest to be usable.
+pub fn last_complete_immutable(immutable_dir: &Path) -> Result<Option<u64>> {
+    if !immutable_dir.exists() {
+        return Ok(None);
+    }
+
+    let mut seen: [BTreeSet<u64>; 3] = Default::default();
+    for entry in fs::read_dir(immutable_dir)? {
+        let path = entry?.path();
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) else {
+            continue;
+        };
+        let Ok(number) = stem.parse::<u64>() else {
+            continue;
+        };
+        if let Some(index) = IMMUTABLE_FILE_EXTENSIONS.iter().position(|e| *e == extension) {
+            seen[index].insert(number);
+        }
+    }
+
+    // Walk up from 0, stopping at the first gap or partial trio
+    let [chunks, primaries, secondaries] = seen;
+    let mut last = None;
+    let mut expected = 0;
+    for number in chunks {
+        if number != expected || !primaries.contains(&number) || !secondaries.contains(&number) {
+            break;
+        }
+        last = Some(number);
+        expected = number + 1;
+    }
+
+    Ok(last)
+}
+
+/// Range of immutable files to download to bring the local database up to
+/// `snapshot_last`, or None if everything is already present
+pub fn missing_immutable_range(
+    local_last: Option<u64>,
+    snapshot_last: u64,
+) -> Option<ImmutableFileRange> {
+    match local_last {
+        None => Some(ImmutableFileRange::Full),
+        Some(local_last) if local_last >= snapshot_last => None,
+        Some(local_last) => Some(ImmutableFileRange::From(local_last + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    fn make_immutable_dir(name: &str, files: &[(u64, &[&str])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mithril_incremental_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (number, extensions) in files {
+            for extension in *extensions {
+                File::create(dir.join(format!("{number:05}.{extension}"))).unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn missing_directory_has_no_immutables() {
+        let dir = std::env::temp_dir().join("mithril_incremental_does_not_exist");
+        assert_eq!(last_complete_immutable(&dir).unwrap(), None);
+    }
+
+    #[test]
+    fn finds_last_complete_immutable() {
+        let all = &["chunk", "primary", "secondary"][..];
+        let dir = make_immutable_dir("complete", &[(0, all), (1, all), (2, all)]);
+        assert_eq!(last_complete_immutable(&dir).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn stops_at_partial_immutable() {
+        let all = &["chunk", "primary", "secondary"][..];
+        let dir = make_immutable_dir(
+            "partial",
+            &[(0, all), (1, all), (2, &["chunk", "primary"]), (3, all)],
+        );
+        assert_eq!(last_complete_immutable(&dir).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn stops_at_gap_in_immutables() {
+        let all = &["chunk", "primary", "secondary"][..];
+        let dir = make_immutable_dir("gap", &[(0, all), (1, all), (3, all)]);
+        assert_eq!(last_complete_immutable(&dir).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn ignores_immutables_not_starting_at_zero() {
+        let all = &["chunk", "primary", "secondary"][..];
+        let files: Vec<_> = (5..=10).map(|number| (number, all)).collect();
+        let dir = make_immutable_dir("leading_gap", &files);
+        assert_eq!(last_complete_immutable(&dir).unwrap(), None);
+        assert_eq!(
+            missing_immutable_range(None, 10),
+            Some(ImmutableFileRange::Full)
+        );
+    }
+
+    #[test]
+    fn full_range_when_nothing_local() {
+        assert_eq!(
+            missing_immutable_range(None, 100),
+            Some(ImmutableFileRange::Full)
+        );
+    }
+
+    #[test]
+    fn partial_range_when_behind() {
+        assert_eq!(
+            missing_immutable_range(Some(90), 100),
+            Some(ImmutableFileRange::From(91))
+        );
+    }
+
+    #[test]
+    fn no_range_when_up_to_date() {
+        assert_eq!(missing_immutable_range(Some(100), 100), None);
+        assert_eq!(missing_immutable_range(Some(101), 100), None);
+    }
+}
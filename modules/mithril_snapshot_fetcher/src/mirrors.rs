@@ -0,0 +1,285 @@
+//! Aggregator mirror selection
+//! Probes each configured aggregator endpoint for health and throughput so
+//! the fetcher can prefer the fastest one and fail over to the others
+
+use std::time::{Duration, Instant};
+
+use acropolis_common::configuration::get_u64_flag;
+use anyhow::{anyhow, Result};
+use config::Config;
+use tracing::{info, warn};
+
+use crate::MithrilSnapshotFetcher;
+
+const DEFAULT_AGGREGATOR_URLS: &str = "aggregator-urls";
+const DEFAULT_PROBE_TIMEOUT: (&str, u64) = ("probe-timeout-secs", 5);
+const DEFAULT_BANDWIDTH_TEST_BYTES: (&str, u64) = ("bandwidth-test-bytes", 256 * 1024);
+
+/// Snapshot list, whose entries give the CDN locations of the archives
+const SNAPSHOT_LIST_PATH: &str = "artifact/snapshots";
+
+/// Result of probing a single aggregator
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeResult {
+    /// Aggregator URL
+    pub url: String,
+
+    /// Whether the aggregator answered its health check
+    pub healthy: bool,
+
+    /// Round trip time of the health check
+    pub latency: Duration,
+
+    /// Measured throughput in bytes per second
+    pub bytes_per_second: f64,
+}
+
+/// Configured set of aggregator mirrors
+pub struct Mirrors {
+    urls: Vec<String>,
+    probe_timeout: Duration,
+    bandwidth_test_bytes: u64,
+}
+
+impl Mirrors {
+    /// Read mirrors from `aggregator-urls`, falling back to the single
+    /// `aggregator-url` (or the network default) if no list is given
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut urls = config.get::<Vec<String>>(DEFAULT_AGGREGATOR_URLS).unwrap_or_default();
+        if urls.is_empty() {
+            urls.push(MithrilSnapshotFetcher::aggregator_url(config)?);
+        }
+
+        Ok(Self {
+            urls,
+            probe_timeout: Duration::from_secs(get_u64_flag(config, DEFAULT_PROBE_TIMEOUT)),
+            bandwidth_test_bytes: get_u64_flag(config, DEFAULT_BANDWIDTH_TEST_BYTES),
+        })
+    }
+
+    /// Probe all mirrors and return the healthy ones, fastest first.  With a
+    /// single mirror configured there is nothing to choose so probing is skipped.
+    pub async fn select(&self) -> Result<Vec<String>> {
+        if self.urls.len() == 1 {
+            return Ok(self.urls.clone());
+        }
+
+        let client = reqwest::Client::builder().timeout(self.probe_timeout).build()?;
+        let probes = self.urls.iter().map(|url| self.probe(&client, url));
+        let results = futures::future::join_all(probes).await;
+
+        for result in &results {
+            if result.healthy {
+                info!(
+                    url = result.url,
+                    latency_ms = result.latency.as_millis() as u64,
+                    kbps = (result.bytes_per_second / 1024.0) as u64,
+                    "Mithril aggregator probe"
+                );
+            } else {
+                warn!(url = result.url, "Mithril aggregator is unavailable");
+            }
+        }
+
+        let ranked = rank_mirrors(results);
+        if ranked.is_empty() {
+            return Err(anyhow!("No Mithril aggregator is reachable"));
+        }
+        Ok(ranked)
+    }
+
+    /// Health check followed by a bandwidth test - a ranged GET of the first
+    /// `bandwidth-test-bytes` of the latest snapshot archive the mirror lists, so
+    /// the throughput is that of the CDN the download will actually come from
+    async fn probe(&self, client: &reqwest::Client, url: &str) -> ProbeResult {
+        let mut result = ProbeResult {
+            url: url.to_string(),
+            healthy: false,
+            latency: self.probe_timeout,
+            bytes_per_second: 0.0,
+        };
+
+        let start = Instant::now();
+        match client.get(url).send().await {
+            Ok(response) if response.status().is_success() => {
+                result.healthy = true;
+                result.latency = start.elapsed();
+            }
+            _ => return result,
+        }
+
+        let list_url = format!("{}/{SNAPSHOT_LIST_PATH}", url.trim_end_matches('/'));
+        let list = match client.get(&list_url).send().await {
+            Ok(response) => response
+                .bytes()
+                .await
+                .ok()
+                .and_then(|body| serde_json::from_slice::<serde_json::Value>(&body).ok()),
+            Err(_) => None,
+        };
+        let Some(archive_url) = list.as_ref().and_then(latest_archive_location) else {
+            warn!(
+                url,
+                "Mithril aggregator lists no snapshot archive to test bandwidth"
+            );
+            return result;
+        };
+
+        let range = format!("bytes=0-{}", self.bandwidth_test_bytes.saturating_sub(1));
+        let Ok(mut response) =
+            client.get(&archive_url).header(reqwest::header::RANGE, range).send().await
+        else {
+            return result;
+        };
+        if !response.status().is_success() {
+            return result;
+        }
+
+        // Timed from the response headers, so the figure is transfer rate rather
+        // than round trip time
+        let start = Instant::now();
+        let mut received: u64 = 0;
+        while received < self.bandwidth_test_bytes {
+            match response.chunk().await {
+                Ok(Some(chunk)) => received += chunk.len() as u64,
+                _ => break,
+            }
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            result.bytes_per_second = received as f64 / elapsed;
+        }
+        result
+    }
+}
+
+/// First download location of the newest snapshot in an aggregator's snapshot
+/// list
+pub fn latest_archive_location(list: &serde_json::Value) -> Option<String> {
+    list.as_array()?
+        .first()?
+        .get("locations")?
+        .as_array()?
+        .iter()
+        .find_map(|location| location.as_str())
+        .map(str::to_string)
+}
+
+/// Order healthy mirrors by throughput, then by latency
+pub fn rank_mirrors(mut results: Vec<ProbeResult>) -> Vec<String> {
+    results.retain(|r| r.healthy);
+    results.sort_by(|a, b| {
+        b.bytes_per_second.total_cmp(&a.bytes_per_second).then_with(|| a.latency.cmp(&b.latency))
+    });
+    results.into_iter().map(|r| r.url).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(url: &str, healthy: bool, latency_ms: u64, bytes_per_second: f64) -> ProbeResult {
+        ProbeResult {
+            url: url.to_string(),
+            healthy,
+            latency: Duration::from_millis(latency_ms),
+            bytes_per_second,
+        }
+    }
+
+    #[test]
+    fn single_url_from_config() {
+        let config = Config::builder()
+            .set_override("aggregator-url", "https://one.example")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mirrors = Mirrors::from_config(&config).unwrap();
+        assert_eq!(mirrors.urls, vec!["https://one.example".to_string()]);
+    }
+
+    #[test]
+    fn url_defaults_to_network_aggregator() {
+        let config =
+            Config::builder().set_override("startup.network", "preprod").unwrap().build().unwrap();
+        let mirrors = Mirrors::from_config(&config).unwrap();
+        assert_eq!(
+            mirrors.urls,
+            vec!["https://aggregator.release-preprod.api.mithril.network/aggregator".to_string()]
+        );
+    }
+
+    #[test]
+    fn custom_network_requires_url() {
+        let config =
+            Config::builder().set_override("startup.network", "custom").unwrap().build().unwrap();
+        assert!(Mirrors::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn url_list_from_config_takes_precedence() {
+        let config = Config::builder()
+            .set_override("aggregator-url", "https://one.example")
+            .unwrap()
+            .set_override(
+                "aggregator-urls",
+                vec!["https://two.example", "https://three.example"],
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let mirrors = Mirrors::from_config(&config).unwrap();
+        assert_eq!(
+            mirrors.urls,
+            vec![
+                "https://two.example".to_string(),
+                "https://three.example".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn ranks_fastest_first_and_drops_unhealthy() {
+        let ranked = rank_mirrors(vec![
+            probe("slow", true, 10, 1000.0),
+            probe("down", false, 0, 0.0),
+            probe("fast", true, 50, 5000.0),
+        ]);
+        assert_eq!(ranked, vec!["fast".to_string(), "slow".to_string()]);
+    }
+
+    #[test]
+    fn equal_throughput_prefers_lower_latency() {
+        let ranked = rank_mirrors(vec![
+            probe("far", true, 200, 1000.0),
+            probe("near", true, 20, 1000.0),
+        ]);
+        assert_eq!(ranked, vec!["near".to_string(), "far".to_string()]);
+    }
+
+    #[test]
+    fn bandwidth_test_uses_latest_archive() {
+        let list = serde_json::json!([
+            {
+                "digest": "new",
+                "locations": ["https://cdn.example/new.tar.zst", "https://mirror.example/new.tar.zst"]
+            },
+            { "digest": "old", "locations": ["https://cdn.example/old.tar.zst"] }
+        ]);
+        assert_eq!(
+            latest_archive_location(&list),
+            Some("https://cdn.example/new.tar.zst".to_string())
+        );
+        assert_eq!(latest_archive_location(&serde_json::json!([])), None);
+        assert_eq!(
+            latest_archive_location(&serde_json::json!([{ "locations": [] }])),
+            None
+        );
+    }
+
+    #[test]
+    fn no_healthy_mirrors() {
+        assert!(rank_mirrors(vec![probe("down", false, 0, 0.0)]).is_empty());
+    }
+}
@@ -5,7 +5,7 @@ use acropolis_codec::map_to_block_era;
 use acropolis_common::{
     commands::chain_sync::ChainSyncCommand,
     configuration::{get_string_flag, StartupMode, SyncMode},
-    genesis_values::GenesisValues,
+    genesis_values::{GenesisValues, Network},
     messages::{CardanoMessage, Command, Message, RawBlockMessage},
     BlockHash, BlockInfo, BlockIntent, BlockStatus, Era, Point,
 };
@@ -14,8 +14,9 @@ use caryatid_sdk::{module, Context, Subscription};
 use chrono::{Duration, Utc};
 use config::Config;
 use mithril_client::{
+    cardano_database_client::DownloadUnpackOptions,
     feedback::{FeedbackReceiver, MithrilEvent},
-    ClientBuilder, MessageBuilder, Snapshot,
+    CardanoDatabaseSnapshot, Client, ClientBuilder, MessageBuilder, Snapshot,
 };
 use pallas::storage::hardano;
 use pallas_traverse::MultiEraBlock;
@@ -26,8 +27,12 @@ use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration as SystemDuration;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info, info_span, Instrument};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
+mod incremental;
+use incremental::SnapshotType;
+mod mirrors;
+use mirrors::Mirrors;
 mod pause;
 use pause::PauseType;
 
@@ -39,26 +44,16 @@ const DEFAULT_BLOCK_PUBLISH_TOPIC: (&str, &str) =
     ("block-publish-topic", "cardano.block.available");
 const DEFAULT_SYNC_COMMAND_TOPIC: (&str, &str) = ("completion-topic", "cardano.sync.command");
 
-const DEFAULT_AGGREGATOR_URL: (&str, &str) = (
-    "aggregator-url",
-    "https://aggregator.release-mainnet.api.mithril.network/aggregator",
-);
-const DEFAULT_GENESIS_KEY: (&str, &str) = (
-    "genesis-key",
-    r#"
-5b3139312c36362c3134302c3138352c3133382c31312c3233372c3230372c3235302c3134342c32
-372c322c3138382c33302c31322c38312c3135352c3230342c31302c3137392c37352c32332c3133
-382c3139362c3231372c352c31342c32302c35372c37392c33392c3137365d"#,
-);
+const DEFAULT_AGGREGATOR_URL: &str = "aggregator-url";
+const DEFAULT_GENESIS_KEY: &str = "genesis-key";
 const DEFAULT_PAUSE: (&str, PauseType) = ("pause", PauseType::NoPause);
 const DEFAULT_STOP: (&str, PauseType) = ("stop", PauseType::NoPause);
 #[cfg(not(target_env = "msvc"))]
 const DEFAULT_PROFILE: (&str, PauseType) = ("profile", PauseType::NoPause);
 const DEFAULT_DOWNLOAD_MAX_AGE: &str = "download-max-age";
 const DEFAULT_DIRECTORY: &str = "../../modules/mithril_snapshot_fetcher/downloads";
-// TODO: Read network name from genesis message
-const DEFAULT_NETWORK_NAME: (&str, &str) = ("startup.network-name", "mainnet");
 const SNAPSHOT_METADATA_FILE: &str = "snapshot_metadata.json";
+const CARDANO_DATABASE_METADATA_FILE: &str = "cardano_database_metadata.json";
 
 /// Mithril feedback receiver
 struct FeedbackLogger {
@@ -131,11 +126,14 @@ impl MithrilSnapshotFetcher {
     /// Uses `../../modules/mithril_snapshot_fetcher/downloads/{network-name}` by default
     /// (e.g. `.../downloads/mainnet`, `.../downloads/preview`).
     /// Can be overridden with the `directory` config key.
-    fn resolve_directory(config: &Config) -> String {
-        config.get_string("directory").unwrap_or_else(|_| {
-            let network = get_string_flag(config, DEFAULT_NETWORK_NAME);
-            format!("{DEFAULT_DIRECTORY}/{network}")
-        })
+    fn resolve_directory(config: &Config) -> Result<String> {
+        match config.get_string("directory") {
+            Ok(directory) => Ok(directory),
+            Err(_) => Ok(format!(
+                "{DEFAULT_DIRECTORY}/{}",
+                Network::from_config(config)?
+            )),
+        }
     }
 
     fn load_snapshot_metadata(path: &Path) -> Result<Snapshot> {
@@ -153,6 +151,15 @@ impl MithrilSnapshotFetcher {
         Ok(())
     }
 
+    fn save_cardano_database_metadata(
+        snapshot: &CardanoDatabaseSnapshot,
+        path: &Path,
+    ) -> Result<()> {
+        let stringified_snapshot = serde_json::to_string_pretty(snapshot)?;
+        fs::write(path, stringified_snapshot)?;
+        Ok(())
+    }
+
     fn should_skip_download(
         old_snapshot_metadata: &Snapshot,
         latest_snapshot_metadata: &Snapshot,
@@ -195,17 +202,72 @@ impl MithrilSnapshotFetcher {
         }
     }
 
-    /// Fetch and unpack a snapshot
-    async fn download_snapshot(config: Arc<Config>) -> Result<()> {
-        let aggregator_url = get_string_flag(&config, DEFAULT_AGGREGATOR_URL);
-        let genesis_key = get_string_flag(&config, DEFAULT_GENESIS_KEY);
-        let directory = Self::resolve_directory(&config);
-        let snapshot_metadata_path = Path::new(&directory).join(SNAPSHOT_METADATA_FILE);
+    /// Aggregator URL from config, or the default for the configured network
+    fn aggregator_url(config: &Config) -> Result<String> {
+        config.get_string(DEFAULT_AGGREGATOR_URL).or_else(|_| {
+            let network = Network::from_config(config)?;
+            network
+                .mithril_aggregator_url()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("No default Mithril aggregator for network '{network}'"))
+        })
+    }
+
+    /// Genesis verification key from config, or the default for the configured network
+    fn genesis_key(config: &Config) -> Result<String> {
+        config.get_string(DEFAULT_GENESIS_KEY).or_else(|_| {
+            let network = Network::from_config(config)?;
+            network
+                .mithril_genesis_key()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("No default Mithril genesis key for network '{network}'"))
+        })
+    }
+
+    /// Build a Mithril client for the given aggregator
+    fn build_client(config: &Config, aggregator_url: &str) -> Result<Client> {
+        let genesis_key = Self::genesis_key(config)?;
 
         let feedback_logger = Arc::new(FeedbackLogger::new());
-        let client = ClientBuilder::aggregator(&aggregator_url, &genesis_key)
+        let client = ClientBuilder::aggregator(aggregator_url, &genesis_key)
             .add_feedback_receiver(feedback_logger)
             .build()?;
+        Ok(client)
+    }
+
+    /// Fetch and unpack a snapshot, trying each healthy aggregator mirror in
+    /// order of measured speed until one succeeds
+    async fn download_snapshot(config: Arc<Config>) -> Result<()> {
+        let aggregator_urls = Mirrors::from_config(&config)?.select().await?;
+        let snapshot_type = SnapshotType::from_config(&config)?;
+
+        let mut last_error = None;
+        for aggregator_url in aggregator_urls {
+            info!("Fetching Mithril snapshot from {aggregator_url}");
+            let result = match Self::build_client(&config, &aggregator_url) {
+                Ok(client) if snapshot_type.is_incremental() => {
+                    Self::download_incremental_snapshot(&client, &config).await
+                }
+                Ok(client) => Self::download_full_snapshot(&client, &config).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("Mithril aggregator {aggregator_url} failed: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No Mithril aggregators configured")))
+    }
+
+    /// Fetch and unpack the full legacy snapshot archive
+    async fn download_full_snapshot(client: &Client, config: &Config) -> Result<()> {
+        let directory = Self::resolve_directory(config)?;
+        let snapshot_metadata_path = Path::new(&directory).join(SNAPSHOT_METADATA_FILE);
 
         // Find the latest snapshot
         let snapshots = client.cardano_database().list().await?;
@@ -219,7 +281,7 @@ impl MithrilSnapshotFetcher {
         // Check if the snapshot is expired by download max age
         let old_snapshot = Self::load_snapshot_metadata(&snapshot_metadata_path);
         if let Ok(old_snapshot) = old_snapshot {
-            if Self::should_skip_download(&old_snapshot, &snapshot, &config) {
+            if Self::should_skip_download(&old_snapshot, &snapshot, config) {
                 info!("Using old Mithril snapshot {old_snapshot:?}");
                 return Ok(());
             }
@@ -255,6 +317,78 @@ impl MithrilSnapshotFetcher {
         Ok(())
     }
 
+    /// Fetch only the immutable files missing from the local database, using the
+    /// Cardano database v2 artifacts
+    async fn download_incremental_snapshot(client: &Client, config: &Config) -> Result<()> {
+        let directory = Self::resolve_directory(config)?;
+        let dir = Path::new(&directory);
+
+        // Find the latest snapshot
+        let snapshots = client.cardano_database_v2().list().await?;
+        let latest_snapshot = snapshots.first().ok_or(anyhow!("No snapshots available"))?;
+        let snapshot = client
+            .cardano_database_v2()
+            .get(&latest_snapshot.hash)
+            .await?
+            .ok_or(anyhow!("No snapshot for hash {}", latest_snapshot.hash))?;
+
+        // Work out what we already have
+        let snapshot_last = snapshot.beacon.immutable_file_number;
+        let local_last = incremental::last_complete_immutable(&dir.join("immutable"))?;
+        let Some(range) = incremental::missing_immutable_range(local_last, snapshot_last) else {
+            info!("SKIP DOWNLOAD: All immutable files up to {snapshot_last} are already present");
+            return Ok(());
+        };
+
+        info!(
+            "Using Mithril Cardano database snapshot {} - fetching immutable files {range:?} up to {snapshot_last}",
+            snapshot.hash
+        );
+
+        // Verify the certificate chain
+        let certificate = client.certificate().verify_chain(&snapshot.certificate_hash).await?;
+
+        // Download only the missing immutable files, leaving existing ones in place
+        fs::create_dir_all(dir)?;
+        let options = DownloadUnpackOptions {
+            allow_override: true,
+            include_ancillary: false,
+            ..DownloadUnpackOptions::default()
+        };
+        client.cardano_database_v2().download_unpack(&snapshot, &range, dir, options).await?;
+
+        // Register download
+        let restored = range.length(snapshot_last).unwrap_or_default();
+        if let Err(e) =
+            client.cardano_database_v2().add_statistics(local_last.is_none(), false, restored).await
+        {
+            error!("Could not increment snapshot download statistics: {:?}", e);
+        }
+
+        // Verify the downloaded immutable files against the certified Merkle root
+        let merkle_proof = client
+            .cardano_database_v2()
+            .compute_merkle_proof(&certificate, &snapshot, &range, dir)
+            .await?;
+        merkle_proof.verify()?;
+        let message =
+            MessageBuilder::new().compute_cardano_database_message(&certificate, &merkle_proof)?;
+
+        if !certificate.match_message(&message) {
+            return Err(anyhow!("Snapshot verification failed"));
+        }
+
+        // Save snapshot metadata as JSON
+        if let Err(e) = Self::save_cardano_database_metadata(
+            &snapshot,
+            &dir.join(CARDANO_DATABASE_METADATA_FILE),
+        ) {
+            error!("Failed to save snapshot metadata: {e}");
+        }
+
+        Ok(())
+    }
+
     /// Process the snapshot
     async fn process_snapshot(
         context: Arc<Context<Message>>,
@@ -272,7 +406,7 @@ impl MithrilSnapshotFetcher {
             .unwrap_or(DEFAULT_SYNC_COMMAND_TOPIC.1.to_string());
         info!("Publishing completion on '{sync_command_topic}'");
 
-        let directory = Self::resolve_directory(&config);
+        let directory = Self::resolve_directory(&config)?;
         let mut pause_constraint =
             PauseType::from_config(&config, DEFAULT_PAUSE).unwrap_or(PauseType::NoPause);
         let stop_constraint =
@@ -0,0 +1,280 @@
+//! Recent chain kept in memory to serve local chain sync clients
+
+use std::{collections::VecDeque, sync::Arc};
+
+use acropolis_common::{BlockHash, Era};
+use pallas::network::miniprotocols::{chainsync::Tip, Point};
+
+/// A block as served over chain sync
+#[derive(Debug, Clone)]
+pub struct StoredBlock {
+    pub slot: u64,
+    pub number: u64,
+    pub hash: BlockHash,
+    pub epoch: u64,
+    pub era: Era,
+
+    /// Era-tagged block CBOR, as received from upstream
+    pub body: Arc<Vec<u8>>,
+}
+
+impl StoredBlock {
+    pub fn point(&self) -> Point {
+        Point::Specific(self.slot, self.hash.to_vec())
+    }
+}
+
+/// The last `capacity` blocks of the current chain
+pub struct VolatileChain {
+    blocks: VecDeque<StoredBlock>,
+    capacity: usize,
+}
+
+impl VolatileChain {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a block to the tip. A block whose number is not above the current tip
+    /// replaces it and everything after it - i.e. the chain switched fork.
+    pub fn roll_forward(&mut self, block: StoredBlock) {
+        while self.blocks.back().is_some_and(|b| b.number >= block.number) {
+            self.blocks.pop_back();
+        }
+        self.blocks.push_back(block);
+        while self.blocks.len() > self.capacity {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Remove all blocks after `slot`
+    pub fn roll_back_to_slot(&mut self, slot: u64) {
+        while self.blocks.back().is_some_and(|b| b.slot > slot) {
+            self.blocks.pop_back();
+        }
+    }
+
+    /// Remove all blocks, after a rollback to Origin
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    pub fn tip(&self) -> Tip {
+        match self.blocks.back() {
+            Some(block) => Tip(block.point(), block.number),
+            None => Tip(Point::Origin, 0),
+        }
+    }
+
+    pub fn tip_block(&self) -> Option<&StoredBlock> {
+        self.blocks.back()
+    }
+
+    /// Whether the chain reaches back to genesis, so that Origin is a valid point
+    fn starts_at_origin(&self) -> bool {
+        self.blocks.front().is_none_or(|b| b.number == 0)
+    }
+
+    fn position(&self, point: &Point) -> Option<usize> {
+        match point {
+            Point::Origin => None,
+            Point::Specific(slot, hash) => {
+                let index = self.blocks.partition_point(|b| b.slot < *slot);
+                self.blocks
+                    .get(index)
+                    .filter(|b| b.slot == *slot && b.hash.as_ref() == hash.as_slice())
+                    .map(|_| index)
+            }
+        }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match point {
+            Point::Origin => self.starts_at_origin(),
+            _ => self.position(point).is_some(),
+        }
+    }
+
+    /// First of `points` which is on the chain
+    pub fn find_intersect(&self, points: &[Point]) -> Option<Point> {
+        points.iter().find(|p| self.contains(p)).cloned()
+    }
+
+    /// The block following `point`, if `point` is on the chain and not the tip
+    pub fn next_after(&self, point: &Point) -> Option<&StoredBlock> {
+        match point {
+            Point::Origin if self.starts_at_origin() => self.blocks.front(),
+            Point::Origin => None,
+            _ => self.position(point).and_then(|i| self.blocks.get(i + 1)),
+        }
+    }
+}
+
+/// What to send a chain sync client next
+#[derive(Debug)]
+pub enum FollowerStep {
+    RollForward(StoredBlock),
+    RollBackward(Point),
+    /// The client is at the tip
+    Await,
+    /// The chain rolled back past everything the client has seen
+    Lost,
+}
+
+/// Read pointer of one chain sync client. Keeps the points it has been sent so that
+/// after a rollback it can be sent back to the most recent one still on the chain.
+pub struct Follower {
+    path: VecDeque<Point>,
+    pending_rollback: bool,
+    capacity: usize,
+}
+
+impl Follower {
+    /// Start following from `intersect`. Per the protocol, the first reply after an
+    /// intersection is a rollback to it.
+    pub fn new(intersect: Point, capacity: usize) -> Self {
+        Self {
+            path: VecDeque::from([intersect]),
+            pending_rollback: true,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// A client which never asked for an intersection reads from Origin
+    pub fn from_origin(capacity: usize) -> Self {
+        Self {
+            path: VecDeque::from([Point::Origin]),
+            pending_rollback: false,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn next(&mut self, chain: &VolatileChain) -> FollowerStep {
+        let mut rolled_back = std::mem::take(&mut self.pending_rollback);
+        while let Some(current) = self.path.back() {
+            if chain.contains(current) {
+                break;
+            }
+            self.path.pop_back();
+            rolled_back = true;
+        }
+        let Some(current) = self.path.back() else {
+            return FollowerStep::Lost;
+        };
+        if rolled_back {
+            return FollowerStep::RollBackward(current.clone());
+        }
+
+        match chain.next_after(current) {
+            Some(block) => {
+                let block = block.clone();
+                self.path.push_back(block.point());
+                while self.path.len() > self.capacity {
+                    self.path.pop_front();
+                }
+                FollowerStep::RollForward(block)
+            }
+            None => FollowerStep::Await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, fork: u8) -> StoredBlock {
+        StoredBlock {
+            slot: number * 10,
+            number,
+            hash: BlockHash::from([fork.wrapping_mul(31).wrapping_add(number as u8); 32]),
+            epoch: 0,
+            era: Era::Conway,
+            body: Arc::new(vec![number as u8]),
+        }
+    }
+
+    fn chain_of(range: std::ops::RangeInclusive<u64>, capacity: usize) -> VolatileChain {
+        let mut chain = VolatileChain::new(capacity);
+        for n in range {
+            chain.roll_forward(block(n, 0));
+        }
+        chain
+    }
+
+    #[test]
+    fn keeps_only_capacity_blocks() {
+        let chain = chain_of(1..=10, 4);
+        assert_eq!(chain.blocks.len(), 4);
+        assert_eq!(chain.tip().1, 10);
+        assert!(!chain.contains(&block(6, 0).point()));
+        assert!(chain.contains(&block(7, 0).point()));
+    }
+
+    #[test]
+    fn fork_switch_replaces_tail() {
+        let mut chain = chain_of(1..=5, 10);
+        chain.roll_forward(block(4, 1));
+        assert_eq!(chain.tip().1, 4);
+        assert!(!chain.contains(&block(5, 0).point()));
+        assert!(chain.contains(&block(4, 1).point()));
+        assert!(chain.contains(&block(3, 0).point()));
+    }
+
+    #[test]
+    fn origin_only_valid_from_genesis() {
+        assert!(chain_of(0..=3, 10).contains(&Point::Origin));
+        assert!(!chain_of(1..=3, 10).contains(&Point::Origin));
+    }
+
+    #[test]
+    fn intersect_picks_first_known_point() {
+        let chain = chain_of(1..=5, 10);
+        let unknown = block(9, 7).point();
+        let found = chain.find_intersect(&[unknown, block(4, 0).point(), block(2, 0).point()]);
+        assert_eq!(found, Some(block(4, 0).point()));
+        assert_eq!(chain.find_intersect(&[block(9, 7).point()]), None);
+    }
+
+    #[test]
+    fn follower_rolls_back_to_intersect_then_forward() {
+        let chain = chain_of(1..=3, 10);
+        let mut follower = Follower::new(block(1, 0).point(), 10);
+
+        assert!(
+            matches!(follower.next(&chain), FollowerStep::RollBackward(p) if p == block(1, 0).point())
+        );
+        assert!(matches!(follower.next(&chain), FollowerStep::RollForward(b) if b.number == 2));
+        assert!(matches!(follower.next(&chain), FollowerStep::RollForward(b) if b.number == 3));
+        assert!(matches!(follower.next(&chain), FollowerStep::Await));
+    }
+
+    #[test]
+    fn follower_follows_chain_rollback() {
+        let mut chain = chain_of(1..=5, 10);
+        let mut follower = Follower::new(block(1, 0).point(), 10);
+        for _ in 0..5 {
+            follower.next(&chain);
+        }
+
+        chain.roll_forward(block(4, 1));
+        assert!(
+            matches!(follower.next(&chain), FollowerStep::RollBackward(p) if p == block(3, 0).point())
+        );
+        assert!(
+            matches!(follower.next(&chain), FollowerStep::RollForward(b) if b.hash == block(4, 1).hash)
+        );
+    }
+
+    #[test]
+    fn follower_lost_when_rollback_passes_its_history() {
+        let mut chain = chain_of(1..=5, 10);
+        let mut follower = Follower::new(block(4, 0).point(), 10);
+        follower.next(&chain);
+        chain.roll_back_to_slot(20);
+        assert!(matches!(follower.next(&chain), FollowerStep::Lost));
+    }
+}
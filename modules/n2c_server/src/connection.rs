@@ -0,0 +1,352 @@
+//! A single node-to-client connection: the handshake, then chain sync and local state
+//! query served side by side over the multiplexed socket.
+
+use std::{collections::HashMap, sync::Arc};
+
+use acropolis_common::{
+    genesis_values::GenesisValues,
+    messages::{Message, StateQuery, StateQueryResponse},
+    protocol_params::ProtocolParams,
+    queries::{
+        errors::QueryError,
+        pools::{PoolsStateQuery, PoolsStateQueryResponse},
+        spdd::{SPDDStateQuery, SPDDStateQueryResponse},
+        utils::query_state,
+    },
+    Era, PoolId, VrfKeyHash,
+};
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::Context;
+use chrono::DateTime;
+use pallas::network::{
+    miniprotocols::{
+        chainsync::{self, BlockContent, ClientRequest},
+        handshake::{self, n2c},
+        Point, PROTOCOL_N2C_CHAIN_SYNC, PROTOCOL_N2C_HANDSHAKE, PROTOCOL_N2C_STATE_QUERY,
+    },
+    multiplexer::{AgentChannel, Bearer, ChannelBuffer, Plexer},
+};
+use tokio::sync::{watch, RwLock};
+use tracing::debug;
+
+use crate::{
+    chain::{Follower, FollowerStep, StoredBlock, VolatileChain},
+    state_query::{self, AcquireFailure, AcquireTarget, EraQuery, LsqMessage, Query},
+};
+
+/// Node-to-client versions we accept (V16 to V20, with the N2C bit set)
+const SUPPORTED_VERSIONS: std::ops::RangeInclusive<u64> = 32784..=32788;
+
+/// State shared between the bus subscribers and all client connections
+pub struct ServerState {
+    pub chain: RwLock<VolatileChain>,
+    /// Signalled whenever `chain` changes, to wake clients waiting at the tip
+    pub chain_changed: watch::Sender<()>,
+    pub genesis: RwLock<Option<GenesisValues>>,
+    pub params: RwLock<Option<Arc<ProtocolParams>>>,
+    pub history_blocks: usize,
+    pub spdd_query_topic: String,
+    pub pools_query_topic: String,
+}
+
+/// Serve one client until it disconnects or breaks the protocol
+pub async fn serve(bearer: Bearer, state: Arc<ServerState>, context: Arc<Context<Message>>) {
+    let mut plexer = Plexer::new(bearer);
+    let handshake_channel = plexer.subscribe_server(PROTOCOL_N2C_HANDSHAKE);
+    let chain_sync_channel = plexer.subscribe_server(PROTOCOL_N2C_CHAIN_SYNC);
+    let state_query_channel = plexer.subscribe_server(PROTOCOL_N2C_STATE_QUERY);
+    let running = plexer.spawn();
+
+    let result = async {
+        let magic = match state.genesis.read().await.as_ref() {
+            Some(genesis) => genesis.magic_number.0 as u64,
+            None => bail!("Not bootstrapped yet"),
+        };
+        if !handshake(handshake_channel, magic).await? {
+            return Ok(());
+        }
+        tokio::try_join!(
+            serve_chain_sync(chain_sync_channel, &state),
+            serve_state_query(state_query_channel, &state, &context),
+        )?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        debug!("Node-to-client connection closed: {e:#}");
+    }
+    running.abort().await;
+}
+
+/// Agree the highest version both sides support. Returns false if there is none.
+async fn handshake(channel: AgentChannel, magic: u64) -> Result<bool> {
+    let mut server = handshake::N2CServer::new(channel);
+    let proposed = server.receive_proposed_versions().await?;
+
+    let version = proposed.values.keys().copied().filter(|v| SUPPORTED_VERSIONS.contains(v)).max();
+    match version {
+        Some(version) => {
+            debug!("Accepting node-to-client version {version}");
+            server.accept_version(version, n2c::VersionData::new(magic, Some(false))).await?;
+            Ok(true)
+        }
+        None => {
+            debug!("No supported version in {:?}", proposed.values.keys());
+            server
+                .refuse(handshake::RefuseReason::VersionMismatch(
+                    SUPPORTED_VERSIONS.collect(),
+                ))
+                .await?;
+            Ok(false)
+        }
+    }
+}
+
+async fn serve_chain_sync(channel: AgentChannel, state: &ServerState) -> Result<()> {
+    let mut server = chainsync::N2CServer::new(channel);
+    let mut chain_changed = state.chain_changed.subscribe();
+    let mut follower = Follower::from_origin(state.history_blocks);
+
+    while let Some(request) = server.recv_while_idle().await? {
+        match request {
+            ClientRequest::Intersect(points) => {
+                let (intersect, tip) = {
+                    let chain = state.chain.read().await;
+                    (chain.find_intersect(&points), chain.tip())
+                };
+                match intersect {
+                    Some(point) => {
+                        follower = Follower::new(point.clone(), state.history_blocks);
+                        server.send_intersect_found(point, tip).await?;
+                    }
+                    None => server.send_intersect_not_found(tip).await?,
+                }
+            }
+
+            ClientRequest::RequestNext => {
+                let mut awaiting = false;
+                loop {
+                    // Mark as seen before reading, so a change during the read still wakes us
+                    chain_changed.borrow_and_update();
+                    let (step, tip) = {
+                        let chain = state.chain.read().await;
+                        (follower.next(&chain), chain.tip())
+                    };
+
+                    match step {
+                        FollowerStep::RollForward(block) => {
+                            let content = BlockContent(block.body.to_vec());
+                            server.send_roll_forward(content, tip).await?;
+                            break;
+                        }
+                        FollowerStep::RollBackward(point) => {
+                            server.send_roll_backward(point, tip).await?;
+                            break;
+                        }
+                        FollowerStep::Await => {
+                            if !awaiting {
+                                server.send_await_reply().await?;
+                                awaiting = true;
+                            }
+                            chain_changed.changed().await?;
+                        }
+                        FollowerStep::Lost => bail!(
+                            "Client fell more than {} blocks behind the tip",
+                            state.history_blocks
+                        ),
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ledger state held by a client between acquire and release
+struct AcquiredState {
+    tip: Option<StoredBlock>,
+    genesis: Option<GenesisValues>,
+    params: Option<Arc<ProtocolParams>>,
+}
+
+/// Only the ledger state at the current tip is available. Older points on the chain
+/// are reported as too old rather than served with the wrong state.
+async fn acquire(
+    state: &ServerState,
+    target: &AcquireTarget,
+) -> Result<AcquiredState, AcquireFailure> {
+    let tip = {
+        let chain = state.chain.read().await;
+        if let AcquireTarget::Point(point) = target {
+            if *point != chain.tip().0 {
+                return Err(if chain.contains(point) {
+                    AcquireFailure::PointTooOld
+                } else {
+                    AcquireFailure::PointNotOnChain
+                });
+            }
+        }
+        chain.tip_block().cloned()
+    };
+
+    Ok(AcquiredState {
+        tip,
+        genesis: state.genesis.read().await.clone(),
+        params: state.params.read().await.clone(),
+    })
+}
+
+async fn serve_state_query(
+    channel: AgentChannel,
+    state: &ServerState,
+    context: &Arc<Context<Message>>,
+) -> Result<()> {
+    let mut buffer = ChannelBuffer::new(channel);
+
+    loop {
+        let mut target = match buffer.recv_full_msg::<LsqMessage>().await? {
+            LsqMessage::Acquire(target) => target,
+            LsqMessage::Done => return Ok(()),
+            other => bail!("Unexpected {other:?} while idle"),
+        };
+
+        'acquired: loop {
+            let acquired = match acquire(state, &target).await {
+                Ok(acquired) => {
+                    buffer.send_msg_chunks(&LsqMessage::Acquired).await?;
+                    acquired
+                }
+                Err(failure) => {
+                    buffer.send_msg_chunks(&LsqMessage::Failure(failure)).await?;
+                    break 'acquired;
+                }
+            };
+
+            loop {
+                match buffer.recv_full_msg::<LsqMessage>().await? {
+                    LsqMessage::Query(bytes) => {
+                        let query = Query::decode(&bytes)?;
+                        let result = answer(&query, &acquired, state, context).await?;
+                        buffer.send_msg_chunks(&LsqMessage::Result(result)).await?;
+                    }
+                    LsqMessage::ReAcquire(new_target) => {
+                        target = new_target;
+                        continue 'acquired;
+                    }
+                    LsqMessage::Release => break 'acquired,
+                    other => bail!("Unexpected {other:?} while acquired"),
+                }
+            }
+        }
+    }
+}
+
+async fn answer(
+    query: &Query,
+    acquired: &AcquiredState,
+    state: &ServerState,
+    context: &Arc<Context<Message>>,
+) -> Result<Vec<u8>> {
+    let tip = acquired.tip.as_ref();
+    let era = tip.map(|b| b.era).unwrap_or(Era::Byron);
+
+    let result = match query {
+        Query::SystemStart => {
+            let genesis = acquired.genesis.as_ref().ok_or_else(|| anyhow!("No genesis values"))?;
+            let start = DateTime::from_timestamp(genesis.byron_timestamp as i64, 0)
+                .ok_or_else(|| anyhow!("Invalid system start {}", genesis.byron_timestamp))?;
+            state_query::encode_system_start(start)
+        }
+        Query::ChainBlockNo => state_query::encode_chain_block_no(tip.map(|b| b.number)),
+        Query::ChainPoint => {
+            state_query::encode_point(&tip.map(StoredBlock::point).unwrap_or(Point::Origin))
+        }
+        Query::CurrentEra => state_query::encode_era(era),
+        Query::IfCurrent {
+            era: query_era,
+            query,
+        } => {
+            if *query_era != u8::from(era) {
+                return Ok(state_query::encode_era_mismatch(*query_era, era));
+            }
+            let result = match query {
+                EraQuery::LedgerTip => {
+                    state_query::encode_point(&tip.map(StoredBlock::point).unwrap_or(Point::Origin))
+                }
+                EraQuery::EpochNo => state_query::encode_epoch(tip.map(|b| b.epoch).unwrap_or(0)),
+                EraQuery::CurrentPParams if era == Era::Conway => acquired
+                    .params
+                    .as_deref()
+                    .and_then(state_query::encode_conway_pparams)
+                    .ok_or_else(|| anyhow!("Protocol parameters not available"))?,
+                EraQuery::StakeDistribution => {
+                    let epoch = tip.map(|b| b.epoch).unwrap_or(0);
+                    let pools = stake_distribution(epoch, state, context).await?;
+                    state_query::encode_stake_distribution(&pools)
+                }
+                other => bail!("Unsupported query {other:?} in era {era}"),
+            };
+            state_query::wrap_current(&result)
+        }
+        Query::Unsupported => bail!("Unsupported query"),
+    };
+    Ok(result)
+}
+
+/// Active stake of each pool in `epoch`, with its VRF key hash
+async fn stake_distribution(
+    epoch: u64,
+    state: &ServerState,
+    context: &Arc<Context<Message>>,
+) -> Result<Vec<(PoolId, u64, VrfKeyHash)>> {
+    let spdd_msg = Arc::new(Message::StateQuery(StateQuery::SPDD(
+        SPDDStateQuery::GetEpochSPDD { epoch },
+    )));
+    let spdd = query_state(
+        context,
+        &state.spdd_query_topic,
+        spdd_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::EpochSPDD(spdd),
+            )) => Ok(spdd),
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving SPDD",
+            )),
+        },
+    )
+    .await?;
+
+    let pools_msg = Arc::new(Message::StateQuery(StateQuery::Pools(
+        PoolsStateQuery::GetPoolsListWithInfo,
+    )));
+    let pools = query_state(
+        context,
+        &state.pools_query_topic,
+        pools_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Pools(
+                PoolsStateQueryResponse::PoolsListWithInfo(list),
+            )) => Ok(list.pools),
+            Message::StateQueryResponse(StateQueryResponse::Pools(
+                PoolsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving pools",
+            )),
+        },
+    )
+    .await?;
+
+    let vrf_keys: HashMap<PoolId, VrfKeyHash> =
+        pools.into_iter().map(|(id, reg)| (id, reg.vrf_key_hash)).collect();
+
+    Ok(spdd
+        .into_iter()
+        .filter_map(|(pool, stake)| vrf_keys.get(&pool).map(|vrf| (pool, stake, *vrf)))
+        .collect())
+}
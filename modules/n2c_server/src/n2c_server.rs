@@ -0,0 +1,203 @@
+//! Acropolis node-to-client server module for Caryatid
+//!
+//! Listens on a Unix socket and speaks the node-to-client mini-protocols, so that
+//! existing Cardano tooling (cardano-cli, Ogmios) can follow the chain and query a
+//! subset of the ledger state through an Acropolis node.
+
+use std::{path::Path, sync::Arc};
+
+use acropolis_common::{
+    caryatid::RollbackWrapper,
+    configuration::{get_bool_flag, get_string_flag, get_u64_flag},
+    declare_cardano_reader,
+    messages::{
+        CardanoMessage, GenesisCompleteMessage, Message, ProtocolParamsMessage, RawBlockMessage,
+        StateTransitionMessage,
+    },
+    queries::{pools::DEFAULT_POOLS_QUERY_TOPIC, spdd::DEFAULT_SPDD_QUERY_TOPIC},
+    Point,
+};
+use anyhow::{bail, Result};
+use caryatid_sdk::{message_bus::Subscription, module, Context};
+use config::Config;
+use pallas::network::multiplexer::Bearer;
+use tokio::{
+    net::UnixListener,
+    sync::{watch, RwLock},
+};
+use tracing::{error, info, warn};
+
+mod chain;
+mod connection;
+mod state_query;
+
+use chain::{StoredBlock, VolatileChain};
+use connection::ServerState;
+
+const DEFAULT_ENABLED: (&str, bool) = ("enabled", false);
+const DEFAULT_SOCKET_PATH: (&str, &str) = ("socket-path", "acropolis.socket");
+/// Blocks kept for chain sync clients - one security parameter's worth on mainnet
+const DEFAULT_HISTORY_BLOCKS: (&str, u64) = ("history-blocks", 2160);
+
+const DEFAULT_BLOCKS_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("blocks-subscribe-topic", "cardano.block.available");
+const DEFAULT_PROTOCOL_PARAMETERS_SUBSCRIBE_TOPIC: (&str, &str) = (
+    "protocol-parameters-subscribe-topic",
+    "cardano.protocol.parameters",
+);
+const DEFAULT_GENESIS_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("genesis-subscribe-topic", "cardano.sequence.bootstrapped");
+
+declare_cardano_reader!(
+    BlockReader,
+    DEFAULT_BLOCKS_SUBSCRIBE_TOPIC.0,
+    DEFAULT_BLOCKS_SUBSCRIBE_TOPIC.1,
+    BlockAvailable,
+    RawBlockMessage
+);
+declare_cardano_reader!(
+    ParamsReader,
+    DEFAULT_PROTOCOL_PARAMETERS_SUBSCRIBE_TOPIC.0,
+    DEFAULT_PROTOCOL_PARAMETERS_SUBSCRIBE_TOPIC.1,
+    ProtocolParams,
+    ProtocolParamsMessage
+);
+declare_cardano_reader!(
+    GenesisReader,
+    DEFAULT_GENESIS_SUBSCRIBE_TOPIC.0,
+    DEFAULT_GENESIS_SUBSCRIBE_TOPIC.1,
+    GenesisComplete,
+    GenesisCompleteMessage
+);
+
+/// Node-to-client server module
+#[module(
+    message_type(Message),
+    name = "n2c-server",
+    description = "Node-to-client local socket server (chain sync and local state query)"
+)]
+pub struct N2CServer;
+
+impl N2CServer {
+    /// Track the chain from the block topic, keeping the last `history_blocks` blocks
+    async fn run_chain(state: Arc<ServerState>, mut blocks: BlockReader) -> Result<()> {
+        loop {
+            match blocks.read_with_rollbacks().await? {
+                RollbackWrapper::Normal((block_info, raw_block)) => {
+                    let block = StoredBlock {
+                        slot: block_info.slot,
+                        number: block_info.number,
+                        hash: block_info.hash,
+                        epoch: block_info.epoch,
+                        era: block_info.era,
+                        body: Arc::new(raw_block.body.clone()),
+                    };
+                    state.chain.write().await.roll_forward(block);
+                }
+                RollbackWrapper::Rollback((_, message)) => {
+                    if let Message::Cardano((
+                        _,
+                        CardanoMessage::StateTransition(StateTransitionMessage::Rollback(point)),
+                    )) = message.as_ref()
+                    {
+                        let mut chain = state.chain.write().await;
+                        match point {
+                            Point::Origin => chain.clear(),
+                            Point::Specific { slot, .. } => chain.roll_back_to_slot(*slot),
+                        }
+                    }
+                }
+            }
+            state.chain_changed.send_replace(());
+        }
+    }
+
+    async fn run_params(state: Arc<ServerState>, mut params: ParamsReader) -> Result<()> {
+        loop {
+            if let RollbackWrapper::Normal((_, message)) = params.read_with_rollbacks().await? {
+                *state.params.write().await = Some(Arc::new(message.params.clone()));
+            }
+        }
+    }
+
+    async fn accept_connections(
+        listener: UnixListener,
+        state: Arc<ServerState>,
+        context: Arc<Context<Message>>,
+    ) -> Result<()> {
+        loop {
+            let (bearer, _) = Bearer::accept_unix(&listener).await?;
+            info!("Node-to-client client connected");
+            tokio::spawn(connection::serve(bearer, state.clone(), context.clone()));
+        }
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        if !get_bool_flag(&config, DEFAULT_ENABLED) {
+            info!("Node-to-client server is disabled in configuration");
+            return Ok(());
+        }
+
+        let socket_path = get_string_flag(&config, DEFAULT_SOCKET_PATH);
+        let history_blocks = get_u64_flag(&config, DEFAULT_HISTORY_BLOCKS) as usize;
+        let spdd_query_topic = get_string_flag(&config, DEFAULT_SPDD_QUERY_TOPIC);
+        let pools_query_topic = get_string_flag(&config, DEFAULT_POOLS_QUERY_TOPIC);
+
+        let (chain_changed, _) = watch::channel(());
+        let state = Arc::new(ServerState {
+            chain: RwLock::new(VolatileChain::new(history_blocks)),
+            chain_changed,
+            genesis: RwLock::new(None),
+            params: RwLock::new(None),
+            history_blocks,
+            spdd_query_topic,
+            pools_query_topic,
+        });
+
+        let blocks = BlockReader::new(&context, &config).await?;
+        let params = ParamsReader::new(&context, &config).await?;
+        let mut genesis = GenesisReader::new(&context, &config).await?;
+
+        // A socket left behind by a previous run would stop us binding
+        if Path::new(&socket_path).exists() {
+            warn!("Removing stale socket {socket_path}");
+            std::fs::remove_file(&socket_path)?;
+        }
+        let listener = UnixListener::bind(&socket_path)?;
+        info!("Node-to-client server listening on {socket_path}");
+
+        let run_state = state.clone();
+        context.run(async move {
+            match genesis.read_with_rollbacks().await {
+                Ok(RollbackWrapper::Normal((_, message))) => {
+                    *run_state.genesis.write().await = Some(message.values.clone());
+                }
+                Ok(_) => error!("Unexpected rollback before genesis"),
+                Err(e) => error!("Failed to read genesis values: {e}"),
+            }
+        });
+
+        let run_state = state.clone();
+        context.run(async move {
+            Self::run_chain(run_state, blocks)
+                .await
+                .unwrap_or_else(|e| error!("Chain tracking failed: {e}"));
+        });
+
+        let run_state = state.clone();
+        context.run(async move {
+            Self::run_params(run_state, params)
+                .await
+                .unwrap_or_else(|e| error!("Protocol parameters tracking failed: {e}"));
+        });
+
+        let run_context = context.clone();
+        context.run(async move {
+            Self::accept_connections(listener, state, run_context)
+                .await
+                .unwrap_or_else(|e| error!("Node-to-client server failed: {e}"));
+        });
+
+        Ok(())
+    }
+}
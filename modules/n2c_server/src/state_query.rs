@@ -0,0 +1,505 @@
+//! Local state query mini-protocol messages and the subset of queries we answer
+//!
+//! Queries are decoded into [`Query`] and results encoded to the CBOR expected by
+//! cardano-cli / Ogmios for node-to-client versions 16 and above.
+
+use acropolis_common::{protocol_params::ProtocolParams, Era, PoolId, VrfKeyHash};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use pallas::codec::minicbor::{
+    self,
+    data::{Tag, Type},
+    decode, encode,
+    encode::Write as _,
+    Decoder, Encoder,
+};
+use pallas::network::miniprotocols::Point;
+
+/// Tag for a CBOR rational number
+const RATIONAL_TAG: u64 = 30;
+
+/// Which ledger state a client wants to acquire
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcquireTarget {
+    Point(Point),
+    VolatileTip,
+    ImmutableTip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AcquireFailure {
+    PointTooOld = 0,
+    PointNotOnChain = 1,
+}
+
+/// Messages of the local state query mini-protocol
+#[derive(Debug, Clone, PartialEq)]
+pub enum LsqMessage {
+    Acquire(AcquireTarget),
+    Acquired,
+    Failure(AcquireFailure),
+    /// Raw CBOR of the query, decoded with [`Query::decode`]
+    Query(Vec<u8>),
+    /// Raw CBOR of the result
+    Result(Vec<u8>),
+    Release,
+    ReAcquire(AcquireTarget),
+    Done,
+}
+
+impl encode::Encode<()> for LsqMessage {
+    fn encode<W: encode::Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _ctx: &mut (),
+    ) -> Result<(), encode::Error<W::Error>> {
+        match self {
+            LsqMessage::Acquire(target) => encode_target(e, target, [0, 8, 10])?,
+            LsqMessage::Acquired => {
+                e.array(1)?.u8(1)?;
+            }
+            LsqMessage::Failure(failure) => {
+                e.array(2)?.u8(2)?.u8(*failure as u8)?;
+            }
+            LsqMessage::Query(query) => {
+                e.array(2)?.u8(3)?;
+                e.writer_mut().write_all(query).map_err(encode::Error::write)?;
+            }
+            LsqMessage::Result(result) => {
+                e.array(2)?.u8(4)?;
+                e.writer_mut().write_all(result).map_err(encode::Error::write)?;
+            }
+            LsqMessage::Release => {
+                e.array(1)?.u8(5)?;
+            }
+            LsqMessage::ReAcquire(target) => encode_target(e, target, [6, 9, 11])?,
+            LsqMessage::Done => {
+                e.array(1)?.u8(7)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Acquire and re-acquire use different labels for each kind of target
+fn encode_target<W: encode::Write>(
+    e: &mut Encoder<W>,
+    target: &AcquireTarget,
+    [point, volatile, immutable]: [u8; 3],
+) -> Result<(), encode::Error<W::Error>> {
+    match target {
+        AcquireTarget::Point(p) => {
+            e.array(2)?.u8(point)?.encode(p)?;
+        }
+        AcquireTarget::VolatileTip => {
+            e.array(1)?.u8(volatile)?;
+        }
+        AcquireTarget::ImmutableTip => {
+            e.array(1)?.u8(immutable)?;
+        }
+    }
+    Ok(())
+}
+
+impl<'b> decode::Decode<'b, ()> for LsqMessage {
+    fn decode(d: &mut Decoder<'b>, _ctx: &mut ()) -> Result<Self, decode::Error> {
+        d.array()?;
+        let label = d.u8()?;
+        let message = match label {
+            0 => LsqMessage::Acquire(AcquireTarget::Point(d.decode()?)),
+            8 => LsqMessage::Acquire(AcquireTarget::VolatileTip),
+            10 => LsqMessage::Acquire(AcquireTarget::ImmutableTip),
+            1 => LsqMessage::Acquired,
+            2 => LsqMessage::Failure(match d.u8()? {
+                0 => AcquireFailure::PointTooOld,
+                _ => AcquireFailure::PointNotOnChain,
+            }),
+            3 => LsqMessage::Query(raw_item(d)?),
+            4 => LsqMessage::Result(raw_item(d)?),
+            5 => LsqMessage::Release,
+            6 => LsqMessage::ReAcquire(AcquireTarget::Point(d.decode()?)),
+            9 => LsqMessage::ReAcquire(AcquireTarget::VolatileTip),
+            11 => LsqMessage::ReAcquire(AcquireTarget::ImmutableTip),
+            7 => LsqMessage::Done,
+            other => {
+                return Err(decode::Error::message(format!(
+                    "unknown local state query message {other}"
+                )))
+            }
+        };
+        Ok(message)
+    }
+}
+
+/// Bytes of the next CBOR data item, undecoded
+fn raw_item(d: &mut Decoder<'_>) -> Result<Vec<u8>, decode::Error> {
+    let start = d.position();
+    d.skip()?;
+    Ok(d.input()[start..d.position()].to_vec())
+}
+
+/// Shelley-based era queries
+#[derive(Debug, Clone, PartialEq)]
+pub enum EraQuery {
+    LedgerTip,
+    EpochNo,
+    CurrentPParams,
+    StakeDistribution,
+    Other(u64),
+}
+
+/// Queries understood by the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    SystemStart,
+    ChainBlockNo,
+    ChainPoint,
+    CurrentEra,
+    /// Era specific query, only answered if `era` is the current era
+    IfCurrent {
+        era: u8,
+        query: EraQuery,
+    },
+    Unsupported,
+}
+
+impl Query {
+    pub fn decode(bytes: &[u8]) -> Result<Self, decode::Error> {
+        let mut d = Decoder::new(bytes);
+        d.array()?;
+        let query = match d.u8()? {
+            0 => Self::decode_block_query(&mut d)?,
+            1 => Query::SystemStart,
+            2 => Query::ChainBlockNo,
+            3 => Query::ChainPoint,
+            _ => Query::Unsupported,
+        };
+        Ok(query)
+    }
+
+    /// Hard fork combinator block query: [0, [era, query]] for a query in a given
+    /// era, [2, [1]] for the current era
+    fn decode_block_query(d: &mut Decoder<'_>) -> Result<Self, decode::Error> {
+        d.array()?;
+        let query = match d.u8()? {
+            0 => {
+                d.array()?;
+                let era = d.u8()?;
+                d.array()?;
+                let query = match d.u64()? {
+                    0 => EraQuery::LedgerTip,
+                    1 => EraQuery::EpochNo,
+                    3 => EraQuery::CurrentPParams,
+                    5 => EraQuery::StakeDistribution,
+                    other => EraQuery::Other(other),
+                };
+                Query::IfCurrent { era, query }
+            }
+            2 => {
+                d.array()?;
+                match d.u8()? {
+                    1 => Query::CurrentEra,
+                    _ => Query::Unsupported,
+                }
+            }
+            _ => Query::Unsupported,
+        };
+        Ok(query)
+    }
+}
+
+type EncodeResult = Result<(), encode::Error<std::convert::Infallible>>;
+
+fn to_bytes(f: impl FnOnce(&mut Encoder<Vec<u8>>) -> EncodeResult) -> Vec<u8> {
+    let mut e = Encoder::new(Vec::new());
+    // Writing into a Vec cannot fail
+    let _ = f(&mut e);
+    e.into_writer()
+}
+
+/// System start as [year, day of year, picoseconds of day]
+pub fn encode_system_start(start: DateTime<Utc>) -> Vec<u8> {
+    let picos = start.num_seconds_from_midnight() as u64 * 1_000_000_000_000
+        + start.nanosecond() as u64 * 1_000;
+    to_bytes(|e| {
+        e.array(3)?.i32(start.year())?.u32(start.ordinal())?.u64(picos)?;
+        Ok(())
+    })
+}
+
+/// Block number of the tip, or Origin for an empty chain
+pub fn encode_chain_block_no(number: Option<u64>) -> Vec<u8> {
+    to_bytes(|e| {
+        match number {
+            Some(n) => e.array(2)?.u8(1)?.u64(n)?,
+            None => e.array(1)?.u8(0)?,
+        };
+        Ok(())
+    })
+}
+
+pub fn encode_point(point: &Point) -> Vec<u8> {
+    to_bytes(|e| {
+        e.encode(point)?;
+        Ok(())
+    })
+}
+
+pub fn encode_era(era: Era) -> Vec<u8> {
+    to_bytes(|e| {
+        e.u8(era.into())?;
+        Ok(())
+    })
+}
+
+/// Wrap an era specific result as a successful "if current era" answer
+pub fn wrap_current(result: &[u8]) -> Vec<u8> {
+    to_bytes(|e| {
+        e.array(1)?;
+        e.writer_mut().write_all(result).map_err(encode::Error::write)?;
+        Ok(())
+    })
+}
+
+/// Answer to an era specific query for an era other than the current one
+pub fn encode_era_mismatch(query_era: u8, ledger_era: Era) -> Vec<u8> {
+    let query_era_name = Era::try_from(query_era).map(|era| era.to_string());
+    to_bytes(|e| {
+        e.array(2)?;
+        e.array(2)?.u8(query_era)?.str(query_era_name.as_deref().unwrap_or("Unknown"))?;
+        e.array(2)?.u8(ledger_era.into())?.str(&ledger_era.to_string())?;
+        Ok(())
+    })
+}
+
+pub fn encode_epoch(epoch: u64) -> Vec<u8> {
+    to_bytes(|e| {
+        e.u64(epoch)?;
+        Ok(())
+    })
+}
+
+fn rational<W: encode::Write>(
+    e: &mut Encoder<W>,
+    numerator: u64,
+    denominator: u64,
+) -> Result<(), encode::Error<W::Error>> {
+    e.tag(Tag::new(RATIONAL_TAG))?.array(2)?.u64(numerator)?.u64(denominator)?;
+    Ok(())
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Stake distribution as a map of pool to [relative stake, VRF key hash]
+pub fn encode_stake_distribution(pools: &[(PoolId, u64, VrfKeyHash)]) -> Vec<u8> {
+    let total: u64 = pools.iter().map(|(_, stake, _)| *stake).sum();
+    to_bytes(|e| {
+        e.map(pools.len() as u64)?;
+        for (pool, stake, vrf) in pools {
+            let divisor = gcd(*stake, total).max(1);
+            e.bytes(pool.as_ref())?.array(2)?;
+            rational(e, stake / divisor, total.max(1) / divisor)?;
+            e.bytes(vrf.as_ref())?;
+        }
+        Ok(())
+    })
+}
+
+/// Current protocol parameters in the Conway era layout. Returns `None` if any of
+/// the eras the Conway parameters are built from is missing.
+pub fn encode_conway_pparams(params: &ProtocolParams) -> Option<Vec<u8>> {
+    let shelley = &params.shelley.as_ref()?.protocol_params;
+    let alonzo = params.alonzo.as_ref()?;
+    let babbage = params.babbage.as_ref()?;
+    let conway = params.conway.as_ref()?;
+    let cost_models = params.cost_models();
+
+    let bytes = to_bytes(|e| {
+        e.array(31)?;
+        e.u32(shelley.minfee_a)?;
+        e.u32(shelley.minfee_b)?;
+        e.u32(shelley.max_block_body_size)?;
+        e.u32(shelley.max_tx_size)?;
+        e.u32(shelley.max_block_header_size)?;
+        e.u64(shelley.key_deposit)?;
+        e.u64(shelley.pool_deposit)?;
+        e.u64(shelley.pool_retire_max_epoch)?;
+        e.u32(shelley.stake_pool_target_num)?;
+        for r in [
+            &shelley.pool_pledge_influence,
+            &shelley.monetary_expansion,
+            &shelley.treasury_cut,
+        ] {
+            rational(e, *r.numer(), *r.denom())?;
+        }
+        e.array(2)?.u64(shelley.protocol_version.major)?.u64(shelley.protocol_version.minor)?;
+        e.u64(shelley.min_pool_cost)?;
+        e.u64(babbage.coins_per_utxo_byte)?;
+
+        let models = [
+            cost_models.plutus_v1,
+            cost_models.plutus_v2,
+            cost_models.plutus_v3,
+        ];
+        e.map(models.iter().flatten().count() as u64)?;
+        for (language, model) in models.iter().enumerate() {
+            if let Some(model) = model {
+                e.u8(language as u8)?.array(model.as_vec().len() as u64)?;
+                for value in model.as_vec() {
+                    e.i64(*value)?;
+                }
+            }
+        }
+
+        let prices = &alonzo.execution_prices;
+        e.array(2)?;
+        rational(e, *prices.mem_price.numer(), *prices.mem_price.denom())?;
+        rational(e, *prices.step_price.numer(), *prices.step_price.denom())?;
+        e.array(2)?.u64(alonzo.max_tx_ex_units.mem)?.u64(alonzo.max_tx_ex_units.steps)?;
+        e.array(2)?.u64(alonzo.max_block_ex_units.mem)?.u64(alonzo.max_block_ex_units.steps)?;
+        e.u32(alonzo.max_value_size)?;
+        e.u32(alonzo.collateral_percentage)?;
+        e.u32(alonzo.max_collateral_inputs)?;
+
+        let pool = &conway.pool_voting_thresholds;
+        let pool_thresholds = [
+            &pool.motion_no_confidence,
+            &pool.committee_normal,
+            &pool.committee_no_confidence,
+            &pool.hard_fork_initiation,
+            &pool.security_voting_threshold,
+        ];
+        e.array(pool_thresholds.len() as u64)?;
+        for r in pool_thresholds {
+            rational(e, *r.numer(), *r.denom())?;
+        }
+
+        let drep = &conway.d_rep_voting_thresholds;
+        let drep_thresholds = [
+            &drep.motion_no_confidence,
+            &drep.committee_normal,
+            &drep.committee_no_confidence,
+            &drep.update_constitution,
+            &drep.hard_fork_initiation,
+            &drep.pp_network_group,
+            &drep.pp_economic_group,
+            &drep.pp_technical_group,
+            &drep.pp_governance_group,
+            &drep.treasury_withdrawal,
+        ];
+        e.array(drep_thresholds.len() as u64)?;
+        for r in drep_thresholds {
+            rational(e, *r.numer(), *r.denom())?;
+        }
+
+        e.u64(conway.committee_min_size)?;
+        e.u32(conway.committee_max_term_length)?;
+        e.u32(conway.gov_action_lifetime)?;
+        e.u64(conway.gov_action_deposit)?;
+        e.u64(conway.d_rep_deposit)?;
+        e.u32(conway.d_rep_activity)?;
+        let ref_script_cost = &conway.min_fee_ref_script_cost_per_byte;
+        rational(e, *ref_script_cost.numer(), *ref_script_cost.denom())?;
+        Ok(())
+    });
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(message: LsqMessage) {
+        let bytes = minicbor::to_vec(&message).unwrap();
+        let decoded: LsqMessage = minicbor::decode(&bytes).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn messages_roundtrip() {
+        roundtrip(LsqMessage::Acquire(AcquireTarget::VolatileTip));
+        roundtrip(LsqMessage::Acquire(AcquireTarget::Point(Point::Specific(
+            42,
+            vec![7; 32],
+        ))));
+        roundtrip(LsqMessage::ReAcquire(AcquireTarget::ImmutableTip));
+        roundtrip(LsqMessage::Failure(AcquireFailure::PointNotOnChain));
+        roundtrip(LsqMessage::Query(vec![0x81, 0x01]));
+        roundtrip(LsqMessage::Result(vec![0x82, 0x01, 0x02]));
+        roundtrip(LsqMessage::Done);
+    }
+
+    #[test]
+    fn decodes_top_level_queries() {
+        assert_eq!(Query::decode(&[0x81, 0x01]).unwrap(), Query::SystemStart);
+        assert_eq!(Query::decode(&[0x81, 0x02]).unwrap(), Query::ChainBlockNo);
+        assert_eq!(Query::decode(&[0x81, 0x03]).unwrap(), Query::ChainPoint);
+    }
+
+    #[test]
+    fn decodes_current_era_query() {
+        // [0, [2, [1]]]
+        let bytes = [0x82, 0x00, 0x82, 0x02, 0x81, 0x01];
+        assert_eq!(Query::decode(&bytes).unwrap(), Query::CurrentEra);
+    }
+
+    #[test]
+    fn decodes_era_query() {
+        // [0, [0, [6, [5]]]] - Conway stake distribution
+        let bytes = [0x82, 0x00, 0x82, 0x00, 0x82, 0x06, 0x81, 0x05];
+        assert_eq!(
+            Query::decode(&bytes).unwrap(),
+            Query::IfCurrent {
+                era: 6,
+                query: EraQuery::StakeDistribution
+            }
+        );
+    }
+
+    #[test]
+    fn system_start_is_year_day_picos() {
+        let start = DateTime::parse_from_rfc3339("2017-09-23T21:44:51Z").unwrap().to_utc();
+        let bytes = encode_system_start(start);
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(d.array().unwrap(), Some(3));
+        assert_eq!(d.i32().unwrap(), 2017);
+        assert_eq!(d.u32().unwrap(), 266);
+        assert_eq!(
+            d.u64().unwrap(),
+            (21 * 3600 + 44 * 60 + 51) * 1_000_000_000_000
+        );
+    }
+
+    #[test]
+    fn stake_distribution_uses_reduced_fractions() {
+        let pools = [
+            (PoolId::from([1; 28]), 100, VrfKeyHash::from([2; 32])),
+            (PoolId::from([3; 28]), 300, VrfKeyHash::from([4; 32])),
+        ];
+        let bytes = encode_stake_distribution(&pools);
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(d.map().unwrap(), Some(2));
+        assert_eq!(d.bytes().unwrap(), &[1; 28]);
+        d.array().unwrap();
+        assert_eq!(d.tag().unwrap(), Tag::new(RATIONAL_TAG));
+        d.array().unwrap();
+        assert_eq!((d.u64().unwrap(), d.u64().unwrap()), (1, 4));
+        assert_eq!(d.bytes().unwrap(), &[2; 32]);
+        assert_eq!(d.datatype().unwrap(), Type::Bytes);
+    }
+
+    #[test]
+    fn era_mismatch_names_both_eras() {
+        let bytes = encode_era_mismatch(5, Era::Conway);
+        let mut d = Decoder::new(&bytes);
+        assert_eq!(d.array().unwrap(), Some(2));
+        d.array().unwrap();
+        assert_eq!((d.u8().unwrap(), d.str().unwrap()), (5, "Babbage"));
+        d.array().unwrap();
+        assert_eq!((d.u8().unwrap(), d.str().unwrap()), (6, "Conway"));
+    }
+}
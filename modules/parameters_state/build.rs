@@ -76,6 +76,17 @@ fn main() {
     download(preview, "alonzo", "preview-alonzo-genesis.json", &vec![]);
     download(preview, "conway", "preview-conway-genesis.json", &vec![]);
 
+    let preprod = "https://book.world.dev.cardano.org/environments/preprod";
+    download(preprod, "byron", "preprod-byron-genesis.json", &vec![]);
+    download(
+        preprod,
+        "shelley",
+        "preprod-shelley-genesis.json",
+        &shelley_fix,
+    );
+    download(preprod, "alonzo", "preprod-alonzo-genesis.json", &vec![]);
+    download(preprod, "conway", "preprod-conway-genesis.json", &vec![]);
+
     let sancho =
         "https://raw.githubusercontent.com/Hornan7/SanchoNet-Tutorials/refs/heads/main/genesis";
     download(sancho, "byron", "sanchonet-byron-genesis.json", &vec![]);
@@ -0,0 +1,106 @@
+//! Field-level differences between two parameter sets, for the audit log
+
+use acropolis_common::{
+    protocol_params::ProtocolParams, queries::parameters::ParameterFieldChange,
+};
+use anyhow::Result;
+use serde_json::Value;
+
+/// List every leaf of `old` and `new` which differs. Nested structures are compared
+/// field by field; arrays (cost models) are compared as a whole.
+pub fn diff_params(
+    old: &ProtocolParams,
+    new: &ProtocolParams,
+) -> Result<Vec<ParameterFieldChange>> {
+    let mut changes = Vec::new();
+    diff_values(
+        "",
+        Some(&serde_json::to_value(old)?),
+        Some(&serde_json::to_value(new)?),
+        &mut changes,
+    );
+    Ok(changes)
+}
+
+fn diff_values(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<ParameterFieldChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys: Vec<_> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_values(&path, old.get(key), new.get(key), changes);
+            }
+        }
+        _ if old == new => {}
+        // A whole section appearing (e.g. at an era's genesis) is a single change
+        _ => changes.push(ParameterFieldChange {
+            path: path.to_string(),
+            old: old.filter(|v| !v.is_null()).cloned(),
+            new: new.filter(|v| !v.is_null()).cloned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn diff(old: Value, new: Value) -> Vec<(String, Option<Value>, Option<Value>)> {
+        let mut changes = Vec::new();
+        diff_values("", Some(&old), Some(&new), &mut changes);
+        changes.into_iter().map(|c| (c.path, c.old, c.new)).collect()
+    }
+
+    #[test]
+    fn nested_fields_are_reported_by_path() {
+        let old = json!({"shelley": {"protocol_params": {"minfee_a": 44, "minfee_b": 155381}}});
+        let new = json!({"shelley": {"protocol_params": {"minfee_a": 45, "minfee_b": 155381}}});
+        assert_eq!(
+            diff(old, new),
+            vec![(
+                "shelley.protocol_params.minfee_a".to_string(),
+                Some(json!(44)),
+                Some(json!(45))
+            )]
+        );
+    }
+
+    #[test]
+    fn new_sections_and_arrays_are_single_changes() {
+        let old = json!({"alonzo": null, "babbage": {"cost_model": [1, 2, 3]}});
+        let new = json!({"alonzo": {"max_value_size": 5000}, "babbage": {"cost_model": [1, 2, 4]}});
+        assert_eq!(
+            diff(old, new),
+            vec![
+                (
+                    "alonzo".to_string(),
+                    None,
+                    Some(json!({"max_value_size": 5000}))
+                ),
+                (
+                    "babbage.cost_model".to_string(),
+                    Some(json!([1, 2, 3])),
+                    Some(json!([1, 2, 4]))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_params_have_no_changes() {
+        let params = ProtocolParams::default();
+        assert!(diff_params(&params, &params).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Genesis key delegations made by certificate, before Conway
+//!
+//! A genesis key delegation certificate hands a genesis key's slots in the overlay
+//! schedule to a new delegate and VRF key. The ledger holds each one back for the
+//! stability window from the certificate's slot, then adopts it. The validators take
+//! the delegates from the protocol parameters, which change at epoch boundaries, so
+//! delegations are adopted at the first boundary after they are due.
+
+use acropolis_common::{
+    messages::TxCertificatesMessage, BlockInfo, GenesisDelegate, GenesisKeyhash, TxCertificate,
+};
+use std::collections::BTreeMap;
+
+/// Delegations certified but not yet adopted
+#[derive(Default, Clone)]
+pub struct GenesisDelegations {
+    /// By the slot they are due, then genesis key
+    future: BTreeMap<(u64, GenesisKeyhash), GenesisDelegate>,
+}
+
+impl GenesisDelegations {
+    /// Hold back the delegations certified in a block for `stability_window` slots
+    pub fn handle_certificates(
+        &mut self,
+        block: &BlockInfo,
+        certs: &TxCertificatesMessage,
+        stability_window: u64,
+    ) {
+        for cert in &certs.certificates {
+            if let TxCertificate::GenesisKeyDelegation(delegation) = &cert.cert {
+                self.future.insert(
+                    (block.slot + stability_window, delegation.genesis_hash),
+                    GenesisDelegate {
+                        delegate: *delegation.genesis_delegate_hash,
+                        vrf: delegation.vrf_key_hash,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Take the delegations due by `slot`, in the order they fell due - so a later
+    /// one for the same genesis key replaces an earlier one
+    pub fn adopt(&mut self, slot: u64) -> Vec<(GenesisKeyhash, GenesisDelegate)> {
+        let mut adopted = Vec::new();
+        while let Some(entry) = self.future.first_entry() {
+            if entry.key().0 > slot {
+                break;
+            }
+            let ((_, genesis_key), delegate) = entry.remove_entry();
+            adopted.push((genesis_key, delegate));
+        }
+        adopted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{
+        hash::Hash, BlockHash, BlockIntent, BlockStatus, Era, GenesisKeyDelegation, PoolId,
+        TxCertificateWithPos, TxIdentifier, VrfKeyHash,
+    };
+
+    fn block(slot: u64) -> BlockInfo {
+        BlockInfo {
+            status: BlockStatus::Immutable,
+            intent: BlockIntent::Apply,
+            slot,
+            number: slot,
+            hash: BlockHash::default(),
+            epoch: 300,
+            epoch_slot: 0,
+            new_epoch: false,
+            is_new_era: false,
+            timestamp: 0,
+            era: Era::Shelley,
+            tip_slot: None,
+        }
+    }
+
+    fn delegate(n: u8) -> GenesisDelegate {
+        GenesisDelegate {
+            delegate: Hash::new([n; 28]),
+            vrf: VrfKeyHash::new(Hash::new([n; 32])),
+        }
+    }
+
+    fn certs(delegations: &[(u8, u8)]) -> TxCertificatesMessage {
+        TxCertificatesMessage {
+            certificates: delegations
+                .iter()
+                .enumerate()
+                .map(|(index, (genesis, delegate))| TxCertificateWithPos {
+                    cert: TxCertificate::GenesisKeyDelegation(GenesisKeyDelegation {
+                        genesis_hash: Hash::new([*genesis; 28]),
+                        genesis_delegate_hash: PoolId::new(Hash::new([*delegate; 28])),
+                        vrf_key_hash: VrfKeyHash::new(Hash::new([*delegate; 32])),
+                    }),
+                    tx_identifier: TxIdentifier::default(),
+                    cert_index: index as u64,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn delegations_are_adopted_once_due() {
+        let mut delegations = GenesisDelegations::default();
+        delegations.handle_certificates(&block(100), &certs(&[(1, 10)]), 50);
+        delegations.handle_certificates(&block(120), &certs(&[(2, 20), (1, 11)]), 50);
+
+        assert!(delegations.adopt(149).is_empty());
+
+        assert_eq!(
+            delegations.adopt(150),
+            vec![(Hash::new([1; 28]), delegate(10))]
+        );
+
+        // The later delegation of key 1 comes last, so it wins
+        assert_eq!(
+            delegations.adopt(1000),
+            vec![
+                (Hash::new([1; 28]), delegate(11)),
+                (Hash::new([2; 28]), delegate(20)),
+            ]
+        );
+        assert!(delegations.adopt(u64::MAX).is_empty());
+    }
+}
@@ -13,7 +13,7 @@ use pallas::ledger::configs::*;
 use serde::Deserialize;
 use std::collections::HashMap;
 
-const PREDEFINED_GENESIS: [(&str, Era, &[u8]); 12] = [
+const PREDEFINED_GENESIS: [(&str, Era, &[u8]); 16] = [
     (
         "sanchonet",
         Era::Byron,
@@ -34,6 +34,26 @@ const PREDEFINED_GENESIS: [(&str, Era, &[u8]); 12] = [
         Era::Conway,
         include_bytes!("../downloads/sanchonet-conway-genesis.json"),
     ),
+    (
+        "preprod",
+        Era::Byron,
+        include_bytes!("../downloads/preprod-byron-genesis.json"),
+    ),
+    (
+        "preprod",
+        Era::Shelley,
+        include_bytes!("../downloads/preprod-shelley-genesis.json"),
+    ),
+    (
+        "preprod",
+        Era::Alonzo,
+        include_bytes!("../downloads/preprod-alonzo-genesis.json"),
+    ),
+    (
+        "preprod",
+        Era::Conway,
+        include_bytes!("../downloads/preprod-conway-genesis.json"),
+    ),
     (
         "preview",
         Era::Byron,
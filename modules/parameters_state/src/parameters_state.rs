@@ -1,20 +1,29 @@
 //! Acropolis Parameter State module for Caryatid
 //! Accepts certificate events and derives the Governance State in memory
 
-use acropolis_common::caryatid::RollbackWrapper;
+use acropolis_common::caryatid::{PrimaryRead, RollbackWrapper};
 use acropolis_common::configuration::{get_bool_flag, get_string_flag, StartupMode};
 use acropolis_common::declare_cardano_reader;
+use acropolis_common::genesis_values::Network;
 use acropolis_common::messages::{
-    GovernanceOutcomesMessage, SnapshotMessage, SnapshotStateMessage, StateTransitionMessage,
+    GovernanceOutcomesMessage, GovernanceProceduresMessage, SnapshotMessage, SnapshotStateMessage,
+    StateTransitionMessage, TxCertificatesMessage,
 };
 use acropolis_common::queries::errors::QueryError;
 use acropolis_common::{
-    messages::{CardanoMessage, Message, ProtocolParamsMessage, StateQuery, StateQueryResponse},
+    epoch_barrier::EpochBarrierAcker,
+    messages::{
+        CardanoMessage, CostModelsMessage, EraTransitionMessage, Message, ProtocolParamsMessage,
+        StateQuery, StateQueryResponse,
+    },
+    metrics,
     queries::parameters::{
         ParametersStateQuery, ParametersStateQueryResponse, DEFAULT_PARAMETERS_QUERY_TOPIC,
     },
+    readiness,
+    state_digest::{StateDigest, StateDigestPublisher},
     state_history::{StateHistory, StateHistoryStore},
-    BlockInfo,
+    BlockInfo, GenesisDelegate, GenesisKeyhash,
 };
 use anyhow::{bail, Result};
 use caryatid_sdk::{message_bus::Subscription, module, Context};
@@ -24,18 +33,32 @@ use tokio::sync::Mutex;
 use tracing::{debug, error, info, info_span, Instrument};
 
 mod alonzo_genesis;
+mod change_log;
+mod genesis_delegations;
 mod genesis_params;
 mod parameters_updater;
+mod pending_updates;
 mod state;
+use genesis_delegations::GenesisDelegations;
 use parameters_updater::ParametersUpdater;
+use pending_updates::PendingUpdates;
 use state::State;
 
 const CONFIG_ENACT_STATE_TOPIC: (&str, &str) = ("enact-state-topic", "cardano.enact.state");
 const CONFIG_PROTOCOL_PARAMETERS_TOPIC: (&str, &str) =
     ("publish-parameters-topic", "cardano.protocol.parameters");
-// TODO: Read network name from genesis message
-const CONFIG_NETWORK_NAME: (&str, &str) = ("startup.network-name", "mainnet");
-const CONFIG_STORE_HISTORY: (&str, bool) = ("store-history", false);
+/// Published only at epoch boundaries where a Plutus cost model changes
+const CONFIG_COST_MODELS_TOPIC: (&str, &str) = ("publish-cost-models-topic", "cardano.cost.models");
+/// Published only at epoch boundaries where the major protocol version changes
+const CONFIG_ERA_TRANSITION_TOPIC: (&str, &str) =
+    ("publish-era-transition-topic", "cardano.era.transition");
+/// Follow submitted update proposals and parameter change actions until they are decided
+const CONFIG_TRACK_PENDING_UPDATES: (&str, bool) = ("track-pending-updates", false);
+const CONFIG_GOVERNANCE_TOPIC: (&str, &str) = ("governance-topic", "cardano.governance");
+/// Follow genesis key delegation certificates, adopting them into the parameters
+const CONFIG_TRACK_GENESIS_DELEGATIONS: (&str, bool) = ("track-genesis-delegations", false);
+const CONFIG_CERTIFICATES_TOPIC: (&str, &str) =
+    ("certificates-subscribe-topic", "cardano.certificates");
 /// Topic for receiving bootstrap data when starting from a CBOR dump snapshot
 const CONFIG_SNAPSHOT_SUBSCRIBE_TOPIC: (&str, &str) =
     ("snapshot-subscribe-topic", "cardano.snapshot");
@@ -47,6 +70,20 @@ declare_cardano_reader!(
     GovernanceOutcomes,
     GovernanceOutcomesMessage
 );
+declare_cardano_reader!(
+    GovProceduresReader,
+    CONFIG_GOVERNANCE_TOPIC.0,
+    CONFIG_GOVERNANCE_TOPIC.1,
+    GovernanceProcedures,
+    GovernanceProceduresMessage
+);
+declare_cardano_reader!(
+    CertificatesReader,
+    CONFIG_CERTIFICATES_TOPIC.0,
+    CONFIG_CERTIFICATES_TOPIC.1,
+    TxCertificates,
+    TxCertificatesMessage
+);
 
 /// Parameters State module
 #[module(
@@ -60,19 +97,37 @@ struct ParametersStateConfig {
     pub context: Arc<Context<Message>>,
     pub network_name: String,
     pub protocol_parameters_topic: String,
+    pub cost_models_topic: String,
+    pub era_transition_topic: String,
     pub parameters_query_topic: String,
-    pub store_history: bool,
+    pub track_pending_updates: bool,
+    pub track_genesis_delegations: bool,
+    pub state_digest: Option<StateDigestPublisher>,
+    pub epoch_barrier: Option<EpochBarrierAcker>,
 }
 
 impl ParametersStateConfig {
-    pub fn new(context: Arc<Context<Message>>, config: &Arc<Config>) -> Arc<Self> {
-        Arc::new(Self {
-            context,
-            network_name: get_string_flag(config, CONFIG_NETWORK_NAME),
+    pub fn new(context: Arc<Context<Message>>, config: &Arc<Config>) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            network_name: Network::from_config(config)?.name().to_string(),
             protocol_parameters_topic: get_string_flag(config, CONFIG_PROTOCOL_PARAMETERS_TOPIC),
+            cost_models_topic: get_string_flag(config, CONFIG_COST_MODELS_TOPIC),
+            era_transition_topic: get_string_flag(config, CONFIG_ERA_TRANSITION_TOPIC),
             parameters_query_topic: get_string_flag(config, DEFAULT_PARAMETERS_QUERY_TOPIC),
-            store_history: get_bool_flag(config, CONFIG_STORE_HISTORY),
-        })
+            track_pending_updates: get_bool_flag(config, CONFIG_TRACK_PENDING_UPDATES),
+            track_genesis_delegations: get_bool_flag(config, CONFIG_TRACK_GENESIS_DELEGATIONS),
+            state_digest: StateDigestPublisher::from_config(
+                context.clone(),
+                config,
+                "parameters-state",
+            ),
+            epoch_barrier: EpochBarrierAcker::from_config(
+                context.clone(),
+                config,
+                "parameters-state",
+            ),
+            context,
+        }))
     }
 }
 
@@ -100,11 +155,60 @@ impl ParametersState {
         Ok(())
     }
 
+    /// Publish an event raised by a change of parameters at an epoch boundary
+    async fn publish_event(
+        config: &Arc<ParametersStateConfig>,
+        topic: &str,
+        block: &BlockInfo,
+        message: CardanoMessage,
+    ) -> Result<()> {
+        let packed_message = Arc::new(Message::Cardano((block.clone(), message)));
+        config.context.publish(topic, packed_message).await
+    }
+
+    /// Read the certificates up to and including `block`, returning the genesis key
+    /// delegations due by its slot
+    async fn catch_up_genesis_delegations(
+        reader: &mut CertificatesReader,
+        history: &mut StateHistory<GenesisDelegations>,
+        block: &BlockInfo,
+        stability_window: u64,
+    ) -> Result<Vec<(GenesisKeyhash, GenesisDelegate)>> {
+        loop {
+            match reader.read_with_rollbacks().await? {
+                RollbackWrapper::Normal((certs_block, certs)) => {
+                    let mut delegations = history.get_or_init_with(GenesisDelegations::default);
+                    delegations.handle_certificates(&certs_block, &certs, stability_window);
+
+                    let caught_up = certs_block.number >= block.number;
+                    let adopted = if caught_up {
+                        delegations.adopt(block.slot)
+                    } else {
+                        Vec::new()
+                    };
+                    history.commit(certs_block.number, delegations);
+                    if caught_up {
+                        return Ok(adopted);
+                    }
+                }
+                RollbackWrapper::Rollback((certs_block, _)) => {
+                    history.get_rolled_back_state(certs_block.number);
+                }
+            }
+        }
+    }
+
     async fn run(
         config: Arc<ParametersStateConfig>,
         history: Arc<Mutex<StateHistory<State>>>,
         mut gov_reader: GovOutcomesReader,
+        mut certs_reader: Option<CertificatesReader>,
     ) -> Result<()> {
+        let mut delegations = StateHistory::<GenesisDelegations>::new(
+            "ParameterState genesis delegations",
+            StateHistoryStore::default_block_store(),
+        );
+
         // Process the snapshot messages first to bootstrap state if needed
 
         loop {
@@ -119,16 +223,93 @@ impl ParametersState {
                         };
 
                         if block.new_epoch {
+                            // Digest of the state as the previous epoch ended
+                            if let Some(state_digest) =
+                                config.state_digest.as_ref().filter(|_| block.epoch > 0)
+                            {
+                                state_digest.publish(&block, state.state_digest()).await;
+                            }
+
                             // Get current params
                             let current_params = state.current_params.get_params();
 
+                            // Catch up on the certificates, for the delegations now due
+                            let genesis_delegations = match certs_reader.as_mut() {
+                                Some(reader) => {
+                                    let stability_window = current_params
+                                        .shelley
+                                        .as_ref()
+                                        .map(|shelley| shelley.stability_window())
+                                        .unwrap_or(0);
+                                    Self::catch_up_genesis_delegations(
+                                        reader,
+                                        &mut delegations,
+                                        &block,
+                                        stability_window,
+                                    )
+                                    .await?
+                                }
+                                None => Vec::new(),
+                            };
+
                             // Process GovOutcomes message on epoch transition
-                            let new_params =
-                                state.handle_enact_state(&block.era, gov.as_ref()).await?;
+                            let new_params = state
+                                .handle_enact_state(
+                                    block.epoch,
+                                    &block.era,
+                                    gov.as_ref(),
+                                    &genesis_delegations,
+                                )
+                                .await?;
 
                             // Publish protocol params message
                             Self::publish_update(&config, block.as_ref(), new_params.clone())?;
 
+                            let cost_models = new_params.params.cost_models();
+                            let changed =
+                                cost_models.changed_languages(&current_params.cost_models());
+                            if !changed.is_empty() {
+                                info!("Epoch {}: cost models changed for {changed:?}", block.epoch);
+                                Self::publish_event(
+                                    &config,
+                                    &config.cost_models_topic,
+                                    block.as_ref(),
+                                    CardanoMessage::CostModels(CostModelsMessage {
+                                        cost_models,
+                                        changed,
+                                    }),
+                                )
+                                .await?;
+                            }
+
+                            let old_protocol_major = current_params.major_protocol_version();
+                            let new_protocol_major = new_params.params.major_protocol_version();
+                            if old_protocol_major != new_protocol_major {
+                                let transition = EraTransitionMessage {
+                                    old_era: current_params.era(),
+                                    new_era: new_params.params.era(),
+                                    epoch: block.epoch,
+                                    slot: block.slot - block.epoch_slot,
+                                    old_protocol_major,
+                                    new_protocol_major,
+                                };
+                                info!(
+                                    "Epoch {}: protocol version {:?} => {:?} ({} => {})",
+                                    block.epoch,
+                                    old_protocol_major,
+                                    new_protocol_major,
+                                    transition.old_era,
+                                    transition.new_era
+                                );
+                                Self::publish_event(
+                                    &config,
+                                    &config.era_transition_topic,
+                                    block.as_ref(),
+                                    CardanoMessage::EraTransition(transition),
+                                )
+                                .await?;
+                            }
+
                             // Commit state on params change
                             if current_params != new_params.params {
                                 debug!(
@@ -139,6 +320,12 @@ impl ParametersState {
                                 let mut h = history.lock().await;
                                 h.commit(block.epoch, state);
                             }
+
+                            if let Some(acker) =
+                                config.epoch_barrier.as_ref().filter(|_| block.epoch > 0)
+                            {
+                                acker.ack(&block).await;
+                            }
                         }
 
                         Ok::<(), anyhow::Error>(())
@@ -148,33 +335,86 @@ impl ParametersState {
                 }
                 RollbackWrapper::Rollback((_, message)) => {
                     // forward the rollback downstream
-                    config.context.publish(&config.protocol_parameters_topic, message).await?;
+                    config
+                        .context
+                        .publish(&config.protocol_parameters_topic, message.clone())
+                        .await?;
+                    config.context.publish(&config.cost_models_topic, message.clone()).await?;
+                    config.context.publish(&config.era_transition_topic, message).await?;
+                }
+            }
+        }
+    }
+
+    /// Follow the governance procedures block by block, keeping the updates which
+    /// have not yet been decided at an epoch boundary
+    async fn run_pending_updates(
+        pending: Arc<Mutex<StateHistory<PendingUpdates>>>,
+        mut procedures_reader: GovProceduresReader,
+        mut outcomes_reader: GovOutcomesReader,
+    ) -> Result<()> {
+        loop {
+            let primary = PrimaryRead::from_read(procedures_reader.read_with_rollbacks().await?);
+            let block = primary.block_info();
+
+            let mut state = if primary.is_rollback() {
+                pending.lock().await.get_rolled_back_state(block.number)
+            } else {
+                pending.lock().await.get_or_init_with(PendingUpdates::default)
+            };
+
+            // Governance publishes outcomes (or the rollback) alongside these blocks
+            if primary.is_rollback() || block.new_epoch {
+                match outcomes_reader.read_with_rollbacks().await? {
+                    RollbackWrapper::Normal((block, outcomes)) => {
+                        state.handle_outcomes(block.epoch, &outcomes)
+                    }
+                    RollbackWrapper::Rollback(_) => {}
                 }
             }
+
+            if let Some(procedures) = primary.message() {
+                state.handle_procedures(block, procedures);
+                pending.lock().await.commit(block.number, state);
+            }
         }
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
-        let cfg = ParametersStateConfig::new(context.clone(), &config);
+        readiness::declare_startup(context.clone(), &config, "parameters-state").await?;
+
+        let cfg = ParametersStateConfig::new(context.clone(), &config)?;
         let gov_reader = GovOutcomesReader::new(&context, &config).await?;
+        let certs_reader =
+            CertificatesReader::new_opt(cfg.track_genesis_delegations, &context, &config).await?;
 
-        let store_history = cfg.store_history;
+        // Initalize state history - past epochs' parameters are kept in the state
+        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
+            "ParameterState",
+            StateHistoryStore::default_epoch_store(),
+        )));
 
-        // Initalize state history
-        let history = if store_history {
-            Arc::new(Mutex::new(StateHistory::<State>::new(
-                "ParameterState",
-                StateHistoryStore::Unbounded,
-            )))
+        let query_state = history.clone();
+
+        let pending = if cfg.track_pending_updates {
+            let pending = Arc::new(Mutex::new(StateHistory::<PendingUpdates>::new(
+                "ParameterState pending updates",
+                StateHistoryStore::default_block_store(),
+            )));
+            let procedures_reader = GovProceduresReader::new(&context, &config).await?;
+            let outcomes_reader = GovOutcomesReader::new(&context, &config).await?;
+
+            let pending_run = pending.clone();
+            context.run(async move {
+                Self::run_pending_updates(pending_run, procedures_reader, outcomes_reader)
+                    .await
+                    .unwrap_or_else(|e| error!("Pending updates tracking failed: {e}"));
+            });
+            Some(pending)
         } else {
-            Arc::new(Mutex::new(StateHistory::new(
-                "ParameterState",
-                StateHistoryStore::default_epoch_store(),
-            )))
+            None
         };
 
-        let query_state = history.clone();
-
         // Subscribe for snapshot messages, if booting from snapshot
         let snapshot_subscribe_topic = config
             .get_string(CONFIG_SNAPSHOT_SUBSCRIBE_TOPIC.0)
@@ -233,9 +473,13 @@ impl ParametersState {
         }
 
         // Handle parameters queries
+        let query_metrics = metrics::QueryMetrics::new("parameters-state");
         context.handle(&cfg.parameters_query_topic, move |message| {
             let history = query_state.clone();
+            let pending = pending.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Parameters(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Parameters(
                         ParametersStateQueryResponse::Error(QueryError::internal_error(
@@ -252,26 +496,55 @@ impl ParametersState {
                         )
                     }
                     ParametersStateQuery::GetEpochParameters { epoch_number } => {
-                        if !store_history {
-                            ParametersStateQueryResponse::Error(QueryError::storage_disabled(
-                                "Historical protocol parameter",
-                            ))
-                        } else {
-                            match lock.get_at_or_before(*epoch_number) {
-                                Some(state) => ParametersStateQueryResponse::EpochParameters(
-                                    state.current_params.get_params(),
-                                ),
-                                None => ParametersStateQueryResponse::Error(QueryError::not_found(
-                                    format!("Epoch {epoch_number} not found in history"),
-                                )),
-                            }
+                        match lock.get_current_state().params_for_epoch(*epoch_number) {
+                            Some(params) => ParametersStateQueryResponse::EpochParameters(params),
+                            None => ParametersStateQueryResponse::Error(QueryError::not_found(
+                                format!("Epoch {epoch_number} not found in history"),
+                            )),
                         }
                     }
+                    ParametersStateQuery::GetCostModels => {
+                        ParametersStateQueryResponse::CostModels(
+                            lock.get_current_state().current_params.get_params().cost_models(),
+                        )
+                    }
                     ParametersStateQuery::GetNetworkName => {
                         ParametersStateQueryResponse::NetworkName(
                             lock.get_current_state().network_name.clone(),
                         )
                     }
+                    ParametersStateQuery::GetPendingParameterUpdates
+                    | ParametersStateQuery::GetFutureParameters => match pending {
+                        Some(pending) => {
+                            let current = lock.get_current_state().current_params;
+                            let update_quorum =
+                                current.get_params().shelley.map(|s| s.update_quorum).unwrap_or(0);
+                            let pending = pending.lock().await.get_current_state();
+
+                            if matches!(query, ParametersStateQuery::GetFutureParameters) {
+                                match pending.future_parameters(&current, update_quorum) {
+                                    Ok(future) => {
+                                        ParametersStateQueryResponse::FutureParameters(future)
+                                    }
+                                    Err(e) => ParametersStateQueryResponse::Error(
+                                        QueryError::internal_error(e.to_string()),
+                                    ),
+                                }
+                            } else {
+                                ParametersStateQueryResponse::PendingParameterUpdates(
+                                    pending.get_pending(update_quorum),
+                                )
+                            }
+                        }
+                        None => ParametersStateQueryResponse::Error(QueryError::storage_disabled(
+                            "Pending parameter update",
+                        )),
+                    },
+                    ParametersStateQuery::GetParameterChangeLog => {
+                        ParametersStateQueryResponse::ParameterChangeLog(
+                            lock.get_current_state().change_log,
+                        )
+                    }
                 };
                 Arc::new(Message::StateQueryResponse(StateQueryResponse::Parameters(
                     response,
@@ -281,7 +554,7 @@ impl ParametersState {
 
         // Start run task
         tokio::spawn(async move {
-            Self::run(cfg_clone, history_clone, gov_reader)
+            Self::run(cfg_clone, history_clone, gov_reader, certs_reader)
                 .await
                 .unwrap_or_else(|e| error!("Failed: {e}"));
         });
@@ -3,8 +3,8 @@ use acropolis_common::protocol_params::{
     AlonzoParams, BabbageParams, ConwayParams, ProtocolParams, ShelleyProtocolParams,
 };
 use acropolis_common::{
-    AlonzoBabbageVotingOutcome, Committee, CommitteeChange, EnactStateElem, Era,
-    GovernanceOutcomeVariant, ProtocolParamUpdate,
+    AlonzoBabbageVotingOutcome, Committee, CommitteeChange, EnactStateElem, Era, GenesisDelegate,
+    GenesisKeyhash, GovernanceOutcomeVariant, ProtocolParamUpdate,
 };
 use anyhow::{anyhow, bail, Result};
 use tracing::{debug, error};
@@ -206,7 +206,7 @@ impl ParametersUpdater {
     // General update procs
     //
 
-    fn update_params(&mut self, pu: &ProtocolParamUpdate) -> Result<()> {
+    pub fn update_params(&mut self, pu: &ProtocolParamUpdate) -> Result<()> {
         self.update_alonzo_params(pu)?;
         self.update_shelley_params(pu)?;
         self.update_babbage_params(pu)?;
@@ -324,6 +324,23 @@ impl ParametersUpdater {
         self.update_params(&params)
     }
 
+    /// Hand genesis keys to the delegates certified for them
+    pub fn apply_genesis_delegations(
+        &mut self,
+        delegations: &[(GenesisKeyhash, GenesisDelegate)],
+    ) -> Result<()> {
+        if delegations.is_empty() {
+            return Ok(());
+        }
+        let Some(shelley) = self.params.shelley.as_mut() else {
+            bail!("Genesis key delegations before Shelley parameters are set");
+        };
+        for (genesis_key, delegate) in delegations {
+            shelley.gen_delegs.0.insert(*genesis_key, delegate.clone());
+        }
+        Ok(())
+    }
+
     pub fn get_params(&self) -> ProtocolParams {
         self.params.clone()
     }
@@ -0,0 +1,183 @@
+//! Parameter updates which have been submitted but not yet enacted
+//!
+//! Pre-Conway update proposals are keyed by the epoch at whose start they apply,
+//! one per genesis key (a later proposal from the same key replaces the earlier one).
+//! Conway parameter change actions stay pending until governance reports an outcome
+//! for them, whether enacted or expired.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ParametersUpdater;
+use acropolis_common::{
+    messages::{GovernanceOutcomesMessage, GovernanceProceduresMessage},
+    queries::parameters::{
+        FutureParameters, PendingGovernanceAction, PendingParameterUpdates, PendingUpdateProposal,
+    },
+    BlockInfo, GenesisKeyhash, GovActionId, GovernanceAction, ProtocolParamUpdate,
+};
+use anyhow::Result;
+
+#[derive(Default, Clone)]
+pub struct PendingUpdates {
+    /// Epoch of the last block seen
+    epoch: u64,
+
+    /// Enactment epoch => proposals by genesis key
+    update_proposals: BTreeMap<u64, BTreeMap<GenesisKeyhash, Box<ProtocolParamUpdate>>>,
+
+    /// Parameter change actions by id, with the epoch they were submitted in
+    governance_actions: HashMap<GovActionId, (u64, Box<ProtocolParamUpdate>)>,
+}
+
+impl PendingUpdates {
+    /// Record the updates submitted in a block
+    pub fn handle_procedures(&mut self, block: &BlockInfo, msg: &GovernanceProceduresMessage) {
+        self.epoch = block.epoch;
+        for update in &msg.alonzo_babbage_updates {
+            let proposals = self.update_proposals.entry(update.enactment_epoch + 1).or_default();
+            for (key, parameter_update) in &update.proposals {
+                proposals.insert(*key, parameter_update.clone());
+            }
+        }
+
+        for proposal in &msg.proposal_procedures {
+            if let GovernanceAction::ParameterChange(action) = &proposal.gov_action {
+                self.governance_actions.insert(
+                    proposal.gov_action_id.clone(),
+                    (block.epoch, action.protocol_param_update.clone()),
+                );
+            }
+        }
+    }
+
+    /// Drop whatever was decided at the start of `epoch`
+    pub fn handle_outcomes(&mut self, epoch: u64, msg: &GovernanceOutcomesMessage) {
+        self.update_proposals.retain(|enactment_epoch, _| *enactment_epoch > epoch);
+
+        for outcome in &msg.conway_outcomes {
+            self.governance_actions.remove(&outcome.voting.procedure.gov_action_id);
+        }
+    }
+
+    pub fn get_pending(&self, update_quorum: u32) -> PendingParameterUpdates {
+        let mut update_proposals = Vec::new();
+        for (enactment_epoch, proposals) in &self.update_proposals {
+            // Genesis keys proposing identical updates vote together
+            let mut grouped: Vec<PendingUpdateProposal> = Vec::new();
+            for (key, parameter_update) in proposals {
+                match grouped.iter_mut().find(|p| &p.parameter_update == parameter_update) {
+                    Some(pending) => pending.proposers.push(*key),
+                    None => grouped.push(PendingUpdateProposal {
+                        enactment_epoch: *enactment_epoch,
+                        proposers: vec![*key],
+                        update_quorum,
+                        parameter_update: parameter_update.clone(),
+                    }),
+                }
+            }
+            update_proposals.extend(grouped);
+        }
+
+        let mut governance_actions: Vec<_> = self
+            .governance_actions
+            .iter()
+            .map(
+                |(action_id, (proposed_epoch, parameter_update))| PendingGovernanceAction {
+                    action_id: action_id.clone(),
+                    proposed_epoch: *proposed_epoch,
+                    parameter_update: parameter_update.clone(),
+                },
+            )
+            .collect();
+        governance_actions.sort_by(|a, b| a.action_id.cmp(&b.action_id));
+
+        PendingParameterUpdates {
+            update_proposals,
+            governance_actions,
+        }
+    }
+
+    /// Current parameters with the update proposals for the next epoch which already
+    /// reach quorum applied
+    pub fn future_parameters(
+        &self,
+        current: &ParametersUpdater,
+        update_quorum: u32,
+    ) -> Result<FutureParameters> {
+        let epoch = self.epoch + 1;
+        let mut updater = current.clone();
+        for proposal in self.get_pending(update_quorum).update_proposals {
+            if proposal.enactment_epoch == epoch && proposal.reaches_quorum() {
+                updater.update_params(&proposal.parameter_update)?;
+            }
+        }
+
+        Ok(FutureParameters {
+            epoch,
+            parameters: updater.get_params(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{
+        messages::GovernanceProceduresMessage, AlonzoBabbageUpdateProposal, BlockHash, BlockIntent,
+        BlockStatus, Era,
+    };
+
+    fn block(epoch: u64) -> BlockInfo {
+        BlockInfo {
+            status: BlockStatus::Immutable,
+            intent: BlockIntent::Apply,
+            slot: epoch * 100,
+            number: epoch * 10,
+            hash: BlockHash::default(),
+            epoch,
+            epoch_slot: 0,
+            new_epoch: false,
+            is_new_era: false,
+            timestamp: 0,
+            era: Era::Shelley,
+            tip_slot: None,
+        }
+    }
+
+    fn update(minfee_a: u64) -> Box<ProtocolParamUpdate> {
+        Box::new(ProtocolParamUpdate {
+            minfee_a: Some(minfee_a),
+            ..ProtocolParamUpdate::default()
+        })
+    }
+
+    fn proposals(epoch: u64, votes: &[(u8, u64)]) -> GovernanceProceduresMessage {
+        GovernanceProceduresMessage {
+            alonzo_babbage_updates: vec![AlonzoBabbageUpdateProposal {
+                proposals: votes
+                    .iter()
+                    .map(|(key, minfee_a)| (GenesisKeyhash::new([*key; 28]), update(*minfee_a)))
+                    .collect(),
+                enactment_epoch: epoch,
+            }],
+            ..GovernanceProceduresMessage::default()
+        }
+    }
+
+    #[test]
+    fn update_proposals_are_grouped_and_dropped_once_decided() {
+        let mut pending = PendingUpdates::default();
+        pending.handle_procedures(&block(5), &proposals(5, &[(1, 45), (2, 45), (3, 50)]));
+        // Key 3 changes its mind
+        pending.handle_procedures(&block(5), &proposals(5, &[(3, 45)]));
+
+        let updates = pending.get_pending(3).update_proposals;
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].enactment_epoch, 6);
+        assert_eq!(updates[0].proposers.len(), 3);
+        assert!(updates[0].reaches_quorum());
+
+        pending.handle_outcomes(6, &GovernanceOutcomesMessage::default());
+        assert!(pending.get_pending(3).update_proposals.is_empty());
+    }
+}
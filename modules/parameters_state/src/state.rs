@@ -1,21 +1,29 @@
 //! Acropolis Protocol Params: State storage
 
-use crate::ParametersUpdater;
+use crate::{change_log::diff_params, ParametersUpdater};
 use acropolis_common::{
     messages::{
         GovernanceOutcomesMessage, ProtocolParametersBootstrapMessage, ProtocolParamsMessage,
     },
-    AlonzoBabbageVotingOutcome, Era, GovernanceOutcomeVariant,
+    protocol_params::ProtocolParams,
+    queries::parameters::{ParameterChangeCause, ParameterChangeRecord},
+    state_digest::{SetDigest, StateDigest},
+    AlonzoBabbageVotingOutcome, Era, GenesisDelegate, GenesisKeyhash, GovernanceOutcomeVariant,
 };
 use anyhow::Result;
-use std::ops::RangeInclusive;
-use tracing::{debug, info};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+use tracing::{debug, error, info};
 
 #[derive(Default, Clone)]
 pub struct State {
     pub network_name: String,
     pub current_params: ParametersUpdater,
     pub current_era: Option<Era>,
+    /// Every parameter change applied at an epoch boundary, oldest first
+    pub change_log: Vec<ParameterChangeRecord>,
+    /// Parameters from each epoch they changed in, starting with the first epoch
+    /// seen, whether from genesis or a snapshot
+    pub epoch_params: BTreeMap<u64, ProtocolParams>,
 }
 
 impl State {
@@ -24,6 +32,20 @@ impl State {
             network_name,
             current_params: ParametersUpdater::new(),
             current_era: None,
+            change_log: Vec::new(),
+            epoch_params: BTreeMap::new(),
+        }
+    }
+
+    /// Parameters in effect for `epoch`, if it is no earlier than the first one seen
+    pub fn params_for_epoch(&self, epoch: u64) -> Option<ProtocolParams> {
+        self.epoch_params.range(..=epoch).next_back().map(|(_, params)| params.clone())
+    }
+
+    /// Keep the parameters for `epoch`, if they differ from the last ones kept
+    fn record_epoch_params(&mut self, epoch: u64, params: &ProtocolParams) {
+        if self.epoch_params.values().next_back() != Some(params) {
+            self.epoch_params.insert(epoch, params.clone());
         }
     }
 
@@ -69,22 +91,87 @@ impl State {
         self.current_params.apply_enact_state(alonzo_gov, conway_gov)
     }
 
+    /// Apply the outcomes decided as the previous epoch ended, and the genesis key
+    /// delegations which have fallen due
     pub async fn handle_enact_state(
         &mut self,
+        epoch: u64,
         new_era: &Era,
         msg: &GovernanceOutcomesMessage,
+        genesis_delegations: &[(GenesisKeyhash, GenesisDelegate)],
     ) -> Result<ProtocolParamsMessage> {
         debug!("Era: {:?}, applying enact state", new_era);
+        let previous_params = self.current_params.get_params();
+        let mut causes = Self::change_causes(self.current_era, new_era, msg)?;
+
         let conway_outcomes: Vec<_> =
             msg.conway_outcomes.iter().map(|o| o.action_to_perform.clone()).collect();
         self.apply_governance_outcomes(new_era, &msg.alonzo_babbage_outcomes, &conway_outcomes)?;
+
+        if !genesis_delegations.is_empty() {
+            self.current_params.apply_genesis_delegations(genesis_delegations)?;
+            causes.push(ParameterChangeCause::GenesisKeyDelegation {
+                genesis_keys: genesis_delegations.iter().map(|(key, _)| *key).collect(),
+            });
+        }
         let params_message = ProtocolParamsMessage {
             params: self.current_params.get_params(),
         };
 
+        match diff_params(&previous_params, &params_message.params) {
+            Ok(changes) if !changes.is_empty() => {
+                info!(
+                    "Epoch {epoch}: {} protocol parameters changed",
+                    changes.len()
+                );
+                self.change_log.push(ParameterChangeRecord {
+                    epoch,
+                    causes,
+                    changes,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => error!("Cannot record parameter changes for epoch {epoch}: {e}"),
+        }
+        self.record_epoch_params(epoch, &params_message.params);
+
         Ok(params_message)
     }
 
+    /// Everything in the outcomes which may change the parameters
+    fn change_causes(
+        current_era: Option<Era>,
+        new_era: &Era,
+        msg: &GovernanceOutcomesMessage,
+    ) -> Result<Vec<ParameterChangeCause>> {
+        let mut causes = Vec::new();
+        if current_era != Some(*new_era) {
+            for era in Self::genesis_era_range(current_era, *new_era) {
+                causes.push(ParameterChangeCause::Genesis(Era::try_from(era)?));
+            }
+        }
+
+        for outcome in msg.alonzo_babbage_outcomes.iter().filter(|o| o.accepted) {
+            causes.push(ParameterChangeCause::UpdateProposal {
+                proposers: outcome.voting.clone(),
+            });
+        }
+
+        for outcome in &msg.conway_outcomes {
+            if outcome.voting.accepted
+                && matches!(
+                    outcome.action_to_perform,
+                    GovernanceOutcomeVariant::EnactStateElem(_)
+                )
+            {
+                causes.push(ParameterChangeCause::GovernanceAction(
+                    outcome.voting.procedure.gov_action_id.clone(),
+                ));
+            }
+        }
+        Ok(causes)
+    }
+
     /// Initialize state from Conway snapshot data
     ///
     /// This method bootstraps the protocol parameters state from a snapshot message.
@@ -103,6 +190,8 @@ impl State {
         self.current_era = Some(param_msg.era);
 
         self.current_params.apply_bootstrap(&self.network_name, param_msg.params.clone())?;
+        let params = self.current_params.get_params();
+        self.record_epoch_params(param_msg.epoch, &params);
 
         info!(
             "Bootstrapped ParametersState to era {:?} with params: {:?}",
@@ -119,10 +208,36 @@ impl State {
     }
 }
 
+impl StateDigest for State {
+    /// Current parameters and era - the change log and past epochs' parameters
+    /// depend on how the node started
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        let mut params = self.current_params.get_params();
+
+        // Parameters held in hash maps are added entry by entry
+        if let Some(byron) = params.byron.as_mut() {
+            for (id, delegate) in std::mem::take(&mut byron.heavy_delegation) {
+                digest.insert(&("heavy-delegation", id, delegate))?;
+            }
+        }
+        if let Some(conway) = params.conway.as_mut() {
+            for (credential, expiry) in std::mem::take(&mut conway.committee.members) {
+                digest.insert(&("committee-member", credential, expiry))?;
+            }
+        }
+
+        digest.insert(&("params", &params))?;
+        digest.insert(&("era", &self.current_era))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::State;
-    use acropolis_common::Era;
+    use acropolis_common::{
+        hash::Hash, messages::GovernanceOutcomesMessage, queries::parameters::ParameterChangeCause,
+        Era, GenesisDelegate, VrfKeyHash,
+    };
     use anyhow::Result;
 
     #[test]
@@ -149,4 +264,90 @@ mod tests {
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn change_log_records_genesis_parameters() -> Result<()> {
+        let mut state = State::new("mainnet".to_string());
+        let outcomes = GovernanceOutcomesMessage::default();
+
+        state.handle_enact_state(0, &Era::Byron, &outcomes, &[]).await?;
+        state.handle_enact_state(1, &Era::Byron, &outcomes, &[]).await?;
+        state.handle_enact_state(208, &Era::Shelley, &outcomes, &[]).await?;
+
+        // Nothing changed in epoch 1
+        let epochs: Vec<_> = state.change_log.iter().map(|r| r.epoch).collect();
+        assert_eq!(epochs, vec![0, 208]);
+
+        let shelley = &state.change_log[1];
+        assert_eq!(
+            shelley.causes,
+            vec![ParameterChangeCause::Genesis(Era::Shelley)]
+        );
+        assert!(shelley.changes.iter().any(|c| c.path == "shelley" && c.old.is_none()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn params_are_kept_for_past_epochs() -> Result<()> {
+        let mut state = State::new("mainnet".to_string());
+        let outcomes = GovernanceOutcomesMessage::default();
+
+        state.handle_enact_state(0, &Era::Byron, &outcomes, &[]).await?;
+        state.handle_enact_state(1, &Era::Byron, &outcomes, &[]).await?;
+        state.handle_enact_state(208, &Era::Shelley, &outcomes, &[]).await?;
+        state.handle_enact_state(209, &Era::Shelley, &outcomes, &[]).await?;
+
+        // Only the epochs where they changed are kept
+        assert_eq!(
+            state.epoch_params.keys().copied().collect::<Vec<_>>(),
+            vec![0, 208]
+        );
+
+        let byron = state.params_for_epoch(207).expect("Byron parameters");
+        assert!(byron.byron.is_some() && byron.shelley.is_none());
+        let shelley = state.params_for_epoch(300).expect("Shelley parameters");
+        assert!(shelley.shelley.is_some());
+        assert_eq!(Some(shelley), state.params_for_epoch(208));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn genesis_delegations_replace_delegates() -> Result<()> {
+        let mut state = State::new("mainnet".to_string());
+        let outcomes = GovernanceOutcomesMessage::default();
+        state.handle_enact_state(208, &Era::Shelley, &outcomes, &[]).await?;
+
+        let params = state.current_params.get_params();
+        let genesis_key =
+            *params.genesis_delegates().expect("Shelley delegates").0.keys().next().unwrap();
+        let delegate = GenesisDelegate {
+            delegate: Hash::new([7; 28]),
+            vrf: VrfKeyHash::new(Hash::new([7; 32])),
+        };
+
+        let message = state
+            .handle_enact_state(
+                209,
+                &Era::Shelley,
+                &outcomes,
+                &[(genesis_key, delegate.clone())],
+            )
+            .await?;
+        let delegates = message.params.genesis_delegates().expect("Shelley delegates");
+        assert_eq!(delegates.0.get(&genesis_key), Some(&delegate));
+        assert_eq!(
+            delegates.0.len(),
+            params.genesis_delegates().unwrap().0.len()
+        );
+
+        let record = state.change_log.last().expect("change record");
+        assert_eq!(record.epoch, 209);
+        assert_eq!(
+            record.causes,
+            vec![ParameterChangeCause::GenesisKeyDelegation {
+                genesis_keys: vec![genesis_key]
+            }]
+        );
+        Ok(())
+    }
 }
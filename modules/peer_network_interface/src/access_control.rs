@@ -0,0 +1,162 @@
+//! Allow/deny rules and connection rate limiting for upstream peers, for deployments
+//! which expose the node publicly and accept peer-sharing from untrusted peers.
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use acropolis_common::commands::peer_network::PeerAccessRules;
+use anyhow::{Result, bail};
+
+/// Window over which `max_connections_per_minute` is counted.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// A single allow or deny rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Rule {
+    /// An IP subnet; a plain IP address is a subnet with a full-length prefix.
+    Subnet { network: IpAddr, prefix: u8 },
+    /// An exact IP address and port.
+    Socket(SocketAddr),
+    /// An exact `host:port` address.
+    Address(String),
+    /// Any port on a named host.
+    Host(String),
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        if rule.is_empty() || rule.contains(char::is_whitespace) {
+            bail!("invalid peer rule '{rule}'");
+        }
+        if let Some((network, prefix)) = rule.split_once('/') {
+            let Ok(network) = network.parse::<IpAddr>() else {
+                bail!("invalid subnet '{rule}'");
+            };
+            let prefix = match prefix.parse::<u8>() {
+                Ok(prefix) if prefix <= max_prefix(&network) => prefix,
+                _ => bail!("invalid prefix length in subnet '{rule}'"),
+            };
+            return Ok(Rule::Subnet { network, prefix });
+        }
+        if let Ok(ip) = rule.parse::<IpAddr>() {
+            return Ok(Rule::Subnet {
+                network: ip,
+                prefix: max_prefix(&ip),
+            });
+        }
+        if let Ok(socket) = rule.parse::<SocketAddr>() {
+            return Ok(Rule::Socket(socket));
+        }
+        match rule.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(Rule::Address(rule.to_ascii_lowercase()))
+            }
+            Some(_) => bail!("invalid peer address '{rule}'"),
+            None => Ok(Rule::Host(rule.to_ascii_lowercase())),
+        }
+    }
+
+    fn matches(&self, address: &str) -> bool {
+        match self {
+            Rule::Subnet { network, prefix } => address
+                .parse::<SocketAddr>()
+                .is_ok_and(|socket| in_subnet(&socket.ip(), network, *prefix)),
+            Rule::Socket(rule) => address.parse::<SocketAddr>().is_ok_and(|s| s == *rule),
+            Rule::Address(rule) => address.eq_ignore_ascii_case(rule),
+            Rule::Host(rule) => {
+                address.rsplit_once(':').is_some_and(|(host, _)| host.eq_ignore_ascii_case(rule))
+            }
+        }
+    }
+}
+
+fn max_prefix(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn in_subnet(ip: &IpAddr, network: &IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let diff = u32::from(*ip) ^ u32::from(*network);
+            prefix == 0 || diff >> (32 - prefix as u32) == 0
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let diff = u128::from(*ip) ^ u128::from(*network);
+            prefix == 0 || diff >> (128 - prefix as u32) == 0
+        }
+        _ => false,
+    }
+}
+
+/// Decides which peers may be connected to, and how quickly new connections are opened.
+///
+/// Peers given by host name can only be matched by host or `host:port` rules, since
+/// subnet rules are checked without resolving names.
+pub struct AccessControl {
+    allow: Vec<Rule>,
+    deny: Vec<Rule>,
+    max_connections_per_minute: u32,
+    /// When each connection in the current rate window was opened.
+    recent_connections: VecDeque<Instant>,
+}
+
+impl AccessControl {
+    /// Build from configured rules, failing on any rule which cannot be parsed.
+    pub fn new(rules: &PeerAccessRules) -> Result<Self> {
+        let mut access = Self {
+            allow: vec![],
+            deny: vec![],
+            max_connections_per_minute: 0,
+            recent_connections: VecDeque::new(),
+        };
+        access.set_rules(rules)?;
+        Ok(access)
+    }
+
+    /// Replace the rules. The connection history is kept so that a reload does not
+    /// reset the rate limit. On error the existing rules are left unchanged.
+    pub fn set_rules(&mut self, rules: &PeerAccessRules) -> Result<()> {
+        let parse = |rules: &[String]| rules.iter().map(|r| Rule::parse(r)).collect();
+        let allow = parse(&rules.allow)?;
+        let deny = parse(&rules.deny)?;
+        self.allow = allow;
+        self.deny = deny;
+        self.max_connections_per_minute = rules.max_connections_per_minute;
+        Ok(())
+    }
+
+    /// Returns true if the rules allow connecting to `address`. Deny rules take
+    /// precedence; an empty allow list allows any peer which is not denied.
+    pub fn permits(&self, address: &str) -> bool {
+        if self.deny.iter().any(|rule| rule.matches(address)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|rule| rule.matches(address))
+    }
+
+    /// Record a new connection at `now` if the rate limit allows it. Returns false,
+    /// recording nothing, if the limit for the current minute has been reached.
+    pub fn try_connect(&mut self, now: Instant) -> bool {
+        if self.max_connections_per_minute == 0 {
+            return true;
+        }
+        while self
+            .recent_connections
+            .front()
+            .is_some_and(|opened| now.saturating_duration_since(*opened) >= RATE_WINDOW)
+        {
+            self.recent_connections.pop_front();
+        }
+        if self.recent_connections.len() >= self.max_connections_per_minute as usize {
+            return false;
+        }
+        self.recent_connections.push_back(now);
+        true
+    }
+}
@@ -182,6 +182,7 @@ impl BlockFlowHandler {
     pub fn handle_new_connection(
         &mut self,
         peer: PeerId,
+        address: &str,
         sync_point: Option<&Point>,
     ) -> Vec<Point> {
         match self {
@@ -195,6 +196,7 @@ impl BlockFlowHandler {
                 }
             }
             BlockFlowHandler::Consensus(state) => {
+                state.peer_addresses.insert(peer, address.to_string());
                 let points = state.choose_points_for_find_intersect();
                 if !points.is_empty() {
                     return points;
@@ -222,6 +224,16 @@ impl BlockFlowHandler {
         }
     }
 
+    /// Hash of a block's parent, if its header has been seen.
+    pub fn block_parent(&self, slot: u64, hash: BlockHash) -> Option<BlockHash> {
+        match self {
+            BlockFlowHandler::Direct { chain } => chain.block_parent(slot, hash),
+            BlockFlowHandler::Consensus(state) => {
+                state.headers.get(&(slot, hash)).and_then(|header| header.parent_hash)
+            }
+        }
+    }
+
     /// Reset state for a new sync point.
     pub fn handle_sync_reset(&mut self) {
         match self {
@@ -515,6 +527,8 @@ pub struct ConsensusFlowState {
     headers: HashMap<(u64, BlockHash), Header>,
     published_points: VecDeque<SpecificPoint>,
     security_param_k: u64,
+    /// Connected peers' addresses, to tell consensus where each offer came from
+    peer_addresses: HashMap<PeerId, String>,
 }
 
 impl ConsensusFlowState {
@@ -528,6 +542,7 @@ impl ConsensusFlowState {
             headers: HashMap::new(),
             published_points: VecDeque::new(),
             security_param_k,
+            peer_addresses: HashMap::new(),
         }
     }
 
@@ -559,6 +574,7 @@ impl ConsensusFlowState {
 
     fn handle_disconnect(&mut self, peer: PeerId) {
         self.tracker.handle_disconnect(peer);
+        self.peer_addresses.remove(&peer);
     }
 
     fn block_announcers(&self, slot: u64, hash: BlockHash) -> Vec<PeerId> {
@@ -595,12 +611,22 @@ impl ConsensusFlowState {
                         info!("Offered block (consensus) {}", hash);
                     }
 
+                    let tiebreak_vrf =
+                        self.headers.get(&(slot, hash)).and_then(|header| header.tiebreak_vrf());
+                    let origin_peer = self
+                        .tracker
+                        .announcers(slot, hash)
+                        .first()
+                        .and_then(|peer| self.peer_addresses.get(peer))
+                        .cloned();
                     let message = Arc::new(Message::Consensus(ConsensusMessage::BlockOffered(
                         BlockOfferedMessage {
                             hash,
                             slot,
                             number,
                             parent_hash,
+                            tiebreak_vrf,
+                            origin_peer,
                         },
                     )));
                     if let Err(e) = self.context.publish(&self.topic, message).await {
@@ -1253,7 +1279,7 @@ mod tests {
 
         let mut handler = BlockFlowHandler::Consensus(state);
         let stale_point = Point::Specific(1, vec![0; 32]);
-        let points = handler.handle_new_connection(PEER_1, Some(&stale_point));
+        let points = handler.handle_new_connection(PEER_1, "127.0.0.1:3001", Some(&stale_point));
 
         // Should use published_points, not the stale sync_point
         assert!(!points.is_empty());
@@ -1269,7 +1295,7 @@ mod tests {
         let state = make_test_consensus_state();
         let mut handler = BlockFlowHandler::Consensus(state);
         let sync_point = Point::Specific(42, vec![0xAA; 32]);
-        let points = handler.handle_new_connection(PEER_1, Some(&sync_point));
+        let points = handler.handle_new_connection(PEER_1, "127.0.0.1:3001", Some(&sync_point));
 
         assert_eq!(points.len(), 1);
         assert_eq!(points[0], sync_point);
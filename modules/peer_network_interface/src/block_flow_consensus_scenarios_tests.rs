@@ -12,6 +12,7 @@ use acropolis_common::messages::{
 };
 use acropolis_common::{
     BlockHash, BlockInfo, BlockIntent, BlockStatus, Era, configuration::BlockFlowMode,
+    genesis_values::Network,
 };
 use acropolis_module_consensus::Consensus;
 use acropolis_test_utils::mainnet_genesis_values;
@@ -134,12 +135,18 @@ async fn make_harness() -> TestHarness {
         genesis_completion_topic: "cardano.sequence.bootstrapped".to_string(),
         sync_command_topic: "cardano.sync.command".to_string(),
         node_addresses: vec![],
+        network: Network::Mainnet,
         cache_dir: PathBuf::from("/tmp"),
+        cache_verify: true,
+        cache_compact: false,
+        cache_max_bytes: 0,
         genesis_values: None,
         consensus_topic: "cardano.consensus.offers".to_string(),
         block_wanted_topic: "cardano.consensus.wants".to_string(),
         target_peer_count: 15,
         min_hot_peers: 3,
+        target_warm_peers: 0,
+        topology_file: None,
         peer_sharing_enabled: false,
         churn_interval_secs: 600,
         peer_sharing_timeout_secs: 10,
@@ -148,6 +155,15 @@ async fn make_harness() -> TestHarness {
         allow_non_public_peer_addrs: true,
         discovery_interval_secs: 0,
         peer_sharing_cooldown_secs: 0,
+        peer_scoring_enabled: true,
+        min_peer_score: 0.0,
+        network_query_topic: "cardano.query.network".to_string(),
+        max_blocks_in_flight: 10,
+        metrics_topic: "test.monitor.network".to_string(),
+        metrics_interval_secs: 0,
+        allow_peers: vec![],
+        deny_peers: vec![],
+        max_connections_per_minute: 0,
     };
 
     let block_wanted_subscription =
@@ -171,6 +187,7 @@ async fn make_harness() -> TestHarness {
         last_epoch: None,
         era: None,
         rolled_back: false,
+        sync_stop: None,
     };
 
     let proposed_sub =
@@ -397,6 +397,10 @@ impl ChainState {
             None => vec![],
         }
     }
+
+    pub fn block_parent(&self, slot: u64, hash: BlockHash) -> Option<BlockHash> {
+        self.blocks.get(&slot)?.header(hash)?.parent_hash
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -1,9 +1,15 @@
 use std::path::PathBuf;
 
-use acropolis_common::genesis_values::GenesisValues;
+use acropolis_common::{
+    commands::peer_network::PeerAccessRules,
+    genesis_values::{GenesisValues, Network},
+    queries::network::DEFAULT_NETWORK_QUERY_TOPIC,
+};
 use anyhow::Result;
 use config::Config;
 
+use crate::topology::Topology;
+
 #[derive(Clone, Debug, serde::Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum SyncPoint {
@@ -20,8 +26,17 @@ pub struct InterfaceConfig {
     pub sync_point: SyncPoint,
     pub genesis_completion_topic: String,
     pub sync_command_topic: String,
+    #[serde(default)]
     pub node_addresses: Vec<String>,
+    #[serde(skip)]
+    pub network: Network,
     pub cache_dir: PathBuf,
+    #[serde(default = "default_cache_verify")]
+    pub cache_verify: bool,
+    #[serde(default)]
+    pub cache_compact: bool,
+    #[serde(default)]
+    pub cache_max_bytes: u64,
     #[serde(flatten)]
     pub genesis_values: Option<GenesisValues>,
     #[serde(default = "default_consensus_topic")]
@@ -32,6 +47,10 @@ pub struct InterfaceConfig {
     pub target_peer_count: usize,
     #[serde(default = "default_min_hot_peers")]
     pub min_hot_peers: usize,
+    #[serde(default = "default_target_warm_peers")]
+    pub target_warm_peers: usize,
+    #[serde(default)]
+    pub topology_file: Option<PathBuf>,
     #[serde(default = "default_peer_sharing_enabled")]
     pub peer_sharing_enabled: bool,
     #[serde(default = "default_churn_interval_secs")]
@@ -48,6 +67,53 @@ pub struct InterfaceConfig {
     pub discovery_interval_secs: u64,
     #[serde(default = "default_peer_sharing_cooldown_secs")]
     pub peer_sharing_cooldown_secs: u64,
+    #[serde(default = "default_peer_scoring_enabled")]
+    pub peer_scoring_enabled: bool,
+    #[serde(default = "default_min_peer_score")]
+    pub min_peer_score: f64,
+    #[serde(default = "default_network_query_topic")]
+    pub network_query_topic: String,
+    #[serde(default = "default_max_blocks_in_flight")]
+    pub max_blocks_in_flight: usize,
+    #[serde(default = "default_metrics_topic")]
+    pub metrics_topic: String,
+    #[serde(default = "default_metrics_interval_secs")]
+    pub metrics_interval_secs: u64,
+    #[serde(default)]
+    pub allow_peers: Vec<String>,
+    #[serde(default)]
+    pub deny_peers: Vec<String>,
+    #[serde(default)]
+    pub max_connections_per_minute: u32,
+}
+
+/// The keys of a config section which can change without a restart
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ReloadableConfig {
+    #[serde(default)]
+    allow_peers: Vec<String>,
+    #[serde(default)]
+    deny_peers: Vec<String>,
+    #[serde(default)]
+    max_connections_per_minute: u32,
+}
+
+/// Config keys which can change without a restart
+pub const RELOADABLE_KEYS: &[&str] = &["allow-peers", "deny-peers", "max-connections-per-minute"];
+
+/// The access rules in a reloaded config section
+pub fn reloaded_access_rules(section: Config) -> Result<PeerAccessRules> {
+    let reloaded: ReloadableConfig = section.try_deserialize()?;
+    Ok(PeerAccessRules {
+        allow: reloaded.allow_peers,
+        deny: reloaded.deny_peers,
+        max_connections_per_minute: reloaded.max_connections_per_minute,
+    })
+}
+
+fn default_cache_verify() -> bool {
+    true
 }
 
 fn default_consensus_topic() -> String {
@@ -66,6 +132,10 @@ fn default_min_hot_peers() -> usize {
     3
 }
 
+fn default_target_warm_peers() -> usize {
+    2
+}
+
 fn default_peer_sharing_enabled() -> bool {
     true
 }
@@ -98,7 +168,40 @@ fn default_peer_sharing_cooldown_secs() -> u64 {
     30
 }
 
+fn default_peer_scoring_enabled() -> bool {
+    true
+}
+
+fn default_min_peer_score() -> f64 {
+    20.0
+}
+
+fn default_max_blocks_in_flight() -> usize {
+    10
+}
+
+fn default_metrics_topic() -> String {
+    "cardano.monitor.network".to_string()
+}
+
+fn default_metrics_interval_secs() -> u64 {
+    10
+}
+
+fn default_network_query_topic() -> String {
+    DEFAULT_NETWORK_QUERY_TOPIC.1.to_string()
+}
+
 impl InterfaceConfig {
+    /// The configured upstream allow/deny rules and connection rate limit.
+    pub fn access_rules(&self) -> PeerAccessRules {
+        PeerAccessRules {
+            allow: self.allow_peers.clone(),
+            deny: self.deny_peers.clone(),
+            max_connections_per_minute: self.max_connections_per_minute,
+        }
+    }
+
     pub fn try_load(config: &Config) -> Result<Self> {
         let full_config = Config::builder()
             .add_source(config::File::from_str(
@@ -107,6 +210,22 @@ impl InterfaceConfig {
             ))
             .add_source(config.clone())
             .build()?;
-        Ok(full_config.try_deserialize()?)
+        let mut cfg: Self = full_config.try_deserialize()?;
+        cfg.network = Network::from_config(config)?;
+        if let Some(path) = &cfg.topology_file {
+            let topology = Topology::load(path)?;
+            for address in topology.addresses() {
+                if !cfg.node_addresses.contains(&address) {
+                    cfg.node_addresses.push(address);
+                }
+            }
+            cfg.min_hot_peers = cfg.min_hot_peers.max(topology.hot_target());
+            cfg.target_warm_peers = cfg.target_warm_peers.max(topology.warm_target());
+        }
+        if cfg.node_addresses.is_empty() {
+            cfg.node_addresses =
+                cfg.network.default_relays().iter().map(|a| a.to_string()).collect();
+        }
+        Ok(cfg)
     }
 }
@@ -1,10 +1,14 @@
-use std::time::Duration;
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use acropolis_common::{BlockHash, Era};
 use anyhow::{Result, bail};
 pub use pallas::network::miniprotocols::Point;
 use pallas::{
-    ledger::traverse::MultiEraHeader,
+    ledger::traverse::{MultiEraBlock, MultiEraHeader},
     network::{
         facades::PeerClient,
         miniprotocols::{blockfetch, chainsync},
@@ -16,10 +20,17 @@ use tokio::{
 };
 use tracing::debug;
 
-use crate::network::PeerMessageSender;
+use crate::{
+    metrics::{self, ConnectionMetrics, TrafficCounters},
+    network::PeerMessageSender,
+};
+
+/// Most blocks requested from a peer in a single BlockFetch range request.
+const MAX_FETCH_BATCH: usize = 100;
 
 pub struct PeerConnection {
     pub address: String,
+    metrics: Arc<ConnectionMetrics>,
     chainsync: mpsc::UnboundedSender<ChainsyncCommand>,
     blockfetch: mpsc::UnboundedSender<BlockfetchCommand>,
 }
@@ -31,12 +42,15 @@ impl PeerConnection {
         sender: PeerMessageSender,
         delay: Duration,
         connect_timeout: Duration,
+        traffic_totals: Arc<TrafficCounters>,
     ) -> Self {
+        let metrics = Arc::new(ConnectionMetrics::new(traffic_totals));
         let worker = PeerConnectionWorker {
             address: address.clone(),
             magic,
             sender,
             connect_timeout,
+            metrics: metrics.clone(),
         };
         let (chainsync_tx, chainsync_rx) = mpsc::unbounded_channel();
         let (blockfetch_tx, blockfetch_rx) = mpsc::unbounded_channel();
@@ -46,11 +60,16 @@ impl PeerConnection {
         });
         Self {
             address,
+            metrics,
             chainsync: chainsync_tx,
             blockfetch: blockfetch_tx,
         }
     }
 
+    pub fn metrics(&self) -> &ConnectionMetrics {
+        &self.metrics
+    }
+
     pub async fn find_tip(&self) -> Result<Point> {
         let (tx, rx) = oneshot::channel();
         self.chainsync.send(ChainsyncCommand::FindTip(tx))?;
@@ -62,14 +81,27 @@ impl PeerConnection {
         Ok(())
     }
 
-    pub fn request_block(&self, hash: BlockHash, slot: u64) -> Result<()> {
-        self.blockfetch.send(BlockfetchCommand::Fetch(hash, slot))?;
+    /// Request a block. With the hash of its parent, it can be fetched in the same range
+    /// request as its parent if that is requested from this peer too.
+    pub fn request_block(
+        &self,
+        hash: BlockHash,
+        slot: u64,
+        parent: Option<BlockHash>,
+    ) -> Result<()> {
+        self.blockfetch.send(BlockfetchCommand::Fetch(FetchRequest {
+            hash,
+            slot,
+            parent,
+        }))?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
 pub enum PeerEvent {
+    /// The connection and handshake succeeded.
+    Connected,
     ChainSync(PeerChainSyncEvent),
     BlockFetched(BlockFetched),
     Disconnected,
@@ -92,6 +124,18 @@ pub struct Header {
     pub parent_hash: Option<BlockHash>,
 }
 
+impl Header {
+    /// Leader VRF output which Praos compares to choose between equally long chains.
+    /// `None` for Byron headers, or if the header cannot be decoded.
+    pub fn tiebreak_vrf(&self) -> Option<Vec<u8>> {
+        match MultiEraHeader::decode(self.era as u8, None, &self.bytes).ok()? {
+            MultiEraHeader::ShelleyCompatible(x) => Some(x.header_body.leader_vrf.0.to_vec()),
+            MultiEraHeader::BabbageCompatible(x) => Some(x.header_body.vrf_result.0.to_vec()),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct BlockFetched {
     pub slot: u64,
@@ -104,6 +148,7 @@ struct PeerConnectionWorker {
     magic: u32,
     sender: PeerMessageSender,
     connect_timeout: Duration,
+    metrics: Arc<ConnectionMetrics>,
 }
 
 impl PeerConnectionWorker {
@@ -130,6 +175,7 @@ impl PeerConnectionWorker {
         )
         .await
         .map_err(|_| anyhow::anyhow!("connect timeout after {}s", timeout_dur.as_secs()))??;
+        self.sender.write(PeerEvent::Connected).await?;
         select! {
             res = self.run_chainsync(client.chainsync, chainsync) => res,
             res = self.run_blockfetch(client.blockfetch, blockfetch) => res,
@@ -144,7 +190,7 @@ impl PeerConnectionWorker {
         let mut reached = None;
         loop {
             select! {
-                msg = client.request_or_await_next(), if reached.is_some() => {
+                msg = self.request_next(&mut client), if reached.is_some() => {
                     if let Some(parsed) = self.parse_chainsync_message(msg?)? {
                         reached = Some(parsed.point);
                         self.sender.write(PeerEvent::ChainSync(parsed.event)).await?;
@@ -162,7 +208,7 @@ impl PeerConnectionWorker {
                     };
                     match cmd {
                         ChainsyncCommand::FindIntersect(points) => {
-                            let (point, tip) = client.find_intersect(points).await?;
+                            let (point, tip) = self.find_intersect(&mut client, points).await?;
                             reached = point;
                             if reached.is_none() {
                                 self.sender.write(PeerEvent::ChainSync(PeerChainSyncEvent::IntersectNotFound(tip.0))).await?;
@@ -170,7 +216,7 @@ impl PeerConnectionWorker {
                         }
                         ChainsyncCommand::FindTip(done) => {
                             let points = reached.as_slice().to_vec();
-                            let (_, tip) = client.find_intersect(points).await?;
+                            let (_, tip) = self.find_intersect(&mut client, points).await?;
                             if done.send(tip.0).is_err() {
                                 bail!("parent process has disconnected");
                             }
@@ -181,17 +227,109 @@ impl PeerConnectionWorker {
         }
     }
 
+    /// Ask for the next header, or keep waiting if the peer already told us to await
+    /// one. The time to the first reply to a new request is recorded as the round trip.
+    async fn request_next(
+        &self,
+        client: &mut chainsync::N2NClient,
+    ) -> Result<chainsync::NextResponse<chainsync::HeaderContent>> {
+        if !client.has_agency() {
+            return Ok(client.recv_while_must_reply().await?);
+        }
+        let sent = Instant::now();
+        self.metrics.record_sent(metrics::REQUEST_NEXT_SIZE);
+        let response = client.request_next().await?;
+        self.metrics.record_round_trip(sent.elapsed());
+        Ok(response)
+    }
+
+    async fn find_intersect(
+        &self,
+        client: &mut chainsync::N2NClient,
+        points: Vec<Point>,
+    ) -> Result<(Option<Point>, chainsync::Tip)> {
+        let sent = Instant::now();
+        self.metrics.record_sent(metrics::find_intersect_size(&points));
+        let result = client.find_intersect(points).await?;
+        self.metrics.record_round_trip(sent.elapsed());
+        Ok(result)
+    }
+
     async fn run_blockfetch(
         &self,
         mut client: blockfetch::Client,
         mut commands: mpsc::UnboundedReceiver<BlockfetchCommand>,
     ) -> Result<()> {
-        while let Some(BlockfetchCommand::Fetch(hash, slot)) = commands.recv().await {
+        while let Some(BlockfetchCommand::Fetch(request)) = commands.recv().await {
+            // Batch up everything already queued, so runs of it can go out as range requests
+            let mut batch = vec![request];
+            while batch.len() < MAX_FETCH_BATCH {
+                match commands.try_recv() {
+                    Ok(BlockfetchCommand::Fetch(request)) => batch.push(request),
+                    Err(_) => break,
+                }
+            }
+            for run in contiguous_runs(batch) {
+                self.fetch_run(&mut client, run).await?;
+            }
+        }
+        bail!("parent process has disconnected");
+    }
+
+    /// Fetch a run of consecutive blocks. Several blocks are requested as a single range
+    /// from the first to the last, and any the range did not cover (e.g. because the peer
+    /// has since switched forks) are then fetched one by one.
+    async fn fetch_run(
+        &self,
+        client: &mut blockfetch::Client,
+        mut run: Vec<FetchRequest>,
+    ) -> Result<()> {
+        if run.len() > 1 {
+            let first = &run[0];
+            let last = &run[run.len() - 1];
+            let range = (
+                Point::Specific(first.slot, first.hash.to_vec()),
+                Point::Specific(last.slot, last.hash.to_vec()),
+            );
+            self.metrics.record_sent(metrics::request_range_size(&range.0, &range.1));
+            let bodies = match client.fetch_range(range).await {
+                Ok(bodies) => bodies,
+                Err(blockfetch::ClientError::NoBlocks) => vec![],
+                Err(error) => return Err(error.into()),
+            };
+
+            let mut wanted: HashSet<BlockHash> = run.iter().map(|request| request.hash).collect();
+            for body in bodies {
+                self.metrics.record_block(body.len());
+                let Ok(block) = MultiEraBlock::decode(&body) else {
+                    continue;
+                };
+                let hash = BlockHash::new(*block.hash());
+                let slot = block.slot();
+                if wanted.remove(&hash) {
+                    self.sender
+                        .write(PeerEvent::BlockFetched(BlockFetched { slot, hash, body }))
+                        .await?;
+                }
+            }
+            run.retain(|request| wanted.contains(&request.hash));
+            if !run.is_empty() {
+                debug!(
+                    peer = self.address,
+                    remaining = run.len(),
+                    "range fetch did not cover all requested blocks"
+                );
+            }
+        }
+
+        for FetchRequest { hash, slot, .. } in run {
             let point = Point::Specific(slot, hash.to_vec());
+            self.metrics.record_sent(metrics::request_range_size(&point, &point));
             let body = client.fetch_single(point).await?;
+            self.metrics.record_block(body.len());
             self.sender.write(PeerEvent::BlockFetched(BlockFetched { slot, hash, body })).await?;
         }
-        bail!("parent process has disconnected");
+        Ok(())
     }
 
     fn parse_chainsync_message(
@@ -218,6 +356,7 @@ impl PeerConnectionWorker {
     }
 
     fn parse_header(&self, header: chainsync::HeaderContent) -> Result<Option<Header>> {
+        self.metrics.record_header(header.cbor.len());
         let hdr_tag = header.byron_prefix.map(|p| p.0);
         let hdr_variant = header.variant;
         let hdr = MultiEraHeader::decode(hdr_variant, hdr_tag, &header.cbor)?;
@@ -249,5 +388,69 @@ struct ParsedChainsyncMessage {
 }
 
 enum BlockfetchCommand {
-    Fetch(BlockHash, u64),
+    Fetch(FetchRequest),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FetchRequest {
+    hash: BlockHash,
+    slot: u64,
+    /// Hash of the block's parent, if known
+    parent: Option<BlockHash>,
+}
+
+/// Split a batch of requests into runs of consecutive blocks, in slot order. A range
+/// request returns every block between its ends, so only blocks which each follow the one
+/// before are fetched together - a gap may hold blocks requested from other peers.
+fn contiguous_runs(mut batch: Vec<FetchRequest>) -> Vec<Vec<FetchRequest>> {
+    batch.sort_by_key(|request| request.slot);
+    let mut runs: Vec<Vec<FetchRequest>> = vec![];
+    for request in batch {
+        match runs.last_mut() {
+            Some(run) if run.last().is_some_and(|prev| request.parent == Some(prev.hash)) => {
+                run.push(request);
+            }
+            _ => runs.push(vec![request]),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(slot: u64, hash: u8, parent: Option<u8>) -> FetchRequest {
+        FetchRequest {
+            hash: BlockHash::new([hash; 32]),
+            slot,
+            parent: parent.map(|parent| BlockHash::new([parent; 32])),
+        }
+    }
+
+    fn slots(runs: &[Vec<FetchRequest>]) -> Vec<Vec<u64>> {
+        runs.iter().map(|run| run.iter().map(|request| request.slot).collect()).collect()
+    }
+
+    #[test]
+    fn only_consecutive_blocks_share_a_range() {
+        // Blocks 3 and 4 were requested from another peer
+        let batch = vec![
+            request(6, 6, Some(5)),
+            request(1, 1, Some(0)),
+            request(2, 2, Some(1)),
+            request(5, 5, Some(4)),
+            request(8, 8, Some(7)),
+        ];
+        assert_eq!(
+            slots(&contiguous_runs(batch)),
+            vec![vec![1, 2], vec![5, 6], vec![8]]
+        );
+    }
+
+    #[test]
+    fn blocks_without_a_known_parent_are_fetched_alone() {
+        let batch = vec![request(1, 1, None), request(2, 2, None)];
+        assert_eq!(slots(&contiguous_runs(batch)), vec![vec![1], vec![2]]);
+    }
 }
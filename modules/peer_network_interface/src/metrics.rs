@@ -0,0 +1,222 @@
+//! Connection-level traffic metrics for the PNI module, published periodically as a
+//! summary of network health.
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use acropolis_common::messages::{NetworkMetricsMessage, PeerConnectionMetrics};
+use pallas::{codec::minicbor, network::miniprotocols::Point};
+
+/// Weight given to each new round trip sample in the moving average.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Encoded size of a ChainSync `MsgRequestNext`.
+pub const REQUEST_NEXT_SIZE: usize = 2;
+
+/// Encoded size of a ChainSync `MsgFindIntersect` for `points`.
+pub fn find_intersect_size(points: &[Point]) -> usize {
+    2 + array_header_size(points.len()) + points.iter().map(point_size).sum::<usize>()
+}
+
+/// Encoded size of a BlockFetch `MsgRequestRange` from `from` to `to`.
+pub fn request_range_size(from: &Point, to: &Point) -> usize {
+    2 + point_size(from) + point_size(to)
+}
+
+fn point_size(point: &Point) -> usize {
+    minicbor::to_vec(point).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+fn array_header_size(len: usize) -> usize {
+    match len {
+        0..=23 => 1,
+        24..=0xff => 2,
+        _ => 3,
+    }
+}
+
+/// Traffic counted at one level - a single connection or the whole network.
+#[derive(Default)]
+pub struct TrafficCounters {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    headers: AtomicU64,
+    blocks: AtomicU64,
+}
+
+impl TrafficCounters {
+    fn add(counter: &AtomicU64, amount: usize) {
+        counter.fetch_add(amount as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TrafficSnapshot {
+        TrafficSnapshot {
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            headers: self.headers.load(Ordering::Relaxed),
+            blocks: self.blocks.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time copy of `TrafficCounters`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrafficSnapshot {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub headers: u64,
+    pub blocks: u64,
+}
+
+impl TrafficSnapshot {
+    fn since(&self, earlier: &TrafficSnapshot) -> TrafficSnapshot {
+        TrafficSnapshot {
+            bytes_in: self.bytes_in.saturating_sub(earlier.bytes_in),
+            bytes_out: self.bytes_out.saturating_sub(earlier.bytes_out),
+            headers: self.headers.saturating_sub(earlier.headers),
+            blocks: self.blocks.saturating_sub(earlier.blocks),
+        }
+    }
+}
+
+/// Metrics for one peer connection. Every count is also added to the network-wide
+/// totals, so traffic from connections which have since closed is not lost.
+pub struct ConnectionMetrics {
+    traffic: TrafficCounters,
+    totals: Arc<TrafficCounters>,
+    /// Smoothed round trip in microseconds; zero until the first sample.
+    round_trip_us: AtomicU64,
+}
+
+impl ConnectionMetrics {
+    pub fn new(totals: Arc<TrafficCounters>) -> Self {
+        Self {
+            traffic: TrafficCounters::default(),
+            totals,
+            round_trip_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_header(&self, bytes: usize) {
+        for counters in [&self.traffic, self.totals.as_ref()] {
+            TrafficCounters::add(&counters.headers, 1);
+            TrafficCounters::add(&counters.bytes_in, bytes);
+        }
+    }
+
+    pub fn record_block(&self, bytes: usize) {
+        for counters in [&self.traffic, self.totals.as_ref()] {
+            TrafficCounters::add(&counters.blocks, 1);
+            TrafficCounters::add(&counters.bytes_in, bytes);
+        }
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        for counters in [&self.traffic, self.totals.as_ref()] {
+            TrafficCounters::add(&counters.bytes_out, bytes);
+        }
+    }
+
+    /// Record the time between sending a request and the first reply to it. Only the
+    /// connection's own worker records samples, so a plain load and store is enough.
+    pub fn record_round_trip(&self, round_trip: Duration) {
+        let sample = round_trip.as_micros().max(1) as f64;
+        let average = match self.round_trip_us.load(Ordering::Relaxed) {
+            0 => sample,
+            avg => avg as f64 + LATENCY_SMOOTHING * (sample - avg as f64),
+        };
+        self.round_trip_us.store(average.round().max(1.0) as u64, Ordering::Relaxed);
+    }
+
+    pub fn traffic(&self) -> TrafficSnapshot {
+        self.traffic.snapshot()
+    }
+
+    pub fn round_trip(&self) -> Option<Duration> {
+        match self.round_trip_us.load(Ordering::Relaxed) {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Summary of this connection for the metrics message.
+    pub fn describe(&self, address: &str, hot: bool) -> PeerConnectionMetrics {
+        let traffic = self.traffic();
+        PeerConnectionMetrics {
+            address: address.to_string(),
+            hot,
+            bytes_in: traffic.bytes_in,
+            bytes_out: traffic.bytes_out,
+            headers_received: traffic.headers,
+            blocks_received: traffic.blocks,
+            mux_latency_ms: self.round_trip().map(|rtt| rtt.as_secs_f64() * 1000.0),
+        }
+    }
+}
+
+/// Builds the periodic network metrics message, turning the running totals into
+/// rates over the time since the previous message.
+pub struct MetricsCollector {
+    totals: Arc<TrafficCounters>,
+    last: TrafficSnapshot,
+    last_at: Instant,
+}
+
+impl MetricsCollector {
+    pub fn new(totals: Arc<TrafficCounters>) -> Self {
+        Self {
+            last: totals.snapshot(),
+            totals,
+            last_at: Instant::now(),
+        }
+    }
+
+    pub fn totals(&self) -> Arc<TrafficCounters> {
+        self.totals.clone()
+    }
+
+    pub fn collect(
+        &mut self,
+        peers: Vec<PeerConnectionMetrics>,
+        cold_peers: usize,
+        now: Instant,
+    ) -> NetworkMetricsMessage {
+        let totals = self.totals.snapshot();
+        let delta = totals.since(&self.last);
+        let elapsed = now.saturating_duration_since(self.last_at).as_secs_f64();
+        let rate = |count: u64| {
+            if elapsed > 0.0 {
+                count as f64 / elapsed
+            } else {
+                0.0
+            }
+        };
+        self.last = totals;
+        self.last_at = now;
+
+        let hot_latencies: Vec<f64> =
+            peers.iter().filter(|p| p.hot).filter_map(|p| p.mux_latency_ms).collect();
+        let mux_latency_ms = (!hot_latencies.is_empty())
+            .then(|| hot_latencies.iter().sum::<f64>() / hot_latencies.len() as f64);
+
+        NetworkMetricsMessage {
+            hot_peers: peers.iter().filter(|p| p.hot).count(),
+            warm_peers: peers.iter().filter(|p| !p.hot).count(),
+            cold_peers,
+            bytes_in: totals.bytes_in,
+            bytes_out: totals.bytes_out,
+            headers_received: totals.headers,
+            blocks_received: totals.blocks,
+            bytes_in_per_sec: rate(delta.bytes_in),
+            bytes_out_per_sec: rate(delta.bytes_out),
+            headers_per_sec: rate(delta.headers),
+            blocks_per_sec: rate(delta.blocks),
+            mux_latency_ms,
+            peers,
+        }
+    }
+}
@@ -1,16 +1,24 @@
 use std::{
-    collections::{BTreeMap, HashSet},
-    time::Duration,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crate::{
     BlockSink,
+    access_control::AccessControl,
     block_flow::BlockFlowHandler,
     connection::{PeerChainSyncEvent, PeerConnection, PeerEvent},
+    metrics::{MetricsCollector, TrafficCounters},
     peer_manager::{PeerManager, PeerManagerConfig},
+    peer_scoring::PeerScores,
     peer_sharing::request_peers,
 };
-use acropolis_common::BlockHash;
+use acropolis_common::{
+    BlockHash,
+    commands::peer_network::PeerAccessRules,
+    messages::{Message, MonitoringMessage, NetworkMetricsMessage},
+};
 use anyhow::{Context as _, Result, bail};
 use pallas::network::miniprotocols::Point;
 use tokio::{sync::mpsc, time};
@@ -19,6 +27,8 @@ use tracing::{debug, info, warn};
 struct PeerData {
     conn: PeerConnection,
     reqs: Vec<(BlockHash, u64)>,
+    /// When each outstanding block request was sent, for latency tracking.
+    req_times: HashMap<BlockHash, Instant>,
     /// True once any protocol event has been received from this peer (ChainSync, BlockFetch,
     /// etc.). Used to distinguish a cold-promoted peer that never managed to connect from
     /// one that ran successfully and then disconnected.
@@ -30,6 +40,7 @@ impl PeerData {
         Self {
             conn,
             reqs: vec![],
+            req_times: HashMap::new(),
             established: false,
         }
     }
@@ -43,11 +54,11 @@ impl PeerData {
         }
     }
 
-    fn request_block(&mut self, hash: BlockHash, slot: u64) -> bool {
+    fn request_block(&mut self, hash: BlockHash, slot: u64, parent: Option<BlockHash>) -> bool {
         if self.reqs.contains(&(hash, slot)) {
             return true;
         }
-        if let Err(error) = self.conn.request_block(hash, slot) {
+        if let Err(error) = self.conn.request_block(hash, slot, parent) {
             warn!(
                 "could not request block from {}: {error:#}",
                 self.conn.address
@@ -55,11 +66,18 @@ impl PeerData {
             return false;
         }
         self.reqs.push((hash, slot));
+        self.req_times.insert(hash, Instant::now());
         true
     }
 
-    fn ack_block(&mut self, hash: BlockHash) {
+    fn ack_block(&mut self, hash: BlockHash) -> Option<Duration> {
         self.reqs.retain(|(h, _)| *h != hash);
+        self.req_times.remove(&hash).map(|sent| sent.elapsed())
+    }
+
+    fn clear_requests(&mut self) {
+        self.reqs.clear();
+        self.req_times.clear();
     }
 }
 
@@ -67,19 +85,23 @@ pub struct NetworkManager {
     network_magic: u32,
     next_id: u64,
     peers: BTreeMap<PeerId, PeerData>,
+    /// Warm peers: connected and handshaken, but not syncing or fetching blocks. These are
+    /// promoted to hot ahead of cold peers as they are known to be reachable.
+    warm: BTreeMap<PeerId, PeerData>,
+    target_warm_peers: usize,
     events: mpsc::Receiver<NetworkEvent>,
     events_sender: mpsc::Sender<NetworkEvent>,
     block_sink: BlockSink,
     published_blocks: u64,
     /// Wanted blocks that could not be fetched immediately because
-    /// no current announcer was available. Retried opportunistically when
-    /// peer events arrive.
+    /// no current announcer was available, or every announcer's in-flight
+    /// window was full. Retried opportunistically when peer events arrive.
     pending_wanted: BTreeMap<(u64, BlockHash), ()>,
     sync_point: Option<Point>,
     flow_handler: BlockFlowHandler,
     pub peer_manager: Option<PeerManager>,
     min_hot_peers: usize,
-    /// PeerIds of peers that were promoted from the cold list via `try_promote_cold_peer`.
+    /// PeerIds of peers that were promoted from the warm or cold lists via `try_promote_peer`.
     /// Used in `on_peer_disconnected` to distinguish cold-promoted peers from initially configured connections.
     cold_origin: HashSet<PeerId>,
     /// Addresses from the static `node_addresses` config. Configured peers are always
@@ -89,6 +111,22 @@ pub struct NetworkManager {
     ipv6_enabled: bool,
     allow_non_public_peer_addrs: bool,
     discovery_interval: Duration,
+    /// Per-peer performance statistics, shared with the peer scores query handler.
+    peer_scores: Arc<Mutex<PeerScores>>,
+    /// Use peer scores to pick block fetch sources and churn victims.
+    peer_scoring_enabled: bool,
+    /// Hot peers scoring below this are replaced at the next churn tick.
+    min_peer_score: f64,
+    /// Most outstanding block requests per peer. Once every announcer of a block is at the
+    /// limit, the block waits in `pending_wanted`. Zero disables the limit.
+    max_blocks_in_flight: usize,
+    /// Builds the periodic network metrics message from per-connection counters.
+    metrics: MetricsCollector,
+    metrics_topic: String,
+    /// Time between network metrics messages. Zero disables them.
+    metrics_interval: Duration,
+    /// Upstream allow/deny rules and the rate limit on new cold peer connections.
+    access: AccessControl,
 }
 
 impl NetworkManager {
@@ -102,6 +140,7 @@ impl NetworkManager {
         flow_handler: BlockFlowHandler,
         target_peer_count: usize,
         min_hot_peers: usize,
+        target_warm_peers: usize,
         peer_sharing_enabled: bool,
         churn_interval_secs: u64,
         peer_sharing_timeout_secs: u64,
@@ -110,6 +149,13 @@ impl NetworkManager {
         allow_non_public_peer_addrs: bool,
         discovery_interval_secs: u64,
         peer_sharing_cooldown_secs: u64,
+        peer_scores: Arc<Mutex<PeerScores>>,
+        peer_scoring_enabled: bool,
+        min_peer_score: f64,
+        max_blocks_in_flight: usize,
+        metrics_topic: String,
+        metrics_interval_secs: u64,
+        access: AccessControl,
     ) -> Self {
         let peer_manager = if peer_sharing_enabled {
             Some(PeerManager::new(PeerManagerConfig {
@@ -124,12 +170,24 @@ impl NetworkManager {
             None
         };
 
+        let node_addresses: Vec<String> = node_addresses
+            .into_iter()
+            .filter(|address| {
+                let permitted = access.permits(address);
+                if !permitted {
+                    warn!(address = %address, "configured peer denied by access rules");
+                }
+                permitted
+            })
+            .collect();
         let configured_addrs: HashSet<String> = node_addresses.iter().cloned().collect();
 
         let mut manager = Self {
             network_magic,
             next_id: 0,
             peers: BTreeMap::new(),
+            warm: BTreeMap::new(),
+            target_warm_peers,
             events,
             events_sender,
             block_sink,
@@ -145,6 +203,14 @@ impl NetworkManager {
             ipv6_enabled,
             allow_non_public_peer_addrs,
             discovery_interval: Duration::from_secs(discovery_interval_secs),
+            peer_scores,
+            peer_scoring_enabled,
+            min_peer_score,
+            max_blocks_in_flight,
+            metrics: MetricsCollector::new(Arc::new(TrafficCounters::default())),
+            metrics_topic,
+            metrics_interval: Duration::from_secs(metrics_interval_secs),
+            access,
         };
 
         if peer_sharing_enabled {
@@ -164,6 +230,7 @@ impl NetworkManager {
                 }
                 manager.handle_new_connection(address, Duration::ZERO);
             }
+            manager.fill_warm_peers();
         } else {
             // Disabled mode: connect all addresses immediately
             for address in node_addresses {
@@ -185,6 +252,8 @@ impl NetworkManager {
         churn_ticker.tick().await; // skip the immediate first tick
         let mut discovery_ticker = time::interval(self.discovery_interval);
         discovery_ticker.tick().await; // skip the immediate first tick
+        let mut metrics_ticker = time::interval(self.metrics_interval.max(Duration::from_secs(1)));
+        metrics_ticker.tick().await; // skip the immediate first tick
 
         loop {
             tokio::select! {
@@ -200,6 +269,9 @@ impl NetworkManager {
                 _ = discovery_ticker.tick(), if self.peer_manager.is_some() => {
                     self.on_discovery_tick();
                 }
+                _ = metrics_ticker.tick(), if !self.metrics_interval.is_zero() => {
+                    self.publish_metrics().await;
+                }
             }
         }
 
@@ -212,10 +284,12 @@ impl NetworkManager {
                 from_peer,
                 addresses,
             } => {
-                let hot: HashSet<String> =
-                    self.peers.values().map(|p| p.conn.address.clone()).collect();
+                let hot = self.connected_addrs();
+                let received = addresses.len();
+                let addresses: Vec<String> =
+                    addresses.into_iter().filter(|a| self.access.permits(a)).collect();
+                let denied = received - addresses.len();
                 if let Some(ref mut pm) = self.peer_manager {
-                    let received = addresses.len();
                     let queried_peer = self
                         .peers
                         .get(&from_peer)
@@ -225,17 +299,19 @@ impl NetworkManager {
                     info!(
                         queried_peer,
                         received,
+                        denied,
                         added,
                         cold_count = pm.cold_count(),
                         "peer-sharing discovery batch complete"
                     );
                 }
-                // Promote cold peers to fill up to min_hot_peers.
+                // Promote peers to fill up to min_hot_peers, then top up the warm set.
                 while self.peers.len() < self.min_hot_peers {
-                    if !self.try_promote_cold_peer() {
+                    if !self.try_promote_peer() {
                         break;
                     }
                 }
+                self.fill_warm_peers();
             }
             NetworkEvent::PeerUpdate { peer, event } => {
                 self.handle_peer_update(peer, event);
@@ -246,7 +322,7 @@ impl NetworkManager {
                 self.pending_wanted.clear();
 
                 for peer in self.peers.values_mut() {
-                    peer.reqs.clear();
+                    peer.clear_requests();
                 }
 
                 if let Point::Specific(slot, _) = point {
@@ -262,8 +338,8 @@ impl NetworkManager {
             }
             NetworkEvent::BlockWanted { hash, slot } => {
                 if let Some(announcers) = self.flow_handler.block_announcers(slot, hash) {
-                    self.request_block(slot, hash, announcers);
                     self.pending_wanted.remove(&(slot, hash));
+                    self.request_block(slot, hash, announcers);
                 } else {
                     if self.flow_handler.knows_block(slot, hash) {
                         warn!(
@@ -284,6 +360,9 @@ impl NetworkManager {
                     self.handle_disconnect(peer);
                 }
             }
+            NetworkEvent::AccessRulesUpdated { rules } => {
+                self.update_access_rules(&rules);
+            }
         }
 
         Ok(())
@@ -292,6 +371,8 @@ impl NetworkManager {
     /// Called when the discovery ticker fires. Selects a cooldown-eligible hot peer and
     /// spawns a peer-sharing exchange task.
     fn on_discovery_tick(&mut self) {
+        self.fill_warm_peers();
+
         let pm = match self.peer_manager.as_mut() {
             Some(pm) => pm,
             None => return,
@@ -375,47 +456,120 @@ impl NetworkManager {
         });
     }
 
-    /// Called when the churn ticker fires. Demotes one randomly selected hot peer
-    /// (above `min_hot_peers`) to cold and promotes a cold peer to maintain count.
+    /// Current health of the peer network, covering hot and warm connections.
+    fn network_metrics(&mut self) -> NetworkMetricsMessage {
+        let hot = self.peers.values().map(|peer| (peer, true));
+        let warm = self.warm.values().map(|peer| (peer, false));
+        let peers = hot
+            .chain(warm)
+            .map(|(peer, is_hot)| peer.conn.metrics().describe(&peer.conn.address, is_hot))
+            .collect();
+        let cold_peers = self.peer_manager.as_ref().map(|pm| pm.cold_count()).unwrap_or(0);
+        self.metrics.collect(peers, cold_peers, Instant::now())
+    }
+
+    async fn publish_metrics(&mut self) {
+        let metrics = self.network_metrics();
+        debug!(
+            hot = metrics.hot_peers,
+            warm = metrics.warm_peers,
+            headers_per_sec = metrics.headers_per_sec,
+            blocks_per_sec = metrics.blocks_per_sec,
+            "publishing network metrics"
+        );
+        let message = Arc::new(Message::Monitoring(MonitoringMessage::Network(metrics)));
+        let bus = &self.block_sink.context.message_bus;
+        if let Err(error) = bus.publish(&self.metrics_topic, message).await {
+            warn!("could not publish network metrics: {error:#}");
+        }
+    }
+
+    /// Called when the churn ticker fires. Replaces any hot peers scoring below
+    /// `min_peer_score`, then demotes one hot peer (above `min_hot_peers`) to cold and
+    /// promotes a cold peer to maintain count. The victim is the lowest scoring peer
+    /// when peer scoring is enabled, otherwise a random one.
     fn on_churn(&mut self) {
+        self.demote_low_scoring_peers();
+
         let hot_count = self.peers.len();
-        let replacement = {
-            let pm = match self.peer_manager.as_mut() {
-                Some(pm) => pm,
-                None => return,
-            };
-            if !pm.should_churn(hot_count) {
-                return;
-            }
-            pm.take_cold_peer()
+        match self.peer_manager.as_ref() {
+            Some(pm) if pm.should_churn(hot_count) => {}
+            _ => return,
+        }
+        let replacement = if self.warm.is_empty() {
+            self.take_permitted_cold_peer()
+        } else {
+            None
         };
 
-        if replacement.is_none() {
+        if self.warm.is_empty() && replacement.is_none() {
             return;
         }
+        let Some(victim_id) = self.select_churn_victim() else {
+            return;
+        };
+        self.demote_peer(victim_id, "churn");
+        match replacement {
+            Some(address) => self.promote_reserved_cold_peer(address),
+            None => {
+                self.promote_warm_peer();
+            }
+        }
+    }
 
-        // Randomly select a hot peer to demote
-        use rand::seq::IteratorRandom;
-        let Some((victim_id, _)) = self.peers.iter().choose(&mut rand::rng()) else {
+    /// Swap out hot peers whose score has fallen below `min_peer_score`, as long as
+    /// there are cold peers to replace them with.
+    fn demote_low_scoring_peers(&mut self) {
+        if !self.peer_scoring_enabled || self.peer_manager.is_none() {
             return;
+        }
+        let low_scoring: Vec<PeerId> = {
+            let scores = self.scores();
+            self.peers
+                .iter()
+                .filter(|(_, p)| scores.score(&p.conn.address) < self.min_peer_score)
+                .map(|(id, _)| *id)
+                .collect()
         };
-        let victim_id = *victim_id;
+        for victim_id in low_scoring {
+            if self.warm.is_empty() {
+                let Some(replacement) = self.take_permitted_cold_peer() else {
+                    break;
+                };
+                self.demote_peer(victim_id, "low score");
+                self.promote_reserved_cold_peer(replacement);
+            } else {
+                self.demote_peer(victim_id, "low score");
+                self.promote_warm_peer();
+            }
+        }
+    }
+
+    /// Drop a hot peer and return it to the cold list.
+    fn demote_peer(&mut self, victim_id: PeerId, reason: &str) {
         let Some(victim) = self.peers.remove(&victim_id) else {
             return;
         };
         self.cold_origin.remove(&victim_id); // clear before ghost disconnect fires
         let address = victim.conn.address.clone();
+        let score = {
+            let mut scores = self.scores();
+            scores.record_demoted(&address);
+            scores.score(&address)
+        };
 
         // Return to cold, bypassing the failed_peers blacklist
         // since a currently hot peer must not be silently discarded.
-        let hot: HashSet<String> = self.peers.values().map(|p| p.conn.address.clone()).collect();
+        let hot = self.connected_addrs();
         if let Some(ref mut pm) = self.peer_manager {
             pm.demote_to_cold(address.clone(), &hot);
             info!(
                 address = %address,
+                score,
+                reason,
                 hot_count = self.peers.len(),
                 cold_count = pm.cold_count(),
-                "peer demoted hot→cold via churn"
+                "peer demoted hot→cold"
             );
         }
 
@@ -423,23 +577,200 @@ impl NetworkManager {
         self.flow_handler.handle_disconnect(victim_id, self.peers.keys().next().copied());
 
         self.rerequest_inflight(victim.reqs);
+    }
 
-        if let Some(address) = replacement {
-            self.promote_reserved_cold_peer(address);
+    fn select_churn_victim(&self) -> Option<PeerId> {
+        if self.peer_scoring_enabled {
+            let scores = self.scores();
+            return self
+                .peers
+                .iter()
+                .min_by(|(_, a), (_, b)| {
+                    scores.score(&a.conn.address).total_cmp(&scores.score(&b.conn.address))
+                })
+                .map(|(id, _)| *id);
         }
+        use rand::seq::IteratorRandom;
+        self.peers.keys().choose(&mut rand::rng()).copied()
     }
 
-    fn try_promote_cold_peer(&mut self) -> bool {
-        let Some(ref mut pm) = self.peer_manager else {
+    fn scores(&self) -> std::sync::MutexGuard<'_, PeerScores> {
+        // Scores are only statistics, so a poisoned lock is still usable.
+        self.peer_scores.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Addresses of all hot and warm peers.
+    fn connected_addrs(&self) -> HashSet<String> {
+        self.peers.values().chain(self.warm.values()).map(|p| p.conn.address.clone()).collect()
+    }
+
+    /// Promote a warm peer to hot, or failing that a cold peer.
+    fn try_promote_peer(&mut self) -> bool {
+        self.promote_warm_peer() || self.try_promote_cold_peer()
+    }
+
+    /// Move the best warm peer into the hot set and start syncing from it. Peers which
+    /// have completed their handshake are preferred over ones still connecting.
+    fn promote_warm_peer(&mut self) -> bool {
+        let candidate = {
+            let scores = self.scores();
+            self.warm
+                .iter()
+                .max_by(|(_, a), (_, b)| {
+                    a.established.cmp(&b.established).then_with(|| {
+                        scores.score(&a.conn.address).total_cmp(&scores.score(&b.conn.address))
+                    })
+                })
+                .map(|(id, _)| *id)
+        };
+        let Some(id) = candidate else {
             return false;
         };
-        let Some(addr) = pm.take_cold_peer() else {
+        let Some(peer) = self.warm.remove(&id) else {
+            return false;
+        };
+        info!(
+            address = %peer.conn.address,
+            hot_count = self.peers.len() + 1,
+            warm_count = self.warm.len(),
+            "promoting warm peer to hot"
+        );
+        self.scores().record_connected(&peer.conn.address);
+        let points = self.flow_handler.handle_new_connection(
+            id,
+            &peer.conn.address,
+            self.sync_point.as_ref(),
+        );
+        peer.find_intersect(points);
+        self.peers.insert(id, peer);
+        self.cold_origin.insert(id);
+        true
+    }
+
+    /// Open warm connections to cold peers until `target_warm_peers` is reached.
+    fn fill_warm_peers(&mut self) {
+        while self.warm.len() < self.target_warm_peers {
+            let Some(address) = self.take_permitted_cold_peer() else {
+                return;
+            };
+            debug!(address = %address, warm_count = self.warm.len() + 1, "promoting cold peer to warm");
+            let id = PeerId(self.next_id);
+            self.next_id += 1;
+            let sender = PeerMessageSender {
+                sink: self.events_sender.clone(),
+                id,
+            };
+            let conn = PeerConnection::new(
+                address,
+                self.network_magic,
+                sender,
+                Duration::ZERO,
+                self.connect_timeout,
+                self.metrics.totals(),
+            );
+            self.warm.insert(id, PeerData::new(conn));
+        }
+    }
+
+    /// Warm peers only report connection state. One that never connected is blacklisted
+    /// like a failed cold promotion; otherwise it goes back to cold.
+    fn handle_warm_peer_update(&mut self, id: PeerId, event: PeerEvent) {
+        match event {
+            PeerEvent::Connected => {
+                if let Some(peer) = self.warm.get_mut(&id) {
+                    peer.established = true;
+                }
+            }
+            PeerEvent::Disconnected => {
+                let Some(peer) = self.warm.remove(&id) else {
+                    return;
+                };
+                let address = peer.conn.address;
+                let is_configured = self.configured_addrs.contains(&address);
+                let hot = self.connected_addrs();
+                let Some(ref mut pm) = self.peer_manager else {
+                    return;
+                };
+                if !peer.established && !is_configured {
+                    info!(address = %address, "warm peer never connected — blacklisting for session");
+                    pm.mark_failed(address);
+                } else {
+                    debug!(address = %address, "warm peer disconnected, returning to cold");
+                    pm.demote_to_cold(address, &hot);
+                }
+            }
+            other => {
+                debug!(peer_id = id.0, "ignoring event from warm peer: {other:?}");
+            }
+        }
+    }
+
+    fn try_promote_cold_peer(&mut self) -> bool {
+        let Some(addr) = self.take_permitted_cold_peer() else {
             return false;
         };
         self.promote_reserved_cold_peer(addr);
         true
     }
 
+    /// Take a cold peer to connect to, unless the connection rate limit has been reached.
+    /// The cold set only ever holds peers the current access rules permit.
+    fn take_permitted_cold_peer(&mut self) -> Option<String> {
+        let pm = self.peer_manager.as_mut()?;
+        if pm.cold_count() == 0 {
+            return None;
+        }
+        if !self.access.try_connect(Instant::now()) {
+            debug!(
+                cold_count = pm.cold_count(),
+                "connection rate limit reached, not connecting to cold peer"
+            );
+            return None;
+        }
+        pm.take_cold_peer()
+    }
+
+    /// Apply new access rules, dropping hot, warm and cold peers they no longer permit
+    /// and topping the hot and warm sets back up from the remaining peers.
+    fn update_access_rules(&mut self, rules: &PeerAccessRules) {
+        if let Err(error) = self.access.set_rules(rules) {
+            warn!("rejected access rules update: {error:#}");
+            return;
+        }
+        let denied_hot: Vec<PeerId> = self
+            .peers
+            .iter()
+            .filter(|(_, p)| !self.access.permits(&p.conn.address))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &denied_hot {
+            self.demote_peer(*id, "access rules");
+        }
+        let warm_count = self.warm.len();
+        self.warm.retain(|_, p| self.access.permits(&p.conn.address));
+        let removed_cold = self
+            .peer_manager
+            .as_mut()
+            .map(|pm| pm.retain_cold(|addr| self.access.permits(addr)))
+            .unwrap_or(0);
+        info!(
+            allow = rules.allow.len(),
+            deny = rules.deny.len(),
+            max_connections_per_minute = rules.max_connections_per_minute,
+            dropped_hot = denied_hot.len(),
+            dropped_warm = warm_count - self.warm.len(),
+            removed_cold,
+            "access rules updated"
+        );
+
+        while self.peers.len() < self.min_hot_peers {
+            if !self.try_promote_peer() {
+                break;
+            }
+        }
+        self.fill_warm_peers();
+    }
+
     fn promote_reserved_cold_peer(&mut self, addr: String) {
         let cold_count = self.peer_manager.as_ref().map(|pm| pm.cold_count()).unwrap_or(0);
         info!(
@@ -459,15 +790,21 @@ impl NetworkManager {
             sink: self.events_sender.clone(),
             id,
         };
+        self.scores().record_connected(&address);
         let conn = PeerConnection::new(
             address,
             self.network_magic,
             sender,
             delay,
             self.connect_timeout,
+            self.metrics.totals(),
         );
         let peer = PeerData::new(conn);
-        let points = self.flow_handler.handle_new_connection(id, self.sync_point.as_ref());
+        let points = self.flow_handler.handle_new_connection(
+            id,
+            &peer.conn.address,
+            self.sync_point.as_ref(),
+        );
         peer.find_intersect(points);
         self.peers.insert(id, peer);
         id
@@ -506,6 +843,11 @@ impl NetworkManager {
     // or when publishing messages to other modules. This avoids deadlock; if our event queue
     // is full and this method is blocked on writing to it, the queue can never drain.
     fn handle_peer_update(&mut self, peer: PeerId, event: PeerEvent) {
+        if self.warm.contains_key(&peer) {
+            self.handle_warm_peer_update(peer, event);
+            return;
+        }
+
         // Mark established on any protocol event so we can distinguish a cold-promoted
         // peer that never managed to connect from one that ran and then disconnected.
         if !matches!(event, PeerEvent::Disconnected)
@@ -520,6 +862,12 @@ impl NetworkManager {
                 let slot = header.slot;
                 let hash = header.hash;
 
+                // A header for a block we already know from another peer adds nothing
+                if let Some(p) = self.peers.get(&peer) {
+                    let useless = self.flow_handler.knows_block(slot, hash);
+                    self.scores().record_header(&p.conn.address, useless);
+                }
+
                 // TODO: Temporary. In Direct mode returns announcers then fetches blocks, in
                 // Consensus mode: returns None, so no block fetch
                 if let Some(peers) = self.flow_handler.handle_roll_forward(peer, header) {
@@ -540,11 +888,19 @@ impl NetworkManager {
                 }
             }
             PeerEvent::BlockFetched(fetched) => {
-                for peer in self.peers.values_mut() {
-                    peer.ack_block(fetched.hash);
+                let mut scores = self.peer_scores.lock().unwrap_or_else(|e| e.into_inner());
+                for (id, p) in self.peers.iter_mut() {
+                    let latency = p.ack_block(fetched.hash);
+                    if *id == peer
+                        && let Some(latency) = latency
+                    {
+                        scores.record_fetch(&p.conn.address, latency);
+                    }
                 }
+                drop(scores);
                 self.flow_handler.handle_block_fetched(fetched.slot, fetched.hash, fetched.body);
             }
+            PeerEvent::Connected => {}
             PeerEvent::Disconnected => {
                 self.handle_disconnect(peer);
             }
@@ -580,6 +936,7 @@ impl NetworkManager {
         let is_configured = self.configured_addrs.contains(&peer.conn.address);
         let established = peer.established;
         let address = peer.conn.address.clone();
+        self.scores().record_disconnect(&address);
 
         // The next peer is temporary needed for Direct mode flow handler only
         self.flow_handler.handle_disconnect(id, self.peers.keys().next().copied());
@@ -588,7 +945,7 @@ impl NetworkManager {
 
         if self.peer_manager.is_none() {
             // Disabled mode: reconnect with 5s backoff
-            self.handle_new_connection(address, Duration::from_secs(5));
+            self.reconnect(address);
             return;
         }
 
@@ -606,7 +963,7 @@ impl NetworkManager {
             }
             // Fill the vacancy if below minimum.
             if self.peers.len() < self.min_hot_peers {
-                let _ = self.try_promote_cold_peer();
+                let _ = self.try_promote_peer();
             }
             return;
         }
@@ -615,23 +972,31 @@ impl NetworkManager {
         // promoted to replace them. Discovered peers return to cold so they can be
         // re-promoted later.
         let needs_promotion = self.peers.len() < self.min_hot_peers;
-        let promoted = needs_promotion && self.try_promote_cold_peer();
+        let promoted = needs_promotion && self.try_promote_peer();
         if promoted {
             if is_configured {
-                self.handle_new_connection(address, Duration::from_secs(5));
+                self.reconnect(address);
             } else {
-                let hot: HashSet<String> =
-                    self.peers.values().map(|p| p.conn.address.clone()).collect();
+                let hot = self.connected_addrs();
                 if let Some(ref mut pm) = self.peer_manager {
                     pm.demote_to_cold(address, &hot);
                 }
             }
         } else {
             // Cold list empty, not below minimum, or not P2P mode — reconnect directly.
-            self.handle_new_connection(address, Duration::from_secs(5));
+            self.reconnect(address);
         }
     }
 
+    /// Reconnect to a peer with a 5s backoff, unless the access rules now deny it.
+    fn reconnect(&mut self, address: String) {
+        if !self.access.permits(&address) {
+            info!(address = %address, "not reconnecting to peer denied by access rules");
+            return;
+        }
+        self.handle_new_connection(address, Duration::from_secs(5));
+    }
+
     fn handle_disconnect(&mut self, id: PeerId) {
         self.on_peer_disconnected(id);
     }
@@ -649,8 +1014,8 @@ impl NetworkManager {
             }
 
             if let Some(announcers) = self.flow_handler.block_announcers(slot, hash) {
-                self.request_block(slot, hash, announcers);
                 self.pending_wanted.remove(&(slot, hash));
+                self.request_block(slot, hash, announcers);
             }
         }
     }
@@ -663,12 +1028,42 @@ impl NetworkManager {
         }
     }
 
-    fn request_block(&mut self, slot: u64, hash: BlockHash, announcers: Vec<PeerId>) {
+    /// Request a block from the first announcer which accepts the request, trying the
+    /// best scoring announcers first when peer scoring is enabled. Announcers whose
+    /// in-flight window is full are skipped, so that a run of blocks is fetched from
+    /// several peers in parallel; if all of them are full, the block is queued in
+    /// `pending_wanted` until one of them has fetched a block.
+    fn request_block(&mut self, slot: u64, hash: BlockHash, mut announcers: Vec<PeerId>) {
+        if announcers
+            .iter()
+            .any(|id| self.peers.get(id).is_some_and(|p| p.reqs.contains(&(hash, slot))))
+        {
+            return; // already in flight
+        }
+        if self.peer_scoring_enabled {
+            let mut ranked: Vec<(PeerId, String)> = announcers
+                .iter()
+                .filter_map(|id| self.peers.get(id).map(|p| (*id, p.conn.address.clone())))
+                .collect();
+            self.scores().rank(&mut ranked);
+            announcers = ranked.into_iter().map(|(id, _)| id).collect();
+        }
+        if self.max_blocks_in_flight > 0 {
+            let known = announcers.iter().any(|id| self.peers.contains_key(id));
+            announcers.retain(|id| {
+                self.peers.get(id).is_some_and(|p| p.reqs.len() < self.max_blocks_in_flight)
+            });
+            if known && announcers.is_empty() {
+                self.pending_wanted.insert((slot, hash), ());
+                return;
+            }
+        }
+        let parent = self.flow_handler.block_parent(slot, hash);
         for announcer in announcers {
             let Some(peer) = self.peers.get_mut(&announcer) else {
                 continue;
             };
-            if peer.request_block(hash, slot) {
+            if peer.request_block(hash, slot, parent) {
                 break; // only fetch from one
             } else {
                 self.handle_disconnect(announcer);
@@ -698,6 +1093,10 @@ pub enum NetworkEvent {
         from_peer: PeerId,
         addresses: Vec<String>,
     },
+    /// New upstream access rules, received as a runtime command.
+    AccessRulesUpdated {
+        rules: PeerAccessRules,
+    },
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -727,6 +1126,7 @@ mod tests {
     use crate::configuration::{InterfaceConfig, SyncPoint};
     use crate::connection::{Header, PeerChainSyncEvent, PeerEvent};
     use acropolis_common::configuration::BlockFlowMode;
+    use acropolis_common::genesis_values::Network;
     use acropolis_common::messages::Message;
     use acropolis_common::{BlockHash, Era};
     use acropolis_test_utils::mainnet_genesis_values;
@@ -753,6 +1153,7 @@ mod tests {
             last_epoch: None,
             era: None,
             rolled_back: false,
+            sync_stop: None,
         }
     }
 
@@ -763,12 +1164,18 @@ mod tests {
             genesis_completion_topic: "test.sequence.bootstrapped".to_string(),
             sync_command_topic: "test.sync.command".to_string(),
             node_addresses: vec![],
+            network: Network::Mainnet,
             cache_dir: PathBuf::from("/tmp"),
+            cache_verify: true,
+            cache_compact: false,
+            cache_max_bytes: 0,
             genesis_values: None,
             consensus_topic: "test.consensus.offers".to_string(),
             block_wanted_topic: "test.consensus.wants".to_string(),
             target_peer_count: 15,
             min_hot_peers: 3,
+            target_warm_peers: 0,
+            topology_file: None,
             peer_sharing_enabled: true,
             churn_interval_secs: 600,
             peer_sharing_timeout_secs: 10,
@@ -777,6 +1184,15 @@ mod tests {
             allow_non_public_peer_addrs: true,
             discovery_interval_secs: 0,
             peer_sharing_cooldown_secs: 0,
+            peer_scoring_enabled: true,
+            min_peer_score: 0.0,
+            network_query_topic: "test.query.network".to_string(),
+            max_blocks_in_flight: 0,
+            metrics_topic: "test.monitor.network".to_string(),
+            metrics_interval_secs: 0,
+            allow_peers: vec![],
+            deny_peers: vec![],
+            max_connections_per_minute: 0,
         }
     }
 
@@ -802,6 +1218,7 @@ mod tests {
             flow_handler,
             cfg.target_peer_count,
             cfg.min_hot_peers,
+            cfg.target_warm_peers,
             cfg.peer_sharing_enabled,
             cfg.churn_interval_secs,
             cfg.peer_sharing_timeout_secs,
@@ -810,6 +1227,13 @@ mod tests {
             cfg.allow_non_public_peer_addrs,
             cfg.discovery_interval_secs,
             cfg.peer_sharing_cooldown_secs,
+            Arc::new(Mutex::new(PeerScores::new())),
+            cfg.peer_scoring_enabled,
+            cfg.min_peer_score,
+            cfg.max_blocks_in_flight,
+            cfg.metrics_topic.clone(),
+            cfg.metrics_interval_secs,
+            AccessControl::new(&cfg.access_rules()).unwrap(),
         )
     }
 
@@ -833,6 +1257,7 @@ mod tests {
             sender,
             Duration::from_secs(3600),
             Duration::from_secs(15),
+            manager.metrics.totals(),
         );
         manager.peers.insert(peer, PeerData::new(conn));
     }
@@ -941,6 +1366,7 @@ mod tests {
             sender,
             Duration::from_secs(3600),
             Duration::from_secs(15),
+            manager.metrics.totals(),
         );
         manager.peers.insert(peer, PeerData::new(conn));
     }
@@ -1149,4 +1575,401 @@ mod tests {
             "stale entry for unknown block should be evicted"
         );
     }
+
+    #[tokio::test]
+    async fn churn_demotes_lowest_scoring_peer() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            min_hot_peers: 2,
+            ..default_test_cfg()
+        })
+        .await;
+
+        if let Some(ref mut pm) = manager.peer_manager {
+            pm.seed(&["cold.peer.example.com:3001".to_string()], &HashSet::new());
+        }
+        for i in 1u64..=4 {
+            add_test_peer_with_address(&mut manager, PeerId(i), &format!("10.0.0.{}:3001", i));
+        }
+        manager.scores().record_disconnect("10.0.0.3:3001");
+
+        manager.on_churn();
+
+        assert!(
+            !manager.peers.contains_key(&PeerId(3)),
+            "the lowest scoring peer should be churned out"
+        );
+        assert_eq!(manager.peers.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn churn_replaces_peers_below_min_score() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            min_hot_peers: 3,
+            min_peer_score: 90.0,
+            ..default_test_cfg()
+        })
+        .await;
+
+        if let Some(ref mut pm) = manager.peer_manager {
+            pm.seed(&["cold.peer.example.com:3001".to_string()], &HashSet::new());
+        }
+        for i in 1u64..=3 {
+            add_test_peer_with_address(&mut manager, PeerId(i), &format!("10.0.0.{}:3001", i));
+        }
+        for _ in 0..3 {
+            manager.scores().record_disconnect("10.0.0.2:3001");
+        }
+
+        manager.on_churn();
+
+        assert!(
+            !manager.peers.contains_key(&PeerId(2)),
+            "peer below min score should be demoted even at min_hot_peers"
+        );
+        assert_eq!(manager.peers.len(), 3, "demoted peer should be replaced");
+        let pm = manager.peer_manager.as_ref().unwrap();
+        assert!(pm.contains_cold("10.0.0.2:3001"));
+    }
+
+    #[tokio::test]
+    async fn request_block_prefers_best_scoring_announcer() {
+        let mut manager = test_consensus_manager().await;
+        add_test_peer_with_address(&mut manager, PeerId(1), "slow.peer:3001");
+        add_test_peer_with_address(&mut manager, PeerId(2), "fast.peer:3001");
+        manager.scores().record_fetch("slow.peer:3001", Duration::from_millis(1500));
+        manager.scores().record_fetch("fast.peer:3001", Duration::from_millis(50));
+
+        let slot = 400;
+        let hash = BlockHash::new([6; 32]);
+        manager.request_block(slot, hash, vec![PeerId(1), PeerId(2)]);
+
+        assert!(manager.peers[&PeerId(2)].reqs.contains(&(hash, slot)));
+        assert!(manager.peers[&PeerId(1)].reqs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn block_fetched_records_latency_for_fetching_peer() {
+        let mut manager = test_consensus_manager().await;
+        add_test_peer_with_address(&mut manager, PeerId(1), "10.0.0.1:3001");
+
+        let slot = 500;
+        let hash = BlockHash::new([7; 32]);
+        manager.request_block(slot, hash, vec![PeerId(1)]);
+        manager.handle_peer_update(
+            PeerId(1),
+            PeerEvent::BlockFetched(crate::connection::BlockFetched {
+                hash,
+                slot,
+                body: vec![],
+            }),
+        );
+
+        let scores = manager.scores();
+        let stats = scores.stats("10.0.0.1:3001").unwrap();
+        assert_eq!(stats.blocks_fetched, 1);
+        assert!(stats.latency_ms.is_some());
+    }
+
+    fn add_test_warm_peer(manager: &mut NetworkManager, peer: PeerId, address: &str) {
+        let sender = PeerMessageSender {
+            sink: manager.events_sender.clone(),
+            id: peer,
+        };
+        let conn = PeerConnection::new(
+            address.to_string(),
+            0,
+            sender,
+            Duration::from_secs(3600),
+            Duration::from_secs(15),
+            manager.metrics.totals(),
+        );
+        manager.warm.insert(peer, PeerData::new(conn));
+    }
+
+    #[tokio::test]
+    async fn fills_warm_peers_from_cold_up_to_target() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            target_warm_peers: 2,
+            ..default_test_cfg()
+        })
+        .await;
+
+        if let Some(ref mut pm) = manager.peer_manager {
+            let cold: Vec<String> = (1..=3).map(|i| format!("10.0.1.{i}:3001")).collect();
+            pm.seed(&cold, &HashSet::new());
+        }
+
+        manager.fill_warm_peers();
+
+        assert_eq!(manager.warm.len(), 2);
+        assert!(manager.peers.is_empty(), "warm peers must not be hot");
+        assert_eq!(manager.peer_manager.as_ref().unwrap().cold_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn hot_vacancy_is_filled_from_warm_before_cold() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            min_hot_peers: 1,
+            ..default_test_cfg()
+        })
+        .await;
+
+        if let Some(ref mut pm) = manager.peer_manager {
+            pm.seed(&["cold.peer.example.com:3001".to_string()], &HashSet::new());
+        }
+        add_test_warm_peer(&mut manager, PeerId(50), "warm.peer.example.com:3001");
+        manager.handle_peer_update(PeerId(50), PeerEvent::Connected);
+        add_test_peer_with_address(&mut manager, PeerId(100), "hot.peer.example.com:3001");
+
+        manager.on_peer_disconnected(PeerId(100));
+
+        assert!(manager.warm.is_empty());
+        assert!(
+            manager.peers.contains_key(&PeerId(50)),
+            "warm peer should have been promoted to hot"
+        );
+        let pm = manager.peer_manager.as_ref().unwrap();
+        assert!(
+            pm.contains_cold("cold.peer.example.com:3001"),
+            "cold peer should be left alone while a warm peer is available"
+        );
+    }
+
+    #[tokio::test]
+    async fn warm_peer_that_never_connects_is_blacklisted() {
+        let mut manager = test_manager_from_cfg(default_test_cfg()).await;
+        add_test_warm_peer(&mut manager, PeerId(1), "unreachable.example.com:3001");
+        add_test_warm_peer(&mut manager, PeerId(2), "flaky.example.com:3001");
+        manager.handle_peer_update(PeerId(2), PeerEvent::Connected);
+
+        manager.handle_peer_update(PeerId(1), PeerEvent::Disconnected);
+        manager.handle_peer_update(PeerId(2), PeerEvent::Disconnected);
+
+        assert!(manager.warm.is_empty());
+        let pm = manager.peer_manager.as_mut().unwrap();
+        assert!(!pm.contains_cold("unreachable.example.com:3001"));
+        assert!(pm.contains_cold("flaky.example.com:3001"));
+        assert_eq!(
+            pm.add_discovered(
+                vec!["unreachable.example.com:3001".to_string()],
+                &HashSet::new()
+            ),
+            0,
+            "blacklisted warm peer must not be re-discovered"
+        );
+    }
+
+    #[tokio::test]
+    async fn request_block_spreads_across_announcers_when_window_is_full() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            peer_sharing_enabled: false,
+            max_blocks_in_flight: 2,
+            ..default_test_cfg()
+        })
+        .await;
+        add_test_peer_with_address(&mut manager, PeerId(1), "10.0.0.1:3001");
+        add_test_peer_with_address(&mut manager, PeerId(2), "10.0.0.2:3001");
+
+        for i in 0..5u8 {
+            let hash = BlockHash::new([10 + i; 32]);
+            manager.request_block(600 + i as u64, hash, vec![PeerId(1), PeerId(2)]);
+        }
+
+        let first = manager.peers[&PeerId(1)].reqs.len();
+        let second = manager.peers[&PeerId(2)].reqs.len();
+        assert_eq!(
+            (first, second),
+            (2, 2),
+            "peer 1 fills its window, then peer 2"
+        );
+        assert_eq!(
+            manager.pending_wanted.len(),
+            1,
+            "the block neither peer has room for waits"
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_requests_never_exceed_the_in_flight_window() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            peer_sharing_enabled: false,
+            max_blocks_in_flight: 2,
+            ..default_test_cfg()
+        })
+        .await;
+        let peers = [PeerId(1), PeerId(2)];
+        add_test_peer_with_address(&mut manager, peers[0], "10.0.0.1:3001");
+        add_test_peer_with_address(&mut manager, peers[1], "10.0.0.2:3001");
+
+        // Both peers announce a chain of ten blocks
+        let blocks: Vec<(u64, BlockHash)> =
+            (0..10u8).map(|i| (700 + i as u64, BlockHash::new([20 + i; 32]))).collect();
+        for peer in peers {
+            manager.flow_handler.handle_tip(peer, Point::Specific(709, vec![29; 32]));
+            let mut parent = BlockHash::new([19; 32]);
+            for (number, (slot, hash)) in blocks.iter().enumerate() {
+                let header = test_header(*slot, number as u64, *hash, parent);
+                let _ = manager.flow_handler.handle_roll_forward(peer, header);
+                parent = *hash;
+            }
+        }
+        for (slot, hash) in &blocks {
+            manager.request_block(*slot, *hash, peers.to_vec());
+        }
+
+        let in_flight =
+            |manager: &NetworkManager| peers.map(|peer| manager.peers[&peer].reqs.len());
+        assert_eq!(in_flight(&manager), [2, 2]);
+        assert_eq!(manager.pending_wanted.len(), 6);
+
+        // Each fetched block frees a slot for the next queued one
+        let mut fetched = vec![];
+        while let Some((peer, (hash, slot))) =
+            peers.iter().find_map(|peer| manager.peers[peer].reqs.first().map(|req| (*peer, *req)))
+        {
+            manager.handle_peer_update(
+                peer,
+                PeerEvent::BlockFetched(crate::connection::BlockFetched {
+                    hash,
+                    slot,
+                    body: vec![],
+                }),
+            );
+            fetched.push(slot);
+            assert!(
+                in_flight(&manager).iter().all(|n| *n <= 2),
+                "in-flight window exceeded: {:?}",
+                in_flight(&manager)
+            );
+        }
+
+        fetched.sort();
+        assert_eq!(
+            fetched,
+            (700..710).collect::<Vec<_>>(),
+            "each block fetched once"
+        );
+        assert!(manager.pending_wanted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn network_metrics_cover_hot_and_warm_connections() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            peer_sharing_enabled: false,
+            ..default_test_cfg()
+        })
+        .await;
+        add_test_peer_with_address(&mut manager, PeerId(1), "10.0.0.1:3001");
+        add_test_warm_peer(&mut manager, PeerId(2), "10.0.0.2:3001");
+
+        manager.peers[&PeerId(1)].conn.metrics().record_header(600);
+        manager.warm[&PeerId(2)].conn.metrics().record_sent(2);
+
+        let metrics = manager.network_metrics();
+        assert_eq!((metrics.hot_peers, metrics.warm_peers), (1, 1));
+        assert_eq!((metrics.bytes_in, metrics.bytes_out), (600, 2));
+        let hot = metrics.peers.iter().find(|p| p.hot).unwrap();
+        assert_eq!(
+            (hot.address.as_str(), hot.headers_received),
+            ("10.0.0.1:3001", 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn access_rules_update_drops_denied_peers() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            min_hot_peers: 1,
+            ..default_test_cfg()
+        })
+        .await;
+        if let Some(ref mut pm) = manager.peer_manager {
+            let cold = [
+                "10.0.0.9:3001".to_string(),
+                "relay.example.com:3001".to_string(),
+            ];
+            pm.seed(&cold, &HashSet::new());
+        }
+        add_test_peer_with_address(&mut manager, PeerId(1), "10.0.0.1:3001");
+        add_test_peer_with_address(&mut manager, PeerId(2), "192.168.1.5:3001");
+        add_test_warm_peer(&mut manager, PeerId(3), "10.0.0.3:3001");
+
+        manager
+            .on_network_event(NetworkEvent::AccessRulesUpdated {
+                rules: PeerAccessRules {
+                    deny: vec!["10.0.0.0/24".to_string()],
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(manager.peers.keys().collect::<Vec<_>>(), vec![&PeerId(2)]);
+        assert!(manager.warm.is_empty());
+        let pm = manager.peer_manager.as_ref().unwrap();
+        assert!(
+            !pm.contains_cold("10.0.0.1:3001"),
+            "denied hot peer must not return to cold"
+        );
+        assert!(!pm.contains_cold("10.0.0.9:3001"));
+        assert!(pm.contains_cold("relay.example.com:3001"));
+    }
+
+    #[tokio::test]
+    async fn invalid_access_rules_are_rejected() {
+        let mut manager = test_manager_from_cfg(default_test_cfg()).await;
+        add_test_peer_with_address(&mut manager, PeerId(1), "10.0.0.1:3001");
+
+        manager.update_access_rules(&PeerAccessRules {
+            deny: vec!["10.0.0.1/33".to_string(), "0.0.0.0/0".to_string()],
+            ..Default::default()
+        });
+
+        assert!(
+            manager.peers.contains_key(&PeerId(1)),
+            "previous rules are kept"
+        );
+    }
+
+    #[tokio::test]
+    async fn denied_peers_are_not_discovered() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            min_hot_peers: 0,
+            allow_peers: vec!["203.0.113.0/24".to_string()],
+            ..default_test_cfg()
+        })
+        .await;
+        manager
+            .on_network_event(NetworkEvent::PeersDiscovered {
+                from_peer: PeerId(1),
+                addresses: vec![
+                    "203.0.113.7:3001".to_string(),
+                    "198.51.100.7:3001".to_string(),
+                ],
+            })
+            .await
+            .unwrap();
+
+        let pm = manager.peer_manager.as_ref().unwrap();
+        assert!(pm.contains_cold("203.0.113.7:3001"));
+        assert!(!pm.contains_cold("198.51.100.7:3001"));
+    }
+
+    #[tokio::test]
+    async fn cold_peer_connections_are_rate_limited() {
+        let mut manager = test_manager_from_cfg(InterfaceConfig {
+            max_connections_per_minute: 2,
+            ..default_test_cfg()
+        })
+        .await;
+        if let Some(ref mut pm) = manager.peer_manager {
+            let cold: Vec<String> = (1..=4).map(|i| format!("10.0.1.{i}:3001")).collect();
+            pm.seed(&cold, &HashSet::new());
+        }
+        manager.target_warm_peers = 4;
+
+        manager.fill_warm_peers();
+
+        assert_eq!(manager.warm.len(), 2);
+        assert_eq!(manager.peer_manager.as_ref().unwrap().cold_count(), 2);
+    }
 }
@@ -142,6 +142,14 @@ impl PeerManager {
         self.failed_peers.insert(address);
     }
 
+    /// Drop cold peers for which `keep` returns false, e.g. after the access rules
+    /// change. Returns the number removed.
+    pub fn retain_cold(&mut self, keep: impl Fn(&str) -> bool) -> usize {
+        let before = self.cold_peers.len();
+        self.cold_peers.retain(|addr| keep(addr));
+        before - self.cold_peers.len()
+    }
+
     /// Returns true if peer-sharing discovery should run.
     ///
     /// Discovery runs continuously whenever `peer_sharing_enabled` and at least one hot
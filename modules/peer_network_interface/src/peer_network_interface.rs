@@ -1,37 +1,52 @@
+pub mod access_control;
 mod block_flow;
 #[cfg(test)]
 mod block_flow_consensus_scenarios_tests;
 mod chain_state;
 mod configuration;
 mod connection;
+pub mod metrics;
 pub(crate) mod network;
 pub mod peer_manager;
+pub mod peer_scoring;
 pub mod peer_sharing;
+pub mod topology;
 
 pub use network::PeerId;
 
 use acropolis_common::{
     BlockInfo, BlockIntent, BlockStatus, Era,
-    commands::chain_sync::ChainSyncCommand,
+    commands::{chain_sync::ChainSyncCommand, peer_network::PeerNetworkCommand},
+    config_reload,
     configuration::BlockFlowMode,
     genesis_values::GenesisValues,
-    messages::{CardanoMessage, Command, Message, RawBlockMessage, StateTransitionMessage},
+    messages::{
+        CardanoMessage, Command, Message, RawBlockMessage, StateQuery, StateQueryResponse,
+        StateTransitionMessage,
+    },
+    queries::{
+        errors::QueryError,
+        network::{NetworkStateQuery, NetworkStateQueryResponse},
+    },
+    sync_stop::SyncStop,
     upstream_cache::{UpstreamCache, UpstreamCacheRecord},
 };
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use caryatid_sdk::{Context, Subscription, module};
 use config::Config;
 use pallas::network::miniprotocols::Point;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-use std::{path::Path, sync::Arc};
+use std::sync::{Arc, Mutex};
 
 use crate::{
+    access_control::AccessControl,
     block_flow::BlockFlowHandler,
-    configuration::{InterfaceConfig, SyncPoint},
+    configuration::{InterfaceConfig, RELOADABLE_KEYS, SyncPoint, reloaded_access_rules},
     connection::Header,
     network::{NetworkEvent, NetworkManager},
+    peer_scoring::PeerScores,
 };
 
 #[module(
@@ -51,9 +66,34 @@ impl PeerNetworkInterface {
             None
         };
         let mut command_subscription = context.subscribe(&cfg.sync_command_topic).await?;
+        let access = AccessControl::new(&cfg.access_rules())?;
 
         let (events_sender, events) = mpsc::channel(1024); // TODO: This might be way too small
 
+        // Peer lists and the connection rate limit can change without a restart
+        let reload_sender = events_sender.clone();
+        config_reload::on_reload(
+            context.clone(),
+            &config,
+            "peer-network-interface",
+            RELOADABLE_KEYS,
+            move |section| {
+                let events_sender = reload_sender.clone();
+                async move {
+                    let rules = reloaded_access_rules(section)?;
+                    AccessControl::new(&rules)?;
+                    events_sender
+                        .send(NetworkEvent::AccessRulesUpdated { rules })
+                        .await
+                        .map_err(|_| anyhow!("network event channel closed"))
+                }
+            },
+        )
+        .await?;
+
+        let peer_scores = Arc::new(Mutex::new(PeerScores::new()));
+        Self::handle_network_queries(&context, &cfg.network_query_topic, peer_scores.clone());
+
         let block_wanted_subscription = if block_flow_mode.is_consensus() {
             Some(context.subscribe(&cfg.block_wanted_topic).await?)
         } else {
@@ -73,6 +113,19 @@ impl PeerNetworkInterface {
                 })
             };
 
+            if let Some(magic) = cfg.network.magic_number()
+                && magic != genesis_values.magic_number
+            {
+                warn!(
+                    "Network '{}' expects magic {}, but genesis has {}",
+                    cfg.network, magic.0, genesis_values.magic_number.0
+                );
+            }
+            if cfg.node_addresses.is_empty() {
+                error!("No node-addresses configured for network '{}'", cfg.network);
+                return;
+            }
+
             let flow_handler = BlockFlowHandler::new(
                 &cfg,
                 block_flow_mode,
@@ -86,7 +139,7 @@ impl PeerNetworkInterface {
             let mut last_epoch = None;
             let mut cache_sync_point = Point::Origin;
             if cfg.sync_point == SyncPoint::Cache {
-                match Self::init_cache(&cfg.cache_dir, &cfg.block_topic, &context).await {
+                match Self::init_cache(&cfg, &context).await {
                     Ok((cache, sync_point)) => {
                         upstream_cache = Some(cache);
                         if let Point::Specific(slot, _) = sync_point {
@@ -109,6 +162,11 @@ impl PeerNetworkInterface {
                 last_epoch,
                 era: None,
                 rolled_back: false,
+                sync_stop: SyncStop::from_config(
+                    context.clone(),
+                    &config,
+                    "peer-network-interface",
+                ),
             };
 
             let sync_point = match cfg.sync_point {
@@ -163,6 +221,7 @@ impl PeerNetworkInterface {
                 flow_handler,
                 cfg.target_peer_count,
                 cfg.min_hot_peers,
+                cfg.target_warm_peers,
                 cfg.peer_sharing_enabled,
                 cfg.churn_interval_secs,
                 cfg.peer_sharing_timeout_secs,
@@ -171,6 +230,13 @@ impl PeerNetworkInterface {
                 cfg.allow_non_public_peer_addrs,
                 cfg.discovery_interval_secs,
                 cfg.peer_sharing_cooldown_secs,
+                peer_scores,
+                cfg.peer_scoring_enabled,
+                cfg.min_peer_score,
+                cfg.max_blocks_in_flight,
+                cfg.metrics_topic,
+                cfg.metrics_interval_secs,
+                access,
             );
 
             match sync_point {
@@ -192,39 +258,111 @@ impl PeerNetworkInterface {
         Ok(())
     }
 
+    fn handle_network_queries(
+        context: &Arc<Context<Message>>,
+        topic: &str,
+        peer_scores: Arc<Mutex<PeerScores>>,
+    ) {
+        info!("Creating query handler on '{}'", topic);
+        context.handle(topic, move |message| {
+            let peer_scores = peer_scores.clone();
+            async move {
+                let Message::StateQuery(StateQuery::Network(query)) = message.as_ref() else {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::Network(
+                        NetworkStateQueryResponse::Error(QueryError::internal_error(
+                            "Invalid message for peer-network-interface",
+                        )),
+                    )));
+                };
+
+                let response = match query {
+                    NetworkStateQuery::GetPeerScores => {
+                        let scores = peer_scores.lock().unwrap_or_else(|e| e.into_inner());
+                        NetworkStateQueryResponse::PeerScores(scores.snapshot())
+                    }
+                    _ => NetworkStateQueryResponse::Error(QueryError::not_implemented(format!(
+                        "Unimplemented query variant: {query:?}"
+                    ))),
+                };
+                Arc::new(Message::StateQueryResponse(StateQueryResponse::Network(
+                    response,
+                )))
+            }
+        });
+    }
+
     async fn forward_commands_to_events(
         mut subscription: Box<dyn Subscription<Message>>,
         events_sender: mpsc::Sender<NetworkEvent>,
     ) {
         while let Ok((_, msg)) = subscription.read().await {
-            if let Message::Command(Command::ChainSync(ChainSyncCommand::FindIntersect(p))) =
-                msg.as_ref()
-            {
-                let point = match p {
-                    acropolis_common::Point::Origin => Point::Origin,
-                    acropolis_common::Point::Specific { hash, slot } => {
-                        Point::Specific(*slot, hash.to_vec())
-                    }
-                };
-
-                if events_sender.send(NetworkEvent::SyncPointUpdate { point }).await.is_err() {
-                    error!("event channel closed");
-                    return;
+            let event = match msg.as_ref() {
+                Message::Command(Command::ChainSync(ChainSyncCommand::FindIntersect(p))) => {
+                    let point = match p {
+                        acropolis_common::Point::Origin => Point::Origin,
+                        acropolis_common::Point::Specific { hash, slot } => {
+                            Point::Specific(*slot, hash.to_vec())
+                        }
+                    };
+                    NetworkEvent::SyncPointUpdate { point }
                 }
+                Message::Command(Command::PeerNetwork(PeerNetworkCommand::SetAccessRules(
+                    rules,
+                ))) => NetworkEvent::AccessRulesUpdated {
+                    rules: rules.clone(),
+                },
+                _ => continue,
+            };
+
+            if events_sender.send(event).await.is_err() {
+                error!("event channel closed");
+                return;
             }
         }
 
         error!("subscription closed");
     }
 
+    /// Check and tidy the cache as configured before it is replayed
+    fn open_cache(cfg: &InterfaceConfig) -> Result<UpstreamCache> {
+        let mut cache = UpstreamCache::new(&cfg.cache_dir)?;
+        if cfg.cache_verify {
+            let report = cache.verify()?;
+            if let Some((chunk, error)) = &report.bad_chunk {
+                warn!(
+                    "Upstream cache chunk {chunk} is damaged ({error}), dropping it and later chunks"
+                );
+                cache.repair()?;
+            }
+            info!(
+                "Upstream cache holds {} blocks in {} chunks",
+                report.records, report.chunks
+            );
+        }
+        if cfg.cache_compact {
+            let dropped = cache.compact()?;
+            info!("Compacted upstream cache, dropping {dropped} rolled back blocks");
+        }
+        if cfg.cache_max_bytes > 0 {
+            let expired = cache.expire(cfg.cache_max_bytes)?;
+            if expired > 0 {
+                info!("Expired {expired} upstream cache chunks");
+            }
+        }
+        Ok(cache)
+    }
+
     async fn init_cache(
-        cache_dir: &Path,
-        block_topic: &str,
+        cfg: &InterfaceConfig,
         context: &Context<Message>,
     ) -> Result<(UpstreamCache, Point)> {
-        let mut cache = UpstreamCache::new(cache_dir)?;
+        let mut cache = Self::open_cache(cfg)?;
+        let block_topic = &cfg.block_topic;
         let mut cache_sync_point = None;
         cache.start_reading()?;
+        if let Some(record) = cache.read_record()? {
+            info!("Replaying upstream cache from block {}", record.id.number);
+        }
         while let Some(record) = cache.read_record()? {
             cache_sync_point = Some((record.id.slot, record.id.hash));
             let message = Arc::new(Message::Cardano((
@@ -280,6 +418,8 @@ struct BlockSink {
     last_epoch: Option<u64>,
     era: Option<Era>,
     rolled_back: bool,
+    /// Blocks past the configured stop point are not published
+    sync_stop: Option<SyncStop>,
 }
 
 impl BlockSink {
@@ -290,6 +430,11 @@ impl BlockSink {
         tip: Option<&Point>,
     ) -> Result<()> {
         let info = self.make_block_info(header, tip);
+        if let Some(sync_stop) = self.sync_stop.as_mut()
+            && !sync_stop.admit(&info).await
+        {
+            return Ok(());
+        }
         let raw_block = RawBlockMessage {
             header: header.bytes.clone(),
             body: body.to_vec(),
@@ -0,0 +1,144 @@
+//! Per-peer performance tracking and scoring for the PNI module.
+use std::{collections::HashMap, time::Duration};
+
+use acropolis_common::queries::network::PeerScore;
+
+/// Weight given to each new latency sample in the moving average.
+const LATENCY_SMOOTHING: f64 = 0.2;
+/// Milliseconds of fetch latency that cost one point of score.
+const LATENCY_MS_PER_POINT: f64 = 50.0;
+/// Maximum points lost to latency.
+const MAX_LATENCY_PENALTY: f64 = 40.0;
+/// Maximum points lost to useless headers (at a 100% useless rate).
+const MAX_USELESS_PENALTY: f64 = 40.0;
+/// Headers needed before the useless rate is trusted.
+const MIN_HEADERS_FOR_RATE: u64 = 10;
+/// Points lost per disconnect.
+const DISCONNECT_PENALTY: f64 = 5.0;
+/// Maximum points lost to disconnects.
+const MAX_DISCONNECT_PENALTY: f64 = 20.0;
+/// Score of a peer with no history.
+pub const MAX_SCORE: f64 = 100.0;
+
+/// Statistics gathered for a single peer address. Kept across reconnections so
+/// that a flapping peer accumulates its disconnects.
+#[derive(Clone, Debug, Default)]
+pub struct PeerStats {
+    pub connected: bool,
+    pub latency_ms: Option<f64>,
+    pub blocks_fetched: u64,
+    pub headers_received: u64,
+    pub useless_headers: u64,
+    pub disconnects: u64,
+}
+
+impl PeerStats {
+    /// Score from 0 (worst) to 100 (best). A peer starts at full score and loses
+    /// points for slow block fetches, announcing headers we already had from
+    /// someone else, and dropping its connection.
+    pub fn score(&self) -> f64 {
+        let latency_penalty = self
+            .latency_ms
+            .map(|ms| (ms / LATENCY_MS_PER_POINT).min(MAX_LATENCY_PENALTY))
+            .unwrap_or(0.0);
+
+        let useless_penalty = if self.headers_received >= MIN_HEADERS_FOR_RATE {
+            MAX_USELESS_PENALTY * self.useless_headers as f64 / self.headers_received as f64
+        } else {
+            0.0
+        };
+
+        let disconnect_penalty =
+            (self.disconnects as f64 * DISCONNECT_PENALTY).min(MAX_DISCONNECT_PENALTY);
+
+        (MAX_SCORE - latency_penalty - useless_penalty - disconnect_penalty).max(0.0)
+    }
+}
+
+/// Tracks `PeerStats` for every peer address we have connected to.
+#[derive(Default)]
+pub struct PeerScores {
+    stats: HashMap<String, PeerStats>,
+}
+
+impl PeerScores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that a connection to `address` has been started.
+    pub fn record_connected(&mut self, address: &str) {
+        self.entry(address).connected = true;
+    }
+
+    /// Record that the connection to `address` was lost.
+    pub fn record_disconnect(&mut self, address: &str) {
+        let stats = self.entry(address);
+        stats.connected = false;
+        stats.disconnects += 1;
+    }
+
+    /// Record that we dropped `address` ourselves (e.g. churn) - not counted against it.
+    pub fn record_demoted(&mut self, address: &str) {
+        self.entry(address).connected = false;
+    }
+
+    /// Record a header announcement; `useless` if the block was already known.
+    pub fn record_header(&mut self, address: &str, useless: bool) {
+        let stats = self.entry(address);
+        stats.headers_received += 1;
+        if useless {
+            stats.useless_headers += 1;
+        }
+    }
+
+    /// Record the time taken to fetch a block.
+    pub fn record_fetch(&mut self, address: &str, latency: Duration) {
+        let stats = self.entry(address);
+        let sample = latency.as_secs_f64() * 1000.0;
+        stats.latency_ms = Some(match stats.latency_ms {
+            Some(avg) => avg + LATENCY_SMOOTHING * (sample - avg),
+            None => sample,
+        });
+        stats.blocks_fetched += 1;
+    }
+
+    /// Current score for `address`; unknown peers get the full score.
+    pub fn score(&self, address: &str) -> f64 {
+        self.stats.get(address).map(PeerStats::score).unwrap_or(MAX_SCORE)
+    }
+
+    pub fn stats(&self, address: &str) -> Option<&PeerStats> {
+        self.stats.get(address)
+    }
+
+    /// Order `candidates` best first. The sort is stable, so equally scored
+    /// candidates keep their original order.
+    pub fn rank<T>(&self, candidates: &mut [(T, String)]) {
+        candidates.sort_by(|(_, a), (_, b)| self.score(b).total_cmp(&self.score(a)));
+    }
+
+    /// Snapshot of all known peers, best first, for the peer scores query.
+    pub fn snapshot(&self) -> Vec<PeerScore> {
+        let mut scores: Vec<PeerScore> = self
+            .stats
+            .iter()
+            .map(|(address, stats)| PeerScore {
+                address: address.clone(),
+                connected: stats.connected,
+                score: stats.score(),
+                latency_ms: stats.latency_ms.map(|ms| ms.round() as u64),
+                blocks_fetched: stats.blocks_fetched,
+                headers_received: stats.headers_received,
+                useless_headers: stats.useless_headers,
+                disconnects: stats.disconnects,
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.address.cmp(&b.address)));
+        scores
+    }
+
+    fn entry(&mut self, address: &str) -> &mut PeerStats {
+        self.stats.entry(address.to_string()).or_default()
+    }
+}
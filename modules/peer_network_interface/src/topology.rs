@@ -0,0 +1,115 @@
+//! Cardano node P2P topology file support for the PNI module.
+//!
+//! Reads the `topology.json` format used by cardano-node, so the same file can seed both.
+use std::{fs, path::Path};
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+
+/// A relay address as written in the topology file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct AccessPoint {
+    pub address: String,
+    pub port: u16,
+}
+
+impl AccessPoint {
+    /// `host:port` form used by peer connections; IPv6 literals are bracketed.
+    pub fn to_peer_address(&self) -> String {
+        if self.address.contains(':') {
+            format!("[{}]:{}", self.address, self.port)
+        } else {
+            format!("{}:{}", self.address, self.port)
+        }
+    }
+}
+
+/// A group of local root peers, with the number of them to keep hot and warm.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalRootGroup {
+    #[serde(default)]
+    pub access_points: Vec<AccessPoint>,
+    #[serde(default)]
+    pub advertise: bool,
+    /// Older topology files use `valency` for the hot target
+    #[serde(default, alias = "valency")]
+    pub hot_valency: Option<usize>,
+    #[serde(default)]
+    pub warm_valency: Option<usize>,
+}
+
+impl LocalRootGroup {
+    /// Hot target for the group - all of its peers if not given.
+    pub fn hot_target(&self) -> usize {
+        self.hot_valency.unwrap_or(self.access_points.len())
+    }
+
+    /// Number of idle (warm) connections to keep on top of the hot ones. In the
+    /// topology file `warmValency` counts all established connections, hot included.
+    pub fn warm_target(&self) -> usize {
+        self.warm_valency.unwrap_or(0).saturating_sub(self.hot_target())
+    }
+}
+
+/// A group of public root peers.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicRootGroup {
+    #[serde(default)]
+    pub access_points: Vec<AccessPoint>,
+    #[serde(default)]
+    pub advertise: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Topology {
+    /// May be `null` when bootstrap peers are disabled
+    #[serde(default)]
+    pub bootstrap_peers: Option<Vec<AccessPoint>>,
+    #[serde(default)]
+    pub local_roots: Vec<LocalRootGroup>,
+    #[serde(default)]
+    pub public_roots: Vec<PublicRootGroup>,
+}
+
+impl Topology {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("could not read topology file {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("could not parse topology file {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    /// Addresses of all local roots, public roots and bootstrap peers, without duplicates.
+    /// Local roots come first as they are the operator's own trusted relays.
+    pub fn addresses(&self) -> Vec<String> {
+        let local = self.local_roots.iter().flat_map(|g| &g.access_points);
+        let public = self.public_roots.iter().flat_map(|g| &g.access_points);
+        let bootstrap = self.bootstrap_peers.iter().flatten();
+
+        let mut addresses: Vec<String> = vec![];
+        for ap in local.chain(public).chain(bootstrap) {
+            let address = ap.to_peer_address();
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        addresses
+    }
+
+    /// Total hot target across the local root groups.
+    pub fn hot_target(&self) -> usize {
+        self.local_roots.iter().map(LocalRootGroup::hot_target).sum()
+    }
+
+    /// Total warm target across the local root groups.
+    pub fn warm_target(&self) -> usize {
+        self.local_roots.iter().map(LocalRootGroup::warm_target).sum()
+    }
+}
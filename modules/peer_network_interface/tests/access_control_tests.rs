@@ -0,0 +1,95 @@
+use acropolis_common::commands::peer_network::PeerAccessRules;
+use acropolis_module_peer_network_interface::access_control::AccessControl;
+use std::time::{Duration, Instant};
+
+fn access(allow: &[&str], deny: &[&str]) -> AccessControl {
+    AccessControl::new(&PeerAccessRules {
+        allow: allow.iter().map(|r| r.to_string()).collect(),
+        deny: deny.iter().map(|r| r.to_string()).collect(),
+        max_connections_per_minute: 0,
+    })
+    .unwrap()
+}
+
+#[test]
+fn no_rules_permit_everything() {
+    let access = access(&[], &[]);
+    assert!(access.permits("1.2.3.4:3001"));
+    assert!(access.permits("relay.example.com:3001"));
+}
+
+#[test]
+fn subnet_rules_match_ipv4_and_ipv6() {
+    let access = access(&[], &["10.0.0.0/8", "2001:db8::/32", "192.0.2.1"]);
+    assert!(!access.permits("10.200.3.4:3001"));
+    assert!(access.permits("11.0.0.1:3001"));
+    assert!(!access.permits("[2001:db8::1]:3001"));
+    assert!(access.permits("[2001:db9::1]:3001"));
+    assert!(
+        !access.permits("192.0.2.1:6000"),
+        "plain IP matches any port"
+    );
+    assert!(access.permits("192.0.2.2:3001"));
+}
+
+#[test]
+fn host_and_address_rules() {
+    let access = access(&[], &["bad.example.com", "relay.example.com:4000"]);
+    assert!(!access.permits("bad.example.com:3001"));
+    assert!(!access.permits("Relay.Example.com:4000"));
+    assert!(access.permits("relay.example.com:3001"));
+    assert!(access.permits("notbad.example.com:3001"));
+}
+
+#[test]
+fn deny_wins_over_allow() {
+    let access = access(&["10.0.0.0/8"], &["10.1.0.0/16"]);
+    assert!(access.permits("10.2.0.1:3001"));
+    assert!(!access.permits("10.1.0.1:3001"));
+    assert!(!access.permits("8.8.8.8:3001"), "not in the allow list");
+    assert!(
+        !access.permits("relay.example.com:3001"),
+        "host names are not resolved to match subnets"
+    );
+}
+
+#[test]
+fn invalid_rules_are_rejected() {
+    for rule in ["10.0.0.0/33", "not-an-ip/8", "host:port", "", "a b"] {
+        let rules = PeerAccessRules {
+            deny: vec![rule.to_string()],
+            ..Default::default()
+        };
+        assert!(
+            AccessControl::new(&rules).is_err(),
+            "{rule:?} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn failed_update_keeps_existing_rules() {
+    let mut access = access(&[], &["10.0.0.0/8"]);
+    let update = PeerAccessRules {
+        deny: vec!["10.0.0.0/99".to_string()],
+        ..Default::default()
+    };
+    assert!(access.set_rules(&update).is_err());
+    assert!(!access.permits("10.0.0.1:3001"));
+}
+
+#[test]
+fn connections_are_rate_limited_per_minute() {
+    let mut access = AccessControl::new(&PeerAccessRules {
+        max_connections_per_minute: 2,
+        ..Default::default()
+    })
+    .unwrap();
+    let start = Instant::now();
+    assert!(access.try_connect(start));
+    assert!(access.try_connect(start + Duration::from_secs(10)));
+    assert!(!access.try_connect(start + Duration::from_secs(20)));
+    assert!(access.try_connect(start + Duration::from_secs(60)));
+    assert!(!access.try_connect(start + Duration::from_secs(65)));
+    assert!(access.try_connect(start + Duration::from_secs(70)));
+}
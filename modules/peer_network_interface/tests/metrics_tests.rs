@@ -0,0 +1,86 @@
+use acropolis_module_peer_network_interface::metrics::{
+    ConnectionMetrics, MetricsCollector, TrafficCounters, find_intersect_size, request_range_size,
+};
+use pallas::network::miniprotocols::Point;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn connection_traffic_is_added_to_totals() {
+    let totals = Arc::new(TrafficCounters::default());
+    let a = ConnectionMetrics::new(totals.clone());
+    let b = ConnectionMetrics::new(totals.clone());
+
+    a.record_header(100);
+    a.record_block(2000);
+    b.record_header(120);
+    b.record_sent(2);
+
+    assert_eq!(a.traffic().bytes_in, 2100);
+    assert_eq!(b.traffic().bytes_in, 120);
+    let total = totals.snapshot();
+    assert_eq!(total.bytes_in, 2220);
+    assert_eq!(total.bytes_out, 2);
+    assert_eq!(total.headers, 2);
+    assert_eq!(total.blocks, 1);
+}
+
+#[test]
+fn round_trip_is_smoothed() {
+    let metrics = ConnectionMetrics::new(Arc::default());
+    assert_eq!(metrics.round_trip(), None);
+
+    metrics.record_round_trip(Duration::from_millis(100));
+    metrics.record_round_trip(Duration::from_millis(1100));
+    assert_eq!(metrics.round_trip(), Some(Duration::from_millis(300)));
+}
+
+#[test]
+fn collector_reports_rates_since_last_collection() {
+    let totals = Arc::new(TrafficCounters::default());
+    let hot = ConnectionMetrics::new(totals.clone());
+    let warm = ConnectionMetrics::new(totals.clone());
+    let mut collector = MetricsCollector::new(totals.clone());
+    let start = Instant::now();
+
+    for _ in 0..20 {
+        hot.record_header(500);
+    }
+    hot.record_round_trip(Duration::from_millis(40));
+    warm.record_round_trip(Duration::from_millis(400));
+
+    let peers = vec![
+        hot.describe("hot:3001", true),
+        warm.describe("warm:3001", false),
+    ];
+    let message = collector.collect(peers, 7, start + Duration::from_secs(10));
+
+    assert_eq!(message.hot_peers, 1);
+    assert_eq!(message.warm_peers, 1);
+    assert_eq!(message.cold_peers, 7);
+    assert_eq!(message.headers_received, 20);
+    assert!(message.headers_per_sec > 1.9 && message.headers_per_sec <= 2.0);
+    assert_eq!(
+        message.mux_latency_ms,
+        Some(40.0),
+        "only hot peers count towards network latency"
+    );
+
+    // Nothing new since the last collection
+    let message = collector.collect(vec![], 0, start + Duration::from_secs(20));
+    assert_eq!(message.headers_received, 20);
+    assert_eq!(message.headers_per_sec, 0.0);
+    assert_eq!(message.mux_latency_ms, None);
+}
+
+#[test]
+fn request_sizes_match_encoding() {
+    let point = Point::Specific(1234567, vec![0xab; 32]);
+    // [4, [[slot, hash]]]: 2 + 1 + (1 + 5 + 34)
+    assert_eq!(find_intersect_size(std::slice::from_ref(&point)), 43);
+    // [0, [slot, hash], [slot, hash]]
+    assert_eq!(request_range_size(&point, &point), 2 + 40 + 40);
+    assert_eq!(find_intersect_size(&[Point::Origin]), 4);
+}
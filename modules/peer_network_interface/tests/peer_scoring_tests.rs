@@ -0,0 +1,98 @@
+use acropolis_module_peer_network_interface::peer_scoring::{MAX_SCORE, PeerScores};
+use std::time::Duration;
+
+#[test]
+fn unknown_peer_has_full_score() {
+    let scores = PeerScores::new();
+    assert_eq!(scores.score("1.2.3.4:3001"), MAX_SCORE);
+}
+
+#[test]
+fn slow_peer_scores_below_fast_peer() {
+    let mut scores = PeerScores::new();
+    scores.record_fetch("fast:3001", Duration::from_millis(40));
+    scores.record_fetch("slow:3001", Duration::from_millis(1200));
+    assert!(scores.score("fast:3001") > scores.score("slow:3001"));
+}
+
+#[test]
+fn latency_is_smoothed() {
+    let mut scores = PeerScores::new();
+    scores.record_fetch("peer:3001", Duration::from_millis(100));
+    scores.record_fetch("peer:3001", Duration::from_millis(1100));
+    let latency = scores.stats("peer:3001").unwrap().latency_ms.unwrap();
+    assert!(
+        (latency - 300.0).abs() < 1e-6,
+        "one slow sample should only move the average part way, got {latency}"
+    );
+}
+
+#[test]
+fn useless_headers_ignored_until_enough_samples() {
+    let mut scores = PeerScores::new();
+    for _ in 0..5 {
+        scores.record_header("peer:3001", true);
+    }
+    assert_eq!(scores.score("peer:3001"), MAX_SCORE);
+
+    for _ in 0..5 {
+        scores.record_header("peer:3001", true);
+    }
+    assert!(scores.score("peer:3001") < MAX_SCORE);
+}
+
+#[test]
+fn disconnects_penalised_but_demotion_is_not() {
+    let mut scores = PeerScores::new();
+    scores.record_connected("flaky:3001");
+    scores.record_disconnect("flaky:3001");
+    scores.record_connected("churned:3001");
+    scores.record_demoted("churned:3001");
+
+    assert!(scores.score("flaky:3001") < MAX_SCORE);
+    assert_eq!(scores.score("churned:3001"), MAX_SCORE);
+    assert!(!scores.stats("flaky:3001").unwrap().connected);
+}
+
+#[test]
+fn score_never_negative() {
+    let mut scores = PeerScores::new();
+    for _ in 0..20 {
+        scores.record_disconnect("bad:3001");
+        scores.record_header("bad:3001", true);
+    }
+    scores.record_fetch("bad:3001", Duration::from_secs(60));
+    assert_eq!(scores.score("bad:3001"), 0.0);
+}
+
+#[test]
+fn rank_orders_best_first_and_keeps_ties_stable() {
+    let mut scores = PeerScores::new();
+    scores.record_disconnect("b:3001");
+    let mut candidates = vec![
+        (1, "a:3001".to_string()),
+        (2, "b:3001".to_string()),
+        (3, "c:3001".to_string()),
+    ];
+    scores.rank(&mut candidates);
+    let order: Vec<i32> = candidates.iter().map(|(id, _)| *id).collect();
+    assert_eq!(order, vec![1, 3, 2]);
+}
+
+#[test]
+fn snapshot_reports_all_peers_best_first() {
+    let mut scores = PeerScores::new();
+    scores.record_connected("good:3001");
+    scores.record_fetch("good:3001", Duration::from_millis(20));
+    scores.record_connected("bad:3001");
+    scores.record_disconnect("bad:3001");
+
+    let snapshot = scores.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(snapshot[0].address, "good:3001");
+    assert!(snapshot[0].connected);
+    assert_eq!(snapshot[0].blocks_fetched, 1);
+    assert_eq!(snapshot[0].latency_ms, Some(20));
+    assert_eq!(snapshot[1].address, "bad:3001");
+    assert_eq!(snapshot[1].disconnects, 1);
+}
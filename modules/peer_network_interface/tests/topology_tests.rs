@@ -0,0 +1,91 @@
+use acropolis_module_peer_network_interface::topology::{AccessPoint, Topology};
+
+const TOPOLOGY: &str = r#"{
+  "bootstrapPeers": [
+    { "address": "backbone.cardano.iog.io", "port": 3001 },
+    { "address": "relay.example.com", "port": 3001 }
+  ],
+  "localRoots": [
+    {
+      "accessPoints": [
+        { "address": "relay.example.com", "port": 3001 },
+        { "address": "10.0.0.2", "port": 3002 }
+      ],
+      "advertise": false,
+      "trustable": true,
+      "hotValency": 1,
+      "warmValency": 2
+    },
+    {
+      "accessPoints": [{ "address": "2001:db8::1", "port": 3001 }],
+      "valency": 1
+    }
+  ],
+  "publicRoots": [
+    {
+      "accessPoints": [{ "address": "backbone.mainnet.emurgornd.com", "port": 3001 }],
+      "advertise": false
+    }
+  ],
+  "useLedgerAfterSlot": 128908821
+}"#;
+
+#[test]
+fn addresses_are_local_then_public_then_bootstrap_without_duplicates() {
+    let topology = Topology::parse(TOPOLOGY).unwrap();
+    assert_eq!(
+        topology.addresses(),
+        vec![
+            "relay.example.com:3001".to_string(),
+            "10.0.0.2:3002".to_string(),
+            "[2001:db8::1]:3001".to_string(),
+            "backbone.mainnet.emurgornd.com:3001".to_string(),
+            "backbone.cardano.iog.io:3001".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn targets_come_from_local_root_valencies() {
+    let topology = Topology::parse(TOPOLOGY).unwrap();
+    assert_eq!(topology.hot_target(), 2);
+    assert_eq!(
+        topology.warm_target(),
+        1,
+        "warmValency includes the hot peers"
+    );
+}
+
+#[test]
+fn null_bootstrap_peers_and_missing_sections_are_accepted() {
+    let topology = Topology::parse(r#"{ "bootstrapPeers": null }"#).unwrap();
+    assert!(topology.addresses().is_empty());
+    assert_eq!(topology.hot_target(), 0);
+}
+
+#[test]
+fn local_root_without_valency_keeps_all_peers_hot() {
+    let topology = Topology::parse(
+        r#"{ "localRoots": [{ "accessPoints": [
+            { "address": "a.example.com", "port": 3001 },
+            { "address": "b.example.com", "port": 3001 }
+        ] }] }"#,
+    )
+    .unwrap();
+    assert_eq!(topology.hot_target(), 2);
+    assert_eq!(topology.warm_target(), 0);
+}
+
+#[test]
+fn ipv6_access_point_is_bracketed() {
+    let ap = AccessPoint {
+        address: "::1".to_string(),
+        port: 3001,
+    };
+    assert_eq!(ap.to_peer_address(), "[::1]:3001");
+}
+
+#[test]
+fn invalid_json_is_an_error() {
+    assert!(Topology::parse("{ not json").is_err());
+}
@@ -0,0 +1,258 @@
+//! Response cache for the epoch-level aggregations (pool lists, stake distributions,
+//! DRep lists) which are otherwise recomputed on every request.
+//!
+//! Entries expire after a TTL, and the whole cache is cleared at each epoch boundary
+//! and on rollback. A response computed across a clear is not stored, since it may
+//! have been built from the previous epoch's state.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use acropolis_common::messages::{CacheMetricsMessage, RESTResponse};
+
+struct CachedResponse {
+    response: RESTResponse,
+    inserted: Instant,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, CachedResponse>,
+    /// Bumped on every invalidation
+    generation: u64,
+    hits: u64,
+    misses: u64,
+    expired: u64,
+    evicted: u64,
+}
+
+/// Result of a cache lookup
+pub enum Lookup {
+    Hit(RESTResponse),
+    /// Not cached; pass the generation back to `insert` with the computed response
+    Miss(u64),
+}
+
+pub struct ResponseCache {
+    name: String,
+    ttl: Duration,
+    max_entries: usize,
+    inner: Mutex<Inner>,
+}
+
+impl ResponseCache {
+    pub fn new(name: &str, ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            ttl,
+            max_entries,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn lookup(&self, key: &str, now: Instant) -> Lookup {
+        let mut inner = self.inner.lock().unwrap();
+        let fresh = match inner.entries.get(key) {
+            Some(entry) if now.duration_since(entry.inserted) < self.ttl => {
+                Some(entry.response.clone())
+            }
+            Some(_) => {
+                inner.entries.remove(key);
+                inner.expired += 1;
+                None
+            }
+            None => None,
+        };
+
+        match fresh {
+            Some(response) => {
+                inner.hits += 1;
+                Lookup::Hit(response)
+            }
+            None => {
+                inner.misses += 1;
+                Lookup::Miss(inner.generation)
+            }
+        }
+    }
+
+    /// Store a successful response, unless the cache was invalidated since the lookup
+    pub fn insert(&self, key: String, response: &RESTResponse, generation: u64, now: Instant) {
+        if response.code != 200 || self.max_entries == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.max_entries {
+            let ttl = self.ttl;
+            let before = inner.entries.len();
+            inner.entries.retain(|_, entry| now.duration_since(entry.inserted) < ttl);
+            inner.expired += (before - inner.entries.len()) as u64;
+
+            if inner.entries.len() >= self.max_entries {
+                let oldest = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.inserted)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    inner.entries.remove(&oldest);
+                    inner.evicted += 1;
+                }
+            }
+        }
+
+        inner.entries.insert(
+            key,
+            CachedResponse {
+                response: response.clone(),
+                inserted: now,
+            },
+        );
+    }
+
+    /// Drop every entry, e.g. at an epoch boundary
+    pub fn invalidate(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.generation += 1;
+    }
+
+    pub fn metrics(&self) -> CacheMetricsMessage {
+        let inner = self.inner.lock().unwrap();
+        CacheMetricsMessage {
+            cache: self.name.clone(),
+            entries: inner.entries.len(),
+            hits: inner.hits,
+            misses: inner.misses,
+            expired: inner.expired,
+            evicted: inner.evicted,
+            invalidations: inner.generation,
+        }
+    }
+}
+
+/// Key a request by its topic, path parameters and query parameters, the latter sorted
+/// so that parameter order does not matter
+pub fn cache_key(
+    topic: &str,
+    params: &[String],
+    query_params: Option<&HashMap<String, String>>,
+) -> String {
+    let mut key = format!("{topic}/{}", params.join("/"));
+    if let Some(query_params) = query_params {
+        let mut pairs: Vec<_> = query_params.iter().collect();
+        pairs.sort();
+        for (i, (name, value)) in pairs.into_iter().enumerate() {
+            key.push(if i == 0 { '?' } else { '&' });
+            key.push_str(&format!("{name}={value}"));
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(body: &str) -> RESTResponse {
+        RESTResponse::with_json(200, body)
+    }
+
+    fn hit(lookup: Lookup) -> Option<String> {
+        match lookup {
+            Lookup::Hit(response) => Some(response.body),
+            Lookup::Miss(_) => None,
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = ResponseCache::new("test", Duration::from_secs(10), 8);
+        let start = Instant::now();
+
+        cache.insert("a".to_string(), &ok("1"), 0, start);
+        assert_eq!(
+            hit(cache.lookup("a", start + Duration::from_secs(9))),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            hit(cache.lookup("a", start + Duration::from_secs(10))),
+            None
+        );
+
+        let metrics = cache.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.expired), (1, 1, 1));
+        assert_eq!(metrics.entries, 0);
+    }
+
+    #[test]
+    fn invalidation_drops_entries_and_stale_inserts() {
+        let cache = ResponseCache::new("test", Duration::from_secs(60), 8);
+        let now = Instant::now();
+
+        cache.insert("a".to_string(), &ok("1"), 0, now);
+        let Lookup::Miss(generation) = cache.lookup("b", now) else {
+            panic!("unexpected hit");
+        };
+
+        // New epoch while "b" is being computed
+        cache.invalidate();
+        cache.insert("b".to_string(), &ok("2"), generation, now);
+
+        assert_eq!(hit(cache.lookup("a", now)), None);
+        assert_eq!(hit(cache.lookup("b", now)), None);
+        assert_eq!(cache.metrics().invalidations, 1);
+    }
+
+    #[test]
+    fn only_successes_are_cached_within_size_limit() {
+        let cache = ResponseCache::new("test", Duration::from_secs(60), 2);
+        let start = Instant::now();
+
+        cache.insert(
+            "error".to_string(),
+            &RESTResponse::with_text(404, "no"),
+            0,
+            start,
+        );
+        cache.insert("a".to_string(), &ok("1"), 0, start);
+        cache.insert("b".to_string(), &ok("2"), 0, start + Duration::from_secs(1));
+        cache.insert("c".to_string(), &ok("3"), 0, start + Duration::from_secs(2));
+
+        let now = start + Duration::from_secs(3);
+        assert_eq!(hit(cache.lookup("error", now)), None);
+        assert_eq!(hit(cache.lookup("a", now)), None);
+        assert_eq!(hit(cache.lookup("c", now)), Some("3".to_string()));
+        assert_eq!(cache.metrics().evicted, 1);
+    }
+
+    #[test]
+    fn key_ignores_query_parameter_order() {
+        let params = vec!["123".to_string()];
+        let a = HashMap::from([
+            ("page".to_string(), "2".to_string()),
+            ("count".to_string(), "50".to_string()),
+        ]);
+        let b = HashMap::from([
+            ("count".to_string(), "50".to_string()),
+            ("page".to_string(), "2".to_string()),
+        ]);
+
+        assert_eq!(
+            cache_key("rest.get.pools", &params, Some(&a)),
+            "rest.get.pools/123?count=50&page=2"
+        );
+        assert_eq!(
+            cache_key("rest.get.pools", &params, Some(&a)),
+            cache_key("rest.get.pools", &params, Some(&b))
+        );
+    }
+}
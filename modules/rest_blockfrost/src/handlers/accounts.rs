@@ -1,4 +1,5 @@
 //! REST handlers for Acropolis Blockfrost /accounts endpoints
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::handlers_config::HandlersConfig;
@@ -16,8 +17,9 @@ use acropolis_common::queries::errors::QueryError;
 use acropolis_common::queries::utils::query_state;
 use acropolis_common::queries::utxos::{UTxOStateQuery, UTxOStateQueryResponse};
 use acropolis_common::rest_error::RESTError;
+use acropolis_common::rest_helper::Pagination;
 use acropolis_common::serialization::{Bech32Conversion, Bech32WithHrp};
-use acropolis_common::{DRepChoice, StakeAddress};
+use acropolis_common::{DRepChoice, ShelleyAddress, StakeAddress, Value};
 use caryatid_sdk::Context;
 
 #[derive(serde::Serialize)]
@@ -101,8 +103,11 @@ pub async fn handle_single_account_blockfrost(
 pub async fn handle_account_registrations_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
@@ -135,6 +140,7 @@ pub async fn handle_account_registrations_blockfrost(
     let Some(registrations) = registrations else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let registrations = pagination.apply(registrations);
 
     // Get TxHashes from TxIdentifiers
     let tx_ids: Vec<_> = registrations.iter().map(|r| r.tx_identifier).collect();
@@ -182,8 +188,11 @@ pub async fn handle_account_registrations_blockfrost(
 pub async fn handle_account_delegations_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
@@ -216,6 +225,7 @@ pub async fn handle_account_delegations_blockfrost(
     let Some(delegations) = delegations else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let delegations = pagination.apply(delegations);
 
     // Get TxHashes from TxIdentifiers
     let tx_ids: Vec<_> = delegations.iter().map(|r| r.tx_identifier).collect();
@@ -268,8 +278,11 @@ pub async fn handle_account_delegations_blockfrost(
 pub async fn handle_account_mirs_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
@@ -302,6 +315,7 @@ pub async fn handle_account_mirs_blockfrost(
     let Some(mirs) = mirs else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let mirs = pagination.apply(mirs);
 
     // Get TxHashes from TxIdentifiers
     let tx_ids: Vec<_> = mirs.iter().map(|r| r.tx_identifier).collect();
@@ -338,6 +352,7 @@ pub async fn handle_account_mirs_blockfrost(
         rest_response.push(AccountWithdrawalREST {
             tx_hash: hex::encode(tx_hash),
             amount: r.amount.to_string(),
+            epoch: r.epoch,
         });
     }
 
@@ -348,13 +363,16 @@ pub async fn handle_account_mirs_blockfrost(
 pub async fn handle_account_withdrawals_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
     let msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
-        AccountsStateQuery::GetAccountRegistrationHistory { account },
+        AccountsStateQuery::GetAccountWithdrawalHistory { account },
     )));
 
     // Get withdrawals from historical accounts state
@@ -382,6 +400,7 @@ pub async fn handle_account_withdrawals_blockfrost(
     let Some(withdrawals) = withdrawals else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let withdrawals = pagination.apply(withdrawals);
 
     // Get TxHashes from TxIdentifiers
     let tx_ids: Vec<_> = withdrawals.iter().map(|r| r.tx_identifier).collect();
@@ -418,6 +437,7 @@ pub async fn handle_account_withdrawals_blockfrost(
         rest_response.push(AccountWithdrawalREST {
             tx_hash: hex::encode(tx_hash),
             amount: w.amount.to_string(),
+            epoch: w.epoch,
         });
     }
 
@@ -428,8 +448,11 @@ pub async fn handle_account_withdrawals_blockfrost(
 pub async fn handle_account_rewards_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
@@ -462,6 +485,7 @@ pub async fn handle_account_rewards_blockfrost(
     let Some(rewards) = rewards else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let rewards = pagination.apply(rewards);
 
     let rest_response = rewards
         .iter()
@@ -478,8 +502,11 @@ pub async fn handle_account_rewards_blockfrost(
 pub async fn handle_account_addresses_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Prepare the message
@@ -512,6 +539,7 @@ pub async fn handle_account_addresses_blockfrost(
     let Some(addresses) = addresses else {
         return Err(RESTError::not_found("Account not found"));
     };
+    let addresses = pagination.apply(addresses);
 
     let rest_response = addresses
         .iter()
@@ -532,8 +560,11 @@ pub async fn handle_account_addresses_blockfrost(
 pub async fn handle_account_assets_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Get addresses from historical accounts state
@@ -565,12 +596,59 @@ pub async fn handle_account_assets_blockfrost(
         return Err(RESTError::not_found("Account not found"));
     };
 
+    // Balances come from the address totals, which are kept up to date block by
+    // block, falling back to summing the account's UTxOs if totals aren't stored
+    let msg = Arc::new(Message::StateQuery(StateQuery::Addresses(
+        AddressStateQuery::GetAddressesTotals {
+            addresses: addresses.clone(),
+        },
+    )));
+    let totals = query_state(
+        &context,
+        &handlers_config.addresses_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Addresses(
+                AddressStateQueryResponse::AddressesTotals(totals),
+            )) => Ok(Some(totals)),
+            Message::StateQueryResponse(StateQueryResponse::Addresses(
+                AddressStateQueryResponse::Error(QueryError::StorageDisabled { .. }),
+            )) => Ok(None),
+            Message::StateQueryResponse(StateQueryResponse::Addresses(
+                AddressStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving account totals",
+            )),
+        },
+    )
+    .await?;
+
+    let mut rest_response = match totals {
+        Some(totals) => AmountList::from(totals.balance()?).0,
+        None => AmountList::from(sum_account_utxos(&context, &handlers_config, addresses).await?).0,
+    };
+    if !rest_response.is_empty() {
+        rest_response.drain(..1);
+    }
+    let rest_response = pagination.apply(rest_response);
+
+    let json = serde_json::to_string_pretty(&rest_response)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Sum the values of the UTxOs held at an account's addresses
+async fn sum_account_utxos(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    addresses: Vec<ShelleyAddress>,
+) -> Result<Value, RESTError> {
     // Get utxos from address state
     let msg = Arc::new(Message::StateQuery(StateQuery::Addresses(
         AddressStateQuery::GetAddressesUTxOs { addresses },
     )));
     let utxo_identifiers = query_state(
-        &context,
+        context,
         &handlers_config.addresses_query_topic,
         msg,
         |message| match message {
@@ -592,7 +670,7 @@ pub async fn handle_account_assets_blockfrost(
         UTxOStateQuery::GetUTxOsSum { utxo_identifiers },
     )));
     let utxos_balance = query_state(
-        &context,
+        context,
         &handlers_config.utxos_query_topic,
         msg,
         |message| match message {
@@ -609,13 +687,7 @@ pub async fn handle_account_assets_blockfrost(
     )
     .await?;
 
-    let mut rest_response = AmountList::from(utxos_balance).0;
-    if !rest_response.is_empty() {
-        rest_response.drain(..1);
-    }
-
-    let json = serde_json::to_string_pretty(&rest_response)?;
-    Ok(RESTResponse::with_json(200, &json))
+    Ok(utxos_balance)
 }
 
 /// Handle `/accounts/{stake_address}/addresses/total` Blockfrost-compatible endpoint
@@ -718,8 +790,11 @@ pub async fn handle_account_totals_blockfrost(
 pub async fn handle_account_utxos_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let account = parse_stake_address(&params)?;
 
     // Get addresses from historical accounts state
@@ -769,6 +844,7 @@ pub async fn handle_account_utxos_blockfrost(
         },
     )
     .await?;
+    let utxo_identifiers = pagination.apply(utxo_identifiers);
 
     // Get BlockHashes and Tx indexes from UTXO Tx hashes
     let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::types::{AddressInfoExtended, AddressTotalsREST, TransactionInfoREST, UTxOREST};
@@ -7,15 +8,19 @@ use acropolis_common::queries::assets::{AssetsStateQuery, AssetsStateQueryRespon
 use acropolis_common::queries::blocks::{BlocksStateQuery, BlocksStateQueryResponse};
 use acropolis_common::queries::errors::QueryError;
 use acropolis_common::rest_error::RESTError;
+use acropolis_common::rest_helper::Pagination;
 use acropolis_common::AssetMetadata;
 use acropolis_common::{
+    extract_strict_query_params,
     messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
     queries::{
         addresses::{AddressStateQuery, AddressStateQueryResponse},
+        misc::Order,
+        stake_deltas::{StakeDeltaQuery, StakeDeltaQueryResponse},
         utils::query_state,
         utxos::{UTxOStateQuery, UTxOStateQueryResponse},
     },
-    Address, Value,
+    Address, ShelleyAddressDelegationPart, TxIdentifier, Value,
 };
 use caryatid_sdk::Context;
 use serde::Serialize;
@@ -28,10 +33,7 @@ pub async fn handle_address_single_blockfrost(
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let address = parse_address(&params)?;
-    let stake_address = match address {
-        Address::Shelley(ref addr) => addr.stake_address_string()?,
-        _ => None,
-    };
+    let stake_address = resolve_stake_address(&context, &handlers_config, &address).await?;
 
     let address_type = address.kind().to_string();
     let is_script = address.is_script();
@@ -125,10 +127,7 @@ pub async fn handle_address_extended_blockfrost(
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let address = parse_address(&params)?;
-    let stake_address = match address {
-        Address::Shelley(ref addr) => addr.stake_address_string()?,
-        _ => None,
-    };
+    let stake_address = resolve_stake_address(&context, &handlers_config, &address).await?;
 
     let address_type = address.kind().to_string();
     let is_script = address.is_script();
@@ -241,7 +240,7 @@ pub async fn handle_address_extended_blockfrost(
     Ok(RESTResponse::with_json(200, &json))
 }
 
-/// Handle `/addresses/{address}/totals` Blockfrost-compatible endpoint
+/// Handle `/addresses/{address}/total` Blockfrost-compatible endpoint
 pub async fn handle_address_totals_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
@@ -288,8 +287,11 @@ pub async fn handle_address_totals_blockfrost(
 pub async fn handle_address_utxos_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let address = parse_address(&params)?;
     let address_str = address.to_string()?;
 
@@ -314,6 +316,7 @@ pub async fn handle_address_utxos_blockfrost(
         },
     )
     .await?;
+    let utxo_identifiers = pagination.apply(utxo_identifiers);
 
     // Get BlockHashes and Tx indexes from UTXO Tx hashes
     let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
@@ -386,8 +389,11 @@ pub async fn handle_address_utxos_blockfrost(
 pub async fn handle_address_asset_utxos_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let address = parse_address(&params)?;
     let address_str = address.to_string()?;
     let (target_policy, target_name) = split_policy_and_asset(&params[1])?;
@@ -453,6 +459,9 @@ pub async fn handle_address_asset_utxos_blockfrost(
         }
     }
 
+    let filtered_identifiers = pagination.apply(filtered_identifiers);
+    let filtered_entries = pagination.apply(filtered_entries);
+
     if filtered_identifiers.is_empty() {
         return Ok(RESTResponse::with_json(200, "[]"));
     }
@@ -460,7 +469,7 @@ pub async fn handle_address_asset_utxos_blockfrost(
     // Get BlockHashes and Tx indexes from UTXO Tx hashes
     let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
         BlocksStateQuery::GetBlockHashesAndIndexOfTransactionHashes {
-            tx_hashes: utxo_identifiers.iter().map(|utxo| utxo.tx_hash).collect(),
+            tx_hashes: filtered_identifiers.iter().map(|utxo| utxo.tx_hash).collect(),
         },
     )));
 
@@ -504,8 +513,20 @@ pub async fn handle_address_asset_utxos_blockfrost(
 pub async fn handle_address_transactions_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    extract_strict_query_params!(query_params, {
+        "count" => count: Option<u64>,
+        "page" => page: Option<u64>,
+        "order" => order: Option<Order>,
+        "from" => from: Option<String>,
+        "to" => to: Option<String>,
+    });
+    let pagination = Pagination::new(count, page, order)?;
+    let from = from.map(|from| parse_tx_bound("from", &from, 0)).transpose()?;
+    let to = to.map(|to| parse_tx_bound("to", &to, u16::MAX)).transpose()?;
+
     let address = parse_address(&params)?;
 
     // Get tx identifiers from address state
@@ -529,6 +550,11 @@ pub async fn handle_address_transactions_blockfrost(
         },
     )
     .await?;
+    let tx_identifiers = tx_identifiers
+        .into_iter()
+        .filter(|tx| from.is_none_or(|from| *tx >= from) && to.is_none_or(|to| *tx <= to))
+        .collect();
+    let tx_identifiers = pagination.apply(tx_identifiers);
 
     // Get tx hashes and timestamps from chain store
     let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
@@ -573,7 +599,63 @@ fn parse_address(params: &[String]) -> Result<Address, RESTError> {
         return Err(RESTError::param_missing("address"));
     };
 
-    Ok(Address::from_string(address_str)?)
+    Address::from_string(address_str)
+        .map_err(|e| RESTError::invalid_param("address", &e.to_string()))
+}
+
+/// Parse a `from`/`to` bound of the form `block_height[:tx_index]`. Without an index the
+/// bound covers the whole block, so `default_index` is the first or last index.
+fn parse_tx_bound(name: &str, value: &str, default_index: u16) -> Result<TxIdentifier, RESTError> {
+    let invalid =
+        || RESTError::invalid_param(name, "must be a block height, optionally with :index");
+    let (height, index) = match value.split_once(':') {
+        Some((height, index)) => (height, index.parse().map_err(|_| invalid())?),
+        None => (value, default_index),
+    };
+    Ok(TxIdentifier::new(
+        height.parse().map_err(|_| invalid())?,
+        index,
+    ))
+}
+
+/// Stake address an address delegates to. Pointer addresses are resolved through the
+/// stake delta filter, and are unassociated if the pointer does not resolve.
+async fn resolve_stake_address(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    address: &Address,
+) -> Result<Option<String>, RESTError> {
+    let Address::Shelley(shelley) = address else {
+        return Ok(None);
+    };
+    let ShelleyAddressDelegationPart::Pointer(pointer) = &shelley.delegation else {
+        return Ok(shelley.stake_address_string()?);
+    };
+
+    let msg = Arc::new(Message::StateQuery(StateQuery::StakeDeltas(
+        StakeDeltaQuery::ResolvePointers {
+            pointers: vec![pointer.clone()],
+        },
+    )));
+    let resolved = query_state(
+        context,
+        &handlers_config.stake_deltas_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::StakeDeltas(
+                StakeDeltaQueryResponse::ResolvedPointers(resolved),
+            )) => Ok(resolved),
+            Message::StateQueryResponse(StateQueryResponse::StakeDeltas(
+                StakeDeltaQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while resolving pointer address",
+            )),
+        },
+    )
+    .await?;
+
+    Ok(resolved.get(pointer).map(|stake| stake.to_string()).transpose()?)
 }
 
 #[derive(Serialize)]
@@ -701,6 +783,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tx_bounds_cover_whole_block_without_index() {
+        assert_eq!(
+            parse_tx_bound("from", "100", 0).unwrap(),
+            TxIdentifier::new(100, 0)
+        );
+        assert_eq!(
+            parse_tx_bound("to", "100", u16::MAX).unwrap(),
+            TxIdentifier::new(100, u16::MAX)
+        );
+        assert_eq!(
+            parse_tx_bound("to", "100:7", u16::MAX).unwrap(),
+            TxIdentifier::new(100, 7)
+        );
+        assert!(parse_tx_bound("from", "100:", 0).is_err());
+        assert!(parse_tx_bound("from", "latest", 0).is_err());
+    }
+
     #[test]
     fn cip25_existence_overrides_decimals() {
         use serde_cbor::Value as CborValue;
@@ -0,0 +1,21 @@
+//! REST handlers for operators of the node
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    messages::{Message, RESTResponse},
+    rest_error::RESTError,
+};
+use caryatid_sdk::Context;
+
+use crate::{handlers_config::HandlersConfig, rate_limits};
+
+/// Handle `/admin/rate-limits`, the usage counters of each configured rate limit
+pub async fn handle_rate_limits_blockfrost(
+    _context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let json = serde_json::to_string_pretty(&rate_limits::usage())?;
+    Ok(RESTResponse::with_json(200, &json))
+}
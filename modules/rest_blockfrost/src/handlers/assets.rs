@@ -1,7 +1,7 @@
 use crate::{
     handlers_config::HandlersConfig,
     types::{
-        AssetAddressRest, AssetInfoRest, AssetMetadataREST, AssetMintRecordRest,
+        AssetAddressRest, AssetInfoRest, AssetMetadataREST, AssetMintRecordRest, AssetSupplyRest,
         AssetTransactionRest, PolicyAssetRest,
     },
     utils::split_policy_and_asset,
@@ -9,13 +9,16 @@ use crate::{
 use acropolis_common::queries::errors::QueryError;
 use acropolis_common::rest_error::RESTError;
 use acropolis_common::{
+    extract_strict_query_params,
     messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
     queries::{
         assets::{AssetsStateQuery, AssetsStateQueryResponse},
+        blocks::{BlocksStateQuery, BlocksStateQueryResponse, TransactionHashes},
         utils::query_state,
     },
+    rest_helper::Pagination,
     serialization::Bech32WithHrp,
-    PolicyId,
+    PolicyId, TxHash, TxIdentifier,
 };
 use blake2::{digest::consts::U20, Blake2b, Digest};
 use caryatid_sdk::Context;
@@ -23,13 +26,17 @@ use hex::FromHex;
 use reqwest::Client;
 use serde_cbor::Value as CborValue;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub async fn handle_assets_list_blockfrost(
     context: Arc<Context<Message>>,
     _params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let assets_list_msg = Arc::new(Message::StateQuery(StateQuery::Assets(
         AssetsStateQuery::GetAssetsList,
     )));
@@ -52,7 +59,8 @@ pub async fn handle_assets_list_blockfrost(
     )
     .await?;
 
-    let rest_assets: Vec<PolicyAssetRest> = assets.iter().map(Into::into).collect();
+    let rest_assets: Vec<PolicyAssetRest> =
+        pagination.apply(assets).iter().map(Into::into).collect();
     let json = serde_json::to_string_pretty(&rest_assets)?;
     Ok(RESTResponse::with_json(200, &json))
 }
@@ -117,14 +125,21 @@ pub async fn handle_asset_single_blockfrost(
             (None, None, None)
         };
 
-    // TODO: Query transaction_state once implemented to fetch inital_mint_tx_hash based on TxIdentifier
+    let tx_hashes =
+        resolve_tx_hashes(&context, &handlers_config, vec![info.initial_mint_tx]).await?;
+    let Some(initial_mint_tx_hash) = tx_hashes.get(&info.initial_mint_tx) else {
+        return Err(RESTError::InternalServerError(
+            "Missing tx hash for initial mint".to_string(),
+        ));
+    };
+
     let response = AssetInfoRest {
         asset,
         policy_id,
         asset_name,
         fingerprint,
         quantity: quantity.to_string(),
-        initial_mint_tx_hash: "transaction_state not yet implemented".to_string(),
+        initial_mint_tx_hash: hex::encode(initial_mint_tx_hash),
         mint_or_burn_count: info.mint_or_burn_count,
         onchain_metadata: onchain_metadata_json,
         onchain_metadata_standard,
@@ -139,8 +154,11 @@ pub async fn handle_asset_single_blockfrost(
 pub async fn handle_asset_history_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let (policy, name) = split_policy_and_asset(&params[0])?;
 
     let asset_query_msg = Arc::new(Message::StateQuery(StateQuery::Assets(
@@ -168,16 +186,85 @@ pub async fn handle_asset_history_blockfrost(
     )
     .await?;
 
-    let rest_history: Vec<AssetMintRecordRest> = history.iter().map(Into::into).collect();
+    let history = pagination.apply(history);
+    let tx_ids = history.iter().map(|record| record.tx).collect();
+    let tx_hashes = resolve_tx_hashes(&context, &handlers_config, tx_ids).await?;
+
+    let mut rest_history = Vec::with_capacity(history.len());
+    for record in &history {
+        let Some(tx_hash) = tx_hashes.get(&record.tx) else {
+            return Err(RESTError::InternalServerError(
+                "Missing tx hash for asset history".to_string(),
+            ));
+        };
+        rest_history.push(AssetMintRecordRest::new(record, tx_hash));
+    }
+
     let json = serde_json::to_string_pretty(&rest_history)?;
     Ok(RESTResponse::with_json(200, &json))
 }
 
+/// Handle `/assets/{asset}/supply`, the supply at the end of `epoch` or the current
+/// supply if no epoch is given
+pub async fn handle_asset_supply_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    query_params: HashMap<String, String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    extract_strict_query_params!(query_params, {
+        "epoch" => epoch: Option<u64>,
+    });
+
+    let (policy, name) = split_policy_and_asset(&params[0])?;
+
+    let asset_query_msg = Arc::new(Message::StateQuery(StateQuery::Assets(
+        AssetsStateQuery::GetAssetSupplyAtEpoch {
+            policy,
+            name,
+            epoch: epoch.unwrap_or(u64::MAX),
+        },
+    )));
+
+    let supply = query_state(
+        &context,
+        &handlers_config.assets_query_topic,
+        asset_query_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Assets(
+                AssetsStateQueryResponse::AssetSupplyAtEpoch(supply),
+            )) => Ok(supply),
+            Message::StateQueryResponse(StateQueryResponse::Assets(
+                AssetsStateQueryResponse::Error(QueryError::NotFound { .. }),
+            )) => Err(QueryError::not_found("Asset history not found")),
+            Message::StateQueryResponse(StateQueryResponse::Assets(
+                AssetsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected response while retrieving asset supply",
+            )),
+        },
+    )
+    .await?;
+
+    let response = AssetSupplyRest {
+        asset: params[0].clone(),
+        epoch,
+        supply: supply.to_string(),
+    };
+
+    let json = serde_json::to_string_pretty(&response)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
 pub async fn handle_asset_transactions_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let (policy, name) = split_policy_and_asset(&params[0])?;
 
     let asset_query_msg = Arc::new(Message::StateQuery(StateQuery::Assets(
@@ -205,14 +292,40 @@ pub async fn handle_asset_transactions_blockfrost(
     )
     .await?;
 
-    // TODO: Query transaction_state once implemented to fetch tx_hash and block_time using TxIdentifier
+    let txs = pagination.apply(txs);
+
+    // Get tx hashes and timestamps from chain store
+    let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
+        BlocksStateQuery::GetTransactionHashesAndTimestamps {
+            tx_ids: txs.clone(),
+        },
+    )));
+    let tx_info = query_state(
+        &context,
+        &handlers_config.blocks_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Blocks(
+                BlocksStateQueryResponse::TransactionHashesAndTimestamps(info),
+            )) => Ok(info),
+            Message::StateQueryResponse(StateQueryResponse::Blocks(
+                BlocksStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving transaction hashes and timestamps",
+            )),
+        },
+    )
+    .await?;
+
     let rest_txs: Vec<AssetTransactionRest> = txs
         .iter()
-        .map(|identifier| AssetTransactionRest {
-            tx_hash: "transaction_state not yet implemented".to_string(),
+        .zip(tx_info.tx_hashes.iter().zip(tx_info.timestamps.iter()))
+        .map(|(identifier, (tx_hash, block_time))| AssetTransactionRest {
+            tx_hash: hex::encode(tx_hash),
             tx_index: identifier.tx_index(),
             block_height: identifier.block_number(),
-            block_time: "transaction_state not yet implemented".to_string(),
+            block_time: *block_time,
         })
         .collect();
 
@@ -223,8 +336,11 @@ pub async fn handle_asset_transactions_blockfrost(
 pub async fn handle_asset_addresses_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let (policy, name) = split_policy_and_asset(&params[0])?;
 
     let asset_query_msg = Arc::new(Message::StateQuery(StateQuery::Assets(
@@ -252,10 +368,14 @@ pub async fn handle_asset_addresses_blockfrost(
     )
     .await?;
 
-    let rest_addrs =
-        addresses.iter().map(AssetAddressRest::try_from).collect::<Result<Vec<_>, _>>().map_err(
-            |e| RESTError::InternalServerError(format!("Failed to convert address entry: {e}")),
-        )?;
+    let rest_addrs = pagination
+        .apply(addresses)
+        .iter()
+        .map(AssetAddressRest::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            RESTError::InternalServerError(format!("Failed to convert address entry: {e}"))
+        })?;
 
     let json = serde_json::to_string_pretty(&rest_addrs)?;
     Ok(RESTResponse::with_json(200, &json))
@@ -264,8 +384,11 @@ pub async fn handle_asset_addresses_blockfrost(
 pub async fn handle_policy_assets_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let policy: PolicyId = PolicyId::from_hex(&params[0])
         .map_err(|_| RESTError::invalid_param("policy_id", "invalid hex"))?;
 
@@ -294,11 +417,41 @@ pub async fn handle_policy_assets_blockfrost(
     )
     .await?;
 
-    let rest_assets: Vec<PolicyAssetRest> = assets.iter().map(Into::into).collect();
+    let rest_assets: Vec<PolicyAssetRest> =
+        pagination.apply(assets).iter().map(Into::into).collect();
     let json = serde_json::to_string_pretty(&rest_assets)?;
     Ok(RESTResponse::with_json(200, &json))
 }
 
+/// Resolve transaction identifiers to their hashes through the chain store
+pub(crate) async fn resolve_tx_hashes(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    tx_ids: Vec<TxIdentifier>,
+) -> Result<HashMap<TxIdentifier, TxHash>, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
+        BlocksStateQuery::GetTransactionHashes { tx_ids },
+    )));
+    let tx_hashes = query_state(
+        context,
+        &handlers_config.blocks_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Blocks(
+                BlocksStateQueryResponse::TransactionHashes(TransactionHashes { tx_hashes }),
+            )) => Ok(tx_hashes),
+            Message::StateQueryResponse(StateQueryResponse::Blocks(
+                BlocksStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while resolving transaction hashes",
+            )),
+        },
+    )
+    .await?;
+    Ok(tx_hashes)
+}
+
 pub async fn fetch_asset_metadata(
     asset: &str,
     offchain_registry_url: &str,
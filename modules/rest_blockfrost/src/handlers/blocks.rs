@@ -10,6 +10,7 @@ use acropolis_common::{
         misc::Order,
         utils::rest_query_state,
     },
+    rest_helper::Pagination,
     BlockHash,
 };
 use caryatid_sdk::Context;
@@ -119,14 +120,8 @@ pub async fn handle_blocks_latest_hash_number_transactions_blockfrost(
         _ => return Err(RESTError::BadRequest("Invalid parameters".to_string())),
     };
 
-    extract_strict_query_params!(query_params, {
-        "count" => limit: Option<u64>,
-        "page" => page: Option<u64>,
-        "order" => order: Option<Order>,
-    });
-    let limit = limit.unwrap_or(100);
-    let skip = (page.unwrap_or(1) - 1) * limit;
-    let order = order.unwrap_or(Order::Asc);
+    let pagination = Pagination::from_query_params(&query_params)?;
+    let (limit, skip, order) = (pagination.count, pagination.offset(), pagination.order);
 
     match param.as_str() {
         "latest" => {
@@ -229,14 +224,8 @@ pub async fn handle_blocks_latest_hash_number_transactions_cbor_blockfrost(
         _ => return Err(RESTError::BadRequest("Invalid parameters".to_string())),
     };
 
-    extract_strict_query_params!(query_params, {
-        "count" => limit: Option<u64>,
-        "page" => page: Option<u64>,
-        "order" => order: Option<Order>,
-    });
-    let limit = limit.unwrap_or(100);
-    let skip = (page.unwrap_or(1) - 1) * limit;
-    let order = order.unwrap_or(Order::Asc);
+    let pagination = Pagination::from_query_params(&query_params)?;
+    let (limit, skip, order) = (pagination.count, pagination.offset(), pagination.order);
 
     match param.as_str() {
         "latest" => {
@@ -342,11 +331,11 @@ pub async fn handle_blocks_hash_number_next_blockfrost(
     let block_key = parse_block_key(param)?;
 
     extract_strict_query_params!(query_params, {
-        "count" => limit: Option<u64>,
+        "count" => count: Option<u64>,
         "page" => page: Option<u64>,
     });
-    let limit = limit.unwrap_or(100);
-    let skip = (page.unwrap_or(1) - 1) * limit;
+    let pagination = Pagination::new(count, page, None)?;
+    let (limit, skip) = (pagination.count, pagination.offset());
 
     let blocks_next_msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
         BlocksStateQuery::GetNextBlocks {
@@ -387,11 +376,11 @@ pub async fn handle_blocks_hash_number_previous_blockfrost(
     let block_key = parse_block_key(param)?;
 
     extract_strict_query_params!(query_params, {
-        "count" => limit: Option<u64>,
+        "count" => count: Option<u64>,
         "page" => page: Option<u64>,
     });
-    let limit = limit.unwrap_or(100);
-    let skip = (page.unwrap_or(1) - 1) * limit;
+    let pagination = Pagination::new(count, page, None)?;
+    let (limit, skip) = (pagination.count, pagination.offset());
 
     let blocks_previous_msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
         BlocksStateQuery::GetPreviousBlocks {
@@ -506,11 +495,11 @@ pub async fn handle_blocks_hash_number_addresses_blockfrost(
     let block_key = parse_block_key(param)?;
 
     extract_strict_query_params!(query_params, {
-        "count" => limit: Option<u64>,
+        "count" => count: Option<u64>,
         "page" => page: Option<u64>,
     });
-    let limit = limit.unwrap_or(100);
-    let skip = (page.unwrap_or(1) - 1) * limit;
+    let pagination = Pagination::new(count, page, None)?;
+    let (limit, skip) = (pagination.count, pagination.offset());
 
     let block_involved_addresses_msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
         BlocksStateQuery::GetBlockInvolvedAddresses {
@@ -1,6 +1,8 @@
 use crate::{
     handlers_config::HandlersConfig,
-    types::{EpochActivityRest, ProtocolParamsRest},
+    types::{
+        EpochActivityRest, ProtocolParamsRest, SPDDByEpochAndPoolItemRest, SPDDByEpochItemRest,
+    },
 };
 use acropolis_common::queries::{
     blocks::{BlocksStateQuery, BlocksStateQueryResponse},
@@ -9,18 +11,22 @@ use acropolis_common::queries::{
 use acropolis_common::rest_error::RESTError;
 use acropolis_common::serialization::Bech32Conversion;
 use acropolis_common::{
+    extract_strict_query_params,
     messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
     queries::{
         accounts::{AccountsStateQuery, AccountsStateQueryResponse},
         epochs::{EpochsStateQuery, EpochsStateQueryResponse},
+        misc::Order,
         parameters::{ParametersStateQuery, ParametersStateQueryResponse},
         pools::{PoolsStateQuery, PoolsStateQueryResponse},
         spdd::{SPDDStateQuery, SPDDStateQueryResponse},
         utils::query_state,
     },
+    rest_helper::Pagination,
     PoolId,
 };
 use caryatid_sdk::Context;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub async fn handle_epoch_info_blockfrost(
@@ -99,11 +105,14 @@ pub async fn handle_epoch_info_blockfrost(
         }
     };
 
-    // For the latest epoch, query accounts-state for the stake pool delegation distribution (SPDD)
-    // Otherwise, fall back to SPDD module to fetch historical epoch totals
+    // Use the active stake recorded by epochs-state if it tracks the SPDD.
+    // Otherwise, for the latest epoch, query accounts-state for the stake pool delegation distribution (SPDD)
+    // and fall back to SPDD module to fetch historical epoch totals
     // if spdd_storage is not enabled, return NULL for active_stakes
     let epoch_number = response.epoch;
-    let total_active_stakes = if is_latest {
+    let total_active_stakes = if response.active_stake.is_some() {
+        response.active_stake
+    } else if is_latest {
         let total_active_stakes_msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
             AccountsStateQuery::GetActiveStakes {},
         )));
@@ -238,6 +247,36 @@ pub async fn handle_epoch_params_blockfrost(
             }
             (epoch, params)
         }
+        // Epochs before parameters-state started, from a snapshot, may still be
+        // in the historical epochs store
+        ParametersStateQueryResponse::Error(QueryError::NotFound { .. })
+            if epoch_number.is_some_and(|epoch| epoch <= latest_epoch_number) =>
+        {
+            let epoch = epoch_number.expect("checked above");
+            let msg = Arc::new(Message::StateQuery(StateQuery::Epochs(
+                EpochsStateQuery::GetEpochParameters {
+                    epoch_number: epoch,
+                },
+            )));
+            let params = query_state(
+                &context,
+                &handlers_config.historical_epochs_query_topic,
+                msg,
+                |message| match message {
+                    Message::StateQueryResponse(StateQueryResponse::Epochs(
+                        EpochsStateQueryResponse::EpochParameters(response),
+                    )) => Ok(response.parameters),
+                    Message::StateQueryResponse(StateQueryResponse::Epochs(
+                        EpochsStateQueryResponse::Error(e),
+                    )) => Err(e),
+                    _ => Err(QueryError::internal_error(
+                        "Unexpected message type while retrieving epoch parameters",
+                    )),
+                },
+            )
+            .await?;
+            (epoch, params)
+        }
         ParametersStateQueryResponse::Error(QueryError::NotFound { .. }) => {
             return Err(RESTError::not_found(
                 "Protocol parameters not found for requested epoch",
@@ -289,8 +328,15 @@ pub async fn handle_epoch_params_blockfrost(
 pub async fn handle_epoch_next_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    extract_strict_query_params!(query_params, {
+        "count" => count: Option<u64>,
+        "page" => page: Option<u64>,
+    });
+    let pagination = Pagination::new(count, page, None)?;
+
     if params.len() != 1 {
         return Err(RESTError::BadRequest(
             "Expected one parameter: an epoch number".to_string(),
@@ -333,9 +379,20 @@ pub async fn handle_epoch_next_blockfrost(
         return Ok(RESTResponse::with_json(200, "[]"));
     }
 
+    // The latest epoch is only known to epochs-state, so historical epochs
+    // state serves the page up to it
+    let (limit, skip) = (pagination.count, pagination.offset());
+    let first = parsed + 1 + skip;
+    if first > latest_epoch.epoch {
+        return Ok(RESTResponse::with_json(200, "[]"));
+    }
+    let historical_limit = limit.min(latest_epoch.epoch - first);
+
     let next_epochs_msg = Arc::new(Message::StateQuery(StateQuery::Epochs(
         EpochsStateQuery::GetNextEpochs {
             epoch_number: parsed,
+            limit: historical_limit,
+            skip,
         },
     )));
 
@@ -356,7 +413,9 @@ pub async fn handle_epoch_next_blockfrost(
         },
     )
     .await?;
-    next_epochs.push(EpochActivityRest::from(latest_epoch));
+    if first + limit > latest_epoch.epoch {
+        next_epochs.push(EpochActivityRest::from(latest_epoch));
+    }
 
     let json = serde_json::to_string_pretty(&next_epochs)?;
     Ok(RESTResponse::with_json(200, &json))
@@ -365,8 +424,15 @@ pub async fn handle_epoch_next_blockfrost(
 pub async fn handle_epoch_previous_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    extract_strict_query_params!(query_params, {
+        "count" => count: Option<u64>,
+        "page" => page: Option<u64>,
+    });
+    let pagination = Pagination::new(count, page, None)?;
+
     if params.len() != 1 {
         return Err(RESTError::BadRequest(
             "Expected one parameter: an epoch number".to_string(),
@@ -408,6 +474,8 @@ pub async fn handle_epoch_previous_blockfrost(
     let previous_epochs_msg = Arc::new(Message::StateQuery(StateQuery::Epochs(
         EpochsStateQuery::GetPreviousEpochs {
             epoch_number: parsed,
+            limit: pagination.count,
+            skip: pagination.offset(),
         },
     )));
     let previous_epochs = query_state(
@@ -435,8 +503,11 @@ pub async fn handle_epoch_previous_blockfrost(
 pub async fn handle_epoch_total_stakes_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     if params.len() != 1 {
         return Err(RESTError::BadRequest(
             "Expected one parameter: an epoch number".to_string(),
@@ -474,17 +545,56 @@ pub async fn handle_epoch_total_stakes_blockfrost(
         return Err(RESTError::not_found("Epoch not found"));
     }
 
-    // TODO: store historical per-accounts SPDD in historical accounts state
-    Err(RESTError::NotImplemented(
-        "Unsupported until historical_accounts_state indexes per-account spdd".to_string(),
-    ))
+    let stakes_msg = Arc::new(Message::StateQuery(StateQuery::SPDD(
+        SPDDStateQuery::GetEpochStakes {
+            epoch: epoch_number,
+            limit: pagination.count,
+            skip: pagination.offset(),
+        },
+    )));
+    let stakes = query_state(
+        &context,
+        &handlers_config.spdd_query_topic,
+        stakes_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::EpochStakes(stakes),
+            )) => Ok(stakes),
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving epoch stakes",
+            )),
+        },
+    )
+    .await?;
+
+    let mut stakes_rest = Vec::<SPDDByEpochItemRest>::with_capacity(stakes.len());
+    for stake in stakes {
+        stakes_rest.push(SPDDByEpochItemRest {
+            stake_address: stake.stake_address.to_string().map_err(|e| {
+                RESTError::InternalServerError(format!(
+                    "Invalid stake address in epoch stakes: {e}"
+                ))
+            })?,
+            pool_id: stake.pool_id,
+            amount: stake.amount,
+        });
+    }
+
+    let json = serde_json::to_string_pretty(&stakes_rest)?;
+    Ok(RESTResponse::with_json(200, &json))
 }
 
 pub async fn handle_epoch_pool_stakes_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     if params.len() != 2 {
         return Err(RESTError::BadRequest(
             "Expected two parameters: an epoch number and a pool ID".to_string(),
@@ -497,7 +607,7 @@ pub async fn handle_epoch_pool_stakes_blockfrost(
         .parse::<u64>()
         .map_err(|_| RESTError::invalid_param("epoch", "invalid epoch number"))?;
 
-    let _pool_id = PoolId::from_bech32(pool_id_str)
+    let pool_id = PoolId::from_bech32(pool_id_str)
         .map_err(|_| RESTError::invalid_param("pool_id", "invalid Bech32 stake pool ID"))?;
 
     // Query latest epoch from epochs-state
@@ -526,16 +636,56 @@ pub async fn handle_epoch_pool_stakes_blockfrost(
         return Err(RESTError::not_found("Epoch not found"));
     }
 
-    Err(RESTError::NotImplemented(
-        "Unsupported until historical_accounts_state indexes per-account spdd".to_string(),
-    ))
+    let stakes_msg = Arc::new(Message::StateQuery(StateQuery::SPDD(
+        SPDDStateQuery::GetEpochPoolStakes {
+            epoch: epoch_number,
+            pool_id,
+            limit: pagination.count,
+            skip: pagination.offset(),
+        },
+    )));
+    let stakes = query_state(
+        &context,
+        &handlers_config.spdd_query_topic,
+        stakes_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::EpochPoolStakes(stakes),
+            )) => Ok(stakes),
+            Message::StateQueryResponse(StateQueryResponse::SPDD(
+                SPDDStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving epoch pool stakes",
+            )),
+        },
+    )
+    .await?;
+
+    let mut stakes_rest = Vec::<SPDDByEpochAndPoolItemRest>::with_capacity(stakes.len());
+    for stake in stakes {
+        stakes_rest.push(SPDDByEpochAndPoolItemRest {
+            stake_address: stake.stake_address.to_string().map_err(|e| {
+                RESTError::InternalServerError(format!(
+                    "Invalid stake address in epoch stakes: {e}"
+                ))
+            })?,
+            amount: stake.amount,
+        });
+    }
+
+    let json = serde_json::to_string_pretty(&stakes_rest)?;
+    Ok(RESTResponse::with_json(200, &json))
 }
 
 pub async fn handle_epoch_total_blocks_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     if params.len() != 1 {
         return Err(RESTError::BadRequest(
             "Expected one parameter: an epoch number".to_string(),
@@ -602,15 +752,20 @@ pub async fn handle_epoch_total_blocks_blockfrost(
         (epoch_info.first_block_height, epoch_info.last_block_height)
     };
 
-    // Query all blocks hashes from chain_store
+    // Query the page of block hashes from chain_store
     // using first_block_height and last_block_height
+    let Some((min_number, max_number)) =
+        pagination.range_bounds(first_block_height, last_block_height)
+    else {
+        return Ok(RESTResponse::with_json(200, "[]"));
+    };
     let block_hashes_msg = Arc::new(Message::StateQuery(StateQuery::Blocks(
         BlocksStateQuery::GetBlockHashesByNumberRange {
-            min_number: first_block_height,
-            max_number: last_block_height,
+            min_number,
+            max_number,
         },
     )));
-    let block_hashes = query_state(
+    let mut block_hashes = query_state(
         &context,
         &handlers_config.blocks_query_topic,
         block_hashes_msg,
@@ -625,6 +780,9 @@ pub async fn handle_epoch_total_blocks_blockfrost(
         },
     )
     .await?;
+    if pagination.order == Order::Desc {
+        block_hashes.reverse();
+    }
 
     let json = serde_json::to_string_pretty(&block_hashes)?;
     Ok(RESTResponse::with_json(200, &json))
@@ -633,8 +791,11 @@ pub async fn handle_epoch_total_blocks_blockfrost(
 pub async fn handle_epoch_pool_blocks_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     if params.len() != 2 {
         return Err(RESTError::BadRequest(
             "Expected two parameters: an epoch number and a pool ID".to_string(),
@@ -673,6 +834,7 @@ pub async fn handle_epoch_pool_blocks_blockfrost(
         },
     )
     .await?;
+    let blocks = pagination.apply(blocks);
 
     // NOTE:
     // Need to query chain_store
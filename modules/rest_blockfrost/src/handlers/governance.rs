@@ -1,8 +1,8 @@
 //! REST handlers for Acropolis Blockfrost /governance endpoints
 use crate::handlers_config::HandlersConfig;
 use crate::types::{
-    DRepInfoREST, DRepMetadataREST, DRepUpdateREST, DRepVoteREST, DRepsListREST, ProposalVoteREST,
-    VoterRoleREST,
+    DRepDelegatorREST, DRepInfoREST, DRepMetadataREST, DRepUpdateREST, DRepVoteREST, DRepsListREST,
+    ProposalInfoREST, ProposalTypeREST, ProposalVoteREST, ProposalsListREST, VoterRoleREST,
 };
 use acropolis_common::queries::errors::QueryError;
 use acropolis_common::rest_error::RESTError;
@@ -10,20 +10,31 @@ use acropolis_common::{
     messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
     queries::{
         accounts::{AccountsStateQuery, AccountsStateQueryResponse},
+        drdd::{DRDDStateQuery, DRDDStateQueryResponse},
         governance::{GovernanceStateQuery, GovernanceStateQueryResponse},
+        utils::query_state,
     },
-    Credential, GovActionId, TxHash, Voter,
+    rest_helper::Pagination,
+    Credential, DRepChoice, GovActionId, Lovelace, TxHash, Voter,
 };
 use caryatid_sdk::Context;
 use reqwest::Client;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Blockfrost IDs of the predefined DReps, which have no registration
+const DREP_ALWAYS_ABSTAIN: &str = "drep_always_abstain";
+const DREP_ALWAYS_NO_CONFIDENCE: &str = "drep_always_no_confidence";
+
 pub async fn handle_dreps_list_blockfrost(
     context: Arc<Context<Message>>,
     _params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
         GovernanceStateQuery::GetDRepsList,
     )));
@@ -39,8 +50,8 @@ pub async fn handle_dreps_list_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::DRepsList(list),
         )) => {
-            let response: Result<Vec<DRepsListREST>, RESTError> = list
-                .dreps
+            let response: Result<Vec<DRepsListREST>, RESTError> = pagination
+                .apply(list.dreps)
                 .iter()
                 .map(|cred| {
                     Ok(DRepsListREST {
@@ -78,6 +89,30 @@ pub async fn handle_single_drep_blockfrost(
         return Err(RESTError::param_missing("DRep ID"));
     };
 
+    let special = match drep_id.as_str() {
+        DREP_ALWAYS_ABSTAIN => Some(DRepChoice::Abstain),
+        DREP_ALWAYS_NO_CONFIDENCE => Some(DRepChoice::NoConfidence),
+        _ => None,
+    };
+    if let Some(drep) = special {
+        let Some(amount) = query_drep_voting_power(&context, &handlers_config, drep).await? else {
+            return Err(RESTError::storage_disabled("DRDD"));
+        };
+        let response = DRepInfoREST {
+            drep_id: drep_id.to_string(),
+            hex: String::new(),
+            amount: amount.to_string(),
+            active: true,
+            active_epoch: None,
+            has_script: false,
+            last_active_epoch: None,
+            retired: false,
+            expired: false,
+        };
+        let json = serde_json::to_string_pretty(&response)?;
+        return Ok(RESTResponse::with_json(200, &json));
+    }
+
     let credential = parse_drep_credential(drep_id)?;
 
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
@@ -99,36 +134,50 @@ pub async fn handle_single_drep_blockfrost(
         )) => {
             let active = !response.info.retired && !response.info.expired;
 
-            let stake_addresses = response.delegators.clone();
-
-            let sum_msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
-                AccountsStateQuery::GetAccountsBalancesSum { stake_addresses },
-            )));
-
-            let raw_sum = context
-                .message_bus
-                .request(&handlers_config.accounts_query_topic, sum_msg)
-                .await
-                .map_err(|e| RESTError::InternalServerError(format!("Message bus error: {e}")))?;
-            let sum_response = Arc::try_unwrap(raw_sum).unwrap_or_else(|arc| (*arc).clone());
-
-            let amount = match sum_response {
-                Message::StateQueryResponse(StateQueryResponse::Accounts(
-                    AccountsStateQueryResponse::AccountsBalancesSum(sum),
-                )) => sum.to_string(),
-
-                Message::StateQueryResponse(StateQueryResponse::Accounts(
-                    AccountsStateQueryResponse::Error(e),
-                )) => {
-                    return Err(RESTError::InternalServerError(format!(
-                        "Failed to sum balances: {e}"
+            // Voting power comes from the DRep distribution when it is stored, otherwise
+            // it is summed from the delegators' live balances
+            let drep = match &credential {
+                Credential::AddrKeyHash(hash) => DRepChoice::Key(*hash),
+                Credential::ScriptHash(hash) => DRepChoice::Script(*hash),
+            };
+            let amount = match query_drep_voting_power(&context, &handlers_config, drep).await? {
+                Some(amount) => amount.to_string(),
+                None => {
+                    let stake_addresses = response.delegators.clone();
+
+                    let sum_msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
+                        AccountsStateQuery::GetAccountsBalancesSum { stake_addresses },
                     )));
-                }
 
-                _ => {
-                    return Err(RESTError::unexpected_response(
-                        "Unexpected response from accounts-state",
-                    ));
+                    let raw_sum = context
+                        .message_bus
+                        .request(&handlers_config.accounts_query_topic, sum_msg)
+                        .await
+                        .map_err(|e| {
+                            RESTError::InternalServerError(format!("Message bus error: {e}"))
+                        })?;
+                    let sum_response =
+                        Arc::try_unwrap(raw_sum).unwrap_or_else(|arc| (*arc).clone());
+
+                    match sum_response {
+                        Message::StateQueryResponse(StateQueryResponse::Accounts(
+                            AccountsStateQueryResponse::AccountsBalancesSum(sum),
+                        )) => sum.to_string(),
+
+                        Message::StateQueryResponse(StateQueryResponse::Accounts(
+                            AccountsStateQueryResponse::Error(e),
+                        )) => {
+                            return Err(RESTError::InternalServerError(format!(
+                                "Failed to sum balances: {e}"
+                            )));
+                        }
+
+                        _ => {
+                            return Err(RESTError::unexpected_response(
+                                "Unexpected response from accounts-state",
+                            ));
+                        }
+                    }
                 }
             };
 
@@ -139,7 +188,7 @@ pub async fn handle_single_drep_blockfrost(
                 active,
                 active_epoch: response.info.active_epoch,
                 has_script: matches!(credential, Credential::ScriptHash(_)),
-                last_active_epoch: response.info.last_active_epoch,
+                last_active_epoch: Some(response.info.last_active_epoch),
                 retired: response.info.retired,
                 expired: response.info.expired,
             };
@@ -163,16 +212,19 @@ pub async fn handle_single_drep_blockfrost(
 pub async fn handle_drep_delegators_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let Some(drep_id) = params.first() else {
         return Err(RESTError::param_missing("DRep ID"));
     };
 
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let credential = parse_drep_credential(drep_id)?;
 
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
-        GovernanceStateQuery::GetDRepDelegators {
+        GovernanceStateQuery::GetDRepDelegatorStakes {
             drep_credential: credential,
         },
     )));
@@ -186,55 +238,30 @@ pub async fn handle_drep_delegators_blockfrost(
 
     match message {
         Message::StateQueryResponse(StateQueryResponse::Governance(
-            GovernanceStateQueryResponse::DRepDelegators(delegators),
+            GovernanceStateQueryResponse::DRepDelegatorStakes(stakes),
         )) => {
-            let msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
-                AccountsStateQuery::GetAccountsUtxoValuesMap {
-                    stake_addresses: delegators.addresses.clone(),
-                },
-            )));
-
-            let raw_msg = context
-                .message_bus
-                .request(&handlers_config.accounts_query_topic, msg)
-                .await
-                .map_err(|e| RESTError::InternalServerError(format!("Message bus error: {e}")))?;
-            let message = Arc::try_unwrap(raw_msg).unwrap_or_else(|arc| (*arc).clone());
-
-            match message {
-                Message::StateQueryResponse(StateQueryResponse::Accounts(
-                    AccountsStateQueryResponse::AccountsUtxoValuesMap(map),
-                )) => {
-                    let response: Result<Vec<_>, RESTError> = map
-                        .into_iter()
-                        .map(|(stake_address, amount)| {
-                            let bech32 = stake_address.to_string().map_err(|e| {
-                                RESTError::InternalServerError(format!(
-                                    "Failed to encode stake address: {}",
-                                    e
-                                ))
-                            })?;
-
-                            Ok(serde_json::json!({
-                                "address": bech32,
-                                "amount": amount.to_string(),
-                            }))
-                        })
-                        .collect();
-
-                    let response = response?;
-                    let json = serde_json::to_string_pretty(&response)?;
-                    Ok(RESTResponse::with_json(200, &json))
-                }
-
-                Message::StateQueryResponse(StateQueryResponse::Accounts(
-                    AccountsStateQueryResponse::Error(e),
-                )) => Err(e.into()),
+            // Keep the page in delegation order
+            let response: Result<Vec<_>, RESTError> = pagination
+                .apply(stakes.delegators)
+                .into_iter()
+                .map(|(stake_address, amount)| {
+                    let bech32 = stake_address.to_string().map_err(|e| {
+                        RESTError::InternalServerError(format!(
+                            "Failed to encode stake address: {}",
+                            e
+                        ))
+                    })?;
+
+                    Ok(DRepDelegatorREST {
+                        address: bech32,
+                        amount: amount.to_string(),
+                    })
+                })
+                .collect();
 
-                _ => Err(RESTError::unexpected_response(
-                    "Unexpected response from accounts-state",
-                )),
-            }
+            let response = response?;
+            let json = serde_json::to_string_pretty(&response)?;
+            Ok(RESTResponse::with_json(200, &json))
         }
 
         Message::StateQueryResponse(StateQueryResponse::Governance(
@@ -327,8 +354,11 @@ pub async fn handle_drep_metadata_blockfrost(
 pub async fn handle_drep_updates_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(drep_id) = params.first() else {
         return Err(RESTError::param_missing("DRep ID"));
     };
@@ -348,8 +378,8 @@ pub async fn handle_drep_updates_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::DRepUpdates(list),
         )) => {
-            let response: Vec<DRepUpdateREST> = list
-                .updates
+            let response: Vec<DRepUpdateREST> = pagination
+                .apply(list.updates)
                 .iter()
                 .map(|event| DRepUpdateREST {
                     tx_hash: "TxHash lookup not yet implemented".to_string(),
@@ -377,12 +407,15 @@ pub async fn handle_drep_updates_blockfrost(
 pub async fn handle_drep_votes_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let Some(drep_id) = params.first() else {
         return Err(RESTError::param_missing("DRep ID"));
     };
 
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let credential = parse_drep_credential(drep_id)?;
 
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
@@ -397,16 +430,25 @@ pub async fn handle_drep_votes_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::DRepVotes(votes),
         )) => {
-            let response: Vec<_> = votes
-                .votes
+            let response: Result<Vec<_>, RESTError> = pagination
+                .apply(votes.votes)
                 .iter()
-                .map(|vote| DRepVoteREST {
-                    tx_hash: hex::encode(vote.tx_hash),
-                    cert_index: vote.vote_index,
-                    vote: vote.vote.clone(),
+                .map(|vote| {
+                    Ok(DRepVoteREST {
+                        tx_hash: hex::encode(vote.tx_hash),
+                        cert_index: vote.vote_index,
+                        proposal_id: vote.proposal.to_bech32().map_err(|e| {
+                            RESTError::encoding_failed(&format!("proposal ID to Bech32: {e}"))
+                        })?,
+                        proposal_tx_hash: hex::encode(vote.proposal.transaction_id),
+                        proposal_cert_index: vote.proposal.action_index,
+                        vote: (&vote.vote).into(),
+                    })
                 })
                 .collect();
 
+            let response = response?;
+
             let json = serde_json::to_string_pretty(&response)?;
             Ok(RESTResponse::with_json(200, &json))
         }
@@ -426,8 +468,11 @@ pub async fn handle_drep_votes_blockfrost(
 pub async fn handle_proposals_list_blockfrost(
     context: Arc<Context<Message>>,
     _params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
         GovernanceStateQuery::GetProposalsList,
     )));
@@ -439,17 +484,24 @@ pub async fn handle_proposals_list_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::ProposalsList(list),
         )) => {
-            if list.proposals.is_empty() {
-                return Ok(RESTResponse::with_json(200, "[]"));
-            }
-
-            let props_bech32: Result<Vec<String>, _> =
-                list.proposals.iter().map(|id| id.to_bech32()).collect();
-
-            let vec = props_bech32
-                .map_err(|e| RESTError::encoding_failed(&format!("proposal IDs to Bech32: {e}")))?;
+            let response: Result<Vec<_>, RESTError> = pagination
+                .apply(list.proposals)
+                .iter()
+                .map(|proposal| {
+                    let id = &proposal.gov_action_id;
+                    Ok(ProposalsListREST {
+                        id: id.to_bech32().map_err(|e| {
+                            RESTError::encoding_failed(&format!("proposal ID to Bech32: {e}"))
+                        })?,
+                        tx_hash: hex::encode(id.transaction_id),
+                        cert_index: id.action_index,
+                        governance_type: ProposalTypeREST::from(&proposal.gov_action),
+                    })
+                })
+                .collect();
 
-            let json = serde_json::to_string(&vec)?;
+            let response = response?;
+            let json = serde_json::to_string_pretty(&response)?;
             Ok(RESTResponse::with_json(200, &json))
         }
 
@@ -483,7 +535,30 @@ pub async fn handle_single_proposal_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::ProposalInfo(info),
         )) => {
-            let json = serde_json::to_string(&info)?;
+            let procedure = info.procedure;
+            let id = &procedure.gov_action_id;
+            let response = ProposalInfoREST {
+                id: id.to_bech32().map_err(|e| {
+                    RESTError::encoding_failed(&format!("proposal ID to Bech32: {e}"))
+                })?,
+                tx_hash: hex::encode(id.transaction_id),
+                cert_index: id.action_index,
+                governance_type: ProposalTypeREST::from(&procedure.gov_action),
+                deposit: procedure.deposit.to_string(),
+                return_address: procedure
+                    .reward_account
+                    .to_string()
+                    .map_err(|e| RESTError::encoding_failed(&format!("return address: {e}")))?,
+                governance_description: serde_json::to_value(&procedure.gov_action)?,
+                ratified_epoch: info.ratified_epoch,
+                enacted_epoch: info.enacted_epoch,
+                // Dropped proposals are not tracked separately from expired ones
+                dropped_epoch: None,
+                expired_epoch: info.expired_epoch,
+                expiration: info.expiration,
+            };
+
+            let json = serde_json::to_string_pretty(&response)?;
             Ok(RESTResponse::with_json(200, &json))
         }
 
@@ -518,12 +593,12 @@ pub async fn handle_proposal_withdrawals_blockfrost(
 pub async fn handle_proposal_votes_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let proposal = parse_gov_action_id(&params)?;
 
-    let tx_hash = hex::encode(proposal.transaction_id);
-    let cert_index = proposal.action_index;
+    let pagination = Pagination::from_query_params(&query_params)?;
 
     let msg = Arc::new(Message::StateQuery(StateQuery::Governance(
         GovernanceStateQuery::GetProposalVotes { proposal },
@@ -536,9 +611,15 @@ pub async fn handle_proposal_votes_blockfrost(
         Message::StateQueryResponse(StateQueryResponse::Governance(
             GovernanceStateQueryResponse::ProposalVotes(votes),
         )) => {
+            // Order by the transaction the vote was cast in, so pages are stable
+            let mut votes: Vec<_> = votes.votes.into_iter().collect();
+            votes.sort_by(|(_, (a_tx, a_proc)), (_, (b_tx, b_proc))| {
+                (a_tx, a_proc.vote_index).cmp(&(b_tx, b_proc.vote_index))
+            });
+
             let mut votes_list = Vec::new();
 
-            for (voter, (_, voting_proc)) in votes.votes {
+            for (voter, (tx_hash, voting_proc)) in pagination.apply(votes) {
                 let voter_role = match voter {
                     Voter::ConstitutionalCommitteeKey(_)
                     | Voter::ConstitutionalCommitteeScript(_) => {
@@ -551,11 +632,11 @@ pub async fn handle_proposal_votes_blockfrost(
                 let voter_str = voter.to_string();
 
                 votes_list.push(ProposalVoteREST {
-                    tx_hash: tx_hash.clone(),
-                    cert_index,
+                    tx_hash: hex::encode(tx_hash),
+                    cert_index: voting_proc.vote_index,
                     voter_role,
                     voter: voter_str,
-                    vote: voting_proc.vote,
+                    vote: (&voting_proc.vote).into(),
                 });
             }
 
@@ -608,6 +689,43 @@ pub fn parse_gov_action_id(params: &[String]) -> Result<GovActionId, RESTError>
     })
 }
 
+/// Voting power of a DRep from the latest DRep delegation distribution, or None if
+/// it is not stored
+async fn query_drep_voting_power(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    drep: DRepChoice,
+) -> Result<Option<Lovelace>, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::DRDD(
+        DRDDStateQuery::GetDRepVotingPower { drep },
+    )));
+
+    let result =
+        query_state(
+            context,
+            &handlers_config.drdd_query_topic,
+            msg,
+            |message| match message {
+                Message::StateQueryResponse(StateQueryResponse::DRDD(
+                    DRDDStateQueryResponse::DRepVotingPower(amount),
+                )) => Ok(amount),
+                Message::StateQueryResponse(StateQueryResponse::DRDD(
+                    DRDDStateQueryResponse::Error(e),
+                )) => Err(e),
+                _ => Err(QueryError::internal_error(
+                    "Unexpected response from drdd-state",
+                )),
+            },
+        )
+        .await;
+
+    match result {
+        Ok(amount) => Ok(Some(amount)),
+        Err(QueryError::StorageDisabled { .. } | QueryError::NotFound { .. }) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 fn parse_drep_credential(drep_id: &str) -> Result<Credential, RESTError> {
     Credential::from_drep_bech32(drep_id)
         .map_err(|e| RESTError::invalid_param("drep_id", &format!("invalid Bech32 DRep ID: {e}")))
@@ -0,0 +1,313 @@
+//! REST handlers for node health and sync status
+//!
+//! Sync progress is measured against the slot expected at the current wall clock
+//! time, assuming one second slots since the tip. Readiness is probed by sending
+//! each state module a cheap query: a module that does not answer in time is
+//! unavailable, and one that answers `NotSynced` - or has not yet seen the startup
+//! messages it declared it needs - has no current state yet.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use acropolis_common::{
+    messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
+    queries::{
+        accounts::{AccountsStateQuery, AccountsStateQueryResponse},
+        blocks::{BlockInfo, BlocksStateQuery, BlocksStateQueryResponse},
+        epochs::{EpochsStateQuery, EpochsStateQueryResponse},
+        errors::QueryError,
+        governance::{GovernanceStateQuery, GovernanceStateQueryResponse},
+        parameters::{ParametersStateQuery, ParametersStateQueryResponse},
+        pools::{PoolsStateQuery, PoolsStateQueryResponse},
+    },
+    readiness,
+    rest_error::RESTError,
+};
+use caryatid_sdk::Context;
+
+use crate::{
+    handlers_config::HandlersConfig,
+    types::{HealthREST, ModuleReadiness, ModuleStatusREST, SyncPhase, SyncStatusREST, TipREST},
+};
+
+/// Handle `/health`, answering 503 until the node is synced and every module is ready
+pub async fn handle_health_blockfrost(
+    context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let status = sync_status(&context, &handlers_config).await;
+    let is_healthy = status.phase == SyncPhase::Synced
+        && status.modules.iter().all(|m| m.status == ModuleReadiness::Ready);
+
+    let health = HealthREST {
+        is_healthy,
+        phase: status.phase,
+        modules: status.modules,
+    };
+    let json = serde_json::to_string_pretty(&health)?;
+    Ok(RESTResponse::with_json(
+        if is_healthy { 200 } else { 503 },
+        &json,
+    ))
+}
+
+/// Handle `/sync`
+pub async fn handle_sync_blockfrost(
+    context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let status = sync_status(&context, &handlers_config).await;
+    let json = serde_json::to_string_pretty(&status)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+async fn sync_status(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+) -> SyncStatusREST {
+    let timeout = Duration::from_secs(handlers_config.health_probe_timeout);
+
+    // Probe all modules at once, so a slow one costs a single timeout
+    let probes: Vec<_> = readiness_probes(handlers_config)
+        .into_iter()
+        .map(|(module, topic, query)| {
+            let context = context.clone();
+            (
+                module,
+                tokio::spawn(async move { probe(&context, &topic, query, timeout).await }),
+            )
+        })
+        .collect();
+
+    let readiness = readiness::matrix();
+    let mut tip = None;
+    let mut modules = Vec::with_capacity(probes.len());
+    for (module, handle) in probes {
+        let (status, detail, latest_block) = match handle.await {
+            Ok(result) => result,
+            Err(e) => (ModuleReadiness::Unavailable, Some(e.to_string()), None),
+        };
+        tip = tip.or(latest_block);
+
+        // Answering isn't enough until it has seen its startup messages
+        let waiting = readiness.waiting_for(module);
+        let (status, detail) = if status == ModuleReadiness::Ready && !waiting.is_empty() {
+            (
+                ModuleReadiness::NotSynced,
+                Some(format!("Waiting for {}", waiting.join(", "))),
+            )
+        } else {
+            (status, detail)
+        };
+        modules.push(ModuleStatusREST {
+            module,
+            status,
+            detail,
+        });
+    }
+
+    let chain_store = modules
+        .iter()
+        .find(|m| m.module == "chain-store")
+        .map(|m| m.status)
+        .unwrap_or(ModuleReadiness::Unavailable);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let estimate = tip.as_ref().map(|tip| SyncEstimate::new(tip.slot, tip.timestamp, now));
+
+    SyncStatusREST {
+        phase: sync_phase(
+            chain_store,
+            estimate.as_ref(),
+            handlers_config.health_sync_tolerance,
+        ),
+        expected_slot: estimate.as_ref().map(|e| e.expected_slot),
+        slots_behind: estimate.as_ref().map(|e| e.slots_behind),
+        sync_progress: estimate.as_ref().map(|e| e.progress).unwrap_or(0.0),
+        tip: tip.map(tip_rest),
+        modules,
+    }
+}
+
+fn tip_rest(block: BlockInfo) -> TipREST {
+    TipREST {
+        hash: block.hash.to_string(),
+        height: block.number,
+        slot: block.slot,
+        epoch: block.epoch,
+        time: block.timestamp,
+    }
+}
+
+/// Module name, query topic and cheap query used to check each module is answering.
+/// The chain store probe also supplies the tip.
+fn readiness_probes(config: &HandlersConfig) -> Vec<(&'static str, String, StateQuery)> {
+    vec![
+        (
+            "chain-store",
+            config.blocks_query_topic.clone(),
+            StateQuery::Blocks(BlocksStateQuery::GetLatestBlock),
+        ),
+        (
+            "accounts-state",
+            config.accounts_query_topic.clone(),
+            StateQuery::Accounts(AccountsStateQuery::GetOptimalPoolSizing),
+        ),
+        (
+            "epochs-state",
+            config.epochs_query_topic.clone(),
+            StateQuery::Epochs(EpochsStateQuery::GetLatestEpoch),
+        ),
+        (
+            "parameters-state",
+            config.parameters_query_topic.clone(),
+            StateQuery::Parameters(ParametersStateQuery::GetNetworkName),
+        ),
+        (
+            "spo-state",
+            config.pools_query_topic.clone(),
+            StateQuery::Pools(PoolsStateQuery::GetPoolsRetiringList),
+        ),
+        (
+            "drep-state",
+            config.dreps_query_topic.clone(),
+            StateQuery::Governance(GovernanceStateQuery::GetDRepsList),
+        ),
+        (
+            "governance-state",
+            config.governance_query_topic.clone(),
+            StateQuery::Governance(GovernanceStateQuery::GetProposalsList),
+        ),
+    ]
+}
+
+async fn probe(
+    context: &Arc<Context<Message>>,
+    topic: &str,
+    query: StateQuery,
+    timeout: Duration,
+) -> (ModuleReadiness, Option<String>, Option<BlockInfo>) {
+    let request = Arc::new(Message::StateQuery(query));
+    let response =
+        match tokio::time::timeout(timeout, context.message_bus.request(topic, request)).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return (ModuleReadiness::Unavailable, Some(e.to_string()), None),
+            Err(_) => {
+                return (
+                    ModuleReadiness::Unavailable,
+                    Some(format!("No response on {topic}")),
+                    None,
+                )
+            }
+        };
+
+    let Message::StateQueryResponse(response) = response.as_ref() else {
+        return (
+            ModuleReadiness::Unavailable,
+            Some(format!("Unexpected response on {topic}")),
+            None,
+        );
+    };
+    match response {
+        StateQueryResponse::Blocks(BlocksStateQueryResponse::LatestBlock(block)) => {
+            (ModuleReadiness::Ready, None, Some(block.clone()))
+        }
+        _ => match query_error(response) {
+            Some(QueryError::NotSynced { message }) => {
+                (ModuleReadiness::NotSynced, Some(message.clone()), None)
+            }
+            _ => (ModuleReadiness::Ready, None, None),
+        },
+    }
+}
+
+fn query_error(response: &StateQueryResponse) -> Option<&QueryError> {
+    match response {
+        StateQueryResponse::Accounts(AccountsStateQueryResponse::Error(e))
+        | StateQueryResponse::Blocks(BlocksStateQueryResponse::Error(e))
+        | StateQueryResponse::Epochs(EpochsStateQueryResponse::Error(e))
+        | StateQueryResponse::Governance(GovernanceStateQueryResponse::Error(e))
+        | StateQueryResponse::Parameters(ParametersStateQueryResponse::Error(e))
+        | StateQueryResponse::Pools(PoolsStateQueryResponse::Error(e)) => Some(e),
+        _ => None,
+    }
+}
+
+/// How far the tip is from the slot expected at the wall clock time
+#[derive(Debug, PartialEq)]
+struct SyncEstimate {
+    expected_slot: u64,
+    slots_behind: u64,
+    /// Percentage, to two decimal places
+    progress: f64,
+}
+
+impl SyncEstimate {
+    fn new(tip_slot: u64, tip_time: u64, now: u64) -> Self {
+        let slots_behind = now.saturating_sub(tip_time);
+        let expected_slot = tip_slot + slots_behind;
+        let progress = if expected_slot == 0 {
+            100.0
+        } else {
+            (tip_slot as f64 * 10000.0 / expected_slot as f64).floor() / 100.0
+        };
+        Self {
+            expected_slot,
+            slots_behind,
+            progress,
+        }
+    }
+}
+
+fn sync_phase(
+    chain_store: ModuleReadiness,
+    estimate: Option<&SyncEstimate>,
+    tolerance: u64,
+) -> SyncPhase {
+    match (chain_store, estimate) {
+        (_, Some(estimate)) if estimate.slots_behind <= tolerance => SyncPhase::Synced,
+        (_, Some(_)) => SyncPhase::Syncing,
+        (ModuleReadiness::NotSynced, None) => SyncPhase::Bootstrapping,
+        (_, None) => SyncPhase::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_measures_tip_against_wall_clock() {
+        let estimate = SyncEstimate::new(1_000, 5_000, 5_030);
+        assert_eq!(estimate.expected_slot, 1_030);
+        assert_eq!(estimate.slots_behind, 30);
+        assert_eq!(estimate.progress, 97.08);
+
+        // A tip stamped after the local clock is not ahead of it
+        let estimate = SyncEstimate::new(1_000, 5_000, 4_990);
+        assert_eq!(estimate.slots_behind, 0);
+        assert_eq!(estimate.progress, 100.0);
+    }
+
+    #[test]
+    fn phase_follows_tip_and_chain_store() {
+        let synced = SyncEstimate::new(1_000, 5_000, 5_060);
+        let behind = SyncEstimate::new(1_000, 5_000, 9_000);
+        let ready = ModuleReadiness::Ready;
+
+        assert_eq!(sync_phase(ready, Some(&synced), 120), SyncPhase::Synced);
+        assert_eq!(sync_phase(ready, Some(&behind), 120), SyncPhase::Syncing);
+        assert_eq!(
+            sync_phase(ModuleReadiness::NotSynced, None, 120),
+            SyncPhase::Bootstrapping
+        );
+        assert_eq!(
+            sync_phase(ModuleReadiness::Unavailable, None, 120),
+            SyncPhase::Unknown
+        );
+    }
+}
@@ -1,8 +1,13 @@
 pub mod accounts;
 pub mod addresses;
+pub mod admin;
 pub mod assets;
 pub mod blocks;
 pub mod epochs;
 pub mod governance;
+pub mod health;
+pub mod network;
 pub mod pools;
+pub mod scripts;
 pub mod transactions;
+pub mod utilities;
@@ -0,0 +1,88 @@
+//! REST handlers for network-wide supply, stake and era information
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
+    queries::{
+        accounts::{AccountsStateQuery, AccountsStateQueryResponse},
+        epochs::{EpochsStateQuery, EpochsStateQueryResponse},
+        errors::QueryError,
+        utils::query_state,
+    },
+    rest_error::RESTError,
+};
+use caryatid_sdk::Context;
+
+use crate::{
+    handlers_config::HandlersConfig,
+    types::{NetworkEraREST, NetworkREST},
+};
+
+/// Handle `/network`
+pub async fn handle_network_blockfrost(
+    context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
+        AccountsStateQuery::GetNetworkStats,
+    )));
+    let stats = query_state(
+        &context,
+        &handlers_config.accounts_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Accounts(
+                AccountsStateQueryResponse::NetworkStats(Some(stats)),
+            )) => Ok(stats),
+            Message::StateQueryResponse(StateQueryResponse::Accounts(
+                AccountsStateQueryResponse::NetworkStats(None),
+            )) => Err(QueryError::not_found(
+                "Network supply before the Shelley era",
+            )),
+            Message::StateQueryResponse(StateQueryResponse::Accounts(
+                AccountsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving network stats",
+            )),
+        },
+    )
+    .await?;
+
+    let json = serde_json::to_string_pretty(&NetworkREST::from(stats))?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/network/eras`
+pub async fn handle_network_eras_blockfrost(
+    context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::Epochs(
+        EpochsStateQuery::GetEraSummaries,
+    )));
+    let eras = query_state(
+        &context,
+        &handlers_config.epochs_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Epochs(
+                EpochsStateQueryResponse::EraSummaries(eras),
+            )) => Ok(eras),
+            Message::StateQueryResponse(StateQueryResponse::Epochs(
+                EpochsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving era summaries",
+            )),
+        },
+    )
+    .await?;
+
+    let eras: Vec<NetworkEraREST> = eras.iter().map(NetworkEraREST::from).collect();
+    let json = serde_json::to_string_pretty(&eras)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
@@ -1,7 +1,11 @@
 //! REST handlers for Acropolis Blockfrost /pools endpoints
 use crate::{
+    handlers::assets::resolve_tx_hashes,
     handlers_config::HandlersConfig,
-    types::{PoolDelegatorRest, PoolInfoRest, PoolRelayRest, PoolUpdateEventRest, PoolVoteRest},
+    types::{
+        PoolDelegatorRest, PoolInfoRest, PoolRelayRest, PoolRelaysRest, PoolUpdateEventRest,
+        PoolUpdateParametersRest, PoolVoteRest,
+    },
 };
 use crate::{
     types::{PoolEpochStateRest, PoolExtendedRest, PoolMetadataRest, PoolRetirementRest},
@@ -18,12 +22,13 @@ use acropolis_common::{
         pools::{PoolsStateQuery, PoolsStateQueryResponse},
         utils::query_state,
     },
-    rest_helper::ToCheckedF64,
-    PoolId, PoolRetirement, PoolUpdateAction, TxIdentifier,
+    relay_topology,
+    rest_helper::{Pagination, ToCheckedF64},
+    PoolId, PoolRegistration, PoolRetirement, PoolUpdateAction, TxIdentifier,
 };
 use caryatid_sdk::Context;
 use rust_decimal::Decimal;
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::join;
 use tracing::warn;
 
@@ -31,8 +36,11 @@ use tracing::warn;
 pub async fn handle_pools_list_blockfrost(
     context: Arc<Context<Message>>,
     _params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     // Prepare the message
     let msg = Arc::new(Message::StateQuery(StateQuery::Pools(
         PoolsStateQuery::GetPoolsList,
@@ -58,7 +66,7 @@ pub async fn handle_pools_list_blockfrost(
     };
 
     let pool_ids: Result<Vec<String>, _> =
-        pool_operators.iter().map(|operator| operator.to_bech32()).collect();
+        pagination.apply(pool_operators).iter().map(|operator| operator.to_bech32()).collect();
 
     let pool_ids = pool_ids.map_err(|e| RESTError::encoding_failed(&format!("pool IDs: {e}")))?;
     let json = serde_json::to_string(&pool_ids)?;
@@ -69,6 +77,7 @@ pub async fn handle_pools_list_blockfrost(
 pub async fn handle_pools_extended_retired_retiring_single_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     let param = match params.as_slice() {
@@ -78,13 +87,23 @@ pub async fn handle_pools_extended_retired_retiring_single_blockfrost(
 
     match param.as_str() {
         "extended" => {
-            handle_pools_extended_blockfrost(context.clone(), handlers_config.clone()).await
+            let pagination = Pagination::from_query_params(&query_params)?;
+            handle_pools_extended_blockfrost(context.clone(), pagination, handlers_config.clone())
+                .await
         }
         "retired" => {
-            handle_pools_retired_blockfrost(context.clone(), handlers_config.clone()).await
+            let pagination = Pagination::from_query_params(&query_params)?;
+            handle_pools_retired_blockfrost(context.clone(), pagination, handlers_config.clone())
+                .await
         }
         "retiring" => {
-            handle_pools_retiring_blockfrost(context.clone(), handlers_config.clone()).await
+            let pagination = Pagination::from_query_params(&query_params)?;
+            handle_pools_retiring_blockfrost(context.clone(), pagination, handlers_config.clone())
+                .await
+        }
+        "relays" => {
+            handle_pools_relays_blockfrost(context.clone(), &query_params, handlers_config.clone())
+                .await
         }
         _ => {
             let pool_id = PoolId::from_bech32(param).map_err(|e| {
@@ -97,6 +116,7 @@ pub async fn handle_pools_extended_retired_retiring_single_blockfrost(
 
 async fn handle_pools_extended_blockfrost(
     context: Arc<Context<Message>>,
+    pagination: Pagination,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     // Get pools info from spo-state
@@ -165,7 +185,7 @@ async fn handle_pools_extended_blockfrost(
         latest_epoch_info_f,
         optimal_pool_sizing_f
     );
-    let pools_list_with_info = pools_list_with_info?;
+    let pools_list_with_info = pagination.apply(pools_list_with_info?);
     let latest_epoch_info = latest_epoch_info?;
     let latest_epoch = latest_epoch_info.epoch;
     let optimal_pool_sizing = optimal_pool_sizing?;
@@ -293,6 +313,7 @@ async fn handle_pools_extended_blockfrost(
 
 async fn handle_pools_retired_blockfrost(
     context: Arc<Context<Message>>,
+    pagination: Pagination,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     // Get retired pools from spo-state
@@ -315,7 +336,8 @@ async fn handle_pools_retired_blockfrost(
     )
     .await?;
 
-    let retired_pools_rest = retired_pools
+    let retired_pools_rest = pagination
+        .apply(retired_pools)
         .iter()
         .filter_map(|PoolRetirement { operator, epoch }| {
             let pool_id = operator.to_bech32().ok()?;
@@ -332,6 +354,7 @@ async fn handle_pools_retired_blockfrost(
 
 async fn handle_pools_retiring_blockfrost(
     context: Arc<Context<Message>>,
+    pagination: Pagination,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
     // Get retiring pools from spo-state
@@ -354,7 +377,8 @@ async fn handle_pools_retiring_blockfrost(
     )
     .await?;
 
-    let retiring_pools_rest = retiring_pools
+    let retiring_pools_rest = pagination
+        .apply(retiring_pools)
         .iter()
         .filter_map(|PoolRetirement { operator, epoch }| {
             let pool_id = operator.to_bech32().ok()?;
@@ -678,8 +702,11 @@ async fn handle_pools_spo_blockfrost(
 pub async fn handle_pool_history_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(pool_id) = params.first() else {
         return Err(RESTError::param_missing("pool ID"));
     };
@@ -730,6 +757,7 @@ pub async fn handle_pool_history_blockfrost(
 
     // remove epoch state whose epoch is greater than or equal to latest_epoch
     pool_history.retain(|state| state.epoch < latest_epoch);
+    let pool_history = pagination.apply(pool_history);
 
     let json = serde_json::to_string(&pool_history)?;
     Ok(RESTResponse::with_json(200, &json))
@@ -840,11 +868,69 @@ pub async fn handle_pool_relays_blockfrost(
     Ok(RESTResponse::with_json(200, &json))
 }
 
+/// Handle `/pools/relays`, the relays of every registered pool - as a cardano-node
+/// topology file with `?format=topology`
+async fn handle_pools_relays_blockfrost(
+    context: Arc<Context<Message>>,
+    query_params: &HashMap<String, String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let as_topology = match query_params.get("format").map(String::as_str) {
+        None | Some("json") => false,
+        Some("topology") => true,
+        Some(_) => {
+            return Err(RESTError::invalid_param(
+                "format",
+                "must be json or topology",
+            ))
+        }
+    };
+
+    let msg = Arc::new(Message::StateQuery(StateQuery::Pools(
+        PoolsStateQuery::GetPoolsRelays,
+    )));
+    let pools_relays = query_state(
+        &context,
+        &handlers_config.pools_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Pools(
+                PoolsStateQueryResponse::PoolsRelays(pools_relays),
+            )) => Ok(pools_relays),
+            Message::StateQueryResponse(StateQueryResponse::Pools(
+                PoolsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving pools relays",
+            )),
+        },
+    )
+    .await?;
+
+    if as_topology {
+        let json = serde_json::to_string_pretty(&relay_topology::topology(&pools_relays))?;
+        return Ok(RESTResponse::with_json(200, &json));
+    }
+
+    let mut pools_rest = Vec::with_capacity(pools_relays.len());
+    for pool in pools_relays {
+        pools_rest.push(PoolRelaysRest {
+            pool_id: pool.pool_id.to_bech32()?,
+            relays: pool.relays.into_iter().map(PoolRelayRest::from).collect(),
+        });
+    }
+    let json = serde_json::to_string(&pools_rest)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
 pub async fn handle_pool_delegators_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(pool_id) = params.first() else {
         return Err(RESTError::param_missing("pool ID"));
     };
@@ -852,9 +938,14 @@ pub async fn handle_pool_delegators_blockfrost(
     let spo = PoolId::from_bech32(pool_id)
         .map_err(|_| RESTError::invalid_param("pool ID", "invalid Bech32 stake pool ID"))?;
 
-    // Get Pool delegators from spo-state
+    // Get the page of Pool delegators from spo-state
     let pool_delegators_msg = Arc::new(Message::StateQuery(StateQuery::Pools(
-        PoolsStateQuery::GetPoolDelegators { pool_id: spo },
+        PoolsStateQuery::GetPoolDelegatorsPage {
+            pool_id: spo,
+            limit: pagination.count,
+            skip: pagination.offset(),
+            order: pagination.order.clone(),
+        },
     )));
 
     let pool_delegators = query_state(
@@ -880,7 +971,7 @@ pub async fn handle_pool_delegators_blockfrost(
     )
     .await?;
 
-    // Get pool_delegators from accounts-state as fallback
+    // Get pool_delegators from accounts-state as fallback, and page them here
     let pool_delegators = match pool_delegators {
         Some(delegators) => delegators,
         None => {
@@ -888,7 +979,7 @@ pub async fn handle_pool_delegators_blockfrost(
             let pool_delegators_msg = Arc::new(Message::StateQuery(StateQuery::Accounts(
                 AccountsStateQuery::GetPoolDelegators { pool_operator: spo },
             )));
-            let pool_delegators = query_state(
+            let mut pool_delegators = query_state(
                 &context,
                 &handlers_config.accounts_query_topic,
                 pool_delegators_msg,
@@ -903,7 +994,9 @@ pub async fn handle_pool_delegators_blockfrost(
                 },
             )
             .await?;
-            pool_delegators
+            // Delegators come from a map, so sort them for stable pages
+            pool_delegators.sort();
+            pagination.apply(pool_delegators)
         }
     };
 
@@ -925,8 +1018,11 @@ pub async fn handle_pool_delegators_blockfrost(
 pub async fn handle_pool_blocks_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(pool_id) = params.first() else {
         return Err(RESTError::param_missing("pool ID"));
     };
@@ -954,6 +1050,7 @@ pub async fn handle_pool_blocks_blockfrost(
         },
     )
     .await?;
+    let pool_blocks = pagination.apply(pool_blocks);
 
     // NOTE:
     // Need to query chain_store
@@ -966,8 +1063,11 @@ pub async fn handle_pool_blocks_blockfrost(
 pub async fn handle_pool_updates_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(pool_id) = params.first() else {
         return Err(RESTError::param_missing("pool ID"));
     };
@@ -998,14 +1098,48 @@ pub async fn handle_pool_updates_blockfrost(
     )
     .await?;
 
-    let pool_updates_rest = pool_updates
+    // Pair each registration with the parameters it replaced before paging
+    let mut previous: Option<PoolRegistration> = None;
+    let pool_updates = pool_updates
         .into_iter()
-        .map(|u| PoolUpdateEventRest {
-            tx_hash: "TxHash lookup not yet implemented".to_string(),
-            cert_index: u.cert_index,
-            action: u.action,
+        .map(|update| {
+            let replaced = match &update.parameters {
+                Some(parameters) => previous.replace(parameters.clone()),
+                None => None,
+            };
+            (update, replaced)
         })
         .collect::<Vec<_>>();
+    let pool_updates = pagination.apply(pool_updates);
+    if pool_updates.is_empty() {
+        return Ok(RESTResponse::with_json(200, "[]"));
+    }
+
+    let tx_ids = pool_updates.iter().map(|(update, _)| update.tx_identifier).collect();
+    let tx_hashes = resolve_tx_hashes(&context, &handlers_config, tx_ids).await?;
+
+    let pool_updates_rest = pool_updates
+        .iter()
+        .map(|(update, replaced)| {
+            let tx_hash = tx_hashes.get(&update.tx_identifier).ok_or_else(|| {
+                RESTError::InternalServerError("Missing tx hash for pool update".to_string())
+            })?;
+            Ok(PoolUpdateEventRest {
+                tx_hash: *tx_hash,
+                cert_index: update.cert_index,
+                action: update.action.clone(),
+                parameters: update
+                    .parameters
+                    .as_ref()
+                    .map(PoolUpdateParametersRest::try_from)
+                    .transpose()?,
+                previous_parameters: replaced
+                    .as_ref()
+                    .map(PoolUpdateParametersRest::try_from)
+                    .transpose()?,
+            })
+        })
+        .collect::<Result<Vec<_>, RESTError>>()?;
 
     let json = serde_json::to_string(&pool_updates_rest)?;
     Ok(RESTResponse::with_json(200, &json))
@@ -1014,8 +1148,11 @@ pub async fn handle_pool_updates_blockfrost(
 pub async fn handle_pool_votes_blockfrost(
     context: Arc<Context<Message>>,
     params: Vec<String>,
+    query_params: HashMap<String, String>,
     handlers_config: Arc<HandlersConfig>,
 ) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
     let Some(pool_id) = params.first() else {
         return Err(RESTError::param_missing("pool ID"));
     };
@@ -1043,7 +1180,8 @@ pub async fn handle_pool_votes_blockfrost(
     )
     .await?;
 
-    let pool_votes_rest = pool_votes
+    let pool_votes_rest = pagination
+        .apply(pool_votes)
         .into_iter()
         .map(|v| PoolVoteRest {
             tx_hash: v.tx_hash,
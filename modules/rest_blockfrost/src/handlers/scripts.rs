@@ -0,0 +1,293 @@
+//! REST handlers for Acropolis Blockfrost /scripts endpoints
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use acropolis_common::{
+    messages::{Message, RESTResponse, StateQuery, StateQueryResponse},
+    queries::{
+        errors::QueryError,
+        parameters::{ParametersStateQuery, ParametersStateQueryResponse},
+        scripts::{ScriptsStateQuery, ScriptsStateQueryResponse},
+        utils::query_state,
+    },
+    rest_error::RESTError,
+    rest_helper::Pagination,
+    DatumHash, ExUnitPrices, ScriptHash,
+};
+use caryatid_sdk::Context;
+use hex::FromHex;
+
+use crate::{
+    handlers::{assets::resolve_tx_hashes, transactions::redeemer_fee},
+    handlers_config::HandlersConfig,
+    types::{
+        ScriptCborRest, ScriptDatumCborRest, ScriptDatumRest, ScriptInfoRest, ScriptJsonRest,
+        ScriptListItemRest, ScriptRedeemerRest,
+    },
+    utils::{native_script_json, plutus_data_json},
+};
+
+fn parse_script_hash(param: &str) -> Result<ScriptHash, RESTError> {
+    ScriptHash::from_hex(param).map_err(|_| RESTError::invalid_param("script_hash", "invalid hex"))
+}
+
+fn parse_datum_hash(param: &str) -> Result<DatumHash, RESTError> {
+    DatumHash::from_hex(param).map_err(|_| RESTError::invalid_param("datum_hash", "invalid hex"))
+}
+
+async fn query_scripts<T>(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    query: ScriptsStateQuery,
+    extractor: impl FnOnce(ScriptsStateQueryResponse) -> Option<T>,
+) -> Result<T, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::Scripts(query)));
+    let result = query_state(
+        context,
+        &handlers_config.scripts_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Scripts(
+                ScriptsStateQueryResponse::Error(e),
+            )) => Err(e),
+            Message::StateQueryResponse(StateQueryResponse::Scripts(response)) => {
+                extractor(response).ok_or_else(|| {
+                    QueryError::internal_error("Unexpected response from scripts-state")
+                })
+            }
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while querying scripts",
+            )),
+        },
+    )
+    .await?;
+    Ok(result)
+}
+
+/// Handle `/scripts`
+pub async fn handle_scripts_list_blockfrost(
+    context: Arc<Context<Message>>,
+    _params: Vec<String>,
+    query_params: HashMap<String, String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+
+    let scripts = query_scripts(
+        &context,
+        &handlers_config,
+        ScriptsStateQuery::GetScriptsList,
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptsList(scripts) => Some(scripts),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let rest_scripts: Vec<ScriptListItemRest> =
+        pagination.apply(scripts).iter().map(Into::into).collect();
+    let json = serde_json::to_string_pretty(&rest_scripts)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/scripts/{script_hash}`
+pub async fn handle_script_single_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let script_hash = parse_script_hash(&params[0])?;
+
+    let info = query_scripts(
+        &context,
+        &handlers_config,
+        ScriptsStateQuery::GetScriptInfo { script_hash },
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptInfo(info) => Some(info),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let json = serde_json::to_string_pretty(&ScriptInfoRest::from(&info))?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/scripts/{script_hash}/json`
+pub async fn handle_script_json_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let script_hash = parse_script_hash(&params[0])?;
+
+    let script = query_scripts(
+        &context,
+        &handlers_config,
+        ScriptsStateQuery::GetScriptJSON { script_hash },
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptJSON(script) => Some(script),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let response = ScriptJsonRest {
+        json: script.native.as_ref().map(native_script_json),
+    };
+    let json = serde_json::to_string_pretty(&response)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/scripts/{script_hash}/cbor`
+pub async fn handle_script_cbor_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let script_hash = parse_script_hash(&params[0])?;
+
+    let script = query_scripts(
+        &context,
+        &handlers_config,
+        ScriptsStateQuery::GetScriptCBOR { script_hash },
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptCBOR(script) => Some(script),
+            _ => None,
+        },
+    )
+    .await?;
+
+    let response = ScriptCborRest {
+        cbor: script.cbor.map(hex::encode),
+    };
+    let json = serde_json::to_string_pretty(&response)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/scripts/{script_hash}/redeemers`
+pub async fn handle_script_redeemers_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    query_params: HashMap<String, String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let pagination = Pagination::from_query_params(&query_params)?;
+    let script_hash = parse_script_hash(&params[0])?;
+
+    let redeemers = query_scripts(
+        &context,
+        &handlers_config,
+        ScriptsStateQuery::GetScriptRedeemers { script_hash },
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptRedeemers(redeemers) => Some(redeemers),
+            _ => None,
+        },
+    )
+    .await?;
+    let redeemers = pagination.apply(redeemers);
+    if redeemers.is_empty() {
+        return Ok(RESTResponse::with_json(200, "[]"));
+    }
+
+    let tx_ids = redeemers.iter().map(|redeemer| redeemer.tx_identifier).collect();
+    let tx_hashes = resolve_tx_hashes(&context, &handlers_config, tx_ids).await?;
+
+    // Fees are priced at the execution prices of each redeemer's epoch
+    let epochs: BTreeSet<u64> = redeemers.iter().map(|redeemer| redeemer.epoch).collect();
+    let mut prices = HashMap::new();
+    for epoch in epochs {
+        prices.insert(
+            epoch,
+            execution_prices(&context, &handlers_config, epoch).await?,
+        );
+    }
+
+    let rest_redeemers = redeemers
+        .iter()
+        .map(|redeemer| {
+            let tx_hash = tx_hashes.get(&redeemer.tx_identifier).ok_or_else(|| {
+                RESTError::InternalServerError("Missing tx hash for redeemer".to_string())
+            })?;
+            let fee = redeemer_fee(&redeemer.ex_units, &prices[&redeemer.epoch]);
+            Ok(ScriptRedeemerRest::new(redeemer, tx_hash, fee))
+        })
+        .collect::<Result<Vec<_>, RESTError>>()?;
+
+    let json = serde_json::to_string_pretty(&rest_redeemers)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+async fn execution_prices(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    epoch_number: u64,
+) -> Result<ExUnitPrices, RESTError> {
+    let msg = Arc::new(Message::StateQuery(StateQuery::Parameters(
+        ParametersStateQuery::GetEpochParameters { epoch_number },
+    )));
+    let params = query_state(
+        context,
+        &handlers_config.parameters_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Parameters(
+                ParametersStateQueryResponse::EpochParameters(params),
+            )) => Ok(params),
+            Message::StateQueryResponse(StateQueryResponse::Parameters(
+                ParametersStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error("Unexpected response")),
+        },
+    )
+    .await?;
+
+    params.alonzo.map(|alonzo| alonzo.execution_prices).ok_or_else(|| {
+        RESTError::InternalServerError(format!("No execution prices for epoch {epoch_number}"))
+    })
+}
+
+async fn query_datum(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    param: &str,
+) -> Result<Vec<u8>, RESTError> {
+    let datum_hash = parse_datum_hash(param)?;
+    let datum = query_scripts(
+        context,
+        handlers_config,
+        ScriptsStateQuery::GetScriptDatum { datum_hash },
+        |response| match response {
+            ScriptsStateQueryResponse::ScriptDatum(datum) => Some(datum),
+            _ => None,
+        },
+    )
+    .await?;
+    Ok(datum.cbor)
+}
+
+/// Handle `/scripts/datum/{datum_hash}`
+pub async fn handle_script_datum_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let cbor = query_datum(&context, &handlers_config, &params[0]).await?;
+    let json_value =
+        plutus_data_json(&cbor).map_err(|e| RESTError::encoding_failed(&format!("datum: {e}")))?;
+    let json = serde_json::to_string_pretty(&ScriptDatumRest { json_value })?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/scripts/datum/{datum_hash}/cbor`
+pub async fn handle_script_datum_cbor_blockfrost(
+    context: Arc<Context<Message>>,
+    params: Vec<String>,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let cbor = query_datum(&context, &handlers_config, &params[0]).await?;
+    let json = serde_json::to_string_pretty(&ScriptDatumCborRest {
+        cbor: hex::encode(cbor),
+    })?;
+    Ok(RESTResponse::with_json(200, &json))
+}
@@ -7,15 +7,18 @@ use acropolis_common::{
     queries::{
         errors::QueryError,
         parameters::{ParametersStateQuery, ParametersStateQueryResponse},
+        scripts::{ScriptsStateQuery, ScriptsStateQueryResponse},
         transactions::{
             TransactionDelegationCertificate, TransactionInfo, TransactionMIR,
             TransactionMetadataItem, TransactionOutputAmount, TransactionPoolRetirementCertificate,
-            TransactionPoolUpdateCertificate, TransactionStakeCertificate, TransactionWithdrawal,
-            TransactionsStateQuery, TransactionsStateQueryResponse,
+            TransactionPoolUpdateCertificate, TransactionRedeemer, TransactionRedeemers,
+            TransactionStakeCertificate, TransactionUTxOInput, TransactionUTxOOutput,
+            TransactionUTxOs, TransactionWithdrawal, TransactionsStateQuery,
+            TransactionsStateQueryResponse,
         },
         utils::{query_state, rest_query_state_async},
     },
-    Lovelace, Relay, TxHash,
+    Datum, ExUnitPrices, ExUnits, Lovelace, RedeemerTag, Relay, TxHash, TxIdentifier, TxOutput,
 };
 use caryatid_sdk::Context;
 use hex::FromHex;
@@ -28,7 +31,7 @@ use std::{
     sync::Arc,
 };
 
-use crate::handlers_config::HandlersConfig;
+use crate::{handlers_config::HandlersConfig, types::AmountList};
 
 struct TxInfo(TransactionInfo, Lovelace, Lovelace);
 
@@ -114,7 +117,7 @@ pub async fn handle_transactions_blockfrost(
 
     match param {
         None => handle_transaction_query(context, tx_hash, handlers_config).await,
-        Some("utxo") => Ok(RESTResponse::with_text(501, "Not implemented")),
+        Some("utxos") => handle_transaction_utxos_query(context, tx_hash, handlers_config).await,
         Some("stakes") => handle_transaction_stakes_query(context, tx_hash, handlers_config).await,
         Some("delegations") => {
             handle_transaction_delegations_query(context, tx_hash, handlers_config).await
@@ -131,13 +134,15 @@ pub async fn handle_transactions_blockfrost(
         }
         Some("metadata") => match param2 {
             None => handle_transaction_metadata_query(context, tx_hash, handlers_config).await,
-            Some("cbor") => Ok(RESTResponse::with_text(501, "Not implemented")),
-            _ => Ok(RESTResponse::with_text(400, "Invalid parameters")),
+            Some("cbor") => Err(RESTError::not_implemented("Not implemented")),
+            _ => Err(RESTError::BadRequest("Invalid parameters".to_string())),
         },
-        Some("redeemers") => Ok(RESTResponse::with_text(501, "Not implemented")),
-        Some("required_signers") => Ok(RESTResponse::with_text(501, "Not implemented")),
-        Some("cbor") => Ok(RESTResponse::with_text(501, "Not implemented")),
-        _ => Ok(RESTResponse::with_text(400, "Invalid parameters")),
+        Some("redeemers") => {
+            handle_transaction_redeemers_query(context, tx_hash, handlers_config).await
+        }
+        Some("required_signers") => Err(RESTError::not_implemented("Not implemented")),
+        Some("cbor") => Err(RESTError::not_implemented("Not implemented")),
+        _ => Err(RESTError::BadRequest("Invalid parameters".to_string())),
     }
 }
 
@@ -588,7 +593,7 @@ impl Serialize for TxMetadata {
     }
 }
 
-struct TxMetadataItem(TransactionMetadataItem);
+pub(crate) struct TxMetadataItem(pub(crate) TransactionMetadataItem);
 
 impl Serialize for TxMetadataItem {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -629,3 +634,313 @@ async fn handle_transaction_metadata_query(
     )
     .await
 }
+
+/// Split a datum into its `data_hash` and `inline_datum` fields
+fn datum_fields(datum: &Option<Datum>) -> (Option<String>, Option<String>) {
+    match datum {
+        Some(Datum::Hash(hash)) => (Some(hex::encode(hash)), None),
+        Some(Datum::Inline(bytes)) => (None, Some(hex::encode(bytes))),
+        None => (None, None),
+    }
+}
+
+/// Serialize the fields common to transaction inputs and outputs
+fn serialize_tx_output<S: SerializeStruct>(
+    state: &mut S,
+    output: &TxOutput,
+) -> Result<(), S::Error> {
+    let Ok(address) = output.address.to_string() else {
+        return Err(S::Error::custom("Can't stringify address"));
+    };
+    let (data_hash, inline_datum) = datum_fields(&output.datum);
+    state.serialize_field("address", &address)?;
+    state.serialize_field("amount", &AmountList::from(output.value.clone()))?;
+    state.serialize_field("output_index", &output.utxo_identifier.output_index)?;
+    state.serialize_field("data_hash", &data_hash)?;
+    state.serialize_field("inline_datum", &inline_datum)?;
+    state.serialize_field(
+        "reference_script_hash",
+        &output.script_ref.as_ref().map(|script_ref| hex::encode(script_ref.script_hash)),
+    )?;
+    Ok(())
+}
+
+struct TxUTxOInput(TransactionUTxOInput);
+
+impl Serialize for TxUTxOInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("TxUTxOInput", 9)?;
+        state.serialize_field("tx_hash", &self.0.output.utxo_identifier.tx_hash)?;
+        serialize_tx_output(&mut state, &self.0.output)?;
+        state.serialize_field("collateral", &self.0.collateral)?;
+        state.serialize_field("reference", &self.0.reference)?;
+        state.end()
+    }
+}
+
+struct TxUTxOOutput(TransactionUTxOOutput);
+
+impl Serialize for TxUTxOOutput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("TxUTxOOutput", 8)?;
+        serialize_tx_output(&mut state, &self.0.output)?;
+        state.serialize_field("collateral", &self.0.collateral)?;
+        // Spending transactions are not indexed
+        state.serialize_field("consumed_by_tx", &None::<TxHash>)?;
+        state.end()
+    }
+}
+
+struct TxUTxOs(TransactionUTxOs);
+
+impl Serialize for TxUTxOs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("TxUTxOs", 3)?;
+        state.serialize_field("hash", &self.0.hash)?;
+        state.serialize_field(
+            "inputs",
+            &self.0.inputs.iter().cloned().map(TxUTxOInput).collect::<Vec<_>>(),
+        )?;
+        state.serialize_field(
+            "outputs",
+            &self.0.outputs.iter().cloned().map(TxUTxOOutput).collect::<Vec<_>>(),
+        )?;
+        state.end()
+    }
+}
+
+/// Handle `/txs/{hash}/utxos`
+async fn handle_transaction_utxos_query(
+    context: Arc<Context<Message>>,
+    tx_hash: TxHash,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let txs_info_msg = Arc::new(Message::StateQuery(StateQuery::Transactions(
+        TransactionsStateQuery::GetTransactionUTxOs { tx_hash },
+    )));
+    rest_query_state_async(
+        &context.clone(),
+        &handlers_config.transactions_query_topic.clone(),
+        txs_info_msg,
+        async move |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Transactions(
+                TransactionsStateQueryResponse::TransactionUTxOs(utxos),
+            )) => Some(Ok(TxUTxOs(utxos))),
+            Message::StateQueryResponse(StateQueryResponse::Transactions(
+                TransactionsStateQueryResponse::Error(e),
+            )) => Some(Err(e)),
+            _ => None,
+        },
+    )
+    .await
+}
+
+/// Fee paid for a script's execution units, rounded up to the next lovelace
+pub(crate) fn redeemer_fee(ex_units: &ExUnits, prices: &ExUnitPrices) -> Lovelace {
+    let mem_numer = *prices.mem_price.numer() as u128;
+    let mem_denom = *prices.mem_price.denom() as u128;
+    let step_numer = *prices.step_price.numer() as u128;
+    let step_denom = *prices.step_price.denom() as u128;
+    let numer = ex_units.mem as u128 * mem_numer * step_denom
+        + ex_units.steps as u128 * step_numer * mem_denom;
+    numer.div_ceil(mem_denom * step_denom) as Lovelace
+}
+
+struct TxRedeemer(TransactionRedeemer, Lovelace);
+
+impl Serialize for TxRedeemer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let purpose = match self.0.purpose {
+            RedeemerTag::Spend => "spend",
+            RedeemerTag::Mint => "mint",
+            RedeemerTag::Cert => "cert",
+            RedeemerTag::Reward => "reward",
+            RedeemerTag::Vote => "vote",
+            RedeemerTag::Propose => "propose",
+        };
+        let mut state = serializer.serialize_struct("TxRedeemer", 8)?;
+        state.serialize_field("tx_index", &self.0.tx_index)?;
+        state.serialize_field("purpose", purpose)?;
+        state.serialize_field("script_hash", &self.0.script_hash)?;
+        state.serialize_field("redeemer_data_hash", &self.0.redeemer_data_hash)?;
+        // Deprecated in Blockfrost in favour of redeemer_data_hash
+        state.serialize_field("datum_hash", &self.0.redeemer_data_hash)?;
+        state.serialize_field("unit_mem", &self.0.ex_units.mem.to_string())?;
+        state.serialize_field("unit_steps", &self.0.ex_units.steps.to_string())?;
+        state.serialize_field("fee", &self.1.to_string())?;
+        state.end()
+    }
+}
+
+/// Handle `/txs/{hash}/redeemers`
+///
+/// Taken from scripts-state's index when it keeps redeemers, since it resolves the
+/// script for every purpose, and otherwise decoded from the stored transaction
+async fn handle_transaction_redeemers_query(
+    context: Arc<Context<Message>>,
+    tx_hash: TxHash,
+    handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let redeemers = match indexed_transaction_redeemers(&context, &handlers_config, tx_hash).await?
+    {
+        Some(redeemers) => redeemers,
+        None => {
+            let msg = Arc::new(Message::StateQuery(StateQuery::Transactions(
+                TransactionsStateQuery::GetTransactionRedeemers { tx_hash },
+            )));
+            query_state(
+                &context,
+                &handlers_config.transactions_query_topic,
+                msg,
+                |message| match message {
+                    Message::StateQueryResponse(StateQueryResponse::Transactions(
+                        TransactionsStateQueryResponse::TransactionRedeemers(redeemers),
+                    )) => Ok(redeemers),
+                    Message::StateQueryResponse(StateQueryResponse::Transactions(
+                        TransactionsStateQueryResponse::Error(e),
+                    )) => Err(e),
+                    _ => Err(QueryError::internal_error(
+                        "Unexpected message type while retrieving transaction redeemers",
+                    )),
+                },
+            )
+            .await?
+        }
+    };
+    if redeemers.redeemers.is_empty() {
+        return Ok(RESTResponse::with_json(200, "[]"));
+    }
+
+    let params_msg = Arc::new(Message::StateQuery(StateQuery::Parameters(
+        ParametersStateQuery::GetEpochParameters {
+            epoch_number: redeemers.epoch,
+        },
+    )));
+    let params = query_state(
+        &context,
+        &handlers_config.parameters_query_topic,
+        params_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Parameters(
+                ParametersStateQueryResponse::EpochParameters(params),
+            )) => Ok(params),
+            Message::StateQueryResponse(StateQueryResponse::Parameters(
+                ParametersStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error("Unexpected response")),
+        },
+    )
+    .await?;
+    let Some(alonzo) = params.alonzo else {
+        return Err(RESTError::InternalServerError(
+            "No execution prices for redeemer epoch".to_string(),
+        ));
+    };
+
+    let rest_redeemers = redeemers
+        .redeemers
+        .into_iter()
+        .map(|redeemer| {
+            let fee = redeemer_fee(&redeemer.ex_units, &alonzo.execution_prices);
+            TxRedeemer(redeemer, fee)
+        })
+        .collect::<Vec<_>>();
+    let json = serde_json::to_string_pretty(&rest_redeemers)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// A transaction's redeemers from scripts-state, or None if it doesn't keep them
+async fn indexed_transaction_redeemers(
+    context: &Arc<Context<Message>>,
+    handlers_config: &HandlersConfig,
+    tx_hash: TxHash,
+) -> Result<Option<TransactionRedeemers>, RESTError> {
+    let info_msg = Arc::new(Message::StateQuery(StateQuery::Transactions(
+        TransactionsStateQuery::GetTransactionInfo { tx_hash },
+    )));
+    let info = query_state(
+        context,
+        &handlers_config.transactions_query_topic,
+        info_msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Transactions(
+                TransactionsStateQueryResponse::TransactionInfo(info),
+            )) => Ok(info),
+            Message::StateQueryResponse(StateQueryResponse::Transactions(
+                TransactionsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving transaction",
+            )),
+        },
+    )
+    .await?;
+
+    let msg = Arc::new(Message::StateQuery(StateQuery::Scripts(
+        ScriptsStateQuery::GetTransactionRedeemers {
+            tx_identifier: TxIdentifier::new(info.block_number as u32, info.index as u16),
+        },
+    )));
+    let redeemers = query_state(
+        context,
+        &handlers_config.scripts_query_topic,
+        msg,
+        |message| match message {
+            Message::StateQueryResponse(StateQueryResponse::Scripts(
+                ScriptsStateQueryResponse::TransactionRedeemers(redeemers),
+            )) => Ok(Some(redeemers)),
+            // Only transactions with redeemers are kept
+            Message::StateQueryResponse(StateQueryResponse::Scripts(
+                ScriptsStateQueryResponse::Error(QueryError::NotFound { .. }),
+            )) => Ok(Some(TransactionRedeemers {
+                epoch: info.epoch,
+                redeemers: Vec::new(),
+            })),
+            Message::StateQueryResponse(StateQueryResponse::Scripts(
+                ScriptsStateQueryResponse::Error(QueryError::StorageDisabled { .. }),
+            )) => Ok(None),
+            Message::StateQueryResponse(StateQueryResponse::Scripts(
+                ScriptsStateQueryResponse::Error(e),
+            )) => Err(e),
+            _ => Err(QueryError::internal_error(
+                "Unexpected message type while retrieving transaction redeemers",
+            )),
+        },
+    )
+    .await?;
+    Ok(redeemers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::rational_number::RationalNumber;
+
+    #[test]
+    fn redeemer_fee_rounds_up() {
+        // Mainnet prices
+        let prices = ExUnitPrices {
+            mem_price: RationalNumber::new(577, 10000),
+            step_price: RationalNumber::new(721, 10000000),
+        };
+        let ex_units = ExUnits {
+            mem: 1700,
+            steps: 476468,
+        };
+        // 98.09 + 34.3533428
+        assert_eq!(redeemer_fee(&ex_units, &prices), 133);
+        assert_eq!(redeemer_fee(&ExUnits { mem: 0, steps: 0 }, &prices), 0);
+    }
+}
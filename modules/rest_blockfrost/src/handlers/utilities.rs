@@ -0,0 +1,179 @@
+//! REST handlers for the utility endpoints, which need no chain state
+
+use std::{collections::HashMap, sync::Arc};
+
+use acropolis_codec::{
+    map_era, map_metadata, map_network, map_transaction, map_transaction_inputs,
+};
+use acropolis_common::{
+    cip19,
+    id_conversion::{convert_drep_id, convert_pool_id, convert_stake_address},
+    messages::{Message, RESTResponse},
+    queries::transactions::TransactionMetadataItem,
+    rest_error::RESTError,
+    validation::Phase1ValidationError,
+    NetworkId, TxIdentifier,
+};
+use caryatid_sdk::Context;
+use pallas_traverse::MultiEraTx;
+
+use crate::{
+    handlers::transactions::TxMetadataItem,
+    handlers_config::HandlersConfig,
+    types::{TxInspectionOutputREST, TxInspectionREST, TxInspectionWithdrawalREST},
+};
+
+/// Handle `/utils/addresses/inspect/{address}`, breaking an address down into its era,
+/// type, network, payment credential and stake reference
+pub async fn handle_address_inspect_blockfrost(
+    _context: Arc<Context<Message>>,
+    params: Vec<String>,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let Some(address) = params.first() else {
+        return Err(RESTError::param_missing("address"));
+    };
+
+    let inspection =
+        cip19::inspect(address).map_err(|e| RESTError::invalid_param("address", &e.to_string()))?;
+    let json = serde_json::to_string_pretty(&inspection)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/utils/pools/convert/{pool_id}`, giving a pool ID in both Bech32 and hex
+pub async fn handle_pool_id_convert_blockfrost(
+    _context: Arc<Context<Message>>,
+    params: Vec<String>,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let Some(pool_id) = params.first() else {
+        return Err(RESTError::param_missing("pool_id"));
+    };
+
+    let conversion = convert_pool_id(pool_id)
+        .map_err(|e| RESTError::invalid_param("pool_id", &e.to_string()))?;
+    let json = serde_json::to_string_pretty(&conversion)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/utils/dreps/convert/{drep_id}`, giving a DRep ID in its CIP-105 and CIP-129
+/// forms and the credential it is for
+pub async fn handle_drep_id_convert_blockfrost(
+    _context: Arc<Context<Message>>,
+    params: Vec<String>,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let Some(drep_id) = params.first() else {
+        return Err(RESTError::param_missing("drep_id"));
+    };
+
+    let conversion = convert_drep_id(drep_id)
+        .map_err(|e| RESTError::invalid_param("drep_id", &e.to_string()))?;
+    let json = serde_json::to_string_pretty(&conversion)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `/utils/accounts/convert/{stake_address}?network=`, converting between a stake
+/// address and its credential. The network is only used to build an address from a
+/// credential, and defaults to mainnet.
+pub async fn handle_stake_address_convert_blockfrost(
+    _context: Arc<Context<Message>>,
+    params: Vec<String>,
+    query_params: HashMap<String, String>,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let Some(stake_address) = params.first() else {
+        return Err(RESTError::param_missing("stake_address"));
+    };
+    let network = query_params.get("network").cloned().map(NetworkId::from).unwrap_or_default();
+
+    let conversion = convert_stake_address(stake_address, network)
+        .map_err(|e| RESTError::invalid_param("stake_address", &e.to_string()))?;
+    let json = serde_json::to_string_pretty(&conversion)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
+
+/// Handle `POST /utils/txs/inspect`, decoding hex transaction CBOR exactly as our decoder
+/// reads it, without submitting it
+pub async fn handle_tx_inspect_blockfrost(
+    _context: Arc<Context<Message>>,
+    body: String,
+    _handlers_config: Arc<HandlersConfig>,
+) -> Result<RESTResponse, RESTError> {
+    let raw_tx = hex::decode(body.trim())?;
+    let tx = MultiEraTx::decode(&raw_tx)
+        .map_err(|e| RESTError::invalid_param("transaction", &e.to_string()))?;
+    let Some(era) = map_era(tx.era()) else {
+        return Err(RESTError::invalid_param(
+            "transaction",
+            &format!("unsupported era {:?}", tx.era()),
+        ));
+    };
+
+    // Certificates name stake addresses, whose network only the outputs tell us
+    let network_id = tx
+        .outputs()
+        .iter()
+        .find_map(|output| output.address().ok()?.network())
+        .and_then(|network| map_network(network).ok())
+        .unwrap_or_default();
+
+    let mapped = map_transaction(&tx, &raw_tx, TxIdentifier::default(), network_id, era);
+
+    let outputs = mapped
+        .produces
+        .iter()
+        .map(TxInspectionOutputREST::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let withdrawals = mapped
+        .withdrawals
+        .iter()
+        .map(TxInspectionWithdrawalREST::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let metadata = map_metadata(&tx.metadata())
+        .map(|metadata| {
+            metadata
+                .0
+                .into_iter()
+                .map(|(label, json_metadata)| {
+                    TxMetadataItem(TransactionMetadataItem {
+                        label: label.to_string(),
+                        json_metadata,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let errors = match mapped.error {
+        Some(Phase1ValidationError::MalformedTransaction { errors }) => errors,
+        Some(e) => vec![e.to_string()],
+        None => Vec::new(),
+    };
+
+    let inspection = TxInspectionREST {
+        hash: tx.hash().to_string(),
+        era: era.to_string().to_lowercase(),
+        size: raw_tx.len(),
+        valid_contract: mapped.is_valid,
+        fee: mapped.fee.to_string(),
+        invalid_before: mapped.validity_interval.invalid_before.map(|slot| slot.to_string()),
+        invalid_hereafter: mapped.validity_interval.invalid_hereafter.map(|slot| slot.to_string()),
+        inputs: map_transaction_inputs(&tx.inputs()).iter().map(|i| i.to_string()).collect(),
+        collateral_inputs: map_transaction_inputs(&tx.collateral())
+            .iter()
+            .map(|i| i.to_string())
+            .collect(),
+        reference_inputs: mapped.reference_inputs.iter().map(|i| i.to_string()).collect(),
+        outputs,
+        certificates: mapped.certs.into_iter().map(|c| c.cert).collect(),
+        withdrawals,
+        mint: (&mapped.mint_burn_deltas).into(),
+        metadata,
+        required_signers: mapped.required_signers.iter().map(hex::encode).collect(),
+        script_hashes: mapped.script_witnesses.iter().map(|(hash, _)| hex::encode(hash)).collect(),
+        errors,
+    };
+
+    let json = serde_json::to_string_pretty(&inspection)?;
+    Ok(RESTResponse::with_json(200, &json))
+}
@@ -5,17 +5,23 @@ use acropolis_common::queries::{
     addresses::DEFAULT_ADDRESS_QUERY_TOPIC,
     assets::{DEFAULT_ASSETS_QUERY_TOPIC, DEFAULT_OFFCHAIN_TOKEN_REGISTRY_URL},
     blocks::DEFAULT_BLOCKS_QUERY_TOPIC,
+    drdd::DEFAULT_DRDD_QUERY_TOPIC,
     epochs::{DEFAULT_EPOCHS_QUERY_TOPIC, DEFAULT_HISTORICAL_EPOCHS_QUERY_TOPIC},
     governance::{DEFAULT_DREPS_QUERY_TOPIC, DEFAULT_GOVERNANCE_QUERY_TOPIC},
     parameters::DEFAULT_PARAMETERS_QUERY_TOPIC,
     pools::DEFAULT_POOLS_QUERY_TOPIC,
+    scripts::DEFAULT_SCRIPTS_QUERY_TOPIC,
     spdd::DEFAULT_SPDD_QUERY_TOPIC,
+    stake_deltas::DEFAULT_STAKE_DELTAS_QUERY_TOPIC,
     transactions::DEFAULT_TRANSACTIONS_QUERY_TOPIC,
     utxos::DEFAULT_UTXOS_QUERY_TOPIC,
 };
 use config::Config;
 
 const DEFAULT_EXTERNAL_API_TIMEOUT: (&str, i64) = ("external_api_timeout", 3); // 3 seconds
+const DEFAULT_HEALTH_PROBE_TIMEOUT: (&str, i64) = ("health_probe_timeout", 2); // 2 seconds
+const DEFAULT_HEALTH_SYNC_TOLERANCE: (&str, i64) = ("health_sync_tolerance", 120); // slots
+const DEFAULT_READY_MODULES: &str = "ready-modules";
 
 #[derive(Clone)]
 pub struct HandlersConfig {
@@ -30,11 +36,21 @@ pub struct HandlersConfig {
     pub epochs_query_topic: String,
     pub historical_epochs_query_topic: String,
     pub spdd_query_topic: String,
+    pub drdd_query_topic: String,
     pub transactions_query_topic: String,
     pub parameters_query_topic: String,
     pub utxos_query_topic: String,
+    pub stake_deltas_query_topic: String,
+    pub scripts_query_topic: String,
     pub external_api_timeout: u64,
     pub offchain_token_registry_url: String,
+    /// Seconds to wait for each module to answer a health probe
+    pub health_probe_timeout: u64,
+    /// Slots the tip may lag the wall clock and still count as synced
+    pub health_sync_tolerance: u64,
+    /// Modules which must be ready before requests are answered - every module
+    /// in the process which declares its readiness if empty
+    pub ready_modules: Vec<String>,
 }
 
 impl From<Arc<Config>> for HandlersConfig {
@@ -95,6 +111,18 @@ impl From<Arc<Config>> for HandlersConfig {
             .get_string(DEFAULT_SPDD_QUERY_TOPIC.0)
             .unwrap_or(DEFAULT_SPDD_QUERY_TOPIC.1.to_string());
 
+        let drdd_query_topic = config
+            .get_string(DEFAULT_DRDD_QUERY_TOPIC.0)
+            .unwrap_or(DEFAULT_DRDD_QUERY_TOPIC.1.to_string());
+
+        let stake_deltas_query_topic = config
+            .get_string(DEFAULT_STAKE_DELTAS_QUERY_TOPIC.0)
+            .unwrap_or(DEFAULT_STAKE_DELTAS_QUERY_TOPIC.1.to_string());
+
+        let scripts_query_topic = config
+            .get_string(DEFAULT_SCRIPTS_QUERY_TOPIC.0)
+            .unwrap_or(DEFAULT_SCRIPTS_QUERY_TOPIC.1.to_string());
+
         let external_api_timeout = config
             .get_int(DEFAULT_EXTERNAL_API_TIMEOUT.0)
             .unwrap_or(DEFAULT_EXTERNAL_API_TIMEOUT.1) as u64;
@@ -103,6 +131,14 @@ impl From<Arc<Config>> for HandlersConfig {
             .get_string(DEFAULT_OFFCHAIN_TOKEN_REGISTRY_URL.0)
             .unwrap_or(DEFAULT_OFFCHAIN_TOKEN_REGISTRY_URL.1.to_string());
 
+        let health_probe_timeout = config
+            .get_int(DEFAULT_HEALTH_PROBE_TIMEOUT.0)
+            .unwrap_or(DEFAULT_HEALTH_PROBE_TIMEOUT.1) as u64;
+
+        let health_sync_tolerance = config
+            .get_int(DEFAULT_HEALTH_SYNC_TOLERANCE.0)
+            .unwrap_or(DEFAULT_HEALTH_SYNC_TOLERANCE.1) as u64;
+
         Self {
             accounts_query_topic,
             historical_accounts_query_topic,
@@ -115,11 +151,17 @@ impl From<Arc<Config>> for HandlersConfig {
             epochs_query_topic,
             historical_epochs_query_topic,
             spdd_query_topic,
+            drdd_query_topic,
             transactions_query_topic,
             parameters_query_topic,
             utxos_query_topic,
+            stake_deltas_query_topic,
+            scripts_query_topic,
             external_api_timeout,
             offchain_token_registry_url,
+            health_probe_timeout,
+            health_sync_tolerance,
+            ready_modules: config.get(DEFAULT_READY_MODULES).unwrap_or_default(),
         }
     }
 }
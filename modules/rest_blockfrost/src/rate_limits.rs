@@ -0,0 +1,423 @@
+//! Rate limits on REST requests, so that one busy client can't take the whole API.
+//!
+//! Each `[module.rest-blockfrost.rate-limits.<name>]` section limits the routes whose
+//! handler topics it lists under `routes`, or every route if it lists none. A limit
+//! allows `requests-per-second` on average with bursts of up to `burst`, and at most
+//! `daily-quota` requests per UTC day; past either, requests are refused with a 429
+//! and a `retry_after` hint. No limits are configured by default.
+//!
+//! The counters are the same for every client, since requests are not told apart.
+
+// TODO: Per-key limits and quotas (Blockfrost's `project_id` header, keys from a file
+// or config) need caryatid_module_rest_server to pass request headers in RESTRequest,
+// which today carries only the method, path, query parameters and body.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use acropolis_common::rest_error::RESTError;
+use anyhow::{bail, Result};
+use config::{Config, ConfigError};
+use serde::Serialize;
+use tracing::info;
+
+const RATE_LIMITS_KEY: &str = "rate-limits";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Limits of this module, set up in `init`
+static LIMITS: OnceLock<RateLimits> = OnceLock::new();
+
+/// A `[module.rest-blockfrost.rate-limits.<name>]` section
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RateLimitConfig {
+    /// Average requests allowed per second - no limit if 0
+    pub requests_per_second: f64,
+
+    /// Requests allowed at once above the average - `requests-per-second` if 0
+    pub burst: u64,
+
+    /// Requests allowed per UTC day - no quota if 0
+    pub daily_quota: u64,
+
+    /// Handler topics of the limited routes, e.g. "rest.get.accounts.*.utxos" - every
+    /// route if empty
+    pub routes: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 0.0,
+            burst: 0,
+            daily_quota: 0,
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// The limits as configured
+pub fn read_config(config: &Config) -> Result<BTreeMap<String, RateLimitConfig>> {
+    let limits: BTreeMap<String, RateLimitConfig> = match config.get(RATE_LIMITS_KEY) {
+        Ok(limits) => limits,
+        Err(ConfigError::NotFound(_)) => BTreeMap::new(),
+        Err(e) => bail!("invalid {RATE_LIMITS_KEY}: {e}"),
+    };
+
+    for (name, limit) in &limits {
+        if !limit.requests_per_second.is_finite() || limit.requests_per_second < 0.0 {
+            bail!("rate limit '{name}' needs a requests-per-second of 0 or more");
+        }
+        if limit.requests_per_second == 0.0 && limit.daily_quota == 0 {
+            bail!("rate limit '{name}' needs a requests-per-second or a daily-quota");
+        }
+    }
+    Ok(limits)
+}
+
+/// Set up the limits - only the first call has any effect
+pub fn init(limits: BTreeMap<String, RateLimitConfig>) {
+    for (name, limit) in &limits {
+        info!(
+            "REST rate limit '{name}': {}/s, {}/day, on {}",
+            limit.requests_per_second,
+            limit.daily_quota,
+            if limit.routes.is_empty() {
+                "every route".to_string()
+            } else {
+                limit.routes.join(", ")
+            }
+        );
+    }
+    let _ = LIMITS.set(RateLimits::new(limits));
+}
+
+/// Count a request on a route against every limit on it, refusing it if any is used up
+pub fn check(topic: &str) -> Result<(), RESTError> {
+    match LIMITS.get() {
+        Some(limits) => limits.check(topic, Instant::now(), utc_now()),
+        None => Ok(()),
+    }
+}
+
+/// Usage of every limit
+pub fn usage() -> Vec<RateLimitUsage> {
+    LIMITS.get().map(|limits| limits.usage(utc_now())).unwrap_or_default()
+}
+
+/// Usage counters of one limit, answered by the admin query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateLimitUsage {
+    pub name: String,
+    pub requests_per_second: f64,
+    pub burst: u64,
+    pub daily_quota: u64,
+    pub routes: Vec<String>,
+    /// Requests counted today
+    pub used_today: u64,
+    /// Requests allowed since startup
+    pub allowed: u64,
+    /// Requests refused since startup
+    pub limited: u64,
+}
+
+/// Seconds since the Unix epoch
+fn utc_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+struct RateLimits {
+    limits: Vec<RateLimit>,
+}
+
+impl RateLimits {
+    fn new(configs: BTreeMap<String, RateLimitConfig>) -> Self {
+        Self {
+            limits: configs
+                .into_iter()
+                .map(|(name, config)| RateLimit::new(name, config))
+                .collect(),
+        }
+    }
+
+    fn check(&self, topic: &str, now: Instant, utc: u64) -> Result<(), RESTError> {
+        let applying: Vec<&RateLimit> =
+            self.limits.iter().filter(|limit| limit.applies_to(topic)).collect();
+
+        // Take nothing from any limit unless every one allows the request
+        let mut states: Vec<_> = applying.iter().map(|limit| limit.state.lock().unwrap()).collect();
+        for (limit, state) in applying.iter().zip(states.iter_mut()) {
+            state.refill(&limit.config, now, utc);
+        }
+        for (limit, state) in applying.iter().zip(states.iter_mut()) {
+            if let Err(e) = state.refusal(&limit.name, &limit.config, utc) {
+                state.limited += 1;
+                return Err(e);
+            }
+        }
+        for (limit, state) in applying.iter().zip(states.iter_mut()) {
+            state.take(&limit.config);
+        }
+        Ok(())
+    }
+
+    fn usage(&self, utc: u64) -> Vec<RateLimitUsage> {
+        self.limits.iter().map(|limit| limit.usage(utc)).collect()
+    }
+}
+
+/// One configured limit
+struct RateLimit {
+    name: String,
+    config: RateLimitConfig,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Requests which may be made now
+    tokens: f64,
+    refilled: Option<Instant>,
+    /// UTC day `used_today` counts
+    day: u64,
+    used_today: u64,
+    allowed: u64,
+    limited: u64,
+}
+
+impl RateLimit {
+    fn new(name: String, config: RateLimitConfig) -> Self {
+        let tokens = config.burst() as f64;
+        Self {
+            name,
+            config,
+            state: Mutex::new(State {
+                tokens,
+                refilled: None,
+                day: 0,
+                used_today: 0,
+                allowed: 0,
+                limited: 0,
+            }),
+        }
+    }
+
+    fn applies_to(&self, topic: &str) -> bool {
+        self.config.routes.is_empty() || self.config.routes.iter().any(|route| route == topic)
+    }
+
+    fn usage(&self, utc: u64) -> RateLimitUsage {
+        let state = self.state.lock().unwrap();
+        RateLimitUsage {
+            name: self.name.clone(),
+            requests_per_second: self.config.requests_per_second,
+            burst: self.config.burst(),
+            daily_quota: self.config.daily_quota,
+            routes: self.config.routes.clone(),
+            used_today: if state.day == utc / SECONDS_PER_DAY {
+                state.used_today
+            } else {
+                0
+            },
+            allowed: state.allowed,
+            limited: state.limited,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn burst(&self) -> u64 {
+        if self.burst > 0 {
+            self.burst
+        } else {
+            (self.requests_per_second.ceil() as u64).max(1)
+        }
+    }
+}
+
+impl State {
+    /// Add the tokens earned since the last request, and start a new day's count
+    fn refill(&mut self, config: &RateLimitConfig, now: Instant, utc: u64) {
+        if let Some(refilled) = self.refilled {
+            let earned = now.duration_since(refilled).as_secs_f64() * config.requests_per_second;
+            self.tokens = (self.tokens + earned).min(config.burst() as f64);
+        }
+        self.refilled = Some(now);
+
+        let day = utc / SECONDS_PER_DAY;
+        if day != self.day {
+            self.day = day;
+            self.used_today = 0;
+        }
+    }
+
+    fn refusal(&self, name: &str, config: &RateLimitConfig, utc: u64) -> Result<(), RESTError> {
+        if config.daily_quota > 0 && self.used_today >= config.daily_quota {
+            return Err(RESTError::TooManyRequests {
+                message: format!(
+                    "Daily quota of {} requests used up ({name})",
+                    config.daily_quota
+                ),
+                retry_after: SECONDS_PER_DAY - utc % SECONDS_PER_DAY,
+            });
+        }
+        if config.requests_per_second > 0.0 && self.tokens < 1.0 {
+            let wait = (1.0 - self.tokens) / config.requests_per_second;
+            return Err(RESTError::TooManyRequests {
+                message: format!(
+                    "Over the limit of {} requests per second ({name})",
+                    config.requests_per_second
+                ),
+                retry_after: (wait.ceil() as u64).max(1),
+            });
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, config: &RateLimitConfig) {
+        if config.requests_per_second > 0.0 {
+            self.tokens -= 1.0;
+        }
+        self.used_today += 1;
+        self.allowed += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limits(config: &str) -> RateLimits {
+        let config = Config::builder()
+            .add_source(config::File::from_str(config, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        RateLimits::new(read_config(&config).unwrap())
+    }
+
+    fn retry_after(result: Result<(), RESTError>) -> Option<u64> {
+        match result {
+            Ok(()) => None,
+            Err(RESTError::TooManyRequests { retry_after, .. }) => Some(retry_after),
+            Err(e) => panic!("unexpected error {e}"),
+        }
+    }
+
+    #[test]
+    fn requests_over_the_rate_wait_for_tokens() {
+        let limits = limits(
+            r#"
+            [rate-limits.global]
+            requests-per-second = 2
+            "#,
+        );
+        let start = Instant::now();
+        let utc = 1_000;
+
+        assert_eq!(
+            retry_after(limits.check("rest.get.blocks.*", start, utc)),
+            None
+        );
+        assert_eq!(
+            retry_after(limits.check("rest.get.pools", start, utc)),
+            None
+        );
+        assert_eq!(
+            retry_after(limits.check("rest.get.pools", start, utc)),
+            Some(1)
+        );
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(
+            retry_after(limits.check("rest.get.pools", later, utc)),
+            None
+        );
+
+        let usage = &limits.usage(utc)[0];
+        assert_eq!((usage.allowed, usage.limited, usage.used_today), (3, 1, 3));
+    }
+
+    #[test]
+    fn daily_quota_resets_at_midnight_utc() {
+        let limits = limits(
+            r#"
+            [rate-limits.utxos]
+            daily-quota = 2
+            routes = ["rest.get.accounts.*.utxos"]
+            "#,
+        );
+        let now = Instant::now();
+        let utc = SECONDS_PER_DAY * 10 + 3_600;
+
+        assert_eq!(
+            retry_after(limits.check("rest.get.accounts.*.utxos", now, utc)),
+            None
+        );
+        assert_eq!(
+            retry_after(limits.check("rest.get.accounts.*.utxos", now, utc)),
+            None
+        );
+        assert_eq!(
+            retry_after(limits.check("rest.get.accounts.*.utxos", now, utc)),
+            Some(SECONDS_PER_DAY - 3_600)
+        );
+
+        // Other routes are not limited
+        assert_eq!(
+            retry_after(limits.check("rest.get.blocks.*", now, utc)),
+            None
+        );
+
+        let tomorrow = SECONDS_PER_DAY * 11;
+        assert_eq!(limits.usage(tomorrow)[0].used_today, 0);
+        assert_eq!(
+            retry_after(limits.check("rest.get.accounts.*.utxos", now, tomorrow)),
+            None
+        );
+    }
+
+    #[test]
+    fn refused_requests_are_not_counted_by_other_limits() {
+        let limits = limits(
+            r#"
+            [rate-limits.global]
+            daily-quota = 100
+
+            [rate-limits.utxos]
+            requests-per-second = 1
+            routes = ["rest.get.accounts.*.utxos"]
+            "#,
+        );
+        let now = Instant::now();
+
+        assert_eq!(
+            retry_after(limits.check("rest.get.accounts.*.utxos", now, 0)),
+            None
+        );
+        assert!(retry_after(limits.check("rest.get.accounts.*.utxos", now, 0)).is_some());
+
+        let usage = limits.usage(0);
+        assert_eq!(usage[0].name, "global");
+        assert_eq!((usage[0].allowed, usage[0].used_today), (1, 1));
+        assert_eq!((usage[1].allowed, usage[1].limited), (1, 1));
+    }
+
+    #[test]
+    fn limits_need_a_rate_or_a_quota() {
+        let config = Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [rate-limits.empty]
+                routes = ["rest.get.pools"]
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        assert!(read_config(&config).is_err());
+    }
+}
@@ -1,24 +1,36 @@
 //! Acropolis Blockfrost-Compatible REST Module
 
-use std::{collections::HashMap, future::Future, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use acropolis_common::configuration::get_string_flag;
+use acropolis_common::configuration::{get_bool_flag, get_string_flag, get_u64_flag};
+use acropolis_common::readiness;
 use acropolis_common::rest_error::RESTError;
 use acropolis_common::{
-    messages::{Message, RESTResponse},
-    rest_helper::{handle_rest_with_path_and_query_parameters, handle_rest_with_path_parameter},
+    messages::{CardanoMessage, Message, MonitoringMessage, RESTResponse},
+    rest_helper::{
+        handle_rest_with_body, handle_rest_with_path_and_query_parameters,
+        handle_rest_with_path_parameter,
+    },
 };
 use anyhow::Result;
 use caryatid_sdk::{module, Context};
 use config::Config;
-use tracing::info;
+use tracing::{info, warn};
 
+mod cache;
 mod cost_models;
 pub mod handlers;
 pub mod handlers_config;
+mod rate_limits;
 pub mod routes;
 mod types;
 mod utils;
+mod workers;
 use handlers::{
     accounts::{
         handle_account_addresses_blockfrost, handle_account_assets_blockfrost,
@@ -32,10 +44,12 @@ use handlers::{
         handle_address_single_blockfrost, handle_address_totals_blockfrost,
         handle_address_transactions_blockfrost, handle_address_utxos_blockfrost,
     },
+    admin::handle_rate_limits_blockfrost,
     assets::{
         handle_asset_addresses_blockfrost, handle_asset_history_blockfrost,
-        handle_asset_single_blockfrost, handle_asset_transactions_blockfrost,
-        handle_assets_list_blockfrost, handle_policy_assets_blockfrost,
+        handle_asset_single_blockfrost, handle_asset_supply_blockfrost,
+        handle_asset_transactions_blockfrost, handle_assets_list_blockfrost,
+        handle_policy_assets_blockfrost,
     },
     blocks::{
         handle_blocks_epoch_slot_blockfrost, handle_blocks_hash_number_addresses_blockfrost,
@@ -59,6 +73,8 @@ use handlers::{
         handle_proposals_list_blockfrost, handle_single_drep_blockfrost,
         handle_single_proposal_blockfrost,
     },
+    health::{handle_health_blockfrost, handle_sync_blockfrost},
+    network::{handle_network_blockfrost, handle_network_eras_blockfrost},
     pools::{
         handle_pool_blocks_blockfrost, handle_pool_delegators_blockfrost,
         handle_pool_history_blockfrost, handle_pool_metadata_blockfrost,
@@ -66,10 +82,39 @@ use handlers::{
         handle_pool_votes_blockfrost, handle_pools_extended_retired_retiring_single_blockfrost,
         handle_pools_list_blockfrost,
     },
+    scripts::{
+        handle_script_cbor_blockfrost, handle_script_datum_blockfrost,
+        handle_script_datum_cbor_blockfrost, handle_script_json_blockfrost,
+        handle_script_redeemers_blockfrost, handle_script_single_blockfrost,
+        handle_scripts_list_blockfrost,
+    },
     transactions::handle_transactions_blockfrost,
+    utilities::{
+        handle_address_inspect_blockfrost, handle_drep_id_convert_blockfrost,
+        handle_pool_id_convert_blockfrost, handle_stake_address_convert_blockfrost,
+        handle_tx_inspect_blockfrost,
+    },
+};
+
+use crate::{
+    cache::{cache_key, Lookup, ResponseCache},
+    handlers_config::HandlersConfig,
+    workers::{AGGREGATE_POOL, DEFAULT_POOL},
 };
 
-use crate::handlers_config::HandlersConfig;
+// Response cache for epoch-level aggregations
+const DEFAULT_CACHE_ENABLED: (&str, bool) = ("cache-enabled", true);
+const DEFAULT_CACHE_TTL: (&str, u64) = ("cache-ttl", 60); // seconds
+const DEFAULT_CACHE_MAX_ENTRIES: (&str, u64) = ("cache-max-entries", 1024);
+const DEFAULT_CACHE_METRICS_TOPIC: (&str, &str) =
+    ("cache-metrics-topic", "cardano.monitor.rest-cache");
+const DEFAULT_CACHE_METRICS_INTERVAL: (&str, u64) = ("cache-metrics-interval", 60); // seconds
+const DEFAULT_WORKER_METRICS_TOPIC: (&str, &str) =
+    ("worker-metrics-topic", "cardano.monitor.rest-workers");
+const DEFAULT_WORKER_METRICS_INTERVAL: (&str, u64) = ("worker-metrics-interval", 60); // seconds
+const NOT_READY_RETRY_AFTER: u64 = 5; // seconds
+const DEFAULT_EPOCH_ACTIVITY_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("epoch-activity-subscribe-topic", "cardano.epoch.activity");
 
 // Accounts topics
 const DEFAULT_HANDLE_SINGLE_ACCOUNT_TOPIC: (&str, &str) =
@@ -179,6 +224,37 @@ const DEFAULT_HANDLE_PROPOSAL_METADATA_TOPIC: (&str, &str) = (
     "rest.get.governance.proposals.*.*.metadata",
 );
 
+// Network topics
+const DEFAULT_HANDLE_NETWORK_TOPIC: (&str, &str) = ("handle-topic-network", "rest.get.network");
+const DEFAULT_HANDLE_NETWORK_ERAS_TOPIC: (&str, &str) =
+    ("handle-topic-network-eras", "rest.get.network.eras");
+
+// Utilities topics
+const DEFAULT_HANDLE_ADDRESS_INSPECT_TOPIC: (&str, &str) = (
+    "handle-topic-address-inspect",
+    "rest.get.utils.addresses.inspect.*",
+);
+const DEFAULT_HANDLE_TX_INSPECT_TOPIC: (&str, &str) =
+    ("handle-topic-tx-inspect", "rest.post.utils.txs.inspect");
+const DEFAULT_HANDLE_POOL_ID_CONVERT_TOPIC: (&str, &str) = (
+    "handle-topic-pool-id-convert",
+    "rest.get.utils.pools.convert.*",
+);
+const DEFAULT_HANDLE_DREP_ID_CONVERT_TOPIC: (&str, &str) = (
+    "handle-topic-drep-id-convert",
+    "rest.get.utils.dreps.convert.*",
+);
+const DEFAULT_HANDLE_STAKE_ADDRESS_CONVERT_TOPIC: (&str, &str) = (
+    "handle-topic-stake-address-convert",
+    "rest.get.utils.accounts.convert.*",
+);
+
+// Health topics
+const DEFAULT_HANDLE_HEALTH_TOPIC: (&str, &str) = ("handle-topic-health", "rest.get.health");
+const DEFAULT_HANDLE_SYNC_TOPIC: (&str, &str) = ("handle-topic-sync", "rest.get.sync");
+const DEFAULT_HANDLE_RATE_LIMITS_TOPIC: (&str, &str) =
+    ("handle-topic-rate-limits", "rest.get.admin.rate-limits");
+
 // Pools topics
 const DEFAULT_HANDLE_POOLS_LIST_TOPIC: (&str, &str) = ("handle-topic-pools-list", "rest.get.pools");
 const DEFAULT_HANDLE_POOLS_EXTENDED_RETIRED_RETIRING_SINGLE_TOPIC: (&str, &str) = (
@@ -246,6 +322,8 @@ const DEFAULT_HANDLE_ASSET_SINGLE_TOPIC: (&str, &str) =
     ("handle-topic-asset-single", "rest.get.assets.*");
 const DEFAULT_HANDLE_ASSET_HISTORY_TOPIC: (&str, &str) =
     ("handle-topic-asset-history", "rest.get.assets.*.history");
+const DEFAULT_HANDLE_ASSET_SUPPLY_TOPIC: (&str, &str) =
+    ("handle-topic-asset-supply", "rest.get.assets.*.supply");
 const DEFAULT_HANDLE_ASSET_TRANSACTIONS_TOPIC: (&str, &str) = (
     "handle-topic-asset-transactions",
     "rest.get.assets.*.transactions",
@@ -257,6 +335,26 @@ const DEFAULT_HANDLE_ASSET_ADDRESSES_TOPIC: (&str, &str) = (
 const DEFAULT_HANDLE_POLICY_ASSETS_TOPIC: (&str, &str) =
     ("handle-topic-policy-assets", "rest.get.assets.policy.*");
 
+// Scripts topics
+const DEFAULT_HANDLE_SCRIPTS_LIST_TOPIC: (&str, &str) =
+    ("handle-topic-scripts-list", "rest.get.scripts");
+const DEFAULT_HANDLE_SCRIPT_SINGLE_TOPIC: (&str, &str) =
+    ("handle-topic-script-single", "rest.get.scripts.*");
+const DEFAULT_HANDLE_SCRIPT_JSON_TOPIC: (&str, &str) =
+    ("handle-topic-script-json", "rest.get.scripts.*.json");
+const DEFAULT_HANDLE_SCRIPT_CBOR_TOPIC: (&str, &str) =
+    ("handle-topic-script-cbor", "rest.get.scripts.*.cbor");
+const DEFAULT_HANDLE_SCRIPT_REDEEMERS_TOPIC: (&str, &str) = (
+    "handle-topic-script-redeemers",
+    "rest.get.scripts.*.redeemers",
+);
+const DEFAULT_HANDLE_SCRIPT_DATUM_TOPIC: (&str, &str) =
+    ("handle-topic-script-datum", "rest.get.scripts.datum.*");
+const DEFAULT_HANDLE_SCRIPT_DATUM_CBOR_TOPIC: (&str, &str) = (
+    "handle-topic-script-datum-cbor",
+    "rest.get.scripts.datum.*.cbor",
+);
+
 // Addresses topics
 const DEFAULT_HANDLE_ADDRESS_SINGLE_TOPIC: (&str, &str) =
     ("handle-topic-address-single", "rest.get.addresses.*");
@@ -293,6 +391,96 @@ impl BlockfrostREST {
 
         info!("Blockfrost REST enabled");
 
+        workers::init(workers::read_config(&config)?);
+        rate_limits::init(rate_limits::read_config(&config)?);
+        Self::start_worker_metrics(context.clone(), &config).await?;
+
+        let cache = if get_bool_flag(&config, DEFAULT_CACHE_ENABLED) {
+            Some(Self::start_cache(context.clone(), &config).await?)
+        } else {
+            None
+        };
+
+        // Handler for /health
+        register_status_handler(
+            context.clone(),
+            DEFAULT_HANDLE_HEALTH_TOPIC,
+            handlers_config.clone(),
+            handle_health_blockfrost,
+        );
+
+        // Handler for /sync
+        register_status_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SYNC_TOPIC,
+            handlers_config.clone(),
+            handle_sync_blockfrost,
+        );
+
+        // Handler for /admin/rate-limits
+        register_status_handler(
+            context.clone(),
+            DEFAULT_HANDLE_RATE_LIMITS_TOPIC,
+            handlers_config.clone(),
+            handle_rate_limits_blockfrost,
+        );
+
+        // Handler for /network
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_NETWORK_TOPIC,
+            handlers_config.clone(),
+            handle_network_blockfrost,
+        );
+
+        // Handler for /network/eras
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_NETWORK_ERAS_TOPIC,
+            handlers_config.clone(),
+            handle_network_eras_blockfrost,
+        );
+
+        // Handler for /utils/addresses/inspect/{address}
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_ADDRESS_INSPECT_TOPIC,
+            handlers_config.clone(),
+            handle_address_inspect_blockfrost,
+        );
+
+        // Handler for POST /utils/txs/inspect
+        register_handler_with_body(
+            context.clone(),
+            DEFAULT_HANDLE_TX_INSPECT_TOPIC,
+            handlers_config.clone(),
+            handle_tx_inspect_blockfrost,
+        );
+
+        // Handler for /utils/pools/convert/{pool_id}
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_POOL_ID_CONVERT_TOPIC,
+            handlers_config.clone(),
+            handle_pool_id_convert_blockfrost,
+        );
+
+        // Handler for /utils/dreps/convert/{drep_id}
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_DREP_ID_CONVERT_TOPIC,
+            handlers_config.clone(),
+            handle_drep_id_convert_blockfrost,
+        );
+
+        // Handler for /utils/accounts/convert/{stake_address}
+        register_handler_with_query(
+            context.clone(),
+            DEFAULT_HANDLE_STAKE_ADDRESS_CONVERT_TOPIC,
+            handlers_config.clone(),
+            handle_stake_address_convert_blockfrost,
+        );
+
         // Handler for /accounts/{stake_address}
         register_handler(
             context.clone(),
@@ -302,7 +490,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/registrations
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_REGISTRATIONS_TOPIC,
             handlers_config.clone(),
@@ -310,7 +498,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/delegations
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_DELEGATIONS_TOPIC,
             handlers_config.clone(),
@@ -318,7 +506,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/mirs
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_MIRS_TOPIC,
             handlers_config.clone(),
@@ -326,7 +514,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/withdrawals
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_WITHDRAWALS_TOPIC,
             handlers_config.clone(),
@@ -334,7 +522,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/rewards
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_REWARDS_TOPIC,
             handlers_config.clone(),
@@ -342,7 +530,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/addresses
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_ADDRESSES_TOPIC,
             handlers_config.clone(),
@@ -350,7 +538,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/addresses/assets
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_ASSETS_TOPIC,
             handlers_config.clone(),
@@ -366,7 +554,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /accounts/{stake_address}/utxos
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ACCOUNT_UTXOS_TOPIC,
             handlers_config.clone(),
@@ -438,10 +626,11 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/dreps
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_DREPS_LIST_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_dreps_list_blockfrost,
         );
 
@@ -454,7 +643,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/dreps/{drep_id}/delegators
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_DREP_DELEGATORS_TOPIC,
             handlers_config.clone(),
@@ -470,7 +659,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/dreps/{drep_id}/updates
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_DREP_UPDATES_TOPIC,
             handlers_config.clone(),
@@ -478,7 +667,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/dreps/{drep_id}/votes
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_DREP_VOTES_TOPIC,
             handlers_config.clone(),
@@ -486,10 +675,11 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/proposals
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_PROPOSALS_LIST_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_proposals_list_blockfrost,
         );
 
@@ -518,7 +708,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /governance/proposals/{tx_hash}/{cert_index}/votes
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_PROPOSAL_VOTES_TOPIC,
             handlers_config.clone(),
@@ -534,23 +724,26 @@ impl BlockfrostREST {
         );
 
         // Handler for /pools
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOLS_LIST_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_pools_list_blockfrost,
         );
 
-        // Handler for /pools/extended, /pools/retired, /pools/retiring, and /pools/{pool_id}
-        register_handler(
+        // Handler for /pools/extended, /pools/retired, /pools/retiring, /pools/relays and
+        // /pools/{pool_id}
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOLS_EXTENDED_RETIRED_RETIRING_SINGLE_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_pools_extended_retired_retiring_single_blockfrost,
         );
 
         // Handler for /pools/{pool_id}/history
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOL_HISTORY_TOPIC,
             handlers_config.clone(),
@@ -574,15 +767,16 @@ impl BlockfrostREST {
         );
 
         // Handler for /pools/{pool_id}/delegators
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOL_DELEGATORS_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_pool_delegators_blockfrost,
         );
 
         // Handler for /pools/{pool_id}/blocks
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOL_BLOCKS_TOPIC,
             handlers_config.clone(),
@@ -590,7 +784,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /pools/{pool_id}/updates
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOL_UPDATES_TOPIC,
             handlers_config.clone(),
@@ -598,7 +792,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /pools/{pool_id}/votes
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POOL_VOTES_TOPIC,
             handlers_config.clone(),
@@ -622,7 +816,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /epochs/{number}/next
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_NEXT_TOPIC,
             handlers_config.clone(),
@@ -630,7 +824,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /epochs/{number}/previous
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_PREVIOUS_TOPIC,
             handlers_config.clone(),
@@ -638,23 +832,25 @@ impl BlockfrostREST {
         );
 
         // Handler for /epochs/{number}/stakes
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_TOTAL_STAKES_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_epoch_total_stakes_blockfrost,
         );
 
         // Handler for /epochs/{number}/stakes/{pool_id}
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_POOL_STAKES_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_epoch_pool_stakes_blockfrost,
         );
 
         // Handler for /epochs/{number}/blocks
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_TOTAL_BLOCKS_TOPIC,
             handlers_config.clone(),
@@ -662,7 +858,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /epochs/{number}/blocks/{pool_id}
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_EPOCH_POOL_BLOCKS_TOPIC,
             handlers_config.clone(),
@@ -670,10 +866,11 @@ impl BlockfrostREST {
         );
 
         // Handler for /assets
-        register_handler(
+        register_cached_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ASSETS_LIST_TOPIC,
             handlers_config.clone(),
+            cache.clone(),
             handle_assets_list_blockfrost,
         );
 
@@ -686,15 +883,23 @@ impl BlockfrostREST {
         );
 
         // Handler for /assets/{asset}/history
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ASSET_HISTORY_TOPIC,
             handlers_config.clone(),
             handle_asset_history_blockfrost,
         );
 
+        // Handler for /assets/{asset}/supply
+        register_handler_with_query(
+            context.clone(),
+            DEFAULT_HANDLE_ASSET_SUPPLY_TOPIC,
+            handlers_config.clone(),
+            handle_asset_supply_blockfrost,
+        );
+
         // Handler for /assets/{asset}/transactions
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ASSET_TRANSACTIONS_TOPIC,
             handlers_config.clone(),
@@ -702,7 +907,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /assets/{asset}/addresses
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ASSET_ADDRESSES_TOPIC,
             handlers_config.clone(),
@@ -710,13 +915,69 @@ impl BlockfrostREST {
         );
 
         // Handler for /assets/policy/{policy_id}
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_POLICY_ASSETS_TOPIC,
             handlers_config.clone(),
             handle_policy_assets_blockfrost,
         );
 
+        // Handler for /scripts
+        register_handler_with_query(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPTS_LIST_TOPIC,
+            handlers_config.clone(),
+            handle_scripts_list_blockfrost,
+        );
+
+        // Handler for /scripts/{script_hash}
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_SINGLE_TOPIC,
+            handlers_config.clone(),
+            handle_script_single_blockfrost,
+        );
+
+        // Handler for /scripts/{script_hash}/json
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_JSON_TOPIC,
+            handlers_config.clone(),
+            handle_script_json_blockfrost,
+        );
+
+        // Handler for /scripts/{script_hash}/cbor
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_CBOR_TOPIC,
+            handlers_config.clone(),
+            handle_script_cbor_blockfrost,
+        );
+
+        // Handler for /scripts/{script_hash}/redeemers
+        register_handler_with_query(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_REDEEMERS_TOPIC,
+            handlers_config.clone(),
+            handle_script_redeemers_blockfrost,
+        );
+
+        // Handler for /scripts/datum/{datum_hash}
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_DATUM_TOPIC,
+            handlers_config.clone(),
+            handle_script_datum_blockfrost,
+        );
+
+        // Handler for /scripts/datum/{datum_hash}/cbor
+        register_handler(
+            context.clone(),
+            DEFAULT_HANDLE_SCRIPT_DATUM_CBOR_TOPIC,
+            handlers_config.clone(),
+            handle_script_datum_cbor_blockfrost,
+        );
+
         // Handler for /addresses/{address}
         register_handler(
             context.clone(),
@@ -742,7 +1003,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /addresses/{address}/utxos
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ADDRESS_UTXOS_TOPIC,
             handlers_config.clone(),
@@ -750,7 +1011,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /addresses/{address}/utxos/{asset}
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ADDRESS_ASSET_UTXOS_TOPIC,
             handlers_config.clone(),
@@ -758,7 +1019,7 @@ impl BlockfrostREST {
         );
 
         // Handler for /addresses/{address}/transactions
-        register_handler(
+        register_handler_with_query(
             context.clone(),
             DEFAULT_HANDLE_ADDRESS_TRANSACTIONS_TOPIC,
             handlers_config.clone(),
@@ -791,6 +1052,131 @@ impl BlockfrostREST {
 
         Ok(())
     }
+
+    /// Create the response cache, clearing it at each epoch boundary and on rollback,
+    /// and publish its metrics periodically
+    async fn start_cache(
+        context: Arc<Context<Message>>,
+        config: &Config,
+    ) -> Result<Arc<ResponseCache>> {
+        let ttl = Duration::from_secs(get_u64_flag(config, DEFAULT_CACHE_TTL));
+        let max_entries = get_u64_flag(config, DEFAULT_CACHE_MAX_ENTRIES) as usize;
+        let metrics_topic = get_string_flag(config, DEFAULT_CACHE_METRICS_TOPIC);
+        let metrics_interval = get_u64_flag(config, DEFAULT_CACHE_METRICS_INTERVAL).max(1);
+        let epoch_activity_topic = get_string_flag(config, DEFAULT_EPOCH_ACTIVITY_SUBSCRIBE_TOPIC);
+        info!(
+            "Caching epoch-level responses for {}s, up to {max_entries} entries",
+            ttl.as_secs()
+        );
+
+        let cache = Arc::new(ResponseCache::new("rest-blockfrost", ttl, max_entries));
+
+        let mut epoch_subscription = context.subscribe(&epoch_activity_topic).await?;
+        let epoch_cache = cache.clone();
+        context.run(async move {
+            loop {
+                match epoch_subscription.read().await {
+                    Ok((_, message)) => match message.as_ref() {
+                        Message::Cardano((_, CardanoMessage::EpochActivity(_)))
+                        | Message::Cardano((_, CardanoMessage::StateTransition(_))) => {
+                            epoch_cache.invalidate()
+                        }
+                        _ => continue,
+                    },
+                    Err(_) => return,
+                }
+            }
+        });
+
+        let mut tick_subscription = context.subscribe("clock.tick").await?;
+        let metrics_cache = cache.clone();
+        let metrics_context = context.clone();
+        context.run(async move {
+            loop {
+                match tick_subscription.read().await {
+                    Ok((_, message)) => match message.as_ref() {
+                        Message::Clock(tick) if tick.number % metrics_interval == 0 => {
+                            let metrics = metrics_cache.metrics();
+                            info!(
+                                entries = metrics.entries,
+                                hits = metrics.hits,
+                                misses = metrics.misses,
+                                "REST cache"
+                            );
+                            let message =
+                                Arc::new(Message::Monitoring(MonitoringMessage::Cache(metrics)));
+                            if let Err(e) =
+                                metrics_context.message_bus.publish(&metrics_topic, message).await
+                            {
+                                warn!("Could not publish REST cache metrics: {e:#}");
+                            }
+                        }
+                        _ => continue,
+                    },
+                    Err(_) => return,
+                }
+            }
+        });
+
+        Ok(cache)
+    }
+
+    /// Publish the load on the worker pools periodically
+    async fn start_worker_metrics(context: Arc<Context<Message>>, config: &Config) -> Result<()> {
+        let metrics_topic = get_string_flag(config, DEFAULT_WORKER_METRICS_TOPIC);
+        let metrics_interval = get_u64_flag(config, DEFAULT_WORKER_METRICS_INTERVAL).max(1);
+
+        let mut tick_subscription = context.subscribe("clock.tick").await?;
+        let metrics_context = context.clone();
+        context.run(async move {
+            loop {
+                match tick_subscription.read().await {
+                    Ok((_, message)) => match message.as_ref() {
+                        Message::Clock(tick) if tick.number % metrics_interval == 0 => {
+                            let metrics = workers::metrics("rest-blockfrost");
+                            for pool in &metrics.pools {
+                                info!(
+                                    pool = %pool.pool,
+                                    in_flight = pool.in_flight,
+                                    queued = pool.queued,
+                                    rejected = pool.rejected,
+                                    "REST workers"
+                                );
+                            }
+                            let message =
+                                Arc::new(Message::Monitoring(MonitoringMessage::Workers(metrics)));
+                            if let Err(e) =
+                                metrics_context.message_bus.publish(&metrics_topic, message).await
+                            {
+                                warn!("Could not publish REST worker metrics: {e:#}");
+                            }
+                        }
+                        _ => continue,
+                    },
+                    Err(_) => return,
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+// TODO: Conditional requests for immutable resources (blocks, transactions, past epochs):
+// hash the response into an ETag, answer 304 when it matches If-None-Match, and send a
+// Cache-Control configured per route group. This needs caryatid_module_rest_server to
+// pass request headers in RESTRequest and to take response headers in RESTResponse,
+// which today carries only the status code, body and content type.
+/// Answer 503 until the state modules behind the API are ready
+fn check_ready(handlers_config: &HandlersConfig) -> Result<(), RESTError> {
+    let waiting = readiness::not_ready(&handlers_config.ready_modules);
+    if waiting.is_empty() {
+        return Ok(());
+    }
+    Err(RESTError::NotSynced {
+        message: format!("Waiting for {} to be ready", waiting.join(", ")),
+        retry_after: Some(NOT_READY_RETRY_AFTER),
+    })
 }
 
 fn register_handler<F, Fut>(
@@ -805,17 +1191,89 @@ fn register_handler<F, Fut>(
         + Clone
         + 'static,
     Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    register_handler_in_pool(context, topic, DEFAULT_POOL, handlers_config, handler_fn);
+}
+
+/// As `register_handler`, handling requests in the given worker pool unless the route
+/// is configured into another
+fn register_handler_in_pool<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    pool: &str,
+    handlers_config: Arc<HandlersConfig>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    register_status_handler_in_pool(
+        context,
+        topic,
+        pool,
+        handlers_config,
+        move |context, params, handlers_config| {
+            let handler_fn = handler_fn.clone();
+            async move {
+                check_ready(&handlers_config)?;
+                handler_fn(context, params, handlers_config).await
+            }
+        },
+    );
+}
+
+/// As `register_handler`, but answering before the state modules are ready
+fn register_status_handler<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    handlers_config: Arc<HandlersConfig>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    register_status_handler_in_pool(context, topic, DEFAULT_POOL, handlers_config, handler_fn);
+}
+
+fn register_status_handler_in_pool<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    pool: &str,
+    handlers_config: Arc<HandlersConfig>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
 {
     let topic_name = get_string_flag(&context.config, topic);
+    let pool = workers::pool_for(&topic_name, pool);
     info!("Creating request handler on '{}'", topic_name);
 
+    let route = topic_name.clone();
     handle_rest_with_path_parameter(context.clone(), &topic_name, move |params| {
         let context = context.clone();
         let handler_fn = handler_fn.clone();
         let params: Vec<String> = params.iter().map(|s| s.to_string()).collect();
         let handlers_config = handlers_config.clone();
+        let pool = pool.clone();
+        let route = route.clone();
 
-        async move { handler_fn(context, params, handlers_config).await }
+        async move {
+            rate_limits::check(&route)?;
+            pool.run(handler_fn(context, params, handlers_config)).await
+        }
     });
 }
 
@@ -831,10 +1289,29 @@ fn register_handler_with_query<F, Fut>(
         + Clone
         + 'static,
     Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    register_handler_with_query_in_pool(context, topic, DEFAULT_POOL, handlers_config, handler_fn);
+}
+
+fn register_handler_with_query_in_pool<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    pool: &str,
+    handlers_config: Arc<HandlersConfig>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, HashMap<String, String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
 {
     let topic_name = get_string_flag(&context.config, topic);
+    let pool = workers::pool_for(&topic_name, pool);
     info!("Creating request handler on '{}'", topic_name);
 
+    let route = topic_name.clone();
     handle_rest_with_path_and_query_parameters(
         context.clone(),
         &topic_name,
@@ -843,8 +1320,153 @@ fn register_handler_with_query<F, Fut>(
             let handler_fn = handler_fn.clone();
             let params: Vec<String> = params.iter().map(|s| s.to_string()).collect();
             let handlers_config = handlers_config.clone();
+            let pool = pool.clone();
+            let route = route.clone();
+
+            async move {
+                rate_limits::check(&route)?;
+                check_ready(&handlers_config)?;
+                pool.run(handler_fn(context, params, query_params, handlers_config)).await
+            }
+        },
+    );
+}
+
+/// As `register_handler`, passing the handler the request body instead of path parameters
+fn register_handler_with_body<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    handlers_config: Arc<HandlersConfig>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, String, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    let topic_name = get_string_flag(&context.config, topic);
+    let pool = workers::pool_for(&topic_name, DEFAULT_POOL);
+    info!("Creating request handler on '{}'", topic_name);
+
+    let route = topic_name.clone();
+    handle_rest_with_body(context.clone(), &topic_name, move |body| {
+        let context = context.clone();
+        let handler_fn = handler_fn.clone();
+        let handlers_config = handlers_config.clone();
+        let pool = pool.clone();
+        let route = route.clone();
+
+        async move {
+            rate_limits::check(&route)?;
+            check_ready(&handlers_config)?;
+            pool.run(handler_fn(context, body, handlers_config)).await
+        }
+    });
+}
+
+/// As `register_handler`, in the aggregate worker pool, serving repeated requests from the
+/// response cache if enabled
+fn register_cached_handler<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    handlers_config: Arc<HandlersConfig>,
+    cache: Option<Arc<ResponseCache>>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    let Some(cache) = cache else {
+        return register_handler_in_pool(
+            context,
+            topic,
+            AGGREGATE_POOL,
+            handlers_config,
+            handler_fn,
+        );
+    };
+    let topic_name = get_string_flag(&context.config, topic);
+
+    register_handler_in_pool(
+        context,
+        topic,
+        AGGREGATE_POOL,
+        handlers_config,
+        move |context, params, handlers_config| {
+            let cache = cache.clone();
+            let handler_fn = handler_fn.clone();
+            let key = cache_key(&topic_name, &params, None);
 
-            async move { handler_fn(context, params, query_params, handlers_config).await }
+            async move { cached(&cache, key, handler_fn(context, params, handlers_config)).await }
         },
     );
 }
+
+/// As `register_handler_with_query`, in the aggregate worker pool, serving repeated
+/// requests from the response cache if enabled
+fn register_cached_handler_with_query<F, Fut>(
+    context: Arc<Context<Message>>,
+    topic: (&str, &str),
+    handlers_config: Arc<HandlersConfig>,
+    cache: Option<Arc<ResponseCache>>,
+    handler_fn: F,
+) where
+    F: Fn(Arc<Context<Message>>, Vec<String>, HashMap<String, String>, Arc<HandlersConfig>) -> Fut
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+    Fut: Future<Output = Result<RESTResponse, RESTError>> + Send + 'static,
+{
+    let Some(cache) = cache else {
+        return register_handler_with_query_in_pool(
+            context,
+            topic,
+            AGGREGATE_POOL,
+            handlers_config,
+            handler_fn,
+        );
+    };
+    let topic_name = get_string_flag(&context.config, topic);
+
+    register_handler_with_query_in_pool(
+        context,
+        topic,
+        AGGREGATE_POOL,
+        handlers_config,
+        move |context, params, query_params, handlers_config| {
+            let cache = cache.clone();
+            let handler_fn = handler_fn.clone();
+            let key = cache_key(&topic_name, &params, Some(&query_params));
+
+            async move {
+                cached(
+                    &cache,
+                    key,
+                    handler_fn(context, params, query_params, handlers_config),
+                )
+                .await
+            }
+        },
+    );
+}
+
+async fn cached(
+    cache: &ResponseCache,
+    key: String,
+    handler: impl Future<Output = Result<RESTResponse, RESTError>>,
+) -> Result<RESTResponse, RESTError> {
+    let generation = match cache.lookup(&key, Instant::now()) {
+        Lookup::Hit(response) => return Ok(response),
+        Lookup::Miss(generation) => generation,
+    };
+    let response = handler.await?;
+    cache.insert(key, &response, generation, Instant::now());
+    Ok(response)
+}
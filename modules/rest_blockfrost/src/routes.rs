@@ -35,6 +35,92 @@ pub struct RouteDefinition {
 
 /// All registered API routes
 pub const ROUTES: &[RouteDefinition] = &[
+    // ==================== Health ====================
+    RouteDefinition {
+        topic_pattern: "rest.get.health",
+        rest_path: "/health",
+        mcp_uri_template: "blockfrost://health",
+        name: "Health",
+        description: "Return whether the node is synced and all state modules are ready",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_health_blockfrost",
+        param_names: &[],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.sync",
+        rest_path: "/sync",
+        mcp_uri_template: "blockfrost://sync",
+        name: "Sync Status",
+        description: "Return the chain tip, sync progress against the wall clock slot, bootstrap phase and module readiness",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_sync_blockfrost",
+        param_names: &[],
+    },
+
+    // ==================== Network ====================
+    RouteDefinition {
+        topic_pattern: "rest.get.network",
+        rest_path: "/network",
+        mcp_uri_template: "blockfrost://network",
+        name: "Network Information",
+        description: "Return the supply of lovelace and the live and active stake across the network",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_network_blockfrost",
+        param_names: &[],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.network.eras",
+        rest_path: "/network/eras",
+        mcp_uri_template: "blockfrost://network/eras",
+        name: "Network Eras",
+        description: "Return the start, end and parameters of each era seen by the node",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_network_eras_blockfrost",
+        param_names: &[],
+    },
+
+    // ==================== Utilities ====================
+    RouteDefinition {
+        topic_pattern: "rest.get.utils.addresses.inspect.*",
+        rest_path: "/utils/addresses/inspect/{address}",
+        mcp_uri_template: "blockfrost://utils/addresses/inspect/{address}",
+        name: "Address Inspection",
+        description: "Break an address down into its era, type, network, payment credential and stake reference",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_address_inspect_blockfrost",
+        param_names: &["address"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.utils.pools.convert.*",
+        rest_path: "/utils/pools/convert/{pool_id}",
+        mcp_uri_template: "blockfrost://utils/pools/convert/{pool_id}",
+        name: "Pool ID Conversion",
+        description: "Convert a pool ID between its pool1 Bech32 and hex forms",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_pool_id_convert_blockfrost",
+        param_names: &["pool_id"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.utils.dreps.convert.*",
+        rest_path: "/utils/dreps/convert/{drep_id}",
+        mcp_uri_template: "blockfrost://utils/dreps/convert/{drep_id}",
+        name: "DRep ID Conversion",
+        description: "Convert a DRep ID between its CIP-105 and CIP-129 Bech32 forms and its credential",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_drep_id_convert_blockfrost",
+        param_names: &["drep_id"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.utils.accounts.convert.*",
+        rest_path: "/utils/accounts/convert/{stake_address}",
+        mcp_uri_template: "blockfrost://utils/accounts/convert/{stake_address}",
+        name: "Stake Address Conversion",
+        description: "Convert between a stake address and its stake credential",
+        handler_type: HandlerType::WithQuery,
+        handler_name: "handle_stake_address_convert_blockfrost",
+        param_names: &["stake_address"],
+    },
+
     // ==================== Accounts ====================
     RouteDefinition {
         topic_pattern: "rest.get.accounts.*",
@@ -52,7 +138,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/registrations",
         name: "Account Registrations",
         description: "Obtain information about the registrations of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_registrations_blockfrost",
         param_names: &["stake_address"],
     },
@@ -62,7 +148,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/delegations",
         name: "Account Delegations",
         description: "Obtain information about the delegations of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_delegations_blockfrost",
         param_names: &["stake_address"],
     },
@@ -72,7 +158,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/mirs",
         name: "Account MIRs",
         description: "Obtain information about MIRs of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_mirs_blockfrost",
         param_names: &["stake_address"],
     },
@@ -82,7 +168,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/withdrawals",
         name: "Account Withdrawals",
         description: "Obtain information about the withdrawals of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_withdrawals_blockfrost",
         param_names: &["stake_address"],
     },
@@ -92,7 +178,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/rewards",
         name: "Account Rewards",
         description: "Obtain information about the rewards history of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_rewards_blockfrost",
         param_names: &["stake_address"],
     },
@@ -102,7 +188,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/addresses",
         name: "Account Addresses",
         description: "Obtain information about the addresses associated with a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_addresses_blockfrost",
         param_names: &["stake_address"],
     },
@@ -112,7 +198,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/addresses/assets",
         name: "Account Assets",
         description: "Obtain information about assets associated with addresses of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_assets_blockfrost",
         param_names: &["stake_address"],
     },
@@ -132,7 +218,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://accounts/{stake_address}/utxos",
         name: "Account UTXOs",
         description: "Obtain information about UTXOs of a specific account",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_account_utxos_blockfrost",
         param_names: &["stake_address"],
     },
@@ -226,7 +312,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/dreps",
         name: "DReps List",
         description: "Return list of registered DReps",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_dreps_list_blockfrost",
         param_names: &[],
     },
@@ -246,7 +332,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/dreps/{drep_id}/delegators",
         name: "DRep Delegators",
         description: "Return list of delegators to a specific DRep",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_drep_delegators_blockfrost",
         param_names: &["drep_id"],
     },
@@ -266,7 +352,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/dreps/{drep_id}/updates",
         name: "DRep Updates",
         description: "Return list of updates to a specific DRep",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_drep_updates_blockfrost",
         param_names: &["drep_id"],
     },
@@ -276,7 +362,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/dreps/{drep_id}/votes",
         name: "DRep Votes",
         description: "Return list of votes cast by a specific DRep",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_drep_votes_blockfrost",
         param_names: &["drep_id"],
     },
@@ -288,7 +374,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/proposals",
         name: "Proposals List",
         description: "Return list of governance proposals",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_proposals_list_blockfrost",
         param_names: &[],
     },
@@ -328,7 +414,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://governance/proposals/{tx_hash}/{cert_index}/votes",
         name: "Proposal Votes",
         description: "Return votes on a specific governance proposal",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_proposal_votes_blockfrost",
         param_names: &["tx_hash", "cert_index"],
     },
@@ -350,7 +436,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools",
         name: "Pools List",
         description: "Return list of registered stake pools",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pools_list_blockfrost",
         param_names: &[],
     },
@@ -359,8 +445,8 @@ pub const ROUTES: &[RouteDefinition] = &[
         rest_path: "/pools/{pool_id}",
         mcp_uri_template: "blockfrost://pools/{pool_id}",
         name: "Pool Information",
-        description: "Return information about a specific pool (also handles extended/retired/retiring)",
-        handler_type: HandlerType::PathOnly,
+        description: "Return information about a specific pool (also handles extended/retired/retiring/relays)",
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pools_extended_retired_retiring_single_blockfrost",
         param_names: &["pool_id"],
     },
@@ -370,7 +456,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools/{pool_id}/history",
         name: "Pool History",
         description: "Return history of a specific pool",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pool_history_blockfrost",
         param_names: &["pool_id"],
     },
@@ -400,7 +486,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools/{pool_id}/delegators",
         name: "Pool Delegators",
         description: "Return list of delegators to a specific pool",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pool_delegators_blockfrost",
         param_names: &["pool_id"],
     },
@@ -410,7 +496,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools/{pool_id}/blocks",
         name: "Pool Blocks",
         description: "Return list of blocks minted by a specific pool",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pool_blocks_blockfrost",
         param_names: &["pool_id"],
     },
@@ -420,7 +506,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools/{pool_id}/updates",
         name: "Pool Updates",
         description: "Return list of certificate updates to a specific pool",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pool_updates_blockfrost",
         param_names: &["pool_id"],
     },
@@ -430,7 +516,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://pools/{pool_id}/votes",
         name: "Pool Votes",
         description: "Return list of votes cast by a specific pool",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_pool_votes_blockfrost",
         param_names: &["pool_id"],
     },
@@ -462,7 +548,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://epochs/{number}/next",
         name: "Next Epochs",
         description: "Return list of epochs following a specific epoch",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_epoch_next_blockfrost",
         param_names: &["number"],
     },
@@ -472,7 +558,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://epochs/{number}/previous",
         name: "Previous Epochs",
         description: "Return list of epochs preceding a specific epoch",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_epoch_previous_blockfrost",
         param_names: &["number"],
     },
@@ -502,7 +588,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://epochs/{number}/blocks",
         name: "Epoch Blocks",
         description: "Return list of blocks within the epoch",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_epoch_total_blocks_blockfrost",
         param_names: &["number"],
     },
@@ -512,7 +598,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://epochs/{number}/blocks/{pool_id}",
         name: "Epoch Pool Blocks",
         description: "Return list of blocks minted by a specific pool in the epoch",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_epoch_pool_blocks_blockfrost",
         param_names: &["number", "pool_id"],
     },
@@ -524,7 +610,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://assets",
         name: "Assets List",
         description: "Return list of assets",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_assets_list_blockfrost",
         param_names: &[],
     },
@@ -544,17 +630,27 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://assets/{asset}/history",
         name: "Asset History",
         description: "Return history of a specific asset",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_asset_history_blockfrost",
         param_names: &["asset"],
     },
+    RouteDefinition {
+        topic_pattern: "rest.get.assets.*.supply",
+        rest_path: "/assets/{asset}/supply",
+        mcp_uri_template: "blockfrost://assets/{asset}/supply",
+        name: "Asset Supply",
+        description: "Return the supply of a specific asset, optionally at the end of an epoch",
+        handler_type: HandlerType::WithQuery,
+        handler_name: "handle_asset_supply_blockfrost",
+        param_names: &["asset"],
+    },
     RouteDefinition {
         topic_pattern: "rest.get.assets.*.transactions",
         rest_path: "/assets/{asset}/transactions",
         mcp_uri_template: "blockfrost://assets/{asset}/transactions",
         name: "Asset Transactions",
         description: "Return list of transactions involving a specific asset",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_asset_transactions_blockfrost",
         param_names: &["asset"],
     },
@@ -564,7 +660,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://assets/{asset}/addresses",
         name: "Asset Addresses",
         description: "Return list of addresses holding a specific asset",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_asset_addresses_blockfrost",
         param_names: &["asset"],
     },
@@ -574,11 +670,83 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://assets/policy/{policy_id}",
         name: "Policy Assets",
         description: "Return list of assets under a specific policy",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_policy_assets_blockfrost",
         param_names: &["policy_id"],
     },
 
+    // ==================== Scripts ====================
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts",
+        rest_path: "/scripts",
+        mcp_uri_template: "blockfrost://scripts",
+        name: "Scripts List",
+        description: "Return list of scripts seen on chain",
+        handler_type: HandlerType::WithQuery,
+        handler_name: "handle_scripts_list_blockfrost",
+        param_names: &[],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.datum.*",
+        rest_path: "/scripts/datum/{datum_hash}",
+        mcp_uri_template: "blockfrost://scripts/datum/{datum_hash}",
+        name: "Datum Value",
+        description: "Return a datum as JSON",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_script_datum_blockfrost",
+        param_names: &["datum_hash"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.datum.*.cbor",
+        rest_path: "/scripts/datum/{datum_hash}/cbor",
+        mcp_uri_template: "blockfrost://scripts/datum/{datum_hash}/cbor",
+        name: "Datum CBOR",
+        description: "Return the CBOR of a datum",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_script_datum_cbor_blockfrost",
+        param_names: &["datum_hash"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.*",
+        rest_path: "/scripts/{script_hash}",
+        mcp_uri_template: "blockfrost://scripts/{script_hash}",
+        name: "Script Information",
+        description: "Return the type and size of a specific script",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_script_single_blockfrost",
+        param_names: &["script_hash"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.*.json",
+        rest_path: "/scripts/{script_hash}/json",
+        mcp_uri_template: "blockfrost://scripts/{script_hash}/json",
+        name: "Script JSON",
+        description: "Return a native script as JSON",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_script_json_blockfrost",
+        param_names: &["script_hash"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.*.cbor",
+        rest_path: "/scripts/{script_hash}/cbor",
+        mcp_uri_template: "blockfrost://scripts/{script_hash}/cbor",
+        name: "Script CBOR",
+        description: "Return the CBOR of a Plutus script",
+        handler_type: HandlerType::PathOnly,
+        handler_name: "handle_script_cbor_blockfrost",
+        param_names: &["script_hash"],
+    },
+    RouteDefinition {
+        topic_pattern: "rest.get.scripts.*.redeemers",
+        rest_path: "/scripts/{script_hash}/redeemers",
+        mcp_uri_template: "blockfrost://scripts/{script_hash}/redeemers",
+        name: "Script Redeemers",
+        description: "Return the redeemers run against a specific script",
+        handler_type: HandlerType::WithQuery,
+        handler_name: "handle_script_redeemers_blockfrost",
+        param_names: &["script_hash"],
+    },
+
     // ==================== Addresses ====================
     RouteDefinition {
         topic_pattern: "rest.get.addresses.*",
@@ -616,7 +784,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://addresses/{address}/utxos",
         name: "Address UTXOs",
         description: "Return UTXOs of a specific address",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_address_utxos_blockfrost",
         param_names: &["address"],
     },
@@ -626,7 +794,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://addresses/{address}/utxos/{asset}",
         name: "Address Asset UTXOs",
         description: "Return UTXOs of a specific address containing a specific asset",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_address_asset_utxos_blockfrost",
         param_names: &["address", "asset"],
     },
@@ -636,7 +804,7 @@ pub const ROUTES: &[RouteDefinition] = &[
         mcp_uri_template: "blockfrost://addresses/{address}/transactions",
         name: "Address Transactions",
         description: "Return transactions involving a specific address",
-        handler_type: HandlerType::PathOnly,
+        handler_type: HandlerType::WithQuery,
         handler_name: "handle_address_transactions_blockfrost",
         param_names: &["address"],
     },
@@ -1,15 +1,23 @@
 use crate::{
     cost_models::{PLUTUS_V1, PLUTUS_V2, PLUTUS_V3},
-    handlers::addresses::AmountListExtended,
+    handlers::{addresses::AmountListExtended, transactions::TxMetadataItem},
 };
 use acropolis_common::{
+    era_summary::{EraBound, EraSummary},
     messages::EpochActivityMessage,
     protocol_params::{Nonce, NonceVariant, ProtocolParams},
-    queries::{accounts::AccountReward, blocks::BlockInfo, governance::DRepActionUpdate},
+    queries::{
+        accounts::{AccountReward, NetworkStats},
+        blocks::BlockInfo,
+        governance::DRepActionUpdate,
+        scripts::{ScriptInfo, ScriptRedeemer},
+    },
     rest_helper::ToCheckedF64,
     serialization::{Bech32WithHrp, DisplayFromBech32, PoolPrefix},
-    AssetAddressEntry, AssetMetadataStandard, AssetMintRecord, Datum, KeyHash, PolicyAsset,
-    PoolEpochState, PoolId, PoolUpdateAction, Relay, TxHash, UTXOValue, ValueMap, Vote, VrfKeyHash,
+    AssetAddressEntry, AssetMetadataStandard, AssetMintRecord, Datum, GovernanceAction, KeyHash,
+    Lovelace, NativeAssetsDelta, PlutusVersion, PolicyAsset, PoolEpochState, PoolId,
+    PoolRegistration, PoolUpdateAction, RedeemerTag, Relay, ScriptHash, ScriptLang, TxCertificate,
+    TxHash, TxOutput, UTXOValue, ValueMap, Vote, VrfKeyHash, Withdrawal,
 };
 use anyhow::Result;
 use num_traits::ToPrimitive;
@@ -50,7 +58,7 @@ impl From<EpochActivityMessage> for EpochActivityRest {
             tx_count: ea_message.total_txs,
             output: ea_message.total_outputs,
             fees: ea_message.total_fees,
-            active_stake: None,
+            active_stake: ea_message.active_stake,
         }
     }
 }
@@ -62,7 +70,7 @@ pub struct BlockInfoREST(pub BlockInfo);
 // REST response structure for /epochs/{number}/stakes
 #[serde_as]
 #[derive(Serialize)]
-pub struct _SPDDByEpochItemRest {
+pub struct SPDDByEpochItemRest {
     pub stake_address: String,
     #[serde_as(as = "DisplayFromBech32<PoolPrefix>")]
     pub pool_id: PoolId,
@@ -73,7 +81,7 @@ pub struct _SPDDByEpochItemRest {
 // REST response structure for /epochs/{number}/stakes/{pool_id}
 #[serde_as]
 #[derive(Serialize)]
-pub struct _SPDDByEpochAndPoolItemRest {
+pub struct SPDDByEpochAndPoolItemRest {
     pub stake_address: String,
     #[serde_as(as = "DisplayFromStr")]
     pub amount: u64,
@@ -97,11 +105,10 @@ pub struct DRepInfoREST {
     pub has_script: bool,
     pub retired: bool,
     pub expired: bool,
-    pub last_active_epoch: u64,
+    pub last_active_epoch: Option<u64>,
 }
 
 // REST response structure for /governance/dreps/{drep_id}/delegators
-#[allow(dead_code)]
 #[derive(Serialize)]
 pub struct DRepDelegatorREST {
     pub address: String,
@@ -132,19 +139,39 @@ pub struct DRepUpdateREST {
 pub struct DRepVoteREST {
     pub tx_hash: String,
     pub cert_index: u32,
-    pub vote: Vote,
+    pub proposal_id: String,
+    pub proposal_tx_hash: String,
+    pub proposal_cert_index: u8,
+    pub vote: VoteREST,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteREST {
+    Yes,
+    No,
+    Abstain,
+}
+
+impl From<&Vote> for VoteREST {
+    fn from(vote: &Vote) -> Self {
+        match vote {
+            Vote::Yes => Self::Yes,
+            Vote::No => Self::No,
+            Vote::Abstain => Self::Abstain,
+        }
+    }
 }
 
 // REST response structure for /governance/proposals
-#[allow(dead_code)]
 #[derive(Serialize)]
 pub struct ProposalsListREST {
+    pub id: String,
     pub tx_hash: String,
-    pub cert_index: u64,
+    pub cert_index: u8,
     pub governance_type: ProposalTypeREST,
 }
 
-#[allow(dead_code)]
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProposalTypeREST {
@@ -157,16 +184,30 @@ pub enum ProposalTypeREST {
     TreasuryWithdrawals,
 }
 
+impl From<&GovernanceAction> for ProposalTypeREST {
+    fn from(action: &GovernanceAction) -> Self {
+        match action {
+            GovernanceAction::ParameterChange(_) => Self::ParameterChange,
+            GovernanceAction::HardForkInitiation(_) => Self::HardForkInitiation,
+            GovernanceAction::TreasuryWithdrawals(_) => Self::TreasuryWithdrawals,
+            GovernanceAction::NoConfidence(_) => Self::NoConfidence,
+            GovernanceAction::UpdateCommittee(_) => Self::NewCommittee,
+            GovernanceAction::NewConstitution(_) => Self::NewConstitution,
+            GovernanceAction::Information => Self::InfoAction,
+        }
+    }
+}
+
 // REST response structure for /governance/proposals/{tx_hash}/{cert_index}
-#[allow(dead_code)]
 #[derive(Serialize)]
 pub struct ProposalInfoREST {
+    pub id: String,
     pub tx_hash: String,
-    pub cert_index: u64,
+    pub cert_index: u8,
     pub governance_type: ProposalTypeREST,
-    pub deposit: u64,
+    pub deposit: String,
     pub return_address: String,
-    pub governance_description: String,
+    pub governance_description: Value,
     pub ratified_epoch: Option<u64>,
     pub enacted_epoch: Option<u64>,
     pub dropped_epoch: Option<u64>,
@@ -253,10 +294,10 @@ pub struct ProposalWithdrawalsREST {
 #[derive(Serialize)]
 pub struct ProposalVoteREST {
     pub tx_hash: String,
-    pub cert_index: u8,
+    pub cert_index: u32,
     pub voter_role: VoterRoleREST,
     pub voter: String,
-    pub vote: Vote,
+    pub vote: VoteREST,
 }
 
 #[derive(Serialize)]
@@ -369,6 +410,13 @@ pub struct PoolRelayRest {
     pub port: u16,
 }
 
+// REST response structure for /pools/relays
+#[derive(Serialize)]
+pub struct PoolRelaysRest {
+    pub pool_id: String,
+    pub relays: Vec<PoolRelayRest>,
+}
+
 impl From<Relay> for PoolRelayRest {
     fn from(value: Relay) -> Self {
         //todo: port is required on BlockFrost. Need a default value, if not provided
@@ -404,9 +452,44 @@ impl From<Relay> for PoolRelayRest {
 #[serde_as]
 #[derive(Serialize)]
 pub struct PoolUpdateEventRest {
-    pub tx_hash: String,
+    #[serde_as(as = "Hex")]
+    pub tx_hash: TxHash,
     pub cert_index: u64,
     pub action: PoolUpdateAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<PoolUpdateParametersRest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_parameters: Option<PoolUpdateParametersRest>,
+}
+
+// Parameters set by a pool registration or update certificate
+#[serde_as]
+#[derive(Serialize)]
+pub struct PoolUpdateParametersRest {
+    #[serde_as(as = "DisplayFromStr")]
+    pub pledge: u64,
+    pub margin_cost: f32,
+    #[serde_as(as = "DisplayFromStr")]
+    pub fixed_cost: u64,
+    pub reward_account: String,
+    pub owners: Vec<String>,
+}
+
+impl TryFrom<&PoolRegistration> for PoolUpdateParametersRest {
+    type Error = anyhow::Error;
+    fn try_from(registration: &PoolRegistration) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pledge: registration.pledge,
+            margin_cost: registration.margin.to_f32(),
+            fixed_cost: registration.cost,
+            reward_account: registration.reward_account.get_credential().to_stake_bech32()?,
+            owners: registration
+                .pool_owners
+                .iter()
+                .map(|owner| owner.get_credential().to_stake_bech32())
+                .collect::<Result<_, _>>()?,
+        })
+    }
 }
 
 // REST response structure for /pools/{pool_id}/votes
@@ -555,15 +638,18 @@ impl From<(u64, ProtocolParams, Option<Nonce>)> for ProtocolParamsRest {
                 .unwrap_or_default(),
             protocol_major_ver: shelley_params.map(|p| p.protocol_version.major),
             protocol_minor_ver: shelley_params.map(|p| p.protocol_version.minor),
-            min_utxo: match (&babbage, &shelley_params) {
-                (Some(b), _) => Some(b.coins_per_utxo_byte.to_string()),
-                (None, Some(s)) => Some(s.min_utxo_value.to_string()),
+            // Blockfrost gives the per-word or per-byte cost from Alonzo on
+            min_utxo: match (&babbage, &alonzo, &shelley_params) {
+                (Some(b), _, _) => Some(b.coins_per_utxo_byte.to_string()),
+                (None, Some(a), _) => Some(a.lovelace_per_utxo_word.to_string()),
+                (None, None, Some(s)) => Some(s.min_utxo_value.to_string()),
                 _ => None,
             },
             min_pool_cost: shelley_params.map(|p| p.min_pool_cost.to_string()),
             nonce: epoch_nonce.map(|nonce| nonce.to_string()),
-            cost_models: Some(params.cost_models_json()),
-            cost_models_raw: Some(params.cost_models_raw()),
+            // No cost models before Alonzo
+            cost_models: alonzo.map(|_| params.cost_models_json()),
+            cost_models_raw: alonzo.map(|_| params.cost_models_raw()),
 
             // Alonzo params
             price_mem: alonzo
@@ -577,7 +663,12 @@ impl From<(u64, ProtocolParams, Option<Nonce>)> for ProtocolParamsRest {
             max_val_size: alonzo.as_ref().map(|a| a.max_value_size.to_string()),
             collateral_percent: alonzo.as_ref().map(|a| a.collateral_percentage),
             max_collateral_inputs: alonzo.as_ref().map(|a| a.max_collateral_inputs),
-            coins_per_utxo_word: alonzo.as_ref().map(|a| a.lovelace_per_utxo_word.to_string()),
+            // Blockfrost gives the per-byte cost here too from Babbage on
+            coins_per_utxo_word: match (&babbage, &alonzo) {
+                (Some(b), _) => Some(b.coins_per_utxo_byte.to_string()),
+                (None, Some(a)) => Some(a.lovelace_per_utxo_word.to_string()),
+                _ => None,
+            },
             // Babbage params
             coins_per_utxo_size: babbage.as_ref().map(|b| b.coins_per_utxo_byte.to_string()),
 
@@ -747,8 +838,15 @@ pub struct AssetMintRecordRest {
     action: String,
 }
 
-impl From<&AssetMintRecord> for AssetMintRecordRest {
-    fn from(record: &AssetMintRecord) -> Self {
+#[derive(Debug, Serialize)]
+pub struct AssetSupplyRest {
+    pub asset: String,
+    pub epoch: Option<u64>,
+    pub supply: String,
+}
+
+impl AssetMintRecordRest {
+    pub fn new(record: &AssetMintRecord, tx_hash: &TxHash) -> Self {
         let action = if !record.burn {
             "minted".to_string()
         } else {
@@ -756,7 +854,7 @@ impl From<&AssetMintRecord> for AssetMintRecordRest {
         };
 
         AssetMintRecordRest {
-            tx_hash: "transaction_state not yet implemented".to_string(),
+            tx_hash: hex::encode(tx_hash),
             amount: record.amount.to_string(),
             action,
         }
@@ -786,10 +884,10 @@ impl From<&PolicyAsset> for PolicyAssetRest {
 
 #[derive(Debug, Serialize)]
 pub struct AssetTransactionRest {
-    pub tx_hash: String, // Requires a query to transactions state which is not yet implemented
+    pub tx_hash: String,
     pub tx_index: u16,
     pub block_height: u32,
-    pub block_time: String, // Change to u64 when transactions state is implemented
+    pub block_time: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -863,9 +961,11 @@ impl From<ValueMap> for AmountList {
             quantity: value.lovelace.to_string(),
         });
 
-        for (policy_id, assets) in value.assets {
-            for (asset_name, amount) in assets {
-                out.push(AmountEntry {
+        // Assets come out of hash maps, so order them by unit for stable pages
+        let mut assets = Vec::new();
+        for (policy_id, policy_assets) in value.assets {
+            for (asset_name, amount) in policy_assets {
+                assets.push(AmountEntry {
                     unit: format!(
                         "{}{}",
                         hex::encode(policy_id),
@@ -876,6 +976,31 @@ impl From<ValueMap> for AmountList {
             }
         }
 
+        assets.sort_by(|a, b| a.unit.cmp(&b.unit));
+        out.extend(assets);
+
+        Self(out)
+    }
+}
+
+/// Minted (positive) and burnt (negative) quantities, without lovelace
+impl From<&NativeAssetsDelta> for AmountList {
+    fn from(deltas: &NativeAssetsDelta) -> Self {
+        let mut out = Vec::new();
+
+        for (policy_id, assets) in deltas {
+            for asset in assets {
+                out.push(AmountEntry {
+                    unit: format!(
+                        "{}{}",
+                        hex::encode(policy_id),
+                        hex::encode(asset.name.as_slice())
+                    ),
+                    quantity: asset.amount.to_string(),
+                });
+            }
+        }
+
         Self(out)
     }
 }
@@ -898,6 +1023,7 @@ pub struct DelegationUpdateREST {
 pub struct AccountWithdrawalREST {
     pub tx_hash: String,
     pub amount: String,
+    pub epoch: u32,
 }
 
 #[derive(Serialize)]
@@ -971,6 +1097,79 @@ impl UTxOREST {
     }
 }
 
+/// REST response structure for /utils/txs/inspect
+#[derive(Serialize)]
+pub struct TxInspectionREST {
+    pub hash: String,
+    pub era: String,
+    pub size: usize,
+    pub valid_contract: bool,
+    pub fee: String,
+    pub invalid_before: Option<String>,
+    pub invalid_hereafter: Option<String>,
+    pub inputs: Vec<String>,
+    pub collateral_inputs: Vec<String>,
+    pub reference_inputs: Vec<String>,
+    pub outputs: Vec<TxInspectionOutputREST>,
+    pub certificates: Vec<TxCertificate>,
+    pub withdrawals: Vec<TxInspectionWithdrawalREST>,
+    pub mint: AmountList,
+    pub metadata: Vec<TxMetadataItem>,
+    pub required_signers: Vec<String>,
+    pub script_hashes: Vec<String>,
+    /// Parts of the transaction the decoder could not map, and left out
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TxInspectionOutputREST {
+    pub address: String,
+    pub amount: AmountList,
+    pub data_hash: Option<String>,
+    pub inline_datum: Option<String>,
+    pub reference_script_hash: Option<String>,
+}
+
+impl TryFrom<&TxOutput> for TxInspectionOutputREST {
+    type Error = anyhow::Error;
+
+    fn try_from(output: &TxOutput) -> Result<Self> {
+        let (data_hash, inline_datum) = match &output.datum {
+            Some(Datum::Hash(h)) => (Some(hex::encode(h)), None),
+            Some(Datum::Inline(bytes)) => (None, Some(hex::encode(bytes))),
+            None => (None, None),
+        };
+
+        Ok(Self {
+            address: output.address.to_string()?,
+            amount: output.value.clone().into(),
+            data_hash,
+            inline_datum,
+            reference_script_hash: output
+                .script_ref
+                .as_ref()
+                .map(|s_ref| hex::encode(s_ref.script_hash)),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct TxInspectionWithdrawalREST {
+    pub address: String,
+    pub amount: String,
+}
+
+impl TryFrom<&Withdrawal> for TxInspectionWithdrawalREST {
+    type Error = anyhow::Error;
+
+    fn try_from(withdrawal: &Withdrawal) -> Result<Self> {
+        Ok(Self {
+            address: withdrawal.address.to_string()?,
+            amount: withdrawal.value.to_string(),
+        })
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct AccountTotalsREST {
     pub stake_address: String,
@@ -1003,3 +1202,267 @@ pub struct TransactionInfoREST {
     pub block_height: u32,
     pub block_time: u64,
 }
+
+/// Where the node is in bringing its state up to the chain tip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    /// The chain store has not answered, so the phase cannot be told
+    Unknown,
+    /// Genesis or snapshot bootstrap has not completed, so there is no tip yet
+    Bootstrapping,
+    /// Following the chain, but more than the tolerance behind the wall clock
+    Syncing,
+    /// Within the tolerance of the wall clock slot
+    Synced,
+}
+
+/// Whether a state module is answering queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleReadiness {
+    Ready,
+    NotSynced,
+    Unavailable,
+}
+
+#[derive(Serialize)]
+pub struct ModuleStatusREST {
+    pub module: &'static str,
+    pub status: ModuleReadiness,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TipREST {
+    pub hash: String,
+    pub height: u64,
+    pub slot: u64,
+    pub epoch: u64,
+    pub time: u64,
+}
+
+// REST response structure for /health
+#[derive(Serialize)]
+pub struct HealthREST {
+    pub is_healthy: bool,
+    pub phase: SyncPhase,
+    pub modules: Vec<ModuleStatusREST>,
+}
+
+// REST response structure for /sync
+#[derive(Serialize)]
+pub struct SyncStatusREST {
+    pub phase: SyncPhase,
+    pub tip: Option<TipREST>,
+    /// Slot expected at the current wall clock time
+    pub expected_slot: Option<u64>,
+    pub slots_behind: Option<u64>,
+    /// Tip slot as a percentage of the expected slot
+    pub sync_progress: f64,
+    pub modules: Vec<ModuleStatusREST>,
+}
+
+// REST response structure for /network
+#[derive(Serialize)]
+pub struct NetworkREST {
+    pub supply: NetworkSupplyREST,
+    pub stake: NetworkStakeREST,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct NetworkSupplyREST {
+    #[serde_as(as = "DisplayFromStr")]
+    pub max: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub total: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub circulating: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub treasury: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub reserves: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub deposits: u64,
+}
+
+#[serde_as]
+#[derive(Serialize)]
+pub struct NetworkStakeREST {
+    #[serde_as(as = "DisplayFromStr")]
+    pub live: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub active: u64,
+    pub pools: u64,
+    pub delegators: u64,
+}
+
+impl From<NetworkStats> for NetworkREST {
+    fn from(stats: NetworkStats) -> Self {
+        Self {
+            supply: NetworkSupplyREST {
+                max: stats.max_supply,
+                total: stats.total_supply,
+                circulating: stats.circulating_supply,
+                treasury: stats.treasury,
+                reserves: stats.reserves,
+                deposits: stats.deposits,
+            },
+            stake: NetworkStakeREST {
+                live: stats.live_stake,
+                active: stats.active_stake,
+                pools: stats.pools,
+                delegators: stats.delegators,
+            },
+        }
+    }
+}
+
+// REST response structure for /network/eras
+// The current era has no end yet
+#[derive(Serialize)]
+pub struct NetworkEraREST {
+    pub start: NetworkEraBoundREST,
+    pub end: Option<NetworkEraBoundREST>,
+    pub parameters: NetworkEraParametersREST,
+}
+
+#[derive(Serialize)]
+pub struct NetworkEraBoundREST {
+    pub time: u64,
+    pub slot: u64,
+    pub epoch: u64,
+}
+
+#[derive(Serialize)]
+pub struct NetworkEraParametersREST {
+    pub epoch_length: u64,
+    pub slot_length: u64,
+    pub safe_zone: u64,
+}
+
+impl From<&EraBound> for NetworkEraBoundREST {
+    fn from(bound: &EraBound) -> Self {
+        Self {
+            time: bound.time.as_secs(),
+            slot: bound.slot,
+            epoch: bound.epoch,
+        }
+    }
+}
+
+impl From<&EraSummary> for NetworkEraREST {
+    fn from(era: &EraSummary) -> Self {
+        Self {
+            start: (&era.start).into(),
+            end: era.end.as_ref().map(NetworkEraBoundREST::from),
+            parameters: NetworkEraParametersREST {
+                epoch_length: era.params.epoch_size_slots,
+                slot_length: era.params.slot_length.as_secs(),
+                safe_zone: era.params.safe_zone,
+            },
+        }
+    }
+}
+
+// REST response structure for /scripts
+#[derive(Serialize)]
+pub struct ScriptListItemRest {
+    pub script_hash: String,
+}
+
+impl From<&ScriptHash> for ScriptListItemRest {
+    fn from(script_hash: &ScriptHash) -> Self {
+        Self {
+            script_hash: script_hash.to_string(),
+        }
+    }
+}
+
+// REST response structure for /scripts/{script_hash}
+#[derive(Serialize)]
+pub struct ScriptInfoRest {
+    pub script_hash: String,
+    #[serde(rename = "type")]
+    pub script_type: &'static str,
+    pub serialised_size: Option<u64>,
+}
+
+impl From<&ScriptInfo> for ScriptInfoRest {
+    fn from(info: &ScriptInfo) -> Self {
+        let script_type = match info.script_lang {
+            ScriptLang::Native => "timelock",
+            ScriptLang::Plutus(PlutusVersion::V1) => "plutusV1",
+            ScriptLang::Plutus(PlutusVersion::V2) => "plutusV2",
+            ScriptLang::Plutus(PlutusVersion::V3) => "plutusV3",
+        };
+        Self {
+            script_hash: info.script_hash.to_string(),
+            script_type,
+            serialised_size: info.serialised_size,
+        }
+    }
+}
+
+// REST response structure for /scripts/{script_hash}/json
+#[derive(Serialize)]
+pub struct ScriptJsonRest {
+    pub json: Option<Value>,
+}
+
+// REST response structure for /scripts/{script_hash}/cbor
+#[derive(Serialize)]
+pub struct ScriptCborRest {
+    pub cbor: Option<String>,
+}
+
+// REST response structure for /scripts/{script_hash}/redeemers
+#[derive(Serialize)]
+pub struct ScriptRedeemerRest {
+    pub tx_hash: String,
+    pub tx_index: u32,
+    pub purpose: &'static str,
+    pub redeemer_data_hash: String,
+    /// Deprecated in Blockfrost in favour of redeemer_data_hash
+    pub datum_hash: String,
+    pub unit_mem: String,
+    pub unit_steps: String,
+    pub fee: String,
+}
+
+impl ScriptRedeemerRest {
+    pub fn new(redeemer: &ScriptRedeemer, tx_hash: &TxHash, fee: Lovelace) -> Self {
+        let purpose = match redeemer.purpose {
+            RedeemerTag::Spend => "spend",
+            RedeemerTag::Mint => "mint",
+            RedeemerTag::Cert => "cert",
+            RedeemerTag::Reward => "reward",
+            RedeemerTag::Vote => "vote",
+            RedeemerTag::Propose => "propose",
+        };
+        Self {
+            tx_hash: tx_hash.to_string(),
+            tx_index: redeemer.tx_index,
+            purpose,
+            redeemer_data_hash: redeemer.redeemer_data_hash.to_string(),
+            datum_hash: redeemer.redeemer_data_hash.to_string(),
+            unit_mem: redeemer.ex_units.mem.to_string(),
+            unit_steps: redeemer.ex_units.steps.to_string(),
+            fee: fee.to_string(),
+        }
+    }
+}
+
+// REST response structure for /scripts/datum/{datum_hash}
+#[derive(Serialize)]
+pub struct ScriptDatumRest {
+    pub json_value: Value,
+}
+
+// REST response structure for /scripts/datum/{datum_hash}/cbor
+#[derive(Serialize)]
+pub struct ScriptDatumCborRest {
+    pub cbor: String,
+}
@@ -1,10 +1,12 @@
 use std::time::Duration;
 
-use acropolis_common::{rest_error::RESTError, AssetName, DataHash, PolicyId};
+use acropolis_common::{rest_error::RESTError, AssetName, DataHash, NativeScript, PolicyId};
 use anyhow::Result;
 use blake2::digest::{Update, VariableOutput};
+use minicbor::{data::Type, decode::Error as DecodeError, Decoder};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 #[derive(Serialize, Deserialize)]
 pub struct PoolMetadataJson {
@@ -107,6 +109,139 @@ pub fn split_policy_and_asset(hex_str: &str) -> Result<(PolicyId, AssetName), RE
     Ok((policy_id, asset_name))
 }
 
+/// Native script in the JSON form used by cardano-cli and Blockfrost
+pub fn native_script_json(script: &NativeScript) -> Value {
+    match script {
+        NativeScript::ScriptPubkey(key_hash) => json!({ "type": "sig", "keyHash": key_hash }),
+        NativeScript::ScriptAll(scripts) => json!({
+            "type": "all",
+            "scripts": scripts.iter().map(native_script_json).collect::<Vec<_>>(),
+        }),
+        NativeScript::ScriptAny(scripts) => json!({
+            "type": "any",
+            "scripts": scripts.iter().map(native_script_json).collect::<Vec<_>>(),
+        }),
+        NativeScript::ScriptNOfK(required, scripts) => json!({
+            "type": "atLeast",
+            "required": required,
+            "scripts": scripts.iter().map(native_script_json).collect::<Vec<_>>(),
+        }),
+        NativeScript::InvalidBefore(slot) => json!({ "type": "after", "slot": slot }),
+        NativeScript::InvalidHereafter(slot) => json!({ "type": "before", "slot": slot }),
+    }
+}
+
+/// Plutus data, from its CBOR, in the detailed JSON schema used by cardano-cli and Blockfrost
+pub fn plutus_data_json(cbor: &[u8]) -> Result<Value, DecodeError> {
+    decode_plutus_data(&mut Decoder::new(cbor))
+}
+
+fn decode_plutus_data(d: &mut Decoder) -> Result<Value, DecodeError> {
+    match d.datatype()? {
+        Type::Tag => {
+            let tag = d.tag()?.as_u64();
+            match tag {
+                // Big unsigned and negative integers
+                2 | 3 => {
+                    let bytes = decode_bytes(d)?;
+                    if bytes.len() > 16 {
+                        return Err(DecodeError::message("integer too large"));
+                    }
+                    let n = bytes.iter().fold(0u128, |n, b| (n << 8) | *b as u128);
+                    let n =
+                        i128::try_from(n).map_err(|_| DecodeError::message("integer too large"))?;
+                    let n = if tag == 2 { n } else { -1 - n };
+                    Ok(json!({ "int": int_json(n) }))
+                }
+                // Constructors 0-6 and 7-127 in the compact encoding
+                121..=127 => constr_json(tag - 121, d),
+                1280..=1400 => constr_json(tag - 1280 + 7, d),
+                // Any other constructor, as [constructor, fields]
+                102 => {
+                    d.array()?;
+                    let constructor = d.u64()?;
+                    constr_json(constructor, d)
+                }
+                _ => Err(DecodeError::message(format!(
+                    "unexpected tag {tag} in plutus data"
+                ))),
+            }
+        }
+        Type::Map | Type::MapIndef => {
+            let mut entries = Vec::new();
+            let mut decode_entry = |d: &mut Decoder| -> Result<(), DecodeError> {
+                let k = decode_plutus_data(d)?;
+                let v = decode_plutus_data(d)?;
+                entries.push(json!({ "k": k, "v": v }));
+                Ok(())
+            };
+            match d.map()? {
+                Some(len) => {
+                    for _ in 0..len {
+                        decode_entry(d)?;
+                    }
+                }
+                None => {
+                    while d.datatype()? != Type::Break {
+                        decode_entry(d)?;
+                    }
+                    d.skip()?; // Skip the break
+                }
+            }
+            Ok(json!({ "map": entries }))
+        }
+        Type::Array | Type::ArrayIndef => Ok(json!({ "list": decode_plutus_list(d)? })),
+        Type::Bytes | Type::BytesIndef => Ok(json!({ "bytes": hex::encode(decode_bytes(d)?) })),
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 => Ok(json!({ "int": d.u64()? })),
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 | Type::Int => {
+            let n = i128::from(d.int()?);
+            Ok(json!({ "int": int_json(n) }))
+        }
+        other => Err(DecodeError::type_mismatch(other)),
+    }
+}
+
+fn constr_json(constructor: u64, d: &mut Decoder) -> Result<Value, DecodeError> {
+    Ok(json!({ "constructor": constructor, "fields": decode_plutus_list(d)? }))
+}
+
+fn decode_plutus_list(d: &mut Decoder) -> Result<Vec<Value>, DecodeError> {
+    let mut items = Vec::new();
+    match d.array()? {
+        Some(len) => {
+            for _ in 0..len {
+                items.push(decode_plutus_data(d)?);
+            }
+        }
+        None => {
+            while d.datatype()? != Type::Break {
+                items.push(decode_plutus_data(d)?);
+            }
+            d.skip()?; // Skip the break
+        }
+    }
+    Ok(items)
+}
+
+fn decode_bytes(d: &mut Decoder) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes = Vec::new();
+    for chunk in d.bytes_iter()? {
+        bytes.extend_from_slice(chunk?);
+    }
+    Ok(bytes)
+}
+
+/// An integer as a JSON number if it fits, otherwise as a string
+fn int_json(n: i128) -> Value {
+    if let Ok(n) = i64::try_from(n) {
+        json!(n)
+    } else if let Ok(n) = u64::try_from(n) {
+        json!(n)
+    } else {
+        json!(n.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -219,4 +354,53 @@ mod tests {
         assert_eq!(policy, policy_id());
         assert_eq!(name.as_slice(), b"MyToken");
     }
+
+    #[test]
+    fn native_script_to_json() {
+        let script = NativeScript::ScriptNOfK(
+            1,
+            vec![
+                NativeScript::InvalidBefore(10),
+                NativeScript::ScriptAll(vec![NativeScript::InvalidHereafter(20)]),
+            ],
+        );
+        assert_eq!(
+            native_script_json(&script),
+            json!({
+                "type": "atLeast",
+                "required": 1,
+                "scripts": [
+                    { "type": "after", "slot": 10 },
+                    { "type": "all", "scripts": [{ "type": "before", "slot": 20 }] },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn plutus_data_to_json() {
+        // Constructor 0 with an indefinite list of fields: 42, h'ab', -1, {1: []}
+        let cbor = hex::decode("d8799f182a41ab20a10180ff").unwrap();
+        assert_eq!(
+            plutus_data_json(&cbor).unwrap(),
+            json!({
+                "constructor": 0,
+                "fields": [
+                    { "int": 42 },
+                    { "bytes": "ab" },
+                    { "int": -1 },
+                    { "map": [{ "k": { "int": 1 }, "v": { "list": [] } }] },
+                ],
+            })
+        );
+
+        // Constructor 7, and a big integer
+        let cbor = hex::decode("d905009fc249010000000000000000ff").unwrap();
+        assert_eq!(
+            plutus_data_json(&cbor).unwrap(),
+            json!({ "constructor": 7, "fields": [{ "int": "18446744073709551616" }] })
+        );
+
+        assert!(plutus_data_json(&hex::decode("f5").unwrap()).is_err());
+    }
 }
@@ -0,0 +1,354 @@
+//! Worker pools bounding how many REST requests are handled at once, so that slow
+//! aggregate endpoints can't hold up the cheap ones.
+//!
+//! Every route belongs to a pool - the epoch-level aggregations to `aggregate` and
+//! everything else to `default`, unless a pool lists the route's topic under
+//! `routes`. A pool handles up to `max-concurrent` requests at once and queues up
+//! to `max-queued` more; past that, requests are refused with a 503.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::Instant,
+};
+
+use acropolis_common::{
+    messages::{RESTResponse, WorkerMetricsMessage, WorkerPoolMetrics},
+    rest_error::RESTError,
+};
+use anyhow::{bail, Result};
+use config::{Config, ConfigError};
+use tokio::sync::Semaphore;
+use tracing::info;
+
+/// Pool for routes not otherwise assigned
+pub const DEFAULT_POOL: &str = "default";
+
+/// Pool for the epoch-level aggregations, which are cached
+pub const AGGREGATE_POOL: &str = "aggregate";
+
+const WORKER_POOLS_KEY: &str = "worker-pools";
+
+/// Pools of this module, set up in `init`
+static POOLS: OnceLock<WorkerPools> = OnceLock::new();
+
+/// A `[module.rest-blockfrost.worker-pools.<name>]` section
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WorkerPoolConfig {
+    /// Requests handled at once
+    pub max_concurrent: usize,
+
+    /// Requests waiting for a worker before more are refused
+    pub max_queued: usize,
+
+    /// Handler topics of the routes in the pool, e.g. "rest.get.accounts.*.utxos"
+    pub routes: Vec<String>,
+}
+
+impl Default for WorkerPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 16,
+            max_queued: 256,
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// The pools as configured, with `default` and `aggregate` added if not given
+pub fn read_config(config: &Config) -> Result<BTreeMap<String, WorkerPoolConfig>> {
+    let mut pools: BTreeMap<String, WorkerPoolConfig> = match config.get(WORKER_POOLS_KEY) {
+        Ok(pools) => pools,
+        Err(ConfigError::NotFound(_)) => BTreeMap::new(),
+        Err(e) => bail!("invalid {WORKER_POOLS_KEY}: {e}"),
+    };
+    pools.entry(DEFAULT_POOL.to_string()).or_insert(WorkerPoolConfig {
+        max_concurrent: 64,
+        max_queued: 1024,
+        routes: Vec::new(),
+    });
+    pools.entry(AGGREGATE_POOL.to_string()).or_insert(WorkerPoolConfig {
+        max_concurrent: 4,
+        max_queued: 64,
+        routes: Vec::new(),
+    });
+
+    for (name, pool) in &pools {
+        if pool.max_concurrent == 0 {
+            bail!("worker pool '{name}' needs max-concurrent of at least 1");
+        }
+    }
+    Ok(pools)
+}
+
+/// Set up the pools - only the first call has any effect
+pub fn init(pools: BTreeMap<String, WorkerPoolConfig>) {
+    for (name, pool) in &pools {
+        info!(
+            "REST worker pool '{name}': {} at once, {} queued",
+            pool.max_concurrent, pool.max_queued
+        );
+    }
+    let _ = POOLS.set(WorkerPools::new(pools));
+}
+
+/// Pool a route is handled in - `default_pool` unless its topic is listed in one
+pub fn pool_for(topic: &str, default_pool: &str) -> Arc<WorkerPool> {
+    POOLS.get_or_init(|| WorkerPools::new(BTreeMap::new())).pool_for(topic, default_pool)
+}
+
+/// Load on every pool
+pub fn metrics(module: &str) -> WorkerMetricsMessage {
+    let pools = POOLS.get().map(WorkerPools::metrics).unwrap_or_default();
+    WorkerMetricsMessage {
+        module: module.to_string(),
+        pools,
+    }
+}
+
+struct WorkerPools {
+    pools: BTreeMap<String, Arc<WorkerPool>>,
+
+    /// Pool of each listed route
+    routes: HashMap<String, String>,
+}
+
+impl WorkerPools {
+    fn new(configs: BTreeMap<String, WorkerPoolConfig>) -> Self {
+        let mut pools = BTreeMap::new();
+        let mut routes = HashMap::new();
+        for (name, config) in configs {
+            for route in &config.routes {
+                routes.insert(route.clone(), name.clone());
+            }
+            pools.insert(name.clone(), Arc::new(WorkerPool::new(&name, &config)));
+        }
+        Self { pools, routes }
+    }
+
+    fn pool_for(&self, topic: &str, default_pool: &str) -> Arc<WorkerPool> {
+        let name = self.routes.get(topic).map(String::as_str).unwrap_or(default_pool);
+        self.pools
+            .get(name)
+            .or_else(|| self.pools.get(DEFAULT_POOL))
+            .cloned()
+            // Pools weren't set up - handle requests without a limit
+            .unwrap_or_else(|| {
+                Arc::new(WorkerPool::new(
+                    DEFAULT_POOL,
+                    &WorkerPoolConfig {
+                        max_concurrent: Semaphore::MAX_PERMITS,
+                        max_queued: 0,
+                        routes: Vec::new(),
+                    },
+                ))
+            })
+    }
+
+    fn metrics(&self) -> Vec<WorkerPoolMetrics> {
+        self.pools.values().map(|pool| pool.metrics()).collect()
+    }
+}
+
+/// Requests handled at once in one pool
+pub struct WorkerPool {
+    name: String,
+    workers: Semaphore,
+    max_concurrent: usize,
+    max_queued: usize,
+
+    in_flight: AtomicU64,
+    queued: AtomicU64,
+    peak_queued: AtomicU64,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    wait_micros: AtomicU64,
+}
+
+impl WorkerPool {
+    fn new(name: &str, config: &WorkerPoolConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            workers: Semaphore::new(config.max_concurrent),
+            max_concurrent: config.max_concurrent,
+            max_queued: config.max_queued,
+            in_flight: AtomicU64::new(0),
+            queued: AtomicU64::new(0),
+            peak_queued: AtomicU64::new(0),
+            completed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            wait_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Handle a request once a worker is free, or refuse it if too many are waiting
+    pub async fn run(
+        &self,
+        request: impl Future<Output = Result<RESTResponse, RESTError>>,
+    ) -> Result<RESTResponse, RESTError> {
+        let permit = match self.workers.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let (place, queued) = Held::take(&self.queued);
+                if queued > self.max_queued as u64 {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Err(RESTError::ServiceUnavailable(format!(
+                        "Too many requests waiting for the '{}' workers, try again later",
+                        self.name
+                    )));
+                }
+                self.peak_queued.fetch_max(queued, Ordering::Relaxed);
+
+                let waiting = Instant::now();
+                let permit = self.workers.acquire().await.expect("worker pool is never closed");
+                self.wait_micros.fetch_add(waiting.elapsed().as_micros() as u64, Ordering::Relaxed);
+                drop(place);
+                permit
+            }
+        };
+
+        let (in_flight, _) = Held::take(&self.in_flight);
+        let response = request.await;
+        drop(in_flight);
+        drop(permit);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        response
+    }
+
+    fn metrics(&self) -> WorkerPoolMetrics {
+        WorkerPoolMetrics {
+            pool: self.name.clone(),
+            max_concurrent: self.max_concurrent,
+            max_queued: self.max_queued,
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+            peak_queued: self.peak_queued.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            wait_seconds: self.wait_micros.load(Ordering::Relaxed) as f64 / 1e6,
+        }
+    }
+}
+
+/// A count of queued or in-flight requests, taken back when dropped - also if the
+/// request is abandoned part way
+struct Held<'a>(&'a AtomicU64);
+
+impl<'a> Held<'a> {
+    /// Count one more, returning the new count
+    fn take(counter: &'a AtomicU64) -> (Self, u64) {
+        let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        (Self(counter), count)
+    }
+}
+
+impl Drop for Held<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    fn pool(max_concurrent: usize, max_queued: usize) -> Arc<WorkerPool> {
+        Arc::new(WorkerPool::new(
+            "test",
+            &WorkerPoolConfig {
+                max_concurrent,
+                max_queued,
+                routes: Vec::new(),
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn requests_queue_then_are_refused_when_the_queue_is_full() {
+        let pool = pool(1, 1);
+
+        // Hold the only worker until released
+        let (release, released) = oneshot::channel::<()>();
+        let busy = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(async {
+                    released.await.ok();
+                    Ok(RESTResponse::with_json(200, "slow"))
+                })
+                .await
+            }
+        });
+        while pool.metrics().in_flight == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(async { Ok(RESTResponse::with_json(200, "cheap")) }).await }
+        });
+        while pool.metrics().queued == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let refused = pool.run(async { Ok(RESTResponse::with_json(200, "refused")) }).await;
+        assert!(matches!(refused, Err(RESTError::ServiceUnavailable(_))));
+
+        release.send(()).unwrap();
+        assert_eq!(busy.await.unwrap().unwrap().body, "slow");
+        assert_eq!(waiting.await.unwrap().unwrap().body, "cheap");
+
+        let metrics = pool.metrics();
+        assert_eq!((metrics.in_flight, metrics.queued), (0, 0));
+        assert_eq!(
+            (metrics.peak_queued, metrics.completed, metrics.rejected),
+            (1, 2, 1)
+        );
+    }
+
+    #[test]
+    fn listed_routes_override_the_default_pool() {
+        let config = Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [worker-pools.default]
+                max-concurrent = 8
+
+                [worker-pools.slow]
+                max-concurrent = 2
+                routes = ["rest.get.accounts.*.utxos"]
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap();
+        let configs = read_config(&config).unwrap();
+        assert_eq!(configs["default"].max_concurrent, 8);
+        assert_eq!(configs["default"].max_queued, 256);
+        assert_eq!(configs["aggregate"].max_concurrent, 4);
+
+        let pools = WorkerPools::new(configs);
+        assert_eq!(
+            pools.pool_for("rest.get.accounts.*.utxos", DEFAULT_POOL).name,
+            "slow"
+        );
+        assert_eq!(
+            pools.pool_for("rest.get.pools", AGGREGATE_POOL).name,
+            "aggregate"
+        );
+        assert_eq!(
+            pools.pool_for("rest.get.blocks.*", DEFAULT_POOL).name,
+            "default"
+        );
+        assert_eq!(
+            pools.pool_for("rest.get.blocks.*", "unknown").name,
+            "default"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Acropolis Scripts State module for Caryatid
+//! Accepts UTxO delta events and records every native and Plutus script seen on
+//! chain, with the redeemers run against it and the datums it was given
+
+use crate::state::{ScriptsStorageConfig, State};
+use acropolis_common::{
+    caryatid::{PrimaryRead, RollbackWrapper},
+    configuration::{get_bool_flag, get_string_flag},
+    declare_cardano_reader,
+    messages::{
+        CardanoMessage, Message, StateQuery, StateQueryResponse, StateTransitionMessage,
+        UTXODeltasMessage,
+    },
+    metrics,
+    queries::{
+        errors::QueryError,
+        scripts::{
+            ScriptCBOR, ScriptDatum, ScriptJSON, ScriptsStateQuery, ScriptsStateQueryResponse,
+            DEFAULT_SCRIPTS_QUERY_TOPIC,
+        },
+    },
+    readiness,
+    state_history::{StateHistory, StateHistoryStore},
+    utxo_batches::UTXODeltasAssembler,
+    ReferenceScript, ScriptHash,
+};
+use anyhow::{bail, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{error, info, info_span, Instrument};
+mod state;
+
+// Subscription topics
+declare_cardano_reader!(
+    UTxODeltasReader,
+    "utxo-deltas-subscribe-topic",
+    "cardano.utxo.deltas",
+    UTXODeltas,
+    UTXODeltasMessage,
+    UTXODeltasBatch => UTXODeltasAssembler
+);
+
+// Configuration defaults
+const DEFAULT_STORE_REDEEMERS: (&str, bool) = ("store-redeemers", false);
+const DEFAULT_STORE_DATUMS: (&str, bool) = ("store-datums", false);
+
+/// Scripts State module
+#[module(
+    message_type(Message),
+    name = "scripts-state",
+    description = "In-memory Scripts State from UTxO delta events"
+)]
+pub struct ScriptsState;
+
+impl ScriptsState {
+    async fn run(
+        history: Arc<Mutex<StateHistory<State>>>,
+        mut utxo_deltas_reader: UTxODeltasReader,
+        storage_config: ScriptsStorageConfig,
+    ) -> Result<()> {
+        loop {
+            // Get current state snapshot
+            let mut state = {
+                let mut h = history.lock().await;
+                h.get_or_init_with(|| State::new(storage_config))
+            };
+
+            let primary = PrimaryRead::from_read(utxo_deltas_reader.read_with_rollbacks().await?);
+
+            if primary.is_rollback() {
+                state = history.lock().await.get_rolled_back_state(primary.block_info().number);
+            }
+
+            if let Some(deltas_msg) = primary.message() {
+                let block_info = primary.block_info();
+                let span = info_span!(
+                    "scripts_state.handle_utxo_deltas",
+                    block = block_info.number
+                );
+                span.in_scope(|| state.handle_utxo_deltas(block_info.epoch, &deltas_msg.deltas));
+
+                let mut h = history.lock().await;
+                h.commit(block_info.number, state);
+            }
+        }
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "scripts-state").await?;
+
+        // Get configuration flags and topics
+        let storage_config = ScriptsStorageConfig {
+            store_redeemers: get_bool_flag(&config, DEFAULT_STORE_REDEEMERS),
+            store_datums: get_bool_flag(&config, DEFAULT_STORE_DATUMS),
+        };
+
+        let scripts_query_topic = get_string_flag(&config, DEFAULT_SCRIPTS_QUERY_TOPIC);
+        info!("Creating scripts query handler on '{scripts_query_topic}'");
+
+        // Initialize state history
+        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
+            "ScriptsState",
+            StateHistoryStore::default_block_store(),
+        )));
+        let history_run = history.clone();
+        let query_history = history.clone();
+        let tick_history = history.clone();
+
+        // Query handler
+        let query_metrics = metrics::QueryMetrics::new("scripts-state");
+        context.handle(&scripts_query_topic, move |message| {
+            let history = query_history.clone();
+            let timer = query_metrics.start(&message);
+            async move {
+                let _timer = timer;
+                let Message::StateQuery(StateQuery::Scripts(query)) = message.as_ref() else {
+                    return Arc::new(Message::StateQueryResponse(StateQueryResponse::Scripts(
+                        ScriptsStateQueryResponse::Error(QueryError::internal_error(
+                            "Invalid message for scripts-state",
+                        )),
+                    )));
+                };
+
+                let state = {
+                    let h = history.lock().await;
+                    h.get_current_state()
+                };
+
+                let not_found = |script_hash: &ScriptHash| {
+                    QueryError::not_found(format!("Script {script_hash}"))
+                };
+
+                let response = match query {
+                    ScriptsStateQuery::GetScriptsList => {
+                        ScriptsStateQueryResponse::ScriptsList(state.get_scripts_list())
+                    }
+                    ScriptsStateQuery::GetScriptInfo { script_hash } => {
+                        match state.get_script_info(script_hash) {
+                            Some(info) => ScriptsStateQueryResponse::ScriptInfo(info),
+                            None => ScriptsStateQueryResponse::Error(not_found(script_hash)),
+                        }
+                    }
+                    ScriptsStateQuery::GetScriptJSON { script_hash } => {
+                        match state.get_script(script_hash) {
+                            Some(ReferenceScript::Native(native)) => {
+                                ScriptsStateQueryResponse::ScriptJSON(ScriptJSON {
+                                    native: Some(native.clone()),
+                                })
+                            }
+                            Some(_) => {
+                                ScriptsStateQueryResponse::ScriptJSON(ScriptJSON { native: None })
+                            }
+                            None => ScriptsStateQueryResponse::Error(not_found(script_hash)),
+                        }
+                    }
+                    ScriptsStateQuery::GetScriptCBOR { script_hash } => {
+                        match state.get_script(script_hash) {
+                            Some(ReferenceScript::Native(_)) => {
+                                ScriptsStateQueryResponse::ScriptCBOR(ScriptCBOR { cbor: None })
+                            }
+                            Some(
+                                ReferenceScript::PlutusV1(bytes)
+                                | ReferenceScript::PlutusV2(bytes)
+                                | ReferenceScript::PlutusV3(bytes),
+                            ) => ScriptsStateQueryResponse::ScriptCBOR(ScriptCBOR {
+                                cbor: Some(bytes.clone()),
+                            }),
+                            None => ScriptsStateQueryResponse::Error(not_found(script_hash)),
+                        }
+                    }
+                    ScriptsStateQuery::GetScriptRedeemers { script_hash } => {
+                        if !state.config.store_redeemers {
+                            ScriptsStateQueryResponse::Error(QueryError::storage_disabled(
+                                "script redeemers",
+                            ))
+                        } else {
+                            match state.get_script_redeemers(script_hash) {
+                                Some(redeemers) => {
+                                    ScriptsStateQueryResponse::ScriptRedeemers(redeemers)
+                                }
+                                None => ScriptsStateQueryResponse::Error(not_found(script_hash)),
+                            }
+                        }
+                    }
+                    ScriptsStateQuery::GetTransactionRedeemers { tx_identifier } => {
+                        if !state.config.store_redeemers {
+                            ScriptsStateQueryResponse::Error(QueryError::storage_disabled(
+                                "transaction redeemers",
+                            ))
+                        } else {
+                            match state.get_transaction_redeemers(tx_identifier) {
+                                Some(redeemers) => ScriptsStateQueryResponse::TransactionRedeemers(
+                                    redeemers.clone(),
+                                ),
+                                None => ScriptsStateQueryResponse::Error(QueryError::not_found(
+                                    "Transaction redeemers",
+                                )),
+                            }
+                        }
+                    }
+                    ScriptsStateQuery::GetScriptDatum { datum_hash } => {
+                        if !state.config.store_datums {
+                            ScriptsStateQueryResponse::Error(QueryError::storage_disabled("datums"))
+                        } else {
+                            match state.get_datum(datum_hash) {
+                                Some(cbor) => ScriptsStateQueryResponse::ScriptDatum(ScriptDatum {
+                                    cbor: cbor.clone(),
+                                }),
+                                None => ScriptsStateQueryResponse::Error(QueryError::not_found(
+                                    format!("Datum {datum_hash}"),
+                                )),
+                            }
+                        }
+                    }
+                };
+                Arc::new(Message::StateQueryResponse(StateQueryResponse::Scripts(
+                    response,
+                )))
+            }
+        });
+
+        // Ticker to log stats
+        let mut subscription = context.subscribe("clock.tick").await?;
+        context.run(async move {
+            loop {
+                let Ok((_, message)) = subscription.read().await else {
+                    return;
+                };
+                if let Message::Clock(message) = message.as_ref() {
+                    if message.number % 60 == 0 {
+                        let span = info_span!("scripts_state.tick", number = message.number);
+                        async {
+                            let guard = tick_history.lock().await;
+                            if let Some(state) = guard.current() {
+                                state.tick();
+                            } else {
+                                info!("no state yet");
+                            }
+                        }
+                        .instrument(span)
+                        .await;
+                    }
+                }
+            }
+        });
+
+        // Subscribe to UTxO deltas
+        let utxo_deltas_reader = UTxODeltasReader::new(&context, &config).await?;
+
+        // Start run task
+        context.run(async move {
+            Self::run(history_run, utxo_deltas_reader, storage_config)
+                .await
+                .unwrap_or_else(|e| error!("Failed: {e}"));
+        });
+
+        Ok(())
+    }
+}
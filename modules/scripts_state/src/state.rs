@@ -0,0 +1,398 @@
+//! Acropolis scripts state storage
+
+use std::collections::HashMap as StdHashMap;
+
+use acropolis_common::{
+    crypto::keyhash_256,
+    get_scripts_needed_from_certificates, get_scripts_needed_from_mint_burn,
+    get_scripts_needed_from_proposal, get_scripts_needed_from_voting,
+    get_scripts_needed_from_withdrawals,
+    queries::{
+        scripts::{ScriptInfo, ScriptRedeemer, ScriptRedeemerCounts},
+        transactions::{TransactionRedeemer, TransactionRedeemers},
+    },
+    Datum, DatumHash, RedeemerPointer, RedeemerTag, ReferenceScript, ScriptHash, TxIdentifier,
+    TxUTxODeltas, UTxOIdentifier,
+};
+use imbl::{HashMap, Vector};
+use tracing::info;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScriptsStorageConfig {
+    pub store_redeemers: bool,
+    pub store_datums: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScriptRecord {
+    script: ReferenceScript,
+    first_seen: TxIdentifier,
+    redeemer_counts: ScriptRedeemerCounts,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct State {
+    pub config: ScriptsStorageConfig,
+
+    /// Every script seen, as a witness or a reference script
+    scripts: HashMap<ScriptHash, ScriptRecord>,
+
+    /// Unspent outputs locked by a script, to tell which script a spend redeemer is for
+    script_utxos: HashMap<UTxOIdentifier, ScriptHash>,
+
+    /// Redeemers run against each script, in chain order
+    redeemers: HashMap<ScriptHash, Vector<ScriptRedeemer>>,
+
+    /// Redeemers of each transaction which had any, failed or not
+    tx_redeemers: HashMap<TxIdentifier, TransactionRedeemers>,
+
+    /// Datums from witness sets and inline datums
+    datums: HashMap<DatumHash, Vec<u8>>,
+}
+
+impl State {
+    pub fn new(config: ScriptsStorageConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn tick(&self) {
+        info!(
+            scripts = self.scripts.len(),
+            script_utxos = self.script_utxos.len(),
+            datums = self.datums.len(),
+            "Scripts state"
+        );
+    }
+
+    pub fn handle_utxo_deltas(&mut self, epoch: u64, deltas: &[TxUTxODeltas]) {
+        for tx in deltas {
+            self.handle_tx(epoch, tx);
+        }
+    }
+
+    fn handle_tx(&mut self, epoch: u64, tx: &TxUTxODeltas) {
+        let provided = tx.script_witnesses.iter().flatten();
+        let created = tx.created_reference_scripts.iter().flatten();
+        for (script_hash, script) in provided.chain(created) {
+            self.scripts.entry(*script_hash).or_insert_with(|| ScriptRecord {
+                script: script.clone(),
+                first_seen: tx.tx_identifier,
+                redeemer_counts: ScriptRedeemerCounts::default(),
+            });
+        }
+
+        if let Some(redeemers) = tx.redeemers.as_ref().filter(|r| !r.is_empty()) {
+            let scripts_needed = self.scripts_needed(tx);
+            let mut tx_redeemers = Vec::with_capacity(redeemers.len());
+            for redeemer in redeemers {
+                let script_hash = scripts_needed.get(&redeemer.redeemer_pointer()).copied();
+                let redeemer_data_hash = keyhash_256(&redeemer.data);
+                tx_redeemers.push(TransactionRedeemer {
+                    tx_index: redeemer.index,
+                    purpose: redeemer.tag.clone(),
+                    script_hash,
+                    redeemer_data_hash,
+                    ex_units: redeemer.ex_units,
+                });
+
+                // Failed transactions only take their collateral, so their redeemers
+                // never ran to completion and don't count as uses of the script
+                if !tx.is_valid {
+                    continue;
+                }
+                let Some(script_hash) = script_hash else {
+                    continue;
+                };
+                let Some(record) = self.scripts.get_mut(&script_hash) else {
+                    continue;
+                };
+                record.redeemer_counts.add(&redeemer.tag);
+
+                if self.config.store_redeemers {
+                    self.redeemers.entry(script_hash).or_default().push_back(ScriptRedeemer {
+                        tx_identifier: tx.tx_identifier,
+                        epoch,
+                        tx_index: redeemer.index,
+                        purpose: redeemer.tag.clone(),
+                        redeemer_data_hash,
+                        ex_units: redeemer.ex_units,
+                    });
+                }
+            }
+
+            if self.config.store_redeemers {
+                self.tx_redeemers.insert(
+                    tx.tx_identifier,
+                    TransactionRedeemers {
+                        epoch,
+                        redeemers: tx_redeemers,
+                    },
+                );
+            }
+        }
+
+        for input in &tx.consumes {
+            self.script_utxos.remove(input);
+        }
+        for output in &tx.produces {
+            if let Some(script_hash) =
+                output.address.get_payment_part().and_then(|part| part.to_script_hash())
+            {
+                self.script_utxos.insert(output.utxo_identifier, script_hash);
+            }
+        }
+
+        if self.config.store_datums {
+            for (datum_hash, datum) in tx.plutus_data.iter().flatten() {
+                self.datums.insert(*datum_hash, datum.clone());
+            }
+            for output in &tx.produces {
+                if let Some(Datum::Inline(datum)) = &output.datum {
+                    self.datums.insert(keyhash_256(datum), datum.clone());
+                }
+            }
+        }
+    }
+
+    /// Scripts each redeemer of a transaction is for
+    /// NOTE:
+    /// Inputs are sorted, and spend redeemers for inputs locked before this
+    /// module started can't be resolved
+    fn scripts_needed(&self, tx: &TxUTxODeltas) -> StdHashMap<RedeemerPointer, ScriptHash> {
+        let mut scripts_needed = StdHashMap::new();
+
+        // A failed transaction consumes its collateral, not the inputs its spend
+        // redeemers point at
+        if tx.is_valid {
+            for (index, input) in tx.consumes.iter().enumerate() {
+                if let Some(script_hash) = self.script_utxos.get(input) {
+                    scripts_needed.insert(
+                        RedeemerPointer {
+                            tag: RedeemerTag::Spend,
+                            index: index as u32,
+                        },
+                        *script_hash,
+                    );
+                }
+            }
+        }
+        if let Some(certs) = tx.certs.as_ref() {
+            scripts_needed.extend(get_scripts_needed_from_certificates(certs));
+        }
+        if let Some(withdrawals) = tx.withdrawals.as_ref() {
+            scripts_needed.extend(get_scripts_needed_from_withdrawals(withdrawals));
+        }
+        if let Some(mint_burn) = tx.mint_burn_deltas.as_ref() {
+            scripts_needed.extend(get_scripts_needed_from_mint_burn(mint_burn));
+        }
+        if let Some(voting_procedures) = tx.voting_procedures.as_ref() {
+            scripts_needed.extend(get_scripts_needed_from_voting(voting_procedures));
+        }
+        if let Some(proposal_procedures) = tx.proposal_procedures.as_ref() {
+            scripts_needed.extend(get_scripts_needed_from_proposal(proposal_procedures));
+        }
+
+        scripts_needed
+    }
+
+    /// All scripts, in the order they were first seen
+    pub fn get_scripts_list(&self) -> Vec<ScriptHash> {
+        let mut scripts: Vec<_> = self
+            .scripts
+            .iter()
+            .map(|(hash, record)| {
+                let first_seen = &record.first_seen;
+                ((first_seen.block_number(), first_seen.tx_index()), *hash)
+            })
+            .collect();
+        scripts.sort();
+        scripts.into_iter().map(|(_, hash)| hash).collect()
+    }
+
+    pub fn get_script_info(&self, script_hash: &ScriptHash) -> Option<ScriptInfo> {
+        let record = self.scripts.get(script_hash)?;
+        let serialised_size = match &record.script {
+            ReferenceScript::Native(_) => None,
+            ReferenceScript::PlutusV1(bytes)
+            | ReferenceScript::PlutusV2(bytes)
+            | ReferenceScript::PlutusV3(bytes) => Some(bytes.len() as u64),
+        };
+        Some(ScriptInfo {
+            script_hash: *script_hash,
+            script_lang: record.script.get_script_lang(),
+            serialised_size,
+            first_seen: record.first_seen,
+            redeemer_counts: record.redeemer_counts.clone(),
+        })
+    }
+
+    pub fn get_script(&self, script_hash: &ScriptHash) -> Option<&ReferenceScript> {
+        self.scripts.get(script_hash).map(|record| &record.script)
+    }
+
+    pub fn get_script_redeemers(&self, script_hash: &ScriptHash) -> Option<Vec<ScriptRedeemer>> {
+        if !self.scripts.contains_key(script_hash) {
+            return None;
+        }
+        Some(
+            self.redeemers
+                .get(script_hash)
+                .map(|redeemers| redeemers.iter().cloned().collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Redeemers of a transaction, if it had any
+    pub fn get_transaction_redeemers(
+        &self,
+        tx_identifier: &TxIdentifier,
+    ) -> Option<&TransactionRedeemers> {
+        self.tx_redeemers.get(tx_identifier)
+    }
+
+    pub fn get_datum(&self, datum_hash: &DatumHash) -> Option<&Vec<u8>> {
+        self.datums.get(datum_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{
+        Address, ExUnits, NativeScript, Redeemer, ShelleyAddress, ShelleyAddressDelegationPart,
+        ShelleyAddressPaymentPart, TxHash, TxOutput, Value,
+    };
+
+    fn native_script() -> (ScriptHash, ReferenceScript) {
+        let script = ReferenceScript::Native(NativeScript::InvalidBefore(42));
+        (script.compute_hash(), script)
+    }
+
+    fn plutus_script() -> (ScriptHash, ReferenceScript) {
+        let script = ReferenceScript::PlutusV2(vec![1, 2, 3, 4]);
+        (script.compute_hash(), script)
+    }
+
+    fn tx(block: u32) -> TxUTxODeltas {
+        TxUTxODeltas {
+            tx_identifier: TxIdentifier::new(block, 0),
+            is_valid: true,
+            ..Default::default()
+        }
+    }
+
+    fn script_output(utxo_identifier: UTxOIdentifier, script_hash: ScriptHash) -> TxOutput {
+        TxOutput {
+            utxo_identifier,
+            address: Address::Shelley(ShelleyAddress {
+                network: Default::default(),
+                payment: ShelleyAddressPaymentPart::ScriptHash(script_hash),
+                delegation: ShelleyAddressDelegationPart::None,
+            }),
+            value: Value::new(2_000_000, Vec::new()),
+            datum: Some(Datum::Inline(vec![0x42])),
+            script_ref: None,
+        }
+    }
+
+    fn spend_redeemer(index: u32) -> Redeemer {
+        Redeemer {
+            tag: RedeemerTag::Spend,
+            index,
+            data: vec![0x80],
+            ex_units: ExUnits {
+                mem: 100,
+                steps: 200,
+            },
+        }
+    }
+
+    fn state() -> State {
+        State::new(ScriptsStorageConfig {
+            store_redeemers: true,
+            store_datums: true,
+        })
+    }
+
+    #[test]
+    fn scripts_are_recorded_when_first_seen() {
+        let mut state = state();
+        let (native_hash, native) = native_script();
+        let (plutus_hash, plutus) = plutus_script();
+
+        let mut first = tx(1);
+        first.created_reference_scripts = Some(vec![(plutus_hash, plutus.clone())]);
+        let mut second = tx(2);
+        second.script_witnesses = Some(vec![(native_hash, native), (plutus_hash, plutus)]);
+        state.handle_utxo_deltas(0, &[first, second]);
+
+        assert_eq!(state.get_scripts_list(), vec![plutus_hash, native_hash]);
+        let info = state.get_script_info(&plutus_hash).unwrap();
+        assert_eq!(info.first_seen, TxIdentifier::new(1, 0));
+        assert_eq!(info.serialised_size, Some(4));
+        assert_eq!(
+            state.get_script_info(&native_hash).unwrap().serialised_size,
+            None
+        );
+    }
+
+    #[test]
+    fn spend_redeemers_are_attributed_to_the_locking_script() {
+        let mut state = state();
+        let (plutus_hash, plutus) = plutus_script();
+        let other = UTxOIdentifier::new(TxHash::new([1; 32]), 0);
+        let locked = UTxOIdentifier::new(TxHash::new([2; 32]), 0);
+
+        let mut lock = tx(1);
+        lock.produces = vec![script_output(locked, plutus_hash)];
+        let mut spend = tx(2);
+        spend.consumes = vec![other, locked];
+        spend.script_witnesses = Some(vec![(plutus_hash, plutus)]);
+        spend.redeemers = Some(vec![spend_redeemer(1)]);
+        state.handle_utxo_deltas(3, &[lock, spend]);
+
+        let info = state.get_script_info(&plutus_hash).unwrap();
+        assert_eq!(info.redeemer_counts.spend, 1);
+        assert_eq!(info.redeemer_counts.total(), 1);
+        let redeemers = state.get_script_redeemers(&plutus_hash).unwrap();
+        assert_eq!(redeemers.len(), 1);
+        assert_eq!(redeemers[0].epoch, 3);
+        assert_eq!(redeemers[0].redeemer_data_hash, keyhash_256(&[0x80]));
+        assert!(state.script_utxos.is_empty());
+        assert_eq!(state.get_datum(&keyhash_256(&[0x42])), Some(&vec![0x42]));
+
+        let tx_redeemers = state.get_transaction_redeemers(&TxIdentifier::new(2, 0)).unwrap();
+        assert_eq!(tx_redeemers.epoch, 3);
+        assert_eq!(tx_redeemers.redeemers.len(), 1);
+        assert_eq!(tx_redeemers.redeemers[0].script_hash, Some(plutus_hash));
+        assert!(state.get_transaction_redeemers(&TxIdentifier::new(1, 0)).is_none());
+    }
+
+    #[test]
+    fn failed_transactions_do_not_count_redeemers() {
+        let mut state = state();
+        let (plutus_hash, plutus) = plutus_script();
+        let locked = UTxOIdentifier::new(TxHash::new([2; 32]), 0);
+
+        let mut lock = tx(1);
+        lock.produces = vec![script_output(locked, plutus_hash)];
+        lock.script_witnesses = Some(vec![(plutus_hash, plutus)]);
+        let mut spend = tx(2);
+        spend.is_valid = false;
+        spend.consumes = vec![locked];
+        spend.redeemers = Some(vec![spend_redeemer(0)]);
+        state.handle_utxo_deltas(0, &[lock, spend]);
+
+        let info = state.get_script_info(&plutus_hash).unwrap();
+        assert_eq!(info.redeemer_counts.total(), 0);
+        assert_eq!(state.get_script_redeemers(&plutus_hash), Some(Vec::new()));
+
+        // Still listed for the transaction, without the script its input was locked by
+        let tx_redeemers = state.get_transaction_redeemers(&TxIdentifier::new(2, 0)).unwrap();
+        assert_eq!(tx_redeemers.redeemers.len(), 1);
+        assert_eq!(tx_redeemers.redeemers[0].script_hash, None);
+    }
+}
@@ -0,0 +1,57 @@
+use std::net::SocketAddr;
+
+use acropolis_common::{
+    configuration::ModuleConfig,
+    sidechain::{validate_rules, ObservationRule},
+};
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SidechainObserverConfig {
+    /// Extended address deltas, from utxo-state with address-delta-publish-mode = "extended"
+    pub address_deltas_topic: String,
+
+    /// Raw transactions, only read if a rule has metadata labels
+    pub transactions_subscribe_topic: String,
+
+    /// REST endpoint for the events
+    pub handle_topic_events: String,
+
+    /// Where to serve the gRPC API, if anywhere
+    pub grpc_bind_address: Option<String>,
+
+    /// What to watch, as [[module.sidechain-observer.rule]] entries
+    #[serde(rename = "rule")]
+    pub rules: Vec<ObservationRule>,
+}
+
+impl Default for SidechainObserverConfig {
+    fn default() -> Self {
+        Self {
+            address_deltas_topic: "cardano.address.deltas".to_string(),
+            transactions_subscribe_topic: "cardano.txs".to_string(),
+            handle_topic_events: "rest.get.sidechain.events".to_string(),
+            grpc_bind_address: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl SidechainObserverConfig {
+    pub fn grpc_socket_addr(&self) -> Option<Result<SocketAddr, String>> {
+        self.grpc_bind_address.as_ref().map(|address| {
+            address.parse().map_err(|e| format!("invalid grpc-bind-address '{address}': {e}"))
+        })
+    }
+}
+
+impl ModuleConfig for SidechainObserverConfig {
+    const MODULE: &'static str = "sidechain-observer";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        validate_rules(&self.rules, errors);
+        if let Some(Err(e)) = self.grpc_socket_addr() {
+            errors.push(e);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! gRPC service over the observed events
+
+use std::{net::SocketAddr, sync::Arc};
+
+use acropolis_common::{
+    sidechain::{SidechainEvent, SidechainEventKind, SidechainObserver},
+    state_history::StateHistory,
+    Datum, UTxOIdentifier,
+};
+use anyhow::Result;
+use tokio::{net::TcpListener, sync::Mutex};
+use tonic::{transport::Server, Request, Response, Status};
+
+use proto::{
+    created, event,
+    sidechain_observer_server::{
+        SidechainObserver as SidechainObserverService, SidechainObserverServer,
+    },
+    Asset, Created, Event, EventsRequest, EventsResponse, LatestBlockRequest, LatestBlockResponse,
+    Metadata, Spent, UtxoId,
+};
+
+pub mod proto {
+    tonic::include_proto!("sidechain_observer");
+
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("sidechain_observer_descriptor");
+}
+
+/// Most events returned in one response
+const MAX_CAPACITY: usize = 10_000;
+
+impl From<&UTxOIdentifier> for UtxoId {
+    fn from(utxo: &UTxOIdentifier) -> Self {
+        UtxoId {
+            tx_hash: utxo.tx_hash.to_vec(),
+            output_index: utxo.output_index.into(),
+        }
+    }
+}
+
+impl TryFrom<&SidechainEvent> for Event {
+    type Error = Status;
+
+    fn try_from(e: &SidechainEvent) -> Result<Self, Self::Error> {
+        let kind = match &e.kind {
+            SidechainEventKind::Created {
+                utxo,
+                address,
+                value,
+                datum,
+            } => event::Kind::Created(Created {
+                utxo: Some(utxo.into()),
+                address: address.to_bytes_key(),
+                lovelace: value.lovelace,
+                assets: value
+                    .assets
+                    .iter()
+                    .flat_map(|(policy, assets)| {
+                        assets.iter().map(|(name, quantity)| Asset {
+                            policy_id: policy.to_vec(),
+                            asset_name: name.as_slice().to_vec(),
+                            quantity: *quantity,
+                        })
+                    })
+                    .collect(),
+                datum: datum.as_ref().map(|datum| match datum {
+                    Datum::Inline(bytes) => created::Datum::InlineDatum(bytes.clone()),
+                    Datum::Hash(hash) => created::Datum::DatumHash(hash.to_vec()),
+                }),
+            }),
+            SidechainEventKind::Spent { utxo } => event::Kind::Spent(Spent {
+                utxo: Some(utxo.into()),
+            }),
+            SidechainEventKind::Metadata { label, metadatum } => event::Kind::Metadata(Metadata {
+                label: *label,
+                metadatum: serde_json::to_string(metadatum)
+                    .map_err(|e| Status::internal(e.to_string()))?,
+            }),
+        };
+
+        Ok(Event {
+            rule: e.rule.clone(),
+            block_number: e.block_number,
+            block_hash: e.block_hash.to_vec(),
+            slot: e.slot,
+            epoch: e.epoch,
+            block_timestamp_unix: e.block_timestamp,
+            tx_index: e.tx_index.into(),
+            tx_hash: e.tx_hash.to_vec(),
+            kind: Some(kind),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Service {
+    history: Arc<Mutex<StateHistory<SidechainObserver>>>,
+}
+
+#[tonic::async_trait]
+impl SidechainObserverService for Service {
+    async fn get_events(
+        &self,
+        request: Request<EventsRequest>,
+    ) -> Result<Response<EventsResponse>, Status> {
+        let req = request.into_inner();
+        let capacity = usize::try_from(req.capacity)
+            .ok()
+            .filter(|capacity| (1..=MAX_CAPACITY).contains(capacity))
+            .ok_or_else(|| {
+                Status::invalid_argument(format!("capacity must be between 1 and {MAX_CAPACITY}"))
+            })?;
+        let start_tx_index = u16::try_from(req.start_tx_index)
+            .map_err(|_| Status::invalid_argument("start_tx_index too large"))?;
+        let rule = (!req.rule.is_empty()).then_some(req.rule.as_str());
+
+        let history = self.history.lock().await;
+        let observer =
+            history.current().ok_or_else(|| Status::unavailable("no blocks observed yet"))?;
+        let events = observer
+            .events_from(req.start_block, start_tx_index, capacity, rule)
+            .into_iter()
+            .map(Event::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Response::new(EventsResponse { events }))
+    }
+
+    async fn get_latest_block(
+        &self,
+        _request: Request<LatestBlockRequest>,
+    ) -> Result<Response<LatestBlockResponse>, Status> {
+        let history = self.history.lock().await;
+        Ok(Response::new(LatestBlockResponse {
+            block_number: history.current().and_then(|observer| observer.last_block()),
+        }))
+    }
+}
+
+pub async fn run(
+    history: Arc<Mutex<StateHistory<SidechainObserver>>>,
+    addr: SocketAddr,
+) -> Result<()> {
+    tracing::info!("Starting gRPC server on {}", addr);
+    let listener = TcpListener::bind(addr).await?;
+
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
+
+    Server::builder()
+        .add_service(reflection)
+        .add_service(SidechainObserverServer::new(Service { history }))
+        .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Acropolis sidechain observer module for Caryatid
+//! Watches the addresses, tokens and metadata labels of a partner chain, as set
+//! by rules in its config, and serves the events over REST and gRPC
+use acropolis_common::{
+    caryatid::{PrimaryRead, RollbackWrapper},
+    configuration::ModuleConfig,
+    declare_cardano_reader,
+    messages::{
+        AddressDeltasMessage, CardanoMessage, Message, RawTxsMessage, StateTransitionMessage,
+    },
+    rest_helper::handle_rest_with_query_parameters,
+    sidechain::{rest::handle_events, SidechainObserver, TxMetadata},
+    state_history::{StateHistory, StateHistoryStore},
+    TxHash,
+};
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use pallas::ledger::traverse::MultiEraTx;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+mod configuration;
+mod grpc;
+
+pub use configuration::SidechainObserverConfig;
+
+declare_cardano_reader!(
+    AddressDeltasReader,
+    "address-deltas-topic",
+    "cardano.address.deltas",
+    AddressDeltas,
+    AddressDeltasMessage
+);
+
+declare_cardano_reader!(
+    TxsReader,
+    "transactions-subscribe-topic",
+    "cardano.txs",
+    ReceivedTxs,
+    RawTxsMessage
+);
+
+/// Sidechain observer module
+#[module(
+    message_type(Message),
+    name = "sidechain-observer",
+    description = "Partner chain observer"
+)]
+pub struct SidechainObserverModule;
+
+/// Metadata of each transaction which has any
+fn tx_metadata(txs: &RawTxsMessage) -> Vec<TxMetadata> {
+    txs.txs
+        .iter()
+        .enumerate()
+        .filter_map(|(tx_index, raw_tx)| match MultiEraTx::decode(raw_tx) {
+            Ok(tx) => {
+                let metadata = acropolis_codec::map_metadata(&tx.metadata())?;
+                let tx_hash: TxHash = tx.hash().to_vec().try_into().ok()?;
+                Some(TxMetadata {
+                    tx_index: tx_index as u16,
+                    tx_hash,
+                    metadata,
+                })
+            }
+            Err(e) => {
+                warn!("Can't decode transaction {tx_index} for its metadata: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+impl SidechainObserverModule {
+    async fn run(
+        history: Arc<Mutex<StateHistory<SidechainObserver>>>,
+        observer: SidechainObserver,
+        mut address_deltas_reader: AddressDeltasReader,
+        mut txs_reader: Option<TxsReader>,
+    ) -> Result<()> {
+        loop {
+            let mut state = history.lock().await.get_or_init_with(|| observer.clone());
+
+            let primary =
+                PrimaryRead::from_read(address_deltas_reader.read_with_rollbacks().await?);
+            if primary.is_rollback() {
+                state = history.lock().await.get_rolled_back_state(primary.block_info().number);
+            }
+
+            // Transactions come block by block alongside the deltas, rollbacks included
+            let metadata = match txs_reader.as_mut() {
+                Some(reader) => match reader.read_with_rollbacks().await? {
+                    RollbackWrapper::Normal((_, txs)) => tx_metadata(&txs),
+                    RollbackWrapper::Rollback(_) => Vec::new(),
+                },
+                None => Vec::new(),
+            };
+
+            if let Some(deltas) = primary.message() {
+                let block = primary.block_info();
+                let events = state.handle_block(block, deltas.as_extended_deltas()?, &metadata);
+                if events > 0 {
+                    debug!(block = block.number, events, "observed sidechain events");
+                }
+                history.lock().await.commit(block.number, state);
+            }
+        }
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = SidechainObserverConfig::load(&config)?;
+        let grpc_addr = cfg.grpc_socket_addr().transpose().map_err(|e| anyhow!(e))?;
+        let observer = SidechainObserver::new(cfg.rules.clone());
+        for rule in observer.rules() {
+            info!("Observing '{}'", rule.name);
+        }
+
+        let address_deltas_reader = AddressDeltasReader::new(&context, &config).await?;
+        let txs_reader = TxsReader::new_opt(observer.watches_metadata(), &context, &config).await?;
+
+        let history = Arc::new(Mutex::new(StateHistory::<SidechainObserver>::new(
+            "sidechain_observer",
+            StateHistoryStore::Unbounded,
+        )));
+
+        info!("Creating request handler on '{}'", cfg.handle_topic_events);
+        let history_rest = history.clone();
+        handle_rest_with_query_parameters(
+            context.clone(),
+            &cfg.handle_topic_events,
+            move |params| handle_events(history_rest.clone(), params),
+        );
+
+        if let Some(addr) = grpc_addr {
+            let history_grpc = history.clone();
+            context.run(async move {
+                grpc::run(history_grpc, addr)
+                    .await
+                    .unwrap_or_else(|e| error!("gRPC server failed: {e}"));
+            });
+        }
+
+        context.run(async move {
+            Self::run(history, observer, address_deltas_reader, txs_reader)
+                .await
+                .unwrap_or_else(|e| error!("Failed: {e}"));
+        });
+
+        Ok(())
+    }
+}
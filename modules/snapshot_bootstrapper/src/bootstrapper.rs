@@ -14,7 +14,7 @@ use crate::downloader::{DownloadError, SnapshotDownloader};
 use crate::publisher::SnapshotPublisher;
 use acropolis_common::configuration::{StartupMode, SyncMode};
 use acropolis_common::{
-    genesis_values::GenesisValues,
+    genesis_values::{GenesisValues, Network},
     messages::{CardanoMessage, Message},
     snapshot::streaming_snapshot::StreamingSnapshotParser,
 };
@@ -130,7 +130,10 @@ impl SnapshotBootstrapper {
         let parser = StreamingSnapshotParser::new(snapshot_path.to_string_lossy().into_owned())
             .with_utxo_sidecar_path(utxo_sidecar_path.to_string_lossy().into_owned());
         parser
-            .parse(&mut publisher, cfg.startup.network_name.into())
+            .parse(
+                &mut publisher,
+                Network::from_name(&cfg.startup.network_name)?.network_id(),
+            )
             .map_err(|e| BootstrapError::Parse(format!("{e:#}")))?;
         info!("Parsed snapshot in {:.2?}", start.elapsed());
 
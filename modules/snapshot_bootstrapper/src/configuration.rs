@@ -23,6 +23,7 @@ pub enum ConfigError {
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct StartupConfig {
+    #[serde(alias = "network")]
     pub network_name: String,
 }
 
@@ -330,9 +330,10 @@ impl AccountsCallback for SnapshotPublisher {
         data: acropolis_common::snapshot::AccountsBootstrapData,
     ) -> Result<()> {
         info!(
-            "Publishing accounts bootstrap for epoch {} with {} accounts, {} pools ({} retiring), {} dreps, snapshots: {}",
+            "Publishing accounts bootstrap for epoch {} with {} accounts ({} pointers), {} pools ({} retiring), {} dreps, snapshots: {}",
             data.epoch,
             data.accounts.len(),
+            data.pointers.len(),
             data.pools.len(),
             data.retiring_pools.len(),
             data.dreps.len(),
@@ -358,6 +359,7 @@ impl AccountsCallback for SnapshotPublisher {
             pot_deltas: data.pot_deltas,
             drep_delegations: self.epoch_context.drep_delegations.clone(),
             proposal_deposits,
+            pointers: data.pointers,
         };
 
         let msg = Arc::new(Message::Snapshot(SnapshotMessage::Bootstrap(
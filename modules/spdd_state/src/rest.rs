@@ -17,12 +17,7 @@ pub async fn handle_spdd(
     let state = {
         match locked.current() {
             Some(state) => state,
-            None => {
-                return Ok(RESTResponse::with_text(
-                    404,
-                    "SPDD state not yet initialized",
-                ))
-            }
+            None => return Err(RESTError::not_synced("SPDD state not yet initialized")),
         }
     };
 
@@ -34,10 +29,10 @@ pub async fn handle_spdd(
         Some(epoch) => match locked.get_by_index(epoch + 1) {
             Some(epoch_state) => epoch_state.get_latest(),
             None => {
-                return Ok(RESTResponse::with_text(
-                    404,
-                    &format!("SPDD not found for epoch {}", epoch),
-                ));
+                return Err(RESTError::NotFound(format!(
+                    "SPDD not found for epoch {}",
+                    epoch
+                )));
             }
         },
         None => state.get_latest(),
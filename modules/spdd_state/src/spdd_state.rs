@@ -1,14 +1,21 @@
 //! Acropolis SPDD state module for Caryatid
 //! Stores historical stake pool delegation distributions
 use acropolis_common::caryatid::{PrimaryRead, RollbackWrapper};
-use acropolis_common::configuration::{get_bool_flag, get_string_flag};
+use acropolis_common::configuration::{get_bool_flag, get_string_flag, get_u64_flag};
+use acropolis_common::control::{self, PauseGate};
 use acropolis_common::declare_cardano_reader;
-use acropolis_common::messages::SPOStakeDistributionMessage;
+use acropolis_common::messages::{SPOStakeDistributionMessage, StakeSnapshotMessage};
 use acropolis_common::queries::errors::QueryError;
+use acropolis_common::spdd_chunks::SPDDAssembler;
 use acropolis_common::state_history::{StateHistory, StateHistoryStore};
 use acropolis_common::{
-    messages::{CardanoMessage, Message, StateQuery, StateQueryResponse, StateTransitionMessage},
+    messages::{
+        CardanoMessage, ControlAction, Message, StateQuery, StateQueryResponse,
+        StateTransitionMessage,
+    },
+    metrics,
     queries::spdd::{SPDDStateQuery, SPDDStateQueryResponse, DEFAULT_SPDD_QUERY_TOPIC},
+    readiness,
     rest_helper::handle_rest_with_query_parameters,
 };
 use anyhow::{bail, Result};
@@ -22,16 +29,30 @@ mod state;
 use state::State;
 mod rest;
 use rest::handle_spdd;
+mod stakes;
+use stakes::StakesState;
 
 const DEFAULT_HANDLE_SPDD_TOPIC: (&str, &str) = ("handle-topic-spdd", "rest.get.spdd");
 const DEFAULT_STORE_SPDD: (&str, bool) = ("store-spdd", false);
+/// Keep per-account stake from accounts-state's snapshots, which it must publish
+const DEFAULT_STORE_STAKES: (&str, bool) = ("store-stakes", false);
+/// Number of epochs' per-account stake kept
+const DEFAULT_STORE_STAKES_EPOCHS: (&str, u64) = ("store-stakes-epochs", 3);
 
 declare_cardano_reader!(
     SPDDReader,
     "stake-pool-distribution-subscribe-topic",
     "cardano.spo.distribution",
     SPOStakeDistribution,
-    SPOStakeDistributionMessage
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
+);
+declare_cardano_reader!(
+    StakeSnapshotReader,
+    "stake-snapshot-subscribe-topic",
+    "cardano.stake.snapshot",
+    StakeSnapshot,
+    StakeSnapshotMessage
 );
 
 /// SPDD State module
@@ -47,30 +68,50 @@ impl SPDDState {
     async fn run(
         history: Arc<Mutex<StateHistory<State>>>,
         mut spdd_reader: SPDDReader,
+        mut pause: PauseGate,
     ) -> anyhow::Result<()> {
         loop {
-            let mut state = history.lock().await.get_or_init_with(State::new);
+            pause.wait().await;
+            let mut update = history.lock().await.get_or_init_with(State::new).update();
 
-            let primary = PrimaryRead::from_read(spdd_reader.read_with_rollbacks().await?);
+            // Each chunk is applied as it arrives
+            let primary =
+                PrimaryRead::from_read(spdd_reader.stream_with_rollbacks(&mut update).await?);
+            let epoch = primary.block_info().epoch;
 
             if primary.is_rollback() {
-                state = history.lock().await.get_rolled_back_state(primary.block_info().epoch);
+                history.lock().await.get_rolled_back_state(epoch);
+                continue;
             }
 
-            if let Some(msg) = primary.message() {
-                let span = info_span!("spdd_state.handle", epoch = msg.epoch);
-                async {
-                    state.apply_spdd_snapshot(msg.spos.iter().map(|(k, v)| (*k, *v)));
-                }
-                .instrument(span)
-                .await;
+            history.lock().await.commit(epoch, update.finish());
+        }
+    }
+
+    async fn run_stakes(
+        history: Arc<Mutex<StateHistory<StakesState>>>,
+        mut stakes_reader: StakeSnapshotReader,
+        keep: usize,
+    ) -> Result<()> {
+        loop {
+            let primary = PrimaryRead::from_read(stakes_reader.read_with_rollbacks().await?);
+            let epoch = primary.block_info().epoch;
+            let mut state = if primary.is_rollback() {
+                history.lock().await.get_rolled_back_state(epoch)
+            } else {
+                history.lock().await.get_or_init_with(StakesState::default)
+            };
 
-                history.lock().await.commit(primary.block_info().epoch, state);
+            if let Some(msg) = primary.message() {
+                state.apply_snapshot(msg.snapshot.clone(), keep);
+                history.lock().await.commit(epoch, state);
             }
         }
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "spdd-state").await?;
+
         // Get configuration
 
         // REST topic (not included in BF)
@@ -99,7 +140,25 @@ impl SPDDState {
             let history_handler = history.clone();
             let spdd_reader = SPDDReader::new(&context, &config).await?;
 
-            context.run(Self::run(history_handler, spdd_reader));
+            // Operators can pause consumption, or ask for a summary of the latest SPDD
+            let history_control = history.clone();
+            let pause =
+                control::on_control(context.clone(), &config, "spdd-state", move |action| {
+                    let history = history_control.clone();
+                    async move {
+                        if action != ControlAction::DumpState {
+                            bail!("spdd-state can't {action:?}");
+                        }
+                        let history = history.lock().await;
+                        Ok(match history.current() {
+                            Some(current) => current.summary(history.len()),
+                            None => "No SPDD received yet".to_string(),
+                        })
+                    }
+                })
+                .await?;
+
+            context.run(Self::run(history_handler, spdd_reader, pause));
 
             // Ticker to log stats
             let mut tick_subscription = context.subscribe("clock.tick").await?;
@@ -132,11 +191,29 @@ impl SPDDState {
             None
         };
 
+        // Per-account stake, from accounts-state's snapshots
+        let stakes_opt = if get_bool_flag(&config, DEFAULT_STORE_STAKES) {
+            let keep = get_u64_flag(&config, DEFAULT_STORE_STAKES_EPOCHS) as usize;
+            let stakes = Arc::new(Mutex::new(StateHistory::<StakesState>::new(
+                "spdd_state_stakes",
+                StateHistoryStore::default_epoch_store(),
+            )));
+            let stakes_reader = StakeSnapshotReader::new(&context, &config).await?;
+            context.run(Self::run_stakes(stakes.clone(), stakes_reader, keep));
+            Some(stakes)
+        } else {
+            None
+        };
+
         // handle spdd query
         let history_query = history_opt.clone();
+        let query_metrics = metrics::QueryMetrics::new("spdd-state");
         context.handle(&spdd_query_topic, move |message| {
             let history_query = history_query.clone();
+            let stakes_query = stakes_opt.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::SPDD(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::SPDD(
                         SPDDStateQueryResponse::Error(QueryError::internal_error(
@@ -145,37 +222,38 @@ impl SPDDState {
                     )));
                 };
 
-                let history = match history_query {
-                    Some(history) => history,
-                    None => {
-                        return Arc::new(Message::StateQueryResponse(StateQueryResponse::SPDD(
-                            SPDDStateQueryResponse::Error(QueryError::storage_disabled("SPDD")),
-                        )))
-                    }
-                };
-
-                let locked = history.lock().await;
-
                 let response = match query {
-                    SPDDStateQuery::GetEpochTotalActiveStakes { epoch } => {
-                        // Since this is active stakes we plus 2 to epoch number
-                        let active_stake = match locked.get_by_index(*epoch - 2) {
-                            Some(state) => state.get_total_active_stakes(),
-                            None => 0,
+                    SPDDStateQuery::GetEpochStakes { epoch, .. }
+                    | SPDDStateQuery::GetEpochPoolStakes { epoch, .. } => {
+                        let Some(stakes) = stakes_query else {
+                            return Arc::new(Message::StateQueryResponse(
+                                StateQueryResponse::SPDD(SPDDStateQueryResponse::Error(
+                                    QueryError::storage_disabled("Per-account stake"),
+                                )),
+                            ));
                         };
-                        SPDDStateQueryResponse::EpochTotalActiveStakes(active_stake)
+                        let state = stakes.lock().await.current().cloned();
+                        match (state.as_ref().and_then(|s| s.get(*epoch)), query) {
+                            (Some(stakes), SPDDStateQuery::GetEpochStakes { limit, skip, .. }) => {
+                                SPDDStateQueryResponse::EpochStakes(stakes.stakes(*limit, *skip))
+                            }
+                            (
+                                Some(stakes),
+                                SPDDStateQuery::GetEpochPoolStakes {
+                                    pool_id,
+                                    limit,
+                                    skip,
+                                    ..
+                                },
+                            ) => SPDDStateQueryResponse::EpochPoolStakes(
+                                stakes.pool_stakes(pool_id, *limit, *skip),
+                            ),
+                            _ => SPDDStateQueryResponse::Error(QueryError::not_found(format!(
+                                "Stake distribution for epoch {epoch}"
+                            ))),
+                        }
                     }
-                    SPDDStateQuery::GetEpochSPDD { epoch } => SPDDStateQueryResponse::EpochSPDD(
-                        locked
-                            .get_by_index(*epoch + 1)
-                            .map(|map| {
-                                map.get_latest()
-                                    .iter()
-                                    .map(|(pool_id, stake)| (*pool_id, stake.active))
-                                    .collect()
-                            })
-                            .unwrap_or_default(),
-                    ),
+                    _ => Self::query_spdd(history_query, query).await,
                 };
 
                 Arc::new(Message::StateQueryResponse(StateQueryResponse::SPDD(
@@ -186,4 +264,42 @@ impl SPDDState {
 
         Ok(())
     }
+
+    /// Answer a query on the stored SPDDs
+    async fn query_spdd(
+        history: Option<Arc<Mutex<StateHistory<State>>>>,
+        query: &SPDDStateQuery,
+    ) -> SPDDStateQueryResponse {
+        let Some(history) = history else {
+            return SPDDStateQueryResponse::Error(QueryError::storage_disabled("SPDD"));
+        };
+        let locked = history.lock().await;
+
+        match query {
+            SPDDStateQuery::GetEpochTotalActiveStakes { epoch } => {
+                // Since this is active stakes we plus 2 to epoch number
+                let active_stake = match locked.get_by_index(*epoch - 2) {
+                    Some(state) => state.get_total_active_stakes(),
+                    None => 0,
+                };
+                SPDDStateQueryResponse::EpochTotalActiveStakes(active_stake)
+            }
+            SPDDStateQuery::GetEpochSPDD { epoch } => SPDDStateQueryResponse::EpochSPDD(
+                locked
+                    .get_by_index(*epoch + 1)
+                    .map(|map| {
+                        map.get_latest()
+                            .iter()
+                            .map(|(pool_id, stake)| (*pool_id, stake.active))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            SPDDStateQuery::GetEpochStakes { .. } | SPDDStateQuery::GetEpochPoolStakes { .. } => {
+                SPDDStateQueryResponse::Error(QueryError::internal_error(
+                    "Per-account stake query in the SPDD",
+                ))
+            }
+        }
+    }
 }
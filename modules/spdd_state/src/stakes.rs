@@ -0,0 +1,145 @@
+//! Per-account stake distributions, from the stake snapshots accounts-state takes
+//! as each epoch ends
+
+use acropolis_common::{
+    epoch_snapshot::EpochSnapshot, queries::spdd::AccountStake, PoolId, StakeAddress,
+};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// One epoch's distribution, with its pools in ID order
+#[derive(Clone)]
+pub struct EpochStakes {
+    snapshot: Arc<EpochSnapshot>,
+    pools: Vec<PoolId>,
+}
+
+impl EpochStakes {
+    pub fn new(snapshot: Arc<EpochSnapshot>) -> Self {
+        let mut pools: Vec<PoolId> = snapshot
+            .spos
+            .iter()
+            .filter(|(_, spo)| !spo.delegators.is_empty())
+            .map(|(pool_id, _)| *pool_id)
+            .collect();
+        pools.sort();
+        Self { snapshot, pools }
+    }
+
+    fn delegators(&self, pool_id: &PoolId) -> &[(StakeAddress, u64)] {
+        self.snapshot.spos.get(pool_id).map(|spo| spo.delegators.as_slice()).unwrap_or_default()
+    }
+
+    /// A page of every pool's delegators
+    pub fn stakes(&self, limit: u64, skip: u64) -> Vec<AccountStake> {
+        self.pools
+            .iter()
+            .flat_map(|pool_id| {
+                self.delegators(pool_id).iter().map(|(stake_address, amount)| AccountStake {
+                    stake_address: stake_address.clone(),
+                    pool_id: *pool_id,
+                    amount: *amount,
+                })
+            })
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// A page of one pool's delegators
+    pub fn pool_stakes(&self, pool_id: &PoolId, limit: u64, skip: u64) -> Vec<AccountStake> {
+        self.delegators(pool_id)
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .map(|(stake_address, amount)| AccountStake {
+                stake_address: stake_address.clone(),
+                pool_id: *pool_id,
+                amount: *amount,
+            })
+            .collect()
+    }
+}
+
+/// Distributions for the latest epochs
+#[derive(Clone, Default)]
+pub struct StakesState {
+    /// By the epoch the stake is active in
+    epochs: BTreeMap<u64, EpochStakes>,
+}
+
+impl StakesState {
+    /// Add the snapshot taken as an epoch ended, which is active two epochs later,
+    /// keeping the latest `keep` epochs
+    pub fn apply_snapshot(&mut self, snapshot: Arc<EpochSnapshot>, keep: usize) {
+        self.epochs.insert(snapshot.epoch + 2, EpochStakes::new(snapshot));
+        while self.epochs.len() > keep.max(1) {
+            self.epochs.pop_first();
+        }
+    }
+
+    /// Distribution active in `epoch`, if kept
+    pub fn get(&self, epoch: u64) -> Option<&EpochStakes> {
+        self.epochs.get(&epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{epoch_snapshot::SnapshotSPO, hash::Hash, Credential, NetworkId};
+
+    fn address(n: u8) -> StakeAddress {
+        StakeAddress::new(
+            Credential::AddrKeyHash(Hash::new([n; 28])),
+            NetworkId::Mainnet,
+        )
+    }
+
+    fn snapshot(epoch: u64) -> Arc<EpochSnapshot> {
+        let mut snapshot = EpochSnapshot {
+            epoch,
+            ..EpochSnapshot::default()
+        };
+        for (pool, delegators) in [
+            (2u8, vec![(3u8, 30u64)]),
+            (1, vec![(1, 10), (2, 20)]),
+            (9, vec![]),
+        ] {
+            snapshot.spos.insert(
+                PoolId::new(Hash::new([pool; 28])),
+                SnapshotSPO {
+                    delegators: delegators.into_iter().map(|(a, v)| (address(a), v)).collect(),
+                    ..SnapshotSPO::default()
+                },
+            );
+        }
+        Arc::new(snapshot)
+    }
+
+    #[test]
+    fn stakes_are_paged_in_pool_order() {
+        let stakes = EpochStakes::new(snapshot(100));
+        let amounts = |page: Vec<AccountStake>| page.iter().map(|s| s.amount).collect::<Vec<_>>();
+
+        assert_eq!(amounts(stakes.stakes(100, 0)), vec![10, 20, 30]);
+        assert_eq!(amounts(stakes.stakes(2, 1)), vec![20, 30]);
+        assert!(stakes.stakes(100, 3).is_empty());
+
+        let pool = PoolId::new(Hash::new([1; 28]));
+        assert_eq!(amounts(stakes.pool_stakes(&pool, 1, 1)), vec![20]);
+        assert!(stakes.pool_stakes(&PoolId::new(Hash::new([7; 28])), 100, 0).is_empty());
+    }
+
+    #[test]
+    fn snapshots_are_kept_by_active_epoch() {
+        let mut state = StakesState::default();
+        for epoch in 100..103 {
+            state.apply_snapshot(snapshot(epoch), 2);
+        }
+
+        assert!(state.get(102).is_none());
+        assert!(state.get(103).is_some());
+        assert!(state.get(104).is_some());
+        assert!(state.get(105).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use acropolis_common::{DelegatedStake, PoolId};
+use acropolis_common::{spdd_chunks::SPDDSink, DelegatedStake, PoolId};
 use imbl::{HashMap, OrdSet};
 use tracing::info;
 
@@ -14,27 +14,12 @@ impl State {
         }
     }
 
-    pub fn apply_spdd_snapshot<I>(&mut self, snapshot: I)
-    where
-        I: IntoIterator<Item = (PoolId, DelegatedStake)>,
-    {
-        let mut next = self.spdd_history.clone();
-
-        let mut present = OrdSet::new();
-        for (k, v_new) in snapshot {
-            let changed = match next.get(&k) {
-                Some(v_old) => *v_old != v_new,
-                None => true,
-            };
-            if changed {
-                next.insert(k, v_new);
-            }
-            present.insert(k);
+    /// Take in the next snapshot as it arrives, replacing the latest once complete
+    pub fn update(self) -> SnapshotUpdate {
+        SnapshotUpdate {
+            state: self,
+            present: OrdSet::new(),
         }
-
-        next.retain(|k, _| present.contains(k));
-
-        self.spdd_history = next;
     }
 
     pub fn get_latest(&self) -> &HashMap<PoolId, DelegatedStake> {
@@ -47,6 +32,15 @@ impl State {
         self.spdd_history.values().map(|v| v.active).sum()
     }
 
+    /// Summary of the state, for operators
+    pub fn summary(&self, num_epochs: usize) -> String {
+        format!(
+            "Tracking {num_epochs} epochs, latest snapshot has {} SPOs with {} active stake",
+            self.spdd_history.len(),
+            self.get_total_active_stakes()
+        )
+    }
+
     pub fn tick(&self, num_epochs: usize) {
         let spo_count = self.spdd_history.len();
         info!(
@@ -55,3 +49,35 @@ impl State {
         );
     }
 }
+
+/// Snapshot being taken in a chunk at a time
+pub struct SnapshotUpdate {
+    state: State,
+
+    /// Pools in the snapshot so far
+    present: OrdSet<PoolId>,
+}
+
+impl SnapshotUpdate {
+    /// Drop the pools which were not in the snapshot
+    pub fn finish(self) -> State {
+        let Self { mut state, present } = self;
+        state.spdd_history.retain(|k, _| present.contains(k));
+        state
+    }
+}
+
+impl SPDDSink for SnapshotUpdate {
+    fn start(&mut self, _epoch: u64, _pools: u64, _total_active: u64) {
+        self.present.clear();
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        for (k, v_new) in spos {
+            if self.state.spdd_history.get(k) != Some(v_new) {
+                self.state.spdd_history.insert(*k, *v_new);
+            }
+            self.present.insert(*k);
+        }
+    }
+}
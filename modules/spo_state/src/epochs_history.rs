@@ -1,9 +1,10 @@
 use crate::store_config::StoreConfig;
 use acropolis_common::{
-    messages::{EpochActivityMessage, SPORewardsMessage, SPOStakeDistributionMessage},
+    messages::{EpochActivityMessage, SPORewardsMessage},
     rational_number::RationalNumber,
+    spdd_chunks::SPDDSink,
     validation::ValidationOutcomes,
-    BlockInfo, KeyHash, PoolEpochState, PoolId,
+    BlockInfo, DelegatedStake, KeyHash, PoolEpochState, PoolId,
 };
 use anyhow::anyhow;
 use dashmap::DashMap;
@@ -111,28 +112,15 @@ impl EpochsHistoryState {
         Some(active_stakes)
     }
 
-    /// Handle SPO Stake Distribution
-    /// Update epochs_history with active_stake (for spdd_message.epoch + 2)
+    /// Handle SPO Stake Distribution as it arrives
+    /// Update epochs_history with active_stake (for the SPDD's epoch + 2)
     ///
-    pub fn handle_spdd(&self, _block: &BlockInfo, spdd_message: &SPOStakeDistributionMessage) {
-        let Some(epochs_history) = self.epochs_history.as_ref() else {
-            return;
-        };
-        let SPOStakeDistributionMessage { epoch, spos } = spdd_message;
-        let epoch_to_update = *epoch + 2;
-
-        let total_active_stake = spos.par_iter().map(|(_, value)| value.active).sum();
-
-        spos.par_iter().for_each(|(spo, value)| {
-            Self::update_epochs_history_with(epochs_history, spo, epoch_to_update, |epoch_state| {
-                epoch_state.active_stake = Some(value.active);
-                epoch_state.delegators_count = Some(value.active_delegators_count);
-                if total_active_stake > 0 {
-                    epoch_state.active_size =
-                        Some(RationalNumber::new(value.active, total_active_stake));
-                }
-            });
-        });
+    pub fn spdd_update(&self) -> SPDDUpdate {
+        SPDDUpdate {
+            epochs_history: self.epochs_history.clone(),
+            epoch_to_update: 0,
+            total_active_stake: 0,
+        }
     }
 
     /// Handle SPO rewards data calculated from accounts-state
@@ -198,9 +186,46 @@ impl EpochsHistoryState {
     }
 }
 
+/// Fills in each pool's active stake from an SPDD, a chunk at a time
+pub struct SPDDUpdate {
+    epochs_history: Option<Arc<DashMap<KeyHash, BTreeMap<u64, EpochState>>>>,
+    epoch_to_update: u64,
+    total_active_stake: u64,
+}
+
+impl SPDDSink for SPDDUpdate {
+    fn start(&mut self, epoch: u64, _pools: u64, total_active: u64) {
+        self.epoch_to_update = epoch + 2;
+        self.total_active_stake = total_active;
+    }
+
+    fn pools(&mut self, spos: &[(PoolId, DelegatedStake)]) {
+        let Some(epochs_history) = self.epochs_history.as_ref() else {
+            return;
+        };
+        let (epoch_to_update, total_active_stake) = (self.epoch_to_update, self.total_active_stake);
+
+        spos.par_iter().for_each(|(spo, value)| {
+            EpochsHistoryState::update_epochs_history_with(
+                epochs_history,
+                spo,
+                epoch_to_update,
+                |epoch_state| {
+                    epoch_state.active_stake = Some(value.active);
+                    epoch_state.delegators_count = Some(value.active_delegators_count);
+                    if total_active_stake > 0 {
+                        epoch_state.active_size =
+                            Some(RationalNumber::new(value.active, total_active_stake));
+                    }
+                },
+            );
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use acropolis_common::{DelegatedStake, SPORewards};
+    use acropolis_common::{caryatid::ChunkAssembler, spdd_chunks::SPDDAssembler, SPORewards};
 
     use super::*;
     use crate::test_utils::*;
@@ -240,7 +265,7 @@ mod tests {
                 active_delegators_count: 1,
             },
         )];
-        epochs_history.handle_spdd(&block, &spdd_msg);
+        SPDDAssembler::stream_whole(&spdd_msg, &mut epochs_history.spdd_update());
 
         let mut epoch_activity_msg = new_epoch_activity_message(1);
         epoch_activity_msg.spo_blocks = vec![(spo_block_key_hash, 1)];
@@ -4,12 +4,16 @@
 use acropolis_common::caryatid::{PrimaryRead, RollbackWrapper, ValidationContext};
 use acropolis_common::configuration::{get_string_flag, StartupMode};
 use acropolis_common::declare_cardano_reader;
+use acropolis_common::epoch_barrier::EpochBarrierAcker;
 use acropolis_common::messages::{
     EpochActivityMessage, GovernanceProceduresMessage, ProtocolParamsMessage, RawBlockMessage,
     SPORewardsMessage, SPOStakeDistributionMessage, StakeAddressDeltasMessage,
     StakeRewardDeltasMessage, StateTransitionMessage, TxCertificatesMessage, WithdrawalsMessage,
 };
+use acropolis_common::metrics;
 use acropolis_common::queries::errors::QueryError;
+use acropolis_common::spdd_chunks::SPDDAssembler;
+use acropolis_common::state_digest::{StateDigest, StateDigestPublisher};
 
 use acropolis_common::{
     messages::{
@@ -21,6 +25,7 @@ use acropolis_common::{
         PoolsStateQueryResponse, DEFAULT_POOLS_QUERY_TOPIC,
     },
     rational_number::RationalNumber,
+    readiness,
     state_history::{StateHistory, StateHistoryStore},
     Era, PoolId,
 };
@@ -29,6 +34,7 @@ use caryatid_sdk::{module, Context, Subscription};
 use config::Config;
 use pallas::ledger::traverse::MultiEraHeader;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
 use tracing::{error, info, info_span, Instrument};
 
@@ -91,7 +97,8 @@ declare_cardano_reader!(
     "spdd-subscribe-topic",
     "cardano.spo.distribution",
     SPOStakeDistribution,
-    SPOStakeDistributionMessage
+    SPOStakeDistributionMessage,
+    SPOStakeDistributionChunk => SPDDAssembler
 );
 declare_cardano_reader!(
     StakeDeltasReader,
@@ -212,6 +219,8 @@ impl SPOState {
         // publishers
         mut spo_state_publisher: SPOStatePublisher,
         mut pool_registration_updates_publisher: PoolRegistrationUpdatesPublisher,
+        digest_publisher: Option<StateDigestPublisher>,
+        barrier_acker: Option<EpochBarrierAcker>,
         validation_publish_topic: String,
     ) -> Result<()> {
         // Wait for snapshot bootstrap if subscription is provided
@@ -239,6 +248,8 @@ impl SPOState {
             }
         }
 
+        let block_apply_latency = metrics::block_apply_latency("spo-state");
+
         // Main loop of synchronised messages
         loop {
             // Get a mutable state
@@ -251,6 +262,7 @@ impl SPOState {
                 "certs_reader",
                 certs_reader.read_with_rollbacks().await,
             )?;
+            let started = Instant::now();
 
             if primary.is_rollback() {
                 state = history.lock().await.get_rolled_back_state(primary.block_info().number);
@@ -263,6 +275,11 @@ impl SPOState {
                 pool_registration_updates_publisher.publish(rollback_message).await?;
             }
 
+            // Digest of the state as the previous epoch ended
+            if let (Some(digest_publisher), Some(_)) = (&digest_publisher, primary.epoch()) {
+                digest_publisher.publish_state(primary.block_info(), &state).await;
+            }
+
             // handle blocks (handle_mint) before handle_tx_certs in case of epoch boundary
             match ctx.consume("block_reader", block_reader.read_with_rollbacks().await)? {
                 RollbackWrapper::Normal((block_info, block_msg)) => {
@@ -358,14 +375,9 @@ impl SPOState {
                 }
 
                 if let Some(reader) = spdd_reader.as_mut() {
-                    // Handle SPDD
-                    match ctx.consume("spdd", reader.read_with_rollbacks().await)? {
-                        RollbackWrapper::Normal((block_info, spdd_message)) => {
-                            // update epochs_history
-                            epochs_history.handle_spdd(&block_info, &spdd_message);
-                        }
-                        RollbackWrapper::Rollback(_) => {}
-                    }
+                    // Handle SPDD, updating epochs_history a chunk at a time
+                    let mut update = epochs_history.spdd_update();
+                    ctx.consume("spdd", reader.stream_with_rollbacks(&mut update).await)?;
                 }
 
                 // Handle SPO rewards
@@ -504,10 +516,15 @@ impl SPOState {
             if primary.message().is_some() {
                 let block_info = primary.block_info();
                 history.lock().await.commit(block_info.number, state);
+                block_apply_latency.observe_duration(started.elapsed());
 
                 if primary.do_validation() {
                     ctx.publish().await;
                 }
+
+                if let Some(acker) = barrier_acker.as_ref().filter(|_| primary.epoch().is_some()) {
+                    acker.ack(block_info).await;
+                }
             }
         }
     }
@@ -535,6 +552,8 @@ impl SPOState {
     }
 
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "spo-state").await?;
+
         // Get configuration
 
         let clock_tick_subscribe_topic =
@@ -562,10 +581,10 @@ impl SPOState {
         let store_config = StoreConfig::from(config.clone());
 
         // Create history
-        let history = Arc::new(Mutex::new(StateHistory::<State>::new(
-            "spo_state",
-            StateHistoryStore::default_block_store(),
-        )));
+        let history = Arc::new(Mutex::new(
+            StateHistory::<State>::new("spo_state", StateHistoryStore::default_block_store())
+                .with_size_estimate(State::estimated_size),
+        ));
         let history_spo_state = history.clone();
         let history_tick = history.clone();
 
@@ -578,12 +597,15 @@ impl SPOState {
         let retired_pools_history_spo_state = retired_pools_history.clone();
 
         // handle pools-state query
+        let query_metrics = metrics::QueryMetrics::new("spo-state");
         context.handle(&pools_query_topic, move |message| {
             let history = history_spo_state.clone();
             let epochs_history = epochs_history_spo_state.clone();
             let retired_pools_history = retired_pools_history_spo_state.clone();
+            let timer = query_metrics.start(&message);
 
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::Pools(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::Pools(
                         PoolsStateQueryResponse::Error(QueryError::internal_error(
@@ -716,6 +738,10 @@ impl SPOState {
                         }
                     }
 
+                    PoolsStateQuery::GetPoolsRelays => {
+                        PoolsStateQueryResponse::PoolsRelays(state.get_pools_relays())
+                    }
+
                     PoolsStateQuery::GetPoolDelegators { pool_id } => {
                         if state.is_historical_delegators_enabled()
                             && state.is_stake_address_enabled()
@@ -738,6 +764,37 @@ impl SPOState {
                         }
                     }
 
+                    PoolsStateQuery::GetPoolDelegatorsPage {
+                        pool_id,
+                        limit,
+                        skip,
+                        order,
+                    } => {
+                        if state.is_historical_delegators_enabled()
+                            && state.is_stake_address_enabled()
+                        {
+                            match state.get_pool_delegators_page(
+                                pool_id,
+                                *limit,
+                                *skip,
+                                order.clone(),
+                            ) {
+                                Some(delegators) => {
+                                    PoolsStateQueryResponse::PoolDelegators(PoolDelegators {
+                                        delegators,
+                                    })
+                                }
+                                None => PoolsStateQueryResponse::Error(QueryError::not_found(
+                                    format!("Pool delegators for {}", pool_id),
+                                )),
+                            }
+                        } else {
+                            PoolsStateQueryResponse::Error(QueryError::storage_disabled(
+                                "pool delegators or stake addresses",
+                            ))
+                        }
+                    }
+
                     PoolsStateQuery::GetPoolTotalBlocksMinted { pool_id } => {
                         PoolsStateQueryResponse::PoolTotalBlocksMinted(
                             state.get_total_blocks_minted_by_pool(pool_id),
@@ -873,6 +930,9 @@ impl SPOState {
             context.clone(),
             pool_registration_updates_publish_topic,
         );
+        let digest_publisher =
+            StateDigestPublisher::from_config(context.clone(), &config, "spo-state");
+        let barrier_acker = EpochBarrierAcker::from_config(context.clone(), &config, "spo-state");
         let context_copy = context.clone();
 
         context.run(async move {
@@ -895,6 +955,8 @@ impl SPOState {
                 stake_reward_deltas_reader,
                 spo_state_publisher,
                 pool_registration_updates_publisher,
+                digest_publisher,
+                barrier_acker,
                 validation_publish_topic,
             )
             .await
@@ -13,14 +13,16 @@ use acropolis_common::{
         StakeRewardDeltasMessage, TxCertificatesMessage, WithdrawalsMessage,
     },
     params::TECHNICAL_PARAMETER_POOL_RETIRE_MAX_EPOCH,
-    queries::governance::VoteRecord,
+    queries::{governance::VoteRecord, misc::Order, pools::PoolRelays},
     stake_addresses::StakeAddressMap,
+    state_digest::{add_sections_to_digest, SetDigest, StateDigest},
     BlockInfo, PoolId, PoolMetadata, PoolRegistration, PoolRetirement, PoolUpdateEvent, Relay,
     StakeAddress, TxCertificate, TxHash, TxIdentifier, Voter, VotingProcedures,
 };
 use acropolis_common::{PoolRegistrationOutcome, PoolRegistrationUpdate};
 use anyhow::{anyhow, Result};
 use imbl::HashMap;
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 use tracing::debug;
 
@@ -56,6 +58,35 @@ pub struct State {
     stake_addresses: Option<Arc<Mutex<StakeAddressMap>>>,
 }
 
+impl StateDigest for State {
+    /// Pool registrations only - block counts and history depend on how the node started
+    fn add_to_digest(&self, digest: &mut SetDigest) -> Result<()> {
+        add_sections_to_digest(self, digest)
+    }
+
+    fn section_digests(&self) -> Result<BTreeMap<&'static str, SetDigest>> {
+        let mut pools = SetDigest::new();
+        for (id, registration) in &self.spos {
+            pools.insert(&("pool", id, registration))?;
+        }
+        let mut updates = SetDigest::new();
+        for (id, registration) in &self.pending_updates {
+            updates.insert(&("pending-update", id, registration))?;
+        }
+        let mut retirements = SetDigest::new();
+        for (epoch, ids) in &self.pending_deregistrations {
+            for id in ids {
+                retirements.insert(&("pending-deregistration", epoch, id))?;
+            }
+        }
+        Ok(BTreeMap::from([
+            ("pools", pools),
+            ("updates", updates),
+            ("retirements", retirements),
+        ]))
+    }
+}
+
 impl State {
     pub fn new(config: &StoreConfig) -> Self {
         Self {
@@ -80,6 +111,17 @@ impl State {
         }
     }
 
+    /// Estimated memory used by the state, for the state history memory budget
+    pub fn estimated_size(&self) -> usize {
+        (self.spos.len() + self.pending_updates.len()) * size_of::<(PoolId, PoolRegistration)>()
+            + self.pending_deregistrations.values().map(Vec::len).sum::<usize>()
+                * size_of::<PoolId>()
+            + self.total_blocks_minted.len() * size_of::<(PoolId, u64)>()
+            + self.historical_spos.as_ref().map_or(0, |historical| {
+                historical.len() * size_of::<(PoolId, HistoricalSPOState)>()
+            })
+    }
+
     pub fn is_historical_state_enabled(&self) -> bool {
         self.historical_spos.is_some()
     }
@@ -170,14 +212,19 @@ impl State {
         *self.total_blocks_minted.get(pool_operator).unwrap_or(&0)
     }
 
-    /// Get all Stake Pool operators' operator hashes
+    /// Get all Stake Pool operators' operator hashes, sorted so they page consistently
     pub fn list_pool_operators(&self) -> Vec<PoolId> {
-        self.spos.keys().cloned().collect()
+        let mut operators: Vec<PoolId> = self.spos.keys().cloned().collect();
+        operators.sort();
+        operators
     }
 
-    /// Get all Stake Pool Operators' operator hashes and their registration information
+    /// Get all Stake Pool Operators' operator hashes and their registration information,
+    /// sorted by operator
     pub fn list_pools_with_info(&self) -> Vec<(PoolId, PoolRegistration)> {
-        self.spos.iter().map(|(k, v)| (*k, v.clone())).collect()
+        let mut pools: Vec<_> = self.spos.iter().map(|(k, v)| (*k, v.clone())).collect();
+        pools.sort_by_key(|(operator, _)| *operator);
+        pools
     }
 
     /// Get pool metadata
@@ -200,6 +247,40 @@ impl State {
         delegators_map.map(|map| map.into_iter().collect())
     }
 
+    /// Get a page of Pool Delegators with their live stake, ordered by stake address
+    /// Only the delegators on the page have their balances looked up
+    /// Return None when store_delegators or store_stake_addresses is not enabled
+    pub fn get_pool_delegators_page(
+        &self,
+        pool_operator: &PoolId,
+        limit: u64,
+        skip: u64,
+        order: Order,
+    ) -> Option<Vec<(StakeAddress, u64)>> {
+        let stake_addresses = self.stake_addresses.as_ref()?;
+        let historical_spos = self.historical_spos.as_ref()?;
+
+        let mut delegators = historical_spos
+            .get(pool_operator)
+            .and_then(|s| s.delegators.as_ref())
+            .map(|s| s.iter().cloned().collect::<Vec<StakeAddress>>())?;
+        delegators.sort();
+        if order == Order::Desc {
+            delegators.reverse();
+        }
+
+        let stake_addresses = stake_addresses.lock().unwrap();
+        delegators
+            .into_iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .map(|stake_address| {
+                let account = stake_addresses.get(&stake_address)?;
+                Some((stake_address, account.utxo_value + account.rewards))
+            })
+            .collect()
+    }
+
     /// Get Blocks by Pool
     /// Return Vector of block heights
     /// Return None when store_blocks not enabled
@@ -228,6 +309,20 @@ impl State {
         self.spos.get(pool_id).map(|p| p.relays.clone())
     }
 
+    /// Relays of every registered pool, in pool ID order
+    pub fn get_pools_relays(&self) -> Vec<PoolRelays> {
+        let mut pools: Vec<_> = self
+            .spos
+            .iter()
+            .map(|(pool_id, registration)| PoolRelays {
+                pool_id: *pool_id,
+                relays: registration.relays.clone(),
+            })
+            .collect();
+        pools.sort_by_key(|pool| pool.pool_id);
+        pools
+    }
+
     /// Get pools that will be retired in the upcoming epochs
     pub fn get_retiring_pools(&self) -> Vec<PoolRetirement> {
         let current_epoch = self.epoch;
@@ -386,8 +481,11 @@ impl State {
                 .entry(reg.operator)
                 .or_insert_with(|| HistoricalSPOState::new(&self.store_config));
             historical_spo.add_pool_registration(reg);
-            historical_spo
-                .add_pool_updates(PoolUpdateEvent::register_event(*tx_identifier, *cert_index));
+            historical_spo.add_pool_updates(PoolUpdateEvent::register_event(
+                *tx_identifier,
+                *cert_index,
+                reg.clone(),
+            ));
         }
 
         pool_registration_update
@@ -685,10 +783,11 @@ impl State {
                     .or_insert_with(|| HistoricalSPOState::new(&self.store_config));
 
                 if let Some(votes) = historical_spo.votes.as_mut() {
-                    for vp in single_votes.voting_procedures.values() {
+                    for (proposal, vp) in single_votes.voting_procedures.iter() {
                         votes.push(VoteRecord {
                             tx_hash: *tx_hash,
                             vote_index: vp.vote_index,
+                            proposal: proposal.clone(),
                             vote: vp.vote.clone(),
                         });
                     }
@@ -766,8 +865,9 @@ mod tests {
     use crate::test_utils::*;
     use acropolis_common::{
         state_history::{StateHistory, StateHistoryStore},
-        PoolRetirement, Ratio, StakeAddress, TxCertificate, TxCertificateWithPos, TxIdentifier,
-        VrfKeyHash,
+        KeyHash, NetworkId, PoolRetirement, PoolUpdateAction, Ratio, StakeAddress,
+        StakeAddressDelta, StakeCredential, StakeRegistrationAndDelegation, TxCertificate,
+        TxCertificateWithPos, TxIdentifier, VrfKeyHash,
     };
     use tokio::sync::Mutex;
 
@@ -1160,4 +1260,99 @@ mod tests {
 
         assert!(state.get_blocks_by_pool_and_epoch(&spo_id, 3).is_none());
     }
+
+    #[test]
+    fn get_pool_delegators_page_returns_sorted_page_with_live_stake() {
+        let mut state = State::new(&save_delegators_store_config());
+        let pool_id = test_pool_id(0);
+        let delegators: Vec<StakeAddress> = (1..=3)
+            .map(|n| {
+                StakeAddress::new(
+                    StakeCredential::AddrKeyHash(KeyHash::new([n; 28])),
+                    NetworkId::Mainnet,
+                )
+            })
+            .collect();
+
+        let mut msg = new_certs_msg();
+        for (cert_index, stake_address) in delegators.iter().rev().enumerate() {
+            msg.certificates.push(TxCertificateWithPos {
+                cert: TxCertificate::StakeRegistrationAndDelegation(
+                    StakeRegistrationAndDelegation {
+                        stake_address: stake_address.clone(),
+                        operator: pool_id,
+                        deposit: 0,
+                    },
+                ),
+                tx_identifier: TxIdentifier::default(),
+                cert_index: cert_index as u64,
+            });
+        }
+        assert!(state.handle_tx_certs_no_errors(&new_block(1), &msg).is_ok());
+        state
+            .handle_stake_deltas(&StakeAddressDeltasMessage {
+                deltas: vec![StakeAddressDelta {
+                    stake_address: delegators[1].clone(),
+                    addresses: Vec::new(),
+                    tx_count: 1,
+                    delta: 500,
+                }],
+            })
+            .unwrap();
+
+        assert_eq!(
+            state.get_pool_delegators_page(&pool_id, 2, 0, Order::Asc),
+            Some(vec![
+                (delegators[0].clone(), 0),
+                (delegators[1].clone(), 500)
+            ])
+        );
+        assert_eq!(
+            state.get_pool_delegators_page(&pool_id, 2, 2, Order::Asc),
+            Some(vec![(delegators[2].clone(), 0)])
+        );
+        assert_eq!(
+            state.get_pool_delegators_page(&pool_id, 1, 0, Order::Desc),
+            Some(vec![(delegators[2].clone(), 0)])
+        );
+        assert!(state.get_pool_delegators_page(&test_pool_id(1), 1, 0, Order::Asc).is_none());
+    }
+
+    #[test]
+    fn pool_updates_record_the_parameters_of_each_certificate() {
+        let mut state = State::new(&save_updates_store_config());
+        let pool_id = test_pool_id(0);
+        let mut msg = new_certs_msg();
+        let mut registration = default_pool_registration(pool_id, None);
+        msg.certificates.push(TxCertificateWithPos {
+            cert: TxCertificate::PoolRegistration(registration.clone()),
+            tx_identifier: TxIdentifier::default(),
+            cert_index: 0,
+        });
+        registration.cost = 340_000_000;
+        msg.certificates.push(TxCertificateWithPos {
+            cert: TxCertificate::PoolRegistration(registration.clone()),
+            tx_identifier: TxIdentifier::default(),
+            cert_index: 1,
+        });
+        msg.certificates.push(TxCertificateWithPos {
+            cert: TxCertificate::PoolRetirement(PoolRetirement {
+                operator: pool_id,
+                epoch: 3,
+            }),
+            tx_identifier: TxIdentifier::default(),
+            cert_index: 2,
+        });
+        assert!(state.handle_tx_certs_no_errors(&new_block(1), &msg).is_ok());
+
+        let updates = state.get_pool_updates(&pool_id).unwrap();
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].parameters.as_ref().map(|p| p.cost), Some(0));
+        assert_eq!(
+            updates[1].parameters.as_ref().map(|p| p.cost),
+            Some(340_000_000)
+        );
+        assert_eq!(updates[2].action, PoolUpdateAction::Deregistered);
+        assert!(updates[2].parameters.is_none());
+    }
 }
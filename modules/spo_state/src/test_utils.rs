@@ -59,6 +59,32 @@ pub fn save_blocks_store_config() -> StoreConfig {
     }
 }
 
+pub fn save_updates_store_config() -> StoreConfig {
+    StoreConfig {
+        store_epochs_history: false,
+        store_retired_pools: false,
+        store_registration: false,
+        store_updates: true,
+        store_delegators: false,
+        store_votes: false,
+        store_blocks: false,
+        store_stake_addresses: false,
+    }
+}
+
+pub fn save_delegators_store_config() -> StoreConfig {
+    StoreConfig {
+        store_epochs_history: false,
+        store_retired_pools: false,
+        store_registration: false,
+        store_updates: false,
+        store_delegators: true,
+        store_votes: false,
+        store_blocks: false,
+        store_stake_addresses: true,
+    }
+}
+
 pub fn new_block(epoch: u64) -> BlockInfo {
     BlockInfo {
         status: BlockStatus::Immutable,
@@ -104,6 +130,7 @@ pub fn new_epoch_activity_message(epoch: u64) -> EpochActivityMessage {
         total_fees: 0,
         spo_blocks: Vec::new(),
         nonce: None,
+        active_stake: None,
     }
 }
 
@@ -16,6 +16,8 @@ const DEFAULT_VALIDATION_TOPIC: (&str, &str) = (
     "publishing-validation-topic",
     "cardano.validation.stake.filter",
 );
+const DEFAULT_SNAPSHOT_SUBSCRIBE_TOPIC: (&str, &str) =
+    ("snapshot-subscribe-topic", "cardano.snapshot");
 /// Directory to put cached shelley address pointers into. Depending on the address
 /// cache mode, these cached pointers can be used instead of tracking current pointer
 /// values in blockchain (which can be quite resource-consuming).
@@ -35,6 +37,7 @@ const DEFAULT_WRITE_FULL_CACHE: (&str, bool) = ("write-full-cache", false);
 pub struct StakeDeltaFilterParams {
     pub stake_address_delta_topic: String,
     pub validation_topic: String,
+    pub snapshot_topic: String,
 
     pub cache_dir: String,
     pub cache_mode: CacheMode,
@@ -55,6 +58,7 @@ impl StakeDeltaFilterParams {
         let params = Self {
             stake_address_delta_topic: get_string_flag(&cfg, DEFAULT_STAKE_ADDRESS_DELTA_TOPIC),
             validation_topic: get_string_flag(&cfg, DEFAULT_VALIDATION_TOPIC),
+            snapshot_topic: get_string_flag(&cfg, DEFAULT_SNAPSHOT_SUBSCRIBE_TOPIC),
             cache_dir: get_string_flag(&cfg, DEFAULT_CACHE_DIR),
             cache_mode: conf_enum::<CacheMode>(&cfg, DEFAULT_CACHE_MODE)?,
             write_full_cache: get_bool_flag(&cfg, DEFAULT_WRITE_FULL_CACHE),
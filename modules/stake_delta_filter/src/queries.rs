@@ -3,6 +3,7 @@ use std::sync::Arc;
 use acropolis_common::{
     configuration::get_string_flag,
     messages::{Message, StateQuery, StateQueryResponse},
+    metrics,
     queries::{
         errors::QueryError,
         stake_deltas::{
@@ -29,9 +30,12 @@ pub fn register_query_handler(
         .unwrap_or(DEFAULT_STAKE_DELTAS_QUERY_TOPIC.1.to_string());
     info!("Registering query handler on '{query_topic}'");
 
+    let query_metrics = metrics::QueryMetrics::new("stake-delta-filter");
     context.handle(&query_topic, move |message| {
         let cache = cache.clone();
+        let timer = query_metrics.start(&message);
         async move {
+            let _timer = timer;
             let Message::StateQuery(StateQuery::StakeDeltas(query)) = message.as_ref() else {
                 return Arc::new(Message::StateQueryResponse(
                     StateQueryResponse::StakeDeltas(StakeDeltaQueryResponse::Error(
@@ -68,9 +72,12 @@ pub fn register_query_handler_stateful(
     let query_topic = get_string_flag(config, DEFAULT_STAKE_DELTAS_QUERY_TOPIC);
     info!("Registering stateful query handler on '{query_topic}'");
 
+    let query_metrics = metrics::QueryMetrics::new("stake-delta-filter");
     context.handle(&query_topic, move |message| {
         let history = history.clone();
+        let timer = query_metrics.start(&message);
         async move {
+            let _timer = timer;
             let Message::StateQuery(StateQuery::StakeDeltas(query)) = message.as_ref() else {
                 return Arc::new(Message::StateQueryResponse(
                     StateQueryResponse::StakeDeltas(StakeDeltaQueryResponse::Error(
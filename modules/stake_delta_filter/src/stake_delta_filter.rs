@@ -5,11 +5,11 @@ use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper, ValidationContext},
     declare_cardano_reader,
     messages::{
-        AddressDeltasMessage, CardanoMessage, GenesisCompleteMessage, Message,
-        StateTransitionMessage, TxCertificatesMessage,
+        AddressDeltasMessage, CardanoMessage, GenesisCompleteMessage, Message, SnapshotMessage,
+        SnapshotStateMessage, StateTransitionMessage, TxCertificatesMessage,
     },
     state_history::{StateHistory, StateHistoryStore},
-    NetworkId,
+    NetworkId, ShelleyAddressPointer, StakeAddress,
 };
 use anyhow::{bail, Result};
 use caryatid_sdk::{module, Context, Subscription};
@@ -64,11 +64,47 @@ use crate::{
 };
 
 impl StakeDeltaFilter {
+    /// Collect the pointer map from the snapshot's accounts, up to snapshot completion
+    async fn read_snapshot_pointers(
+        mut subscription: Box<dyn Subscription<Message>>,
+    ) -> Result<Vec<(ShelleyAddressPointer, StakeAddress)>> {
+        let mut pointers = Vec::new();
+        loop {
+            let (_, message) = subscription.read().await?;
+            match message.as_ref() {
+                Message::Snapshot(SnapshotMessage::Bootstrap(
+                    SnapshotStateMessage::AccountsState(accounts),
+                )) => {
+                    pointers = accounts.pointers.clone();
+                }
+                Message::Snapshot(SnapshotMessage::Complete) => break,
+                _ => (),
+            }
+        }
+
+        info!("Bootstrapped {} pointers from snapshot", pointers.len());
+        Ok(pointers)
+    }
+
+    /// Cache with the snapshot's pointers added, if there are any
+    fn with_snapshot_pointers(
+        cache: Arc<PointerCache>,
+        pointers: &[(ShelleyAddressPointer, StakeAddress)],
+    ) -> Arc<PointerCache> {
+        if pointers.is_empty() {
+            return cache;
+        }
+        let mut cache = cache.as_ref().clone();
+        cache.add_snapshot_pointers(pointers);
+        Arc::new(cache)
+    }
+
     async fn run(
         context: Arc<Context<Message>>,
         mut genesis_reader: GenesisReader,
         address_delta_reader: AddressDeltasReader,
         certs_reader: CertsReader,
+        snapshot_subscription: Option<Box<dyn Subscription<Message>>>,
         publisher: DeltaPublisher,
         params: Arc<StakeDeltaFilterParams>,
     ) -> Result<()> {
@@ -79,9 +115,17 @@ impl StakeDeltaFilter {
             }
         };
 
+        let snapshot_pointers = match snapshot_subscription {
+            Some(subscription) => Self::read_snapshot_pointers(subscription).await?,
+            None => Vec::new(),
+        };
+
         match params.as_ref().cache_mode {
             CacheMode::Predefined => {
-                let cache = PointerCache::try_load_predefined(&get_network_name(network_id))?;
+                let cache = Self::with_snapshot_pointers(
+                    PointerCache::try_load_predefined(&get_network_name(network_id))?,
+                    &snapshot_pointers,
+                );
                 register_query_handler(&context, &context.config, cache.clone());
 
                 Self::stateless_run(
@@ -94,8 +138,10 @@ impl StakeDeltaFilter {
                 .await?;
             }
             CacheMode::Read => {
-                let cache =
-                    PointerCache::try_load(&params.get_cache_file_name(".json", &network_id)?)?;
+                let cache = Self::with_snapshot_pointers(
+                    PointerCache::try_load(&params.get_cache_file_name(".json", &network_id)?)?,
+                    &snapshot_pointers,
+                );
                 register_query_handler(&context, &context.config, cache.clone());
 
                 Self::stateless_run(
@@ -111,6 +157,7 @@ impl StakeDeltaFilter {
             CacheMode::WriteIfAbsent => {
                 match PointerCache::try_load(&params.get_cache_file_name(".json", &network_id)?) {
                     Ok(cache) => {
+                        let cache = Self::with_snapshot_pointers(cache, &snapshot_pointers);
                         register_query_handler(&context, &context.config, cache.clone());
 
                         Self::stateless_run(
@@ -137,6 +184,7 @@ impl StakeDeltaFilter {
                             publisher,
                             network_id,
                             params,
+                            snapshot_pointers,
                             context,
                         )
                         .await?;
@@ -158,6 +206,7 @@ impl StakeDeltaFilter {
                     publisher,
                     network_id,
                     params,
+                    snapshot_pointers,
                     context,
                 )
                 .await?;
@@ -173,6 +222,11 @@ impl StakeDeltaFilter {
         let certs_reader = CertsReader::new(&context, &config).await?;
 
         let params = StakeDeltaFilterParams::init(config.clone())?;
+        let snapshot_subscription = if params.is_snapshot_mode {
+            Some(context.subscribe(&params.snapshot_topic).await?)
+        } else {
+            None
+        };
         let publisher = DeltaPublisher::new(context.clone(), params.clone());
 
         // Start run task
@@ -183,6 +237,7 @@ impl StakeDeltaFilter {
                 genesis_reader,
                 address_delta_reader,
                 certs_reader,
+                snapshot_subscription,
                 publisher,
                 params,
             )
@@ -230,6 +285,7 @@ impl StakeDeltaFilter {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn stateful_run(
         history: Arc<Mutex<StateHistory<State>>>,
         mut certs_reader: CertsReader,
@@ -237,6 +293,7 @@ impl StakeDeltaFilter {
         mut publisher: DeltaPublisher,
         network: NetworkId,
         params: Arc<StakeDeltaFilterParams>,
+        snapshot_pointers: Vec<(ShelleyAddressPointer, StakeAddress)>,
         context: Arc<Context<Message>>,
     ) -> Result<()> {
         if !params.is_snapshot_mode {
@@ -251,7 +308,10 @@ impl StakeDeltaFilter {
             let mut ctx =
                 ValidationContext::new(&context, &params.validation_topic, "stake_delta_filter");
 
-            let mut state = history.lock().await.get_or_init_with(|| State::new(params.clone()));
+            let mut state = history
+                .lock()
+                .await
+                .get_or_init_with(|| State::new(params.clone(), &snapshot_pointers));
 
             let primary = PrimaryRead::from_sync(
                 &mut ctx,
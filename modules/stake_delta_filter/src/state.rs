@@ -9,7 +9,7 @@ use acropolis_common::{
         AddressDeltasMessage, CardanoMessage, Message, StakeAddressDeltasMessage,
         TxCertificatesMessage,
     },
-    Address, BlockInfo, ShelleyAddressPointer, TxCertificate,
+    Address, BlockInfo, ShelleyAddressPointer, StakeAddress, TxCertificate,
 };
 use anyhow::Result;
 use caryatid_sdk::Context;
@@ -80,6 +80,8 @@ impl State {
         block: &BlockInfo,
         msg: &TxCertificatesMessage,
     ) -> Result<()> {
+        // Every registration up to this block has been seen, not only the latest one
+        self.pointer_cache.update_max_slot(block.slot);
         for tx_cert in msg.certificates.iter() {
             if let TxCertificate::StakeRegistration(stake_address) = &tx_cert.cert {
                 let ptr = ShelleyAddressPointer {
@@ -95,9 +97,14 @@ impl State {
         Ok(())
     }
 
-    pub fn new(params: Arc<StakeDeltaFilterParams>) -> Self {
+    pub fn new(
+        params: Arc<StakeDeltaFilterParams>,
+        snapshot_pointers: &[(ShelleyAddressPointer, StakeAddress)],
+    ) -> Self {
+        let mut pointer_cache = PointerCache::new();
+        pointer_cache.add_snapshot_pointers(snapshot_pointers);
         Self {
-            pointer_cache: PointerCache::new(),
+            pointer_cache,
             params: params.clone(),
             tracker: Tracker::new(),
         }
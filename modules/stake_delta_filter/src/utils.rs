@@ -37,7 +37,7 @@ impl PointerCache {
         }
     }
 
-    fn update_max_slot(&mut self, processed_slot: u64) {
+    pub fn update_max_slot(&mut self, processed_slot: u64) {
         self.max_slot = max(self.max_slot, processed_slot);
     }
 
@@ -46,6 +46,14 @@ impl PointerCache {
         self.pointer_map.insert(ptr, Some(addr));
     }
 
+    /// Add the pointer map taken from a snapshot; it reflects the ledger at the snapshot,
+    /// so overrides whatever the cache had for the same pointers.
+    pub fn add_snapshot_pointers(&mut self, pointers: &[(ShelleyAddressPointer, StakeAddress)]) {
+        for (ptr, addr) in pointers {
+            self.set_pointer(ptr.clone(), addr.clone(), ptr.slot);
+        }
+    }
+
     pub fn update_block(&mut self, blk: &BlockInfo) {
         if self.conway_start_slot.is_none() && blk.era >= Era::Conway {
             self.conway_start_slot = Some(blk.slot);
@@ -645,4 +653,23 @@ mod test {
 
         Ok(())
     }
+    #[test]
+    fn snapshot_pointers_override_cache() {
+        let ptr = ShelleyAddressPointer {
+            slot: 100,
+            tx_index: 1,
+            cert_index: 0,
+        };
+        let script = StakeAddress::new(
+            StakeCredential::ScriptHash(Hash::new([7; 28])),
+            NetworkId::Mainnet,
+        );
+
+        let mut cache = PointerCache::new();
+        cache.add_empty_pointer(&ptr);
+        cache.add_snapshot_pointers(&[(ptr.clone(), script.clone())]);
+
+        assert_eq!(cache.decode_pointer(&ptr), Some(&Some(script)));
+        assert_eq!(cache.max_slot, 100);
+    }
 }
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use acropolis_common::configuration::{deserialize_duration, ModuleConfig};
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct StatsConfig {
+    pub clock_tick_subscribe_topic: String,
+    pub state_history_metrics_topic: String,
+    pub query_metrics_topic: String,
+    pub message_traces_topic: String,
+    pub handle_metrics_topic: String,
+    pub handle_query_metrics_topic: String,
+    pub handle_state_digests_topic: String,
+    pub handle_state_digests_epoch_topic: String,
+    pub handle_message_traces_topic: String,
+
+    /// Number of epochs of state digests to keep
+    pub state_digest_epochs: usize,
+
+    /// How often to log and publish statistics, in whole seconds (clock ticks)
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            clock_tick_subscribe_topic: "clock.tick".to_string(),
+            state_history_metrics_topic: "cardano.monitor.state-history".to_string(),
+            query_metrics_topic: "cardano.monitor.queries".to_string(),
+            message_traces_topic: "cardano.monitor.message-traces".to_string(),
+            handle_metrics_topic: "rest.get.metrics".to_string(),
+            handle_query_metrics_topic: "rest.get.metrics.queries".to_string(),
+            handle_state_digests_topic: "rest.get.state-digests".to_string(),
+            handle_state_digests_epoch_topic: "rest.get.state-digests.*".to_string(),
+            handle_message_traces_topic: "rest.get.message-traces".to_string(),
+            state_digest_epochs: 10,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ModuleConfig for StatsConfig {
+    const MODULE: &'static str = "stats";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.interval.as_secs() == 0 || self.interval.subsec_nanos() != 0 {
+            errors.push(format!(
+                "interval must be a whole number of seconds, not {:?}",
+                self.interval
+            ));
+        }
+        if self.state_digest_epochs == 0 {
+            errors.push("state-digest-epochs must be at least 1".to_string());
+        }
+    }
+}
@@ -1,31 +1,185 @@
-use acropolis_common::{configuration::get_string_flag, messages::Message};
+use acropolis_common::{
+    configuration::{get_string_flag, ModuleConfig},
+    hash::Hash,
+    message_trace,
+    messages::{
+        CardanoMessage, Message, MessageTracesMessage, MonitoringMessage, RESTResponse,
+        StateHistoryMetricsMessage,
+    },
+    metrics,
+    rest_error::RESTError,
+    rest_helper::{
+        handle_rest, handle_rest_with_path_parameter, handle_rest_with_query_parameters,
+    },
+    state_digest::DEFAULT_STATE_DIGEST_TOPIC,
+    state_history,
+};
 use anyhow::Result;
 use caryatid_sdk::{module, Context};
 use config::Config;
-use std::sync::Arc;
-use tracing::{error, info};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
 
-const DEFAULT_CLOCK_TICK_SUBSCRIBE_TOPIC: (&str, &str) =
-    ("clock-tick-subscribe-topic", "clock.tick");
+mod configuration;
+pub use configuration::StatsConfig;
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Number of the slowest query types to log
+const SLOWEST_QUERIES_LOGGED: usize = 5;
+
+/// Message traces served if the request doesn't give a limit
+const DEFAULT_MESSAGE_TRACES: usize = 100;
+
+/// Most recent state digests, by epoch then module
+type StateDigests = Arc<Mutex<BTreeMap<u64, BTreeMap<String, Hash<32>>>>>;
 
 #[module(message_type(Message), name = "stats", description = "Logs statistics")]
 pub struct Stats;
 
 impl Stats {
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
-        let clock_tick_subscribe_topic =
-            get_string_flag(&config, DEFAULT_CLOCK_TICK_SUBSCRIBE_TOPIC);
+        let StatsConfig {
+            clock_tick_subscribe_topic,
+            state_history_metrics_topic,
+            query_metrics_topic,
+            message_traces_topic,
+            handle_metrics_topic,
+            handle_query_metrics_topic,
+            handle_state_digests_topic,
+            handle_state_digests_epoch_topic,
+            handle_message_traces_topic,
+            state_digest_epochs,
+            interval,
+        } = StatsConfig::load(&config)?;
         info!("Creating subscriber on '{clock_tick_subscribe_topic}'");
+        info!("Publishing state history metrics on '{state_history_metrics_topic}'");
+        info!("Publishing query metrics on '{query_metrics_topic}'");
+        info!("Creating request handler on '{handle_metrics_topic}'");
+        handle_rest(context.clone(), &handle_metrics_topic, || async {
+            let mut response = RESTResponse::with_text(200, &metrics::render());
+            response.content_type = PROMETHEUS_CONTENT_TYPE.to_string();
+            Ok(response)
+        });
+
+        // Query types by mean service time, to find what is slowing REST responses
+        info!("Creating request handler on '{handle_query_metrics_topic}'");
+        handle_rest(context.clone(), &handle_query_metrics_topic, || async {
+            let json = serde_json::to_string_pretty(&metrics::query_metrics())?;
+            Ok(RESTResponse::with_json(200, &json))
+        });
+
+        // Sampled message traces, optionally for a topic pattern and after a sequence number
+        info!("Creating request handler on '{handle_message_traces_topic}'");
+        handle_rest_with_query_parameters(
+            context.clone(),
+            &handle_message_traces_topic,
+            |params| async move {
+                let after = params
+                    .get("after")
+                    .map(|after| after.parse::<u64>())
+                    .transpose()
+                    .map_err(|_| RESTError::invalid_param("after", "must be a number"))?;
+                let limit = params
+                    .get("limit")
+                    .map(|limit| limit.parse::<usize>())
+                    .transpose()
+                    .map_err(|_| RESTError::invalid_param("limit", "must be a number"))?
+                    .unwrap_or(DEFAULT_MESSAGE_TRACES);
+                let traces =
+                    message_trace::traces(params.get("topic").map(String::as_str), after, limit);
+                let json = serde_json::to_string_pretty(&traces)?;
+                Ok(RESTResponse::with_json(200, &json))
+            },
+        );
+
+        // Collect the state digests published by other modules
+        let state_digest_topic = get_string_flag(&config, DEFAULT_STATE_DIGEST_TOPIC);
+        info!("Creating subscriber on '{state_digest_topic}'");
+        info!("Creating request handler on '{handle_state_digests_topic}'");
+        info!("Creating request handler on '{handle_state_digests_epoch_topic}'");
+        let digests = StateDigests::default();
+
+        let digests_all = digests.clone();
+        handle_rest(context.clone(), &handle_state_digests_topic, move || {
+            let digests = digests_all.clone();
+            async move {
+                let json = serde_json::to_string_pretty(&*digests.lock().unwrap())?;
+                Ok(RESTResponse::with_json(200, &json))
+            }
+        });
+
+        let digests_epoch = digests.clone();
+        handle_rest_with_path_parameter(
+            context.clone(),
+            &handle_state_digests_epoch_topic,
+            move |params| {
+                let digests = digests_epoch.clone();
+                let epoch = params.first().map(|epoch| epoch.to_string());
+                async move {
+                    let epoch = epoch.ok_or_else(|| RESTError::param_missing("epoch"))?;
+                    let epoch: u64 = epoch
+                        .parse()
+                        .map_err(|_| RESTError::invalid_param("epoch", "must be a number"))?;
+                    let epoch_digests =
+                        digests.lock().unwrap().get(&epoch).cloned().ok_or_else(|| {
+                            RESTError::not_found(&format!("No state digests for epoch {epoch}"))
+                        })?;
+                    let json = serde_json::to_string_pretty(&epoch_digests)?;
+                    Ok(RESTResponse::with_json(200, &json))
+                }
+            },
+        );
+
+        let mut digest_subscription = context.subscribe(&state_digest_topic).await?;
+        context.run(async move {
+            loop {
+                let Ok((_, message)) = digest_subscription.read().await else {
+                    error!("Failed to run Stats state digest subscription");
+                    continue;
+                };
+                if let Message::Cardano((_, CardanoMessage::StateDigest(message))) =
+                    message.as_ref()
+                {
+                    let mut digests = digests.lock().unwrap();
+                    digests
+                        .entry(message.epoch)
+                        .or_default()
+                        .insert(message.module.clone(), message.digest);
+                    while digests.len() > state_digest_epochs {
+                        digests.pop_first();
+                    }
+                }
+            }
+        });
+
         let mut clock_tick_subscription = context.subscribe(&clock_tick_subscribe_topic).await?;
+        let run_context = context.clone();
         context.run(async move {
+            let mut last_trace = None;
             loop {
                 let Ok((_, tick_message)) = clock_tick_subscription.read().await else {
                     error!("Failed to run Stats clock tick subscription");
                     continue;
                 };
                 if let Message::Clock(tick_message) = tick_message.as_ref() {
-                    if tick_message.number.is_multiple_of(60) {
+                    if tick_message.number.is_multiple_of(interval.as_secs()) {
                         Self::log_stats().await;
+                        Self::publish_state_history_metrics(
+                            &run_context,
+                            &state_history_metrics_topic,
+                        )
+                        .await;
+                        Self::publish_query_metrics(&run_context, &query_metrics_topic).await;
+                        if message_trace::enabled() {
+                            last_trace = Self::publish_message_traces(
+                                &run_context,
+                                &message_traces_topic,
+                                last_trace,
+                            )
+                            .await;
+                        }
                     }
                 }
             }
@@ -33,6 +187,67 @@ impl Stats {
         Ok(())
     }
 
+    async fn publish_state_history_metrics(context: &Arc<Context<Message>>, topic: &str) {
+        let histories = state_history::metrics();
+        for history in &histories {
+            info!(
+                history = history.name,
+                entries = history.entries,
+                bytes = ?history.bytes,
+                compacted = history.compacted,
+                "State history"
+            );
+        }
+
+        let message = Arc::new(Message::Monitoring(MonitoringMessage::StateHistory(
+            StateHistoryMetricsMessage { histories },
+        )));
+        if let Err(e) = context.message_bus.publish(topic, message).await {
+            warn!("Could not publish state history metrics: {e:#}");
+        }
+    }
+
+    async fn publish_query_metrics(context: &Arc<Context<Message>>, topic: &str) {
+        let metrics = metrics::query_metrics();
+        for query in metrics.queries.iter().take(SLOWEST_QUERIES_LOGGED) {
+            info!(
+                module = query.module,
+                query = query.query,
+                count = query.count,
+                mean_ms = query.mean_seconds() * 1000.0,
+                "Slow query"
+            );
+        }
+        for (module, depth) in metrics.depths.iter().filter(|(_, depth)| **depth > 0) {
+            info!(module, depth, "Queries pending");
+        }
+
+        let message = Arc::new(Message::Monitoring(MonitoringMessage::Queries(metrics)));
+        if let Err(e) = context.message_bus.publish(topic, message).await {
+            warn!("Could not publish query metrics: {e:#}");
+        }
+    }
+
+    /// Publish the traces recorded after `last`, returning the new last one
+    async fn publish_message_traces(
+        context: &Arc<Context<Message>>,
+        topic: &str,
+        last: Option<u64>,
+    ) -> Option<u64> {
+        let traces = message_trace::traces(None, last, usize::MAX);
+        let Some(newest) = traces.last().map(|trace| trace.sequence) else {
+            return last;
+        };
+
+        let message = Arc::new(Message::Monitoring(MonitoringMessage::MessageTraces(
+            MessageTracesMessage { traces },
+        )));
+        if let Err(e) = context.message_bus.publish(topic, message).await {
+            warn!("Could not publish message traces: {e:#}");
+        }
+        Some(newest)
+    }
+
     async fn log_stats() {
         #[cfg(not(target_env = "msvc"))]
         {
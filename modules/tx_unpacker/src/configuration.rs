@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use acropolis_common::configuration::ModuleConfig;
+
+/// Outputs are only published if their topic is set
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct TxUnpackerConfig {
+    pub publish_utxo_deltas_topic: Option<String>,
+    pub publish_asset_deltas_topic: Option<String>,
+    pub publish_withdrawals_topic: Option<String>,
+    pub publish_certificates_topic: Option<String>,
+    pub publish_governance_topic: Option<String>,
+    pub publish_block_txs_topic: Option<String>,
+    pub publish_tx_validation_topic: String,
+
+    /// Most transactions whose UTXO deltas go in one message - bigger blocks are split
+    /// into batches. No limit if 0
+    pub utxo_deltas_max_batch: usize,
+
+    /// Parameters are only needed, and subscribed to, when validating
+    pub protocol_parameters_subscribe_topic: Option<String>,
+
+    // Read by the message readers
+    pub transactions_subscribe_topic: String,
+    pub genesis_subscribe_topic: String,
+}
+
+impl Default for TxUnpackerConfig {
+    fn default() -> Self {
+        Self {
+            publish_utxo_deltas_topic: None,
+            publish_asset_deltas_topic: None,
+            publish_withdrawals_topic: None,
+            publish_certificates_topic: None,
+            publish_governance_topic: None,
+            publish_block_txs_topic: None,
+            publish_tx_validation_topic: "cardano.validation.tx".to_string(),
+            utxo_deltas_max_batch: 0,
+            protocol_parameters_subscribe_topic: None,
+            transactions_subscribe_topic: "cardano.txs".to_string(),
+            genesis_subscribe_topic: "cardano.sequence.bootstrapped".to_string(),
+        }
+    }
+}
+
+impl ModuleConfig for TxUnpackerConfig {
+    const MODULE: &'static str = "tx-unpacker";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        // Each output has its own message type, so they can't share a topic
+        let outputs = [
+            ("publish-utxo-deltas-topic", &self.publish_utxo_deltas_topic),
+            (
+                "publish-asset-deltas-topic",
+                &self.publish_asset_deltas_topic,
+            ),
+            ("publish-withdrawals-topic", &self.publish_withdrawals_topic),
+            (
+                "publish-certificates-topic",
+                &self.publish_certificates_topic,
+            ),
+            ("publish-governance-topic", &self.publish_governance_topic),
+            ("publish-block-txs-topic", &self.publish_block_txs_topic),
+        ];
+        let mut seen: HashMap<&str, &str> = HashMap::new();
+        for (key, topic) in outputs {
+            let Some(topic) = topic else {
+                continue;
+            };
+            if let Some(other) = seen.insert(topic, key) {
+                errors.push(format!("{key} and {other} are both '{topic}'"));
+            }
+        }
+    }
+}
@@ -3,29 +3,53 @@ use acropolis_common::{
     messages::{ProtocolParamsMessage, RawTxsMessage},
     protocol_params::ProtocolParams,
     validation::{TransactionValidationError, ValidationError},
-    BlockInfo, GenesisDelegates,
+    BlockInfo, Era, GenesisDelegates,
 };
 use anyhow::Result;
+use tracing::warn;
 
 #[derive(Default, Clone)]
 pub struct State {
     pub protocol_params: ProtocolParams,
+
+    /// Era the ledger rules are in, from the protocol version. None until the first
+    /// parameters arrive, in which case the era of the block is used.
+    pub ledger_era: Option<Era>,
 }
 
 impl State {
     pub fn new() -> Self {
         Self {
             protocol_params: ProtocolParams::default(),
+            ledger_era: None,
         }
     }
 
     pub fn handle_protocol_params(&mut self, msg: &ProtocolParamsMessage) {
         self.protocol_params = msg.params.clone();
+        self.ledger_era = Some(msg.params.era());
+    }
+
+    /// Era to decode and validate the block's transactions in
+    fn validation_era(&self, block_info: &BlockInfo) -> Era {
+        match self.ledger_era {
+            Some(era) => {
+                if era != block_info.era {
+                    warn!(
+                        "Block {} is tagged {} but the protocol version is in {era}",
+                        block_info.number, block_info.era
+                    );
+                }
+                era
+            }
+            None => block_info.era,
+        }
     }
 
     fn validate_transaction(
         &self,
         block_info: &BlockInfo,
+        era: Era,
         raw_tx: &[u8],
         genesis_delegs: &GenesisDelegates,
     ) -> Result<(), Box<TransactionValidationError>> {
@@ -34,7 +58,7 @@ impl State {
             &self.protocol_params,
             genesis_delegs,
             block_info.slot,
-            block_info.era,
+            era,
         )
     }
 
@@ -44,11 +68,14 @@ impl State {
         txs_msg: &RawTxsMessage,
         genesis_delegs: &GenesisDelegates,
     ) -> Result<(), Box<ValidationError>> {
+        let era = self.validation_era(block_info);
+        // Delegates changed by certificate are in the parameters, once Shelley's are set
+        let genesis_delegs = self.protocol_params.genesis_delegates().unwrap_or(genesis_delegs);
         let mut bad_transactions = Vec::new();
         for (tx_index, raw_tx) in txs_msg.txs.iter().enumerate() {
             let tx_index = tx_index as u16;
 
-            if let Err(e) = self.validate_transaction(block_info, raw_tx, genesis_delegs) {
+            if let Err(e) = self.validate_transaction(block_info, era, raw_tx, genesis_delegs) {
                 bad_transactions.push((tx_index, *e));
             }
         }
@@ -6,13 +6,15 @@ use std::sync::Arc;
 use crate::state::State;
 use acropolis_common::{
     caryatid::{PrimaryRead, RollbackWrapper, ValidationContext},
-    configuration::get_string_flag,
+    configuration::ModuleConfig,
+    epoch_barrier::EpochBarrierGate,
     messages::{
         AssetDeltasMessage, CardanoMessage, GenesisCompleteMessage, GovernanceProceduresMessage,
         Message, ProtocolParamsMessage, RawTxsMessage, StateTransitionMessage,
-        TxCertificatesMessage, UTXODeltasMessage, WithdrawalsMessage,
+        TxCertificatesMessage, WithdrawalsMessage,
     },
     state_history::{StateHistory, StateHistoryStore},
+    utxo_batches::utxo_delta_messages,
     *,
 };
 use anyhow::{bail, Result};
@@ -23,10 +25,13 @@ use pallas::codec::minicbor::encode;
 use pallas::ledger::traverse::MultiEraTx;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, info_span, Instrument};
+mod configuration;
 mod crypto;
 pub mod state;
 pub mod validations;
 
+pub use configuration::TxUnpackerConfig;
+
 #[cfg(test)]
 mod test_utils;
 
@@ -52,9 +57,6 @@ declare_cardano_reader!(
     GenesisCompleteMessage
 );
 
-const DEFAULT_VALIDATION_OUTCOME_PUBLISH_TOPIC: (&str, &str) =
-    ("publish-tx-validation-topic", "cardano.validation.tx");
-
 const CIP25_METADATA_LABEL: u64 = 721;
 
 /// Tx unpacker module
@@ -73,6 +75,7 @@ impl TxUnpacker {
         history: Arc<Mutex<StateHistory<State>>>,
         // publishers
         publish_utxo_deltas_topic: Option<String>,
+        utxo_deltas_max_batch: usize,
         publish_asset_deltas_topic: Option<String>,
         publish_withdrawals_topic: Option<String>,
         publish_certificates_topic: Option<String>,
@@ -82,6 +85,7 @@ impl TxUnpacker {
         mut txs_reader: TxsReader,
         mut params_reader: Option<ParamsReader>,
         mut genesis_reader: GenesisReader,
+        mut barrier_gate: Option<EpochBarrierGate>,
     ) -> Result<()> {
         let genesis = match genesis_reader.read_with_rollbacks().await? {
             RollbackWrapper::Normal((_, genesis)) => genesis.values.clone(),
@@ -104,6 +108,9 @@ impl TxUnpacker {
 
             if let Some(rollback_message) = primary.rollback_message() {
                 state = history.lock().await.get_rolled_back_state(primary.block_info().number);
+                if let Some(gate) = barrier_gate.as_mut() {
+                    gate.rollback();
+                }
 
                 let mut futures = Vec::new();
                 if let Some(ref topic) = publish_utxo_deltas_topic {
@@ -135,6 +142,11 @@ impl TxUnpacker {
 
             if let Some(txs_msg) = primary.message() {
                 let block = primary.block_info().as_ref();
+
+                // Hold back the deltas until the last epoch is finished everywhere
+                if let Some(gate) = barrier_gate.as_mut() {
+                    gate.admit(block).await;
+                }
                 if tracing::enabled!(tracing::Level::DEBUG) {
                     debug!("Received {} txs for slot {}", txs_msg.txs.len(), block.slot);
                 }
@@ -187,7 +199,7 @@ impl TxUnpacker {
                                             let mut metadata_raw = Vec::new();
                                             match encode(metadata, &mut metadata_raw) {
                                                 Ok(()) => {
-                                                    cip25_metadata_updates.push(metadata_raw);
+                                                    cip25_metadata_updates.push((tx_identifier, metadata_raw));
                                                 }
                                                 Err(e) => {
                                                     error!("failed to encode CIP-25 metadatum: {e:#}");
@@ -243,14 +255,22 @@ impl TxUnpacker {
                 // Publish messages in parallel
                 let mut futures = Vec::new();
                 if let Some(ref topic) = publish_utxo_deltas_topic {
-                    let msg = Message::Cardano((
-                        block.clone(),
-                        CardanoMessage::UTXODeltas(UTXODeltasMessage {
-                            deltas: utxo_deltas,
-                        }),
-                    ));
-
-                    futures.push(context.message_bus.publish(topic, Arc::new(msg)));
+                    // Batches of a block must arrive in order, so only the last is sent
+                    // alongside the other messages
+                    let mut messages = utxo_delta_messages(utxo_deltas, utxo_deltas_max_batch);
+                    let last = messages.pop();
+                    for batch in messages {
+                        let msg = Message::Cardano((block.clone(), batch));
+                        context
+                            .message_bus
+                            .publish(topic, Arc::new(msg))
+                            .await
+                            .unwrap_or_else(|e| error!("Failed to publish: {e}"));
+                    }
+                    if let Some(last) = last {
+                        let msg = Message::Cardano((block.clone(), last));
+                        futures.push(context.message_bus.publish(topic, Arc::new(msg)));
+                    }
                 }
 
                 if let Some(ref topic) = publish_asset_deltas_topic {
@@ -352,54 +372,61 @@ impl TxUnpacker {
 
     /// Main init function
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let TxUnpackerConfig {
+            publish_utxo_deltas_topic,
+            publish_asset_deltas_topic,
+            publish_withdrawals_topic,
+            publish_certificates_topic,
+            publish_governance_topic: publish_governance_procedures_topic,
+            publish_block_txs_topic,
+            publish_tx_validation_topic,
+            utxo_deltas_max_batch,
+            protocol_parameters_subscribe_topic,
+            ..
+        } = TxUnpackerConfig::load(&config)?;
+
         // Publishers
-        let publish_utxo_deltas_topic = config.get_string("publish-utxo-deltas-topic").ok();
         if let Some(ref topic) = publish_utxo_deltas_topic {
             info!("Publishing UTXO deltas on '{topic}'");
+            if utxo_deltas_max_batch > 0 {
+                info!("Batching UTXO deltas at {utxo_deltas_max_batch} transactions");
+            }
         }
 
-        let publish_asset_deltas_topic = config.get_string("publish-asset-deltas-topic").ok();
         if let Some(ref topic) = publish_asset_deltas_topic {
             info!("Publishing native asset deltas on '{topic}'");
         }
 
-        let publish_withdrawals_topic = config.get_string("publish-withdrawals-topic").ok();
         if let Some(ref topic) = publish_withdrawals_topic {
             info!("Publishing withdrawals on '{topic}'");
         }
 
-        let publish_certificates_topic = config.get_string("publish-certificates-topic").ok();
         if let Some(ref topic) = publish_certificates_topic {
             info!("Publishing certificates on '{topic}'");
         }
 
-        let publish_governance_procedures_topic =
-            config.get_string("publish-governance-topic").ok();
         if let Some(ref topic) = publish_governance_procedures_topic {
             info!("Publishing governance procedures on '{topic}'");
         }
 
-        let publish_block_txs_topic = config.get_string("publish-block-txs-topic").ok();
         if let Some(ref topic) = publish_block_txs_topic {
             info!("Publishing block txs on '{topic}'");
         }
 
-        let publish_tx_validation_topic =
-            get_string_flag(&config, DEFAULT_VALIDATION_OUTCOME_PUBLISH_TOPIC);
-
         // Main transaction reader
         let txs_reader = TxsReader::new(&context, &config).await?;
 
         // Optional subscription for parameters (only needed if we are validating)
-        let protocol_params_subscribe_topic =
-            config.get_string("protocol-parameters-subscribe-topic").ok();
-        let params_reader = match protocol_params_subscribe_topic {
+        let params_reader = match protocol_parameters_subscribe_topic {
             Some(_) => Some(ParamsReader::new(&context, &config).await?),
             None => None,
         };
 
         let genesis_reader = GenesisReader::new(&context, &config).await?;
 
+        // Epoch boundary barrier, if enabled
+        let barrier_gate = EpochBarrierGate::from_config(context.clone(), &config).await?;
+
         // Initialize State
         let history = Arc::new(Mutex::new(StateHistory::<State>::new(
             "tx_unpacker",
@@ -412,6 +439,7 @@ impl TxUnpacker {
                 context_run,
                 history,
                 publish_utxo_deltas_topic,
+                utxo_deltas_max_batch,
                 publish_asset_deltas_topic,
                 publish_withdrawals_topic,
                 publish_certificates_topic,
@@ -420,6 +448,7 @@ impl TxUnpacker {
                 txs_reader,
                 params_reader,
                 genesis_reader,
+                barrier_gate,
             )
             .await
             .unwrap_or_else(|e| error!("Failed to run Tx Unpacker: {e}"));
@@ -2,8 +2,8 @@
 // Faster and API is simpler because it uses internally sharded locks
 // but it takes a lot more memory than HashMap
 
-use crate::state::ImmutableUTXOStore;
-use acropolis_common::{ShelleyAddressPointer, UTXOValue, UTxOIdentifier};
+use crate::state::{utxo_digest_entry, ImmutableUTXOStore};
+use acropolis_common::{state_digest::SetDigest, ShelleyAddressPointer, UTXOValue, UTxOIdentifier};
 use anyhow::Result;
 use async_trait::async_trait;
 use config::Config;
@@ -96,4 +96,12 @@ impl ImmutableUTXOStore for DashMapImmutableUTXOStore {
 
         Ok(result)
     }
+
+    async fn digest(&self) -> Result<SetDigest> {
+        let mut digest = SetDigest::new();
+        for entry in self.utxos.iter() {
+            digest.insert(&utxo_digest_entry(entry.key(), entry.value()))?;
+        }
+        Ok(digest)
+    }
 }
@@ -1,7 +1,9 @@
 //! Fake store for immutable UTXOs
 
 use crate::state::ImmutableUTXOStore;
-use acropolis_common::{Address, ShelleyAddressPointer, UTXOValue, UTxOIdentifier, Value};
+use acropolis_common::{
+    state_digest::SetDigest, Address, ShelleyAddressPointer, UTXOValue, UTxOIdentifier, Value,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use config::Config;
@@ -78,4 +80,8 @@ impl ImmutableUTXOStore for FakeImmutableUTXOStore {
     async fn sum_pointer_utxos(&self) -> Result<HashMap<ShelleyAddressPointer, u64>> {
         Ok(HashMap::new())
     }
+
+    async fn digest(&self) -> Result<SetDigest> {
+        Ok(SetDigest::new())
+    }
 }
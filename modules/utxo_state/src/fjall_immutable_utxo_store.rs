@@ -1,7 +1,7 @@
 //! On-disk store using Fjall for immutable UTXOs
 
-use crate::state::ImmutableUTXOStore;
-use acropolis_common::{ShelleyAddressPointer, UTXOValue, UTxOIdentifier};
+use crate::state::{utxo_digest_entry, ImmutableUTXOStore};
+use acropolis_common::{state_digest::SetDigest, ShelleyAddressPointer, UTXOValue, UTxOIdentifier};
 use anyhow::Result;
 use async_trait::async_trait;
 use config::Config;
@@ -154,4 +154,15 @@ impl ImmutableUTXOStore for FjallImmutableUTXOStore {
 
         Ok(result)
     }
+
+    async fn digest(&self) -> Result<SetDigest> {
+        let mut digest = SetDigest::new();
+        for entry in self.keyspace.iter() {
+            let (key_bytes, value_bytes) = entry.into_inner()?;
+            let key = UTxOIdentifier::from_bytes(&key_bytes)?;
+            let utxo: UTXOValue = serde_cbor::from_slice(&value_bytes)?;
+            digest.insert(&utxo_digest_entry(&key, &utxo))?;
+        }
+        Ok(digest)
+    }
 }
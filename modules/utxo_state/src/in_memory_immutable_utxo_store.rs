@@ -1,8 +1,21 @@
 //! In-memory store for immutable UTXOs using standard HashMap
+//!
+//! Values are held compactly: addresses are interned and shared between UTXOs, and
+//! anything beyond the lovelace (assets, datum, script ref) is packed into a single
+//! CBOR blob, which is absent for plain ADA outputs. Keys stay full UTxOIdentifiers -
+//! packing them into a u64 would truncate the transaction hash, so lookups would need
+//! collision handling.
+//!
+//! The tests below only check that an entry is smaller inline than a full one. The
+//! saving in resident memory has not been measured on mainnet - the peak resident
+//! memory in a benchmark mode report, with this store and another, would show it.
 
-use crate::state::ImmutableUTXOStore;
-use acropolis_common::{ShelleyAddressPointer, UTXOValue, UTxOIdentifier};
-use anyhow::Result;
+use crate::state::{utxo_digest_entry, ImmutableUTXOStore};
+use acropolis_common::{
+    state_digest::SetDigest, Address, Datum, NativeAssets, ScriptRef, ShelleyAddressPointer,
+    UTXOValue, UTxOIdentifier, Value,
+};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use config::Config;
 use std::collections::HashMap;
@@ -10,9 +23,138 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::info;
 
+/// Assets, datum and script ref, as packed into a compact UTXO
+type CompactExtras = (NativeAssets, Option<Datum>, Option<ScriptRef>);
+
+/// UTXO value as held in memory
+struct CompactUTXO {
+    /// Interned address
+    address: u32,
+
+    /// Value in Lovelace
+    lovelace: u64,
+
+    /// CBOR of the assets, datum and script ref, if it has any of them
+    extras: Option<Box<[u8]>>,
+}
+
+/// Reference counted address table, so each distinct address is only held once
+#[derive(Default)]
+struct AddressInterner {
+    /// Address to its slot
+    ids: HashMap<Arc<Address>, u32>,
+
+    /// Address and count of UTXOs using it, by slot
+    slots: Vec<Option<(Arc<Address>, u32)>>,
+
+    /// Slots released for reuse
+    free: Vec<u32>,
+}
+
+impl AddressInterner {
+    fn intern(&mut self, address: &Address) -> u32 {
+        if let Some(&id) = self.ids.get(address) {
+            if let Some((_, refs)) = &mut self.slots[id as usize] {
+                *refs += 1;
+            }
+            return id;
+        }
+
+        let address = Arc::new(address.clone());
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.slots[id as usize] = Some((address.clone(), 1));
+                id
+            }
+            None => {
+                self.slots.push(Some((address.clone(), 1)));
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.ids.insert(address, id);
+        id
+    }
+
+    fn release(&mut self, id: u32) {
+        let slot = &mut self.slots[id as usize];
+        if let Some((address, refs)) = slot {
+            *refs -= 1;
+            if *refs == 0 {
+                self.ids.remove(address.as_ref());
+                *slot = None;
+                self.free.push(id);
+            }
+        }
+    }
+
+    fn get(&self, id: u32) -> &Address {
+        match &self.slots[id as usize] {
+            Some((address, _)) => address.as_ref(),
+            None => panic!("UTXO refers to released address slot {id}"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CompactUTXOs {
+    utxos: HashMap<UTxOIdentifier, CompactUTXO>,
+    addresses: AddressInterner,
+}
+
+impl CompactUTXOs {
+    fn insert(&mut self, key: UTxOIdentifier, value: &UTXOValue) -> Result<()> {
+        let extras =
+            if value.value.assets.is_empty() && value.datum.is_none() && value.script_ref.is_none()
+            {
+                None
+            } else {
+                let extras = (&value.value.assets, &value.datum, &value.script_ref);
+                Some(minicbor::to_vec(extras)?.into_boxed_slice())
+            };
+
+        let compact = CompactUTXO {
+            address: self.addresses.intern(&value.address),
+            lovelace: value.value.lovelace,
+            extras,
+        };
+        if let Some(old) = self.utxos.insert(key, compact) {
+            self.addresses.release(old.address);
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &UTxOIdentifier) -> Option<CompactUTXO> {
+        let compact = self.utxos.remove(key)?;
+        self.addresses.release(compact.address);
+        Some(compact)
+    }
+
+    fn address(&self, compact: &CompactUTXO) -> &Address {
+        self.addresses.get(compact.address)
+    }
+
+    fn expand(&self, compact: &CompactUTXO) -> Result<UTXOValue> {
+        let (assets, datum, script_ref) = match &compact.extras {
+            Some(bytes) => minicbor::decode::<CompactExtras>(bytes)
+                .map_err(|e| anyhow!("Corrupt compact UTXO extras: {e}"))?,
+            None => (NativeAssets::new(), None, None),
+        };
+
+        Ok(UTXOValue {
+            address: self.address(compact).clone(),
+            value: Value {
+                lovelace: compact.lovelace,
+                assets,
+            },
+            datum,
+            script_ref,
+        })
+    }
+}
+
 pub struct InMemoryImmutableUTXOStore {
     /// Map of UTXOs
-    utxos: RwLock<HashMap<UTxOIdentifier, UTXOValue>>,
+    utxos: RwLock<CompactUTXOs>,
 }
 
 impl InMemoryImmutableUTXOStore {
@@ -20,7 +162,7 @@ impl InMemoryImmutableUTXOStore {
         info!("Storing immutable UTXOs in memory (standard)");
 
         Self {
-            utxos: RwLock::new(HashMap::new()),
+            utxos: RwLock::new(CompactUTXOs::default()),
         }
     }
 }
@@ -29,8 +171,7 @@ impl InMemoryImmutableUTXOStore {
 impl ImmutableUTXOStore for InMemoryImmutableUTXOStore {
     /// Add a UTXO
     async fn add_utxo(&self, key: UTxOIdentifier, value: UTXOValue) -> Result<()> {
-        self.utxos.write().await.insert(key, value);
-        Ok(())
+        self.utxos.write().await.insert(key, &value)
     }
 
     /// Delete a UTXO
@@ -41,13 +182,13 @@ impl ImmutableUTXOStore for InMemoryImmutableUTXOStore {
 
     /// Lookup a UTXO
     async fn lookup_utxo(&self, key: &UTxOIdentifier) -> Result<Option<UTXOValue>> {
-        // Essential to clone here because ref is not async safe
-        Ok(self.utxos.read().await.get(key).cloned())
+        let utxos = self.utxos.read().await;
+        utxos.utxos.get(key).map(|compact| utxos.expand(compact)).transpose()
     }
 
     /// Get the number of UTXOs in the store
     async fn len(&self) -> Result<usize> {
-        Ok(self.utxos.read().await.len())
+        Ok(self.utxos.read().await.utxos.len())
     }
 
     /// Cancel all unspent Byron redeem (AVVM) addresses.
@@ -58,14 +199,17 @@ impl ImmutableUTXOStore for InMemoryImmutableUTXOStore {
 
         // Find all redeem addresses
         let keys_to_remove: Vec<_> = utxos
+            .utxos
             .iter()
-            .filter(|(_, utxo)| utxo.address.is_redeem())
+            .filter(|(_, compact)| utxos.address(compact).is_redeem())
             .map(|(key, _)| *key)
             .collect();
 
         // Remove them and collect the cancelled UTxOs
         for key in keys_to_remove {
-            if let Some(utxo) = utxos.remove(&key) {
+            if let Some(compact) = utxos.utxos.get(&key) {
+                let utxo = utxos.expand(compact)?;
+                utxos.remove(&key);
                 cancelled.push((key, utxo));
             }
         }
@@ -81,19 +225,134 @@ impl ImmutableUTXOStore for InMemoryImmutableUTXOStore {
 
     /// Get the total lovelace of UTXOs in the store
     async fn sum_lovelace(&self) -> Result<u64> {
-        Ok(self.utxos.read().await.values().map(|v| v.value.lovelace).sum())
+        Ok(self.utxos.read().await.utxos.values().map(|compact| compact.lovelace).sum())
     }
 
     async fn sum_pointer_utxos(&self) -> Result<HashMap<ShelleyAddressPointer, u64>> {
         let utxos = self.utxos.read().await;
         let mut result: HashMap<ShelleyAddressPointer, u64> = HashMap::new();
 
-        for utxo in utxos.values() {
-            if let Some(ptr) = utxo.address.get_pointer() {
-                *result.entry(ptr).or_insert(0) += utxo.value.lovelace;
+        for compact in utxos.utxos.values() {
+            if let Some(ptr) = utxos.address(compact).get_pointer() {
+                *result.entry(ptr).or_insert(0) += compact.lovelace;
             }
         }
 
         Ok(result)
     }
+
+    async fn digest(&self) -> Result<SetDigest> {
+        let utxos = self.utxos.read().await;
+        let mut digest = SetDigest::new();
+        for (key, compact) in utxos.utxos.iter() {
+            digest.insert(&utxo_digest_entry(key, &utxos.expand(compact)?))?;
+        }
+        Ok(digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acropolis_common::{
+        hash::Hash, AssetName, ByronAddress, NativeAsset, PolicyId, ScriptHash, ScriptLang, TxHash,
+    };
+
+    fn utxo(address: &Address, lovelace: u64, datum: Option<Datum>) -> UTXOValue {
+        UTXOValue {
+            address: address.clone(),
+            value: Value {
+                lovelace,
+                assets: NativeAssets::new(),
+            },
+            datum,
+            script_ref: None,
+        }
+    }
+
+    fn asset(name: &[u8], amount: u64) -> NativeAsset {
+        NativeAsset {
+            name: AssetName::new(name).unwrap(),
+            amount,
+        }
+    }
+
+    #[tokio::test]
+    async fn compact_utxos_round_trip_and_share_addresses() -> Result<()> {
+        let store = InMemoryImmutableUTXOStore::new(Arc::new(Config::builder().build()?));
+        let address = Address::Byron(ByronAddress {
+            payload: vec![1, 2, 3],
+        });
+        let first = UTxOIdentifier::new(TxHash::new([1; 32]), 0);
+        let second = UTxOIdentifier::new(TxHash::new([2; 32]), 1);
+
+        store.add_utxo(first, utxo(&address, 10, None)).await?;
+        store
+            .add_utxo(
+                second,
+                utxo(&address, 20, Some(Datum::Inline(vec![0xca, 0xfe]))),
+            )
+            .await?;
+        assert_eq!(store.utxos.read().await.addresses.ids.len(), 1);
+
+        let value = store.lookup_utxo(&second).await?.expect("second UTXO");
+        assert_eq!(value.address, address);
+        assert_eq!(value.value.lovelace, 20);
+        assert_eq!(value.datum, Some(Datum::Inline(vec![0xca, 0xfe])));
+        assert_eq!(store.sum_lovelace().await?, 30);
+
+        store.delete_utxo(&first).await?;
+        store.delete_utxo(&second).await?;
+        assert!(store.utxos.read().await.addresses.ids.is_empty());
+        assert_eq!(store.len().await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compact_utxos_round_trip_multi_asset_values() -> Result<()> {
+        let store = InMemoryImmutableUTXOStore::new(Arc::new(Config::builder().build()?));
+        let address = Address::Byron(ByronAddress {
+            payload: vec![4, 5, 6],
+        });
+        let key = UTxOIdentifier::new(TxHash::new([3; 32]), 2);
+        let value = UTXOValue {
+            address: address.clone(),
+            value: Value {
+                lovelace: 1_500_000,
+                assets: vec![
+                    (
+                        PolicyId::new([1; 28]),
+                        vec![asset(b"", 7), asset(b"token", 1_000_000)],
+                    ),
+                    (PolicyId::new([2; 28]), vec![asset(&[0xff; 32], u64::MAX)]),
+                ],
+            },
+            datum: Some(Datum::Hash(Hash::new([9; 32]))),
+            script_ref: Some(ScriptRef {
+                script_hash: ScriptHash::new([8; 28]),
+                script_lang: ScriptLang::plutus_v2(),
+            }),
+        };
+
+        store.add_utxo(key, value.clone()).await?;
+        let found = store.lookup_utxo(&key).await?.expect("UTXO");
+        assert_eq!(found.address, address);
+        assert_eq!(found.value, value.value);
+        assert_eq!(found.datum, value.datum);
+        let script_ref = found.script_ref.expect("script ref");
+        assert_eq!(script_ref.script_hash, ScriptHash::new([8; 28]));
+        assert_eq!(script_ref.script_lang, ScriptLang::plutus_v2());
+        Ok(())
+    }
+
+    #[test]
+    fn compact_entries_are_smaller_than_full_ones() {
+        // Inline size only - heap use depends on the addresses and extras held
+        let compact = size_of::<(UTxOIdentifier, CompactUTXO)>();
+        let full = size_of::<(UTxOIdentifier, UTXOValue)>();
+        assert!(
+            compact < full,
+            "compact entry is {compact} bytes, full one {full}"
+        );
+    }
 }
@@ -4,8 +4,11 @@ use crate::reference_scripts_state::ReferenceScriptsState;
 use crate::validations;
 use crate::volatile_index::VolatileIndex;
 use acropolis_common::genesis_values::GenesisValues;
+use acropolis_common::hash::Hash;
 use acropolis_common::messages::Message;
+use acropolis_common::metrics;
 use acropolis_common::protocol_params::ProtocolParams;
+use acropolis_common::state_digest::SetDigest;
 use acropolis_common::state_history::{StateHistory, StateHistoryStore};
 use acropolis_common::validation::ValidationError;
 use acropolis_common::{
@@ -83,6 +86,17 @@ pub trait ImmutableUTXOStore: Send + Sync {
     /// Used at the Conway hard fork boundary to remove pointer address stake
     /// from the distribution (per Conway spec 9.1.2).
     async fn sum_pointer_utxos(&self) -> Result<HashMap<ShelleyAddressPointer, u64>>;
+
+    /// Get a digest of all UTXOs in the store, with entries from [`utxo_digest_entry`]
+    async fn digest(&self) -> Result<SetDigest>;
+}
+
+/// Entry for a UTXO in the state digest
+pub fn utxo_digest_entry<'a>(
+    key: &'a UTxOIdentifier,
+    value: &'a UTXOValue,
+) -> (&'static str, &'a UTxOIdentifier, &'a UTXOValue) {
+    ("utxo", key, value)
 }
 
 /// Ledger state storage
@@ -279,6 +293,22 @@ impl State {
         total
     }
 
+    /// Digest of the live UTXO set - the immutable store, plus volatile UTXOs,
+    /// less volatile spends. Unlike the other states this needs the async store,
+    /// so doesn't implement `StateDigest`.
+    pub async fn state_digest(&self) -> Result<Hash<32>> {
+        let mut digest = self.immutable_utxos.digest().await?;
+        for (key, value) in &self.volatile_utxos {
+            digest.insert(&utxo_digest_entry(key, value))?;
+        }
+        for key in self.volatile_spent.utxos() {
+            if let Some(value) = self.lookup_utxo(key).await? {
+                digest.remove(&utxo_digest_entry(key, &value))?;
+            }
+        }
+        Ok(digest.finish())
+    }
+
     /// Cancel all unspent Byron redeem (AVVM) addresses at the Allegra hard fork boundary.
     /// Returns (count of cancelled UTxOs, total lovelace value).
     ///
@@ -483,6 +513,8 @@ impl State {
     async fn log_stats(&self) {
         let n_immutable = self.immutable_utxos.len().await.unwrap_or_default();
         let n_valid = self.count_valid_utxos().await;
+        metrics::state_size("utxo-state", "immutable_utxos").set(n_immutable as f64);
+        metrics::state_size("utxo-state", "volatile_utxos").set(self.volatile_utxos.len() as f64);
         debug!(
             slot = self.last_slot,
             number = self.last_number,
@@ -908,7 +940,8 @@ impl State {
             deltas.iter().flat_map(|tx_deltas| tx_deltas.consumes.iter()).collect::<Vec<_>>();
         all_inputs.extend(deltas.iter().flat_map(|tx_deltas| tx_deltas.reference_inputs.iter()));
         let mut utxos = self.collect_utxos(&all_inputs).await;
-        let cost_models = protocol_params.cost_models();
+        // Cost models in force for this epoch, with missing parameters defaulted
+        let cost_models = protocol_params.cost_models().for_evaluation();
 
         for tx_deltas in deltas.iter() {
             if block.status != BlockStatus::Bootstrap {
@@ -1161,6 +1194,43 @@ pub mod tests {
         assert_eq!(0, state.count_valid_utxos().await);
     }
 
+    #[tokio::test]
+    async fn state_digest_is_independent_of_volatility() {
+        let outputs: Vec<TxOutput> = (0..2)
+            .map(|index| TxOutput {
+                utxo_identifier: UTxOIdentifier::new(TxHash::default(), index),
+                address: create_address(index as u8),
+                value: Value::new(42, vec![]),
+                datum: None,
+                script_ref: None,
+            })
+            .collect();
+
+        let mut digests = Vec::new();
+        for status in [BlockStatus::Immutable, BlockStatus::Volatile] {
+            let mut state = new_state();
+            let block1 = create_block(status.clone(), 1, 1);
+            state.observe_block(&block1).await.unwrap();
+            for output in &outputs {
+                state.observe_output(output, &block1).await.unwrap();
+            }
+
+            let block2 = create_block(status, 2, 2);
+            state.observe_block(&block2).await.unwrap();
+            state.observe_input(&outputs[0].utxo_identifier, &block2).await.unwrap();
+            digests.push(state.state_digest().await.unwrap());
+        }
+        assert_eq!(digests[0], digests[1]);
+
+        // Only the unspent output remains
+        let mut state = new_state();
+        let block = create_block(BlockStatus::Immutable, 1, 1);
+        state.observe_block(&block).await.unwrap();
+        state.observe_output(&outputs[1], &block).await.unwrap();
+        assert_eq!(digests[0], state.state_digest().await.unwrap());
+        assert_ne!(digests[0], new_state().state_digest().await.unwrap());
+    }
+
     #[tokio::test]
     async fn rollback_removes_future_created_utxos() {
         let mut state = new_state();
@@ -4,14 +4,21 @@
 use acropolis_common::{
     caryatid::{RollbackAwarePublisher, RollbackWrapper, ValidationContext},
     configuration::{get_string_flag, StartupMode},
+    control::{self, PauseGate},
+    dead_letter::DeadLetterQueue,
     declare_cardano_reader,
+    epoch_barrier::EpochBarrierAcker,
     messages::{
-        CardanoMessage, GenesisCompleteMessage, Message, PoolRegistrationUpdatesMessage,
-        ProtocolParamsMessage, SnapshotMessage, SnapshotStateMessage,
-        StakeRegistrationUpdatesMessage, StateQuery, StateQueryResponse, StateTransitionMessage,
-        UTXODeltasMessage,
+        CardanoMessage, ControlAction, GenesisCompleteMessage, Message,
+        PoolRegistrationUpdatesMessage, ProtocolParamsMessage, SnapshotMessage,
+        SnapshotStateMessage, StakeRegistrationUpdatesMessage, StateQuery, StateQueryResponse,
+        StateTransitionMessage, UTXODeltasMessage,
     },
+    metrics,
     queries::utxos::{UTxOStateQuery, UTxOStateQueryResponse, DEFAULT_UTXOS_QUERY_TOPIC},
+    readiness,
+    state_digest::StateDigestPublisher,
+    utxo_batches::UTXODeltasAssembler,
     Pots,
 };
 use caryatid_sdk::{module, Context, Subscription};
@@ -64,7 +71,8 @@ declare_cardano_reader!(
     "utxo-deltas-subscribe-topic",
     "cardano.utxo.deltas",
     UTXODeltas,
-    UTXODeltasMessage
+    UTXODeltasMessage,
+    UTXODeltasBatch => UTXODeltasAssembler
 );
 declare_cardano_reader!(
     ParamsReader,
@@ -137,9 +145,15 @@ impl UTXOState {
         mut pool_updates_reader: Option<PoolUpdatesReader>,
         mut stake_updates_reader: Option<StakeUpdatesReader>,
         mut pots_reader: PotsReader,
+        state_digest: Option<StateDigestPublisher>,
+        barrier_acker: Option<EpochBarrierAcker>,
         publish_tx_validation_topic: String,
         is_snapshot_mode: bool,
+        dead_letters: DeadLetterQueue,
+        mut pause: PauseGate,
     ) -> Result<()> {
+        let block_apply_latency = metrics::block_apply_latency("utxo-state");
+
         let genesis_values = match bootstrapped_reader.read_with_rollbacks().await? {
             RollbackWrapper::Normal((block_info, genesis_complete)) => {
                 info!(
@@ -161,6 +175,7 @@ impl UTXOState {
         let mut bootstrap_block_processed = false;
 
         loop {
+            pause.wait().await;
             let mut ctx =
                 ValidationContext::new(&context, &publish_tx_validation_topic, "utxo_state");
 
@@ -184,6 +199,12 @@ impl UTXOState {
 
             // Read protocol parameters and pots if new epoch
             if is_new_epoch {
+                // Digest of the UTXOs as the previous epoch ended
+                if let (Some(state_digest), Some((block, _))) = (&state_digest, &deltas_msg) {
+                    let digest = state.lock().await.state_digest().await;
+                    state_digest.publish(block, digest).await;
+                }
+
                 match ctx.consume("params_reader", params_reader.read_with_rollbacks().await)? {
                     RollbackWrapper::Normal((_, params)) => {
                         current_protocol_params = params.params.clone();
@@ -255,17 +276,23 @@ impl UTXOState {
                 }
 
                 let span = info_span!("utxo_state.handle", block = block.number);
-                async {
+                let result = async {
                     let mut state = state.lock().await;
-                    state
-                        .handle_utxo_deltas(block, deltas_msg)
-                        .await
-                        .inspect_err(|e| error!("Messaging handling error: {e}"))
-                        .ok();
+                    let _timer = block_apply_latency.start_timer();
+                    state.handle_utxo_deltas(block, deltas_msg).await
                 }
                 .instrument(span)
                 .await;
 
+                // Kept for replay once fixed - not under the state lock, which a replay takes
+                if let Err(e) = result {
+                    let message = Message::Cardano((
+                        block.as_ref().clone(),
+                        CardanoMessage::UTXODeltas(deltas_msg.as_ref().clone()),
+                    ));
+                    dead_letters.push("utxo_deltas_reader", &message, &e).await;
+                }
+
                 if !bootstrap_block_processed {
                     bootstrap_block_processed = true;
                 }
@@ -277,12 +304,18 @@ impl UTXOState {
                     .lock()
                     .await
                     .commit_protocol_parameters(&block_info, current_protocol_params.clone());
+
+                if let Some(acker) = barrier_acker.as_ref().filter(|_| is_new_epoch) {
+                    acker.ack(&block_info).await;
+                }
             }
         }
     }
 
     /// Main init function
     pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        readiness::declare_startup(context.clone(), &config, "utxo-state").await?;
+
         // These registration updates subscriptions are only needed for validation
         let pool_registration_updates_subscribe_topic =
             config.get_string("pool-registration-updates-subscribe-topic").ok();
@@ -347,12 +380,44 @@ impl UTXOState {
             AddressDeltaPublisher::new(context.clone(), config.clone(), address_delta_publish_mode);
         state.register_address_delta_observer(Arc::new(deltas_publisher));
 
+        let state_digest =
+            StateDigestPublisher::from_config(context.clone(), &config, "utxo-state");
+        let barrier_acker = EpochBarrierAcker::from_config(context.clone(), &config, "utxo-state");
+
         // Create block totals publisher and pass it observations
-        let totals_publisher = BlockTotalsPublisher::new(context.clone(), config);
+        let totals_publisher = BlockTotalsPublisher::new(context.clone(), config.clone());
         state.register_block_totals_observer(Arc::new(totals_publisher));
 
         let state = Arc::new(Mutex::new(state));
 
+        // Deltas which fail to apply are kept, and operators can replay them
+        let dead_letters = DeadLetterQueue::from_config(&config, "utxo-state");
+        let state_control = state.clone();
+        let dead_letters_control = dead_letters.clone();
+        let pause = control::on_control(context.clone(), &config, "utxo-state", move |action| {
+            let state = state_control.clone();
+            let dead_letters = dead_letters_control.clone();
+            async move {
+                if action != ControlAction::ReplayDeadLetters {
+                    bail!("utxo-state can't {action:?}");
+                }
+                dead_letters
+                    .replay(|message| {
+                        let state = state.clone();
+                        async move {
+                            let Message::Cardano((block, CardanoMessage::UTXODeltas(deltas))) =
+                                &message
+                            else {
+                                bail!("Not a UTxO deltas message");
+                            };
+                            state.lock().await.handle_utxo_deltas(block, deltas).await
+                        }
+                    })
+                    .await
+            }
+        })
+        .await?;
+
         let state_run = state.clone();
         let context_run = context.clone();
         context.run(async move {
@@ -365,8 +430,12 @@ impl UTXOState {
                 pool_updates_reader,
                 stake_updates_reader,
                 pots_reader,
+                state_digest,
+                barrier_acker,
                 utxo_validation_publish_topic,
                 is_snapshot_mode,
+                dead_letters,
+                pause,
             )
             .await
             .unwrap_or_else(|e| error!("Failed: {e}"));
@@ -437,9 +506,12 @@ impl UTXOState {
 
         // Query handler
         let state_query = state.clone();
+        let query_metrics = metrics::QueryMetrics::new("utxo-state");
         context.handle(&utxos_query_topic, move |message| {
             let state_mutex = state_query.clone();
+            let timer = query_metrics.start(&message);
             async move {
+                let _timer = timer;
                 let Message::StateQuery(StateQuery::UTxOs(query)) = message.as_ref() else {
                     return Arc::new(Message::StateQueryResponse(StateQueryResponse::UTxOs(
                         UTxOStateQueryResponse::Error(QueryError::internal_error(
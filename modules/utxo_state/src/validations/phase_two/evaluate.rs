@@ -348,17 +348,9 @@ fn evaluate_single_script(
     };
 
     // 3. Get cost model for this version
-    let cost_model = match plutus_version {
-        PlutusVersion::V1 => cost_models.plutus_v1.as_ref().ok_or(
-            Phase2ValidationError::MissingCostModel(common_plutus_version),
-        )?,
-        PlutusVersion::V2 => cost_models.plutus_v2.as_ref().ok_or(
-            Phase2ValidationError::MissingCostModel(common_plutus_version),
-        )?,
-        PlutusVersion::V3 => cost_models.plutus_v3.as_ref().ok_or(
-            Phase2ValidationError::MissingCostModel(common_plutus_version),
-        )?,
-    };
+    let cost_model = cost_models.get(common_plutus_version).ok_or(
+        Phase2ValidationError::MissingCostModel(common_plutus_version),
+    )?;
 
     // 4. Flat-decode the script
     let mut program = amaru_uplc::flat::decode::<DeBruijn>(
@@ -26,6 +26,11 @@ impl VolatileIndex {
         self.blocks.iter().map(|v| v.len()).sum()
     }
 
+    /// All UTXOs in the index, oldest block first
+    pub fn utxos(&self) -> impl Iterator<Item = &UTxOIdentifier> {
+        self.blocks.iter().flatten()
+    }
+
     /// Add a new block entry
     pub fn add_block(&mut self, number: u64) {
         // Capture the first volatile block we get
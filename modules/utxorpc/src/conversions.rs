@@ -0,0 +1,182 @@
+//! Conversions between Acropolis types and UtxoRPC messages
+
+use acropolis_common::{
+    protocol_params::ProtocolParams, queries::blocks::BlockCBOR, queries::errors::QueryError,
+    BlockHash, Point, TxHash, UTXOValue, UTxOIdentifier,
+};
+use tonic::Status;
+use utxorpc_spec::utxorpc::v1alpha::{
+    cardano,
+    query::{any_utxo_data, AnyUtxoData, ChainPoint, TxoRef},
+    sync::{AnyChainBlock, BlockRef},
+};
+
+pub fn block_ref(hash: &BlockHash, slot: u64) -> BlockRef {
+    BlockRef {
+        index: slot,
+        hash: hash.to_vec().into(),
+    }
+}
+
+pub fn point_to_block_ref(point: &Point) -> BlockRef {
+    match point {
+        Point::Origin => BlockRef {
+            index: 0,
+            hash: Default::default(),
+        },
+        Point::Specific { hash, slot } => block_ref(hash, *slot),
+    }
+}
+
+pub fn chain_point(hash: &BlockHash, slot: u64) -> ChainPoint {
+    ChainPoint {
+        slot,
+        hash: hash.to_vec().into(),
+    }
+}
+
+/// Blocks are served as native CBOR only; clients decode them with their own Cardano library
+pub fn any_chain_block(block: BlockCBOR) -> AnyChainBlock {
+    AnyChainBlock {
+        native_bytes: block.cbor.into(),
+        chain: None,
+    }
+}
+
+pub fn parse_block_hash(bytes: &[u8]) -> Result<BlockHash, Status> {
+    BlockHash::try_from(bytes)
+        .map_err(|_| Status::invalid_argument(format!("invalid block hash length {}", bytes.len())))
+}
+
+pub fn parse_txo_ref(txo_ref: &TxoRef) -> Result<UTxOIdentifier, Status> {
+    let tx_hash = TxHash::try_from(&txo_ref.hash[..]).map_err(|_| {
+        Status::invalid_argument(format!("invalid tx hash length {}", txo_ref.hash.len()))
+    })?;
+    let output_index = u16::try_from(txo_ref.index)
+        .map_err(|_| Status::invalid_argument(format!("invalid output index {}", txo_ref.index)))?;
+    Ok(UTxOIdentifier::new(tx_hash, output_index))
+}
+
+pub fn any_utxo_data(identifier: &UTxOIdentifier, utxo: &UTXOValue) -> AnyUtxoData {
+    let assets = utxo
+        .value
+        .assets
+        .iter()
+        .map(|(policy_id, assets)| cardano::Multiasset {
+            policy_id: policy_id.to_vec().into(),
+            assets: assets
+                .iter()
+                .map(|asset| cardano::Asset {
+                    name: asset.name.as_slice().to_vec().into(),
+                    output_coin: asset.amount,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        })
+        .collect();
+
+    AnyUtxoData {
+        txo_ref: Some(TxoRef {
+            hash: identifier.tx_hash.to_vec().into(),
+            index: identifier.output_index as u32,
+        }),
+        parsed_state: Some(any_utxo_data::ParsedState::Cardano(cardano::TxOutput {
+            address: utxo.address.to_bytes_key().into(),
+            coin: utxo.value.lovelace,
+            assets,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+pub fn pparams(params: &ProtocolParams) -> cardano::PParams {
+    let mut pparams = cardano::PParams {
+        coins_per_utxo_byte: params.coins_per_utxo_byte().unwrap_or_default(),
+        max_value_size: params.max_value_size().unwrap_or_default(),
+        ..Default::default()
+    };
+
+    if let Some(shelley) = &params.shelley {
+        let p = &shelley.protocol_params;
+        pparams.max_tx_size = p.max_tx_size as u64;
+        pparams.min_fee_coefficient = p.minfee_a as u64;
+        pparams.min_fee_constant = p.minfee_b as u64;
+        pparams.max_block_body_size = p.max_block_body_size as u64;
+        pparams.max_block_header_size = p.max_block_header_size as u64;
+        pparams.stake_key_deposit = p.key_deposit;
+        pparams.pool_deposit = p.pool_deposit;
+        pparams.pool_retirement_epoch_bound = p.pool_retire_max_epoch;
+        pparams.desired_number_of_pools = p.stake_pool_target_num as u64;
+        pparams.min_pool_cost = p.min_pool_cost;
+        pparams.protocol_version = Some(cardano::ProtocolVersion {
+            major: p.protocol_version.major as u32,
+            minor: p.protocol_version.minor as u32,
+        });
+    }
+
+    if let Some(alonzo) = &params.alonzo {
+        pparams.collateral_percentage = alonzo.collateral_percentage as u64;
+        pparams.max_collateral_inputs = alonzo.max_collateral_inputs as u64;
+    }
+
+    pparams
+}
+
+pub fn query_error_status(error: QueryError) -> Status {
+    match error {
+        QueryError::NotFound { .. } => Status::not_found(error.to_string()),
+        QueryError::InvalidRequest { .. } => Status::invalid_argument(error.to_string()),
+        QueryError::StorageDisabled { .. } | QueryError::NotImplemented { .. } => {
+            Status::unimplemented(error.to_string())
+        }
+        QueryError::NotSynced { .. } | QueryError::Unavailable { .. } => {
+            Status::unavailable(error.to_string())
+        }
+        QueryError::RolledBack { .. } => Status::not_found(error.to_string()),
+        QueryError::Internal { .. } => Status::internal(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txo_ref_round_trips() {
+        let identifier = UTxOIdentifier::new(TxHash::from([7; 32]), 3);
+        let txo_ref = TxoRef {
+            hash: identifier.tx_hash.to_vec().into(),
+            index: 3,
+        };
+        assert_eq!(parse_txo_ref(&txo_ref).unwrap(), identifier);
+    }
+
+    #[test]
+    fn txo_ref_rejects_bad_input() {
+        let short_hash = TxoRef {
+            hash: vec![0; 31].into(),
+            index: 0,
+        };
+        assert!(parse_txo_ref(&short_hash).is_err());
+
+        let big_index = TxoRef {
+            hash: vec![0; 32].into(),
+            index: u32::from(u16::MAX) + 1,
+        };
+        assert!(parse_txo_ref(&big_index).is_err());
+    }
+
+    #[test]
+    fn origin_maps_to_empty_block_ref() {
+        let origin = point_to_block_ref(&Point::Origin);
+        assert_eq!(origin.index, 0);
+        assert!(origin.hash.is_empty());
+
+        let hash = BlockHash::from([1; 32]);
+        let specific = point_to_block_ref(&Point::Specific { hash, slot: 42 });
+        assert_eq!(specific.index, 42);
+        assert_eq!(parse_block_hash(&specific.hash).unwrap(), hash);
+    }
+}
@@ -0,0 +1,247 @@
+//! UtxoRPC query service, backed by the parameters, UTxO and address state queries
+
+use std::sync::Arc;
+
+use acropolis_common::{
+    messages::{Message, StateQuery, StateQueryResponse},
+    queries::{
+        addresses::{AddressStateQuery, AddressStateQueryResponse},
+        blocks::{BlocksStateQuery, BlocksStateQueryResponse},
+        errors::QueryError,
+        parameters::{ParametersStateQuery, ParametersStateQueryResponse},
+        utils::query_state,
+        utxos::{UTxOStateQuery, UTxOStateQueryResponse},
+    },
+    Address, ShelleyAddress, UTXOValue, UTxOIdentifier,
+};
+use caryatid_sdk::Context;
+use tonic::{Request, Response, Status};
+use utxorpc_spec::utxorpc::v1alpha::query::{
+    any_chain_params, any_utxo_pattern::UtxoPattern, query_service_server::QueryService,
+    AnyChainParams, AnyUtxoData, ChainPoint, ReadDataRequest, ReadDataResponse, ReadParamsRequest,
+    ReadParamsResponse, ReadUtxosRequest, ReadUtxosResponse, SearchUtxosRequest,
+    SearchUtxosResponse,
+};
+
+use crate::conversions::{any_utxo_data, chain_point, parse_txo_ref, pparams, query_error_status};
+
+/// Maximum UTxOs returned by a single `ReadUtxos` or `SearchUtxos` call
+const MAX_UTXOS_PER_REQUEST: usize = 100;
+
+#[derive(Clone)]
+pub struct QueryTopics {
+    pub blocks: String,
+    pub parameters: String,
+    pub utxos: String,
+    pub addresses: String,
+}
+
+#[derive(Clone)]
+pub struct UtxoRpcQueryService {
+    context: Arc<Context<Message>>,
+    topics: QueryTopics,
+}
+
+impl UtxoRpcQueryService {
+    pub fn new(context: Arc<Context<Message>>, topics: QueryTopics) -> Self {
+        Self { context, topics }
+    }
+
+    async fn query<T>(
+        &self,
+        topic: &str,
+        query: StateQuery,
+        extractor: impl FnOnce(StateQueryResponse) -> Option<Result<T, QueryError>>,
+    ) -> Result<T, Status> {
+        let msg = Arc::new(Message::StateQuery(query));
+        query_state(&self.context, topic, msg, |message| match message {
+            Message::StateQueryResponse(response) => extractor(response).unwrap_or_else(|| {
+                Err(QueryError::internal_error(format!(
+                    "Unexpected response message type while calling {topic}"
+                )))
+            }),
+            _ => Err(QueryError::internal_error(format!(
+                "Unexpected message type while calling {topic}"
+            ))),
+        })
+        .await
+        .map_err(query_error_status)
+    }
+
+    async fn ledger_tip(&self) -> Result<ChainPoint, Status> {
+        self.query(
+            &self.topics.blocks,
+            StateQuery::Blocks(BlocksStateQuery::GetLatestBlock),
+            |response| match response {
+                StateQueryResponse::Blocks(BlocksStateQueryResponse::LatestBlock(block)) => {
+                    Some(Ok(chain_point(&block.hash, block.slot)))
+                }
+                StateQueryResponse::Blocks(BlocksStateQueryResponse::Error(e)) => Some(Err(e)),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    async fn utxos(
+        &self,
+        utxo_identifiers: Vec<UTxOIdentifier>,
+    ) -> Result<Vec<AnyUtxoData>, Status> {
+        if utxo_identifiers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<UTXOValue> = self
+            .query(
+                &self.topics.utxos,
+                StateQuery::UTxOs(UTxOStateQuery::GetUTxOs {
+                    utxo_identifiers: utxo_identifiers.clone(),
+                }),
+                |response| match response {
+                    StateQueryResponse::UTxOs(UTxOStateQueryResponse::UTxOs(values)) => {
+                        Some(Ok(values))
+                    }
+                    StateQueryResponse::UTxOs(UTxOStateQueryResponse::Error(e)) => Some(Err(e)),
+                    _ => None,
+                },
+            )
+            .await?;
+
+        Ok(utxo_identifiers
+            .iter()
+            .zip(values.iter())
+            .map(|(identifier, value)| any_utxo_data(identifier, value))
+            .collect())
+    }
+}
+
+#[tonic::async_trait]
+impl QueryService for UtxoRpcQueryService {
+    async fn read_params(
+        &self,
+        _request: Request<ReadParamsRequest>,
+    ) -> Result<Response<ReadParamsResponse>, Status> {
+        let params = self
+            .query(
+                &self.topics.parameters,
+                StateQuery::Parameters(ParametersStateQuery::GetLatestEpochParameters),
+                |response| match response {
+                    StateQueryResponse::Parameters(
+                        ParametersStateQueryResponse::LatestEpochParameters(params),
+                    ) => Some(Ok(params)),
+                    StateQueryResponse::Parameters(ParametersStateQueryResponse::Error(e)) => {
+                        Some(Err(e))
+                    }
+                    _ => None,
+                },
+            )
+            .await?;
+
+        Ok(Response::new(ReadParamsResponse {
+            values: Some(AnyChainParams {
+                params: Some(any_chain_params::Params::Cardano(pparams(&params))),
+            }),
+            ledger_tip: Some(self.ledger_tip().await?),
+        }))
+    }
+
+    async fn read_utxos(
+        &self,
+        request: Request<ReadUtxosRequest>,
+    ) -> Result<Response<ReadUtxosResponse>, Status> {
+        let keys = request.into_inner().keys;
+        if keys.len() > MAX_UTXOS_PER_REQUEST {
+            return Err(Status::invalid_argument(format!(
+                "At most {MAX_UTXOS_PER_REQUEST} UTxOs can be read at once"
+            )));
+        }
+
+        let identifiers = keys.iter().map(parse_txo_ref).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Response::new(ReadUtxosResponse {
+            items: self.utxos(identifiers).await?,
+            ledger_tip: Some(self.ledger_tip().await?),
+        }))
+    }
+
+    async fn search_utxos(
+        &self,
+        request: Request<SearchUtxosRequest>,
+    ) -> Result<Response<SearchUtxosResponse>, Status> {
+        let req = request.into_inner();
+
+        // Only a single exact address match can be answered from the address index
+        let address = req
+            .predicate
+            .filter(|p| p.not.is_empty() && p.all_of.is_empty() && p.any_of.is_empty())
+            .and_then(|p| p.r#match)
+            .and_then(|m| m.utxo_pattern)
+            .and_then(|UtxoPattern::Cardano(pattern)| pattern.asset.is_none().then_some(pattern))
+            .and_then(|pattern| pattern.address)
+            .filter(|a| a.payment_part.is_empty() && a.delegation_part.is_empty())
+            .map(|a| a.exact_address)
+            .filter(|bytes| !bytes.is_empty())
+            .ok_or_else(|| {
+                Status::unimplemented("Only exact address UTxO searches are supported")
+            })?;
+        let address = ShelleyAddress::from_bytes_key(&address)
+            .map(Address::Shelley)
+            .map_err(|e| Status::invalid_argument(format!("Invalid address: {e}")))?;
+
+        let offset = match req.start_token.as_str() {
+            "" => 0,
+            token => token
+                .parse::<usize>()
+                .map_err(|_| Status::invalid_argument("Invalid start token"))?,
+        };
+        let max_items = match usize::try_from(req.max_items) {
+            Ok(0) | Err(_) => MAX_UTXOS_PER_REQUEST,
+            Ok(n) => n.min(MAX_UTXOS_PER_REQUEST),
+        };
+
+        let identifiers = match self
+            .query(
+                &self.topics.addresses,
+                StateQuery::Addresses(AddressStateQuery::GetAddressUTxOs { address }),
+                |response| match response {
+                    StateQueryResponse::Addresses(AddressStateQueryResponse::AddressUTxOs(
+                        utxos,
+                    )) => Some(Ok(utxos)),
+                    StateQueryResponse::Addresses(AddressStateQueryResponse::Error(e)) => {
+                        Some(Err(e))
+                    }
+                    _ => None,
+                },
+            )
+            .await
+        {
+            Ok(identifiers) => identifiers,
+            // An address never seen on chain simply has no UTxOs
+            Err(status) if status.code() == tonic::Code::NotFound => Vec::new(),
+            Err(status) => return Err(status),
+        };
+
+        let page: Vec<_> = identifiers.iter().skip(offset).take(max_items).copied().collect();
+        let next_offset = offset + page.len();
+        let next_token = if next_offset < identifiers.len() {
+            next_offset.to_string()
+        } else {
+            String::new()
+        };
+
+        Ok(Response::new(SearchUtxosResponse {
+            items: self.utxos(page).await?,
+            ledger_tip: Some(self.ledger_tip().await?),
+            next_token,
+        }))
+    }
+
+    async fn read_data(
+        &self,
+        _request: Request<ReadDataRequest>,
+    ) -> Result<Response<ReadDataResponse>, Status> {
+        Err(Status::unimplemented(
+            "Datum lookup by hash is not available",
+        ))
+    }
+}
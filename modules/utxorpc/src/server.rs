@@ -0,0 +1,32 @@
+use std::net::SocketAddr;
+
+use anyhow::Result;
+use tokio::net::TcpListener;
+use tonic::transport::Server;
+use utxorpc_spec::utxorpc::v1alpha::{
+    query::query_service_server::QueryServiceServer,
+    submit::submit_service_server::SubmitServiceServer,
+    sync::sync_service_server::SyncServiceServer,
+};
+
+use crate::{query::UtxoRpcQueryService, submit::UtxoRpcSubmitService, sync::UtxoRpcSyncService};
+
+pub async fn run(
+    addr: SocketAddr,
+    sync: UtxoRpcSyncService,
+    query: UtxoRpcQueryService,
+    submit: UtxoRpcSubmitService,
+) -> Result<()> {
+    tracing::info!("Starting UtxoRPC server on {}", addr);
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("UtxoRPC server listening on {}", addr);
+
+    Server::builder()
+        .add_service(SyncServiceServer::new(sync))
+        .add_service(QueryServiceServer::new(query))
+        .add_service(SubmitServiceServer::new(submit))
+        .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+        .await?;
+
+    Ok(())
+}
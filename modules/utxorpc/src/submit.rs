@@ -0,0 +1,98 @@
+//! UtxoRPC submit service, forwarding transactions to the tx-submitter
+
+use std::{pin::Pin, sync::Arc};
+
+use acropolis_common::{
+    commands::transactions::{TransactionsCommand, TransactionsCommandResponse},
+    messages::{Command, CommandResponse, Message},
+};
+use caryatid_sdk::Context;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use utxorpc_spec::utxorpc::v1alpha::submit::{
+    any_chain_tx, submit_service_server::SubmitService, ReadMempoolRequest, ReadMempoolResponse,
+    SubmitTxRequest, SubmitTxResponse, WaitForTxRequest, WaitForTxResponse, WatchMempoolRequest,
+    WatchMempoolResponse,
+};
+
+#[derive(Clone)]
+pub struct UtxoRpcSubmitService {
+    context: Arc<Context<Message>>,
+    submit_topic: String,
+}
+
+impl UtxoRpcSubmitService {
+    pub fn new(context: Arc<Context<Message>>, submit_topic: String) -> Self {
+        Self {
+            context,
+            submit_topic,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SubmitService for UtxoRpcSubmitService {
+    async fn submit_tx(
+        &self,
+        request: Request<SubmitTxRequest>,
+    ) -> Result<Response<SubmitTxResponse>, Status> {
+        let mut refs = Vec::new();
+        for tx in request.into_inner().tx {
+            let Some(any_chain_tx::Type::Raw(cbor)) = tx.r#type else {
+                return Err(Status::invalid_argument("Transaction has no raw bytes"));
+            };
+
+            let request = Arc::new(Message::Command(Command::Transactions(
+                TransactionsCommand::Submit {
+                    cbor: cbor.to_vec(),
+                    wait_for_ack: true,
+                },
+            )));
+            let response = self
+                .context
+                .request(&self.submit_topic, request)
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+
+            match response.as_ref() {
+                Message::CommandResponse(CommandResponse::Transactions(
+                    TransactionsCommandResponse::Submitted { id },
+                )) => refs.push(id.to_vec().into()),
+                Message::CommandResponse(CommandResponse::Transactions(
+                    TransactionsCommandResponse::Error(e),
+                )) => return Err(Status::failed_precondition(e.clone())),
+                _ => return Err(Status::internal("Unexpected response from tx submitter")),
+            }
+        }
+
+        Ok(Response::new(SubmitTxResponse { r#ref: refs }))
+    }
+
+    type WaitForTxStream = Pin<Box<dyn Stream<Item = Result<WaitForTxResponse, Status>> + Send>>;
+
+    async fn wait_for_tx(
+        &self,
+        _request: Request<WaitForTxRequest>,
+    ) -> Result<Response<Self::WaitForTxStream>, Status> {
+        Err(Status::unimplemented(
+            "Waiting for transactions is not available",
+        ))
+    }
+
+    async fn read_mempool(
+        &self,
+        _request: Request<ReadMempoolRequest>,
+    ) -> Result<Response<ReadMempoolResponse>, Status> {
+        Err(Status::unimplemented("Mempool is not available"))
+    }
+
+    type WatchMempoolStream =
+        Pin<Box<dyn Stream<Item = Result<WatchMempoolResponse, Status>> + Send>>;
+
+    async fn watch_mempool(
+        &self,
+        _request: Request<WatchMempoolRequest>,
+    ) -> Result<Response<Self::WatchMempoolStream>, Status> {
+        Err(Status::unimplemented("Mempool is not available"))
+    }
+}
@@ -0,0 +1,289 @@
+//! UtxoRPC sync service, backed by chain-store block queries and the live block topic
+
+use std::{pin::Pin, sync::Arc};
+
+use acropolis_common::{
+    messages::{Message, StateQuery, StateQueryResponse},
+    queries::{
+        blocks::{BlockCBOR, BlockKey, BlocksStateQuery, BlocksStateQueryResponse},
+        errors::QueryError,
+        utils::query_state,
+    },
+    Point,
+};
+use caryatid_sdk::Context;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{Code, Request, Response, Status};
+use utxorpc_spec::utxorpc::v1alpha::sync::{
+    follow_tip_response::Action, sync_service_server::SyncService, DumpHistoryRequest,
+    DumpHistoryResponse, FetchBlockRequest, FetchBlockResponse, FollowTipRequest,
+    FollowTipResponse, ReadTipRequest, ReadTipResponse,
+};
+
+use crate::conversions::{
+    any_chain_block, block_ref, parse_block_hash, point_to_block_ref, query_error_status,
+};
+
+/// Maximum blocks returned by a single `DumpHistory` or `FetchBlock` call
+const MAX_BLOCKS_PER_REQUEST: u64 = 100;
+
+/// Change at the chain tip, fanned out to `FollowTip` subscribers
+#[derive(Debug, Clone)]
+pub enum TipEvent {
+    Apply(BlockCBOR),
+    Reset(Point),
+}
+
+#[derive(Clone)]
+pub struct UtxoRpcSyncService {
+    context: Arc<Context<Message>>,
+    blocks_query_topic: String,
+    tip: broadcast::Sender<TipEvent>,
+    buffer: usize,
+}
+
+impl UtxoRpcSyncService {
+    pub fn new(
+        context: Arc<Context<Message>>,
+        blocks_query_topic: String,
+        tip: broadcast::Sender<TipEvent>,
+        buffer: usize,
+    ) -> Self {
+        Self {
+            context,
+            blocks_query_topic,
+            tip,
+            buffer,
+        }
+    }
+
+    async fn query_blocks(
+        &self,
+        query: BlocksStateQuery,
+    ) -> Result<BlocksStateQueryResponse, Status> {
+        let msg = Arc::new(Message::StateQuery(StateQuery::Blocks(query)));
+        query_state(
+            &self.context,
+            &self.blocks_query_topic,
+            msg,
+            |message| match message {
+                Message::StateQueryResponse(StateQueryResponse::Blocks(
+                    BlocksStateQueryResponse::Error(e),
+                )) => Err(e),
+                Message::StateQueryResponse(StateQueryResponse::Blocks(response)) => Ok(response),
+                _ => Err(QueryError::internal_error(
+                    "Unexpected message type while querying blocks",
+                )),
+            },
+        )
+        .await
+        .map_err(query_error_status)
+    }
+
+    async fn block_cbor(&self, block_key: BlockKey) -> Result<BlockCBOR, Status> {
+        match self.query_blocks(BlocksStateQuery::GetBlockCBOR { block_key }).await? {
+            BlocksStateQueryResponse::BlockCBOR(block) => Ok(block),
+            _ => Err(Status::internal(
+                "Unexpected response while retrieving block",
+            )),
+        }
+    }
+
+    async fn blocks_cbor(
+        &self,
+        min_number: u64,
+        max_number: u64,
+    ) -> Result<Vec<BlockCBOR>, Status> {
+        match self
+            .query_blocks(BlocksStateQuery::GetBlocksCBORByNumberRange {
+                min_number,
+                max_number,
+            })
+            .await?
+        {
+            BlocksStateQueryResponse::BlocksCBORByNumberRange(blocks) => Ok(blocks),
+            _ => Err(Status::internal(
+                "Unexpected response while retrieving blocks",
+            )),
+        }
+    }
+
+    /// Stream stored blocks after `start`, then live tip changes, into `sender`
+    async fn follow(
+        self,
+        start: Option<BlockCBOR>,
+        mut live: broadcast::Receiver<TipEvent>,
+        sender: mpsc::Sender<Result<FollowTipResponse, Status>>,
+    ) {
+        let send = |action| {
+            let sender = sender.clone();
+            async move {
+                sender
+                    .send(Ok(FollowTipResponse {
+                        action: Some(action),
+                    }))
+                    .await
+                    .is_ok()
+            }
+        };
+
+        // Highest block already sent from the store, so the live feed can skip overlap
+        let mut replayed = None;
+        if let Some(start) = start {
+            if !send(Action::Reset(block_ref(&start.hash, start.slot))).await {
+                return;
+            }
+
+            let mut next = start.number + 1;
+            loop {
+                let blocks = match self.blocks_cbor(next, next + MAX_BLOCKS_PER_REQUEST - 1).await {
+                    Ok(blocks) => blocks,
+                    Err(e) => {
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    }
+                };
+                let Some(last) = blocks.last() else {
+                    break;
+                };
+                next = last.number + 1;
+                replayed = Some(last.number);
+
+                for block in blocks {
+                    if !send(Action::Apply(any_chain_block(block))).await {
+                        return;
+                    }
+                }
+            }
+        }
+
+        loop {
+            let action = match live.recv().await {
+                Ok(TipEvent::Apply(block)) => {
+                    if replayed.is_some_and(|number| block.number <= number) {
+                        continue;
+                    }
+                    replayed = None;
+                    Action::Apply(any_chain_block(block))
+                }
+                Ok(TipEvent::Reset(point)) => {
+                    replayed = None;
+                    Action::Reset(point_to_block_ref(&point))
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    let _ = sender
+                        .send(Err(Status::resource_exhausted(format!(
+                            "Client fell behind the chain tip by {skipped} events"
+                        ))))
+                        .await;
+                    return;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            if !send(action).await {
+                return;
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SyncService for UtxoRpcSyncService {
+    async fn fetch_block(
+        &self,
+        request: Request<FetchBlockRequest>,
+    ) -> Result<Response<FetchBlockResponse>, Status> {
+        let refs = request.into_inner().r#ref;
+        if refs.len() as u64 > MAX_BLOCKS_PER_REQUEST {
+            return Err(Status::invalid_argument(format!(
+                "At most {MAX_BLOCKS_PER_REQUEST} blocks can be fetched at once"
+            )));
+        }
+
+        let mut block = Vec::with_capacity(refs.len());
+        for r in refs {
+            let hash = parse_block_hash(&r.hash)?;
+            block.push(any_chain_block(
+                self.block_cbor(BlockKey::Hash(hash)).await?,
+            ));
+        }
+
+        Ok(Response::new(FetchBlockResponse { block }))
+    }
+
+    async fn dump_history(
+        &self,
+        request: Request<DumpHistoryRequest>,
+    ) -> Result<Response<DumpHistoryResponse>, Status> {
+        let req = request.into_inner();
+        let max_items = (req.max_items as u64).clamp(1, MAX_BLOCKS_PER_REQUEST);
+
+        let start = match req.start_token {
+            Some(token) => {
+                let hash = parse_block_hash(&token.hash)?;
+                self.block_cbor(BlockKey::Hash(hash)).await?.number
+            }
+            None => 0,
+        };
+
+        // Fetch one extra block to use as the next page token
+        let mut blocks = self.blocks_cbor(start, start + max_items).await?;
+        let next_token = if blocks.len() as u64 > max_items {
+            blocks.pop().map(|next| block_ref(&next.hash, next.slot))
+        } else {
+            None
+        };
+
+        Ok(Response::new(DumpHistoryResponse {
+            block: blocks.into_iter().map(any_chain_block).collect(),
+            next_token,
+        }))
+    }
+
+    type FollowTipStream = Pin<Box<dyn Stream<Item = Result<FollowTipResponse, Status>> + Send>>;
+
+    async fn follow_tip(
+        &self,
+        request: Request<FollowTipRequest>,
+    ) -> Result<Response<Self::FollowTipStream>, Status> {
+        let intersect = request.into_inner().intersect;
+
+        // Subscribe before replaying stored blocks, so none are missed in between
+        let live = self.tip.subscribe();
+
+        let mut start = None;
+        for r in &intersect {
+            let hash = parse_block_hash(&r.hash)?;
+            match self.block_cbor(BlockKey::Hash(hash)).await {
+                Ok(block) => {
+                    start = Some(block);
+                    break;
+                }
+                Err(status) if status.code() == Code::NotFound => continue,
+                Err(status) => return Err(status),
+            }
+        }
+        if !intersect.is_empty() && start.is_none() {
+            return Err(Status::not_found("No intersection found"));
+        }
+
+        let (sender, receiver) = mpsc::channel(self.buffer);
+        tokio::spawn(self.clone().follow(start, live, sender));
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    async fn read_tip(
+        &self,
+        _request: Request<ReadTipRequest>,
+    ) -> Result<Response<ReadTipResponse>, Status> {
+        match self.query_blocks(BlocksStateQuery::GetLatestBlock).await? {
+            BlocksStateQueryResponse::LatestBlock(block) => Ok(Response::new(ReadTipResponse {
+                tip: Some(block_ref(&block.hash, block.slot)),
+            })),
+            _ => Err(Status::internal("Unexpected response while retrieving tip")),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Acropolis UtxoRPC module for Caryatid
+//!
+//! Serves the UtxoRPC (u5c) sync, query and submit services over gRPC, so that
+//! clients built for Dolos or other u5c providers can use Acropolis directly.
+//! Requests are answered from the existing state query topics; `FollowTip`
+//! streams blocks from the block topic.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use acropolis_common::{
+    caryatid::RollbackWrapper,
+    configuration::{get_bool_flag, get_string_flag, get_u64_flag},
+    declare_cardano_reader,
+    messages::{CardanoMessage, Message, RawBlockMessage, StateTransitionMessage},
+    queries::{
+        addresses::DEFAULT_ADDRESS_QUERY_TOPIC, blocks::BlockCBOR,
+        blocks::DEFAULT_BLOCKS_QUERY_TOPIC, parameters::DEFAULT_PARAMETERS_QUERY_TOPIC,
+        utxos::DEFAULT_UTXOS_QUERY_TOPIC,
+    },
+};
+use anyhow::{bail, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+mod conversions;
+mod query;
+mod server;
+mod submit;
+mod sync;
+
+use query::{QueryTopics, UtxoRpcQueryService};
+use submit::UtxoRpcSubmitService;
+use sync::{TipEvent, UtxoRpcSyncService};
+
+declare_cardano_reader!(
+    BlockReader,
+    "blocks-subscribe-topic",
+    "cardano.block.available",
+    BlockAvailable,
+    RawBlockMessage
+);
+
+const DEFAULT_ENABLED: (&str, bool) = ("enabled", false);
+const DEFAULT_ADDRESS: (&str, &str) = ("address", "127.0.0.1");
+const DEFAULT_PORT: (&str, u64) = ("port", 50052);
+const DEFAULT_SUBMIT_TOPIC: (&str, &str) = ("submit-topic", "cardano.txs.submit");
+/// Tip changes held for `FollowTip` clients before slow ones are disconnected
+const DEFAULT_TIP_CHANNEL_CAPACITY: (&str, u64) = ("tip-channel-capacity", 256);
+/// Responses buffered per `FollowTip` stream
+const DEFAULT_STREAM_BUFFER: (&str, u64) = ("stream-buffer", 16);
+
+/// UtxoRPC module
+#[module(
+    message_type(Message),
+    name = "utxorpc",
+    description = "UtxoRPC (u5c) gRPC service"
+)]
+pub struct UtxoRpc;
+
+impl UtxoRpc {
+    async fn run(mut reader: BlockReader, tip: broadcast::Sender<TipEvent>) -> Result<()> {
+        loop {
+            let event = match reader.read_with_rollbacks().await? {
+                RollbackWrapper::Normal((block_info, block)) => TipEvent::Apply(BlockCBOR {
+                    hash: block_info.hash,
+                    number: block_info.number,
+                    slot: block_info.slot,
+                    cbor: block.body.clone(),
+                }),
+                RollbackWrapper::Rollback((_, message)) => match message.as_ref() {
+                    Message::Cardano((
+                        _,
+                        CardanoMessage::StateTransition(StateTransitionMessage::Rollback(point)),
+                    )) => TipEvent::Reset(point.clone()),
+                    _ => continue,
+                },
+            };
+
+            // Sending only fails when nobody is following the tip
+            let _ = tip.send(event);
+        }
+    }
+
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        if !get_bool_flag(&config, DEFAULT_ENABLED) {
+            info!("UtxoRPC server is disabled in configuration");
+            return Ok(());
+        }
+
+        let address = get_string_flag(&config, DEFAULT_ADDRESS);
+        let port: u16 = get_u64_flag(&config, DEFAULT_PORT).try_into()?;
+        let addr: SocketAddr = format!("{address}:{port}").parse()?;
+        let capacity = get_u64_flag(&config, DEFAULT_TIP_CHANNEL_CAPACITY).max(1) as usize;
+        let buffer = get_u64_flag(&config, DEFAULT_STREAM_BUFFER).max(1) as usize;
+
+        let topics = QueryTopics {
+            blocks: get_string_flag(&config, DEFAULT_BLOCKS_QUERY_TOPIC),
+            parameters: get_string_flag(&config, DEFAULT_PARAMETERS_QUERY_TOPIC),
+            utxos: get_string_flag(&config, DEFAULT_UTXOS_QUERY_TOPIC),
+            addresses: get_string_flag(&config, DEFAULT_ADDRESS_QUERY_TOPIC),
+        };
+        let submit_topic = get_string_flag(&config, DEFAULT_SUBMIT_TOPIC);
+
+        let block_reader = BlockReader::new(&context, &config).await?;
+        let (tip, _) = broadcast::channel(capacity);
+
+        let sync =
+            UtxoRpcSyncService::new(context.clone(), topics.blocks.clone(), tip.clone(), buffer);
+        let query = UtxoRpcQueryService::new(context.clone(), topics);
+        let submit = UtxoRpcSubmitService::new(context.clone(), submit_topic);
+
+        context.run(async move {
+            Self::run(block_reader, tip).await.unwrap_or_else(|e| error!("Failed: {e}"));
+        });
+
+        tokio::spawn(async move {
+            if let Err(e) = server::run(addr, sync, query, submit).await {
+                error!("UtxoRPC server error: {e}");
+            }
+        });
+
+        Ok(())
+    }
+}
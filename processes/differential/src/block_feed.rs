@@ -0,0 +1,250 @@
+//! Block feed module - once a snapshot has been bootstrapped, publishes the
+//! captured blocks which follow it up to the first block of the target epoch,
+//! then collects the state digests the modules publish as the epoch before ends
+
+use acropolis_common::{
+    commands::chain_sync::ChainSyncCommand,
+    configuration::{deserialize_duration, get_string_flag, ModuleConfig},
+    messages::{CardanoMessage, Command, Message},
+    state_digest::DEFAULT_STATE_DIGEST_TOPIC,
+    upstream_cache::UpstreamCache,
+    BlockInfo, Point,
+};
+use anyhow::{bail, Result};
+use caryatid_sdk::{module, Context, Subscription};
+use config::Config;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock,
+    },
+    time::Duration,
+};
+use tokio::{sync::Notify, time::Instant};
+use tracing::{error, info, warn};
+
+use crate::report::DigestSet;
+
+/// Notified once the digests have been written, or the run has failed
+static FINISHED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Whether the digests were written
+static SUCCEEDED: AtomicBool = AtomicBool::new(false);
+
+/// The `[module.block-feed]` section
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct BlockFeedConfig {
+    /// Upstream cache holding the captured blocks
+    pub cache_dir: String,
+
+    /// Topic to publish the blocks on
+    pub block_topic: String,
+
+    /// Topic the snapshot bootstrapper sends the point to sync from on
+    pub sync_command_topic: String,
+
+    /// Blocks are fed up to and including the first block of this epoch
+    pub target_epoch: u64,
+
+    /// Modules expected to publish a digest
+    pub modules: Vec<String>,
+
+    /// How long to wait for the digests once the last block is published
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+
+    /// File the digests are written to
+    pub output: String,
+}
+
+impl Default for BlockFeedConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: "upstream-cache".to_string(),
+            block_topic: "cardano.block.proposed".to_string(),
+            sync_command_topic: "cardano.sync.command".to_string(),
+            target_epoch: 0,
+            modules: [
+                "utxo-state",
+                "spo-state",
+                "drep-state",
+                "accounts-state",
+                "parameters-state",
+            ]
+            .map(String::from)
+            .to_vec(),
+            timeout: Duration::from_secs(3600),
+            output: "digests.json".to_string(),
+        }
+    }
+}
+
+impl ModuleConfig for BlockFeedConfig {
+    const MODULE: &'static str = "block-feed";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.target_epoch == 0 {
+            errors.push("target-epoch must be given".to_string());
+        }
+        if self.modules.is_empty() {
+            errors.push("modules must name at least one module".to_string());
+        }
+    }
+}
+
+/// Block feed module
+#[module(
+    message_type(Message),
+    name = "block-feed",
+    description = "Feeds captured blocks after a snapshot and collects the state digests"
+)]
+pub struct BlockFeed;
+
+impl BlockFeed {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = BlockFeedConfig::load(&config)?;
+        let digest_topic = get_string_flag(&config, DEFAULT_STATE_DIGEST_TOPIC);
+
+        info!("Creating subscriber on '{}'", cfg.sync_command_topic);
+        let commands = context.subscribe(&cfg.sync_command_topic).await?;
+        info!("Creating subscriber on '{digest_topic}'");
+        let digests = context.subscribe(&digest_topic).await?;
+
+        let run_context = context.clone();
+        context.run(async move {
+            let succeeded = match Self::run(&run_context, &cfg, commands, digests).await {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Block feed failed: {e:#}");
+                    false
+                }
+            };
+            SUCCEEDED.store(succeeded, Ordering::SeqCst);
+            FINISHED.notify_one();
+        });
+
+        Ok(())
+    }
+
+    async fn run(
+        context: &Arc<Context<Message>>,
+        cfg: &BlockFeedConfig,
+        mut commands: Box<dyn Subscription<Message>>,
+        digests: Box<dyn Subscription<Message>>,
+    ) -> Result<()> {
+        let point = Self::wait_for_sync_point(&mut commands).await?;
+        let last = Self::feed(context, cfg, &point).await?;
+        info!(
+            "Published blocks up to {} in slot {}, epoch {}",
+            last.number, last.slot, last.epoch
+        );
+
+        let set = Self::collect_digests(cfg, digests, last.epoch - 1).await?;
+        std::fs::write(&cfg.output, serde_json::to_vec_pretty(&set)?)?;
+        info!("Wrote {} digests to {}", set.digests.len(), cfg.output);
+        Ok(())
+    }
+
+    /// The point the snapshot was taken at, once it has been bootstrapped
+    async fn wait_for_sync_point(commands: &mut Box<dyn Subscription<Message>>) -> Result<Point> {
+        loop {
+            let (_, message) = commands.read().await?;
+            if let Message::Command(Command::ChainSync(
+                ChainSyncCommand::FindIntersect(point) | ChainSyncCommand::StartMithril(point),
+            )) = message.as_ref()
+            {
+                info!("Snapshot bootstrapped at slot {}", point.slot());
+                return Ok(point.clone());
+            }
+        }
+    }
+
+    /// Publish the cached blocks after `point`, returning the first one in the
+    /// target epoch
+    async fn feed(
+        context: &Arc<Context<Message>>,
+        cfg: &BlockFeedConfig,
+        point: &Point,
+    ) -> Result<BlockInfo> {
+        let mut cache = UpstreamCache::new(&cfg.cache_dir)?;
+        cache.start_reading()?;
+
+        let mut found_point = false;
+        let mut count = 0;
+        while let Some(record) = cache.read_record()? {
+            cache.next_record()?;
+            let block = record.id;
+            if block.slot < point.slot() {
+                continue;
+            }
+            if !found_point {
+                if block.slot != point.slot() || Some(&block.hash) != point.hash() {
+                    bail!(
+                        "Captured blocks in {} don't include the snapshot point at slot {}",
+                        cfg.cache_dir,
+                        point.slot()
+                    );
+                }
+                found_point = true;
+                continue;
+            }
+
+            let message = Arc::new(Message::Cardano((
+                block.clone(),
+                CardanoMessage::BlockAvailable(Arc::unwrap_or_clone(record.message)),
+            )));
+            context.message_bus.publish(&cfg.block_topic, message).await?;
+            count += 1;
+
+            if block.epoch >= cfg.target_epoch {
+                info!("Published {count} captured blocks");
+                return Ok(block);
+            }
+        }
+
+        bail!(
+            "Captured blocks in {} end before epoch {}",
+            cfg.cache_dir,
+            cfg.target_epoch
+        )
+    }
+
+    /// Digests published for the end of `epoch`, until every expected module has
+    /// published one or the timeout runs out
+    async fn collect_digests(
+        cfg: &BlockFeedConfig,
+        mut digests: Box<dyn Subscription<Message>>,
+        epoch: u64,
+    ) -> Result<DigestSet> {
+        let mut set = DigestSet {
+            epoch,
+            ..DigestSet::default()
+        };
+        let deadline = Instant::now() + cfg.timeout;
+        while !cfg.modules.iter().all(|module| set.digests.contains_key(module)) {
+            let Ok(read) = tokio::time::timeout_at(deadline, digests.read()).await else {
+                let missing: Vec<_> =
+                    cfg.modules.iter().filter(|m| !set.digests.contains_key(*m)).collect();
+                warn!("No digests for the end of epoch {epoch} from {missing:?}");
+                break;
+            };
+            let (_, message) = read?;
+            if let Message::Cardano((_, CardanoMessage::StateDigest(digest))) = message.as_ref() {
+                if digest.epoch == epoch {
+                    set.digests.insert(digest.module.clone(), digest.digest);
+                    if !digest.sections.is_empty() {
+                        set.sections.insert(digest.module.clone(), digest.sections.clone());
+                    }
+                }
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Wait until the block feed has finished, returning whether it wrote the digests
+pub async fn finished() -> bool {
+    FINISHED.notified().await;
+    SUCCEEDED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,218 @@
+//! 'main' for the Acropolis differential testing process
+//!
+//! Checks that replaying an epoch of blocks from one ledger snapshot arrives at
+//! the state in the next snapshot. Each side is a separate run of this process:
+//! one bootstraps from the snapshot for epoch N and is fed the captured blocks of
+//! epoch N, the other bootstraps from the snapshot for epoch N+1. Both are then
+//! fed the first block of epoch N+1, on which every module publishes the digest
+//! of its state as epoch N ended, and the digests are compared module by module.
+//! A mismatch names the module whose state diverged and, for modules which publish
+//! a digest per section of their state, the sections - not the entries within them.
+
+use acropolis_common::messages::Message;
+use anyhow::{bail, Context, Result};
+use caryatid_process::Process;
+use caryatid_sdk::ModuleRegistry;
+use config::{Config, Environment, File};
+use std::{path::Path, sync::Arc};
+use tokio::process::Command;
+use tracing::{error, info};
+use tracing_subscriber::{filter, fmt, prelude::*, EnvFilter, Registry};
+
+// External modules
+use acropolis_module_accounts_state::AccountsState;
+use acropolis_module_block_unpacker::BlockUnpacker;
+use acropolis_module_drdd_state::DRDDState;
+use acropolis_module_drep_state::DRepState;
+use acropolis_module_epochs_state::EpochsState;
+use acropolis_module_genesis_bootstrapper::GenesisBootstrapper;
+use acropolis_module_governance_state::GovernanceState;
+use acropolis_module_parameters_state::ParametersState;
+use acropolis_module_snapshot_bootstrapper::SnapshotBootstrapper;
+use acropolis_module_spdd_state::SPDDState;
+use acropolis_module_spo_state::SPOState;
+use acropolis_module_stake_delta_filter::StakeDeltaFilter;
+use acropolis_module_tx_unpacker::TxUnpacker;
+use acropolis_module_utxo_state::UTXOState;
+
+mod block_feed;
+mod report;
+
+use block_feed::BlockFeed;
+use report::{DigestSet, Report};
+
+fn setup_run(process: &mut dyn ModuleRegistry<Message>) {
+    GenesisBootstrapper::register(process);
+    SnapshotBootstrapper::register(process);
+    BlockUnpacker::register(process);
+    TxUnpacker::register(process);
+    UTXOState::register(process);
+    SPOState::register(process);
+    DRepState::register(process);
+    GovernanceState::register(process);
+    ParametersState::register(process);
+    StakeDeltaFilter::register(process);
+    EpochsState::register(process);
+    AccountsState::register(process);
+    SPDDState::register(process);
+    DRDDState::register(process);
+
+    BlockFeed::register(process);
+}
+
+#[derive(Debug, clap::Parser)]
+#[command(name = "acropolis_process_differential")]
+struct Args {
+    #[arg(long, value_name = "PATH", default_values_t = vec!["differential.toml".to_string()])]
+    config: Vec<String>,
+
+    /// Epoch of the snapshot to replay from - the snapshot for the epoch after
+    /// it is the reference
+    #[arg(long)]
+    epoch: u64,
+
+    /// File to write the comparison to
+    #[arg(long, value_name = "PATH", default_value = "differential-report.json")]
+    report: String,
+
+    /// Directory for the digests from each run
+    #[arg(long, value_name = "PATH", default_value = "differential")]
+    work_dir: String,
+
+    /// Do a single run, bootstrapping from the snapshot for this epoch
+    #[arg(long, hide = true, requires = "digests")]
+    run_from: Option<u64>,
+
+    /// File a single run writes its digests to
+    #[arg(long, hide = true)]
+    digests: Option<String>,
+}
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let args = <self::Args as clap::Parser>::parse();
+
+    // Standard logging using RUST_LOG for log levels default to INFO for events only
+    let fmt_layer = fmt::layer()
+        .with_filter(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info")))
+        .with_filter(filter::filter_fn(|meta| meta.is_event()));
+    Registry::default().with(fmt_layer).init();
+
+    match (args.run_from, &args.digests) {
+        (Some(snapshot_epoch), Some(digests)) => run(&args, snapshot_epoch, digests).await,
+        _ => compare(&args).await,
+    }
+}
+
+/// Run both sides, then compare their digests
+async fn compare(args: &Args) -> Result<()> {
+    info!("Acropolis differential test of epoch {}", args.epoch);
+    std::fs::create_dir_all(&args.work_dir)?;
+
+    let replayed = run_side(args, "replayed", args.epoch).await?;
+    let reference = run_side(args, "reference", args.epoch + 1).await?;
+
+    let report = Report::compare(args.epoch, args.epoch + 1, &replayed, &reference);
+    std::fs::write(&args.report, serde_json::to_vec_pretty(&report)?)?;
+    info!("Wrote report to {}", args.report);
+
+    let divergences: Vec<_> = report.divergences().collect();
+    for divergence in &divergences {
+        error!(
+            module = %divergence.module,
+            outcome = ?divergence.outcome,
+            replayed = ?divergence.replayed,
+            reference = ?divergence.reference,
+            "State diverges at the end of epoch {}",
+            args.epoch
+        );
+        for section in divergence.divergent_sections() {
+            error!(
+                module = %divergence.module,
+                section = %section.section,
+                outcome = ?section.outcome,
+                replayed = ?section.replayed,
+                reference = ?section.reference,
+                "Section diverges at the end of epoch {}",
+                args.epoch
+            );
+        }
+    }
+    if !divergences.is_empty() {
+        bail!(
+            "{} of {} modules diverge from the snapshot for epoch {}",
+            divergences.len(),
+            report.modules.len(),
+            args.epoch + 1
+        );
+    }
+
+    info!(
+        "All {} modules match the snapshot for epoch {}",
+        report.modules.len(),
+        args.epoch + 1
+    );
+    Ok(())
+}
+
+/// Run this process again to bootstrap from one snapshot, reading back the
+/// digests it writes
+async fn run_side(args: &Args, side: &str, snapshot_epoch: u64) -> Result<DigestSet> {
+    let digests = Path::new(&args.work_dir).join(format!("{side}.json"));
+    info!("Running the {side} side from the snapshot for epoch {snapshot_epoch}");
+
+    let mut command = Command::new(std::env::current_exe()?);
+    for file in &args.config {
+        command.arg("--config").arg(file);
+    }
+    let status = command
+        .arg("--epoch")
+        .arg(args.epoch.to_string())
+        .arg("--run-from")
+        .arg(snapshot_epoch.to_string())
+        .arg("--digests")
+        .arg(&digests)
+        .status()
+        .await?;
+    if !status.success() {
+        bail!("The {side} run failed ({status})");
+    }
+
+    let json = std::fs::read(&digests)
+        .with_context(|| format!("reading {side} digests from {}", digests.display()))?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Bootstrap from a snapshot and feed blocks up to the first of the epoch after
+/// the one being tested
+async fn run(args: &Args, snapshot_epoch: u64, digests: &str) -> Result<()> {
+    // Read the config, pointing it at this run's snapshot and target
+    let mut builder = Config::builder();
+    for file in &args.config {
+        builder = builder.add_source(File::with_name(file));
+    }
+    let config = Arc::new(
+        builder
+            .add_source(Environment::with_prefix("ACROPOLIS"))
+            .set_override("global.state-digest.enabled", true)?
+            .set_override("module.snapshot-bootstrapper.epoch", snapshot_epoch)?
+            .set_override("module.block-feed.target-epoch", args.epoch + 1)?
+            .set_override("module.block-feed.output", digests)?
+            .build()?,
+    );
+
+    let mut process = Process::<Message>::create(config).await;
+    setup_run(&mut process);
+
+    // Run it, until the digests are written
+    tokio::select! {
+        result = process.run() => result?,
+        succeeded = block_feed::finished() => {
+            if !succeeded {
+                bail!("Run from the snapshot for epoch {snapshot_epoch} failed");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! Comparison of the state digests from a replay against those from a reference
+//! snapshot
+//!
+//! Each module publishes one digest over all of its state, and some also a digest
+//! per section of it (pools, pending retirements, rewards, pots, ...), so a
+//! divergence is reported per module and, where the module splits its state up,
+//! per section - but not per entry.
+
+use acropolis_common::hash::Hash;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Digests of each module's state at the end of an epoch, from one run
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DigestSet {
+    /// Epoch which has ended
+    pub epoch: u64,
+
+    /// By module
+    pub digests: BTreeMap<String, Hash<32>>,
+
+    /// By module then section, for modules which split their state up
+    #[serde(default)]
+    pub sections: BTreeMap<String, BTreeMap<String, Hash<32>>>,
+}
+
+/// How a module's (or section's) replayed state compares with the reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Matches,
+    Differs,
+    /// The replay published no digest for it
+    MissingFromReplay,
+    /// The reference run published no digest for it
+    MissingFromReference,
+}
+
+impl Outcome {
+    fn of(replayed: Option<Hash<32>>, reference: Option<Hash<32>>) -> Self {
+        match (replayed, reference) {
+            (Some(a), Some(b)) if a == b => Outcome::Matches,
+            (Some(_), Some(_)) => Outcome::Differs,
+            (None, _) => Outcome::MissingFromReplay,
+            (_, None) => Outcome::MissingFromReference,
+        }
+    }
+}
+
+/// One section of a module's state, from both runs
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SectionComparison {
+    pub section: String,
+    pub outcome: Outcome,
+    pub replayed: Option<Hash<32>>,
+    pub reference: Option<Hash<32>>,
+}
+
+/// One module's digests from both runs
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ModuleComparison {
+    pub module: String,
+    pub outcome: Outcome,
+    pub replayed: Option<Hash<32>>,
+    pub reference: Option<Hash<32>>,
+
+    /// In section order - empty if neither run split the module's state up
+    pub sections: Vec<SectionComparison>,
+}
+
+impl ModuleComparison {
+    /// Sections whose state doesn't match, or couldn't be compared
+    pub fn divergent_sections(&self) -> impl Iterator<Item = &SectionComparison> {
+        self.sections.iter().filter(|s| s.outcome != Outcome::Matches)
+    }
+}
+
+/// Result of a differential run
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Report {
+    /// Epoch the replay started from
+    pub snapshot_epoch: u64,
+
+    /// Epoch whose snapshot is the reference
+    pub reference_epoch: u64,
+
+    pub modules: Vec<ModuleComparison>,
+}
+
+impl Report {
+    /// Compare module by module, in module order, and section by section within
+    /// each module
+    pub fn compare(
+        snapshot_epoch: u64,
+        reference_epoch: u64,
+        replayed: &DigestSet,
+        reference: &DigestSet,
+    ) -> Self {
+        let modules: BTreeSet<&String> =
+            replayed.digests.keys().chain(reference.digests.keys()).collect();
+        let modules = modules
+            .into_iter()
+            .map(|module| {
+                let sections = Self::compare_sections(
+                    replayed.sections.get(module),
+                    reference.sections.get(module),
+                );
+                let replayed = replayed.digests.get(module).copied();
+                let reference = reference.digests.get(module).copied();
+                ModuleComparison {
+                    module: module.clone(),
+                    outcome: Outcome::of(replayed, reference),
+                    replayed,
+                    reference,
+                    sections,
+                }
+            })
+            .collect();
+
+        Self {
+            snapshot_epoch,
+            reference_epoch,
+            modules,
+        }
+    }
+
+    fn compare_sections(
+        replayed: Option<&BTreeMap<String, Hash<32>>>,
+        reference: Option<&BTreeMap<String, Hash<32>>>,
+    ) -> Vec<SectionComparison> {
+        let empty = BTreeMap::new();
+        let replayed = replayed.unwrap_or(&empty);
+        let reference = reference.unwrap_or(&empty);
+        let sections: BTreeSet<&String> = replayed.keys().chain(reference.keys()).collect();
+        sections
+            .into_iter()
+            .map(|section| {
+                let replayed = replayed.get(section).copied();
+                let reference = reference.get(section).copied();
+                SectionComparison {
+                    section: section.clone(),
+                    outcome: Outcome::of(replayed, reference),
+                    replayed,
+                    reference,
+                }
+            })
+            .collect()
+    }
+
+    /// Modules whose state doesn't match, or couldn't be compared
+    pub fn divergences(&self) -> impl Iterator<Item = &ModuleComparison> {
+        self.modules.iter().filter(|m| m.outcome != Outcome::Matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests(entries: &[(&str, u8)]) -> DigestSet {
+        DigestSet {
+            epoch: 500,
+            digests: entries
+                .iter()
+                .map(|(module, n)| (module.to_string(), Hash::new([*n; 32])))
+                .collect(),
+            sections: BTreeMap::new(),
+        }
+    }
+
+    fn with_sections(mut set: DigestSet, module: &str, entries: &[(&str, u8)]) -> DigestSet {
+        let sections =
+            entries.iter().map(|(section, n)| (section.to_string(), Hash::new([*n; 32]))).collect();
+        set.sections.insert(module.to_string(), sections);
+        set
+    }
+
+    #[test]
+    fn reports_each_module_once_in_order() {
+        let replayed = digests(&[("utxo-state", 1), ("spo-state", 2), ("drep-state", 3)]);
+        let reference = digests(&[("utxo-state", 1), ("spo-state", 9), ("accounts-state", 4)]);
+        let report = Report::compare(500, 501, &replayed, &reference);
+
+        let outcomes: Vec<_> =
+            report.modules.iter().map(|m| (m.module.as_str(), m.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("accounts-state", Outcome::MissingFromReplay),
+                ("drep-state", Outcome::MissingFromReference),
+                ("spo-state", Outcome::Differs),
+                ("utxo-state", Outcome::Matches),
+            ]
+        );
+        assert_eq!(report.divergences().count(), 3);
+        assert_eq!(report.modules[2].reference, Some(Hash::new([9; 32])));
+    }
+
+    #[test]
+    fn identical_digests_have_no_divergences() {
+        let replayed = digests(&[("utxo-state", 1), ("spo-state", 2)]);
+        let report = Report::compare(500, 501, &replayed, &replayed.clone());
+        assert_eq!(report.divergences().count(), 0);
+    }
+
+    #[test]
+    fn narrows_divergence_to_sections() {
+        let replayed = with_sections(
+            digests(&[("utxo-state", 1), ("spo-state", 2)]),
+            "spo-state",
+            &[("pools", 5), ("retirements", 6), ("updates", 7)],
+        );
+        let reference = with_sections(
+            digests(&[("utxo-state", 1), ("spo-state", 3)]),
+            "spo-state",
+            &[("pools", 5), ("retirements", 8)],
+        );
+        let report = Report::compare(500, 501, &replayed, &reference);
+
+        let spo = &report.modules[0];
+        assert_eq!(spo.outcome, Outcome::Differs);
+        let sections: Vec<_> =
+            spo.sections.iter().map(|s| (s.section.as_str(), s.outcome)).collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("pools", Outcome::Matches),
+                ("retirements", Outcome::Differs),
+                ("updates", Outcome::MissingFromReference),
+            ]
+        );
+        assert_eq!(spo.divergent_sections().count(), 2);
+
+        // Modules which don't split their state up have no sections
+        assert!(report.modules[1].sections.is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+//! The fault injector disturbs a topic as configured
+
+use acropolis_common::messages::Message;
+use acropolis_module_fault_injector::FaultInjector;
+use acropolis_simulation::Simulation;
+use anyhow::Result;
+
+const CONFIG: &str = r#"
+[module.fault-injector]
+seed = 1
+
+[[module.fault-injector.rule]]
+subscribe-topic = "test.clean"
+publish-topic = "test.faulty"
+duplicate = 1.0
+"#;
+
+#[tokio::test]
+async fn fault_injector_duplicates_relayed_messages() -> Result<()> {
+    let sim = Simulation::new(CONFIG)?;
+    sim.start("fault-injector", |context, config| async move {
+        FaultInjector.init(context, config).await
+    })
+    .await?;
+    sim.capture("test.faulty").await?;
+
+    for n in 0..3 {
+        sim.publish("test.clean", Message::String(n.to_string())).await?;
+    }
+
+    let relayed: Vec<String> = sim
+        .captured("test.faulty")
+        .iter()
+        .map(|message| match message.as_ref() {
+            Message::String(s) => s.clone(),
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(relayed, vec!["0", "0", "1", "1", "2", "2"]);
+    Ok(())
+}
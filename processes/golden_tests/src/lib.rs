@@ -8,6 +8,7 @@ use std::{
 use anyhow::Result;
 
 use acropolis_common::messages::Message;
+use acropolis_module_fault_injector::FaultInjector;
 use acropolis_module_snapshot_bootstrapper::SnapshotBootstrapper;
 use acropolis_module_spo_state::SPOState;
 use acropolis_module_tx_unpacker::TxUnpacker;
@@ -16,6 +17,8 @@ use config::{Config, Environment, File};
 use test_module::TestModule;
 use tokio::{sync::watch, time::timeout};
 
+mod fault_tests;
+mod rollback_tests;
 mod test_module;
 
 static TEST_COMPLETION_TX: Mutex<Option<watch::Sender<bool>>> = Mutex::new(None);
@@ -53,6 +56,7 @@ async fn golden_test() -> Result<()> {
     TxUnpacker::register(&mut process);
     TestModule::register(&mut process);
     SPOState::register(&mut process);
+    FaultInjector::register(&mut process);
 
     match timeout(Duration::from_secs(30), async {
         tokio::select! {
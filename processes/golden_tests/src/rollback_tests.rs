@@ -0,0 +1,423 @@
+//! Rolling back and replaying blocks leaves the state modules as if the chain had
+//! never forked
+//!
+//! The same blocks are fed twice - straight through, and with a fork in the middle
+//! which is rolled back to and abandoned before the blocks after the fork point are
+//! replayed. Each module publishes the digest of its state on the first block of
+//! the next epoch, and both runs must arrive at the same digests.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use acropolis_common::{
+    hash::Hash,
+    messages::{
+        BlockTxsMessage, CardanoMessage, GenesisCompleteMessage, GovernanceProceduresMessage,
+        Message, ProtocolParamsMessage, RawBlockMessage, StakeAddressDeltasMessage,
+        StateTransitionMessage, TxCertificatesMessage, UTXODeltasMessage, WithdrawalsMessage,
+    },
+    protocol_params::{ConwayParams, ProtocolParams, ShelleyParams, ShelleyProtocolParams},
+    rational_number::RationalNumber,
+    Address, Anchor, BlockHash, BlockInfo, BlockIntent, BlockStatus, ByronAddress, Credential,
+    DRepRegistration, Era, GovActionId, GovernanceAction, Point, PoolRegistration,
+    ProposalProcedure, Ratio, StakeAddress, TxCertificate, TxCertificateWithPos, TxHash,
+    TxIdentifier, TxOutput, TxUTxODeltas, UTxOIdentifier, Value, VrfKeyHash,
+};
+use acropolis_module_accounts_state::AccountsState;
+use acropolis_module_drep_state::DRepState;
+use acropolis_module_epochs_state::EpochsState;
+use acropolis_module_governance_state::GovernanceState;
+use acropolis_module_spo_state::SPOState;
+use acropolis_module_utxo_state::UTXOState;
+use acropolis_simulation::Simulation;
+use acropolis_test_utils::mainnet_genesis_values;
+use anyhow::{Context, Result};
+
+const CONFIG: &str = r#"
+[global.state-digest]
+enabled = true
+"#;
+
+const DIGEST_TOPIC: &str = "cardano.state.digest";
+
+/// Modules under test, which each publish a digest
+const MODULES: [&str; 6] = [
+    "utxo-state",
+    "spo-state",
+    "drep-state",
+    "accounts-state",
+    "governance-state",
+    "epochs-state",
+];
+
+/// Blocks in epoch 0 - the one after is the first of epoch 1
+const LAST_BLOCK: u64 = 6;
+
+/// First block to be rolled back
+const FORK_POINT: u64 = 4;
+
+/// Seed of the blocks on the abandoned fork, so they differ from the chain's
+const FORK: u8 = 100;
+
+/// A block, and the seed its contents are made from
+struct Block {
+    info: BlockInfo,
+    seed: u8,
+
+    /// Seed of the block whose output this block spends
+    parent_seed: Option<u8>,
+}
+
+impl Block {
+    fn new(number: u64, fork: u8, status: BlockStatus) -> Self {
+        let seed = fork + number as u8;
+        let parent_seed = match number {
+            1 => None,
+            n if n == FORK_POINT => Some(n as u8 - 1),
+            n => Some(fork + n as u8 - 1),
+        };
+        Self {
+            info: block_info(number, seed, status),
+            seed,
+            parent_seed,
+        }
+    }
+}
+
+fn block_info(number: u64, seed: u8, status: BlockStatus) -> BlockInfo {
+    let epoch = u64::from(number > LAST_BLOCK);
+    BlockInfo {
+        status,
+        intent: BlockIntent::Apply,
+        slot: number,
+        number,
+        hash: BlockHash::new([seed; 32]),
+        epoch,
+        epoch_slot: number,
+        // Block 1 starts epoch 0, which only governance acts on
+        new_epoch: number == 1 || number == LAST_BLOCK + 1,
+        is_new_era: false,
+        tip_slot: None,
+        timestamp: number,
+        era: Era::Conway,
+    }
+}
+
+fn protocol_params() -> ProtocolParams {
+    ProtocolParams {
+        shelley: Some(ShelleyParams {
+            active_slots_coeff: RationalNumber::new(1, 20),
+            epoch_length: 432_000,
+            security_param: 2160,
+            protocol_params: ShelleyProtocolParams {
+                decentralisation_param: RationalNumber::ONE,
+                ..ShelleyProtocolParams::default()
+            },
+            ..ShelleyParams::default()
+        }),
+        conway: Some(ConwayParams {
+            d_rep_activity: 20,
+            ..ConwayParams::default()
+        }),
+        ..ProtocolParams::default()
+    }
+}
+
+async fn publish(
+    sim: &Simulation,
+    topic: &str,
+    info: &BlockInfo,
+    message: CardanoMessage,
+) -> Result<()> {
+    sim.publish(topic, Message::Cardano((info.clone(), message))).await
+}
+
+/// Start the modules, and give them the messages they read at genesis
+async fn start() -> Result<Simulation> {
+    let sim = Simulation::new(CONFIG)?;
+    sim.capture(DIGEST_TOPIC).await?;
+    sim.start("utxo-state", |context, config| async move {
+        UTXOState.init(context, config).await
+    })
+    .await?;
+    sim.start("spo-state", |context, config| async move {
+        SPOState.init(context, config).await
+    })
+    .await?;
+    sim.start("drep-state", |context, config| async move {
+        DRepState.init(context, config).await
+    })
+    .await?;
+    sim.start("accounts-state", |context, config| async move {
+        AccountsState.init(context, config).await
+    })
+    .await?;
+    sim.start("governance-state", |context, config| async move {
+        GovernanceState.init(context, config).await
+    })
+    .await?;
+    sim.start("epochs-state", |context, config| async move {
+        EpochsState.init(context, config).await
+    })
+    .await?;
+
+    let genesis = block_info(0, 0, BlockStatus::Bootstrap);
+    publish(
+        &sim,
+        "cardano.sequence.bootstrapped",
+        &genesis,
+        CardanoMessage::GenesisComplete(GenesisCompleteMessage {
+            values: mainnet_genesis_values(),
+        }),
+    )
+    .await?;
+    publish(
+        &sim,
+        "cardano.block.txs",
+        &genesis,
+        CardanoMessage::BlockInfoMessage(BlockTxsMessage {
+            total_txs: 0,
+            total_output: 0,
+            total_fees: 0,
+        }),
+    )
+    .await?;
+    Ok(sim)
+}
+
+/// Protocol parameters, as published on each new epoch - the pots come from
+/// accounts_state
+async fn publish_epoch_messages(sim: &Simulation, info: &BlockInfo) -> Result<()> {
+    publish(
+        sim,
+        "cardano.protocol.parameters",
+        info,
+        CardanoMessage::ProtocolParams(ProtocolParamsMessage {
+            params: protocol_params(),
+        }),
+    )
+    .await
+}
+
+/// Publish what each module reads for a block - a transaction spending the
+/// parent's output, and a pool and a DRep registered. Blocks on the abandoned
+/// fork also carry a governance proposal, so the rollback has to remove it.
+async fn publish_block(sim: &Simulation, block: &Block) -> Result<()> {
+    let info = &block.info;
+    let tx_identifier = TxIdentifier::new(info.number as u32, 0);
+
+    let deltas = TxUTxODeltas {
+        tx_identifier,
+        consumes: block
+            .parent_seed
+            .map(|seed| UTxOIdentifier::new(TxHash::from([seed; 32]), 0))
+            .into_iter()
+            .collect(),
+        produces: vec![TxOutput {
+            utxo_identifier: UTxOIdentifier::new(TxHash::from([block.seed; 32]), 0),
+            address: Address::Byron(ByronAddress {
+                payload: vec![block.seed],
+            }),
+            value: Value::new(1_000_000 * u64::from(block.seed), Vec::new()),
+            datum: None,
+            script_ref: None,
+        }],
+        is_valid: true,
+        ..TxUTxODeltas::default()
+    };
+    publish(
+        sim,
+        "cardano.utxo.deltas",
+        info,
+        CardanoMessage::UTXODeltas(UTXODeltasMessage {
+            deltas: vec![deltas],
+        }),
+    )
+    .await?;
+
+    let pool = PoolRegistration {
+        operator: [block.seed; 28].into(),
+        vrf_key_hash: VrfKeyHash::new(Hash::new([block.seed; 32])),
+        pledge: 1_000,
+        cost: 340,
+        margin: Ratio {
+            numerator: 1,
+            denominator: 100,
+        },
+        reward_account: StakeAddress::default(),
+        pool_owners: vec![StakeAddress::default()],
+        relays: vec![],
+        pool_metadata: None,
+    };
+    let drep = DRepRegistration {
+        credential: Credential::AddrKeyHash([block.seed; 28].into()),
+        deposit: 500,
+        anchor: None,
+    };
+    let certificates = [
+        TxCertificate::PoolRegistration(pool),
+        TxCertificate::DRepRegistration(drep),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(index, cert)| TxCertificateWithPos {
+        cert,
+        tx_identifier,
+        cert_index: index as u64,
+    })
+    .collect();
+    publish(
+        sim,
+        "cardano.certificates",
+        info,
+        CardanoMessage::TxCertificates(TxCertificatesMessage { certificates }),
+    )
+    .await?;
+
+    publish(
+        sim,
+        "cardano.withdrawals",
+        info,
+        CardanoMessage::Withdrawals(WithdrawalsMessage {
+            withdrawals: vec![],
+        }),
+    )
+    .await?;
+    publish(
+        sim,
+        "cardano.stake.deltas",
+        info,
+        CardanoMessage::StakeAddressDeltas(StakeAddressDeltasMessage { deltas: vec![] }),
+    )
+    .await?;
+
+    let proposal_procedures = (block.seed >= FORK)
+        .then(|| ProposalProcedure {
+            deposit: 100_000,
+            reward_account: StakeAddress::default(),
+            gov_action_id: GovActionId {
+                transaction_id: TxHash::from([block.seed; 32]),
+                action_index: 0,
+            },
+            gov_action: GovernanceAction::Information,
+            anchor: Anchor {
+                url: format!("https://example.com/{}", block.seed),
+                data_hash: vec![block.seed],
+            },
+        })
+        .into_iter()
+        .collect();
+    publish(
+        sim,
+        "cardano.governance",
+        info,
+        CardanoMessage::GovernanceProcedures(GovernanceProceduresMessage {
+            proposal_procedures,
+            ..GovernanceProceduresMessage::default()
+        }),
+    )
+    .await?;
+    publish(
+        sim,
+        "cardano.block.proposed",
+        info,
+        CardanoMessage::BlockAvailable(RawBlockMessage {
+            header: vec![],
+            body: vec![],
+        }),
+    )
+    .await?;
+    publish(
+        sim,
+        "cardano.block.txs",
+        info,
+        CardanoMessage::BlockInfoMessage(BlockTxsMessage {
+            total_txs: 1,
+            total_output: 1_000_000 * u128::from(block.seed),
+            total_fees: 200_000 + u64::from(block.seed),
+        }),
+    )
+    .await?;
+
+    if info.new_epoch {
+        publish_epoch_messages(sim, info).await?;
+    }
+    Ok(())
+}
+
+/// Roll every topic back to before `FORK_POINT`
+async fn publish_rollback(sim: &Simulation) -> Result<()> {
+    let info = block_info(FORK_POINT, FORK_POINT as u8, BlockStatus::RolledBack);
+    let point = Point::Specific {
+        hash: BlockHash::new([FORK_POINT as u8 - 1; 32]),
+        slot: FORK_POINT - 1,
+    };
+    for topic in [
+        "cardano.utxo.deltas",
+        "cardano.certificates",
+        "cardano.governance",
+        "cardano.block.proposed",
+        "cardano.protocol.parameters",
+        "cardano.withdrawals",
+        "cardano.stake.deltas",
+        "cardano.block.txs",
+    ] {
+        publish(
+            sim,
+            topic,
+            &info,
+            CardanoMessage::StateTransition(StateTransitionMessage::Rollback(point.clone())),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Digests of the state as epoch 0 ended, once every module has published one
+async fn digests(sim: &Simulation) -> Result<BTreeMap<String, Hash<32>>> {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let digests: BTreeMap<_, _> = sim
+                .captured(DIGEST_TOPIC)
+                .iter()
+                .filter_map(|message| match message.as_ref() {
+                    Message::Cardano((_, CardanoMessage::StateDigest(digest)))
+                        if digest.epoch == 0 =>
+                    {
+                        Some((digest.module.clone(), digest.digest))
+                    }
+                    _ => None,
+                })
+                .collect();
+            if digests.len() == MODULES.len() {
+                return digests;
+            }
+            sim.settle().await;
+        }
+    })
+    .await
+    .context("Not every module published a state digest")
+}
+
+#[tokio::test]
+async fn replaying_after_a_rollback_matches_a_run_without_one() -> Result<()> {
+    let straight = start().await?;
+    for number in 1..=LAST_BLOCK + 1 {
+        publish_block(&straight, &Block::new(number, 0, BlockStatus::Volatile)).await?;
+    }
+
+    let forked = start().await?;
+    for number in 1..=LAST_BLOCK {
+        publish_block(&forked, &Block::new(number, 0, BlockStatus::Volatile)).await?;
+    }
+    publish_rollback(&forked).await?;
+    for number in FORK_POINT..LAST_BLOCK {
+        publish_block(&forked, &Block::new(number, FORK, BlockStatus::RolledBack)).await?;
+    }
+    publish_rollback(&forked).await?;
+    for number in FORK_POINT..=LAST_BLOCK + 1 {
+        publish_block(&forked, &Block::new(number, 0, BlockStatus::RolledBack)).await?;
+    }
+
+    assert_eq!(digests(&forked).await?, digests(&straight).await?);
+    Ok(())
+}
@@ -53,7 +53,7 @@ impl TestModule {
                 era: Era::Conway,
             },
             CardanoMessage::ReceivedTxs(RawTxsMessage {
-                txs: vec![tx_bytes],
+                txs: vec![tx_bytes.into()],
             }),
         ));
 
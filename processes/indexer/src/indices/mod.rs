@@ -1,2 +1,18 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use std::path::Path;
+use std::time::Duration;
+
 pub mod fjall_pool_cost_index;
 pub mod in_memory_pool_cost_index;
+pub mod sqlite_address_balance_index;
+pub mod sqlite_pool_cost_index;
+
+/// Open an SQLite database shared by several indexes and the query API
+pub fn open_sqlite(path: impl AsRef<Path>) -> Result<Connection> {
+    let connection = Connection::open(path)?;
+    // Readers don't block the writer, and writers wait for each other
+    connection.pragma_update(None, "journal_mode", "WAL")?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    Ok(connection)
+}
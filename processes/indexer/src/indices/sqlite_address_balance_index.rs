@@ -0,0 +1,94 @@
+use acropolis_codec::map_address;
+use acropolis_common::{BlockInfo, Point};
+use acropolis_module_custom_indexer::chain_index::ChainIndex;
+use anyhow::Result;
+use caryatid_sdk::async_trait;
+use pallas::ledger::traverse::MultiEraTx;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::open_sqlite;
+
+/// Every UTxO with the slot it was created and spent in. Balances are the sum of
+/// the unspent ones, and rolling back deletes or unspends whatever came later.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS utxos (
+        tx_hash BLOB NOT NULL,
+        output_index INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        lovelace INTEGER NOT NULL,
+        created_slot INTEGER NOT NULL,
+        spent_slot INTEGER,
+        PRIMARY KEY (tx_hash, output_index)
+    );
+    CREATE INDEX IF NOT EXISTS utxos_by_address ON utxos (address) WHERE spent_slot IS NULL;
+";
+
+pub struct SqliteAddressBalanceIndex {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteAddressBalanceIndex {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = open_sqlite(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+#[async_trait]
+impl ChainIndex for SqliteAddressBalanceIndex {
+    fn name(&self) -> String {
+        "sqlite-address-balance-index".into()
+    }
+
+    async fn handle_onchain_tx(&mut self, info: &BlockInfo, tx: &MultiEraTx<'_>) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        // Inputs, or collateral if the scripts failed
+        for input in tx.consumes() {
+            transaction.execute(
+                "UPDATE utxos SET spent_slot = ?1 WHERE tx_hash = ?2 AND output_index = ?3",
+                params![info.slot, input.hash().as_ref(), input.index()],
+            )?;
+        }
+
+        let tx_hash = tx.hash();
+        for (index, output) in tx.produces() {
+            let address = map_address(&output.address()?)?.to_string()?;
+            transaction.execute(
+                "INSERT OR REPLACE INTO utxos (tx_hash, output_index, address, lovelace, created_slot)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![tx_hash.as_ref(), index, address, output.value().coin(), info.slot],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn handle_rollback(&mut self, point: &Point) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM utxos WHERE created_slot > ?1",
+            params![point.slot()],
+        )?;
+        transaction.execute(
+            "UPDATE utxos SET spent_slot = NULL WHERE spent_slot > ?1",
+            params![point.slot()],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    async fn reset(&mut self, start: &Point) -> Result<Point> {
+        self.connection.lock().unwrap().execute("DELETE FROM utxos", [])?;
+        Ok(start.clone())
+    }
+}
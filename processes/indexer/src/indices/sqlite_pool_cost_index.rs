@@ -0,0 +1,94 @@
+use acropolis_codec::to_pool_id;
+use acropolis_common::{serialization::Bech32Conversion, BlockInfo, Point, PoolId};
+use acropolis_module_custom_indexer::chain_index::ChainIndex;
+use anyhow::Result;
+use caryatid_sdk::async_trait;
+use pallas::ledger::primitives::{alonzo, conway};
+use pallas::ledger::traverse::{MultiEraCert, MultiEraTx};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::open_sqlite;
+
+/// Pool costs kept as a history of registrations and retirements (with a NULL
+/// cost), so rolling back is just deleting the newest rows
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS pool_costs (
+        pool_id TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        cost INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS pool_costs_by_pool ON pool_costs (pool_id, slot);
+";
+
+pub struct SqlitePoolCostIndex {
+    connection: Mutex<Connection>,
+}
+
+impl SqlitePoolCostIndex {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = open_sqlite(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn record(&self, slot: u64, pool_id: PoolId, cost: Option<u64>) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO pool_costs (pool_id, slot, cost) VALUES (?1, ?2, ?3)",
+            params![pool_id.to_bech32()?, slot, cost],
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChainIndex for SqlitePoolCostIndex {
+    fn name(&self) -> String {
+        "sqlite-pool-cost-index".into()
+    }
+
+    async fn handle_onchain_tx(&mut self, info: &BlockInfo, tx: &MultiEraTx<'_>) -> Result<()> {
+        for cert in tx.certs().iter() {
+            match cert {
+                MultiEraCert::AlonzoCompatible(cert) => match cert.as_ref().as_ref() {
+                    alonzo::Certificate::PoolRegistration { operator, cost, .. } => {
+                        self.record(info.slot, to_pool_id(operator), Some(*cost))?;
+                    }
+                    alonzo::Certificate::PoolRetirement(operator, ..) => {
+                        self.record(info.slot, to_pool_id(operator), None)?;
+                    }
+                    _ => {}
+                },
+                MultiEraCert::Conway(cert) => match cert.as_ref().as_ref() {
+                    conway::Certificate::PoolRegistration { operator, cost, .. } => {
+                        self.record(info.slot, to_pool_id(operator), Some(*cost))?;
+                    }
+                    conway::Certificate::PoolRetirement(operator, ..) => {
+                        self.record(info.slot, to_pool_id(operator), None)?;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_rollback(&mut self, point: &Point) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "DELETE FROM pool_costs WHERE slot > ?1",
+            params![point.slot()],
+        )?;
+        Ok(())
+    }
+
+    async fn reset(&mut self, start: &Point) -> Result<Point> {
+        self.connection.lock().unwrap().execute("DELETE FROM pool_costs", [])?;
+        Ok(start.clone())
+    }
+}
@@ -15,9 +15,12 @@ use acropolis_module_genesis_bootstrapper::GenesisBootstrapper;
 use acropolis_module_peer_network_interface::PeerNetworkInterface;
 
 mod indices;
+mod query_api;
 
 use crate::indices::fjall_pool_cost_index::{FjallPoolCostIndex, FjallPoolCostState};
 use crate::indices::in_memory_pool_cost_index::{InMemoryPoolCostIndex, InMemoryPoolCostState};
+use crate::indices::sqlite_address_balance_index::SqliteAddressBalanceIndex;
+use crate::indices::sqlite_pool_cost_index::SqlitePoolCostIndex;
 use acropolis_module_custom_indexer::cursor_store::InMemoryCursorStore;
 
 #[derive(Debug, clap::Parser)]
@@ -38,6 +41,13 @@ async fn main() -> Result<()> {
     let config =
         Arc::new(builder.add_source(Environment::with_prefix("ACROPOLIS")).build().unwrap());
 
+    let sqlite_path = config
+        .get_string("sqlite-indexes.path")
+        .unwrap_or_else(|_| "sqlite-indexes.db".to_string());
+    let api_address = config
+        .get_string("sqlite-indexes.api-address")
+        .unwrap_or_else(|_| "127.0.0.1:4380".to_string());
+
     let mut process = Process::<Message>::create(config).await;
 
     // Core modules to fetch blocks and publish decoded transactions
@@ -102,10 +112,32 @@ async fn main() -> Result<()> {
     indexer
         .add_index(
             FjallPoolCostIndex::new("fjall-pool-cost-index", sender_2)?,
+            shelley_start.clone(),
+            false,
+        )
+        .await?;
+    indexer
+        .add_index(
+            SqlitePoolCostIndex::new(&sqlite_path)?,
+            shelley_start.clone(),
+            false,
+        )
+        .await?;
+    indexer
+        .add_index(
+            SqliteAddressBalanceIndex::new(&sqlite_path)?,
             shelley_start,
             false,
         )
         .await?;
+
+    // Query API over the SQLite indexes
+    tokio::spawn(async move {
+        if let Err(e) = query_api::serve(sqlite_path.into(), api_address).await {
+            tracing::error!("Index query API failed: {e:#}");
+        }
+    });
+
     process.run().await?;
 
     Ok(())
@@ -0,0 +1,123 @@
+//! Minimal HTTP API over the SQLite example indexes
+//!
+//! - `GET /pools` - current cost of every registered pool
+//! - `GET /pools/{pool_id}` - current cost of one pool
+//! - `GET /addresses/{address}/balance` - lovelace held by an address
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use tracing::{error, info};
+
+/// Latest row per pool, unless it is a retirement
+const CURRENT_POOL_COSTS: &str = "
+    SELECT pool_id, cost FROM pool_costs AS p
+    WHERE cost IS NOT NULL
+      AND slot = (SELECT MAX(slot) FROM pool_costs WHERE pool_id = p.pool_id)
+";
+
+#[derive(Serialize)]
+struct PoolCost {
+    pool_id: String,
+    cost: u64,
+}
+
+#[derive(Serialize)]
+struct AddressBalance {
+    address: String,
+    lovelace: u64,
+    utxo_count: u64,
+}
+
+type Db = Arc<Mutex<Connection>>;
+
+pub async fn serve(database: PathBuf, address: String) -> Result<()> {
+    let connection = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    let app = Router::new()
+        .route("/pools", get(get_pools))
+        .route("/pools/{pool_id}", get(get_pool))
+        .route("/addresses/{address}/balance", get(get_address_balance))
+        .with_state(Arc::new(Mutex::new(connection)));
+
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    info!("Index query API listening on {address}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+fn internal_error(e: rusqlite::Error) -> StatusCode {
+    error!("Index query failed: {e}");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn get_pools(State(db): State<Db>) -> Result<Json<Vec<PoolCost>>, StatusCode> {
+    let connection = db.lock().unwrap();
+    let mut statement = connection
+        .prepare(&format!("{CURRENT_POOL_COSTS} ORDER BY pool_id"))
+        .map_err(internal_error)?;
+    let pools = statement
+        .query_map([], |row| {
+            Ok(PoolCost {
+                pool_id: row.get(0)?,
+                cost: row.get(1)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(internal_error)?;
+    Ok(Json(pools))
+}
+
+async fn get_pool(
+    State(db): State<Db>,
+    Path(pool_id): Path<String>,
+) -> Result<Json<PoolCost>, StatusCode> {
+    let connection = db.lock().unwrap();
+    connection
+        .query_row(
+            &format!("{CURRENT_POOL_COSTS} AND pool_id = ?1"),
+            params![pool_id],
+            |row| {
+                Ok(PoolCost {
+                    pool_id: row.get(0)?,
+                    cost: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_address_balance(
+    State(db): State<Db>,
+    Path(address): Path<String>,
+) -> Result<Json<AddressBalance>, StatusCode> {
+    let connection = db.lock().unwrap();
+    let (lovelace, utxo_count) = connection
+        .query_row(
+            "SELECT COALESCE(SUM(lovelace), 0), COUNT(*) FROM utxos
+             WHERE address = ?1 AND spent_slot IS NULL",
+            params![address],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(internal_error)?;
+    Ok(Json(AddressBalance {
+        address,
+        lovelace,
+        utxo_count,
+    }))
+}
@@ -0,0 +1,499 @@
+//! Benchmark mode - times the sync of a range of slots, then writes a report of
+//! how fast each module applied its blocks, how much was sent on the measured bus
+//! topics and how much memory was used, and shuts the process down
+
+use acropolis_common::{
+    configuration::get_string_flag,
+    message_schema,
+    messages::{CardanoMessage, Message},
+    metrics,
+};
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::{module, Context};
+use config::Config;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::shutdown_coordinator;
+
+/// Topic the blocks being timed are read from
+const DEFAULT_BLOCK_TOPIC: (&str, &str) = ("block-topic", "cardano.block.proposed");
+
+/// How often memory use is sampled
+const DEFAULT_SAMPLE_INTERVAL: (&str, &str) = ("sample-interval", "1s");
+
+/// Bus topics whose messages are counted and sized
+const MEASURE_TOPICS_KEY: &str = "measure-topics";
+const DEFAULT_MEASURE_TOPICS: &[&str] = &["cardano.utxo.deltas"];
+
+/// Slots to time, inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SlotRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl FromStr for SlotRange {
+    type Err = anyhow::Error;
+
+    /// Parse `FROM-TO`
+    fn from_str(s: &str) -> Result<Self> {
+        let (from, to) =
+            s.split_once('-').ok_or_else(|| anyhow!("slot range '{s}' is not FROM-TO"))?;
+        let range = Self {
+            from: from.trim().parse()?,
+            to: to.trim().parse()?,
+        };
+        if range.from > range.to {
+            bail!("slot range '{s}' ends before it starts");
+        }
+        Ok(range)
+    }
+}
+
+/// Range to time and where to write the report, from the command line
+static BENCHMARK: OnceLock<(SlotRange, PathBuf)> = OnceLock::new();
+
+/// Run the benchmark over `range`, writing the report to `report`
+pub fn set_benchmark(range: SlotRange, report: PathBuf) {
+    let _ = BENCHMARK.set((range, report));
+}
+
+/// How fast a module applied the blocks in the range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModuleThroughput {
+    pub blocks: u64,
+    pub apply_seconds: f64,
+    pub blocks_per_second: f64,
+    pub mean_apply_ms: f64,
+}
+
+/// How much was sent on a bus topic in the range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TopicThroughput {
+    pub messages: u64,
+    /// Size of the messages encoded as CBOR, as on an external bus
+    pub bytes: u64,
+    pub messages_per_second: f64,
+    pub bytes_per_second: f64,
+    pub mean_message_bytes: f64,
+}
+
+/// Messages and bytes seen on a topic since startup
+#[derive(Default)]
+struct TopicCounter {
+    messages: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl TopicCounter {
+    fn count(&self, message: &Message) {
+        let bytes = message_schema::encode_cbor(message).map(|cbor| cbor.len()).unwrap_or(0);
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn totals(&self) -> (u64, u64) {
+        (
+            self.messages.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed),
+        )
+    }
+}
+
+type TopicCounters = BTreeMap<String, Arc<TopicCounter>>;
+
+/// Memory use, from jemalloc
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct MemoryStats {
+    pub allocated: u64,
+    pub active: u64,
+    pub resident: u64,
+    pub mapped: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryReport {
+    pub start: MemoryStats,
+    pub end: MemoryStats,
+    pub peak_allocated: u64,
+    pub peak_resident: u64,
+}
+
+/// Written as JSON at the end of the range
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceReport {
+    pub range: SlotRange,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    pub blocks: u64,
+    pub wall_seconds: f64,
+    pub blocks_per_second: f64,
+
+    /// Modules which time their block application
+    pub modules: BTreeMap<String, ModuleThroughput>,
+
+    /// Measured bus topics
+    pub bus: BTreeMap<String, TopicThroughput>,
+
+    /// Not available without jemalloc
+    pub memory: Option<MemoryReport>,
+}
+
+/// Blocks applied and seconds taken so far, by module
+fn apply_totals() -> BTreeMap<String, (u64, f64)> {
+    metrics::histogram_series(metrics::BLOCK_APPLY_SECONDS)
+        .into_iter()
+        .filter_map(|(labels, histogram)| {
+            let (_, module) = labels.into_iter().find(|(key, _)| key == "module")?;
+            Some((module, histogram.totals()))
+        })
+        .collect()
+}
+
+/// Throughput of each module between two sets of totals
+fn throughput(
+    start: &BTreeMap<String, (u64, f64)>,
+    end: &BTreeMap<String, (u64, f64)>,
+) -> BTreeMap<String, ModuleThroughput> {
+    end.iter()
+        .filter_map(|(module, (count, sum))| {
+            let (start_count, start_sum) = start.get(module).copied().unwrap_or_default();
+            let blocks = count - start_count;
+            let apply_seconds = sum - start_sum;
+            if blocks == 0 {
+                return None;
+            }
+            let throughput = ModuleThroughput {
+                blocks,
+                apply_seconds,
+                blocks_per_second: if apply_seconds > 0.0 {
+                    blocks as f64 / apply_seconds
+                } else {
+                    0.0
+                },
+                mean_apply_ms: apply_seconds * 1000.0 / blocks as f64,
+            };
+            Some((module.clone(), throughput))
+        })
+        .collect()
+}
+
+/// Messages and bytes sent on each measured topic so far
+fn bus_totals(counters: &TopicCounters) -> BTreeMap<String, (u64, u64)> {
+    counters.iter().map(|(topic, counter)| (topic.clone(), counter.totals())).collect()
+}
+
+/// What was sent on each topic between two sets of totals, over `seconds`
+fn bus_throughput(
+    start: &BTreeMap<String, (u64, u64)>,
+    end: &BTreeMap<String, (u64, u64)>,
+    seconds: f64,
+) -> BTreeMap<String, TopicThroughput> {
+    let per_second = |count: u64| {
+        if seconds > 0.0 {
+            count as f64 / seconds
+        } else {
+            0.0
+        }
+    };
+    end.iter()
+        .map(|(topic, (messages, bytes))| {
+            let (start_messages, start_bytes) = start.get(topic).copied().unwrap_or_default();
+            let messages = messages - start_messages;
+            let bytes = bytes - start_bytes;
+            let throughput = TopicThroughput {
+                messages,
+                bytes,
+                messages_per_second: per_second(messages),
+                bytes_per_second: per_second(bytes),
+                mean_message_bytes: if messages > 0 {
+                    bytes as f64 / messages as f64
+                } else {
+                    0.0
+                },
+            };
+            (topic.clone(), throughput)
+        })
+        .collect()
+}
+
+fn memory() -> Option<MemoryStats> {
+    #[cfg(not(target_env = "msvc"))]
+    {
+        // The jemalloc epoch must be advanced to flush any cached stats
+        tikv_jemalloc_ctl::epoch::advance().ok()?;
+        Some(MemoryStats {
+            allocated: tikv_jemalloc_ctl::stats::allocated::read().ok()? as u64,
+            active: tikv_jemalloc_ctl::stats::active::read().ok()? as u64,
+            resident: tikv_jemalloc_ctl::stats::resident::read().ok()? as u64,
+            mapped: tikv_jemalloc_ctl::stats::mapped::read().ok()? as u64,
+        })
+    }
+    #[cfg(target_env = "msvc")]
+    None
+}
+
+/// The range being timed, once its first block has been seen
+struct Run {
+    started: Instant,
+    first_slot: u64,
+    last_slot: u64,
+    blocks: u64,
+    apply_totals: BTreeMap<String, (u64, f64)>,
+    bus_totals: BTreeMap<String, (u64, u64)>,
+    memory: Option<MemoryReport>,
+}
+
+impl Run {
+    fn start(slot: u64, counters: &TopicCounters) -> Self {
+        Self {
+            started: Instant::now(),
+            first_slot: slot,
+            last_slot: slot,
+            blocks: 0,
+            apply_totals: apply_totals(),
+            bus_totals: bus_totals(counters),
+            memory: memory().map(|start| MemoryReport {
+                start,
+                end: start,
+                peak_allocated: start.allocated,
+                peak_resident: start.resident,
+            }),
+        }
+    }
+
+    fn sample_memory(&mut self) {
+        if let (Some(report), Some(now)) = (&mut self.memory, memory()) {
+            report.end = now;
+            report.peak_allocated = report.peak_allocated.max(now.allocated);
+            report.peak_resident = report.peak_resident.max(now.resident);
+        }
+    }
+
+    fn finish(mut self, range: SlotRange, counters: &TopicCounters) -> PerformanceReport {
+        self.sample_memory();
+        let wall_seconds = self.started.elapsed().as_secs_f64();
+        PerformanceReport {
+            range,
+            first_slot: self.first_slot,
+            last_slot: self.last_slot,
+            blocks: self.blocks,
+            wall_seconds,
+            blocks_per_second: if wall_seconds > 0.0 {
+                self.blocks as f64 / wall_seconds
+            } else {
+                0.0
+            },
+            modules: throughput(&self.apply_totals, &apply_totals()),
+            bus: bus_throughput(&self.bus_totals, &bus_totals(counters), wall_seconds),
+            memory: self.memory,
+        }
+    }
+}
+
+/// Benchmark module
+#[module(
+    message_type(Message),
+    name = "benchmark",
+    description = "Times the sync of a range of slots"
+)]
+pub struct Benchmark;
+
+impl Benchmark {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let Some((range, report_path)) = BENCHMARK.get() else {
+            warn!("No benchmark range given, not benchmarking");
+            return Ok(());
+        };
+        let range = *range;
+
+        let topic = get_string_flag(&config, DEFAULT_BLOCK_TOPIC);
+        let sample_interval = get_string_flag(&config, DEFAULT_SAMPLE_INTERVAL);
+        let sample_interval: Duration = humantime::parse_duration(&sample_interval)
+            .map_err(|e| anyhow!("bad {} '{sample_interval}': {e}", DEFAULT_SAMPLE_INTERVAL.0))?;
+        info!(
+            "Benchmarking slots {} to {} on '{topic}', reporting to {}",
+            range.from,
+            range.to,
+            report_path.display()
+        );
+        let mut subscription = context.subscribe(&topic).await?;
+
+        let measure_topics: Vec<String> = config.get(MEASURE_TOPICS_KEY).unwrap_or_else(|_| {
+            DEFAULT_MEASURE_TOPICS.iter().map(|topic| topic.to_string()).collect()
+        });
+        let mut counters = TopicCounters::new();
+        for measured in measure_topics {
+            let counter = Arc::new(TopicCounter::default());
+            let mut measured_subscription = context.subscribe(&measured).await?;
+            let topic_counter = counter.clone();
+            context.run(async move {
+                while let Ok((_, message)) = measured_subscription.read().await {
+                    topic_counter.count(&message);
+                }
+            });
+            counters.insert(measured, counter);
+        }
+
+        context.run(async move {
+            let mut run: Option<Run> = None;
+            let mut sample = tokio::time::interval(sample_interval);
+            loop {
+                let message = tokio::select! {
+                    _ = sample.tick() => {
+                        if let Some(run) = &mut run {
+                            run.sample_memory();
+                        }
+                        continue;
+                    }
+                    result = subscription.read() => match result {
+                        Ok((_, message)) => message,
+                        Err(_) => return,
+                    },
+                };
+                let Message::Cardano((block, CardanoMessage::BlockAvailable(_))) = message.as_ref()
+                else {
+                    continue;
+                };
+                if block.slot < range.from {
+                    continue;
+                }
+
+                let current = run.get_or_insert_with(|| {
+                    info!("Benchmark started at slot {}", block.slot);
+                    Run::start(block.slot, &counters)
+                });
+                current.blocks += 1;
+                current.last_slot = block.slot;
+                if block.slot < range.to {
+                    continue;
+                }
+
+                let Some(finished) = run.take() else {
+                    return;
+                };
+                if finished.first_slot > range.to {
+                    error!(
+                        "Sync started at slot {}, after the benchmark range",
+                        finished.first_slot
+                    );
+                } else {
+                    write_report(&finished.finish(range, &counters), report_path);
+                }
+                shutdown_coordinator::request();
+                return;
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn write_report(report: &PerformanceReport, path: &Path) {
+    info!(
+        "Benchmark finished: {} blocks in {:.1}s, {:.1} blocks/s",
+        report.blocks, report.wall_seconds, report.blocks_per_second
+    );
+    for (module, throughput) in &report.modules {
+        info!(
+            "{module}: {} blocks, {:.1} blocks/s, mean {:.3}ms",
+            throughput.blocks, throughput.blocks_per_second, throughput.mean_apply_ms
+        );
+    }
+    for (topic, throughput) in &report.bus {
+        info!(
+            "{topic}: {} messages, {:.1} messages/s, {:.0} bytes/s, mean {:.0} bytes",
+            throughput.messages,
+            throughput.messages_per_second,
+            throughput.bytes_per_second,
+            throughput.mean_message_bytes
+        );
+    }
+
+    let result = serde_json::to_string_pretty(report)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(std::fs::write(path, json)?));
+    match result {
+        Ok(()) => info!("Wrote performance report to {}", path.display()),
+        Err(e) => error!(
+            "Could not write performance report to {}: {e:#}",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_slot_ranges() {
+        assert_eq!(
+            "1000-2000".parse::<SlotRange>().unwrap(),
+            SlotRange {
+                from: 1000,
+                to: 2000
+            }
+        );
+        assert!("2000-1000".parse::<SlotRange>().is_err());
+        assert!("1000".parse::<SlotRange>().is_err());
+    }
+
+    #[test]
+    fn throughput_covers_only_the_range() {
+        let start = BTreeMap::from([
+            ("utxo-state".to_string(), (100, 1.0)),
+            ("spo-state".to_string(), (100, 0.5)),
+        ]);
+        let end = BTreeMap::from([
+            ("utxo-state".to_string(), (300, 3.0)),
+            ("spo-state".to_string(), (100, 0.5)),
+            ("accounts-state".to_string(), (50, 0.25)),
+        ]);
+
+        let throughput = throughput(&start, &end);
+        assert_eq!(
+            throughput.keys().collect::<Vec<_>>(),
+            vec!["accounts-state", "utxo-state"]
+        );
+        assert_eq!(
+            throughput["utxo-state"],
+            ModuleThroughput {
+                blocks: 200,
+                apply_seconds: 2.0,
+                blocks_per_second: 100.0,
+                mean_apply_ms: 10.0,
+            }
+        );
+        assert_eq!(throughput["accounts-state"].blocks_per_second, 200.0);
+    }
+
+    #[test]
+    fn bus_throughput_covers_only_the_range() {
+        let start = BTreeMap::from([("cardano.utxo.deltas".to_string(), (10, 1_000))]);
+        let end = BTreeMap::from([("cardano.utxo.deltas".to_string(), (110, 51_000))]);
+
+        let bus = bus_throughput(&start, &end, 2.0);
+        assert_eq!(
+            bus["cardano.utxo.deltas"],
+            TopicThroughput {
+                messages: 100,
+                bytes: 50_000,
+                messages_per_second: 50.0,
+                bytes_per_second: 25_000.0,
+                mean_message_bytes: 500.0,
+            }
+        );
+    }
+}
@@ -1,46 +1,32 @@
 //! 'main' for the Acropolis omnibus process
 
-use acropolis_common::messages::Message;
-use anyhow::Result;
+use acropolis_common::{
+    configuration::{self, config_check},
+    message_schema,
+    message_trace::{self, TraceSettings},
+    messages::Message,
+    queries::circuit::{self, QueryPolicies},
+    readiness,
+    state_history::{self, HistoryRetention},
+    upstream_cache::UpstreamCache,
+};
+use anyhow::{bail, Result};
 use caryatid_process::Process;
-use config::{Config, Environment, File};
-use std::sync::Arc;
-use tracing::info;
-
-// External modules
-use acropolis_module_accounts_state::AccountsState;
-use acropolis_module_address_state::AddressState;
-use acropolis_module_assets_state::AssetsState;
-use acropolis_module_block_kes_validator::BlockKesValidator;
-use acropolis_module_block_unpacker::BlockUnpacker;
-use acropolis_module_block_vrf_validator::BlockVrfValidator;
-use acropolis_module_chain_store::ChainStore;
-use acropolis_module_consensus::Consensus;
-use acropolis_module_drdd_state::DRDDState;
-use acropolis_module_drep_state::DRepState;
-use acropolis_module_epochs_state::EpochsState;
-use acropolis_module_fake_block_injector::FakeBlockInjector;
-use acropolis_module_genesis_bootstrapper::GenesisBootstrapper;
-use acropolis_module_governance_state::GovernanceState;
-use acropolis_module_historical_accounts_state::HistoricalAccountsState;
-use acropolis_module_historical_epochs_state::HistoricalEpochsState;
-use acropolis_module_mcp_server::MCPServer;
-use acropolis_module_midnight_state::MidnightState;
-use acropolis_module_mithril_snapshot_fetcher::MithrilSnapshotFetcher;
-use acropolis_module_parameters_state::ParametersState;
-use acropolis_module_peer_network_interface::PeerNetworkInterface;
-use acropolis_module_rest_blockfrost::BlockfrostREST;
-use acropolis_module_snapshot_bootstrapper::SnapshotBootstrapper;
-use acropolis_module_spdd_state::SPDDState;
-use acropolis_module_spo_state::SPOState;
-use acropolis_module_stake_delta_filter::StakeDeltaFilter;
-use acropolis_module_stats::Stats;
-use acropolis_module_tx_unpacker::TxUnpacker;
-use acropolis_module_utxo_state::UTXOState;
-
-use caryatid_module_clock::Clock;
-use caryatid_module_rest_server::RESTServer;
-use caryatid_module_spy::Spy;
+use config::{Config, ConfigError, Environment, File};
+use std::{path::PathBuf, sync::Arc};
+use tracing::{error, info, warn};
+
+mod benchmark;
+mod modules;
+mod reload_coordinator;
+mod shutdown_coordinator;
+
+use acropolis_module_block_unpacker::BlockUnpackerConfig;
+use acropolis_module_chain_store::ChainStoreConfig;
+use acropolis_module_correctness_verifier::CorrectnessVerifierConfig;
+use acropolis_module_sidechain_observer::SidechainObserverConfig;
+use acropolis_module_stats::StatsConfig;
+use acropolis_module_tx_unpacker::TxUnpackerConfig;
 
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry_otlp::SpanExporter;
@@ -60,6 +46,96 @@ static GLOBAL: Jemalloc = Jemalloc;
 struct Args {
     #[arg(long, value_name = "PATH", default_values_t = vec![option_env!("ACROPOLIS_OMNIBUS_DEFAULT_CONFIG").unwrap_or("omnibus.toml").to_string()])]
     config: Vec<String>,
+
+    /// Validate the configuration and exit
+    #[arg(long)]
+    check_config: bool,
+
+    /// Time the sync of a range of slots, write a performance report and exit
+    #[arg(long, value_name = "FROM-TO")]
+    benchmark: Option<benchmark::SlotRange>,
+
+    /// Where to write the benchmark performance report
+    #[arg(long, value_name = "PATH", default_value = "benchmark.json")]
+    benchmark_report: PathBuf,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Inspect or repair the upstream block cache, instead of running the node
+    Cache {
+        /// Cache directory - by default the peer network interface's cache-dir
+        #[arg(long, value_name = "PATH")]
+        dir: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum CacheAction {
+    /// Report what the cache holds, and the first damaged chunk if any
+    Inspect,
+
+    /// Drop the first damaged chunk and everything after it
+    Repair {
+        /// Also drop the blocks replaced by rollbacks
+        #[arg(long)]
+        compact: bool,
+
+        /// Also drop the oldest chunks until the cache fits in this many bytes
+        #[arg(long, value_name = "BYTES")]
+        max_bytes: Option<u64>,
+    },
+}
+
+/// Run a cache subcommand
+fn run_cache_command(dir: PathBuf, action: CacheAction) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("No upstream cache at {}", dir.display());
+    }
+    let mut cache = UpstreamCache::new(&dir)?;
+    let report = cache.verify()?;
+    info!(
+        "Upstream cache {}: {} blocks ({:?} to {:?}) in {} chunks, {} bytes, {} rollbacks",
+        dir.display(),
+        report.records,
+        report.first_block,
+        report.last_block,
+        report.chunks,
+        report.bytes,
+        report.rollbacks
+    );
+    if let Some((chunk, error)) = &report.bad_chunk {
+        warn!("Chunk {chunk} is damaged: {error}");
+    }
+
+    if let CacheAction::Repair { compact, max_bytes } = action {
+        let removed = cache.repair()?;
+        info!("Dropped {removed} damaged chunks");
+        if compact {
+            let dropped = cache.compact()?;
+            info!("Dropped {dropped} rolled back blocks");
+        }
+        if let Some(max_bytes) = max_bytes {
+            let expired = cache.expire(max_bytes)?;
+            info!("Expired {expired} chunks");
+        }
+    }
+    Ok(())
+}
+
+/// Read the config files, with environment overrides
+pub fn load_config(files: &[String]) -> Result<Config> {
+    let mut builder = Config::builder();
+    for file in files {
+        builder = builder.add_source(File::with_name(file));
+    }
+    Ok(builder.add_source(Environment::with_prefix("ACROPOLIS")).build()?)
 }
 
 /// Standard main
@@ -94,52 +170,98 @@ pub async fn main() -> Result<()> {
     info!("Acropolis omnibus process");
 
     // Read the config
-    let mut builder = Config::builder();
-    for file in &args.config {
-        builder = builder.add_source(File::with_name(file));
+    let config = Arc::new(load_config(&args.config)?);
+
+    // Talk to older processes on an external bus in their message schema
+    if let Ok(version) = config.get_int("message-schema.send-version") {
+        message_schema::set_send_version(version as u32)?;
+        info!("Sending message schema version {version}");
+    }
+    match config.get::<HistoryRetention>("global.state-history") {
+        Ok(retention) => {
+            info!("State history retention: {retention:?}");
+            state_history::set_retention(retention)?;
+        }
+        Err(ConfigError::NotFound(_)) => {}
+        Err(e) => bail!("invalid global.state-history: {e}"),
+    }
+    if let Ok(policies) = config.get::<QueryPolicies>("global.query") {
+        info!("Query policies: {policies:?}");
+        circuit::set_policies(policies)?;
+    }
+    if let Ok(settings) = config.get::<TraceSettings>("global.trace") {
+        info!("Message tracing: {settings:?}");
+        message_trace::set_settings(settings)?;
+    }
+    if let Ok(types) = config.get::<Vec<String>>("message-schema.cbor-messages") {
+        message_schema::set_cbor_messages(&types)?;
+        info!("Sending {types:?} messages as CBOR");
+    }
+
+    if let Some(Command::Cache { dir, action }) = args.command {
+        let dir = dir.unwrap_or_else(|| {
+            PathBuf::from(
+                config
+                    .get_string("module.peer-network-interface.cache-dir")
+                    .unwrap_or_else(|_| "upstream-cache".to_string()),
+            )
+        });
+        return run_cache_command(dir, action);
+    }
+
+    // Report every problem in the module configs before starting anything
+    let config_checks = [
+        config_check::<BlockUnpackerConfig>(),
+        config_check::<ChainStoreConfig>(),
+        config_check::<CorrectnessVerifierConfig>(),
+        config_check::<SidechainObserverConfig>(),
+        config_check::<StatsConfig>(),
+        config_check::<TxUnpackerConfig>(),
+    ];
+    let mut errors = configuration::check_config(&config, &config_checks);
+    let selected = modules::select(&config, &mut errors);
+    modules::check_dependencies(&config, &selected, &mut errors);
+    if args.benchmark.is_some() && config.get_table("module.benchmark").is_err() {
+        errors.push("--benchmark needs a [module.benchmark] section".to_string());
+    }
+    for e in &errors {
+        error!("Configuration error: {e}");
+    }
+    if !errors.is_empty() {
+        bail!("{} configuration error(s)", errors.len());
+    }
+    if args.check_config {
+        info!("Configuration is valid");
+        return Ok(());
+    }
+
+    let readiness_log_interval =
+        config.get_string("global.readiness.log-interval").unwrap_or_else(|_| "10s".to_string());
+    let readiness_log_interval = humantime::parse_duration(&readiness_log_interval)?;
+    reload_coordinator::set_startup_config(args.config.clone(), (*config).clone());
+    if let Some(range) = args.benchmark {
+        benchmark::set_benchmark(range, args.benchmark_report.clone());
     }
-    let config = Arc::new(builder.add_source(Environment::with_prefix("ACROPOLIS")).build()?);
 
     // Create the process
     let mut process = Process::<Message>::create(config).await;
 
-    // Register modules
-    GenesisBootstrapper::register(&mut process);
-    SnapshotBootstrapper::register(&mut process);
-    MithrilSnapshotFetcher::register(&mut process);
-    BlockUnpacker::register(&mut process);
-    PeerNetworkInterface::register(&mut process);
-    TxUnpacker::register(&mut process);
-    UTXOState::register(&mut process);
-    SPOState::register(&mut process);
-    DRepState::register(&mut process);
-    GovernanceState::register(&mut process);
-    ParametersState::register(&mut process);
-    StakeDeltaFilter::register(&mut process);
-    EpochsState::register(&mut process);
-    AccountsState::register(&mut process);
-    AddressState::register(&mut process);
-    AssetsState::register(&mut process);
-    HistoricalAccountsState::register(&mut process);
-    HistoricalEpochsState::register(&mut process);
-    BlockfrostREST::register(&mut process);
-    SPDDState::register(&mut process);
-    DRDDState::register(&mut process);
-    Consensus::register(&mut process);
-    ChainStore::register(&mut process);
-    BlockVrfValidator::register(&mut process);
-    BlockKesValidator::register(&mut process);
-    FakeBlockInjector::register(&mut process);
-    MCPServer::register(&mut process);
-    MidnightState::register(&mut process);
-    Stats::register(&mut process);
-
-    Clock::<Message>::register(&mut process);
-    RESTServer::<Message>::register(&mut process);
-    Spy::<Message>::register(&mut process);
-
-    // Run it
-    process.run().await?;
+    // Register the selected modules
+    for module in &selected {
+        (module.register)(&mut process);
+    }
+    if args.benchmark.is_some() {
+        benchmark::Benchmark::register(&mut process);
+    }
+
+    // Log what the state modules are waiting for, until they are all ready
+    tokio::spawn(readiness::log_until_ready(readiness_log_interval));
+
+    // Run it, until interrupted and the modules have flushed
+    tokio::select! {
+        result = process.run() => result?,
+        _ = shutdown_coordinator::finished() => {}
+    }
 
     // Bye!
     info!("Exiting");
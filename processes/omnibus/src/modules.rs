@@ -0,0 +1,706 @@
+//! Which modules the omnibus process registers
+//!
+//! Every module is registered unless a `[modules]` section picks a profile -
+//! a named set of modules for one job - and/or enables or disables modules
+//! by name. Before anything starts, every required topic of the modules which
+//! will run is checked to have a module which publishes it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use acropolis_common::messages::Message;
+use caryatid_process::Process;
+use config::{Config, ConfigError};
+
+use crate::{reload_coordinator::ReloadCoordinator, shutdown_coordinator::ShutdownCoordinator};
+
+use acropolis_module_accounts_state::AccountsState;
+use acropolis_module_address_state::AddressState;
+use acropolis_module_assets_state::AssetsState;
+use acropolis_module_block_kes_validator::BlockKesValidator;
+use acropolis_module_block_producer::BlockProducer;
+use acropolis_module_block_unpacker::BlockUnpacker;
+use acropolis_module_block_vrf_validator::BlockVrfValidator;
+use acropolis_module_chain_store::ChainStore;
+use acropolis_module_consensus::Consensus;
+use acropolis_module_correctness_verifier::CorrectnessVerifier;
+use acropolis_module_drdd_state::DRDDState;
+use acropolis_module_drep_state::DRepState;
+use acropolis_module_epochs_state::EpochsState;
+use acropolis_module_event_stream::EventStream;
+use acropolis_module_fake_block_injector::FakeBlockInjector;
+use acropolis_module_genesis_bootstrapper::GenesisBootstrapper;
+use acropolis_module_governance_state::GovernanceState;
+use acropolis_module_graphql::GraphQL;
+use acropolis_module_historical_accounts_state::HistoricalAccountsState;
+use acropolis_module_historical_epochs_state::HistoricalEpochsState;
+use acropolis_module_mcp_server::MCPServer;
+use acropolis_module_midnight_state::MidnightState;
+use acropolis_module_mithril_snapshot_fetcher::MithrilSnapshotFetcher;
+use acropolis_module_n2c_server::N2CServer;
+use acropolis_module_parameters_state::ParametersState;
+use acropolis_module_peer_network_interface::PeerNetworkInterface;
+use acropolis_module_rest_blockfrost::BlockfrostREST;
+use acropolis_module_scripts_state::ScriptsState;
+use acropolis_module_sidechain_observer::SidechainObserverModule;
+use acropolis_module_snapshot_bootstrapper::SnapshotBootstrapper;
+use acropolis_module_spdd_state::SPDDState;
+use acropolis_module_spo_state::SPOState;
+use acropolis_module_stake_delta_filter::StakeDeltaFilter;
+use acropolis_module_stats::Stats;
+use acropolis_module_tx_unpacker::TxUnpacker;
+use acropolis_module_utxo_state::UTXOState;
+use acropolis_module_utxorpc::UtxoRpc;
+
+use caryatid_module_clock::Clock;
+use caryatid_module_rest_server::RESTServer;
+use caryatid_module_spy::Spy;
+
+/// Topic config key and its default. An empty default means the module only
+/// uses the topic if it is configured.
+type Topic = (&'static str, &'static str);
+
+/// A module the omnibus process can run
+pub struct ModuleSpec {
+    /// Name of its `[module.*]` section
+    pub name: &'static str,
+
+    /// Add it to the process
+    pub register: fn(&mut Process<Message>),
+
+    /// Topics it can't work without
+    pub requires: &'static [Topic],
+
+    /// Topics it publishes
+    pub produces: &'static [Topic],
+}
+
+/// Every module, in registration order
+pub const MODULES: &[ModuleSpec] = &[
+    ModuleSpec {
+        name: "genesis-bootstrapper",
+        register: |process| GenesisBootstrapper::register(process),
+        requires: &[],
+        produces: &[
+            ("completion-topic", "cardano.sequence.bootstrapped"),
+            ("publish-utxo-deltas-topic", "cardano.utxo.deltas"),
+        ],
+    },
+    ModuleSpec {
+        name: "snapshot-bootstrapper",
+        register: |process| SnapshotBootstrapper::register(process),
+        requires: &[],
+        produces: &[("snapshot-topic", "cardano.snapshot")],
+    },
+    ModuleSpec {
+        name: "mithril-snapshot-fetcher",
+        register: |process| MithrilSnapshotFetcher::register(process),
+        requires: &[],
+        produces: &[("block-publish-topic", "cardano.block.available")],
+    },
+    ModuleSpec {
+        name: "block-unpacker",
+        register: |process| BlockUnpacker::register(process),
+        requires: &[("subscribe-topic", "cardano.block.proposed")],
+        produces: &[("publish-topic", "cardano.txs")],
+    },
+    ModuleSpec {
+        name: "peer-network-interface",
+        register: |process| PeerNetworkInterface::register(process),
+        requires: &[],
+        produces: &[("block-topic", "cardano.block.available")],
+    },
+    ModuleSpec {
+        name: "tx-unpacker",
+        register: |process| TxUnpacker::register(process),
+        requires: &[
+            ("transactions-subscribe-topic", "cardano.txs"),
+            ("protocol-parameters-subscribe-topic", ""),
+        ],
+        produces: &[
+            ("publish-utxo-deltas-topic", ""),
+            ("publish-asset-deltas-topic", ""),
+            ("publish-withdrawals-topic", ""),
+            ("publish-certificates-topic", ""),
+            ("publish-governance-topic", ""),
+            ("publish-block-txs-topic", ""),
+        ],
+    },
+    ModuleSpec {
+        name: "utxo-state",
+        register: |process| UTXOState::register(process),
+        requires: &[
+            ("utxo-deltas-subscribe-topic", "cardano.utxo.deltas"),
+            (
+                "protocol-parameters-subscribe-topic",
+                "cardano.protocol.parameters",
+            ),
+        ],
+        produces: &[("address-delta-topic", ""), ("block-totals-topic", "")],
+    },
+    ModuleSpec {
+        name: "spo-state",
+        register: |process| SPOState::register(process),
+        requires: &[("certificates-subscribe-topic", "cardano.certificates")],
+        produces: &[
+            ("publish-spo-state-topic", "cardano.spo.state"),
+            (
+                "publish-pool-registration-updates-topic",
+                "cardano.pool.registration.updates",
+            ),
+        ],
+    },
+    ModuleSpec {
+        name: "drep-state",
+        register: |process| DRepState::register(process),
+        requires: &[("certificates-subscribe-topic", "cardano.certificates")],
+        produces: &[("publish-drep-state-topic", "cardano.drep.state")],
+    },
+    ModuleSpec {
+        name: "governance-state",
+        register: |process| GovernanceState::register(process),
+        requires: &[("subscribe-topic", "cardano.governance")],
+        produces: &[("enact-state-topic", "cardano.enact.state")],
+    },
+    ModuleSpec {
+        name: "parameters-state",
+        register: |process| ParametersState::register(process),
+        requires: &[],
+        produces: &[("publish-parameters-topic", "cardano.protocol.parameters")],
+    },
+    ModuleSpec {
+        name: "stake-delta-filter",
+        register: |process| StakeDeltaFilter::register(process),
+        requires: &[
+            ("subscription-address-delta-topic", "cardano.address.deltas"),
+            ("subscription-certificates-topic", "cardano.certificates"),
+        ],
+        produces: &[("publishing-stake-delta-topic", "cardano.stake.deltas")],
+    },
+    ModuleSpec {
+        name: "epochs-state",
+        register: |process| EpochsState::register(process),
+        requires: &[("block-subscribe-topic", "cardano.block.proposed")],
+        produces: &[
+            ("epoch-activity-publish-topic", "cardano.epoch.activity"),
+            ("epoch-nonce-publish-topic", "cardano.epoch.nonce"),
+        ],
+    },
+    ModuleSpec {
+        name: "accounts-state",
+        register: |process| AccountsState::register(process),
+        requires: &[
+            ("spo-state-subscribe-topic", "cardano.spo.state"),
+            ("epoch-activity-subscribe-topic", "cardano.epoch.activity"),
+            ("stake-deltas-subscribe-topic", "cardano.stake.deltas"),
+            ("certificates-subscribe-topic", "cardano.certificates"),
+            ("withdrawals-subscribe-topic", "cardano.withdrawals"),
+        ],
+        produces: &[
+            (
+                "publish-drep-distribution-topic",
+                "cardano.drep.distribution",
+            ),
+            ("publish-pots-topic", "cardano.pots"),
+            ("publish-spo-default-vote-topic", "cardano.spo.default-vote"),
+            ("publish-spo-distribution-topic", "cardano.spo.distribution"),
+            ("publish-spo-rewards-topic", "cardano.spo.rewards"),
+            (
+                "publish-stake-registration-updates-topic",
+                "cardano.stake.registration.updates",
+            ),
+            (
+                "publish-stake-reward-deltas-topic",
+                "cardano.stake.reward.deltas",
+            ),
+        ],
+    },
+    ModuleSpec {
+        name: "address-state",
+        register: |process| AddressState::register(process),
+        requires: &[("address-deltas-subscribe-topic", "cardano.address.deltas")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "assets-state",
+        register: |process| AssetsState::register(process),
+        requires: &[("asset-deltas-subscribe-topic", "cardano.asset.deltas")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "scripts-state",
+        register: |process| ScriptsState::register(process),
+        requires: &[("utxo-deltas-subscribe-topic", "cardano.utxo.deltas")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "historical-accounts-state",
+        register: |process| HistoricalAccountsState::register(process),
+        requires: &[
+            ("rewards-subscribe-topic", "cardano.stake.reward.deltas"),
+            ("certificates-subscribe-topic", "cardano.certificates"),
+            ("withdrawals-subscribe-topic", "cardano.withdrawals"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "historical-epochs-state",
+        register: |process| HistoricalEpochsState::register(process),
+        requires: &[("epoch-activity-subscribe-topic", "cardano.epoch.activity")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "rest-blockfrost",
+        register: |process| BlockfrostREST::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "spdd-state",
+        register: |process| SPDDState::register(process),
+        requires: &[(
+            "stake-pool-distribution-subscribe-topic",
+            "cardano.spo.distribution",
+        )],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "correctness-verifier",
+        register: |process| CorrectnessVerifier::register(process),
+        requires: &[
+            ("spdd-subscribe-topic", "cardano.spo.distribution"),
+            ("spo-rewards-subscribe-topic", "cardano.spo.rewards"),
+        ],
+        produces: &[("publish-report-topic", "cardano.verification.report")],
+    },
+    ModuleSpec {
+        name: "drdd-state",
+        register: |process| DRDDState::register(process),
+        requires: &[(
+            "drep-distribution-subscribe-topic",
+            "cardano.drep.distribution",
+        )],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "consensus",
+        register: |process| Consensus::register(process),
+        requires: &[("blocks-available-topic", "cardano.block.available")],
+        produces: &[("blocks-proposed-topic", "cardano.block.proposed")],
+    },
+    ModuleSpec {
+        name: "chain-store",
+        register: |process| ChainStore::register(process),
+        requires: &[("blocks-subscribe-topic", "cardano.block.available")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "block-vrf-validator",
+        register: |process| BlockVrfValidator::register(process),
+        requires: &[
+            ("block-subscribe-topic", "cardano.block.proposed"),
+            ("epoch-nonce-subscribe-topic", "cardano.epoch.nonce"),
+            ("spdd-subscribe-topic", "cardano.spo.distribution"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "block-kes-validator",
+        register: |process| BlockKesValidator::register(process),
+        requires: &[
+            ("block-subscribe-topic", "cardano.block.proposed"),
+            ("spo-state-subscribe-topic", "cardano.spo.state"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "block-producer",
+        register: |process| BlockProducer::register(process),
+        requires: &[
+            ("epoch-nonce-subscribe-topic", "cardano.epoch.nonce"),
+            ("spdd-subscribe-topic", "cardano.spo.distribution"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "fake-block-injector",
+        register: |process| FakeBlockInjector::register(process),
+        requires: &[],
+        produces: &[("block-publish-topic", "cardano.block.available")],
+    },
+    ModuleSpec {
+        name: "mcp-server",
+        register: |process| MCPServer::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "event-stream",
+        register: |process| EventStream::register(process),
+        requires: &[
+            ("address-deltas-subscribe-topic", "cardano.address.deltas"),
+            ("blocks-subscribe-topic", "cardano.block.proposed"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "utxorpc",
+        register: |process| UtxoRpc::register(process),
+        requires: &[("blocks-subscribe-topic", "cardano.block.available")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "graphql",
+        register: |process| GraphQL::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "n2c-server",
+        register: |process| N2CServer::register(process),
+        requires: &[("blocks-subscribe-topic", "cardano.block.available")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "midnight-state",
+        register: |process| MidnightState::register(process),
+        requires: &[
+            ("address-deltas-topic", "cardano.address.deltas"),
+            ("epoch-nonce-topic", "cardano.epoch.nonce"),
+        ],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "sidechain-observer",
+        register: |process| SidechainObserverModule::register(process),
+        requires: &[("address-deltas-topic", "cardano.address.deltas")],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "stats",
+        register: |process| Stats::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "clock",
+        register: |process| Clock::<Message>::register(process),
+        requires: &[],
+        produces: &[("topic", "clock.tick")],
+    },
+    ModuleSpec {
+        name: "rest-server",
+        register: |process| RESTServer::<Message>::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "spy",
+        register: |process| Spy::<Message>::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "reload-coordinator",
+        register: |process| ReloadCoordinator::register(process),
+        requires: &[],
+        produces: &[],
+    },
+    ModuleSpec {
+        name: "shutdown-coordinator",
+        register: |process| ShutdownCoordinator::register(process),
+        requires: &[],
+        produces: &[],
+    },
+];
+
+/// Bootstrap, fetch and unpack the chain, and keep the basic ledger state
+const CORE: &[&str] = &[
+    "genesis-bootstrapper",
+    "snapshot-bootstrapper",
+    "mithril-snapshot-fetcher",
+    "peer-network-interface",
+    "consensus",
+    "block-unpacker",
+    "tx-unpacker",
+    "utxo-state",
+    "parameters-state",
+    "epochs-state",
+    "clock",
+    "stats",
+    "reload-coordinator",
+    "shutdown-coordinator",
+];
+
+/// Stake, pools, DReps and governance
+const LEDGER: &[&str] = &[
+    "spo-state",
+    "drep-state",
+    "governance-state",
+    "stake-delta-filter",
+    "accounts-state",
+];
+
+/// Built-in profiles, as groups of modules
+const PROFILES: &[(&str, &[&[&str]])] = &[
+    (
+        "api-node",
+        &[
+            CORE,
+            LEDGER,
+            &[
+                "chain-store",
+                "address-state",
+                "assets-state",
+                "scripts-state",
+                "historical-accounts-state",
+                "historical-epochs-state",
+                "spdd-state",
+                "drdd-state",
+                "rest-blockfrost",
+                "rest-server",
+                "mcp-server",
+                "graphql",
+                "utxorpc",
+                "n2c-server",
+            ],
+        ],
+    ),
+    (
+        "indexer",
+        &[
+            CORE,
+            &[
+                "chain-store",
+                "address-state",
+                "assets-state",
+                "scripts-state",
+                "event-stream",
+                "utxorpc",
+            ],
+        ],
+    ),
+    (
+        "validator",
+        &[
+            CORE,
+            LEDGER,
+            &["chain-store", "block-vrf-validator", "block-kes-validator"],
+        ],
+    ),
+];
+
+/// The `[modules]` section
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct ModulesConfig {
+    /// Profile to start from - every module if not given
+    pub profile: Option<String>,
+
+    /// Modules to add to the profile
+    pub enable: Vec<String>,
+
+    /// Modules to take out of the profile
+    pub disable: Vec<String>,
+
+    /// Extra profiles, which replace any built-in one of the same name
+    pub profiles: HashMap<String, Vec<String>>,
+}
+
+fn spec(name: &str) -> Option<&'static ModuleSpec> {
+    MODULES.iter().find(|module| module.name == name)
+}
+
+/// Pick the modules to register, adding any problems found
+pub fn select(config: &Config, errors: &mut Vec<String>) -> Vec<&'static ModuleSpec> {
+    let modules = match config.get::<ModulesConfig>("modules") {
+        Ok(modules) => modules,
+        Err(ConfigError::NotFound(_)) => ModulesConfig::default(),
+        Err(e) => {
+            errors.push(format!("[modules] {e}"));
+            return Vec::new();
+        }
+    };
+    select_from(&modules, errors)
+}
+
+fn select_from(modules: &ModulesConfig, errors: &mut Vec<String>) -> Vec<&'static ModuleSpec> {
+    let known = |name: &str, errors: &mut Vec<String>| {
+        let found = spec(name).is_some();
+        if !found {
+            errors.push(format!("[modules] unknown module '{name}'"));
+        }
+        found
+    };
+
+    let mut selected: BTreeSet<String> = match &modules.profile {
+        None => MODULES.iter().map(|module| module.name.to_string()).collect(),
+        Some(profile) => {
+            if let Some(names) = modules.profiles.get(profile) {
+                names.iter().filter(|name| known(name, errors)).cloned().collect()
+            } else if let Some((_, groups)) =
+                PROFILES.iter().find(|(name, _)| *name == profile.as_str())
+            {
+                groups.iter().flat_map(|group| group.iter()).map(|name| name.to_string()).collect()
+            } else {
+                errors.push(format!("[modules] unknown profile '{profile}'"));
+                BTreeSet::new()
+            }
+        }
+    };
+    for name in &modules.enable {
+        if known(name, errors) {
+            selected.insert(name.clone());
+        }
+    }
+    for name in &modules.disable {
+        if known(name, errors) {
+            selected.remove(name);
+        }
+    }
+
+    MODULES.iter().filter(|module| selected.contains(module.name)).collect()
+}
+
+/// Whether a selected module will actually run - it needs a `[module.*]`
+/// section, and some have an `enabled` flag as well
+fn is_active(config: &Config, module: &ModuleSpec) -> bool {
+    let section = format!("module.{}", module.name);
+    config.get_table(&section).is_ok()
+        && config.get_bool(&format!("{section}.enabled")).unwrap_or(true)
+}
+
+fn topic(config: &Config, module: &ModuleSpec, (key, default): Topic) -> Option<String> {
+    let topic = config
+        .get_string(&format!("module.{}.{key}", module.name))
+        .unwrap_or_else(|_| default.to_string());
+    (!topic.is_empty()).then_some(topic)
+}
+
+/// Check every topic an active module requires is published by another active
+/// module, adding any problems found
+pub fn check_dependencies(config: &Config, selected: &[&ModuleSpec], errors: &mut Vec<String>) {
+    let active: Vec<_> = selected.iter().filter(|module| is_active(config, module)).collect();
+
+    let mut producers: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for module in &active {
+        for produced in module.produces {
+            if let Some(topic) = topic(config, module, *produced) {
+                producers.entry(topic).or_default().push(module.name);
+            }
+        }
+    }
+
+    for module in &active {
+        for required in module.requires {
+            if let Some(topic) = topic(config, module, *required) {
+                if !producers.contains_key(&topic) {
+                    errors.push(format!(
+                        "[module.{}] nothing publishes '{topic}' ({})",
+                        module.name, required.0
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{File, FileFormat};
+
+    fn config(toml: &str) -> Config {
+        Config::builder().add_source(File::from_str(toml, FileFormat::Toml)).build().unwrap()
+    }
+
+    fn names(modules: &[&ModuleSpec]) -> Vec<&'static str> {
+        modules.iter().map(|module| module.name).collect()
+    }
+
+    #[test]
+    fn every_module_selected_without_a_modules_section() {
+        let mut errors = Vec::new();
+        let selected = select(&config(""), &mut errors);
+        assert!(errors.is_empty());
+        assert_eq!(selected.len(), MODULES.len());
+    }
+
+    #[test]
+    fn profile_adjusted_by_enable_and_disable() {
+        let mut errors = Vec::new();
+        let selected = select(
+            &config(
+                r#"
+                [modules]
+                profile = "validator"
+                enable = ["spy"]
+                disable = ["chain-store", "stats"]
+                "#,
+            ),
+            &mut errors,
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        let selected = names(&selected);
+        assert!(selected.contains(&"block-vrf-validator"));
+        assert!(selected.contains(&"spy"));
+        assert!(!selected.contains(&"chain-store"));
+        assert!(!selected.contains(&"stats"));
+        assert!(!selected.contains(&"rest-blockfrost"));
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        let mut errors = Vec::new();
+        select(
+            &config(
+                r#"
+                [modules]
+                profile = "miner"
+                disable = ["no-such-module"]
+                "#,
+            ),
+            &mut errors,
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "[modules] unknown profile 'miner'",
+                "[modules] unknown module 'no-such-module'",
+            ]
+        );
+    }
+
+    #[test]
+    fn built_in_profiles_name_known_modules() {
+        for (profile, groups) in PROFILES {
+            for name in groups.iter().flat_map(|group| group.iter()) {
+                assert!(spec(name).is_some(), "{profile} has unknown module {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn missing_producer_is_an_error() {
+        let config = config(
+            r#"
+            [module.block-unpacker]
+            [module.tx-unpacker]
+            publish-utxo-deltas-topic = "cardano.utxo.deltas"
+            [module.parameters-state]
+            [module.utxo-state]
+            [module.address-state]
+            [module.spdd-state]
+            enabled = false
+            "#,
+        );
+        let mut errors = Vec::new();
+        check_dependencies(&config, &select(&config, &mut errors), &mut errors);
+        assert_eq!(
+            errors,
+            vec![
+                "[module.block-unpacker] nothing publishes 'cardano.block.proposed' (subscribe-topic)",
+                "[module.address-state] nothing publishes 'cardano.address.deltas' (address-deltas-subscribe-topic)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Reloads the configuration on SIGHUP or a ReloadConfig command, applying what
+//! the modules can change without a restart
+
+use acropolis_common::{
+    config_reload::{self, DEFAULT_RELOAD_REPORT_TOPIC, DEFAULT_RELOAD_TOPIC},
+    configuration::get_string_flag,
+    messages::{Command, CommandResponse, ConfigReloadReport, Message},
+};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::sync::{Arc, OnceLock};
+use tracing::{error, info, warn};
+
+/// Config files the process was started with, and the config read from them
+static STARTUP: OnceLock<(Vec<String>, Config)> = OnceLock::new();
+
+/// Record the config the process is started with, to reload and compare against
+pub fn set_startup_config(files: Vec<String>, config: Config) {
+    let _ = STARTUP.set((files, config));
+}
+
+/// Reload coordinator module
+#[module(
+    message_type(Message),
+    name = "reload-coordinator",
+    description = "Reloads the configuration while the process runs"
+)]
+pub struct ReloadCoordinator;
+
+impl ReloadCoordinator {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let Some((files, startup)) = STARTUP.get() else {
+            warn!("No startup configuration recorded, reloading is disabled");
+            return Ok(());
+        };
+
+        let topic = get_string_flag(&config, DEFAULT_RELOAD_TOPIC);
+        let report_topic = get_string_flag(&config, DEFAULT_RELOAD_REPORT_TOPIC);
+        info!("Reloading configuration on SIGHUP, or on '{topic}'");
+        let mut subscription = context.subscribe(&topic).await?;
+        let mut hangup = Hangup::new()?;
+
+        let run_context = context.clone();
+        context.run(async move {
+            loop {
+                tokio::select! {
+                    _ = hangup.recv() => info!("Hangup, reloading configuration"),
+                    result = subscription.read() => match result {
+                        Ok((_, message)) => {
+                            if !matches!(message.as_ref(), Message::Command(Command::ReloadConfig)) {
+                                continue;
+                            }
+                            info!("Reloading configuration on request");
+                        }
+                        Err(_) => return,
+                    },
+                }
+
+                // Changes are always against the startup config, since only
+                // some of them can be applied
+                let result = match crate::load_config(files) {
+                    Ok(new) => config_reload::reload(&run_context, &config, startup, &new).await,
+                    Err(e) => Err(e),
+                };
+                let report = match result {
+                    Ok(report) => report,
+                    Err(e) => {
+                        error!("Could not reload configuration: {e:#}");
+                        continue;
+                    }
+                };
+
+                log_report(&report);
+                let message =
+                    Arc::new(Message::CommandResponse(CommandResponse::ConfigReload(report)));
+                if let Err(e) = run_context.publish(&report_topic, message).await {
+                    warn!("Could not publish reload report: {e:#}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn log_report(report: &ConfigReloadReport) {
+    if report.applied.is_empty() && report.restart_required.is_empty() {
+        info!("Configuration unchanged");
+    }
+    if !report.applied.is_empty() {
+        info!("Applied {}", report.applied.join(", "));
+    }
+    if !report.restart_required.is_empty() {
+        warn!(
+            "Restart required to apply {}",
+            report.restart_required.join(", ")
+        );
+    }
+    for (module, error) in &report.failed {
+        error!("{module} could not apply its changed configuration: {error}");
+    }
+}
+
+/// SIGHUP, where there is one
+struct Hangup {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl Hangup {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+        })
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        self.signal.recv().await;
+        #[cfg(not(unix))]
+        std::future::pending::<()>().await;
+    }
+}
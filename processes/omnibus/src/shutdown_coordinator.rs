@@ -0,0 +1,79 @@
+//! Shuts the process down cleanly on SIGINT or SIGTERM, or when asked to from
+//! within the process, once the modules have flushed their on-disk stores
+
+use acropolis_common::{messages::Message, shutdown};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// Notified once the shutdown has finished, or been abandoned
+static FINISHED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Notified when something in the process asks for a shutdown
+static REQUESTED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Shutdown coordinator module
+#[module(
+    message_type(Message),
+    name = "shutdown-coordinator",
+    description = "Flushes the modules' stores before the process exits"
+)]
+pub struct ShutdownCoordinator;
+
+impl ShutdownCoordinator {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let run_context = context.clone();
+        context.run(async move {
+            tokio::select! {
+                result = wait_for_signal() => {
+                    if let Err(e) = result {
+                        error!("Could not listen for signals: {e}");
+                        return;
+                    }
+                    info!("Interrupted, flushing before exit");
+                }
+                _ = REQUESTED.notified() => info!("Shutdown requested, flushing before exit"),
+            }
+
+            tokio::select! {
+                result = shutdown::shutdown(&run_context, &config) => match result {
+                    Ok(true) => info!("All modules flushed"),
+                    Ok(false) => warn!("Exiting without every module flushed"),
+                    Err(e) => error!("Shutdown failed: {e:#}"),
+                },
+                _ = wait_for_signal() => warn!("Interrupted again, exiting without waiting"),
+            }
+            FINISHED.notify_one();
+        });
+
+        Ok(())
+    }
+}
+
+/// Shut the process down as if interrupted
+pub fn request() {
+    REQUESTED.notify_one();
+}
+
+/// Wait until the shutdown has finished
+pub async fn finished() {
+    FINISHED.notified().await
+}
+
+async fn wait_for_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
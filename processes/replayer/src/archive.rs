@@ -0,0 +1,230 @@
+//! Versioned archive of captured bus traffic
+//!
+//! An archive is a directory holding `manifest.json` - the format version, and
+//! the topics and slots captured - and `messages.jsonl`, one `{topic, message}`
+//! record per line in the order the messages arrived.
+
+use acropolis_common::messages::Message;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Lines, Write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Format written by this version - archives in any other are refused
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const MESSAGES_FILE: &str = "messages.jsonl";
+
+/// Flush the messages and manifest every so many messages, so an interrupted
+/// capture can still be replayed
+const FLUSH_INTERVAL: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Manifest {
+    pub version: u32,
+    pub topics: Vec<String>,
+
+    /// Slot bounds the capture was asked for
+    pub from_slot: u64,
+    pub to_slot: Option<u64>,
+
+    /// Messages written, and the slots of the blocks they came with
+    pub messages: u64,
+    pub first_slot: Option<u64>,
+    pub last_slot: Option<u64>,
+
+    /// Whether the capture reached its end, rather than being interrupted
+    pub complete: bool,
+}
+
+#[derive(Serialize)]
+struct RecordRef<'a> {
+    topic: &'a str,
+    message: &'a Message,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    topic: String,
+    message: Message,
+}
+
+/// Slot of the block a message came with, if any
+pub fn slot(message: &Message) -> Option<u64> {
+    match message {
+        Message::Cardano((block, _)) => Some(block.slot),
+        _ => None,
+    }
+}
+
+/// Writes a new archive
+pub struct ArchiveWriter {
+    dir: PathBuf,
+    manifest: Manifest,
+    messages: BufWriter<File>,
+}
+
+impl ArchiveWriter {
+    /// Create an archive in `dir`, replacing any already there
+    pub fn create(
+        dir: &Path,
+        topics: &[String],
+        from_slot: u64,
+        to_slot: Option<u64>,
+    ) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let writer = Self {
+            dir: dir.to_path_buf(),
+            manifest: Manifest {
+                version: ARCHIVE_VERSION,
+                topics: topics.to_vec(),
+                from_slot,
+                to_slot,
+                messages: 0,
+                first_slot: None,
+                last_slot: None,
+                complete: false,
+            },
+            messages: BufWriter::new(File::create(dir.join(MESSAGES_FILE))?),
+        };
+        writer.write_manifest()?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, topic: &str, message: &Message) -> Result<()> {
+        serde_json::to_writer(&mut self.messages, &RecordRef { topic, message })?;
+        self.messages.write_all(b"\n")?;
+
+        self.manifest.messages += 1;
+        if let Some(slot) = slot(message) {
+            self.manifest.first_slot.get_or_insert(slot);
+            self.manifest.last_slot = Some(slot);
+        }
+        if self.manifest.messages.is_multiple_of(FLUSH_INTERVAL) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.messages.flush()?;
+        self.write_manifest()
+    }
+
+    fn write_manifest(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join(MANIFEST_FILE), json)?;
+        Ok(())
+    }
+
+    /// Mark the archive complete
+    pub fn finish(mut self) -> Result<Manifest> {
+        self.manifest.complete = true;
+        self.flush()?;
+        Ok(self.manifest)
+    }
+}
+
+/// Reads an archive's messages in the order they were captured
+pub struct ArchiveReader {
+    pub manifest: Manifest,
+    lines: Lines<BufReader<File>>,
+    line: u64,
+}
+
+impl ArchiveReader {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Could not read {}: {e}", manifest_path.display()))?;
+        let manifest: Manifest = serde_json::from_str(&manifest)
+            .map_err(|e| anyhow!("Bad manifest {}: {e}", manifest_path.display()))?;
+        if manifest.version != ARCHIVE_VERSION {
+            bail!(
+                "Archive {} is version {}, this replayer reads version {ARCHIVE_VERSION}",
+                dir.display(),
+                manifest.version
+            );
+        }
+        if !manifest.complete {
+            warn!(
+                "Archive {} was not completed, replaying what was captured",
+                dir.display()
+            );
+        }
+
+        let messages = File::open(dir.join(MESSAGES_FILE))?;
+        Ok(Self {
+            manifest,
+            lines: BufReader::new(messages).lines(),
+            line: 0,
+        })
+    }
+}
+
+impl Iterator for ArchiveReader {
+    /// Topic and message
+    type Item = Result<(String, Message)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        self.line += 1;
+        let record = line.map_err(anyhow::Error::from).and_then(|line| {
+            serde_json::from_str::<Record>(&line)
+                .map_err(|e| anyhow!("Bad record at line {}: {e}", self.line))
+        });
+        Some(record.map(|record| (record.topic, record.message)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_round_trips_in_capture_order() {
+        let dir = std::env::temp_dir().join(format!("replayer-archive-{}", std::process::id()));
+        let topics = vec![
+            "cardano.governance".to_string(),
+            "cardano.spo.state".to_string(),
+        ];
+
+        let mut writer = ArchiveWriter::create(&dir, &topics, 100, Some(200)).unwrap();
+        writer.write("cardano.spo.state", &Message::String("first".into())).unwrap();
+        writer.write("cardano.governance", &Message::String("second".into())).unwrap();
+        let manifest = writer.finish().unwrap();
+        assert_eq!(manifest.messages, 2);
+
+        let reader = ArchiveReader::open(&dir).unwrap();
+        assert_eq!(reader.manifest, manifest);
+        let records: Vec<(String, Message)> = reader.map(Result::unwrap).collect();
+        assert_eq!(
+            records
+                .iter()
+                .map(|(topic, message)| match message {
+                    Message::String(s) => format!("{topic} {s}"),
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect::<Vec<_>>(),
+            vec!["cardano.spo.state first", "cardano.governance second"]
+        );
+
+        // Other versions are refused
+        let mut old = manifest.clone();
+        old.version = ARCHIVE_VERSION + 1;
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_string(&old).unwrap(),
+        )
+        .unwrap();
+        assert!(ArchiveReader::open(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
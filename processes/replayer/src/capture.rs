@@ -0,0 +1,150 @@
+//! Topic capture module - records any set of topics between slot bounds into an
+//! archive, to replay later
+
+use acropolis_common::{configuration::ModuleConfig, messages::Message};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::{path::Path, sync::Arc};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+use crate::{
+    archive::{self, ArchiveWriter},
+    replayer_config::CaptureConfig,
+};
+
+/// Messages waiting to be written, before the topics are held up
+const CAPTURE_QUEUE_SIZE: usize = 1000;
+
+/// Topic capture module
+#[module(
+    message_type(Message),
+    name = "topic-capture",
+    description = "Captures topics into an archive"
+)]
+pub struct TopicCapture;
+
+/// What to do with a message
+#[derive(Debug, PartialEq)]
+enum Admit {
+    Skip,
+    Keep,
+    Done,
+}
+
+/// Which messages fall within the slot bounds
+struct CaptureWindow {
+    from_slot: u64,
+    to_slot: Option<u64>,
+
+    /// Whether a block in the bounds has been seen - messages without a block
+    /// are kept from then on
+    open: bool,
+}
+
+impl CaptureWindow {
+    fn new(from_slot: u64, to_slot: Option<u64>) -> Self {
+        Self {
+            from_slot,
+            to_slot,
+            open: false,
+        }
+    }
+
+    fn admit(&mut self, slot: Option<u64>) -> Admit {
+        match slot {
+            Some(slot) if slot < self.from_slot => Admit::Skip,
+            Some(slot) if self.to_slot.is_some_and(|to_slot| slot > to_slot) => Admit::Done,
+            Some(_) => {
+                self.open = true;
+                Admit::Keep
+            }
+            None if self.open => Admit::Keep,
+            None => Admit::Skip,
+        }
+    }
+}
+
+impl TopicCapture {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = CaptureConfig::load(&config)?;
+        let mut writer = ArchiveWriter::create(
+            Path::new(&cfg.path),
+            &cfg.topics,
+            cfg.from_slot,
+            cfg.to_slot,
+        )?;
+        info!(
+            "Capturing slots {} to {} into {}",
+            cfg.from_slot,
+            cfg.to_slot.map_or("the end".to_string(), |slot| slot.to_string()),
+            cfg.path
+        );
+
+        // Funnel every topic through one writer, so the archive keeps the
+        // order the messages arrived in
+        let (sender, mut receiver) = mpsc::channel(CAPTURE_QUEUE_SIZE);
+        for topic in &cfg.topics {
+            info!("Creating subscriber on '{topic}'");
+            let mut subscription = context.subscribe(topic).await?;
+            let sender = sender.clone();
+            let topic = topic.clone();
+            context.run(async move {
+                while let Ok((_, message)) = subscription.read().await {
+                    if sender.send((topic.clone(), message)).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut window = CaptureWindow::new(cfg.from_slot, cfg.to_slot);
+        context.run(async move {
+            while let Some((topic, message)) = receiver.recv().await {
+                match window.admit(archive::slot(&message)) {
+                    Admit::Skip => continue,
+                    Admit::Keep => {
+                        if let Err(e) = writer.write(&topic, &message) {
+                            error!("Could not capture message on '{topic}': {e:#}");
+                            return;
+                        }
+                    }
+                    Admit::Done => break,
+                }
+            }
+
+            match writer.finish() {
+                Ok(manifest) => info!(
+                    "Captured {} messages, slots {:?} to {:?}",
+                    manifest.messages, manifest.first_slot, manifest.last_slot
+                ),
+                Err(e) => error!("Could not finish capture: {e:#}"),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_keeps_the_slot_bounds() {
+        let mut window = CaptureWindow::new(100, Some(200));
+
+        // Nothing is kept before the first block in the bounds
+        assert_eq!(window.admit(None), Admit::Skip);
+        assert_eq!(window.admit(Some(99)), Admit::Skip);
+        assert_eq!(window.admit(Some(100)), Admit::Keep);
+        assert_eq!(window.admit(None), Admit::Keep);
+        assert_eq!(window.admit(Some(200)), Admit::Keep);
+        assert_eq!(window.admit(Some(201)), Admit::Done);
+
+        let mut unbounded = CaptureWindow::new(0, None);
+        assert_eq!(unbounded.admit(Some(u64::MAX)), Admit::Keep);
+    }
+}
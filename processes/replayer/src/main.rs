@@ -37,14 +37,19 @@ use caryatid_module_clock::Clock;
 use caryatid_module_rest_server::RESTServer;
 use caryatid_module_spy::Spy;
 
+mod archive;
+mod capture;
 mod playback;
 mod recorder;
 mod recorder_alonzo_governance;
+mod replay;
 mod replayer_config;
 
+use capture::TopicCapture;
 use playback::Playback;
 use recorder::Recorder;
 use recorder_alonzo_governance::RecorderAlonzoGovernance;
+use replay::TopicReplay;
 
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
@@ -116,10 +121,59 @@ fn setup_governance_replay(process: &mut dyn ModuleRegistry<Message>) {
     Spy::<Message>::register(process);
 }
 
+/// Sync from the network, capturing the configured topics
+fn setup_capture(process: &mut dyn ModuleRegistry<Message>) {
+    tracing::info!("Capturing");
+    GenesisBootstrapper::register(process);
+    MithrilSnapshotFetcher::register(process);
+    PeerNetworkInterface::register(process);
+    BlockUnpacker::register(process);
+    TxUnpacker::register(process);
+    UTXOState::register(process);
+    SPOState::register(process);
+    DRepState::register(process);
+    GovernanceState::register(process);
+    ParametersState::register(process);
+    StakeDeltaFilter::register(process);
+    EpochsState::register(process);
+    AccountsState::register(process);
+    SPDDState::register(process);
+    DRDDState::register(process);
+    Consensus::register(process);
+
+    TopicCapture::register(process);
+
+    Clock::<Message>::register(process);
+    RESTServer::<Message>::register(process);
+    Spy::<Message>::register(process);
+}
+
+/// Feed captured topics to whichever state modules are configured
+fn setup_replay(process: &mut dyn ModuleRegistry<Message>) {
+    tracing::info!("Replaying");
+    UTXOState::register(process);
+    SPOState::register(process);
+    DRepState::register(process);
+    GovernanceState::register(process);
+    ParametersState::register(process);
+    StakeDeltaFilter::register(process);
+    EpochsState::register(process);
+    AccountsState::register(process);
+    SPDDState::register(process);
+    DRDDState::register(process);
+    BlockfrostREST::register(process);
+
+    TopicReplay::register(process);
+
+    Clock::<Message>::register(process);
+    RESTServer::<Message>::register(process);
+    Spy::<Message>::register(process);
+}
+
 #[derive(Debug, clap::Parser)]
 #[command(
     name = "acropolis_process_replayer",
-    group(clap::ArgGroup::new("mode").required(true).args(&["governance_collect", "governance_replay", "alonzo_governance_collect"])),
+    group(clap::ArgGroup::new("mode").required(true).args(&["governance_collect", "governance_replay", "alonzo_governance_collect", "capture", "replay"])),
 )]
 struct Args {
     #[arg(long, value_name = "PATH", default_values_t = vec![option_env!("ACROPOLIS_REPLAYER_DEFAULT_CONFIG").unwrap_or("replayer.toml").to_string()])]
@@ -135,6 +189,14 @@ struct Args {
 
     #[arg(long)]
     alonzo_governance_collect: bool,
+
+    /// Capture the topics in [module.topic-capture] into an archive
+    #[arg(long)]
+    capture: bool,
+
+    /// Replay the archive in [module.topic-replay]
+    #[arg(long)]
+    replay: bool,
 }
 
 #[tokio::main]
@@ -183,6 +245,10 @@ pub async fn main() -> Result<()> {
         setup_governance_replay(&mut process)
     } else if args.alonzo_governance_collect {
         setup_alonzo_governance_collect(&mut process)
+    } else if args.capture {
+        setup_capture(&mut process)
+    } else if args.replay {
+        setup_replay(&mut process)
     } else {
         unreachable!()
     }
@@ -1,10 +1,12 @@
 //! Governance recorder module
 
 use acropolis_common::{
+    caryatid::ChunkAssembler,
     messages::{
         CardanoMessage, DRepStakeDistributionMessage, GovernanceProceduresMessage, Message,
         SPOStakeDistributionMessage,
     },
+    spdd_chunks::SPDDAssembler,
     BlockInfo,
 };
 use anyhow::{anyhow, Result};
@@ -78,13 +80,23 @@ impl Recorder {
     async fn read_spo(
         spo_s: &mut Box<dyn Subscription<Message>>,
     ) -> Result<(BlockInfo, SPOStakeDistributionMessage)> {
-        match spo_s.read().await?.1.as_ref() {
-            Message::Cardano((blk, CardanoMessage::SPOStakeDistribution(distr))) => {
-                Ok((blk.clone(), distr.clone()))
+        let mut assembler = SPDDAssembler::default();
+        loop {
+            match spo_s.read().await?.1.as_ref() {
+                Message::Cardano((blk, CardanoMessage::SPOStakeDistribution(distr))) => {
+                    return Ok((blk.clone(), distr.clone()));
+                }
+                Message::Cardano((blk, CardanoMessage::SPOStakeDistributionChunk(chunk))) => {
+                    if let Some(distr) = assembler.push(chunk)? {
+                        return Ok((blk.clone(), distr));
+                    }
+                }
+                msg => {
+                    return Err(anyhow!(
+                        "Unexpected message {msg:?} for SPO distribution topic"
+                    ));
+                }
             }
-            msg => Err(anyhow!(
-                "Unexpected message {msg:?} for SPO distribution topic"
-            )),
         }
     }
 
@@ -0,0 +1,83 @@
+//! Topic replay module - publishes the messages in an archive on the topics
+//! they were captured from, at a chosen speed
+
+use acropolis_common::{configuration::ModuleConfig, messages::Message};
+use anyhow::Result;
+use caryatid_sdk::{module, Context};
+use config::Config;
+use std::{path::Path, sync::Arc, time::Duration};
+use tracing::{error, info};
+
+use crate::{
+    archive::{self, ArchiveReader},
+    replayer_config::ReplayConfig,
+};
+
+/// Topic replay module
+#[module(
+    message_type(Message),
+    name = "topic-replay",
+    description = "Replays topics from an archive"
+)]
+pub struct TopicReplay;
+
+impl TopicReplay {
+    pub async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let cfg = ReplayConfig::load(&config)?;
+        let reader = ArchiveReader::open(Path::new(&cfg.path))?;
+        let manifest = &reader.manifest;
+        info!(
+            "Replaying {} messages on {}, slots {:?} to {:?}, from {}",
+            manifest.messages,
+            manifest.topics.join(", "),
+            manifest.first_slot,
+            manifest.last_slot,
+            cfg.path
+        );
+
+        info!("Creating startup subscriber on '{}'", cfg.startup_topic);
+        let mut startup = context.subscribe(&cfg.startup_topic).await?;
+        let run_context = context.clone();
+        context.run(async move {
+            let Ok(_) = startup.read().await else {
+                return;
+            };
+            match Self::replay(&run_context, reader, &cfg).await {
+                Ok(count) => info!("Replayed {count} messages"),
+                Err(e) => error!("Replay failed: {e:#}"),
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn replay(
+        context: &Arc<Context<Message>>,
+        reader: ArchiveReader,
+        cfg: &ReplayConfig,
+    ) -> Result<u64> {
+        let mut count = 0;
+        let mut last_slot = None;
+        for record in reader {
+            let (topic, message) = record?;
+            if !cfg.topics.is_empty() && !cfg.topics.contains(&topic) {
+                continue;
+            }
+
+            // Keep to the speed, going by the slots of the blocks
+            if let Some(slot) = archive::slot(&message) {
+                if let Some(last_slot) = last_slot.filter(|_| cfg.speed > 0.0) {
+                    let slots = slot.saturating_sub(last_slot);
+                    if slots > 0 {
+                        tokio::time::sleep(Duration::from_secs_f64(slots as f64 / cfg.speed)).await;
+                    }
+                }
+                last_slot = Some(slot);
+            }
+
+            context.message_bus.publish(&topic, Arc::new(message)).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
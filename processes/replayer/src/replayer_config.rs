@@ -1,3 +1,4 @@
+use acropolis_common::configuration::ModuleConfig;
 use config::Config;
 use std::sync::Arc;
 use tracing::info;
@@ -56,3 +57,82 @@ impl ReplayerConfig {
         ])
     }
 }
+
+/// The `[module.topic-capture]` section
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CaptureConfig {
+    /// Topics to capture
+    pub topics: Vec<String>,
+
+    /// Archive directory
+    pub path: String,
+
+    /// First slot to capture
+    pub from_slot: u64,
+
+    /// Last slot to capture - until stopped if not given
+    pub to_slot: Option<u64>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            topics: Vec::new(),
+            path: "capture".to_string(),
+            from_slot: 0,
+            to_slot: None,
+        }
+    }
+}
+
+impl ModuleConfig for CaptureConfig {
+    const MODULE: &'static str = "topic-capture";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.topics.is_empty() {
+            errors.push("topics must name at least one topic".to_string());
+        }
+        if self.to_slot.is_some_and(|to_slot| to_slot < self.from_slot) {
+            errors.push("to-slot must not be before from-slot".to_string());
+        }
+    }
+}
+
+/// The `[module.topic-replay]` section
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ReplayConfig {
+    /// Archive directory
+    pub path: String,
+
+    /// Topics to replay - every captured topic if empty
+    pub topics: Vec<String>,
+
+    /// Slots replayed per second, 0 for as fast as possible
+    pub speed: f64,
+
+    /// Replay starts once the process has started
+    pub startup_topic: String,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            path: "capture".to_string(),
+            topics: Vec::new(),
+            speed: 0.0,
+            startup_topic: "cardano.sequence.start".to_string(),
+        }
+    }
+}
+
+impl ModuleConfig for ReplayConfig {
+    const MODULE: &'static str = "topic-replay";
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        if !self.speed.is_finite() || self.speed < 0.0 {
+            errors.push(format!("speed must be 0 or more, not {}", self.speed));
+        }
+    }
+}
@@ -47,9 +47,22 @@ rm -rf processes/omnibus/downloads/* \
 # start omnibus process in the background as it needs to
 #  still be running when this step ends
 make run-bootstrap-store-spdd-drdd > $logfile 2>&1  &
+omnibus_pid=$!
+
+# fail if omnibus has exited - the correctness verifier stops it on a
+# discrepancy with the reference SPDD or rewards
+check_omnibus_running()
+{
+  if ! kill -0 "$omnibus_pid" 2>/dev/null; then
+    echo "Omnibus process exited before reaching the target epoch"
+    grep -E "ERROR" $logfile | tail -n 50 || true
+    exit 1
+  fi
+}
 
 # give omnibus plenty of time to get REST up and running
 sleep 30
+check_omnibus_running
 
 current_acropolis_epoch=$(get_acropolis_epoch latest)
 
@@ -61,6 +74,7 @@ do
   printf "%(%c )T"
   echo "Acropolis epoch: $current_acropolis_epoch"
   sleep "$sleeptime"
+  check_omnibus_running
   current_acropolis_epoch=$(get_acropolis_epoch latest)
 done
 
@@ -0,0 +1,293 @@
+//! Deterministic simulation of modules, for tests
+//!
+//! A [`Simulation`] runs a set of modules on an in-memory bus, without a
+//! network or snapshot. Tests feed them a [`Script`] of messages, move a
+//! virtual clock on, and check what they published on each topic - against
+//! expectations in the test, or a golden file:
+//!
+//! ```ignore
+//! #[tokio::test(start_paused = true)]
+//! async fn spo_state_follows_certificates() -> Result<()> {
+//!     let mut sim = Simulation::new(include_str!("spo.toml"))?;
+//!     sim.start("spo-state", |context, config| async move {
+//!         SPOState.init(context, config).await
+//!     })
+//!     .await?;
+//!     sim.capture("cardano.spo.state").await?;
+//!     sim.run(&Script::from_file("tests/spo/script.json")?).await?;
+//!     sim.assert_golden("tests/spo/golden.json")
+//! }
+//! ```
+//!
+//! Run the tests on a paused tokio runtime (tokio's `test-util` feature) so that
+//! timers in the modules follow the virtual clock. Golden files are written, rather than compared, when
+//! `UPDATE_GOLDEN` is set.
+
+use acropolis_common::messages::{ClockTickMessage, Message};
+use anyhow::{anyhow, bail, Result};
+use caryatid_sdk::{mock_bus::MockBus, Context};
+use chrono::{DateTime, TimeDelta, Utc};
+use config::{Config, File, FileFormat};
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::watch;
+
+mod script;
+pub use script::{Script, Step};
+
+/// Topic the virtual clock ticks on, as the clock module's default
+pub const CLOCK_TOPIC: &str = "clock.tick";
+
+/// Environment variable which makes [`Simulation::assert_golden`] write the
+/// golden file instead
+pub const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+/// Rounds in a row with nothing new captured before the modules are taken to
+/// have settled
+const SETTLE_QUIET_ROUNDS: usize = 3;
+
+/// Most rounds to wait for the modules to settle
+const SETTLE_MAX_ROUNDS: usize = 1000;
+
+/// Messages published on each captured topic, in order
+type Captured = Arc<Mutex<BTreeMap<String, Vec<Arc<Message>>>>>;
+
+pub struct Simulation {
+    context: Arc<Context<Message>>,
+    captured: Captured,
+
+    /// Virtual time of the last clock tick, and how many there have been
+    now: DateTime<Utc>,
+    ticks: u64,
+
+    /// Keeps the modules running
+    _running: watch::Sender<bool>,
+}
+
+impl Simulation {
+    /// Simulate a process with this config, in TOML
+    pub fn new(config: &str) -> Result<Self> {
+        let config = Arc::new(
+            Config::builder().add_source(File::from_str(config, FileFormat::Toml)).build()?,
+        );
+        let bus = Arc::new(MockBus::<Message>::new(&config));
+        let (running, run_signal) = watch::channel(true);
+        Ok(Self {
+            context: Arc::new(Context::new(config, bus, run_signal)),
+            captured: Captured::default(),
+            now: DateTime::UNIX_EPOCH,
+            ticks: 0,
+            _running: running,
+        })
+    }
+
+    pub fn context(&self) -> Arc<Context<Message>> {
+        self.context.clone()
+    }
+
+    /// Config a module is given - its `[module.*]` section, with the `[global]`
+    /// section merged in as the process does
+    pub fn module_config(&self, module: &str) -> Result<Arc<Config>> {
+        let process: serde_json::Value = (*self.context.config).clone().try_deserialize()?;
+        let mut builder = Config::builder();
+        for section in [&process["global"], &process["module"][module]] {
+            if section.is_object() {
+                builder =
+                    builder.add_source(File::from_str(&section.to_string(), FileFormat::Json));
+            }
+        }
+        Ok(Arc::new(builder.build()?))
+    }
+
+    /// Start a module, by calling its init with its config
+    pub async fn start<F, Fut>(&self, module: &str, init: F) -> Result<()>
+    where
+        F: FnOnce(Arc<Context<Message>>, Arc<Config>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        init(self.context.clone(), self.module_config(module)?)
+            .await
+            .map_err(|e| anyhow!("{module} failed to start: {e:#}"))?;
+        self.settle().await;
+        Ok(())
+    }
+
+    /// Record everything published on a topic from now on
+    pub async fn capture(&self, topic: &str) -> Result<()> {
+        let mut subscription = self.context.subscribe(topic).await?;
+        let captured = self.captured.clone();
+        captured.lock().unwrap().entry(topic.to_string()).or_default();
+        let topic = topic.to_string();
+        self.context.run(async move {
+            while let Ok((_, message)) = subscription.read().await {
+                captured.lock().unwrap().entry(topic.clone()).or_default().push(message);
+            }
+        });
+        Ok(())
+    }
+
+    /// Publish a message, and wait for the modules to settle
+    pub async fn publish(&self, topic: &str, message: Message) -> Result<()> {
+        self.context.publish(topic, Arc::new(message)).await?;
+        self.settle().await;
+        Ok(())
+    }
+
+    /// Move the virtual clock on, ticking once a second
+    pub async fn advance(&mut self, seconds: u64) -> Result<()> {
+        for _ in 0..seconds {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            self.now += TimeDelta::seconds(1);
+            self.ticks += 1;
+            let tick = ClockTickMessage {
+                time: self.now,
+                number: self.ticks,
+            };
+            self.publish(CLOCK_TOPIC, Message::Clock(tick)).await?;
+        }
+        Ok(())
+    }
+
+    /// Run each step of a script in turn
+    pub async fn run(&mut self, script: &Script) -> Result<()> {
+        for step in &script.steps {
+            match step {
+                Step::Publish { topic, message } => self.publish(topic, message.clone()).await?,
+                Step::Advance { advance } => self.advance(*advance).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Let the modules run until they stop publishing on the captured topics
+    pub async fn settle(&self) {
+        let mut last = self.captured_count();
+        let mut quiet = 0;
+        for _ in 0..SETTLE_MAX_ROUNDS {
+            tokio::task::yield_now().await;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let count = self.captured_count();
+            if count == last {
+                quiet += 1;
+                if quiet == SETTLE_QUIET_ROUNDS {
+                    return;
+                }
+            } else {
+                last = count;
+                quiet = 0;
+            }
+        }
+    }
+
+    fn captured_count(&self) -> usize {
+        self.captured.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Messages published on a captured topic so far
+    pub fn captured(&self, topic: &str) -> Vec<Arc<Message>> {
+        self.captured.lock().unwrap().get(topic).cloned().unwrap_or_default()
+    }
+
+    /// Everything captured, as JSON keyed by topic
+    pub fn transcript(&self) -> Result<serde_json::Value> {
+        let captured = self.captured.lock().unwrap();
+        let mut transcript = serde_json::Map::new();
+        for (topic, messages) in captured.iter() {
+            let messages = messages
+                .iter()
+                .map(|message| serde_json::to_value(message.as_ref()))
+                .collect::<Result<Vec<_>, _>>()?;
+            transcript.insert(topic.clone(), serde_json::Value::Array(messages));
+        }
+        Ok(serde_json::Value::Object(transcript))
+    }
+
+    /// Compare the transcript with a golden file, or write it if
+    /// `UPDATE_GOLDEN` is set
+    pub fn assert_golden(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if std::env::var_os(UPDATE_GOLDEN).is_some() {
+            return self.write_golden(path);
+        }
+
+        let golden = std::fs::read_to_string(path).map_err(|e| {
+            anyhow!(
+                "Could not read golden file {}: {e} - run with {UPDATE_GOLDEN}=1 to create it",
+                path.display()
+            )
+        })?;
+        let golden: serde_json::Value = serde_json::from_str(&golden)?;
+        if let Some(difference) = first_difference(&golden, &self.transcript()?) {
+            bail!(
+                "Transcript differs from {} at {difference} - run with {UPDATE_GOLDEN}=1 to \
+                 accept it",
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Write the transcript as a golden file
+    pub fn write_golden(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(
+            path,
+            serde_json::to_string_pretty(&self.transcript()?)? + "\n",
+        )?;
+        Ok(())
+    }
+}
+
+/// Where two transcripts first differ, as a topic and message index
+fn first_difference(golden: &serde_json::Value, actual: &serde_json::Value) -> Option<String> {
+    let empty = serde_json::Map::new();
+    let golden = golden.as_object().unwrap_or(&empty);
+    let actual = actual.as_object().unwrap_or(&empty);
+
+    let mut topics: Vec<&String> = golden.keys().chain(actual.keys()).collect();
+    topics.sort();
+    topics.dedup();
+    for topic in topics {
+        let no_messages = Vec::new();
+        let expected = golden.get(topic).and_then(|m| m.as_array()).unwrap_or(&no_messages);
+        let got = actual.get(topic).and_then(|m| m.as_array()).unwrap_or(&no_messages);
+        if let Some(index) =
+            (0..expected.len().max(got.len())).find(|i| expected.get(*i) != got.get(*i))
+        {
+            return Some(format!(
+                "'{topic}' message {index} ({} expected, {} published)",
+                expected.len(),
+                got.len()
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn first_difference_names_topic_and_index() {
+        let golden = json!({ "a": [1, 2], "b": [3] });
+        assert_eq!(first_difference(&golden, &golden), None);
+        assert_eq!(
+            first_difference(&golden, &json!({ "a": [1, 2], "b": [4] })),
+            Some("'b' message 0 (1 expected, 1 published)".to_string())
+        );
+        assert_eq!(
+            first_difference(&golden, &json!({ "a": [1], "b": [3] })),
+            Some("'a' message 1 (2 expected, 1 published)".to_string())
+        );
+    }
+}
@@ -0,0 +1,51 @@
+//! Scripted message sequences
+
+use acropolis_common::messages::Message;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// One step of a script
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Step {
+    /// Publish a message, and wait for the modules to settle
+    Publish { topic: String, message: Message },
+
+    /// Move the virtual clock on this many seconds, ticking each one
+    Advance { advance: u64 },
+}
+
+/// Steps run in order, as a JSON array of `{"topic", "message"}` and
+/// `{"advance"}` objects
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Could not read script {}: {e}", path.display()))?;
+        Self::from_json(&json).map_err(|e| anyhow!("Bad script {}: {e}", path.display()))
+    }
+
+    pub fn publish(mut self, topic: &str, message: Message) -> Self {
+        self.steps.push(Step::Publish {
+            topic: topic.to_string(),
+            message,
+        });
+        self
+    }
+
+    pub fn advance(mut self, seconds: u64) -> Self {
+        self.steps.push(Step::Advance { advance: seconds });
+        self
+    }
+}
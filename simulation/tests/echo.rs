@@ -0,0 +1,90 @@
+//! Runs the harness against a small module which answers every message
+
+use std::sync::Arc;
+
+use acropolis_common::messages::Message;
+use acropolis_simulation::{Script, Simulation};
+use anyhow::Result;
+use caryatid_sdk::Context;
+use config::Config;
+
+const CONFIG: &str = r#"
+[global.echo]
+prefix = "echo"
+
+[module.echo]
+subscribe-topic = "test.in"
+"#;
+
+/// Republishes strings on `<subscribe-topic>.out`, and counts clock ticks
+struct Echo;
+
+impl Echo {
+    async fn init(&self, context: Arc<Context<Message>>, config: Arc<Config>) -> Result<()> {
+        let topic = config.get_string("subscribe-topic")?;
+        let prefix = config.get_string("echo.prefix")?;
+        let out_topic = format!("{topic}.out");
+
+        let mut subscription = context.subscribe(&topic).await?;
+        let run_context = context.clone();
+        let string_topic = out_topic.clone();
+        context.run(async move {
+            while let Ok((_, message)) = subscription.read().await {
+                if let Message::String(s) = message.as_ref() {
+                    let reply = Arc::new(Message::String(format!("{prefix} {s}")));
+                    let _ = run_context.publish(&string_topic, reply).await;
+                }
+            }
+        });
+
+        let mut ticks = context.subscribe("clock.tick").await?;
+        let run_context = context.clone();
+        context.run(async move {
+            while let Ok((_, message)) = ticks.read().await {
+                if let Message::Clock(tick) = message.as_ref() {
+                    let reply = Arc::new(Message::String(format!("tick {}", tick.number)));
+                    let _ = run_context.publish(&out_topic, reply).await;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn script_drives_modules_and_matches_golden() -> Result<()> {
+    let mut sim = Simulation::new(CONFIG)?;
+    sim.start("echo", |context, config| async move {
+        Echo.init(context, config).await
+    })
+    .await?;
+    sim.capture("test.in.out").await?;
+
+    let script =
+        Script::from_json(r#"[{ "topic": "test.in", "message": { "String": "hello" } }]"#)?
+            .advance(2)
+            .publish("test.in", Message::String("bye".to_string()));
+    sim.run(&script).await?;
+
+    let replies: Vec<String> = sim
+        .captured("test.in.out")
+        .iter()
+        .map(|message| match message.as_ref() {
+            Message::String(s) => s.clone(),
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(replies, vec!["echo hello", "tick 1", "tick 2", "echo bye"]);
+
+    let golden =
+        std::env::temp_dir().join(format!("simulation-golden-{}.json", std::process::id()));
+    sim.write_golden(&golden)?;
+    sim.assert_golden(&golden)?;
+
+    // Anything published after the golden file was written is a difference
+    sim.publish("test.in", Message::String("again".to_string())).await?;
+    assert!(sim.assert_golden(&golden).is_err());
+
+    std::fs::remove_file(&golden)?;
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use std::str::FromStr;
 
 use acropolis_common::{
-    genesis_values::GenesisValues, hash::Hash, GenesisDelegates, MagicNumber, Pots,
+    genesis_values::GenesisValues, hash::Hash, rational_number::RationalNumber, GenesisDelegates,
+    MagicNumber, Pots,
 };
 
 const MAINNET_SHELLEY_GENESIS_HASH: &str =
@@ -72,5 +73,8 @@ pub fn mainnet_genesis_values() -> GenesisValues {
             treasury: 0,
             deposits: 0,
         },
+        slots_per_kes_period: 129600,
+        max_kes_evolutions: 62,
+        active_slots_coeff: RationalNumber::new(1, 20),
     }
 }